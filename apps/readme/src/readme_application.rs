@@ -3,9 +3,10 @@ use std::sync::Arc;
 use blitz_dom::DocumentConfig;
 use blitz_dom::net::Resource;
 use blitz_html::HtmlDocument;
-use blitz_net::Provider;
+use blitz_net::{DownloadInfo, DownloadState, MultipartForm, Provider, ProviderError};
 use blitz_shell::{BlitzApplication, BlitzShellEvent, View, WindowConfig};
 use blitz_traits::navigation::{NavigationOptions, NavigationProvider};
+use blitz_traits::net::{Bytes, Method};
 use tokio::runtime::Handle;
 use winit::application::ApplicationHandler;
 use winit::event::{Modifiers, StartCause, WindowEvent};
@@ -37,6 +38,7 @@ impl ReadmeApplication {
         navigation_provider: Arc<dyn NavigationProvider>,
     ) -> Self {
         let handle = Handle::current();
+        net_provider.downloads().set_listener(report_download);
         Self {
             inner: BlitzApplication::new(proxy.clone()),
             handle,
@@ -75,23 +77,57 @@ impl ReadmeApplication {
         });
     }
 
-    fn navigate(&mut self, options: NavigationOptions) {
+    fn navigate(&mut self, mut options: NavigationOptions) {
         let proxy = self.inner.proxy.clone();
-        self.net_provider.fetch_with_callback(
-            dbg!(options.into_request()),
-            Box::new(move |result| {
-                let (url, bytes) = result.unwrap();
-                let contents = std::str::from_utf8(&bytes).unwrap().to_string();
-                proxy
-                    .send_event(BlitzShellEvent::NavigationLoad {
-                        url,
-                        contents,
-                        is_md: false,
-                        retain_scroll_position: false,
-                    })
-                    .unwrap();
-            }),
-        );
+        let doc_id = options.source_document;
+        // Forms are streamed by the upload, rather than encoded in memory by `into_request`
+        let form = options.form_data.take().map(MultipartForm::from_form_data);
+        let request = dbg!(options.into_request());
+        // Responses which can't be displayed are downloaded instead (sending the request again,
+        // unless that would submit a form twice)
+        let downloads = Arc::clone(self.net_provider.downloads());
+        let download = (request.method == Method::GET).then(|| request.clone());
+        let load = move |result: Result<(String, Bytes), ProviderError>| {
+            let (url, bytes) = result.unwrap();
+            let Ok(contents) = String::from_utf8(bytes.to_vec()) else {
+                match download.map(|request| downloads.start(Some(doc_id), request, None)) {
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => eprintln!("Couldn't download {url}: {err}"),
+                    None => eprintln!("Couldn't display {url}"),
+                }
+                return;
+            };
+            proxy
+                .send_event(BlitzShellEvent::NavigationLoad {
+                    url,
+                    contents,
+                    is_md: false,
+                    retain_scroll_position: false,
+                })
+                .unwrap();
+        };
+        match form {
+            Some(form) => {
+                let net_provider = Arc::clone(&self.net_provider);
+                self.handle.spawn(async move {
+                    load(net_provider.upload(Some(doc_id), request, form).await);
+                });
+            }
+            None => self
+                .net_provider
+                .fetch_with_callback(Some(doc_id), request, Box::new(load)),
+        }
+    }
+
+    /// Save a link with a `download` attribute to disk
+    fn download(&mut self, mut options: NavigationOptions) {
+        let file_name = options.download.take();
+        let doc_id = options.source_document;
+        let request = options.into_request();
+        let downloads = self.net_provider.downloads();
+        if let Err(err) = downloads.start(Some(doc_id), request, file_name) {
+            eprintln!("Couldn't download: {err}");
+        }
     }
 
     fn load_document(
@@ -142,10 +178,18 @@ impl ApplicationHandler<BlitzShellEvent> for ReadmeApplication {
         self.inner.suspended(event_loop);
     }
 
+    fn memory_warning(&mut self, event_loop: &ActiveEventLoop) {
+        self.inner.memory_warning(event_loop);
+    }
+
     fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
         self.inner.new_events(event_loop, cause);
     }
 
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.inner.about_to_wait(event_loop);
+    }
+
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
@@ -183,6 +227,9 @@ impl ApplicationHandler<BlitzShellEvent> for ReadmeApplication {
                     self.reload_document(true);
                 }
             }
+            BlitzShellEvent::Navigate(options) if options.download.is_some() => {
+                self.download(*options);
+            }
             BlitzShellEvent::Navigate(options) => {
                 let old_url = std::mem::replace(&mut self.raw_url, options.url.to_string());
                 self.url_history.push(old_url);
@@ -201,3 +248,13 @@ impl ApplicationHandler<BlitzShellEvent> for ReadmeApplication {
         }
     }
 }
+
+fn report_download(info: &DownloadInfo) {
+    match (&info.state, &info.path) {
+        (DownloadState::Completed, Some(path)) => {
+            println!("Downloaded {} to {}", info.url, path.display());
+        }
+        (DownloadState::Failed(err), _) => eprintln!("Failed to download {}: {err}", info.url),
+        _ => {}
+    }
+}
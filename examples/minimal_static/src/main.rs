@@ -0,0 +1,52 @@
+//! The "minimal static renderer" build profile
+//!
+//! Renders an HTML file to a binary PPM image using only blitz-dom, blitz-html, blitz-paint and
+//! the vello_cpu backend, with every optional feature disabled. CI builds this crate to make sure
+//! that the profile keeps compiling and to track its binary size.
+//!
+//! ```sh
+//! cargo build --profile small -p minimal_static
+//! ./target/small/minimal_static page.html > page.ppm
+//! ```
+
+use std::io::Write;
+use std::sync::Arc;
+
+use anyrender::render_to_buffer;
+use anyrender_vello_cpu::VelloCpuImageRenderer;
+use blitz_dom::DocumentConfig;
+use blitz_html::HtmlDocument;
+use blitz_paint::paint_scene;
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 600;
+
+fn main() {
+    let path = std::env::args().nth(1).expect("usage: minimal_static <file.html>");
+    let html = std::fs::read_to_string(&path).expect("failed to read html file");
+
+    let mut doc = HtmlDocument::from_html(
+        &html,
+        DocumentConfig {
+            viewport: Some(Viewport::new(WIDTH, HEIGHT, 1.0, ColorScheme::Light)),
+            net_provider: Some(Arc::new(DummyNetProvider)),
+            ..DocumentConfig::for_testing()
+        },
+    )
+    .into_inner();
+    doc.resolve();
+
+    let rgba = render_to_buffer::<VelloCpuImageRenderer, _>(
+        |scene| paint_scene(scene, &doc, 1.0, WIDTH, HEIGHT),
+        WIDTH,
+        HEIGHT,
+    );
+
+    let mut out = std::io::stdout().lock();
+    write!(out, "P6\n{WIDTH} {HEIGHT}\n255\n").unwrap();
+    for pixel in rgba.chunks_exact(4) {
+        out.write_all(&pixel[..3]).unwrap();
+    }
+}
@@ -111,10 +111,12 @@ async fn main() {
 
     timer.time("Resolved styles and layout");
 
-    // Determine height to render
-    let computed_height = document.as_ref().root_element().final_layout.size.height;
+    // Render the whole page, including content which overflows the root element. Pages wider
+    // than the viewport are scaled down to fit its width.
     let render_width = (width as f64 * scale) as u32;
-    let render_height = ((computed_height as f64).max(height as f64).min(4000.0) * scale) as u32;
+    let scale = scale * document.as_ref().scale_to_fit(width as f64, f64::INFINITY).min(1.0);
+    let page_height = document.as_ref().content_size().height * scale;
+    let render_height = page_height.max(height as f64 * scale).min(4000.0 * scale) as u32;
 
     // Render document to RGBA buffer
     let buffer = if use_cpu_renderer {
@@ -0,0 +1,382 @@
+//! A [`PaintScene`] which records drawing commands to be replayed later
+//!
+//! [`DisplayList`]s own everything they draw, so a scene can be painted on one thread (like a
+//! document's worker thread) and drawn by a backend on another.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use peniko::kurbo::{Affine, Point, Rect, Shape, Stroke};
+use peniko::{BlendMode, Brush, BrushRef, Color, Fill, Gradient, Image};
+
+use crate::dyn_scene::{ErasedShape, with_shape};
+use crate::{Paint, PaintScene};
+
+/// An owned [`Paint`]
+enum RecordedPaint {
+    Solid(Color),
+    Gradient(Gradient),
+    Image(Image),
+    Custom(Arc<dyn Any + Send + Sync>),
+}
+
+impl RecordedPaint {
+    fn new(paint: Paint<'_>) -> Self {
+        match paint {
+            Paint::Solid(color) => Self::Solid(color),
+            Paint::Gradient(gradient) => Self::Gradient(gradient.clone()),
+            Paint::Image(image) => Self::Image(image.clone()),
+            Paint::Custom(custom) => Self::Custom(custom),
+        }
+    }
+
+    fn as_paint(&self) -> Paint<'_> {
+        match self {
+            Self::Solid(color) => Paint::Solid(*color),
+            Self::Gradient(gradient) => Paint::Gradient(gradient),
+            Self::Image(image) => Paint::Image(image),
+            Self::Custom(custom) => Paint::Custom(custom.clone()),
+        }
+    }
+}
+
+enum Command {
+    PushLayer {
+        blend: BlendMode,
+        alpha: f32,
+        transform: Affine,
+        clip: ErasedShape,
+    },
+    PopLayer,
+    Stroke {
+        style: Stroke,
+        transform: Affine,
+        brush: Brush,
+        brush_transform: Option<Affine>,
+        shape: ErasedShape,
+    },
+    Fill {
+        style: Fill,
+        transform: Affine,
+        brush: RecordedPaint,
+        brush_transform: Option<Affine>,
+        shape: ErasedShape,
+    },
+    TextBuffer {
+        buffer: blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    },
+    BoxShadow {
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    },
+}
+
+/// A recorded list of drawing commands, which can be replayed into any [`PaintScene`]
+#[derive(Default)]
+pub struct DisplayList {
+    commands: Vec<Command>,
+}
+
+impl DisplayList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of commands recorded
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Whether nothing has been drawn into the list
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Draw the recorded commands into `scene`
+    pub fn replay(&self, scene: &mut impl PaintScene) {
+        for command in &self.commands {
+            match command {
+                Command::PushLayer {
+                    blend,
+                    alpha,
+                    transform,
+                    clip,
+                } => with_shape!(scene.push_layer(*blend, *alpha, *transform; clip)),
+                Command::PopLayer => scene.pop_layer(),
+                Command::Stroke {
+                    style,
+                    transform,
+                    brush,
+                    brush_transform,
+                    shape,
+                } => {
+                    let brush = BrushRef::from(brush);
+                    with_shape!(scene.stroke(style, *transform, brush, *brush_transform; shape))
+                }
+                Command::Fill {
+                    style,
+                    transform,
+                    brush,
+                    brush_transform,
+                    shape,
+                } => {
+                    let brush = brush.as_paint();
+                    with_shape!(scene.fill(*style, *transform, brush, *brush_transform; shape))
+                }
+                Command::TextBuffer {
+                    buffer,
+                    position,
+                    color,
+                    transform,
+                } => scene.render_text_buffer(buffer, *position, *color, *transform),
+                Command::BoxShadow {
+                    transform,
+                    rect,
+                    brush,
+                    radius,
+                    std_dev,
+                } => scene.draw_box_shadow(*transform, *rect, *brush, *radius, *std_dev),
+            }
+        }
+    }
+
+    /// Draw the recorded commands into `scene` with their opacity multiplied by `alpha`, which
+    /// looks the same as drawing them into a layer with `alpha` when there's only one of them.
+    /// Returns `false` without drawing anything if there are more (or a layer, text, or a custom
+    /// paint), in which case a layer is needed.
+    pub fn replay_with_alpha(&self, scene: &mut impl PaintScene, alpha: f32) -> bool {
+        let command = match self.commands.as_slice() {
+            [] => return true,
+            [command] => command,
+            _ => return false,
+        };
+        match command {
+            Command::Stroke {
+                style,
+                transform,
+                brush,
+                brush_transform,
+                shape,
+            } => {
+                let brush = brush.clone().multiply_alpha(alpha);
+                let brush = BrushRef::from(&brush);
+                with_shape!(scene.stroke(style, *transform, brush, *brush_transform; shape))
+            }
+            Command::Fill {
+                style,
+                transform,
+                brush,
+                brush_transform,
+                shape,
+            } => {
+                let brush = match brush {
+                    RecordedPaint::Solid(color) => Brush::Solid(color.multiply_alpha(alpha)),
+                    RecordedPaint::Gradient(gradient) => {
+                        Brush::Gradient(gradient.clone().multiply_alpha(alpha))
+                    }
+                    RecordedPaint::Image(image) => {
+                        Brush::Image(image.clone().multiply_alpha(alpha))
+                    }
+                    RecordedPaint::Custom(_) => return false,
+                };
+                let brush = Paint::from(BrushRef::from(&brush));
+                with_shape!(scene.fill(*style, *transform, brush, *brush_transform; shape))
+            }
+            // Spans with their own color ignore the buffer's color, and glyphs can overlap
+            Command::PushLayer { .. } | Command::PopLayer | Command::TextBuffer { .. } => {
+                return false;
+            }
+            Command::BoxShadow {
+                transform,
+                rect,
+                brush,
+                radius,
+                std_dev,
+            } => {
+                let brush = brush.multiply_alpha(alpha);
+                scene.draw_box_shadow(*transform, *rect, brush, *radius, *std_dev)
+            }
+        }
+        true
+    }
+}
+
+impl PaintScene for DisplayList {
+    fn reset(&mut self) {
+        self.commands.clear();
+    }
+
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        self.commands.push(Command::PushLayer {
+            blend: blend.into(),
+            alpha,
+            transform,
+            clip: ErasedShape::new(clip),
+        });
+    }
+
+    fn pop_layer(&mut self) {
+        self.commands.push(Command::PopLayer);
+    }
+
+    fn stroke<'a>(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: impl Into<BrushRef<'a>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.commands.push(Command::Stroke {
+            style: style.clone(),
+            transform,
+            brush: brush.into().to_owned(),
+            brush_transform,
+            shape: ErasedShape::new(shape),
+        });
+    }
+
+    fn fill<'a>(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: impl Into<Paint<'a>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.commands.push(Command::Fill {
+            style,
+            transform,
+            brush: RecordedPaint::new(brush.into()),
+            brush_transform,
+            shape: ErasedShape::new(shape),
+        });
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        self.commands.push(Command::TextBuffer {
+            buffer: buffer.clone(),
+            position,
+            color,
+            transform,
+        });
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        self.commands.push(Command::BoxShadow {
+            transform,
+            rect,
+            brush,
+            radius,
+            std_dev,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_recorded_on_another_thread_are_replayed_in_order() {
+        let list = std::thread::spawn(|| {
+            let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+            let transform = Affine::translate((5.0, 5.0));
+            let mut list = DisplayList::new();
+            list.push_layer(BlendMode::default(), 0.5, Affine::IDENTITY, &rect);
+            list.fill(Fill::EvenOdd, transform, Color::WHITE, None, &rect);
+            list.stroke(&Stroke::new(2.0), transform, Color::BLACK, None, &rect);
+            list.draw_box_shadow(transform, rect, Color::BLACK, 2.0, 4.0);
+            list.pop_layer();
+            list
+        })
+        .join()
+        .unwrap();
+
+        let mut scene = DisplayList::new();
+        list.replay(&mut scene);
+        let [
+            Command::PushLayer { alpha, .. },
+            Command::Fill {
+                style,
+                transform,
+                brush,
+                ..
+            },
+            Command::Stroke { style: stroke, .. },
+            Command::BoxShadow { std_dev, .. },
+            Command::PopLayer,
+        ] = scene.commands.as_slice()
+        else {
+            panic!("expected the recorded commands");
+        };
+        assert_eq!(*alpha, 0.5);
+        assert_eq!(*style, Fill::EvenOdd);
+        assert_eq!(*transform, Affine::translate((5.0, 5.0)));
+        let RecordedPaint::Solid(color) = brush else {
+            panic!("expected a solid fill");
+        };
+        assert_eq!(color.components, Color::WHITE.components);
+        assert_eq!(stroke.width, 2.0);
+        assert_eq!(*std_dev, 4.0);
+    }
+
+    #[test]
+    fn single_commands_are_replayed_with_alpha() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let mut list = DisplayList::new();
+        list.fill(Fill::NonZero, Affine::IDENTITY, Color::BLACK, None, &rect);
+
+        let mut scene = DisplayList::new();
+        assert!(list.replay_with_alpha(&mut scene, 0.5));
+        let [Command::Fill { brush, .. }] = scene.commands.as_slice() else {
+            panic!("expected a single fill");
+        };
+        let RecordedPaint::Solid(color) = brush else {
+            panic!("expected a solid fill");
+        };
+        assert_eq!(color.components[3], 0.5);
+
+        // Overlapping commands would show through each other
+        list.fill(Fill::NonZero, Affine::IDENTITY, Color::BLACK, None, &rect);
+        let mut scene = DisplayList::new();
+        assert!(!list.replay_with_alpha(&mut scene, 0.5));
+        assert!(scene.is_empty());
+    }
+
+    #[test]
+    fn text_is_drawn_into_a_layer() {
+        let mut list = DisplayList::new();
+        let buffer = blitz_text::Buffer::new_empty(blitz_text::Metrics::new(16.0, 20.0));
+        list.render_text_buffer(&buffer, Point::ZERO, Color::BLACK, Affine::IDENTITY);
+
+        let mut scene = DisplayList::new();
+        assert!(!list.replay_with_alpha(&mut scene, 0.5));
+        assert!(scene.is_empty());
+    }
+}
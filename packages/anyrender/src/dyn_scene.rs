@@ -0,0 +1,263 @@
+//! A type-erased [`PaintScene`], for renderers which pick their backend at runtime
+//!
+//! [`PaintScene`] isn't object safe, so [`DynScenePainter`] goes through a private object safe
+//! mirror of it which every [`PaintScene`] implements. Shapes are passed on as rects and rounded
+//! rects where possible, so that backends keep their fast paths for them.
+
+use peniko::kurbo::{Affine, BezPath, Point, Rect, RoundedRect, Shape, Stroke};
+use peniko::{BlendMode, BrushRef, Color, Fill, Image};
+
+use crate::{FragmentKey, Paint, PaintScene};
+
+const DEFAULT_TOLERANCE: f64 = 0.1;
+
+pub(crate) enum ErasedShape {
+    Rect(Rect),
+    RoundedRect(RoundedRect),
+    Path(BezPath),
+}
+
+impl ErasedShape {
+    pub(crate) fn new(shape: &impl Shape) -> Self {
+        if let Some(rect) = shape.as_rect() {
+            Self::Rect(rect)
+        } else if let Some(rounded_rect) = shape.as_rounded_rect() {
+            Self::RoundedRect(rounded_rect)
+        } else {
+            Self::Path(shape.into_path(DEFAULT_TOLERANCE))
+        }
+    }
+}
+
+/// Call the [`PaintScene`] method `$method` on `$scene`, with the concrete shape inside an
+/// [`ErasedShape`] as the last argument
+macro_rules! with_shape {
+    ($scene:ident.$method:ident($($arg:expr),*; $shape:expr)) => {
+        match $shape {
+            ErasedShape::Rect(shape) => PaintScene::$method($scene, $($arg,)* shape),
+            ErasedShape::RoundedRect(shape) => PaintScene::$method($scene, $($arg,)* shape),
+            ErasedShape::Path(shape) => PaintScene::$method($scene, $($arg,)* shape),
+        }
+    };
+}
+pub(crate) use with_shape;
+
+trait ErasedPaintScene {
+    fn reset(&mut self);
+    fn push_layer(&mut self, blend: BlendMode, alpha: f32, transform: Affine, clip: &ErasedShape);
+    fn pop_layer(&mut self);
+    fn stroke(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: BrushRef<'_>,
+        brush_transform: Option<Affine>,
+        shape: &ErasedShape,
+    );
+    fn fill(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: Paint<'_>,
+        brush_transform: Option<Affine>,
+        shape: &ErasedShape,
+    );
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    );
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    );
+    fn draw_image(&mut self, image: &Image, transform: Affine);
+    fn supports_retained_fragments(&self) -> bool;
+    fn draw_retained_fragment(&mut self, key: FragmentKey, transform: Affine) -> bool;
+    fn begin_fragment(&mut self, key: FragmentKey);
+    fn end_fragment(&mut self);
+}
+
+impl<S: PaintScene> ErasedPaintScene for S {
+    fn reset(&mut self) {
+        PaintScene::reset(self);
+    }
+
+    fn push_layer(&mut self, blend: BlendMode, alpha: f32, transform: Affine, clip: &ErasedShape) {
+        with_shape!(self.push_layer(blend, alpha, transform; clip));
+    }
+
+    fn pop_layer(&mut self) {
+        PaintScene::pop_layer(self);
+    }
+
+    fn stroke(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: BrushRef<'_>,
+        brush_transform: Option<Affine>,
+        shape: &ErasedShape,
+    ) {
+        with_shape!(self.stroke(style, transform, brush, brush_transform; shape));
+    }
+
+    fn fill(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: Paint<'_>,
+        brush_transform: Option<Affine>,
+        shape: &ErasedShape,
+    ) {
+        with_shape!(self.fill(style, transform, brush, brush_transform; shape));
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        PaintScene::render_text_buffer(self, buffer, position, color, transform);
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        PaintScene::draw_box_shadow(self, transform, rect, brush, radius, std_dev);
+    }
+
+    fn draw_image(&mut self, image: &Image, transform: Affine) {
+        PaintScene::draw_image(self, image, transform);
+    }
+
+    fn supports_retained_fragments(&self) -> bool {
+        PaintScene::supports_retained_fragments(self)
+    }
+
+    fn draw_retained_fragment(&mut self, key: FragmentKey, transform: Affine) -> bool {
+        PaintScene::draw_retained_fragment(self, key, transform)
+    }
+
+    fn begin_fragment(&mut self, key: FragmentKey) {
+        PaintScene::begin_fragment(self, key);
+    }
+
+    fn end_fragment(&mut self) {
+        PaintScene::end_fragment(self);
+    }
+}
+
+/// A [`PaintScene`] which forwards to a scene whose type is only known at runtime
+pub struct DynScenePainter<'a> {
+    inner: &'a mut dyn ErasedPaintScene,
+}
+
+impl<'a> DynScenePainter<'a> {
+    pub fn new<S: PaintScene>(scene: &'a mut S) -> Self {
+        Self { inner: scene }
+    }
+}
+
+impl PaintScene for DynScenePainter<'_> {
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        let clip = ErasedShape::new(clip);
+        self.inner.push_layer(blend.into(), alpha, transform, &clip);
+    }
+
+    fn pop_layer(&mut self) {
+        self.inner.pop_layer();
+    }
+
+    fn stroke<'b>(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: impl Into<BrushRef<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        let shape = ErasedShape::new(shape);
+        self.inner
+            .stroke(style, transform, brush.into(), brush_transform, &shape);
+    }
+
+    fn fill<'b>(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: impl Into<Paint<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        let shape = ErasedShape::new(shape);
+        self.inner
+            .fill(style, transform, brush.into(), brush_transform, &shape);
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        self.inner
+            .render_text_buffer(buffer, position, color, transform);
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        self.inner
+            .draw_box_shadow(transform, rect, brush, radius, std_dev);
+    }
+
+    fn draw_image(&mut self, image: &Image, transform: Affine) {
+        self.inner.draw_image(image, transform);
+    }
+
+    fn supports_retained_fragments(&self) -> bool {
+        self.inner.supports_retained_fragments()
+    }
+
+    fn draw_retained_fragment(&mut self, key: FragmentKey, transform: Affine) -> bool {
+        self.inner.draw_retained_fragment(key, transform)
+    }
+
+    fn begin_fragment(&mut self, key: FragmentKey) {
+        self.inner.begin_fragment(key);
+    }
+
+    fn end_fragment(&mut self) {
+        self.inner.end_fragment();
+    }
+}
@@ -0,0 +1,72 @@
+//! Helpers for backends which can't interpolate gradients in every color space
+//!
+//! CSS gradients are interpolated in Oklab by default, and may be interpolated in any color
+//! space. Backends (or surfaces) which only interpolate between sRGB-encoded stops draw such
+//! gradients with [`with_srgb_interpolation`], which adds stops sampled in the gradient's own
+//! interpolation space.
+
+use peniko::color::{ColorSpaceTag, DynamicColor, Srgb};
+use peniko::{ColorStop, ColorStops, Gradient};
+
+/// The number of parts the span between two stops is split into when it's resampled
+const SEGMENTS_PER_STOP: usize = 16;
+
+/// `gradient` with stops encoded in sRGB, which look the same when interpolated in sRGB as the
+/// original stops do in the gradient's interpolation space (and hue direction)
+pub fn with_srgb_interpolation(gradient: &Gradient) -> Gradient {
+    let mut resampled = gradient.clone();
+    resampled.interpolation_cs = ColorSpaceTag::Srgb;
+    if gradient.interpolation_cs == ColorSpaceTag::Srgb {
+        return resampled;
+    }
+
+    let to_srgb = |color: DynamicColor| {
+        DynamicColor::from_alpha_color(color.to_alpha_color::<Srgb>())
+    };
+    let (space, direction) = (gradient.interpolation_cs, gradient.hue_direction);
+    let mut stops = ColorStops::default();
+    for pair in gradient.stops.windows(2) {
+        let [from, to] = [pair[0], pair[1]];
+        let interpolator = from.color.interpolate(to.color, space, direction);
+        for segment in 0..SEGMENTS_PER_STOP {
+            let t = segment as f32 / SEGMENTS_PER_STOP as f32;
+            stops.push(ColorStop {
+                offset: from.offset + (to.offset - from.offset) * t,
+                color: to_srgb(interpolator.eval(t)),
+            });
+        }
+    }
+    if let Some(last) = gradient.stops.last() {
+        stops.push(ColorStop {
+            offset: last.offset,
+            color: to_srgb(last.color),
+        });
+    }
+    resampled.stops = stops;
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::Color;
+
+    #[test]
+    fn stops_are_sampled_in_the_interpolation_space() {
+        let mut gradient = Gradient::new_linear((0.0, 0.0), (1.0, 0.0))
+            .with_stops([Color::from_rgb8(255, 0, 0), Color::from_rgb8(0, 0, 255)]);
+        assert_eq!(with_srgb_interpolation(&gradient), gradient);
+
+        gradient.interpolation_cs = ColorSpaceTag::Oklab;
+        let resampled = with_srgb_interpolation(&gradient);
+        assert_eq!(resampled.interpolation_cs, ColorSpaceTag::Srgb);
+        assert_eq!(resampled.stops.len(), SEGMENTS_PER_STOP + 1);
+
+        // Halfway between red and blue in Oklab is a lighter purple than in sRGB
+        let mid = resampled.stops[SEGMENTS_PER_STOP / 2];
+        assert_eq!(mid.offset, 0.5);
+        let [r, g, b, a] = mid.color.to_alpha_color::<Srgb>().components;
+        assert!(r > 0.5 && b > 0.5 && g > 0.2, "{r} {g} {b}");
+        assert_eq!(a, 1.0);
+    }
+}
@@ -26,6 +26,7 @@
 //! Currently existing backends are:
 //!  - [anyrender_vello](https://docs.rs/anyrender_vello)
 //!  - [anyrender_vello_cpu](https://docs.rs/anyrender_vello_cpu)
+//!  - [anyrender_tinyskia](https://docs.rs/anyrender_tinyskia)
 
 use std::sync::Arc;
 
@@ -36,6 +37,16 @@ pub mod wasm_send_sync;
 pub use wasm_send_sync::*;
 pub mod types;
 pub use types::*;
+pub mod quality;
+pub use quality::*;
+pub mod dyn_scene;
+pub use dyn_scene::DynScenePainter;
+pub mod display_list;
+pub use display_list::DisplayList;
+pub mod gradient;
+
+/// Why a [`WindowRenderer`] couldn't be resumed
+pub type ResumeError = Box<dyn std::error::Error + Send + Sync>;
 
 
 
@@ -49,12 +60,46 @@ pub trait WindowRenderer {
     fn is_active(&self) -> bool;
     fn set_size(&mut self, width: u32, height: u32);
     fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F);
-    
+
+    /// Like [`resume`](Self::resume), but reports failure (e.g. no usable GPU adapter) instead of
+    /// panicking, so that callers can fall back to another renderer.
+    /// Default implementation calls `resume` - renderers which can fail should override this
+    fn try_resume(
+        &mut self,
+        window: Arc<dyn WindowHandle>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ResumeError> {
+        self.resume(window, width, height);
+        Ok(())
+    }
+
     /// Initialize text system for a document with GPU context
     /// Default implementation does nothing - renderers that support text should override this
     fn initialize_text_system(&self, _doc: &dyn std::any::Any) -> Result<(), String> {
         Ok(()) // Default no-op implementation
     }
+
+    /// Change the quality of subsequent frames (see [`QualityController`])
+    /// Default implementation does nothing - renderers without quality settings can ignore this
+    fn set_quality(&mut self, _quality: RenderQuality) {}
+
+    /// The color space of the surface, which painted colors must be encoded in
+    /// Default implementation reports sRGB
+    fn color_space(&self) -> SurfaceColorSpace {
+        SurfaceColorSpace::Srgb
+    }
+
+    /// Declare that only `damage` (in physical pixels) of the next frame differs from the previous
+    /// frame, so that renderers can redraw and present just those regions. The whole scene must
+    /// still be painted. Applies to the next call to [`render`](Self::render) only.
+    /// Default implementation does nothing - renderers which always redraw everything can ignore this
+    fn set_damage(&mut self, _damage: &[Rect]) {}
+
+    /// Shrink caches which can only be trimmed on the renderer's thread (e.g. GPU glyph atlases)
+    /// to the budgets they were given, between frames. Applications call this when idle.
+    /// Default implementation does nothing - renderers without such caches can ignore this
+    fn trim_caches(&mut self) {}
 }
 
 /// Abstraction for rendering a scene to an image buffer
@@ -156,4 +201,27 @@ pub trait PaintScene {
             &Rect::new(0.0, 0.0, image.width as f64, image.height as f64),
         );
     }
+
+    /// Whether the scene can retain fragments across frames (see
+    /// [`draw_retained_fragment`](Self::draw_retained_fragment))
+    fn supports_retained_fragments(&self) -> bool {
+        false
+    }
+
+    /// Draw the fragment retained for `key`, with `transform` applied. Returns `false` without
+    /// drawing anything if no fragment with the same id and version is retained. The fragment can
+    /// then be recorded by drawing it, relative to the origin, between
+    /// [`begin_fragment`](Self::begin_fragment) and [`end_fragment`](Self::end_fragment).
+    ///
+    /// Backends discard the fragments which weren't drawn in a frame at the end of it.
+    fn draw_retained_fragment(&mut self, _key: FragmentKey, _transform: Affine) -> bool {
+        false
+    }
+
+    /// Start recording the fragment for `key`. The drawing commands up to the matching
+    /// [`end_fragment`](Self::end_fragment) are retained rather than drawn.
+    fn begin_fragment(&mut self, _key: FragmentKey) {}
+
+    /// Finish recording the current fragment
+    fn end_fragment(&mut self) {}
 }
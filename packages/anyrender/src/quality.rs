@@ -0,0 +1,223 @@
+//! Render quality levels and an adaptive controller which switches between them
+//!
+//! Backends which support it apply a [`RenderQuality`] through [`WindowRenderer::set_quality`].
+//! [`QualityController`] is fed the duration of each frame and steps the quality down when frames
+//! consistently exceed the budget, and back up once there is enough headroom again.
+//!
+//! [`WindowRenderer::set_quality`]: crate::WindowRenderer::set_quality
+
+use std::time::Duration;
+
+/// The antialiasing method used when rasterizing paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Antialiasing {
+    /// Analytic area coverage. The cheapest method, but can produce conflation artifacts.
+    Area,
+    /// 8x multisampling
+    Msaa8,
+    /// 16x multisampling
+    #[default]
+    Msaa16,
+    /// No antialiasing: each pixel is either covered or not, for displays which show smoothed
+    /// edges as fuzz (like e-paper). Backends which always antialias use [`Area`](Self::Area).
+    Off,
+}
+
+/// Quality settings for the effects that dominate render cost on weak hardware
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderQuality {
+    pub antialiasing: Antialiasing,
+    /// Draw box shadows at all
+    pub box_shadows: bool,
+    /// Upper limit for the standard deviation of blurs (in device pixels). Smaller blurs sample
+    /// fewer pixels.
+    pub max_blur_std_dev: Option<f64>,
+}
+
+impl RenderQuality {
+    /// Full quality: no limits
+    pub const HIGH: Self = Self {
+        antialiasing: Antialiasing::Msaa16,
+        box_shadows: true,
+        max_blur_std_dev: None,
+    };
+
+    /// Cheaper antialiasing and blurs
+    pub const MEDIUM: Self = Self {
+        antialiasing: Antialiasing::Msaa8,
+        box_shadows: true,
+        max_blur_std_dev: Some(8.0),
+    };
+
+    /// No multisampling and no shadows
+    pub const LOW: Self = Self {
+        antialiasing: Antialiasing::Area,
+        box_shadows: false,
+        max_blur_std_dev: Some(0.0),
+    };
+
+    /// The blur to actually use for a box shadow, or `None` if it should be skipped
+    pub fn box_shadow_std_dev(&self, std_dev: f64) -> Option<f64> {
+        if !self.box_shadows {
+            return None;
+        }
+        Some(match self.max_blur_std_dev {
+            Some(max) => std_dev.min(max),
+            None => std_dev,
+        })
+    }
+}
+
+impl Default for RenderQuality {
+    fn default() -> Self {
+        Self::HIGH
+    }
+}
+
+/// Configuration for a [`QualityController`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveQualityConfig {
+    /// Target time to render a frame
+    pub frame_budget: Duration,
+    /// Available quality levels, from best to worst
+    pub levels: Vec<RenderQuality>,
+    /// Number of consecutive over-budget frames before the quality is reduced
+    pub degrade_after: u32,
+    /// Number of consecutive frames within `restore_threshold` of the budget before the quality
+    /// is raised again
+    pub restore_after: u32,
+    /// Fraction of the budget a frame must fit in to count towards restoring quality. Keeping this
+    /// below `1.0` stops the controller from oscillating between two levels.
+    pub restore_threshold: f64,
+}
+
+impl Default for AdaptiveQualityConfig {
+    /// A 60fps budget with the [`HIGH`](RenderQuality::HIGH), [`MEDIUM`](RenderQuality::MEDIUM)
+    /// and [`LOW`](RenderQuality::LOW) levels
+    fn default() -> Self {
+        Self {
+            frame_budget: Duration::from_micros(16_667),
+            levels: vec![
+                RenderQuality::HIGH,
+                RenderQuality::MEDIUM,
+                RenderQuality::LOW,
+            ],
+            degrade_after: 3,
+            restore_after: 60,
+            restore_threshold: 0.6,
+        }
+    }
+}
+
+impl AdaptiveQualityConfig {
+    /// The default levels with a custom frame budget
+    pub fn with_budget(frame_budget: Duration) -> Self {
+        Self {
+            frame_budget,
+            ..Default::default()
+        }
+    }
+}
+
+/// Steps between [`RenderQuality`] levels based on measured frame times
+#[derive(Debug, Clone)]
+pub struct QualityController {
+    config: AdaptiveQualityConfig,
+    level: usize,
+    over_budget: u32,
+    under_budget: u32,
+}
+
+impl QualityController {
+    pub fn new(config: AdaptiveQualityConfig) -> Self {
+        Self {
+            config,
+            level: 0,
+            over_budget: 0,
+            under_budget: 0,
+        }
+    }
+
+    /// The index of the current level in [`AdaptiveQualityConfig::levels`]
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// The current quality
+    pub fn quality(&self) -> RenderQuality {
+        self.config
+            .levels
+            .get(self.level)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Record how long a frame took to render. Returns the new quality if it changed.
+    pub fn record_frame(&mut self, frame_time: Duration) -> Option<RenderQuality> {
+        let budget = self.config.frame_budget;
+        let lowest = self.config.levels.len().saturating_sub(1);
+
+        if frame_time > budget {
+            self.under_budget = 0;
+            self.over_budget += 1;
+            if self.over_budget >= self.config.degrade_after && self.level < lowest {
+                self.over_budget = 0;
+                self.level += 1;
+                return Some(self.quality());
+            }
+        } else if frame_time.as_secs_f64() <= budget.as_secs_f64() * self.config.restore_threshold
+        {
+            self.over_budget = 0;
+            self.under_budget += 1;
+            if self.under_budget >= self.config.restore_after && self.level > 0 {
+                self.under_budget = 0;
+                self.level -= 1;
+                return Some(self.quality());
+            }
+        } else {
+            // Within budget but without enough headroom to step back up
+            self.over_budget = 0;
+            self.under_budget = 0;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degrades_and_restores_with_hysteresis() {
+        let mut controller = QualityController::new(AdaptiveQualityConfig {
+            frame_budget: Duration::from_millis(10),
+            degrade_after: 2,
+            restore_after: 3,
+            ..Default::default()
+        });
+
+        let slow = Duration::from_millis(20);
+        let fast = Duration::from_millis(2);
+
+        assert_eq!(controller.record_frame(slow), None);
+        assert_eq!(controller.record_frame(slow), Some(RenderQuality::MEDIUM));
+        assert_eq!(controller.record_frame(slow), None);
+        assert_eq!(controller.record_frame(slow), Some(RenderQuality::LOW));
+        // Already at the lowest level
+        assert_eq!(controller.record_frame(slow), None);
+        assert_eq!(controller.record_frame(slow), None);
+
+        assert_eq!(controller.record_frame(fast), None);
+        assert_eq!(controller.record_frame(fast), None);
+        assert_eq!(controller.record_frame(fast), Some(RenderQuality::MEDIUM));
+        assert_eq!(controller.level(), 1);
+    }
+
+    #[test]
+    fn low_quality_skips_shadows() {
+        assert_eq!(RenderQuality::LOW.box_shadow_std_dev(4.0), None);
+        assert_eq!(RenderQuality::MEDIUM.box_shadow_std_dev(20.0), Some(8.0));
+        assert_eq!(RenderQuality::HIGH.box_shadow_std_dev(20.0), Some(20.0));
+    }
+}
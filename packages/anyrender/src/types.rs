@@ -14,6 +14,16 @@ pub struct CustomPaint {
     pub scale: f64,
 }
 
+/// Identifies a fragment of a scene which backends may retain across frames (see
+/// [`PaintScene::draw_retained_fragment`](crate::PaintScene::draw_retained_fragment))
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FragmentKey {
+    /// Identifies what the fragment draws, e.g. a node
+    pub id: u64,
+    /// Changes whenever the fragment would be drawn differently
+    pub version: u64,
+}
+
 #[derive(Clone, Debug)]
 pub enum Paint<'a> {
     /// Solid color brush.
@@ -54,3 +64,12 @@ impl<'a> From<BrushRef<'a>> for Paint<'a> {
         }
     }
 }
+
+/// The color space a window surface's pixels are shown in. Colors painted into a surface must be
+/// encoded in its color space.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SurfaceColorSpace {
+    #[default]
+    Srgb,
+    DisplayP3,
+}
@@ -0,0 +1,27 @@
+use anyrender::{ImageRenderer, PaintScene};
+
+use crate::TinySkiaScenePainter;
+
+pub struct TinySkiaImageRenderer {
+    scene: TinySkiaScenePainter,
+}
+
+impl ImageRenderer for TinySkiaImageRenderer {
+    type ScenePainter<'a> = TinySkiaScenePainter;
+
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            scene: TinySkiaScenePainter::new(width, height),
+        }
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F, buffer: &mut Vec<u8>) {
+        self.scene.reset();
+        draw_fn(&mut self.scene);
+        buffer.clear();
+        buffer.extend(self.scene.pixmap().pixels().iter().flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        }));
+    }
+}
@@ -0,0 +1,16 @@
+//! An Anyrender backend using the tiny-skia crate
+//!
+//! tiny-skia is a small and mature software rasterizer. This backend is intended as a last resort
+//! for platforms where `anyrender_vello` can't get a usable GPU (old GPUs, minimal containers) and
+//! `anyrender_vello_cpu` isn't available or fails.
+mod image_renderer;
+mod scene;
+#[cfg(feature = "window")]
+mod window_renderer;
+
+pub use image_renderer::TinySkiaImageRenderer;
+pub use scene::TinySkiaScenePainter;
+#[cfg(feature = "window")]
+pub use window_renderer::TinySkiaWindowRenderer;
+
+pub use tiny_skia;
@@ -0,0 +1,824 @@
+use anyrender::{Antialiasing, Paint, PaintScene, RenderQuality};
+use blitz_text::baseline_shift::glyph_baseline_offset;
+use blitz_text::cosmyc::{Command, Placement, SwashCache, SwashContent};
+use blitz_text::kashida::justified_glyphs;
+use peniko::color::{Rgba8, Srgb};
+use peniko::kurbo::{Affine, Cap, Join, PathEl, Point, Rect, RoundedRect, Shape, Stroke};
+use peniko::{
+    BlendMode, BrushRef, Color, Compose, Extend, Fill, Gradient, GradientKind, Image, ImageQuality,
+    Mix,
+};
+use tiny_skia::{
+    ColorU8, FillRule, FilterQuality, GradientStop, IntSize, LineCap, LineJoin, LinearGradient,
+    Mask, Path, PathBuilder, Pattern, Pixmap, PixmapPaint, RadialGradient, Shader, SpreadMode,
+    StrokeDash, Transform,
+};
+
+const DEFAULT_TOLERANCE: f64 = 0.1;
+
+/// A layer pushed with [`PaintScene::push_layer`]
+struct Layer {
+    /// The clip of this layer intersected with the clips of every layer below it
+    mask: Mask,
+    /// Set for layers which have to be blended as a whole. Plain clips draw straight into the
+    /// target of the layer below them.
+    group: Option<Group>,
+}
+
+struct Group {
+    pixmap: Pixmap,
+    blend: tiny_skia::BlendMode,
+    alpha: f32,
+}
+
+pub struct TinySkiaScenePainter {
+    pixmap: Pixmap,
+    layers: Vec<Layer>,
+    quality: RenderQuality,
+    glyph_cache: SwashCache,
+}
+
+impl TinySkiaScenePainter {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            pixmap: Pixmap::new(width.max(1), height.max(1)).unwrap(),
+            layers: Vec::new(),
+            quality: RenderQuality::default(),
+            glyph_cache: SwashCache::new(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.pixmap.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.pixmap.height()
+    }
+
+    /// The rendered scene, as premultiplied RGBA
+    pub fn pixmap(&self) -> &Pixmap {
+        &self.pixmap
+    }
+
+    pub fn quality(&self) -> RenderQuality {
+        self.quality
+    }
+
+    pub fn set_quality(&mut self, quality: RenderQuality) {
+        self.quality = quality;
+    }
+
+    pub fn finish(self) -> Pixmap {
+        self.pixmap
+    }
+
+    fn anti_alias(&self) -> bool {
+        self.quality.antialiasing != Antialiasing::Off
+    }
+
+    /// Draw the glyphs of `buffer` from the glyph cache. Only valid for transforms which are a
+    /// uniform scale (by `scale`) plus a translation.
+    fn rasterize_text(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+        scale: f64,
+    ) {
+        let anti_alias = self.anti_alias();
+        let (pixmap, mask) = target(&mut self.pixmap, &mut self.layers);
+        let glyph_cache = &mut self.glyph_cache;
+        let color = color.to_rgba8();
+
+        let _ = blitz_text::measurement::with_font_system(|font_system| {
+            for run in buffer.layout_runs() {
+                for glyph in justified_glyphs(buffer, &run, font_system).iter() {
+                    let baseline = run.line_y + glyph_baseline_offset(glyph);
+                    let origin = transform * Point::new(position.x, position.y + baseline as f64);
+                    let physical = glyph.physical((origin.x as f32, origin.y as f32), scale as f32);
+                    let Some(image) = glyph_cache.get_image(font_system, physical.cache_key) else {
+                        continue;
+                    };
+                    let Some(glyph_pixmap) =
+                        glyph_pixmap(image.placement, image.content, &image.data, color, anti_alias)
+                    else {
+                        continue;
+                    };
+                    pixmap.draw_pixmap(
+                        physical.x + image.placement.left,
+                        physical.y - image.placement.top,
+                        glyph_pixmap.as_ref(),
+                        &PixmapPaint::default(),
+                        Transform::identity(),
+                        mask,
+                    );
+                }
+            }
+        });
+    }
+
+    /// Fill the outlines of the glyphs of `buffer`, which works under any transform but loses
+    /// hinting and color glyphs
+    fn fill_text_outlines(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        let paint = paint(Shader::SolidColor(to_color(color)), self.anti_alias());
+        let (pixmap, mask) = target(&mut self.pixmap, &mut self.layers);
+        let glyph_cache = &mut self.glyph_cache;
+        let transform = to_transform(transform);
+
+        let _ = blitz_text::measurement::with_font_system(|font_system| {
+            for run in buffer.layout_runs() {
+                for glyph in justified_glyphs(buffer, &run, font_system).iter() {
+                    let cache_key = glyph.physical((0.0, 0.0), 1.0).cache_key;
+                    let Some(commands) = glyph_cache.get_outline_commands(font_system, cache_key)
+                    else {
+                        continue;
+                    };
+                    let Some(path) = outline_path(commands) else {
+                        continue;
+                    };
+                    let offset = (glyph.x_offset, glyph.y_offset);
+                    let baseline = run.line_y + glyph_baseline_offset(glyph);
+                    let x = position.x as f32 + glyph.x + glyph.font_size * offset.0;
+                    let y = position.y as f32 + baseline + glyph.y - glyph.font_size * offset.1;
+                    let transform = transform.pre_translate(x, y);
+                    pixmap.fill_path(&path, &paint, FillRule::Winding, transform, mask);
+                }
+            }
+        });
+    }
+}
+
+/// The pixmap that drawing currently goes to, and the clip to apply to it
+fn target<'a>(
+    pixmap: &'a mut Pixmap,
+    layers: &'a mut [Layer],
+) -> (&'a mut Pixmap, Option<&'a Mask>) {
+    let Some((top, rest)) = layers.split_last_mut() else {
+        return (pixmap, None);
+    };
+    let pixmap = match &mut top.group {
+        Some(group) => &mut group.pixmap,
+        None => rest
+            .iter_mut()
+            .rev()
+            .find_map(|layer| layer.group.as_mut().map(|group| &mut group.pixmap))
+            .unwrap_or(pixmap),
+    };
+    (pixmap, Some(&top.mask))
+}
+
+impl PaintScene for TinySkiaScenePainter {
+    fn reset(&mut self) {
+        self.pixmap.fill(tiny_skia::Color::TRANSPARENT);
+        self.layers.clear();
+    }
+
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        let (width, height) = (self.width(), self.height());
+        let mut mask = match self.layers.last() {
+            Some(layer) => layer.mask.clone(),
+            None => {
+                let mut mask = Mask::new(width, height).unwrap();
+                mask.data_mut().fill(u8::MAX);
+                mask
+            }
+        };
+        match to_path(clip) {
+            Some(path) => {
+                let transform = to_transform(transform);
+                mask.intersect_path(&path, FillRule::Winding, self.anti_alias(), transform);
+            }
+            None => mask.data_mut().fill(0),
+        }
+
+        let blend = blend.into();
+        // Other layers are isolated groups, which their contents (like `DestOut` layers knocking
+        // shapes out of them) are composited into before the group is composited itself
+        let is_plain_clip =
+            blend.mix == Mix::Clip && blend.compose == Compose::SrcOver && alpha >= 1.0;
+        let group = (!is_plain_clip).then(|| Group {
+            pixmap: Pixmap::new(width, height).unwrap(),
+            blend: to_blend_mode(blend),
+            alpha,
+        });
+        self.layers.push(Layer { mask, group });
+    }
+
+    fn pop_layer(&mut self) {
+        let Some(layer) = self.layers.pop() else {
+            return;
+        };
+        let Some(group) = layer.group else {
+            return;
+        };
+        let paint = PixmapPaint {
+            opacity: group.alpha,
+            blend_mode: group.blend,
+            quality: FilterQuality::Nearest,
+        };
+        // The group is composited into whatever the parent layer draws into, but only inside
+        // its own clip
+        let (pixmap, _) = target(&mut self.pixmap, &mut self.layers);
+        pixmap.draw_pixmap(
+            0,
+            0,
+            group.pixmap.as_ref(),
+            &paint,
+            Transform::identity(),
+            Some(&layer.mask),
+        );
+    }
+
+    fn stroke<'a>(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: impl Into<BrushRef<'a>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        let Some(path) = to_path(shape) else {
+            return;
+        };
+        let stroke = to_stroke(style);
+        let transform = to_transform(transform);
+        let brush_transform = to_transform(brush_transform.unwrap_or(Affine::IDENTITY));
+        let anti_alias = self.anti_alias();
+        let (pixmap, mask) = target(&mut self.pixmap, &mut self.layers);
+        with_paint(brush_to_paint(brush.into()), brush_transform, anti_alias, |paint| {
+            pixmap.stroke_path(&path, paint, &stroke, transform, mask);
+        });
+    }
+
+    fn fill<'a>(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: impl Into<Paint<'a>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        let Some(path) = to_path(shape) else {
+            return;
+        };
+        let fill_rule = match style {
+            Fill::NonZero => FillRule::Winding,
+            Fill::EvenOdd => FillRule::EvenOdd,
+        };
+        let transform = to_transform(transform);
+        let brush_transform = to_transform(brush_transform.unwrap_or(Affine::IDENTITY));
+        let anti_alias = self.anti_alias();
+        let (pixmap, mask) = target(&mut self.pixmap, &mut self.layers);
+        with_paint(brush.into(), brush_transform, anti_alias, |paint| {
+            pixmap.fill_path(&path, paint, fill_rule, transform, mask);
+        });
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        match device_scale(transform) {
+            Some(scale) => self.rasterize_text(buffer, position, color, transform, scale),
+            None => self.fill_text_outlines(buffer, position, color, transform),
+        }
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        let Some(std_dev) = self.quality.box_shadow_std_dev(std_dev) else {
+            return;
+        };
+        let Some(path) = to_path(&RoundedRect::from_rect(rect, radius)) else {
+            return;
+        };
+
+        // Blur in device space, over the region the blurred shape covers. Shape outside of the
+        // pixmap still contributes to the blur near its edges.
+        let std_dev = std_dev * transform.determinant().abs().sqrt();
+        let spread = (std_dev * 3.0).ceil();
+        let screen = Rect::new(0.0, 0.0, self.width() as f64, self.height() as f64);
+        let region = transform
+            .transform_rect_bbox(rect)
+            .inflate(spread, spread)
+            .intersect(screen.inflate(spread, spread))
+            .expand();
+        if region.is_zero_area() {
+            return;
+        }
+        let (x0, y0) = (region.x0 as i32, region.y0 as i32);
+        let (width, height) = (region.width() as u32, region.height() as u32);
+        let Some(mut coverage) = Mask::new(width, height) else {
+            return;
+        };
+        let transform = to_transform(transform).post_translate(-x0 as f32, -y0 as f32);
+        coverage.fill_path(&path, FillRule::Winding, true, transform);
+        blur(coverage.data_mut(), width as usize, height as usize, std_dev);
+
+        let Some(shadow) = coverage_pixmap(width, height, coverage.data(), brush.to_rgba8()) else {
+            return;
+        };
+        let (pixmap, mask) = target(&mut self.pixmap, &mut self.layers);
+        pixmap.draw_pixmap(
+            x0,
+            y0,
+            shadow.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            mask,
+        );
+    }
+}
+
+/// The scale factor of `transform` if it only scales uniformly and translates, in which case
+/// glyphs can be rasterized directly in device space
+fn device_scale(transform: Affine) -> Option<f64> {
+    let [a, b, c, d, _, _] = transform.as_coeffs();
+    (b == 0.0 && c == 0.0 && a == d && a > 0.0).then_some(a)
+}
+
+fn to_transform(affine: Affine) -> Transform {
+    let [a, b, c, d, e, f] = affine.as_coeffs().map(|coeff| coeff as f32);
+    Transform::from_row(a, b, c, d, e, f)
+}
+
+fn to_point(point: Point) -> tiny_skia::Point {
+    tiny_skia::Point::from_xy(point.x as f32, point.y as f32)
+}
+
+fn to_color(color: Color) -> tiny_skia::Color {
+    let Rgba8 { r, g, b, a } = color.to_rgba8();
+    tiny_skia::Color::from_rgba8(r, g, b, a)
+}
+
+/// Convert a shape to a path, or `None` if it is empty
+fn to_path(shape: &impl Shape) -> Option<Path> {
+    let mut builder = PathBuilder::new();
+    for element in shape.path_elements(DEFAULT_TOLERANCE) {
+        match element {
+            PathEl::MoveTo(p) => builder.move_to(p.x as f32, p.y as f32),
+            PathEl::LineTo(p) => builder.line_to(p.x as f32, p.y as f32),
+            PathEl::QuadTo(p1, p2) => {
+                builder.quad_to(p1.x as f32, p1.y as f32, p2.x as f32, p2.y as f32)
+            }
+            PathEl::CurveTo(p1, p2, p3) => builder.cubic_to(
+                p1.x as f32,
+                p1.y as f32,
+                p2.x as f32,
+                p2.y as f32,
+                p3.x as f32,
+                p3.y as f32,
+            ),
+            PathEl::ClosePath => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+/// Convert glyph outline commands, which are y-up, to a y-down path around the glyph origin
+fn outline_path(commands: &[Command]) -> Option<Path> {
+    let mut builder = PathBuilder::new();
+    for command in commands {
+        match *command {
+            Command::MoveTo(p) => builder.move_to(p.x, -p.y),
+            Command::LineTo(p) => builder.line_to(p.x, -p.y),
+            Command::QuadTo(p1, p2) => builder.quad_to(p1.x, -p1.y, p2.x, -p2.y),
+            Command::CurveTo(p1, p2, p3) => builder.cubic_to(p1.x, -p1.y, p2.x, -p2.y, p3.x, -p3.y),
+            Command::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+fn to_stroke(stroke: &Stroke) -> tiny_skia::Stroke {
+    let dashes = stroke.dash_pattern.iter().map(|dash| *dash as f32).collect();
+    tiny_skia::Stroke {
+        width: stroke.width as f32,
+        miter_limit: stroke.miter_limit as f32,
+        line_cap: match stroke.start_cap {
+            Cap::Butt => LineCap::Butt,
+            Cap::Round => LineCap::Round,
+            Cap::Square => LineCap::Square,
+        },
+        line_join: match stroke.join {
+            Join::Bevel => LineJoin::Bevel,
+            Join::Miter => LineJoin::Miter,
+            Join::Round => LineJoin::Round,
+        },
+        dash: StrokeDash::new(dashes, stroke.dash_offset as f32),
+    }
+}
+
+fn to_blend_mode(blend: BlendMode) -> tiny_skia::BlendMode {
+    use tiny_skia::BlendMode as B;
+    match blend.compose {
+        Compose::SrcOver => {}
+        Compose::Clear => return B::Clear,
+        Compose::Copy => return B::Source,
+        Compose::Dest => return B::Destination,
+        Compose::DestOver => return B::DestinationOver,
+        Compose::SrcIn => return B::SourceIn,
+        Compose::DestIn => return B::DestinationIn,
+        Compose::SrcOut => return B::SourceOut,
+        Compose::DestOut => return B::DestinationOut,
+        Compose::SrcAtop => return B::SourceAtop,
+        Compose::DestAtop => return B::DestinationAtop,
+        Compose::Xor => return B::Xor,
+        Compose::Plus | Compose::PlusLighter => return B::Plus,
+    }
+    match blend.mix {
+        Mix::Normal | Mix::Clip => B::SourceOver,
+        Mix::Multiply => B::Multiply,
+        Mix::Screen => B::Screen,
+        Mix::Overlay => B::Overlay,
+        Mix::Darken => B::Darken,
+        Mix::Lighten => B::Lighten,
+        Mix::ColorDodge => B::ColorDodge,
+        Mix::ColorBurn => B::ColorBurn,
+        Mix::HardLight => B::HardLight,
+        Mix::SoftLight => B::SoftLight,
+        Mix::Difference => B::Difference,
+        Mix::Exclusion => B::Exclusion,
+        Mix::Hue => B::Hue,
+        Mix::Saturation => B::Saturation,
+        Mix::Color => B::Color,
+        Mix::Luminosity => B::Luminosity,
+    }
+}
+
+fn brush_to_paint(brush: BrushRef<'_>) -> Paint<'_> {
+    match brush {
+        BrushRef::Solid(color) => Paint::Solid(color),
+        BrushRef::Gradient(gradient) => Paint::Gradient(gradient),
+        BrushRef::Image(image) => Paint::Image(image),
+    }
+}
+
+fn paint(shader: Shader<'_>, anti_alias: bool) -> tiny_skia::Paint<'_> {
+    tiny_skia::Paint {
+        shader,
+        anti_alias,
+        ..Default::default()
+    }
+}
+
+/// Call `draw` with the tiny-skia equivalent of `brush`, unless it would draw nothing
+fn with_paint(
+    brush: Paint<'_>,
+    brush_transform: Transform,
+    anti_alias: bool,
+    draw: impl FnOnce(&tiny_skia::Paint<'_>),
+) {
+    let shader = match brush {
+        Paint::Solid(color) => Shader::SolidColor(to_color(color)),
+        Paint::Gradient(gradient) => match gradient_shader(gradient, brush_transform) {
+            Some(shader) => shader,
+            None => return,
+        },
+        Paint::Image(image) => {
+            let Some(pixmap) = image_pixmap(image) else {
+                return;
+            };
+            let shader = Pattern::new(
+                pixmap.as_ref(),
+                spread_mode(image.x_extend),
+                filter_quality(image.quality),
+                image.alpha,
+                brush_transform,
+            );
+            draw(&paint(shader, anti_alias));
+            return;
+        }
+        // TODO: custom paint
+        Paint::Custom(_) => return,
+    };
+    draw(&paint(shader, anti_alias));
+}
+
+/// tiny-skia gradients always interpolate in sRGB (so gradients interpolated in other color
+/// spaces are resampled first), and radial gradients can't have a start radius
+fn gradient_shader(gradient: &Gradient, transform: Transform) -> Option<Shader<'static>> {
+    let gradient = anyrender::gradient::with_srgb_interpolation(gradient);
+    let colors = gradient
+        .stops
+        .iter()
+        .map(|stop| (stop.offset, stop.color.to_alpha_color::<Srgb>()));
+    let mode = spread_mode(gradient.extend);
+    match gradient.kind {
+        GradientKind::Linear { start, end } => {
+            let stops = colors
+                .map(|(offset, color)| GradientStop::new(offset, to_color(color)))
+                .collect();
+            LinearGradient::new(to_point(start), to_point(end), stops, mode, transform)
+        }
+        GradientKind::Radial {
+            start_center,
+            end_center,
+            end_radius,
+            ..
+        } => {
+            let stops = colors
+                .map(|(offset, color)| GradientStop::new(offset, to_color(color)))
+                .collect();
+            let (start, end) = (to_point(start_center), to_point(end_center));
+            RadialGradient::new(start, end, end_radius, stops, mode, transform)
+        }
+        // tiny-skia has no sweep gradients, so fill with the average color instead
+        GradientKind::Sweep { .. } => {
+            let count = gradient.stops.len();
+            if count == 0 {
+                return None;
+            }
+            let sum = colors.fold([0.0; 4], |sum, (_, color)| {
+                let premul = color.premultiply().components;
+                std::array::from_fn(|i| sum[i] + premul[i])
+            });
+            let [r, g, b, a] = sum.map(|component| component / count as f32);
+            if a <= 0.0 {
+                return None;
+            }
+            let color = tiny_skia::Color::from_rgba(r / a, g / a, b / a, a)?;
+            Some(Shader::SolidColor(color))
+        }
+    }
+}
+
+fn spread_mode(extend: Extend) -> SpreadMode {
+    match extend {
+        Extend::Pad => SpreadMode::Pad,
+        Extend::Repeat => SpreadMode::Repeat,
+        Extend::Reflect => SpreadMode::Reflect,
+    }
+}
+
+fn filter_quality(quality: ImageQuality) -> FilterQuality {
+    match quality {
+        ImageQuality::Low => FilterQuality::Nearest,
+        ImageQuality::Medium => FilterQuality::Bilinear,
+        ImageQuality::High => FilterQuality::Bicubic,
+    }
+}
+
+/// Convert an unpremultiplied RGBA8 image to a pixmap
+fn image_pixmap(image: &Image) -> Option<Pixmap> {
+    let size = IntSize::from_wh(image.width, image.height)?;
+    let data = image
+        .data
+        .as_ref()
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            let color = ColorU8::from_rgba(pixel[0], pixel[1], pixel[2], pixel[3]).premultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    Pixmap::from_vec(data, size)
+}
+
+/// A pixmap of `color` with the alpha of each pixel scaled by `coverage`
+fn coverage_pixmap(width: u32, height: u32, coverage: &[u8], color: Rgba8) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(width, height)?;
+    for (pixel, &coverage) in pixmap.data_mut().chunks_exact_mut(4).zip(coverage) {
+        pixel.copy_from_slice(&premultiply(color, coverage));
+    }
+    Some(pixmap)
+}
+
+/// A pixmap of a rasterized glyph, drawn in `color` unless it is a color glyph. Without
+/// `anti_alias`, pixels the glyph covers less than half of are left out and the rest are filled.
+fn glyph_pixmap(
+    placement: Placement,
+    content: SwashContent,
+    data: &[u8],
+    color: Rgba8,
+    anti_alias: bool,
+) -> Option<Pixmap> {
+    let snap = |coverage: u8| match anti_alias {
+        true => coverage,
+        false if coverage >= 128 => u8::MAX,
+        false => 0,
+    };
+    match content {
+        SwashContent::Mask if anti_alias => {
+            coverage_pixmap(placement.width, placement.height, data, color)
+        }
+        SwashContent::Mask => {
+            let coverage: Vec<u8> = data.iter().copied().map(snap).collect();
+            coverage_pixmap(placement.width, placement.height, &coverage, color)
+        }
+        SwashContent::SubpixelMask => {
+            let coverage: Vec<u8> = data
+                .chunks_exact(4)
+                .map(|pixel| ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8)
+                .map(snap)
+                .collect();
+            coverage_pixmap(placement.width, placement.height, &coverage, color)
+        }
+        SwashContent::Color => {
+            let mut pixmap = Pixmap::new(placement.width, placement.height)?;
+            for (pixel, src) in pixmap.data_mut().chunks_exact_mut(4).zip(data.chunks_exact(4)) {
+                let color = ColorU8::from_rgba(src[0], src[1], src[2], src[3]).premultiply();
+                pixel.copy_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+            }
+            Some(pixmap)
+        }
+    }
+}
+
+fn premultiply(color: Rgba8, coverage: u8) -> [u8; 4] {
+    let alpha = color.a as u32 * coverage as u32 / 255;
+    let scale = |component: u8| (component as u32 * alpha / 255) as u8;
+    [scale(color.r), scale(color.g), scale(color.b), alpha as u8]
+}
+
+/// Approximate a gaussian blur of an alpha buffer with three box blurs
+fn blur(data: &mut [u8], width: usize, height: usize, std_dev: f64) {
+    // The box size from the filter effects spec for `feGaussianBlur`
+    let size = (std_dev * 3.0 * (2.0 * std::f64::consts::PI).sqrt() / 4.0 + 0.5).floor();
+    let radius = (size as usize) / 2;
+    if radius == 0 {
+        return;
+    }
+    let mut scratch = vec![0; data.len()];
+    for _ in 0..3 {
+        box_blur(data, &mut scratch, width, height, 1, width, radius);
+        box_blur(&scratch, data, height, width, width, 1, radius);
+    }
+}
+
+/// Box blur `lines` lines of `len` pixels, `step` apart within a line and `line_step` apart
+/// from one line to the next. Pixels outside of the buffer are transparent.
+fn box_blur(
+    src: &[u8],
+    dst: &mut [u8],
+    len: usize,
+    lines: usize,
+    step: usize,
+    line_step: usize,
+    radius: usize,
+) {
+    let size = (radius * 2 + 1) as u32;
+    for line in 0..lines {
+        let at = |i: usize| line * line_step + i * step;
+        let mut sum: u32 = (0..radius.min(len)).map(|i| src[at(i)] as u32).sum();
+        for i in 0..len {
+            if i + radius < len {
+                sum += src[at(i + radius)] as u32;
+            }
+            dst[at(i)] = (sum / size) as u8;
+            if i >= radius {
+                sum -= src[at(i - radius)] as u32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyrender::DisplayList;
+    use blitz_text::{Attrs, Family, Metrics, Shaping};
+    use peniko::color::palette;
+
+    use super::*;
+
+    /// Render `group` with `alpha`, folding the alpha into its brush if it can be
+    fn render_group(group: &DisplayList, alpha: f32, with_layer: bool) -> Pixmap {
+        let mut painter = TinySkiaScenePainter::new(32, 32);
+        if with_layer {
+            let clip = Rect::new(0.0, 0.0, 32.0, 32.0);
+            painter.push_layer(Mix::Normal, alpha, Affine::IDENTITY, &clip);
+            group.replay(&mut painter);
+            painter.pop_layer();
+        } else {
+            assert!(group.replay_with_alpha(&mut painter, alpha));
+        }
+        painter.finish()
+    }
+
+    #[test]
+    fn alpha_folded_into_brushes_matches_opacity_layers() {
+        let rect = Rect::new(4.0, 4.0, 28.0, 28.0);
+        let gradient = Gradient::new_linear((4.0, 0.0), (28.0, 0.0))
+            .with_stops([palette::css::RED, palette::css::BLUE]);
+
+        let mut fill = DisplayList::new();
+        fill.fill(Fill::NonZero, Affine::IDENTITY, palette::css::GREEN, None, &rect);
+        let mut gradient_fill = DisplayList::new();
+        gradient_fill.fill(Fill::NonZero, Affine::IDENTITY, &gradient, None, &rect);
+        let mut stroke = DisplayList::new();
+        let style = Stroke::new(3.0);
+        stroke.stroke(&style, Affine::IDENTITY, palette::css::BLACK, None, &rect);
+
+        for group in [fill, gradient_fill, stroke] {
+            let folded = render_group(&group, 0.4, false);
+            let layered = render_group(&group, 0.4, true);
+            for (a, b) in folded.data().iter().zip(layered.data()) {
+                assert!(a.abs_diff(*b) <= 1, "{a} != {b}");
+            }
+        }
+    }
+
+    /// "Hi" in DejaVu Sans at 16px
+    fn text_buffer() -> blitz_text::Buffer {
+        const FONT: &[u8] = include_bytes!("../../blitz-test/assets/fonts/DejaVuSans.ttf");
+        blitz_text::measurement::with_font_system(|font_system| {
+            font_system.db_mut().load_font_data(FONT.to_vec());
+            let mut buffer = blitz_text::Buffer::new(font_system, Metrics::new(16.0, 20.0));
+            let attrs = Attrs::new().family(Family::Name("DejaVu Sans"));
+            buffer.set_text(font_system, "Hi", &attrs, Shaping::Advanced);
+            buffer.shape_until_scroll(font_system, false);
+            buffer
+        })
+        .unwrap()
+    }
+
+    /// The bounds of the pixels which were drawn on
+    fn inked_bounds(pixmap: &Pixmap) -> Option<Rect> {
+        let width = pixmap.width() as usize;
+        pixmap
+            .pixels()
+            .iter()
+            .enumerate()
+            .filter(|(_, pixel)| pixel.alpha() > 0)
+            .map(|(idx, _)| {
+                let (x, y) = ((idx % width) as f64, (idx / width) as f64);
+                Rect::new(x, y, x + 1.0, y + 1.0)
+            })
+            .reduce(|bounds, pixel| bounds.union(pixel))
+    }
+
+    #[test]
+    fn text_is_drawn_where_it_is_laid_out() {
+        let buffer = text_buffer();
+        let render = |transform| {
+            let mut painter = TinySkiaScenePainter::new(96, 96);
+            let color = palette::css::RED;
+            painter.render_text_buffer(&buffer, Point::new(8.0, 8.0), color, transform);
+            painter.finish()
+        };
+
+        // Rasterized from the glyph cache, below the line's top and within its advance
+        let rasterized = render(Affine::IDENTITY);
+        let bounds = inked_bounds(&rasterized).unwrap();
+        let width = buffer.layout_runs().map(|run| run.line_w).sum::<f32>() as f64;
+        assert!(bounds.x0 >= 8.0 && bounds.x1 <= 8.0 + width.ceil() + 1.0, "{bounds:?}");
+        assert!(bounds.y0 > 8.0 && bounds.y1 <= 8.0 + 20.0, "{bounds:?}");
+        assert!(rasterized.pixels().iter().all(|pixel| pixel.green() == 0 && pixel.blue() == 0));
+
+        // Uniform scales are still rasterized, at the scaled size
+        let scaled = inked_bounds(&render(Affine::scale(2.0))).unwrap();
+        assert!((scaled.width() - 2.0 * bounds.width()).abs() <= 3.0, "{scaled:?}");
+        assert!((scaled.height() - 2.0 * bounds.height()).abs() <= 3.0, "{scaled:?}");
+
+        // Other transforms fill the glyph outlines, which land in the same place
+        let stretched = inked_bounds(&render(Affine::scale_non_uniform(2.0, 1.0))).unwrap();
+        assert!((stretched.y0 - bounds.y0).abs() <= 1.0, "{stretched:?}");
+        assert!((stretched.y1 - bounds.y1).abs() <= 1.0, "{stretched:?}");
+        assert!((stretched.width() - 2.0 * bounds.width()).abs() <= 3.0, "{stretched:?}");
+    }
+
+    #[test]
+    fn edges_are_aliased_with_antialiasing_off() {
+        let buffer = text_buffer();
+        let render = |antialiasing| {
+            let mut painter = TinySkiaScenePainter::new(96, 96);
+            painter.set_quality(RenderQuality {
+                antialiasing,
+                ..RenderQuality::default()
+            });
+            let circle = peniko::kurbo::Circle::new((48.0, 48.0), 20.5);
+            painter.fill(Fill::NonZero, Affine::IDENTITY, palette::css::BLUE, None, &circle);
+            let color = palette::css::RED;
+            painter.render_text_buffer(&buffer, Point::new(8.0, 8.0), color, Affine::IDENTITY);
+            painter.finish()
+        };
+        let is_partly_covered = |pixel: &tiny_skia::PremultipliedColorU8| {
+            pixel.alpha() != 0 && pixel.alpha() != u8::MAX
+        };
+
+        assert!(render(Antialiasing::Area).pixels().iter().any(is_partly_covered));
+        assert!(!render(Antialiasing::Off).pixels().iter().any(is_partly_covered));
+    }
+}
@@ -0,0 +1,118 @@
+use std::{num::NonZero, sync::Arc};
+
+use anyrender::{PaintScene, RenderQuality, ResumeError, WindowHandle, WindowRenderer};
+use softbuffer::{Context, Surface};
+
+use crate::TinySkiaScenePainter;
+
+// Simple struct to hold the state of the renderer
+pub struct ActiveRenderState {
+    _context: Context<Arc<dyn WindowHandle>>,
+    surface: Surface<Arc<dyn WindowHandle>, Arc<dyn WindowHandle>>,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum RenderState {
+    Active(ActiveRenderState),
+    Suspended,
+}
+
+pub struct TinySkiaWindowRenderer {
+    // The fields MUST be in this order, so that the surface is dropped before the window
+    // Window is cached even when suspended so that it can be reused when the app is resumed after being suspended
+    render_state: RenderState,
+    window_handle: Option<Arc<dyn WindowHandle>>,
+    render_context: TinySkiaScenePainter,
+}
+
+impl TinySkiaWindowRenderer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            render_state: RenderState::Suspended,
+            window_handle: None,
+            render_context: TinySkiaScenePainter::new(0, 0),
+        }
+    }
+}
+
+impl WindowRenderer for TinySkiaWindowRenderer {
+    type ScenePainter<'a> = TinySkiaScenePainter;
+
+    fn is_active(&self) -> bool {
+        matches!(self.render_state, RenderState::Active(_))
+    }
+
+    fn resume(&mut self, window_handle: Arc<dyn WindowHandle>, width: u32, height: u32) {
+        self.try_resume(window_handle, width, height).unwrap();
+    }
+
+    fn try_resume(
+        &mut self,
+        window_handle: Arc<dyn WindowHandle>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ResumeError> {
+        // softbuffer's errors aren't Send + Sync
+        let context = Context::new(window_handle.clone()).map_err(|err| err.to_string())?;
+        let surface =
+            Surface::new(&context, window_handle.clone()).map_err(|err| err.to_string())?;
+        self.render_state = RenderState::Active(ActiveRenderState {
+            _context: context,
+            surface,
+        });
+        self.window_handle = Some(window_handle);
+
+        self.set_size(width, height);
+        Ok(())
+    }
+
+    fn suspend(&mut self) {
+        self.render_state = RenderState::Suspended;
+    }
+
+    fn set_size(&mut self, physical_width: u32, physical_height: u32) {
+        if let RenderState::Active(state) = &mut self.render_state {
+            let width = physical_width.max(1);
+            let height = physical_height.max(1);
+            state
+                .surface
+                .resize(NonZero::new(width).unwrap(), NonZero::new(height).unwrap())
+                .unwrap();
+            let quality = self.render_context.quality();
+            self.render_context = TinySkiaScenePainter::new(width, height);
+            self.render_context.set_quality(quality);
+        };
+    }
+
+    fn set_quality(&mut self, quality: RenderQuality) {
+        self.render_context.set_quality(quality);
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+        let RenderState::Active(state) = &mut self.render_state else {
+            return;
+        };
+        let Ok(mut surface_buffer) = state.surface.buffer_mut() else {
+            return;
+        };
+
+        // Paint
+        self.render_context.reset();
+        draw_fn(&mut self.render_context);
+
+        let pixels = self.render_context.pixmap().pixels();
+        let out = surface_buffer.as_mut();
+        assert_eq!(pixels.len(), out.len());
+        for (src, dest) in pixels.iter().zip(out.iter_mut()) {
+            if src.alpha() == 0 {
+                *dest = u32::MAX;
+            } else {
+                let (r, g, b) = (src.red() as u32, src.green() as u32, src.blue() as u32);
+                *dest = (r << 16) | (g << 8) | b;
+            }
+        }
+
+        surface_buffer.present().unwrap();
+    }
+}
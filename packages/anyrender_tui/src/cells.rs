@@ -0,0 +1,228 @@
+use std::fmt::{self, Write};
+
+use crate::scene::TextCell;
+
+/// The character used to draw cells without text: the foreground color fills the top half of
+/// the cell and the background color shows through in the bottom half.
+const UPPER_HALF_BLOCK: char = '▀';
+
+/// A 24-bit "truecolor" terminal color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+}
+
+/// A single terminal cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Rgb,
+    pub bg: Rgb,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            fg: Rgb::WHITE,
+            bg: Rgb::WHITE,
+        }
+    }
+}
+
+/// A grid of terminal cells, stored row by row.
+///
+/// The [`Display`](fmt::Display) impl writes the grid using ANSI truecolor escape sequences,
+/// one line per row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellGrid {
+    columns: u16,
+    rows: u16,
+    cells: Vec<Cell>,
+}
+
+impl CellGrid {
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Self {
+            columns,
+            rows,
+            cells: vec![Cell::default(); columns as usize * rows as usize],
+        }
+    }
+
+    pub fn columns(&self) -> u16 {
+        self.columns
+    }
+
+    pub fn rows(&self) -> u16 {
+        self.rows
+    }
+
+    pub fn get(&self, column: u16, row: u16) -> Option<&Cell> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        self.cells
+            .get(row as usize * self.columns as usize + column as usize)
+    }
+
+    pub fn get_mut(&mut self, column: u16, row: u16) -> Option<&mut Cell> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        self.cells
+            .get_mut(row as usize * self.columns as usize + column as usize)
+    }
+
+    /// Approximate an RGBA8 image with half block cells.
+    ///
+    /// `rgba` must be `columns * cell_width` pixels wide and `rows * cell_height` pixels tall.
+    /// Pixels are composited over white, which is the default page background.
+    pub(crate) fn from_rgba(
+        rgba: &[u8],
+        columns: u16,
+        rows: u16,
+        cell_width: u16,
+        cell_height: u16,
+    ) -> Self {
+        let mut grid = Self::new(columns, rows);
+        let stride = columns as usize * cell_width as usize * 4;
+        let half = (cell_height / 2).max(1) as usize;
+
+        let average = |x0: usize, y0: usize, y1: usize| {
+            let mut sum = [0u32; 3];
+            let mut count = 0u32;
+            for y in y0..y1 {
+                for x in x0..x0 + cell_width as usize {
+                    let idx = y * stride + x * 4;
+                    let Some(px) = rgba.get(idx..idx + 4) else {
+                        continue;
+                    };
+                    // Buffers are premultiplied, so compositing over white is `c + (255 - a)`
+                    let background = 255 - px[3] as u32;
+                    for (channel, sum) in sum.iter_mut().enumerate() {
+                        *sum += (px[channel] as u32 + background).min(255);
+                    }
+                    count += 1;
+                }
+            }
+            match count {
+                0 => Rgb::WHITE,
+                _ => Rgb(
+                    (sum[0] / count) as u8,
+                    (sum[1] / count) as u8,
+                    (sum[2] / count) as u8,
+                ),
+            }
+        };
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let x0 = column as usize * cell_width as usize;
+                let y0 = row as usize * cell_height as usize;
+                let top = average(x0, y0, y0 + half);
+                let bottom = average(x0, y0 + half, y0 + cell_height as usize);
+
+                if let Some(cell) = grid.get_mut(column, row) {
+                    *cell = if top == bottom {
+                        Cell {
+                            ch: ' ',
+                            fg: top,
+                            bg: bottom,
+                        }
+                    } else {
+                        Cell {
+                            ch: UPPER_HALF_BLOCK,
+                            fg: top,
+                            bg: bottom,
+                        }
+                    };
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Write text into the cells it covers, replacing the block approximation.
+    ///
+    /// The cell background becomes the average color of the whole cell so that the text
+    /// keeps the color of whatever it was drawn on top of.
+    pub(crate) fn overlay_text(&mut self, text: &[TextCell], cell_width: u16, cell_height: u16) {
+        for text_cell in text {
+            let column = (text_cell.position.x / cell_width as f64).floor();
+            let row = (text_cell.position.y / cell_height as f64).floor();
+            if column < 0.0 || row < 0.0 {
+                continue;
+            }
+
+            let Some(cell) = self.get_mut(column as u16, row as u16) else {
+                continue;
+            };
+            let [r, g, b, _] = text_cell.color.to_rgba8().to_u8_array();
+            let bg = Rgb(
+                ((cell.fg.0 as u16 + cell.bg.0 as u16) / 2) as u8,
+                ((cell.fg.1 as u16 + cell.bg.1 as u16) / 2) as u8,
+                ((cell.fg.2 as u16 + cell.bg.2 as u16) / 2) as u8,
+            );
+            *cell = Cell {
+                ch: text_cell.ch,
+                fg: Rgb(r, g, b),
+                bg,
+            };
+        }
+    }
+}
+
+impl fmt::Display for CellGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.cells.chunks(self.columns.max(1) as usize) {
+            let mut current: Option<(Rgb, Rgb)> = None;
+            for cell in row {
+                if current != Some((cell.fg, cell.bg)) {
+                    let Rgb(fr, fg, fb) = cell.fg;
+                    let Rgb(br, bg, bb) = cell.bg;
+                    write!(f, "\x1b[38;2;{fr};{fg};{fb};48;2;{br};{bg};{bb}m")?;
+                    current = Some((cell.fg, cell.bg));
+                }
+                f.write_char(cell.ch)?;
+            }
+            f.write_str("\x1b[0m\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_blocks_split_top_and_bottom() {
+        // One 2x2 cell: black on top, white (transparent) at the bottom
+        let rgba = [
+            0, 0, 0, 255, 0, 0, 0, 255, //
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let grid = CellGrid::from_rgba(&rgba, 1, 1, 2, 2);
+        assert_eq!(
+            grid.get(0, 0),
+            Some(&Cell {
+                ch: UPPER_HALF_BLOCK,
+                fg: Rgb(0, 0, 0),
+                bg: Rgb::WHITE,
+            })
+        );
+    }
+
+    #[test]
+    fn display_emits_truecolor_escapes() {
+        let grid = CellGrid::new(2, 1);
+        assert_eq!(
+            grid.to_string(),
+            "\x1b[38;2;255;255;255;48;2;255;255;255m  \x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! An experimental Anyrender backend which approximates a scene with terminal cells
+//!
+//! The scene is rasterized with vello_cpu at a fixed number of pixels per cell. Each cell is then
+//! drawn as an upper half block character (`▀`) whose foreground color is the average of the top
+//! half of the cell and whose background color is the average of the bottom half, which doubles
+//! the vertical resolution.
+//!
+//! Text is not rasterized. Instead, [`TuiScenePainter`] intercepts text buffers and writes their
+//! characters straight into the cells they cover, so that text stays readable (and selectable)
+//! in the terminal.
+//!
+//! ```ignore
+//! let mut renderer = TuiRenderer::new(120, 40);
+//! let (width, height) = renderer.pixel_size();
+//! let grid = renderer.render(|scene| paint_scene(scene, &doc, 1.0, width, height));
+//! print!("{grid}");
+//! ```
+
+mod cells;
+mod renderer;
+mod scene;
+
+pub use cells::{Cell, CellGrid, Rgb};
+pub use renderer::TuiRenderer;
+pub use scene::{TextCell, TuiScenePainter};
@@ -0,0 +1,79 @@
+use anyrender::ImageRenderer;
+use anyrender_vello_cpu::{VelloCpuImageRenderer, VelloCpuScenePainter};
+
+use crate::cells::CellGrid;
+use crate::scene::{TextCell, TuiScenePainter};
+
+/// Default number of pixels per terminal cell. Terminal cells are roughly twice as tall as
+/// they are wide, and 16px is a typical line height for body text.
+const DEFAULT_CELL_WIDTH: u16 = 8;
+const DEFAULT_CELL_HEIGHT: u16 = 16;
+
+/// Renders a scene into a [`CellGrid`] by rasterizing it with vello_cpu and then downsampling
+/// the pixels into terminal cells.
+pub struct TuiRenderer {
+    columns: u16,
+    rows: u16,
+    cell_width: u16,
+    cell_height: u16,
+    raster: VelloCpuImageRenderer,
+    rgba: Vec<u8>,
+}
+
+impl TuiRenderer {
+    /// Create a renderer for a terminal of `columns` x `rows` cells
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Self::with_cell_size(columns, rows, DEFAULT_CELL_WIDTH, DEFAULT_CELL_HEIGHT)
+    }
+
+    /// Create a renderer with a custom number of pixels per cell
+    pub fn with_cell_size(columns: u16, rows: u16, cell_width: u16, cell_height: u16) -> Self {
+        let cell_width = cell_width.max(1);
+        let cell_height = cell_height.max(2);
+        let width = columns as u32 * cell_width as u32;
+        let height = rows as u32 * cell_height as u32;
+        Self {
+            columns,
+            rows,
+            cell_width,
+            cell_height,
+            raster: VelloCpuImageRenderer::new(width, height),
+            rgba: Vec::new(),
+        }
+    }
+
+    /// The size (in pixels) of the surface that scenes should be painted for
+    pub fn pixel_size(&self) -> (u32, u32) {
+        (
+            self.columns as u32 * self.cell_width as u32,
+            self.rows as u32 * self.cell_height as u32,
+        )
+    }
+
+    pub fn render<F>(&mut self, draw_fn: F) -> CellGrid
+    where
+        F: FnOnce(&mut TuiScenePainter<'_, VelloCpuScenePainter>),
+    {
+        let mut text: Vec<TextCell> = Vec::new();
+        self.raster.render(
+            |scene| {
+                let mut scene = TuiScenePainter {
+                    inner: scene,
+                    text: &mut text,
+                };
+                draw_fn(&mut scene);
+            },
+            &mut self.rgba,
+        );
+
+        let mut grid = CellGrid::from_rgba(
+            &self.rgba,
+            self.columns,
+            self.rows,
+            self.cell_width,
+            self.cell_height,
+        );
+        grid.overlay_text(&text, self.cell_width, self.cell_height);
+        grid
+    }
+}
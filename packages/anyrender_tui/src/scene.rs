@@ -0,0 +1,112 @@
+use anyrender::{Paint, PaintScene};
+use kurbo::{Affine, Point, Rect, Shape, Stroke};
+use peniko::{BlendMode, BrushRef, Color, Fill};
+
+/// A single character of text, positioned in pixel space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextCell {
+    /// The character to draw
+    pub ch: char,
+    /// Position of the center of the glyph (in device pixels)
+    pub position: Point,
+    pub color: Color,
+}
+
+/// A [`PaintScene`] which forwards all drawing to a raster scene, except for text which is
+/// collected so that it can be written directly into terminal cells.
+pub struct TuiScenePainter<'a, S: PaintScene> {
+    pub(crate) inner: &'a mut S,
+    pub(crate) text: &'a mut Vec<TextCell>,
+}
+
+impl<S: PaintScene> PaintScene for TuiScenePainter<'_, S> {
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.text.clear();
+    }
+
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        self.inner.push_layer(blend, alpha, transform, clip);
+    }
+
+    fn pop_layer(&mut self) {
+        self.inner.pop_layer();
+    }
+
+    fn stroke<'b>(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: impl Into<BrushRef<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.inner
+            .stroke(style, transform, brush, brush_transform, shape);
+    }
+
+    fn fill<'b>(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: impl Into<Paint<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.inner
+            .fill(style, transform, brush, brush_transform, shape);
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs.iter() {
+                let Some(ch) = run
+                    .text
+                    .get(glyph.start..glyph.end)
+                    .and_then(|cluster| cluster.chars().next())
+                else {
+                    continue;
+                };
+                if ch.is_whitespace() {
+                    continue;
+                }
+
+                // Glyph positions are relative to the baseline. Nudge the point up by roughly
+                // half an x-height so it lands in the middle of the glyph rather than below it.
+                let local = Point {
+                    x: position.x + (glyph.x + glyph.w / 2.0) as f64,
+                    y: position.y + (run.line_y + glyph.y - glyph.font_size * 0.3) as f64,
+                };
+                self.text.push(TextCell {
+                    ch,
+                    position: transform * local,
+                    color,
+                });
+            }
+        }
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        self.inner
+            .draw_box_shadow(transform, rect, brush, radius, std_dev);
+    }
+}
@@ -0,0 +1,22 @@
+//! Configuration of the GPU renderers
+
+use anyrender::SurfaceColorSpace;
+
+use crate::{GlyphAtlasConfig, HdrConfig};
+
+/// Configuration of a [`VelloWindowRenderer`](crate::VelloWindowRenderer)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GpuRenderConfig {
+    /// How the glyph atlas is kept in check. The atlas is shared by the renderers on a device (see
+    /// [`SharedGlyphCache`](crate::SharedGlyphCache)), so this applies to all of them.
+    pub glyph_atlas: GlyphAtlasConfig,
+    /// The color space the platform shows the surface in, which painted colors are encoded in.
+    /// wgpu can't tag surfaces with a color space, so this declares how they are presented: e.g.
+    /// macOS doesn't color match surfaces by default, showing them in the display's gamut, which
+    /// is Display P3 on most recent Apple displays.
+    pub color_space: SurfaceColorSpace,
+    /// Present to an HDR surface ([`HDR_SURFACE_FORMAT`](crate::HDR_SURFACE_FORMAT)) when the
+    /// surface supports one, so that custom paint sources can submit HDR textures. Only takes
+    /// effect when the renderer is resumed.
+    pub hdr: Option<HdrConfig>,
+}
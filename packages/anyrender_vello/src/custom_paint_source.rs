@@ -5,6 +5,7 @@ use vello::Renderer as VelloRenderer;
 use vello::peniko::Image;
 use wgpu::{Instance, TexelCopyTextureInfoBase, Texture};
 
+use crate::hdr::HdrTextures;
 use crate::wgpu_context::DeviceHandle;
 
 pub trait CustomPaintSource: 'static {
@@ -21,6 +22,8 @@ pub trait CustomPaintSource: 'static {
 
 pub struct CustomPaintCtx<'r> {
     pub(crate) renderer: &'r mut VelloRenderer,
+    /// Present when rendering to an HDR surface
+    pub(crate) hdr_textures: Option<&'r mut HdrTextures>,
 }
 
 #[derive(Copy, Clone, PartialEq, Hash)]
@@ -28,6 +31,7 @@ pub struct TextureHandle {
     pub(crate) id: u64,
     pub(crate) width: u32,
     pub(crate) height: u32,
+    pub(crate) hdr: bool,
 }
 
 impl TextureHandle {
@@ -37,8 +41,19 @@ impl TextureHandle {
 }
 
 impl CustomPaintCtx<'_> {
-    pub(crate) fn new<'a>(renderer: &'a mut VelloRenderer) -> CustomPaintCtx<'a> {
-        CustomPaintCtx { renderer }
+    pub(crate) fn new<'a>(
+        renderer: &'a mut VelloRenderer,
+        hdr_textures: Option<&'a mut HdrTextures>,
+    ) -> CustomPaintCtx<'a> {
+        CustomPaintCtx {
+            renderer,
+            hdr_textures,
+        }
+    }
+
+    /// Whether the output is HDR, so that [`Self::register_hdr_texture`] can be used
+    pub fn is_hdr(&self) -> bool {
+        self.hdr_textures.is_some()
     }
 
     pub fn register_texture(&mut self, texture: Texture) -> TextureHandle {
@@ -47,6 +62,7 @@ impl CustomPaintCtx<'_> {
             id: dummy_image.data.id(),
             width: texture.width(),
             height: texture.height(),
+            hdr: false,
         };
         let base = TexelCopyTextureInfoBase {
             texture: texture.clone(),
@@ -66,7 +82,31 @@ impl CustomPaintCtx<'_> {
         handle
     }
 
+    /// Register a texture with extended-range linear sRGB content, where 1.0 is SDR white (e.g. an
+    /// `Rgba16Float` video frame), to be shown at its full range. The texture needs the
+    /// `TEXTURE_BINDING` usage.
+    ///
+    /// Returns `None` when the output isn't HDR (see [`Self::is_hdr`]), in which case the source
+    /// should tone-map its content to an SDR texture and register that instead.
+    pub fn register_hdr_texture(&mut self, texture: Texture) -> Option<TextureHandle> {
+        let hdr_textures = self.hdr_textures.as_deref_mut()?;
+        let handle = TextureHandle {
+            id: Blob::new(Arc::new([])).id(),
+            width: texture.width(),
+            height: texture.height(),
+            hdr: true,
+        };
+        hdr_textures.insert(handle.id, texture);
+        Some(handle)
+    }
+
     pub fn unregister_texture(&mut self, handle: TextureHandle) {
+        if handle.hdr {
+            if let Some(hdr_textures) = self.hdr_textures.as_deref_mut() {
+                hdr_textures.remove(handle.id);
+            }
+            return;
+        }
         let dummy_image = dummy_image(Some(handle.id), handle.width, handle.height);
         self.renderer.override_image(&dummy_image, None);
     }
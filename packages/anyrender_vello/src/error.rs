@@ -22,3 +22,17 @@ pub enum TextRenderError {
 }
 
 pub type TextRenderResult<T> = Result<T, TextRenderError>;
+
+/// Why a [`Readback`](crate::Readback) failed
+#[derive(Error, Debug)]
+pub enum ReadbackError {
+    #[error("Buffer mapping failed: {0}")]
+    MapFailed(#[from] wgpu::BufferAsyncError),
+
+    #[error("Device lost before the readback finished")]
+    DeviceLost,
+
+    #[cfg(feature = "png")]
+    #[error("PNG encoding failed: {0}")]
+    PngEncodingFailed(#[from] png::EncodingError),
+}
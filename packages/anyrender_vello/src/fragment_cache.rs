@@ -0,0 +1,236 @@
+//! Scene fragments retained across frames
+//!
+//! Rebuilding the whole vello scene every frame re-encodes content which usually hasn't changed.
+//! Painters can instead record parts of the scene as fragments (see
+//! [`PaintScene::begin_fragment`](anyrender::PaintScene::begin_fragment)), which are kept in a
+//! [`FragmentCache`] and spliced into later frames with [`vello::Scene::append`] for as long as
+//! their [`FragmentKey`] stays the same. Only fragments whose version changed are re-encoded.
+//!
+//! Text is drawn by glyphon rather than vello, so a fragment also keeps the text areas that were
+//! drawn while it was recorded, and queues them again (moved by the fragment's translation, and
+//! clipped to the clip it's drawn in) whenever it is reused.
+
+use anyrender::FragmentKey;
+use peniko::kurbo::{Affine, Rect};
+use rustc_hash::FxHashMap;
+
+use crate::PendingTextArea;
+
+/// An encoded fragment, drawn relative to the origin
+pub(crate) struct RetainedFragment {
+    version: u64,
+    pub(crate) scene: vello::Scene,
+    pub(crate) text_areas: Vec<PendingTextArea>,
+    /// The last frame the fragment was drawn in
+    last_used: u64,
+}
+
+/// Reuse statistics for the last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FragmentCacheStats {
+    /// Fragments spliced into the frame without being re-encoded
+    pub reused: u32,
+    /// Fragments encoded during the frame (new, or changed since the last frame)
+    pub encoded: u32,
+    /// Fragments retained after the frame
+    pub retained: usize,
+}
+
+/// Fragments retained by a renderer, keyed by [`FragmentKey::id`]
+#[derive(Default)]
+pub struct FragmentCache {
+    fragments: FxHashMap<u64, RetainedFragment>,
+    frame: u64,
+    current: FragmentCacheStats,
+    last_frame: FragmentCacheStats,
+    /// Fragments being recorded, innermost last
+    pub(crate) recordings: Vec<FragmentRecording>,
+}
+
+impl FragmentCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new frame
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.current = FragmentCacheStats::default();
+    }
+
+    /// Finish the current frame, discarding the fragments which weren't drawn in it
+    pub fn end_frame(&mut self) {
+        debug_assert!(self.recordings.is_empty(), "unfinished fragment recording");
+        self.recordings.clear();
+
+        let frame = self.frame;
+        self.fragments
+            .retain(|_, fragment| fragment.last_used == frame);
+        self.current.retained = self.fragments.len();
+        self.last_frame = self.current;
+    }
+
+    /// Discard every retained fragment
+    pub fn clear(&mut self) {
+        self.fragments.clear();
+    }
+
+    /// Statistics for the last finished frame
+    pub fn stats(&self) -> FragmentCacheStats {
+        self.last_frame
+    }
+
+    /// The fragment for `key`, if one with the same version is retained. Marks it as used in the
+    /// current frame.
+    pub(crate) fn get(&mut self, key: FragmentKey) -> Option<&RetainedFragment> {
+        let frame = self.frame;
+        let fragment = self
+            .fragments
+            .get_mut(&key.id)
+            .filter(|fragment| fragment.version == key.version)?;
+        // Fragments recorded in this frame were counted as encoded
+        if fragment.last_used != frame {
+            fragment.last_used = frame;
+            self.current.reused += 1;
+        }
+        Some(fragment)
+    }
+
+    /// Retain a newly encoded fragment, replacing any older version
+    pub(crate) fn insert(
+        &mut self,
+        key: FragmentKey,
+        scene: vello::Scene,
+        text_areas: Vec<PendingTextArea>,
+    ) {
+        self.current.encoded += 1;
+        self.fragments.insert(
+            key.id,
+            RetainedFragment {
+                version: key.version,
+                scene,
+                text_areas,
+                last_used: self.frame,
+            },
+        );
+    }
+}
+
+/// A fragment being recorded by a [`VelloScenePainter`](crate::VelloScenePainter)
+pub(crate) struct FragmentRecording {
+    pub(crate) key: FragmentKey,
+    /// The scene the fragment is being recorded for, set aside while it is recorded
+    pub(crate) parent: vello::Scene,
+    /// Index of the first text area drawn while recording
+    pub(crate) text_area_start: usize,
+}
+
+/// Move a text area by the translation of `transform`, clipping it to `clip`
+pub(crate) fn translate_text_area(
+    text_area: &PendingTextArea,
+    transform: Affine,
+    clip: Rect,
+) -> PendingTextArea {
+    let offset = transform.translation();
+    PendingTextArea {
+        left: text_area.left + offset.x as f32,
+        top: text_area.top + offset.y as f32,
+        bounds: clip_text_bounds(translate_text_bounds(text_area.bounds, transform), clip),
+        ..text_area.clone()
+    }
+}
+
+/// The bounds glyphon clips text to for the device space clip `clip`, which may be unbounded
+pub(crate) fn text_bounds(clip: Rect) -> glyphon::TextBounds {
+    // Float to int casts saturate, so infinite clips are as unbounded as the default bounds
+    glyphon::TextBounds {
+        left: clip.x0.floor() as i32,
+        top: clip.y0.floor() as i32,
+        right: clip.x1.ceil() as i32,
+        bottom: clip.y1.ceil() as i32,
+    }
+}
+
+/// Clip text bounds to `clip`. Text entirely outside it gets empty bounds.
+fn clip_text_bounds(bounds: glyphon::TextBounds, clip: Rect) -> glyphon::TextBounds {
+    let clip = text_bounds(clip);
+    let left = bounds.left.max(clip.left);
+    let top = bounds.top.max(clip.top);
+    glyphon::TextBounds {
+        left,
+        top,
+        right: bounds.right.min(clip.right).max(left),
+        bottom: bounds.bottom.min(clip.bottom).max(top),
+    }
+}
+
+/// Move text bounds by the translation of `transform`. Unbounded edges stay unbounded.
+fn translate_text_bounds(bounds: glyphon::TextBounds, transform: Affine) -> glyphon::TextBounds {
+    let offset = transform.translation();
+    let (x, y) = (offset.x.round() as i32, offset.y.round() as i32);
+    let translate = |edge: i32, by: i32| match edge {
+        i32::MIN | i32::MAX => edge,
+        _ => edge.saturating_add(by),
+    };
+    glyphon::TextBounds {
+        left: translate(bounds.left, x),
+        top: translate(bounds.top, y),
+        right: translate(bounds.right, x),
+        bottom: translate(bounds.bottom, y),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_fragments_until_their_version_changes() {
+        let key = FragmentKey { id: 1, version: 1 };
+        let mut cache = FragmentCache::new();
+
+        cache.begin_frame();
+        assert!(cache.get(key).is_none());
+        cache.insert(key, vello::Scene::new(), Vec::new());
+        assert!(cache.get(key).is_some());
+        cache.end_frame();
+        let stats = FragmentCacheStats {
+            reused: 0,
+            encoded: 1,
+            retained: 1,
+        };
+        assert_eq!(cache.stats(), stats);
+
+        cache.begin_frame();
+        assert!(cache.get(key).is_some());
+        assert!(cache.get(FragmentKey { version: 2, ..key }).is_none());
+        cache.end_frame();
+        assert_eq!(cache.stats().reused, 1);
+
+        // Fragments which aren't drawn in a frame are discarded
+        cache.begin_frame();
+        cache.end_frame();
+        assert_eq!(cache.stats().retained, 0);
+    }
+
+    #[test]
+    fn replayed_text_is_clipped_to_the_active_clip() {
+        // Text recorded inside a 100x20 clip of its own, at the fragment's origin
+        let recorded = text_bounds(Rect::new(0.0, 0.0, 100.0, 20.0));
+        // Replayed 300px down, into a scroller whose padding box ends 200px down
+        let transform = Affine::translate((10.0, 300.0));
+        let scroller = Rect::new(0.0, 0.0, 400.0, 200.0);
+        let bounds = clip_text_bounds(translate_text_bounds(recorded, transform), scroller);
+        assert_eq!(bounds.top, bounds.bottom);
+
+        // Partly inside the scroller, the text is clipped to the part that is
+        let transform = Affine::translate((10.0, 190.0));
+        let bounds = clip_text_bounds(translate_text_bounds(recorded, transform), scroller);
+        assert_eq!((bounds.left, bounds.top, bounds.right, bounds.bottom), (10, 190, 110, 200));
+
+        // Text without a clip of its own is only clipped by the scroller
+        let unbounded = glyphon::TextBounds::default();
+        let bounds = clip_text_bounds(translate_text_bounds(unbounded, transform), scroller);
+        assert_eq!((bounds.left, bounds.top, bounds.right, bounds.bottom), (0, 0, 400, 200));
+    }
+}
@@ -0,0 +1,658 @@
+//! Glyph atlas shared between renderers
+//!
+//! Every [`GlyphonState`](crate::GlyphonState) created for the same device and texture format on
+//! a thread uses the same [`SharedGlyphCache`], so that windows (and the documents in them) don't
+//! each rasterize and upload their own copy of the same glyphs. The font system is shared too, as
+//! glyphs in the atlas are keyed by font ids which are only meaningful within one font system.
+//!
+//! To share the device between windows, create their renderers from the same
+//! [`WGPUContext`](crate::wgpu_context::WGPUContext) (see
+//! [`VelloWindowRenderer::with_context`](crate::VelloWindowRenderer::with_context)).
+//!
+//! Glyphon evicts every glyph which hasn't been prepared since the atlas was last trimmed, so the
+//! shared atlas is only trimmed once every renderer using it has prepared a frame. A renderer
+//! which draws often can't evict the glyphs of one which draws rarely, unless it has stopped
+//! drawing altogether: renderers which haven't drawn since the last trim while another drew
+//! [`IDLE_FRAMES`] frames no longer hold up trims. Their glyphs are uploaded again with their
+//! next frame.
+//!
+//! When to trim is set by a [`GlyphAtlasConfig`]. Glyphon doesn't report what its atlas holds, so
+//! the cache keeps its own account of the glyphs prepared since each trim, and of the area they
+//! take up, which it reports per frame as [`GlyphAtlasUsage`]. The atlas texture grows to fit the
+//! glyphs of the busiest frames and never shrinks, so once it has grown past
+//! [`GlyphAtlasConfig::max_atlas_size`] and a trim brings its glyphs back within that size, the
+//! atlas is rebuilt at its initial size.
+//!
+//! The atlas also registers with the global [`CacheCoordinator`], which may ask it to shrink when
+//! the app's caches are over their memory budget or the system is low on memory. As the atlas can
+//! only be used on its renderers' thread, that happens at the end of the next frame, or when a
+//! renderer next trims its caches while idle (see [`SharedGlyphCache::trim_to_budget`]).
+
+use std::cell::{Cell, RefCell};
+use std::hash::Hash;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use blitz_text::CacheKey;
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+thread_local! {
+    static SHARED_CACHES: RefCell<Vec<SharedCacheEntry>> = const { RefCell::new(Vec::new()) };
+}
+
+struct SharedCacheEntry {
+    device: wgpu::Device,
+    format: wgpu::TextureFormat,
+    cache: Weak<RefCell<SharedGlyphCache>>,
+}
+
+/// When glyphs are evicted from the shared atlas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GlyphEvictionPolicy {
+    /// Trim the atlas every [`GlyphAtlasConfig::trim_interval`] frames, evicting the glyphs which
+    /// weren't drawn since it was last trimmed
+    #[default]
+    Periodic,
+    /// Only trim the atlas when its glyphs don't fit in [`GlyphAtlasConfig::max_atlas_size`],
+    /// evicting the least recently used ones: those which weren't drawn since it was last trimmed.
+    /// Without a maximum size, glyphs are only evicted when glyphon runs out of atlas space.
+    LeastRecentlyUsed,
+}
+
+/// How the shared glyph atlas is kept in check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphAtlasConfig {
+    pub eviction: GlyphEvictionPolicy,
+    /// The number of frames every renderer using the atlas must draw between trims. `0` disables
+    /// trimming, leaving eviction to glyphon.
+    pub trim_interval: u32,
+    /// The side, in texels, of the square the atlas's glyphs should fit in. `None` lets the atlas
+    /// grow as large as the device allows.
+    pub max_atlas_size: Option<u32>,
+}
+
+impl Default for GlyphAtlasConfig {
+    fn default() -> Self {
+        Self {
+            eviction: GlyphEvictionPolicy::Periodic,
+            trim_interval: 1,
+            max_atlas_size: None,
+        }
+    }
+}
+
+impl GlyphAtlasConfig {
+    fn max_area(&self) -> Option<u64> {
+        self.max_atlas_size.map(|size| size as u64 * size as u64)
+    }
+
+    /// Whether the eviction policy calls for a trim, with glyphs of `resident_area` in the atlas
+    fn trim_due(&self, resident_area: u64) -> bool {
+        match self.eviction {
+            GlyphEvictionPolicy::Periodic => true,
+            GlyphEvictionPolicy::LeastRecentlyUsed => {
+                self.max_area().is_some_and(|max_area| resident_area > max_area)
+            }
+        }
+    }
+}
+
+/// How a renderer has used the shared glyph cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlyphCacheUsage {
+    /// Frames rendered using the cache
+    pub frames: u64,
+    /// Text areas prepared over all frames
+    pub text_areas: u64,
+    /// Whether the renderer has prepared text since the atlas was last trimmed
+    pub prepared_since_trim: bool,
+    /// Frames rendered since the atlas was last trimmed
+    pub frames_since_trim: u32,
+}
+
+/// The shared atlas after a renderer's frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlyphAtlasUsage {
+    /// Distinct glyphs drawn in the frame
+    pub glyphs: usize,
+    /// Glyphs drawn in the frame which weren't in the atlas yet
+    pub uploaded: usize,
+    /// Glyphs in the atlas after the frame
+    pub resident: usize,
+    /// Estimated area of the glyphs in the atlas after the frame, in texels
+    pub resident_area: u64,
+    /// Whether the atlas was trimmed after the frame
+    pub trimmed: bool,
+    /// Whether the atlas was rebuilt after the frame, to shrink its texture
+    pub rebuilt: bool,
+}
+
+/// A renderer using the shared glyph cache
+#[derive(Debug, Clone, Copy, Default)]
+struct GlyphCacheUser {
+    usage: GlyphCacheUsage,
+    last_frame: GlyphAtlasUsage,
+}
+
+/// Renderers which haven't drawn a frame since the atlas was last trimmed stop holding up trims
+/// once another renderer has drawn this many
+pub const IDLE_FRAMES: u32 = 60;
+
+/// Ids of renderers which were dropped while the cache was borrowed, to unregister the next time
+/// it's used
+#[derive(Clone, Default)]
+pub(crate) struct DepartedUsers(Rc<Cell<Vec<u64>>>);
+
+impl DepartedUsers {
+    pub(crate) fn push(&self, id: u64) {
+        let mut ids = self.0.take();
+        ids.push(id);
+        self.0.set(ids);
+    }
+}
+
+/// The renderers using the shared glyph cache
+#[derive(Default)]
+struct GlyphCacheUsers {
+    users: FxHashMap<u64, GlyphCacheUser>,
+    next_id: u64,
+    departed: DepartedUsers,
+}
+
+impl GlyphCacheUsers {
+    fn register(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.users.insert(id, GlyphCacheUser::default());
+        id
+    }
+
+    /// Unregister the renderers which were dropped while the cache was borrowed
+    fn remove_departed(&mut self) {
+        for id in self.departed.0.take() {
+            self.users.remove(&id);
+        }
+    }
+
+    /// Whether every renderer has drawn `trim_interval` frames and prepared text since the atlas
+    /// was last trimmed, other than idle ones
+    fn ready_to_trim(&self, trim_interval: u32) -> bool {
+        if trim_interval == 0 {
+            return false;
+        }
+        let busiest = self
+            .users
+            .values()
+            .map(|user| user.usage.frames_since_trim)
+            .max()
+            .unwrap_or(0);
+        let idle = busiest >= IDLE_FRAMES.max(trim_interval);
+        self.users.values().all(|user| {
+            let usage = &user.usage;
+            (usage.prepared_since_trim && usage.frames_since_trim >= trim_interval)
+                || (idle && usage.frames_since_trim == 0)
+        })
+    }
+
+    /// Note that the atlas was trimmed, so every renderer's glyphs must be prepared again
+    fn trimmed(&mut self) {
+        for user in self.users.values_mut() {
+            user.usage.prepared_since_trim = false;
+            user.usage.frames_since_trim = 0;
+        }
+    }
+}
+
+/// A glyph which was uploaded to the atlas
+#[derive(Debug, Clone, Copy)]
+struct ResidentGlyph {
+    /// Estimated area in the atlas, in texels
+    area: u64,
+    /// Whether it was drawn since the atlas was last trimmed, and so survives the next trim
+    in_use: bool,
+}
+
+/// The glyphs prepared since the atlas was created, less those evicted by trims
+struct ResidentGlyphs<K = CacheKey> {
+    glyphs: FxHashMap<K, ResidentGlyph>,
+    /// Estimated area of the glyphs, in texels
+    area: u64,
+    /// The largest `area` since the atlas was created, which its texture had to fit
+    peak_area: u64,
+}
+
+impl<K> Default for ResidentGlyphs<K> {
+    fn default() -> Self {
+        Self {
+            glyphs: FxHashMap::default(),
+            area: 0,
+            peak_area: 0,
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash> ResidentGlyphs<K> {
+    /// Record that `glyphs` were drawn, returning how many weren't in the atlas yet
+    fn prepare(&mut self, glyphs: &FxHashSet<K>, area_of: impl Fn(&K) -> u64) -> usize {
+        let mut uploaded = 0;
+        for key in glyphs {
+            let glyph = self.glyphs.entry(*key).or_insert_with(|| {
+                uploaded += 1;
+                let area = area_of(key);
+                self.area += area;
+                ResidentGlyph {
+                    area,
+                    in_use: false,
+                }
+            });
+            glyph.in_use = true;
+        }
+        self.peak_area = self.peak_area.max(self.area);
+        uploaded
+    }
+
+    /// Evict the glyphs which weren't drawn since the last trim
+    fn trim(&mut self) {
+        let mut evicted_area = 0;
+        self.glyphs.retain(|_, glyph| {
+            if !glyph.in_use {
+                evicted_area += glyph.area;
+            }
+            std::mem::replace(&mut glyph.in_use, false)
+        });
+        self.area -= evicted_area;
+    }
+
+    /// Whether the texture grew past `max_area` for glyphs which have since been evicted, and
+    /// those left fit within it
+    fn outgrew(&self, max_area: Option<u64>) -> bool {
+        max_area.is_some_and(|max| self.peak_area > max && self.area <= max)
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Estimate the atlas area taken up by a glyph: its em square, plus padding
+fn estimated_glyph_area(key: &CacheKey) -> u64 {
+    let side = f32::from_bits(key.font_size_bits).ceil().max(0.0) as u64 + 2;
+    side * side
+}
+
+/// Glyphs may be in the color atlas, which takes four bytes per texel
+const BYTES_PER_TEXEL: u64 = 4;
+
+/// The atlas's account with the [`CacheCoordinator`], which can be used from any thread
+struct AtlasBudget {
+    /// Estimated bytes of the glyphs in the atlas after the last frame
+    resident_bytes: AtomicUsize,
+    /// The bytes the coordinator asked the atlas to shrink to, or `usize::MAX` if it hasn't
+    target_bytes: AtomicUsize,
+}
+
+impl ManagedCache for AtlasBudget {
+    fn name(&self) -> &str {
+        "glyph atlas"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resident_bytes.load(Ordering::Relaxed)
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        self.target_bytes.fetch_min(target_bytes, Ordering::Relaxed);
+    }
+}
+
+/// Glyph atlas, rasterization cache and font system shared by the renderers on one device
+pub struct SharedGlyphCache {
+    /// Shared cache for pipelines and resources
+    pub cache: glyphon::Cache,
+    /// GPU texture atlas for caching glyphs
+    pub text_atlas: glyphon::TextAtlas,
+    /// Cache for font rasterization
+    pub swash_cache: glyphon::SwashCache,
+    /// Shared font system between blitz-text and glyphon
+    pub font_system: Rc<RefCell<blitz_text::FontSystem>>,
+    config: GlyphAtlasConfig,
+    users: GlyphCacheUsers,
+    trims: u64,
+    rebuilds: u64,
+    resident: ResidentGlyphs,
+    budget: Arc<AtlasBudget>,
+    // Kept to rebuild the atlas
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    format: wgpu::TextureFormat,
+}
+
+impl SharedGlyphCache {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        // Create cache for shared resources (pipelines, shaders, etc.)
+        let cache = glyphon::Cache::new(device);
+        let text_atlas = glyphon::TextAtlas::new(device, queue, &cache, format);
+
+        let budget = Arc::new(AtlasBudget {
+            resident_bytes: AtomicUsize::new(0),
+            target_bytes: AtomicUsize::new(usize::MAX),
+        });
+        let managed_budget: Arc<dyn ManagedCache> = budget.clone();
+        CacheCoordinator::global().register(&managed_budget, 1);
+
+        Self {
+            cache,
+            text_atlas,
+            swash_cache: glyphon::SwashCache::new(),
+            // Create font system - expensive operation done once per device
+            font_system: Rc::new(RefCell::new(blitz_text::new_font_system())),
+            config: GlyphAtlasConfig::default(),
+            users: GlyphCacheUsers::default(),
+            trims: 0,
+            rebuilds: 0,
+            resident: ResidentGlyphs::default(),
+            budget,
+            device: device.clone(),
+            queue: queue.clone(),
+            format,
+        }
+    }
+
+    /// Get the cache for `device` and `format`, creating it if no renderer on this thread is
+    /// using one
+    pub fn for_device(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> Rc<RefCell<Self>> {
+        SHARED_CACHES.with_borrow_mut(|caches| {
+            caches.retain(|entry| entry.cache.strong_count() > 0);
+
+            let existing = caches
+                .iter()
+                .find(|entry| entry.device == *device && entry.format == format)
+                .and_then(|entry| entry.cache.upgrade());
+            if let Some(cache) = existing {
+                return cache;
+            }
+
+            let cache = Rc::new(RefCell::new(Self::new(device, queue, format)));
+            caches.push(SharedCacheEntry {
+                device: device.clone(),
+                format,
+                cache: Rc::downgrade(&cache),
+            });
+            cache
+        })
+    }
+
+    pub fn config(&self) -> GlyphAtlasConfig {
+        self.config
+    }
+
+    /// Change how the atlas is kept in check. This applies to every renderer sharing it.
+    pub fn set_config(&mut self, config: GlyphAtlasConfig) {
+        self.config = config;
+    }
+
+    /// Start tracking a renderer's usage, returning the id to report it with
+    pub fn register_user(&mut self) -> u64 {
+        self.users.register()
+    }
+
+    /// Stop tracking a renderer which no longer uses the cache
+    pub fn unregister_user(&mut self, id: u64) {
+        self.users.users.remove(&id);
+    }
+
+    /// Where renderers which are dropped while the cache is borrowed leave their ids, to be
+    /// unregistered the next time it's used
+    pub(crate) fn departed_users(&self) -> DepartedUsers {
+        self.users.departed.clone()
+    }
+
+    /// Record that a renderer prepared `text_areas` text areas, drawing `glyphs`, for its next
+    /// frame
+    pub fn record_prepare(
+        &mut self,
+        id: u64,
+        text_areas: usize,
+        glyphs: impl IntoIterator<Item = CacheKey>,
+    ) {
+        self.users.remove_departed();
+        let Some(user) = self.users.users.get_mut(&id) else {
+            return;
+        };
+        user.usage.text_areas += text_areas as u64;
+        user.usage.prepared_since_trim = true;
+
+        let glyphs: FxHashSet<CacheKey> = glyphs.into_iter().collect();
+        let uploaded = self.resident.prepare(&glyphs, estimated_glyph_area);
+        user.last_frame = GlyphAtlasUsage {
+            glyphs: glyphs.len(),
+            uploaded,
+            ..GlyphAtlasUsage::default()
+        };
+    }
+
+    /// Record that a renderer finished rendering a frame, and trim the atlas if that is due under
+    /// the eviction policy and every renderer which isn't idle has prepared text since it was last
+    /// trimmed
+    pub fn end_frame(&mut self, id: u64) {
+        self.users.remove_departed();
+        let Some(user) = self.users.users.get_mut(&id) else {
+            return;
+        };
+        user.usage.frames += 1;
+        user.usage.frames_since_trim = user.usage.frames_since_trim.saturating_add(1);
+
+        let config = self.config;
+        let due = config.trim_due(self.resident.area);
+        let ready = self.users.ready_to_trim(config.trim_interval);
+
+        let (mut trimmed, mut rebuilt) = (false, false);
+        if due && ready {
+            self.trim();
+            trimmed = true;
+            // The texture is still the size it grew to. If the remaining glyphs fit within the
+            // maximum, start again with a small one.
+            if self.resident.outgrew(config.max_area()) {
+                self.rebuild_atlas();
+                rebuilt = true;
+            }
+        }
+
+        let (shrink_trimmed, shrink_rebuilt) = self.shrink_to_budget(trimmed);
+        trimmed |= shrink_trimmed;
+        rebuilt |= shrink_rebuilt;
+
+        let (resident, resident_area) = (self.resident.glyphs.len(), self.resident.area);
+        if let Some(user) = self.users.users.get_mut(&id) {
+            user.last_frame = GlyphAtlasUsage {
+                resident,
+                resident_area,
+                trimmed,
+                rebuilt,
+                ..user.last_frame
+            };
+        }
+    }
+
+    /// Shrink the atlas if the coordinator asked it to, without waiting for the end of a frame.
+    /// Renderers call this between frames, so that an atlas whose renderers are idle still
+    /// shrinks.
+    pub fn trim_to_budget(&mut self) {
+        self.users.remove_departed();
+        self.shrink_to_budget(false);
+    }
+
+    /// Shrink the atlas to the size the coordinator asked for, if it asked, returning whether it
+    /// was trimmed (unless it already was, as `trimmed` says) and whether it was rebuilt. Glyphs
+    /// prepared since the last trim survive a trim, so if that isn't enough the atlas is rebuilt
+    /// without any. Also reports the atlas's size to the coordinator.
+    fn shrink_to_budget(&mut self, trimmed: bool) -> (bool, bool) {
+        let (mut shrink_trimmed, mut rebuilt) = (false, false);
+        let target_bytes = self.budget.target_bytes.swap(usize::MAX, Ordering::Relaxed);
+        let target_area = target_bytes as u64 / BYTES_PER_TEXEL;
+        if self.resident.area > target_area {
+            if !trimmed {
+                self.trim();
+                shrink_trimmed = true;
+            }
+            if self.resident.area > target_area {
+                self.rebuild_atlas();
+                rebuilt = true;
+            }
+        }
+
+        let resident_bytes = self.resident.area.saturating_mul(BYTES_PER_TEXEL);
+        self.budget
+            .resident_bytes
+            .store(resident_bytes.try_into().unwrap_or(usize::MAX), Ordering::Relaxed);
+        (shrink_trimmed, rebuilt)
+    }
+
+    /// Evict the glyphs which weren't prepared since the last trim
+    fn trim(&mut self) {
+        self.text_atlas.trim();
+        self.trims += 1;
+        self.users.trimmed();
+        self.resident.trim();
+    }
+
+    /// Replace the atlas with an empty one. The glyphs of each renderer's next frame are uploaded
+    /// to it again.
+    fn rebuild_atlas(&mut self) {
+        self.text_atlas =
+            glyphon::TextAtlas::new(&self.device, &self.queue, &self.cache, self.format);
+        self.rebuilds += 1;
+        self.resident.clear();
+    }
+
+    /// How the renderer with `id` has used the cache
+    pub fn usage(&self, id: u64) -> Option<GlyphCacheUsage> {
+        self.users.users.get(&id).map(|user| user.usage)
+    }
+
+    /// The state of the atlas after the last frame of the renderer with `id`
+    pub fn atlas_usage(&self, id: u64) -> Option<GlyphAtlasUsage> {
+        self.users.users.get(&id).map(|user| user.last_frame)
+    }
+
+    /// Number of renderers sharing the cache
+    pub fn user_count(&self) -> usize {
+        self.users.users.len()
+    }
+
+    /// Number of times the atlas has been trimmed
+    pub fn trim_count(&self) -> u64 {
+        self.trims
+    }
+
+    /// Number of times the atlas has been rebuilt to shrink its texture
+    pub fn rebuild_count(&self) -> u64 {
+        self.rebuilds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyphs(keys: &[u32]) -> FxHashSet<u32> {
+        keys.iter().copied().collect()
+    }
+
+    #[test]
+    fn least_recently_used_glyphs_are_evicted_past_the_maximum_size() {
+        let config = GlyphAtlasConfig {
+            eviction: GlyphEvictionPolicy::LeastRecentlyUsed,
+            trim_interval: 1,
+            max_atlas_size: Some(20),
+        };
+        let area = |_: &u32| 100;
+        let mut resident = ResidentGlyphs::default();
+
+        assert_eq!(resident.prepare(&glyphs(&[1, 2, 3]), area), 3);
+        assert!(!config.trim_due(resident.area));
+
+        // Glyphs drawn since the last trim survive it, even those of earlier frames
+        assert_eq!(resident.prepare(&glyphs(&[3, 4, 5]), area), 2);
+        assert!(config.trim_due(resident.area));
+        resident.trim();
+        assert_eq!(resident.area, 500);
+
+        assert_eq!(resident.prepare(&glyphs(&[4, 5]), area), 0);
+        assert!(config.trim_due(resident.area));
+        resident.trim();
+        let mut left: Vec<u32> = resident.glyphs.keys().copied().collect();
+        left.sort();
+        assert_eq!(left, [4, 5]);
+        assert_eq!(resident.area, 200);
+        assert!(resident.outgrew(config.max_area()));
+
+        resident.clear();
+        assert_eq!(resident.prepare(&glyphs(&[4, 5]), area), 2);
+        assert!(!resident.outgrew(config.max_area()));
+    }
+
+    #[test]
+    fn periodic_eviction_is_always_due() {
+        let periodic = GlyphAtlasConfig::default();
+        assert!(periodic.trim_due(0));
+
+        let unbounded = GlyphAtlasConfig {
+            eviction: GlyphEvictionPolicy::LeastRecentlyUsed,
+            ..periodic
+        };
+        assert!(!unbounded.trim_due(u64::MAX));
+    }
+
+    #[test]
+    fn idle_renderers_stop_holding_up_trims() {
+        let mut users = GlyphCacheUsers::default();
+        let busy = users.register();
+        let idle = users.register();
+        let draw = |users: &mut GlyphCacheUsers, id: u64| {
+            let usage = &mut users.users.get_mut(&id).unwrap().usage;
+            usage.prepared_since_trim = true;
+            usage.frames_since_trim += 1;
+        };
+
+        draw(&mut users, busy);
+        draw(&mut users, idle);
+        assert!(users.ready_to_trim(1));
+        assert!(!users.ready_to_trim(0));
+        users.trimmed();
+
+        // A renderer which draws rarely keeps its glyphs until it has drawn again...
+        for _ in 1..IDLE_FRAMES {
+            draw(&mut users, busy);
+            assert!(!users.ready_to_trim(1));
+        }
+        // ...unless it hasn't drawn at all while the other drew `IDLE_FRAMES` frames
+        draw(&mut users, busy);
+        assert!(users.ready_to_trim(1));
+    }
+
+    #[test]
+    fn renderers_dropped_while_the_cache_is_borrowed_are_unregistered_later() {
+        let mut users = GlyphCacheUsers::default();
+        let kept = users.register();
+        let dropped = users.register();
+        users.departed.clone().push(dropped);
+        assert_eq!(users.users.len(), 2);
+
+        users.remove_departed();
+        assert!(users.users.contains_key(&kept));
+        assert!(!users.users.contains_key(&dropped));
+
+        // The dropped renderer no longer holds up trims
+        let usage = &mut users.users.get_mut(&kept).unwrap().usage;
+        usage.prepared_since_trim = true;
+        usage.frames_since_trim = 1;
+        assert!(users.ready_to_trim(1));
+    }
+}
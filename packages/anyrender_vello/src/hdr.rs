@@ -0,0 +1,375 @@
+//! Presenting to HDR surfaces
+//!
+//! Pages are always rendered in SDR, into the same `Rgba8Unorm` target as on SDR surfaces. On an
+//! HDR surface that target is converted to the surface's extended-range linear format while
+//! presenting, with SDR white shown at [`HdrConfig::sdr_white_nits`], and the HDR textures custom
+//! paint sources submitted (e.g. video frames) are drawn beneath it at their full range, through
+//! holes cleared in the SDR scene where they were painted.
+
+use anyrender::SurfaceColorSpace;
+use peniko::kurbo::{Affine, Point, Rect};
+use rustc_hash::FxHashMap;
+use wgpu::util::DeviceExt as _;
+use wgpu::{
+    BindGroupLayout, Buffer, CommandEncoder, Device, Queue, RenderPipeline, Sampler, Texture,
+    TextureFormat, TextureView,
+};
+
+/// The format HDR surfaces are configured with. It is presented as extended-range linear sRGB
+/// (scRGB on Windows, EDR on macOS).
+pub const HDR_SURFACE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Linear Display P3 to linear sRGB, by rows
+const P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.224_940_2, -0.224_940_4, 0.0],
+    [-0.042_056_955, 1.042_057_1, 0.0],
+    [-0.019_637_555, -0.078_636_05, 1.098_273_5],
+];
+
+/// How SDR content is shown on an HDR surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HdrConfig {
+    /// The brightness SDR white (e.g. a white page background) is shown at, in nits. Ignored on
+    /// macOS, where the system maps SDR white to the display's brightness setting.
+    pub sdr_white_nits: u16,
+}
+
+impl Default for HdrConfig {
+    /// The reference white of ITU-R BT.2408
+    fn default() -> Self {
+        Self {
+            sdr_white_nits: 203,
+        }
+    }
+}
+
+impl HdrConfig {
+    /// The linear value SDR white is written to the surface as. EDR values are relative to SDR
+    /// white, while 1.0 is 80 nits in scRGB.
+    fn sdr_white(self) -> f32 {
+        if cfg!(target_os = "macos") {
+            1.0
+        } else {
+            self.sdr_white_nits as f32 / 80.0
+        }
+    }
+}
+
+/// The HDR textures registered by custom paint sources, and where they were painted this frame
+#[derive(Default)]
+pub(crate) struct HdrTextures {
+    textures: FxHashMap<u64, Texture>,
+    layers: Vec<HdrLayer>,
+}
+
+/// An HDR texture painted into a quad of the surface
+struct HdrLayer {
+    texture_id: u64,
+    /// Positions in pixels and texture coordinates, in triangle strip order
+    vertices: [[f32; 4]; 4],
+}
+
+impl HdrTextures {
+    pub(crate) fn insert(&mut self, id: u64, texture: Texture) {
+        self.textures.insert(id, texture);
+    }
+
+    pub(crate) fn remove(&mut self, id: u64) {
+        self.textures.remove(&id);
+    }
+
+    /// Drop the textures, which belong to the device of a suspended renderer
+    pub(crate) fn clear(&mut self) {
+        self.textures.clear();
+        self.layers.clear();
+    }
+
+    pub(crate) fn begin_frame(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Paint a texture into `bounds` (the bounds of the filled shape), with `brush_transform`
+    /// mapping the texture's pixels into the shape's space
+    pub(crate) fn push_layer(
+        &mut self,
+        texture_id: u64,
+        texture_size: (u32, u32),
+        transform: Affine,
+        brush_transform: Affine,
+        bounds: Rect,
+    ) {
+        let to_texture = brush_transform.inverse();
+        let (width, height) = (texture_size.0 as f64, texture_size.1 as f64);
+        let corners = [
+            Point::new(bounds.x0, bounds.y0),
+            Point::new(bounds.x1, bounds.y0),
+            Point::new(bounds.x0, bounds.y1),
+            Point::new(bounds.x1, bounds.y1),
+        ];
+        let vertices = corners.map(|corner| {
+            let position = transform * corner;
+            let texel = to_texture * corner;
+            [position.x, position.y, texel.x / width, texel.y / height].map(|v| v as f32)
+        });
+        self.layers.push(HdrLayer {
+            texture_id,
+            vertices,
+        });
+    }
+}
+
+/// Converts the SDR target to an HDR surface
+pub(crate) struct HdrCompositor {
+    layout: BindGroupLayout,
+    sdr_pipeline: RenderPipeline,
+    layer_pipeline: RenderPipeline,
+    sampler: Sampler,
+    params: Buffer,
+}
+
+impl HdrCompositor {
+    pub(crate) fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("HDR compositor"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("hdr.wgsl").into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("HDR compositor"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("HDR compositor"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |label: &str,
+                        vertex: &str,
+                        fragment: &str,
+                        buffers: &[wgpu::VertexBufferLayout<'_>]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some(vertex),
+                    compilation_options: Default::default(),
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(fragment),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_SURFACE_FORMAT,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let layer_vertices = wgpu::VertexBufferLayout {
+            array_stride: 16,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2],
+        };
+        let sdr_pipeline = pipeline("HDR compositor: SDR", "vs_fullscreen", "fs_sdr", &[]);
+        let layer_pipeline = pipeline(
+            "HDR compositor: HDR layers",
+            "vs_layer",
+            "fs_layer",
+            &[layer_vertices],
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("HDR compositor"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("HDR compositor params"),
+            size: 64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            layout,
+            sdr_pipeline,
+            layer_pipeline,
+            sampler,
+            params,
+        }
+    }
+
+    /// Update the parameters the next [`Self::composite`] uses
+    pub(crate) fn prepare(
+        &self,
+        queue: &Queue,
+        width: u32,
+        height: u32,
+        config: HdrConfig,
+        color_space: SurfaceColorSpace,
+    ) {
+        queue.write_buffer(&self.params, 0, &params(width, height, config, color_space));
+    }
+
+    /// Draw the HDR layers painted this frame and then the SDR target `sdr` over them into
+    /// `target`, which must be a view of an [`HDR_SURFACE_FORMAT`] texture
+    pub(crate) fn composite(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        sdr: &TextureView,
+        hdr_textures: &HdrTextures,
+        target: &TextureView,
+    ) {
+        let bind_group = |view: &TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("HDR compositor"),
+                layout: &self.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.params.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+
+        let layers: Vec<_> = hdr_textures
+            .layers
+            .iter()
+            .filter_map(|layer| {
+                let texture = hdr_textures.textures.get(&layer.texture_id)?;
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                Some((layer, bind_group(&view)))
+            })
+            .collect();
+        let vertices: Vec<u8> = layers
+            .iter()
+            .flat_map(|(layer, _)| layer.vertices.as_flattened())
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        let vertex_buffer = (!vertices.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("HDR layer vertices"),
+                contents: &vertices,
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+        let sdr_bind_group = bind_group(sdr);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("HDR Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if let Some(vertex_buffer) = &vertex_buffer {
+            pass.set_pipeline(&self.layer_pipeline);
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            for (index, (_, layer_bind_group)) in layers.iter().enumerate() {
+                let first = index as u32 * 4;
+                pass.set_bind_group(0, layer_bind_group, &[]);
+                pass.draw(first..first + 4, 0..1);
+            }
+        }
+        pass.set_pipeline(&self.sdr_pipeline);
+        pass.set_bind_group(0, &sdr_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// The shader's `Params`: the gamut matrix's columns (padded to 16 bytes each), the viewport
+/// size and the SDR white value
+fn params(width: u32, height: u32, config: HdrConfig, color_space: SurfaceColorSpace) -> Vec<u8> {
+    let gamut = match color_space {
+        SurfaceColorSpace::Srgb => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        SurfaceColorSpace::DisplayP3 => P3_TO_SRGB,
+    };
+    let mut params = [0.0f32; 16];
+    for column in 0..3 {
+        for row in 0..3 {
+            params[column * 4 + row] = gamut[row][column];
+        }
+    }
+    params[12] = width as f32;
+    params[13] = height as f32;
+    params[14] = config.sdr_white();
+    params.iter().flat_map(|value| value.to_ne_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_map_shape_bounds_to_texture_coordinates() {
+        let mut hdr_textures = HdrTextures::default();
+        hdr_textures.push_layer(
+            1,
+            (200, 100),
+            Affine::translate((10.0, 20.0)),
+            Affine::scale(0.5),
+            Rect::new(0.0, 0.0, 50.0, 50.0),
+        );
+
+        let vertices = hdr_textures.layers[0].vertices;
+        assert_eq!(vertices[0], [10.0, 20.0, 0.0, 0.0]);
+        assert_eq!(vertices[3], [60.0, 70.0, 0.5, 1.0]);
+    }
+}
@@ -0,0 +1,58 @@
+// Converts the SDR target to an extended-range linear HDR surface, and draws HDR textures beneath it
+
+struct Params {
+    // Converts linear SDR content from the surface's color space to linear sRGB primaries
+    gamut: mat3x3<f32>,
+    viewport: vec2<f32>,
+    // The linear value SDR white is written as
+    sdr_white: f32,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // One triangle covering the whole viewport
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    let low = c / 12.92;
+    let high = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, c <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_sdr(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Vello writes unpremultiplied, gamma encoded colors
+    let color = textureLoad(source, vec2<i32>(in.position.xy), 0);
+    let linear = params.gamut * srgb_to_linear(color.rgb) * params.sdr_white;
+    return vec4<f32>(linear * color.a, color.a);
+}
+
+@vertex
+fn vs_layer(@location(0) position: vec2<f32>, @location(1) uv: vec2<f32>) -> VertexOutput {
+    let ndc = position / params.viewport * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_layer(in: VertexOutput) -> @location(0) vec4<f32> {
+    // HDR textures are linear sRGB, with 1.0 being SDR white
+    let color = textureSample(source, source_sampler, in.uv);
+    return vec4<f32>(color.rgb * params.sdr_white * color.a, color.a);
+}
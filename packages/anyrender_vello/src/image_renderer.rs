@@ -1,5 +1,6 @@
-use anyrender::ImageRenderer;
+use anyrender::{ImageRenderer, RenderQuality};
 use rustc_hash::FxHashMap;
+use vello::kurbo::Affine;
 use vello::{RendererOptions, Scene as VelloScene};
 use wgpu::{
     BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, TexelCopyBufferInfo,
@@ -7,10 +8,12 @@ use wgpu::{
 };
 
 use crate::wgpu_context::WGPUContext;
-use crate::{DEFAULT_THREADS, VelloScenePainter, GlyphonState};
+use crate::{DEFAULT_THREADS, GlyphonState, Readback, VelloScenePainter};
 
 pub struct VelloImageRenderer {
     size: Extent3d,
+    /// Everything drawn is scaled by this
+    scale_factor: f64,
     // render_context: vello::util::RenderContext,
     device: wgpu::Device,
     queue: wgpu::Queue,
@@ -31,12 +34,6 @@ impl ImageRenderer for VelloImageRenderer {
         Self: 'a;
 
     fn new(width: u32, height: u32) -> Self {
-        let size = Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
-
         // Create render context
         let mut context = WGPUContext::new();
 
@@ -44,9 +41,23 @@ impl ImageRenderer for VelloImageRenderer {
         let device_id = pollster::block_on(context.find_or_create_device(None))
             .expect("No compatible device found");
         let device_handle = context.device_pool.remove(device_id);
-        let device = device_handle.device;
-        let queue = device_handle.queue;
+        Self::with_device(device_handle.device, device_handle.queue, width, height)
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        cpu_buffer: &mut Vec<u8>,
+    ) {
+        self.render_to_texture(draw_fn);
+        self.read_internal_texture(cpu_buffer);
+    }
+}
 
+impl VelloImageRenderer {
+    /// A renderer using an existing device, so that many renderers (e.g. for batches of
+    /// thumbnails at different sizes) can share it
+    pub fn with_device(device: wgpu::Device, queue: wgpu::Queue, width: u32, height: u32) -> Self {
         // Create renderer
         let mut renderer = vello::Renderer::new(
             &device,
@@ -76,34 +87,17 @@ impl ImageRenderer for VelloImageRenderer {
             )
         ).expect("Failed to initialize text system singleton");
 
-        let texture = device.create_texture(&TextureDescriptor {
-            label: Some("Target texture"),
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
-            view_formats: &[],
-        });
-        println!("✅ VelloImageRenderer: Texture created");
-
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        println!("✅ VelloImageRenderer: Texture view created");
-
-        let padded_byte_width = (width * 4).next_multiple_of(256);
-        let buffer_size = padded_byte_width as u64 * height as u64;
-        let gpu_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("val"),
-            size: buffer_size,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        println!("✅ VelloImageRenderer: GPU buffer created");
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let (texture, texture_view, gpu_buffer) = create_target(&device, size);
 
         println!("✅ VelloImageRenderer: Constructing Self");
         Self {
             size,
+            scale_factor: 1.0,
             device,
             queue,
             renderer,
@@ -114,11 +108,44 @@ impl ImageRenderer for VelloImageRenderer {
         }
     }
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
-        &mut self,
-        draw_fn: F,
-        cpu_buffer: &mut Vec<u8>,
-    ) {
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// The `Rgba8Unorm` texture scenes are rendered into
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Change the size of the rendered images, keeping the device and vello renderer
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        (self.texture, self.texture_view, self.gpu_buffer) = create_target(&self.device, self.size);
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Scale everything drawn by `scale_factor`, e.g. to render a scene painted at 1x as a 2x
+    /// image. The size of the rendered images is unchanged (see [`resize`](Self::resize)).
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Render a scene into [`texture`](Self::texture), without reading it back
+    pub fn render_to_texture<F>(&mut self, draw_fn: F) -> &wgpu::Texture
+    where
+        F: FnOnce(&mut VelloScenePainter<'_>),
+    {
         // Create glyphon state temporarily for this render
         // (We can't store it in Self because it contains Rc which is not Send,
         // and VelloImageRenderer must be Send for parallel WPT test execution)
@@ -135,15 +162,47 @@ impl ImageRenderer for VelloImageRenderer {
             renderer: &mut self.renderer,
             custom_paint_sources: &mut FxHashMap::default(),
             glyphon_state: Some(&mut glyphon_state),
+            quality: RenderQuality::default(),
+            fragment_cache: None,
+            hdr_textures: None,
+            clips: Vec::new(),
         };
         draw_fn(&mut scene);
-        self.scene = Some(scene.finish());
-        self.render_internal_scene(cpu_buffer);
+        let mut scene = scene.finish();
+        if self.scale_factor != 1.0 {
+            let mut scaled = VelloScene::new();
+            scaled.append(&scene, Some(Affine::scale(self.scale_factor)));
+            scene = scaled;
+        }
+        self.scene = Some(scene);
+        self.render_internal_scene();
+        &self.texture
     }
-}
 
-impl VelloImageRenderer {
-    fn render_internal_scene(&mut self, cpu_buffer: &mut Vec<u8>) {
+    /// Render a scene and start reading it back. The returned [`Readback`] can be awaited while
+    /// the renderer goes on to render the next scene.
+    pub fn render_to_readback<F>(&mut self, draw_fn: F) -> Readback
+    where
+        F: FnOnce(&mut VelloScenePainter<'_>),
+    {
+        self.render_to_texture(draw_fn);
+        Readback::start(&self.device, &self.queue, &self.texture)
+    }
+
+    /// Render each of `scenes` in turn, reusing the device, renderer and texture, and start
+    /// reading them all back
+    pub fn render_batch<I, F>(&mut self, scenes: I) -> Vec<Readback>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnOnce(&mut VelloScenePainter<'_>),
+    {
+        scenes
+            .into_iter()
+            .map(|draw_fn| self.render_to_readback(draw_fn))
+            .collect()
+    }
+
+    fn render_internal_scene(&mut self) {
         let render_params = vello::RenderParams {
             base_color: vello::peniko::Color::WHITE,
             width: self.size.width,
@@ -161,6 +220,11 @@ impl VelloImageRenderer {
             ))
             .expect("Got non-Send/Sync error from rendering");
 
+        // Empty the Vello scene (memory optimisation)
+        self.scene.as_mut().unwrap().reset();
+    }
+
+    fn read_internal_texture(&mut self, cpu_buffer: &mut Vec<u8>) {
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
@@ -202,8 +266,33 @@ impl VelloImageRenderer {
         // Unmap buffer
         drop(data);
         self.gpu_buffer.unmap();
-
-        // Empty the Vello scene (memory optimisation)
-        self.scene.as_mut().unwrap().reset();
     }
 }
+
+/// The texture scenes are rendered into, and the buffer it is copied into to read it back
+fn create_target(
+    device: &wgpu::Device,
+    size: Extent3d,
+) -> (wgpu::Texture, wgpu::TextureView, wgpu::Buffer) {
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("Target texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let padded_byte_width = (size.width * 4).next_multiple_of(256);
+    let buffer_size = padded_byte_width as u64 * size.height as u64;
+    let gpu_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("val"),
+        size: buffer_size,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    (texture, texture_view, gpu_buffer)
+}
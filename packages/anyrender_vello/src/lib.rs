@@ -1,19 +1,35 @@
 //! A [`vello`] backend for the [`anyrender`] 2D drawing abstraction
+mod config;
 mod debug;
 mod error;
+mod fragment_cache;
+mod hdr;
 mod image_renderer;
+mod readback;
 mod scene;
 mod window_renderer;
 
 pub mod custom_paint_source;
+pub mod glyph_cache;
 pub mod wgpu_context;
 
+use std::cell::RefCell;
 use std::num::NonZeroUsize;
+use std::rc::Rc;
+use std::sync::Arc;
 
+pub use config::GpuRenderConfig;
 pub use custom_paint_source::*;
 use debug::DebugTimer;
-pub use error::{TextRenderError, TextRenderResult};
+pub use error::{ReadbackError, TextRenderError, TextRenderResult};
+pub use fragment_cache::{FragmentCache, FragmentCacheStats};
+pub use glyph_cache::{
+    GlyphAtlasConfig, GlyphAtlasUsage, GlyphCacheUsage, GlyphEvictionPolicy, SharedGlyphCache,
+};
+pub use hdr::{HDR_SURFACE_FORMAT, HdrConfig};
 pub use image_renderer::VelloImageRenderer;
+pub use readback::{Readback, RenderedImage};
+use rustc_hash::FxHashMap;
 pub use scene::VelloScenePainter;
 pub use wgpu;
 pub use wgpu_context::DeviceHandle;
@@ -24,25 +40,34 @@ const DEFAULT_THREADS: Option<NonZeroUsize> = NonZeroUsize::new(1);
 #[cfg(not(target_os = "macos"))]
 const DEFAULT_THREADS: Option<NonZeroUsize> = None;
 
-use std::cell::RefCell;
-use std::rc::Rc;
+/// Block on a future which completes without waiting on the GPU. The browser's event loop can't
+/// be blocked on wasm32, so the future is only polled in place there.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    #[cfg(not(target_arch = "wasm32"))]
+    return pollster::block_on(future);
+    #[cfg(target_arch = "wasm32")]
+    return blitz_text::runtime::block_on(future);
+}
 
 /// State management for glyphon text rendering
 pub struct GlyphonState {
     /// The main text renderer that draws to GPU
     pub text_renderer: glyphon::TextRenderer,
-    /// GPU texture atlas for caching glyphs
-    pub text_atlas: glyphon::TextAtlas,
+    /// Glyph atlas, swash cache and font system shared with other renderers on the same device
+    pub glyph_cache: Rc<RefCell<SharedGlyphCache>>,
     /// Shared font system between blitz-text and glyphon  
     pub font_system: Rc<RefCell<blitz_text::FontSystem>>,
-    /// Cache for font rasterization
-    pub swash_cache: glyphon::SwashCache,
     /// Viewport configuration for the window
     pub viewport: glyphon::Viewport,
-    /// Shared cache for pipelines and resources
-    pub cache: glyphon::Cache,
     /// Text areas collected during scene building
     pub pending_text_areas: Vec<PendingTextArea>,
+    /// Id this renderer reports its glyph cache usage with
+    glyph_cache_user: u64,
+    /// Where this renderer leaves its id if it's dropped while the glyph cache is borrowed
+    departed_users: glyph_cache::DepartedUsers,
+    /// Fonts for drawing glyph runs with vello, by id, so that each font's data is only copied
+    /// out of the font system once
+    vello_fonts: FxHashMap<blitz_text::fontdb::ID, peniko::Font>,
 }
 
 impl GlyphonState {
@@ -54,44 +79,129 @@ impl GlyphonState {
         width: u32,
         height: u32,
     ) -> Self {
-        // Create font system - expensive operation done once
-        let font_system = Rc::new(RefCell::new(blitz_text::FontSystem::new()));
-
-        // Create swash cache for glyph rasterization
-        let swash_cache = glyphon::SwashCache::new();
-
-        // Create cache for shared resources (pipelines, shaders, etc.)
-        let cache = glyphon::Cache::new(device);
-
-        // Create text atlas for GPU glyph caching - 4096x4096 texture
-        let mut text_atlas = glyphon::TextAtlas::new(device, queue, &cache, format);
+        // Share the text atlas (and the font system its glyphs are keyed by) with every other
+        // renderer using this device
+        let glyph_cache = SharedGlyphCache::for_device(device, queue, format);
+        let mut shared = glyph_cache.borrow_mut();
+        let glyph_cache_user = shared.register_user();
+        let departed_users = shared.departed_users();
+        let font_system = shared.font_system.clone();
 
         // Create text renderer for 2D text overlay rendering
         // - MultisampleState::default() (count: 1): Glyphon handles its own antialiasing
         // - None for depth_stencil: 2D text doesn't need depth testing; rendered in painter's order
         //   (would only need Some(DepthStencilState) for 3D text or stencil effects)
         let text_renderer = glyphon::TextRenderer::new(
-            &mut text_atlas,
+            &mut shared.text_atlas,
             device,
             wgpu::MultisampleState::default(),
             None, // No depth stencil - not needed for 2D text overlays
         );
 
         // Create viewport with proper constructor
-        let mut viewport = glyphon::Viewport::new(device, &cache);
+        let mut viewport = glyphon::Viewport::new(device, &shared.cache);
 
         // Update viewport with window dimensions
         viewport.update(queue, glyphon::Resolution { width, height });
 
+        drop(shared);
         Self {
             text_renderer,
-            text_atlas,
+            glyph_cache,
             font_system,
-            swash_cache,
             viewport,
-            cache,
             pending_text_areas: Vec::new(),
+            glyph_cache_user,
+            departed_users,
+            vello_fonts: FxHashMap::default(),
+        }
+    }
+
+    /// How this renderer has used the shared glyph cache
+    pub fn glyph_cache_usage(&self) -> Option<GlyphCacheUsage> {
+        self.glyph_cache.borrow().usage(self.glyph_cache_user)
+    }
+
+    /// The state of the shared glyph atlas after this renderer's last frame
+    pub fn atlas_usage(&self) -> Option<GlyphAtlasUsage> {
+        self.glyph_cache.borrow().atlas_usage(self.glyph_cache_user)
+    }
+
+    /// Change how the shared glyph atlas is kept in check, for every renderer sharing it
+    pub fn set_atlas_config(&self, config: GlyphAtlasConfig) {
+        self.glyph_cache.borrow_mut().set_config(config);
+    }
+
+    /// The font `id` in the shared font system, to draw glyph runs with vello
+    pub(crate) fn vello_font(&mut self, id: blitz_text::fontdb::ID) -> Option<peniko::Font> {
+        if let Some(font) = self.vello_fonts.get(&id) {
+            return Some(font.clone());
         }
+        let font = self.font_system.borrow().db().with_face_data(id, |data, index| {
+            peniko::Font::new(peniko::Blob::new(Arc::new(data.to_vec())), index)
+        })?;
+        self.vello_fonts.insert(id, font.clone());
+        Some(font)
+    }
+
+    /// Prepare the pending text areas for rendering, uploading their glyphs to the atlas
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), glyphon::PrepareError> {
+        let text_areas = self.pending_text_areas.iter().map(|area| glyphon::TextArea {
+            buffer: &area.buffer,
+            left: area.left,
+            top: area.top,
+            scale: area.scale,
+            bounds: area.bounds,
+            default_color: area.color,
+            // Empty slice - custom glyphs are for icons/emoji/special graphics
+            // Standard font-based text rendering doesn't require custom glyphs
+            custom_glyphs: &[],
+        });
+
+        let mut shared = self.glyph_cache.borrow_mut();
+        let shared = &mut *shared;
+        let result = self.text_renderer.prepare(
+            device,
+            queue,
+            &mut shared.font_system.borrow_mut(),
+            &mut shared.text_atlas,
+            &self.viewport,
+            text_areas,
+            &mut shared.swash_cache,
+        );
+        // The glyphs glyphon looked up in (or added to) the atlas
+        let glyphs = self.pending_text_areas.iter().flat_map(|area| {
+            area.buffer.layout_runs().flat_map(move |run| {
+                run.glyphs
+                    .iter()
+                    .map(move |glyph| glyph.physical((area.left, area.top), area.scale).cache_key)
+            })
+        });
+        shared.record_prepare(self.glyph_cache_user, self.pending_text_areas.len(), glyphs);
+        result
+    }
+
+    /// Draw the prepared text
+    pub fn render(&self, pass: &mut wgpu::RenderPass<'_>) -> Result<(), glyphon::RenderError> {
+        let shared = self.glyph_cache.borrow();
+        self.text_renderer
+            .render(&shared.text_atlas, &self.viewport, pass)
+    }
+
+    /// Record that a frame has been rendered, which may let the shared atlas be trimmed
+    pub fn end_frame(&mut self) {
+        self.glyph_cache
+            .borrow_mut()
+            .end_frame(self.glyph_cache_user);
+    }
+
+    /// Shrink the shared atlas if the cache coordinator asked it to, between frames
+    pub fn trim_to_budget(&mut self) {
+        self.glyph_cache.borrow_mut().trim_to_budget();
     }
 
     /// Update viewport dimensions when window is resized
@@ -111,7 +221,17 @@ impl GlyphonState {
     }
 }
 
+impl Drop for GlyphonState {
+    fn drop(&mut self) {
+        match self.glyph_cache.try_borrow_mut() {
+            Ok(mut shared) => shared.unregister_user(self.glyph_cache_user),
+            Err(_) => self.departed_users.push(self.glyph_cache_user),
+        }
+    }
+}
+
 /// A text area waiting to be rendered
+#[derive(Clone)]
 pub struct PendingTextArea {
     /// The blitz-text buffer containing shaped text
     pub buffer: Rc<blitz_text::Buffer>,
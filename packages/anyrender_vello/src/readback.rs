@@ -0,0 +1,133 @@
+//! Copying rendered textures back to the CPU
+//!
+//! A [`Readback`] copies a texture into a mappable buffer as soon as it is started, and can then be
+//! awaited without blocking the calling thread: the device is polled on a short-lived thread until
+//! the buffer is mapped.
+
+use wgpu::{
+    BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, PollType, Queue,
+    TexelCopyBufferInfo, TexelCopyBufferLayout, Texture,
+};
+
+use crate::ReadbackError;
+
+/// An image read back from the GPU, as tightly packed rows of RGBA8 pixels
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RenderedImage {
+    /// Encode the image as a PNG file
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> Result<Vec<u8>, ReadbackError> {
+        let mut png_data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_data, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()?;
+        Ok(png_data)
+    }
+}
+
+/// A copy of a texture to the CPU which is in flight
+pub struct Readback {
+    device: Device,
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_byte_width: u32,
+    receiver: flume::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+impl Readback {
+    /// Start copying `texture`, which must be `Rgba8Unorm` with `COPY_SRC` usage, once the work
+    /// already submitted to `queue` is done
+    pub fn start(device: &Device, queue: &Queue, texture: &Texture) -> Self {
+        let size = texture.size();
+        let padded_byte_width =
+            (size.width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Readback buffer"),
+            size: padded_byte_width as u64 * size.height as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Copy out readback buffer"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_byte_width),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = flume::bounded(1);
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        Self {
+            device: device.clone(),
+            buffer,
+            width: size.width,
+            height: size.height,
+            padded_byte_width,
+            receiver,
+        }
+    }
+
+    /// Wait for the copy to finish without blocking the calling thread
+    pub async fn into_image(self) -> Result<RenderedImage, ReadbackError> {
+        // WebGPU maps buffers by itself, elsewhere the device has to be polled
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let device = self.device.clone();
+            std::thread::spawn(move || device.poll(PollType::Wait));
+        }
+        let mapped = self.receiver.recv_async().await;
+        mapped.map_err(|_| ReadbackError::DeviceLost)??;
+        Ok(self.unpad())
+    }
+
+    /// Block the calling thread until the copy is finished
+    pub fn wait(self) -> Result<RenderedImage, ReadbackError> {
+        self.device
+            .poll(PollType::Wait)
+            .map_err(|_| ReadbackError::DeviceLost)?;
+        let mapped = self.receiver.recv();
+        mapped.map_err(|_| ReadbackError::DeviceLost)??;
+        Ok(self.unpad())
+    }
+
+    /// Strip the padding wgpu requires at the end of each row from the mapped buffer
+    fn unpad(self) -> RenderedImage {
+        let byte_width = (self.width * 4) as usize;
+        let mut data = Vec::with_capacity(byte_width * self.height as usize);
+        let mapped = self.buffer.slice(..).get_mapped_range();
+        for row in mapped.chunks_exact(self.padded_byte_width as usize) {
+            data.extend_from_slice(&row[..byte_width]);
+        }
+        drop(mapped);
+        self.buffer.unmap();
+
+        RenderedImage {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+}
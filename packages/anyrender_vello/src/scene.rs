@@ -1,13 +1,20 @@
 use std::rc::Rc;
 
-use anyrender::{CustomPaint, Paint, PaintScene};
+use anyrender::{CustomPaint, FragmentKey, Paint, PaintScene, RenderQuality};
+use blitz_text::baseline_shift::{glyph_baseline_offset, has_baseline_shifts};
+use blitz_text::kashida::{justified_glyphs, may_have_kashidas};
 use glyphon;
 use peniko::kurbo::{Affine, Point, Rect, Shape, Stroke};
-use peniko::{BlendMode, BrushRef, Color, Fill};
+use peniko::{BlendMode, BrushRef, Color, Compose, Fill, Mix};
 use rustc_hash::FxHashMap;
 use vello::Renderer as VelloRenderer;
 
-use crate::{CustomPaintSource, GlyphonState, custom_paint_source::CustomPaintCtx};
+use crate::fragment_cache::{FragmentRecording, text_bounds, translate_text_area};
+use crate::hdr::HdrTextures;
+use crate::{
+    CustomPaintSource, FragmentCache, GlyphonState, TextureHandle,
+    custom_paint_source::CustomPaintCtx,
+};
 
 // Conversion functions for kurbo version compatibility (0.12.0 to 0.11.3)
 fn convert_affine_to_vello(affine: Affine) -> vello::kurbo::Affine {
@@ -61,14 +68,34 @@ pub struct VelloScenePainter<'r> {
     pub custom_paint_sources: &'r mut FxHashMap<u64, Box<dyn CustomPaintSource>>,
     pub inner: vello::Scene,
     pub glyphon_state: Option<&'r mut GlyphonState>,
+    pub quality: RenderQuality,
+    /// Fragments retained across frames. Fragments aren't supported without a cache.
+    pub fragment_cache: Option<&'r mut FragmentCache>,
+    /// HDR textures painted this frame, when rendering to an HDR surface
+    pub(crate) hdr_textures: Option<&'r mut HdrTextures>,
+    /// The device space bounds of the clip of each pushed layer, within the clips of the layers
+    /// it's pushed in. Glyphon draws text on top of the scene, so it's clipped to these instead.
+    pub(crate) clips: Vec<Rect>,
 }
 
+/// The clip of text which isn't drawn in any layer, or of a fragment being recorded
+const UNCLIPPED: Rect = Rect::new(
+    f64::NEG_INFINITY,
+    f64::NEG_INFINITY,
+    f64::INFINITY,
+    f64::INFINITY,
+);
+
 impl VelloScenePainter<'_> {
     pub fn finish(self) -> vello::Scene {
         self.inner
     }
 
-    fn render_custom_source(&mut self, custom_paint: CustomPaint) -> Option<peniko::Image> {
+    fn clip(&self) -> Rect {
+        self.clips.last().copied().unwrap_or(UNCLIPPED)
+    }
+
+    fn render_custom_source(&mut self, custom_paint: CustomPaint) -> Option<TextureHandle> {
         let CustomPaint {
             source_id,
             width,
@@ -88,6 +115,7 @@ impl VelloScenePainter<'_> {
         let VelloScenePainter {
             renderer,
             custom_paint_sources,
+            hdr_textures,
             ..
         } = self;
 
@@ -102,15 +130,94 @@ impl VelloScenePainter<'_> {
         }
         let source = custom_paint_sources.get_mut(&source_id)?;
         println!("🎨🔧 render_custom_source: found source, calling source.render()");
-        let ctx = CustomPaintCtx::new(renderer);
+        let ctx = CustomPaintCtx::new(renderer, hdr_textures.as_deref_mut());
         let texture_handle = source.render(ctx, width, height, scale)?;
         println!(
-            "🎨🔧 render_custom_source: got texture handle ID {}, returning dummy image",
+            "🎨🔧 render_custom_source: got texture handle ID {}",
             texture_handle.id
         );
 
-        // Return dummy image
-        Some(texture_handle.dummy_image())
+        Some(texture_handle)
+    }
+
+    /// Paint an HDR texture, which is drawn beneath the SDR scene when presenting, through a hole
+    /// cleared in the scene
+    fn paint_hdr_texture(
+        &mut self,
+        handle: TextureHandle,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        let Some(hdr_textures) = self.hdr_textures.as_deref_mut() else {
+            return;
+        };
+        hdr_textures.push_layer(
+            handle.id,
+            (handle.width, handle.height),
+            transform,
+            brush_transform.unwrap_or_default(),
+            shape.bounding_box(),
+        );
+
+        let clear = BlendMode::new(Mix::Normal, Compose::Clear);
+        let vello_transform = convert_affine_to_vello(transform);
+        let vello_shape = convert_shape_to_vello(shape);
+        self.inner.push_layer(clear, 1.0, vello_transform, &vello_shape);
+        self.inner.pop_layer();
+    }
+
+    /// Draw a text buffer as vello glyph runs, which (unlike glyphon) honour the full transform
+    fn draw_transformed_glyphs(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        // The buffer's fonts are loaded from the font system it was shaped with, which text
+        // isn't drawn without
+        let Some(glyphon) = self.glyphon_state.as_deref_mut() else {
+            return;
+        };
+        let vello_transform = convert_affine_to_vello(transform);
+
+        for run in buffer.layout_runs() {
+            let run_glyphs = justified_glyphs(buffer, &run, &mut glyphon.font_system.borrow_mut());
+            // Group glyphs by font and size so that each font is only looked up once per line
+            let mut font_groups: std::collections::BTreeMap<
+                (blitz_text::fontdb::ID, u32),
+                Vec<&blitz_text::LayoutGlyph>,
+            > = std::collections::BTreeMap::new();
+            for glyph in run_glyphs.iter() {
+                font_groups
+                    .entry((glyph.font_id, glyph.font_size.to_bits()))
+                    .or_default()
+                    .push(glyph);
+            }
+
+            for ((font_id, _), glyphs) in font_groups {
+                let Some(font) = glyphon.vello_font(font_id) else {
+                    continue;
+                };
+                self.inner
+                    .draw_glyphs(&font)
+                    .font_size(glyphs[0].font_size)
+                    .transform(vello_transform)
+                    .brush(color)
+                    .draw(
+                        Fill::NonZero,
+                        glyphs.iter().map(|glyph| vello::Glyph {
+                            id: glyph.glyph_id as u32,
+                            x: position.x as f32 + glyph.x,
+                            y: position.y as f32
+                                + run.line_y
+                                + glyph.y
+                                + glyph_baseline_offset(glyph),
+                        }),
+                    );
+            }
+        }
     }
 }
 
@@ -126,12 +233,15 @@ impl PaintScene for VelloScenePainter<'_> {
         transform: Affine,
         clip: &impl Shape,
     ) {
+        let bounds = transform.transform_rect_bbox(clip.bounding_box());
+        self.clips.push(bounds.intersect(self.clip()));
         let vello_transform = convert_affine_to_vello(transform);
         let vello_clip = convert_shape_to_vello(clip);
         self.inner.push_layer(blend, alpha, vello_transform, &vello_clip);
     }
 
     fn pop_layer(&mut self) {
+        self.clips.pop();
         self.inner.pop_layer();
     }
 
@@ -178,12 +288,16 @@ impl PaintScene for VelloScenePainter<'_> {
                     custom_paint.width,
                     custom_paint.height
                 );
-                let Some(image) = self.render_custom_source(*custom_paint) else {
+                let Some(handle) = self.render_custom_source(*custom_paint) else {
                     log::warn!("render_custom_source returned None");
                     return;
                 };
-                log::trace!("got image from render_custom_source");
-                dummy_image = image;
+                if handle.hdr {
+                    self.paint_hdr_texture(handle, transform, brush_transform, shape);
+                    return;
+                }
+                log::trace!("got texture handle from render_custom_source");
+                dummy_image = handle.dummy_image();
                 BrushRef::Image(&dummy_image)
             }
         };
@@ -203,6 +317,9 @@ impl PaintScene for VelloScenePainter<'_> {
         radius: f64,
         std_dev: f64,
     ) {
+        let Some(std_dev) = self.quality.box_shadow_std_dev(std_dev) else {
+            return;
+        };
         let vello_transform = convert_affine_to_vello(transform);
         let vello_rect = convert_rect_to_vello(rect);
         self.inner
@@ -217,6 +334,15 @@ impl PaintScene for VelloScenePainter<'_> {
         transform: Affine,
     ) {
         println!("🎯 render_text_buffer called! glyphon_state is: {}", if self.glyphon_state.is_some() { "Some" } else { "None" });
+        // Glyphon can only translate text and draws the glyphs as they were laid out, so rotated
+        // text (e.g. vertical writing modes), text with raised or lowered spans (e.g. `<sup>`) and
+        // justified Arabic text is drawn as vello glyph runs instead
+        let [_, b, c, _, _, _] = transform.as_coeffs();
+        if b != 0.0 || c != 0.0 || has_baseline_shifts(buffer) || may_have_kashidas(buffer) {
+            self.draw_transformed_glyphs(buffer, position, color, transform);
+            return;
+        }
+
         if let Some(glyphon) = &mut self.glyphon_state {
             // Convert peniko Color to glyphon Color
             let glyphon_color = glyphon::Color::rgba(
@@ -237,7 +363,7 @@ impl PaintScene for VelloScenePainter<'_> {
                 top: scaled_pos.y as f32,
                 scale: 1.0, // Scale is already applied in transform
                 color: glyphon_color,
-                bounds: glyphon::TextBounds::default(),
+                bounds: text_bounds(self.clips.last().copied().unwrap_or(UNCLIPPED)),
                 z_index: glyphon.pending_text_areas.len() as f32,
             });
 
@@ -257,4 +383,68 @@ impl PaintScene for VelloScenePainter<'_> {
             );
         }
     }
+
+    fn supports_retained_fragments(&self) -> bool {
+        self.fragment_cache.is_some()
+    }
+
+    fn draw_retained_fragment(&mut self, key: FragmentKey, transform: Affine) -> bool {
+        let Some(fragment) = self
+            .fragment_cache
+            .as_deref_mut()
+            .and_then(|cache| cache.get(key))
+        else {
+            return false;
+        };
+
+        self.inner
+            .append(&fragment.scene, Some(convert_affine_to_vello(transform)));
+        let clip = self.clips.last().copied().unwrap_or(UNCLIPPED);
+        if let Some(glyphon) = &mut self.glyphon_state {
+            for text_area in &fragment.text_areas {
+                let mut text_area = translate_text_area(text_area, transform, clip);
+                text_area.z_index = glyphon.pending_text_areas.len() as f32;
+                glyphon.pending_text_areas.push(text_area);
+            }
+        }
+        true
+    }
+
+    fn begin_fragment(&mut self, key: FragmentKey) {
+        let Some(cache) = self.fragment_cache.as_deref_mut() else {
+            return;
+        };
+        let text_area_start = self
+            .glyphon_state
+            .as_ref()
+            .map_or(0, |glyphon| glyphon.pending_text_areas.len());
+        cache.recordings.push(FragmentRecording {
+            key,
+            parent: std::mem::replace(&mut self.inner, vello::Scene::new()),
+            text_area_start,
+        });
+        // Fragments are recorded relative to their origin, so the text in them is only clipped by
+        // their own layers until they are drawn
+        self.clips.push(UNCLIPPED);
+    }
+
+    fn end_fragment(&mut self) {
+        let Some(cache) = self.fragment_cache.as_deref_mut() else {
+            return;
+        };
+        let Some(recording) = cache.recordings.pop() else {
+            return;
+        };
+
+        self.clips.pop();
+        let scene = std::mem::replace(&mut self.inner, recording.parent);
+        // Text drawn while recording is queued again, in place, whenever the fragment is drawn
+        let text_areas = match &mut self.glyphon_state {
+            Some(glyphon) => glyphon
+                .pending_text_areas
+                .split_off(recording.text_area_start),
+            None => Vec::new(),
+        };
+        cache.insert(recording.key, scene, text_areas);
+    }
 }
@@ -12,6 +12,8 @@ use wgpu::{
     SurfaceConfiguration, SurfaceTarget, Texture, TextureFormat, TextureView, util::TextureBlitter,
 };
 
+use crate::hdr::{HDR_SURFACE_FORMAT, HdrCompositor};
+
 // Errors that can occur in WgpuContext.
 #[derive(Debug)]
 pub enum WgpuContextError {
@@ -197,7 +199,10 @@ fn create_intermediate_texture(width: u32, height: u32, device: &Device) -> (Tex
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+        // Text is drawn into it with a render pass when presenting to an HDR surface
+        usage: wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::RENDER_ATTACHMENT,
         format: TextureFormat::Rgba8Unorm,
         view_formats: &[],
     });
@@ -229,6 +234,9 @@ pub struct RenderSurface<'s> {
 
     // Blitter for blitting from the intermediate texture to the surface.
     pub blitter: TextureBlitter,
+
+    // Converts the intermediate texture to the surface when it's HDR
+    pub(crate) hdr: Option<HdrCompositor>,
 }
 
 impl std::fmt::Debug for RenderSurface<'_> {
@@ -241,6 +249,7 @@ impl std::fmt::Debug for RenderSurface<'_> {
             .field("target_texture", &self.target_texture)
             .field("target_view", &self.target_view)
             .field("blitter", &"(Not Debug)")
+            .field("hdr", &self.hdr.is_some())
             .finish()
     }
 }
@@ -284,11 +293,39 @@ impl<'s> RenderSurface<'s> {
             target_texture,
             target_view,
             blitter,
+            hdr: None,
         };
         surface.configure();
         Ok(surface)
     }
 
+    /// Switch the surface to [`HDR_SURFACE_FORMAT`], if it supports it. Content is still rendered
+    /// in SDR, and converted to the surface's range when presenting.
+    pub fn enable_hdr(&mut self) -> bool {
+        let capabilities = self.surface.get_capabilities(&self.device_handle.adapter);
+        if !capabilities.formats.contains(&HDR_SURFACE_FORMAT) {
+            return false;
+        }
+        self.format = HDR_SURFACE_FORMAT;
+        self.config.format = HDR_SURFACE_FORMAT;
+        self.hdr = Some(HdrCompositor::new(&self.device_handle.device));
+        self.configure();
+        true
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.hdr.is_some()
+    }
+
+    /// The format content is drawn in before it's presented: the surface's format, or the
+    /// intermediate texture's when the surface is HDR
+    pub fn composition_format(&self) -> TextureFormat {
+        match self.hdr {
+            Some(_) => TextureFormat::Rgba8Unorm,
+            None => self.format,
+        }
+    }
+
     /// Resizes the surface to the new dimensions.
     pub fn resize(&mut self, width: u32, height: u32) {
         let (texture, view) =
@@ -1,9 +1,13 @@
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::{
     Arc,
     atomic::{self, AtomicU64},
 };
 
-use anyrender::{WindowHandle, WindowRenderer};
+use anyrender::{
+    Antialiasing, RenderQuality, ResumeError, SurfaceColorSpace, WindowHandle, WindowRenderer,
+};
 use peniko::Color;
 use rustc_hash::FxHashMap;
 use vello::{
@@ -12,10 +16,14 @@ use vello::{
 use wgpu::{CommandEncoderDescriptor, Features, Limits, PresentMode, TextureViewDescriptor};
 
 use crate::{
-    CustomPaintSource, DebugTimer,
+    CustomPaintSource, DebugTimer, HDR_SURFACE_FORMAT,
+    hdr::HdrTextures,
     wgpu_context::{DeviceHandle, RenderSurface, WGPUContext},
 };
-use crate::{DEFAULT_THREADS, GlyphonState, VelloScenePainter};
+use crate::{
+    DEFAULT_THREADS, FragmentCache, FragmentCacheStats, GlyphAtlasUsage, GlyphonState,
+    GpuRenderConfig, VelloScenePainter,
+};
 
 static PAINT_SOURCE_ID: AtomicU64 = AtomicU64::new(0);
 
@@ -47,11 +55,15 @@ pub struct VelloWindowRenderer {
     window_handle: Option<Arc<dyn WindowHandle>>,
 
     // Vello
-    wgpu_context: WGPUContext,
+    wgpu_context: Rc<RefCell<WGPUContext>>,
     scene: Option<VelloScene>,
     glyphon_state: Option<GlyphonState>,
+    fragment_cache: FragmentCache,
 
     custom_paint_sources: FxHashMap<u64, Box<dyn CustomPaintSource>>,
+    hdr_textures: HdrTextures,
+    quality: RenderQuality,
+    config: GpuRenderConfig,
 }
 impl VelloWindowRenderer {
     #[allow(clippy::new_without_default)]
@@ -62,13 +74,40 @@ impl VelloWindowRenderer {
     pub fn with_features_and_limits(features: Option<Features>, limits: Option<Limits>) -> Self {
         let features =
             features.unwrap_or_default() | Features::CLEAR_TEXTURE | Features::PIPELINE_CACHE;
+        let wgpu_context = WGPUContext::with_features_and_limits(Some(features), limits);
+        Self::with_context(Rc::new(RefCell::new(wgpu_context)))
+    }
+
+    /// Create a renderer using an existing wgpu context. Renderers which share a context share
+    /// its devices, and with them their glyph atlas (see [`SharedGlyphCache`]).
+    ///
+    /// The context should have been created with [`Features::CLEAR_TEXTURE`] and
+    /// [`Features::PIPELINE_CACHE`] enabled.
+    ///
+    /// [`SharedGlyphCache`]: crate::SharedGlyphCache
+    pub fn with_context(wgpu_context: Rc<RefCell<WGPUContext>>) -> Self {
         Self {
-            wgpu_context: WGPUContext::with_features_and_limits(Some(features), limits),
+            wgpu_context,
             render_state: RenderState::Suspended,
             window_handle: None,
             scene: Some(VelloScene::new()),
             glyphon_state: None,
+            fragment_cache: FragmentCache::new(),
             custom_paint_sources: FxHashMap::default(),
+            hdr_textures: HdrTextures::default(),
+            quality: RenderQuality::default(),
+            config: GpuRenderConfig::default(),
+        }
+    }
+
+    pub fn config(&self) -> GpuRenderConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: GpuRenderConfig) {
+        self.config = config;
+        if let Some(glyphon) = &self.glyphon_state {
+            glyphon.set_atlas_config(config.glyph_atlas);
         }
     }
 
@@ -76,6 +115,21 @@ impl VelloWindowRenderer {
         self.render_state.current_device_handle()
     }
 
+    /// The wgpu context used by this renderer, for creating other renderers which share it
+    pub fn wgpu_context(&self) -> Rc<RefCell<WGPUContext>> {
+        self.wgpu_context.clone()
+    }
+
+    /// How many retained scene fragments the last frame reused and re-encoded
+    pub fn fragment_cache_stats(&self) -> FragmentCacheStats {
+        self.fragment_cache.stats()
+    }
+
+    /// The state of the glyph atlas after the last frame
+    pub fn glyph_atlas_usage(&self) -> Option<GlyphAtlasUsage> {
+        self.glyphon_state.as_ref()?.atlas_usage()
+    }
+
     pub fn current_surface_format(&self) -> Option<wgpu::TextureFormat> {
         match &self.render_state {
             RenderState::Active(state) => Some(state.surface.config.format),
@@ -83,12 +137,30 @@ impl VelloWindowRenderer {
         }
     }
 
+    /// The format text and other GPU content is drawn in, which differs from the surface's format
+    /// on HDR surfaces (see [`RenderSurface::composition_format`])
+    pub fn current_composition_format(&self) -> Option<wgpu::TextureFormat> {
+        match &self.render_state {
+            RenderState::Active(state) => Some(state.surface.composition_format()),
+            RenderState::Suspended => None,
+        }
+    }
+
+    /// Whether the window is presented in HDR, which needs [`GpuRenderConfig::hdr`] and a surface
+    /// which supports [`HDR_SURFACE_FORMAT`]
+    pub fn is_hdr(&self) -> bool {
+        match &self.render_state {
+            RenderState::Active(state) => state.surface.is_hdr(),
+            RenderState::Suspended => false,
+        }
+    }
+
 
 
     pub fn register_custom_paint_source(&mut self, mut source: Box<dyn CustomPaintSource>) -> u64 {
         if let Some(device_handle) = self.render_state.current_device_handle() {
-            let instance = &self.wgpu_context.instance;
-            source.resume(instance, device_handle);
+            let wgpu_context = self.wgpu_context.borrow();
+            source.resume(&wgpu_context.instance, device_handle);
         }
         let id = PAINT_SOURCE_ID.fetch_add(1, atomic::Ordering::SeqCst);
         self.custom_paint_sources.insert(id, source);
@@ -109,30 +181,39 @@ impl VelloWindowRenderer {
             println!("⚠️ Paint source ID {} not found in map", id);
         }
     }
-}
-
-impl WindowRenderer for VelloWindowRenderer {
-    type ScenePainter<'a>
-        = VelloScenePainter<'a>
-    where
-        Self: 'a;
 
-    fn is_active(&self) -> bool {
-        matches!(self.render_state, RenderState::Active(_))
+    /// Resume the renderer without blocking while its GPU device is requested, as is required
+    /// on the web (where WebGPU requests are only resolved by the browser's event loop)
+    // Nothing else borrows the context while a renderer is resuming
+    #[allow(clippy::await_holding_refcell_ref)]
+    pub async fn resume_async(
+        &mut self,
+        window_handle: Arc<dyn WindowHandle>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ResumeError> {
+        let surface = self
+            .wgpu_context
+            .borrow_mut()
+            .create_surface(window_handle.clone(), width, height, PresentMode::AutoVsync)
+            .await?;
+        self.resume_with_surface(window_handle, surface, width, height)
     }
 
-    fn resume(&mut self, window_handle: Arc<dyn WindowHandle>, width: u32, height: u32) {
+    fn resume_with_surface(
+        &mut self,
+        window_handle: Arc<dyn WindowHandle>,
+        mut surface: RenderSurface<'static>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ResumeError> {
         let self_ptr = self as *const Self;
         println!("🟣 VelloWindowRenderer::resume() instance {:p} - custom_paint_sources has {} sources BEFORE resume", 
                  self_ptr, self.custom_paint_sources.len());
         
-        let surface = pollster::block_on(self.wgpu_context.create_surface(
-            window_handle.clone(),
-            width,
-            height,
-            PresentMode::AutoVsync,
-        ))
-        .expect("Error creating surface");
+        if self.config.hdr.is_some() && !surface.enable_hdr() {
+            log::warn!("The surface doesn't support {HDR_SURFACE_FORMAT:?}, presenting in SDR");
+        }
 
         self.window_handle = Some(window_handle);
 
@@ -144,14 +225,16 @@ impl WindowRenderer for VelloWindowRenderer {
             pipeline_cache: None,
         };
 
-        let renderer = VelloRenderer::new(&surface.device_handle.device, options).unwrap();
+        let renderer = VelloRenderer::new(&surface.device_handle.device, options)
+            .map_err(|err| format!("Error creating renderer: {err}"))?;
 
         self.render_state = RenderState::Active(ActiveRenderState { renderer, surface });
 
         // Get device handle and initialize custom paint sources
         {
             let device_handle = self.render_state.current_device_handle().unwrap();
-            let instance = &self.wgpu_context.instance;
+            let wgpu_context = self.wgpu_context.borrow();
+            let instance = &wgpu_context.instance;
             println!("🟣 VelloWindowRenderer::resume() - resuming {} custom paint sources", 
                      self.custom_paint_sources.len());
             for source in self.custom_paint_sources.values_mut() {
@@ -168,13 +251,15 @@ impl WindowRenderer for VelloWindowRenderer {
         };
 
         println!("🎯 INITIALIZING GLYPHON STATE in resume()");
-        self.glyphon_state = Some(GlyphonState::new(
+        let glyphon_state = GlyphonState::new(
             &state.surface.device_handle.device,
             &state.surface.device_handle.queue,
-            state.surface.config.format,
+            state.surface.composition_format(),
             width,
             height,
-        ));
+        );
+        glyphon_state.set_atlas_config(self.config.glyph_atlas);
+        self.glyphon_state = Some(glyphon_state);
         println!("✅ GLYPHON STATE INITIALIZED SUCCESSFULLY");
 
         // Text system initialization will be handled separately
@@ -184,16 +269,48 @@ impl WindowRenderer for VelloWindowRenderer {
         match state.renderer.initialize_resolver(
             &state.surface.device_handle.device,
             &state.surface.device_handle.queue,
-            state.surface.config.format,
+            state.surface.composition_format(),
         ) {
             Ok(()) => {
                 log::info!("Successfully initialized vello resolver with GPU context");
             }
             Err(e) => {
                 log::error!("Failed to initialize vello resolver: {}", e);
-                return;
             }
         }
+
+        Ok(())
+    }
+}
+
+impl WindowRenderer for VelloWindowRenderer {
+    type ScenePainter<'a>
+        = VelloScenePainter<'a>
+    where
+        Self: 'a;
+
+    fn is_active(&self) -> bool {
+        matches!(self.render_state, RenderState::Active(_))
+    }
+
+    fn resume(&mut self, window_handle: Arc<dyn WindowHandle>, width: u32, height: u32) {
+        self.try_resume(window_handle, width, height)
+            .expect("Error resuming renderer");
+    }
+
+    fn try_resume(
+        &mut self,
+        window_handle: Arc<dyn WindowHandle>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ResumeError> {
+        let surface = pollster::block_on(self.wgpu_context.borrow_mut().create_surface(
+            window_handle.clone(),
+            width,
+            height,
+            PresentMode::AutoVsync,
+        ))?;
+        self.resume_with_surface(window_handle, surface, width, height)
     }
 
     fn suspend(&mut self) {
@@ -209,6 +326,7 @@ impl WindowRenderer for VelloWindowRenderer {
         }
         
         self.glyphon_state = None; // Drop glyphon resources
+        self.hdr_textures.clear();
         self.render_state = RenderState::Suspended;
     }
 
@@ -218,17 +336,35 @@ impl WindowRenderer for VelloWindowRenderer {
         };
     }
 
+    fn color_space(&self) -> SurfaceColorSpace {
+        self.config.color_space
+    }
+
+    fn set_quality(&mut self, quality: RenderQuality) {
+        // Retained fragments were encoded for the previous quality
+        if quality != self.quality {
+            self.fragment_cache.clear();
+        }
+        self.quality = quality;
+    }
+
+    fn trim_caches(&mut self) {
+        if let Some(glyphon) = &mut self.glyphon_state {
+            glyphon.trim_to_budget();
+        }
+    }
+
     fn initialize_text_system(&self, doc: &dyn std::any::Any) -> Result<(), String> {
         println!("🔧 VelloWindowRenderer::initialize_text_system called");
         // Try to downcast to BaseDocument
         if let Some(base_doc) = doc.downcast_ref::<blitz_dom::BaseDocument>() {
             println!("🔧 Successfully downcast to BaseDocument");
             if let Some(device_handle) = self.current_device_handle() {
-                let format = self.current_surface_format().unwrap_or(wgpu::TextureFormat::Bgra8UnormSrgb);
+                let format = self.current_composition_format().unwrap_or(wgpu::TextureFormat::Bgra8UnormSrgb);
                 println!("🎯 INITIALIZING TEXT SYSTEM with GPU context");
                 // Initialize text system with GPU context for hardware-accelerated rendering
                 // Same parameters as TextRenderer::new() - see lib.rs for detailed explanation
-                match pollster::block_on(base_doc.initialize_text_system_with_gpu_context(
+                match crate::block_on(base_doc.initialize_text_system_with_gpu_context(
                     &device_handle.device,
                     &device_handle.queue,
                     format,
@@ -278,73 +414,59 @@ impl WindowRenderer for VelloWindowRenderer {
             base_color: Color::WHITE,
             width: state.surface.config.width,
             height: state.surface.config.height,
-            antialiasing_method: vello::AaConfig::Msaa16,
+            antialiasing_method: match self.quality.antialiasing {
+                Antialiasing::Area | Antialiasing::Off => vello::AaConfig::Area,
+                Antialiasing::Msaa8 => vello::AaConfig::Msaa8,
+                Antialiasing::Msaa16 => vello::AaConfig::Msaa16,
+            },
         };
 
-        // Regenerate the vello scene
+        // Regenerate the vello scene, reusing the fragments retained from the last frame
+        self.fragment_cache.begin_frame();
+        self.hdr_textures.begin_frame();
         let mut scene = VelloScenePainter {
             inner: self.scene.take().unwrap(),
             renderer: &mut state.renderer,
             custom_paint_sources: &mut self.custom_paint_sources,
             glyphon_state: self.glyphon_state.as_mut(),
+            quality: self.quality,
+            fragment_cache: Some(&mut self.fragment_cache),
+            hdr_textures: surface.is_hdr().then_some(&mut self.hdr_textures),
+            clips: Vec::new(),
         };
         draw_fn(&mut scene);
         self.scene = Some(scene.finish());
+        self.fragment_cache.end_frame();
         timer.record_time("cmd");
 
-        // Prepare collected text with glyphon BEFORE vello rendering
+        // Prepare collected text with glyphon BEFORE vello rendering. This happens even when there
+        // is no text, so that text from a previous frame (whose glyphs may since have been evicted
+        // from the shared atlas) isn't drawn again.
         if let Some(glyphon) = &mut self.glyphon_state {
-            if !glyphon.pending_text_areas.is_empty() {
-                // Convert pending text areas to glyphon format
-                let text_areas: Vec<glyphon::TextArea> = glyphon
-                    .pending_text_areas
-                    .iter()
-                    .map(|area| glyphon::TextArea {
-                        buffer: &area.buffer,
-                        left: area.left,
-                        top: area.top,
-                        scale: area.scale,
-                        bounds: area.bounds,
-                        default_color: area.color,
-                        // Empty slice - custom glyphs are for icons/emoji/special graphics
-                        // Standard font-based text rendering doesn't require custom glyphs
-                        custom_glyphs: &[],
-                    })
-                    .collect();
-
-                // Update viewport to match current window size
-                glyphon.viewport.update(
-                    &device_handle.queue,
-                    glyphon::Resolution {
-                        width: state.surface.config.width,
-                        height: state.surface.config.height,
-                    },
-                );
-
-                // Prepare glyphs for GPU - this uploads glyph textures to the atlas
-                match glyphon.text_renderer.prepare(
-                    &device_handle.device,
-                    &device_handle.queue,
-                    &mut glyphon.font_system.borrow_mut(),
-                    &mut glyphon.text_atlas,
-                    &glyphon.viewport,
-                    text_areas,
-                    &mut glyphon.swash_cache,
-                ) {
-                    Ok(_) => {
-                        log::trace!(
-                            "Prepared {} text areas for rendering",
-                            glyphon.pending_text_areas.len()
-                        );
-                    }
-                    Err(e) => {
-                        log::error!("Failed to prepare text for rendering: {:?}", e);
-                    }
+            // Update viewport to match current window size
+            glyphon.viewport.update(
+                &device_handle.queue,
+                glyphon::Resolution {
+                    width: state.surface.config.width,
+                    height: state.surface.config.height,
+                },
+            );
+
+            // Prepare glyphs for GPU - this uploads glyph textures to the atlas
+            match glyphon.prepare(&device_handle.device, &device_handle.queue) {
+                Ok(()) => {
+                    log::trace!(
+                        "Prepared {} text areas for rendering",
+                        glyphon.pending_text_areas.len()
+                    );
+                }
+                Err(e) => {
+                    log::error!("Failed to prepare text for rendering: {:?}", e);
                 }
-
-                // Clear pending areas for next frame
-                glyphon.pending_text_areas.clear();
             }
+
+            // Clear pending areas for next frame
+            glyphon.pending_text_areas.clear();
         }
         timer.record_time("text_prepare");
 
@@ -383,14 +505,22 @@ impl WindowRenderer for VelloWindowRenderer {
                 label: Some("Surface Blit"),
             });
 
-        state.surface.blitter.copy(
-            &device_handle.device,
-            &mut encoder,
-            &surface.target_view,
-            &surface_texture
-                .texture
-                .create_view(&TextureViewDescriptor::default()),
-        );
+        let surface_view = surface_texture
+            .texture
+            .create_view(&TextureViewDescriptor::default());
+        // On HDR surfaces text is drawn into the SDR target, which is converted when presenting
+        let text_target = match &surface.hdr {
+            Some(_) => &surface.target_view,
+            None => {
+                state.surface.blitter.copy(
+                    &device_handle.device,
+                    &mut encoder,
+                    &surface.target_view,
+                    &surface_view,
+                );
+                &surface_view
+            }
+        };
 
         // Render glyphon text on top of vello shapes
         if let Some(glyphon) = &mut self.glyphon_state {
@@ -399,9 +529,7 @@ impl WindowRenderer for VelloWindowRenderer {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Glyphon Text Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_texture
-                        .texture
-                        .create_view(&TextureViewDescriptor::default()),
+                    view: text_target,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load, // CRITICAL: Load existing content, don't clear!
@@ -415,11 +543,7 @@ impl WindowRenderer for VelloWindowRenderer {
             });
 
             // Render text with glyphon
-            match glyphon.text_renderer.render(
-                &glyphon.text_atlas,
-                &glyphon.viewport,
-                &mut render_pass,
-            ) {
+            match glyphon.render(&mut render_pass) {
                 Ok(_) => {
                     log::trace!("Rendered text successfully");
                 }
@@ -431,10 +555,31 @@ impl WindowRenderer for VelloWindowRenderer {
             // render_pass is dropped here, ending the pass
         }
 
+        if let Some(hdr) = &surface.hdr {
+            hdr.prepare(
+                &device_handle.queue,
+                surface.config.width,
+                surface.config.height,
+                self.config.hdr.unwrap_or_default(),
+                self.config.color_space,
+            );
+            hdr.composite(
+                &device_handle.device,
+                &mut encoder,
+                &surface.target_view,
+                &self.hdr_textures,
+                &surface_view,
+            );
+        }
+
         device_handle.queue.submit(Some(encoder.finish()));
         surface_texture.present();
         timer.record_time("present");
 
+        if let Some(glyphon) = &mut self.glyphon_state {
+            glyphon.end_frame();
+        }
+
         if let Err(e) = device_handle.device.poll(wgpu::PollType::wait()) {
             log::warn!("Device poll error: {e}");
         }
@@ -1,28 +1,39 @@
 use anyrender::ImageRenderer;
 
-use crate::VelloCpuScenePainter;
-use crate::vello_cpu::{RenderContext, RenderMode};
+use crate::vello_cpu::Pixmap;
+use crate::{TextRenderingConfig, VelloCpuScenePainter};
 
 pub struct VelloCpuImageRenderer {
     scene: VelloCpuScenePainter,
 }
 
+impl VelloCpuImageRenderer {
+    pub fn set_text_rendering(&mut self, config: TextRenderingConfig) {
+        self.scene.set_text_rendering(config);
+    }
+
+    /// See [`VelloCpuScenePainter::set_num_threads`]
+    pub fn set_num_threads(&mut self, num_threads: usize) {
+        self.scene.set_num_threads(num_threads);
+    }
+}
+
 impl ImageRenderer for VelloCpuImageRenderer {
     type ScenePainter<'a> = VelloCpuScenePainter;
 
     fn new(width: u32, height: u32) -> Self {
         Self {
-            scene: VelloCpuScenePainter(RenderContext::new(width as u16, height as u16)),
+            scene: VelloCpuScenePainter::new(width as u16, height as u16),
         }
     }
 
     fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F, buffer: &mut Vec<u8>) {
-        let width = self.scene.0.width();
-        let height = self.scene.0.height();
+        let width = self.scene.context.width();
+        let height = self.scene.context.height();
         draw_fn(&mut self.scene);
-        buffer.resize(width as usize * height as usize * 4, 0);
-        self.scene
-            .0
-            .render_to_buffer(&mut *buffer, width, height, RenderMode::OptimizeSpeed);
+        let mut pixmap = Pixmap::new(width, height);
+        self.scene.render_to_pixmap(&mut pixmap);
+        buffer.clear();
+        buffer.extend(pixmap.data().iter().flat_map(|pixel| pixel.to_u8_array()));
     }
 }
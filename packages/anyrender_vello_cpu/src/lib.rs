@@ -1,10 +1,16 @@
 //! An Anyrender backend using the vello_cpu crate
 mod image_renderer;
 mod scene;
+mod simd;
+mod text;
+mod tiled;
+#[cfg(feature = "window")]
 mod window_renderer;
 
 pub use image_renderer::VelloCpuImageRenderer;
 pub use scene::VelloCpuScenePainter;
+pub use text::{TextAntialiasing, TextRenderingConfig};
+#[cfg(feature = "window")]
 pub use window_renderer::VelloCpuWindowRenderer;
 
 // Re-export vello_cpu based on feature flags (vendored uses GitHub forks)
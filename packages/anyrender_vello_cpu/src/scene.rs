@@ -1,10 +1,19 @@
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use anyrender::{Paint, PaintScene};
+use blitz_text::baseline_shift::glyph_baseline_offset;
+use blitz_text::kashida::justified_glyphs;
 use kurbo::{Affine, Shape};
+use peniko::kurbo::{Point, Rect};
 use peniko::{BlendMode, BrushRef, Color, Fill, Font, color::PremulRgba8};
 
-use crate::vello_cpu::{self, PaintType, Pixmap, RenderMode};
+use crate::text::{
+    DeferredText, DeviceGlyphRun, TextAntialiasing, TextRenderingConfig, device_scale,
+};
+use crate::tiled::{Command, TiledRenderer, dirty_tiles, resolve_num_threads};
+use crate::vello_cpu::vello_common::glyph::Glyph;
+use crate::vello_cpu::{self, PaintType, Pixmap, RenderContext, RenderMode};
 
 const DEFAULT_TOLERANCE: f64 = 0.1;
 
@@ -29,10 +38,6 @@ fn convert_peniko_stroke_to_kurbo(stroke: &peniko::kurbo::Stroke) -> kurbo::Stro
         .with_miter_limit(stroke.miter_limit)
 }
 
-fn convert_peniko_point_to_kurbo(point: peniko::kurbo::Point) -> kurbo::Point {
-    kurbo::Point::new(point.x, point.y)
-}
-
 fn convert_peniko_rect_to_kurbo(rect: peniko::kurbo::Rect) -> kurbo::Rect {
     kurbo::Rect::new(rect.x0, rect.y0, rect.x1, rect.y1)
 }
@@ -172,20 +177,157 @@ fn premultiply(image: &peniko::Image) -> Vec<PremulRgba8> {
         .collect()
 }
 
-pub struct VelloCpuScenePainter(pub crate::vello_cpu::RenderContext);
+/// Load a font from the system font database
+fn load_font(font_id: blitz_text::fontdb::ID) -> Font {
+    let font_system = blitz_text::EnhancedFontSystem::new();
+    let (font_data, face_index) = font_system.get_font_data_guaranteed(font_id);
+    let font_blob = peniko::Blob::new(std::sync::Arc::new(font_data));
+    Font::new(font_blob, face_index)
+}
+
+pub struct VelloCpuScenePainter {
+    pub context: RenderContext,
+    text_config: TextRenderingConfig,
+    /// Subpixel antialiased text, blended in once the frame is rendered
+    deferred_text: DeferredText,
+    /// The number of layers currently pushed
+    layer_depth: usize,
+    num_threads: usize,
+    partial_rendering: bool,
+    /// Records the drawing commands when rasterizing with multiple threads or partial rendering,
+    /// instead of issuing them to `context`
+    tiles: Option<TiledRenderer>,
+}
 
 impl VelloCpuScenePainter {
-    pub fn finish(self) -> Pixmap {
-        let mut pixmap = Pixmap::new(self.0.width(), self.0.height());
-        self.0
-            .render_to_pixmap(&mut pixmap, RenderMode::OptimizeSpeed);
+    pub fn new(width: u16, height: u16) -> Self {
+        Self::with_num_threads(width, height, 0)
+    }
+
+    /// A painter which rasterizes with `num_threads` threads (see
+    /// [`set_num_threads`](Self::set_num_threads))
+    pub fn with_num_threads(width: u16, height: u16, num_threads: usize) -> Self {
+        let mut painter = Self {
+            context: RenderContext::new(width, height),
+            text_config: TextRenderingConfig::default(),
+            deferred_text: DeferredText::default(),
+            layer_depth: 0,
+            num_threads,
+            partial_rendering: false,
+            tiles: None,
+        };
+        painter.update_tiles();
+        painter
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    /// Set the number of threads frames are rasterized with, which clears the scene. `0` (the
+    /// default) uses the available parallelism, up to 8 threads, and `1` rasterizes on the
+    /// calling thread, as does any value without the `multithreading` feature.
+    pub fn set_num_threads(&mut self, num_threads: usize) {
+        self.reset();
+        self.num_threads = num_threads;
+        self.update_tiles();
+    }
+
+    pub fn partial_rendering(&self) -> bool {
+        self.partial_rendering
+    }
+
+    /// Record the drawing commands even when rasterizing on a single thread, so that
+    /// [`render_dirty_to_pixmap`](Self::render_dirty_to_pixmap) can re-rasterize just the dirty
+    /// parts of a frame. This clears the scene.
+    pub fn set_partial_rendering(&mut self, enabled: bool) {
+        self.reset();
+        self.partial_rendering = enabled;
+        self.update_tiles();
+    }
+
+    fn update_tiles(&mut self) {
+        let threads = resolve_num_threads(self.num_threads);
+        self.tiles = (threads > 1 || self.partial_rendering).then(|| TiledRenderer::new(threads));
+    }
+
+    pub fn text_rendering(&self) -> TextRenderingConfig {
+        self.text_config
+    }
+
+    pub fn set_text_rendering(&mut self, config: TextRenderingConfig) {
+        self.text_config = config;
+    }
+
+    /// Render the scene into `pixmap`, which must be the size of the scene
+    pub fn render_to_pixmap(&mut self, pixmap: &mut Pixmap) {
+        let frame = Rect::new(0.0, 0.0, pixmap.width() as f64, pixmap.height() as f64);
+        self.render_regions(pixmap, &[frame]);
+    }
+
+    /// Render the parts of the scene which `dirty` (in device space) touches into `pixmap`, which
+    /// holds the previous frame, leaving the rest of it as it is. Returns the regions which were
+    /// rendered: `dirty` expanded to whole tiles. Without partial rendering (see
+    /// [`set_partial_rendering`](Self::set_partial_rendering)) or multiple threads, the whole frame
+    /// is rendered.
+    pub fn render_dirty_to_pixmap(&mut self, pixmap: &mut Pixmap, dirty: &[Rect]) -> Vec<Rect> {
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let regions = match self.tiles {
+            Some(_) => dirty_tiles(dirty, width, height),
+            None => vec![Rect::new(0.0, 0.0, width as f64, height as f64)],
+        };
+        self.render_regions(pixmap, &regions);
+        regions
+    }
+
+    pub fn finish(mut self) -> Pixmap {
+        let mut pixmap = Pixmap::new(self.context.width(), self.context.height());
+        self.render_to_pixmap(&mut pixmap);
         pixmap
     }
+
+    /// Rasterize `regions` of the frame into `pixmap`, then blend in the deferred text within them
+    fn render_regions(&mut self, pixmap: &mut Pixmap, regions: &[Rect]) {
+        match &self.tiles {
+            Some(tiles) => tiles.render(pixmap, regions),
+            None => self
+                .context
+                .render_to_pixmap(pixmap, RenderMode::OptimizeSpeed),
+        }
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let gamma = self.text_config.gamma;
+        self.deferred_text
+            .composite(pixmap.data_mut(), width, height, gamma, regions);
+    }
+
+    fn draw(&mut self, command: Command) {
+        if let Some(tiles) = &mut self.tiles {
+            tiles.commands.push(command);
+            return;
+        }
+        command.apply(&mut self.context, Point::ZERO);
+    }
+
+    /// Draw any deferred subpixel text that drawing over `bounds` (in device space) would cover
+    fn flush_text_under(&mut self, bounds: Rect) {
+        // Everything drawn inside a layer is clipped to it, and the text under its clip was
+        // flushed when it was pushed
+        if self.layer_depth == 0 && !self.deferred_text.is_empty() {
+            for run in self.deferred_text.take_overlapping(bounds) {
+                self.draw(run.into_command());
+            }
+        }
+    }
 }
 
 impl PaintScene for VelloCpuScenePainter {
     fn reset(&mut self) {
-        self.0.reset();
+        self.context.reset();
+        self.deferred_text.clear();
+        self.layer_depth = 0;
+        if let Some(tiles) = &mut self.tiles {
+            tiles.commands.clear();
+        }
     }
 
     fn push_layer(
@@ -195,19 +337,24 @@ impl PaintScene for VelloCpuScenePainter {
         transform: peniko::kurbo::Affine,
         clip: &impl peniko::kurbo::Shape,
     ) {
+        let bounds = transform.transform_rect_bbox(clip.bounding_box());
+        self.flush_text_under(bounds);
+        self.layer_depth += 1;
+
         let transform = convert_peniko_affine_to_kurbo(transform);
         let clip = convert_peniko_shape_to_kurbo(clip);
-        self.0.set_transform(convert_affine_to_peniko(transform));
-        self.0.push_layer(
-            Some(&convert_bezpath_to_peniko(&clip.into_path(DEFAULT_TOLERANCE))),
-            Some(blend.into()),
-            Some(alpha),
-            None,
-        );
+        self.draw(Command::PushLayer {
+            transform: convert_affine_to_peniko(transform),
+            clip: convert_bezpath_to_peniko(&clip.into_path(DEFAULT_TOLERANCE)),
+            blend: blend.into(),
+            alpha,
+            bounds,
+        });
     }
 
     fn pop_layer(&mut self) {
-        self.0.pop_layer();
+        self.layer_depth = self.layer_depth.saturating_sub(1);
+        self.draw(Command::PopLayer);
     }
 
     fn stroke<'a>(
@@ -218,16 +365,23 @@ impl PaintScene for VelloCpuScenePainter {
         brush_transform: Option<peniko::kurbo::Affine>,
         shape: &impl peniko::kurbo::Shape,
     ) {
+        // Miter joins reach up to half the miter limit times the width beyond the path
+        let pad = style.width * 0.5 * style.miter_limit.max(1.0);
+        let bounds = transform.transform_rect_bbox(shape.bounding_box().inflate(pad, pad));
+        self.flush_text_under(bounds);
+
         let style = convert_peniko_stroke_to_kurbo(style);
         let transform = convert_peniko_affine_to_kurbo(transform);
         let brush_transform = brush_transform.map(convert_peniko_affine_to_kurbo);
         let shape = convert_peniko_shape_to_kurbo(shape);
-        self.0.set_transform(convert_affine_to_peniko(transform));
-        self.0.set_stroke(convert_stroke_to_peniko(&style));
-        self.0.set_paint(brush_ref_to_paint_type(brush.into()));
-        self.0
-            .set_paint_transform(convert_affine_to_peniko(brush_transform.unwrap_or(Affine::IDENTITY)));
-        self.0.stroke_path(&convert_bezpath_to_peniko(&shape.into_path(DEFAULT_TOLERANCE)));
+        self.draw(Command::Stroke {
+            transform: convert_affine_to_peniko(transform),
+            stroke: convert_stroke_to_peniko(&style),
+            paint: brush_ref_to_paint_type(brush.into()),
+            paint_transform: convert_affine_to_peniko(brush_transform.unwrap_or(Affine::IDENTITY)),
+            path: convert_bezpath_to_peniko(&shape.into_path(DEFAULT_TOLERANCE)),
+            bounds,
+        });
     }
 
     fn fill<'a>(
@@ -238,16 +392,29 @@ impl PaintScene for VelloCpuScenePainter {
         brush_transform: Option<peniko::kurbo::Affine>,
         shape: &impl peniko::kurbo::Shape,
     ) {
+        let bounds = transform.transform_rect_bbox(shape.bounding_box());
+        self.flush_text_under(bounds);
+
+        let brush = brush.into();
+        // Solid rects on whole pixels can be filled without rasterizing them
+        if let (Paint::Solid(color), Some(rect)) = (&brush, shape.as_rect()) {
+            if let Some(command) = Command::solid_rect(transform, rect, *color) {
+                self.draw(command);
+                return;
+            }
+        }
+
         let transform = convert_peniko_affine_to_kurbo(transform);
         let brush_transform = brush_transform.map(convert_peniko_affine_to_kurbo);
         let shape = convert_peniko_shape_to_kurbo(shape);
-        self.0.set_transform(convert_affine_to_peniko(transform));
-        self.0.set_fill_rule(style);
-        self.0
-            .set_paint(anyrender_paint_to_vello_cpu_paint(brush.into()));
-        self.0
-            .set_paint_transform(convert_affine_to_peniko(brush_transform.unwrap_or(Affine::IDENTITY)));
-        self.0.fill_path(&convert_bezpath_to_peniko(&shape.into_path(DEFAULT_TOLERANCE)));
+        self.draw(Command::Fill {
+            transform: convert_affine_to_peniko(transform),
+            fill_rule: style,
+            paint: anyrender_paint_to_vello_cpu_paint(brush),
+            paint_transform: convert_affine_to_peniko(brush_transform.unwrap_or(Affine::IDENTITY)),
+            path: convert_bezpath_to_peniko(&shape.into_path(DEFAULT_TOLERANCE)),
+            bounds,
+        });
     }
 
     fn render_text_buffer(
@@ -257,62 +424,97 @@ impl PaintScene for VelloCpuScenePainter {
         color: peniko::Color,
         transform: peniko::kurbo::Affine,
     ) {
-        let position = convert_peniko_point_to_kurbo(position);
-        let transform = convert_peniko_affine_to_kurbo(transform);
-        // Set the base transform and paint color
-        self.0.set_transform(convert_affine_to_peniko(transform));
-        self.0
-            .set_paint(brush_ref_to_paint_type(BrushRef::Solid(color)));
-        self.0.set_fill_rule(Fill::NonZero);
+        let config = self.text_config;
+        let device_space = device_scale(transform).is_some();
+        // See the `text` module for when subpixel antialiasing can be used
+        let defer = config.antialiasing == TextAntialiasing::SubpixelRgb
+            && self.layer_depth == 0
+            && device_space;
 
         // Process each layout run from the blitz_text buffer
         for run in buffer.layout_runs() {
             if run.glyphs.is_empty() {
                 continue;
             }
+            let run_glyphs = blitz_text::measurement::with_font_system(|font_system| {
+                justified_glyphs(buffer, &run, font_system)
+            })
+            .unwrap_or(Cow::Borrowed(run.glyphs));
 
-            // Group glyphs by font to minimize glyph run creation overhead
+            // Group glyphs by font and size to minimize glyph run creation overhead
             let mut font_groups: std::collections::BTreeMap<
-                blitz_text::fontdb::ID,
+                (blitz_text::fontdb::ID, u32),
                 Vec<&blitz_text::LayoutGlyph>,
             > = std::collections::BTreeMap::new();
 
-            for glyph in run.glyphs {
-                font_groups.entry(glyph.font_id).or_default().push(glyph);
+            for glyph in run_glyphs.iter() {
+                font_groups
+                    .entry((glyph.font_id, glyph.font_size.to_bits()))
+                    .or_default()
+                    .push(glyph);
             }
 
             // Render each font group
-            for (font_id, glyphs) in font_groups {
+            for ((font_id, _), glyphs) in font_groups {
                 if glyphs.is_empty() {
                     continue;
                 }
 
                 // Get the first glyph to determine font properties
-                let first_glyph = glyphs[0];
-                let font_size = first_glyph.font_size;
-
-                // Get actual font data using blitz-text's EnhancedFontSystem
-                let font_system = blitz_text::EnhancedFontSystem::new();
-                let (font_data, face_index) = font_system.get_font_data_guaranteed(font_id);
-                let font_blob = peniko::Blob::new(std::sync::Arc::new(font_data));
-                let font = Font::new(font_blob, face_index);
+                let font_size = glyphs[0].font_size;
+                let font = load_font(font_id);
 
                 // Convert blitz_text glyphs to vello_cpu glyphs
-                let vello_glyphs: Vec<crate::vello_cpu::vello_common::glyph::Glyph> = glyphs
-                    .iter()
-                    .map(|layout_glyph| crate::vello_cpu::vello_common::glyph::Glyph {
-                        id: layout_glyph.glyph_id as u32,
-                        x: position.x as f32 + layout_glyph.x,
-                        y: position.y as f32 + run.line_y + layout_glyph.y,
-                    })
-                    .collect();
+                let vello_glyphs = glyphs.iter().map(|layout_glyph| Glyph {
+                    id: layout_glyph.glyph_id as u32,
+                    x: position.x as f32 + layout_glyph.x,
+                    y: position.y as f32
+                        + run.line_y
+                        + layout_glyph.y
+                        + glyph_baseline_offset(layout_glyph),
+                });
+
+                // Text which is only scaled and translated is positioned in device space
+                if device_space {
+                    let glyph_run = DeviceGlyphRun::new(
+                        font,
+                        font_size,
+                        color,
+                        transform,
+                        config.subpixel_positioning,
+                        vello_glyphs,
+                    );
+                    if defer {
+                        self.deferred_text.push(glyph_run);
+                    } else {
+                        self.flush_text_under(glyph_run.bounds);
+                        self.draw(glyph_run.into_command());
+                    }
+                    continue;
+                }
+
+                let vello_glyphs: Vec<Glyph> = vello_glyphs.collect();
+                // The same conservative ink bounds as `DeviceGlyphRun`, in text space
+                let em = font_size as f64;
+                let bounds = vello_glyphs.iter().fold(None, |bounds: Option<Rect>, glyph| {
+                    let (x, y) = (glyph.x as f64, glyph.y as f64);
+                    let ink = Rect::new(x - em, y - em * 2.0, x + em * 2.0, y + em);
+                    Some(bounds.map_or(ink, |bounds| bounds.union(ink)))
+                });
+                let bounds = transform.transform_rect_bbox(bounds.unwrap_or_default());
+                self.flush_text_under(bounds);
 
                 // Render the glyph run with proper font size and positioning
-                self.0
-                    .glyph_run(&font)
-                    .font_size(font_size)
-                    .hint(true)
-                    .fill_glyphs(vello_glyphs.into_iter());
+                let transform = convert_peniko_affine_to_kurbo(transform);
+                self.draw(Command::Glyphs {
+                    transform: convert_affine_to_peniko(transform),
+                    color,
+                    font,
+                    font_size,
+                    hint: true,
+                    glyphs: vello_glyphs,
+                    bounds,
+                });
             }
         }
     }
@@ -324,11 +526,68 @@ impl PaintScene for VelloCpuScenePainter {
         radius: f64,
         std_dev: f64,
     ) {
+        // Blurs reach about three standard deviations beyond the shape
+        let blur = std_dev * 3.0;
+        let bounds = transform.transform_rect_bbox(rect.inflate(blur, blur));
+        self.flush_text_under(bounds);
+
         let transform = convert_peniko_affine_to_kurbo(transform);
         let rect = convert_peniko_rect_to_kurbo(rect);
-        self.0.set_transform(convert_affine_to_peniko(transform));
-        self.0.set_paint(PaintType::Solid(color));
-        self.0
-            .fill_blurred_rounded_rect(&convert_rect_to_peniko(rect), radius as f32, std_dev as f32);
+        self.draw(Command::BlurredRect {
+            transform: convert_affine_to_peniko(transform),
+            color,
+            rect: convert_rect_to_peniko(rect),
+            radius: radius as f32,
+            std_dev: std_dev as f32,
+            bounds,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::{Affine, Circle, Line, Shape, Stroke};
+
+    fn fill(scene: &mut VelloCpuScenePainter, color: Color, shape: &impl Shape) {
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, shape);
+    }
+
+    fn draw(scene: &mut VelloCpuScenePainter) {
+        let rgba = Color::from_rgba8;
+        // Solid rects, which are filled without rasterizing them when the frame is banded
+        fill(scene, Color::WHITE, &Rect::new(0.0, 0.0, 64.0, 96.0));
+        fill(scene, rgba(200, 0, 0, 255), &Rect::new(4.0, 4.0, 60.0, 40.0));
+        fill(scene, rgba(0, 0, 200, 128), &Rect::new(10.0, 20.0, 50.0, 70.0));
+        // Antialiased shapes crossing the edges of the bands
+        fill(scene, rgba(0, 150, 0, 200), &Circle::new((32.0, 48.0), 20.5));
+        let line = Line::new((2.0, 90.0), (62.0, 5.0));
+        scene.stroke(&Stroke::new(3.0), Affine::IDENTITY, Color::BLACK, None, &line);
+        let clip = Rect::new(0.0, 30.0, 64.0, 60.0);
+        scene.push_layer(BlendMode::default(), 0.5, Affine::IDENTITY, &clip);
+        fill(scene, rgba(255, 200, 0, 255), &Circle::new((20.0, 45.0), 15.0));
+        scene.pop_layer();
+    }
+
+    fn render(num_threads: usize, partial_rendering: bool) -> Vec<PremulRgba8> {
+        let mut scene = VelloCpuScenePainter::with_num_threads(64, 96, num_threads);
+        scene.set_partial_rendering(partial_rendering);
+        draw(&mut scene);
+        scene.finish().data().to_vec()
+    }
+
+    #[test]
+    fn banded_rendering_matches_rendering_in_one_go() {
+        let reference = render(1, false);
+        for (num_threads, partial_rendering) in [(1, true), (4, false), (4, true)] {
+            let banded = render(num_threads, partial_rendering);
+            assert_eq!(banded.len(), reference.len());
+            for (idx, (banded, reference)) in banded.iter().zip(&reference).enumerate() {
+                // Fast paths may round blended channels differently than vello_cpu
+                let channels = banded.to_u8_array().into_iter().zip(reference.to_u8_array());
+                let diff = channels.map(|(a, b)| a.abs_diff(b)).max().unwrap();
+                assert!(diff <= 1, "{num_threads} threads, pixel {idx}: {banded:?} {reference:?}");
+            }
+        }
     }
 }
@@ -0,0 +1,382 @@
+//! Text rendering options, and subpixel (LCD) antialiasing
+//!
+//! vello_cpu antialiases glyph outlines with a single coverage value per pixel. With
+//! [`TextAntialiasing::SubpixelRgb`], glyphs are instead rasterized at three times the horizontal
+//! resolution, filtered to limit color fringes, and blended into the finished frame channel by
+//! channel (in linear light, using [`TextRenderingConfig::gamma`]), treating the red, green and
+//! blue stripes of each pixel as separate samples.
+//!
+//! The blending happens after vello_cpu has rendered the frame, so subpixel text is deferred until
+//! then. That is only correct for text which nothing drawn later overlaps: text which is overlapped
+//! is drawn with grayscale antialiasing just before whatever overlaps it, so the painting order is
+//! kept. Text drawn inside a layer (a clip, opacity or blend mode), text drawn with a transform
+//! that rotates or skews it, and text over transparent pixels (which have no color to blend
+//! against per channel) are always grayscale.
+
+use peniko::color::PremulRgba8;
+use peniko::kurbo::{Affine, Rect};
+use peniko::{Color, Fill, Font};
+
+use crate::tiled::Command;
+use crate::vello_cpu::vello_common::glyph::Glyph;
+use crate::vello_cpu::{PaintType, Pixmap, RenderContext, RenderMode};
+
+/// How glyph edges are antialiased
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAntialiasing {
+    /// A single coverage value per pixel
+    #[default]
+    Grayscale,
+    /// A coverage value for each of the red, green and blue stripes of a pixel, for displays whose
+    /// subpixels are laid out horizontally in that order
+    SubpixelRgb,
+}
+
+/// How a CPU renderer draws text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextRenderingConfig {
+    /// Place glyphs at fractional pixel positions, as laid out. Otherwise the origin of each glyph
+    /// is snapped to a whole pixel, which gives sharper stems but uneven spacing.
+    pub subpixel_positioning: bool,
+    pub antialiasing: TextAntialiasing,
+    /// The gamma subpixel antialiased text is blended with. `1.0` blends the encoded values.
+    pub gamma: f32,
+}
+
+impl Default for TextRenderingConfig {
+    fn default() -> Self {
+        Self {
+            subpixel_positioning: true,
+            antialiasing: TextAntialiasing::Grayscale,
+            gamma: 2.2,
+        }
+    }
+}
+
+/// The scale of `transform`, if it only scales uniformly and translates. Text drawn with such a
+/// transform is positioned in device space.
+pub(crate) fn device_scale(transform: Affine) -> Option<f64> {
+    let [a, b, c, d, _, _] = transform.as_coeffs();
+    (a == d && a > 0.0 && b == 0.0 && c == 0.0).then_some(a)
+}
+
+/// A run of glyphs with a single font, size and color, positioned in device space
+pub(crate) struct DeviceGlyphRun {
+    font: Font,
+    font_size: f32,
+    color: Color,
+    glyphs: Vec<Glyph>,
+    /// A conservative bound of the glyphs' ink
+    pub(crate) bounds: Rect,
+}
+
+impl DeviceGlyphRun {
+    /// Move `glyphs` into device space with `transform`, which must satisfy [`device_scale`].
+    /// Baselines are snapped to whole pixels, as hinting expects.
+    pub(crate) fn new(
+        font: Font,
+        font_size: f32,
+        color: Color,
+        transform: Affine,
+        subpixel_positioning: bool,
+        glyphs: impl Iterator<Item = Glyph>,
+    ) -> Self {
+        let [scale, _, _, _, dx, dy] = transform.as_coeffs();
+        let glyphs: Vec<Glyph> = glyphs
+            .map(|glyph| {
+                let x = (scale * glyph.x as f64 + dx) as f32;
+                let y = (scale * glyph.y as f64 + dy) as f32;
+                Glyph {
+                    id: glyph.id,
+                    x: if subpixel_positioning { x } else { x.round() },
+                    y: y.round(),
+                }
+            })
+            .collect();
+        let font_size = font_size * scale as f32;
+
+        // Glyphs rarely reach further than an em before their origin or two after it, two ems
+        // above their baseline or one below it
+        let (mut x0, mut y0) = (f32::INFINITY, f32::INFINITY);
+        let (mut x1, mut y1) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for glyph in &glyphs {
+            x0 = x0.min(glyph.x - font_size);
+            x1 = x1.max(glyph.x + font_size * 2.0);
+            y0 = y0.min(glyph.y - font_size * 2.0);
+            y1 = y1.max(glyph.y + font_size);
+        }
+        let bounds = match glyphs.is_empty() {
+            true => Rect::ZERO,
+            false => Rect::new(x0 as f64, y0 as f64, x1 as f64, y1 as f64),
+        };
+
+        Self {
+            font,
+            font_size,
+            color,
+            glyphs,
+            bounds,
+        }
+    }
+
+    /// The command drawing the run with grayscale antialiasing
+    pub(crate) fn into_command(self) -> Command {
+        Command::Glyphs {
+            transform: Affine::IDENTITY,
+            color: self.color,
+            font: self.font,
+            font_size: self.font_size,
+            hint: true,
+            glyphs: self.glyphs,
+            bounds: self.bounds,
+        }
+    }
+
+    /// Rasterize the part of the run within `bounds` (whole pixels) at three times the horizontal
+    /// resolution. The alpha of each pixel of the result is the coverage of one subpixel.
+    fn subpixel_coverage(&self, bounds: Rect) -> Pixmap {
+        let width = bounds.width() as u16 * 3;
+        let height = bounds.height() as u16;
+        let mut ctx = RenderContext::new(width, height);
+        ctx.set_transform(
+            Affine::translate((-bounds.x0, -bounds.y0)).then_scale_non_uniform(3.0, 1.0),
+        );
+        ctx.set_paint(PaintType::Solid(Color::WHITE));
+        ctx.set_fill_rule(Fill::NonZero);
+        // Hinting needs a uniform scale. Baselines are already on whole pixels.
+        ctx.glyph_run(&self.font)
+            .font_size(self.font_size)
+            .hint(false)
+            .fill_glyphs(self.glyphs.iter().copied());
+
+        let mut pixmap = Pixmap::new(width, height);
+        ctx.render_to_pixmap(&mut pixmap, RenderMode::OptimizeSpeed);
+        pixmap
+    }
+}
+
+/// Subpixel antialiased text waiting to be blended into the finished frame, in painting order
+#[derive(Default)]
+pub(crate) struct DeferredText {
+    runs: Vec<DeviceGlyphRun>,
+}
+
+impl DeferredText {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, run: DeviceGlyphRun) {
+        if !run.glyphs.is_empty() {
+            self.runs.push(run);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.runs.clear();
+    }
+
+    /// Remove the runs overlapping `bounds`, in painting order, so that they can be drawn with
+    /// grayscale antialiasing before something is drawn over them. Earlier runs which those
+    /// overlap must be drawn below them, so are removed too.
+    pub(crate) fn take_overlapping(&mut self, bounds: Rect) -> Vec<DeviceGlyphRun> {
+        let mut covered = vec![bounds];
+        let mut take = vec![false; self.runs.len()];
+        for (idx, run) in self.runs.iter().enumerate().rev() {
+            if covered.iter().any(|bounds| bounds.overlaps(run.bounds)) {
+                covered.push(run.bounds);
+                take[idx] = true;
+            }
+        }
+
+        let (taken, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.runs)
+            .into_iter()
+            .zip(take)
+            .partition(|(_, take)| *take);
+        self.runs = kept.into_iter().map(|(run, _)| run).collect();
+        taken.into_iter().map(|(run, _)| run).collect()
+    }
+
+    /// Blend every run into `regions` of `pixels`, the finished frame, in rows of `width` pixels.
+    /// The regions must have whole-pixel edges and not overlap.
+    pub(crate) fn composite(
+        &mut self,
+        pixels: &mut [PremulRgba8],
+        width: u16,
+        height: u16,
+        gamma: f32,
+        regions: &[Rect],
+    ) {
+        if self.runs.is_empty() {
+            return;
+        }
+        let gamma = GammaTables::new(gamma);
+        // Keep the coverage pixmap within the u16 size limit
+        let frame = Rect::new(0.0, 0.0, width.min(u16::MAX / 3) as f64, height as f64);
+
+        for run in self.runs.drain(..) {
+            let bounds = run.bounds.expand().intersect(frame);
+            if bounds.width() < 1.0 || bounds.height() < 1.0 {
+                continue;
+            }
+            if !regions.iter().any(|region| region.overlaps(bounds)) {
+                continue;
+            }
+            // The coverage of the whole run, so that the LCD filter sees the subpixels just
+            // outside each region
+            let coverage = run.subpixel_coverage(bounds);
+            let coverage = coverage.data();
+            let color = run.color.to_rgba8();
+            let color = [color.r, color.g, color.b, color.a];
+            let coverage_width = bounds.width() as usize * 3;
+
+            for region in regions {
+                let area = region.intersect(bounds);
+                if area.width() < 1.0 || area.height() < 1.0 {
+                    continue;
+                }
+                let (x0, x1) = (area.x0 as usize, area.x1 as usize);
+                for y in area.y0 as usize..area.y1 as usize {
+                    let row = y - bounds.y0 as usize;
+                    let coverage_row = &coverage[row * coverage_width..(row + 1) * coverage_width];
+                    let start = y * width as usize;
+                    for x in x0..x1 {
+                        let pixel = &mut pixels[start + x];
+                        let coverage = filter_subpixels(coverage_row, (x - bounds.x0 as usize) * 3);
+                        *pixel = blend_subpixels(*pixel, color, coverage, &gamma);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// FreeType's default LCD filter. It spreads each subpixel's coverage over its neighbours,
+/// trading a little sharpness for much less color fringing. The weights sum to 256.
+const LCD_FILTER: [u32; 5] = [8, 77, 86, 77, 8];
+
+/// The filtered coverage (`0.0..=1.0`) of the red, green and blue subpixels starting at `start`
+fn filter_subpixels(row: &[PremulRgba8], start: usize) -> [f32; 3] {
+    std::array::from_fn(|channel| {
+        let center = start + channel;
+        let sum: u32 = LCD_FILTER
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, weight)| {
+                let subpixel = (center + idx).checked_sub(2)?;
+                Some(weight * row.get(subpixel)?.a as u32)
+            })
+            .sum();
+        sum as f32 / (256.0 * 255.0)
+    })
+}
+
+/// Blend `color` (unpremultiplied RGBA8) over `pixel` with a separate coverage for each channel
+fn blend_subpixels(
+    pixel: PremulRgba8,
+    color: [u8; 4],
+    coverage: [f32; 3],
+    gamma: &GammaTables,
+) -> PremulRgba8 {
+    let alpha = color[3] as f32 / 255.0;
+    let dest = [pixel.r, pixel.g, pixel.b];
+
+    // Without an opaque backdrop there is nothing to blend each channel against
+    if pixel.a != 255 {
+        let coverage = alpha * (coverage[0] + coverage[1] + coverage[2]) / 3.0;
+        let over = |src: u8, dest: u8| {
+            (src as f32 * coverage + dest as f32 * (1.0 - coverage)).round() as u8
+        };
+        return PremulRgba8 {
+            r: over(color[0], dest[0]),
+            g: over(color[1], dest[1]),
+            b: over(color[2], dest[2]),
+            a: over(255, pixel.a),
+        };
+    }
+
+    let [r, g, b] = std::array::from_fn(|channel| {
+        let coverage = alpha * coverage[channel];
+        let src = gamma.to_linear[color[channel] as usize];
+        let dest = gamma.to_linear[dest[channel] as usize];
+        gamma.encode(src * coverage + dest * (1.0 - coverage))
+    });
+    PremulRgba8 { r, g, b, a: 255 }
+}
+
+/// Lookup tables for converting between encoded and linear values
+struct GammaTables {
+    to_linear: [f32; 256],
+    from_linear: Vec<u8>,
+}
+
+impl GammaTables {
+    /// Precision of linear values when encoding them
+    const LINEAR_STEPS: usize = 4096;
+
+    fn new(gamma: f32) -> Self {
+        let gamma = gamma.max(f32::EPSILON);
+        let steps = Self::LINEAR_STEPS - 1;
+        Self {
+            to_linear: std::array::from_fn(|value| (value as f32 / 255.0).powf(gamma)),
+            from_linear: (0..=steps)
+                .map(|step| ((step as f32 / steps as f32).powf(1.0 / gamma) * 255.0).round() as u8)
+                .collect(),
+        }
+    }
+
+    fn encode(&self, linear: f32) -> u8 {
+        let step = (linear.clamp(0.0, 1.0) * (Self::LINEAR_STEPS - 1) as f32).round();
+        self.from_linear[step as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(bounds: Rect) -> DeviceGlyphRun {
+        DeviceGlyphRun {
+            font: Font::new(peniko::Blob::new(std::sync::Arc::new(Vec::new())), 0),
+            font_size: 16.0,
+            color: Color::BLACK,
+            glyphs: vec![Glyph::default()],
+            bounds,
+        }
+    }
+
+    #[test]
+    fn overlapped_runs_are_taken_with_the_runs_below_them() {
+        let mut deferred = DeferredText::default();
+        deferred.push(run(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        deferred.push(run(Rect::new(100.0, 0.0, 110.0, 10.0)));
+        deferred.push(run(Rect::new(5.0, 5.0, 15.0, 15.0)));
+
+        // Only the last run is drawn over, but it must stay above the first one
+        let taken = deferred.take_overlapping(Rect::new(12.0, 12.0, 20.0, 20.0));
+        let taken: Vec<f64> = taken.iter().map(|run| run.bounds.x0).collect();
+        assert_eq!(taken, [0.0, 5.0]);
+        assert_eq!(deferred.runs.len(), 1);
+        assert_eq!(deferred.runs[0].bounds.x0, 100.0);
+    }
+
+    #[test]
+    fn subpixel_blending() {
+        let gamma = GammaTables::new(2.2);
+        let white = PremulRgba8 {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        };
+        let black = [0, 0, 0, 255];
+
+        assert_eq!(blend_subpixels(white, black, [0.0; 3], &gamma), white);
+        let covered = blend_subpixels(white, black, [1.0, 0.0, 0.0], &gamma);
+        assert_eq!((covered.r, covered.g, covered.b), (0, 255, 255));
+
+        // Half coverage is half the light, which is brighter than half the encoded value
+        let half = blend_subpixels(white, black, [0.5; 3], &gamma);
+        assert!(half.r > 128);
+    }
+}
@@ -0,0 +1,483 @@
+//! Banded and partial rasterization
+//!
+//! With more than one thread, or with partial rendering enabled,
+//! [`VelloCpuScenePainter`](crate::VelloCpuScenePainter) records its drawing commands instead of
+//! issuing them to a `RenderContext` straight away. When the frame is rendered, the regions being
+//! redrawn (the whole frame, or just its dirty tiles) are split into horizontal bands which are
+//! rasterized in parallel on a rayon thread pool, each by its own `RenderContext` that only gets
+//! the commands overlapping the band.
+//!
+//! Bands which start with solid color fills of whole-pixel rects (page and element backgrounds)
+//! fill those directly, and only hand the rest of their commands to vello_cpu when there are any,
+//! compositing its output over the fills.
+
+#[cfg(feature = "multithreading")]
+use std::sync::{Arc, Mutex};
+
+use peniko::color::PremulRgba8;
+use peniko::kurbo::{Affine, BezPath, Point, Rect, Stroke};
+use peniko::{BlendMode, Color, Fill, Font};
+#[cfg(feature = "multithreading")]
+use rayon::ThreadPool;
+#[cfg(feature = "multithreading")]
+use rayon::prelude::*;
+
+use crate::simd;
+use crate::vello_cpu::vello_common::glyph::Glyph;
+use crate::vello_cpu::{PaintType, Pixmap, RenderContext, RenderMode};
+
+/// A drawing command, as issued to a `RenderContext`. Everything but [`Command::PopLayer`]
+/// carries a conservative bound of what it draws, in device space.
+pub(crate) enum Command {
+    PushLayer {
+        transform: Affine,
+        clip: BezPath,
+        blend: BlendMode,
+        alpha: f32,
+        bounds: Rect,
+    },
+    PopLayer,
+    Fill {
+        transform: Affine,
+        fill_rule: Fill,
+        paint: PaintType,
+        paint_transform: Affine,
+        path: BezPath,
+        bounds: Rect,
+    },
+    Stroke {
+        transform: Affine,
+        stroke: Stroke,
+        paint: PaintType,
+        paint_transform: Affine,
+        path: BezPath,
+        bounds: Rect,
+    },
+    /// A solid color fill of a rect with whole-pixel edges, in device space
+    SolidRect { rect: Rect, color: Color },
+    Glyphs {
+        transform: Affine,
+        color: Color,
+        font: Font,
+        font_size: f32,
+        hint: bool,
+        glyphs: Vec<Glyph>,
+        bounds: Rect,
+    },
+    BlurredRect {
+        transform: Affine,
+        color: Color,
+        rect: Rect,
+        radius: f32,
+        std_dev: f32,
+        bounds: Rect,
+    },
+}
+
+impl Command {
+    /// A solid fill of `rect` transformed by `transform`, if that is a [`Command::SolidRect`]
+    pub(crate) fn solid_rect(transform: Affine, rect: Rect, color: Color) -> Option<Self> {
+        let [a, b, c, d, _, _] = transform.as_coeffs();
+        if b != 0.0 || c != 0.0 || a <= 0.0 || d <= 0.0 {
+            return None;
+        }
+        let rect = transform.transform_rect_bbox(rect);
+        let aligned = |value: f64| (value - value.round()).abs() < 1e-3;
+        [rect.x0, rect.y0, rect.x1, rect.y1]
+            .into_iter()
+            .all(aligned)
+            .then(|| Self::SolidRect {
+                rect: rect.round(),
+                color,
+            })
+    }
+
+    /// Issue the command to `ctx`, whose top left corner is at `origin` in the frame
+    pub(crate) fn apply(&self, ctx: &mut RenderContext, origin: Point) {
+        let offset = Affine::translate(-origin.to_vec2());
+        match self {
+            Self::PushLayer {
+                transform,
+                clip,
+                blend,
+                alpha,
+                ..
+            } => {
+                ctx.set_transform(offset * *transform);
+                ctx.push_layer(Some(clip), Some(*blend), Some(*alpha), None);
+            }
+            Self::PopLayer => ctx.pop_layer(),
+            Self::Fill {
+                transform,
+                fill_rule,
+                paint,
+                paint_transform,
+                path,
+                ..
+            } => {
+                ctx.set_transform(offset * *transform);
+                ctx.set_fill_rule(*fill_rule);
+                ctx.set_paint(paint.clone());
+                ctx.set_paint_transform(*paint_transform);
+                ctx.fill_path(path);
+            }
+            Self::Stroke {
+                transform,
+                stroke,
+                paint,
+                paint_transform,
+                path,
+                ..
+            } => {
+                ctx.set_transform(offset * *transform);
+                ctx.set_stroke(stroke.clone());
+                ctx.set_paint(paint.clone());
+                ctx.set_paint_transform(*paint_transform);
+                ctx.stroke_path(path);
+            }
+            Self::SolidRect { rect, color } => {
+                ctx.set_transform(offset);
+                ctx.set_paint(PaintType::Solid(*color));
+                ctx.fill_rect(rect);
+            }
+            Self::Glyphs {
+                transform,
+                color,
+                font,
+                font_size,
+                hint,
+                glyphs,
+                ..
+            } => {
+                ctx.set_transform(offset * *transform);
+                ctx.set_paint(PaintType::Solid(*color));
+                ctx.set_fill_rule(Fill::NonZero);
+                ctx.glyph_run(font)
+                    .font_size(*font_size)
+                    .hint(*hint)
+                    .fill_glyphs(glyphs.iter().copied());
+            }
+            Self::BlurredRect {
+                transform,
+                color,
+                rect,
+                radius,
+                std_dev,
+                ..
+            } => {
+                ctx.set_transform(offset * *transform);
+                ctx.set_paint(PaintType::Solid(*color));
+                ctx.fill_blurred_rounded_rect(rect, *radius, *std_dev);
+            }
+        }
+    }
+
+    fn bounds(&self) -> Option<Rect> {
+        match self {
+            Self::PopLayer => None,
+            Self::SolidRect { rect, .. } => Some(*rect),
+            Self::PushLayer { bounds, .. }
+            | Self::Fill { bounds, .. }
+            | Self::Stroke { bounds, .. }
+            | Self::Glyphs { bounds, .. }
+            | Self::BlurredRect { bounds, .. } => Some(*bounds),
+        }
+    }
+}
+
+/// Bands are a multiple of this many rows, and never shorter
+const BAND_ROWS: usize = 16;
+/// More bands than threads even out the work between threads when the frame's content isn't
+/// spread evenly
+const BANDS_PER_THREAD: usize = 4;
+/// Dirty rects are expanded to whole tiles of this many pixels square, so that many small changes
+/// close together are rasterized as a few regions
+const TILE_SIZE: usize = 64;
+
+/// The commands of a frame which is being recorded for banded rasterization
+pub(crate) struct TiledRenderer {
+    pub(crate) commands: Vec<Command>,
+    #[cfg(feature = "multithreading")]
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl TiledRenderer {
+    /// A renderer which splits frames between `threads` threads
+    #[cfg_attr(not(feature = "multithreading"), allow(unused_variables))]
+    pub(crate) fn new(threads: usize) -> Self {
+        Self {
+            commands: Vec::new(),
+            #[cfg(feature = "multithreading")]
+            pool: (threads > 1).then(|| thread_pool(threads)),
+        }
+    }
+
+    fn threads(&self) -> usize {
+        #[cfg(feature = "multithreading")]
+        if let Some(pool) = &self.pool {
+            return pool.current_num_threads();
+        }
+        1
+    }
+
+    /// Rasterize the recorded commands into `regions` of `pixmap`, leaving the rest of it as it
+    /// is. The regions must have whole-pixel edges, be within the frame and not overlap.
+    pub(crate) fn render(&self, pixmap: &mut Pixmap, regions: &[Rect]) {
+        let height = pixmap.height() as usize;
+        if pixmap.width() == 0 || height == 0 {
+            return;
+        }
+        let band_rows = match self.threads() {
+            1 => height,
+            threads => height
+                .div_ceil(threads * BANDS_PER_THREAD)
+                .next_multiple_of(BAND_ROWS),
+        };
+        let bands: Vec<Rect> = regions
+            .iter()
+            .flat_map(|region| split_rows(*region, band_rows))
+            .collect();
+
+        let render = |band: &Rect| render_band(&self.commands, *band);
+        #[cfg(feature = "multithreading")]
+        let rendered: Vec<Pixmap> = match &self.pool {
+            Some(pool) => pool.install(|| bands.par_iter().map(render).collect()),
+            None => bands.iter().map(render).collect(),
+        };
+        #[cfg(not(feature = "multithreading"))]
+        let rendered: Vec<Pixmap> = bands.iter().map(render).collect();
+
+        for (band, rendered) in bands.iter().zip(rendered) {
+            copy_region(pixmap, *band, &rendered);
+        }
+    }
+}
+
+/// Split `region` into bands of at most `band_rows` rows
+fn split_rows(region: Rect, band_rows: usize) -> impl Iterator<Item = Rect> {
+    let (y0, y1) = (region.y0 as usize, region.y1 as usize);
+    (y0..y1).step_by(band_rows.max(1)).map(move |top| {
+        let bottom = (top + band_rows).min(y1);
+        Rect::new(region.x0, top as f64, region.x1, bottom as f64)
+    })
+}
+
+/// Rasterize the commands which are visible in `band` (in device space, with whole-pixel edges)
+/// into a pixmap of its size
+fn render_band(commands: &[Command], band: Rect) -> Pixmap {
+    let (width, height) = (band.width() as u16, band.height() as u16);
+    let visible = visible_commands(commands, band);
+
+    // Whatever is composited over the leading solid rects mustn't depend on what it is drawn
+    // over, as vello_cpu draws it over transparent pixels
+    let isolated = visible.iter().all(|command| match command {
+        Command::PushLayer { blend, .. } => *blend == BlendMode::default(),
+        _ => true,
+    });
+    let solid_count = match isolated {
+        true => visible
+            .iter()
+            .take_while(|command| matches!(command, Command::SolidRect { .. }))
+            .count(),
+        false => 0,
+    };
+    let (solid, rest) = visible.split_at(solid_count);
+
+    let mut pixmap = Pixmap::new(width, height);
+    for command in solid {
+        if let Command::SolidRect { rect, color } = command {
+            let color = color.premultiply().to_rgba8();
+            fill_rect(&mut pixmap, band.origin(), *rect, color);
+        }
+    }
+    if rest.is_empty() {
+        return pixmap;
+    }
+
+    let mut ctx = RenderContext::new(width, height);
+    for command in rest {
+        command.apply(&mut ctx, band.origin());
+    }
+    if solid.is_empty() {
+        ctx.render_to_pixmap(&mut pixmap, RenderMode::OptimizeSpeed);
+        return pixmap;
+    }
+    let mut layer = Pixmap::new(width, height);
+    ctx.render_to_pixmap(&mut layer, RenderMode::OptimizeSpeed);
+    simd::composite_over(pixmap.data_mut(), layer.data());
+    pixmap
+}
+
+/// Copy `rendered` into the `region` of `pixmap` it was rendered for
+fn copy_region(pixmap: &mut Pixmap, region: Rect, rendered: &Pixmap) {
+    let width = pixmap.width() as usize;
+    let (x0, y0) = (region.x0 as usize, region.y0 as usize);
+    let region_width = rendered.width() as usize;
+    let rows = pixmap.data_mut().chunks_exact_mut(width).skip(y0);
+    for (row, src) in rows.zip(rendered.data().chunks_exact(region_width)) {
+        row[x0..x0 + region_width].copy_from_slice(src);
+    }
+}
+
+/// The commands which draw anything within `area`. Layers whose clip is outside it are skipped
+/// along with everything drawn in them.
+fn visible_commands(commands: &[Command], area: Rect) -> Vec<&Command> {
+    // Antialiasing can touch the pixels just outside a shape's bounds
+    let area = area.inflate(1.0, 1.0);
+    let mut visible = Vec::new();
+    let mut skipped_layers = 0usize;
+    for command in commands {
+        if skipped_layers > 0 {
+            match command {
+                Command::PushLayer { .. } => skipped_layers += 1,
+                Command::PopLayer => skipped_layers -= 1,
+                _ => {}
+            }
+            continue;
+        }
+        match command.bounds() {
+            Some(bounds) if !bounds.overlaps(area) => {
+                if matches!(command, Command::PushLayer { .. }) {
+                    skipped_layers = 1;
+                }
+            }
+            _ => visible.push(command),
+        }
+    }
+    visible
+}
+
+/// Fill the part of `rect` (in device space) within `pixmap`, whose top left corner is at
+/// `origin` in the frame
+fn fill_rect(pixmap: &mut Pixmap, origin: Point, rect: Rect, color: PremulRgba8) {
+    let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+    let rect = rect - origin.to_vec2();
+    let x0 = (rect.x0.max(0.0) as usize).min(width);
+    let x1 = (rect.x1.max(0.0) as usize).min(width);
+    let row0 = (rect.y0.max(0.0) as usize).min(height);
+    let row1 = (rect.y1.max(0.0) as usize).min(height);
+    if x0 >= x1 {
+        return;
+    }
+    for row in pixmap.data_mut().chunks_exact_mut(width).take(row1).skip(row0) {
+        simd::fill_solid(&mut row[x0..x1], color);
+    }
+}
+
+/// Expand `dirty` (in device space) to the tiles of a `width` by `height` frame which it touches,
+/// merged into as few regions as possible. The regions don't overlap.
+pub(crate) fn dirty_tiles(dirty: &[Rect], width: u16, height: u16) -> Vec<Rect> {
+    let (width, height) = (width as usize, height as usize);
+    let columns = width.div_ceil(TILE_SIZE);
+    let rows = height.div_ceil(TILE_SIZE);
+    let mut tiles = vec![false; columns * rows];
+    let frame = Rect::new(0.0, 0.0, width as f64, height as f64);
+    for rect in dirty {
+        let rect = rect.intersect(frame);
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            continue;
+        }
+        let column0 = rect.x0 as usize / TILE_SIZE;
+        let column1 = (rect.x1.ceil() as usize).div_ceil(TILE_SIZE);
+        for row in rect.y0 as usize / TILE_SIZE..(rect.y1.ceil() as usize).div_ceil(TILE_SIZE) {
+            tiles[row * columns + column0..row * columns + column1].fill(true);
+        }
+    }
+
+    // Runs of dirty tiles in a row, merged with the same run in the row above
+    let mut regions = Vec::new();
+    let mut open: Vec<(usize, usize, usize)> = Vec::new();
+    for row in 0..=rows {
+        let mut runs = Vec::new();
+        if row < rows {
+            let tiles = &tiles[row * columns..(row + 1) * columns];
+            let mut column = 0;
+            while column < columns {
+                if !tiles[column] {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < columns && tiles[column] {
+                    column += 1;
+                }
+                runs.push((start, column));
+            }
+        }
+
+        let mut next_open = Vec::new();
+        for (start, end) in runs {
+            match open.iter().position(|&(s, e, _)| (s, e) == (start, end)) {
+                Some(idx) => next_open.push(open.swap_remove(idx)),
+                None => next_open.push((start, end, row)),
+            }
+        }
+        for (start, end, top) in open {
+            regions.push(Rect::new(
+                (start * TILE_SIZE) as f64,
+                (top * TILE_SIZE) as f64,
+                (end * TILE_SIZE).min(width) as f64,
+                (row * TILE_SIZE).min(height) as f64,
+            ));
+        }
+        open = next_open;
+    }
+    regions
+}
+
+/// The number of threads used for `num_threads` (see
+/// [`VelloCpuScenePainter::set_num_threads`](crate::VelloCpuScenePainter::set_num_threads))
+pub(crate) fn resolve_num_threads(num_threads: usize) -> usize {
+    match num_threads {
+        _ if !cfg!(feature = "multithreading") => 1,
+        0 => std::thread::available_parallelism().map_or(1, |threads| threads.get().min(8)),
+        threads => threads,
+    }
+}
+
+/// A thread pool with `threads` threads, shared by the painters which use that many
+#[cfg(feature = "multithreading")]
+fn thread_pool(threads: usize) -> Arc<ThreadPool> {
+    static POOL: Mutex<Option<Arc<ThreadPool>>> = Mutex::new(None);
+
+    let mut pool = POOL.lock().unwrap();
+    if let Some(pool) = pool.as_ref().filter(|pool| pool.current_num_threads() == threads) {
+        return Arc::clone(pool);
+    }
+    let new_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("vello-cpu-raster-{index}"))
+        .build()
+        .expect("Error creating vello_cpu thread pool");
+    let new_pool = Arc::new(new_pool);
+    *pool = Some(Arc::clone(&new_pool));
+    new_pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_tiles_are_merged() {
+        let tile = TILE_SIZE as f64;
+        // Two rects in the same column of tiles, and one spanning two columns below them
+        let dirty = [
+            Rect::new(1.0, 1.0, 2.0, 2.0),
+            Rect::new(10.0, tile + 5.0, 20.0, tile + 6.0),
+            Rect::new(tile - 1.0, tile * 2.0, tile + 1.0, tile * 2.0 + 1.0),
+        ];
+        let regions = dirty_tiles(&dirty, 1000, 1000);
+        assert_eq!(
+            regions,
+            [
+                Rect::new(0.0, 0.0, tile, tile * 2.0),
+                Rect::new(0.0, tile * 2.0, tile * 2.0, tile * 3.0),
+            ]
+        );
+
+        // Tiles at the edges are cut to the frame
+        let regions = dirty_tiles(&[Rect::new(95.0, 95.0, 200.0, 200.0)], 100, 100);
+        assert_eq!(regions, [Rect::new(tile, tile, 100.0, 100.0)]);
+    }
+}
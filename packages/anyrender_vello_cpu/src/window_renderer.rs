@@ -1,11 +1,13 @@
+use std::collections::VecDeque;
 use std::{num::NonZero, sync::Arc};
 
-use anyrender::{WindowHandle, WindowRenderer};
+use anyrender::{PaintScene, ResumeError, WindowHandle, WindowRenderer};
 use peniko::color::PremulRgba8;
+use peniko::kurbo::Rect;
 use softbuffer::{Context, Surface};
 
-use crate::VelloCpuScenePainter;
-use crate::vello_cpu::{Pixmap, RenderContext, RenderMode};
+use crate::vello_cpu::Pixmap;
+use crate::{TextRenderingConfig, VelloCpuScenePainter};
 
 // Simple struct to hold the state of the renderer
 pub struct ActiveRenderState {
@@ -25,17 +27,55 @@ pub struct VelloCpuWindowRenderer {
     render_state: RenderState,
     window_handle: Option<Arc<dyn WindowHandle>>,
     render_context: VelloCpuScenePainter,
+    /// The last frame rendered, which the dirty regions of the next frame are rendered over
+    frame: Option<Pixmap>,
+    /// The parts of the next frame which changed, if not all of it did (see
+    /// [`WindowRenderer::set_damage`])
+    damage: Option<Vec<Rect>>,
+    /// The regions which changed in recent frames, most recent first. Surface buffers can hold
+    /// an older frame than the last one, and need these copied into them too.
+    presented: VecDeque<Vec<Rect>>,
 }
 
+/// How many frames' changed regions are kept, for updating surface buffers older than the last
+/// frame. Older buffers are copied to in full.
+const MAX_BUFFER_AGE: usize = 4;
+
 impl VelloCpuWindowRenderer {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             render_state: RenderState::Suspended,
             window_handle: None,
-            render_context: VelloCpuScenePainter(RenderContext::new(0, 0)),
+            render_context: Self::painter(0, 0, 0, TextRenderingConfig::default()),
+            frame: None,
+            damage: None,
+            presented: VecDeque::new(),
         }
     }
+
+    /// A painter which records its commands, so that just the damaged tiles of a frame are
+    /// re-rasterized
+    fn painter(
+        width: u16,
+        height: u16,
+        num_threads: usize,
+        text_config: TextRenderingConfig,
+    ) -> VelloCpuScenePainter {
+        let mut painter = VelloCpuScenePainter::with_num_threads(width, height, num_threads);
+        painter.set_partial_rendering(true);
+        painter.set_text_rendering(text_config);
+        painter
+    }
+
+    pub fn set_text_rendering(&mut self, config: TextRenderingConfig) {
+        self.render_context.set_text_rendering(config);
+    }
+
+    /// See [`VelloCpuScenePainter::set_num_threads`]
+    pub fn set_num_threads(&mut self, num_threads: usize) {
+        self.render_context.set_num_threads(num_threads);
+    }
 }
 
 impl WindowRenderer for VelloCpuWindowRenderer {
@@ -46,8 +86,19 @@ impl WindowRenderer for VelloCpuWindowRenderer {
     }
 
     fn resume(&mut self, window_handle: Arc<dyn WindowHandle>, width: u32, height: u32) {
-        let context = Context::new(window_handle.clone()).unwrap();
-        let surface = Surface::new(&context, window_handle.clone()).unwrap();
+        self.try_resume(window_handle, width, height).unwrap();
+    }
+
+    fn try_resume(
+        &mut self,
+        window_handle: Arc<dyn WindowHandle>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ResumeError> {
+        // softbuffer's errors aren't Send + Sync
+        let context = Context::new(window_handle.clone()).map_err(|err| err.to_string())?;
+        let surface =
+            Surface::new(&context, window_handle.clone()).map_err(|err| err.to_string())?;
         self.render_state = RenderState::Active(ActiveRenderState {
             _context: context,
             surface,
@@ -55,6 +106,7 @@ impl WindowRenderer for VelloCpuWindowRenderer {
         self.window_handle = Some(window_handle);
 
         self.set_size(width, height);
+        Ok(())
     }
 
     fn suspend(&mut self) {
@@ -70,14 +122,23 @@ impl WindowRenderer for VelloCpuWindowRenderer {
                     NonZero::new(physical_height.max(1)).unwrap(),
                 )
                 .unwrap();
-            self.render_context = VelloCpuScenePainter(RenderContext::new(
+            self.render_context = Self::painter(
                 physical_width as u16,
                 physical_height as u16,
-            ));
+                self.render_context.num_threads(),
+                self.render_context.text_rendering(),
+            );
+            self.frame = None;
+            self.presented.clear();
         };
     }
 
+    fn set_damage(&mut self, damage: &[Rect]) {
+        self.damage = Some(damage.to_vec());
+    }
+
     fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+        let damage = self.damage.take();
         let RenderState::Active(state) = &mut self.render_state else {
             return;
         };
@@ -85,18 +146,54 @@ impl WindowRenderer for VelloCpuWindowRenderer {
             return;
         };
 
-        // Paint
-        let width = self.render_context.0.width();
-        let height = self.render_context.0.height();
-        let mut pixmap = Pixmap::new(width, height);
+        // Paint, re-rasterizing just the damaged tiles when the last frame is still valid
+        let width = self.render_context.context.width();
+        let height = self.render_context.context.height();
+        let full_frame = Rect::new(0.0, 0.0, width as f64, height as f64);
         draw_fn(&mut self.render_context);
-        self.render_context
-            .0
-            .render_to_pixmap(&mut pixmap, RenderMode::OptimizeSpeed);
+        let regions = match (damage, &mut self.frame) {
+            (Some(damage), Some(frame)) => {
+                self.render_context.render_dirty_to_pixmap(frame, &damage)
+            }
+            (_, frame) => {
+                let frame = frame.insert(Pixmap::new(width, height));
+                self.render_context.render_to_pixmap(frame);
+                vec![full_frame]
+            }
+        };
+        let frame = self.frame.as_ref().unwrap();
 
+        // The buffer holds the frame presented `age` frames ago (or garbage when the age is 0), so
+        // the regions which changed in the frames since then need copying too
+        let age = surface_buffer.age() as usize;
         let out = surface_buffer.as_mut();
-        assert_eq!(pixmap.data().len(), out.len());
-        for (src, dest) in pixmap.data().iter().zip(out.iter_mut()) {
+        assert_eq!(frame.data().len(), out.len());
+        if age == 0 || age > self.presented.len() + 1 {
+            copy_to_buffer(frame, out, full_frame);
+        } else {
+            let stale = self.presented.iter().take(age - 1).flatten();
+            for region in regions.iter().chain(stale) {
+                copy_to_buffer(frame, out, *region);
+            }
+        }
+
+        let damage: Vec<softbuffer::Rect> = regions.iter().filter_map(surface_rect).collect();
+        surface_buffer.present_with_damage(&damage).unwrap();
+        self.presented.push_front(regions);
+        self.presented.truncate(MAX_BUFFER_AGE);
+
+        // Empty the Vello render context (memory optimisation)
+        self.render_context.reset();
+    }
+}
+
+/// Copy `region` of `frame` into `out`, a softbuffer buffer of the same size
+fn copy_to_buffer(frame: &Pixmap, out: &mut [u32], region: Rect) {
+    let width = frame.width() as usize;
+    let (x0, x1) = (region.x0 as usize, region.x1 as usize);
+    let rows = frame.data().chunks_exact(width).zip(out.chunks_exact_mut(width));
+    for (src, dest) in rows.take(region.y1 as usize).skip(region.y0 as usize) {
+        for (src, dest) in src[x0..x1].iter().zip(&mut dest[x0..x1]) {
             let PremulRgba8 { r, g, b, a } = *src;
             if a == 0 {
                 *dest = u32::MAX;
@@ -104,10 +201,14 @@ impl WindowRenderer for VelloCpuWindowRenderer {
                 *dest = (r as u32) << 16 | (g as u32) << 8 | b as u32;
             }
         }
-
-        surface_buffer.present().unwrap();
-
-        // Empty the Vello render context (memory optimisation)
-        self.render_context.0.reset();
     }
 }
+
+fn surface_rect(region: &Rect) -> Option<softbuffer::Rect> {
+    Some(softbuffer::Rect {
+        x: region.x0 as u32,
+        y: region.y0 as u32,
+        width: NonZero::new(region.width() as u32)?,
+        height: NonZero::new(region.height() as u32)?,
+    })
+}
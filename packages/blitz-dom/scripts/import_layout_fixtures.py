@@ -0,0 +1,262 @@
+#!/usr/bin/env python3
+"""Import grid and flexbox layout fixtures for tests/layout_conformance_tests.rs.
+
+taffy: converts taffy's gentest fixtures (test_fixtures/{flex,grid}/*.html) to the fixture format,
+taking the expected layouts from the matching generated tests (tests/generated/{flex,grid}/*.rs),
+which were recorded in Chrome. Writes tests/layout/taffy_flex.json and tests/layout/taffy_grid.json.
+
+wpt: converts WPT tests which use check-layout-th.js (data-expected-width, data-offset-x, ...),
+one fixture per top-level element carrying expectations. Tests can be given as files or as
+directories, which are searched for check-layout tests; without any, the grid and flexbox
+directories in WPT_SUBSET are imported. The checkout defaults to $WPT_DIR, as for the WPT runner.
+Writes tests/layout/wpt.json.
+
+Fixtures containing text are skipped: both suites measure text with the Ahem font, and the
+fixtures only pin down box layout. Re-run after updating the checkouts; fixtures which fail for
+known reasons can be kept with a "skip" field explaining why, and that field is preserved.
+
+The fixtures written by this script aren't checked in yet: tests/layout only holds curated ones.
+
+Usage:
+    scripts/import_layout_fixtures.py taffy path/to/taffy
+    scripts/import_layout_fixtures.py wpt [path/to/wpt] [css/css-grid/alignment ...]
+"""
+
+import html.parser
+import json
+import os
+import pathlib
+import re
+import sys
+
+LAYOUT_DIR = pathlib.Path(__file__).resolve().parent.parent / "tests" / "layout"
+
+# The WPT directories imported by default
+WPT_SUBSET = (
+    "css/css-grid/alignment",
+    "css/css-grid/grid-items",
+    "css/css-grid/layout-algorithm",
+    "css/css-flexbox",
+)
+WPT_EXTENSIONS = (".htm", ".html", ".xht", ".xhtml")
+
+# Attributes which the runner applies to the elements it creates
+KEPT_ATTRIBUTES = ("id", "class", "style")
+VOID_ELEMENTS = {"br", "hr", "img", "input", "link", "meta"}
+WPT_EXPECTATIONS = {
+    "data-expected-width": "width",
+    "data-expected-height": "height",
+    "data-offset-x": "offset_x",
+    "data-offset-y": "offset_y",
+}
+
+
+class Element:
+    def __init__(self, tag, attrs):
+        self.tag = tag
+        self.attrs = dict(attrs)
+        self.children = []
+        self.has_text = False
+
+    def find(self, predicate):
+        if predicate(self):
+            return self
+        for child in self.children:
+            found = child.find(predicate)
+            if found is not None:
+                return found
+        return None
+
+    def walk(self):
+        yield self
+        for child in self.children:
+            yield from child.walk()
+
+
+class TreeBuilder(html.parser.HTMLParser):
+    def __init__(self):
+        super().__init__()
+        self.root = Element("#document", [])
+        self.stack = [self.root]
+        self.styles = []
+
+    def handle_starttag(self, tag, attrs):
+        element = Element(tag, attrs)
+        self.stack[-1].children.append(element)
+        if tag not in VOID_ELEMENTS:
+            self.stack.append(element)
+
+    def handle_startendtag(self, tag, attrs):
+        self.stack[-1].children.append(Element(tag, attrs))
+
+    def handle_endtag(self, tag):
+        for index in range(len(self.stack) - 1, 0, -1):
+            if self.stack[index].tag == tag:
+                del self.stack[index:]
+                break
+
+    def handle_data(self, data):
+        current = self.stack[-1]
+        if current.tag == "style":
+            self.styles.append(data)
+        elif data.strip() and current.tag not in ("script", "title"):
+            current.has_text = True
+
+
+def parse_html(path):
+    builder = TreeBuilder()
+    builder.feed(path.read_text(encoding="utf-8"))
+    return builder
+
+
+def to_fixture_node(element, expectations):
+    """Convert an element tree, or return None if the runner can't reproduce it"""
+    if element.has_text:
+        return None
+    node = {}
+    if element.tag != "div":
+        node["tag"] = element.tag
+    for name in KEPT_ATTRIBUTES:
+        if name in element.attrs:
+            node[name] = element.attrs[name]
+    expect = expectations(element)
+    if expect:
+        node["expect"] = expect
+    children = []
+    for child in element.children:
+        if child.tag in ("script", "style"):
+            continue
+        converted = to_fixture_node(child, expectations)
+        if converted is None:
+            return None
+        children.append(converted)
+    if children:
+        node["children"] = children
+    return node
+
+
+def taffy_expectations(source):
+    """Layouts asserted by a generated taffy test, in the order nodes are first checked (pre-order)"""
+    # Newer taffy generates a border-box and a content-box variant of each test. The fixtures
+    # themselves use border-box sizing.
+    variant = re.search(r"fn \w+__border_box\(\)(.*?)(?=\n#\[test\]|\Z)", source, re.S)
+    body = variant.group(1) if variant else source
+    layouts = {}
+    pattern = re.compile(r"taffy\.layout\((node\w*)\)(.*?)(?=taffy\.layout\(|\Z)", re.S)
+    keys = {"size.width": "width", "size.height": "height", "location.x": "x", "location.y": "y"}
+    for match in pattern.finditer(body):
+        values = layouts.setdefault(match.group(1), {})
+        for field, key in keys.items():
+            found = re.search(re.escape(field) + r",\s*(-?[\d.]+)f32", match.group(2))
+            if found:
+                values.setdefault(key, float(found.group(1)))
+    return list(layouts.values())
+
+
+def import_taffy(checkout):
+    for kind in ("flex", "grid"):
+        fixtures = []
+        for html_path in sorted((checkout / "test_fixtures" / kind).glob("*.html")):
+            name = html_path.stem
+            generated = checkout / "tests" / "generated" / kind / f"{name}.rs"
+            if name.startswith("x") or not generated.exists():
+                continue
+            test_root = parse_html(html_path).root.find(lambda el: el.attrs.get("id") == "test-root")
+            if test_root is None:
+                continue
+            layouts = taffy_expectations(generated.read_text(encoding="utf-8"))
+            if len(layouts) != sum(1 for _ in test_root.walk()):
+                print(f"{name}: expectations don't match the fixture's nodes, skipping")
+                continue
+            remaining = iter(layouts)
+            root = to_fixture_node(test_root, lambda _: next(remaining))
+            if root is None:
+                print(f"{name}: contains text, skipping")
+                continue
+            fixtures.append({"name": name, "source": "taffy", "root": root})
+        write_fixtures(LAYOUT_DIR / f"taffy_{kind}.json", fixtures)
+
+
+def wpt_expectations(element):
+    return {
+        key: float(element.attrs[attr])
+        for attr, key in WPT_EXPECTATIONS.items()
+        if attr in element.attrs
+    }
+
+
+def wpt_tests(checkout, paths):
+    """The check-layout tests in `paths`, relative to the checkout"""
+    for path in paths:
+        if not (checkout / path).exists():
+            print(f"{path}: not found, skipping")
+            continue
+        if not (checkout / path).is_dir():
+            yield path
+            continue
+        for test in sorted((checkout / path).rglob("*")):
+            if test.suffix not in WPT_EXTENSIONS or "-ref" in test.stem:
+                continue
+            if "check-layout-th.js" in test.read_text(encoding="utf-8", errors="replace"):
+                yield test.relative_to(checkout).as_posix()
+
+
+def import_wpt(checkout, paths):
+    fixtures = []
+    for test in wpt_tests(checkout, paths or WPT_SUBSET):
+        parsed = parse_html(checkout / test)
+        body = parsed.root.find(lambda el: el.tag == "body")
+        if body is None:
+            continue
+        css = "\n".join(style.strip() for style in parsed.styles)
+        candidates = [
+            child
+            for child in body.children
+            if any(wpt_expectations(el) for el in child.walk())
+        ]
+        for index, element in enumerate(candidates):
+            root = to_fixture_node(element, wpt_expectations)
+            name = f"{test}#{index}"
+            if root is None:
+                print(f"{name}: contains text, skipping")
+                continue
+            fixture = {"name": name, "source": "wpt", "root": root}
+            if css:
+                fixture["css"] = css
+            fixtures.append(fixture)
+    write_fixtures(LAYOUT_DIR / "wpt.json", fixtures)
+
+
+def write_fixtures(path, fixtures):
+    # Keep the reasons known failures are skipped across re-imports
+    if path.exists():
+        previous = json.loads(path.read_text(encoding="utf-8"))["fixtures"]
+        skips = {fixture["name"]: fixture["skip"] for fixture in previous if "skip" in fixture}
+        for fixture in fixtures:
+            if fixture["name"] in skips:
+                fixture["skip"] = skips[fixture["name"]]
+    path.write_text(json.dumps({"fixtures": fixtures}, indent=2) + "\n", encoding="utf-8")
+    print(f"wrote {len(fixtures)} fixtures to {path}")
+
+
+def main():
+    if len(sys.argv) < 2 or sys.argv[1] not in ("taffy", "wpt"):
+        sys.exit(__doc__)
+    if sys.argv[1] == "taffy":
+        if len(sys.argv) < 3:
+            sys.exit(__doc__)
+        import_taffy(pathlib.Path(sys.argv[2]))
+        return
+
+    args = sys.argv[2:]
+    if args and (pathlib.Path(args[0]) / "css").is_dir():
+        checkout, paths = pathlib.Path(args[0]), args[1:]
+    elif "WPT_DIR" in os.environ:
+        checkout, paths = pathlib.Path(os.environ["WPT_DIR"]), args
+    else:
+        sys.exit("WPT_DIR is not set. Pass a copy of https://github.com/web-platform-tests/wpt.")
+    import_wpt(checkout, paths)
+
+
+if __name__ == "__main__":
+    main()
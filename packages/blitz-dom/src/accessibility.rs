@@ -1,10 +1,61 @@
+use std::collections::HashMap;
+
 use accesskit::{Node as AccessKitNode, NodeId, Role, Tree, TreeUpdate};
+use serde::{Deserialize, Serialize};
+
+use crate::util::collapse_whitespace;
+use crate::{BaseDocument, ElementData, Node as BlitzDomNode, NodeData, local_name};
 
-use crate::{BaseDocument, Node as BlitzDomNode, local_name};
+/// A snapshot of a document's accessibility tree, see [`BaseDocument::accessibility_snapshot`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilitySnapshot {
+    /// The id of the focused node (if any)
+    pub focus: Option<usize>,
+    pub root: AccessibilityNodeSnapshot,
+}
+
+/// A node in an [`AccessibilitySnapshot`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilityNodeSnapshot {
+    /// The id of the DOM node
+    pub id: usize,
+    /// The name of the AccessKit [`Role`] (e.g. `"Button"`)
+    pub role: String,
+    /// The element's tag name. `None` for text runs and the document.
+    pub tag: Option<String>,
+    /// The accessible name
+    pub name: Option<String>,
+    /// The (whitespace collapsed) text of a text run
+    pub value: Option<String>,
+    pub states: AccessibilityStates,
+    /// The border box in document coordinates. `None` for nodes which don't have a box of their
+    /// own (text runs and the document).
+    pub bounds: Option<AccessibilityBounds>,
+    pub children: Vec<AccessibilityNodeSnapshot>,
+}
+
+/// The states of an element in an [`AccessibilitySnapshot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct AccessibilityStates {
+    pub focused: bool,
+    pub hovered: bool,
+    pub disabled: bool,
+    /// Whether a checkbox or radio button is checked. `None` for other elements.
+    pub checked: Option<bool>,
+}
+
+/// A rectangle in document coordinates (CSS pixels)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccessibilityBounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
 
 impl BaseDocument {
     pub fn build_accessibility_tree(&self) -> TreeUpdate {
-        let mut nodes = std::collections::HashMap::new();
+        let mut nodes = HashMap::new();
         let mut window = AccessKitNode::new(Role::Window);
 
         self.visit(|node_id, node| {
@@ -18,6 +69,21 @@ impl BaseDocument {
             nodes.insert(node_id, (id, builder));
         });
 
+        // Nodes are visited in DOM order, so reorder the children of masonry containers
+        for container_id in self.masonry_reading_orders.keys() {
+            if let Some((_, container)) = nodes.get_mut(container_id) {
+                let position: HashMap<u64, usize> = self
+                    .children_in_reading_order(*container_id)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, id)| (id as u64, i))
+                    .collect();
+                let mut children = container.children().to_vec();
+                children.sort_by_key(|child| position.get(&child.0).copied().unwrap_or(usize::MAX));
+                container.set_children(children);
+            }
+        }
+
         let mut nodes: Vec<_> = nodes
             .into_iter()
             .map(|(_, (id, node))| (id, node))
@@ -44,27 +110,11 @@ impl BaseDocument {
             builder.set_role(Role::Window)
         } else if let Some(element_data) = node.element_data() {
             let name = element_data.name.local.to_string();
+            let role = accessibility_role(element_data);
 
-            let role = match &*name {
-                "button" => Role::Button,
-                "a" | "link" => Role::Link,
-                "div" => Role::GenericContainer,
-                "header" => Role::Header,
-                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Role::Heading,
-                "p" => Role::Paragraph,
-                "section" => Role::Section,
-                "img" => Role::Image,
-                "input" => match element_data.attr(local_name!("type")).unwrap_or("text") {
-                    "text" | "email" | "password" => Role::TextInput,
-                    "number" => Role::NumberInput,
-                    "checkbox" => Role::CheckBox,
-                    "radio" => Role::RadioButton,
-                    "submit" | "button" => Role::Button,
-                    _ => Role::TextInput,
-                },
-                _ => Role::GenericContainer,
-            };
-
+            if let Some(label) = self.accessible_name(node, role) {
+                builder.set_label(label);
+            }
             builder.set_role(role);
             builder.set_html_tag(name);
         } else if node.is_text_node() {
@@ -77,4 +127,125 @@ impl BaseDocument {
 
         (id, builder)
     }
+
+    /// A serializable snapshot of the accessibility tree.
+    ///
+    /// This contains the same roles and names as [`build_accessibility_tree`](Self::build_accessibility_tree),
+    /// along with element states and layout bounds, so that accessibility output can be asserted
+    /// on (or diffed as JSON) without going through a platform adapter. Nodes which are
+    /// `display: none` or `aria-hidden="true"`, comments, and whitespace-only text are omitted.
+    pub fn accessibility_snapshot(&self) -> AccessibilitySnapshot {
+        AccessibilitySnapshot {
+            focus: self.focus_node_id,
+            root: self.snapshot_accessibility_node(self.root_node()),
+        }
+    }
+
+    fn snapshot_accessibility_node(&self, node: &BlitzDomNode) -> AccessibilityNodeSnapshot {
+        let mut snapshot = AccessibilityNodeSnapshot {
+            id: node.id,
+            role: format!("{:?}", Role::Window),
+            tag: None,
+            name: None,
+            value: None,
+            states: AccessibilityStates::default(),
+            bounds: None,
+            children: Vec::new(),
+        };
+
+        if let Some(element_data) = node.element_data() {
+            let role = accessibility_role(element_data);
+            snapshot.role = format!("{role:?}");
+            snapshot.tag = Some(element_data.name.local.to_string());
+            snapshot.name = self.accessible_name(node, role);
+            snapshot.states = AccessibilityStates {
+                focused: self.focus_node_id == Some(node.id),
+                hovered: node.is_hovered(),
+                disabled: element_data.has_attr(local_name!("disabled")),
+                checked: matches!(role, Role::CheckBox | Role::RadioButton).then(|| {
+                    element_data
+                        .checkbox_input_checked()
+                        .unwrap_or_else(|| element_data.has_attr(local_name!("checked")))
+                }),
+            };
+
+            let position = node.absolute_position(0.0, 0.0);
+            let size = node.final_layout.size;
+            snapshot.bounds = Some(AccessibilityBounds {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+            });
+        } else if let NodeData::Text(data) = &node.data {
+            snapshot.role = format!("{:?}", Role::TextRun);
+            snapshot.value = Some(collapse_whitespace(&data.content));
+        }
+
+        snapshot.children = self
+            .children_in_reading_order(node.id)
+            .into_iter()
+            .map(|child_id| &self.nodes[child_id])
+            .filter(|child| is_accessible(child))
+            .map(|child| self.snapshot_accessibility_node(child))
+            .collect();
+
+        snapshot
+    }
+
+    /// The children of a node in the order assistive technologies read them in.
+    ///
+    /// This is DOM order, except in masonry containers, whose items are read in the order they
+    /// were placed in (see `masonry-auto-flow`), reordered by their `reading-order`. Children that
+    /// aren't placed, like absolutely positioned ones, come last.
+    pub fn children_in_reading_order(&self, node_id: usize) -> Vec<usize> {
+        let node = &self.nodes[node_id];
+        let mut children = node.children.clone();
+        let reading_order = self
+            .masonry_reading_orders
+            .get(&node_id)
+            .filter(|_| node.style().display == taffy::Display::Grid);
+        if let Some(reading_order) = reading_order {
+            let position: HashMap<usize, usize> =
+                reading_order.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+            children.sort_by_key(|id| position.get(id).copied().unwrap_or(usize::MAX));
+        }
+        children
+    }
+}
+
+fn accessibility_role(element_data: &ElementData) -> Role {
+    match &*element_data.name.local {
+        "button" => Role::Button,
+        "a" | "link" => Role::Link,
+        "div" => Role::GenericContainer,
+        "header" => Role::Header,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Role::Heading,
+        "p" => Role::Paragraph,
+        "section" => Role::Section,
+        "img" => Role::Image,
+        "input" => match element_data.attr(local_name!("type")).unwrap_or("text") {
+            "text" | "email" | "password" => Role::TextInput,
+            "number" => Role::NumberInput,
+            "checkbox" => Role::CheckBox,
+            "radio" => Role::RadioButton,
+            "submit" | "button" => Role::Button,
+            _ => Role::TextInput,
+        },
+        _ => Role::GenericContainer,
+    }
+}
+
+/// Whether a node (and its subtree) is exposed in an [`AccessibilitySnapshot`]
+fn is_accessible(node: &BlitzDomNode) -> bool {
+    match &node.data {
+        NodeData::Element(element_data) => {
+            element_data.attr(local_name!("aria-hidden")) != Some("true")
+                && !node
+                    .primary_styles()
+                    .is_some_and(|style| style.get_box().display.is_none())
+        }
+        NodeData::Text(data) => !data.content.chars().all(|c| c.is_ascii_whitespace()),
+        _ => false,
+    }
 }
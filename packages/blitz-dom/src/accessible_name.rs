@@ -0,0 +1,52 @@
+//! Accessible names, which assistive technologies announce elements by
+//!
+//! Names are computed with a simplified version of <https://www.w3.org/TR/accname-1.2/>, with
+//! whitespace collapsed like text extracted from painted documents is (see
+//! [`push_collapsed_text`]).
+
+use accesskit::Role;
+
+use crate::util::{collapse_whitespace, push_collapsed_text};
+use crate::{BaseDocument, Node as BlitzDomNode, NodeData, local_name};
+
+impl BaseDocument {
+    /// Compute a (simplified) accessible name for an element.
+    ///
+    /// Uses `aria-label`, then `alt` for images, then the element's text content for roles that
+    /// take their name from their contents.
+    pub(crate) fn accessible_name(&self, node: &BlitzDomNode, role: Role) -> Option<String> {
+        let element_data = node.element_data()?;
+
+        let explicit = element_data
+            .attr(local_name!("aria-label"))
+            .or_else(|| match role {
+                Role::Image => element_data.attr(local_name!("alt")),
+                _ => None,
+            });
+        if let Some(label) = explicit {
+            let label = collapse_whitespace(label);
+            return (!label.is_empty()).then_some(label);
+        }
+
+        if !matches!(role, Role::Button | Role::Link | Role::Heading) {
+            return None;
+        }
+
+        let mut name = String::new();
+        self.push_name_from_content(node, &mut name);
+        name.truncate(name.trim_end().len());
+        (!name.is_empty()).then_some(name)
+    }
+
+    fn push_name_from_content(&self, node: &BlitzDomNode, out: &mut String) {
+        match &node.data {
+            NodeData::Text(data) => push_collapsed_text(out, &data.content),
+            NodeData::Element(..) | NodeData::AnonymousBlock(..) => {
+                for child_id in node.children.iter() {
+                    self.push_name_from_content(&self.nodes[*child_id], out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
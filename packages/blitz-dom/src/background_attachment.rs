@@ -0,0 +1,83 @@
+//! The `background-attachment` property
+//!
+//! Stylo only parses `local` in gecko mode, so like the `scrollbar-*` properties (see
+//! [`crate::scrollbar`]) declarations of `background-attachment` are renamed to the
+//! `--blitz-background-attachment` custom property before stylesheets are parsed, and read back
+//! from the computed custom properties. It isn't inherited, so a user agent stylesheet resets it
+//! on every element, to the guaranteed-invalid value: elements which don't declare it use the
+//! attachment stylo computed from the `background` shorthand. The shorthand isn't renamed, but
+//! each declaration of it is followed by one resetting the custom property, so that it overrides
+//! earlier `background-attachment` declarations as it would the longhand. Stylo still drops
+//! `background` declarations which use `local`.
+
+use crate::BaseDocument;
+use crate::util::custom_property;
+
+/// Resets `background-attachment` on every element, as it isn't inherited
+pub(crate) const BACKGROUND_ATTACHMENT_STYLESHEET: &str =
+    "* { --blitz-background-attachment: initial; }\n";
+
+/// The custom property `property` is renamed to, if it is `background-attachment`
+pub(crate) fn custom_property_for(property: &str) -> Option<&'static str> {
+    property
+        .eq_ignore_ascii_case("background-attachment")
+        .then_some("--blitz-background-attachment")
+}
+
+/// The custom property reset by declarations of `property`, if it is the `background` shorthand
+pub(crate) fn custom_property_reset_by(property: &str) -> Option<&'static str> {
+    property
+        .eq_ignore_ascii_case("background")
+        .then_some("--blitz-background-attachment")
+}
+
+/// Whether a background layer scrolls along with the element, its contents or the viewport
+///
+/// See <https://drafts.csswg.org/css-backgrounds/#background-attachment>
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundAttachment {
+    /// Fixed to the element's border box
+    #[default]
+    Scroll,
+    /// Fixed to the viewport
+    Fixed,
+    /// Fixed to the element's contents, so that it scrolls with them in scroll containers
+    Local,
+}
+
+impl BackgroundAttachment {
+    /// Parse a value like `local, scroll`, with a keyword for each layer. Unknown keywords are
+    /// `scroll`.
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        let parse = |keyword: &str| match keyword.trim().to_ascii_lowercase().as_str() {
+            "fixed" => Self::Fixed,
+            "local" => Self::Local,
+            _ => Self::Scroll,
+        };
+        value.split(',').map(parse).collect()
+    }
+}
+
+impl BaseDocument {
+    /// The `background-attachment` of each background layer of a node, if it's declared with
+    /// the longhand property, or `None` if it's set by the `background` shorthand (or not at all),
+    /// in which case stylo's computed value applies
+    pub fn background_attachments(&self, node_id: usize) -> Option<Vec<BackgroundAttachment>> {
+        let styles = self.nodes.get(node_id)?.primary_styles()?;
+        let value = custom_property(&self.stylist, &styles, "blitz-background-attachment")?;
+        Some(BackgroundAttachment::parse_list(&value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lists() {
+        use BackgroundAttachment::*;
+
+        assert_eq!(BackgroundAttachment::parse_list("local"), [Local]);
+        assert_eq!(BackgroundAttachment::parse_list(" Fixed, local ,auto"), [Fixed, Local, Scroll]);
+    }
+}
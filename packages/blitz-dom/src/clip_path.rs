@@ -0,0 +1,267 @@
+//! The regions elements are clipped to by `clip-path`
+//!
+//! Regions are computed from an element's styles and layout, so that painting and hit-testing
+//! clip to the same region. Basic shapes (`inset()`, `circle()`, `ellipse()`, `polygon()` and
+//! `path()`) and boxes are supported. References to SVG `<clipPath>` elements (`url()`) and the
+//! `shape()` function are not, and don't clip at all.
+
+use std::f64::consts::{FRAC_PI_2, PI, SQRT_2};
+
+use peniko::kurbo::{self, BezPath, Point, Rect, Shape as _, Vec2};
+use style::properties::ComputedValues;
+use style::values::computed::basic_shape::{BasicShape, ClipPath, ShapeRadius};
+use style::values::computed::{BorderCornerRadius, Length, LengthPercentage, Position};
+use style::values::generics::basic_shape::{
+    FillRule, GenericPathOrShapeFunction, ShapeBox, ShapeGeometryBox,
+};
+use style::values::generics::position::GenericPositionOrAuto;
+use style_traits::ToCss;
+
+/// The tolerance curves are flattened with (in CSS px)
+const TOLERANCE: f64 = 0.1;
+
+/// The region an element is clipped to by its `clip-path`, relative to the top-left corner of its
+/// border box (in CSS px)
+#[derive(Clone, Debug)]
+pub struct ClipRegion {
+    pub path: BezPath,
+    /// Whether the path is filled with the `evenodd` fill rule (rather than `nonzero`)
+    pub even_odd: bool,
+}
+
+impl ClipRegion {
+    fn new(path: BezPath) -> Self {
+        Self {
+            path,
+            even_odd: false,
+        }
+    }
+
+    /// Whether `point` is inside the region
+    pub fn contains(&self, point: Point) -> bool {
+        let winding = self.path.winding(point);
+        if self.even_odd {
+            winding % 2 != 0
+        } else {
+            winding != 0
+        }
+    }
+}
+
+/// The region an element with `style` and `layout` is clipped to, or `None` if it isn't clipped
+pub fn clip_region(style: &ComputedValues, layout: &taffy::Layout) -> Option<ClipRegion> {
+    match style.clone_clip_path() {
+        ClipPath::None | ClipPath::Url(_) => None,
+        ClipPath::Box(geometry_box) => {
+            let reference = reference_box(layout, geometry_box);
+            Some(ClipRegion::new(reference.to_path(TOLERANCE)))
+        }
+        ClipPath::Shape(shape, geometry_box) => {
+            shape_region(&shape, reference_box(layout, geometry_box))
+        }
+    }
+}
+
+/// The box a shape is sized and positioned against, which is the border box unless another one
+/// is given
+fn reference_box(layout: &taffy::Layout, geometry_box: ShapeGeometryBox) -> Rect {
+    let size = layout.size.map(f64::from);
+    let border_box = Rect::new(0.0, 0.0, size.width, size.height);
+    let inset = |rect: Rect, edges: taffy::Rect<f32>| {
+        let edges = edges.map(f64::from);
+        Rect::new(
+            rect.x0 + edges.left,
+            rect.y0 + edges.top,
+            rect.x1 - edges.right,
+            rect.y1 - edges.bottom,
+        )
+    };
+
+    // SVG's boxes (fill-box, stroke-box and view-box) are the border box of HTML elements
+    let ShapeGeometryBox::ShapeBox(shape_box) = geometry_box else {
+        return border_box;
+    };
+    match shape_box {
+        ShapeBox::MarginBox => {
+            let margin = layout.margin.map(f64::from);
+            Rect::new(
+                -margin.left,
+                -margin.top,
+                size.width + margin.right,
+                size.height + margin.bottom,
+            )
+        }
+        ShapeBox::BorderBox => border_box,
+        ShapeBox::PaddingBox => inset(border_box, layout.border),
+        ShapeBox::ContentBox => inset(inset(border_box, layout.border), layout.padding),
+    }
+}
+
+fn shape_region(shape: &BasicShape, reference: Rect) -> Option<ClipRegion> {
+    let (width, height) = (reference.width(), reference.height());
+    let resolve = |value: &LengthPercentage, basis: f64| -> f64 {
+        value.resolve(Length::new(basis as f32)).px().into()
+    };
+    let center = |position: &GenericPositionOrAuto<Position>| match position {
+        GenericPositionOrAuto::Position(position) => Point::new(
+            reference.x0 + resolve(&position.horizontal, width),
+            reference.y0 + resolve(&position.vertical, height),
+        ),
+        GenericPositionOrAuto::Auto => reference.center(),
+    };
+
+    let region = match shape {
+        BasicShape::Rect(inset) => {
+            let rect = Rect::new(
+                reference.x0 + resolve(&inset.rect.3, width),
+                reference.y0 + resolve(&inset.rect.0, height),
+                reference.x1 - resolve(&inset.rect.1, width),
+                reference.y1 - resolve(&inset.rect.2, height),
+            );
+            // Insets which add up to more than the box leave nothing
+            let rect = Rect::new(rect.x0, rect.y0, rect.x1.max(rect.x0), rect.y1.max(rect.y0));
+
+            let corner = |radius: &BorderCornerRadius| {
+                Vec2::new(resolve(&radius.0.width.0, width), resolve(&radius.0.height.0, height))
+            };
+            let round = &inset.round;
+            let radii = [
+                corner(&round.top_left),
+                corner(&round.top_right),
+                corner(&round.bottom_right),
+                corner(&round.bottom_left),
+            ];
+            ClipRegion::new(rounded_rect_path(rect, radii))
+        }
+        BasicShape::Circle(circle) => {
+            let center = center(&circle.position);
+            let sides = [
+                center.x - reference.x0,
+                reference.x1 - center.x,
+                center.y - reference.y0,
+                reference.y1 - center.y,
+            ];
+            // Percentages are of the box's diagonal, normalized to its sides
+            let basis = width.hypot(height) / SQRT_2;
+            let radius = resolve_radius(&circle.radius, &sides, basis, resolve);
+            ClipRegion::new(kurbo::Circle::new(center, radius).to_path(TOLERANCE))
+        }
+        BasicShape::Ellipse(ellipse) => {
+            let center = center(&ellipse.position);
+            let x_sides = [center.x - reference.x0, reference.x1 - center.x];
+            let y_sides = [center.y - reference.y0, reference.y1 - center.y];
+            let radii = Vec2::new(
+                resolve_radius(&ellipse.semiaxis_x, &x_sides, width, resolve),
+                resolve_radius(&ellipse.semiaxis_y, &y_sides, height, resolve),
+            );
+            ClipRegion::new(kurbo::Ellipse::new(center, radii, 0.0).to_path(TOLERANCE))
+        }
+        BasicShape::Polygon(polygon) => {
+            let mut path = BezPath::new();
+            for (i, coord) in polygon.coordinates.iter().enumerate() {
+                let point = Point::new(
+                    reference.x0 + resolve(&coord.0, width),
+                    reference.y0 + resolve(&coord.1, height),
+                );
+                if i == 0 {
+                    path.move_to(point);
+                } else {
+                    path.line_to(point);
+                }
+            }
+            path.close_path();
+            ClipRegion {
+                path,
+                even_odd: polygon.fill == FillRule::Evenodd,
+            }
+        }
+        BasicShape::PathOrShape(GenericPathOrShapeFunction::Path(path)) => {
+            // Path data is serialized as a quoted string of SVG path commands, which are in px
+            // relative to the reference box
+            let data = path.path.to_css_string();
+            let mut bez_path = BezPath::from_svg(data.trim_matches('"')).ok()?;
+            bez_path.apply_affine(kurbo::Affine::translate(reference.origin().to_vec2()));
+            ClipRegion {
+                path: bez_path,
+                even_odd: path.fill == FillRule::Evenodd,
+            }
+        }
+        // TODO: Support the `shape()` function
+        BasicShape::PathOrShape(GenericPathOrShapeFunction::Shape(_)) => return None,
+    };
+    Some(region)
+}
+
+/// The length of a radius of a circle or ellipse, given the distances from its center to the sides
+/// of the reference box (for `closest-side` and `farthest-side`) and the basis of percentages
+fn resolve_radius(
+    radius: &ShapeRadius,
+    sides: &[f64],
+    basis: f64,
+    resolve: impl Fn(&LengthPercentage, f64) -> f64,
+) -> f64 {
+    let sides = sides.iter().map(|side| side.abs());
+    match radius {
+        ShapeRadius::Length(length) => resolve(&length.0, basis).max(0.0),
+        ShapeRadius::ClosestSide => sides.fold(f64::INFINITY, f64::min),
+        ShapeRadius::FarthestSide => sides.fold(0.0, f64::max),
+    }
+}
+
+/// `rect` with its corners (top-left, top-right, bottom-right then bottom-left) rounded by
+/// elliptical `radii`, which are scaled down if adjacent ones overlap
+pub(crate) fn rounded_rect_path(rect: Rect, radii: [Vec2; 4]) -> BezPath {
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    let fit = |length: f64, a: f64, b: f64| if a + b > length { length / (a + b) } else { 1.0 };
+    let scale = fit(rect.width(), top_left.x, top_right.x)
+        .min(fit(rect.width(), bottom_left.x, bottom_right.x))
+        .min(fit(rect.height(), top_left.y, bottom_left.y))
+        .min(fit(rect.height(), top_right.y, bottom_right.y));
+    let [top_left, top_right, bottom_right, bottom_left] = radii.map(|radius| radius * scale);
+
+    // Corners are drawn clockwise, each from the angle `start` (in radians)
+    let corner = |center: (f64, f64), radii: Vec2, start: f64| {
+        kurbo::Arc::new(center, radii, start, FRAC_PI_2, 0.0).append_iter(TOLERANCE)
+    };
+    let mut path = BezPath::new();
+    path.move_to((rect.x0 + top_left.x, rect.y0));
+    path.line_to((rect.x1 - top_right.x, rect.y0));
+    path.extend(corner((rect.x1 - top_right.x, rect.y0 + top_right.y), top_right, -FRAC_PI_2));
+    path.line_to((rect.x1, rect.y1 - bottom_right.y));
+    path.extend(corner((rect.x1 - bottom_right.x, rect.y1 - bottom_right.y), bottom_right, 0.0));
+    path.line_to((rect.x0 + bottom_left.x, rect.y1));
+    path.extend(corner((rect.x0 + bottom_left.x, rect.y1 - bottom_left.y), bottom_left, FRAC_PI_2));
+    path.line_to((rect.x0, rect.y0 + top_left.y));
+    path.extend(corner((rect.x0 + top_left.x, rect.y0 + top_left.y), top_left, PI));
+    path.close_path();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_follow_their_shape() {
+        let radius = Vec2::new(10.0, 10.0);
+        let rect = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let rounded = ClipRegion::new(rounded_rect_path(rect, [radius; 4]));
+        assert!(rounded.contains(Point::new(50.0, 25.0)));
+        assert!(rounded.contains(Point::new(5.0, 25.0)));
+        // Outside of the rounded corner
+        assert!(!rounded.contains(Point::new(1.0, 1.0)));
+
+        // A square drawn twice winds around its inside twice
+        let mut path = Rect::new(0.0, 0.0, 10.0, 10.0).to_path(TOLERANCE);
+        path.extend(Rect::new(0.0, 0.0, 10.0, 10.0).to_path(TOLERANCE));
+        let mut region = ClipRegion::new(path);
+        assert!(region.contains(Point::new(5.0, 5.0)));
+        region.even_odd = true;
+        assert!(!region.contains(Point::new(5.0, 5.0)));
+
+        let resolve = |_: &LengthPercentage, _: f64| 0.0;
+        let sides = [10.0, 30.0, -20.0];
+        assert_eq!(resolve_radius(&ShapeRadius::ClosestSide, &sides, 0.0, resolve), 10.0);
+        assert_eq!(resolve_radius(&ShapeRadius::FarthestSide, &sides, 0.0, resolve), 30.0);
+    }
+}
@@ -0,0 +1,223 @@
+//! The color scheme a document is rendered in, and forced dark mode
+//!
+//! Pages opt in to being rendered in dark mode with `<meta name="color-scheme" content="light
+//! dark">`, or with the `color-scheme` property of the root element, which takes precedence over
+//! the meta tag unless it's `normal`. Stylo doesn't compute the property in servo mode, so its
+//! declarations are renamed to a custom property before stylesheets are parsed (see
+//! [`crate::system_colors`]). The property of other elements is ignored: the whole document is
+//! rendered in one scheme.
+//!
+//! The document's used color scheme is the user's preferred one if the page supports it, and
+//! light for pages which don't declare any. System colors resolve to the used scheme's palette,
+//! and so do the default colors of the canvas and of form controls.
+//!
+//! Pages without any dark styles can be darkened with forced dark mode instead (see
+//! [`BaseDocument::set_forced_dark`]), which blitz-paint applies by inverting the lightness of
+//! the colors it paints.
+
+use blitz_traits::shell::ColorScheme;
+use markup5ever::local_name;
+use style::stylesheets::CssRule;
+use style_traits::ToCss;
+
+use crate::BaseDocument;
+use crate::traversal::TreeTraverser;
+use crate::util::custom_property;
+
+/// The custom property `property` is renamed to, if it is `color-scheme`
+pub(crate) fn custom_property_for(property: &str) -> Option<&'static str> {
+    property
+        .eq_ignore_ascii_case("color-scheme")
+        .then_some("--blitz-color-scheme")
+}
+
+/// The color schemes a page supports, from its `<meta name="color-scheme">` or its root
+/// element's `color-scheme`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ColorSchemeSupport {
+    pub light: bool,
+    pub dark: bool,
+    /// Whether the page opted out of being darkened by forced dark mode (`only light`)
+    pub only: bool,
+}
+
+impl ColorSchemeSupport {
+    /// Parse the `content` of a color scheme meta tag or a `color-scheme` value, such as
+    /// `light dark`
+    pub fn parse(content: &str) -> Self {
+        let mut support = Self::default();
+        for keyword in content.split_ascii_whitespace() {
+            match keyword.to_ascii_lowercase().as_str() {
+                "light" => support.light = true,
+                "dark" => support.dark = true,
+                "only" => support.only = true,
+                _ => {}
+            }
+        }
+        support
+    }
+
+    /// Whether no scheme is declared, as for `normal`
+    pub fn is_normal(&self) -> bool {
+        !self.light && !self.dark
+    }
+
+    /// The scheme a page with this support is rendered in for a user who prefers `preferred`
+    pub fn used(&self, preferred: ColorScheme) -> ColorScheme {
+        match preferred {
+            ColorScheme::Dark if self.dark => ColorScheme::Dark,
+            ColorScheme::Light if self.dark && !self.light => ColorScheme::Dark,
+            _ => ColorScheme::Light,
+        }
+    }
+}
+
+impl BaseDocument {
+    pub fn color_scheme_support(&self) -> ColorSchemeSupport {
+        self.color_scheme_support
+    }
+
+    /// The color scheme the document is rendered in, which system colors resolve to
+    pub fn used_color_scheme(&self) -> ColorScheme {
+        self.used_color_scheme
+    }
+
+    /// Whether forced dark mode is enabled (see [`Self::is_forced_dark`] for whether it applies)
+    pub fn forced_dark(&self) -> bool {
+        self.forced_dark
+    }
+
+    /// Darken pages which have no dark styles of their own when the user prefers dark mode
+    pub fn set_forced_dark(&mut self, enabled: bool) {
+        self.forced_dark = enabled;
+        self.shell_provider.request_redraw();
+    }
+
+    /// Whether the document is painted darkened: forced dark mode is enabled, the user prefers
+    /// dark mode, and the page neither supports it nor has opted out of being darkened
+    pub fn is_forced_dark(&self) -> bool {
+        let support = self.color_scheme_support;
+        self.forced_dark
+            && self.viewport.color_scheme == ColorScheme::Dark
+            && !support.dark
+            && !support.only
+            && !self.has_dark_styles
+    }
+
+    /// Check whether any author stylesheet has `prefers-color-scheme: dark` rules again, after
+    /// one was added or removed
+    pub(crate) fn update_has_dark_styles(&mut self) {
+        let guard = self.guard.read();
+        self.has_dark_styles = self.nodes_to_stylesheet.values().any(|sheet| {
+            sheet.0.rules(&guard).iter().any(|rule| match rule {
+                CssRule::Media(rule) => rule
+                    .media_queries
+                    .read_with(&guard)
+                    .to_css_string()
+                    .contains("prefers-color-scheme: dark"),
+                _ => false,
+            })
+        });
+    }
+
+    /// Read the document's color scheme meta tag again, after one may have been changed
+    pub(crate) fn update_color_scheme_meta(&mut self) {
+        let support = TreeTraverser::new(self)
+            .filter_map(|node_id| self.nodes[node_id].element_data())
+            .find(|element| {
+                element.name.local == local_name!("meta")
+                    && element
+                        .attr(local_name!("name"))
+                        .is_some_and(|name| name.eq_ignore_ascii_case("color-scheme"))
+            })
+            .map(|element| {
+                ColorSchemeSupport::parse(element.attr(local_name!("content")).unwrap_or_default())
+            })
+            .unwrap_or_default();
+        self.color_scheme_meta = support;
+        self.update_color_scheme_support();
+    }
+
+    /// Read the root element's `color-scheme` again, after it was restyled. Returns whether the
+    /// used color scheme changed, in which case the document needs restyling for system colors
+    /// to follow it.
+    pub(crate) fn update_color_scheme_property(&mut self) -> bool {
+        let root = self.root_element();
+        let value = root.primary_styles().and_then(|styles| {
+            custom_property(&self.stylist, &styles, "blitz-color-scheme")
+        });
+        let support = value.map(|value| ColorSchemeSupport::parse(&value));
+        if support == self.color_scheme_property {
+            return false;
+        }
+        self.color_scheme_property = support;
+        let used = self.used_color_scheme;
+        self.update_color_scheme_support();
+        self.used_color_scheme != used
+    }
+
+    /// Combine the meta tag and the root element's `color-scheme`, which wins unless it's `normal`
+    fn update_color_scheme_support(&mut self) {
+        self.color_scheme_support = self
+            .color_scheme_property
+            .filter(|support| !support.is_normal())
+            .unwrap_or(self.color_scheme_meta);
+        self.update_used_color_scheme();
+    }
+
+    /// Switch system colors to the palette of the used color scheme, if it changed
+    pub(crate) fn update_used_color_scheme(&mut self) {
+        let used = self.color_scheme_support.used(self.viewport.color_scheme);
+        if used == self.used_color_scheme {
+            return;
+        }
+        self.remove_user_agent_stylesheet(&self.system_colors.to_stylesheet(self.used_color_scheme));
+        self.used_color_scheme = used;
+        self.add_user_agent_stylesheet(&self.system_colors.to_stylesheet(used));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blitz_traits::shell::Viewport;
+
+    use super::*;
+    use crate::testing::{append_styled, document_with_viewport};
+
+    #[test]
+    fn used_color_scheme() {
+        let normal = ColorSchemeSupport::parse("");
+        assert_eq!(normal.used(ColorScheme::Dark), ColorScheme::Light);
+
+        let both = ColorSchemeSupport::parse("light dark");
+        assert_eq!(both.used(ColorScheme::Dark), ColorScheme::Dark);
+        assert_eq!(both.used(ColorScheme::Light), ColorScheme::Light);
+
+        let dark = ColorSchemeSupport::parse("only dark");
+        assert!(dark.only);
+        assert_eq!(dark.used(ColorScheme::Light), ColorScheme::Dark);
+    }
+
+    /// A document with an empty body, styled by `css`, for a user who prefers dark mode
+    fn dark_mode_document(css: &str) -> BaseDocument {
+        let mut doc = document_with_viewport(Viewport::new(100, 100, 1.0, ColorScheme::Dark));
+        doc.add_user_agent_stylesheet(css);
+        let mut mutr = doc.mutate();
+        let html = append_styled(&mut mutr, 0, "html", "");
+        append_styled(&mut mutr, html, "body", "");
+        drop(mutr);
+        doc.resolve();
+        doc
+    }
+
+    #[test]
+    fn the_root_elements_color_scheme_is_used() {
+        let doc = dark_mode_document(":root { color-scheme: light dark }");
+        assert_eq!(doc.used_color_scheme(), ColorScheme::Dark);
+        assert!(doc.color_scheme_support().dark);
+
+        // Other elements don't change the document's scheme
+        let doc = dark_mode_document("body { color-scheme: dark }");
+        assert_eq!(doc.used_color_scheme(), ColorScheme::Light);
+    }
+}
@@ -2,11 +2,14 @@ use std::sync::Arc;
 
 
 use blitz_traits::{
+    input::InputProvider,
     navigation::NavigationProvider,
     net::NetProvider,
+    script::ScriptProvider,
     shell::{ShellProvider, Viewport},
 };
 
+use crate::{BaseDocument, SystemColorTheme};
 use crate::net::Resource;
 
 /// Options used when constructing a [`BaseDocument`](crate::BaseDocument)
@@ -18,12 +21,18 @@ pub struct DocumentConfig {
     pub base_url: Option<String>,
     /// User Agent stylesheets
     pub ua_stylesheets: Option<Vec<String>>,
+    /// The palettes CSS system colors (`Canvas`, `ButtonFace`, ...) resolve to
+    pub system_colors: Option<SystemColorTheme>,
     /// Net provider to handle network requests for resources
     pub net_provider: Option<Arc<dyn NetProvider<Resource>>>,
     /// Navigation provider to handle link clicks and form submissions
     pub navigation_provider: Option<Arc<dyn NavigationProvider>>,
     /// Shell provider to redraw requests, clipboard, etc
     pub shell_provider: Option<Arc<dyn ShellProvider>>,
+    /// Input provider for devices like gamepads
+    pub input_provider: Option<Arc<dyn InputProvider>>,
+    /// Script provider to run `<script>`s with a JavaScript engine
+    pub script_provider: Option<Arc<dyn ScriptProvider<BaseDocument>>>,
     // text_system is now managed internally by BaseDocument - no longer in config
 }
 
@@ -33,6 +42,7 @@ impl DocumentConfig {
     pub fn for_testing() -> Self {
         Self {
             viewport: Some(blitz_traits::shell::Viewport::default()),
+            net_provider: Some(Arc::new(blitz_traits::net::DummyNetProvider)),
             shell_provider: Some(std::sync::Arc::new(
                 blitz_traits::shell::DummyShellProvider
             )),
@@ -9,12 +9,14 @@ use std::task::Context as TaskContext;
 
 use app_units::Au;
 // Blitz text system imports for font metrics
-use blitz_text::measurement::enhanced::font_metrics::FontMetricsCalculator;
-use blitz_text::{ensure_embedded_fallback, Family, FontSystem, Stretch, Style as FontStyle, Weight, fontdb};
+use blitz_text::Edit as _;
+use blitz_traits::blob::BlobRegistry;
 use blitz_traits::devtools::DevtoolSettings;
-use blitz_traits::events::{DomEvent, HitResult, UiEvent};
+use blitz_traits::events::{DomEvent, GamepadAxis, HitResult, UiEvent};
+use blitz_traits::input::{DummyInputProvider, InputProvider};
 use blitz_traits::navigation::NavigationProvider;
-use blitz_traits::net::{NetProvider, SharedProvider};
+use blitz_traits::net::{Bytes, NetProvider, Request, RequestPriority, SharedProvider};
+use blitz_traits::script::{DummyScriptProvider, ScriptProvider};
 use blitz_traits::shell::{ColorScheme, ShellProvider, Viewport};
 use cursor_icon::CursorIcon;
 use markup5ever::local_name;
@@ -23,6 +25,7 @@ use peniko::kurbo;
 use selectors::{Element, matching::QuirksMode};
 use slab::Slab;
 use style::Atom;
+use style::animation::DocumentAnimationSet;
 use style::attr::{AttrIdentifier, AttrValue};
 use style::data::{ElementData as StyloElementData, ElementStyles};
 use style::media_queries::MediaType;
@@ -43,18 +46,46 @@ use style::{
     stylesheets::{AllowImportRules, DocumentStyleSheet, Origin, Stylesheet},
     stylist::Stylist,
 };
+use stylo_taffy::GridContext;
 use taffy::AvailableSpace;
 use url::Url;
 
-use crate::events::handle_dom_event;
+use crate::background_attachment::BACKGROUND_ATTACHMENT_STYLESHEET;
+use crate::color_scheme::ColorSchemeSupport;
+use crate::emulation::{DeviceEmulation, ViewportMeta};
+use crate::events::{PointerCaptures, handle_dom_event};
+use crate::frame::FrameScheduler;
+use crate::idle::IdleScheduler;
+use crate::image_decode::ImageDecode;
 use crate::layout::construct::collect_layout_children;
+#[cfg(feature = "svg")]
+use crate::layout::construct::refresh_inline_svgs;
+use crate::layout::container_queries::MAX_CONTAINER_QUERY_PASSES;
+use crate::layout::generated_content::GeneratedContent;
+use crate::layout::grid_contributions::GridContributionCache;
+use crate::layout::masonry::auto_flow::MASONRY_STYLESHEET;
 use crate::mutator::ViewportMut;
-use crate::net::{Resource, StylesheetLoader};
-use crate::node::{ImageData, NodeFlags, RasterImageData, SpecialElementData, Status};
+use crate::font_face_set::FontFaceSet;
+use crate::journal::MutationJournal;
+use crate::lifecycle::{
+    DocumentEvent, DocumentEventListener, DocumentLifecycle, DocumentVisibility, LifecycleListener,
+    PreloadHandler, TrackedNetProvider,
+};
+use crate::media::{MediaListener, MediaPreferences, PreferenceDependentSheets};
+use crate::metadata::DocumentIcon;
+use crate::navigation::BlitzNavigationProvider;
+use crate::net::{Resource, StylesheetLoader, fetch_font_face};
+use crate::node::{ImageData, NodeFlags, SpecialElementData, Status};
+use crate::scrollbar::{
+    SCROLLBAR_STYLESHEET, ScrollbarAxis, ScrollbarMode, ScrollbarOwner, ScrollbarPress,
+};
+use crate::spatial_navigation::SpatialDirection;
 use crate::stylo_to_cursor_icon::stylo_to_cursor_icon;
+use crate::system_colors::{SystemColor, SystemColorTheme};
 use crate::traversal::TreeTraverser;
-use crate::url::DocumentUrl;
-use crate::util::ImageType;
+use crate::url::{CustomSchemes, DocumentUrl};
+use crate::util::{Color, ImageType};
+use crate::visual_viewport::VisualViewport;
 use crate::{
     DEFAULT_CSS, DocumentConfig, DocumentMutator, ElementData, EventDriver, Node, NodeData,
     NoopEventHandler, TextNodeData,
@@ -78,171 +109,54 @@ pub trait Document: Deref<Target = BaseDocument> + DerefMut + 'static {
 
     fn as_any_mut(&mut self) -> &mut dyn Any;
 
+    /// Whether the document is still being parsed, so that it shouldn't become
+    /// [`Interactive`](DocumentLifecycle::Interactive) yet. Documents built all at once aren't,
+    /// and are made interactive by the shell showing them if they didn't do so themselves.
+    fn is_parsing(&self) -> bool {
+        false
+    }
+
     /// Get the [`Document`]'s id
     fn id(&self) -> usize {
         self.id
     }
 }
 
-/// Font metrics provider using blitz-text integration for real font metrics
-///
-/// # Graceful Degradation
-/// - Primary: Uses goldylox-based FontMetricsCalculator for optimal performance
-/// - Fallback: Falls back to HashMap-based caching if goldylox initialization fails
-/// - Last Resort: Loads embedded BlitzFallback font to ensure basic text rendering works
+/// Font metrics for resolving font-relative units (like `ex` and `ch`)
 ///
-/// This ensures text measurement always succeeds, even in constrained environments
-/// like headless rendering, missing system fonts, or goldylox cache failures.
-struct BlitzFontMetricsProvider {
-    font_metrics_calculator: FontMetricsCalculator,
-    font_system: std::sync::Mutex<FontSystem>,
-}
-
-impl std::fmt::Debug for BlitzFontMetricsProvider {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("BlitzFontMetricsProvider").finish()
-    }
-}
-
-impl BlitzFontMetricsProvider {
-    async fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let font_metrics_calculator = FontMetricsCalculator::new().await?;
-        let font_system = FontSystem::new_with_fonts(std::iter::empty());
-        Ok(Self {
-            font_metrics_calculator,
-            font_system: std::sync::Mutex::new(font_system),
-        })
-    }
-}
-
-impl Default for BlitzFontMetricsProvider {
-    fn default() -> Self {
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| {
-                    // Fallback: Use FontMetricsCalculator default which has HashMap fallback
-                    // and create a minimal FontSystem with embedded fallback font
-                    let font_metrics_calculator = FontMetricsCalculator::default();
-                    let mut font_system = FontSystem::new_with_fonts(std::iter::empty());
-                    
-                    // Ensure embedded fallback font is loaded for basic text rendering
-                    let _ = ensure_embedded_fallback(&mut font_system);
-                    
-                    Self {
-                        font_metrics_calculator,
-                        font_system: std::sync::Mutex::new(font_system),
-                    }
-                })
-            })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| {
-                        let font_metrics_calculator = FontMetricsCalculator::default();
-                        let mut font_system = FontSystem::new_with_fonts(std::iter::empty());
-                        let _ = ensure_embedded_fallback(&mut font_system);
-                        
-                        Self {
-                            font_metrics_calculator,
-                            font_system: std::sync::Mutex::new(font_system),
-                        }
-                    })
-                })
-        }
-    }
-}
+/// Metrics are read from the first font matching an element's font style, in the calling
+/// thread's font system. Metrics the font doesn't have (or all of them, if no font matches) fall
+/// back to proportions of the font size.
+#[derive(Debug, Default)]
+struct BlitzFontMetricsProvider;
 
 impl FontMetricsProvider for BlitzFontMetricsProvider {
     fn query_font_metrics(
         &self,
-        _vertical: bool,
-        _font: &Font,
+        vertical: bool,
+        font: &Font,
         base_size: style::values::computed::CSSPixelLength,
         _flags: style::values::computed::font::QueryFontMetricsFlags,
     ) -> style::font_metrics::FontMetrics {
-        // Extract font size in pixels
         let font_size = base_size.px();
-
-        // Query for a proper fontdb::ID using the font information
-        let font_id = if let Ok(font_system) = self.font_system.lock() {
-            // Create a font query from the CSS Font struct
-            let query = fontdb::Query {
-                families: &[
-                    // Try to use generic families as fallback
-                    Family::SansSerif,
-                ],
-                weight: Weight::NORMAL,
-                stretch: Stretch::Normal,
-                style: FontStyle::Normal,
-            };
-
-            font_system.db().query(&query)
-        } else {
-            None
+        let attrs = crate::layout::stylo_to_blitz::font_attrs(font);
+        let metrics = blitz_text::measurement::with_font_system(|font_system| {
+            blitz_text::FontUnitMetrics::for_attrs(font_system, &attrs, font_size, vertical)
+        })
+        .ok()
+        .flatten();
+        let metric = |measure: fn(&blitz_text::FontUnitMetrics) -> Option<f32>, ratio: f32| {
+            let px = metrics.as_ref().and_then(measure).unwrap_or(font_size * ratio);
+            CSSPixelLength::new(px)
         };
-
-        // Query blitz-text font metrics calculator if we have a valid font ID
-        if let Some(font_id) = font_id {
-            // Use tokio runtime to call async calculate method
-            use tokio::runtime::Handle;
-            let blitz_metrics_opt = if let Ok(handle) = Handle::try_current() {
-                handle.block_on(async {
-                    self.font_metrics_calculator.calculate(font_id, font_size).await
-                })
-            } else {
-                tokio::runtime::Runtime::new()
-                    .expect("Failed to create tokio runtime")
-                    .block_on(async {
-                        self.font_metrics_calculator.calculate(font_id, font_size).await
-                    })
-            };
-            
-            if let Some(blitz_metrics) = blitz_metrics_opt
-            {
-                // Convert blitz-text FontMetrics to style::font_metrics::FontMetrics
-                style::font_metrics::FontMetrics {
-                    x_height: blitz_metrics
-                        .x_height
-                        .map(|x| CSSPixelLength::new(x as f32)),
-                    zero_advance_measure: Some(CSSPixelLength::new(
-                        blitz_metrics.average_char_width,
-                    )),
-                    ic_width: Some(CSSPixelLength::new(blitz_metrics.average_char_width)), /* Use average for ideographic */
-                    cap_height: blitz_metrics
-                        .cap_height
-                        .map(|c| CSSPixelLength::new(c as f32)),
-                    ascent: CSSPixelLength::new(blitz_metrics.ascent as f32),
-                    script_percent_scale_down: None,
-                    script_script_percent_scale_down: None,
-                }
-            } else {
-                // Fallback if blitz-text calculation fails
-                style::font_metrics::FontMetrics {
-                    x_height: Some(CSSPixelLength::new(font_size * 0.5)),
-                    zero_advance_measure: Some(CSSPixelLength::new(font_size * 0.6)),
-                    ic_width: Some(CSSPixelLength::new(font_size * 0.6)),
-                    cap_height: Some(CSSPixelLength::new(font_size * 0.7)),
-                    ascent: CSSPixelLength::new(font_size * 0.8),
-                    script_percent_scale_down: None,
-                    script_script_percent_scale_down: None,
-                }
-            }
-        } else {
-            // Fallback to computed values if blitz-text calculation fails
-            style::font_metrics::FontMetrics {
-                x_height: Some(CSSPixelLength::new(font_size * 0.5)),
-                zero_advance_measure: Some(CSSPixelLength::new(font_size * 0.6)),
-                ic_width: Some(CSSPixelLength::new(font_size * 0.6)),
-                cap_height: Some(CSSPixelLength::new(font_size * 0.7)),
-                ascent: CSSPixelLength::new(font_size * 0.8),
-                script_percent_scale_down: None,
-                script_script_percent_scale_down: None,
-            }
+        style::font_metrics::FontMetrics {
+            x_height: Some(metric(|metrics| metrics.x_height, 0.5)),
+            zero_advance_measure: Some(metric(|metrics| metrics.zero_advance, 0.6)),
+            ic_width: Some(metric(|metrics| metrics.ideographic_advance, 0.6)),
+            cap_height: Some(metric(|metrics| metrics.cap_height, 0.7)),
+            ascent: metric(|metrics| Some(metrics.ascent), 0.8),
+            script_percent_scale_down: None,
+            script_script_percent_scale_down: None,
         }
     }
 
@@ -271,6 +185,14 @@ pub struct BaseDocument {
     pub(crate) viewport: Viewport,
     // Scroll within our viewport
     pub(crate) viewport_scroll: kurbo::Point,
+    /// Pinch-zoom, which magnifies the laid out document without laying it out again
+    pub(crate) visual_viewport: VisualViewport,
+    /// The device the document is laid out for, if not the window it's shown in
+    pub(crate) device_emulation: Option<DeviceEmulation>,
+    /// The viewport set before a device was emulated
+    pub(crate) unemulated_viewport: Option<Viewport>,
+    /// The document's `<meta name="viewport">`, honored when emulating a mobile device
+    pub(crate) viewport_meta: Option<ViewportMeta>,
 
     /// A slab-backed tree of nodes
     ///
@@ -298,8 +220,25 @@ pub struct BaseDocument {
     pub(crate) active_node_id: Option<usize>,
     /// The node which recieved a mousedown event (if any)
     pub(crate) mousedown_node_id: Option<usize>,
+    /// Whether scrollbars are overlay or classic scrollbars
+    pub(crate) scrollbar_mode: ScrollbarMode,
+    /// The scrollbar the mouse is over (if any)
+    pub(crate) hovered_scrollbar: Option<(ScrollbarOwner, ScrollbarAxis)>,
+    /// The scrollbar the main mouse button is pressed on (if any)
+    pub(crate) scrollbar_press: Option<ScrollbarPress>,
     /// Whether there are active animations (so we should re-render every frame)
     pub(crate) is_animating: bool,
+    /// Whether animations are suppressed (e.g. on displays with slow refresh such as e-paper)
+    pub(crate) animations_suppressed: bool,
+    /// Animation frame callbacks and frame timing, see [`crate::frame`]
+    pub(crate) frame_scheduler: FrameScheduler,
+    /// The CSS animations and transitions of each element, which stylo starts while styling and
+    /// the frame clock advances
+    pub(crate) animations: DocumentAnimationSet,
+    /// Callbacks waiting for the document to be idle, see [`crate::idle`]
+    pub(crate) idle_scheduler: IdleScheduler,
+    /// The `blob:` URLs created by the document, which are revoked when it's dropped
+    pub(crate) object_urls: Vec<Url>,
 
     /// Map of node ID's for fast lookups
     pub(crate) nodes_to_id: HashMap<String, usize>,
@@ -308,10 +247,75 @@ pub struct BaseDocument {
     /// Stylesheets added by the useragent
     /// where the key is the hashed CSS
     pub(crate) ua_stylesheets: HashMap<String, DocumentStyleSheet>,
+    /// List markers and pseudo-element text, from the last evaluation of the document's counters
+    pub(crate) generated_content: GeneratedContent,
+    /// Whether children were added or removed since counters were last evaluated
+    pub(crate) generated_content_dirty: bool,
+    /// Sizes of grid items measured during the current layout pass
+    pub(crate) grid_contributions: GridContributionCache,
+    /// The grid context of each grid container's children, from the last style flush
+    pub(crate) grid_child_contexts: HashMap<usize, Arc<GridContext>>,
+    /// The items of each masonry container in reading order, from its last layout
+    pub(crate) masonry_reading_orders: HashMap<usize, Vec<usize>>,
+    /// The layout trace being recorded, see [`crate::layout::trace`]
+    #[cfg(feature = "layout-trace")]
+    pub(crate) layout_trace: Option<crate::layout::trace::LayoutTraceRecorder>,
+    /// The palettes CSS system colors resolve to
+    pub(crate) system_colors: SystemColorTheme,
+    /// The document's title, from its `<title>`
+    pub(crate) title: String,
+    /// The `<link rel="icon">` the document's icon is fetched from, with the icon's URL
+    pub(crate) icon_link: Option<(usize, Url)>,
+    /// The document's icon, once it's been fetched
+    pub(crate) icon: Option<DocumentIcon>,
+    /// The document's theme color, from its `<meta name="theme-color">`
+    pub(crate) theme_color: Option<Color>,
+    /// The color schemes the page supports, from its meta tag or its root element's
+    /// `color-scheme`
+    pub(crate) color_scheme_support: ColorSchemeSupport,
+    /// The color schemes declared by the page's `<meta name="color-scheme">`
+    pub(crate) color_scheme_meta: ColorSchemeSupport,
+    /// The color schemes declared by the root element's `color-scheme`, once it's been styled
+    pub(crate) color_scheme_property: Option<ColorSchemeSupport>,
+    /// The color scheme the page is rendered in, which system colors resolve to
+    pub(crate) used_color_scheme: ColorScheme,
+    /// Whether pages without dark styles are darkened when the user prefers dark mode
+    pub(crate) forced_dark: bool,
+    /// Whether any author stylesheet has `prefers-color-scheme: dark` rules
+    pub(crate) has_dark_styles: bool,
+    /// Inline `<svg>` elements, with a fingerprint of the colors they were parsed with
+    #[cfg(feature = "svg")]
+    pub(crate) inline_svg_colors: HashMap<usize, u64>,
+    /// Loading status of the fonts declared by `@font-face` rules
+    pub(crate) font_faces: FontFaceSet,
     /// Map from form control node ID's to their associated forms node ID's
     pub(crate) controls_to_form: HashMap<usize, usize>,
     /// Set of changed nodes for updating the accessibility tree
     pub(crate) changed_nodes: HashSet<usize>,
+    /// Where the document is in its lifecycle
+    pub(crate) lifecycle: DocumentLifecycle,
+    pub(crate) lifecycle_listeners: Vec<LifecycleListener>,
+    pub(crate) document_event_listeners: Vec<DocumentEventListener>,
+    /// Whether the document can be seen
+    pub(crate) visibility: DocumentVisibility,
+    /// Whether the document has been painted yet
+    pub(crate) has_painted: bool,
+    /// Which elements pointers are captured by
+    pub(crate) pointer_captures: PointerCaptures,
+    /// The direction each gamepad stick axis is pushed in, if it's pushed far enough to navigate
+    pub(crate) gamepad_stick_directions: HashMap<(u64, GamepadAxis), SpatialDirection>,
+    /// Called when the media environment changes
+    pub(crate) media_listeners: Vec<MediaListener>,
+    /// Stylesheets which are parsed again when the user preferences `@media` rules match on change
+    pub(crate) preference_sheets: PreferenceDependentSheets,
+    /// The number of requests made through `net_provider` which are in flight
+    pub(crate) pending_requests: Arc<AtomicUsize>,
+    /// The number of preloads (`<link rel=preload>` and `<link rel=prefetch>`) which are in flight
+    pub(crate) pending_preloads: Arc<AtomicUsize>,
+    /// The decodes of the images of `<img>` elements, which follow their layout
+    pub(crate) image_decodes: HashMap<usize, Arc<ImageDecode>>,
+    /// Undo history of mutations made through [`DocumentMutator`] (if enabled)
+    pub(crate) journal: Option<MutationJournal>,
 
     // Service providers
     /// Network provider. Can be used to fetch assets.
@@ -321,6 +325,10 @@ pub struct BaseDocument {
     pub navigation_provider: Arc<dyn NavigationProvider>,
     /// Shell provider. Can be used to request a redraw or set the cursor icon
     pub shell_provider: Arc<dyn ShellProvider>,
+    /// Input provider. Shells poll it for events from devices like gamepads
+    pub input_provider: Arc<dyn InputProvider>,
+    /// Script provider. Runs the document's `<script>`s, if an engine is plugged in
+    pub script_provider: Arc<dyn ScriptProvider<BaseDocument>>,
 }
 
 pub(crate) fn make_device(viewport: &Viewport, quirks_mode: QuirksMode) -> Device {
@@ -329,12 +337,16 @@ pub(crate) fn make_device(viewport: &Viewport, quirks_mode: QuirksMode) -> Devic
     let viewport_size = euclid::Size2D::new(width, height);
     let device_pixel_ratio = euclid::Scale::new(viewport.scale());
 
+    let media_type = match viewport.media_type {
+        blitz_traits::shell::MediaType::Screen => MediaType::screen(),
+        blitz_traits::shell::MediaType::Print => MediaType::print(),
+    };
     Device::new(
-        MediaType::screen(),
+        media_type,
         quirks_mode,
         viewport_size,
         device_pixel_ratio,
-        Box::new(BlitzFontMetricsProvider::default()),
+        Box::new(BlitzFontMetricsProvider),
         ComputedValues::initial_values_with_font_override(Font::initial_values()),
         match viewport.color_scheme {
             ColorScheme::Light => PrefersColorScheme::Light,
@@ -351,6 +363,7 @@ impl BaseDocument {
         let id = ID_GENERATOR.fetch_add(1, Ordering::SeqCst);
         let viewport = config.viewport.unwrap_or_default();
         let device = make_device(&viewport, QuirksMode::NoQuirks);
+        let preference_sheets = PreferenceDependentSheets::new(MediaPreferences::of(&viewport));
         let stylist = Stylist::new(device, QuirksMode::NoQuirks);
         let snapshots = SnapshotMap::new();
         let quirks_mode = Cell::new(QuirksMode::NoQuirks);
@@ -364,6 +377,7 @@ impl BaseDocument {
         style_config::set_bool("layout.legacy_layout", true);
         style_config::set_bool("layout.unimplemented", true);
         style_config::set_bool("layout.columns.enabled", true);
+        style_config::set_bool("layout.container_queries.enabled", true);
 
         let base_url = config
             .base_url
@@ -372,15 +386,27 @@ impl BaseDocument {
 
 
 
-        let net_provider = config
-            .net_provider
-            .ok_or("NetProvider is required for production use")?;
-        let navigation_provider = config
-            .navigation_provider
-            .unwrap_or_else(|| Arc::new(crate::navigation::BlitzNavigationProvider::default()));
+        let pending_requests = Arc::new(AtomicUsize::new(0));
+        let net_provider: Arc<dyn NetProvider<Resource>> = Arc::new(TrackedNetProvider::new(
+            config
+                .net_provider
+                .ok_or("NetProvider is required for production use")?,
+            pending_requests.clone(),
+        ));
+        let custom_schemes = CustomSchemes::of(net_provider.clone());
+        let base_url = base_url.with_custom_schemes(custom_schemes.clone());
+        let navigation_provider = config.navigation_provider.unwrap_or_else(|| {
+            Arc::new(BlitzNavigationProvider::default().with_custom_schemes(custom_schemes))
+        });
         let shell_provider = config
             .shell_provider
             .ok_or("ShellProvider is required for production use")?;
+        let input_provider = config
+            .input_provider
+            .unwrap_or_else(|| Arc::new(DummyInputProvider));
+        let script_provider = config
+            .script_provider
+            .unwrap_or_else(|| Arc::new(DummyScriptProvider));
 
         let mut doc = Self {
             id,
@@ -393,20 +419,68 @@ impl BaseDocument {
             viewport,
             devtool_settings: DevtoolSettings::default(),
             viewport_scroll: kurbo::Point::ZERO,
+            visual_viewport: VisualViewport::default(),
+            device_emulation: None,
+            unemulated_viewport: None,
+            viewport_meta: None,
             url: base_url,
             ua_stylesheets: HashMap::new(),
+            generated_content: GeneratedContent::default(),
+            generated_content_dirty: true,
+            grid_contributions: GridContributionCache::default(),
+            grid_child_contexts: HashMap::new(),
+            masonry_reading_orders: HashMap::new(),
+            #[cfg(feature = "layout-trace")]
+            layout_trace: None,
+            system_colors: config.system_colors.unwrap_or_default(),
+            title: String::new(),
+            icon_link: None,
+            icon: None,
+            theme_color: None,
+            color_scheme_support: ColorSchemeSupport::default(),
+            color_scheme_meta: ColorSchemeSupport::default(),
+            color_scheme_property: None,
+            used_color_scheme: ColorScheme::Light,
+            forced_dark: false,
+            has_dark_styles: false,
+            #[cfg(feature = "svg")]
+            inline_svg_colors: HashMap::new(),
             nodes_to_stylesheet: BTreeMap::new(),
+            font_faces: FontFaceSet::default(),
 
             hover_node_id: None,
             focus_node_id: None,
             active_node_id: None,
             mousedown_node_id: None,
+            scrollbar_mode: ScrollbarMode::default(),
+            hovered_scrollbar: None,
+            scrollbar_press: None,
             is_animating: false,
+            animations_suppressed: false,
+            frame_scheduler: FrameScheduler::default(),
+            animations: DocumentAnimationSet::default(),
+            idle_scheduler: IdleScheduler::default(),
+            object_urls: Vec::new(),
             changed_nodes: HashSet::new(),
             controls_to_form: HashMap::new(),
+            lifecycle: DocumentLifecycle::Loading,
+            lifecycle_listeners: Vec::new(),
+            document_event_listeners: Vec::new(),
+            visibility: DocumentVisibility::Visible,
+            has_painted: false,
+            pointer_captures: PointerCaptures::default(),
+            gamepad_stick_directions: HashMap::new(),
+            media_listeners: Vec::new(),
+            preference_sheets,
+            pending_requests,
+            pending_preloads: Arc::new(AtomicUsize::new(0)),
+            image_decodes: HashMap::new(),
+            journal: None,
             net_provider,
             navigation_provider,
             shell_provider,
+            input_provider,
+            script_provider,
         };
 
         // Initialise document with root Document node
@@ -421,6 +495,10 @@ impl BaseDocument {
             }
             None => doc.add_user_agent_stylesheet(DEFAULT_CSS),
         }
+        doc.add_user_agent_stylesheet(&doc.system_colors.to_stylesheet(doc.used_color_scheme));
+        doc.add_user_agent_stylesheet(SCROLLBAR_STYLESHEET);
+        doc.add_user_agent_stylesheet(MASONRY_STYLESHEET);
+        doc.add_user_agent_stylesheet(BACKGROUND_ATTACHMENT_STYLESHEET);
 
         // Stylo data on the root node container is needed to render the node
         let stylo_element_data = StyloElementData {
@@ -434,6 +512,7 @@ impl BaseDocument {
             ..Default::default()
         };
         *doc.root_node().stylo_element_data.borrow_mut() = Some(stylo_element_data);
+        doc.net_provider.set_document_url(doc.id, Some(&*doc.url));
 
         // Bullet font will be loaded when text_system is accessed via with_text_system()
 
@@ -442,7 +521,10 @@ impl BaseDocument {
 
     /// Set the Document's networking provider
     pub fn set_net_provider(&mut self, net_provider: SharedProvider<Resource>) {
-        self.net_provider = net_provider;
+        self.net_provider = Arc::new(TrackedNetProvider::new(
+            net_provider,
+            self.pending_requests.clone(),
+        ));
     }
 
     /// Set the Document's navigation provider
@@ -455,28 +537,66 @@ impl BaseDocument {
         self.shell_provider = shell_provider;
     }
 
+    /// Set the Document's input provider
+    pub fn set_input_provider(&mut self, input_provider: Arc<dyn InputProvider>) {
+        self.input_provider = input_provider;
+    }
+
+    /// Set the Document's script provider
+    pub fn set_script_provider(&mut self, script_provider: Arc<dyn ScriptProvider<BaseDocument>>) {
+        self.script_provider = script_provider;
+    }
+
+    /// Run the script provider's tasks which are ready, returning whether any ran
+    pub fn poll_scripts(&mut self, cx: &mut TaskContext<'_>) -> bool {
+        let script_provider = Arc::clone(&self.script_provider);
+        script_provider.poll_tasks(self, cx)
+    }
+
     /// Initialize the text system with GPU context
 
     /// Set base url for resolving linked resources (stylesheets, images, fonts, etc)
     pub fn set_base_url(&mut self, url: &str) {
-        match Url::parse(url) {
-            Ok(parsed_url) => {
-                self.url = DocumentUrl::from(parsed_url);
-            }
+        let url = match Url::parse(url) {
+            Ok(parsed_url) => DocumentUrl::from(parsed_url),
             Err(e) => {
                 eprintln!(
                     "Warning: Failed to parse URL '{}': {}. Using default URL.",
                     url, e
                 );
-                self.url = DocumentUrl::default();
+                DocumentUrl::default()
             }
-        }
+        };
+        let custom_schemes = CustomSchemes::of(self.net_provider.clone());
+        self.url = url.with_custom_schemes(custom_schemes);
+        self.net_provider.set_document_url(self.id, Some(&*self.url));
+    }
+
+    /// Give `bytes` (of the MIME type `content_type`) a `blob:` URL, which can be used wherever
+    /// the document loads resources from, until it's revoked or the document is dropped
+    pub fn create_object_url(&mut self, bytes: impl Into<Bytes>, content_type: &str) -> Url {
+        let registry = BlobRegistry::global();
+        let url = registry.create_object_url(Some(&*self.url), bytes, content_type);
+        self.object_urls.push(url.clone());
+        url
+    }
+
+    /// Stop `url` (a URL from [`create_object_url`](Self::create_object_url)) being loadable,
+    /// freeing its resource
+    pub fn revoke_object_url(&mut self, url: &Url) {
+        self.object_urls.retain(|object_url| object_url != url);
+        BlobRegistry::global().revoke_object_url(url);
     }
 
     pub fn guard(&self) -> &SharedRwLock {
         &self.guard
     }
 
+    /// The fonts declared by the document's `@font-face` rules and their loading status
+    pub fn fonts(&self) -> &FontFaceSet {
+        &self.font_faces
+    }
+
     pub fn tree(&self) -> &Slab<Node> {
         &self.nodes
     }
@@ -512,6 +632,71 @@ impl BaseDocument {
         self.id
     }
 
+    /// Where the document is in its lifecycle
+    pub fn lifecycle(&self) -> DocumentLifecycle {
+        self.lifecycle
+    }
+
+    /// Call `listener` every time the document moves to a new [`DocumentLifecycle`] state. The
+    /// listener is dropped along with the document (after being told that it is unloading).
+    pub fn add_lifecycle_listener(&mut self, listener: impl FnMut(DocumentLifecycle) + 'static) {
+        self.lifecycle_listeners.push(Box::new(listener));
+    }
+
+    /// Advance the document to `state`. Listeners are told about every state in between, in order.
+    /// States can't go backwards, so setting an earlier state than the current one does nothing.
+    pub fn set_lifecycle(&mut self, state: DocumentLifecycle) {
+        while self.lifecycle < state {
+            let Some(next) = self.lifecycle.next() else {
+                break;
+            };
+            self.lifecycle = next;
+            for listener in &mut self.lifecycle_listeners {
+                listener(next);
+            }
+            match next {
+                DocumentLifecycle::Interactive => {
+                    self.dispatch_document_event(DocumentEvent::DomContentLoaded)
+                }
+                DocumentLifecycle::Complete => self.dispatch_document_event(DocumentEvent::Load),
+                _ => {}
+            }
+        }
+    }
+
+    /// The number of requests for sub-resources which are in flight
+    pub fn pending_requests(&self) -> usize {
+        self.pending_requests.load(Ordering::SeqCst)
+    }
+
+    /// The number of preloads (`<link rel=preload>` and `<link rel=prefetch>`) which are in flight.
+    /// Preloads (but not prefetches) are counted in [`pending_requests`](Self::pending_requests)
+    /// too, so the document only becomes [`Complete`](DocumentLifecycle::Complete) once they have
+    /// loaded.
+    pub fn pending_preloads(&self) -> usize {
+        self.pending_preloads.load(Ordering::SeqCst)
+    }
+
+    /// Fetch `href` (relative to the document's base URL) ahead of the element which needs it, as
+    /// a `<link rel=preload>` does. The response answers the element's own request for it.
+    pub fn preload(&self, href: &str, priority: RequestPriority) {
+        let Some(url) = self.url.resolve_relative(href) else {
+            return;
+        };
+        self.net_provider.fetch(
+            self.id(),
+            Request::preload(url, priority),
+            Box::new(PreloadHandler::new(self.pending_preloads.clone())),
+        );
+    }
+
+    /// Move an interactive document to [`DocumentLifecycle::Complete`] once nothing is loading
+    fn check_load_complete(&mut self) {
+        if self.lifecycle == DocumentLifecycle::Interactive && self.pending_requests() == 0 {
+            self.set_lifecycle(DocumentLifecycle::Complete);
+        }
+    }
+
     pub fn get_node(&self, node_id: usize) -> Option<&Node> {
         self.nodes.get(node_id)
     }
@@ -540,7 +725,7 @@ impl BaseDocument {
 
     /// Safe access to text system - uses global singleton
     /// Returns an error if text system hasn't been initialized with GPU context
-    pub fn with_text_system<R>(&self, f: impl FnOnce(&blitz_text::UnifiedTextSystem) -> R) -> Result<R, &'static str> {
+    pub fn with_text_system<R>(&self, f: impl FnOnce(&crate::TextSystem) -> R) -> Result<R, &'static str> {
         // Use the singleton's safe access method
         // UnifiedTextSystem uses interior mutability so immutable reference is sufficient
         crate::TextSystemSingleton::with_text_system(f)
@@ -563,7 +748,7 @@ impl BaseDocument {
 
     /// Safe method to access both text system and nodes without borrow conflicts
     /// Returns an error if text system hasn't been initialized with GPU context
-    pub fn with_text_and_nodes<R>(&mut self, f: impl FnOnce(&blitz_text::UnifiedTextSystem, &mut Box<Slab<Node>>) -> R) -> Result<R, &'static str> {
+    pub fn with_text_and_nodes<R>(&mut self, f: impl FnOnce(&crate::TextSystem, &mut Box<Slab<Node>>) -> R) -> Result<R, &'static str> {
         // Use singleton for text system access
         crate::TextSystemSingleton::with_text_system(|text_system| {
             f(text_system, &mut self.nodes)
@@ -572,6 +757,7 @@ impl BaseDocument {
 
     /// Initialize text system with GPU context - must be called before using text system methods
     /// This replaces the removed headless initialization pattern with proper GPU context usage
+    #[cfg(feature = "gpu")]
     pub async fn initialize_text_system_with_gpu_context(
         &self,
         device: &wgpu::Device,
@@ -607,12 +793,16 @@ impl BaseDocument {
         DocumentMutator::new(self)
     }
 
+    /// Run the default action of `event`, unless `default_prevented`. Events which follow from it
+    /// (like the `click` after a `mouseup`, which follows even if the `mouseup` was cancelled) are
+    /// passed to `dispatch_event`.
     pub fn handle_dom_event<F: FnMut(DomEvent)>(
         &mut self,
         event: &mut DomEvent,
+        default_prevented: bool,
         dispatch_event: F,
     ) {
-        handle_dom_event(self, event, dispatch_event)
+        handle_dom_event(self, event, default_prevented, dispatch_event)
     }
 
     pub fn as_any_mut(&mut self) -> &mut dyn Any {
@@ -729,6 +919,23 @@ impl BaseDocument {
         id
     }
 
+    /// Create a node under `id`, which must be vacant (to restore a node which was dropped)
+    pub(crate) fn create_node_with_id(&mut self, id: usize, node_data: NodeData) {
+        let slab_ptr = self.nodes.as_mut() as *mut Slab<Node>;
+        let guard = self.guard.clone();
+        let quirks_mode = self.quirks_mode.get();
+        let node = Node::new(slab_ptr, id, guard, quirks_mode, node_data);
+
+        if self.nodes.vacant_key() == id {
+            self.nodes.insert(node);
+        } else {
+            // Slabs can only be given the keys of their entries when they're built
+            let nodes = std::mem::take(self.nodes.as_mut());
+            *self.nodes = nodes.into_iter().chain([(id, node)]).collect();
+        }
+        self.changed_nodes.insert(id);
+    }
+
     /// Whether the document has been mutated
     pub fn has_changes(&self) -> bool {
         self.changed_nodes.is_empty()
@@ -765,6 +972,7 @@ impl BaseDocument {
     pub(crate) fn remove_and_drop_pe(&mut self, node_id: usize) -> Option<Node> {
         fn remove_pe_ignoring_parent(doc: &mut BaseDocument, node_id: usize) -> Option<Node> {
             let mut node = doc.nodes.try_remove(node_id);
+            doc.grid_child_contexts.remove(&node_id);
             if let Some(node) = &mut node {
                 for &child in &node.children {
                     remove_pe_ignoring_parent(doc, child);
@@ -824,24 +1032,45 @@ impl BaseDocument {
     }
 
     pub fn make_stylesheet(&self, css: impl AsRef<str>, origin: Origin) -> DocumentStyleSheet {
-        let data = Stylesheet::from_str(
-            css.as_ref(),
-            self.url.url_extra_data(),
-            origin,
-            ServoArc::new(self.guard.wrap(MediaList::empty())),
-            self.guard.clone(),
-            Some(&StylesheetLoader(self.id, self.net_provider.clone())),
-            None,
-            self.quirks_mode.get(),
-            AllowImportRules::Yes,
-        );
+        let url_data = self.url.url_extra_data();
+        let loader = self.stylesheet_loader();
+        let sheet = self.preference_sheets.parse(css.as_ref(), &url_data, |css| {
+            ServoArc::new(Stylesheet::from_str(
+                css,
+                url_data.clone(),
+                origin,
+                ServoArc::new(self.guard.wrap(MediaList::empty())),
+                self.guard.clone(),
+                Some(&loader),
+                None,
+                self.quirks_mode.get(),
+                AllowImportRules::Yes,
+            ))
+        });
+
+        DocumentStyleSheet(sheet)
+    }
 
-        DocumentStyleSheet(ServoArc::new(data))
+    /// Loads the imports of the document's stylesheets
+    pub(crate) fn stylesheet_loader(&self) -> StylesheetLoader {
+        StylesheetLoader(
+            self.id,
+            self.net_provider.clone(),
+            self.font_faces.clone(),
+            self.preference_sheets.clone(),
+        )
     }
 
     pub fn upsert_stylesheet_for_node(&mut self, node_id: usize) {
         let raw_styles = self.nodes[node_id].text_content();
         let sheet = self.make_stylesheet(raw_styles, Origin::Author);
+        fetch_font_face(
+            self.id,
+            &sheet.0,
+            &self.net_provider,
+            &self.font_faces,
+            &self.guard.read(),
+        );
         self.add_stylesheet_for_node(sheet, node_id);
     }
 
@@ -851,6 +1080,7 @@ impl BaseDocument {
         if let Some(old) = old {
             self.stylist.remove_stylesheet(old, &self.guard.read())
         }
+        self.update_has_dark_styles();
 
         // Store data on element
         let element = match self
@@ -958,11 +1188,23 @@ impl BaseDocument {
     }
 
     pub fn load_resource(&mut self, resource: Resource) {
+        self.apply_resource(resource);
+        self.check_load_complete();
+    }
+
+    fn apply_resource(&mut self, resource: Resource) {
         match resource {
             Resource::Css(node_id, css) => {
                 self.add_stylesheet_for_node(css, node_id);
             }
-            Resource::Image(node_id, kind, width, height, image_data) => {
+            Resource::Image(node_id, ImageType::Icon, image) => {
+                self.set_icon(node_id, image);
+            }
+            #[cfg(feature = "svg")]
+            Resource::Svg(node_id, ImageType::Icon, tree) => {
+                self.set_svg_icon(node_id, tree);
+            }
+            Resource::Image(node_id, kind, image) => {
                 let node = match self.get_node_mut(node_id) {
                     Some(node) => node,
                     None => {
@@ -986,9 +1228,8 @@ impl BaseDocument {
                                 return;
                             }
                         };
-                        element_data.special_data = SpecialElementData::Image(Box::new(
-                            ImageData::Raster(RasterImageData::new(width, height, image_data)),
-                        ));
+                        element_data.special_data =
+                            SpecialElementData::Image(Box::new(ImageData::Raster(image)));
 
                         // Clear layout cache
                         node.cache.clear();
@@ -999,10 +1240,10 @@ impl BaseDocument {
                             .and_then(|el| el.background_images.get_mut(idx))
                         {
                             bg_image.status = Status::Ok;
-                            bg_image.image =
-                                ImageData::Raster(RasterImageData::new(width, height, image_data))
+                            bg_image.image = ImageData::Raster(image)
                         }
                     }
+                    ImageType::Icon => {} // Handled above
                 }
             }
             #[cfg(feature = "svg")]
@@ -1045,15 +1286,21 @@ impl BaseDocument {
                             bg_image.image = ImageData::Svg(tree);
                         }
                     }
+                    ImageType::Icon => {} // Handled above
                 }
             }
-            Resource::Font(bytes) => {
+            Resource::Font(face_id, bytes) => {
                 // Register font with blitz-text UnifiedTextSystem
-                let _ = self.with_text_system(|text_system| text_system.with_font_system(|font_system| {
+                let result = self.with_text_system(|text_system| text_system.with_font_system(|font_system| {
                     use std::sync::Arc;
                     let source = blitz_text::fontdb::Source::Binary(Arc::new(bytes.to_vec()));
-                    font_system.db_mut().load_font_source(source);
+                    font_system.db_mut().load_font_source(source).len()
                 }));
+                match result {
+                    Ok(0) => self.font_faces.mark_error(face_id, "Invalid font data"),
+                    Ok(_) => self.font_faces.mark_loaded(face_id),
+                    Err(err) => self.font_faces.mark_error(face_id, err),
+                }
             }
             Resource::None => {
                 // Do nothing
@@ -1084,7 +1331,7 @@ impl BaseDocument {
             if let Some(ref mut state) = existing_snapshot.state {
                 state.set(ElementState::HOVER, node.is_hovered());
                 state.set(ElementState::FOCUS, node.is_focussed());
-                state.set(ElementState::FOCUSRING, node.is_focussed());
+                state.set(ElementState::FOCUSRING, node.has_focus_ring());
                 state.set(ElementState::ACTIVE, node.is_active());
                 state.set(ElementState::VISITED, false); // Privacy-safe default
             }
@@ -1174,6 +1421,9 @@ impl BaseDocument {
                 },
             );
         }
+
+        // The change may also affect ancestors or siblings which match a `:has()` selector
+        self.invalidate_relative_selectors(node_id);
     }
 
     pub fn snapshot_node_and(&mut self, node_id: usize, cb: impl FnOnce(&mut Node)) {
@@ -1191,15 +1441,63 @@ impl BaseDocument {
             return;
         }
 
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("resolve", nodes = self.nodes.len()).entered();
+        self.resolve_style_and_layout();
+
+        // The root element's `color-scheme` is only known once it's styled, and switching to
+        // another scheme's system colors restyles the document
+        if self.update_color_scheme_property() {
+            self.resolve_style_and_layout();
+        }
+
+        // `@container` rules depend on the layout of their container, which in turn depends on
+        // style. Re-run style and layout until container sizes settle (or we give up).
+        for _ in 1..MAX_CONTAINER_QUERY_PASSES {
+            if !self.update_container_sizes() {
+                break;
+            }
+            self.resolve_style_and_layout();
+        }
+
+        // Skip (or stop skipping) the contents of `content-visibility` elements based on the
+        // new layout, and relayout if that changed anything
+        if self.update_content_visibility() {
+            self.resolve_layout();
+        }
+
+        self.update_image_decodes();
+
+        // Requests which failed never produce a resource, so check here too
+        self.check_load_complete();
+    }
+
+    /// Run a single style pass followed by a single layout pass
+    fn resolve_style_and_layout(&mut self) {
         // we need to resolve stylist first since it will need to drive our layout bits
-        self.resolve_stylist();
+        let restyled = self.resolve_stylist();
+
+        // Counters feed list markers and `::before`/`::after` text, which box construction reads.
+        // They can only change if elements were restyled, added or removed.
+        if std::mem::take(&mut self.generated_content_dirty) || restyled {
+            self.resolve_generated_content();
+        }
 
         // Fix up tree for layout (insert anonymous blocks as necessary, etc)
         self.resolve_layout_children();
 
+        // Keep `currentColor` in inline SVGs in sync with their elements' colors, which can only
+        // have changed if elements were restyled
+        #[cfg(feature = "svg")]
+        if restyled {
+            refresh_inline_svgs(self);
+        }
+        #[cfg(not(feature = "svg"))]
+        let _ = restyled;
+
         // Merge stylo into taffy
         self.flush_styles_to_layout(self.root_element().id);
-        
+
         // Recursively invalidate style cache for entire document tree after style flush
         let _ = self.invalidate_taffy_style_cache_recursive(self.root_element().id);
 
@@ -1242,6 +1540,28 @@ impl BaseDocument {
         }
     }
 
+    /// Select all of the text in the text input `node_id`, like the "Select All" command
+    pub fn select_all_text(&mut self, node_id: usize) {
+        let input_data = self
+            .nodes
+            .get_mut(node_id)
+            .and_then(|node| node.element_data_mut())
+            .and_then(|el| el.text_input_data_mut());
+        let Some(input_data) = input_data else {
+            return;
+        };
+
+        let editor = &mut input_data.editor;
+        let end = editor.with_buffer(|buffer| {
+            let line = buffer.lines.len().saturating_sub(1);
+            let index = buffer.lines.get(line).map_or(0, |line| line.text().len());
+            blitz_text::Cursor::new(line, index)
+        });
+        editor.set_selection(blitz_text::Selection::Normal(blitz_text::Cursor::new(0, 0)));
+        editor.set_cursor(end);
+        self.shell_provider.request_redraw();
+    }
+
     pub fn set_mousedown_node_id(&mut self, node_id: Option<usize>) {
         self.mousedown_node_id = node_id;
     }
@@ -1265,6 +1585,20 @@ impl BaseDocument {
         true
     }
 
+    /// Focus `focus_node_id` because the user pointed at it (by clicking or tapping it). Unlike
+    /// elements focussed with the keyboard, it only shows its focus ring (and matches
+    /// `:focus-visible`) if it takes text input.
+    pub fn set_focus_from_pointer(&mut self, focus_node_id: usize) -> bool {
+        let changed = self.set_focus_to(focus_node_id);
+        let takes_text = self.nodes[focus_node_id]
+            .element_data()
+            .is_some_and(|el| matches!(el.special_data, SpecialElementData::TextInput(_)));
+        if !takes_text {
+            self.snapshot_node_and(focus_node_id, |node| node.hide_focus_ring());
+        }
+        changed
+    }
+
     pub fn active_node(&mut self) -> bool {
         let Some(hover_node_id) = self.get_hover_node_id() else {
             return false;
@@ -1349,28 +1683,56 @@ impl BaseDocument {
         self.hover_node_id
     }
 
+    /// Set the viewport of the window the document is shown in. While a device is emulated, the
+    /// document is laid out for the device instead, and this viewport is used once emulation ends.
     pub fn set_viewport(&mut self, viewport: Viewport) {
-        self.viewport = viewport;
+        if self.device_emulation.is_some() {
+            self.unemulated_viewport = Some(viewport.clone());
+        }
+        let environment = self.media_environment();
+        self.viewport = self.emulate_device(viewport);
         self.set_stylist_device(make_device(&self.viewport, self.quirks_mode.get()));
         self.scroll_viewport_by(0.0, 0.0); // Clamp scroll offset
+        let window_size = self.logical_window_size();
+        self.visual_viewport.clamp(window_size);
+        self.update_used_color_scheme();
+        if self.media_environment() != environment {
+            self.update_theme_color();
+        }
+        self.notify_media_listeners(environment);
     }
 
     pub fn viewport(&self) -> &Viewport {
         &self.viewport
     }
 
+    pub fn system_colors(&self) -> &SystemColorTheme {
+        &self.system_colors
+    }
+
+    /// Replace the palettes CSS system colors resolve to
+    pub fn set_system_colors(&mut self, system_colors: SystemColorTheme) {
+        let scheme = self.used_color_scheme;
+        self.remove_user_agent_stylesheet(&self.system_colors.to_stylesheet(scheme));
+        self.system_colors = system_colors;
+        self.add_user_agent_stylesheet(&self.system_colors.to_stylesheet(scheme));
+    }
+
+    /// The value of a CSS system color in the used color scheme
+    pub fn system_color(&self, color: SystemColor) -> Color {
+        self.system_colors.palette(self.used_color_scheme).get(color)
+    }
+
     pub fn viewport_mut(&mut self) -> ViewportMut<'_> {
         ViewportMut::new(self)
     }
 
     pub fn zoom_by(&mut self, increment: f32) {
-        *self.viewport.zoom_mut() += increment;
-        self.set_viewport(self.viewport.clone());
+        *self.viewport_mut().zoom_mut() += increment;
     }
 
     pub fn zoom_to(&mut self, zoom: f32) {
-        *self.viewport.zoom_mut() = zoom;
-        self.set_viewport(self.viewport.clone());
+        *self.viewport_mut().zoom_mut() = zoom;
     }
 
     pub fn get_viewport(&self) -> Viewport {
@@ -1385,8 +1747,17 @@ impl BaseDocument {
         &mut self.devtool_settings
     }
 
+    /// Whether the document needs another frame, because it has active animations or callbacks
+    /// waiting for the next frame
     pub fn is_animating(&self) -> bool {
-        self.is_animating
+        let is_animating = self.is_animating || self.has_running_css_animations();
+        (is_animating && !self.animations_suppressed) || self.has_animation_frame_callbacks()
+    }
+
+    /// Suppress animations so that the document only re-renders in response to changes,
+    /// rather than every frame while animations are active
+    pub fn set_animations_suppressed(&mut self, suppressed: bool) {
+        self.animations_suppressed = suppressed;
     }
 
     /// Update the device and reset the stylist to process the new size
@@ -1408,6 +1779,8 @@ impl BaseDocument {
 
     /// Ensure that the layout_children field is populated for all nodes
     pub fn resolve_layout_children(&mut self) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("construct_boxes").entered();
         resolve_layout_children_recursive(self, self.root_node().id);
 
         fn resolve_layout_children_recursive(doc: &mut BaseDocument, node_id: usize) {
@@ -1480,7 +1853,10 @@ impl BaseDocument {
     ///
     /// Compute layout using trait-based taffy API for consistent integration
     pub fn resolve_layout(&mut self) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("layout").entered();
         let size = self.stylist.device().au_viewport_size();
+        self.grid_contributions.clear();
 
         let available_space = taffy::Size {
             width: AvailableSpace::Definite(size.width.to_f32_px()),
@@ -1656,20 +2032,43 @@ impl BaseDocument {
 
     /// Scroll the viewport by the given values
     pub fn scroll_viewport_by(&mut self, x: f64, y: f64) {
-        let content_size = self.root_element().final_layout.size;
+        let content_size = self.content_size();
         let new_scroll = (self.viewport_scroll.x - x, self.viewport_scroll.y - y);
         let window_width = self.viewport.window_size.0 as f64 / self.viewport.scale() as f64;
         let window_height = self.viewport.window_size.1 as f64 / self.viewport.scale() as f64;
         self.viewport_scroll.x = f64::max(
             0.0,
-            f64::min(new_scroll.0, content_size.width as f64 - window_width),
+            f64::min(new_scroll.0, content_size.width - window_width),
         );
         self.viewport_scroll.y = f64::max(
             0.0,
-            f64::min(new_scroll.1, content_size.height as f64 - window_height),
+            f64::min(new_scroll.1, content_size.height - window_height),
         )
     }
 
+    /// The laid out size of the document (in CSS pixels), including any content which overflows
+    /// the root element. This is the size of the area which can be scrolled to.
+    pub fn content_size(&self) -> kurbo::Size {
+        let layout = &self.root_element().final_layout;
+        kurbo::Size {
+            width: layout.size.width.max(layout.content_size.width) as f64,
+            height: layout.size.height.max(layout.content_size.height) as f64,
+        }
+    }
+
+    /// The scale at which the whole document fits within `width` x `height` (in physical
+    /// pixels), preserving its aspect ratio. Pass `f64::INFINITY` as the height to fit the width
+    /// only (e.g. for a full-page screenshot which should be scrolled vertically).
+    ///
+    /// Returns `1.0` if the document has not been laid out yet.
+    pub fn scale_to_fit(&self, width: f64, height: f64) -> f64 {
+        let content_size = self.content_size();
+        if content_size.width <= 0.0 || content_size.height <= 0.0 {
+            return 1.0;
+        }
+        f64::min(width / content_size.width, height / content_size.height)
+    }
+
     pub fn viewport_scroll(&self) -> kurbo::Point {
         self.viewport_scroll
     }
@@ -1698,8 +2097,7 @@ impl BaseDocument {
                 }
             }
         }
-        
-        // TODO: Implement proper animation detection for CSS animations, transitions, etc.
+
         false
     }
 
@@ -1711,6 +2109,30 @@ impl BaseDocument {
     }
 }
 
+impl Drop for BaseDocument {
+    fn drop(&mut self) {
+        self.set_lifecycle(DocumentLifecycle::Unloading);
+        self.lifecycle_listeners.clear();
+        self.document_event_listeners.clear();
+        self.media_listeners.clear();
+
+        self.net_provider.cancel(self.id);
+        self.script_provider.document_unloaded(self.id);
+        if self.device_emulation.take().is_some_and(|device| device.user_agent.is_some()) {
+            self.net_provider.set_user_agent(self.id, None);
+        }
+        self.net_provider.set_document_url(self.id, None);
+        for url in self.object_urls.drain(..) {
+            BlobRegistry::global().revoke_object_url(&url);
+        }
+        self.font_faces.close();
+
+        crate::events::clear_composition_state(self.id);
+        // Grid contexts are cached per document, on the thread the document was laid out on
+        crate::layout::grid_context::cache::clear_cache(self.id);
+    }
+}
+
 impl AsRef<BaseDocument> for BaseDocument {
     fn as_ref(&self) -> &BaseDocument {
         self
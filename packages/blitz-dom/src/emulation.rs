@@ -0,0 +1,329 @@
+//! Device emulation, and the viewport `<meta>` tag
+//!
+//! Like desktop browsers, Blitz ignores `<meta name="viewport">` by default. With a
+//! [`DeviceEmulation`] set with [`BaseDocument::set_device_emulation`] the document is laid out
+//! as it would be on that device instead: at the size and pixel ratio of its screen, and for
+//! mobile devices at the width the viewport meta tag asks for (980px without one), scaled to fit
+//! the screen. This makes it possible to preview responsive designs, including headlessly.
+
+use blitz_traits::shell::{HoverCapability, MediaType, Viewport};
+use kurbo::Point;
+use markup5ever::local_name;
+
+use crate::BaseDocument;
+use crate::traversal::TreeTraverser;
+use crate::visual_viewport::VisualViewport;
+
+/// The width mobile browsers lay out pages without a viewport meta tag at
+const DEFAULT_LAYOUT_WIDTH: f32 = 980.0;
+
+/// The device a document is laid out and rendered for
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceEmulation {
+    /// The width of the screen, in CSS pixels
+    pub width: u32,
+    /// The height of the screen, in CSS pixels
+    pub height: u32,
+    pub device_pixel_ratio: f32,
+    /// Whether the viewport meta tag is honored
+    pub mobile: bool,
+    /// Whether presses of the main mouse button also emit touch events
+    pub touch: bool,
+    /// The `User-Agent` the document's resources are fetched with. This needs a net provider which
+    /// supports [`set_user_agent`](blitz_traits::net::NetProvider::set_user_agent).
+    pub user_agent: Option<String>,
+}
+
+impl DeviceEmulation {
+    /// A phone or tablet with a touchscreen
+    pub fn mobile(width: u32, height: u32, device_pixel_ratio: f32) -> Self {
+        Self {
+            width,
+            height,
+            device_pixel_ratio,
+            mobile: true,
+            touch: true,
+            user_agent: None,
+        }
+    }
+
+    /// A desktop screen of a different size or pixel ratio than the window's
+    pub fn desktop(width: u32, height: u32, device_pixel_ratio: f32) -> Self {
+        Self {
+            mobile: false,
+            touch: false,
+            ..Self::mobile(width, height, device_pixel_ratio)
+        }
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+}
+
+/// The `width` of a viewport meta tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportWidth {
+    DeviceWidth,
+    /// In CSS pixels
+    Px(f32),
+}
+
+/// The properties of a `<meta name="viewport">` tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportMeta {
+    pub width: Option<ViewportWidth>,
+    pub initial_scale: Option<f32>,
+    pub minimum_scale: Option<f32>,
+    pub maximum_scale: Option<f32>,
+    /// Whether the page can be pinch-zoomed
+    pub user_scalable: bool,
+}
+
+impl Default for ViewportMeta {
+    fn default() -> Self {
+        Self {
+            width: None,
+            initial_scale: None,
+            minimum_scale: None,
+            maximum_scale: None,
+            user_scalable: true,
+        }
+    }
+}
+
+impl ViewportMeta {
+    /// Parse the `content` of a viewport meta tag, such as `width=device-width, initial-scale=1`.
+    /// Unknown and invalid properties are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut meta = Self::default();
+        for property in content.split([',', ';']) {
+            let Some((name, value)) = property.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            let number = value
+                .parse::<f32>()
+                .ok()
+                .filter(|number| number.is_finite() && *number > 0.0);
+            match name.trim().to_ascii_lowercase().as_str() {
+                "width" if value.eq_ignore_ascii_case("device-width") => {
+                    meta.width = Some(ViewportWidth::DeviceWidth)
+                }
+                "width" => meta.width = number.map(ViewportWidth::Px),
+                "initial-scale" => meta.initial_scale = number,
+                "minimum-scale" => meta.minimum_scale = number,
+                "maximum-scale" => meta.maximum_scale = number,
+                "user-scalable" => {
+                    meta.user_scalable = !(value.eq_ignore_ascii_case("no") || value == "0")
+                }
+                _ => {}
+            }
+        }
+        meta
+    }
+
+    /// The width (in CSS pixels) to lay the page out at on a screen `device_width` CSS pixels wide
+    pub fn layout_width(&self, device_width: f32) -> f32 {
+        let width = match self.width {
+            Some(ViewportWidth::DeviceWidth) => device_width,
+            Some(ViewportWidth::Px(width)) => width,
+            None if self.initial_scale.is_some() => 0.0,
+            None => DEFAULT_LAYOUT_WIDTH,
+        };
+        // The page is at least wide enough to fill the screen at its initial scale
+        let min_width = self
+            .initial_scale
+            .map_or(0.0, |scale| device_width / self.clamp_scale(scale));
+        width.max(min_width).clamp(1.0, 10_000.0)
+    }
+
+    /// The scale the page is initially shown at, relative to the screen's CSS pixels
+    pub fn initial_scale(&self, device_width: f32) -> f32 {
+        let scale = self
+            .initial_scale
+            .unwrap_or_else(|| device_width / self.layout_width(device_width));
+        self.clamp_scale(scale)
+    }
+
+    fn clamp_scale(&self, scale: f32) -> f32 {
+        let min = self.minimum_scale.unwrap_or(0.1);
+        let max = self.maximum_scale.unwrap_or(10.0).max(min);
+        scale.clamp(min, max)
+    }
+}
+
+impl BaseDocument {
+    pub fn device_emulation(&self) -> Option<&DeviceEmulation> {
+        self.device_emulation.as_ref()
+    }
+
+    /// The document's viewport meta tag, if it has one
+    pub fn viewport_meta(&self) -> Option<&ViewportMeta> {
+        self.viewport_meta.as_ref()
+    }
+
+    /// Lay the document out for `emulation`'s device, or stop emulating a device if `None`.
+    ///
+    /// While emulating a device, the size and pixel ratio of the viewports passed to
+    /// [`Self::set_viewport`] are replaced with the device's (and for mobile devices so is the
+    /// zoom). The viewport last set with [`Self::set_viewport`] or [`Self::viewport_mut`], during
+    /// the emulation or before it started, is restored when it ends.
+    pub fn set_device_emulation(&mut self, emulation: Option<DeviceEmulation>) {
+        let previous = self.device_emulation.take();
+        let user_agent = emulation.as_ref().and_then(|device| device.user_agent.clone());
+        if user_agent.is_some() || previous.is_some_and(|device| device.user_agent.is_some()) {
+            self.net_provider.set_user_agent(self.id, user_agent);
+        }
+
+        let viewport = match emulation {
+            Some(_) => self
+                .unemulated_viewport
+                .get_or_insert_with(|| self.viewport.clone())
+                .clone(),
+            None => self
+                .unemulated_viewport
+                .take()
+                .unwrap_or_else(|| self.viewport.clone()),
+        };
+        self.device_emulation = emulation;
+        self.set_viewport(viewport);
+        self.apply_initial_scale();
+    }
+
+    /// The viewport last set with [`Self::set_viewport`], before it was changed for the emulated
+    /// device (if any)
+    pub(crate) fn unemulated_viewport(&self) -> Viewport {
+        self.unemulated_viewport.clone().unwrap_or_else(|| self.viewport.clone())
+    }
+
+    /// Replace the parts of `viewport` which the emulated device (if any) determines
+    pub(crate) fn emulate_device(&self, mut viewport: Viewport) -> Viewport {
+        let Some(device) = &self.device_emulation else {
+            return viewport;
+        };
+        // Print layout is at the size of the page rather than the screen's
+        if viewport.media_type == MediaType::Print {
+            return viewport;
+        }
+
+        let scale = device.device_pixel_ratio;
+        viewport.hidpi_scale = scale;
+        viewport.window_size = (
+            (device.width as f32 * scale).round() as u32,
+            (device.height as f32 * scale).round() as u32,
+        );
+        if device.touch {
+            viewport.hover = HoverCapability::None;
+        }
+        if device.mobile {
+            let meta = self.viewport_meta.unwrap_or_default();
+            let width = device.width as f32;
+            viewport.zoom = width / meta.layout_width(width);
+        }
+        viewport
+    }
+
+    /// Read the document's viewport meta tag again, after one may have been changed
+    pub(crate) fn update_viewport_meta(&mut self) {
+        let meta = TreeTraverser::new(self)
+            .map(|node_id| &self.nodes[node_id])
+            .filter_map(|node| node.element_data())
+            .find(|element| {
+                element.name.local == local_name!("meta")
+                    && element
+                        .attr(local_name!("name"))
+                        .is_some_and(|name| name.eq_ignore_ascii_case("viewport"))
+            })
+            .map(|element| {
+                ViewportMeta::parse(element.attr(local_name!("content")).unwrap_or_default())
+            });
+        if meta == self.viewport_meta {
+            return;
+        }
+
+        self.viewport_meta = meta;
+        if self.device_emulation.as_ref().is_some_and(|device| device.mobile) {
+            self.set_viewport(self.unemulated_viewport());
+            self.apply_initial_scale();
+        }
+    }
+
+    /// Pinch-zoom the page to the initial scale of its viewport meta tag, if emulating a mobile
+    /// device. The layout zoom already scales the page to fit the screen, so this is only needed
+    /// if the initial scale is larger than that.
+    fn apply_initial_scale(&mut self) {
+        self.visual_viewport = VisualViewport::default();
+        let Some(device) = self.device_emulation.as_ref().filter(|device| device.mobile) else {
+            return;
+        };
+        let meta = self.viewport_meta.unwrap_or_default();
+        let scale = meta.initial_scale(device.width as f32) / self.viewport.zoom;
+        let window_size = self.logical_window_size();
+        self.visual_viewport.zoom_by(scale as f64, Point::ZERO, window_size);
+    }
+
+    /// Whether the page can be pinch-zoomed, which mobile pages can disable
+    pub(crate) fn is_user_scalable(&self) -> bool {
+        !self.device_emulation.as_ref().is_some_and(|device| device.mobile)
+            || self.viewport_meta.is_none_or(|meta| meta.user_scalable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blitz_traits::shell::ColorScheme;
+
+    use super::*;
+    use crate::testing::document_with_viewport;
+
+    #[test]
+    fn viewports_set_while_emulating_are_restored_afterwards() {
+        let mut doc = document_with_viewport(Viewport::new(800, 600, 1.0, ColorScheme::Light));
+        doc.set_device_emulation(Some(DeviceEmulation::desktop(300, 200, 2.0)));
+        assert_eq!(doc.viewport().window_size, (600, 400));
+        assert_eq!(doc.viewport().hidpi_scale, 2.0);
+
+        // The window is resized, but the document stays laid out for the device
+        doc.set_viewport(Viewport::new(1000, 700, 1.0, ColorScheme::Light));
+        assert_eq!(doc.viewport().window_size, (600, 400));
+
+        doc.set_device_emulation(None);
+        assert_eq!(doc.viewport().window_size, (1000, 700));
+        assert_eq!(doc.viewport().hidpi_scale, 1.0);
+    }
+
+    #[test]
+    fn viewport_mut_edits_the_window_viewport_while_emulating() {
+        let mut doc = document_with_viewport(Viewport::new(800, 600, 1.0, ColorScheme::Light));
+        doc.set_device_emulation(Some(DeviceEmulation::desktop(300, 200, 2.0)));
+        assert_eq!(doc.viewport_mut().window_size, (800, 600));
+        doc.viewport_mut().window_size = (1200, 900);
+        doc.zoom_to(1.5);
+        assert_eq!(doc.viewport().window_size, (600, 400));
+        assert_eq!(doc.viewport().zoom, 1.5);
+
+        doc.set_device_emulation(None);
+        assert_eq!(doc.viewport().window_size, (1200, 900));
+        assert_eq!(doc.viewport().zoom, 1.5);
+    }
+
+    #[test]
+    fn viewport_meta_layout_width() {
+        let meta = ViewportMeta::parse("width=device-width, initial-scale=1, user-scalable=no");
+        assert_eq!(meta.width, Some(ViewportWidth::DeviceWidth));
+        assert!(!meta.user_scalable);
+        assert_eq!(meta.layout_width(390.0), 390.0);
+        assert_eq!(meta.initial_scale(390.0), 1.0);
+
+        // Without a width, pages are laid out for desktop screens and scaled down to fit
+        let meta = ViewportMeta::parse("");
+        assert_eq!(meta.layout_width(490.0), 980.0);
+        assert_eq!(meta.initial_scale(490.0), 0.5);
+
+        // A page can't be narrower than the screen at its initial scale
+        let meta = ViewportMeta::parse("width=300; initial-scale=2");
+        assert_eq!(meta.layout_width(800.0), 400.0);
+    }
+}
@@ -1,9 +1,22 @@
 use std::collections::VecDeque;
 
-use blitz_traits::events::{BlitzMouseButtonEvent, DomEvent, DomEventData, EventState, UiEvent};
+use blitz_traits::events::{
+    BlitzMouseButtonEvent, BlitzPointerEvent, BlitzTouchEvent, DomEvent, DomEventData, EventPhase,
+    EventState, MOUSE_POINTER_ID, MouseEventButton, MouseEventButtons, PointerType, UiEvent,
+};
+
+use blitz_traits::shell::HoverCapability;
 
 use crate::{BaseDocument, DocumentMutator};
 
+/// Handles events on behalf of the page (by running its event listeners, say)
+///
+/// Each event is handled once per phase of its path through the tree, with [`DomEvent::phase`]
+/// saying which: first while capturing, with `chain` going from the root down to the target's
+/// parent, then at the target, with `chain` only holding the target, and finally (if the event
+/// bubbles) while bubbling, with `chain` going from the target's parent up to the root. Phases
+/// with an empty chain are skipped, and later phases aren't handled once
+/// [`EventState::stop_propagation`] has been called.
 pub trait EventHandler {
     fn handle_event(
         &mut self,
@@ -46,21 +59,71 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
     }
 
     pub fn handle_ui_event(&mut self, event: UiEvent) {
-        let viewport_scroll = self.doc().viewport_scroll();
-        let zoom = self.doc().viewport.zoom();
+        match event {
+            UiEvent::PointerDown(event) => {
+                self.handle_pointer_event(event, DomEventData::PointerDown)
+            }
+            UiEvent::PointerMove(event) => {
+                self.handle_pointer_event(event, DomEventData::PointerMove)
+            }
+            UiEvent::PointerUp(event) => self.handle_pointer_event(event, DomEventData::PointerUp),
+            UiEvent::PointerCancel(event) => {
+                self.handle_pointer_event(event, DomEventData::PointerCancel)
+            }
+            event => self.handle_input_event(event, None, false),
+        }
+    }
+
+    /// Convert window coordinates to document coordinates
+    fn window_to_dom(&self, x: f32, y: f32) -> (f32, f32) {
+        let doc = self.doc();
+        let zoom = doc.viewport.zoom() as f64;
+        // Window coordinates are magnified by pinch-zoom, and then by the document's zoom
+        let point = kurbo::Point::new(x as f64, y as f64);
+        let point = doc.visual_viewport().to_layout_viewport(point);
+        let point = (point.to_vec2() / zoom + doc.viewport_scroll().to_vec2()).to_point();
+        (point.x as f32, point.y as f32)
+    }
+
+    /// Handle a mouse, keyboard or gamepad event. Mouse events are either the mouse's own, which
+    /// its pointer events are dispatched before, or the compatibility mouse events of
+    /// `compat_for`. Compatibility mouse events which are `suppressed` (as the pointer's
+    /// `pointerdown` was cancelled) aren't dispatched, but their default actions still run.
+    fn handle_input_event(&mut self, event: UiEvent, compat_for: Option<u64>, suppressed: bool) {
+        // Scrollbars are part of the browser rather than the page, so the page doesn't see the
+        // mouse events they handle
+        let mouse_position = match &event {
+            UiEvent::MouseMove(event) | UiEvent::MouseDown(event) | UiEvent::MouseUp(event) => {
+                Some(self.window_to_dom(event.x, event.y))
+            }
+            _ => None,
+        };
+        if let Some((x, y)) = mouse_position {
+            let point = kurbo::Point::new(x as f64, y as f64);
+            if self.doc_mut().handle_scrollbar_event(&event, point) {
+                return;
+            }
+        }
 
         let mut hover_node_id = self.doc().hover_node_id;
         let focussed_node_id = self.doc().focus_node_id;
+        // Pointers which can't hover (like fingers) only point at elements while pressed
+        let can_hover =
+            self.doc().viewport.hover == HoverCapability::Hover && compat_for.is_none();
 
         // Update document input state (hover, focus, active, etc)
         match &event {
-            UiEvent::MouseMove(event) => {
-                let dom_x = event.x + viewport_scroll.x as f32 / zoom;
-                let dom_y = event.y + viewport_scroll.y as f32 / zoom;
+            UiEvent::MouseMove(event) if can_hover || !event.buttons.is_empty() => {
+                let (dom_x, dom_y) = self.window_to_dom(event.x, event.y);
                 self.doc_mut().set_hover_to(dom_x, dom_y);
                 hover_node_id = self.doc().hover_node_id;
             }
-            UiEvent::MouseDown(_) => {
+            UiEvent::MouseDown(event) => {
+                if !can_hover {
+                    let (dom_x, dom_y) = self.window_to_dom(event.x, event.y);
+                    self.doc_mut().set_hover_to(dom_x, dom_y);
+                    hover_node_id = self.doc().hover_node_id;
+                }
                 self.doc_mut().active_node();
                 self.doc_mut().set_mousedown_node_id(hover_node_id);
             }
@@ -77,54 +140,284 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
             UiEvent::KeyUp(_) => focussed_node_id,
             UiEvent::KeyDown(_) => focussed_node_id,
             UiEvent::Ime(_) => focussed_node_id,
+            UiEvent::GamepadButtonDown(_) => focussed_node_id,
+            UiEvent::GamepadButtonUp(_) => focussed_node_id,
+            UiEvent::GamepadAxisMove(_) => focussed_node_id,
+            UiEvent::PointerDown(_)
+            | UiEvent::PointerMove(_)
+            | UiEvent::PointerUp(_)
+            | UiEvent::PointerCancel(_) => hover_node_id,
         };
 
         let data = match event {
-            UiEvent::MouseMove(data) => DomEventData::MouseMove(BlitzMouseButtonEvent {
-                x: data.x + viewport_scroll.x as f32 / zoom,
-                y: data.y + viewport_scroll.y as f32 / zoom,
-                ..data
-            }),
-            UiEvent::MouseUp(data) => DomEventData::MouseUp(BlitzMouseButtonEvent {
-                x: data.x + viewport_scroll.x as f32 / zoom,
-                y: data.y + viewport_scroll.y as f32 / zoom,
-                ..data
-            }),
-            UiEvent::MouseDown(data) => DomEventData::MouseDown(BlitzMouseButtonEvent {
-                x: data.x + viewport_scroll.x as f32 / zoom,
-                y: data.y + viewport_scroll.y as f32 / zoom,
-                ..data
-            }),
+            UiEvent::MouseMove(data) => {
+                let (x, y) = self.window_to_dom(data.x, data.y);
+                DomEventData::MouseMove(BlitzMouseButtonEvent { x, y, ..data })
+            }
+            UiEvent::MouseUp(data) => {
+                let (x, y) = self.window_to_dom(data.x, data.y);
+                DomEventData::MouseUp(BlitzMouseButtonEvent { x, y, ..data })
+            }
+            UiEvent::MouseDown(data) => {
+                let (x, y) = self.window_to_dom(data.x, data.y);
+                DomEventData::MouseDown(BlitzMouseButtonEvent { x, y, ..data })
+            }
             UiEvent::KeyUp(data) => DomEventData::KeyUp(data),
             UiEvent::KeyDown(data) => DomEventData::KeyDown(data),
             UiEvent::Ime(data) => DomEventData::Ime(data),
+            UiEvent::GamepadButtonDown(data) => DomEventData::GamepadButtonDown(data),
+            UiEvent::GamepadButtonUp(data) => DomEventData::GamepadButtonUp(data),
+            UiEvent::GamepadAxisMove(data) => DomEventData::GamepadAxisMove(data),
+            UiEvent::PointerDown(_)
+            | UiEvent::PointerMove(_)
+            | UiEvent::PointerUp(_)
+            | UiEvent::PointerCancel(_) => {
+                unreachable!("Pointer events are handled by handle_pointer_event")
+            }
         };
 
-        let target = target.unwrap_or_else(|| self.doc().root_element().id);
+        let mut target = target.unwrap_or_else(|| self.doc().root_element().id);
+
+        // The mouse is a pointer too, whose pointer events come before its mouse events. Both go
+        // to the element capturing it, if any.
+        let mouse_pointer = match compat_for {
+            None => mouse_pointer_event(&data),
+            Some(_) => None,
+        };
+        let mut suppressed = suppressed;
+        if let Some((pointer_data, pointer)) = mouse_pointer {
+            self.process_pointer_capture(&pointer);
+            target = self.doc().pointer_capture(MOUSE_POINTER_ID).unwrap_or(target);
+            suppressed = !self.dispatch_pointer_event(pointer_data, &pointer, target);
+        }
+        if let Some(pointer_id) = compat_for {
+            target = self.doc().pointer_capture(pointer_id).unwrap_or(target);
+        }
+
+        // Emulated touchscreens turn presses of the main mouse button into touches, which are
+        // dispatched before the mouse events like browsers do for real touches
+        let emulates_touch = self.doc().device_emulation().is_some_and(|device| device.touch);
+        if emulates_touch && compat_for.is_none() {
+            let touch_target = self.doc().mousedown_node_id.unwrap_or(target);
+            if let Some(touch) = emulated_touch(&data) {
+                self.handle_dom_event(DomEvent::new(touch_target, touch));
+            }
+        }
+
         let dom_event = DomEvent::new(target, data);
+        if suppressed {
+            self.run_default_action(dom_event);
+        } else {
+            self.handle_dom_event(dom_event);
+        }
+    }
+
+    /// Handle an event from a touch or pen, which is dispatched as a pointer event followed by
+    /// (for touches) a touch event and (for primary pointers) compatibility mouse events
+    fn handle_pointer_event(
+        &mut self,
+        event: BlitzPointerEvent,
+        make_data: fn(BlitzPointerEvent) -> DomEventData,
+    ) {
+        let (x, y) = self.window_to_dom(event.x, event.y);
+        let pointer = BlitzPointerEvent { x, y, ..event.clone() };
+        self.process_pointer_capture(&pointer);
+
+        let hit = self.doc().hit(x, y).map(|hit| hit.node_id);
+        let target = self
+            .doc()
+            .pointer_capture(pointer.pointer_id)
+            .or(hit)
+            .unwrap_or_else(|| self.doc().root_element().id);
+
+        let data = make_data(pointer.clone());
+        let touch = BlitzTouchEvent {
+            identifier: pointer.pointer_id,
+            x,
+            y,
+            mods: pointer.mods,
+        };
+        // Compatibility mouse events are in window coordinates, like the mouse's own
+        let mouse = event.to_mouse();
+        let (touch, mouse) = match &data {
+            DomEventData::PointerDown(_) => {
+                (DomEventData::TouchStart(touch), Some(UiEvent::MouseDown(mouse)))
+            }
+            DomEventData::PointerMove(_) => {
+                (DomEventData::TouchMove(touch), Some(UiEvent::MouseMove(mouse)))
+            }
+            DomEventData::PointerUp(_) => {
+                (DomEventData::TouchEnd(touch), Some(UiEvent::MouseUp(mouse)))
+            }
+            // Cancelled pointers don't get compatibility mouse events
+            _ => (DomEventData::TouchEnd(touch), None),
+        };
 
-        self.handle_dom_event(dom_event);
+        let compat = self.dispatch_pointer_event(data, &pointer, target);
+        if pointer.pointer_type == PointerType::Touch {
+            self.handle_dom_event(DomEvent::new(target, touch));
+        }
+        if let Some(mouse) = mouse.filter(|_| pointer.is_primary) {
+            self.handle_input_event(mouse, Some(pointer.pointer_id), !compat);
+        }
+    }
+
+    /// Dispatch a pointer event at `target`, pressing or releasing the pointer. Returns whether
+    /// the compatibility mouse events which follow it are dispatched, which they aren't once the
+    /// `pointerdown` event of a press has been cancelled (though a `click` still is).
+    fn dispatch_pointer_event(
+        &mut self,
+        data: DomEventData,
+        pointer: &BlitzPointerEvent,
+        target: usize,
+    ) -> bool {
+        let pointer_id = pointer.pointer_id;
+        let is_down = matches!(data, DomEventData::PointerDown(_));
+        let is_up = matches!(data, DomEventData::PointerUp(_) | DomEventData::PointerCancel(_));
+
+        if is_down {
+            self.doc_mut().press_pointer(pointer_id);
+            // Touches are captured by the element they start on, as if it had captured them
+            if pointer.pointer_type == PointerType::Touch {
+                self.doc_mut().set_pointer_capture(target, pointer_id);
+            }
+        }
+
+        let default_prevented = self.dispatch_dom_event(DomEvent::new(target, data));
+        if is_down && default_prevented {
+            self.doc_mut().suppress_compat_mouse_events(pointer_id);
+        }
+        let compat = !self.doc().compat_mouse_events_suppressed(pointer_id);
+
+        if is_up {
+            self.doc_mut().release_pointer(pointer_id);
+            self.process_pointer_capture(pointer);
+        }
+        compat
+    }
+
+    /// Make the pointer's pending capture take effect, dispatching `lostpointercapture` and
+    /// `gotpointercapture` events if it changed
+    fn process_pointer_capture(&mut self, pointer: &BlitzPointerEvent) {
+        let Some((lost, got)) = self.doc_mut().process_pending_pointer_capture(pointer.pointer_id)
+        else {
+            return;
+        };
+        if let Some(node_id) = lost {
+            let data = DomEventData::LostPointerCapture(pointer.clone());
+            self.handle_dom_event(DomEvent::new(node_id, data));
+        }
+        if let Some(node_id) = got {
+            let data = DomEventData::GotPointerCapture(pointer.clone());
+            self.handle_dom_event(DomEvent::new(node_id, data));
+        }
     }
 
     pub fn handle_dom_event(&mut self, event: DomEvent) {
+        self.dispatch_dom_event(event);
+    }
+
+    /// Run the default action of `event` without dispatching it, and dispatch the events that
+    /// dispatches (like the `click` which follows a `mouseup`)
+    fn run_default_action(&mut self, mut event: DomEvent) {
+        let mut queue = Vec::new();
+        self.doc_mut()
+            .handle_dom_event(&mut event, false, |new_evt| queue.push(new_evt));
+        for event in queue {
+            self.dispatch_dom_event(event);
+        }
+    }
+
+    /// Handle an event (and the events its default action dispatches), returning whether its
+    /// default action was prevented
+    fn dispatch_dom_event(&mut self, event: DomEvent) -> bool {
+        let mut default_prevented = None;
         let mut queue = VecDeque::with_capacity(4);
         queue.push_back(event);
 
         while let Some(mut event) = queue.pop_front() {
-            let chain = if event.bubbles {
-                self.doc().node_chain(event.target)
-            } else {
-                vec![event.target]
-            };
+            // The target followed by its ancestors
+            let path = self.doc().node_chain(event.target);
+            let ancestors = &path[1..];
+            let capturing: Vec<usize> = ancestors.iter().rev().copied().collect();
+            let bubbling = if event.bubbles { ancestors } else { &[] };
+            let phases = [
+                (EventPhase::Capturing, capturing.as_slice()),
+                (EventPhase::AtTarget, &path[..1]),
+                (EventPhase::Bubbling, bubbling),
+            ];
 
-            let mut event_state = EventState::default();
-            self.handler
-                .handle_event(&chain, &mut event, &mut self.mutr, &mut event_state);
+            // Handlers can't prevent the default action of passive events, so don't wait for them
+            if event.passive {
+                self.doc_mut()
+                    .handle_dom_event(&mut event, false, |new_evt| queue.push_back(new_evt));
+            }
 
-            if !event_state.is_cancelled() {
+            let mut event_state = EventState::for_event(&event);
+            for (phase, chain) in phases {
+                if chain.is_empty() {
+                    continue;
+                }
+                event.phase = phase;
+                self.handler
+                    .handle_event(chain, &mut event, &mut self.mutr, &mut event_state);
+                if event_state.propagation_is_stopped() {
+                    break;
+                }
+            }
+
+            let prevented = !event.passive && event_state.is_cancelled();
+            default_prevented.get_or_insert(prevented);
+            if !event.passive {
                 self.doc_mut()
-                    .handle_dom_event(&mut event, |new_evt| queue.push_back(new_evt));
+                    .handle_dom_event(&mut event, prevented, |new_evt| queue.push_back(new_evt));
             }
         }
+
+        default_prevented.unwrap_or(false)
+    }
+}
+
+/// The pointer event of the mouse which comes before a mouse event, if any
+fn mouse_pointer_event(data: &DomEventData) -> Option<(DomEventData, BlitzPointerEvent)> {
+    let pointer = match data {
+        DomEventData::MouseMove(event)
+        | DomEventData::MouseDown(event)
+        | DomEventData::MouseUp(event) => BlitzPointerEvent::from_mouse(event),
+        _ => return None,
+    };
+    // Only pressing the first button and releasing the last make `pointerdown` and `pointerup`
+    // events, other changes to the buttons make `pointermove` events
+    let pointer_data = match data {
+        DomEventData::MouseDown(_) if pointer.buttons.bits().count_ones() == 1 => {
+            DomEventData::PointerDown(pointer.clone())
+        }
+        DomEventData::MouseUp(_) if pointer.buttons.is_empty() => {
+            DomEventData::PointerUp(pointer.clone())
+        }
+        _ => DomEventData::PointerMove(pointer.clone()),
+    };
+    Some((pointer_data, pointer))
+}
+
+/// The touch event a mouse event is emulated as, if any
+fn emulated_touch(data: &DomEventData) -> Option<DomEventData> {
+    let touch = |event: &BlitzMouseButtonEvent| BlitzTouchEvent {
+        identifier: 0,
+        x: event.x,
+        y: event.y,
+        mods: event.mods,
+    };
+    match data {
+        DomEventData::MouseDown(event) if event.button == MouseEventButton::Main => {
+            Some(DomEventData::TouchStart(touch(event)))
+        }
+        DomEventData::MouseMove(event) if event.buttons.contains(MouseEventButtons::Primary) => {
+            Some(DomEventData::TouchMove(touch(event)))
+        }
+        DomEventData::MouseUp(event) if event.button == MouseEventButton::Main => {
+            Some(DomEventData::TouchEnd(touch(event)))
+        }
+        _ => None,
     }
 }
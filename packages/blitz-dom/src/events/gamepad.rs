@@ -0,0 +1,78 @@
+use blitz_traits::events::{
+    BlitzGamepadAxisEvent, BlitzGamepadButtonEvent, DomEvent, GamepadAxis, GamepadButton,
+};
+use keyboard_types::Modifiers;
+
+use crate::events::keyboard::move_focus;
+use crate::{BaseDocument, SpatialDirection};
+
+/// How far a stick has to be pushed to navigate
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// The D-pad moves focus, and the south button (A or Cross) clicks the focused element
+pub(crate) fn handle_gamepad_button<F: FnMut(DomEvent)>(
+    doc: &mut BaseDocument,
+    event: &BlitzGamepadButtonEvent,
+    mut dispatch_event: F,
+) {
+    let direction = match event.button {
+        GamepadButton::DPadUp => SpatialDirection::Up,
+        GamepadButton::DPadDown => SpatialDirection::Down,
+        GamepadButton::DPadLeft => SpatialDirection::Left,
+        GamepadButton::DPadRight => SpatialDirection::Right,
+        GamepadButton::South => {
+            if let Some(node_id) = doc.focus_node_id {
+                let click = doc.nodes[node_id].synthetic_click_event(Modifiers::empty());
+                dispatch_event(DomEvent::new(node_id, click));
+            }
+            return;
+        }
+        _ => return,
+    };
+    navigate(doc, direction, dispatch_event);
+}
+
+/// The left stick moves focus once each time it's pushed past the threshold
+pub(crate) fn handle_gamepad_axis<F: FnMut(DomEvent)>(
+    doc: &mut BaseDocument,
+    event: &BlitzGamepadAxisEvent,
+    dispatch_event: F,
+) {
+    let (negative, positive) = match event.axis {
+        GamepadAxis::LeftStickX => (SpatialDirection::Left, SpatialDirection::Right),
+        GamepadAxis::LeftStickY => (SpatialDirection::Up, SpatialDirection::Down),
+        GamepadAxis::RightStickX | GamepadAxis::RightStickY => return,
+    };
+    let direction = if event.value <= -STICK_THRESHOLD {
+        Some(negative)
+    } else if event.value >= STICK_THRESHOLD {
+        Some(positive)
+    } else {
+        None
+    };
+
+    let key = (event.gamepad_id, event.axis);
+    let previous = match direction {
+        Some(direction) => doc.gamepad_stick_directions.insert(key, direction),
+        None => doc.gamepad_stick_directions.remove(&key),
+    };
+    if let Some(direction) = direction.filter(|&direction| previous != Some(direction)) {
+        navigate(doc, direction, dispatch_event);
+    }
+}
+
+fn navigate<F: FnMut(DomEvent)>(
+    doc: &mut BaseDocument,
+    direction: SpatialDirection,
+    dispatch_event: F,
+) {
+    if let Some(node_id) = doc.focusable_in_direction(direction) {
+        move_focus(
+            doc,
+            |doc| {
+                doc.set_focus_to(node_id);
+            },
+            dispatch_event,
+        );
+    }
+}
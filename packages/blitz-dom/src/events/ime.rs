@@ -1,13 +1,21 @@
 use std::{cell::RefCell, collections::HashMap};
 
-use blitz_text::{Cursor, Edit, text_system::Action};
+use blitz_text::{Action, Cursor, Edit};
 use blitz_traits::events::BlitzImeEvent;
 
 use crate::BaseDocument;
 
-// Thread-local IME composition state tracker for managing preedit text
+// Thread-local IME composition state tracker for managing preedit text, keyed by
+// (document id, node id)
 thread_local! {
-    static COMPOSITION_STATE: RefCell<HashMap<usize, CompositionInfo>> = RefCell::new(HashMap::new());
+    static COMPOSITION_STATE: RefCell<HashMap<(usize, usize), CompositionInfo>> = RefCell::new(HashMap::new());
+}
+
+/// Discard the composition state of a document's nodes (when the document is dropped)
+pub(crate) fn clear_composition_state(doc_id: usize) {
+    let _ = COMPOSITION_STATE.try_with(|state| {
+        state.borrow_mut().retain(|(doc, _), _| *doc != doc_id);
+    });
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +37,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
         if !has_text_input {
             return;
         }
+        let key = (doc.id(), node_id);
 
         match event {
             BlitzImeEvent::Enabled => { /* Do nothing */ }
@@ -36,7 +45,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
                 // Clear any active composition state
                 COMPOSITION_STATE.with(|state| {
                     let mut state = state.borrow_mut();
-                    if let Some(composition) = state.remove(&node_id) {
+                    if let Some(composition) = state.remove(&key) {
                         // Use with_text_and_nodes to avoid borrow conflicts
                         let _ = doc.with_text_and_nodes(|text_system, nodes| {
                             text_system.with_font_system(|font_system| {
@@ -48,7 +57,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
                                     // Clear existing preedit text by selecting and deleting it
                                     let preedit_end = composition.preedit_start + composition.preedit_text.len();
                                     editor.set_cursor(Cursor::new(0, composition.preedit_start));
-                                    editor.set_selection(blitz_text::text_system::Selection::Normal(
+                                    editor.set_selection(blitz_text::Selection::Normal(
                                         Cursor::new(0, preedit_end),
                                     ));
                                     editor.action(font_system, Action::Delete);
@@ -76,7 +85,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
                     let text_clone = text.clone();
                     COMPOSITION_STATE.with(|state| {
                         let mut state = state.borrow_mut();
-                        if let Some(composition) = state.remove(&node_id) {
+                        if let Some(composition) = state.remove(&key) {
                             // Use with_text_and_nodes to avoid borrow conflicts
                             let _ = doc.with_text_and_nodes(|text_system, nodes| {
                                 text_system.with_font_system(|font_system| {
@@ -88,7 +97,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
                                         // Replace preedit text with committed text
                                         let preedit_end = composition.preedit_start + composition.preedit_text.len();
                                         editor.set_cursor(Cursor::new(0, composition.preedit_start));
-                                        editor.set_selection(blitz_text::text_system::Selection::Normal(
+                                        editor.set_selection(blitz_text::Selection::Normal(
                                             Cursor::new(0, preedit_end),
                                         ));
                                         editor.action(font_system, Action::Delete);
@@ -122,7 +131,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
 
                         if text_clone.is_empty() {
                             // Clear composition when text is empty
-                            state.remove(&node_id);
+                            state.remove(&key);
                         } else {
                             // Use with_text_and_nodes to avoid borrow conflicts
                             let _ = doc.with_text_and_nodes(|text_system, nodes| {
@@ -133,10 +142,10 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
                                         let editor = &mut input_data.editor;
 
                                         // Clear any existing preedit text
-                                        if let Some(composition) = state.get(&node_id) {
+                                        if let Some(composition) = state.get(&key) {
                                             let preedit_end = composition.preedit_start + composition.preedit_text.len();
                                             editor.set_cursor(Cursor::new(0, composition.preedit_start));
-                                            editor.set_selection(blitz_text::text_system::Selection::Normal(
+                                            editor.set_selection(blitz_text::Selection::Normal(
                                                 Cursor::new(0, preedit_end),
                                             ));
                                             editor.action(font_system, Action::Delete);
@@ -158,7 +167,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
                                             if start != end {
                                                 let selection_end = preedit_start + end.min(text_clone.len());
                                                 editor.set_selection(
-                                                    blitz_text::text_system::Selection::Normal(Cursor::new(
+                                                    blitz_text::Selection::Normal(Cursor::new(
                                                         current_cursor.line,
                                                         selection_end,
                                                     )),
@@ -168,7 +177,7 @@ pub(crate) fn handle_ime_event(doc: &mut BaseDocument, event: BlitzImeEvent) {
 
                                         // Store composition info for later cleanup
                                         state.insert(
-                                            node_id,
+                                            key,
                                             CompositionInfo {
                                                 preedit_text: text_clone.clone(),
                                                 preedit_start,
@@ -1,13 +1,14 @@
 // Edit import removed - use blitz_text re-exports
-use blitz_text::{Edit, UnifiedTextSystem};
+use blitz_text::Edit;
 use blitz_traits::{
-    events::{BlitzInputEvent, BlitzKeyEvent, DomEvent, DomEventData},
+    events::{BlitzInputEvent, BlitzKeyEvent, DomEvent, DomEventData, MouseEventButton},
     shell::ShellProvider,
 };
 use keyboard_types::{Key, Modifiers, NamedKey};
 use markup5ever::local_name;
 
 // FontContext and LayoutContext replaced with blitz-text UnifiedTextSystem
+use crate::events::mouse::submit_event;
 use crate::{BaseDocument, node::TextInputData};
 
 #[derive(Debug, Clone)]
@@ -28,37 +29,29 @@ pub(crate) fn handle_keypress<F: FnMut(DomEvent)>(
 ) {
     // Handle Tab navigation (both forward and reverse)
     if event.key == Key::Named(NamedKey::Tab) {
-        // Generate Change and Blur events for the currently focused element before focus moves
-        // According to HTML Living Standard: Change events fire before Blur when focus moves
-        if let Some(current_focus_target) = doc.focus_node_id {
-            // Tab navigation commits any pending changes before moving focus
-            // Change event fires first ONLY if the input value has been modified (HTML standards-compliant)
-            if let Some(input_data) = doc.nodes[current_focus_target].element_data()
-                .and_then(|ed| ed.text_input_data()) {
-                if input_data.has_value_changed() {
-                    trigger_change_event(current_focus_target, &mut dispatch_event);
+        move_focus(
+            doc,
+            |doc| {
+                if event.modifiers.contains(Modifiers::SHIFT) {
+                    // Shift+Tab for reverse tab navigation
+                    doc.focus_previous_node();
+                } else {
+                    doc.focus_next_node();
                 }
-            }
-            // Blur event fires second as focus leaves the element
-            trigger_blur_event(current_focus_target, &mut dispatch_event);
-        }
-        
-        if event.modifiers.contains(Modifiers::SHIFT) {
-            // Shift+Tab for reverse tab navigation
-            doc.focus_previous_node();
-        } else {
-            doc.focus_next_node();
-        }
-        
-        // Generate Focus event for the newly focused element and capture original value
-        if let Some(new_focus_target) = doc.focus_node_id {
-            // Capture original value for HTML standards-compliant Change event detection
-            if let Some(input_data) = doc.nodes[new_focus_target].element_data_mut()
-                .and_then(|ed| ed.text_input_data_mut()) {
-                input_data.capture_original_value();
-            }
-            trigger_focus_event(new_focus_target, dispatch_event);
-        }
+            },
+            &mut dispatch_event,
+        );
+        return;
+    }
+
+    // The context menu key and Shift+F10 open a context menu for the focused element, as if it
+    // was right-clicked in its center
+    let is_shift_f10 =
+        event.key == Key::Named(NamedKey::F10) && event.modifiers.contains(Modifiers::SHIFT);
+    if event.key == Key::Named(NamedKey::ContextMenu) || is_shift_f10 {
+        let mut data = doc.nodes[target].synthetic_click_event_data(event.modifiers);
+        data.button = MouseEventButton::Secondary;
+        dispatch_event(DomEvent::new(target, DomEventData::ContextMenu(data)));
         return;
     }
 
@@ -113,10 +106,7 @@ pub(crate) fn handle_keypress<F: FnMut(DomEvent)>(
                         ));
                     }
                     GeneratedEvent::Submit => {
-                        // Generate submit event that can be handled by script
-                        dispatch_event(DomEvent::new(target, DomEventData::Submit));
-                        // Also perform implicit form submission for compatibility
-                        implicit_form_submission(doc, target);
+                        implicit_form_submission(doc, target, &mut dispatch_event);
                     }
                     GeneratedEvent::Blur => {
                         // Handle blur from various sources (Escape key, focus loss, etc.)
@@ -159,7 +149,7 @@ const ACTION_MOD: Modifiers = Modifiers::CONTROL;
 
 fn apply_keypress_event(
     input_data: &mut TextInputData,
-    text_system: &UnifiedTextSystem,
+    text_system: &crate::TextSystem,
     shell_provider: &dyn ShellProvider,
     event: BlitzKeyEvent,
 ) -> Option<GeneratedEvent> {
@@ -314,6 +304,41 @@ fn apply_keypress_event(
     })
 }
 
+/// Move focus with `change_focus`, dispatching the change and blur events of the element losing
+/// focus and the focus event of the one gaining it
+pub(crate) fn move_focus<F: FnMut(DomEvent)>(
+    doc: &mut BaseDocument,
+    change_focus: impl FnOnce(&mut BaseDocument),
+    mut dispatch_event: F,
+) {
+    // Generate Change and Blur events for the currently focused element before focus moves
+    // According to HTML Living Standard: Change events fire before Blur when focus moves
+    if let Some(current_focus_target) = doc.focus_node_id {
+        // Moving focus commits any pending changes
+        // Change event fires first ONLY if the input value has been modified (HTML standards-compliant)
+        if let Some(input_data) = doc.nodes[current_focus_target].element_data()
+            .and_then(|ed| ed.text_input_data()) {
+            if input_data.has_value_changed() {
+                trigger_change_event(current_focus_target, &mut dispatch_event);
+            }
+        }
+        // Blur event fires second as focus leaves the element
+        trigger_blur_event(current_focus_target, &mut dispatch_event);
+    }
+
+    change_focus(doc);
+
+    // Generate Focus event for the newly focused element and capture original value
+    if let Some(new_focus_target) = doc.focus_node_id {
+        // Capture original value for HTML standards-compliant Change event detection
+        if let Some(input_data) = doc.nodes[new_focus_target].element_data_mut()
+            .and_then(|ed| ed.text_input_data_mut()) {
+            input_data.capture_original_value();
+        }
+        trigger_focus_event(new_focus_target, dispatch_event);
+    }
+}
+
 /// Generate focus event when an element programmatically receives focus
 pub(crate) fn trigger_focus_event<F: FnMut(DomEvent)>(
     target: usize,
@@ -366,8 +391,14 @@ pub(crate) fn trigger_keypress_event<F: FnMut(DomEvent)>(
 }
 
 
+/// Submit the form owning `text_target` (through its `submit` event), if it is a form which can be
+/// submitted implicitly
 /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#field-that-blocks-implicit-submission
-fn implicit_form_submission(doc: &BaseDocument, text_target: usize) {
+fn implicit_form_submission<F: FnMut(DomEvent)>(
+    doc: &BaseDocument,
+    text_target: usize,
+    mut dispatch_event: F,
+) {
     let Some(form_owner_id) = doc.controls_to_form.get(&text_target) else {
         return;
     };
@@ -401,5 +432,5 @@ fn implicit_form_submission(doc: &BaseDocument, text_target: usize) {
         return;
     }
 
-    doc.submit_form(*form_owner_id, *form_owner_id);
+    dispatch_event(submit_event(*form_owner_id, *form_owner_id, None));
 }
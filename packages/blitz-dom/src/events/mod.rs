@@ -1,24 +1,34 @@
 mod driver;
+mod gamepad;
 mod ime;
 mod keyboard;
 mod mouse;
+mod pointer;
 
 use blitz_traits::events::{DomEvent, DomEventData};
 pub use driver::{EventDriver, EventHandler, NoopEventHandler};
-pub(crate) use ime::handle_ime_event;
+use gamepad::{handle_gamepad_axis, handle_gamepad_button};
+pub(crate) use ime::{clear_composition_state, handle_ime_event};
 pub(crate) use keyboard::handle_keypress;
-use mouse::handle_mouseup;
+use mouse::{handle_context_menu, handle_mouseup};
 pub(crate) use mouse::{handle_click, handle_mousedown, handle_mousemove};
+pub(crate) use pointer::PointerCaptures;
 
 use crate::BaseDocument;
 
 pub(crate) fn handle_dom_event<F: FnMut(DomEvent)>(
     doc: &mut BaseDocument,
     event: &mut DomEvent,
+    default_prevented: bool,
     dispatch_event: F,
 ) {
     let target_node_id = event.target;
 
+    // A click follows a mouseup whether or not its default action was prevented
+    if default_prevented && !matches!(event.data, DomEventData::MouseUp(_)) {
+        return;
+    }
+
     match &event.data {
         DomEventData::MouseMove(mouse_event) => {
             let changed = handle_mousemove(
@@ -41,6 +51,9 @@ pub(crate) fn handle_dom_event<F: FnMut(DomEvent)>(
         DomEventData::Click(event) => {
             handle_click(doc, target_node_id, event, dispatch_event);
         }
+        DomEventData::ContextMenu(_) => {
+            handle_context_menu(doc, target_node_id);
+        }
         DomEventData::KeyDown(event) => {
             handle_keypress(doc, target_node_id, event.clone(), dispatch_event);
         }
@@ -65,8 +78,28 @@ pub(crate) fn handle_dom_event<F: FnMut(DomEvent)>(
         DomEventData::Blur => {
             // Do nothing (no default action)
         }
-        DomEventData::Submit => {
-            // Do nothing (form submission is handled elsewhere)
+        DomEventData::Submit(event) => {
+            doc.submit_form_with_coordinates(target_node_id, event.submitter, event.coordinates);
+        }
+        DomEventData::TouchStart(_) | DomEventData::TouchMove(_) | DomEventData::TouchEnd(_) => {
+            // Do nothing (no default action)
+        }
+        DomEventData::PointerDown(_)
+        | DomEventData::PointerMove(_)
+        | DomEventData::PointerUp(_)
+        | DomEventData::PointerCancel(_)
+        | DomEventData::GotPointerCapture(_)
+        | DomEventData::LostPointerCapture(_) => {
+            // Do nothing (no default action)
+        }
+        DomEventData::GamepadButtonDown(event) => {
+            handle_gamepad_button(doc, event, dispatch_event);
+        }
+        DomEventData::GamepadButtonUp(_) => {
+            // Do nothing (no default action)
+        }
+        DomEventData::GamepadAxisMove(event) => {
+            handle_gamepad_axis(doc, event, dispatch_event);
         }
     }
 }
@@ -1,11 +1,12 @@
 use blitz_text::Edit;
-use blitz_text::text_system::Action;
+use blitz_text::Action;
 use blitz_traits::{
     events::{
-        BlitzInputEvent, BlitzMouseButtonEvent, DomEvent, DomEventData, MouseEventButton,
-        MouseEventButtons,
+        BlitzInputEvent, BlitzMouseButtonEvent, BlitzSubmitEvent, DomEvent, DomEventData,
+        MouseEventButton, MouseEventButtons,
     },
     navigation::NavigationOptions,
+    shell::ContextMenuRequest,
 };
 use markup5ever::local_name;
 
@@ -103,7 +104,14 @@ pub(crate) fn handle_mousedown(doc: &mut BaseDocument, target: usize, x: f32, y:
         (content_box_offset, disabled, has_text_input)
     };
 
-    if disabled || !has_text_input {
+    if disabled {
+        return;
+    }
+    if !has_text_input {
+        // Pressing on an element focusses the focusable element it's in, if any
+        if let Some(focus_target) = focusable_ancestor(doc, target) {
+            doc.set_focus_from_pointer(focus_target);
+        }
         return;
     }
 
@@ -128,7 +136,20 @@ pub(crate) fn handle_mousedown(doc: &mut BaseDocument, target: usize, x: f32, y:
         });
     });
 
-    doc.set_focus_to(hit.node_id);
+    doc.set_focus_from_pointer(hit.node_id);
+}
+
+/// The nearest focusable element which is `node_id` or contains it
+fn focusable_ancestor(doc: &BaseDocument, node_id: usize) -> Option<usize> {
+    let mut maybe_node_id = Some(node_id);
+    while let Some(id) = maybe_node_id {
+        let node = &doc.nodes[id];
+        if node.is_focussable() {
+            return Some(id);
+        }
+        maybe_node_id = node.parent;
+    }
+    None
 }
 
 pub(crate) fn handle_mouseup<F: FnMut(DomEvent)>(
@@ -168,6 +189,11 @@ pub(crate) fn handle_mouseup<F: FnMut(DomEvent)>(
         return;
     }
 
+    // Right-clicking opens a context menu
+    if event.button == MouseEventButton::Secondary {
+        dispatch_event(DomEvent::new(target, DomEventData::ContextMenu(event.clone())));
+    }
+
     // Determine whether to dispatch a click event
     let do_click = true;
     // let do_click = doc.mouse_down_node.is_some_and(|mouse_down_id| {
@@ -183,6 +209,34 @@ pub(crate) fn handle_mouseup<F: FnMut(DomEvent)>(
     }
 }
 
+/// Ask the shell to show a context menu for `target`, with what it was opened on: the text input
+/// it's in (and its selected text) and the link it's in
+pub(crate) fn handle_context_menu(doc: &BaseDocument, target: usize) {
+    let mut request = ContextMenuRequest {
+        node_id: target,
+        ..Default::default()
+    };
+    let mut maybe_node_id = Some(target);
+    while let Some(node_id) = maybe_node_id {
+        let node = &doc.nodes[node_id];
+        maybe_node_id = node.parent;
+        let Some(el) = node.element_data() else {
+            continue;
+        };
+
+        if let Some(input_data) = el.text_input_data() {
+            request.editable = true;
+            let selected_text = input_data.editor.copy_selection();
+            request.selected_text = selected_text.filter(|text| !text.is_empty());
+        } else if el.name.local == local_name!("a") && request.link.is_none() {
+            let href = el.attr(local_name!("href"));
+            let url = href.and_then(|href| doc.url.resolve_relative(href));
+            request.link = url.map(|url| url.to_string());
+        }
+    }
+    doc.shell_provider.show_context_menu(request);
+}
+
 pub(crate) fn handle_click<F: FnMut(DomEvent)>(
     doc: &mut BaseDocument,
     target: usize,
@@ -217,7 +271,7 @@ pub(crate) fn handle_click<F: FnMut(DomEvent)>(
                 node_id,
                 DomEventData::Input(BlitzInputEvent { value }),
             ));
-            doc.set_focus_to(node_id);
+            doc.set_focus_from_pointer(node_id);
             return;
         } else if el.name.local == local_name!("input")
             && matches!(el.attr(local_name!("type")), Some("radio"))
@@ -244,7 +298,7 @@ pub(crate) fn handle_click<F: FnMut(DomEvent)>(
                 DomEventData::Input(BlitzInputEvent { value }),
             ));
 
-            doc.set_focus_to(node_id);
+            doc.set_focus_from_pointer(node_id);
 
             return;
         }
@@ -269,11 +323,10 @@ pub(crate) fn handle_click<F: FnMut(DomEvent)>(
         } else if el.name.local == local_name!("a") {
             if let Some(href) = el.attr(local_name!("href")) {
                 if let Some(url) = doc.url.resolve_relative(href) {
-                    doc.navigation_provider.navigate_to(NavigationOptions::new(
-                        url,
-                        String::from("text/plain"),
-                        doc.id(),
-                    ));
+                    let download = el.attr(local_name!("download")).map(String::from);
+                    let options = NavigationOptions::new(url, String::from("text/plain"), doc.id())
+                        .set_download(download);
+                    doc.navigation_provider.navigate_to(options);
                 } else {
                     println!("{href} is not parseable as a url. : {:?}", *doc.url)
                 }
@@ -284,28 +337,22 @@ pub(crate) fn handle_click<F: FnMut(DomEvent)>(
         } else if (el.name.local == local_name!("input")
             && el.attr(local_name!("type")) == Some("submit")
             || el.name.local == local_name!("button"))
-            && let Some(form_owner) = doc.controls_to_form.get(&node_id)
+            && let Some(&form_owner) = doc.controls_to_form.get(&node_id)
         {
-            doc.submit_form(*form_owner, node_id);
+            dispatch_event(submit_event(form_owner, node_id, None));
         } else if el.name.local == local_name!("input")
             && el.attr(local_name!("type")) == Some("image")
-            && let Some(form_owner) = doc.controls_to_form.get(&node_id)
+            && let Some(&form_owner) = doc.controls_to_form.get(&node_id)
         {
             // Use existing hit detection for element-relative coordinates
-            if let Some(hit) = doc.hit(event.x, event.y) {
-                doc.submit_form_with_coordinates(
-                    *form_owner,
-                    node_id,
-                    Some((hit.x as i32, hit.y as i32)),
-                );
-            } else {
+            let coordinates = doc.hit(event.x, event.y).map(|hit| (hit.x as i32, hit.y as i32));
+            if coordinates.is_none() {
                 eprintln!(
                     "Warning: Click on image button {} has no hit result",
                     node_id
                 );
-                // Fallback to submitting without coordinates
-                doc.submit_form(*form_owner, node_id);
             }
+            dispatch_event(submit_event(form_owner, node_id, coordinates));
             return;
         }
 
@@ -313,6 +360,24 @@ pub(crate) fn handle_click<F: FnMut(DomEvent)>(
         maybe_node_id = doc.nodes[node_id].parent;
     }
 
-    // If nothing is matched then clear focus
-    doc.clear_focus();
+    // If nothing is matched then clear focus, unless the click was on a focusable element (which
+    // was focussed when it was pressed)
+    if focusable_ancestor(doc, target).is_none() {
+        doc.clear_focus();
+    }
+}
+
+/// The `submit` event of the form `form_id`, whose default action submits the form
+pub(crate) fn submit_event(
+    form_id: usize,
+    submitter: usize,
+    coordinates: Option<(i32, i32)>,
+) -> DomEvent {
+    DomEvent::new(
+        form_id,
+        DomEventData::Submit(BlitzSubmitEvent {
+            submitter,
+            coordinates,
+        }),
+    )
 }
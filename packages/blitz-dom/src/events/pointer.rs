@@ -0,0 +1,96 @@
+//! Pointer capture, see <https://w3c.github.io/pointerevents/#pointer-capture>
+//!
+//! A captured pointer's events (and its compatibility mouse events) are targeted at the element
+//! capturing it rather than at the element under it, until it's released. Touches are captured
+//! by the element they start on.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::BaseDocument;
+
+#[derive(Debug, Default)]
+pub(crate) struct PointerCaptures {
+    /// The element each captured pointer is captured by
+    targets: HashMap<u64, usize>,
+    /// Captures set or released since the pointer's last event, which take effect (firing
+    /// `gotpointercapture` and `lostpointercapture` events) before its next one
+    pending: HashMap<u64, Option<usize>>,
+    /// The pointers which are pressed, and so can be captured
+    pressed: HashSet<u64>,
+    /// Pressed pointers whose `pointerdown` event was cancelled, which don't get compatibility
+    /// mouse events until they're released
+    suppressed: HashSet<u64>,
+}
+
+impl BaseDocument {
+    /// Target the events of a pressed pointer at an element until the pointer is released, see
+    /// <https://w3c.github.io/pointerevents/#dom-element-setpointercapture>
+    ///
+    /// The capture takes effect before the pointer's next event. Does nothing if the pointer
+    /// isn't pressed.
+    pub fn set_pointer_capture(&mut self, node_id: usize, pointer_id: u64) {
+        let is_element = self.get_node(node_id).is_some_and(|node| node.is_element());
+        if is_element && self.pointer_captures.pressed.contains(&pointer_id) {
+            self.pointer_captures.pending.insert(pointer_id, Some(node_id));
+        }
+    }
+
+    /// Stop targeting a pointer's events at an element which captured it
+    pub fn release_pointer_capture(&mut self, node_id: usize, pointer_id: u64) {
+        if self.has_pointer_capture(node_id, pointer_id) {
+            self.pointer_captures.pending.insert(pointer_id, None);
+        }
+    }
+
+    /// Whether an element has captured a pointer (or will have, by the pointer's next event)
+    pub fn has_pointer_capture(&self, node_id: usize, pointer_id: u64) -> bool {
+        let pending = self.pointer_captures.pending.get(&pointer_id);
+        let target = pending.copied().unwrap_or_else(|| self.pointer_capture(pointer_id));
+        target == Some(node_id)
+    }
+
+    /// The element which has captured a pointer, if any
+    pub fn pointer_capture(&self, pointer_id: u64) -> Option<usize> {
+        let target = self.pointer_captures.targets.get(&pointer_id).copied();
+        // Elements which have been removed lose their captures
+        target.filter(|&node_id| self.get_node(node_id).is_some())
+    }
+
+    /// Make a pointer's pending capture take effect, returning the elements which lost and got
+    /// the capture if it changed
+    pub(crate) fn process_pending_pointer_capture(
+        &mut self,
+        pointer_id: u64,
+    ) -> Option<(Option<usize>, Option<usize>)> {
+        let pending = self.pointer_captures.pending.remove(&pointer_id)?;
+        let current = self.pointer_capture(pointer_id);
+        match pending {
+            Some(node_id) => self.pointer_captures.targets.insert(pointer_id, node_id),
+            None => self.pointer_captures.targets.remove(&pointer_id),
+        };
+        (pending != current).then_some((current, pending))
+    }
+
+    pub(crate) fn press_pointer(&mut self, pointer_id: u64) {
+        self.pointer_captures.pressed.insert(pointer_id);
+    }
+
+    /// Release a pointer, and with it its capture
+    pub(crate) fn release_pointer(&mut self, pointer_id: u64) {
+        self.pointer_captures.pressed.remove(&pointer_id);
+        self.pointer_captures.suppressed.remove(&pointer_id);
+        if self.pointer_capture(pointer_id).is_some() {
+            self.pointer_captures.pending.insert(pointer_id, None);
+        } else {
+            self.pointer_captures.pending.remove(&pointer_id);
+        }
+    }
+
+    pub(crate) fn suppress_compat_mouse_events(&mut self, pointer_id: u64) {
+        self.pointer_captures.suppressed.insert(pointer_id);
+    }
+
+    pub(crate) fn compat_mouse_events_suppressed(&self, pointer_id: u64) -> bool {
+        self.pointer_captures.suppressed.contains(&pointer_id)
+    }
+}
@@ -0,0 +1,265 @@
+//! Loading status of the fonts declared by a document's `@font-face` rules
+//!
+//! Loosely modelled on the [CSS Font Loading API](https://drafts.csswg.org/css-font-loading/#FontFaceSet-interface).
+//! Each `@font-face` source which is fetched is tracked as a [`FontFace`]. A face is first
+//! *loading*, and then becomes *loaded* once its data has been registered with the text system
+//! (or *error* if it could not be fetched or parsed). Embedders can:
+//!
+//!  - query the status of every face with [`FontFaceSet::faces`]
+//!  - wait for all pending faces to settle with [`FontFaceSet::ready`]
+//!  - receive a [`FontFaceEvent`] as each face settles with [`FontFaceSet::add_listener`]
+//!
+//! The set is shared with the network handlers which fetch the fonts, so it is cheap to clone and
+//! can be used from any thread.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use url::Url;
+
+/// The loading status of a single [`FontFace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontFaceStatus {
+    Loading,
+    Loaded,
+    Error,
+}
+
+/// The loading status of a [`FontFaceSet`] as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontFaceSetStatus {
+    /// At least one face is still loading
+    Loading,
+    /// Every face has either loaded or failed
+    Loaded,
+}
+
+/// A font source declared by an `@font-face` rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    pub id: usize,
+    /// The `font-family` descriptor of the rule
+    pub family: Option<String>,
+    pub url: Url,
+    pub status: FontFaceStatus,
+    /// Why the face failed to load (if it did)
+    pub error: Option<String>,
+}
+
+/// Emitted by a [`FontFaceSet`] when a face settles
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontFaceEvent {
+    /// The face was loaded and is available to the text system
+    Load(FontFace),
+    /// The face could not be fetched or parsed
+    Error(FontFace),
+    /// There are no faces left loading (like the `loadingdone` DOM event)
+    LoadingDone,
+}
+
+type Listener = Arc<dyn Fn(&FontFaceEvent) + Send + Sync>;
+
+#[derive(Default)]
+struct FontFaceSetInner {
+    faces: Vec<FontFace>,
+    listeners: Vec<Listener>,
+    wakers: Vec<Waker>,
+}
+
+impl FontFaceSetInner {
+    fn is_loading(&self) -> bool {
+        self.faces
+            .iter()
+            .any(|face| face.status == FontFaceStatus::Loading)
+    }
+}
+
+/// The fonts declared by a document's `@font-face` rules
+#[derive(Clone, Default)]
+pub struct FontFaceSet(Arc<Mutex<FontFaceSetInner>>);
+
+impl std::fmt::Debug for FontFaceSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontFaceSet")
+            .field("faces", &self.lock().faces)
+            .finish()
+    }
+}
+
+impl FontFaceSet {
+    fn lock(&self) -> MutexGuard<'_, FontFaceSetInner> {
+        // A panicking listener can't leave the set in an inconsistent state, so ignore poisoning
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// A snapshot of every face which has been requested
+    pub fn faces(&self) -> Vec<FontFace> {
+        self.lock().faces.clone()
+    }
+
+    pub fn face(&self, id: usize) -> Option<FontFace> {
+        self.lock().faces.get(id).cloned()
+    }
+
+    pub fn status(&self) -> FontFaceSetStatus {
+        match self.lock().is_loading() {
+            true => FontFaceSetStatus::Loading,
+            false => FontFaceSetStatus::Loaded,
+        }
+    }
+
+    /// Whether every face has either loaded or failed
+    pub fn is_ready(&self) -> bool {
+        self.status() == FontFaceSetStatus::Loaded
+    }
+
+    /// A future which resolves once no faces are loading. Faces which are requested before it
+    /// resolves (e.g. from a stylesheet which is still being fetched) are waited for too.
+    pub fn ready(&self) -> FontsReady {
+        FontsReady(self.clone())
+    }
+
+    /// Call `listener` every time a face settles
+    pub fn add_listener(&self, listener: impl Fn(&FontFaceEvent) + Send + Sync + 'static) {
+        self.lock().listeners.push(Arc::new(listener));
+    }
+
+    /// Start tracking a face. Returns `None` if a face with the same url is already tracked, in
+    /// which case it doesn't need to be fetched again.
+    pub(crate) fn register(&self, family: Option<String>, url: Url) -> Option<usize> {
+        let mut inner = self.lock();
+        if inner.faces.iter().any(|face| face.url == url) {
+            return None;
+        }
+        let id = inner.faces.len();
+        inner.faces.push(FontFace {
+            id,
+            family,
+            url,
+            status: FontFaceStatus::Loading,
+            error: None,
+        });
+        Some(id)
+    }
+
+    pub(crate) fn mark_loaded(&self, id: usize) {
+        self.settle(id, FontFaceStatus::Loaded, None);
+    }
+
+    pub(crate) fn mark_error(&self, id: usize, error: impl Into<String>) {
+        self.settle(id, FontFaceStatus::Error, Some(error.into()));
+    }
+
+    /// Fail every face which is still loading and drop the listeners. Called when the document is
+    /// dropped, so that anything waiting on [`FontFaceSet::ready`] is woken.
+    pub(crate) fn close(&self) {
+        let loading: Vec<usize> = self
+            .lock()
+            .faces
+            .iter()
+            .filter(|face| face.status == FontFaceStatus::Loading)
+            .map(|face| face.id)
+            .collect();
+        for id in loading {
+            self.mark_error(id, "Document was dropped");
+        }
+        // Settling the last face woke any waiters
+        self.lock().listeners.clear();
+    }
+
+    fn settle(&self, id: usize, status: FontFaceStatus, error: Option<String>) {
+        let (face, done, listeners, wakers) = {
+            let mut inner = self.lock();
+            let Some(face) = inner.faces.get_mut(id) else {
+                return;
+            };
+            if face.status != FontFaceStatus::Loading {
+                return;
+            }
+            face.status = status;
+            face.error = error;
+            let face = face.clone();
+
+            let done = !inner.is_loading();
+            let wakers = match done {
+                true => std::mem::take(&mut inner.wakers),
+                false => Vec::new(),
+            };
+            (face, done, inner.listeners.clone(), wakers)
+        };
+
+        // Listeners are called without holding the lock so that they can query the set
+        let event = match status {
+            FontFaceStatus::Error => FontFaceEvent::Error(face),
+            _ => FontFaceEvent::Load(face),
+        };
+        for listener in &listeners {
+            listener(&event);
+        }
+        if done {
+            for listener in &listeners {
+                listener(&FontFaceEvent::LoadingDone);
+            }
+        }
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Future returned by [`FontFaceSet::ready`]
+pub struct FontsReady(FontFaceSet);
+
+impl Future for FontsReady {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.0.lock();
+        if !inner.is_loading() {
+            return Poll::Ready(());
+        }
+        if !inner.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            inner.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com/").unwrap().join(path).unwrap()
+    }
+
+    #[test]
+    fn settles_faces_and_notifies_listeners() {
+        let set = FontFaceSet::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        set.add_listener(move |event| events_clone.lock().unwrap().push(event.clone()));
+
+        let a = set.register(Some("A".into()), url("a.woff2")).unwrap();
+        let b = set.register(Some("B".into()), url("b.ttf")).unwrap();
+        assert_eq!(set.register(Some("A".into()), url("a.woff2")), None);
+        assert_eq!(set.status(), FontFaceSetStatus::Loading);
+
+        set.mark_loaded(a);
+        assert!(!set.is_ready());
+        set.mark_error(b, "404");
+        assert!(set.is_ready());
+        // Settling twice is ignored
+        set.mark_loaded(b);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], FontFaceEvent::Load(face) if face.id == a));
+        assert!(
+            matches!(&events[1], FontFaceEvent::Error(face) if face.error.as_deref() == Some("404"))
+        );
+        assert_eq!(events[2], FontFaceEvent::LoadingDone);
+    }
+}
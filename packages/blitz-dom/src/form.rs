@@ -1,13 +1,14 @@
 use core::str::FromStr;
 use std::fmt::Display;
+use std::path::{Path, PathBuf};
 
 use blitz_text::Edit;
-use blitz_traits::navigation::NavigationOptions;
+use blitz_traits::navigation::{FormEntry, FormEntryValue, NavigationOptions};
 use markup5ever::{LocalName, local_name};
 
 use crate::{
     BaseDocument, ElementData,
-    node::FileData,
+    node::{FileData, FileInputData, SpecialElementData},
     traversal::{AncestorTraverser, TreeTraverser},
 };
 
@@ -126,6 +127,47 @@ impl BaseDocument {
         }
     }
 
+    /// Sets the files picked for an `<input type=file>` (by the embedder's file dialog, say) to
+    /// those at `paths`. Their contents aren't read: forms stream them from disk as they're sent.
+    /// Only the first file is kept for inputs without a `multiple` attribute.
+    ///
+    /// Returns `false` if the node isn't a file input, or one of the files can't be read.
+    pub fn set_selected_files(&mut self, node_id: usize, paths: &[PathBuf]) -> bool {
+        let Some(element) = self.nodes[node_id].element_data_mut() else {
+            return false;
+        };
+        let is_file_input = element.name.local == local_name!("input")
+            && element.attr(local_name!("type")) == Some("file");
+        if !is_file_input {
+            return false;
+        }
+
+        let multiple = element.has_attr(local_name!("multiple"));
+        let paths = if multiple { paths } else { &paths[..paths.len().min(1)] };
+        let mut selected_files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let Ok(metadata) = std::fs::metadata(path) else {
+                return false;
+            };
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+            selected_files.push(FileData {
+                name: name.unwrap_or_default(),
+                content_type: content_type_for(path).to_string(),
+                size: metadata.len(),
+                data: Vec::new(),
+                path: Some(path.clone()),
+            });
+        }
+
+        let accept = element.attr(local_name!("accept")).map(str::to_string);
+        element.special_data = SpecialElementData::FileInput(FileInputData {
+            selected_files,
+            accept,
+            multiple,
+        });
+        true
+    }
+
     /// Submits a form with the given form node ID and submitter node ID
     ///
     /// # Arguments
@@ -175,6 +217,7 @@ impl BaseDocument {
         .unwrap_or(RequestContentType::FormUrlEncoded);
 
         let mut post_resource = None;
+        let mut form_data = None;
 
         match (scheme, method) {
             ("http" | "https" | "data", FormMethod::Get) => {
@@ -194,9 +237,7 @@ impl BaseDocument {
                     post_resource = Some(body.into());
                 }
                 RequestContentType::MultipartFormData => {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("Multipart Forms are currently not supported");
-                    return;
+                    form_data = Some(entry.convert_to_form_data());
                 }
                 RequestContentType::TextPlain => {
                     let pairs = entry.convert_to_list_of_name_value_pairs();
@@ -264,7 +305,8 @@ impl BaseDocument {
 
         let navigation_options =
             NavigationOptions::new(parsed_action, enctype.to_string(), self.id())
-                .set_document_resource(post_resource);
+                .set_document_resource(post_resource)
+                .set_form_data(form_data);
 
         self.navigation_provider.navigate_to(navigation_options)
     }
@@ -324,6 +366,7 @@ impl BaseDocument {
         .unwrap_or(RequestContentType::FormUrlEncoded);
 
         let mut post_resource = None;
+        let mut form_data = None;
 
         match (scheme, method) {
             ("http" | "https" | "data", FormMethod::Get) => {
@@ -343,9 +386,7 @@ impl BaseDocument {
                     post_resource = Some(body.into());
                 }
                 RequestContentType::MultipartFormData => {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("Multipart Forms are currently not supported");
-                    return;
+                    form_data = Some(entry.convert_to_form_data());
                 }
                 RequestContentType::TextPlain => {
                     let pairs = entry.convert_to_list_of_name_value_pairs();
@@ -413,7 +454,8 @@ impl BaseDocument {
 
         let navigation_options =
             NavigationOptions::new(parsed_action, enctype.to_string(), self.id())
-                .set_document_resource(post_resource);
+                .set_document_resource(post_resource)
+                .set_form_data(form_data);
 
         self.navigation_provider.navigate_to(navigation_options)
     }
@@ -629,6 +671,7 @@ fn construct_entry_list(
                     content_type: "application/octet-stream".to_string(),
                     size: 0,
                     data: Vec::new(),
+                    path: None,
                 }));
             } else {
                 // Otherwise, for each file in selected files, create an entry with name and a
@@ -677,6 +720,26 @@ fn construct_entry_list(
 ///
 /// # Returns
 /// A new string with normalized CRLF line endings
+/// The content type of a picked file, from its extension
+fn content_type_for(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("txt") => "text/plain",
+        Some("html" | "htm") => "text/html",
+        Some("css") => "text/css",
+        Some("csv") => "text/csv",
+        Some("json") => "application/json",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
 fn normalize_line_endings(input: &str) -> String {
     // Replace every occurrence of U+000D (CR) not followed by U+000A (LF),
     // and every occurrence of U+000A (LF) not preceded by U+000D (CR),
@@ -825,6 +888,31 @@ impl EntryList {
             })
             .collect()
     }
+
+    /// Converts the entry list to the entries of a `multipart/form-data` body, normalizing line
+    /// endings in names and text values
+    ///
+    /// <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#multipart/form-data-encoding-algorithm>
+    pub fn convert_to_form_data(&self) -> Vec<FormEntry> {
+        self.0
+            .iter()
+            .map(|entry| {
+                let value = match &entry.value {
+                    EntryValue::Text(text) => FormEntryValue::Text(normalize_line_endings(text)),
+                    EntryValue::File(file_data) => FormEntryValue::File {
+                        name: file_data.name.clone(),
+                        content_type: file_data.content_type.clone(),
+                        data: file_data.data.clone().into(),
+                        path: file_data.path.clone(),
+                    },
+                };
+                FormEntry {
+                    name: normalize_line_endings(&entry.name),
+                    value,
+                }
+            })
+            .collect()
+    }
 }
 
 /// Entry value type for form submission
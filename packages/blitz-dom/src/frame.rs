@@ -0,0 +1,223 @@
+//! A frame clock shared by everything which animates
+//!
+//! The shell drives the clock: before resolving each frame it calls
+//! [`BaseDocument::tick_animation_frame`] with the time of the frame, which runs the callbacks
+//! registered with [`BaseDocument::request_animation_frame`] (like `requestAnimationFrame` on the
+//! web) and advances the CSS animations and transitions which stylo started while styling to the
+//! time of the frame, restyling the elements they animate. After painting it
+//! reports how long the frame took with [`BaseDocument::finish_animation_frame`], so that
+//! callbacks doing expensive work (like decoding video) can check how much of the frame budget
+//! is left in [`FrameStats`].
+//!
+//! Callbacks requested while callbacks are running (for example a callback which requests
+//! itself, to run every frame) run in the next frame rather than the current one.
+
+use std::time::Duration;
+
+use style::animation::AnimationState;
+use style::invalidation::element::restyle_hints::RestyleHint;
+
+use crate::BaseDocument;
+
+/// Called with the document and the time of the frame, in milliseconds (like a
+/// `DOMHighResTimeStamp`)
+pub type FrameCallback = Box<dyn FnOnce(&mut BaseDocument, f64)>;
+
+/// Identifies a callback registered with [`BaseDocument::request_animation_frame`], so that it
+/// can be cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FrameCallbackId(u64);
+
+/// Timing of the frames produced so far
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    /// The number of frames which have been ticked
+    pub frames: u64,
+    /// The time each frame should take, `1/60s` unless the shell knows the display's refresh rate
+    pub budget: Duration,
+    /// The time of the current frame
+    pub timestamp: Duration,
+    /// The time between the previous frame and the current one
+    pub interval: Duration,
+    /// How long producing the last finished frame took (running callbacks, styling, layout and
+    /// painting)
+    pub frame_time: Duration,
+    /// The number of frames which took longer than the budget
+    pub over_budget_frames: u64,
+    /// The number of frames which were skipped because the clock ticked late
+    pub skipped_frames: u64,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self {
+            frames: 0,
+            budget: FrameScheduler::DEFAULT_BUDGET,
+            timestamp: Duration::ZERO,
+            interval: Duration::ZERO,
+            frame_time: Duration::ZERO,
+            over_budget_frames: 0,
+            skipped_frames: 0,
+        }
+    }
+}
+
+impl FrameStats {
+    /// The part of the budget which the last finished frame didn't use
+    pub fn budget_remaining(&self) -> Duration {
+        self.budget.saturating_sub(self.frame_time)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct FrameScheduler {
+    next_id: u64,
+    callbacks: Vec<(FrameCallbackId, FrameCallback)>,
+    /// Whether the callbacks of the current frame are running
+    running: bool,
+    /// Callbacks which were cancelled while the callbacks of the current frame were running
+    cancelled: Vec<FrameCallbackId>,
+    stats: FrameStats,
+}
+
+impl FrameScheduler {
+    const DEFAULT_BUDGET: Duration = Duration::from_nanos(1_000_000_000 / 60);
+}
+
+impl BaseDocument {
+    /// Run `callback` at the start of the next frame, with the time of that frame
+    pub fn request_animation_frame(
+        &mut self,
+        callback: impl FnOnce(&mut BaseDocument, f64) + 'static,
+    ) -> FrameCallbackId {
+        let scheduler = &mut self.frame_scheduler;
+        scheduler.next_id += 1;
+        let id = FrameCallbackId(scheduler.next_id);
+        scheduler.callbacks.push((id, Box::new(callback)));
+        self.shell_provider.request_redraw();
+        id
+    }
+
+    /// Don't run a callback registered with
+    /// [`request_animation_frame`](Self::request_animation_frame)
+    pub fn cancel_animation_frame(&mut self, id: FrameCallbackId) {
+        let scheduler = &mut self.frame_scheduler;
+        scheduler.callbacks.retain(|(callback_id, _)| *callback_id != id);
+        // The callback may be one of the current frame's, which have been taken out to run
+        if scheduler.running {
+            scheduler.cancelled.push(id);
+        }
+    }
+
+    /// Whether any callbacks are waiting for the next frame
+    pub fn has_animation_frame_callbacks(&self) -> bool {
+        !self.frame_scheduler.callbacks.is_empty()
+    }
+
+    /// Start a frame at `timestamp` (measured from any fixed point, as long as it is the same for
+    /// every frame), running the callbacks waiting for it
+    pub fn tick_animation_frame(&mut self, timestamp: Duration) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "animation_frame",
+            callbacks = self.frame_scheduler.callbacks.len(),
+        )
+        .entered();
+
+        let stats = &mut self.frame_scheduler.stats;
+        if stats.frames > 0 {
+            stats.interval = timestamp.saturating_sub(stats.timestamp);
+            // A frame which starts more than one and a half budgets after the last one means the
+            // display refreshed without a new frame at least once
+            let intervals = stats.interval.as_secs_f64() / stats.budget.as_secs_f64();
+            if intervals >= 1.5 {
+                stats.skipped_frames += intervals.round() as u64 - 1;
+            }
+        }
+        stats.frames += 1;
+        stats.timestamp = timestamp;
+        self.update_css_animations(timestamp.as_secs_f64());
+
+        let callbacks = std::mem::take(&mut self.frame_scheduler.callbacks);
+        let milliseconds = timestamp.as_secs_f64() * 1000.0;
+        self.frame_scheduler.running = true;
+        for (id, callback) in callbacks {
+            if !self.frame_scheduler.cancelled.contains(&id) {
+                callback(self, milliseconds);
+            }
+        }
+        self.frame_scheduler.running = false;
+        self.frame_scheduler.cancelled.clear();
+    }
+
+    /// Record that the current frame has been painted, `frame_time` after it was ticked
+    pub fn finish_animation_frame(&mut self, frame_time: Duration) {
+        let stats = &mut self.frame_scheduler.stats;
+        stats.frame_time = frame_time;
+        if frame_time > stats.budget {
+            stats.over_budget_frames += 1;
+        }
+    }
+
+    /// Set the time each frame should take (by the shell, from the display's refresh rate)
+    pub fn set_frame_budget(&mut self, budget: Duration) {
+        self.frame_scheduler.stats.budget = budget;
+    }
+
+    /// Timing of the frames produced so far
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_scheduler.stats
+    }
+
+    /// Whether any CSS animations or transitions are pending or running
+    pub(crate) fn has_running_css_animations(&self) -> bool {
+        let sets = self.animations.sets.read();
+        sets.values().any(|set| set.needs_animation_ticks())
+    }
+
+    /// Start the pending CSS animations and transitions, and finish those which have ended at
+    /// `now` (in seconds), restyling the elements with any which are running or just changed
+    /// state
+    fn update_css_animations(&mut self, now: f64) {
+        let mut animating = Vec::new();
+        for (key, set) in self.animations.sets.write().iter_mut() {
+            let mut changed = false;
+            for animation in &mut set.animations {
+                if animation.state == AnimationState::Pending {
+                    animation.state = AnimationState::Running;
+                }
+                if animation.state == AnimationState::Running {
+                    changed |= animation.iterate_if_necessary(now);
+                    if animation.has_ended(now) {
+                        animation.state = AnimationState::Finished;
+                    }
+                }
+            }
+            for transition in &mut set.transitions {
+                if transition.state == AnimationState::Pending {
+                    transition.state = AnimationState::Running;
+                }
+                if transition.state == AnimationState::Running && transition.has_ended(now) {
+                    transition.state = AnimationState::Finished;
+                    changed = true;
+                }
+            }
+            if changed || set.needs_animation_ticks() {
+                animating.push(key.node.0);
+            }
+        }
+
+        let hint = RestyleHint::RESTYLE_CSS_ANIMATIONS | RestyleHint::RESTYLE_CSS_TRANSITIONS;
+        for node_id in animating {
+            if let Some(node) = self.nodes.get_mut(node_id) {
+                node.set_restyle_hint(hint);
+            }
+        }
+    }
+
+    /// The time of the current frame, in seconds, which CSS animations and transitions are
+    /// sampled at
+    pub(crate) fn animation_time(&self) -> f64 {
+        self.frame_scheduler.stats.timestamp.as_secs_f64()
+    }
+}
@@ -0,0 +1,239 @@
+//! Hit-testing which matches what's painted
+//!
+//! [`BaseDocument::hit`] finds the single node events are dispatched to.
+//! [`BaseDocument::hit_test`] finds every element under a point, which embedders need for things
+//! like custom context menus. It follows painting:
+//!
+//! - Elements are hit within their border box, with its corners rounded by `border-radius` and
+//!   transformed by their `transform` (which, like when painting, doesn't apply to descendants).
+//! - Descendants are clipped to the padding box by `overflow` and paint containment, and
+//!   everything is clipped by `clip-path`.
+//! - Elements with `pointer-events: none` or which aren't visible aren't hit, though their
+//!   descendants can be.
+//! - Inline elements (which don't have boxes of their own) are hit where their text is laid out.
+//!
+//! [`BaseDocument::painted_border_box`] finds where an element's border box is painted, the same
+//! way except that the transforms of the elements it's in apply to it too.
+
+use blitz_traits::events::HitResult;
+use peniko::kurbo::{Affine, BezPath, Point, Rect, Shape as _, Vec2};
+use style::properties::ComputedValues;
+use style::properties::generated::longhands::pointer_events::computed_value::T as PointerEvents;
+use style::properties::generated::longhands::visibility::computed_value::T as Visibility;
+use style::values::computed::{BorderCornerRadius, Length, Overflow};
+use style::values::specified::Contain;
+
+use crate::clip_path::{clip_region, rounded_rect_path};
+use crate::{BaseDocument, Node};
+
+impl BaseDocument {
+    /// Every element under the point (`x`, `y`), topmost first. Points are in the same
+    /// coordinates as [`hit`](Self::hit)'s, and each result's are relative to the top-left corner
+    /// of the element's border box (or of its inline formatting context's content box, for
+    /// inline elements).
+    pub fn hit_test(&self, x: f32, y: f32) -> Vec<HitResult> {
+        let mut hits = Vec::new();
+        if let Some(root) = self.try_root_element() {
+            hit_test_node(root, Point::new(x.into(), y.into()), &mut hits);
+        }
+        hits
+    }
+
+    /// The bounds of an element's border box as it's painted, in the same coordinates as
+    /// [`hit`](Self::hit)'s points: transformed by its own `transform` and those of the elements
+    /// it's in, and moved by their scroll offsets. `None` if the element isn't laid out or has no
+    /// area.
+    pub fn painted_border_box(&self, node_id: usize) -> Option<Rect> {
+        let node = self.get_node(node_id)?;
+        let layout = &node.final_layout;
+        if layout.size.width <= 0.0 || layout.size.height <= 0.0 {
+            return None;
+        }
+        let style = node.primary_styles()?;
+        let size = Rect::new(0.0, 0.0, layout.size.width.into(), layout.size.height.into());
+        let location = Vec2::new(layout.location.x.into(), layout.location.y.into());
+        let mut transform = Affine::translate(location) * element_transform(&style, layout.size);
+
+        let mut parent_id = node.layout_parent.get();
+        while let Some(parent) = parent_id.and_then(|parent_id| self.get_node(parent_id)) {
+            let layout = &parent.final_layout;
+            let mut content_offset = -parent.scroll_offset.to_vec2();
+            if parent.flags.is_inline_root() {
+                content_offset += Vec2::new(
+                    (layout.border.left + layout.padding.left).into(),
+                    (layout.border.top + layout.padding.top).into(),
+                );
+            }
+            let parent_transform = parent
+                .primary_styles()
+                .map_or(Affine::IDENTITY, |style| element_transform(&style, layout.size));
+            let location = Vec2::new(layout.location.x.into(), layout.location.y.into());
+            transform = Affine::translate(location)
+                * parent_transform
+                * Affine::translate(content_offset)
+                * transform;
+            parent_id = parent.layout_parent.get();
+        }
+        Some(transform.transform_rect_bbox(size))
+    }
+}
+
+/// Add the hits on `node` and its descendants at `point` (relative to the top-left corner of its
+/// parent's border box) to `hits`, topmost first
+fn hit_test_node(node: &Node, point: Point, hits: &mut Vec<HitResult>) {
+    let Some(style) = node.primary_styles() else {
+        return;
+    };
+    if matches!(node.style().display, taffy::Display::None) {
+        return;
+    }
+    let layout = &node.final_layout;
+    let point = point - Vec2::new(layout.location.x.into(), layout.location.y.into());
+
+    // The point within the element's transformed border box
+    let transform = element_transform(&style, layout.size);
+    if transform.determinant().abs() < f64::EPSILON {
+        // Elements transformed down to nothing aren't painted
+        return;
+    }
+    let box_point = transform.inverse() * point;
+
+    if let Some(clip) = clip_region(&style, layout) {
+        if !clip.contains(box_point) {
+            return;
+        }
+    }
+
+    let box_style = style.get_box();
+    let clips_contents = node.containment().contains(Contain::PAINT)
+        || box_style.overflow_x != Overflow::Visible
+        || box_style.overflow_y != Overflow::Visible;
+    let contents_hit = !node.flags.skips_contents()
+        && (!clips_contents || padding_box_shape(&style, layout).contains(box_point));
+    if contents_hit {
+        let mut content_point = point + node.scroll_offset.to_vec2();
+        if node.flags.is_inline_root() {
+            let content_box_offset = Vec2::new(
+                (layout.border.left + layout.padding.left).into(),
+                (layout.border.top + layout.padding.top).into(),
+            );
+            content_point -= content_box_offset;
+        }
+        // Children are painted in order, so the last ones are on top
+        for &child_id in node.paint_children.borrow().iter().flatten().rev() {
+            hit_test_node(node.with(child_id), content_point, hits);
+        }
+        if node.flags.is_inline_root() {
+            hit_test_inline_elements(node, content_point, hits);
+        }
+    }
+
+    if is_hittable(node, &style) && border_box_shape(&style, layout).contains(box_point) {
+        hits.push(HitResult {
+            node_id: node.id,
+            x: box_point.x as f32,
+            y: box_point.y as f32,
+        });
+    }
+}
+
+/// Add the hits on the inline elements (like an `<a>` in a `<p>`) whose text is under `point`
+/// (relative to the inline root's content box) to `hits`, innermost first
+fn hit_test_inline_elements(root: &Node, point: Point, hits: &mut Vec<HitResult>) {
+    let Some(text_layout) = root
+        .element_data()
+        .and_then(|element| element.inline_layout_data.as_ref())
+    else {
+        return;
+    };
+    let Some(text_node_id) = text_layout.text_node_at(point.x as f32, point.y as f32) else {
+        return;
+    };
+
+    let mut parent = root.with(text_node_id).parent;
+    while let Some(parent_id) = parent.filter(|&parent_id| parent_id != root.id) {
+        let element = root.with(parent_id);
+        if element.primary_styles().is_some_and(|style| is_hittable(element, &style)) {
+            hits.push(HitResult {
+                node_id: parent_id,
+                x: point.x as f32,
+                y: point.y as f32,
+            });
+        }
+        parent = element.parent;
+    }
+}
+
+/// Whether `node` itself (rather than its descendants) can be hit
+fn is_hittable(node: &Node, style: &ComputedValues) -> bool {
+    node.is_element()
+        && style.get_inherited_box().visibility == Visibility::Visible
+        && style.clone_pointer_events() != PointerEvents::None
+}
+
+/// The 2D transform `style` applies to the element's border box, as it's painted. 3D transforms
+/// aren't painted, so aren't applied.
+fn element_transform(style: &ComputedValues, size: taffy::Size<f32>) -> Affine {
+    let box_style = style.get_box();
+    if box_style.transform.0.is_empty() {
+        return Affine::IDENTITY;
+    }
+    let Ok((matrix, false)) = box_style.transform.to_transform_3d_matrix(None) else {
+        return Affine::IDENTITY;
+    };
+    let origin = &box_style.transform_origin;
+    let origin = Vec2::new(
+        origin.horizontal.resolve(Length::new(size.width)).px().into(),
+        origin.vertical.resolve(Length::new(size.height)).px().into(),
+    );
+    let matrix = [matrix.m11, matrix.m12, matrix.m21, matrix.m22, matrix.m41, matrix.m42];
+    Affine::translate(origin) * Affine::new(matrix.map(f64::from)) * Affine::translate(-origin)
+}
+
+/// The element's border box, with its corners rounded by `border-radius`
+fn border_box_shape(style: &ComputedValues, layout: &taffy::Layout) -> BezPath {
+    let size = layout.size.map(f64::from);
+    let border_box = Rect::new(0.0, 0.0, size.width, size.height);
+    rounded_rect_path(border_box, border_radii(style, size))
+}
+
+/// The element's padding box, with its corners rounded by `border-radius` less the border
+fn padding_box_shape(style: &ComputedValues, layout: &taffy::Layout) -> BezPath {
+    let size = layout.size.map(f64::from);
+    let border = layout.border.map(f64::from);
+    let padding_box = Rect::new(
+        border.left,
+        border.top,
+        size.width - border.right,
+        size.height - border.bottom,
+    );
+    let [top_left, top_right, bottom_right, bottom_left] = border_radii(style, size);
+    let shrink = |radius: Vec2, x: f64, y: f64| {
+        Vec2::new((radius.x - x).max(0.0), (radius.y - y).max(0.0))
+    };
+    let radii = [
+        shrink(top_left, border.left, border.top),
+        shrink(top_right, border.right, border.top),
+        shrink(bottom_right, border.right, border.bottom),
+        shrink(bottom_left, border.left, border.bottom),
+    ];
+    rounded_rect_path(padding_box, radii)
+}
+
+/// The radii of the corners of the border box (top-left, top-right, bottom-right then
+/// bottom-left), in CSS px
+fn border_radii(style: &ComputedValues, size: taffy::Size<f64>) -> [Vec2; 4] {
+    let border = style.get_border();
+    let resolve = |radius: &BorderCornerRadius| {
+        Vec2::new(
+            radius.0.width.0.resolve(Length::new(size.width as f32)).px().into(),
+            radius.0.height.0.resolve(Length::new(size.height as f32)).px().into(),
+        )
+    };
+    [
+        resolve(&border.border_top_left_radius),
+        resolve(&border.border_top_right_radius),
+        resolve(&border.border_bottom_right_radius),
+        resolve(&border.border_bottom_left_radius),
+    ]
+}
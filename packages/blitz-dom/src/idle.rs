@@ -0,0 +1,160 @@
+//! Low-priority work which runs when frames have time to spare
+//!
+//! Like `requestIdleCallback` on the web, [`BaseDocument::request_idle_callback`] queues work
+//! which shouldn't delay rendering. The shell calls [`BaseDocument::run_idle_callbacks`] in idle
+//! periods (when the event loop has nothing else to do, or between animation frames with time
+//! left in their budget), and callbacks run until the period's deadline passes. Each callback is
+//! given an [`IdleDeadline`] so that it can split long work up, requesting another callback to
+//! carry on.
+//!
+//! A callback requested with a timeout runs in the first idle period after the timeout passes,
+//! even if that period has no time left.
+//!
+//! The document uses idle periods itself to fetch and decode lazily-loaded images
+//! (`<img loading="lazy">`) speculatively. Documents only have idle periods when their embedder
+//! runs them, so embedders which don't should call [`BaseDocument::run_idle_callbacks`]
+//! themselves.
+
+use std::time::Duration;
+
+// `std::time::Instant` isn't available on wasm32
+use web_time::Instant;
+
+use crate::BaseDocument;
+
+/// Called with the document and the deadline of the idle period it runs in
+pub type IdleCallback = Box<dyn FnOnce(&mut BaseDocument, IdleDeadline)>;
+
+/// Identifies a callback registered with [`BaseDocument::request_idle_callback`], so that it can
+/// be cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IdleCallbackId(u64);
+
+/// When the idle period an [`IdleCallback`] runs in ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleDeadline {
+    deadline: Instant,
+    did_timeout: bool,
+}
+
+impl IdleDeadline {
+    /// The time left in the idle period, which is zero once it has ended
+    pub fn time_remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the callback is running because its timeout passed, rather than because there was
+    /// time to spare
+    pub fn did_timeout(&self) -> bool {
+        self.did_timeout
+    }
+}
+
+struct PendingIdleCallback {
+    id: IdleCallbackId,
+    timeout: Option<Instant>,
+    callback: IdleCallback,
+}
+
+#[derive(Default)]
+pub(crate) struct IdleScheduler {
+    next_id: u64,
+    callbacks: Vec<PendingIdleCallback>,
+    /// Whether the callbacks of the current idle period are running
+    running: bool,
+    /// Callbacks which were cancelled while the callbacks of the current idle period were running
+    cancelled: Vec<IdleCallbackId>,
+}
+
+impl IdleScheduler {
+    /// The longest an idle period lasts, so that input arriving during one isn't noticeably delayed
+    pub(crate) const MAX_PERIOD: Duration = Duration::from_millis(50);
+}
+
+impl BaseDocument {
+    /// Run `callback` when the document is idle, or once `timeout` has passed (if given)
+    pub fn request_idle_callback(
+        &mut self,
+        callback: impl FnOnce(&mut BaseDocument, IdleDeadline) + 'static,
+        timeout: Option<Duration>,
+    ) -> IdleCallbackId {
+        let scheduler = &mut self.idle_scheduler;
+        scheduler.next_id += 1;
+        let id = IdleCallbackId(scheduler.next_id);
+        scheduler.callbacks.push(PendingIdleCallback {
+            id,
+            timeout: timeout.map(|timeout| Instant::now() + timeout),
+            callback: Box::new(callback),
+        });
+        id
+    }
+
+    /// Don't run a callback registered with [`request_idle_callback`](Self::request_idle_callback)
+    pub fn cancel_idle_callback(&mut self, id: IdleCallbackId) {
+        let scheduler = &mut self.idle_scheduler;
+        scheduler.callbacks.retain(|pending| pending.id != id);
+        // The callback may be one of the current idle period's, which have been taken out to run
+        if scheduler.running {
+            scheduler.cancelled.push(id);
+        }
+    }
+
+    /// Whether any callbacks are waiting for an idle period
+    pub fn has_idle_callbacks(&self) -> bool {
+        !self.idle_scheduler.callbacks.is_empty()
+    }
+
+    /// The earliest timeout of the waiting callbacks, which the shell should wake up for
+    pub fn next_idle_timeout(&self) -> Option<Instant> {
+        let callbacks = &self.idle_scheduler.callbacks;
+        callbacks.iter().filter_map(|pending| pending.timeout).min()
+    }
+
+    /// The deadline of an idle period starting now: the end of the current frame's budget while
+    /// animating, or 50ms from now otherwise. `frame_start` is when the current frame was ticked.
+    pub fn idle_deadline(&self, frame_start: Instant) -> Instant {
+        let period_end = Instant::now() + IdleScheduler::MAX_PERIOD;
+        if self.is_animating() {
+            period_end.min(frame_start + self.frame_stats().budget)
+        } else {
+            period_end
+        }
+    }
+
+    /// Run the waiting callbacks in order until `deadline`, along with any whose timeout has
+    /// passed. Callbacks requested meanwhile wait for the next idle period. Returns the number of
+    /// callbacks which ran.
+    pub fn run_idle_callbacks(&mut self, deadline: Instant) -> usize {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "idle_callbacks",
+            callbacks = self.idle_scheduler.callbacks.len(),
+        )
+        .entered();
+
+        let callbacks = std::mem::take(&mut self.idle_scheduler.callbacks);
+        let mut waiting = Vec::new();
+        let mut ran = 0;
+        self.idle_scheduler.running = true;
+        for pending in callbacks {
+            if self.idle_scheduler.cancelled.contains(&pending.id) {
+                continue;
+            }
+            let now = Instant::now();
+            let did_timeout = pending.timeout.is_some_and(|timeout| timeout <= now);
+            if now < deadline || did_timeout {
+                (pending.callback)(self, IdleDeadline { deadline, did_timeout });
+                ran += 1;
+            } else {
+                waiting.push(pending);
+            }
+        }
+        self.idle_scheduler.running = false;
+        self.idle_scheduler.cancelled.clear();
+
+        // Callbacks which didn't get to run go before those requested by the ones which did
+        waiting.append(&mut self.idle_scheduler.callbacks);
+        self.idle_scheduler.callbacks = waiting;
+        ran
+    }
+}
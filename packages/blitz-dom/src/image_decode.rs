@@ -0,0 +1,689 @@
+//! Decoding images off the main thread
+//!
+//! [`ImageHandler`](crate::net::ImageHandler)s hand the images they receive to a pool of worker
+//! threads, which decode the most urgent images first. Decoded bitmaps are kept in a cache keyed
+//! by URL and target size, whose memory is managed by the global [`CacheCoordinator`], so that
+//! an image used in several places (or by several documents) is only decoded once.
+//!
+//! Images can be decoded at the size they're displayed at rather than their natural size, which
+//! saves a lot of memory when a large photo is shown as a thumbnail. JPEGs are then decoded at a
+//! fraction of their size (with DCT scaling), which is also much faster than decoding them whole.
+//!
+//! The decodes of `<img>` elements follow their layout (see [`ImageDecode`]): after each layout
+//! they're given the size the image is laid out at in device pixels, and the urgency of whether
+//! it's in the viewport. Images which were decoded smaller than they're now laid out (like after
+//! the page is zoomed in) are decoded again.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use blitz_traits::net::{Bytes, RequestPriority, SharedCallback, Url};
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use jpeg_decoder::PixelFormat;
+
+use crate::BaseDocument;
+use crate::net::Resource;
+use crate::node::RasterImageData;
+use crate::util::ImageType;
+
+/// The most worker threads images are decoded on
+const MAX_WORKERS: usize = 4;
+
+/// Decode `bytes` (fetched from `url`, if known) as a raster image, scaled down to cover
+/// `target_size` if it's smaller than the image. Decoded images are cached by URL and target
+/// size, and reused while the bytes they were decoded from are the same.
+pub(crate) fn decode_image(
+    url: Option<&Url>,
+    bytes: &[u8],
+    target_size: Option<(u32, u32)>,
+) -> Option<RasterImageData> {
+    let cache = DecodedImageCache::global();
+    let key = url.map(|url| CacheKey {
+        url: url.clone(),
+        target_size,
+    });
+    let source_hash = BuildHasherDefault::<DefaultHasher>::default().hash_one(bytes);
+    if let Some(image) = key.as_ref().and_then(|key| cache.get(key, source_hash)) {
+        return Some(image);
+    }
+
+    let format = image::guess_format(bytes).ok()?;
+    let scaled_jpeg = match (format, target_size) {
+        (ImageFormat::Jpeg, Some(target_size)) => decode_jpeg_scaled(bytes, target_size),
+        _ => None,
+    };
+    let (image, natural_size) = match scaled_jpeg {
+        Some(decoded) => decoded,
+        None => {
+            let image = image::load_from_memory_with_format(bytes, format).ok()?;
+            let natural_size = (image.width(), image.height());
+            (image, natural_size)
+        }
+    };
+    let image = match target_size {
+        Some(target_size) => scale_to_cover(image, target_size),
+        None => image,
+    };
+
+    let (width, height) = (image.width(), image.height());
+    let (natural_width, natural_height) = natural_size;
+    let data = Arc::new(image.into_rgba8().into_raw());
+    let image = RasterImageData::new(width, height, data)
+        .with_natural_size(natural_width, natural_height);
+    if let Some(key) = key {
+        cache.insert(key, source_hash, image.clone());
+    }
+    Some(image)
+}
+
+/// Decode a JPEG at the smallest fraction of its size which covers `target_size`, returning it
+/// along with its natural size. Fails for JPEGs which are better decoded by `image` (CMYK and
+/// 16-bit ones).
+fn decode_jpeg_scaled(
+    bytes: &[u8],
+    target_size: (u32, u32),
+) -> Option<(DynamicImage, (u32, u32))> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    let natural_size = (u32::from(info.width), u32::from(info.height));
+    if !matches!(info.pixel_format, PixelFormat::RGB24 | PixelFormat::L8) {
+        return None;
+    }
+
+    let (cover_width, cover_height) = cover_size(natural_size, target_size)?;
+    let clamp = |size: u32| u16::try_from(size).unwrap_or(u16::MAX);
+    let (width, height) = decoder.scale(clamp(cover_width), clamp(cover_height)).ok()?;
+    let pixels = decoder.decode().ok()?;
+    let (width, height) = (u32::from(width), u32::from(height));
+    let image = match info.pixel_format {
+        PixelFormat::RGB24 => DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, pixels)?),
+        _ => DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, pixels)?),
+    };
+    Some((image, natural_size))
+}
+
+/// Scale `image` down to the smallest size (with the same aspect ratio) which covers
+/// `target_size`, unless it's that small already
+fn scale_to_cover(image: DynamicImage, target_size: (u32, u32)) -> DynamicImage {
+    match cover_size((image.width(), image.height()), target_size) {
+        Some((width, height)) => image.resize_exact(width, height, FilterType::Triangle),
+        None => image,
+    }
+}
+
+/// The smallest size with the aspect ratio of `size` which covers `target_size`, or `None` if
+/// `size` isn't larger than that
+fn cover_size((width, height): (u32, u32), target_size: (u32, u32)) -> Option<(u32, u32)> {
+    let (width, height) = (u64::from(width), u64::from(height));
+    let (target_width, target_height) = (u64::from(target_size.0), u64::from(target_size.1));
+    // Scale by whichever of the ratios of the target size to the size is greater
+    let (width, height) = if target_width * height >= target_height * width {
+        if target_width >= width {
+            return None;
+        }
+        (target_width, (height * target_width).div_ceil(width))
+    } else {
+        if target_height >= height {
+            return None;
+        }
+        ((width * target_height).div_ceil(height), target_height)
+    };
+    let clamp = |size: u64| u32::try_from(size.max(1)).unwrap_or(u32::MAX);
+    Some((clamp(width), clamp(height)))
+}
+
+/// How urgently a decode is needed, which may change while it waits for a worker (like when the
+/// image is scrolled into view)
+#[derive(Clone)]
+pub(crate) struct DecodePriority(Arc<AtomicU8>);
+
+impl Default for DecodePriority {
+    fn default() -> Self {
+        Self::new(RequestPriority::Normal)
+    }
+}
+
+impl DecodePriority {
+    pub(crate) fn new(priority: RequestPriority) -> Self {
+        let decode_priority = Self(Arc::new(AtomicU8::new(0)));
+        decode_priority.set(priority);
+        decode_priority
+    }
+
+    pub(crate) fn get(&self) -> RequestPriority {
+        match self.0.load(Ordering::Relaxed) {
+            0 => RequestPriority::High,
+            1 => RequestPriority::Normal,
+            _ => RequestPriority::Low,
+        }
+    }
+
+    pub(crate) fn set(&self, priority: RequestPriority) {
+        let value = match priority {
+            RequestPriority::High => 0,
+            RequestPriority::Normal => 1,
+            RequestPriority::Low => 2,
+        };
+        self.0.store(value, Ordering::Relaxed);
+    }
+}
+
+/// A decode waiting for a worker
+struct Job {
+    priority: DecodePriority,
+    /// The order jobs were queued in, so that jobs of the same priority are run in that order
+    seq: u64,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+impl Job {
+    /// Jobs with the greatest key are run first
+    fn key(&self) -> Reverse<(RequestPriority, u64)> {
+        Reverse((self.priority.get(), self.seq))
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    /// The jobs waiting for a worker. Their priorities can change while they wait, so they aren't
+    /// kept in order but searched for the most urgent one.
+    jobs: Vec<Job>,
+    next_seq: u64,
+    workers: usize,
+    /// The workers waiting for a job
+    idle: usize,
+}
+
+impl Queue {
+    fn push(&mut self, priority: DecodePriority, run: Box<dyn FnOnce() + Send>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.jobs.push(Job {
+            priority,
+            seq,
+            run,
+        });
+    }
+
+    /// Take the most urgent job
+    fn pop(&mut self) -> Option<Job> {
+        let (index, _) = self.jobs.iter().enumerate().max_by_key(|(_, job)| job.key())?;
+        Some(self.jobs.swap_remove(index))
+    }
+}
+
+/// The worker threads images are decoded on, which are started as they're needed
+#[derive(Default)]
+pub(crate) struct DecodePool {
+    queue: Mutex<Queue>,
+    job_queued: Condvar,
+}
+
+impl DecodePool {
+    pub(crate) fn global() -> &'static DecodePool {
+        static GLOBAL: OnceLock<DecodePool> = OnceLock::new();
+        GLOBAL.get_or_init(DecodePool::default)
+    }
+
+    /// Run `job` on a worker once the jobs of a higher `priority` (and those of the same priority
+    /// queued before it) have been run. Jobs are run on the calling thread where threads aren't
+    /// available.
+    pub(crate) fn spawn(
+        &'static self,
+        priority: DecodePriority,
+        job: impl FnOnce() + Send + 'static,
+    ) {
+        let mut queue = self.lock();
+        queue.push(priority, Box::new(job));
+
+        if queue.idle == 0 && queue.workers < max_workers() {
+            let name = format!("blitz-image-decode-{}", queue.workers);
+            let worker = std::thread::Builder::new().name(name).spawn(move || self.work());
+            if worker.is_ok() {
+                queue.workers += 1;
+            }
+        }
+        if queue.workers == 0 {
+            let mut pending = Queue {
+                jobs: std::mem::take(&mut queue.jobs),
+                ..Queue::default()
+            };
+            drop(queue);
+            while let Some(job) = pending.pop() {
+                run(job);
+            }
+            return;
+        }
+        drop(queue);
+        self.job_queued.notify_one();
+    }
+
+    fn work(&self) {
+        loop {
+            let mut queue = self.lock();
+            let job = loop {
+                if let Some(job) = queue.pop() {
+                    break job;
+                }
+                queue.idle += 1;
+                queue = self.job_queued.wait(queue).unwrap_or_else(|err| err.into_inner());
+                queue.idle -= 1;
+            };
+            drop(queue);
+            run(job);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Run `job`, carrying on if it panics (as decoders may on malformed images)
+fn run(job: Job) {
+    let _ = std::panic::catch_unwind(AssertUnwindSafe(job.run));
+}
+
+fn max_workers() -> usize {
+    if cfg!(target_arch = "wasm32") {
+        return 0;
+    }
+    let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+    (parallelism / 2).clamp(1, MAX_WORKERS)
+}
+
+/// What an image was fetched for and its bytes, for decoding it (again)
+pub(crate) struct DecodeSource {
+    pub(crate) doc_id: usize,
+    pub(crate) node_id: usize,
+    pub(crate) kind: ImageType,
+    pub(crate) url: Option<Url>,
+    pub(crate) bytes: Bytes,
+    pub(crate) callback: SharedCallback<Resource>,
+}
+
+/// An image as it was last decoded
+struct Decoded {
+    size: (u32, u32),
+    natural_size: (u32, u32),
+    /// What the image was decoded from, kept while it's smaller than its natural size so that it
+    /// can be decoded again when it's laid out larger
+    source: Option<DecodeSource>,
+}
+
+#[derive(Default)]
+struct DecodeState {
+    target_size: Option<(u32, u32)>,
+    decoded: Option<Decoded>,
+}
+
+/// The decode of an image, which is told the size the image is laid out at and how urgently it's
+/// needed as those change
+#[derive(Default)]
+pub(crate) struct ImageDecode {
+    priority: DecodePriority,
+    state: Mutex<DecodeState>,
+}
+
+impl ImageDecode {
+    pub(crate) fn priority(&self) -> DecodePriority {
+        self.priority.clone()
+    }
+
+    pub(crate) fn target_size(&self) -> Option<(u32, u32)> {
+        self.lock().target_size
+    }
+
+    pub(crate) fn set_target_size(&self, target_size: Option<(u32, u32)>) {
+        self.lock().target_size = target_size;
+    }
+
+    /// Decode the image in `source` as a raster image at the current target size, and deliver it
+    /// to the source's callback. Returns `false` if it isn't a raster image.
+    pub(crate) fn decode(&self, source: DecodeSource) -> bool {
+        let target_size = self.target_size();
+        let Some(image) = decode_image(source.url.as_ref(), &source.bytes, target_size) else {
+            return false;
+        };
+
+        let size = (image.width, image.height);
+        let natural_size = (image.natural_width, image.natural_height);
+        let scaled_down = size.0 < natural_size.0 || size.1 < natural_size.1;
+        let resource = Resource::Image(source.node_id, source.kind.clone(), image);
+        let (doc_id, callback) = (source.doc_id, source.callback.clone());
+        self.lock().decoded = Some(Decoded {
+            size,
+            natural_size,
+            source: scaled_down.then_some(source),
+        });
+        callback.call(doc_id, Ok(resource));
+        true
+    }
+
+    /// Follow the layout of the image: decode it at `target_size` from now on (and again, if it
+    /// was decoded smaller than that), with the urgency of `priority`
+    pub(crate) fn update(
+        self: &Arc<Self>,
+        target_size: Option<(u32, u32)>,
+        priority: RequestPriority,
+    ) {
+        self.priority.set(priority);
+        let mut state = self.lock();
+        state.target_size = target_size;
+
+        let Some(target_size) = target_size else {
+            return;
+        };
+        // Images are only decoded again once they're laid out larger than they were decoded. The
+        // source is handed to the new decode, which keeps it again if it's still needed.
+        let too_small = |decoded: &mut Decoded| {
+            let needed = cover_size(decoded.natural_size, target_size);
+            let (width, height) = needed.unwrap_or(decoded.natural_size);
+            decoded.source.is_some() && (width > decoded.size.0 || height > decoded.size.1)
+        };
+        let Some(Decoded {
+            source: Some(source),
+            ..
+        }) = state.decoded.take_if(too_small)
+        else {
+            return;
+        };
+        drop(state);
+        let decode = self.clone();
+        DecodePool::global().spawn(self.priority(), move || {
+            decode.decode(source);
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DecodeState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl BaseDocument {
+    /// Tell the decodes of `<img>` elements the size (in device pixels) their images are laid
+    /// out at, and decode those in the viewport first
+    pub(crate) fn update_image_decodes(&mut self) {
+        let scale = self.viewport.scale();
+        let viewport_size = self.stylist.device().au_viewport_size();
+        let min_x = self.viewport_scroll.x as f32;
+        let min_y = self.viewport_scroll.y as f32;
+        let max_x = min_x + viewport_size.width.to_f32_px();
+        let max_y = min_y + viewport_size.height.to_f32_px();
+
+        let nodes = &self.nodes;
+        self.image_decodes.retain(|&node_id, decode| {
+            let Some(node) = nodes.get(node_id).filter(|node| node.is_in_document()) else {
+                return false;
+            };
+
+            let layout = &node.final_layout;
+            let width = layout.size.width
+                - layout.padding.horizontal_axis_sum()
+                - layout.border.horizontal_axis_sum();
+            let height = layout.size.height
+                - layout.padding.vertical_axis_sum()
+                - layout.border.vertical_axis_sum();
+            // Images which aren't sized by their style are laid out at their natural size (and
+            // with no size until they're decoded)
+            let device_pixels = |size: f32| (size.max(0.0) * scale).ceil() as u32;
+            let target_size = (device_pixels(width), device_pixels(height));
+            let target_size = (target_size != (0, 0)).then_some(target_size);
+
+            let position = node.absolute_position(0.0, 0.0);
+            let in_view = position.x + layout.size.width >= min_x
+                && position.x <= max_x
+                && position.y + layout.size.height >= min_y
+                && position.y <= max_y;
+            let priority = if in_view {
+                RequestPriority::High
+            } else {
+                RequestPriority::Low
+            };
+
+            decode.update(target_size, priority);
+            true
+        });
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    url: Url,
+    target_size: Option<(u32, u32)>,
+}
+
+struct CachedImage {
+    image: RasterImageData,
+    /// The hash of the bytes the image was decoded from, so that changed images (like app assets
+    /// which are reloaded) are decoded again
+    source_hash: u64,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    images: HashMap<CacheKey, CachedImage>,
+    /// Counts uses, for evicting the least recently used images first
+    clock: u64,
+}
+
+/// Decoded images, by URL and target size
+#[derive(Default)]
+struct DecodedImageCache {
+    entries: Mutex<CacheEntries>,
+}
+
+impl DecodedImageCache {
+    fn global() -> &'static DecodedImageCache {
+        static GLOBAL: OnceLock<Arc<DecodedImageCache>> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let cache = Arc::new(DecodedImageCache::default());
+            let managed_cache: Arc<dyn ManagedCache> = cache.clone();
+            CacheCoordinator::global().register(&managed_cache, 2);
+            cache
+        })
+    }
+
+    fn get(&self, key: &CacheKey, source_hash: u64) -> Option<RasterImageData> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let cached = entries.images.get_mut(key)?;
+        if cached.source_hash != source_hash {
+            return None;
+        }
+        cached.last_used = clock;
+        Some(cached.image.clone())
+    }
+
+    fn insert(&self, key: CacheKey, source_hash: u64, image: RasterImageData) {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let last_used = entries.clock;
+        let cached = CachedImage {
+            image,
+            source_hash,
+            last_used,
+        };
+        entries.images.insert(key, cached);
+        drop(entries);
+        CacheCoordinator::global().enforce_budget();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheEntries> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Evicted images are decoded again the next time they're loaded. Documents keep the images they
+/// display, so evicting those only frees memory once the documents are done with them.
+impl ManagedCache for DecodedImageCache {
+    fn name(&self) -> &str {
+        "decoded images"
+    }
+
+    fn memory_usage(&self) -> usize {
+        let entries = self.lock();
+        entries.images.values().map(|cached| cached.image.data.len()).sum()
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        let mut entries = self.lock();
+        let mut usage: usize = entries.images.values().map(|cached| cached.image.data.len()).sum();
+        if usage <= target_bytes {
+            return;
+        }
+
+        let mut images: Vec<(CacheKey, u64, usize)> = entries
+            .images
+            .iter()
+            .map(|(key, cached)| (key.clone(), cached.last_used, cached.image.data.len()))
+            .collect();
+        images.sort_by_key(|&(_, last_used, _)| last_used);
+        for (key, _, len) in images {
+            if usage <= target_bytes {
+                break;
+            }
+            entries.images.remove(&key);
+            usage -= len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_scaled_to_cover_their_target_size() {
+        assert_eq!(cover_size((4000, 3000), (400, 400)), Some((534, 400)));
+        assert_eq!(cover_size((4000, 3000), (200, 600)), Some((800, 600)));
+        assert_eq!(cover_size((400, 300), (400, 400)), None);
+        assert_eq!(cover_size((0, 300), (100, 100)), None);
+    }
+
+    #[test]
+    fn urgent_jobs_run_first() {
+        let mut queue = Queue::default();
+        let low = DecodePriority::new(RequestPriority::Low);
+        queue.push(low.clone(), Box::new(|| {}));
+        queue.push(DecodePriority::new(RequestPriority::Normal), Box::new(|| {}));
+        queue.push(DecodePriority::new(RequestPriority::High), Box::new(|| {}));
+        queue.push(DecodePriority::new(RequestPriority::Normal), Box::new(|| {}));
+        let order: Vec<u64> = std::iter::from_fn(|| queue.pop()).map(|job| job.seq).collect();
+        assert_eq!(order, [2, 1, 3, 0]);
+
+        // Jobs whose priority is raised while they wait are run sooner
+        queue.push(DecodePriority::new(RequestPriority::Normal), Box::new(|| {}));
+        queue.push(low.clone(), Box::new(|| {}));
+        low.set(RequestPriority::High);
+        assert_eq!(queue.pop().map(|job| job.seq), Some(5));
+    }
+
+    /// A PNG of `width` by `height` pixels
+    fn png(width: u32, height: u32) -> Bytes {
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::new(width, height));
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        Bytes::from(bytes.into_inner())
+    }
+
+    #[test]
+    fn images_are_decoded_again_when_laid_out_larger() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sender = Mutex::new(sender);
+        let callback = move |_doc_id: usize, result: Result<Resource, Option<String>>| {
+            if let Ok(Resource::Image(_, _, image)) = result {
+                let _ = sender.lock().unwrap().send(image);
+            }
+        };
+        let source = DecodeSource {
+            doc_id: 0,
+            node_id: 0,
+            kind: ImageType::Image,
+            url: None,
+            bytes: png(64, 32),
+            callback: Arc::new(callback),
+        };
+        let decoded_size = || {
+            let image = receiver.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+            assert_eq!((image.natural_width, image.natural_height), (64, 32));
+            (image.width, image.height)
+        };
+
+        let decode = Arc::new(ImageDecode::default());
+        decode.update(Some((16, 8)), RequestPriority::High);
+        assert!(decode.decode(source));
+        assert_eq!(decoded_size(), (16, 8));
+
+        // Images laid out smaller aren't decoded again
+        decode.update(Some((8, 4)), RequestPriority::High);
+        assert!(receiver.try_recv().is_err());
+
+        decode.update(Some((32, 16)), RequestPriority::High);
+        assert_eq!(decoded_size(), (32, 16));
+
+        // Images at their natural size don't need decoding again
+        decode.update(Some((128, 64)), RequestPriority::High);
+        assert_eq!(decoded_size(), (64, 32));
+        decode.update(Some((256, 128)), RequestPriority::High);
+        assert!(receiver.recv_timeout(std::time::Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn img_decodes_follow_layout() {
+        use blitz_traits::shell::Viewport;
+        use markup5ever::{LocalName, QualName, ns};
+        use selectors::matching::QuirksMode;
+
+        use crate::{Attribute, DocumentConfig};
+
+        let config = DocumentConfig {
+            viewport: Some(Viewport {
+                window_size: (400, 200),
+                hidpi_scale: 2.0,
+                ..Viewport::default()
+            }),
+            ..DocumentConfig::for_testing()
+        };
+        let mut doc = BaseDocument::new(config).unwrap();
+        let mut mutr = doc.mutate();
+        let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+        let mut img = |style: &str| {
+            let attrs = [("src", "https://example.com/image.png"), ("style", style)];
+            let attrs = attrs
+                .into_iter()
+                .map(|(name, value)| Attribute {
+                    name: QualName::new(None, ns!(), LocalName::from(name)),
+                    value: value.to_string(),
+                })
+                .collect();
+            mutr.create_element(name("img"), attrs, QuirksMode::NoQuirks)
+        };
+        let in_view = img("display: block; width: 50px; height: 20px; padding: 5px");
+        let below = img("display: block; width: 10px; margin-top: 1000px");
+        let body = mutr.create_element(name("body"), Vec::new(), QuirksMode::NoQuirks);
+        mutr.append_children(body, &[in_view, below]);
+        let html = mutr.create_element(name("html"), Vec::new(), QuirksMode::NoQuirks);
+        mutr.append_children(html, &[body]);
+        mutr.append_children(0, &[html]);
+        drop(mutr);
+        doc.resolve();
+
+        // Images are decoded at the size of their content box in device pixels, and those which
+        // are only sized in one dimension (until they're decoded) are decoded to cover that
+        let decode = |node_id: usize| doc.image_decodes[&node_id].clone();
+        assert_eq!(decode(in_view).target_size(), Some((100, 40)));
+        assert_eq!(decode(in_view).priority().get(), RequestPriority::High);
+        assert_eq!(decode(below).target_size(), Some((20, 0)));
+        assert_eq!(decode(below).priority().get(), RequestPriority::Low);
+    }
+}
@@ -0,0 +1,157 @@
+//! Targeted invalidation for relational (`:has()`) selectors
+//!
+//! A mutation inside an element can change whether an *ancestor* (or a preceding sibling)
+//! matches a `:has()` selector, which ordinary snapshot-based invalidation never looks at. Rather
+//! than restyling the whole document on every mutation, we use the selector flags that stylo
+//! records while matching:
+//!
+//!  - `ANCHORS_RELATIVE_SELECTOR[_NON_SUBJECT]` is set on elements which a `:has()` selector was
+//!    matched against (the "anchors")
+//!  - `RELATIVE_SELECTOR_SEARCH_DIRECTION_*` is set on elements which were visited while searching
+//!    for a match, recording in which direction the anchor lies (ancestors, previous siblings, or
+//!    both)
+//!
+//! Together these form an invalidation set: when an element changes we walk only in the recorded
+//! directions and restyle the anchors that we find. Elements which were never visited by a
+//! `:has()` search have no search flags and cost nothing to invalidate.
+
+use selectors::matching::ElementSelectorFlags;
+use style::invalidation::element::restyle_hints::RestyleHint;
+
+use crate::BaseDocument;
+
+enum Direction {
+    Before,
+    After,
+}
+
+const SEARCH_DIRECTIONS: ElementSelectorFlags =
+    ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_ANCESTOR
+        .union(ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_SIBLING)
+        .union(ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_ANCESTOR_SIBLING);
+
+impl BaseDocument {
+    /// Restyle the `:has()` anchors whose match may depend on the state or attributes of `node_id`.
+    ///
+    /// Returns the number of anchors that were restyled.
+    pub(crate) fn invalidate_relative_selectors(&mut self, node_id: usize) -> usize {
+        let flags = *self.nodes[node_id].selector_flags.borrow() & SEARCH_DIRECTIONS;
+        if flags.is_empty() {
+            return 0;
+        }
+        self.restyle_relative_selector_anchors(node_id, flags)
+    }
+
+    /// Restyle the `:has()` anchors which may be affected by children being added to or removed
+    /// from `parent_id`. `child_ids` are the affected children (whether added or removed).
+    ///
+    /// Returns the number of anchors that were restyled.
+    pub(crate) fn invalidate_relative_selectors_for_children(
+        &mut self,
+        parent_id: usize,
+        child_ids: &[usize],
+    ) -> usize {
+        let mut restyled = 0;
+
+        // The parent itself may be an anchor (e.g. `:has(> .child)`)
+        restyled += self.restyle_if_anchor(parent_id) as usize;
+
+        // Anything which searched through the parent will also search through its new children
+        let parent_flags = *self.nodes[parent_id].selector_flags.borrow() & SEARCH_DIRECTIONS;
+        if !parent_flags.is_empty() {
+            restyled += self.restyle_relative_selector_anchors(parent_id, parent_flags);
+        }
+
+        // Sibling relations (`:has(+ .a)`, `:has(~ .a)`) are anchored on the children themselves
+        let sibling_search = self.nodes[parent_id]
+            .children
+            .iter()
+            .chain(child_ids)
+            .filter_map(|id| self.nodes.get(*id))
+            .any(|child| {
+                child.selector_flags.borrow().intersects(
+                    ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_SIBLING
+                        | ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_ANCESTOR_SIBLING,
+                )
+            });
+        if sibling_search {
+            let children = self.nodes[parent_id].children.clone();
+            for child_id in children {
+                restyled += self.restyle_if_anchor(child_id) as usize;
+            }
+        }
+
+        restyled
+    }
+
+    /// Walk from `node_id` in the directions given by `flags`, restyling any anchors found
+    fn restyle_relative_selector_anchors(
+        &mut self,
+        node_id: usize,
+        flags: ElementSelectorFlags,
+    ) -> usize {
+        let ancestors = flags.intersects(
+            ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_ANCESTOR
+                | ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_ANCESTOR_SIBLING,
+        );
+        let siblings = flags.intersects(
+            ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_SIBLING
+                | ElementSelectorFlags::RELATIVE_SELECTOR_SEARCH_DIRECTION_ANCESTOR_SIBLING,
+        );
+
+        let mut restyled = 0;
+        let mut current = Some(node_id);
+        while let Some(id) = current {
+            if id != node_id {
+                restyled += self.restyle_if_anchor(id) as usize;
+            }
+            if siblings {
+                for sibling_id in self.siblings_of(id, Direction::Before) {
+                    restyled += self.restyle_if_anchor(sibling_id) as usize;
+                }
+            }
+            if !ancestors {
+                break;
+            }
+            current = self.nodes[id].parent;
+        }
+
+        restyled
+    }
+
+    /// Restyle `node_id` if it is the anchor of a `:has()` selector. Returns whether it was.
+    fn restyle_if_anchor(&mut self, node_id: usize) -> bool {
+        let flags = *self.nodes[node_id].selector_flags.borrow();
+
+        if flags.contains(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR_NON_SUBJECT) {
+            // The anchor is in a compound that isn't the subject of the selector (e.g.
+            // `.a:has(.b) .c` or `.a:has(.b) + .c`), so its descendants and later siblings may
+            // change too.
+            self.nodes[node_id].set_restyle_hint(RestyleHint::restyle_subtree());
+            for sibling_id in self.siblings_of(node_id, Direction::After) {
+                self.nodes[sibling_id].set_restyle_hint(RestyleHint::restyle_subtree());
+            }
+            true
+        } else if flags.contains(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR) {
+            self.nodes[node_id].set_restyle_hint(RestyleHint::RESTYLE_SELF);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The siblings before (nearest first) or after `node_id`
+    fn siblings_of(&self, node_id: usize, direction: Direction) -> Vec<usize> {
+        let Some(parent_id) = self.nodes[node_id].parent else {
+            return Vec::new();
+        };
+        let children = &self.nodes[parent_id].children;
+        let Some(idx) = children.iter().position(|id| *id == node_id) else {
+            return Vec::new();
+        };
+        match direction {
+            Direction::Before => children[..idx].iter().rev().copied().collect(),
+            Direction::After => children[idx + 1..].to_vec(),
+        }
+    }
+}
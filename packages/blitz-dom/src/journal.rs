@@ -0,0 +1,147 @@
+//! Undo history for [`DocumentMutator`](crate::DocumentMutator)
+//!
+//! When journaling is enabled (see [`DocumentMutator::enable_journal`]), every mutation made
+//! through a mutator records the operation which would reverse it. The operations recorded
+//! between two commits form a transaction, and transactions are committed when the mutator is
+//! flushed (which happens when it is dropped), or explicitly with
+//! [`DocumentMutator::commit_transaction`].
+//!
+//! Nodes removed with [`DocumentMutator::remove_and_drop_node`] are dropped straight away. The
+//! transaction which removed them keeps a snapshot of their content instead, from which undoing
+//! it creates them again under the same ids.
+//!
+//! [`DocumentMutator::enable_journal`]: crate::DocumentMutator::enable_journal
+//! [`DocumentMutator::commit_transaction`]: crate::DocumentMutator::commit_transaction
+//! [`DocumentMutator::remove_and_drop_node`]: crate::DocumentMutator::remove_and_drop_node
+
+use std::collections::VecDeque;
+
+use crate::{NodeData, QualName};
+
+/// An operation which reverses a mutation
+#[derive(Debug, Clone)]
+pub(crate) enum InverseOp {
+    /// Restore the content of a text node
+    SetText { node_id: usize, text: String },
+    /// Restore an attribute's value, or remove it if it wasn't set
+    SetAttribute {
+        node_id: usize,
+        name: QualName,
+        value: Option<String>,
+    },
+    /// Move a node back to where it was: before `next_sibling` in `parent`, at the end of
+    /// `parent` if there was no next sibling, or out of the tree if there was no parent
+    Place {
+        node_id: usize,
+        parent: Option<usize>,
+        next_sibling: Option<usize>,
+    },
+    /// Drop a node which was created
+    DropNode { node_id: usize },
+    /// Create dropped nodes again, outside of the tree
+    Restore { nodes: Vec<NodeSnapshot> },
+}
+
+/// What is needed to create a dropped node again
+#[derive(Debug, Clone)]
+pub(crate) struct NodeSnapshot {
+    pub(crate) id: usize,
+    /// The node's parent, unless it's the root of the dropped subtree
+    pub(crate) parent: Option<usize>,
+    pub(crate) data: NodeData,
+    pub(crate) children: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    pub(crate) ops: Vec<InverseOp>,
+}
+
+#[derive(Debug)]
+pub(crate) struct MutationJournal {
+    /// The maximum number of transactions which can be undone
+    limit: usize,
+    history: VecDeque<Transaction>,
+    /// Operations recorded since the last commit
+    current: Transaction,
+}
+
+impl MutationJournal {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            history: VecDeque::new(),
+            current: Transaction::default(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, op: InverseOp) {
+        self.current.ops.push(op);
+    }
+
+    /// Change the number of transactions kept, dropping the oldest ones which no longer fit
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        let excess = self.history.len().saturating_sub(self.limit);
+        self.history.drain(..excess);
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.current.ops.is_empty() || !self.history.is_empty()
+    }
+
+    /// End the current transaction
+    pub(crate) fn commit(&mut self) {
+        if !self.current.ops.is_empty() {
+            self.history.push_back(std::mem::take(&mut self.current));
+        }
+        self.set_limit(self.limit)
+    }
+
+    /// Commit the current transaction and take the most recent one off the history
+    pub(crate) fn pop(&mut self) -> Option<Transaction> {
+        self.commit();
+        self.history.pop_back()
+    }
+
+    /// Add the operations of `transactions` to the current transaction
+    pub(crate) fn extend(&mut self, transactions: Vec<Transaction>) {
+        for transaction in transactions {
+            self.current.ops.extend(transaction.ops);
+        }
+    }
+
+    /// Take every transaction, ending the journal
+    pub(crate) fn into_transactions(mut self) -> Vec<Transaction> {
+        self.commit();
+        self.history.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit_evicts_transactions_over_the_limit() {
+        let mut journal = MutationJournal::new(2);
+        for node_id in 0..3 {
+            journal.record(InverseOp::DropNode { node_id });
+            journal.commit();
+            assert_eq!(journal.history.len(), node_id.min(1) + 1);
+        }
+        // Committing without recording anything doesn't add a transaction
+        journal.commit();
+        assert_eq!(journal.history.len(), 2);
+        assert!(matches!(
+            journal.history[0].ops[..],
+            [InverseOp::DropNode { node_id: 1 }]
+        ));
+
+        let last = journal.pop();
+        assert!(matches!(
+            last.unwrap().ops[..],
+            [InverseOp::DropNode { node_id: 2 }]
+        ));
+    }
+}
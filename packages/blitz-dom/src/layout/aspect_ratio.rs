@@ -0,0 +1,124 @@
+//! `aspect-ratio` of non-replaced boxes, in every layout mode
+//!
+//! A box with a preferred aspect ratio whose size its parent sets in one axis (like the stretched
+//! width of a block, or the height of a stretched flex item) and which is `auto` in the other is
+//! laid out with the other size transferred through the ratio, and clamped by that axis's min/max
+//! sizes. This is done before dispatching on the box's layout mode, so it applies to block, flex,
+//! grid, table, inline, ruby and math layout alike. Sizes set in style are transferred by taffy,
+//! and replaced elements apply their aspect ratio when they are measured (see
+//! [`super::replaced`]).
+//!
+//! With `min-height: auto`, a box which isn't a scroll container grows to fit content taller
+//! than the transferred height, rather than letting it overflow (its automatic minimum size).
+//! Widths transferred from a height are used as they are.
+//!
+//! See <https://drafts.csswg.org/css-sizing-4/#aspect-ratio>
+
+use markup5ever::local_name;
+use taffy::{
+    BoxSizing, LayoutInput, LayoutOutput, MaybeMath as _, MaybeResolve as _, NodeId, Overflow,
+    ResolveOrZero as _, Size, SizingMode, compute_cached_layout,
+};
+
+use super::resolve_calc_value;
+use crate::BaseDocument;
+use crate::node::Node;
+
+/// The size of a box in both axes, one of them transferred through its preferred aspect ratio
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AspectRatioTransfer {
+    size: Size<f32>,
+    /// Whether the box grows to fit content taller than the transferred height
+    fits_content: bool,
+}
+
+impl BaseDocument {
+    /// The size transferred through the node's preferred aspect ratio, if it has one and its
+    /// parent sets its size in one axis only
+    pub(crate) fn aspect_ratio_transfer(
+        &self,
+        node_id: NodeId,
+        inputs: &LayoutInput,
+    ) -> Option<AspectRatioTransfer> {
+        if inputs.sizing_mode != SizingMode::InherentSize {
+            return None;
+        }
+        let node = self.node_from_id(node_id);
+        let style = node.style();
+        let ratio = style.aspect_ratio?;
+        if !node.is_element() || is_measured_as_replaced(node) {
+            return None;
+        }
+
+        // The ratio applies to the content box with `box-sizing: content-box`
+        let parent_size = inputs.parent_size;
+        let padding = style.padding.resolve_or_zero(parent_size, resolve_calc_value);
+        let border = style.border.resolve_or_zero(parent_size, resolve_calc_value);
+        let padding_border = padding + border;
+        let adjustment = match style.box_sizing {
+            BoxSizing::ContentBox => Size {
+                width: padding_border.horizontal_components().sum(),
+                height: padding_border.vertical_components().sum(),
+            },
+            BoxSizing::BorderBox => Size::ZERO,
+        };
+        let min_size = style.min_size.maybe_resolve(parent_size, resolve_calc_value);
+        let max_size = style.max_size.maybe_resolve(parent_size, resolve_calc_value);
+        let clamp = |size: f32, min: Option<f32>, max: Option<f32>, adjustment: f32| {
+            size.maybe_clamp(min.map(|min| min + adjustment), max.map(|max| max + adjustment))
+        };
+
+        match (inputs.known_dimensions.width, inputs.known_dimensions.height) {
+            (Some(width), None) if style.size.height.is_auto() => {
+                let height = (width - adjustment.width).max(0.0) / ratio + adjustment.height;
+                let height = clamp(height, min_size.height, max_size.height, adjustment.height);
+                let scroll_container =
+                    matches!(style.overflow.y, Overflow::Scroll | Overflow::Hidden);
+                Some(AspectRatioTransfer {
+                    size: Size { width, height },
+                    fits_content: style.min_size.height.is_auto() && !scroll_container,
+                })
+            }
+            (None, Some(height)) if style.size.width.is_auto() => {
+                let width = (height - adjustment.height).max(0.0) * ratio + adjustment.width;
+                let width = clamp(width, min_size.width, max_size.width, adjustment.width);
+                Some(AspectRatioTransfer {
+                    size: Size { width, height },
+                    fits_content: false,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Measure or lay out a node with the size transferred through its preferred aspect ratio
+    pub(crate) fn compute_layout_with_aspect_ratio(
+        &mut self,
+        node_id: NodeId,
+        inputs: LayoutInput,
+        transfer: AspectRatioTransfer,
+    ) -> LayoutOutput {
+        if transfer.fits_content {
+            let output =
+                compute_cached_layout(self, node_id, inputs, Self::compute_uncached_layout);
+            if output.size.height >= transfer.size.height {
+                return output;
+            }
+        }
+        let inputs = LayoutInput {
+            known_dimensions: transfer.size.map(Some),
+            ..inputs
+        };
+        compute_cached_layout(self, node_id, inputs, Self::compute_uncached_layout)
+    }
+}
+
+/// Whether the node is measured as a replaced element, which applies its aspect ratio itself
+fn is_measured_as_replaced(node: &Node) -> bool {
+    node.data.downcast_element().is_some_and(|element| {
+        let name = &element.name.local;
+        *name == local_name!("img")
+            || *name == local_name!("canvas")
+            || (cfg!(feature = "svg") && *name == local_name!("svg"))
+    })
+}
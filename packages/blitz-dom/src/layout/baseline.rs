@@ -0,0 +1,119 @@
+//! Baselines of boxes whose layout algorithms don't report one to taffy
+//!
+//! Taffy's flexbox and grid algorithms align `baseline` items using
+//! [`LayoutOutput::first_baselines`](taffy::LayoutOutput), and flex and grid containers export
+//! their own. Leaf layouts and taffy's block layout leave it empty, so:
+//!
+//! - inline formatting contexts use the baseline of their first line box
+//! - block containers use the first baseline of their first in-flow child that has one, which
+//!   for flex and grid containers is that of their first item
+//! - replaced elements synthesize a baseline from the bottom of their border box
+//!
+//! See <https://drafts.csswg.org/css-align-3/#baseline-export>.
+
+use blitz_text::WritingMode;
+use taffy::{Display, LayoutOutput, Position, ResolveOrZero as _, RunMode};
+
+use super::resolve_calc_value;
+use super::stylo_to_blitz::text_writing_mode;
+use crate::BaseDocument;
+
+impl BaseDocument {
+    /// The distance from the top of an inline formatting context's border box to the baseline of
+    /// its first line box, once its lines have been broken. Vertical text has no horizontal
+    /// baseline.
+    pub(crate) fn inline_first_baseline(
+        &self,
+        node_id: usize,
+        parent_width: Option<f32>,
+    ) -> Option<f32> {
+        let node = &self.nodes[node_id];
+        let vertical = node
+            .primary_styles()
+            .is_some_and(|s| text_writing_mode(&s).0 != WritingMode::HorizontalTopBottom);
+        if vertical {
+            return None;
+        }
+        let inline_layout = node.element_data()?.inline_layout_data.as_ref()?;
+        let line_y = inline_layout.layout.inner().layout_runs().next()?.line_y;
+
+        let style = node.style();
+        let padding = style.padding.top.resolve_or_zero(parent_width, resolve_calc_value);
+        let border = style.border.top.resolve_or_zero(parent_width, resolve_calc_value);
+        Some(padding + border + line_y / self.viewport.scale())
+    }
+
+    /// The first baseline of a block container whose children have been laid out: that of its
+    /// first in-flow child with a baseline, offset by the child's position
+    pub(crate) fn block_first_baseline(&self, node_id: usize) -> Option<f32> {
+        let layout_children = self.nodes[node_id].layout_children.borrow();
+        layout_children.as_ref()?.iter().find_map(|&child_id| {
+            if !self.is_in_flow(child_id) {
+                return None;
+            }
+            let baseline = self.laid_out_first_baseline(child_id)?;
+            Some(self.nodes[child_id].unrounded_layout.location.y + baseline)
+        })
+    }
+
+    /// The first baseline of a flex or grid container whose items have been laid out: that of its
+    /// startmost item in the first line or row, offset by the item's position. Items without a
+    /// baseline have one synthesized from the bottom of their border box.
+    fn flex_or_grid_first_baseline(&self, node_id: usize) -> Option<f32> {
+        let layout_children = self.nodes[node_id].layout_children.borrow();
+        let first_item = layout_children
+            .as_ref()?
+            .iter()
+            .copied()
+            .filter(|&child_id| self.is_in_flow(child_id))
+            .min_by(|&a, &b| {
+                let (a, b) = (self.nodes[a].unrounded_layout, self.nodes[b].unrounded_layout);
+                let y = a.location.y.total_cmp(&b.location.y);
+                y.then(a.location.x.total_cmp(&b.location.x))
+            })?;
+        let layout = &self.nodes[first_item].unrounded_layout;
+        let baseline = self.laid_out_first_baseline(first_item).unwrap_or(layout.size.height);
+        Some(layout.location.y + baseline)
+    }
+
+    /// The first baseline of a box whose descendants have been laid out, from the top of its
+    /// border box
+    fn laid_out_first_baseline(&self, node_id: usize) -> Option<f32> {
+        if self.nodes[node_id].flags.is_inline_root() {
+            return self.inline_first_baseline(node_id, None);
+        }
+        match self.nodes[node_id].style().display {
+            Display::Block => self.block_first_baseline(node_id),
+            Display::Flex | Display::Grid => self.flex_or_grid_first_baseline(node_id),
+            _ => None,
+        }
+    }
+
+    fn is_in_flow(&self, node_id: usize) -> bool {
+        let style = self.nodes[node_id].style();
+        style.position != Position::Absolute && style.display != Display::None
+    }
+
+    /// Fill in the first baseline that taffy's block layout leaves empty. It is measured from the
+    /// positions of the children, so only once they have been laid out.
+    pub(crate) fn with_block_baseline(
+        &self,
+        node_id: usize,
+        run_mode: RunMode,
+        mut output: LayoutOutput,
+    ) -> LayoutOutput {
+        if run_mode == RunMode::PerformLayout && output.first_baselines.y.is_none() {
+            output.first_baselines.y = self.block_first_baseline(node_id);
+        }
+        output
+    }
+}
+
+/// A replaced element's baseline is synthesized from the bottom edge of its border box
+/// https://drafts.csswg.org/css-align-3/#synthesize-baseline
+pub(crate) fn synthesized_baseline(border_box_height: f32) -> taffy::Point<Option<f32>> {
+    taffy::Point {
+        x: None,
+        y: Some(border_box_height),
+    }
+}
@@ -5,13 +5,30 @@ use slab::Slab;
 use style::values::computed::Display;
 use style::values::specified::box_::{DisplayInside, DisplayOutside};
 
-use crate::layout::stylo_to_blitz::TextCollapseMode;
-use crate::node::{Node, NodeData, SpecialElementData};
+use crate::layout::replaced::is_replaced_element;
+use crate::layout::stylo_to_blitz::{TextCollapseMode, white_space_collapse_to_mode};
+use crate::node::{Node, NodeData};
+
+/// A run of collected text which came from a single text node
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InlineTextRun {
+    /// Byte offset of the start of the run in the collected text
+    pub(crate) start: usize,
+    /// The text node the run came from
+    pub(crate) node_id: usize,
+    /// Whether lines can break after each of the run's preserved spaces, as they can with
+    /// `white-space: break-spaces`
+    pub(crate) breakable_spaces: bool,
+}
 
 /// Collect text content from inline nodes recursively
 /// Simplified version of build_inline_layout_recursive that just extracts text
+///
+/// The start of the text from each text node is recorded in `runs`, so that styles which differ
+/// between the elements in an inline context can be applied to their text.
 pub(crate) fn collect_inline_text_recursive(
     text_content: &mut String,
+    runs: &mut Vec<InlineTextRun>,
     nodes: &Slab<Node>,
     node_id: usize,
     collapse_mode: TextCollapseMode,
@@ -34,7 +51,13 @@ pub(crate) fn collect_inline_text_recursive(
                 (DisplayOutside::None, DisplayInside::Contents) => {
                     // Recurse into display:contents nodes
                     for child_id in node.children.iter().copied() {
-                        collect_inline_text_recursive(text_content, nodes, child_id, collapse_mode);
+                        collect_inline_text_recursive(
+                            text_content,
+                            runs,
+                            nodes,
+                            child_id,
+                            collapse_mode,
+                        );
                     }
                 }
                 (DisplayOutside::Inline, DisplayInside::Flow) => {
@@ -43,7 +66,7 @@ pub(crate) fn collect_inline_text_recursive(
                     // Handle special elements
                     if *tag_name == markup5ever::local_name!("br") {
                         text_content.push('\n');
-                    } else if is_replaced_element(&element_data.special_data, tag_name) {
+                    } else if is_replaced_element(element_data) {
                         // Replaced elements don't contribute text content
                         // but they take up space in layout
                     } else {
@@ -51,6 +74,7 @@ pub(crate) fn collect_inline_text_recursive(
                         if let Some(before_id) = node.before {
                             collect_inline_text_recursive(
                                 text_content,
+                                runs,
                                 nodes,
                                 before_id,
                                 collapse_mode,
@@ -59,6 +83,7 @@ pub(crate) fn collect_inline_text_recursive(
                         for child_id in node.children.iter().copied() {
                             collect_inline_text_recursive(
                                 text_content,
+                                runs,
                                 nodes,
                                 child_id,
                                 collapse_mode,
@@ -67,6 +92,7 @@ pub(crate) fn collect_inline_text_recursive(
                         if let Some(after_id) = node.after {
                             collect_inline_text_recursive(
                                 text_content,
+                                runs,
                                 nodes,
                                 after_id,
                                 collapse_mode,
@@ -79,61 +105,24 @@ pub(crate) fn collect_inline_text_recursive(
             }
         }
         NodeData::Text(text_data) => {
-            // Apply whitespace collapsing based on mode
-            let processed_text = match collapse_mode {
-                TextCollapseMode::Collapse => {
-                    // Normal HTML whitespace collapsing
-                    let collapsed = text_data
-                        .content
-                        .chars()
-                        .fold((String::new(), false), |(mut acc, prev_was_space), c| {
-                            if c.is_ascii_whitespace() {
-                                if !prev_was_space {
-                                    acc.push(' ');
-                                }
-                                (acc, true)
-                            } else {
-                                acc.push(c);
-                                (acc, false)
-                            }
-                        })
-                        .0;
-                    collapsed
-                }
-                TextCollapseMode::Preserve => {
-                    // Preserve all whitespace (like pre)
-                    text_data.content.clone()
-                }
-                TextCollapseMode::PreserveNewlines => {
-                    // Preserve newlines but collapse other spaces
-                    text_data
-                        .content
-                        .lines()
-                        .map(|line| {
-                            line.chars()
-                                .fold((String::new(), false), |(mut acc, prev_was_space), c| {
-                                    if c.is_ascii_whitespace() && c != '\n' {
-                                        if !prev_was_space {
-                                            acc.push(' ');
-                                        }
-                                        (acc, true)
-                                    } else {
-                                        acc.push(c);
-                                        (acc, false)
-                                    }
-                                })
-                                .0
-                        })
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                }
-                TextCollapseMode::PreserveBreakable => {
-                    // Like preserve but allows line breaking
-                    text_data.content.clone()
-                }
-            };
+            // White space is processed with the styles of the text's own element, which can
+            // differ from those of the inline context (e.g. `<code>` with `white-space: pre`)
+            let collapse_mode = node
+                .parent
+                .and_then(|parent_id| nodes[parent_id].primary_styles())
+                .map(|styles| {
+                    white_space_collapse_to_mode(
+                        styles.get_inherited_text().clone_white_space_collapse(),
+                    )
+                })
+                .unwrap_or(collapse_mode);
 
-            text_content.push_str(&processed_text);
+            runs.push(InlineTextRun {
+                start: text_content.len(),
+                node_id,
+                breakable_spaces: collapse_mode == TextCollapseMode::PreserveBreakable,
+            });
+            process_white_space(text_content, &text_data.content, collapse_mode);
         }
         NodeData::Comment => {
             // Comments don't contribute to text content
@@ -145,24 +134,168 @@ pub(crate) fn collect_inline_text_recursive(
     }
 }
 
-/// Check if an element is a replaced element
-#[inline]
-fn is_replaced_element(
-    special_data: &SpecialElementData,
-    tag_name: &markup5ever::LocalName,
-) -> bool {
+/// Remove a forced line break (a preserved segment break or a `<br>`) from the end of the text
+/// collected for an inline context, as it ends the last line rather than starting an empty one
+pub(crate) fn trim_final_line_break(text_content: &mut String, runs: &mut [InlineTextRun]) {
+    if !text_content.ends_with('\n') {
+        return;
+    }
+    text_content.pop();
+    let len = text_content.len();
+    for run in runs {
+        run.start = run.start.min(len);
+    }
+}
+
+/// Append text to the collected text, processing its white space as CSS Text 3 § 4.1 describes
+///
+/// Spaces which would end up at the start of a line or next to a collapsible space in the
+/// collected text are removed too. Preserved tabs are expanded by the text layout, which is given
+/// the element's `tab-size`.
+fn process_white_space(collected: &mut String, text: &str, mode: TextCollapseMode) {
+    match mode {
+        TextCollapseMode::Collapse => collapse_white_space(collected, text),
+        TextCollapseMode::PreserveNewlines => {
+            // Spaces and tabs around segment breaks are removed, and other sequences of them
+            // collapse to a single space, but the segment breaks themselves are preserved
+            for (index, line) in text.split('\n').enumerate() {
+                if index > 0 {
+                    trim_collapsible_end(collected);
+                    collected.push('\n');
+                }
+                collapse_white_space(collected, line);
+            }
+        }
+        // `break-spaces` only differs from `pre-wrap` in line breaking, which is done by the text
+        // layout (see `InlineTextRun::breakable_spaces`)
+        TextCollapseMode::Preserve | TextCollapseMode::PreserveBreakable => {
+            collected.push_str(text)
+        }
+    }
+}
+
+/// Segment breaks next to a zero width space in the source text are removed
+const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+/// Collapse sequences of spaces, tabs and segment breaks to at most one space, transforming
+/// segment breaks between East Asian wide characters (which don't separate words) away entirely
+fn collapse_white_space(collected: &mut String, text: &str) {
+    let mut pending_space = false;
+    let mut pending_break = false;
+    for c in text.chars() {
+        match c {
+            ' ' | '\t' => pending_space = true,
+            '\n' | '\r' => pending_break = true,
+            _ => {
+                let previous = collected.chars().next_back();
+                let at_line_start = previous.is_none_or(|previous| previous == '\n');
+                let after_space = previous == Some(' ');
+                // Spaces around a segment break are removed along with it
+                let removed_break = pending_break
+                    && previous.is_some_and(|previous| {
+                        previous == ZERO_WIDTH_SPACE
+                            || c == ZERO_WIDTH_SPACE
+                            || (is_east_asian_wide(previous) && is_east_asian_wide(c))
+                    });
+                if (pending_space || pending_break)
+                    && !at_line_start
+                    && !after_space
+                    && !removed_break
+                {
+                    collected.push(' ');
+                }
+                pending_space = false;
+                pending_break = false;
+                collected.push(c);
+            }
+        }
+    }
+
+    // Trailing white space still collapses with the start of the next text
+    let previous = collected.chars().next_back();
+    let collapsible = previous.is_some_and(|previous| !matches!(previous, ' ' | '\n'));
+    if (pending_space || pending_break) && collapsible {
+        collected.push(' ');
+    }
+}
+
+/// Remove collapsible spaces from the end of the collected text, before a preserved segment break
+fn trim_collapsible_end(collected: &mut String) {
+    let trimmed_len = collected.trim_end_matches(' ').len();
+    collected.truncate(trimmed_len);
+}
+
+/// Whether a character is East Asian Fullwidth, Wide or Halfwidth, and not Hangul, for the
+/// segment break transformation rules
+fn is_east_asian_wide(c: char) -> bool {
     matches!(
-        special_data,
-        SpecialElementData::Image(_)
-            | SpecialElementData::Canvas(_)
-            | SpecialElementData::TextInput(_)
-            | SpecialElementData::CheckboxInput(_)
-    ) || matches!(
-        *tag_name,
-        markup5ever::local_name!("canvas")
-            | markup5ever::local_name!("img")
-            | markup5ever::local_name!("svg")
-            | markup5ever::local_name!("textarea")
-            | markup5ever::local_name!("button")
+        c,
+        '\u{2E80}'..='\u{303E}'
+            | '\u{3041}'..='\u{33FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{A000}'..='\u{A4CF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FE30}'..='\u{FE4F}'
+            | '\u{FF00}'..='\u{FF60}'
+            | '\u{FF61}'..='\u{FF9F}'
+            | '\u{FFE0}'..='\u{FFE6}'
+            | '\u{20000}'..='\u{3FFFD}'
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(texts: &[&str], mode: TextCollapseMode) -> String {
+        let mut collected = String::new();
+        for text in texts {
+            process_white_space(&mut collected, text, mode);
+        }
+        collected
+    }
+
+    #[test]
+    fn collapses_white_space_across_text_nodes() {
+        let collected = process(&["  Hello \t\n ", " world ", "\n!"], TextCollapseMode::Collapse);
+        assert_eq!(collected, "Hello world !");
+    }
+
+    #[test]
+    fn removes_segment_breaks_between_wide_characters() {
+        assert_eq!(process(&["日本\n語"], TextCollapseMode::Collapse), "日本語");
+        assert_eq!(process(&["日本 語"], TextCollapseMode::Collapse), "日本 語");
+    }
+
+    #[test]
+    fn preserves_segment_breaks_in_pre_line() {
+        let collected = process(&["a  b  \n   c"], TextCollapseMode::PreserveNewlines);
+        assert_eq!(collected, "a b\nc");
+    }
+
+    #[test]
+    fn final_line_breaks_do_not_start_an_empty_line() {
+        let mut collected = process(&["code\n"], TextCollapseMode::Preserve);
+        trim_final_line_break(&mut collected, &mut []);
+        assert_eq!(collected, "code");
+
+        // Only the last break is removed, and runs after it start at the new end
+        let mut runs = [InlineTextRun {
+            start: 3,
+            node_id: 1,
+            breakable_spaces: false,
+        }];
+        let mut collected = process(&["a\n\n"], TextCollapseMode::Preserve);
+        trim_final_line_break(&mut collected, &mut runs);
+        assert_eq!(collected, "a\n");
+        assert_eq!(runs[0].start, 2);
+    }
+
+    #[test]
+    fn preserves_spaces_without_adding_text() {
+        let collected = process(&["a \tb"], TextCollapseMode::PreserveBreakable);
+        assert_eq!(collected, "a \tb");
+        assert_eq!(process(&["\ta  "], TextCollapseMode::Preserve), "\ta  ");
+    }
+}
@@ -1,24 +1,28 @@
 use core::str;
+use std::ops::Range;
 use std::sync::Arc;
 
 // Replaced parley with cosmyc-text for text processing
 use blitz_text::Edit;
+use blitz_text::baseline_shift::encode_baseline_shift;
 use markup5ever::{QualName, local_name, ns};
 use style::{
     data::ElementData as StyloElementData,
-    properties::longhands::{
-        list_style_position::computed_value::T as ListStylePosition,
-        list_style_type::computed_value::T as ListStyleType,
-    },
+    properties::longhands::list_style_position::computed_value::T as ListStylePosition,
     shared_lock::StylesheetGuards,
     values::{
-        computed::{Content, ContentItem, Display},
+        computed::Display,
         specified::box_::{DisplayInside, DisplayOutside},
     },
 };
 
 use super::{
-    collect_inline_text::collect_inline_text_recursive, stylo_to_blitz, table::build_table_context,
+    collect_inline_text::{InlineTextRun, collect_inline_text_recursive, trim_final_line_break},
+    generated_content::PseudoBox,
+    math::is_math_layout_element,
+    replaced::is_replaced_element,
+    stylo_to_blitz,
+    table::build_table_context,
 };
 use crate::{
     BaseDocument, ElementData, Node, NodeData,
@@ -96,70 +100,12 @@ pub(crate) fn collect_layout_children(
         }
 
         #[cfg(feature = "svg")]
-        if matches!(tag_name, "svg") {
-            let mut outer_html = match doc.get_node(container_node_id) {
-                Some(node) => node.outer_html(),
-                None => {
-                    eprintln!(
-                        "Warning: Cannot process SVG for node {}: node not found",
-                        container_node_id
-                    );
-                    return;
-                }
-            };
-
-            // HACK: usvg fails to parse SVGs that don't have the SVG xmlns set. So inject it
-            // if the generated source doesn't have it.
-            if !outer_html.contains("xmlns") {
-                outer_html =
-                    outer_html.replace("<svg", "<svg xmlns=\"http://www.w3.org/2000/svg\"");
-            }
-
-            match crate::util::parse_svg(outer_html.as_bytes()) {
-                Ok(svg) => {
-                    let node = match doc.get_node_mut(container_node_id) {
-                        Some(node) => node,
-                        None => {
-                            eprintln!(
-                                "Warning: Cannot set SVG data for node {}: node not found",
-                                container_node_id
-                            );
-                            return;
-                        }
-                    };
-                    let element_data = match node.element_data_mut() {
-                        Some(element) => element,
-                        None => {
-                            eprintln!(
-                                "Warning: Cannot set SVG data for node {}: node is not an element",
-                                container_node_id
-                            );
-                            return;
-                        }
-                    };
-                    element_data.special_data = SpecialElementData::Image(Box::new(svg.into()));
-                }
-                Err(err) => {
-                    println!("{container_node_id} SVG parse failed");
-                    println!("{outer_html}");
-                    dbg!(err);
-                }
-            };
+        if tag_name == "svg" && el.name.ns == ns!(svg) {
+            load_inline_svg(doc, container_node_id);
             return;
         }
 
-        // Only ol tags have start and reversed attributes
-        let (mut index, reversed) = if tag_name == "ol" {
-            (
-                el.attr_parsed(local_name!("start"))
-                    .map(|start: usize| start - 1)
-                    .unwrap_or(0),
-                el.attr_parsed(local_name!("reversed")).unwrap_or(false),
-            )
-        } else {
-            (1, false)
-        };
-        collect_list_item_children(doc, &mut index, reversed, container_node_id);
+        collect_list_item_children(doc, container_node_id);
     }
 
     // Skip further construction if the node has no children or psuedo-children
@@ -168,22 +114,7 @@ pub(crate) fn collect_layout_children(
         let node = &doc.nodes[container_node_id];
         if node.children.is_empty() && node.before.is_none() && node.after.is_none() {
             // Check if this is a replaced element that should still be added to layout
-            let is_replaced = node
-                .element_data()
-                .map(|el| {
-                    // Check for replaced elements by special data or tag name
-                    matches!(
-                        el.special_data,
-                        SpecialElementData::Image(_)
-                            | SpecialElementData::Canvas(_)
-                            | SpecialElementData::TextInput(_)
-                            | SpecialElementData::CheckboxInput(_)
-                    ) || matches!(
-                        el.name.local.as_ref(),
-                        "canvas" | "img" | "svg" | "input" | "textarea" | "button"
-                    )
-                })
-                .unwrap_or(false);
+            let is_replaced = node.element_data().is_some_and(is_replaced_element);
 
             if !is_replaced {
                 // Not a replaced element - skip it
@@ -284,6 +215,24 @@ pub(crate) fn collect_layout_children(
                 return;
             }
 
+            // Ruby bases and annotations are positioned by `compute_ruby_layout`. Annotations are
+            // blocks, so the runs of base content between them get wrapped in anonymous blocks below.
+            if doc.nodes[container_node_id]
+                .data
+                .is_element_with_tag_name(&local_name!("ruby"))
+            {
+                doc.nodes[container_node_id]
+                    .flags
+                    .insert(NodeFlags::IS_RUBY_ROOT);
+            }
+
+            // The children of MathML layout elements are positioned by `compute_math_layout`
+            if is_math_layout_element(&doc.nodes[container_node_id]) {
+                doc.nodes[container_node_id]
+                    .flags
+                    .insert(NodeFlags::IS_MATH_LAYOUT);
+            }
+
             // If the children are either all inline or all block then simply return the regular children
             // as the layout children
             if (all_block | all_inline) & !has_contents {
@@ -392,9 +341,9 @@ fn flush_pseudo_elements(doc: &mut BaseDocument, node_id: usize) {
 
     // Sync pseudo element
     // TODO: Make incremental
-    for (idx, pe_style, pe_node_id) in [
-        (1, before_style, before_node_id),
-        (0, after_style, after_node_id),
+    for (idx, pseudo, pe_style, pe_node_id) in [
+        (1, PseudoBox::Before, before_style, before_node_id),
+        (0, PseudoBox::After, after_style, after_node_id),
     ] {
         // Delete psuedo element if it exists but shouldn't
         if let (Some(pe_node_id), None) = (pe_node_id, &pe_style) {
@@ -410,18 +359,10 @@ fn flush_pseudo_elements(doc: &mut BaseDocument, node_id: usize) {
             )));
             doc.nodes[new_node_id].parent = Some(node_id);
 
-            let content = &pe_style.as_ref().get_counters().content;
-            if let Content::Items(item_data) = content {
-                let items = &item_data.items[0..item_data.alt_start];
-                match &items[0] {
-                    ContentItem::String(owned_str) => {
-                        let text_node_id = doc.create_text_node(owned_str);
-                        doc.nodes[new_node_id].children.push(text_node_id);
-                    }
-                    _ => {
-                        // TODO: other types of content
-                    }
-                }
+            let content = doc.generated_content.pseudo_content.get(&(node_id, pseudo)).cloned();
+            if let Some(text) = content {
+                let text_node_id = doc.create_text_node(&text);
+                doc.nodes[new_node_id].children.push(text_node_id);
             }
 
             let mut element_data = StyloElementData::default();
@@ -434,7 +375,7 @@ fn flush_pseudo_elements(doc: &mut BaseDocument, node_id: usize) {
 
         // Else: Update psuedo element
         if let (Some(pe_node_id), Some(pe_style)) = (pe_node_id, pe_style) {
-            // TODO: Update content
+            update_pseudo_element_text(doc, pe_node_id, (node_id, pseudo));
 
             let mut node_styles = doc.nodes[pe_node_id].stylo_element_data.borrow_mut();
             let node_styles = match node_styles.as_mut() {
@@ -465,24 +406,40 @@ fn flush_pseudo_elements(doc: &mut BaseDocument, node_id: usize) {
     }
 }
 
-fn collect_list_item_children(
-    doc: &mut BaseDocument,
-    index: &mut usize,
-    reversed: bool,
-    node_id: usize,
-) {
-    let mut children = doc.nodes[node_id].children.clone();
-    if reversed {
-        children.reverse();
+/// Keep the text of a pseudo-element in sync with its generated content, which may change without
+/// its style changing (when it uses counters)
+fn update_pseudo_element_text(doc: &mut BaseDocument, pe_node_id: usize, key: (usize, PseudoBox)) {
+    let text = doc.generated_content.pseudo_content.get(&key).cloned();
+    let text_node_id = doc.nodes[pe_node_id]
+        .children
+        .iter()
+        .copied()
+        .find(|&child_id| doc.nodes[child_id].is_text_node());
+    match text_node_id {
+        Some(text_node_id) => {
+            if let Some(text_data) = doc.nodes[text_node_id].text_data_mut() {
+                text_data.content = text.unwrap_or_default();
+            }
+        }
+        None => {
+            if let Some(text) = text {
+                let text_node_id = doc.create_text_node(&text);
+                doc.nodes[text_node_id].parent = Some(pe_node_id);
+                doc.nodes[pe_node_id].children.push(text_node_id);
+            }
+        }
     }
+}
+
+fn collect_list_item_children(doc: &mut BaseDocument, node_id: usize) {
+    let children = doc.nodes[node_id].children.clone();
     for child in children.into_iter() {
-        if let Some(layout) = node_list_item_child(doc, child, *index) {
+        if let Some(layout) = node_list_item_child(doc, child) {
             let node = &mut doc.nodes[child];
             match node.element_data_mut() {
                 Some(element_data) => {
                     element_data.list_item_data = Some(Box::new(layout));
-                    *index += 1;
-                    collect_list_item_children(doc, index, reversed, child);
+                    collect_list_item_children(doc, child);
                 }
                 None => {
                     eprintln!(
@@ -502,11 +459,7 @@ fn collect_list_item_children(
 }
 
 // Return a child node which is of display: list-item
-fn node_list_item_child(
-    doc: &mut BaseDocument,
-    child_id: usize,
-    index: usize,
-) -> Option<ListItemLayout> {
+fn node_list_item_child(doc: &mut BaseDocument, child_id: usize) -> Option<ListItemLayout> {
     let node = &doc.nodes[child_id];
 
     // We only care about elements with display: list-item (li's have this automatically)
@@ -541,14 +494,17 @@ fn node_list_item_child(
             return None;
         }
     };
+    // Markers are numbered by the `list-item` counter, which is evaluated for the whole document
+    let marker = doc.generated_content.markers.get(&child_id)?.clone();
+    let marker_style = doc.generated_content.marker_styles.get(&child_id).cloned();
     let list_style_type = styles.clone_list_style_type();
     let list_style_position = styles.clone_list_style_position();
-    let marker = marker_for_style(list_style_type, index)?;
 
     let position = match list_style_position {
         ListStylePosition::Inside => ListItemLayoutPosition::Inside,
         ListStylePosition::Outside => {
-            let cosmyc_style = stylo_to_blitz::style(child_id, &styles);
+            let cosmyc_style =
+                stylo_to_blitz::style(child_id, marker_style.as_ref().unwrap_or(&styles));
 
             // Set appropriate font family for bullet symbols
             let attrs = if let Some(font_family) = stylo_to_blitz::font_for_bullet(list_style_type)
@@ -600,90 +556,13 @@ fn node_list_item_child(
         }
     };
 
-    Some(ListItemLayout { marker, position })
-}
-
-// Determine the marker to render for a given list style type
-fn marker_for_style(list_style_type: ListStyleType, index: usize) -> Option<Marker> {
-    if list_style_type == ListStyleType::None {
-        return None;
-    }
-
-    Some(match list_style_type {
-        ListStyleType::LowerAlpha => {
-            let mut marker = String::new();
-            build_alpha_marker(index, &mut marker);
-            Marker::String(format!("{marker}. "))
-        }
-        ListStyleType::UpperAlpha => {
-            let mut marker = String::new();
-            build_alpha_marker(index, &mut marker);
-            Marker::String(format!("{}. ", marker.to_ascii_uppercase()))
-        }
-        ListStyleType::Decimal => Marker::String(format!("{}. ", index + 1)),
-        ListStyleType::Disc => Marker::Char('•'),
-        ListStyleType::Circle => Marker::Char('◦'),
-        ListStyleType::Square => Marker::Char('▪'),
-        ListStyleType::DisclosureOpen => Marker::Char('▾'),
-        ListStyleType::DisclosureClosed => Marker::Char('▸'),
-        _ => Marker::Char('□'),
+    Some(ListItemLayout {
+        marker,
+        position,
+        style: marker_style,
     })
 }
 
-const ALPHABET: [char; 26] = [
-    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
-    't', 'u', 'v', 'w', 'x', 'y', 'z',
-];
-
-// Construct alphanumeric marker from index, appending characters when index exceeds powers of 26
-fn build_alpha_marker(index: usize, str: &mut String) {
-    let rem = index % 26;
-    let sym = ALPHABET[rem];
-    str.insert(0, sym);
-    let rest = (index - rem) as i64 / 26 - 1;
-    if rest >= 0 {
-        build_alpha_marker(rest as usize, str);
-    }
-}
-
-#[test]
-fn test_marker_for_disc() {
-    let result = marker_for_style(ListStyleType::Disc, 0);
-    assert_eq!(result, Some(Marker::Char('•')));
-}
-
-#[test]
-fn test_marker_for_decimal() {
-    let result_1 = marker_for_style(ListStyleType::Decimal, 0);
-    let result_2 = marker_for_style(ListStyleType::Decimal, 1);
-    assert_eq!(result_1, Some(Marker::String("1. ".to_string())));
-    assert_eq!(result_2, Some(Marker::String("2. ".to_string())));
-}
-
-#[test]
-fn test_marker_for_lower_alpha() {
-    let result_1 = marker_for_style(ListStyleType::LowerAlpha, 0);
-    let result_2 = marker_for_style(ListStyleType::LowerAlpha, 1);
-    let result_extended_1 = marker_for_style(ListStyleType::LowerAlpha, 26);
-    let result_extended_2 = marker_for_style(ListStyleType::LowerAlpha, 27);
-    assert_eq!(result_1, Some(Marker::String("a. ".to_string())));
-    assert_eq!(result_2, Some(Marker::String("b. ".to_string())));
-    assert_eq!(result_extended_1, Some(Marker::String("aa. ".to_string())));
-    assert_eq!(result_extended_2, Some(Marker::String("ab. ".to_string())));
-}
-
-#[test]
-fn test_marker_for_upper_alpha() {
-    let result_1 = marker_for_style(ListStyleType::UpperAlpha, 0);
-    let result_2 = marker_for_style(ListStyleType::UpperAlpha, 1);
-    let result_extended_1 = marker_for_style(ListStyleType::UpperAlpha, 26);
-    let result_extended_2 = marker_for_style(ListStyleType::UpperAlpha, 27);
-    assert_eq!(result_1, Some(Marker::String("A. ".to_string())));
-    assert_eq!(result_2, Some(Marker::String("B. ".to_string())));
-    assert_eq!(result_extended_1, Some(Marker::String("AA. ".to_string())));
-    assert_eq!(result_extended_2, Some(Marker::String("AB. ".to_string())));
-}
-
 /// Handles the cases where there are text nodes or inline nodes that need to be wrapped in an anonymous block node
 fn collect_complex_layout_children(
     doc: &mut BaseDocument,
@@ -963,6 +842,7 @@ pub(crate) fn build_inline_layout(
     if let Some(ListItemLayout {
         marker,
         position: ListItemLayoutPosition::Inside,
+        ..
     }) = root_node
         .element_data()
         .and_then(|el| el.list_item_data.as_deref())
@@ -974,47 +854,130 @@ pub(crate) fn build_inline_layout(
     };
 
     // Collect text content from all child nodes
-    if let Some(before_id) = root_node.before {
-        collect_inline_text_recursive(&mut text_content, &doc.nodes, before_id, collapse_mode);
-    }
-    for child_id in root_node.children.iter().copied() {
-        collect_inline_text_recursive(&mut text_content, &doc.nodes, child_id, collapse_mode);
+    let mut text_runs = Vec::new();
+    let child_ids = root_node
+        .before
+        .into_iter()
+        .chain(root_node.children.iter().copied())
+        .chain(root_node.after);
+    for child_id in child_ids {
+        collect_inline_text_recursive(
+            &mut text_content,
+            &mut text_runs,
+            &doc.nodes,
+            child_id,
+            collapse_mode,
+        );
     }
-    if let Some(after_id) = root_node.after {
-        collect_inline_text_recursive(&mut text_content, &doc.nodes, after_id, collapse_mode);
+    trim_final_line_break(&mut text_content, &mut text_runs);
+    let run_ranges: Vec<(&InlineTextRun, Range<usize>)> = text_runs
+        .iter()
+        .zip(
+            text_runs
+                .iter()
+                .skip(1)
+                .map(|run| run.start)
+                .chain([text_content.len()]),
+        )
+        .map(|(run, end)| (run, run.start..end))
+        .collect();
+
+    // cosmyc-text expands every tab in a buffer to the same width, so the tabs of the inline
+    // context are expanded to the `tab-size` of the element the first of them is in
+    let tab_width = run_ranges
+        .iter()
+        .find(|(_, range)| text_content[range.clone()].contains('\t'))
+        .and_then(|(run, _)| doc.nodes[run.node_id].parent)
+        .and_then(|parent_id| doc.nodes[parent_id].primary_styles())
+        .map(|s| stylo_to_blitz::tab_width(&s))
+        .or_else(|| root_node_style.as_ref().map(|s| stylo_to_blitz::tab_width(s)))
+        .unwrap_or(8);
+    let break_spaces: Vec<Range<usize>> = run_ranges
+        .iter()
+        .filter(|(run, _)| run.breakable_spaces)
+        .map(|(_, range)| range.clone())
+        .collect();
+
+    // Font features, font size, spacing and baseline can differ between the elements of the
+    // inline context, so the text of each element is shaped with its own styles. Text before the
+    // first run (e.g. an inside list marker) takes the styles of the root.
+    let root_run_style = RunStyle {
+        features: cosmyc_style.attrs.font_features.clone(),
+        metrics: cosmyc_style.metrics,
+        spacing: root_node_style
+            .as_ref()
+            .map(|s| stylo_to_blitz::text_spacing(s))
+            .unwrap_or_default(),
+        baseline_shift: 0.0,
+    };
+    let mut style_runs = vec![(0, root_run_style.clone())];
+    for run in &text_runs {
+        let style = doc.nodes[run.node_id]
+            .parent
+            .and_then(|parent_id| run_style(doc, parent_id, inline_context_root_node_id))
+            .unwrap_or_else(|| root_run_style.clone());
+        style_runs.push((run.start, style));
     }
+    let uniform_styles = style_runs.iter().all(|(_, style)| *style == root_run_style);
 
-    // Set the collected text in the buffer with styling
+    // Set the collected text in the buffer with styling. Letter and word spacing are applied by
+    // splitting the text into spans with different letter spacing.
     println!("🔍 build_inline_layout: Node {} collected text: '{}'", inline_context_root_node_id, text_content);
     let result = doc.with_text_system(|text_system| text_system.with_font_system(|font_system| {
-        buffer.set_text_cached(
-            font_system,
-            &text_content,
-            &cosmyc_style.attrs.as_attrs(),
-            blitz_text::Shaping::Advanced,
-        );
+        buffer.inner_mut().set_tab_width(font_system, tab_width);
+        let attrs = cosmyc_style.attrs.as_attrs();
+        if root_run_style.spacing.is_zero() && uniform_styles {
+            buffer.set_text_cached(font_system, &text_content, &attrs, blitz_text::Shaping::Advanced);
+        } else {
+            let run_ends = style_runs
+                .iter()
+                .skip(1)
+                .map(|(start, _)| *start)
+                .chain([text_content.len()]);
+            let spans = style_runs
+                .iter()
+                .zip(run_ends)
+                .filter(|((start, _), end)| start < end)
+                .flat_map(|((start, style), end)| {
+                    style
+                        .spacing
+                        .spans(&text_content[*start..end], style.metrics.font_size)
+                        .into_iter()
+                        .map(move |(span, letter_spacing)| (span, style, letter_spacing))
+                })
+                .map(|(span, style, letter_spacing)| {
+                    let mut span_attrs = attrs.clone();
+                    span_attrs.font_features = style.features.clone();
+                    span_attrs.letter_spacing_opt = letter_spacing;
+                    let mut metrics = style.metrics;
+                    if style.baseline_shift != 0.0 {
+                        span_attrs.metadata =
+                            encode_baseline_shift(span_attrs.metadata, style.baseline_shift);
+                        // Lines are as tall as their tallest span, with the text centered in it,
+                        // so growing the span by the shift on both sides makes its line hold the
+                        // moved text
+                        metrics.line_height += 2.0 * style.baseline_shift.abs();
+                    }
+                    if metrics != cosmyc_style.metrics {
+                        span_attrs.metrics_opt = Some(metrics.into());
+                    }
+                    (span, span_attrs)
+                });
+            buffer.set_rich_text_cached(
+                font_system,
+                spans,
+                &attrs,
+                blitz_text::Shaping::Advanced,
+                None,
+            );
+        }
     }));
     println!("🔍 build_inline_layout: Node {} text_system result: {:?}", inline_context_root_node_id, result);
 
     // Extract text alignment from CSS styles
     let alignment = root_node_style
         .as_ref()
-        .map(|s| {
-            use style::values::specified::TextAlignKeyword;
-            match s.clone_text_align() {
-                TextAlignKeyword::Start
-                | TextAlignKeyword::Left
-                | TextAlignKeyword::MozLeft => blitz_text::Align::Left,
-                TextAlignKeyword::Right | TextAlignKeyword::MozRight => {
-                    blitz_text::Align::Right
-                }
-                TextAlignKeyword::Center | TextAlignKeyword::MozCenter => {
-                    blitz_text::Align::Center
-                }
-                TextAlignKeyword::Justify => blitz_text::Align::Justified,
-                TextAlignKeyword::End => blitz_text::Align::Right,
-            }
-        });
+        .map(|s| stylo_to_blitz::text_alignment(s));
 
     // Apply alignment to all buffer lines
     buffer.inner_mut().lines.iter_mut().for_each(|line| {
@@ -1039,6 +1002,8 @@ pub(crate) fn build_inline_layout(
             text: text_content,
             layout: buffer,
             inline_boxes: Vec::new(), // Empty inline boxes for this case
+            text_nodes: text_runs.iter().map(|run| (run.start, run.node_id)).collect(),
+            break_spaces,
             cached_content_widths: None,
             cached_text_hash: None,
         },
@@ -1066,3 +1031,129 @@ pub(crate) fn build_inline_layout(
     // in the collect_inline_text.rs file. This provides better separation of concerns and
     // simplifies the text collection process for cosmyc-text buffers.
 }
+
+/// The styles which can differ between the text runs of an inline context
+#[derive(Clone, PartialEq)]
+struct RunStyle {
+    features: blitz_text::FontFeatures,
+    metrics: blitz_text::Metrics,
+    /// `letter-spacing` and `word-spacing`, which are inherited, but can be set on any element
+    spacing: blitz_text::TextSpacing,
+    /// How far the run is raised above the baseline of the inline context, in pixels
+    baseline_shift: f32,
+}
+
+/// Compute the styles of the text directly inside `element_id`. Baseline shifts accumulate, so
+/// `vertical-align` is summed over the element and its ancestors up to the inline context root.
+fn run_style(doc: &BaseDocument, element_id: usize, inline_root_id: usize) -> Option<RunStyle> {
+    let styles = doc.nodes[element_id].primary_styles()?;
+
+    let mut baseline_shift = 0.0;
+    let mut node_id = element_id;
+    while node_id != inline_root_id {
+        let Some(parent_id) = doc.nodes[node_id].parent else {
+            break;
+        };
+        if let (Some(node_styles), Some(parent_styles)) = (
+            doc.nodes[node_id].primary_styles(),
+            doc.nodes[parent_id].primary_styles(),
+        ) {
+            let parent_font_size = stylo_to_blitz::font_metrics(&parent_styles).font_size;
+            baseline_shift += stylo_to_blitz::baseline_shift(&node_styles, parent_font_size);
+        }
+        node_id = parent_id;
+    }
+
+    Some(RunStyle {
+        features: stylo_to_blitz::font_features(&styles).to_font_features(),
+        metrics: stylo_to_blitz::font_metrics(&styles),
+        spacing: stylo_to_blitz::text_spacing(&styles),
+        baseline_shift,
+    })
+}
+
+/// Parse an inline `<svg>` element (with its subtree) into an SVG image, remembering the colors
+/// `currentColor` resolved against so that it can be reparsed when they change
+#[cfg(feature = "svg")]
+pub(crate) fn load_inline_svg(doc: &mut BaseDocument, node_id: usize) {
+    let source = match doc.get_node(node_id) {
+        Some(node) => node.svg_source(),
+        None => {
+            eprintln!("Warning: Cannot process SVG for node {node_id}: node not found");
+            return;
+        }
+    };
+
+    match crate::util::parse_svg(source.as_bytes()) {
+        Ok(svg) => {
+            let node = match doc.get_node_mut(node_id) {
+                Some(node) => node,
+                None => {
+                    eprintln!("Warning: Cannot set SVG data for node {node_id}: node not found");
+                    return;
+                }
+            };
+            let element_data = match node.element_data_mut() {
+                Some(element) => element,
+                None => {
+                    eprintln!(
+                        "Warning: Cannot set SVG data for node {node_id}: node is not an element"
+                    );
+                    return;
+                }
+            };
+            element_data.special_data = SpecialElementData::Image(Box::new(svg.into()));
+        }
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Failed to parse the inline SVG of node {node_id}: {_err}");
+        }
+    };
+
+    if let Some(fingerprint) = svg_color_fingerprint(doc, node_id) {
+        doc.inline_svg_colors.insert(node_id, fingerprint);
+    }
+}
+
+/// Reparse the inline SVGs containing elements whose color changed since they were parsed
+#[cfg(feature = "svg")]
+pub(crate) fn refresh_inline_svgs(doc: &mut BaseDocument) {
+    let stale: Vec<usize> = doc
+        .inline_svg_colors
+        .iter()
+        .filter(|&(&node_id, &fingerprint)| {
+            svg_color_fingerprint(doc, node_id) != Some(fingerprint)
+        })
+        .map(|(&node_id, _)| node_id)
+        .collect();
+
+    for node_id in stale {
+        doc.inline_svg_colors.remove(&node_id);
+        let is_svg = doc
+            .get_node(node_id)
+            .is_some_and(|node| node.data.is_element_with_tag_name(&local_name!("svg")));
+        if is_svg {
+            load_inline_svg(doc, node_id);
+        }
+    }
+}
+
+/// Hash the computed colors of the elements in an inline SVG
+#[cfg(feature = "svg")]
+fn svg_color_fingerprint(doc: &BaseDocument, node_id: usize) -> Option<u64> {
+    use std::hash::{Hash, Hasher};
+
+    use crate::util::ToColorColor as _;
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    let mut stack = vec![node_id];
+    while let Some(id) = stack.pop() {
+        let node = doc.get_node(id)?;
+        if let Some(styles) = node.primary_styles() {
+            let color = styles.clone_color().as_color_color();
+            color.to_rgba8().to_u32().hash(&mut hasher);
+        }
+        stack.extend(node.children.iter().copied());
+    }
+    Some(hasher.finish())
+}
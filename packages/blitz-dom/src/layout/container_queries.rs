@@ -0,0 +1,61 @@
+//! Size container queries (`@container`)
+//!
+//! Elements with a `container-type` other than `normal` establish size query containers.
+//! Stylo evaluates `@container` rules against the container's content-box size, which it reads
+//! through `TElement::query_container_size`. That size is only known after layout, so
+//! [`BaseDocument::resolve`] interleaves style and layout: after each layout pass the container
+//! sizes are recorded here, and if any of them changed the affected subtrees are restyled and the
+//! document is laid out again.
+//!
+//! See <https://drafts.csswg.org/css-conditional-5/#container-queries>
+
+use style::invalidation::element::restyle_hints::RestyleHint;
+use style::values::specified::box_::ContainerType;
+
+use crate::BaseDocument;
+
+/// The maximum number of style + layout passes per `resolve()`.
+///
+/// Container queries can form cycles (a query result changing the container's own size through
+/// its ancestors), so the iteration is capped rather than run to a fixed point.
+pub(crate) const MAX_CONTAINER_QUERY_PASSES: usize = 3;
+
+impl BaseDocument {
+    /// Record the content-box size of every size query container from the latest layout.
+    ///
+    /// Containers whose size changed get a restyle hint for their descendants so that `@container`
+    /// rules are re-evaluated on the next style pass. Returns `true` if any container changed.
+    pub(crate) fn update_container_sizes(&mut self) -> bool {
+        let mut changed = false;
+
+        for (_, node) in self.nodes.iter_mut() {
+            let is_container = node.primary_styles().is_some_and(|styles| {
+                styles.clone_container_type().intersects(
+                    ContainerType::SIZE | ContainerType::INLINE_SIZE,
+                )
+            });
+
+            let size = is_container.then(|| {
+                let layout = &node.final_layout;
+                taffy::Size {
+                    width: (layout.size.width
+                        - layout.padding.horizontal_axis_sum()
+                        - layout.border.horizontal_axis_sum())
+                    .max(0.0),
+                    height: (layout.size.height
+                        - layout.padding.vertical_axis_sum()
+                        - layout.border.vertical_axis_sum())
+                    .max(0.0),
+                }
+            });
+
+            if node.container_size != size {
+                node.container_size = size;
+                node.set_restyle_hint(RestyleHint::RESTYLE_DESCENDANTS);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
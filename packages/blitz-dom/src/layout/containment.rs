@@ -0,0 +1,175 @@
+//! CSS Containment (`contain`) and `content-visibility` support
+//!
+//! Size containment lays an element out as if it were empty, so its size never depends on its
+//! descendants. Elements with `content-visibility: auto` that are far from the viewport (and
+//! `content-visibility: hidden` elements) go one step further and skip layout and paint of their
+//! contents entirely, keeping the size they had the last time they were laid out. Because the
+//! skipped subtree is never visited, its nodes keep their previous layout and are not re-laid out
+//! until the element becomes relevant to the user again.
+//!
+//! See <https://drafts.csswg.org/css-contain-2/>
+
+use style::values::specified::Contain;
+use style::values::specified::box_::ContentVisibility;
+use taffy::{NodeId, RunMode, compute_leaf_layout};
+
+use super::resolve_calc_value;
+use crate::BaseDocument;
+use crate::node::{DisplayOuter, NodeFlags};
+
+/// How far (as a fraction of the viewport size) outside the viewport a `content-visibility: auto`
+/// element may be and still count as relevant to the user. Laying out slightly off-screen content
+/// avoids visible pop-in while scrolling.
+const RELEVANCE_VIEWPORT_MARGIN: f32 = 0.5;
+
+/// Compute layout for a node with size containment or skipped contents.
+///
+/// Returns `None` if the node is not size contained (or its contained axes are already fixed
+/// by the parent), in which case layout should proceed as normal.
+pub(crate) fn compute_contained_layout(
+    tree: &mut BaseDocument,
+    node_id: NodeId,
+    inputs: taffy::tree::LayoutInput,
+) -> Option<taffy::tree::LayoutOutput> {
+    let node = &tree.nodes[node_id.into()];
+    if !node.is_element() {
+        return None;
+    }
+
+    let contain = node.containment();
+    let skips_contents = node.flags.skips_contents();
+    let contains_inline_size = contain.contains(Contain::INLINE_SIZE);
+    let contains_block_size = contain.contains(Contain::BLOCK_SIZE);
+    if !skips_contents && !contains_inline_size && !contains_block_size {
+        return None;
+    }
+
+    // A skipped `content-visibility: auto` element keeps the content-box size from its last
+    // layout (the "last remembered size" from css-sizing-4). Otherwise size containment sizes
+    // the element as if it had no contents.
+    let placeholder = if skips_contents && node.content_visibility() == ContentVisibility::Auto {
+        let layout = &node.unrounded_layout;
+        taffy::Size {
+            width: (layout.size.width
+                - layout.padding.horizontal_axis_sum()
+                - layout.border.horizontal_axis_sum())
+            .max(0.0),
+            height: (layout.size.height
+                - layout.padding.vertical_axis_sum()
+                - layout.border.vertical_axis_sum())
+            .max(0.0),
+        }
+    } else {
+        taffy::Size::ZERO
+    };
+
+    let sized = compute_leaf_layout(
+        inputs,
+        node.style(),
+        resolve_calc_value,
+        |known_dimensions, _available_space| taffy::Size {
+            width: known_dimensions.width.unwrap_or(placeholder.width),
+            height: known_dimensions.height.unwrap_or(placeholder.height),
+        },
+    );
+
+    if skips_contents {
+        return Some(taffy::tree::LayoutOutput {
+            content_size: sized.size,
+            first_baselines: taffy::Point::NONE,
+            ..sized
+        });
+    }
+
+    if inputs.run_mode == RunMode::ComputeSize && contains_inline_size && contains_block_size {
+        return Some(sized);
+    }
+
+    // Fix the contained axes and lay out the contents within them
+    let known_dimensions = taffy::Size {
+        width: inputs
+            .known_dimensions
+            .width
+            .or(contains_inline_size.then_some(sized.size.width)),
+        height: inputs
+            .known_dimensions
+            .height
+            .or(contains_block_size.then_some(sized.size.height)),
+    };
+    if known_dimensions == inputs.known_dimensions {
+        return None;
+    }
+
+    let inputs = taffy::tree::LayoutInput {
+        known_dimensions,
+        ..inputs
+    };
+    Some(taffy::LayoutPartialTree::compute_child_layout(
+        tree, node_id, inputs,
+    ))
+}
+
+impl BaseDocument {
+    /// Update which `content-visibility` elements skip their contents.
+    ///
+    /// `hidden` elements always skip their contents. `auto` elements skip them while they are
+    /// not relevant to the user: far from the viewport and not containing the focused element.
+    /// Relevance is determined from the previous layout, so an element is always laid out at least
+    /// once before it can be skipped.
+    ///
+    /// Returns `true` if any element changed state, in which case layout must be re-run.
+    pub(crate) fn update_content_visibility(&mut self) -> bool {
+        let viewport_size = self.stylist.device().au_viewport_size();
+        let margin_x = viewport_size.width.to_f32_px() * RELEVANCE_VIEWPORT_MARGIN;
+        let margin_y = viewport_size.height.to_f32_px() * RELEVANCE_VIEWPORT_MARGIN;
+        let min_x = self.viewport_scroll.x as f32 - margin_x;
+        let min_y = self.viewport_scroll.y as f32 - margin_y;
+        let max_x = self.viewport_scroll.x as f32 + viewport_size.width.to_f32_px() + margin_x;
+        let max_y = self.viewport_scroll.y as f32 + viewport_size.height.to_f32_px() + margin_y;
+
+        let focus_ancestors = self.maybe_node_layout_ancestors(self.focus_node_id);
+
+        let mut changed = Vec::new();
+        for (node_id, node) in self.nodes.iter() {
+            if !node.is_element() || node.display_outer == DisplayOuter::Inline {
+                continue;
+            }
+
+            let should_skip = match node.content_visibility() {
+                ContentVisibility::Visible => false,
+                ContentVisibility::Hidden => true,
+                ContentVisibility::Auto => {
+                    let size = node.final_layout.size;
+                    let has_layout = size.width > 0.0 || size.height > 0.0;
+                    let position = node.absolute_position(0.0, 0.0);
+                    let on_screen = position.x + size.width >= min_x
+                        && position.x <= max_x
+                        && position.y + size.height >= min_y
+                        && position.y <= max_y;
+
+                    has_layout && !on_screen && !focus_ancestors.contains(&node_id)
+                }
+            };
+
+            if should_skip != node.flags.skips_contents() {
+                changed.push((node_id, should_skip));
+            }
+        }
+
+        for &(node_id, should_skip) in &changed {
+            self.nodes[node_id]
+                .flags
+                .set(NodeFlags::SKIPS_CONTENTS, should_skip);
+
+            // The element's size may change when its contents are (un)skipped
+            let mut ancestor = Some(node_id);
+            while let Some(id) = ancestor {
+                let node = &mut self.nodes[id];
+                node.cache.clear();
+                ancestor = node.layout_parent.get();
+            }
+        }
+
+        !changed.is_empty()
+    }
+}
@@ -0,0 +1,645 @@
+//! CSS counters, and the generated content which uses them
+//!
+//! After style passes which restyled elements, or after elements were added or removed, the
+//! document is walked in tree order to evaluate `counter-reset` and `counter-increment`
+//! (`counter-set` is Gecko-only in stylo), including the implicit `list-item` counter of list
+//! items and the `start`, `reversed` and `value` attributes of HTML lists. This
+//! produces the markers of list items and the text of `::before` and `::after` pseudo-elements,
+//! which box construction reads. When either changes, the boxes which show them are constructed
+//! again.
+//!
+//! `::marker` styles are computed lazily for each list item. Their `content` replaces the marker
+//! from `list-style-type`, and outside markers are drawn with their font and color. Inside markers
+//! are part of their item's text, so they are drawn like it.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use markup5ever::local_name;
+use style::Atom;
+use style::properties::ComputedValues;
+use style::selector_parser::PseudoElement;
+use style::servo_arc::Arc as ServoArc;
+use style::shared_lock::StylesheetGuards;
+use style::stylist::RuleInclusion;
+use style::values::computed::{Content, ContentItem};
+use style_traits::ToCss;
+
+use crate::BaseDocument;
+use crate::layout::stylo_to_blitz;
+use crate::node::{Marker, Node};
+
+/// Which of an element's pseudo-elements generated content is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum PseudoBox {
+    Before,
+    After,
+}
+
+/// The result of evaluating the document's counters
+#[derive(Debug, Default)]
+pub(crate) struct GeneratedContent {
+    /// The marker of each list item
+    pub(crate) markers: HashMap<usize, Marker>,
+    /// The styles of list items' `::marker`s, for those which any rules apply to
+    pub(crate) marker_styles: HashMap<usize, ServoArc<ComputedValues>>,
+    /// The text of the `content` of `::before` and `::after` pseudo-elements
+    pub(crate) pseudo_content: HashMap<(usize, PseudoBox), String>,
+}
+
+/// A predefined counter style, which formats counter values as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterStyle {
+    None,
+    Disc,
+    Circle,
+    Square,
+    DisclosureOpen,
+    DisclosureClosed,
+    Decimal,
+    DecimalLeadingZero,
+    LowerRoman,
+    UpperRoman,
+    LowerAlpha,
+    UpperAlpha,
+    LowerGreek,
+}
+
+impl CounterStyle {
+    /// The counter style called `name`. Unknown styles fall back to `decimal`.
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Self::None,
+            "disc" => Self::Disc,
+            "circle" => Self::Circle,
+            "square" => Self::Square,
+            "disclosure-open" => Self::DisclosureOpen,
+            "disclosure-closed" => Self::DisclosureClosed,
+            "decimal-leading-zero" => Self::DecimalLeadingZero,
+            "lower-roman" => Self::LowerRoman,
+            "upper-roman" => Self::UpperRoman,
+            "lower-alpha" | "lower-latin" => Self::LowerAlpha,
+            "upper-alpha" | "upper-latin" => Self::UpperAlpha,
+            "lower-greek" => Self::LowerGreek,
+            _ => Self::Decimal,
+        }
+    }
+
+    /// The counter style of a computed `list-style-type` or counter style value
+    pub(crate) fn from_css(value: &impl ToCss) -> Self {
+        Self::from_name(&value.to_css_string())
+    }
+
+    /// The symbol of a style which shows the same symbol for every value
+    fn symbol(self) -> Option<char> {
+        match self {
+            Self::Disc => Some('•'),
+            Self::Circle => Some('◦'),
+            Self::Square => Some('▪'),
+            Self::DisclosureOpen => Some('▾'),
+            Self::DisclosureClosed => Some('▸'),
+            _ => None,
+        }
+    }
+
+    /// Format a counter value, as `counter()` does
+    pub fn format(self, value: i32) -> String {
+        if let Some(symbol) = self.symbol() {
+            return symbol.to_string();
+        }
+        // Values outside a style's range are formatted with `decimal` instead
+        let formatted = match self {
+            Self::None => Some(String::new()),
+            Self::DecimalLeadingZero if (0..10).contains(&value) => Some(format!("0{value}")),
+            Self::LowerRoman => roman(value).map(|roman| roman.to_ascii_lowercase()),
+            Self::UpperRoman => roman(value),
+            Self::LowerAlpha => alphabetic(value, &LATIN),
+            Self::UpperAlpha => alphabetic(value, &LATIN).map(|alpha| alpha.to_ascii_uppercase()),
+            Self::LowerGreek => alphabetic(value, &GREEK),
+            _ => None,
+        };
+        formatted.unwrap_or_else(|| value.to_string())
+    }
+
+    /// The marker of a list item whose `list-item` counter is `value`
+    pub fn marker(self, value: i32) -> Option<Marker> {
+        match self {
+            Self::None => None,
+            style => Some(match style.symbol() {
+                Some(symbol) => Marker::Char(symbol),
+                None => Marker::String(format!("{}. ", style.format(value))),
+            }),
+        }
+    }
+}
+
+const LATIN: [char; 26] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+
+const GREEK: [char; 24] = [
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ',
+    'υ', 'φ', 'χ', 'ψ', 'ω',
+];
+
+/// Format a positive value with an alphabetic system: a, b, ..., z, aa, ab, ...
+fn alphabetic(value: i32, symbols: &[char]) -> Option<String> {
+    if value < 1 {
+        return None;
+    }
+    let mut value = value as usize;
+    let mut text = Vec::new();
+    while value > 0 {
+        value -= 1;
+        text.push(symbols[value % symbols.len()]);
+        value /= symbols.len();
+    }
+    Some(text.into_iter().rev().collect())
+}
+
+/// Format a value between 1 and 3999 as an uppercase roman numeral
+fn roman(value: i32) -> Option<String> {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    if !(1..4000).contains(&value) {
+        return None;
+    }
+    let mut value = value;
+    let mut text = String::new();
+    for (numeral_value, numeral) in NUMERALS {
+        while value >= numeral_value {
+            text.push_str(numeral);
+            value -= numeral_value;
+        }
+    }
+    Some(text)
+}
+
+/// An instance of a counter
+struct Counter {
+    name: Atom,
+    value: i32,
+    /// The tree depth of the element which created the counter. Its scope is that element, its
+    /// following siblings, and their descendants.
+    depth: usize,
+    /// Whether list items count down (the `list-item` counter of `<ol reversed>`)
+    reversed: bool,
+}
+
+/// Walks the document in tree order, keeping track of the counters in scope
+#[derive(Default)]
+struct CounterResolver {
+    counters: Vec<Counter>,
+    /// How many quotes are currently open, for `open-quote` and `close-quote`
+    quote_depth: usize,
+    output: GeneratedContent,
+}
+
+impl BaseDocument {
+    /// Evaluate the document's counters and the generated content using them
+    pub(crate) fn resolve_generated_content(&mut self) {
+        let Some(root_id) = self.try_root_element().map(|root| root.id) else {
+            return;
+        };
+        let mut resolver = CounterResolver::default();
+        resolver.visit_element(self, root_id, 0);
+        let generated_content = resolver.output;
+
+        // Construct the boxes showing generated content which changed again. Markers are set up
+        // by their item's parent, and pseudo-elements by their originating element.
+        let previous = std::mem::take(&mut self.generated_content);
+        let mut invalidated = Vec::new();
+        for node_id in changed_keys(&previous.markers, &generated_content.markers, PartialEq::eq)
+            .chain(changed_keys(
+                &previous.marker_styles,
+                &generated_content.marker_styles,
+                draw_alike,
+            ))
+        {
+            invalidated.extend(self.nodes.get(node_id).and_then(|node| node.parent));
+        }
+        for (node_id, _) in changed_keys(
+            &previous.pseudo_content,
+            &generated_content.pseudo_content,
+            PartialEq::eq,
+        ) {
+            invalidated.push(node_id);
+            invalidated.extend(self.nodes.get(node_id).and_then(|node| node.parent));
+        }
+        for node_id in invalidated {
+            if let Some(node) = self.nodes.get(node_id) {
+                *node.layout_children.borrow_mut() = None;
+            }
+        }
+        self.generated_content = generated_content;
+    }
+}
+
+impl CounterResolver {
+    fn visit_element(&mut self, doc: &BaseDocument, node_id: usize, depth: usize) {
+        let node = &doc.nodes[node_id];
+        let Some(element) = node.element_data() else {
+            return;
+        };
+        let Some(styles) = node.primary_styles() else {
+            return;
+        };
+        if styles.get_box().display.is_none() {
+            return;
+        }
+        self.enter(depth);
+
+        let list_item = Atom::from("list-item");
+        let counters = styles.get_counters();
+        for reset in counters.counter_reset.iter() {
+            self.reset(reset.name.0.clone(), reset.value, depth, false);
+        }
+        // `<ol start reversed>`
+        if element.name.local == local_name!("ol") {
+            let reversed = element.attr(local_name!("reversed")).is_some();
+            let start = element.attr_parsed::<i32>(local_name!("start"));
+            let value = match (start, reversed) {
+                (Some(start), false) => start - 1,
+                (Some(start), true) => start + 1,
+                (None, false) => 0,
+                (None, true) => count_list_items(doc, node_id) + 1,
+            };
+            self.reset(list_item.clone(), value, depth, reversed);
+        }
+
+        let mut increments_list_item = false;
+        for increment in counters.counter_increment.iter() {
+            increments_list_item |= increment.name.0 == list_item;
+            self.increment(&increment.name.0, increment.value, depth);
+        }
+        let is_list_item = styles.get_box().display.is_list_item();
+        if is_list_item {
+            // List items count themselves, down in reversed lists
+            if !increments_list_item {
+                let reversed = self.find(&list_item).is_some_and(|counter| counter.reversed);
+                self.increment(&list_item, if reversed { -1 } else { 1 }, depth);
+            }
+            // `<li value>`
+            if let Some(value) = element.attr_parsed::<i32>(local_name!("value")) {
+                self.find_or_create(&list_item, depth).value = value;
+            }
+            self.resolve_marker(doc, node, &styles, depth);
+        }
+
+        if let Some(before) = styles_of_pseudo(node, PseudoBox::Before) {
+            self.visit_pseudo(node_id, PseudoBox::Before, &before, depth + 1);
+        }
+        for &child_id in &node.children {
+            self.visit_element(doc, child_id, depth + 1);
+        }
+        if let Some(after) = styles_of_pseudo(node, PseudoBox::After) {
+            self.visit_pseudo(node_id, PseudoBox::After, &after, depth + 1);
+        }
+    }
+
+    fn visit_pseudo(
+        &mut self,
+        node_id: usize,
+        pseudo: PseudoBox,
+        styles: &ComputedValues,
+        depth: usize,
+    ) {
+        if styles.get_box().display.is_none() {
+            return;
+        }
+        self.enter(depth);
+        let counters = styles.get_counters();
+        for reset in counters.counter_reset.iter() {
+            self.reset(reset.name.0.clone(), reset.value, depth, false);
+        }
+        for increment in counters.counter_increment.iter() {
+            self.increment(&increment.name.0, increment.value, depth);
+        }
+        if let Some(text) = self.content_text(styles, depth) {
+            self.output.pseudo_content.insert((node_id, pseudo), text);
+        }
+    }
+
+    fn resolve_marker(
+        &mut self,
+        doc: &BaseDocument,
+        node: &Node,
+        styles: &ComputedValues,
+        depth: usize,
+    ) {
+        let marker_styles = marker_styles(doc, node, styles);
+        let content = marker_styles
+            .as_ref()
+            .and_then(|marker_styles| self.content_text(marker_styles, depth));
+        let hidden = marker_styles.as_ref().is_some_and(|marker_styles| {
+            matches!(marker_styles.get_counters().content, Content::None)
+        });
+        let marker = match content {
+            Some(content) => Some(Marker::String(content)),
+            None if hidden => None,
+            None => {
+                let value = self.find(&Atom::from("list-item")).map_or(0, |counter| counter.value);
+                CounterStyle::from_css(&styles.clone_list_style_type()).marker(value)
+            }
+        };
+        if let Some(marker) = marker {
+            self.output.markers.insert(node.id, marker);
+        }
+        if let Some(marker_styles) = marker_styles {
+            self.output.marker_styles.insert(node.id, marker_styles);
+        }
+    }
+
+    /// The text of a `content` value other than `normal` and `none`
+    fn content_text(&mut self, styles: &ComputedValues, depth: usize) -> Option<String> {
+        let Content::Items(items) = &styles.get_counters().content else {
+            return None;
+        };
+        let mut text = String::new();
+        for item in &items.items[0..items.alt_start] {
+            match item {
+                ContentItem::String(string) => text.push_str(string),
+                ContentItem::Counter(name, style) => {
+                    let value = self.find_or_create(&name.0, depth).value;
+                    text.push_str(&CounterStyle::from_css(style).format(value));
+                }
+                ContentItem::Counters(name, separator, style) => {
+                    self.find_or_create(&name.0, depth);
+                    let style = CounterStyle::from_css(style);
+                    let values: Vec<String> = self
+                        .counters
+                        .iter()
+                        .filter(|counter| counter.name == name.0)
+                        .map(|counter| style.format(counter.value))
+                        .collect();
+                    text.push_str(&values.join(&**separator));
+                }
+                ContentItem::OpenQuote => {
+                    text.push(if self.quote_depth % 2 == 0 { '“' } else { '‘' });
+                    self.quote_depth += 1;
+                }
+                ContentItem::CloseQuote => {
+                    self.quote_depth = self.quote_depth.saturating_sub(1);
+                    text.push(if self.quote_depth % 2 == 0 { '”' } else { '’' });
+                }
+                ContentItem::NoOpenQuote => self.quote_depth += 1,
+                ContentItem::NoCloseQuote => {
+                    self.quote_depth = self.quote_depth.saturating_sub(1);
+                }
+                // Images and attribute values aren't supported yet
+                _ => {}
+            }
+        }
+        Some(text)
+    }
+
+    /// Leave the scope of the counters created by the descendants of the previous siblings of
+    /// an element at `depth`
+    fn enter(&mut self, depth: usize) {
+        self.counters.retain(|counter| counter.depth <= depth);
+    }
+
+    fn find(&self, name: &Atom) -> Option<&Counter> {
+        self.counters.iter().rev().find(|counter| counter.name == *name)
+    }
+
+    /// The innermost counter called `name`, which is created on the current element (with the
+    /// value 0) if there is none
+    fn find_or_create(&mut self, name: &Atom, depth: usize) -> &mut Counter {
+        match self.counters.iter().rposition(|counter| counter.name == *name) {
+            Some(index) => &mut self.counters[index],
+            None => {
+                self.reset(name.clone(), 0, depth, false);
+                self.counters.last_mut().unwrap()
+            }
+        }
+    }
+
+    fn reset(&mut self, name: Atom, value: i32, depth: usize, reversed: bool) {
+        let counter = Counter {
+            name,
+            value,
+            depth,
+            reversed,
+        };
+        // A counter created by a previous sibling is replaced rather than nested in
+        let sibling = self.counters.iter().rposition(|existing| {
+            existing.name == counter.name && existing.depth == depth
+        });
+        match sibling {
+            Some(index) => self.counters[index] = counter,
+            None => self.counters.push(counter),
+        }
+    }
+
+    fn increment(&mut self, name: &Atom, by: i32, depth: usize) {
+        let counter = self.find_or_create(name, depth);
+        counter.value = counter.value.saturating_add(by);
+    }
+}
+
+/// The keys whose values differ between two maps, including those only in one of them
+fn changed_keys<'a, K: Copy + Eq + Hash, V>(
+    previous: &'a HashMap<K, V>,
+    current: &'a HashMap<K, V>,
+    eq: impl Fn(&V, &V) -> bool + 'a,
+) -> impl Iterator<Item = K> + 'a {
+    let changed = current.iter().filter_map(move |(key, value)| match previous.get(key) {
+        Some(previous) if eq(previous, value) => None,
+        _ => Some(*key),
+    });
+    let removed = previous.keys().filter(|key| !current.contains_key(key)).copied();
+    changed.chain(removed)
+}
+
+/// Whether two `::marker` styles draw their marker the same way. Lazily computed styles are
+/// never shared between style passes, so they're compared by value.
+fn draw_alike(previous: &ServoArc<ComputedValues>, current: &ServoArc<ComputedValues>) -> bool {
+    if ServoArc::ptr_eq(previous, current) {
+        return true;
+    }
+    let previous = stylo_to_blitz::style(0, previous);
+    let current = stylo_to_blitz::style(0, current);
+    previous.attrs == current.attrs
+        && previous.metrics == current.metrics
+        && previous.wrap == current.wrap
+}
+
+/// The styles of an element's `::before` or `::after` pseudo-element
+fn styles_of_pseudo(node: &Node, pseudo: PseudoBox) -> Option<ServoArc<ComputedValues>> {
+    // Stylo keeps the styles of eager pseudo-elements in the order `::after`, `::before`
+    let index = match pseudo {
+        PseudoBox::Before => 1,
+        PseudoBox::After => 0,
+    };
+    let data = node.stylo_element_data.borrow();
+    data.as_ref()?.styles.pseudos.as_array()[index].clone()
+}
+
+/// The styles of a list item's `::marker`, if any rules apply to it
+fn marker_styles(
+    doc: &BaseDocument,
+    node: &Node,
+    styles: &ComputedValues,
+) -> Option<ServoArc<ComputedValues>> {
+    let guard = doc.guard.read();
+    let guards = StylesheetGuards::same(&guard);
+    doc.stylist.lazily_compute_pseudo_element_style(
+        &guards,
+        node,
+        &PseudoElement::Marker,
+        RuleInclusion::All,
+        styles,
+        // Only compute a style if some rule applies to the marker
+        true,
+        None,
+    )
+}
+
+/// The number of list items in a list, which a reversed list counts down from
+fn count_list_items(doc: &BaseDocument, list_id: usize) -> i32 {
+    doc.nodes[list_id]
+        .children
+        .iter()
+        .filter(|&&child_id| {
+            doc.nodes[child_id]
+                .primary_styles()
+                .is_some_and(|styles| styles.get_box().display.is_list_item())
+        })
+        .count() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use style::properties::longhands::list_style_type::computed_value::T as ListStyleType;
+
+    use super::*;
+    use crate::testing::{append_styled, document};
+
+    fn marker(list_style_type: ListStyleType, value: i32) -> Option<Marker> {
+        CounterStyle::from_css(&list_style_type).marker(value)
+    }
+
+    #[test]
+    fn test_marker_for_disc() {
+        assert_eq!(marker(ListStyleType::Disc, 1), Some(Marker::Char('•')));
+    }
+
+    #[test]
+    fn test_marker_for_decimal() {
+        assert_eq!(marker(ListStyleType::Decimal, 1), Some(Marker::String("1. ".to_string())));
+        assert_eq!(marker(ListStyleType::Decimal, 2), Some(Marker::String("2. ".to_string())));
+    }
+
+    #[test]
+    fn test_marker_for_lower_alpha() {
+        let result = |value| marker(ListStyleType::LowerAlpha, value);
+        assert_eq!(result(1), Some(Marker::String("a. ".to_string())));
+        assert_eq!(result(2), Some(Marker::String("b. ".to_string())));
+        assert_eq!(result(27), Some(Marker::String("aa. ".to_string())));
+        assert_eq!(result(28), Some(Marker::String("ab. ".to_string())));
+    }
+
+    #[test]
+    fn test_marker_for_upper_alpha() {
+        let result = |value| marker(ListStyleType::UpperAlpha, value);
+        assert_eq!(result(1), Some(Marker::String("A. ".to_string())));
+        assert_eq!(result(2), Some(Marker::String("B. ".to_string())));
+        assert_eq!(result(27), Some(Marker::String("AA. ".to_string())));
+        assert_eq!(result(28), Some(Marker::String("AB. ".to_string())));
+    }
+
+    #[test]
+    fn formats_counter_styles() {
+        assert_eq!(CounterStyle::from_name("lower-roman").format(1994), "mcmxciv");
+        assert_eq!(CounterStyle::from_name("upper-roman").format(4000), "4000");
+        assert_eq!(CounterStyle::from_name("upper-alpha").format(28), "AB");
+        assert_eq!(CounterStyle::from_name("lower-greek").format(25), "αα");
+        assert_eq!(CounterStyle::from_name("decimal-leading-zero").format(7), "07");
+        assert_eq!(CounterStyle::from_name("unknown").format(-3), "-3");
+        assert_eq!(CounterStyle::from_name("square").marker(3), Some(Marker::Char('▪')));
+        assert_eq!(CounterStyle::from_name("none").marker(3), None);
+    }
+
+    /// A document styled by `css`, with `count` elements called `item` in an element called
+    /// `container`
+    fn list_document(
+        css: &str,
+        container: &str,
+        item: &str,
+        count: usize,
+    ) -> (BaseDocument, Vec<usize>) {
+        let mut doc = document();
+        let mut mutr = doc.mutate();
+        let html = append_styled(&mut mutr, 0, "html", "");
+        let style = append_styled(&mut mutr, html, "style", "");
+        let css = mutr.create_text_node(css);
+        mutr.append_children(style, &[css]);
+        let body = append_styled(&mut mutr, html, "body", "");
+        let container = append_styled(&mut mutr, body, container, "");
+        let items = (0..count)
+            .map(|_| append_styled(&mut mutr, container, item, ""))
+            .collect();
+        drop(mutr);
+        doc.resolve();
+        (doc, items)
+    }
+
+    fn before_text(doc: &BaseDocument, node_id: usize) -> Option<&str> {
+        let content = &doc.generated_content.pseudo_content;
+        content.get(&(node_id, PseudoBox::Before)).map(String::as_str)
+    }
+
+    #[test]
+    fn counters_are_reset_and_incremented() {
+        let css = "div { counter-reset: section 2 } \
+                   p { counter-increment: section 3 } \
+                   p::before { content: counter(section, upper-roman) \". \" }";
+        let (doc, items) = list_document(css, "div", "p", 2);
+        assert_eq!(before_text(&doc, items[0]), Some("V. "));
+        assert_eq!(before_text(&doc, items[1]), Some("VIII. "));
+    }
+
+    #[test]
+    fn marker_content_replaces_the_list_style_type() {
+        let css = "li::marker { content: \"(\" counter(list-item) \") \"; color: red }";
+        let (doc, items) = list_document(css, "ol", "li", 2);
+        let markers = &doc.generated_content.markers;
+        assert_eq!(markers.get(&items[0]), Some(&Marker::String("(1) ".to_string())));
+        assert_eq!(markers.get(&items[1]), Some(&Marker::String("(2) ".to_string())));
+        assert!(doc.generated_content.marker_styles.contains_key(&items[1]));
+    }
+
+    #[test]
+    fn removing_list_items_renumbers_the_rest() {
+        let (mut doc, items) = list_document("", "ol", "li", 2);
+        doc.mutate().remove_node(items[0]);
+        doc.resolve();
+        let markers = &doc.generated_content.markers;
+        assert_eq!(markers.get(&items[1]), Some(&Marker::String("1. ".to_string())));
+        assert!(!markers.contains_key(&items[0]));
+    }
+
+    #[test]
+    fn unchanged_marker_styles_keep_their_boxes() {
+        let (mut doc, items) = list_document("li::marker { color: red }", "ol", "li", 2);
+        let list_id = doc.nodes[items[0]].parent.unwrap();
+        // Evaluating the counters again computes new `::marker` styles with the same values
+        doc.resolve_generated_content();
+        assert!(doc.nodes[list_id].layout_children.borrow().is_some());
+    }
+}
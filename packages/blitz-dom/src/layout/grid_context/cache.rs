@@ -10,6 +10,7 @@ use std::time::Duration;
 
 use taffy::prelude::NodeId;
 
+use super::super::grid_style_access::GridStyleAccess;
 use super::resolution::check_parent_grid_container;
 use super::types::{GridContextError, ParentGridContext};
 
@@ -54,14 +55,14 @@ impl GridContextCache {
         self.cache_generation += 1;
     }
 
-    /// High-performance cached lookup with O(1) BaseDocument optimization
+    /// High-performance cached lookup with O(1) parent access when the tree provides it
     pub fn get_or_compute_parent_context<Tree>(
         &mut self,
         tree: &Tree,
         node_id: NodeId,
     ) -> Result<Option<ParentGridContext>, GridContextError>
     where
-        Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+        Tree: GridStyleAccess,
     {
         // Level 1: Check cache first
         if let Some(parent_opt) = self.parent_cache.get(&node_id) {
@@ -76,21 +77,16 @@ impl GridContextCache {
             }
         }
 
-        // Level 2: Use O(1) direct access for BaseDocument
+        // Level 2: Use O(1) direct parent access where available
         self.cache_misses.fetch_add(1, Ordering::Relaxed);
         let result = self.compute_parent_context_efficient(tree, node_id)?;
 
         // Update cache with result
         if let Some(context) = &result {
-            // For BaseDocument, we can easily get the parent to cache
-            if let Some(base_doc) = (tree as &dyn std::any::Any).downcast_ref::<crate::BaseDocument>() {
-                if let Some(node) = base_doc.get_node(usize::from(node_id)) {
-                    if let Some(parent_id) = node.parent {
-                        let parent_node_id = NodeId::from(parent_id);
-                        self.parent_cache.insert(node_id, Some(parent_node_id));
-                        self.context_cache.insert(parent_node_id, context.clone());
-                    }
-                }
+            // Trees that track parents let us cache the parent -> context mapping
+            if let Some(parent_node_id) = tree.grid_parent(node_id) {
+                self.parent_cache.insert(node_id, Some(parent_node_id));
+                self.context_cache.insert(parent_node_id, context.clone());
             }
         } else {
             self.parent_cache.insert(node_id, None);
@@ -100,31 +96,21 @@ impl GridContextCache {
         Ok(result)
     }
 
-    /// Compute parent context using O(1) direct access for BaseDocument
+    /// Compute parent context using O(1) direct parent access where available
     fn compute_parent_context_efficient<Tree>(
         &mut self,
         tree: &Tree,
         target_node: NodeId,
     ) -> Result<Option<ParentGridContext>, GridContextError>
     where
-        Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+        Tree: GridStyleAccess,
     {
-        // Use direct parent access for BaseDocument (O(1))
-        if let Some(base_doc) = (tree as &dyn std::any::Any).downcast_ref::<crate::BaseDocument>() {
-            let node = match base_doc.get_node(usize::from(target_node)) {
-                Some(node) => node,
-                None => return Ok(None),
-            };
-            
-            let parent_id = match node.parent {
-                Some(id) => NodeId::from(id),
-                None => return Ok(None),
-            };
-            
+        // Use direct parent access (O(1)) when the tree tracks parents
+        if let Some(parent_id) = tree.grid_parent(target_node) {
             return check_parent_grid_container(tree, parent_id);
         }
-        
-        // Fallback for non-BaseDocument trees (keep existing heuristic for compatibility)
+
+        // Fallback for trees without parent links (keep existing heuristic for compatibility)
         self.find_parent_heuristic(tree, target_node)
             .and_then(|parent_opt| {
                 match parent_opt {
@@ -136,7 +122,7 @@ impl GridContextCache {
 
 
 
-    /// Simplified heuristic for non-BaseDocument trees only
+    /// Simplified heuristic for trees without parent links only
     fn find_parent_heuristic<Tree>(
         &self,
         tree: &Tree,
@@ -145,8 +131,8 @@ impl GridContextCache {
     where
         Tree: taffy::TraversePartialTree,
     {
-        // This method is now only used for non-BaseDocument trees
-        // Most usage should go through the O(1) BaseDocument path
+        // This method is now only used for trees without parent links
+        // Most usage should go through the O(1) `GridStyleAccess::grid_parent` path
         
         let target_idx = usize::from(target_node);
         
@@ -196,7 +182,7 @@ impl GridContextCache {
     where
         Tree: taffy::TraversePartialTree,
     {
-        // Use only heuristic for non-BaseDocument trees
+        // Use only heuristic for trees without parent links
         self.find_parent_heuristic(tree, target_node)
     }
 
@@ -290,15 +276,43 @@ impl GridContextCache {
     }
 }
 
-// Thread-local cache for high-performance grid context resolution
+// Thread-local caches for high-performance grid context resolution, one per tree (see
+// `GridStyleAccess::grid_tree_id`)
 thread_local! {
-    static GRID_CONTEXT_CACHE: RefCell<GridContextCache> = RefCell::new(GridContextCache::new());
+    static GRID_CONTEXT_CACHES: RefCell<HashMap<usize, GridContextCache>> =
+        RefCell::new(HashMap::new());
 }
 
 /// High-performance entry point for grid context resolution with thread-local caching
-pub fn with_cache<F, R>(f: F) -> R
+pub fn with_cache<F, R>(tree_id: usize, f: F) -> R
 where
     F: FnOnce(&mut GridContextCache) -> R,
 {
-    GRID_CONTEXT_CACHE.with(|cache| f(&mut cache.borrow_mut()))
+    GRID_CONTEXT_CACHES.with(|caches| {
+        let mut caches = caches.borrow_mut();
+        f(caches.entry(tree_id).or_insert_with(GridContextCache::new))
+    })
+}
+
+/// Drop this thread's cache for a tree, leaving other trees' caches alone. Does nothing if the
+/// thread is shutting down.
+pub fn clear_cache(tree_id: usize) {
+    let _ = GRID_CONTEXT_CACHES.try_with(|caches| caches.borrow_mut().remove(&tree_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearing_a_trees_cache_keeps_other_trees_entries() {
+        let node_id = NodeId::from(1usize);
+        for tree_id in [1, 2] {
+            with_cache(tree_id, |cache| cache.parent_cache.insert(node_id, None));
+        }
+
+        clear_cache(1);
+        assert!(with_cache(1, |cache| cache.parent_cache.is_empty()));
+        assert!(with_cache(2, |cache| cache.parent_cache.contains_key(&node_id)));
+    }
 }
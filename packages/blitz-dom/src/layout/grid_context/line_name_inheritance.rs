@@ -76,6 +76,133 @@ impl LineNameInheritanceMapper {
         Ok(merged_names)
     }
 
+    /// Line name inheritance including the line names implied by the parent's named areas
+    ///
+    /// CSS Spec: "The subgrid inherits the implicitly-assigned line names from the parent's
+    ///            named areas. If a named grid area only partially overlaps the subgrid, its
+    ///            implicitly-assigned line names will be assigned to the first and/or last
+    ///            line of the subgrid."
+    ///
+    /// Each area gives the lines it starts and ends at the names `<name>-start` and
+    /// `<name>-end`, so that `grid-area: <name>` placements inside the subgrid resolve to the
+    /// part of the area the subgrid spans.
+    pub fn map_subgrid_line_names_with_areas(
+        &mut self,
+        parent_line_names: &[Vec<String>],
+        parent_areas: &[taffy::GridTemplateArea<String>],
+        subgrid_declared_names: &[String],
+        subgrid_span: GridSpan,
+        subgrid_node_id: NodeId,
+        axis: GridAxis,
+    ) -> Result<Vec<Vec<String>>, super::super::grid_errors::SubgridError> {
+        let mut line_names = parent_line_names.to_vec();
+        line_names.resize(line_names.len().max(subgrid_span.end), Vec::new());
+
+        for area in parent_areas {
+            let (start, end) = match axis {
+                GridAxis::Row => (area.row_start, area.row_end),
+                GridAxis::Column => (area.column_start, area.column_end),
+            };
+            // Area lines are 1-based, line name indices 0-based
+            let (Some(mut start), Some(mut end)) =
+                ((start as usize).checked_sub(1), (end as usize).checked_sub(1))
+            else {
+                continue;
+            };
+            let last_line = subgrid_span.end.saturating_sub(1);
+            if start < last_line && end > subgrid_span.start {
+                start = start.max(subgrid_span.start);
+                end = end.min(last_line);
+            }
+
+            for (index, suffix) in [(start, "start"), (end, "end")] {
+                if index >= line_names.len() {
+                    line_names.resize(index + 1, Vec::new());
+                }
+                let name = format!("{}-{suffix}", area.name);
+                if !line_names[index].contains(&name) {
+                    line_names[index].push(name);
+                }
+            }
+        }
+
+        self.map_subgrid_line_names(
+            &line_names,
+            subgrid_declared_names,
+            subgrid_span,
+            subgrid_node_id,
+            axis,
+        )
+    }
+
+    /// Translate the parent's named areas into the lines of a subgrid spanning `row_span` and
+    /// `column_span` of the parent's lines, clipping them to the subgrid
+    ///
+    /// Areas the subgrid doesn't overlap are dropped. Keeping the areas themselves (rather than
+    /// only their line names) lets subgrids nested in the subgrid clip them again.
+    pub fn map_subgrid_grid_areas(
+        parent_areas: &[taffy::GridTemplateArea<String>],
+        row_span: GridSpan,
+        column_span: GridSpan,
+    ) -> Vec<taffy::GridTemplateArea<String>> {
+        // The 1-based lines of the subgrid an area's lines clip to, if they overlap it
+        let clip = |start: u16, end: u16, span: GridSpan| {
+            let first_line = span.start as u16 + 1;
+            let last_line = span.end as u16;
+            (start < last_line && end > first_line).then(|| {
+                let start = start.max(first_line) - first_line + 1;
+                let end = end.min(last_line) - first_line + 1;
+                (start, end)
+            })
+        };
+
+        parent_areas
+            .iter()
+            .filter_map(|area| {
+                let (row_start, row_end) = clip(area.row_start, area.row_end, row_span)?;
+                let (column_start, column_end) =
+                    clip(area.column_start, area.column_end, column_span)?;
+                Some(taffy::GridTemplateArea {
+                    name: area.name.clone(),
+                    row_start,
+                    row_end,
+                    column_start,
+                    column_end,
+                })
+            })
+            .collect()
+    }
+
+    /// Add the line names a subgrid declares (`subgrid [a] [b]`) to those it inherits, line by
+    /// line from its first. Names declared for lines past the subgrid's last are ignored.
+    pub fn merge_declared_line_names(
+        mut line_names: Vec<Vec<String>>,
+        declared_names: &[Vec<String>],
+    ) -> Vec<Vec<String>> {
+        for (names, declared) in line_names.iter_mut().zip(declared_names) {
+            for name in declared {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        line_names
+    }
+
+    /// The areas of a subgrid: those it declares, followed by the inherited ones it doesn't
+    /// declare an area of the same name as
+    pub fn merge_subgrid_grid_areas(
+        mut own_areas: Vec<taffy::GridTemplateArea<String>>,
+        inherited_areas: Vec<taffy::GridTemplateArea<String>>,
+    ) -> Vec<taffy::GridTemplateArea<String>> {
+        let inherited: Vec<_> = inherited_areas
+            .into_iter()
+            .filter(|area| !own_areas.iter().any(|own| own.name == area.name))
+            .collect();
+        own_areas.extend(inherited);
+        own_areas
+    }
+
     /// Extract parent line names for exact subgrid span per CSS specification
     ///
     /// CSS Spec: "The subgrid inherits the line names from its parent grid
@@ -283,3 +410,119 @@ impl Default for LineNameInheritanceMapper {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(name: &str, rows: (u16, u16), columns: (u16, u16)) -> taffy::GridTemplateArea<String> {
+        taffy::GridTemplateArea {
+            name: name.to_string(),
+            row_start: rows.0,
+            row_end: rows.1,
+            column_start: columns.0,
+            column_end: columns.1,
+        }
+    }
+
+    /// "header header header" / "nav main aside"
+    fn parent_areas() -> Vec<taffy::GridTemplateArea<String>> {
+        vec![
+            area("header", (1, 2), (1, 4)),
+            area("nav", (2, 3), (1, 2)),
+            area("main", (2, 3), (2, 3)),
+            area("aside", (2, 3), (3, 4)),
+        ]
+    }
+
+    #[test]
+    fn inherits_area_line_names() {
+        let mut mapper = LineNameInheritanceMapper::new();
+        // A subgrid spanning the last two columns (lines 2 to 4)
+        let span = GridSpan { start: 1, end: 4 };
+        let names = mapper
+            .map_subgrid_line_names_with_areas(
+                &[],
+                &parent_areas(),
+                &[],
+                span,
+                NodeId::from(1usize),
+                GridAxis::Column,
+            )
+            .unwrap();
+
+        // `header` is clipped to the subgrid, `nav` ends on its first line
+        assert_eq!(names.len(), 3);
+        assert!(names[0].contains(&"header-start".to_string()));
+        assert!(names[0].contains(&"nav-end".to_string()));
+        assert!(names[0].contains(&"main-start".to_string()));
+        assert!(names[1].contains(&"main-end".to_string()));
+        assert!(names[1].contains(&"aside-start".to_string()));
+        assert!(names[2].contains(&"header-end".to_string()));
+        assert!(!names.iter().flatten().any(|name| name == "nav-start"));
+    }
+
+    #[test]
+    fn clips_areas_through_nested_subgrids() {
+        // A subgrid spanning both rows and the last two columns of the parent
+        let outer = LineNameInheritanceMapper::map_subgrid_grid_areas(
+            &parent_areas(),
+            GridSpan { start: 0, end: 3 },
+            GridSpan { start: 1, end: 4 },
+        );
+        assert_eq!(outer.len(), 3);
+        assert_eq!(outer[0], area("header", (1, 2), (1, 3)));
+        assert_eq!(outer[1], area("main", (2, 3), (1, 2)));
+        assert_eq!(outer[2], area("aside", (2, 3), (2, 3)));
+
+        // A subgrid nested in it, spanning its second row and last column
+        let inner = LineNameInheritanceMapper::map_subgrid_grid_areas(
+            &outer,
+            GridSpan { start: 1, end: 3 },
+            GridSpan { start: 1, end: 3 },
+        );
+        assert_eq!(inner, [area("aside", (1, 2), (1, 2))]);
+
+        // Line names are inherited through both levels
+        let mut mapper = LineNameInheritanceMapper::new();
+        let names = mapper
+            .map_subgrid_line_names_with_areas(
+                &[],
+                &outer,
+                &[],
+                GridSpan { start: 1, end: 3 },
+                NodeId::from(2usize),
+                GridAxis::Column,
+            )
+            .unwrap();
+        assert!(names[0].contains(&"header-start".to_string()));
+        assert!(names[0].contains(&"aside-start".to_string()));
+        assert!(names[1].contains(&"header-end".to_string()));
+        assert!(names[1].contains(&"aside-end".to_string()));
+    }
+
+    #[test]
+    fn subgrids_keep_their_own_line_names_and_areas() {
+        let inherited = vec![vec!["a".to_string()], vec![], vec!["c".to_string()]];
+        let declared = [
+            vec!["a".to_string(), "first".to_string()],
+            vec!["second".to_string()],
+            vec![],
+            vec!["past-the-end".to_string()],
+        ];
+        let names = LineNameInheritanceMapper::merge_declared_line_names(inherited, &declared);
+        assert_eq!(names, [vec!["a", "first"], vec!["second"], vec!["c"]]);
+
+        // The subgrid's own `main` shadows the inherited one
+        let own = vec![area("main", (1, 2), (1, 3))];
+        let inherited = LineNameInheritanceMapper::map_subgrid_grid_areas(
+            &parent_areas(),
+            GridSpan { start: 0, end: 3 },
+            GridSpan { start: 1, end: 4 },
+        );
+        let areas = LineNameInheritanceMapper::merge_subgrid_grid_areas(own, inherited);
+        let names: Vec<_> = areas.iter().map(|area| area.name.as_str()).collect();
+        assert_eq!(names, ["main", "header", "aside"]);
+        assert_eq!(areas[0], area("main", (1, 2), (1, 3)));
+    }
+}
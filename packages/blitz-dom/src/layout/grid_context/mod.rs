@@ -19,8 +19,9 @@ pub use resolution::{
 };
 pub use track_extraction::{
     detect_subgrid_axis_from_style, detect_subgrid_from_stylo, expand_repetition_pattern,
-    extract_line_names_from_style, extract_line_names_from_stylo_computed_styles,
-    extract_tracks_from_stylo_computed_styles, extract_tracks_from_template_list,
+    extract_grid_areas_from_style, extract_line_names_from_style,
+    extract_line_names_from_stylo_computed_styles, extract_tracks_from_stylo_computed_styles,
+    extract_tracks_from_template_list,
 };
 pub use types::{
     GridAxis, GridContextError, GridSpan, ParentGridContext, SubgridInheritanceLevel,
@@ -6,12 +6,13 @@
 use taffy::GridContainerStyle;
 use taffy::prelude::NodeId;
 
+use super::super::grid_style_access::GridStyleAccess;
 use super::cache::with_cache;
 use super::track_extraction::{
-    detect_subgrid_axis_from_style, extract_line_names_from_style,
+    detect_subgrid_axis_from_style, extract_grid_areas_from_style, extract_line_names_from_style,
     extract_tracks_from_stylo_computed_styles, extract_tracks_from_template_list,
 };
-use super::types::{GridAxis, GridContextError, ParentGridContext};
+use super::types::{GridAxis, GridContextError, ParentGridContext, TrackExtractionError};
 
 /// Optimized entry point replacing current O(n²) implementation
 pub fn resolve_parent_grid_context_for_generic_tree_efficient<Tree>(
@@ -19,9 +20,9 @@ pub fn resolve_parent_grid_context_for_generic_tree_efficient<Tree>(
     node_id: NodeId,
 ) -> Result<Option<ParentGridContext>, GridContextError>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
-    with_cache(|cache| cache.get_or_compute_parent_context(tree, node_id))
+    with_cache(tree.grid_tree_id(), |cache| cache.get_or_compute_parent_context(tree, node_id))
 }
 
 /// Resolve parent grid context for generic tree implementations
@@ -50,7 +51,7 @@ pub fn resolve_parent_grid_context_for_generic_tree<Tree>(
     node_id: NodeId,
 ) -> Result<Option<ParentGridContext>, GridContextError>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
     // Use the efficient cached implementation transparently
     resolve_parent_grid_context_for_generic_tree_efficient(tree, node_id)
@@ -77,9 +78,9 @@ pub fn find_potential_parents_constrained<Tree>(
     node_id: NodeId,
 ) -> Result<Vec<NodeId>, GridContextError>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
-    with_cache(|cache| {
+    with_cache(tree.grid_tree_id(), |cache| {
         // Try to find the parent using efficient algorithms
         if let Some(parent) = cache.find_actual_parent(tree, node_id)? {
             // Found a single parent - return it as a vector for API compatibility
@@ -97,7 +98,7 @@ pub fn check_parent_grid_container<Tree>(
     node_id: NodeId,
 ) -> Result<Option<ParentGridContext>, GridContextError>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
     // Get the grid container style for the potential parent
     let grid_style = tree.get_grid_container_style(node_id);
@@ -112,32 +113,35 @@ where
     }
 
     // Extract track information from the grid container
-    // For BaseDocument, use stylo integration; for generic trees, use existing approach
-    let (parent_row_tracks, parent_column_tracks) = if let Some(base_doc) =
-        (tree as &dyn std::any::Any).downcast_ref::<crate::BaseDocument>()
+    // Prefer stylo integration when computed styles are available
+    let (parent_row_tracks, parent_column_tracks) = if let Some(computed_styles) =
+        tree.grid_computed_styles(node_id)
     {
-        let node = base_doc.node_from_id(node_id.into());
-        if let Some(computed_styles) = node.primary_styles() {
-            let row_tracks =
-                extract_tracks_from_stylo_computed_styles(&computed_styles, GridAxis::Row)
-                    .map_err(|_| GridContextError::TrackExtractionFailed)?;
-
-            let column_tracks =
-                extract_tracks_from_stylo_computed_styles(&computed_styles, GridAxis::Column)
-                    .map_err(|_| GridContextError::TrackExtractionFailed)?;
+        // A parent which is itself a subgrid has had the tracks it inherits written to its
+        // layout style by the time its children are laid out
+        let row_tracks =
+            extract_tracks_from_stylo_computed_styles(&computed_styles, GridAxis::Row)
+                .or_else(|error| match error {
+                    TrackExtractionError::SubgridInheritanceRequired => {
+                        extract_tracks_from_template_list(grid_style.grid_template_rows())
+                    }
+                    error => Err(error),
+                })
+                .map_err(|_| GridContextError::TrackExtractionFailed)?;
 
-            (row_tracks, column_tracks)
-        } else {
-            // Fallback to generic approach if computed styles not available
-            let row_tracks = extract_tracks_from_template_list(grid_style.grid_template_rows())
+        let column_tracks =
+            extract_tracks_from_stylo_computed_styles(&computed_styles, GridAxis::Column)
+                .or_else(|error| match error {
+                    TrackExtractionError::SubgridInheritanceRequired => {
+                        extract_tracks_from_template_list(grid_style.grid_template_columns())
+                    }
+                    error => Err(error),
+                })
                 .map_err(|_| GridContextError::TrackExtractionFailed)?;
-            let column_tracks =
-                extract_tracks_from_template_list(grid_style.grid_template_columns())
-                    .map_err(|_| GridContextError::TrackExtractionFailed)?;
-            (row_tracks, column_tracks)
-        }
+
+        (row_tracks, column_tracks)
     } else {
-        // Fallback to generic approach for non-BaseDocument trees
+        // Fallback to generic approach if computed styles not available
         let row_tracks = extract_tracks_from_template_list(grid_style.grid_template_rows())
             .map_err(|_| GridContextError::TrackExtractionFailed)?;
         let column_tracks = extract_tracks_from_template_list(grid_style.grid_template_columns())
@@ -153,6 +157,8 @@ where
         extract_line_names_from_style(grid_style.grid_template_column_names())
             .map_err(|_| GridContextError::TrackExtractionFailed)?;
 
+    let parent_grid_areas = extract_grid_areas_from_style(grid_style.grid_template_areas());
+
     // Build the parent grid context
     let parent_context = ParentGridContext {
         row_track_count: parent_row_tracks.len(),
@@ -161,6 +167,7 @@ where
         parent_column_tracks,
         parent_row_line_names,
         parent_column_line_names,
+        parent_grid_areas,
         parent_has_subgrid_rows: detect_subgrid_axis_from_style(tree, node_id, true),
         parent_has_subgrid_columns: detect_subgrid_axis_from_style(tree, node_id, false),
         parent_size: taffy::Size::NONE, // No parent size available in this context
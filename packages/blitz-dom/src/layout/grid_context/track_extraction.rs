@@ -7,6 +7,7 @@ use style::properties::ComputedValues;
 use style::values::CustomIdent;
 use style::values::computed::GridTemplateComponent;
 
+use super::super::grid_style_access::GridStyleAccess;
 use super::types::{GridAxis, TrackExtractionError};
 
 /// Extract TrackSizingFunction values from grid template track list
@@ -103,29 +104,57 @@ where
     let mut result = Vec::new();
 
     for line_name_set in names {
-        let line_group: Vec<String> = line_name_set.map(|name| format!("{:?}", name)).collect();
+        let line_group: Vec<String> =
+            line_name_set.map(|name| name.as_ref().to_string()).collect();
         result.push(line_group);
     }
 
     Ok(result)
 }
 
-/// Detect if a node has subgrid for a specific axis using taffy grid infrastructure
+/// Extract the named areas of a grid container's `grid-template-areas`
+pub fn extract_grid_areas_from_style<CustomIdent>(
+    areas: Option<impl IntoIterator<Item = taffy::GridTemplateArea<CustomIdent>>>,
+) -> Vec<taffy::GridTemplateArea<String>>
+where
+    CustomIdent: taffy::CheapCloneStr,
+{
+    let Some(areas) = areas else {
+        return Vec::new();
+    };
+
+    areas
+        .into_iter()
+        .map(|area| taffy::GridTemplateArea {
+            name: area.name.as_ref().to_string(),
+            row_start: area.row_start,
+            row_end: area.row_end,
+            column_start: area.column_start,
+            column_end: area.column_end,
+        })
+        .collect()
+}
+
+/// Detect if a node has subgrid for a specific axis
 ///
-/// Note: This is a simplified version since taffy doesn't support subgrid detection yet.
-/// Real axis-specific subgrid detection requires access to stylo computed values
+/// Taffy has no `subgrid` track component, so detection relies on the stylo computed
+/// values exposed through [`GridStyleAccess`]. Trees without computed styles report `false`.
 pub fn detect_subgrid_axis_from_style<Tree>(
-    _tree: &Tree,
-    _node_id: taffy::prelude::NodeId,
-    _is_row_axis: bool,
+    tree: &Tree,
+    node_id: taffy::prelude::NodeId,
+    is_row_axis: bool,
 ) -> bool
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
-    // Taffy doesn't have GridTemplateComponent::Subgrid variant yet
-    // This function exists to maintain the interface but returns false
-    // Real axis-specific subgrid detection happens in BaseDocument functions using stylo
-    false
+    let axis = if is_row_axis {
+        GridAxis::Row
+    } else {
+        GridAxis::Column
+    };
+
+    tree.grid_computed_styles(node_id)
+        .is_some_and(|styles| detect_subgrid_from_stylo(&styles, axis))
 }
 
 /// Extract tracks directly from stylo computed styles using existing conversion infrastructure
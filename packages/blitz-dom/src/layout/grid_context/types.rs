@@ -15,6 +15,8 @@ pub struct ParentGridContext {
     pub parent_row_line_names: Vec<Vec<String>>,
     /// Parent grid line names for column axis
     pub parent_column_line_names: Vec<Vec<String>>,
+    /// Named areas of the parent grid's `grid-template-areas`, in its line numbers
+    pub parent_grid_areas: Vec<taffy::GridTemplateArea<String>>,
     /// Whether parent has subgrid in rows
     pub parent_has_subgrid_rows: bool,
     /// Whether parent has subgrid in columns
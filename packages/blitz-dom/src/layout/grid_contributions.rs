@@ -0,0 +1,133 @@
+//! Memoized size contributions of grid items
+//!
+//! Taffy's grid algorithm measures every item several times while sizing tracks: for its min- and
+//! max-content contributions in each axis, again once the tracks of the other axis are sized, and
+//! once more when track sizing is re-run. Its per-node [`Cache`](taffy::Cache) keeps only one size
+//! for each kind of available space, so with many intrinsically sized items (like an auto-fill
+//! grid of cards) these measurements keep evicting each other and whole subtrees get laid out
+//! over and over. This cache keeps every size measured for a grid item during a layout pass,
+//! keyed by the exact inputs it was measured with.
+
+use std::collections::HashMap;
+
+use taffy::{AvailableSpace, LayoutInput, LayoutOutput, RequestedAxis, RunMode, SizingMode};
+
+/// The inputs that affect the size a node is measured at, with lengths compared by their bits
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct MeasureKey {
+    known_dimensions: [Option<u32>; 2],
+    available_space: [(u8, u32); 2],
+    parent_size: [Option<u32>; 2],
+    axis: u8,
+    inherent_size: bool,
+}
+
+impl MeasureKey {
+    fn new(inputs: &LayoutInput) -> Self {
+        let available = |space: AvailableSpace| match space {
+            AvailableSpace::Definite(value) => (0, value.to_bits()),
+            AvailableSpace::MinContent => (1, 0),
+            AvailableSpace::MaxContent => (2, 0),
+        };
+        let bits = |value: Option<f32>| value.map(f32::to_bits);
+        Self {
+            known_dimensions: [
+                bits(inputs.known_dimensions.width),
+                bits(inputs.known_dimensions.height),
+            ],
+            available_space: [
+                available(inputs.available_space.width),
+                available(inputs.available_space.height),
+            ],
+            parent_size: [bits(inputs.parent_size.width), bits(inputs.parent_size.height)],
+            axis: match inputs.axis {
+                RequestedAxis::Horizontal => 0,
+                RequestedAxis::Vertical => 1,
+                RequestedAxis::Both => 2,
+            },
+            inherent_size: inputs.sizing_mode == SizingMode::InherentSize,
+        }
+    }
+}
+
+/// Sizes of grid items measured with [`RunMode::ComputeSize`] during the current layout pass
+#[derive(Default)]
+pub(crate) struct GridContributionCache {
+    entries: HashMap<usize, Vec<(MeasureKey, LayoutOutput)>>,
+}
+
+impl GridContributionCache {
+    /// Whether a measurement with these inputs can be memoized
+    pub(crate) fn applies_to(inputs: &LayoutInput) -> bool {
+        inputs.run_mode == RunMode::ComputeSize
+    }
+
+    pub(crate) fn get(&self, node_id: usize, inputs: &LayoutInput) -> Option<LayoutOutput> {
+        let key = MeasureKey::new(inputs);
+        let entries = self.entries.get(&node_id)?;
+        entries.iter().find(|(entry_key, _)| *entry_key == key).map(|(_, output)| *output)
+    }
+
+    pub(crate) fn insert(&mut self, node_id: usize, inputs: &LayoutInput, output: LayoutOutput) {
+        let key = MeasureKey::new(inputs);
+        let entries = self.entries.entry(node_id).or_default();
+        match entries.iter_mut().find(|(entry_key, _)| *entry_key == key) {
+            Some(entry) => entry.1 = output,
+            None => entries.push((key, output)),
+        }
+    }
+
+    /// Forget the sizes of a node whose layout cache was cleared
+    pub(crate) fn remove(&mut self, node_id: usize) {
+        self.entries.remove(&node_id);
+    }
+
+    /// Forget everything. Called at the start of each layout pass, as anything may have changed
+    /// since the last one.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use taffy::{Line, Size};
+
+    use super::*;
+
+    fn inputs(width: AvailableSpace) -> LayoutInput {
+        LayoutInput {
+            run_mode: RunMode::ComputeSize,
+            sizing_mode: SizingMode::InherentSize,
+            axis: RequestedAxis::Vertical,
+            known_dimensions: Size {
+                width: Some(100.0),
+                height: None,
+            },
+            parent_size: Size::NONE,
+            available_space: Size {
+                width,
+                height: AvailableSpace::MaxContent,
+            },
+            vertical_margins_are_collapsible: Line::FALSE,
+        }
+    }
+
+    #[test]
+    fn keeps_every_measurement_until_cleared() {
+        let mut cache = GridContributionCache::default();
+        let narrow = inputs(AvailableSpace::Definite(100.0));
+        let wide = inputs(AvailableSpace::Definite(200.0));
+        let output = |height| LayoutOutput::from_outer_size(Size { width: 100.0, height });
+
+        cache.insert(1, &narrow, output(40.0));
+        cache.insert(1, &wide, output(20.0));
+        assert_eq!(cache.get(1, &narrow).map(|o| o.size.height), Some(40.0));
+        assert_eq!(cache.get(1, &wide).map(|o| o.size.height), Some(20.0));
+        assert!(cache.get(1, &inputs(AvailableSpace::MinContent)).is_none());
+        assert!(cache.get(2, &narrow).is_none());
+
+        cache.remove(1);
+        assert!(cache.get(1, &narrow).is_none());
+    }
+}
@@ -2,10 +2,9 @@
 
 use taffy::{NodeId, geometry::AbstractAxis, TraversePartialTree, GridContainerStyle};
 
-use crate::BaseDocument;
 use super::super::grid_context::ParentGridContext;
 use super::super::grid_errors::GridPreprocessingError;
-use super::super::intrinsic_sizing::calculate_item_intrinsic_size_for_masonry;
+use super::super::grid_style_access::GridStyleAccess;
 use super::super::subgrid::layout_states::IntrinsicSizingState;
 use super::placement_types::*;
 use super::types::*;
@@ -47,12 +46,15 @@ impl GridLayoutCoordinator {
     }
 
     /// Collect intrinsic size contributions (Pass 2)
-    pub fn collect_intrinsic_size_contributions(
+    pub fn collect_intrinsic_size_contributions<Tree>(
         &mut self,
-        tree: &BaseDocument,
+        tree: &Tree,
         subgrid_id: NodeId,
         inputs: &taffy::tree::LayoutInput,
-    ) -> Result<Vec<TrackSizeContribution>, GridPreprocessingError> {
+    ) -> Result<Vec<TrackSizeContribution>, GridPreprocessingError>
+    where
+        Tree: GridStyleAccess,
+    {
         // 1. Measure content intrinsic sizes for all subgrid items
         let content_sizes = self.measure_content_intrinsic_sizes(tree, subgrid_id, inputs)?;
 
@@ -78,7 +80,7 @@ impl GridLayoutCoordinator {
         tree: &Tree,
     ) -> Result<Vec<ItemPlacement>, GridPreprocessingError>
     where
-        Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + std::any::Any,
+        Tree: GridStyleAccess,
     {
         // 1. Initialize placement state with proper grid size, flow direction, and dense packing
         
@@ -156,12 +158,15 @@ impl GridLayoutCoordinator {
     }
 
     /// Coordinate bidirectional sizing (Pass 4)
-    pub fn coordinate_bidirectional_sizing(
+    pub fn coordinate_bidirectional_sizing<Tree>(
         &mut self,
-        tree: &BaseDocument,
+        tree: &Tree,
         subgrid_id: NodeId,
         inputs: &taffy::tree::LayoutInput,
-    ) -> Result<(), GridPreprocessingError> {
+    ) -> Result<(), GridPreprocessingError>
+    where
+        Tree: GridStyleAccess,
+    {
         // 1. Collect size contributions from subgrid items
         let size_contributions = self.collect_subgrid_size_contributions(tree, subgrid_id, inputs)?;
 
@@ -181,13 +186,16 @@ impl GridLayoutCoordinator {
     }
     
     /// Execute bidirectional sizing loop with convergence
-    pub fn execute_bidirectional_sizing_loop(
+    pub fn execute_bidirectional_sizing_loop<Tree>(
         &mut self,
-        tree: &BaseDocument,
+        tree: &Tree,
         subgrid_id: NodeId,
         parent_context: &ParentGridContext,
         inputs: &taffy::tree::LayoutInput,
-    ) -> Result<(), GridPreprocessingError> {
+    ) -> Result<(), GridPreprocessingError>
+    where
+        Tree: GridStyleAccess,
+    {
         const MAX_PASSES: usize = 5;
         const TOLERANCE: f32 = 0.1;
         
@@ -320,12 +328,15 @@ impl GridLayoutCoordinator {
     }
 
     /// Bidirectional size contribution flow implementation
-    pub fn collect_subgrid_size_contributions(
+    pub fn collect_subgrid_size_contributions<Tree>(
         &mut self,
-        tree: &BaseDocument,
+        tree: &Tree,
         subgrid_id: NodeId,
         inputs: &taffy::tree::LayoutInput,
-    ) -> Result<Vec<TrackSizeContribution>, GridPreprocessingError> {
+    ) -> Result<Vec<TrackSizeContribution>, GridPreprocessingError>
+    where
+        Tree: GridStyleAccess,
+    {
         let mut contributions = Vec::new();
         
         // Step 1: Get intrinsic sizes from measurement
@@ -399,12 +410,15 @@ impl GridLayoutCoordinator {
     }
 
     /// Enhanced intrinsic sizing with content measurement
-    pub fn measure_content_intrinsic_sizes(
+    pub fn measure_content_intrinsic_sizes<Tree>(
         &self,
-        tree: &BaseDocument,
+        tree: &Tree,
         subgrid_id: NodeId,
         inputs: &taffy::tree::LayoutInput,
-    ) -> Result<Vec<IntrinsicSizeContribution>, GridPreprocessingError> {
+    ) -> Result<Vec<IntrinsicSizeContribution>, GridPreprocessingError>
+    where
+        Tree: GridStyleAccess,
+    {
         let mut contributions = Vec::new();
         
         // Get all items in the subgrid
@@ -414,8 +428,7 @@ impl GridLayoutCoordinator {
             let item_id = tree.get_child_id(subgrid_id, i);
             
             // USE EXISTING MEASUREMENT INFRASTRUCTURE
-            let min_content_size = calculate_item_intrinsic_size_for_masonry(
-                tree,
+            let min_content_size = tree.grid_item_intrinsic_size(
                 item_id,
                 &taffy::tree::LayoutInput {
                     available_space: taffy::Size {
@@ -427,8 +440,7 @@ impl GridLayoutCoordinator {
                 AbstractAxis::Block,
             )?;
             
-            let max_content_size = calculate_item_intrinsic_size_for_masonry(
-                tree,
+            let max_content_size = tree.grid_item_intrinsic_size(
                 item_id,
                 &taffy::tree::LayoutInput {
                     available_space: taffy::Size {
@@ -456,11 +468,14 @@ impl GridLayoutCoordinator {
     }
     
     /// Determine which tracks an item affects
-    fn determine_affected_tracks(
+    fn determine_affected_tracks<Tree>(
         &self,
         item_id: NodeId,
-        tree: &BaseDocument,
-    ) -> Result<Vec<usize>, GridPreprocessingError> {
+        tree: &Tree,
+    ) -> Result<Vec<usize>, GridPreprocessingError>
+    where
+        Tree: GridStyleAccess,
+    {
         // In a full implementation, this would:
         // 1. Get item's grid-row-start, grid-row-end (or grid-column for column axis)
         // 2. Resolve GridLine positions to track indices
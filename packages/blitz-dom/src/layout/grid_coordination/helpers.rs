@@ -4,6 +4,7 @@ use taffy::NodeId;
 
 use super::super::grid_context::{GridAxis, ParentGridContext};
 use super::super::grid_errors::GridPreprocessingError;
+use super::super::grid_style_access::GridStyleAccess;
 use super::placement_types::*;
 use super::track_types::*;
 use super::types::*;
@@ -132,8 +133,8 @@ impl GridLayoutCoordinator {
     /// Helper: Replace grid-template-* properties
     /// 
     /// Converts inherited track definitions to Taffy-compatible track sizing functions.
-    /// Returns the converted functions for application in the calling context where
-    /// BaseDocument access is available.
+    /// Returns the converted functions for application in the calling context, which
+    /// writes them back through `GridStyleAccess::with_grid_style_mut`.
     /// This implements the CSS Grid Level 2 subgrid property replacement algorithm.
     pub fn replace_grid_template_properties(
         &mut self,
@@ -257,40 +258,21 @@ impl GridLayoutCoordinator {
         tree: &Tree,
     ) -> Result<Vec<(NodeId, i32)>, GridPreprocessingError>
     where
-        Tree: taffy::TraversePartialTree + std::any::Any,
+        Tree: GridStyleAccess,
     {
-        use crate::BaseDocument;
-
-        // Get all children of the subgrid
+        // Get all children of the subgrid along with their CSS order values
         let child_count = tree.child_count(subgrid_id);
-        let mut items_with_order = Vec::with_capacity(child_count);
-
-        // Downcast to BaseDocument to access order property
-        if let Some(base_doc) = (tree as &dyn std::any::Any).downcast_ref::<BaseDocument>() {
-            // Iterate through children and extract order values
-            for i in 0..child_count {
+        let mut items_with_order: Vec<(NodeId, i32)> = (0..child_count)
+            .map(|i| {
                 let child_id = tree.get_child_id(subgrid_id, i);
+                (child_id, tree.grid_item_order(child_id))
+            })
+            .collect();
 
-                // Access node and get order property
-                let node = &base_doc.nodes[usize::from(child_id)];
-                let order_value = node.order();
-
-                items_with_order.push((child_id, order_value));
-            }
-
-            // Stable sort by order value (preserves DOM order for equal values)
-            items_with_order.sort_by_key(|(_, order)| *order);
-
-            Ok(items_with_order)
-        } else {
-            // If not BaseDocument, return items in DOM order with default order value of 0
-            for i in 0..child_count {
-                let child_id = tree.get_child_id(subgrid_id, i);
-                items_with_order.push((child_id, 0));
-            }
+        // Stable sort by order value (preserves DOM order for equal values)
+        items_with_order.sort_by_key(|(_, order)| *order);
 
-            Ok(items_with_order)
-        }
+        Ok(items_with_order)
     }
 
     /// Helper: Process explicit placements
@@ -470,12 +452,15 @@ impl GridLayoutCoordinator {
     }
 
     /// Helper: Update parent track sizing
-    pub fn update_parent_track_sizing(
+    pub fn update_parent_track_sizing<Tree>(
         &mut self,
-        _tree: &crate::BaseDocument,
+        _tree: &Tree,
         subgrid_id: NodeId,
         contributions: Vec<TrackSizeContribution>,
-    ) -> Result<bool, GridPreprocessingError> {
+    ) -> Result<bool, GridPreprocessingError>
+    where
+        Tree: GridStyleAccess,
+    {
         let mut any_changes = false;
         
         // Get parent grid ID from subgrid state
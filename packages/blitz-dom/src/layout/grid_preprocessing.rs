@@ -7,14 +7,13 @@ use taffy::prelude::*;
 use taffy::GridContainerStyle;
 
 use super::grid_context::{
-    GridAxis, ParentGridContext, detect_subgrid_from_stylo,
-    extract_line_names_from_stylo_computed_styles, extract_tracks_from_stylo_computed_styles,
+    GridAxis, detect_subgrid_axis_from_style, detect_subgrid_from_stylo,
     resolve_parent_grid_context_for_generic_tree,
 };
 use super::grid_errors::GridPreprocessingError;
 use super::masonry::apply_masonry_layout;
-use super::subgrid::{coordinate_nested_subgrids, preprocess_subgrid_for_generic_tree};
-use crate::BaseDocument;
+use super::grid_style_access::GridStyleAccess;
+use super::subgrid::coordinate_nested_subgrids;
 
 /// Central grid preprocessing function that handles subgrid and masonry before calling taffy
 /// This is the key integration point where we implement CSS Grid Level 2 and 3 features
@@ -24,13 +23,14 @@ pub fn preprocess_and_compute_grid_layout<Tree>(
     inputs: taffy::tree::LayoutInput,
 ) -> taffy::tree::LayoutOutput
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
-    // Check if this is a BaseDocument - if so, use BaseDocument-specific preprocessing
-    if let Some(base_doc) = (tree as &mut dyn std::any::Any).downcast_mut::<BaseDocument>() {
-        if let Ok(result) = apply_basedocument_grid_preprocessing(base_doc, node_id, inputs) {
-            return result;
-        }
+    // Masonry layout records the reading order of the items again if the node is still masonry
+    tree.set_masonry_reading_order(node_id, None);
+
+    // Trees backed by stylo get preprocessing driven directly by computed values
+    if let Ok(result) = apply_stylo_grid_preprocessing(tree, node_id, inputs) {
+        return result;
     }
 
     // Check if we need special preprocessing for subgrid or masonry
@@ -47,25 +47,24 @@ where
     taffy::compute_grid_layout(tree, node_id, inputs)
 }
 
-/// Apply BaseDocument-specific grid preprocessing with direct stylo integration
-pub fn apply_basedocument_grid_preprocessing(
-    tree: &mut BaseDocument,
+/// Apply grid preprocessing with direct stylo integration
+///
+/// Fails if the tree cannot provide computed styles for `node_id`, or if the grid has subgrid
+/// axes, in which case the caller falls back to the generic trait-level preprocessing.
+///
+/// This runs for every layout of every grid (taffy lays grids out several times while sizing
+/// them and their ancestors), so it only inspects the templates rather than converting them.
+pub fn apply_stylo_grid_preprocessing<Tree>(
+    tree: &mut Tree,
     node_id: NodeId,
     inputs: taffy::tree::LayoutInput,
-) -> Result<taffy::tree::LayoutOutput, GridPreprocessingError> {
-    // Step 1: Extract all needed data in a scoped block to avoid borrow conflicts
-    let (
-        row_tracks,
-        column_tracks,
-        row_line_names,
-        column_line_names,
-        has_subgrid_rows,
-        has_subgrid_columns,
-        has_masonry_rows,
-        has_masonry_columns,
-    ) = {
-        let node = tree.node_from_id(node_id.into());
-        let computed_styles = node.primary_styles().ok_or_else(|| {
+) -> Result<taffy::tree::LayoutOutput, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    // Step 1: Detect features in a scoped block to avoid borrow conflicts
+    let (has_subgrid, has_masonry_axis, display_is_masonry) = {
+        let computed_styles = tree.grid_computed_styles(node_id).ok_or_else(|| {
             GridPreprocessingError::preprocessing_failed(
                 "computed_styles_access",
                 node_id.into(),
@@ -73,164 +72,69 @@ pub fn apply_basedocument_grid_preprocessing(
             )
         })?;
 
-        // Extract tracks using new stylo integration
-        let row_tracks = extract_tracks_from_stylo_computed_styles(&computed_styles, GridAxis::Row)
-            .map_err(|e| {
-                GridPreprocessingError::track_extraction_failed(format!(
-                    "Row track extraction failed for node {}: {:?}",
-                    usize::from(node_id),
-                    e
-                ))
-            })?;
-
-        let column_tracks =
-            extract_tracks_from_stylo_computed_styles(&computed_styles, GridAxis::Column).map_err(
-                |e| {
-                    GridPreprocessingError::track_extraction_failed(format!(
-                        "Column track extraction failed for node {}: {:?}",
-                        usize::from(node_id),
-                        e
-                    ))
-                },
-            )?;
-
-        // Extract line names using new stylo integration
-        let row_line_names =
-            extract_line_names_from_stylo_computed_styles(&computed_styles, GridAxis::Row)
-                .map_err(|e| {
-                    GridPreprocessingError::track_extraction_failed(format!(
-                        "Row line name extraction failed for node {}: {:?}",
-                        usize::from(node_id),
-                        e
-                    ))
-                })?;
-
-        let column_line_names =
-            extract_line_names_from_stylo_computed_styles(&computed_styles, GridAxis::Column)
-                .map_err(|e| {
-                    GridPreprocessingError::track_extraction_failed(format!(
-                        "Column line name extraction failed for node {}: {:?}",
-                        usize::from(node_id),
-                        e
-                    ))
-                })?;
-
-        // Detect subgrid usage using new stylo integration
-        let has_subgrid_rows = detect_subgrid_from_stylo(&computed_styles, GridAxis::Row);
-        let has_subgrid_columns = detect_subgrid_from_stylo(&computed_styles, GridAxis::Column);
+        let has_subgrid = detect_subgrid_from_stylo(&computed_styles, GridAxis::Row)
+            || detect_subgrid_from_stylo(&computed_styles, GridAxis::Column);
 
         // Check for masonry layout using RAW stylo values before conversion
         let style_wrapper = stylo_taffy::TaffyStyloStyle::from(&*computed_styles);
-        let mut has_masonry_rows = stylo_taffy::convert::is_masonry_axis(style_wrapper.raw_grid_template_rows());
-        let mut has_masonry_columns = stylo_taffy::convert::is_masonry_axis(style_wrapper.raw_grid_template_columns());
-        
+        let has_masonry_axis =
+            stylo_taffy::convert::is_masonry_axis(style_wrapper.raw_grid_template_rows())
+                || stylo_taffy::convert::is_masonry_axis(
+                    style_wrapper.raw_grid_template_columns(),
+                );
+
         // Check for display: masonry or display: inline-masonry
-        let display = computed_styles.clone_display();
-        let display_is_masonry = stylo_taffy::convert::is_display_masonry(display);
-        
-        if display_is_masonry && !has_masonry_rows && !has_masonry_columns {
-            // display: masonry with columns defined → rows are masonry
-            // display: masonry with rows defined → columns are masonry
-            // display: masonry with neither → rows are masonry (default)
-            let cols_had_tracklist = style_wrapper.grid_template_columns().is_some();
-            let rows_had_tracklist = style_wrapper.grid_template_rows().is_some();
-            
-            if cols_had_tracklist && !rows_had_tracklist {
-                has_masonry_rows = true;
-            } else if rows_had_tracklist && !cols_had_tracklist {
-                has_masonry_columns = true;
-            } else {
-                // Default: masonry on rows
-                has_masonry_rows = true;
-            }
-        }
+        let display_is_masonry =
+            stylo_taffy::convert::is_display_masonry(computed_styles.clone_display());
 
-        (
-            row_tracks,
-            column_tracks,
-            row_line_names,
-            column_line_names,
-            has_subgrid_rows,
-            has_subgrid_columns,
-            has_masonry_rows,
-            has_masonry_columns,
-        )
+        (has_subgrid, has_masonry_axis, display_is_masonry)
     }; // All borrows are dropped here
 
-    // Step 2: Apply preprocessing based on detected features
-    if has_subgrid_rows || has_subgrid_columns {
-        #[cfg(feature = "tracing")]
-        tracing::debug!(
-            "Subgrid detected: rows={}, cols={} for node {}",
-            has_subgrid_rows,
-            has_subgrid_columns,
+    // Step 2: Subgrid axes inherit their tracks from the parent grid, so there are no tracks to
+    // extract from them here. The generic preprocessing resolves them from the parent grid's
+    // context.
+    if has_subgrid {
+        return Err(GridPreprocessingError::track_extraction_failed(format!(
+            "Grid {} has subgrid tracks",
             usize::from(node_id)
-        );
-
-        // Apply subgrid preprocessing with extracted parent context
-        let parent_context = ParentGridContext {
-            parent_row_tracks: row_tracks.clone(),
-            parent_column_tracks: column_tracks.clone(),
-            parent_row_line_names: row_line_names,
-            parent_column_line_names: column_line_names,
-            parent_has_subgrid_rows: has_subgrid_rows,
-            parent_has_subgrid_columns: has_subgrid_columns,
-            row_track_count: row_tracks.len(),
-            column_track_count: column_tracks.len(),
-            parent_size: inputs.parent_size,
-        };
-
-        // Apply subgrid preprocessing
-        preprocess_subgrid_for_generic_tree(tree, node_id, &parent_context).map_err(|e| {
-            GridPreprocessingError::preprocessing_failed(
-                "subgrid_preprocessing",
-                node_id.into(),
-                format!("Subgrid preprocessing failed: {:?}", e),
-            )
-        })?;
-
-        // After subgrid preprocessing, compute the layout
-        return Ok(taffy::compute_grid_layout(tree, node_id, inputs));
+        )));
     }
 
-    if has_masonry_rows || has_masonry_columns {
+    if has_masonry_axis || display_is_masonry {
         // Masonry axis will be determined automatically from styles inside apply_masonry_layout
         return apply_masonry_layout(tree, node_id, inputs);
     }
 
-    // Step 3: Standard grid layout with extracted tracks
+    // Step 3: Standard grid layout
     Ok(taffy::compute_grid_layout(tree, node_id, inputs))
 }
 
 /// Check if a grid node needs special preprocessing for subgrid or masonry
 pub fn check_needs_grid_preprocessing<Tree>(tree: &Tree, node_id: NodeId) -> bool
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
-    // Optimization: For BaseDocument, check actual styles to avoid unnecessary preprocessing
+    // Optimization: check actual styles to avoid unnecessary preprocessing
     // This provides O(1) detection when Stylo ComputedValues are available
-    if let Some(base_doc) = (tree as &dyn std::any::Any).downcast_ref::<BaseDocument>() {
-        let node = base_doc.node_from_id(node_id);
-        if let Some(styles) = node.primary_styles() {
-            let style_wrapper = stylo_taffy::TaffyStyloStyle::from(&*styles);
-            
-            // Check for subgrid usage
-            let has_subgrid = detect_subgrid_from_stylo(&styles, GridAxis::Row) 
-                || detect_subgrid_from_stylo(&styles, GridAxis::Column);
-            
-            // Check for masonry layout using RAW values before conversion
-            let has_masonry = stylo_taffy::convert::is_masonry_axis(style_wrapper.raw_grid_template_rows())
-                || stylo_taffy::convert::is_masonry_axis(style_wrapper.raw_grid_template_columns());
-            
-            // Check for display: masonry
-            let display = styles.clone_display();
-            let display_is_masonry = stylo_taffy::convert::is_display_masonry(display);
-            
-            return has_subgrid || has_masonry || display_is_masonry;
-        }
+    if let Some(styles) = tree.grid_computed_styles(node_id) {
+        let style_wrapper = stylo_taffy::TaffyStyloStyle::from(&*styles);
+
+        // Check for subgrid usage
+        let has_subgrid = detect_subgrid_from_stylo(&styles, GridAxis::Row)
+            || detect_subgrid_from_stylo(&styles, GridAxis::Column);
+
+        // Check for masonry layout using RAW values before conversion
+        let has_masonry = stylo_taffy::convert::is_masonry_axis(style_wrapper.raw_grid_template_rows())
+            || stylo_taffy::convert::is_masonry_axis(style_wrapper.raw_grid_template_columns());
+
+        // Check for display: masonry
+        let display = styles.clone_display();
+        let display_is_masonry = stylo_taffy::convert::is_display_masonry(display);
+
+        return has_subgrid || has_masonry || display_is_masonry;
     }
-    
-    // Conservative fallback for non-BaseDocument trees or when styles unavailable
+
+    // Conservative fallback for trees without computed styles
     // Ensures preprocessing is attempted for any potentially special grid layouts
     true
 }
@@ -244,9 +148,9 @@ pub fn apply_grid_preprocessing<Tree>(
     _inputs: taffy::tree::LayoutInput,
 ) -> Option<taffy::tree::LayoutOutput>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
-    // Generic preprocessing for non-BaseDocument trees or BaseDocument fallback
+    // Generic preprocessing for trees without computed styles, or the stylo path's fallback
     // 
     // ARCHITECTURE: This function operates at the generic trait level using taffy::Style
     // (via LayoutGridContainer trait) rather than raw Stylo ComputedValues.
@@ -262,7 +166,11 @@ where
     // This is intentional - subgrid is an enhancement feature, not a requirement.
     match resolve_parent_grid_context_for_generic_tree(tree, node_id) {
         Ok(Some(parent_context)) => {
-            if parent_context.parent_has_subgrid_rows || parent_context.parent_has_subgrid_columns {
+            // `parent_has_subgrid_*` describe the parent, which may be a subgrid itself. What
+            // matters here is whether this node is one.
+            let is_subgrid = detect_subgrid_axis_from_style(tree, node_id, true)
+                || detect_subgrid_axis_from_style(tree, node_id, false);
+            if is_subgrid {
                 // Apply subgrid preprocessing with the resolved parent context
                 if let Ok(_) = coordinate_nested_subgrids(tree, node_id, &parent_context, 0) {
                     // Subgrid preprocessing applied successfully
@@ -294,7 +202,7 @@ where
     //    - Standard grid layout processes these tracks correctly
     //    - Result: Fully functional masonry layout
     //
-    // 2. BaseDocument Path:
+    // 2. Stylo Path:
     //    - Runtime detection via TaffyStyloStyle::has_masonry_rows/columns()  
     //    - Advanced placement algorithm via apply_masonry_layout()
     //    - Implements CSS Grid Level 3 shortest-track placement
@@ -0,0 +1,159 @@
+//! Tree-level access required by the subgrid and masonry algorithms
+//!
+//! The CSS Grid Level 2/3 preprocessing in this crate needs more than taffy's
+//! `LayoutGridContainer` exposes: raw stylo computed values (to see `subgrid`
+//! and `masonry` keywords before they are lowered to taffy tracks), DOM parent
+//! links, the ability to rewrite a subgrid's templates, and intrinsic item sizes.
+//!
+//! [`GridStyleAccess`] captures exactly that surface so the algorithms can stay
+//! generic over the layout tree instead of downcasting to [`BaseDocument`].
+
+use atomic_refcell::AtomicRef;
+use blitz_text::measurement::types::FontMetrics;
+use style::properties::ComputedValues;
+use taffy::NodeId;
+use taffy::geometry::AbstractAxis;
+
+use super::grid_errors::GridPreprocessingError;
+use super::intrinsic_sizing::{
+    calculate_item_intrinsic_size_for_masonry, extract_font_metrics_fallback,
+};
+use super::masonry::auto_flow::{MasonryAutoFlow, parse_reading_order};
+use crate::BaseDocument;
+use crate::util::custom_property;
+
+/// Style and structure queries used by grid preprocessing (subgrid and masonry)
+///
+/// Trees that cannot provide stylo computed values may return `None` from
+/// [`grid_computed_styles`](Self::grid_computed_styles); the preprocessing then
+/// falls back to standard taffy grid layout.
+pub trait GridStyleAccess: taffy::LayoutGridContainer {
+    /// Stylo computed values for a node, if the tree is backed by stylo
+    fn grid_computed_styles(&self, node_id: NodeId) -> Option<AtomicRef<'_, ComputedValues>>;
+
+    /// The parent of a node in the tree, if any
+    fn grid_parent(&self, node_id: NodeId) -> Option<NodeId>;
+
+    /// Identifies the tree in the thread's grid context caches, as node ids are only unique within
+    /// a tree. Trees which are laid out on the same thread must return different ids.
+    fn grid_tree_id(&self) -> usize {
+        0
+    }
+
+    /// The CSS `order` of a grid item. Defaults to `0` (document order).
+    fn grid_item_order(&self, _node_id: NodeId) -> i32 {
+        0
+    }
+
+    /// Mutate the taffy style of a node in place.
+    ///
+    /// Used by subgrid preprocessing to replace `grid-template-*` with the inherited
+    /// parent tracks. Implementations must invalidate any cached style conversion so
+    /// the next layout sees the mutation. Returns `None` if the tree does not allow it.
+    fn with_grid_style_mut<R>(
+        &mut self,
+        node_id: NodeId,
+        f: impl FnOnce(&mut taffy::Style) -> R,
+    ) -> Option<R>;
+
+    /// Intrinsic size of a masonry (or subgrid) item before placement
+    fn grid_item_intrinsic_size(
+        &self,
+        item_id: NodeId,
+        inputs: &taffy::tree::LayoutInput,
+        masonry_axis: AbstractAxis,
+    ) -> Result<taffy::Size<f32>, GridPreprocessingError>;
+
+    /// Font metrics used for baseline fallback when an item has no layout baseline
+    fn grid_font_metrics(&self, _font_family: &str, _font_size: f32) -> Option<FontMetrics> {
+        None
+    }
+
+    /// The `masonry-auto-flow` of a masonry container. Defaults to `pack definite-first`.
+    fn grid_masonry_auto_flow(&self, _node_id: NodeId) -> MasonryAutoFlow {
+        MasonryAutoFlow::default()
+    }
+
+    /// The `reading-order` of a masonry item. Defaults to `0`.
+    fn grid_reading_order(&self, _node_id: NodeId) -> i32 {
+        0
+    }
+
+    /// Record the order assistive technologies should read a masonry container's items in, or
+    /// forget it (`None`) before the container is laid out again. Ignored by default.
+    fn set_masonry_reading_order(&mut self, _node_id: NodeId, _items: Option<Vec<NodeId>>) {}
+}
+
+impl GridStyleAccess for BaseDocument {
+    fn grid_computed_styles(&self, node_id: NodeId) -> Option<AtomicRef<'_, ComputedValues>> {
+        self.get_node(node_id.into())?.primary_styles()
+    }
+
+    fn grid_parent(&self, node_id: NodeId) -> Option<NodeId> {
+        self.get_node(node_id.into())?.parent.map(NodeId::from)
+    }
+
+    fn grid_tree_id(&self) -> usize {
+        self.id()
+    }
+
+    fn grid_item_order(&self, node_id: NodeId) -> i32 {
+        self.get_node(node_id.into()).map_or(0, |node| node.order())
+    }
+
+    fn with_grid_style_mut<R>(
+        &mut self,
+        node_id: NodeId,
+        f: impl FnOnce(&mut taffy::Style) -> R,
+    ) -> Option<R> {
+        let node = self.get_node_mut(node_id.into())?;
+        let result = f(node.style_mut());
+
+        // Increment style generation to invalidate cache
+        node.style_generation
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+        Some(result)
+    }
+
+    fn grid_item_intrinsic_size(
+        &self,
+        item_id: NodeId,
+        inputs: &taffy::tree::LayoutInput,
+        masonry_axis: AbstractAxis,
+    ) -> Result<taffy::Size<f32>, GridPreprocessingError> {
+        calculate_item_intrinsic_size_for_masonry(self, item_id, inputs, masonry_axis)
+    }
+
+    fn grid_font_metrics(&self, font_family: &str, font_size: f32) -> Option<FontMetrics> {
+        Some(extract_font_metrics_fallback(self, font_family, font_size))
+    }
+
+    fn grid_masonry_auto_flow(&self, node_id: NodeId) -> MasonryAutoFlow {
+        let styles = self.grid_computed_styles(node_id);
+        let value = styles.and_then(|styles| {
+            custom_property(&self.stylist, &styles, "blitz-masonry-auto-flow")
+        });
+        value.map_or_else(Default::default, |value| MasonryAutoFlow::parse(&value))
+    }
+
+    fn grid_reading_order(&self, node_id: NodeId) -> i32 {
+        let styles = self.grid_computed_styles(node_id);
+        let value = styles.and_then(|styles| {
+            custom_property(&self.stylist, &styles, "blitz-reading-order")
+        });
+        value.map_or(0, |value| parse_reading_order(&value))
+    }
+
+    fn set_masonry_reading_order(&mut self, node_id: NodeId, items: Option<Vec<NodeId>>) {
+        match items {
+            Some(items) => {
+                let items = items.into_iter().map(usize::from).collect();
+                self.masonry_reading_orders.insert(usize::from(node_id), items);
+            }
+            None => {
+                self.masonry_reading_orders.remove(&usize::from(node_id));
+            }
+        }
+    }
+}
@@ -4,7 +4,10 @@ use taffy::{
     ResolveOrZero as _, Size, compute_leaf_layout,
 };
 
+use blitz_text::WritingMode;
+
 use super::resolve_calc_value;
+use super::stylo_to_blitz::{text_alignment, text_writing_mode};
 use crate::BaseDocument;
 
 impl BaseDocument {
@@ -55,6 +58,13 @@ impl BaseDocument {
         // TODO: eliminate clone
         let style = self.nodes[node_id].style().clone();
 
+        // In vertical writing modes the text is laid out horizontally, with the inline axis mapped
+        // onto the physical height, and then rotated when it is painted.
+        let vertical = self.nodes[node_id]
+            .primary_styles()
+            .is_some_and(|s| text_writing_mode(&s).0 != WritingMode::HorizontalTopBottom);
+        let viewport_height = self.viewport.window_size.1 as f32;
+
         let output = compute_leaf_layout(
             inputs,
             &style,
@@ -83,12 +93,16 @@ impl BaseDocument {
                         ibox.height = 0.0;
                     } else {
                         let output = self.compute_child_layout(NodeId::from(ibox.id), child_inputs);
-                        ibox.width = (margin.left + margin.right + output.size.width) * scale;
-                        ibox.height = (margin.top + margin.bottom + output.size.height) * scale;
+                        let width = (margin.left + margin.right + output.size.width) * scale;
+                        let height = (margin.top + margin.bottom + output.size.height) * scale;
+                        (ibox.width, ibox.height) = match vertical {
+                            true => (height, width),
+                            false => (width, height),
+                        };
                     }
                 }
 
-                // Determine width
+                // Determine the inline size (the width, or the height in vertical writing modes)
                 let padding = style
                     .padding
                     .resolve_or_zero(inputs.parent_size, resolve_calc_value);
@@ -96,11 +110,34 @@ impl BaseDocument {
                     .border
                     .resolve_or_zero(inputs.parent_size, resolve_calc_value);
                 let container_pb = padding + border;
-                let pbw = container_pb.horizontal_components().sum() * scale;
+                let (known_inline, available_inline, parent_inline, pb_inline) = if vertical {
+                    let available = match available_space.height {
+                        AvailableSpace::Definite(height) => AvailableSpace::Definite(height),
+                        // An orthogonal flow with an indefinite inline size uses the viewport
+                        // https://drafts.csswg.org/css-writing-modes-4/#orthogonal-auto
+                        _ => AvailableSpace::Definite(viewport_height / scale),
+                    };
+                    (
+                        inputs.known_dimensions.height,
+                        available,
+                        inputs.parent_size.height,
+                        container_pb.vertical_components().sum(),
+                    )
+                } else {
+                    (
+                        inputs.known_dimensions.width,
+                        available_space.width,
+                        inputs.parent_size.width,
+                        container_pb.horizontal_components().sum(),
+                    )
+                };
+                let (style_size, style_min_size, style_max_size) = match vertical {
+                    true => (style.size.height, style.min_size.height, style.max_size.height),
+                    false => (style.size.width, style.min_size.width, style.max_size.width),
+                };
+                let pbw = pb_inline * scale;
 
-                let width = inputs
-                    .known_dimensions
-                    .width
+                let inline_size = known_inline
                     .map(|w| (w * scale) - pbw)
                     .unwrap_or_else(|| {
                         // Get font system for content width calculation
@@ -110,7 +147,7 @@ impl BaseDocument {
                             // If text system is not available, provide reasonable defaults
                             crate::node::ContentWidths { min: 0.0, max: 0.0 }
                         });
-                        let computed_width = match available_space.width {
+                        let computed_width = match available_inline {
                             AvailableSpace::MinContent => content_sizes.min,
                             AvailableSpace::MaxContent => content_sizes.max,
                             AvailableSpace::Definite(limit) => (limit * scale)
@@ -118,20 +155,14 @@ impl BaseDocument {
                                 .max(content_sizes.min),
                         }
                         .ceil();
-                        let style_width = style
-                            .size
-                            .width
-                            .maybe_resolve(inputs.parent_size.width, resolve_calc_value)
+                        let style_width = style_size
+                            .maybe_resolve(parent_inline, resolve_calc_value)
                             .map(|w| w * scale);
-                        let min_width = style
-                            .min_size
-                            .width
-                            .maybe_resolve(inputs.parent_size.width, resolve_calc_value)
+                        let min_width = style_min_size
+                            .maybe_resolve(parent_inline, resolve_calc_value)
                             .map(|w| w * scale);
-                        let max_width = style
-                            .max_size
-                            .width
-                            .maybe_resolve(inputs.parent_size.width, resolve_calc_value)
+                        let max_width = style_max_size
+                            .maybe_resolve(parent_inline, resolve_calc_value)
                             .map(|w| w * scale);
 
                         (style_width)
@@ -143,36 +174,28 @@ impl BaseDocument {
 
                 // Perform inline layout
                 let _ = self.with_text_system(|text_system| text_system.with_font_system(|font_system| {
-                    inline_layout.break_all_lines(font_system, Some(width));
+                    inline_layout.break_all_lines(font_system, Some(inline_size));
                 }));
 
                 if inputs.run_mode == taffy::RunMode::ComputeSize {
-                    return taffy::Size {
-                        width: width.ceil() / scale,
-                        // Height will be ignored in RequestedAxis is Horizontal
-                        height: inline_layout.height() / scale,
+                    let inline = inline_size.ceil() / scale;
+                    // Block size will be ignored if RequestedAxis is the inline axis
+                    let block = inline_layout.height() / scale;
+                    return match vertical {
+                        true => taffy::Size {
+                            width: block,
+                            height: inline,
+                        },
+                        false => taffy::Size {
+                            width: inline,
+                            height: block,
+                        },
                     };
                 }
 
                 let alignment = self.nodes[node_id]
                     .primary_styles()
-                    .map(|s| {
-                        use style::values::specified::TextAlignKeyword;
-
-                        match s.clone_text_align() {
-                            TextAlignKeyword::Start
-                            | TextAlignKeyword::Left
-                            | TextAlignKeyword::MozLeft => CosmicAlign::Left,
-                            TextAlignKeyword::Right | TextAlignKeyword::MozRight => {
-                                CosmicAlign::Right
-                            }
-                            TextAlignKeyword::Center | TextAlignKeyword::MozCenter => {
-                                CosmicAlign::Center
-                            }
-                            TextAlignKeyword::Justify => CosmicAlign::Justified,
-                            TextAlignKeyword::End => CosmicAlign::Right,
-                        }
-                    })
+                    .map(|s| text_alignment(&s))
                     .unwrap_or(CosmicAlign::Left);
 
                 // Apply alignment to all lines in the buffer
@@ -190,7 +213,15 @@ impl BaseDocument {
                         .border
                         .resolve_or_zero(inputs.parent_size, resolve_calc_value);
 
+                let block_size = inline_layout.height();
                 for ibox in &inline_layout.inline_boxes {
+                    // Map the box from the (horizontal) text layout into physical coordinates.
+                    // Vertical text is rotated clockwise, so the first line is on the right.
+                    let (ibox_x, ibox_y, ibox_width, ibox_height) = match vertical {
+                        true => (block_size - ibox.y - ibox.height, ibox.x, ibox.height, ibox.width),
+                        false => (ibox.x, ibox.y, ibox.width, ibox.height),
+                    };
+
                     let node = &self.nodes[ibox.id as usize];
                     let padding = node
                         .style()
@@ -243,7 +274,7 @@ impl BaseDocument {
                                     },
                                 )
                             })
-                            .unwrap_or((ibox.x / scale) + margin.left + container_pb.left);
+                            .unwrap_or((ibox_x / scale) + margin.left + container_pb.left);
 
                         layout.location.y = top
                             .or_else(|| {
@@ -253,17 +284,17 @@ impl BaseDocument {
                                     },
                                 )
                             })
-                            .unwrap_or((ibox.y / scale) + margin.top + container_pb.top);
+                            .unwrap_or((ibox_y / scale) + margin.top + container_pb.top);
 
                         layout.padding = padding;
                         layout.border = border;
                     } else {
                         // Handle relative/static positioning - use inline box coordinates
                         let layout = &mut self.nodes[ibox.id as usize].unrounded_layout;
-                        layout.size.width = (ibox.width / scale) - margin.left - margin.right;
-                        layout.size.height = (ibox.height / scale) - margin.top - margin.bottom;
-                        layout.location.x = (ibox.x / scale) + margin.left + container_pb.left;
-                        layout.location.y = (ibox.y / scale) + margin.top + container_pb.top;
+                        layout.size.width = (ibox_width / scale) - margin.left - margin.right;
+                        layout.size.height = (ibox_height / scale) - margin.top - margin.bottom;
+                        layout.location.x = (ibox_x / scale) + margin.left + container_pb.left;
+                        layout.location.y = (ibox_y / scale) + margin.top + container_pb.top;
                         layout.padding = padding;
                         layout.border = border;
                     }
@@ -275,15 +306,15 @@ impl BaseDocument {
                 // println!("known_dimensions: w: {:?} h: {:?}", inputs.known_dimensions.width, inputs.known_dimensions.height);
                 // println!("\n");
 
+                let inline = inline_layout.width().ceil() / scale;
+                let block = inline_layout.height() / scale;
+                let (width, height) = match vertical {
+                    true => (block, inline),
+                    false => (inline, block),
+                };
                 taffy::Size {
-                    width: inputs
-                        .known_dimensions
-                        .width
-                        .unwrap_or_else(|| inline_layout.width().ceil() / scale),
-                    height: inputs
-                        .known_dimensions
-                        .height
-                        .unwrap_or_else(|| inline_layout.height() / scale),
+                    width: inputs.known_dimensions.width.unwrap_or(width),
+                    height: inputs.known_dimensions.height.unwrap_or(height),
                 }
             },
         );
@@ -301,6 +332,9 @@ impl BaseDocument {
             }
         }
 
+        // Export the baseline of the first line for baseline alignment in flex and grid layout
+        let mut output = output;
+        output.first_baselines.y = self.inline_first_baseline(node_id, inputs.parent_size.width);
         output
     }
 }
@@ -5,12 +5,12 @@
 //! from inline.rs, replaced.rs, and collect_inline_text.rs.
 
 use style::properties::ComputedValues;
-use taffy::{AvailableSpace, CoreStyle, NodeId};
+use taffy::{AvailableSpace, CoreStyle, MaybeMath, NodeId};
 
 use crate::BaseDocument;
 use crate::layout::collect_inline_text::collect_inline_text_recursive;
 use crate::layout::grid_errors::GridPreprocessingError;
-use crate::layout::replaced::{ReplacedContext, replaced_measure_function};
+use crate::layout::replaced::{ReplacedContext, prefers_natural_ratio, replaced_measure_function};
 use crate::layout::stylo_to_blitz::TextCollapseMode;
 use crate::node::{Node, NodeData};
 use blitz_text::measurement::types::FontMetrics;
@@ -43,9 +43,24 @@ pub fn calculate_item_intrinsic_size_for_masonry(
     };
 
     // Phase 3: Merge explicit and content-based sizes
-    let merged_size = taffy::Size {
-        width: explicit_width.unwrap_or(content_size.width),
-        height: explicit_height.unwrap_or(content_size.height),
+    // Non-replaced boxes with a preferred aspect ratio transfer sizes between axes (replaced
+    // elements already account for their aspect ratio when measured)
+    let explicit_size = taffy::Size {
+        width: explicit_width,
+        height: explicit_height,
+    };
+    let aspect_ratio = match is_replaced_element(node) {
+        true => None,
+        false => node.primary_styles().and_then(|styles| {
+            stylo_taffy::convert::aspect_ratio(styles.get_position().aspect_ratio)
+        }),
+    };
+    let merged_size = match aspect_ratio {
+        Some(ratio) => transfer_aspect_ratio(node, explicit_size, content_size, ratio),
+        None => taffy::Size {
+            width: explicit_width.unwrap_or(content_size.width),
+            height: explicit_height.unwrap_or(content_size.height),
+        },
     };
 
     // Phase 4: Apply min/max constraints per CSS specification
@@ -54,6 +69,81 @@ pub fn calculate_item_intrinsic_size_for_masonry(
     Ok(constrained_size)
 }
 
+/// Size a box with a preferred aspect ratio, transferring a definite size in one axis through the
+/// ratio to the other (ratio-dependent) axis, where it is clamped by that axis's min/max sizes.
+/// When neither size is definite the width comes from content and the height from the ratio.
+/// https://drafts.csswg.org/css-sizing-4/#aspect-ratio-size-transfers
+fn transfer_aspect_ratio(
+    node: &Node,
+    explicit_size: taffy::Size<Option<f32>>,
+    content_size: taffy::Size<f32>,
+    ratio: f32,
+) -> taffy::Size<f32> {
+    let (min_size, max_size) = match node.primary_styles() {
+        Some(computed_styles) => {
+            let style_wrapper = stylo_taffy::TaffyStyloStyle::from(computed_styles);
+            let min_size = style_wrapper.min_size();
+            let max_size = style_wrapper.max_size();
+            (
+                min_size.map(|size| size.into_option()),
+                max_size.map(|size| size.into_option()),
+            )
+        }
+        None => (taffy::Size::NONE, taffy::Size::NONE),
+    };
+
+    match (explicit_size.width, explicit_size.height) {
+        (Some(width), Some(height)) => taffy::Size { width, height },
+        (None, Some(height)) => taffy::Size {
+            width: ratio_dependent_size(
+                height * ratio,
+                content_size.width,
+                min_size.width,
+                max_size.width,
+            ),
+            height,
+        },
+        (width, None) => {
+            let width = width.unwrap_or_else(|| {
+                content_size
+                    .width
+                    .maybe_min(max_size.width)
+                    .maybe_max(min_size.width)
+            });
+            taffy::Size {
+                width,
+                height: ratio_dependent_size(
+                    width / ratio,
+                    content_size.height,
+                    min_size.height,
+                    max_size.height,
+                ),
+            }
+        }
+    }
+}
+
+/// Clamp a size transferred through the aspect ratio by the min/max sizes of its axis. With
+/// `min-*: auto` the automatic minimum size is the content size (capped by the max size), so
+/// content isn't made to overflow by the aspect ratio.
+/// https://drafts.csswg.org/css-sizing-4/#aspect-ratio-minimum
+fn ratio_dependent_size(
+    transferred: f32,
+    content: f32,
+    min_size: Option<f32>,
+    max_size: Option<f32>,
+) -> f32 {
+    let min_size = min_size.unwrap_or_else(|| content.maybe_min(max_size));
+    transferred.maybe_min(max_size).max(min_size)
+}
+
+/// Whether the node is a replaced element, which is measured from its natural size
+fn is_replaced_element(node: &Node) -> bool {
+    node.data.downcast_element().is_some_and(|element| {
+        matches!(element.name.local.as_ref(), "img" | "canvas" | "video" | "object" | "embed")
+    })
+}
+
 /// Extract explicit sizes from computed styles if available
 /// Returns partial sizes - dimensions may be None if not explicitly defined
 fn get_explicit_size<T: std::ops::Deref<Target = ComputedValues>>(
@@ -80,16 +170,13 @@ fn calculate_content_intrinsic_size(
             // Use existing inline layout measurement infrastructure
             measure_text_content_intrinsic_size(tree, item_id, inputs)
         }
-        NodeData::Element(element_data) => {
-            match element_data.name.local.as_ref() {
-                "img" | "canvas" | "video" | "object" | "embed" => {
-                    // Use existing replaced element measurement system
-                    measure_replaced_element_intrinsic_size(tree, item_id, inputs)
-                }
-                _ => {
-                    // Layout children using Taffy's intrinsic sizing patterns
-                    measure_element_content_intrinsic_size(tree, item_id, inputs)
-                }
+        NodeData::Element(_) => {
+            if is_replaced_element(node) {
+                // Use existing replaced element measurement system
+                measure_replaced_element_intrinsic_size(tree, item_id, inputs)
+            } else {
+                // Layout children using Taffy's intrinsic sizing patterns
+                measure_element_content_intrinsic_size(tree, item_id, inputs)
             }
         }
         _ => Ok(taffy::Size::ZERO),
@@ -133,6 +220,7 @@ fn measure_text_content_intrinsic_size(
     let mut text_content = String::new();
     collect_inline_text_recursive(
         &mut text_content,
+        &mut Vec::new(),
         &tree.nodes,
         item_id.into(),
         TextCollapseMode::Collapse,
@@ -299,6 +387,7 @@ fn measure_replaced_element_intrinsic_size(
     let replaced_context = ReplacedContext {
         inherent_size,
         attr_size,
+        prefers_natural_ratio: prefers_natural_ratio(node),
     };
 
     // Use existing replaced_measure_function from replaced.rs:23
@@ -575,3 +664,22 @@ pub(crate) fn calculate_line_height_from_raw_metrics(
     
     ascent_px + descent_px + line_gap_px
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ratio_dependent_size;
+
+    #[test]
+    fn ratio_dependent_size_respects_min_max_and_content() {
+        // The transferred size is used when content fits
+        assert_eq!(ratio_dependent_size(50.0, 20.0, None, None), 50.0);
+        // Content acts as the automatic minimum size
+        assert_eq!(ratio_dependent_size(50.0, 80.0, None, None), 80.0);
+        // ...unless it is larger than the max size
+        assert_eq!(ratio_dependent_size(50.0, 80.0, None, Some(60.0)), 60.0);
+        // An explicit min size replaces the automatic minimum
+        assert_eq!(ratio_dependent_size(50.0, 80.0, Some(0.0), None), 50.0);
+        // The min size wins over the max size
+        assert_eq!(ratio_dependent_size(50.0, 0.0, Some(70.0), Some(60.0)), 70.0);
+    }
+}
@@ -12,9 +12,12 @@ use taffy::{
     compute_flexbox_layout, compute_leaf_layout, prelude::*,
 };
 
+use super::baseline::synthesized_baseline;
+use super::containment::compute_contained_layout;
+use super::grid_contributions::GridContributionCache;
 use super::grid_preprocessing::preprocess_and_compute_grid_layout;
 use super::intrinsic_sizing::{calculate_line_height_from_metrics, extract_font_metrics_fallback};
-use super::replaced::{ReplacedContext, replaced_measure_function};
+use super::replaced::{ReplacedContext, prefers_natural_ratio, replaced_measure_function};
 use super::resolve_calc_value;
 use super::table::TableTreeWrapper;
 use super::tree_iteration::RefCellChildIter;
@@ -27,6 +30,7 @@ impl BaseDocument {
     pub(crate) fn node_from_id(&self, node_id: taffy::prelude::NodeId) -> &crate::node::Node {
         &self.nodes[node_id.into()]
     }
+
     pub(crate) fn node_from_id_mut(
         &mut self,
         node_id: taffy::prelude::NodeId,
@@ -34,6 +38,12 @@ impl BaseDocument {
         &mut self.nodes[node_id.into()]
     }
 
+    /// Whether the node is laid out by a grid container
+    fn is_grid_item(&self, node_id: NodeId) -> bool {
+        let parent = self.node_from_id(node_id).layout_parent.get();
+        parent.is_some_and(|parent_id| self.nodes[parent_id].style().display == Display::Grid)
+    }
+
     /// Resolve parent grid context using optimized cached algorithms
     ///
     /// This method provides efficient O(log n) parent grid context resolution
@@ -125,239 +135,307 @@ impl LayoutPartialTree for BaseDocument {
         node_id: NodeId,
         inputs: taffy::tree::LayoutInput,
     ) -> taffy::tree::LayoutOutput {
-        compute_cached_layout(self, node_id, inputs, |tree, node_id, inputs| {
-            // Extract style information first (before mutable borrow)
-            let font_info = {
-                let node = &tree.nodes[node_id.into()];
-                node.primary_styles().map(|style| {
-                    use style::values::computed::font::{SingleFontFamily, GenericFontFamily};
-
-                    let font_size = style.clone_font_size().used_size().px();
-                    let line_height_style = style.clone_line_height();
-
-                    // Extract font family for metric-based line-height calculation
-                    let font_family = match style.get_font().font_family.families.iter().next() {
-                        Some(family) => match family {
-                            SingleFontFamily::FamilyName(name) => name.name.as_ref().to_string(),
-                            SingleFontFamily::Generic(generic) => match generic {
-                                GenericFontFamily::Serif => "serif".to_string(),
-                                GenericFontFamily::SansSerif => "sans-serif".to_string(),
-                                GenericFontFamily::Monospace => "monospace".to_string(),
-                                GenericFontFamily::Cursive => "cursive".to_string(),
-                                GenericFontFamily::Fantasy => "fantasy".to_string(),
-                                _ => "sans-serif".to_string(),
-                            }
-                        }
-                        None => "sans-serif".to_string(),
-                    };
+        #[cfg(feature = "layout-trace")]
+        if let Some(trace) = &mut self.layout_trace {
+            trace.enter(node_id.into());
+        }
 
-                    (font_size, line_height_style, font_family)
-                })
-            };
+        let output = self.compute_node_layout(node_id, inputs);
+
+        #[cfg(feature = "layout-trace")]
+        if let Some(trace) = &mut self.layout_trace {
+            trace.exit(node_id.into(), &inputs, &output);
+        }
+        output
+    }
+}
 
-            // Calculate line height (can now use tree immutably)
-            let font_styles = font_info.map(|(font_size, line_height_style, font_family)| {
-                use style::values::computed::font::LineHeight;
+impl BaseDocument {
+    /// Measure or lay out a node, dispatching on its kind and display
+    fn compute_node_layout(
+        &mut self,
+        node_id: NodeId,
+        inputs: taffy::tree::LayoutInput,
+    ) -> taffy::tree::LayoutOutput {
+        // Grid track sizing measures items many times over, see `GridContributionCache`
+        let memoize = GridContributionCache::applies_to(&inputs) && self.is_grid_item(node_id);
+        if memoize {
+            if let Some(output) = self.grid_contributions.get(node_id.into(), &inputs) {
+                return output;
+            }
+        }
 
-                let line_height = match line_height_style {
-                    LineHeight::Normal => {
-                        // CSS spec: use font metrics for 'normal' (CSS Inline Layout Module Level 3)
-                        let font_metrics = extract_font_metrics_fallback(tree, &font_family, font_size);
-                        calculate_line_height_from_metrics(&font_metrics, font_size, 0.0)
-                    },
-                    LineHeight::Number(num) => font_size * num.0,
-                    LineHeight::Length(value) => value.0.px(),
-                    // Note: MozBlockHeight variant only available with gecko feature
+        let output = match self.aspect_ratio_transfer(node_id, &inputs) {
+            Some(transfer) => self.compute_layout_with_aspect_ratio(node_id, inputs, transfer),
+            None => compute_cached_layout(self, node_id, inputs, Self::compute_uncached_layout),
+        };
+
+        if memoize {
+            self.grid_contributions.insert(node_id.into(), &inputs, output);
+        }
+        output
+    }
+
+    /// Measure or lay out a node which isn't in the layout cache
+    pub(crate) fn compute_uncached_layout(
+        tree: &mut Self,
+        node_id: NodeId,
+        inputs: taffy::tree::LayoutInput,
+    ) -> taffy::tree::LayoutOutput {
+        // Size containment and skipped contents (`contain`, `content-visibility`)
+        if let Some(output) = compute_contained_layout(tree, node_id, inputs) {
+            return output;
+        }
+
+        // Extract style information first (before mutable borrow)
+        let font_info = {
+            let node = &tree.nodes[node_id.into()];
+            node.primary_styles().map(|style| {
+                use style::values::computed::font::{SingleFontFamily, GenericFontFamily};
+
+                let font_size = style.clone_font_size().used_size().px();
+                let line_height_style = style.clone_line_height();
+
+                // Extract font family for metric-based line-height calculation
+                let font_family = match style.get_font().font_family.families.iter().next() {
+                    Some(family) => match family {
+                        SingleFontFamily::FamilyName(name) => name.name.as_ref().to_string(),
+                        SingleFontFamily::Generic(generic) => match generic {
+                            GenericFontFamily::Serif => "serif".to_string(),
+                            GenericFontFamily::SansSerif => "sans-serif".to_string(),
+                            GenericFontFamily::Monospace => "monospace".to_string(),
+                            GenericFontFamily::Cursive => "cursive".to_string(),
+                            GenericFontFamily::Fantasy => "fantasy".to_string(),
+                            _ => "sans-serif".to_string(),
+                        }
+                    }
+                    None => "sans-serif".to_string(),
                 };
 
-                (font_size, line_height)
-            });
-            let font_size = font_styles.map(|s| s.0);
-            let resolved_line_height = font_styles.map(|s| s.1);
+                (font_size, line_height_style, font_family)
+            })
+        };
+
+        // Calculate line height (can now use tree immutably)
+        let font_styles = font_info.map(|(font_size, line_height_style, font_family)| {
+            use style::values::computed::font::LineHeight;
+
+            let line_height = match line_height_style {
+                LineHeight::Normal => {
+                    // CSS spec: use font metrics for 'normal' (CSS Inline Layout Module Level 3)
+                    let font_metrics = extract_font_metrics_fallback(tree, &font_family, font_size);
+                    calculate_line_height_from_metrics(&font_metrics, font_size, 0.0)
+                },
+                LineHeight::Number(num) => font_size * num.0,
+                LineHeight::Length(value) => value.0.px(),
+                // Note: MozBlockHeight variant only available with gecko feature
+            };
 
-            let node = &mut tree.nodes[node_id.into()];
+            (font_size, line_height)
+        });
+        let font_size = font_styles.map(|s| s.0);
+        let resolved_line_height = font_styles.map(|s| s.1);
 
-            match &mut node.data {
-                NodeData::Text(data) => {
-                    // With the new "inline context" architecture all text nodes should be wrapped in an "inline layout context"
-                    // and should therefore never be measured individually.
-                    println!(
-                        "ERROR: Tried to lay out text node individually ({})",
-                        usize::from(node_id)
+        let node = &mut tree.nodes[node_id.into()];
+
+        match &mut node.data {
+            NodeData::Text(data) => {
+                // With the new "inline context" architecture all text nodes should be wrapped in an "inline layout context"
+                // and should therefore never be measured individually.
+                println!(
+                    "ERROR: Tried to lay out text node individually ({})",
+                    usize::from(node_id)
+                );
+                dbg!(data);
+                taffy::LayoutOutput::HIDDEN
+            }
+            NodeData::Element(element_data) | NodeData::AnonymousBlock(element_data) => {
+                // TODO: deduplicate with single-line text input
+                if *element_data.name.local == *"textarea" {
+                    let rows = element_data
+                        .attr(local_name!("rows"))
+                        .and_then(|val| val.parse::<f32>().ok())
+                        .unwrap_or(2.0);
+
+                    let cols = element_data
+                        .attr(local_name!("cols"))
+                        .and_then(|val| val.parse::<f32>().ok());
+
+                    return compute_leaf_layout(
+                        inputs,
+                        node.style(),
+                        resolve_calc_value,
+                        |_known_size, _available_space| taffy::Size {
+                            width: cols
+                                .map(|cols| cols * font_size.unwrap_or(16.0) * 0.6)
+                                .unwrap_or(300.0),
+                            height: resolved_line_height.unwrap_or(16.0) * rows,
+                        },
                     );
-                    dbg!(data);
-                    taffy::LayoutOutput::HIDDEN
                 }
-                NodeData::Element(element_data) | NodeData::AnonymousBlock(element_data) => {
-                    // TODO: deduplicate with single-line text input
-                    if *element_data.name.local == *"textarea" {
-                        let rows = element_data
-                            .attr(local_name!("rows"))
-                            .and_then(|val| val.parse::<f32>().ok())
-                            .unwrap_or(2.0);
-
-                        let cols = element_data
-                            .attr(local_name!("cols"))
-                            .and_then(|val| val.parse::<f32>().ok());
-
-                        return compute_leaf_layout(
-                            inputs,
-                            node.style(),
-                            resolve_calc_value,
-                            |_known_size, _available_space| taffy::Size {
-                                width: cols
-                                    .map(|cols| cols * font_size.unwrap_or(16.0) * 0.6)
-                                    .unwrap_or(300.0),
-                                height: resolved_line_height.unwrap_or(16.0) * rows,
-                            },
-                        );
-                    }
 
-                    if *element_data.name.local == *"input" {
-                        match element_data.attr(local_name!("type")) {
-                            // if the input type is hidden, hide it
-                            Some("hidden") => {
-                                node.style_mut().display = Display::None;
-                                return taffy::LayoutOutput::HIDDEN;
-                            }
-                            Some("checkbox") => {
-                                return compute_leaf_layout(
-                                    inputs,
-                                    &node.style(),
-                                    resolve_calc_value,
-                                    |_known_size, _available_space| {
-                                        let width = node.style().size.width.resolve_or_zero(
-                                            inputs.parent_size.width,
-                                            resolve_calc_value,
-                                        );
-                                        let height = node.style().size.height.resolve_or_zero(
-                                            inputs.parent_size.height,
-                                            resolve_calc_value,
-                                        );
-                                        let min_size = width.min(height);
-                                        taffy::Size {
-                                            width: min_size,
-                                            height: min_size,
-                                        }
-                                    },
-                                );
-                            }
-                            None | Some("text" | "password" | "email") => {
-                                return compute_leaf_layout(
-                                    inputs,
-                                    &node.style(),
-                                    resolve_calc_value,
-                                    |_known_size, _available_space| taffy::Size {
-                                        width: 300.0,
-                                        height: resolved_line_height.unwrap_or(16.0),
-                                    },
-                                );
-                            }
-                            _ => {}
+                if *element_data.name.local == *"input" {
+                    match element_data.attr(local_name!("type")) {
+                        // if the input type is hidden, hide it
+                        Some("hidden") => {
+                            node.style_mut().display = Display::None;
+                            return taffy::LayoutOutput::HIDDEN;
                         }
-                    }
-
-                    if *element_data.name.local == *"img"
-                        || *element_data.name.local == *"canvas"
-                        || (cfg!(feature = "svg") && *element_data.name.local == *"svg")
-                    {
-                        // Get width and height attributes on image element
-                        let attr_size = taffy::Size {
-                            width: element_data
-                                .attr(local_name!("width"))
-                                .and_then(|val| val.parse::<f32>().ok()),
-                            height: element_data
-                                .attr(local_name!("height"))
-                                .and_then(|val| val.parse::<f32>().ok()),
-                        };
-
-                        // Get image's native size
-                        let inherent_size = match &element_data.special_data {
-                            SpecialElementData::Image(image_data) => match &**image_data {
-                                ImageData::Raster(image) => taffy::Size {
-                                    width: image.width as f32,
-                                    height: image.height as f32,
-                                },
-                                #[cfg(feature = "svg")]
-                                ImageData::Svg(svg) => {
-                                    let size = svg.size();
+                        Some("checkbox") => {
+                            return compute_leaf_layout(
+                                inputs,
+                                &node.style(),
+                                resolve_calc_value,
+                                |_known_size, _available_space| {
+                                    let width = node.style().size.width.resolve_or_zero(
+                                        inputs.parent_size.width,
+                                        resolve_calc_value,
+                                    );
+                                    let height = node.style().size.height.resolve_or_zero(
+                                        inputs.parent_size.height,
+                                        resolve_calc_value,
+                                    );
+                                    let min_size = width.min(height);
                                     taffy::Size {
-                                        width: size.width(),
-                                        height: size.height(),
+                                        width: min_size,
+                                        height: min_size,
                                     }
-                                }
-                                ImageData::None => taffy::Size::ZERO,
-                            },
-                            SpecialElementData::Canvas(_) => taffy::Size {
-                                width: 300.0,  // HTML5 canvas default width
-                                height: 150.0, // HTML5 canvas default height
-                            },
-                            SpecialElementData::None => taffy::Size::ZERO,
-                            _ => unreachable!(),
-                        };
-
-                        let replaced_context = ReplacedContext {
-                            inherent_size,
-                            attr_size,
-                        };
-
-                        let computed = replaced_measure_function(
-                            inputs.known_dimensions,
-                            inputs.parent_size,
-                            &replaced_context,
-                            &node.style(),
-                            false,
-                        );
-
-                        return taffy::LayoutOutput {
-                            size: computed,
-                            content_size: computed,
-                            first_baselines: taffy::Point::NONE,
-                            top_margin: CollapsibleMarginSet::ZERO,
-                            bottom_margin: CollapsibleMarginSet::ZERO,
-                            margins_can_collapse_through: false,
-                        };
+                                },
+                            );
+                        }
+                        None | Some("text" | "password" | "email") => {
+                            return compute_leaf_layout(
+                                inputs,
+                                &node.style(),
+                                resolve_calc_value,
+                                |_known_size, _available_space| taffy::Size {
+                                    width: 300.0,
+                                    height: resolved_line_height.unwrap_or(16.0),
+                                },
+                            );
+                        }
+                        _ => {}
                     }
+                }
 
-                    if node.flags.is_table_root() {
-                        // Build table context on-demand with proper preprocessing
-                        let (table_context, layout_children) = super::table::build_table_context(
-                            tree, 
-                            usize::from(node_id)
-                        );
-                        
-                        // Update the node's layout children to use the computed table layout
-                        let table_node = &mut tree.nodes[usize::from(node_id)];
-                        *table_node.layout_children.borrow_mut() = Some(layout_children);
-                        
-                        // Create table wrapper with proper context
-                        let context = std::sync::Arc::new(table_context);
-                        let mut table_wrapper = TableTreeWrapper {
-                            doc: tree,
-                            ctx: context,
-                        };
-                        
-                        // Compute proper CSS table layout using grid engine
-                        return taffy::compute_grid_layout(&mut table_wrapper, node_id, inputs);
-                    }
+                if *element_data.name.local == *"img"
+                    || *element_data.name.local == *"canvas"
+                    || (cfg!(feature = "svg") && *element_data.name.local == *"svg")
+                {
+                    // Get width and height attributes on image element
+                    let attr_size = taffy::Size {
+                        width: element_data
+                            .attr(local_name!("width"))
+                            .and_then(|val| val.parse::<f32>().ok()),
+                        height: element_data
+                            .attr(local_name!("height"))
+                            .and_then(|val| val.parse::<f32>().ok()),
+                    };
 
-                    if node.flags.is_inline_root() {
-                        return tree.compute_inline_layout(usize::from(node_id), inputs);
-                    }
+                    // Get image's native size
+                    let inherent_size = match &element_data.special_data {
+                        SpecialElementData::Image(image_data) => match &**image_data {
+                            ImageData::Raster(image) => taffy::Size {
+                                width: image.natural_width as f32,
+                                height: image.natural_height as f32,
+                            },
+                            #[cfg(feature = "svg")]
+                            ImageData::Svg(svg) => {
+                                let size = svg.size();
+                                taffy::Size {
+                                    width: size.width(),
+                                    height: size.height(),
+                                }
+                            }
+                            ImageData::None => taffy::Size::ZERO,
+                        },
+                        SpecialElementData::Canvas(_) => taffy::Size {
+                            width: 300.0,  // HTML5 canvas default width
+                            height: 150.0, // HTML5 canvas default height
+                        },
+                        SpecialElementData::None => taffy::Size::ZERO,
+                        _ => unreachable!(),
+                    };
 
-                    // The default CSS file will set
-                    match node.style().display {
-                        Display::Block => compute_block_layout(tree, node_id, inputs),
-                        Display::Flex => compute_flexbox_layout(tree, node_id, inputs),
-                        Display::Grid => preprocess_and_compute_grid_layout(tree, node_id, inputs),
-                        Display::None => taffy::LayoutOutput::HIDDEN,
+                    let replaced_context = ReplacedContext {
+                        inherent_size,
+                        attr_size,
+                        prefers_natural_ratio: prefers_natural_ratio(node),
+                    };
+
+                    let computed = replaced_measure_function(
+                        inputs.known_dimensions,
+                        inputs.parent_size,
+                        &replaced_context,
+                        &node.style(),
+                        false,
+                    );
+
+                    return taffy::LayoutOutput {
+                        size: computed,
+                        content_size: computed,
+                        first_baselines: synthesized_baseline(computed.height),
+                        top_margin: CollapsibleMarginSet::ZERO,
+                        bottom_margin: CollapsibleMarginSet::ZERO,
+                        margins_can_collapse_through: false,
+                    };
+                }
+
+                if node.flags.is_table_root() {
+                    // Build table context on-demand with proper preprocessing
+                    let (table_context, layout_children) = super::table::build_table_context(
+                        tree, 
+                        usize::from(node_id)
+                    );
+                    
+                    // Update the node's layout children to use the computed table layout
+                    let table_node = &mut tree.nodes[usize::from(node_id)];
+                    *table_node.layout_children.borrow_mut() = Some(layout_children);
+                    
+                    // Create table wrapper with proper context. The context is kept on the
+                    // table for painting its collapsed borders.
+                    #[allow(clippy::arc_with_non_send_sync)]
+                    let context = std::sync::Arc::new(table_context);
+                    let table_node = &mut tree.nodes[usize::from(node_id)];
+                    if let Some(element) = table_node.element_data_mut() {
+                        element.special_data = SpecialElementData::TableRoot(context.clone());
                     }
+                    let mut table_wrapper = TableTreeWrapper {
+                        doc: tree,
+                        ctx: context,
+                    };
+                    
+                    // Compute proper CSS table layout using grid engine
+                    return taffy::compute_grid_layout(&mut table_wrapper, node_id, inputs);
                 }
-                NodeData::Document => compute_block_layout(tree, node_id, inputs),
 
-                _ => taffy::LayoutOutput::HIDDEN,
+                if node.flags.is_ruby_root() {
+                    return tree.compute_ruby_layout(usize::from(node_id), inputs);
+                }
+
+                if node.flags.is_math_layout() {
+                    return tree.compute_math_layout(usize::from(node_id), inputs);
+                }
+
+                if node.flags.is_inline_root() {
+                    return tree.compute_inline_layout(usize::from(node_id), inputs);
+                }
+
+                // The default CSS file will set
+                match node.style().display {
+                    Display::Block => {
+                        let output = compute_block_layout(tree, node_id, inputs);
+                        tree.with_block_baseline(node_id.into(), inputs.run_mode, output)
+                    }
+                    Display::Flex => compute_flexbox_layout(tree, node_id, inputs),
+                    Display::Grid => preprocess_and_compute_grid_layout(tree, node_id, inputs),
+                    Display::None => taffy::LayoutOutput::HIDDEN,
+                }
             }
-        })
+            NodeData::Document => compute_block_layout(tree, node_id, inputs),
+
+            _ => taffy::LayoutOutput::HIDDEN,
+        }
     }
 }
 
@@ -395,6 +473,7 @@ impl taffy::CacheTree for BaseDocument {
     #[inline]
     fn cache_clear(&mut self, node_id: NodeId) {
         self.node_from_id_mut(node_id).cache.clear();
+        self.grid_contributions.remove(node_id.into());
     }
 }
 
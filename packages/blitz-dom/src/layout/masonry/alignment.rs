@@ -6,18 +6,19 @@ use taffy::prelude::NodeId;
 use taffy::geometry::AbstractAxis;
 use taffy::{AlignSelf, FlexboxItemStyle, GridItemStyle};
 
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 /// Get alignment values for a masonry item
 /// Returns (grid_axis_alignment, masonry_axis_alignment)
-pub fn get_masonry_item_alignment(
-    tree: &BaseDocument,
+pub fn get_masonry_item_alignment<Tree>(
+    tree: &Tree,
     item_id: NodeId,
     masonry_axis: AbstractAxis,
-) -> (Option<AlignSelf>, Option<AlignSelf>) {
-    let node = tree.node_from_id(item_id.into());
-    
-    if let Some(styles) = node.primary_styles() {
+) -> (Option<AlignSelf>, Option<AlignSelf>)
+where
+    Tree: GridStyleAccess,
+{
+    if let Some(styles) = tree.grid_computed_styles(item_id) {
         let wrapper = stylo_taffy::TaffyStyloStyle::from(styles);
         
         match masonry_axis {
@@ -0,0 +1,78 @@
+//! The `masonry-auto-flow` and `reading-order` properties
+//!
+//! Stylo parses neither, so like the `scrollbar-*` properties (see [`crate::scrollbar`]) their
+//! declarations are renamed to the `--blitz-masonry-auto-flow` and `--blitz-reading-order` custom
+//! properties before stylesheets are parsed, and read back from the computed custom properties.
+//! Neither is inherited, so a user agent stylesheet resets them on every element.
+
+/// The custom properties `masonry-auto-flow` and `reading-order` are renamed to
+const MASONRY_PROPERTIES: [(&str, &str); 2] = [
+    ("masonry-auto-flow", "--blitz-masonry-auto-flow"),
+    ("reading-order", "--blitz-reading-order"),
+];
+
+/// Resets `masonry-auto-flow` and `reading-order` on every element, as they aren't inherited
+pub(crate) const MASONRY_STYLESHEET: &str =
+    "* { --blitz-masonry-auto-flow: pack definite-first; --blitz-reading-order: 0; }\n";
+
+/// The custom property `property` is renamed to, if it is `masonry-auto-flow` or `reading-order`
+pub(crate) fn custom_property_for(property: &str) -> Option<&'static str> {
+    MASONRY_PROPERTIES
+        .iter()
+        .find(|(name, _)| property.eq_ignore_ascii_case(name))
+        .map(|(_, custom_property)| *custom_property)
+}
+
+/// The `masonry-auto-flow` property
+///
+/// See <https://www.w3.org/TR/css-grid-3/#masonry-auto-flow>
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MasonryAutoFlow {
+    /// `next`: place each item in the track after the previous item's, instead of in the
+    /// shortest track (`pack`)
+    pub next: bool,
+    /// `ordered`: place items in order-modified document order, instead of placing the items with
+    /// a definite position in the grid axis first (`definite-first`)
+    pub ordered: bool,
+}
+
+impl MasonryAutoFlow {
+    /// Parse a value like `next ordered`. Unknown keywords are ignored.
+    pub fn parse(value: &str) -> Self {
+        let mut auto_flow = Self::default();
+        for keyword in value.split_ascii_whitespace() {
+            match keyword.to_ascii_lowercase().as_str() {
+                "pack" => auto_flow.next = false,
+                "next" => auto_flow.next = true,
+                "definite-first" => auto_flow.ordered = false,
+                "ordered" => auto_flow.ordered = true,
+                _ => {}
+            }
+        }
+        auto_flow
+    }
+}
+
+/// Parse a `reading-order` value, which is an integer (`0` if it isn't one)
+pub(crate) fn parse_reading_order(value: &str) -> i32 {
+    value.trim().parse().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_values() {
+        assert_eq!(MasonryAutoFlow::parse("pack definite-first"), MasonryAutoFlow::default());
+        assert_eq!(
+            MasonryAutoFlow::parse("Ordered next"),
+            MasonryAutoFlow {
+                next: true,
+                ordered: true,
+            }
+        );
+        assert_eq!(parse_reading_order(" -2"), -2);
+        assert_eq!(parse_reading_order("auto"), 0);
+    }
+}
@@ -9,9 +9,7 @@ use taffy::geometry::AbstractAxis;
 use taffy::prelude::NodeId;
 
 use super::super::grid_errors::GridPreprocessingError;
-use super::super::intrinsic_sizing::extract_font_metrics_fallback;
-use crate::node::ElementData;
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 // Import traits to use align_self() and justify_self() methods
 use taffy::FlexboxItemStyle;
@@ -55,14 +53,15 @@ pub struct BaselineAdjustment {
 }
 
 /// Check if an item should participate in baseline alignment
-pub fn should_align_baseline(
-    tree: &BaseDocument,
+pub fn should_align_baseline<Tree>(
+    tree: &Tree,
     item_id: NodeId,
     masonry_axis: AbstractAxis,
-) -> bool {
-    let node = tree.node_from_id(item_id.into());
-
-    if let Some(styles) = node.primary_styles() {
+) -> bool
+where
+    Tree: GridStyleAccess,
+{
+    if let Some(styles) = tree.grid_computed_styles(item_id) {
         let style_wrapper = stylo_taffy::TaffyStyloStyle::from(styles);
 
         // Check align-self in the masonry axis direction
@@ -82,13 +81,16 @@ pub fn should_align_baseline(
 
 /// Extract baseline from layout output (NEW - Taffy's approach)
 /// This is called AFTER the item has been laid out
-pub fn extract_item_baseline_from_layout(
-    tree: &BaseDocument,
+pub fn extract_item_baseline_from_layout<Tree>(
+    tree: &Tree,
     item_id: NodeId,
     layout_output: &taffy::tree::LayoutOutput,
     masonry_axis: AbstractAxis,
     container_size: taffy::Size<Option<f32>>,
-) -> Option<f32> {
+) -> Option<f32>
+where
+    Tree: GridStyleAccess,
+{
     // Method 1: Extract from layout output (PRIMARY - from Taffy's grid)
     // Pattern from: /tmp/taffy/src/compute/grid/track_sizing.rs:501-507
     if let Some(baseline_y) = layout_output.first_baselines.y {
@@ -108,13 +110,10 @@ pub fn extract_item_baseline_from_layout(
     }
 
     // Method 2: Fallback to font metrics for simple text
-    let node = tree.node_from_id(item_id.into());
-    if let Some(element) = node.data.downcast_element() {
-        if let Some(styles) = node.primary_styles() {
-            if let Some(baseline) = calculate_baseline_from_font_metrics(tree, element, &*styles) {
-                let top_margin = extract_top_margin(tree, item_id, masonry_axis, container_size);
-                return Some(baseline + top_margin);
-            }
+    if let Some(styles) = tree.grid_computed_styles(item_id) {
+        if let Some(baseline) = calculate_baseline_from_font_metrics(tree, &styles) {
+            let top_margin = extract_top_margin(tree, item_id, masonry_axis, container_size);
+            return Some(baseline + top_margin);
         }
     }
 
@@ -124,17 +123,16 @@ pub fn extract_item_baseline_from_layout(
 
 /// Extract baseline offset for a masonry item (DEPRECATED - use extract_item_baseline_from_layout)
 #[allow(dead_code)]
-pub fn extract_item_baseline(
-    tree: &BaseDocument,
+pub fn extract_item_baseline<Tree>(
+    tree: &Tree,
     item_id: NodeId,
-) -> Option<f32> {
-    let node = tree.node_from_id(item_id.into());
-
+) -> Option<f32>
+where
+    Tree: GridStyleAccess,
+{
     // Method 1: Calculate from font metrics for text content
-    if let Some(element) = node.data.downcast_element() {
-        if let Some(styles) = node.primary_styles() {
-            return calculate_baseline_from_font_metrics(tree, element, &*styles);
-        }
+    if let Some(styles) = tree.grid_computed_styles(item_id) {
+        return calculate_baseline_from_font_metrics(tree, &styles);
     }
 
     // Method 2: For replaced elements, use margin box bottom
@@ -142,11 +140,13 @@ pub fn extract_item_baseline(
 }
 
 /// Calculate baseline from font metrics
-fn calculate_baseline_from_font_metrics(
-    tree: &BaseDocument,
-    _element: &ElementData,
+fn calculate_baseline_from_font_metrics<Tree>(
+    tree: &Tree,
     styles: &style::properties::ComputedValues,
-) -> Option<f32> {
+) -> Option<f32>
+where
+    Tree: GridStyleAccess,
+{
     let font = styles.get_font();
     let font_size = font.font_size.computed_size.px();
 
@@ -173,7 +173,7 @@ fn calculate_baseline_from_font_metrics(
     };
 
     // Get font metrics (reuse existing infrastructure)
-    let metrics = extract_font_metrics_fallback(tree, font_family, font_size);
+    let metrics = tree.grid_font_metrics(font_family, font_size)?;
 
     // Baseline = ascent scaled to font size
     // (This is the alphabetic baseline, standard for Latin text)
@@ -184,15 +184,16 @@ fn calculate_baseline_from_font_metrics(
 }
 
 /// Extract top margin for an item
-fn extract_top_margin(
-    tree: &BaseDocument,
+fn extract_top_margin<Tree>(
+    tree: &Tree,
     item_id: NodeId,
     masonry_axis: AbstractAxis,
     container_size: taffy::Size<Option<f32>>,
-) -> f32 {
-    let node = tree.node_from_id(item_id.into());
-
-    if let Some(styles) = node.primary_styles() {
+) -> f32
+where
+    Tree: GridStyleAccess,
+{
+    if let Some(styles) = tree.grid_computed_styles(item_id) {
         let margin = styles.get_margin();
 
         // For masonry, the "top" margin depends on the masonry axis direction
@@ -217,13 +218,16 @@ fn extract_top_margin(
 }
 
 /// Calculate baseline adjustments for all placed items
-pub fn calculate_baseline_adjustments(
-    tree: &BaseDocument,
+pub fn calculate_baseline_adjustments<Tree>(
+    tree: &Tree,
     placed_items: &[(NodeId, stylo_taffy::GridArea)],
     layout_outputs: &[taffy::tree::LayoutOutput],  // ✅ NEW: Layout outputs for baseline extraction
     masonry_axis: AbstractAxis,
     container_size: taffy::Size<Option<f32>>,
-) -> Result<Vec<BaselineAdjustment>, GridPreprocessingError> {
+) -> Result<Vec<BaselineAdjustment>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     // Step 1: Extract baseline info for all items that need baseline alignment
     let mut baseline_items: Vec<(usize, MasonryItemBaseline)> = Vec::new();
 
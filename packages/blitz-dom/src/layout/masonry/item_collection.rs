@@ -8,10 +8,10 @@ use taffy::{
 };
 
 use super::super::grid_errors::GridPreprocessingError;
-use super::super::intrinsic_sizing::calculate_item_intrinsic_size_for_masonry;
+use super::auto_flow::MasonryAutoFlow;
 use super::track_counting::grid_axis_from_masonry;
 use super::virtual_placement::GridItemInfo;
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 /// Enhanced track configuration with item-tolerance support
 /// Provides complete configuration for CSS Grid Level 3 masonry layout
@@ -26,17 +26,21 @@ pub struct MasonryConfig {
     pub dense_packing: bool,
     /// Range of auto-fit tracks (start_index, end_index) if auto-fit is used
     pub auto_fit_range: Option<(usize, usize)>,
+    /// The container's `masonry-auto-flow`
+    pub auto_flow: MasonryAutoFlow,
 }
 
 /// Calculate track count and extract item-tolerance from computed styles
 /// Implements CSS Grid Level 3 masonry configuration extraction
-pub fn calculate_masonry_config(
-    tree: &BaseDocument,
+pub fn calculate_masonry_config<Tree>(
+    tree: &Tree,
     node_id: NodeId,
     inputs: &taffy::tree::LayoutInput,
-) -> Result<MasonryConfig, GridPreprocessingError> {
-    let node = tree.node_from_id(node_id.into());
-    let computed_styles = node.primary_styles().ok_or_else(|| {
+) -> Result<MasonryConfig, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    let computed_styles = tree.grid_computed_styles(node_id).ok_or_else(|| {
         GridPreprocessingError::preprocessing_failed(
             "masonry_config_calculation",
             node_id.into(),
@@ -158,17 +162,20 @@ pub fn calculate_masonry_config(
         item_tolerance,
         dense_packing,
         auto_fit_range,
+        auto_flow: tree.grid_masonry_auto_flow(node_id),
     })
 }
 
 /// Extract masonry item tolerance from computed styles
 /// Implements CSS Grid Level 3 masonry-item-tolerance property extraction
-fn extract_masonry_item_tolerance_from_styles(
-    tree: &BaseDocument,
+fn extract_masonry_item_tolerance_from_styles<Tree>(
+    tree: &Tree,
     node_id: NodeId,
-) -> Result<f32, GridPreprocessingError> {
-    let node = tree.node_from_id(node_id.into());
-    let computed_styles = node.primary_styles().ok_or_else(|| {
+) -> Result<f32, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    let computed_styles = tree.grid_computed_styles(node_id).ok_or_else(|| {
         GridPreprocessingError::preprocessing_failed(
             "masonry_item_tolerance_extraction",
             node_id.into(),
@@ -185,12 +192,14 @@ fn extract_masonry_item_tolerance_from_styles(
 
 /// Extract dense packing configuration from grid-auto-flow
 /// Implements CSS Grid Level 3 dense packing detection
-fn extract_dense_packing_from_styles(
-    tree: &BaseDocument,
+fn extract_dense_packing_from_styles<Tree>(
+    tree: &Tree,
     node_id: NodeId,
-) -> Result<bool, GridPreprocessingError> {
-    let node = tree.node_from_id(node_id.into());
-    let computed_styles = node.primary_styles().ok_or_else(|| {
+) -> Result<bool, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    let computed_styles = tree.grid_computed_styles(node_id).ok_or_else(|| {
         GridPreprocessingError::preprocessing_failed(
             "dense_packing_extraction",
             node_id.into(),
@@ -211,10 +220,13 @@ fn extract_dense_packing_from_styles(
 /// Enhanced item collection that processes spans for intrinsic sizing
 ///
 /// Uses existing GridItemInfo fields that are currently unused (WARNING 10)
-pub fn collect_and_sort_masonry_items(
-    tree: &BaseDocument,
+pub fn collect_and_sort_masonry_items<Tree>(
+    tree: &Tree,
     container_id: NodeId,
-) -> Result<Vec<GridItemInfo>, GridPreprocessingError> {
+) -> Result<Vec<GridItemInfo>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     let mut items = collect_grid_items_for_masonry(tree, container_id)?; // Existing function
 
     // Sort by order field for proper placement sequence ✨ Uses WARNING 10 field
@@ -225,10 +237,13 @@ pub fn collect_and_sort_masonry_items(
 
 /// Collect grid items that need masonry placement
 /// Enhanced to detect grid spans and maintain proper placement order
-pub fn collect_grid_items_for_masonry(
-    tree: &BaseDocument,
+pub fn collect_grid_items_for_masonry<Tree>(
+    tree: &Tree,
     container_id: NodeId,
-) -> Result<Vec<GridItemInfo>, GridPreprocessingError> {
+) -> Result<Vec<GridItemInfo>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     let mut items = Vec::new();
     let child_count = tree.child_count(container_id);
 
@@ -236,8 +251,7 @@ pub fn collect_grid_items_for_masonry(
         let child_id = tree.get_child_id(container_id, i);
 
         // Check if child is a grid item (not absolutely positioned)
-        let node = tree.node_from_id(child_id.into());
-        if let Some(styles) = node.primary_styles() {
+        if let Some(styles) = tree.grid_computed_styles(child_id) {
             let style_wrapper = stylo_taffy::TaffyStyloStyle::from(styles);
 
             // Skip absolutely positioned items
@@ -247,7 +261,7 @@ pub fn collect_grid_items_for_masonry(
                 let grid_column = style_wrapper.grid_column();
 
                 // Enhanced span calculation handling all GridPlacement variants
-                let row_span = match (grid_row.start, grid_row.end) {
+                let row_span = match (grid_row.start.clone(), grid_row.end.clone()) {
                     (taffy::GridPlacement::Line(start), taffy::GridPlacement::Line(end)) => {
                         (end.as_i16() - start.as_i16()).abs().max(1) as usize
                     }
@@ -264,7 +278,7 @@ pub fn collect_grid_items_for_masonry(
                     _ => 1, // Auto, NamedLine, or invalid combinations default to 1
                 };
 
-                let column_span = match (grid_column.start, grid_column.end) {
+                let column_span = match (grid_column.start.clone(), grid_column.end.clone()) {
                     (taffy::GridPlacement::Line(start), taffy::GridPlacement::Line(end)) => {
                         (end.as_i16() - start.as_i16()).abs().max(1) as usize
                     }
@@ -286,6 +300,8 @@ pub fn collect_grid_items_for_masonry(
                     order: i, // Maintain source order for masonry
                     row_span,
                     column_span,
+                    row_start: definite_start_line(&grid_row, row_span),
+                    column_start: definite_start_line(&grid_column, column_span),
                 });
             }
         }
@@ -294,17 +310,32 @@ pub fn collect_grid_items_for_masonry(
     Ok(items)
 }
 
+/// The line a placement starts at, if it is definite. Line `0` is invalid and treated as `auto`.
+fn definite_start_line(placement: &taffy::Line<taffy::GridPlacement>, span: usize) -> Option<i16> {
+    let line = match (&placement.start, &placement.end) {
+        (taffy::GridPlacement::Line(start), taffy::GridPlacement::Line(end)) => {
+            start.as_i16().min(end.as_i16())
+        }
+        (taffy::GridPlacement::Line(start), _) => start.as_i16(),
+        (_, taffy::GridPlacement::Line(end)) => end.as_i16() - span as i16,
+        _ => return None,
+    };
+    (line != 0).then_some(line)
+}
+
 /// Calculate masonry item size using proper CSS intrinsic sizing
 /// Replaces hardcoded 200.0px/100.0px fallbacks with CSS Sizing Module Level 3 compliance
-pub fn estimate_item_size_for_masonry(
-    tree: &BaseDocument,
+pub fn estimate_item_size_for_masonry<Tree>(
+    tree: &Tree,
     item_id: NodeId,
     inputs: &taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
-) -> Result<Size<f32>, GridPreprocessingError> {
+) -> Result<Size<f32>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     // Use proper intrinsic sizing instead of hardcoded fallbacks
-    calculate_item_intrinsic_size_for_masonry(
-        tree,
+    tree.grid_item_intrinsic_size(
         item_id,
         inputs,
         masonry_axis, // Use actual masonry axis from config
@@ -313,15 +344,18 @@ pub fn estimate_item_size_for_masonry(
 
 /// Place item using Taffy-sized track information
 /// Uses actual track sizes from Taffy's track sizing algorithm instead of hardcoded values
-pub fn place_item_in_taffy_sized_track(
-    tree: &BaseDocument,
+pub fn place_item_in_taffy_sized_track<Tree>(
+    tree: &Tree,
     item: &GridItemInfo,
     track_index: usize,
     track_size: &f32,
     masonry_state: &stylo_taffy::MasonryTrackState,
     masonry_axis: AbstractAxis,
     inputs: &taffy::tree::LayoutInput,
-) -> Result<(NodeId, stylo_taffy::GridArea), GridPreprocessingError> {
+) -> Result<(NodeId, stylo_taffy::GridArea), GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     use stylo_taffy::GridArea;
 
     // For masonry, items ALWAYS fill their track in the grid axis
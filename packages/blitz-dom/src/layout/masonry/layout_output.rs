@@ -7,18 +7,21 @@ use taffy::{Layout, LayoutPartialTree, Point, Rect, Size, geometry::AbstractAxis
 
 use super::super::grid_errors::GridPreprocessingError;
 use super::taffy_integration::{calculate_container_size_from_placements, grid_area_to_layout};
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 /// Layout masonry items and return their layout outputs for baseline calculation
 /// This must be called BEFORE baseline adjustments are calculated
-pub fn layout_masonry_items(
-    tree: &mut BaseDocument,
+pub fn layout_masonry_items<Tree>(
+    tree: &mut Tree,
     placed_items: &[(NodeId, stylo_taffy::GridArea)],
     inputs: taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
     track_sizes: &[f32],
     gap_size: f32,
-) -> Result<Vec<taffy::tree::LayoutOutput>, GridPreprocessingError> {
+) -> Result<Vec<taffy::tree::LayoutOutput>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     let mut layout_outputs = Vec::with_capacity(placed_items.len());
 
     // Layout each item and its children (following Taffy's grid pattern)
@@ -63,14 +66,17 @@ pub fn layout_masonry_items(
 
 /// Apply final positions to laid-out masonry items
 /// This must be called AFTER baseline adjustments have been applied to grid_areas
-pub fn apply_masonry_positions(
-    tree: &mut BaseDocument,
+pub fn apply_masonry_positions<Tree>(
+    tree: &mut Tree,
     placed_items: &[(NodeId, stylo_taffy::GridArea)],
     layout_outputs: &[taffy::tree::LayoutOutput],
     masonry_axis: AbstractAxis,
     track_sizes: &[f32],
     gap_size: f32,
-) {
+)
+where
+    Tree: GridStyleAccess,
+{
     // Apply final positions to each item
     for (idx, (item_id, grid_area)) in placed_items.iter().enumerate() {
         let (location, size) = grid_area_to_layout(grid_area, masonry_axis, track_sizes, gap_size);
@@ -8,10 +8,12 @@ use taffy::geometry::AbstractAxis;
 use taffy::prelude::NodeId;
 
 use super::grid_errors::GridPreprocessingError;
-use crate::BaseDocument;
+use super::grid_style_access::GridStyleAccess;
 
 // Internal modules only - no public re-exports needed as functions are used with full paths
 
+pub(crate) mod auto_flow;
+
 // Internal modules
 mod baseline_alignment;
 mod gap_detection;
@@ -27,11 +29,14 @@ mod virtual_placement;
 /// 
 /// Masonry axis is determined automatically from styles - no need to pass it in.
 /// Config extracts both masonry_axis (flow direction) and grid_axis (track direction).
-pub fn apply_masonry_layout(
-    tree: &mut BaseDocument,
+pub fn apply_masonry_layout<Tree>(
+    tree: &mut Tree,
     node_id: NodeId,
     inputs: taffy::tree::LayoutInput,
-) -> Result<taffy::tree::LayoutOutput, GridPreprocessingError> {
+) -> Result<taffy::tree::LayoutOutput, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     use stylo_taffy::MasonryTrackState;
 
     // Phase 1: Get masonry configuration with auto-repeat support (MUST BE FIRST)
@@ -52,20 +57,32 @@ pub fn apply_masonry_layout(
         MasonryTrackState::new_with_tolerance(config.track_count, config.item_tolerance);
 
     // Phase 4: Collect and sort items by placement order ✨ WARNING 10
-    let grid_items = item_collection::collect_and_sort_masonry_items(tree, node_id)?;
+    let mut grid_items = item_collection::collect_and_sort_masonry_items(tree, node_id)?;
+
+    // `masonry-auto-flow: definite-first` places the items with a definite grid-axis position
+    // before the auto-placed ones. The sort is stable, so each group keeps its order.
+    if !config.auto_flow.ordered {
+        grid_items.sort_by_key(|item| item.grid_axis_placement(config.masonry_axis).1.is_none());
+    }
 
     // Phase 5: Place items using pre-sized tracks with optional dense packing
     let mut placed_items = Vec::new();
+    // The track after the last placed item, for `masonry-auto-flow: next`
+    let mut next_track = 0;
 
     for item in grid_items {
         // Grid axis determines which span to use (perpendicular to masonry flow)
-        let item_span = match config.masonry_axis {
-            AbstractAxis::Block => item.column_span,  // Vertical flow → spans across columns
-            AbstractAxis::Inline => item.row_span,    // Horizontal flow → spans across rows
-        };
-
-        // Determine placement track based on span and dense packing
-        let placement_track = if item_span > 1 {
+        let (item_span, start_line) = item.grid_axis_placement(config.masonry_axis);
+        let definite_track =
+            start_line.map(|line| definite_track(line, item_span, config.track_count));
+
+        // Determine placement track based on definite placement, span and dense packing
+        let placement_track = if let Some(track) = definite_track {
+            track
+        } else if config.auto_flow.next {
+            // Wrap around to the first track when the item doesn't fit in the remaining ones
+            if next_track + item_span > config.track_count { 0 } else { next_track }
+        } else if item_span > 1 {
             // Spanning items ALWAYS need dense placement logic to find shortest span
             // (not just when dense_packing is enabled)
             masonry_state.find_dense_placement(item_span)
@@ -120,6 +137,7 @@ pub fn apply_masonry_layout(
         let item_size_for_track = placement.1.masonry_axis_size;
 
         masonry_state.place_item_with_tracking(placement_track, item_size_for_track, item_span);
+        next_track = (placement_track + item_span) % config.track_count;
 
         #[cfg(feature = "tracing")]
         tracing::debug!(
@@ -132,6 +150,11 @@ pub fn apply_masonry_layout(
         placed_items.push(placement);
     }
 
+    // Items are read in the order they were placed in, reordered by their `reading-order`
+    let mut reading_order: Vec<NodeId> = placed_items.iter().map(|(id, _)| *id).collect();
+    reading_order.sort_by_key(|&item_id| tree.grid_reading_order(item_id));
+    tree.set_masonry_reading_order(node_id, Some(reading_order));
+
     // Phase 5.5: Collapse empty auto-fit tracks if needed
     let mut collapsed_track_sizes = track_sizes.clone();
     if let Some((auto_fit_start, auto_fit_end)) = config.auto_fit_range {
@@ -144,8 +167,7 @@ pub fn apply_masonry_layout(
     }
 
     // Extract gap size for position calculations
-    let node = tree.node_from_id(node_id.into());
-    let gap_size = if let Some(styles) = node.primary_styles() {
+    let gap_size = if let Some(styles) = tree.grid_computed_styles(node_id) {
         let style_wrapper = stylo_taffy::TaffyStyloStyle::from(styles);
         use taffy::ResolveOrZero;
         
@@ -213,6 +235,14 @@ pub fn apply_masonry_layout(
     ))
 }
 
+/// The track a definite grid-axis start line resolves to, moved back so that the item's span
+/// fits in the grid. Negative lines count back from the end of the grid.
+fn definite_track(line: i16, span: usize, track_count: usize) -> usize {
+    let line = if line > 0 { line as isize } else { track_count as isize + 2 + line as isize };
+    let track = (line.max(1) - 1) as usize;
+    track.min(track_count.saturating_sub(span))
+}
+
 /// Collapse empty auto-fit tracks in the specified range
 /// This implements the CSS Grid auto-fit behavior where empty auto-fit tracks collapse to size 0
 fn collapse_auto_fit_tracks_in_range(
@@ -278,3 +308,19 @@ fn collapse_auto_fit_tracks_in_range(
         // the position calculation will automatically account for collapsed tracks
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::definite_track;
+
+    #[test]
+    fn resolves_definite_tracks() {
+        assert_eq!(definite_track(2, 1, 4), 1);
+        // The last line is the end of the last track
+        assert_eq!(definite_track(-2, 1, 4), 3);
+        assert_eq!(definite_track(-5, 1, 4), 0);
+        // Items which would overflow the grid are moved back
+        assert_eq!(definite_track(4, 2, 4), 2);
+        assert_eq!(definite_track(9, 1, 4), 3);
+    }
+}
@@ -7,7 +7,7 @@ use taffy::{AvailableSpace, Size, geometry::AbstractAxis};
 
 use super::super::grid_errors::GridPreprocessingError;
 use super::virtual_placement::GridItemInfo;
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 /// Create Taffy grid style for masonry track sizing
 /// Converts masonry track definitions to a Taffy grid container style
@@ -48,15 +48,17 @@ pub fn create_taffy_grid_style_for_masonry(
 
 /// Create Taffy item style for masonry items
 /// Extracts CSS properties and converts to Taffy style
-pub fn create_taffy_item_style_for_masonry(
-    tree: &BaseDocument,
+pub fn create_taffy_item_style_for_masonry<Tree>(
+    tree: &Tree,
     item: &GridItemInfo,
     inputs: &taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
-) -> Result<taffy::Style, GridPreprocessingError> {
+) -> Result<taffy::Style, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     // Get intrinsic size for the item
-    let item_size = super::super::intrinsic_sizing::calculate_item_intrinsic_size_for_masonry(
-        tree,
+    let item_size = tree.grid_item_intrinsic_size(
         item.node_id,
         inputs,
         masonry_axis,
@@ -8,7 +8,7 @@ use taffy::prelude::NodeId;
 use taffy::RepetitionCount;
 
 use super::super::grid_errors::GridPreprocessingError;
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 /// Result of track counting that includes auto-fit range information
 #[derive(Debug, Clone)]
@@ -51,14 +51,16 @@ where
 
 /// Calculate actual track count for auto-fill/auto-fit in masonry grid axis
 /// Based on Taffy's explicit_grid.rs implementation (lines 103-179)
-pub fn calculate_auto_repeat_track_count(
-    tree: &BaseDocument,
+pub fn calculate_auto_repeat_track_count<Tree>(
+    tree: &Tree,
     node_id: NodeId,
     masonry_axis: AbstractAxis,
     available_size: Option<f32>,
-) -> Result<TrackCountResult, GridPreprocessingError> {
-    let node = tree.node_from_id(node_id.into());
-    let computed_styles = node.primary_styles().ok_or_else(|| {
+) -> Result<TrackCountResult, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    let computed_styles = tree.grid_computed_styles(node_id).ok_or_else(|| {
         GridPreprocessingError::preprocessing_failed(
             "auto_repeat_calculation",
             node_id.into(),
@@ -197,7 +199,7 @@ pub fn calculate_auto_repeat_track_count(
     if per_additional_rep < MIN_DIVISOR {
         // If per-repetition size is essentially zero, use conservative upper bound
         // Based on minimum reasonable track size (1em ~= 16px)
-        let min_track_size = if let Some(styles) = tree.node_from_id(node_id.into()).primary_styles() {
+        let min_track_size = if let Some(styles) = tree.grid_computed_styles(node_id) {
             styles.get_font().font_size.computed_size().px()
         } else {
             16.0
@@ -283,13 +285,16 @@ where
 
 /// Estimate track size with intrinsic sizing support
 /// For intrinsic sizing functions, samples grid items to get realistic size estimates
-fn estimate_track_size(
+fn estimate_track_size<Tree>(
     sizing_fn: taffy::TrackSizingFunction,
     parent_size: f32,
-    tree: &BaseDocument,
+    tree: &Tree,
     node_id: NodeId,
     masonry_axis: AbstractAxis,
-) -> f32 {
+) -> f32
+where
+    Tree: GridStyleAccess,
+{
     // Try max sizing function first (prefer if definite)
     if let Some(max_val) = sizing_fn.max.definite_value(Some(parent_size), |_, _| 0.0) {
         // Floor by min if both definite
@@ -311,17 +316,18 @@ fn estimate_track_size(
 /// Estimate intrinsic track size using font-based heuristic
 /// For intrinsic sizing functions (min-content, max-content, auto), we use a heuristic
 /// based on the container's font size since we cannot measure items before track counting
-fn estimate_intrinsic_track_size(
-    tree: &BaseDocument,
+fn estimate_intrinsic_track_size<Tree>(
+    tree: &Tree,
     node_id: NodeId,
     _masonry_axis: AbstractAxis,
     _container_size: f32,
-) -> f32 {
-    let node = tree.node_from_id(node_id.into());
-    
+) -> f32
+where
+    Tree: GridStyleAccess,
+{
     // Use font-based heuristic: 3em is a reasonable estimate for intrinsic content
     // This matches common text line height (1.5em) + some vertical spacing
-    if let Some(styles) = node.primary_styles() {
+    if let Some(styles) = tree.grid_computed_styles(node_id) {
         let font_size = styles.get_font().font_size.computed_size().px();
         return font_size * 3.0;
     }
@@ -331,15 +337,16 @@ fn estimate_intrinsic_track_size(
 }
 
 /// Calculate total space used by tracks (excluding auto-repeat if exclude_auto = true)
-fn calculate_track_space<'a, I>(
+fn calculate_track_space<'a, I, Tree>(
     tracks: I,
     container_size: f32,
     exclude_auto: bool,
-    tree: &BaseDocument,
+    tree: &Tree,
     node_id: NodeId,
     masonry_axis: AbstractAxis,
 ) -> f32
 where
+    Tree: GridStyleAccess,
     I: Iterator<Item = taffy::GenericGridTemplateComponent<String, &'a taffy::GridTemplateRepetition<String>>> + Clone,
 {
     tracks
@@ -371,14 +378,16 @@ where
 /// Get track count from the definite (non-masonry) axis
 /// Now supports auto-fill/auto-fit with dynamic calculation
 #[allow(dead_code)] // Infrastructure for CSS Grid Level 3 masonry layout
-pub fn get_definite_axis_track_count(
-    tree: &BaseDocument,
+pub fn get_definite_axis_track_count<Tree>(
+    tree: &Tree,
     node_id: NodeId,
     masonry_axis: AbstractAxis,
     available_size: Option<f32>,
-) -> Result<usize, GridPreprocessingError> {
-    let node = tree.node_from_id(node_id.into());
-    let computed_styles = node.primary_styles().ok_or_else(|| {
+) -> Result<usize, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    let computed_styles = tree.grid_computed_styles(node_id).ok_or_else(|| {
         GridPreprocessingError::preprocessing_failed(
             "track_count_extraction",
             node_id.into(),
@@ -412,12 +421,14 @@ pub fn get_definite_axis_track_count(
 
 /// Calculate track count for the definite (non-masonry) axis
 #[allow(dead_code)] // Infrastructure for CSS Grid Level 3 masonry layout
-pub fn calculate_definite_track_count(
-    tree: &BaseDocument,
+pub fn calculate_definite_track_count<Tree>(
+    tree: &Tree,
     node_id: NodeId,
-) -> Result<usize, GridPreprocessingError> {
-    let node = tree.node_from_id(node_id.into());
-    let computed_styles = node.primary_styles().ok_or_else(|| {
+) -> Result<usize, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    let computed_styles = tree.grid_computed_styles(node_id).ok_or_else(|| {
         GridPreprocessingError::preprocessing_failed(
             "track_count_calculation",
             node_id.into(),
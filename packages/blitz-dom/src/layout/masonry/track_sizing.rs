@@ -9,18 +9,19 @@ use taffy::RepetitionCount;
 use super::super::grid_errors::GridPreprocessingError;
 use super::track_counting::grid_axis_from_masonry;
 use super::virtual_placement::{GridItemInfo, create_virtual_placements_for_spanning_items};
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 /// Expand track template to exactly the specified track count
 /// Handles auto-fill/auto-fit by calculating the correct number of repetitions
-fn expand_track_template_to_count<'a, I>(
+fn expand_track_template_to_count<'a, I, Tree>(
     tracks: I,
     target_count: usize,
-    _tree: &BaseDocument,
+    _tree: &Tree,
     _node_id: NodeId,
     _masonry_axis: AbstractAxis,
 ) -> Result<Vec<taffy::TrackSizingFunction>, GridPreprocessingError>
 where
+    Tree: GridStyleAccess,
     I: Iterator<Item = taffy::GenericGridTemplateComponent<String, &'a taffy::GridTemplateRepetition<String>>> + Clone,
 {
     let mut result = Vec::new();
@@ -129,13 +130,16 @@ where
 ///
 /// The track_count parameter comes from calculate_masonry_config which properly
 /// handles auto-fill/auto-fit calculations.
-pub fn size_masonry_tracks_before_placement(
-    tree: &BaseDocument,
+pub fn size_masonry_tracks_before_placement<Tree>(
+    tree: &Tree,
     container_id: NodeId,
     inputs: &taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
     track_count: usize,
-) -> Result<Vec<f32>, GridPreprocessingError> {
+) -> Result<Vec<f32>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     use super::super::grid_errors::MasonryError;
     use taffy::GridContainerStyle;
 
@@ -150,8 +154,7 @@ pub fn size_masonry_tracks_before_placement(
     }
 
     // Step 1: Extract track template (with auto-repeat unexpanded) from the definite axis
-    let node = tree.node_from_id(container_id.into());
-    let computed_styles = node.primary_styles().ok_or_else(|| {
+    let computed_styles = tree.grid_computed_styles(container_id).ok_or_else(|| {
         GridPreprocessingError::preprocessing_failed(
             "track_definition_extraction",
             container_id.into(),
@@ -201,6 +204,7 @@ pub fn size_masonry_tracks_before_placement(
     // Step 3: ✨ NEW - Create virtual placements for spanning items
     let _virtual_placements = create_virtual_placements_for_spanning_items(
         tree,
+        container_id,
         &all_items,
         track_count,
         masonry_axis,
@@ -223,14 +227,17 @@ pub fn size_masonry_tracks_before_placement(
 
 /// Calculate track sizes using Taffy's proven grid layout algorithm
 /// Leverages Taffy's sophisticated track sizing instead of manual implementation
-fn calculate_track_sizes_from_definitions(
+fn calculate_track_sizes_from_definitions<Tree>(
     track_definitions: &[taffy::TrackSizingFunction],
     available_space: f32,
     grid_items: &[GridItemInfo],
-    tree: &BaseDocument,
+    tree: &Tree,
     inputs: &taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
-) -> Result<Vec<f32>, GridPreprocessingError> {
+) -> Result<Vec<f32>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     use taffy::ResolveOrZero;
     
     // For fixed-size tracks, use the specified sizes directly
@@ -277,14 +284,17 @@ fn calculate_track_sizes_from_definitions(
 /// Create track sizing information using Taffy patterns
 /// Since taffy::GridTrack doesn't exist, use track sizing functions directly
 #[allow(dead_code)] // Infrastructure for CSS Grid Level 3 masonry layout
-fn create_track_sizing_for_masonry(
+fn create_track_sizing_for_masonry<Tree>(
     track_definitions: &[taffy::TrackSizingFunction],
     available_space: f32,
     grid_items: &[GridItemInfo],
-    tree: &BaseDocument,
+    tree: &Tree,
     inputs: &taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
-) -> Result<Vec<f32>, GridPreprocessingError> {
+) -> Result<Vec<f32>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     // Use Taffy's proven track sizing approach instead of manual calculation
     compute_taffy_track_sizes(
         track_definitions,
@@ -319,14 +329,17 @@ fn calculate_available_space_for_masonry(
 
 /// Compute track sizes using Taffy's real grid layout algorithm
 /// Creates a minimal Taffy tree and uses compute_grid_layout to get actual track sizes
-fn compute_taffy_track_sizes(
+fn compute_taffy_track_sizes<Tree>(
     track_definitions: &[taffy::TrackSizingFunction],
     available_space: f32,
     grid_items: &[GridItemInfo],
-    tree: &BaseDocument,
+    tree: &Tree,
     inputs: &taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
-) -> Result<Vec<f32>, GridPreprocessingError> {
+) -> Result<Vec<f32>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     use taffy::TaffyTree;
 
     // Create a minimal Taffy tree to run the real grid algorithm
@@ -8,7 +8,7 @@ use taffy::prelude::NodeId;
 use taffy::MaybeResolve;
 
 use super::super::grid_errors::GridPreprocessingError;
-use crate::BaseDocument;
+use super::super::grid_style_access::GridStyleAccess;
 
 /// Virtual placement representing a spanning item at a specific position
 /// Implements CSS Grid Level 3 "every possible start position" requirement
@@ -30,6 +30,22 @@ pub struct GridItemInfo {
     pub order: usize,
     pub row_span: usize,
     pub column_span: usize,
+    /// The line the item's row placement starts at, if it is definite (1-based, negative lines
+    /// count from the end)
+    pub row_start: Option<i16>,
+    /// The line the item's column placement starts at, if it is definite
+    pub column_start: Option<i16>,
+}
+
+impl GridItemInfo {
+    /// The span and definite start line of the item in the grid axis (perpendicular to
+    /// `masonry_axis`)
+    pub fn grid_axis_placement(&self, masonry_axis: AbstractAxis) -> (usize, Option<i16>) {
+        match masonry_axis {
+            AbstractAxis::Block => (self.column_span, self.column_start),
+            AbstractAxis::Inline => (self.row_span, self.row_start),
+        }
+    }
 }
 
 /// Create virtual placements for all spanning items per CSS Grid Level 3
@@ -37,13 +53,17 @@ pub struct GridItemInfo {
 /// For each spanning item, creates virtual placements at ALL possible start positions
 /// where the item could be placed, implementing the specification requirement:
 /// "spanning items with automatic placement are assumed to be placed at every possible start position"
-pub fn create_virtual_placements_for_spanning_items(
-    tree: &BaseDocument,
+pub fn create_virtual_placements_for_spanning_items<Tree>(
+    tree: &Tree,
+    container_id: NodeId,
     items: &[GridItemInfo],
     track_count: usize,
     masonry_axis: AbstractAxis,
     inputs: &taffy::tree::LayoutInput,
-) -> Result<Vec<VirtualPlacement>, GridPreprocessingError> {
+) -> Result<Vec<VirtualPlacement>, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     let mut virtual_placements = Vec::new();
 
     for item in items {
@@ -63,7 +83,7 @@ pub fn create_virtual_placements_for_spanning_items(
             };
 
             // CSS Grid Level 3: "subtract the combined size of the gaps it would span"
-            let gap_size = calculate_gap_size_for_span(tree, span, masonry_axis)?;
+            let gap_size = calculate_gap_size_for_span(tree, container_id, span, masonry_axis)?;
             let adjusted_intrinsic_size = (base_intrinsic_size - gap_size).max(0.0);
 
             // CSS Grid Level 3: "divide by its span"
@@ -87,17 +107,21 @@ pub fn create_virtual_placements_for_spanning_items(
 
 /// Calculate gap size that a spanning item would span
 /// Per CSS Grid Level 3: "subtract the combined size of the gaps it would span"
-fn calculate_gap_size_for_span(
-    tree: &BaseDocument,
+fn calculate_gap_size_for_span<Tree>(
+    tree: &Tree,
+    container_id: NodeId,
     span: usize,
     masonry_axis: AbstractAxis,
-) -> Result<f32, GridPreprocessingError> {
+) -> Result<f32, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     if span <= 1 {
         return Ok(0.0);
     }
 
     // Extract actual gap size from computed styles using Taffy's CSS property resolution
-    let gap_per_track = extract_grid_gap_from_styles(tree, masonry_axis)?;
+    let gap_per_track = extract_grid_gap_from_styles(tree, container_id, masonry_axis)?;
     let total_gaps = (span - 1) as f32;
 
     Ok(gap_per_track * total_gaps)
@@ -105,14 +129,15 @@ fn calculate_gap_size_for_span(
 
 /// Extract grid gap from computed styles
 /// Uses CSS Grid gap properties to get actual gap values instead of hardcoded defaults
-fn extract_grid_gap_from_styles(
-    tree: &BaseDocument,
+fn extract_grid_gap_from_styles<Tree>(
+    tree: &Tree,
+    container_id: NodeId,
     masonry_axis: AbstractAxis,
-) -> Result<f32, GridPreprocessingError> {
-    // Need container context to extract gap - use root container as representative
-    let root_node = tree.root_node();
-    
-    if let Some(computed_styles) = root_node.primary_styles() {
+) -> Result<f32, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
+    if let Some(computed_styles) = tree.grid_computed_styles(container_id) {
         // Extract gap directly from computed styles
         let position_styles = computed_styles.get_position();
         let gap = match masonry_axis {
@@ -130,14 +155,17 @@ fn extract_grid_gap_from_styles(
 /// Enhanced track sizing that properly handles spanning items with virtual placement
 /// Replaces calculate_track_intrinsic_size to implement CSS Grid Level 3 specification
 #[allow(dead_code)] // Infrastructure for CSS Grid Level 3 masonry layout
-pub fn calculate_track_intrinsic_size_with_spanning(
-    tree: &BaseDocument,
+pub fn calculate_track_intrinsic_size_with_spanning<Tree>(
+    tree: &Tree,
     regular_items: &[GridItemInfo],
     virtual_placements: &[VirtualPlacement],
     track_idx: usize,
     inputs: &taffy::tree::LayoutInput,
     masonry_axis: AbstractAxis,
-) -> Result<f32, GridPreprocessingError> {
+) -> Result<f32, GridPreprocessingError>
+where
+    Tree: GridStyleAccess,
+{
     let mut max_intrinsic_size: f32 = 0.0;
 
     // Step 1: Process regular non-spanning items (span = 1)
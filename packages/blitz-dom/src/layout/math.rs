@@ -0,0 +1,258 @@
+//! Basic MathML Core layout (`<math>`, `<mrow>`, `<mfrac>`, `<msqrt>`, `<msub>`, `<msup>`,
+//! `<msubsup>`)
+//!
+//! Stylo doesn't support `display: math`, so the UA stylesheet makes `<math>` an `inline-block`
+//! and the other MathML elements `block`s. This module then positions the children of the
+//! elements flagged with [`NodeFlags::IS_MATH_LAYOUT`](crate::node::NodeFlags):
+//!
+//! - `<math>`, `<mrow>` and `<msqrt>` place their children in a row, aligned on their baselines.
+//!   The radical sign and its overbar come from the UA stylesheet.
+//! - `<mfrac>` stacks its numerator over its denominator around the math axis. The fraction bar is
+//!   the numerator's bottom border, which spans the fraction as both are laid out at its width.
+//! - `<msub>`, `<msup>` and `<msubsup>` place their scripts after the base, lowered and raised
+//!   from its baseline.
+//!
+//! Token elements (`<mi>`, `<mn>`, `<mo>` etc.) are ordinary inline contexts. Shifts and gaps are
+//! fixed fractions of the font size rather than values from the font's MATH table, and elements
+//! with the wrong number of children are laid out as rows.
+//! See <https://w3c.github.io/mathml-core/#layout-algorithms>.
+
+use markup5ever::{local_name, ns};
+use taffy::{
+    Layout, LayoutInput, LayoutOutput, LayoutPartialTree as _, Line, NodeId, Point,
+    RequestedAxis, ResolveOrZero as _, RunMode, Size,
+};
+
+use super::resolve_calc_value;
+use super::stylo_to_blitz::font_metrics;
+use crate::{BaseDocument, Node};
+
+/// Height of the math axis (the centre of fraction bars) above the baseline, in ems
+const AXIS_HEIGHT: f32 = 0.25;
+/// How far a superscript's baseline is raised above the base's, in ems
+const SUPERSCRIPT_SHIFT_UP: f32 = 0.4;
+/// How far a subscript's baseline is lowered below the base's, in ems
+const SUBSCRIPT_SHIFT_DOWN: f32 = 0.25;
+
+/// How a MathML layout element arranges its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MathLayoutKind {
+    Row,
+    Fraction,
+    Scripts { sub: bool, sup: bool },
+}
+
+/// A measured child of a MathML layout element
+#[derive(Debug, Clone, Copy)]
+struct MathItem {
+    id: usize,
+    output: LayoutOutput,
+    /// Distance from the top of the child to its baseline
+    baseline: f32,
+    /// Position of the child, relative to the start of the element's baseline
+    x: f32,
+    top: f32,
+}
+
+/// Whether the children of `node` are positioned by [`BaseDocument::compute_math_layout`]
+pub(crate) fn is_math_layout_element(node: &Node) -> bool {
+    node.element_data().is_some_and(|element| {
+        element.name.ns == ns!(mathml)
+            && matches!(
+                element.name.local,
+                local_name!("math")
+                    | local_name!("mrow")
+                    | local_name!("msqrt")
+                    | local_name!("mfrac")
+                    | local_name!("msub")
+                    | local_name!("msup")
+                    | local_name!("msubsup")
+            )
+    })
+}
+
+impl BaseDocument {
+    pub(crate) fn compute_math_layout(
+        &mut self,
+        node_id: usize,
+        inputs: LayoutInput,
+    ) -> LayoutOutput {
+        let style = self.nodes[node_id].style().clone();
+        let padding = style
+            .padding
+            .resolve_or_zero(inputs.parent_size, resolve_calc_value);
+        let border = style
+            .border
+            .resolve_or_zero(inputs.parent_size, resolve_calc_value);
+        let pb = padding + border;
+        let font_size = self.nodes[node_id]
+            .primary_styles()
+            .map_or(16.0, |s| font_metrics(&s).font_size);
+
+        let children = self.nodes[node_id]
+            .layout_children
+            .borrow()
+            .clone()
+            .unwrap_or_default();
+        let visible: Vec<usize> = children
+            .iter()
+            .copied()
+            .filter(|&id| {
+                self.nodes[id].style().display != taffy::Display::None
+                    && !self.is_whitespace_block(id)
+            })
+            .collect();
+
+        // Measure every child at its max-content size
+        let child_inputs = LayoutInput {
+            known_dimensions: Size::NONE,
+            parent_size: Size::NONE,
+            available_space: Size::MAX_CONTENT,
+            axis: RequestedAxis::Both,
+            vertical_margins_are_collapsible: Line::FALSE,
+            ..inputs
+        };
+        let mut items: Vec<MathItem> = visible
+            .iter()
+            .map(|&id| {
+                let output = self.compute_child_layout(NodeId::from(id), child_inputs);
+                MathItem {
+                    id,
+                    output,
+                    baseline: self.math_baseline(id, &output),
+                    x: 0.0,
+                    top: 0.0,
+                }
+            })
+            .collect();
+
+        match self.math_layout_kind(node_id) {
+            MathLayoutKind::Fraction if items.len() == 2 => {
+                let width = items
+                    .iter()
+                    .map(|item| item.output.size.width)
+                    .fold(0.0, f32::max);
+                // Lay out the numerator and denominator at the width of the fraction, so that
+                // `text-align` centres them and the numerator's border spans the fraction
+                let inputs = LayoutInput {
+                    known_dimensions: Size {
+                        width: Some(width),
+                        height: None,
+                    },
+                    ..child_inputs
+                };
+                for item in &mut items {
+                    item.output = self.compute_child_layout(NodeId::from(item.id), inputs);
+                }
+
+                let axis = AXIS_HEIGHT * font_size;
+                items[0].top = -axis - items[0].output.size.height;
+                items[1].top = -axis;
+            }
+            MathLayoutKind::Scripts { sub, sup }
+                if items.len() == 1 + sub as usize + sup as usize =>
+            {
+                let (base, scripts) = items.split_first_mut().unwrap();
+                base.top = -base.baseline;
+                for (idx, script) in scripts.iter_mut().enumerate() {
+                    // The subscript of an `msubsup` comes before its superscript
+                    script.x = base.output.size.width;
+                    script.top = match sub && idx == 0 {
+                        true => SUBSCRIPT_SHIFT_DOWN * font_size - script.baseline,
+                        false => -SUPERSCRIPT_SHIFT_UP * font_size - script.baseline,
+                    };
+                }
+            }
+            _ => {
+                let mut x = 0.0;
+                for item in &mut items {
+                    item.x = x;
+                    item.top = -item.baseline;
+                    x += item.output.size.width;
+                }
+            }
+        }
+
+        // Shift everything down so that the highest child is at the top of the content box
+        let ascent = items.iter().map(|item| -item.top).fold(0.0, f32::max);
+        let content_size = Size {
+            width: items
+                .iter()
+                .map(|item| item.x + item.output.size.width)
+                .fold(0.0, f32::max),
+            height: items
+                .iter()
+                .map(|item| ascent + item.top + item.output.size.height)
+                .fold(0.0, f32::max),
+        };
+        let size = Size {
+            width: inputs
+                .known_dimensions
+                .width
+                .unwrap_or(content_size.width + pb.left + pb.right),
+            height: inputs
+                .known_dimensions
+                .height
+                .unwrap_or(content_size.height + pb.top + pb.bottom),
+        };
+        let first_baselines = Point {
+            x: None,
+            y: (!items.is_empty()).then_some(pb.top + ascent),
+        };
+
+        if inputs.run_mode == RunMode::PerformLayout {
+            for (order, item) in items.iter().enumerate() {
+                let mut layout = Layout::with_order(order as u32);
+                layout.location = Point {
+                    x: pb.left + item.x,
+                    y: pb.top + ascent + item.top,
+                };
+                layout.size = item.output.size;
+                layout.content_size = item.output.content_size;
+                self.set_unrounded_layout(NodeId::from(item.id), &layout);
+            }
+
+            // Hidden children and whitespace still need a layout
+            for child_id in children {
+                if !visible.contains(&child_id) {
+                    taffy::compute_hidden_layout(self, NodeId::from(child_id));
+                }
+            }
+        }
+
+        LayoutOutput::from_sizes_and_baselines(size, content_size, first_baselines)
+    }
+
+    fn math_layout_kind(&self, node_id: usize) -> MathLayoutKind {
+        let Some(element) = self.nodes[node_id].element_data() else {
+            return MathLayoutKind::Row;
+        };
+        match element.name.local {
+            local_name!("mfrac") => MathLayoutKind::Fraction,
+            local_name!("msub") => MathLayoutKind::Scripts {
+                sub: true,
+                sup: false,
+            },
+            local_name!("msup") => MathLayoutKind::Scripts {
+                sub: false,
+                sup: true,
+            },
+            local_name!("msubsup") => MathLayoutKind::Scripts {
+                sub: true,
+                sup: true,
+            },
+            _ => MathLayoutKind::Row,
+        }
+    }
+
+    /// The distance from the top of a measured child to its baseline. Token elements are inline
+    /// contexts, whose baseline is that of their first line. Children without a baseline sit on
+    /// the baseline, like inline-blocks.
+    fn math_baseline(&self, node_id: usize, output: &LayoutOutput) -> f32 {
+        output
+            .first_baselines
+            .y
+            .or_else(|| self.inline_first_baseline(node_id, None))
+            .unwrap_or(output.size.height)
+    }
+}
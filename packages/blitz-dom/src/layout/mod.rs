@@ -8,20 +8,31 @@ use style::values::computed::CSSPixelLength;
 use style::values::computed::length_percentage::CalcLengthPercentage;
 
 // Core layout modules
+pub(crate) mod aspect_ratio;
+pub(crate) mod baseline;
 pub(crate) mod collect_inline_text;
 pub(crate) mod construct;
+pub(crate) mod container_queries;
+pub(crate) mod generated_content;
+pub(crate) mod containment;
 pub(crate) mod inline;
 pub(crate) mod intrinsic_sizing;
+pub(crate) mod math;
 pub(crate) mod replaced;
+pub(crate) mod ruby;
 pub(crate) mod style_cache;
 pub(crate) mod stylo_to_blitz;
-pub(crate) mod table;
+pub mod table;
+#[cfg(feature = "layout-trace")]
+pub mod trace;
 
 // Decomposed layout modules
 pub mod grid_context;
 pub mod grid_coordination;
+pub(crate) mod grid_contributions;
 pub(crate) mod grid_errors;
 pub(crate) mod grid_preprocessing;
+pub mod grid_style_access;
 pub(crate) mod layout_traits;
 pub(crate) mod masonry;
 pub mod subgrid;
@@ -33,6 +44,10 @@ pub(crate) mod tree_iteration;
 // Export grid layout coordinator from decomposed modules
 // Export grid context types directly
 pub use grid_context::ParentGridContext;
+pub use grid_errors::GridPreprocessingError;
+pub use grid_style_access::GridStyleAccess;
+pub use masonry::auto_flow::MasonryAutoFlow;
+pub use stylo_to_blitz::text_writing_mode;
 pub use grid_coordination::{
     AutoPlacementState, DensePackingState, GridArea, GridLayoutCoordinator, GridPosition,
     InheritedTrackDefinitions, IntrinsicSizeContribution, IntrinsicSizingState, ItemPlacement,
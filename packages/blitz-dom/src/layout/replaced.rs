@@ -1,11 +1,37 @@
 use taffy::{BoxSizing, CoreStyle as _, MaybeMath, MaybeResolve, ResolveOrZero as _, Size};
 
 use crate::layout::resolve_calc_value;
+use crate::node::{ElementData, Node, SpecialElementData};
 
 #[derive(Debug, Clone, Copy)]
 pub struct ReplacedContext {
     pub inherent_size: taffy::Size<f32>,
     pub attr_size: taffy::Size<Option<f32>>,
+    /// Whether the natural aspect ratio is used over the style's preferred aspect ratio
+    pub prefers_natural_ratio: bool,
+}
+
+/// Whether an element is replaced (or a form control, which is laid out like one). Replaced
+/// elements are laid out even without children, and don't contribute text to inline layout.
+pub(crate) fn is_replaced_element(element: &ElementData) -> bool {
+    matches!(
+        element.special_data,
+        SpecialElementData::Image(_)
+            | SpecialElementData::Canvas(_)
+            | SpecialElementData::TextInput(_)
+            | SpecialElementData::CheckboxInput(_)
+    ) || matches!(
+        element.name.local.as_ref(),
+        "canvas" | "img" | "svg" | "input" | "textarea" | "button"
+    )
+}
+
+/// Whether the node's `aspect-ratio` lets its natural aspect ratio take precedence over the
+/// preferred aspect ratio from style
+pub(crate) fn prefers_natural_ratio(node: &Node) -> bool {
+    node.primary_styles().is_none_or(|styles| {
+        stylo_taffy::convert::prefers_natural_aspect_ratio(styles.get_position().aspect_ratio)
+    })
 }
 
 /// Whether a height/width value is violating it's min- and max- constraints
@@ -46,9 +72,15 @@ pub fn replaced_measure_function(
         Size::ZERO
     };
 
-    // Use aspect_ratio from style, fall back to inherent aspect ratio
-    let s_aspect_ratio = style.aspect_ratio;
-    let aspect_ratio = s_aspect_ratio.unwrap_or_else(|| inherent_size.width / inherent_size.height);
+    // `aspect-ratio: auto && <ratio>` uses the inherent aspect ratio when there is one, and
+    // otherwise the aspect ratio from style. A plain `<ratio>` always uses the style's ratio.
+    let inherent_ratio = (inherent_size.width > 0.0 && inherent_size.height > 0.0)
+        .then(|| inherent_size.width / inherent_size.height);
+    let aspect_ratio = match (inherent_ratio, style.aspect_ratio) {
+        (Some(ratio), _) if image_context.prefers_natural_ratio => ratio,
+        (_, Some(ratio)) => ratio,
+        _ => inherent_size.width / inherent_size.height,
+    };
     let inv_aspect_ratio = 1.0 / aspect_ratio;
 
     // Resolve sizes
@@ -0,0 +1,269 @@
+//! Ruby annotation layout (`<ruby>`, `<rt>`)
+//!
+//! Stylo (in servo mode) does not support the `ruby-*` display types, so the UA stylesheet makes
+//! `<ruby>` an `inline-block` and `<rt>` a `block`. Box construction then wraps each run of base
+//! content in an anonymous block, leaving the ruby container with layout children of the form
+//! `base, rt, base, rt, ...`. This module pairs those children up and positions every annotation
+//! over its base (above in horizontal writing modes, to the right in vertical ones).
+//!
+//! An annotation which is wider than its base is centered over it, and may overhang a neighbouring
+//! base that has no annotation of its own by up to half the annotation's line height. Any excess
+//! beyond that widens the pair. See <https://drafts.csswg.org/css-ruby/#ruby-overhang>.
+
+use blitz_text::WritingMode;
+use markup5ever::local_name;
+use taffy::{
+    Layout, LayoutInput, LayoutOutput, LayoutPartialTree as _, Line, NodeId, Point,
+    RequestedAxis, ResolveOrZero as _, RunMode, Size,
+};
+
+use super::resolve_calc_value;
+use super::stylo_to_blitz::text_writing_mode;
+use crate::{BaseDocument, NodeData};
+
+/// A base and the annotation which sits over it. Either may be missing.
+#[derive(Debug, Clone, Copy, Default)]
+struct RubyPair {
+    base: Option<usize>,
+    annotation: Option<usize>,
+}
+
+/// The measured size of a child, in logical (inline, block) coordinates
+#[derive(Debug, Clone, Copy, Default)]
+struct LogicalSize {
+    inline: f32,
+    block: f32,
+}
+
+/// A measured child of a ruby container
+#[derive(Debug, Clone, Copy)]
+struct RubyItem {
+    output: LayoutOutput,
+    size: LogicalSize,
+}
+
+impl BaseDocument {
+    pub(crate) fn compute_ruby_layout(
+        &mut self,
+        node_id: usize,
+        inputs: LayoutInput,
+    ) -> LayoutOutput {
+        let vertical = self.nodes[node_id]
+            .primary_styles()
+            .is_some_and(|s| text_writing_mode(&s).0 != WritingMode::HorizontalTopBottom);
+        let style = self.nodes[node_id].style().clone();
+        let padding = style
+            .padding
+            .resolve_or_zero(inputs.parent_size, resolve_calc_value);
+        let border = style
+            .border
+            .resolve_or_zero(inputs.parent_size, resolve_calc_value);
+        let pb = padding + border;
+
+        let children = self.nodes[node_id]
+            .layout_children
+            .borrow()
+            .clone()
+            .unwrap_or_default();
+        let pairs = self.ruby_pairs(&children);
+
+        // Measure every child at its max-content size
+        let child_inputs = LayoutInput {
+            known_dimensions: Size::NONE,
+            parent_size: Size::NONE,
+            available_space: Size::MAX_CONTENT,
+            axis: RequestedAxis::Both,
+            vertical_margins_are_collapsible: Line::FALSE,
+            ..inputs
+        };
+        let measure = |doc: &mut Self, id: Option<usize>| -> RubyItem {
+            let Some(id) = id else {
+                return RubyItem {
+                    output: LayoutOutput::HIDDEN,
+                    size: LogicalSize::default(),
+                };
+            };
+            let output = doc.compute_child_layout(NodeId::from(id), child_inputs);
+            let size = match vertical {
+                true => LogicalSize {
+                    inline: output.size.height,
+                    block: output.size.width,
+                },
+                false => LogicalSize {
+                    inline: output.size.width,
+                    block: output.size.height,
+                },
+            };
+            RubyItem { output, size }
+        };
+        let measured: Vec<(RubyItem, RubyItem)> = pairs
+            .iter()
+            .map(|pair| (measure(self, pair.base), measure(self, pair.annotation)))
+            .collect();
+
+        let annotation_block = measured
+            .iter()
+            .map(|(_, annotation)| annotation.size.block)
+            .fold(0.0, f32::max);
+        let base_block = measured
+            .iter()
+            .map(|(base, _)| base.size.block)
+            .fold(0.0, f32::max);
+
+        // Position pairs along the inline axis
+        let mut placements = Vec::with_capacity(pairs.len());
+        let mut cursor = 0.0;
+        for (idx, (base, annotation)) in measured.iter().enumerate() {
+            let (base, annotation) = (base.size, annotation.size);
+            let excess = (annotation.inline - base.inline).max(0.0);
+            let max_overhang = (excess / 2.0).min(annotation.block / 2.0);
+            let can_overhang = |neighbour: Option<&RubyPair>| {
+                neighbour.is_some_and(|pair| pair.base.is_some() && pair.annotation.is_none())
+            };
+            let overhang_start = match idx > 0 && can_overhang(pairs.get(idx - 1)) {
+                true => max_overhang,
+                false => 0.0,
+            };
+            let overhang_end = match can_overhang(pairs.get(idx + 1)) {
+                true => max_overhang,
+                false => 0.0,
+            };
+
+            let width = base.inline.max(annotation.inline - overhang_start - overhang_end);
+            let base_start = cursor + (width - base.inline) / 2.0;
+            let annotation_start = (base_start + (base.inline - annotation.inline) / 2.0)
+                .max(cursor - overhang_start)
+                .min(cursor + width + overhang_end - annotation.inline);
+            placements.push((base_start, annotation_start));
+            cursor += width;
+        }
+        let content_inline = cursor;
+        let content_block = annotation_block + base_block;
+
+        let content_size = match vertical {
+            true => Size {
+                width: content_block,
+                height: content_inline,
+            },
+            false => Size {
+                width: content_inline,
+                height: content_block,
+            },
+        };
+        let size = Size {
+            width: inputs
+                .known_dimensions
+                .width
+                .unwrap_or(content_size.width + pb.left + pb.right),
+            height: inputs
+                .known_dimensions
+                .height
+                .unwrap_or(content_size.height + pb.top + pb.bottom),
+        };
+
+        // The baseline of the ruby container is the baseline of its first base
+        let first_baseline = measured
+            .iter()
+            .find_map(|(base, _)| base.output.first_baselines.y);
+        let first_baselines = Point {
+            x: None,
+            y: first_baseline.filter(|_| !vertical).map(|y| y + annotation_block + pb.top),
+        };
+
+        if inputs.run_mode == RunMode::PerformLayout {
+            // Map a logical position (block offset measured from the annotation side) to a
+            // physical one. In vertical writing modes annotations are on the right.
+            let physical = |inline: f32, block: f32, item: LogicalSize| match vertical {
+                true => Point {
+                    x: pb.left + content_block - block - item.block,
+                    y: pb.top + inline,
+                },
+                false => Point {
+                    x: pb.left + inline,
+                    y: pb.top + block,
+                },
+            };
+
+            for (order, (pair, (&(base_start, annotation_start), (base, annotation)))) in pairs
+                .iter()
+                .zip(placements.iter().zip(measured.iter()))
+                .enumerate()
+            {
+                if let Some(id) = pair.annotation {
+                    let location = physical(annotation_start, 0.0, annotation.size);
+                    self.place_ruby_child(id, order as u32, location, annotation.output.size);
+                }
+                if let Some(id) = pair.base {
+                    let location = physical(base_start, annotation_block, base.size);
+                    self.place_ruby_child(id, order as u32, location, base.output.size);
+                }
+            }
+
+            // Hidden children (e.g. `<rp>`) still need a layout
+            for child_id in children {
+                if !pairs
+                    .iter()
+                    .any(|pair| pair.base == Some(child_id) || pair.annotation == Some(child_id))
+                {
+                    taffy::compute_hidden_layout(self, NodeId::from(child_id));
+                }
+            }
+        }
+
+        LayoutOutput::from_sizes_and_baselines(size, content_size, first_baselines)
+    }
+
+    /// Group the layout children of a ruby container into base/annotation pairs
+    fn ruby_pairs(&self, children: &[usize]) -> Vec<RubyPair> {
+        let mut pairs: Vec<RubyPair> = Vec::new();
+        for &child_id in children {
+            let node = &self.nodes[child_id];
+            if node.style().display == taffy::Display::None || self.is_whitespace_block(child_id) {
+                continue;
+            }
+
+            if node.data.is_element_with_tag_name(&local_name!("rt")) {
+                match pairs.last_mut() {
+                    Some(pair) if pair.annotation.is_none() => pair.annotation = Some(child_id),
+                    // An annotation without a base of its own
+                    _ => pairs.push(RubyPair {
+                        base: None,
+                        annotation: Some(child_id),
+                    }),
+                }
+            } else {
+                pairs.push(RubyPair {
+                    base: Some(child_id),
+                    annotation: None,
+                });
+            }
+        }
+        pairs
+    }
+
+    /// Whether `node_id` is an anonymous block containing nothing but whitespace (i.e. the gaps
+    /// between `</rt>` and the next base in the source)
+    pub(super) fn is_whitespace_block(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+        matches!(node.data, NodeData::AnonymousBlock(_))
+            && node.children.iter().all(|child_id| {
+                self.nodes[*child_id]
+                    .text_data()
+                    .is_some_and(|text| text.content.chars().all(|c| c.is_ascii_whitespace()))
+            })
+    }
+
+    fn place_ruby_child(
+        &mut self,
+        node_id: usize,
+        order: u32,
+        location: Point<f32>,
+        size: Size<f32>,
+    ) {
+        let mut layout = Layout::with_order(order);
+        layout.location = location;
+        layout.size = size;
+        layout.content_size = size;
+        self.set_unrounded_layout(NodeId::from(node_id), &layout);
+    }
+}
@@ -6,11 +6,13 @@
 //!
 //! Uses generation tracking to invalidate cached styles only when necessary.
 
+use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use taffy::{NodeId, Style};
 use stylo_taffy::GridContext;
 use style::values::specified::box_::DisplayInside;
 use crate::BaseDocument;
+use crate::scrollbar::computed_scrollbar_style;
 
 /// Error types for style cache operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -88,7 +90,7 @@ impl BaseDocument {
         
         // Convert stylo style to taffy style with conditional grid context support
         // This matches the exact logic from flush_styles_to_layout for consistency
-        let new_taffy_style = if let Some(ref grid_ctx) = grid_context {
+        let mut new_taffy_style = if let Some(grid_ctx) = grid_context.as_deref() {
             stylo_taffy::to_taffy_style_with_grid_context(
                 primary_styles,
                 &device,
@@ -98,6 +100,8 @@ impl BaseDocument {
         } else {
             stylo_taffy::to_taffy_style_with_device(primary_styles, &device)
         };
+        let scrollbar_width = computed_scrollbar_style(&self.stylist, primary_styles).width;
+        new_taffy_style.scrollbar_width = self.scrollbar_mode.gutter(scrollbar_width);
         
         // Safely update the style using interior mutability
         // SAFETY: This unsafe operation is justified by the following invariants:
@@ -158,7 +162,7 @@ impl BaseDocument {
     /// This implements efficient grid context detection with zero allocation
     /// and matches the grid detection logic from flush_styles_to_layout.
     #[inline]
-    fn detect_grid_context_for_node(&self, node_id: usize) -> Option<GridContext> {
+    fn detect_grid_context_for_node(&self, node_id: usize) -> Option<Arc<GridContext>> {
         // Get parent node if it exists
         let node = self.nodes.get(node_id)?;
         let parent_id = node.parent?;
@@ -176,8 +180,12 @@ impl BaseDocument {
         
         // Use identical grid detection logic as in flush_styles_to_layout
         if display.inside() == DisplayInside::Grid {
-            // Create grid context using the same logic as create_grid_context_for_children
-            self.create_grid_context_for_children(parent_id)
+            // Reuse the context created when styles were last flushed to layout, rather than
+            // converting the parent's templates again for each of its items
+            match self.grid_child_contexts.get(&parent_id) {
+                Some(context) => Some(context.clone()),
+                None => self.create_grid_context_for_children(parent_id).map(Arc::new),
+            }
         } else {
             None
         }
@@ -3,14 +3,16 @@
 // Converts CSS ComputedValues to cosmyc_text attributes
 
 use blitz_text::{
-    AttrsOwned, CacheKeyFlags, Family, FamilyOwned, FontFeatures, Metrics, Stretch,
-    Style as FontStyle, Weight, Wrap,
+    Align, Attrs, AttrsOwned, CacheKeyFlags, CssFontFeatures, Family, FamilyOwned, FontFeatures,
+    FontVariantCaps, Metrics, Stretch, Style as FontStyle, TextOrientation, TextSpacing, Weight,
+    Wrap, WritingMode,
 };
 use style::properties::ComputedValues;
+use style::properties::style_structs::Font;
 use style::properties::longhands::list_style_type::computed_value::T as ListStyleType;
 use style::properties::longhands::white_space_collapse::computed_value::T as WhiteSpaceCollapse;
 use style::values::computed::font::LineHeight;
-use style::values::computed::{FontStyle as StyleFontStyle, FontWeight};
+use style::values::computed::{CSSPixelLength, FontStyle as StyleFontStyle, FontWeight};
 
 /// Convert Stylo ComputedValues to cosmyc_text::Attrs
 /// Zero-allocation conversion using references where possible
@@ -20,49 +22,7 @@ pub fn style(node_id: usize, computed: &ComputedValues) -> CosmicStyle {
     let text = computed.get_inherited_text();
     let color = text.clone_color();
 
-    // Extract font family with zero allocation
-    let family = match font.font_family.families.iter().next() {
-        Some(family) => {
-            use style::values::computed::font::SingleFontFamily;
-            match family {
-                SingleFontFamily::Generic(generic) => {
-                    use style::values::computed::font::GenericFontFamily;
-                    match generic {
-                        GenericFontFamily::Serif => Family::Serif,
-                        GenericFontFamily::SansSerif => Family::SansSerif,
-                        GenericFontFamily::Monospace => Family::Monospace,
-                        GenericFontFamily::Cursive => Family::Cursive,
-                        GenericFontFamily::Fantasy => Family::Fantasy,
-                        _ => Family::SansSerif,
-                    }
-                }
-                SingleFontFamily::FamilyName(name) => Family::Name(name.name.as_ref()),
-            }
-        }
-        _ => Family::SansSerif,
-    };
-
-    // Convert font weight - optimized for common cases
-    let weight = match font.font_weight {
-        FontWeight::NORMAL => Weight::NORMAL,
-        FontWeight::BOLD => Weight::BOLD,
-        weight => Weight(weight.value() as u16),
-    };
-
-    // Convert font style - using stylo's constants
-    let style = if font.font_style == StyleFontStyle::NORMAL {
-        FontStyle::Normal
-    } else if font.font_style == StyleFontStyle::ITALIC {
-        FontStyle::Italic
-    } else {
-        FontStyle::Oblique // For oblique angles
-    };
-
-    // Extract font size in pixels
-    let font_size_px = font.font_size.computed_size.px();
-
-    // Convert line height efficiently
-    let line_height = convert_line_height(&font.line_height, font_size_px);
+    let font_attrs = font_attrs(font);
 
     // Extract text color using stylo's color space conversion
     let srgb = color.to_color_space(style::color::ColorSpace::Srgb);
@@ -108,31 +68,105 @@ pub fn style(node_id: usize, computed: &ComputedValues) -> CosmicStyle {
                     _ => Wrap::None,
                 }
             }
-            WhiteSpaceCollapse::BreakSpaces => Wrap::WordOrGlyph,
+            // Lines are also broken after preserved spaces which would overflow them, when the
+            // inline layout is broken into lines (see `TextLayout::break_all_lines`)
+            WhiteSpaceCollapse::BreakSpaces => Wrap::Word,
         }
     };
 
     CosmicStyle {
         attrs: AttrsOwned {
             color_opt: Some(text_color),
-            family_owned: FamilyOwned::new(family),
+            family_owned: FamilyOwned::new(font_attrs.family),
             stretch: Stretch::Normal,
-            style,
-            weight,
+            style: font_attrs.style,
+            weight: font_attrs.weight,
             metadata: node_id,
             cache_key_flags: CacheKeyFlags::empty(),
             metrics_opt: None,
             letter_spacing_opt: None,
-            font_features: FontFeatures::new(),
-        },
-        metrics: Metrics {
-            font_size: font_size_px,
-            line_height,
+            font_features: font_features(computed).to_font_features(),
         },
+        metrics: font_metrics(computed),
         wrap,
     }
 }
 
+/// The attributes fonts are matched with for a font style: its first family, weight and style
+pub fn font_attrs(font: &Font) -> Attrs<'_> {
+    // Extract font family with zero allocation
+    let family = match font.font_family.families.iter().next() {
+        Some(family) => {
+            use style::values::computed::font::SingleFontFamily;
+            match family {
+                SingleFontFamily::Generic(generic) => {
+                    use style::values::computed::font::GenericFontFamily;
+                    match generic {
+                        GenericFontFamily::Serif => Family::Serif,
+                        GenericFontFamily::SansSerif => Family::SansSerif,
+                        GenericFontFamily::Monospace => Family::Monospace,
+                        GenericFontFamily::Cursive => Family::Cursive,
+                        GenericFontFamily::Fantasy => Family::Fantasy,
+                        _ => Family::SansSerif,
+                    }
+                }
+                SingleFontFamily::FamilyName(name) => Family::Name(name.name.as_ref()),
+            }
+        }
+        _ => Family::SansSerif,
+    };
+
+    // Convert font weight - optimized for common cases
+    let weight = match font.font_weight {
+        FontWeight::NORMAL => Weight::NORMAL,
+        FontWeight::BOLD => Weight::BOLD,
+        weight => Weight(weight.value() as u16),
+    };
+
+    // Convert font style - using stylo's constants
+    let style = if font.font_style == StyleFontStyle::NORMAL {
+        FontStyle::Normal
+    } else if font.font_style == StyleFontStyle::ITALIC {
+        FontStyle::Italic
+    } else {
+        FontStyle::Oblique // For oblique angles
+    };
+
+    Attrs::new().family(family).weight(weight).style(style)
+}
+
+/// Convert the computed `font-size` and `line-height` of an element to pixels
+#[inline(always)]
+pub fn font_metrics(computed: &ComputedValues) -> Metrics {
+    let font = computed.get_font();
+    let font_size = font.font_size.computed_size.px();
+    Metrics {
+        font_size,
+        line_height: convert_line_height(&font.line_height, font_size),
+    }
+}
+
+/// Convert the computed `vertical-align` of an inline element to the distance its baseline is
+/// raised above its parent's baseline, in pixels
+///
+/// `sub` and `super` are relative to the parent's font size and lengths are relative to the
+/// element's line height. The keywords which align with the line box rather than the parent
+/// (`top`, `middle` etc.) aren't supported by inline layout, so don't shift the baseline.
+#[inline(always)]
+pub fn baseline_shift(computed: &ComputedValues, parent_font_size: f32) -> f32 {
+    use style::values::generics::box_::{GenericVerticalAlign, VerticalAlignKeyword};
+
+    match computed.get_box().clone_vertical_align() {
+        GenericVerticalAlign::Keyword(VerticalAlignKeyword::Super) => parent_font_size / 3.0,
+        GenericVerticalAlign::Keyword(VerticalAlignKeyword::Sub) => -parent_font_size / 5.0,
+        GenericVerticalAlign::Keyword(_) => 0.0,
+        GenericVerticalAlign::Length(length) => {
+            let line_height = font_metrics(computed).line_height;
+            length.resolve(CSSPixelLength::new(line_height)).px()
+        }
+    }
+}
+
 /// Wrapper for cosmyc text style components
 pub struct CosmicStyle {
     pub attrs: AttrsOwned,
@@ -211,3 +245,105 @@ pub fn font_for_bullet(style_type: ListStyleType) -> Option<Family<'static>> {
         _ => None,
     }
 }
+
+/// Convert the computed `tab-size` of an element to a number of spaces
+///
+/// cosmyc-text measures tab stops in spaces, so lengths are converted with the usual width of a
+/// space (a quarter of an em).
+#[inline(always)]
+pub fn tab_width(computed: &ComputedValues) -> u16 {
+    use style::values::generics::length::LengthOrNumber;
+
+    let spaces = match computed.get_inherited_text().clone_tab_size() {
+        LengthOrNumber::Number(number) => number.0,
+        LengthOrNumber::Length(length) => {
+            let space_width = computed.get_font().font_size.computed_size.px() * 0.25;
+            length.0.px() / space_width.max(f32::EPSILON)
+        }
+    };
+    spaces.round().clamp(1.0, u16::MAX as f32) as u16
+}
+
+/// Convert the computed `writing-mode` and `text-orientation` of an element
+#[inline(always)]
+pub fn text_writing_mode(computed: &ComputedValues) -> (WritingMode, TextOrientation) {
+    let writing_mode = computed.writing_mode;
+    let mode = if !writing_mode.is_vertical() {
+        WritingMode::HorizontalTopBottom
+    } else if writing_mode.is_vertical_lr() {
+        WritingMode::VerticalLeftRight
+    } else {
+        WritingMode::VerticalRightLeft
+    };
+    let orientation = if writing_mode.is_upright() {
+        TextOrientation::Upright
+    } else if writing_mode.is_sideways() {
+        TextOrientation::Sideways
+    } else {
+        TextOrientation::Mixed
+    };
+    (mode, orientation)
+}
+
+/// Convert the computed `text-align` and `text-justify` of an element
+#[inline(always)]
+pub fn text_alignment(computed: &ComputedValues) -> Align {
+    use style::properties::longhands::text_justify::computed_value::T as TextJustify;
+    use style::values::specified::TextAlignKeyword;
+
+    match computed.clone_text_align() {
+        TextAlignKeyword::Start | TextAlignKeyword::Left | TextAlignKeyword::MozLeft => {
+            Align::Left
+        }
+        TextAlignKeyword::Right | TextAlignKeyword::MozRight => Align::Right,
+        TextAlignKeyword::Center | TextAlignKeyword::MozCenter => Align::Center,
+        // `text-justify: none` disables justification, leaving the text start-aligned
+        TextAlignKeyword::Justify => match computed.get_inherited_text().clone_text_justify() {
+            TextJustify::None => Align::Left,
+            _ => Align::Justified,
+        },
+        TextAlignKeyword::End => Align::Right,
+    }
+}
+
+/// Convert the computed `letter-spacing` and `word-spacing` of an element to pixels
+#[inline(always)]
+pub fn text_spacing(computed: &ComputedValues) -> TextSpacing {
+    let text = computed.get_inherited_text();
+    // Percentages are relative to the font size
+    let font_size = CSSPixelLength::new(computed.get_font().font_size.computed_size.px());
+    TextSpacing {
+        letter_spacing: text.letter_spacing.0.resolve(font_size).px(),
+        word_spacing: text.word_spacing.resolve(font_size).px(),
+    }
+}
+
+/// Convert the computed font feature properties of an element
+///
+/// Stylo only supports the `normal` and `small-caps` values of `font-variant-caps` in servo mode,
+/// and treats `font-variant-numeric` and `font-variant-ligatures` as Gecko-only properties, so
+/// those are left at their initial values. Their features (e.g. `tnum` for tabular numbers) can
+/// still be enabled through `font-feature-settings`.
+#[inline(always)]
+pub fn font_features(computed: &ComputedValues) -> CssFontFeatures {
+    use style::properties::longhands::font_variant_caps::computed_value::T as StyleFontVariantCaps;
+
+    let font = computed.get_font();
+    let caps = match font.font_variant_caps {
+        StyleFontVariantCaps::SmallCaps => FontVariantCaps::SmallCaps,
+        _ => FontVariantCaps::Normal,
+    };
+    // Tags are stored as big-endian integers
+    let settings = font
+        .font_feature_settings
+        .0
+        .iter()
+        .map(|setting| (setting.tag.0.to_be_bytes(), setting.value.max(0) as u32))
+        .collect();
+
+    CssFontFeatures {
+        caps,
+        settings,
+        ..Default::default()
+    }
+}
@@ -44,6 +44,7 @@ pub use types::{
 // Legacy imports for compatibility
 use super::grid_context::ParentGridContext;
 use super::grid_errors::{SubgridError, SubgridResult};
+use super::grid_style_access::GridStyleAccess;
 
 /// Complete nested subgrid coordination implementing CSS Grid Level 2 multi-level inheritance
 ///
@@ -57,7 +58,7 @@ pub fn coordinate_nested_subgrids<Tree>(
     nesting_depth: usize,
 ) -> SubgridResult<NestedSubgridCoordination>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
     const MAX_SUBGRID_NESTING_DEPTH: usize = 10; // Prevent infinite recursion
 
@@ -103,7 +104,7 @@ fn process_current_subgrid_level<Tree>(
     coordination: &mut NestedSubgridCoordination,
 ) -> SubgridResult<()>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
     use super::grid_coordination::types::GridLayoutCoordinator;
 
@@ -122,49 +123,46 @@ where
     let replaced_templates = coordinator.replace_grid_template_properties(subgrid_id, &inherited_tracks)
         .map_err(|e| SubgridError::CoordinationFailed { details: e.to_string() })?;
 
-    // Step 5: Apply to node's Style - requires BaseDocument access
-    let base_doc = (tree as &mut dyn std::any::Any)
-        .downcast_mut::<crate::BaseDocument>()
-        .ok_or_else(|| SubgridError::StyleAccess {
-            node_id: usize::from(subgrid_id),
-            reason: "Failed to downcast tree to BaseDocument for style update".to_string(),
-        })?;
-
-    let node = base_doc.node_from_id_mut(subgrid_id);
-    let style = node.style_mut();
-
-    // Apply row templates
-    if !replaced_templates.row_functions.is_empty() {
-        style.grid_template_rows = replaced_templates.row_functions
-            .iter()
-            .map(|track| taffy::GridTemplateComponent::Single(*track))
-            .collect();
-
-        #[cfg(feature = "tracing")]
-        tracing::debug!(
-            "Applied {} inherited row tracks to subgrid node {}",
-            style.grid_template_rows.len(),
-            usize::from(subgrid_id)
-        );
-    }
+    // Step 5: Apply to node's Style
+    tree.with_grid_style_mut(subgrid_id, |style| {
+        // Apply row templates
+        if !replaced_templates.row_functions.is_empty() {
+            style.grid_template_rows = replaced_templates.row_functions
+                .iter()
+                .map(|track| taffy::GridTemplateComponent::Single(*track))
+                .collect();
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                "Applied {} inherited row tracks to subgrid node {}",
+                style.grid_template_rows.len(),
+                usize::from(subgrid_id)
+            );
+        }
 
-    // Apply column templates
-    if !replaced_templates.column_functions.is_empty() {
-        style.grid_template_columns = replaced_templates.column_functions
-            .iter()
-            .map(|track| taffy::GridTemplateComponent::Single(*track))
-            .collect();
-
-        #[cfg(feature = "tracing")]
-        tracing::debug!(
-            "Applied {} inherited column tracks to subgrid node {}",
-            style.grid_template_columns.len(),
-            usize::from(subgrid_id)
-        );
-    }
+        // Apply column templates
+        if !replaced_templates.column_functions.is_empty() {
+            style.grid_template_columns = replaced_templates.column_functions
+                .iter()
+                .map(|track| taffy::GridTemplateComponent::Single(*track))
+                .collect();
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                "Applied {} inherited column tracks to subgrid node {}",
+                style.grid_template_columns.len(),
+                usize::from(subgrid_id)
+            );
+        }
+    })
+    .ok_or_else(|| SubgridError::StyleAccess {
+        node_id: usize::from(subgrid_id),
+        reason: "Tree does not allow mutating the subgrid's style".to_string(),
+    })?;
 
-    // Increment style generation to invalidate cache
-    node.style_generation.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    // Step 5b: Inherit the parent's line names in the subgridded axes, including those of its
+    // named areas, so that `grid-area: <name>` placements resolve against the parent's areas
+    inherit_line_names_and_areas(tree, subgrid_id, &subgrid_span, parent_context)?;
 
     // Step 6: Setup line name mapping for subgrid items
     let line_name_mapping = coordinator.setup_line_name_mapping(subgrid_id, parent_context, tree)
@@ -210,33 +208,114 @@ where
     Ok(())
 }
 
+/// Write the line names and named areas a subgrid inherits from its parent to its style
+fn inherit_line_names_and_areas<Tree>(
+    tree: &mut Tree,
+    subgrid_id: taffy::prelude::NodeId,
+    subgrid_span: &super::grid_coordination::GridArea,
+    parent_context: &ParentGridContext,
+) -> SubgridResult<()>
+where
+    Tree: GridStyleAccess,
+{
+    use super::grid_context::{
+        GridAxis as LineAxis, GridSpan, LineNameInheritanceMapper, detect_subgrid_axis_from_style,
+    };
+
+    let subgrid_rows = detect_subgrid_axis_from_style(tree, subgrid_id, true);
+    let subgrid_columns = detect_subgrid_axis_from_style(tree, subgrid_id, false);
+
+    // The line names and areas the subgrid declares itself are read from its computed styles
+    // rather than its style, which holds those inherited by an earlier layout
+    let (own_row_names, own_column_names, own_areas) = tree
+        .grid_computed_styles(subgrid_id)
+        .map(|styles| {
+            let position = styles.get_position();
+            (
+                stylo_taffy::convert::subgrid_line_names(&position.grid_template_rows),
+                stylo_taffy::convert::subgrid_line_names(&position.grid_template_columns),
+                stylo_taffy::convert::grid_template_areas(&position.grid_template_areas)
+                    .unwrap_or_default(),
+            )
+        })
+        .unwrap_or_default();
+
+    // Convert the 1-based lines of the subgrid's area to the range of line indices it spans
+    let span = |start: i32, end: i32| GridSpan {
+        start: (start - 1).max(0) as usize,
+        end: end.max(0) as usize,
+    };
+    let row_span = span(subgrid_span.row_start, subgrid_span.row_end);
+    let column_span = span(subgrid_span.column_start, subgrid_span.column_end);
+
+    let mut mapper = LineNameInheritanceMapper::new();
+    let mut map_line_names = |line_names: &[Vec<String>], span, axis| {
+        mapper.map_subgrid_line_names_with_areas(
+            line_names,
+            &parent_context.parent_grid_areas,
+            &[],
+            span,
+            subgrid_id,
+            axis,
+        )
+    };
+    let merge_line_names = LineNameInheritanceMapper::merge_declared_line_names;
+    let row_names = subgrid_rows
+        .then(|| map_line_names(&parent_context.parent_row_line_names, row_span, LineAxis::Row))
+        .transpose()?
+        .map(|names| merge_line_names(names, &own_row_names));
+    let column_names = subgrid_columns
+        .then(|| {
+            let line_names = &parent_context.parent_column_line_names;
+            map_line_names(line_names, column_span, LineAxis::Column)
+        })
+        .transpose()?
+        .map(|names| merge_line_names(names, &own_column_names));
+
+    // Areas are only inherited by subgrids in both axes, for subgrids nested in them to clip
+    // again. The subgrid's own areas take precedence over inherited ones of the same name.
+    let inherited_areas = match subgrid_rows && subgrid_columns {
+        true => LineNameInheritanceMapper::map_subgrid_grid_areas(
+            &parent_context.parent_grid_areas,
+            row_span,
+            column_span,
+        ),
+        false => Vec::new(),
+    };
+    let areas = LineNameInheritanceMapper::merge_subgrid_grid_areas(own_areas, inherited_areas);
+
+    tree.with_grid_style_mut(subgrid_id, |style| {
+        if let Some(names) = row_names {
+            style.grid_template_row_names = names;
+        }
+        if let Some(names) = column_names {
+            style.grid_template_column_names = names;
+        }
+        // Replacing the areas drops those inherited by an earlier layout, which may have changed
+        style.grid_template_areas = areas;
+    })
+    .ok_or_else(|| SubgridError::StyleAccess {
+        node_id: usize::from(subgrid_id),
+        reason: "Tree does not allow mutating the subgrid's style".to_string(),
+    })
+}
+
 /// Discover child subgrids within a parent subgrid
 fn discover_child_subgrids<Tree>(
     tree: &Tree,
     parent_id: taffy::prelude::NodeId,
 ) -> SubgridResult<Vec<taffy::prelude::NodeId>>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
     use style::values::specified::box_::DisplayInside;
 
-    // Downcast to BaseDocument to access Stylo computed styles
-    // Note: Read-only access, so use &dyn not &mut dyn
-    let base_doc = (tree as &dyn std::any::Any)
-        .downcast_ref::<crate::BaseDocument>()
-        .ok_or_else(|| SubgridError::StyleAccess {
-            node_id: usize::from(parent_id),
-            reason: "Failed to downcast tree to BaseDocument for child discovery".to_string(),
-        })?;
-
     let mut child_subgrids = Vec::new();
 
     // Iterate through all children using Taffy's TraversePartialTree API
     for child_id in tree.child_ids(parent_id) {
-        let child_node = base_doc.node_from_id(child_id);
-
         // Get child's computed styles
-        if let Some(child_styles) = child_node.primary_styles() {
+        if let Some(child_styles) = tree.grid_computed_styles(child_id) {
             // Check if child has display: grid (must be grid container to be subgrid)
             let child_display = child_styles.clone_display();
             if child_display.inside() == DisplayInside::Grid {
@@ -269,7 +348,7 @@ pub fn preprocess_subgrid_for_generic_tree<Tree>(
     parent_context: &ParentGridContext,
 ) -> SubgridResult<()>
 where
-    Tree: taffy::LayoutGridContainer + taffy::TraversePartialTree + 'static,
+    Tree: GridStyleAccess,
 {
     // Call the main coordination function and discard result for compatibility
     coordinate_nested_subgrids(tree, subgrid_id, parent_context, 0)?;
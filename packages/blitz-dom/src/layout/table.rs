@@ -1,15 +1,23 @@
+use std::collections::HashMap;
 use std::{ops::Range, sync::Arc};
 
 use markup5ever::local_name;
+use style::color::AbsoluteColor;
+use style::computed_values::border_collapse::T as BorderCollapse;
+use style::computed_values::caption_side::T as CaptionSide;
 use style::computed_values::table_layout::T as TableLayout;
+use style::properties::ComputedValues;
+use style::values::specified::BorderStyle;
 use style::values::specified::box_::{DisplayInside, DisplayOutside};
 use taffy::{
-    Dimension, LayoutPartialTree as _, Line, MaxTrackSizingFunction, MinTrackSizingFunction,
-    ResolveOrZero, compute_leaf_layout, style_helpers,
+    CompactLength, Dimension, LayoutPartialTree as _, LengthPercentage, Line,
+    MaxTrackSizingFunction, MinTrackSizingFunction, ResolveOrZero, compute_leaf_layout,
+    style_helpers,
 };
 
 use super::resolve_calc_value;
 use crate::BaseDocument;
+use crate::node::SpecialElementData;
 
 pub struct TableTreeWrapper<'doc> {
     pub(crate) doc: &'doc mut BaseDocument,
@@ -20,12 +28,23 @@ pub struct TableTreeWrapper<'doc> {
 pub struct TableContext {
     style: taffy::Style,
     items: Vec<TableItem>,
+    /// The borders of each cell after conflict resolution, if the table collapses its borders
+    collapsed_borders: Vec<CollapsedCellBorders>,
+}
+
+impl TableContext {
+    /// The borders of the table's cells, if it uses the collapsing border model. These are drawn
+    /// by the table, centered on the grid lines, rather than by the cells themselves.
+    pub fn collapsed_borders(&self) -> &[CollapsedCellBorders] {
+        &self.collapsed_borders
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum TableItemKind {
     Row,
     Cell,
+    Caption,
 }
 
 #[derive(Debug, Clone)]
@@ -33,59 +52,201 @@ pub struct TableItem {
     kind: TableItemKind,
     node_id: usize,
     style: taffy::Style,
+    /// The grid area the item covers, as zero-based `(row, column)` ranges
+    rows: Range<u16>,
+    columns: Range<u16>,
+    /// The cell's half of each of its collapsed borders, which it's laid out with instead of the
+    /// borders in its style
+    collapsed_border: Option<taffy::Rect<LengthPercentage>>,
+}
+
+/// The borders of a cell in a table with `border-collapse: collapse`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollapsedCellBorders {
+    pub node_id: usize,
+    pub borders: taffy::Rect<CollapsedBorder>,
+    /// Whether there are no cells after the cell in its rows, or below it in its columns. Each
+    /// edge is drawn once: cells draw their top and left borders, and their right and bottom
+    /// ones only if no other cell shares them.
+    pub last_in_row: bool,
+    pub last_in_column: bool,
+}
+
+/// A border in the collapsing border model, which is shared by adjacent cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollapsedBorder {
+    pub width: f32,
+    pub style: BorderStyle,
+    pub color: AbsoluteColor,
+}
+
+impl CollapsedBorder {
+    const NONE: Self = Self {
+        width: 0.0,
+        style: BorderStyle::None,
+        color: AbsoluteColor::TRANSPARENT_BLACK,
+    };
+
+    /// The borders of an element, in the order top, right, bottom, left
+    fn of(styles: &ComputedValues) -> taffy::Rect<Self> {
+        let border = styles.get_border();
+        let current_color = styles.clone_color();
+        let side = |width: f32, style: BorderStyle, color: &style::values::computed::Color| Self {
+            width,
+            style,
+            color: color.resolve_to_absolute(&current_color),
+        };
+        taffy::Rect {
+            top: side(
+                border.border_top_width.to_f32_px(),
+                border.border_top_style,
+                &border.border_top_color,
+            ),
+            right: side(
+                border.border_right_width.to_f32_px(),
+                border.border_right_style,
+                &border.border_right_color,
+            ),
+            bottom: side(
+                border.border_bottom_width.to_f32_px(),
+                border.border_bottom_style,
+                &border.border_bottom_color,
+            ),
+            left: side(
+                border.border_left_width.to_f32_px(),
+                border.border_left_style,
+                &border.border_left_color,
+            ),
+        }
+    }
+
+    /// Whether this border wins a conflict with an earlier candidate (CSS 2 § 17.6.2.1). Ties go
+    /// to the earlier candidate, so candidates are considered from cells to the table, and from
+    /// the top left.
+    fn beats(&self, other: &Self) -> bool {
+        match (self.style, other.style) {
+            (_, BorderStyle::Hidden) => false,
+            (BorderStyle::Hidden, _) => true,
+            (BorderStyle::None, _) => false,
+            (_, BorderStyle::None) => true,
+            _ if self.width != other.width => self.width > other.width,
+            _ => style_priority(self.style) > style_priority(other.style),
+        }
+    }
+
+    /// The winner of a conflict between borders
+    fn resolve(candidates: impl IntoIterator<Item = Self>) -> Self {
+        let winner = candidates.into_iter().fold(None, |winner: Option<Self>, candidate| {
+            match winner {
+                Some(winner) if !candidate.beats(&winner) => Some(winner),
+                _ => Some(candidate),
+            }
+        });
+        match winner {
+            Some(winner) if !matches!(winner.style, BorderStyle::None | BorderStyle::Hidden) => {
+                winner
+            }
+            _ => Self::NONE,
+        }
+    }
+}
+
+/// The priority of border styles with the same width in border conflicts
+fn style_priority(style: BorderStyle) -> u8 {
+    match style {
+        BorderStyle::Double => 8,
+        BorderStyle::Solid => 7,
+        BorderStyle::Dashed => 6,
+        BorderStyle::Dotted => 5,
+        BorderStyle::Ridge => 4,
+        BorderStyle::Outset => 3,
+        BorderStyle::Groove => 2,
+        BorderStyle::Inset => 1,
+        BorderStyle::None | BorderStyle::Hidden => 0,
+    }
 }
 
 pub(crate) fn build_table_context(
     doc: &mut BaseDocument,
     table_root_node_id: usize,
 ) -> (TableContext, Vec<usize>) {
-    let mut items: Vec<TableItem> = Vec::new();
-    let mut row = 0u16;
-    let mut col = 0u16;
-
     let root_node = &mut doc.nodes[table_root_node_id];
 
     let children = std::mem::take(&mut root_node.children);
 
-    let Some(stylo_styles) = root_node.primary_styles() else {
+    let stylo_element_data = root_node.stylo_element_data.borrow();
+    let primary_styles = stylo_element_data.as_ref().and_then(|data| data.styles.get_primary());
+    let Some(table_styles) = primary_styles.cloned() else {
         panic!("Ignoring table because it has no styles");
     };
+    drop(stylo_element_data);
+
+    // The collapsed borders the cells were last laid out with
+    let previous_borders = match root_node.element_data().map(|element| &element.special_data) {
+        Some(SpecialElementData::TableRoot(context)) => context
+            .items
+            .iter()
+            .filter_map(|item| Some((item.node_id, item.collapsed_border?)))
+            .collect(),
+        _ => HashMap::new(),
+    };
 
     // Use production-quality scrollbar width detection
     let device = doc.stylist.device();
-    let mut style = stylo_taffy::to_taffy_style_with_device(&stylo_styles, &device);
+    let mut style = stylo_taffy::to_taffy_style_with_device(&table_styles, &device);
     style.grid_auto_columns = Vec::new();
     style.grid_auto_rows = Vec::new();
 
-    let is_fixed = match stylo_styles.clone_table_layout() {
+    let is_fixed = match table_styles.clone_table_layout() {
         TableLayout::Fixed => true,
         TableLayout::Auto => false,
     };
 
-    drop(stylo_styles);
-
-    let mut column_sizes: Vec<taffy::Dimension> = Vec::new();
+    // Captions go above or below the rows, depending on `caption-side`
+    let mut top_captions = Vec::new();
+    let mut bottom_captions = Vec::new();
     for child_id in children.iter().copied() {
-        collect_table_cells(
-            doc,
-            child_id,
-            is_fixed,
-            &mut row,
-            &mut col,
-            &mut items,
-            &mut column_sizes,
-        );
-    }
-    column_sizes.resize(col as usize, Dimension::auto());
-
-    style.grid_template_columns = column_sizes
-        .into_iter()
-        .map(|_| {
-            taffy::MinMax {
-                min: MinTrackSizingFunction::auto(),
-                max: MaxTrackSizingFunction::auto(),
+        let Some(styles) = doc.nodes[child_id].primary_styles() else {
+            continue;
+        };
+        if styles.clone_display().outside() == DisplayOutside::TableCaption {
+            match styles.clone_caption_side() {
+                CaptionSide::Top => top_captions.push(child_id),
+                CaptionSide::Bottom => bottom_captions.push(child_id),
             }
-            .into()
+        }
+    }
+
+    let mut builder = TableBuilder {
+        is_fixed,
+        row: top_captions.len() as u16,
+        first_row: top_captions.len() as u16 + 1,
+        ..TableBuilder::default()
+    };
+    for child_id in children.iter().copied() {
+        builder.collect(doc, child_id);
+    }
+    let TableBuilder {
+        mut items,
+        columns,
+        column_count,
+        row,
+        ..
+    } = builder;
+    let body_rows = top_captions.len() as u16..row;
+
+    for (index, caption_id) in top_captions.iter().enumerate() {
+        items.push(caption_item(doc, *caption_id, index as u16));
+    }
+    for (index, caption_id) in bottom_captions.iter().enumerate() {
+        items.push(caption_item(doc, *caption_id, row + index as u16));
+    }
+    let row_count = row as usize + bottom_captions.len();
+
+    style.grid_template_columns = (0..column_count as usize)
+        .map(|column| {
+            let width = columns.get(column).copied().unwrap_or(Dimension::auto());
+            column_track(width, is_fixed).into()
         })
         .collect();
     style.grid_template_rows = vec![
@@ -94,172 +255,571 @@ pub(crate) fn build_table_context(
             max: MaxTrackSizingFunction::auto(),
         }
         .into();
-        row as usize
+        row_count
     ];
 
+    let collapsed_borders = match table_styles.clone_border_collapse() {
+        BorderCollapse::Separate => {
+            // `border-spacing` separates the cells from each other and from the table's padding
+            let spacing = &table_styles.get_inherited_table().border_spacing.0;
+            let (horizontal, vertical) = (spacing.width.0.px(), spacing.height.0.px());
+            style.gap = taffy::Size {
+                width: LengthPercentage::length(horizontal),
+                height: LengthPercentage::length(vertical),
+            };
+            let grow = |padding: LengthPercentage, by: f32| {
+                let raw = padding.into_raw();
+                match raw.tag() {
+                    CompactLength::LENGTH_TAG => LengthPercentage::length(raw.value() + by),
+                    _ => padding,
+                }
+            };
+            style.padding = taffy::Rect {
+                left: grow(style.padding.left, horizontal),
+                right: grow(style.padding.right, horizontal),
+                top: grow(style.padding.top, vertical),
+                bottom: grow(style.padding.bottom, vertical),
+            };
+            Vec::new()
+        }
+        BorderCollapse::Collapse => {
+            let (collapsed, table_border) =
+                collapse_borders(doc, &items, &table_styles, body_rows, column_count);
+            apply_collapsed_borders(&mut items, &mut style, &collapsed, table_border);
+            collapsed
+        }
+    };
+    invalidate_changed_borders(doc, &items, previous_borders);
+
     let layout_children = items
         .iter()
-        .filter(|item| item.kind == TableItemKind::Cell)
+        .filter(|item| item.kind != TableItemKind::Row)
         .map(|cell| cell.node_id)
         .collect();
     let root_node = &mut doc.nodes[table_root_node_id];
     root_node.children = children;
 
-    (TableContext { style, items }, layout_children)
+    (
+        TableContext {
+            style,
+            items,
+            collapsed_borders,
+        },
+        layout_children,
+    )
 }
 
-pub(crate) fn collect_table_cells(
-    doc: &mut BaseDocument,
-    node_id: usize,
+/// The size of a column of a table. With `table-layout: fixed`, columns without a width share
+/// the space left by the others. Otherwise columns are at least as wide as their content.
+fn column_track(
+    width: Dimension,
     is_fixed: bool,
-    row: &mut u16,
-    col: &mut u16,
-    cells: &mut Vec<TableItem>,
-    columns: &mut Vec<Dimension>,
-) {
-    let node = &doc.nodes[node_id];
-
-    if !node.is_element() {
-        return;
+) -> taffy::MinMax<MinTrackSizingFunction, MaxTrackSizingFunction> {
+    let raw = width.into_raw();
+    match (raw.tag(), is_fixed) {
+        (CompactLength::LENGTH_TAG, true) => taffy::MinMax {
+            min: MinTrackSizingFunction::length(raw.value()),
+            max: MaxTrackSizingFunction::length(raw.value()),
+        },
+        (CompactLength::PERCENT_TAG, true) => taffy::MinMax {
+            min: MinTrackSizingFunction::percent(raw.value()),
+            max: MaxTrackSizingFunction::percent(raw.value()),
+        },
+        (_, true) => taffy::MinMax {
+            min: MinTrackSizingFunction::length(0.0),
+            max: MaxTrackSizingFunction::fr(1.0),
+        },
+        (CompactLength::LENGTH_TAG, false) => taffy::MinMax {
+            min: MinTrackSizingFunction::auto(),
+            max: MaxTrackSizingFunction::length(raw.value()),
+        },
+        (CompactLength::PERCENT_TAG, false) => taffy::MinMax {
+            min: MinTrackSizingFunction::auto(),
+            max: MaxTrackSizingFunction::percent(raw.value()),
+        },
+        (_, false) => taffy::MinMax {
+            min: MinTrackSizingFunction::auto(),
+            max: MaxTrackSizingFunction::auto(),
+        },
     }
+}
 
-    let Some(display) = node.primary_styles().map(|s| s.clone_display()) else {
-        println!("Ignoring table descendent because it has no styles");
-        return;
+fn caption_item(doc: &BaseDocument, node_id: usize, row: u16) -> TableItem {
+    let device = doc.stylist.device();
+    let mut style = match doc.nodes[node_id].primary_styles() {
+        Some(styles) => stylo_taffy::to_taffy_style_with_device(&styles, &device),
+        None => taffy::Style::default(),
     };
-
-    if display.outside() == DisplayOutside::None {
-        return;
+    style.grid_column = Line {
+        start: style_helpers::line(1),
+        end: style_helpers::line(-1),
+    };
+    style.grid_row = Line {
+        start: style_helpers::line(row as i16 + 1),
+        end: taffy::GridPlacement::Span(1),
+    };
+    TableItem {
+        kind: TableItemKind::Caption,
+        node_id,
+        style,
+        rows: row..row + 1,
+        columns: 0..0,
+        collapsed_border: None,
     }
+}
 
-    match display.inside() {
-        DisplayInside::TableRowGroup
-        | DisplayInside::TableHeaderGroup
-        | DisplayInside::TableFooterGroup
-        | DisplayInside::Contents => {
-            let children = std::mem::take(&mut doc.nodes[node_id].children);
-            for child_id in children.iter().copied() {
-                collect_table_cells(doc, child_id, is_fixed, row, col, cells, columns);
-            }
-            doc.nodes[node_id].children = children;
+/// Places the rows and cells of a table in its grid
+#[derive(Default)]
+struct TableBuilder {
+    is_fixed: bool,
+    /// The number of rows placed so far, including captions above the table
+    row: u16,
+    /// The (one-based) number of the table's first row
+    first_row: u16,
+    /// The column the next cell in the current row goes in
+    col: u16,
+    column_count: u16,
+    items: Vec<TableItem>,
+    /// The specified width of each column
+    columns: Vec<Dimension>,
+    /// For each column, how many more rows (including the current one) are covered by cells
+    /// with `rowspan` in earlier rows
+    spanned_rows: Vec<u16>,
+}
+
+impl TableBuilder {
+    fn collect(&mut self, doc: &mut BaseDocument, node_id: usize) {
+        let node = &doc.nodes[node_id];
+
+        if !node.is_element() {
+            return;
         }
-        DisplayInside::TableRow => {
-            *row += 1;
-            *col = 0;
 
-            {
+        let Some(display) = node.primary_styles().map(|s| s.clone_display()) else {
+            println!("Ignoring table descendent because it has no styles");
+            return;
+        };
+
+        if matches!(display.outside(), DisplayOutside::None | DisplayOutside::TableCaption) {
+            return;
+        }
+
+        match display.inside() {
+            DisplayInside::TableRowGroup
+            | DisplayInside::TableHeaderGroup
+            | DisplayInside::TableFooterGroup
+            | DisplayInside::Contents => {
+                let children = std::mem::take(&mut doc.nodes[node_id].children);
+                for child_id in children.iter().copied() {
+                    self.collect(doc, child_id);
+                }
+                doc.nodes[node_id].children = children;
+            }
+            DisplayInside::TableColumnGroup => {
+                let children = std::mem::take(&mut doc.nodes[node_id].children);
+                if children.is_empty() {
+                    // A column group without columns stands for `span` columns itself
+                    self.push_columns(doc, node_id);
+                }
+                for child_id in children.iter().copied() {
+                    self.collect(doc, child_id);
+                }
+                doc.nodes[node_id].children = children;
+            }
+            DisplayInside::TableColumn => self.push_columns(doc, node_id),
+            DisplayInside::TableRow => {
+                self.row += 1;
+                self.col = 0;
+                for rows in &mut self.spanned_rows {
+                    *rows = rows.saturating_sub(1);
+                }
+
+                {
+                    let stylo_style = match node.primary_styles() {
+                        Some(styles) => styles,
+                        None => {
+                            eprintln!(
+                                "Warning: Table row node {} has no primary styles, skipping",
+                                node_id
+                            );
+                            return;
+                        }
+                    };
+                    let device = doc.stylist.device();
+                    let mut style =
+                        stylo_taffy::to_taffy_style_with_device(&*stylo_style, &device);
+                    style.grid_column = Line {
+                        start: style_helpers::line(1),
+                        end: style_helpers::line(-1),
+                    };
+                    style.grid_row = Line {
+                        start: style_helpers::line(self.row as i16),
+                        end: taffy::GridPlacement::Span(1),
+                    };
+                    self.items.push(TableItem {
+                        kind: TableItemKind::Row,
+                        node_id,
+                        style,
+                        rows: self.row - 1..self.row,
+                        columns: 0..0,
+                        collapsed_border: None,
+                    });
+                }
+
+                let children = std::mem::take(&mut doc.nodes[node_id].children);
+                for child_id in children.iter().copied() {
+                    self.collect(doc, child_id);
+                }
+                doc.nodes[node_id].children = children;
+            }
+            DisplayInside::TableCell => {
                 let stylo_style = match node.primary_styles() {
                     Some(styles) => styles,
                     None => {
                         eprintln!(
-                            "Warning: Table row node {} has no primary styles, skipping",
+                            "Warning: Table cell node {} has no primary styles, skipping",
                             node_id
                         );
                         return;
                     }
                 };
+                let colspan: u16 = node
+                    .attr(local_name!("colspan"))
+                    .and_then(|val| val.parse().ok())
+                    .unwrap_or(1)
+                    .clamp(1, 1000);
+                // `rowspan="0"` (spanning the rest of the row group) is treated as 1
+                let rowspan: u16 = node
+                    .attr(local_name!("rowspan"))
+                    .and_then(|val| val.parse().ok())
+                    .unwrap_or(1)
+                    .clamp(1, 65534);
                 let device = doc.stylist.device();
                 let mut style = stylo_taffy::to_taffy_style_with_device(&*stylo_style, &device);
+
+                // Cells go in the first columns which aren't covered by cells from earlier rows
+                while self.spanned_rows.get(self.col as usize).is_some_and(|rows| *rows > 0) {
+                    self.col += 1;
+                }
+                let columns = self.col..self.col + colspan;
+
+                // With `table-layout: fixed`, only the first row determines the column widths
+                if !self.is_fixed || self.row == self.first_row {
+                    let width = cell_width(&style, colspan);
+                    for column in columns.clone() {
+                        self.widen_column(column, width);
+                    }
+                }
+
                 style.grid_column = Line {
-                    start: style_helpers::line(1),
-                    end: style_helpers::line(-1),
+                    start: style_helpers::line((self.col + 1) as i16),
+                    end: taffy::GridPlacement::Span(colspan),
                 };
                 style.grid_row = Line {
-                    start: style_helpers::line(*row as i16),
-                    end: taffy::GridPlacement::Span(1),
+                    start: style_helpers::line(self.row as i16),
+                    end: taffy::GridPlacement::Span(rowspan),
                 };
-                cells.push(TableItem {
-                    kind: TableItemKind::Row,
+                style.size.width = Dimension::auto();
+                self.items.push(TableItem {
+                    kind: TableItemKind::Cell,
                     node_id,
                     style,
+                    rows: self.row - 1..self.row - 1 + rowspan,
+                    columns: columns.clone(),
+                    collapsed_border: None,
                 });
+
+                if self.spanned_rows.len() < columns.end as usize {
+                    self.spanned_rows.resize(columns.end as usize, 0);
+                }
+                for column in columns.clone() {
+                    self.spanned_rows[column as usize] = rowspan;
+                }
+                self.col = columns.end;
+                self.column_count = self.column_count.max(columns.end);
+            }
+            DisplayInside::None => {
+                // Ignore
             }
+            _ => {
+                println!(
+                    "Warning: ignoring non-table typed descendent of table ({:?})",
+                    display.inside()
+                );
+            }
+        }
+    }
+
+    /// Record the widths of the columns which a `<col>` or `<colgroup>` stands for
+    fn push_columns(&mut self, doc: &BaseDocument, node_id: usize) {
+        let node = &doc.nodes[node_id];
+        let span: u16 = node
+            .attr(local_name!("span"))
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(1)
+            .clamp(1, 1000);
+        let width = node.primary_styles().map_or(Dimension::auto(), |styles| {
+            let device = doc.stylist.device();
+            let style = stylo_taffy::to_taffy_style_with_device(&styles, &device);
+            cell_width(&style, 1)
+        });
+        for _ in 0..span {
+            self.columns.push(width);
+        }
+        self.column_count = self.column_count.max(self.columns.len() as u16);
+    }
 
-            let children = std::mem::take(&mut doc.nodes[node_id].children);
-            for child_id in children.iter().copied() {
-                collect_table_cells(doc, child_id, is_fixed, row, col, cells, columns);
+    /// Apply a cell's width to a column, which is as wide as the widest cell with a length
+    fn widen_column(&mut self, column: u16, width: Dimension) {
+        let column = column as usize;
+        if self.columns.len() <= column {
+            self.columns.resize(column + 1, Dimension::auto());
+        }
+        let current = self.columns[column].into_raw();
+        let new = width.into_raw();
+        self.columns[column] = match (current.tag(), new.tag()) {
+            (CompactLength::LENGTH_TAG, CompactLength::LENGTH_TAG) => {
+                Dimension::length(current.value().max(new.value()))
             }
-            doc.nodes[node_id].children = children;
+            // Percentages take precedence over lengths, and the first one wins
+            (CompactLength::PERCENT_TAG, _) => self.columns[column],
+            (_, CompactLength::LENGTH_TAG | CompactLength::PERCENT_TAG) => width,
+            _ => self.columns[column],
+        };
+    }
+}
+
+/// The width a cell gives each of the columns it spans, including its padding
+fn cell_width(style: &taffy::Style, colspan: u16) -> Dimension {
+    let width = style.size.width.into_raw();
+    match width.tag() {
+        CompactLength::LENGTH_TAG => {
+            let padding = style
+                .padding
+                .resolve_or_zero(None::<f32>, |calc_ptr, parent_size| {
+                    resolve_calc_value(calc_ptr, parent_size)
+                });
+            let border = style
+                .border
+                .resolve_or_zero(None::<f32>, |calc_ptr, parent_size| {
+                    resolve_calc_value(calc_ptr, parent_size)
+                });
+            let outer = width.value() + padding.left + padding.right + border.left + border.right;
+            Dimension::length(outer / colspan as f32)
         }
-        DisplayInside::TableCell => {
-            let stylo_style = match node.primary_styles() {
-                Some(styles) => styles,
-                None => {
-                    eprintln!(
-                        "Warning: Table cell node {} has no primary styles, skipping",
-                        node_id
-                    );
-                    return;
+        CompactLength::PERCENT_TAG => Dimension::percent(width.value() / colspan as f32),
+        // Fallback to auto for unknown dimension types instead of panicking
+        _ => Dimension::auto(),
+    }
+}
+
+/// Resolve the conflicts between the borders of adjacent cells, and the rows and table they're
+/// at the edge of. Each edge between two slots of the grid is resolved once, and each side of a
+/// cell gets the winner of the edges along it. Also returns the widest collapsed border on each
+/// edge of the table.
+fn collapse_borders(
+    doc: &BaseDocument,
+    items: &[TableItem],
+    table_styles: &ComputedValues,
+    body_rows: Range<u16>,
+    column_count: u16,
+) -> (Vec<CollapsedCellBorders>, taffy::Rect<f32>) {
+    let row_count = body_rows.len();
+    let columns = usize::from(column_count);
+    let grid_rows = |rows: &Range<u16>| {
+        let start = rows.start.saturating_sub(body_rows.start);
+        let end = rows.end.min(body_rows.end).saturating_sub(body_rows.start);
+        usize::from(start)..usize::from(end.max(start))
+    };
+
+    // The cell covering each slot of the grid, and the borders of each row
+    let mut cells = Vec::new();
+    let mut slots: Vec<Option<usize>> = vec![None; row_count * columns];
+    let mut rows: Vec<Option<taffy::Rect<CollapsedBorder>>> = vec![None; row_count];
+    for item in items {
+        let Some(styles) = doc.nodes[item.node_id].primary_styles() else {
+            continue;
+        };
+        let borders = CollapsedBorder::of(&styles);
+        match item.kind {
+            TableItemKind::Row => {
+                for row in grid_rows(&item.rows) {
+                    rows[row] = Some(borders);
                 }
-            };
-            let colspan: u16 = node
-                .attr(local_name!("colspan"))
-                .and_then(|val| val.parse().ok())
-                .unwrap_or(1);
-            let device = doc.stylist.device();
-            let mut style = stylo_taffy::to_taffy_style_with_device(&*stylo_style, &device);
-
-            // TODO: account for padding/border/margin
-            if *row == 1 {
-                let column = match style.size.width.tag() {
-                    taffy::CompactLength::LENGTH_TAG => {
-                        let len = style.size.width.value();
-                        let padding = style
-                            .padding
-                            .resolve_or_zero(None::<f32>, |calc_ptr, parent_size| {
-                                resolve_calc_value(calc_ptr, parent_size)
-                            });
-                        Dimension::length(len + padding.left + padding.right)
-                    }
-                    taffy::CompactLength::PERCENT_TAG => {
-                        Dimension::percent(style.size.width.value())
+            }
+            TableItemKind::Cell => {
+                for row in grid_rows(&item.rows) {
+                    for column in item.columns.clone() {
+                        slots[row * columns + usize::from(column)] = Some(cells.len());
                     }
-                    taffy::CompactLength::AUTO_TAG => Dimension::auto(),
-                    // Fallback to auto for unknown dimension types instead of panicking
-                    _ => Dimension::auto(),
-                };
-                columns.push(column);
-            } else if !is_fixed
-                && (*col as usize) < columns.len()
-                && taffy::CompactLength::LENGTH_TAG == style.size.width.tag()
-            {
-                let new_len = style.size.width.value();
-                let tag = columns[*col as usize].tag();
-                let value = columns[*col as usize].value();
-                columns[*col as usize] = match tag {
-                    taffy::CompactLength::LENGTH_TAG => Dimension::length(value.max(new_len)),
-                    taffy::CompactLength::AUTO_TAG => Dimension::length(new_len),
-                    taffy::CompactLength::PERCENT_TAG => Dimension::percent(value),
-                    // Fallback to auto for unknown dimension types instead of panicking
-                    _ => Dimension::auto(),
                 }
+                cells.push((item, borders));
             }
+            TableItemKind::Caption => {}
+        }
+    }
+    let cell_at = |row: usize, column: usize| {
+        if row < row_count && column < columns {
+            slots[row * columns + column]
+        } else {
+            None
+        }
+    };
+    let table = CollapsedBorder::of(table_styles);
+
+    // The edges above each row and below the last one. Ties go to cells, then rows, then the
+    // table, and to the top left.
+    let mut horizontal = Vec::with_capacity((row_count + 1) * columns);
+    for row in 0..=row_count {
+        let row_above = row.checked_sub(1).and_then(|row| rows[row]);
+        let row_below = rows.get(row).copied().flatten();
+        for column in 0..columns {
+            let above = row.checked_sub(1).and_then(|row| cell_at(row, column));
+            let below = cell_at(row, column);
+            // Cells which span rows have no edges between them
+            if above.is_some() && above == below {
+                horizontal.push(CollapsedBorder::NONE);
+                continue;
+            }
+            horizontal.push(CollapsedBorder::resolve(
+                above
+                    .map(|cell| cells[cell].1.bottom)
+                    .into_iter()
+                    .chain(below.map(|cell| cells[cell].1.top))
+                    .chain(row_above.map(|row| row.bottom))
+                    .chain(row_below.map(|row| row.top))
+                    .chain((row == 0).then_some(table.top))
+                    .chain((row == row_count).then_some(table.bottom)),
+            ));
+        }
+    }
+
+    // The edges before each column and after the last one
+    let mut vertical = Vec::with_capacity(row_count * (columns + 1));
+    for (row, row_borders) in rows.iter().enumerate() {
+        for column in 0..=columns {
+            let before = column.checked_sub(1).and_then(|column| cell_at(row, column));
+            let after = cell_at(row, column);
+            if before.is_some() && before == after {
+                vertical.push(CollapsedBorder::NONE);
+                continue;
+            }
+            let (at_left, at_right) = (column == 0, column == columns);
+            vertical.push(CollapsedBorder::resolve(
+                before
+                    .map(|cell| cells[cell].1.right)
+                    .into_iter()
+                    .chain(after.map(|cell| cells[cell].1.left))
+                    .chain(row_borders.filter(|_| at_left).map(|row| row.left))
+                    .chain(row_borders.filter(|_| at_right).map(|row| row.right))
+                    .chain(at_left.then_some(table.left))
+                    .chain(at_right.then_some(table.right)),
+            ));
+        }
+    }
+    let horizontal_edge = |row: usize, column: usize| horizontal[row * columns + column];
+    let vertical_edge = |row: usize, column: usize| vertical[row * (columns + 1) + column];
 
-            style.grid_column = Line {
-                start: style_helpers::line((*col + 1) as i16),
-                end: taffy::GridPlacement::Span(colspan),
+    let collapsed = cells
+        .iter()
+        .map(|(cell, _)| {
+            let cell_rows = grid_rows(&cell.rows);
+            let cell_columns = usize::from(cell.columns.start)..usize::from(cell.columns.end);
+            let edge = |row: usize| {
+                let edges = cell_columns.clone().map(|column| horizontal_edge(row, column));
+                CollapsedBorder::resolve(edges)
             };
-            style.grid_row = Line {
-                start: style_helpers::line(*row as i16),
-                end: taffy::GridPlacement::Span(1),
+            let side = |column: usize| {
+                CollapsedBorder::resolve(cell_rows.clone().map(|row| vertical_edge(row, column)))
             };
-            style.size.width = Dimension::auto();
-            cells.push(TableItem {
-                kind: TableItemKind::Cell,
-                node_id,
-                style,
-            });
-
-            *col += colspan;
-        }
-        DisplayInside::None => {
-            // Ignore
+            CollapsedCellBorders {
+                node_id: cell.node_id,
+                borders: taffy::Rect {
+                    top: edge(cell_rows.start),
+                    right: side(cell_columns.end),
+                    bottom: edge(cell_rows.end),
+                    left: side(cell_columns.start),
+                },
+                last_in_row: cell_rows
+                    .clone()
+                    .any(|row| cell_at(row, cell_columns.end).is_none()),
+                last_in_column: cell_columns
+                    .clone()
+                    .any(|column| cell_at(cell_rows.end, column).is_none()),
+            }
+        })
+        .collect();
+
+    let widest_row_edge = |row: usize| {
+        (0..columns).map(|column| horizontal_edge(row, column).width).fold(0.0f32, f32::max)
+    };
+    let widest_column_edge = |column: usize| {
+        (0..row_count).map(|row| vertical_edge(row, column).width).fold(0.0f32, f32::max)
+    };
+    let table_border = taffy::Rect {
+        top: widest_row_edge(0),
+        bottom: widest_row_edge(row_count),
+        left: widest_column_edge(0),
+        right: widest_column_edge(columns),
+    };
+    (collapsed, table_border)
+}
+
+/// Lay out cells with half of each collapsed border (the other half belongs to the neighboring
+/// cell or the table), and the table with half of the widest border on each of its edges
+fn apply_collapsed_borders(
+    items: &mut [TableItem],
+    table_style: &mut taffy::Style,
+    collapsed: &[CollapsedCellBorders],
+    table_border: taffy::Rect<f32>,
+) {
+    let half = |border: &CollapsedBorder| LengthPercentage::length(border.width / 2.0);
+    // The collapsed borders are in the same order as the cells
+    let mut collapsed = collapsed.iter().peekable();
+    for item in items.iter_mut().filter(|item| item.kind == TableItemKind::Cell) {
+        let Some(cell) = collapsed.next_if(|cell| cell.node_id == item.node_id) else {
+            continue;
+        };
+        let borders = &cell.borders;
+        let border = taffy::Rect {
+            left: half(&borders.left),
+            right: half(&borders.right),
+            top: half(&borders.top),
+            bottom: half(&borders.bottom),
+        };
+        item.style.border = border;
+        item.collapsed_border = Some(border);
+    }
+
+    // Border spacing and the table's padding don't apply in the collapsing border model
+    table_style.gap = taffy::Size {
+        width: LengthPercentage::length(0.0),
+        height: LengthPercentage::length(0.0),
+    };
+    table_style.padding = taffy::Rect {
+        left: LengthPercentage::length(0.0),
+        right: LengthPercentage::length(0.0),
+        top: LengthPercentage::length(0.0),
+        bottom: LengthPercentage::length(0.0),
+    };
+    table_style.border = table_border.map(|width| LengthPercentage::length(width / 2.0));
+}
+
+/// Clear the layout caches of cells which were laid out with different collapsed borders, given
+/// the borders from when the table was last laid out
+fn invalidate_changed_borders(
+    doc: &mut BaseDocument,
+    items: &[TableItem],
+    mut previous: HashMap<usize, taffy::Rect<LengthPercentage>>,
+) {
+    for item in items {
+        if previous.remove(&item.node_id) != item.collapsed_border {
+            doc.nodes[item.node_id].cache.clear();
         }
-        _ => {
-            println!(
-                "Warning: ignoring non-table typed descendent of table ({:?})",
-                display.inside()
-            );
+    }
+    for node_id in previous.into_keys() {
+        if let Some(node) = doc.nodes.get_mut(node_id) {
+            node.cache.clear();
         }
     }
 }
@@ -329,9 +889,17 @@ impl taffy::LayoutPartialTree for TableTreeWrapper<'_> {
                     taffy::Size::ZERO
                 })
             }
-            TableItemKind::Cell => {
+            TableItemKind::Cell | TableItemKind::Caption => {
+                // The cell's own borders are put back once it's laid out with the collapsed ones
+                let own_border = cell.collapsed_border.map(|border| {
+                    std::mem::replace(&mut self.doc.nodes[cell.node_id].style_mut().border, border)
+                });
                 let node_id = taffy::NodeId::from(cell.node_id);
-                self.doc.compute_child_layout(node_id, inputs)
+                let output = self.doc.compute_child_layout(node_id, inputs);
+                if let Some(border) = own_border {
+                    self.doc.nodes[cell.node_id].style_mut().border = border;
+                }
+                output
             }
         }
     }
@@ -356,3 +924,40 @@ impl taffy::LayoutGridContainer for TableTreeWrapper<'_> {
         &self.ctx.items[usize::from(child_node_id)].style
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn border(width: f32, style: BorderStyle) -> CollapsedBorder {
+        CollapsedBorder {
+            width,
+            style,
+            color: AbsoluteColor::BLACK,
+        }
+    }
+
+    #[test]
+    fn resolves_border_conflicts() {
+        let thin = border(1.0, BorderStyle::Solid);
+        let thick = border(3.0, BorderStyle::Dotted);
+        let double = border(1.0, BorderStyle::Double);
+        let hidden = border(0.0, BorderStyle::Hidden);
+        let none = border(5.0, BorderStyle::None);
+
+        // Wider borders win, then borders with more prominent styles, then earlier candidates
+        assert_eq!(CollapsedBorder::resolve([thin, thick]), thick);
+        assert_eq!(CollapsedBorder::resolve([thin, double]), double);
+        let dashed = border(1.0, BorderStyle::Dashed);
+        let white = CollapsedBorder {
+            color: AbsoluteColor::WHITE,
+            ..dashed
+        };
+        assert_eq!(CollapsedBorder::resolve([dashed, white]), dashed);
+
+        // `hidden` suppresses every other border, and `none` loses to all of them
+        assert_eq!(CollapsedBorder::resolve([thick, hidden]), CollapsedBorder::NONE);
+        assert_eq!(CollapsedBorder::resolve([none, thin]), thin);
+        assert_eq!(CollapsedBorder::resolve([none]), CollapsedBorder::NONE);
+    }
+}
@@ -0,0 +1,527 @@
+//! Recording and replaying layout passes, for debugging grid and flexbox layout
+//!
+//! With the `layout-trace` feature, [`BaseDocument::start_layout_trace`] records every call taffy
+//! makes to measure or lay out a node, with its inputs and outputs, until
+//! [`BaseDocument::finish_layout_trace`] returns the [`LayoutTrace`]. Along with the calls, the
+//! trace keeps a snapshot of the taffy style and layout children of every node that was laid out.
+//!
+//! A trace can be saved as JSON and loaded again elsewhere. [`LayoutTrace::replay`] then runs
+//! taffy's block, flexbox and grid algorithms over the snapshot in isolation, without a document,
+//! stylesheets or fonts. Nodes that Blitz lays out itself (text, replaced elements, form controls,
+//! tables, ruby, math and contained elements) are measured by looking up their recorded outputs,
+//! so any size that differs from the recording points at the block, flexbox or grid code.
+//!
+//! Styles with `calc()` lengths can't be serialized, as they point into Stylo's computed values.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use taffy::{
+    AvailableSpace, Display, LayoutInput, LayoutOutput, NodeId, RequestedAxis, RunMode,
+    SizingMode, TaffyTree,
+};
+
+use crate::layout::replaced::is_replaced_element;
+use crate::{BaseDocument, NodeData};
+
+/// The run mode of a traced call, see [`taffy::RunMode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TracedRunMode {
+    PerformLayout,
+    ComputeSize,
+    PerformHiddenLayout,
+}
+
+/// The sizing mode of a traced call, see [`taffy::SizingMode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TracedSizingMode {
+    ContentSize,
+    InherentSize,
+}
+
+/// The axis a traced call was measuring, see [`taffy::RequestedAxis`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TracedAxis {
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+/// Space available to a node, see [`taffy::AvailableSpace`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TracedSpace {
+    Definite(f32),
+    MinContent,
+    MaxContent,
+}
+
+impl From<AvailableSpace> for TracedSpace {
+    fn from(space: AvailableSpace) -> Self {
+        match space {
+            AvailableSpace::Definite(value) => Self::Definite(value),
+            AvailableSpace::MinContent => Self::MinContent,
+            AvailableSpace::MaxContent => Self::MaxContent,
+        }
+    }
+}
+
+impl From<TracedSpace> for AvailableSpace {
+    fn from(space: TracedSpace) -> Self {
+        match space {
+            TracedSpace::Definite(value) => Self::Definite(value),
+            TracedSpace::MinContent => Self::MinContent,
+            TracedSpace::MaxContent => Self::MaxContent,
+        }
+    }
+}
+
+/// The inputs of a traced call. Sizes are `[width, height]`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TracedInput {
+    pub run_mode: TracedRunMode,
+    pub sizing_mode: TracedSizingMode,
+    pub axis: TracedAxis,
+    pub known_dimensions: [Option<f32>; 2],
+    pub parent_size: [Option<f32>; 2],
+    pub available_space: [TracedSpace; 2],
+    /// Whether the top and bottom margins can collapse with the node's children
+    pub vertical_margins_are_collapsible: [bool; 2],
+}
+
+impl From<&LayoutInput> for TracedInput {
+    fn from(inputs: &LayoutInput) -> Self {
+        Self {
+            run_mode: match inputs.run_mode {
+                RunMode::PerformLayout => TracedRunMode::PerformLayout,
+                RunMode::ComputeSize => TracedRunMode::ComputeSize,
+                RunMode::PerformHiddenLayout => TracedRunMode::PerformHiddenLayout,
+            },
+            sizing_mode: match inputs.sizing_mode {
+                SizingMode::ContentSize => TracedSizingMode::ContentSize,
+                SizingMode::InherentSize => TracedSizingMode::InherentSize,
+            },
+            axis: match inputs.axis {
+                RequestedAxis::Horizontal => TracedAxis::Horizontal,
+                RequestedAxis::Vertical => TracedAxis::Vertical,
+                RequestedAxis::Both => TracedAxis::Both,
+            },
+            known_dimensions: [inputs.known_dimensions.width, inputs.known_dimensions.height],
+            parent_size: [inputs.parent_size.width, inputs.parent_size.height],
+            available_space: [
+                inputs.available_space.width.into(),
+                inputs.available_space.height.into(),
+            ],
+            vertical_margins_are_collapsible: [
+                inputs.vertical_margins_are_collapsible.start,
+                inputs.vertical_margins_are_collapsible.end,
+            ],
+        }
+    }
+}
+
+/// The output of a traced call. Sizes are `[width, height]`, baselines `[x, y]`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TracedOutput {
+    pub size: [f32; 2],
+    pub content_size: [f32; 2],
+    pub first_baselines: [Option<f32>; 2],
+}
+
+impl From<&LayoutOutput> for TracedOutput {
+    fn from(output: &LayoutOutput) -> Self {
+        Self {
+            size: [output.size.width, output.size.height],
+            content_size: [output.content_size.width, output.content_size.height],
+            first_baselines: [output.first_baselines.x, output.first_baselines.y],
+        }
+    }
+}
+
+/// A single call to measure or lay out a node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutCall {
+    pub node_id: usize,
+    /// How many calls this one is nested in, `0` for the root of a layout pass
+    pub depth: usize,
+    pub inputs: TracedInput,
+    pub output: TracedOutput,
+}
+
+/// A node as it was when the trace was finished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracedNode {
+    pub style: taffy::Style,
+    pub layout_children: Vec<usize>,
+    /// Whether the node is laid out by Blitz instead of taffy's block, flexbox or grid algorithms,
+    /// in which case a replay looks its sizes up in the recorded calls
+    pub measured: bool,
+}
+
+/// A recorded layout pass, see the [module docs](self)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayoutTrace {
+    /// The node the last top-level call was made on, usually the root element
+    pub root: Option<usize>,
+    pub calls: Vec<LayoutCall>,
+    pub nodes: HashMap<usize, TracedNode>,
+}
+
+/// A node whose replayed size differs from its recorded one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LayoutMismatch {
+    pub node_id: usize,
+    pub recorded: [f32; 2],
+    pub replayed: [f32; 2],
+}
+
+/// The result of [`LayoutTrace::replay`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayReport {
+    /// Nodes whose final layout has a different size than recorded
+    pub mismatches: Vec<LayoutMismatch>,
+    /// Nodes that were measured with inputs they were never measured with while recording. Their
+    /// size is taken from their final recorded layout instead.
+    pub unrecorded_measures: Vec<usize>,
+}
+
+impl ReplayReport {
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Sizes within this distance are considered equal when replaying
+const TOLERANCE: f32 = 0.01;
+
+impl LayoutTrace {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// The output of the last call that laid out `node_id`, which is where it ended up
+    pub fn final_layout(&self, node_id: usize) -> Option<&TracedOutput> {
+        self.calls
+            .iter()
+            .rev()
+            .find(|call| {
+                call.node_id == node_id && call.inputs.run_mode == TracedRunMode::PerformLayout
+            })
+            .map(|call| &call.output)
+    }
+
+    /// Lay the snapshot out again with taffy and compare the sizes with the recorded ones
+    pub fn replay(&self) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        let Some(root) = self.root else {
+            return report;
+        };
+        // The root is laid out in the space of the last top-level call on it, as earlier
+        // top-level calls may have been on other nodes or measured it in other spaces
+        let root_call = self
+            .calls
+            .iter()
+            .rev()
+            .find(|call| call.depth == 0 && call.node_id == root);
+        let Some(root_call) = root_call else {
+            return report;
+        };
+
+        let mut tree: TaffyTree<usize> = TaffyTree::new();
+        tree.disable_rounding();
+        let mut taffy_ids = HashMap::new();
+        let Some(taffy_root) = self.build_node(&mut tree, root, &mut taffy_ids) else {
+            return report;
+        };
+
+        let available_space = taffy::Size {
+            width: root_call.inputs.available_space[0].into(),
+            height: root_call.inputs.available_space[1].into(),
+        };
+        let measured = tree.compute_layout_with_measure(
+            taffy_root,
+            available_space,
+            |known_dimensions, available_space, _, node_id, _| {
+                let Some(node_id) = node_id.map(|node_id| *node_id) else {
+                    return taffy::Size::ZERO;
+                };
+                let [width, height] = match self.recorded_measure(
+                    node_id,
+                    known_dimensions,
+                    available_space,
+                ) {
+                    Some(output) => output.size,
+                    None => {
+                        report.unrecorded_measures.push(node_id);
+                        self.final_layout(node_id).map(|output| output.size).unwrap_or([0.0; 2])
+                    }
+                };
+                taffy::Size { width, height }
+            },
+        );
+        if measured.is_err() {
+            return report;
+        }
+
+        let mut node_ids: Vec<_> = taffy_ids.keys().copied().collect();
+        node_ids.sort_unstable();
+        for node_id in node_ids {
+            let (Some(recorded), Ok(layout)) =
+                (self.final_layout(node_id), tree.layout(taffy_ids[&node_id]))
+            else {
+                continue;
+            };
+            let replayed = [layout.size.width, layout.size.height];
+            let differs = |a: f32, b: f32| (a - b).abs() > TOLERANCE;
+            if differs(recorded.size[0], replayed[0]) || differs(recorded.size[1], replayed[1]) {
+                report.mismatches.push(LayoutMismatch {
+                    node_id,
+                    recorded: recorded.size,
+                    replayed,
+                });
+            }
+        }
+        report.unrecorded_measures.sort_unstable();
+        report.unrecorded_measures.dedup();
+        report
+    }
+
+    fn build_node(
+        &self,
+        tree: &mut TaffyTree<usize>,
+        node_id: usize,
+        taffy_ids: &mut HashMap<usize, NodeId>,
+    ) -> Option<NodeId> {
+        let node = self.nodes.get(&node_id)?;
+        let taffy_id = if node.measured {
+            tree.new_leaf_with_context(node.style.clone(), node_id).ok()?
+        } else {
+            let children: Vec<_> = node
+                .layout_children
+                .iter()
+                .filter_map(|&child_id| self.build_node(tree, child_id, taffy_ids))
+                .collect();
+            let taffy_id = tree.new_with_children(node.style.clone(), &children).ok()?;
+            tree.set_node_context(taffy_id, Some(node_id)).ok()?;
+            taffy_id
+        };
+        taffy_ids.insert(node_id, taffy_id);
+        Some(taffy_id)
+    }
+
+    /// The output of a recorded call that measured `node_id` with these inputs
+    fn recorded_measure(
+        &self,
+        node_id: usize,
+        known_dimensions: taffy::Size<Option<f32>>,
+        available_space: taffy::Size<AvailableSpace>,
+    ) -> Option<&TracedOutput> {
+        let known_dimensions = [known_dimensions.width, known_dimensions.height];
+        let available_space: [TracedSpace; 2] =
+            [available_space.width.into(), available_space.height.into()];
+        self.calls
+            .iter()
+            .find(|call| {
+                call.node_id == node_id
+                    && call.inputs.known_dimensions == known_dimensions
+                    && call.inputs.available_space == available_space
+            })
+            .map(|call| &call.output)
+    }
+}
+
+/// Collects the calls of a layout trace while it is running
+#[derive(Default)]
+pub(crate) struct LayoutTraceRecorder {
+    root: Option<usize>,
+    calls: Vec<LayoutCall>,
+    depth: usize,
+}
+
+impl LayoutTraceRecorder {
+    /// Called before a node is measured or laid out
+    pub(crate) fn enter(&mut self, node_id: usize) {
+        if self.depth == 0 {
+            self.root = Some(node_id);
+        }
+        self.depth += 1;
+    }
+
+    /// Called with the result of the call [`enter`](Self::enter) was called for
+    pub(crate) fn exit(&mut self, node_id: usize, inputs: &LayoutInput, output: &LayoutOutput) {
+        self.depth -= 1;
+        self.calls.push(LayoutCall {
+            node_id,
+            depth: self.depth,
+            inputs: inputs.into(),
+            output: output.into(),
+        });
+    }
+}
+
+impl BaseDocument {
+    /// Start recording every call to measure or lay out a node, see [`LayoutTrace`]
+    pub fn start_layout_trace(&mut self) {
+        self.layout_trace = Some(LayoutTraceRecorder::default());
+    }
+
+    /// Stop recording and return the calls made since [`start_layout_trace`], along with a
+    /// snapshot of the nodes they were made on
+    ///
+    /// [`start_layout_trace`]: Self::start_layout_trace
+    pub fn finish_layout_trace(&mut self) -> Option<LayoutTrace> {
+        let recorder = self.layout_trace.take()?;
+        let mut trace = LayoutTrace {
+            root: recorder.root,
+            calls: recorder.calls,
+            nodes: HashMap::new(),
+        };
+        if let Some(root) = trace.root {
+            self.snapshot_layout_node(root, &mut trace.nodes);
+        }
+        Some(trace)
+    }
+
+    fn snapshot_layout_node(&self, node_id: usize, nodes: &mut HashMap<usize, TracedNode>) {
+        let Some(node) = self.nodes.get(node_id) else {
+            return;
+        };
+        let layout_children = node.layout_children.borrow().clone().unwrap_or_default();
+        let measured = self.is_measured_by_blitz(node_id);
+        if !measured {
+            for &child_id in &layout_children {
+                self.snapshot_layout_node(child_id, nodes);
+            }
+        }
+        nodes.insert(
+            node_id,
+            TracedNode {
+                style: node.style().clone(),
+                layout_children,
+                measured,
+            },
+        );
+    }
+
+    /// Whether `compute_child_layout` lays a node out with something other than taffy's block,
+    /// flexbox or grid algorithms
+    fn is_measured_by_blitz(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+        let element_data = match &node.data {
+            NodeData::Document => return false,
+            NodeData::Element(element_data) | NodeData::AnonymousBlock(element_data) => {
+                element_data
+            }
+            _ => return true,
+        };
+        let contained = node.flags.skips_contents() || !node.containment().is_empty();
+        contained
+            || is_replaced_element(element_data)
+            || node.flags.is_table_root()
+            || node.flags.is_ruby_root()
+            || node.flags.is_math_layout()
+            || node.flags.is_inline_root()
+            || node.style().display == Display::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use taffy::{Dimension, Style};
+
+    use super::*;
+
+    fn output(width: f32, height: f32) -> TracedOutput {
+        TracedOutput {
+            size: [width, height],
+            content_size: [width, height],
+            first_baselines: [None, None],
+        }
+    }
+
+    fn call(node_id: usize, depth: usize, out: TracedOutput) -> LayoutCall {
+        LayoutCall {
+            node_id,
+            depth,
+            inputs: TracedInput {
+                run_mode: TracedRunMode::PerformLayout,
+                sizing_mode: TracedSizingMode::InherentSize,
+                axis: TracedAxis::Both,
+                known_dimensions: [None, None],
+                parent_size: [Some(200.0), Some(100.0)],
+                available_space: [TracedSpace::Definite(200.0), TracedSpace::Definite(100.0)],
+                vertical_margins_are_collapsible: [false, false],
+            },
+            output: out,
+        }
+    }
+
+    fn flex_trace(recorded_width: f32) -> LayoutTrace {
+        let container = TracedNode {
+            style: Style {
+                display: Display::Flex,
+                size: taffy::Size {
+                    width: Dimension::length(200.0),
+                    height: Dimension::length(100.0),
+                },
+                ..Style::default()
+            },
+            layout_children: vec![2],
+            measured: false,
+        };
+        let item = TracedNode {
+            style: Style {
+                size: taffy::Size {
+                    width: Dimension::length(50.0),
+                    height: Dimension::auto(),
+                },
+                ..Style::default()
+            },
+            layout_children: Vec::new(),
+            measured: true,
+        };
+        LayoutTrace {
+            root: Some(1),
+            calls: vec![
+                call(2, 1, output(recorded_width, 100.0)),
+                call(1, 0, output(200.0, 100.0)),
+            ],
+            nodes: HashMap::from([(1, container), (2, item)]),
+        }
+    }
+
+    #[test]
+    fn replays_through_json() {
+        let trace = LayoutTrace::from_json(&flex_trace(50.0).to_json().unwrap()).unwrap();
+        assert_eq!(trace.final_layout(2), Some(&output(50.0, 100.0)));
+        assert!(trace.replay().is_match());
+    }
+
+    #[test]
+    fn replays_the_root_in_the_space_of_its_last_layout() {
+        // The root was measured at a narrower width before it was laid out
+        let mut trace = flex_trace(50.0);
+        let mut measure = call(1, 0, output(100.0, 100.0));
+        measure.inputs.run_mode = TracedRunMode::ComputeSize;
+        measure.inputs.available_space[0] = TracedSpace::Definite(100.0);
+        trace.calls.insert(0, measure);
+        trace.nodes.get_mut(&1).unwrap().style.size.width = Dimension::auto();
+        assert!(trace.replay().is_match());
+    }
+
+    #[test]
+    fn reports_mismatched_sizes() {
+        let report = flex_trace(80.0).replay();
+        assert_eq!(
+            report.mismatches,
+            vec![LayoutMismatch {
+                node_id: 2,
+                recorded: [80.0, 100.0],
+                replayed: [50.0, 100.0],
+            }]
+        );
+    }
+}
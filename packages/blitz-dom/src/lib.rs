@@ -37,44 +37,87 @@ mod document;
 pub mod node;
 
 pub mod atom_utils;
+pub mod background_attachment;
+pub mod clip_path;
+pub mod color_scheme;
 mod config;
 mod debug;
+pub mod emulation;
 mod events;
+pub mod font_face_set;
 mod form;
+mod frame;
+mod hit_test;
+mod idle;
+mod image_decode;
+/// Targeted restyles for `:has()` selectors
+mod invalidation;
+mod journal;
 /// Integration of taffy and the DOM.
 pub mod layout;
+mod lifecycle;
+pub mod media;
+mod memory;
+mod metadata;
 mod mutator;
 pub mod navigation;
+pub mod pagination;
 mod query_selector;
+pub mod scrollbar;
+mod spatial_navigation;
 /// Implementations that interact with servo's style engine
 mod stylo;
 pub mod stylo_to_cursor_icon;
+mod system_colors;
+pub mod testing;
 /// High-performance text system singleton
 mod text_system_singleton;
 mod traversal;
 mod url;
+pub mod visual_viewport;
 
 pub mod net;
 pub mod util;
 
 #[cfg(feature = "accessibility")]
 mod accessibility;
+#[cfg(feature = "accessibility")]
+mod accessible_name;
 
+#[cfg(feature = "accessibility")]
+pub use accessibility::{
+    AccessibilityBounds, AccessibilityNodeSnapshot, AccessibilitySnapshot, AccessibilityStates,
+};
+pub use clip_path::ClipRegion;
+pub use color_scheme::ColorSchemeSupport;
 pub use config::DocumentConfig;
 pub use document::{BaseDocument, Document};
+pub use emulation::{DeviceEmulation, ViewportMeta};
+pub use frame::{FrameCallback, FrameCallbackId, FrameStats};
+pub use idle::{IdleCallback, IdleCallbackId, IdleDeadline};
+pub use lifecycle::{DocumentEvent, DocumentLifecycle, DocumentVisibility};
+pub use media::MediaEnvironment;
+pub use memory::{MemoryReport, MemoryUsage};
+pub use metadata::{DocumentIcon, ICON_SIZE};
 pub use markup5ever::{
     LocalName, Namespace, NamespaceStaticSet, Prefix, PrefixStaticSet, QualName, local_name,
     namespace_prefix, namespace_url, ns,
 };
 pub use mutator::DocumentMutator;
 pub use node::{Attribute, ElementData, Node, NodeData, TextNodeData};
+pub use query_selector::PseudoClassState;
+pub use scrollbar::{Scrollbar, ScrollbarMode, ScrollbarOwner, ScrollbarStyle};
+pub use spatial_navigation::SpatialDirection;
+pub use system_colors::{SystemColor, SystemColorPalette, SystemColorTheme};
+pub use visual_viewport::VisualViewport;
 // FontContext has been replaced with cosmyc-text FontSystem
 pub use style::Atom;
 pub use style::invalidation::element::restyle_hints::RestyleHint;
 pub type SelectorList = selectors::SelectorList<style::selector_parser::SelectorImpl>;
 pub use events::{EventDriver, EventHandler, NoopEventHandler};
+pub use font_face_set::{FontFace, FontFaceEvent, FontFaceSet, FontFaceSetStatus, FontFaceStatus};
 pub use navigation::BlitzNavigationProvider;
-pub use text_system_singleton::{TextSystemSingleton, TextSystemSingletonError};
+pub use text_system_singleton::{TextSystem, TextSystemSingleton, TextSystemSingletonError};
 pub use selectors::matching::QuirksMode;
 
 use std::sync::Arc;
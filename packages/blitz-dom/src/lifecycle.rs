@@ -0,0 +1,257 @@
+//! The lifecycle of a document
+//!
+//! A [`BaseDocument`](crate::BaseDocument) moves forward through the states of
+//! [`DocumentLifecycle`], loosely following the `readyState` of an HTML document
+//! (<https://html.spec.whatwg.org/multipage/dom.html#current-document-readiness>):
+//!
+//!  - [`Loading`](DocumentLifecycle::Loading) while the document is being parsed or built
+//!  - [`Interactive`](DocumentLifecycle::Interactive) once the document has been built, while
+//!    sub-resources (stylesheets, images, fonts) may still be loading
+//!  - [`Complete`](DocumentLifecycle::Complete) once no requests (including preloads, but not
+//!    prefetches) are in flight
+//!  - [`Unloading`](DocumentLifecycle::Unloading) when the document is dropped
+//!
+//! Embedders which want to run something once the document has loaded (rather than checking its
+//! state every frame) can listen for [`DocumentEvent`]s, which mirror the `DOMContentLoaded`,
+//! `load` and `visibilitychange` events of the web, and also report the first paint. The shell
+//! tells the document when it has been painted and when its window is shown or hidden, and makes
+//! documents it shows interactive once they are no longer [parsing](crate::Document::is_parsing).
+//!
+//! When a document is dropped it releases everything tied to it which would otherwise outlive it:
+//!
+//!  - in-flight requests are cancelled with [`NetProvider::cancel`], so their handlers (and the
+//!    callbacks they hold) are dropped without being called
+//!  - `@font-face` fonts which are still loading are marked as failed, which wakes anything
+//!    waiting on [`FontFaceSet::ready`](crate::font_face_set::FontFaceSet::ready), and the font
+//!    listeners are dropped
+//!  - lifecycle listeners are dropped after being told about the `Unloading` state
+//!  - thread-local state keyed by the document (IME compositions) and thread-local layout caches
+//!    are cleared
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, channel};
+use std::sync::{Arc, Mutex};
+
+use blitz_traits::net::{
+    BoxedHandler, Bytes, NetCallback, NetHandler, NetProvider, Request, RequestPriority,
+    SharedCallback, Url,
+};
+
+use crate::BaseDocument;
+use crate::net::Resource;
+
+/// The state of a document. States only ever advance (in the order they are declared).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DocumentLifecycle {
+    /// The document is being parsed or built
+    Loading,
+    /// The document has been built, but sub-resources may still be loading
+    Interactive,
+    /// The document and all of its sub-resources have loaded (or failed to)
+    Complete,
+    /// The document is being dropped
+    Unloading,
+}
+
+impl DocumentLifecycle {
+    pub(crate) fn next(self) -> Option<Self> {
+        match self {
+            Self::Loading => Some(Self::Interactive),
+            Self::Interactive => Some(Self::Complete),
+            Self::Complete => Some(Self::Unloading),
+            Self::Unloading => None,
+        }
+    }
+}
+
+pub(crate) type LifecycleListener = Box<dyn FnMut(DocumentLifecycle)>;
+
+/// An event in the life of a document, see [`BaseDocument::add_document_event_listener`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DocumentEvent {
+    /// The document has been built, and became [`Interactive`](DocumentLifecycle::Interactive)
+    DomContentLoaded,
+    /// The document's sub-resources have loaded, and it became
+    /// [`Complete`](DocumentLifecycle::Complete)
+    Load,
+    /// The document has been painted for the first time
+    FirstPaint,
+    /// The document has been shown or hidden
+    VisibilityChange(DocumentVisibility),
+}
+
+/// Whether a document can be seen, see
+/// <https://html.spec.whatwg.org/multipage/interaction.html#page-visibility>
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DocumentVisibility {
+    #[default]
+    Visible,
+    /// The document's window is minimized, or covered by other windows
+    Hidden,
+}
+
+pub(crate) type DocumentEventListener = Box<dyn FnMut(DocumentEvent)>;
+
+impl BaseDocument {
+    /// Call `listener` with every [`DocumentEvent`] from now on. Events which happened before the
+    /// listener was added aren't repeated, so check [`lifecycle`](Self::lifecycle) first.
+    pub fn add_document_event_listener(&mut self, listener: impl FnMut(DocumentEvent) + 'static) {
+        self.document_event_listeners.push(Box::new(listener));
+    }
+
+    /// A channel which receives every [`DocumentEvent`] from now on. It is closed when the
+    /// document is dropped.
+    pub fn document_events(&mut self) -> Receiver<DocumentEvent> {
+        let (sender, receiver) = channel();
+        self.add_document_event_listener(move |event| {
+            let _ = sender.send(event);
+        });
+        receiver
+    }
+
+    pub(crate) fn dispatch_document_event(&mut self, event: DocumentEvent) {
+        for listener in &mut self.document_event_listeners {
+            listener(event);
+        }
+    }
+
+    /// Whether the document can be seen
+    pub fn visibility(&self) -> DocumentVisibility {
+        self.visibility
+    }
+
+    /// Set whether the document can be seen (by the shell, when its window is shown or hidden)
+    pub fn set_visibility(&mut self, visibility: DocumentVisibility) {
+        if self.visibility != visibility {
+            self.visibility = visibility;
+            self.dispatch_document_event(DocumentEvent::VisibilityChange(visibility));
+        }
+    }
+
+    /// Whether the document has been painted yet
+    pub fn has_painted(&self) -> bool {
+        self.has_painted
+    }
+
+    /// Record that the document has been painted (by the shell, after rendering a frame)
+    pub fn mark_painted(&mut self) {
+        if !self.has_painted {
+            self.has_painted = true;
+            self.dispatch_document_event(DocumentEvent::FirstPaint);
+        }
+    }
+}
+
+/// Wraps a document's [`NetProvider`] to count the requests which are in flight, so that the
+/// document knows when it has finished loading.
+pub(crate) struct TrackedNetProvider {
+    inner: Arc<dyn NetProvider<Resource>>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl TrackedNetProvider {
+    pub(crate) fn new(inner: Arc<dyn NetProvider<Resource>>, pending: Arc<AtomicUsize>) -> Self {
+        Self { inner, pending }
+    }
+}
+
+impl NetProvider<Resource> for TrackedNetProvider {
+    fn fetch(&self, doc_id: usize, request: Request, handler: BoxedHandler<Resource>) {
+        // Prefetches (the only low priority preloads) are for later navigations, which the load
+        // event doesn't wait for
+        if request.preload && request.priority == RequestPriority::Low {
+            self.inner.fetch(doc_id, request, handler);
+            return;
+        }
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        let handler = TrackedHandler {
+            handler,
+            _guard: PendingGuard(self.pending.clone()),
+        };
+        self.inner.fetch(doc_id, request, Box::new(handler));
+    }
+
+    fn cancel(&self, doc_id: usize) {
+        self.inner.cancel(doc_id);
+    }
+
+    fn set_user_agent(&self, doc_id: usize, user_agent: Option<String>) {
+        self.inner.set_user_agent(doc_id, user_agent);
+    }
+
+    fn set_document_url(&self, doc_id: usize, url: Option<&Url>) {
+        self.inner.set_document_url(doc_id, url);
+    }
+
+    fn preconnect(&self, doc_id: usize, url: &Url) {
+        self.inner.preconnect(doc_id, url);
+    }
+
+    fn is_custom_scheme(&self, scheme: &str) -> bool {
+        self.inner.is_custom_scheme(scheme)
+    }
+}
+
+/// A request is counted as in flight until its handler has passed on a resource, or until the
+/// handler (or, once it has run, the callback it was given) is dropped without one
+struct TrackedHandler {
+    handler: BoxedHandler<Resource>,
+    _guard: PendingGuard,
+}
+
+impl NetHandler<Resource> for TrackedHandler {
+    fn bytes(self: Box<Self>, doc_id: usize, bytes: Bytes, callback: SharedCallback<Resource>) {
+        let Self {
+            handler,
+            _guard: guard,
+        } = *self;
+        // Handlers which finish on another thread (like images, which are decoded on the decode
+        // pool) hold on to the callback until they do
+        let callback = Arc::new(TrackedCallback {
+            callback,
+            guard: Mutex::new(Some(guard)),
+        });
+        handler.bytes(doc_id, bytes, callback);
+    }
+}
+
+/// The callback of a [`TrackedHandler`], which settles its request once the first resource has
+/// been passed on
+struct TrackedCallback {
+    callback: SharedCallback<Resource>,
+    guard: Mutex<Option<PendingGuard>>,
+}
+
+impl NetCallback<Resource> for TrackedCallback {
+    fn call(&self, doc_id: usize, result: Result<Resource, Option<String>>) {
+        self.callback.call(doc_id, result);
+        self.guard.lock().unwrap_or_else(|err| err.into_inner()).take();
+    }
+}
+
+/// Handles the response to a preload, which the net provider keeps to answer the next request for
+/// the resource with. The preload counts as pending until its response has been received.
+pub(crate) struct PreloadHandler {
+    _guard: PendingGuard,
+}
+
+impl PreloadHandler {
+    pub(crate) fn new(pending: Arc<AtomicUsize>) -> Self {
+        pending.fetch_add(1, Ordering::SeqCst);
+        Self {
+            _guard: PendingGuard(pending),
+        }
+    }
+}
+
+impl NetHandler<Resource> for PreloadHandler {
+    fn bytes(self: Box<Self>, _doc_id: usize, _bytes: Bytes, _callback: SharedCallback<Resource>) {}
+}
+
+struct PendingGuard(Arc<AtomicUsize>);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
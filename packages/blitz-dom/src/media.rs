@@ -0,0 +1,258 @@
+//! The environment `@media` rules are evaluated in
+//!
+//! The [`MediaEnvironment`] is the part of the document's [`Viewport`] that media queries observe.
+//! Changing it (with [`BaseDocument::set_media_environment`], [`BaseDocument::set_viewport`] or
+//! [`BaseDocument::viewport_mut`]) re-evaluates the document's `@media` rules, and restyles the
+//! document if any of them now match differently.
+//!
+//! Stylo doesn't evaluate the `prefers-reduced-motion`, `hover` and `any-hover` media features in
+//! servo mode, so before stylesheets are parsed, each of those features in an `@media` rule is
+//! replaced with a condition which always or never matches, depending on the document's
+//! [`MediaPreferences`] (see [`crate::system_colors`]). The stylesheets which use them are kept
+//! with their source, and parsed again when the preferences change. Blitz also only hovers
+//! elements while they are pressed on devices which can't hover.
+
+use std::ops::Range;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use blitz_traits::shell::{ColorScheme, HoverCapability, MediaType, Viewport};
+use style::servo_arc::Arc as ServoArc;
+use style::stylesheets::{AllowImportRules, OriginSet, Stylesheet, UrlExtraData};
+
+use crate::BaseDocument;
+use crate::net::StylesheetLoader;
+use crate::system_colors::substitute_unsupported_stylesheet;
+
+pub(crate) type MediaListener = Box<dyn FnMut(&MediaEnvironment)>;
+
+/// The properties of the device and user preferences which `@media` rules can match on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaEnvironment {
+    pub media_type: MediaType,
+    /// The width of the viewport, in CSS pixels
+    pub width: f32,
+    /// The height of the viewport, in CSS pixels
+    pub height: f32,
+    /// Device pixels per CSS pixel, including the document's zoom
+    pub device_pixel_ratio: f32,
+    pub color_scheme: ColorScheme,
+    pub prefers_reduced_motion: bool,
+    pub hover: HoverCapability,
+}
+
+impl BaseDocument {
+    pub fn media_environment(&self) -> MediaEnvironment {
+        let viewport = &self.viewport;
+        let scale = viewport.scale();
+        MediaEnvironment {
+            media_type: viewport.media_type,
+            width: viewport.window_size.0 as f32 / scale,
+            height: viewport.window_size.1 as f32 / scale,
+            device_pixel_ratio: scale,
+            color_scheme: viewport.color_scheme,
+            prefers_reduced_motion: viewport.prefers_reduced_motion,
+            hover: viewport.hover,
+        }
+    }
+
+    /// Change the viewport to match `environment`. The zoom is kept, so a change of device pixel
+    /// ratio changes the viewport's hidpi scale.
+    pub fn set_media_environment(&mut self, environment: MediaEnvironment) {
+        let mut viewport = self.unemulated_viewport();
+        let scale = environment.device_pixel_ratio;
+        viewport.hidpi_scale = scale / viewport.zoom;
+        viewport.window_size = (
+            (environment.width * scale).round() as u32,
+            (environment.height * scale).round() as u32,
+        );
+        viewport.media_type = environment.media_type;
+        viewport.color_scheme = environment.color_scheme;
+        viewport.prefers_reduced_motion = environment.prefers_reduced_motion;
+        viewport.hover = environment.hover;
+        self.set_viewport(viewport);
+    }
+
+    /// Call `listener` with the new [`MediaEnvironment`] every time it changes
+    pub fn add_media_listener(&mut self, listener: impl FnMut(&MediaEnvironment) + 'static) {
+        self.media_listeners.push(Box::new(listener));
+    }
+
+    /// Tell the media listeners about the new environment, if it's different from `previous`,
+    /// having parsed the stylesheets which depend on it again
+    pub(crate) fn notify_media_listeners(&mut self, previous: MediaEnvironment) {
+        let environment = self.media_environment();
+        if environment == previous {
+            return;
+        }
+        let loader = self.stylesheet_loader();
+        let preferences = MediaPreferences::of(&self.viewport);
+        if self.preference_sheets.set_preferences(preferences, &loader) {
+            self.stylist.force_stylesheet_origins_dirty(OriginSet::all());
+        }
+        for listener in &mut self.media_listeners {
+            listener(&environment);
+        }
+    }
+}
+
+/// The user preferences `@media` rules can match on which stylo doesn't evaluate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MediaPreferences {
+    pub prefers_reduced_motion: bool,
+    pub hover: HoverCapability,
+}
+
+impl MediaPreferences {
+    pub(crate) fn of(viewport: &Viewport) -> Self {
+        Self {
+            prefers_reduced_motion: viewport.prefers_reduced_motion,
+            hover: viewport.hover,
+        }
+    }
+
+    /// Whether the media feature `name`, with `value` (or in a boolean context without one),
+    /// matches these preferences, if it's one stylo doesn't evaluate and `value` is valid for it
+    fn matches(&self, name: &str, value: Option<&str>) -> Option<bool> {
+        match (name, value) {
+            ("prefers-reduced-motion", None | Some("reduce")) => Some(self.prefers_reduced_motion),
+            ("prefers-reduced-motion", Some("no-preference")) => {
+                Some(!self.prefers_reduced_motion)
+            }
+            ("hover" | "any-hover", None | Some("hover")) => {
+                Some(self.hover == HoverCapability::Hover)
+            }
+            ("hover" | "any-hover", Some("none")) => Some(self.hover == HoverCapability::None),
+            _ => None,
+        }
+    }
+}
+
+/// The media features in the prelude of an `@media` rule which stylo doesn't evaluate (including
+/// their parentheses), and whether each matches `preferences`
+pub(crate) fn preference_features(
+    prelude: &str,
+    preferences: MediaPreferences,
+) -> Vec<(Range<usize>, bool)> {
+    let mut features = Vec::new();
+    let mut start = 0;
+    while let Some(open) = prelude[start..].find('(').map(|open| start + open) {
+        let Some(close) = prelude[open..].find(')').map(|close| open + close) else {
+            break;
+        };
+        // Only the innermost parentheses hold a feature
+        let feature = &prelude[open + 1..close];
+        if let Some(nested) = feature.rfind('(') {
+            start = open + 1 + nested;
+            continue;
+        }
+        let feature = feature.to_ascii_lowercase();
+        let (name, value) = match feature.split_once(':') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (feature.trim(), None),
+        };
+        if let Some(matches) = preferences.matches(name, value) {
+            features.push((open..close + 1, matches));
+        }
+        start = close + 1;
+    }
+    features
+}
+
+/// A stylesheet whose `@media` rules match on the document's [`MediaPreferences`]
+struct PreferenceDependentSheet {
+    sheet: ServoArc<Stylesheet>,
+    css: String,
+    url_data: UrlExtraData,
+}
+
+struct PreferenceDependentSheetsInner {
+    preferences: MediaPreferences,
+    sheets: Vec<PreferenceDependentSheet>,
+}
+
+/// The stylesheets of a document whose `@media` rules match on its [`MediaPreferences`], which
+/// are parsed again when they change. Shared with the threads stylesheets are loaded on.
+#[derive(Clone)]
+pub(crate) struct PreferenceDependentSheets(Arc<Mutex<PreferenceDependentSheetsInner>>);
+
+impl PreferenceDependentSheets {
+    pub(crate) fn new(preferences: MediaPreferences) -> Self {
+        let inner = PreferenceDependentSheetsInner {
+            preferences,
+            sheets: Vec::new(),
+        };
+        Self(Arc::new(Mutex::new(inner)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PreferenceDependentSheetsInner> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Parse `css` (from `url_data`) into a stylesheet with `parse`, once the unsupported CSS in
+    /// it has been substituted for the current preferences, and keep it if it depends on them
+    pub(crate) fn parse(
+        &self,
+        css: &str,
+        url_data: &UrlExtraData,
+        parse: impl Fn(&str) -> ServoArc<Stylesheet>,
+    ) -> ServoArc<Stylesheet> {
+        let mut preferences = self.lock().preferences;
+        loop {
+            let (substituted, dependent) = substitute_unsupported_stylesheet(css, preferences);
+            let sheet = parse(&substituted);
+            if !dependent {
+                return sheet;
+            }
+            // The lock isn't held while parsing, as loading the stylesheet's imports may parse
+            // them straight away, so the preferences may have changed in the meantime
+            let mut inner = self.lock();
+            if inner.preferences == preferences {
+                inner.sheets.push(PreferenceDependentSheet {
+                    sheet: sheet.clone(),
+                    css: css.to_string(),
+                    url_data: url_data.clone(),
+                });
+                return sheet;
+            }
+            preferences = inner.preferences;
+        }
+    }
+
+    /// Parse the stylesheets which depend on the preferences again if they changed, loading
+    /// their imports with `loader`. Returns whether any were parsed.
+    pub(crate) fn set_preferences(
+        &self,
+        preferences: MediaPreferences,
+        loader: &StylesheetLoader,
+    ) -> bool {
+        let mut inner = self.lock();
+        if inner.preferences == preferences {
+            return false;
+        }
+        inner.preferences = preferences;
+        // Stylesheets nothing else refers to any more have been removed from the document
+        inner.sheets.retain(|dependent| !dependent.sheet.is_unique());
+        let sheets: Vec<_> = inner
+            .sheets
+            .iter()
+            .map(|dependent| {
+                let (sheet, url_data) = (dependent.sheet.clone(), dependent.url_data.clone());
+                (sheet, dependent.css.clone(), url_data)
+            })
+            .collect();
+        drop(inner);
+
+        for (sheet, css, url_data) in &sheets {
+            let (substituted, _) = substitute_unsupported_stylesheet(css, preferences);
+            Stylesheet::update_from_str(
+                sheet,
+                &substituted,
+                url_data.clone(),
+                Some(loader),
+                None,
+                AllowImportRules::Yes,
+            );
+        }
+        !sheets.is_empty()
+    }
+}
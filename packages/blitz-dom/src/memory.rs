@@ -0,0 +1,278 @@
+//! Estimates of the memory held by a document and the subsystems it renders with
+//!
+//! [`BaseDocument::memory_report`] walks the document's nodes and asks the text system for its
+//! cache statistics, so that embedders can show an `about:memory`-style view or decide when to
+//! evict caches. Sizes are estimates: they count the data each subsystem owns, but not allocator
+//! overhead, and data shared between nodes (like images, computed styles and the style structs
+//! computed styles share) is counted once.
+
+use std::collections::HashSet;
+use std::ptr;
+use std::sync::Arc;
+
+use blitz_text::fontdb;
+use style::properties::ComputedValues;
+
+use crate::node::{ImageData, NodeData};
+use crate::{Attribute, BaseDocument, Node, TextSystemSingleton};
+
+/// How many of something a subsystem holds, and roughly how many bytes they take up
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl MemoryUsage {
+    fn add(&mut self, bytes: usize) {
+        self.count += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// The memory held by a document and the subsystems it uses
+///
+/// Fonts, text caches and glyph atlases belong to the global text system, so they're shared by
+/// every document, and are zero if the text system hasn't been initialized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// The document's nodes, with their attributes and text
+    pub nodes: MemoryUsage,
+    /// Distinct computed styles of elements, counting the bytes of the style structs they hold
+    pub styles: MemoryUsage,
+    /// Layout results, taffy's layout caches, and laid out inline text of nodes
+    pub layout: MemoryUsage,
+    /// Decoded images (`<img>`s, background images and inline SVGs, counting SVGs' nodes, path
+    /// data and embedded images)
+    pub images: MemoryUsage,
+    /// Font faces loaded into the text system, counting the bytes of those held in memory
+    pub fonts: MemoryUsage,
+    /// Entries in the text system's measurement cache, and the memory of its shaping and
+    /// rasterization caches
+    pub text_caches: MemoryUsage,
+    /// Glyphs in the GPU glyph atlases, and the estimated size of the atlases' textures
+    pub glyph_atlas: MemoryUsage,
+}
+
+impl MemoryReport {
+    /// The estimated bytes held across all subsystems
+    pub fn total_bytes(&self) -> usize {
+        [
+            self.nodes,
+            self.styles,
+            self.layout,
+            self.images,
+            self.fonts,
+            self.text_caches,
+            self.glyph_atlas,
+        ]
+        .iter()
+        .map(|usage| usage.bytes)
+        .sum()
+    }
+}
+
+impl BaseDocument {
+    /// Estimate the memory held by this document and the subsystems it uses
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        let mut seen_styles = HashSet::new();
+        let mut seen_style_structs = HashSet::new();
+        let mut seen_images = HashSet::new();
+
+        for (_, node) in self.nodes.iter() {
+            let layout_bytes = layout_bytes(node);
+            report.nodes.add(node_bytes(node) - layout_bytes.inline);
+            report.layout.add(layout_bytes.inline + layout_bytes.heap);
+
+            let style_data = node.stylo_element_data.borrow();
+            let style = style_data.as_ref().and_then(|data| data.styles.get_primary());
+            let style = style.map(|style| &**style);
+            if let Some(style) = style.filter(|&style| seen_styles.insert(ptr::from_ref(style))) {
+                let struct_bytes = style_bytes(style, &mut seen_style_structs);
+                report.styles.add(size_of::<ComputedValues>() + struct_bytes);
+            }
+            drop(style_data);
+
+            let Some(element) = node.element_data() else {
+                continue;
+            };
+            let background_images = element.background_images.iter().flatten();
+            let images = element
+                .image_data()
+                .into_iter()
+                .chain(background_images.map(|background| &background.image));
+            for image in images {
+                match image {
+                    ImageData::Raster(raster) => {
+                        if seen_images.insert(Arc::as_ptr(&raster.data).cast::<()>()) {
+                            report.images.add(raster.data.len());
+                        }
+                    }
+                    #[cfg(feature = "svg")]
+                    ImageData::Svg(tree) => {
+                        if seen_images.insert(Arc::as_ptr(tree).cast::<()>()) {
+                            report.images.add(svg_bytes(tree));
+                        }
+                    }
+                    ImageData::None => {}
+                }
+            }
+        }
+
+        let _ = TextSystemSingleton::with_font_system(|font_system| {
+            report.fonts = font_memory(font_system.db());
+        });
+        // Without the `gpu` feature there are no text caches or glyph atlas besides the fonts
+        #[cfg(feature = "gpu")]
+        let _ = TextSystemSingleton::with_text_system(|text_system| {
+            let stats = text_system.get_comprehensive_stats();
+            let cosmyc = &stats.cosmyc_integration_stats.integration_metrics;
+            report.text_caches = MemoryUsage {
+                count: stats.measurement_stats.current_cache_size,
+                bytes: cosmyc.total_memory_usage + stats.gpu_cache_stats.size_bytes as usize,
+            };
+            let atlas = &stats.atlas_stats;
+            report.glyph_atlas = MemoryUsage {
+                count: atlas.glyph_allocations.saturating_sub(atlas.glyph_deallocations) as usize,
+                bytes: atlas.estimated_memory_usage,
+            };
+        });
+
+        report
+    }
+}
+
+fn node_bytes(node: &Node) -> usize {
+    let mut bytes = size_of::<Node>() + node.children.capacity() * size_of::<usize>();
+    match &node.data {
+        NodeData::Element(element) | NodeData::AnonymousBlock(element) => {
+            bytes += element.attrs.capacity() * size_of::<Attribute>();
+            bytes += element
+                .attrs
+                .iter()
+                .map(|attr| attr.value.capacity())
+                .sum::<usize>();
+        }
+        NodeData::Text(text) => bytes += text.content.capacity(),
+        NodeData::Document | NodeData::Comment => {}
+    }
+    bytes
+}
+
+/// The bytes of the style structs of `style` which aren't in `seen` (styles with the same values
+/// for the properties of a style struct share it)
+fn style_bytes(style: &ComputedValues, seen: &mut HashSet<*const ()>) -> usize {
+    fn distinct<T>(style_struct: &T, seen: &mut HashSet<*const ()>) -> usize {
+        if seen.insert(ptr::from_ref(style_struct).cast::<()>()) {
+            size_of::<T>()
+        } else {
+            0
+        }
+    }
+
+    [
+        distinct(style.get_background(), seen),
+        distinct(style.get_border(), seen),
+        distinct(style.get_box(), seen),
+        distinct(style.get_column(), seen),
+        distinct(style.get_counters(), seen),
+        distinct(style.get_effects(), seen),
+        distinct(style.get_font(), seen),
+        distinct(style.get_inherited_box(), seen),
+        distinct(style.get_inherited_table(), seen),
+        distinct(style.get_inherited_text(), seen),
+        distinct(style.get_inherited_ui(), seen),
+        distinct(style.get_list(), seen),
+        distinct(style.get_margin(), seen),
+        distinct(style.get_outline(), seen),
+        distinct(style.get_padding(), seen),
+        distinct(style.get_position(), seen),
+        distinct(style.get_svg(), seen),
+        distinct(style.get_table(), seen),
+        distinct(style.get_text(), seen),
+        distinct(style.get_ui(), seen),
+    ]
+    .iter()
+    .sum()
+}
+
+/// The bytes of an SVG's nodes, path data and embedded images
+#[cfg(feature = "svg")]
+fn svg_bytes(tree: &usvg::Tree) -> usize {
+    size_of::<usvg::Tree>() + svg_group_bytes(tree.root())
+}
+
+#[cfg(feature = "svg")]
+fn svg_group_bytes(group: &usvg::Group) -> usize {
+    let mut bytes = size_of::<usvg::Group>();
+    if let Some(clip_path) = group.clip_path() {
+        bytes += svg_group_bytes(clip_path.root());
+    }
+    if let Some(mask) = group.mask() {
+        bytes += svg_group_bytes(mask.root());
+    }
+    for node in group.children() {
+        bytes += size_of::<usvg::Node>();
+        bytes += match node {
+            usvg::Node::Group(group) => svg_group_bytes(group),
+            usvg::Node::Path(path) => {
+                let data = path.data();
+                size_of::<usvg::Path>() + size_of_val(data.points()) + size_of_val(data.verbs())
+            }
+            usvg::Node::Image(image) => {
+                size_of::<usvg::Image>()
+                    + match image.kind() {
+                        usvg::ImageKind::JPEG(data)
+                        | usvg::ImageKind::PNG(data)
+                        | usvg::ImageKind::GIF(data)
+                        | usvg::ImageKind::WEBP(data) => data.len(),
+                        usvg::ImageKind::SVG(tree) => svg_bytes(tree),
+                    }
+            }
+            usvg::Node::Text(text) => size_of::<usvg::Text>() + svg_group_bytes(text.flattened()),
+        };
+    }
+    bytes
+}
+
+/// The bytes of a node's layout data stored in the node itself, and stored elsewhere
+struct LayoutBytes {
+    inline: usize,
+    heap: usize,
+}
+
+fn layout_bytes(node: &Node) -> LayoutBytes {
+    let inline = size_of_val(&node.cache)
+        + size_of_val(&node.unrounded_layout)
+        + size_of_val(&node.final_layout);
+
+    let children = |list: &Option<Vec<usize>>| list.as_ref().map_or(0, Vec::capacity);
+    let mut heap = (children(&node.layout_children.borrow())
+        + children(&node.paint_children.borrow()))
+        * size_of::<usize>();
+    if let Some(layout) = node
+        .element_data()
+        .and_then(|element| element.inline_layout_data.as_ref())
+    {
+        heap += size_of_val(&**layout);
+    }
+    LayoutBytes { inline, heap }
+}
+
+/// The font faces in `db`, counting the bytes of those loaded into memory (rather than read
+/// from files when they're used). Faces from the same font collection share their data.
+fn font_memory(db: &fontdb::Database) -> MemoryUsage {
+    let mut usage = MemoryUsage::default();
+    let mut seen_data = HashSet::new();
+    for face in db.faces() {
+        usage.count += 1;
+        let fontdb::Source::Binary(data) = &face.source else {
+            continue;
+        };
+        if seen_data.insert(Arc::as_ptr(data) as *const u8) {
+            usage.bytes += (**data).as_ref().len();
+        }
+    }
+    usage
+}
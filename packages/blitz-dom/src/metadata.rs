@@ -0,0 +1,221 @@
+//! The document's title, icon and theme color, which shells show in the window's chrome
+//!
+//! They're read again whenever the elements they come from change, and the document's
+//! [`ShellProvider`](blitz_traits::shell::ShellProvider) is told when they're different:
+//!
+//! - The title is the text of the first `<title>`, with its whitespace collapsed.
+//! - The icon is the image linked by the last `<link rel="icon">`, which is fetched and decoded
+//!   like other images. SVG icons are kept as SVGs, for the shell to rasterize (see
+//!   [`DocumentIcon`]).
+//! - The theme color is the `content` of the first `<meta name="theme-color">` whose `media`
+//!   matches the document's device, as `@media` rules do.
+
+#[cfg(feature = "svg")]
+use std::sync::Arc;
+
+use blitz_traits::net::{Request, RequestPriority, Url};
+use blitz_traits::shell::WindowIcon;
+use cssparser::{Parser, ParserInput};
+use markup5ever::local_name;
+use style::media_queries::MediaList;
+use style::parser::ParserContext;
+use style::stylesheets::{CssRuleType, Origin};
+use style_traits::ParsingMode;
+
+use crate::net::ImageHandler;
+use crate::node::RasterImageData;
+use crate::traversal::TreeTraverser;
+use crate::util::{Color, ImageType};
+use crate::{BaseDocument, Node};
+
+/// The size (in px) icons are decoded at, which is large enough for any window's chrome
+pub const ICON_SIZE: u32 = 64;
+
+/// A document's icon
+#[derive(Debug, Clone)]
+pub enum DocumentIcon {
+    /// A raster image, scaled down when it's decoded if it's larger than [`ICON_SIZE`]
+    Raster(WindowIcon),
+    /// An SVG image. The document doesn't paint, so it can't tell the shell provider about SVG
+    /// icons: shells rasterize them (to fit within [`ICON_SIZE`]) when they redraw, which the
+    /// document requests once one is loaded.
+    #[cfg(feature = "svg")]
+    Svg(Arc<usvg::Tree>),
+}
+
+/// SVG icons are compared by identity
+impl PartialEq for DocumentIcon {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Raster(icon), Self::Raster(other)) => icon == other,
+            #[cfg(feature = "svg")]
+            (Self::Svg(svg), Self::Svg(other)) => Arc::ptr_eq(svg, other),
+            #[cfg(feature = "svg")]
+            _ => false,
+        }
+    }
+}
+
+impl BaseDocument {
+    /// The document's title, from its `<title>`
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The document's icon, once the image its `<link rel="icon">` links to has loaded
+    pub fn icon(&self) -> Option<&DocumentIcon> {
+        self.icon.as_ref()
+    }
+
+    /// The document's theme color, from its `<meta name="theme-color">`
+    pub fn theme_color(&self) -> Option<Color> {
+        self.theme_color
+    }
+
+    /// Read the document's title again, after its `<title>` may have been changed
+    pub(crate) fn update_title(&mut self) {
+        let title = self
+            .find_title_node()
+            .map(|node| collapse_whitespace(&node.text_content()))
+            .unwrap_or_default();
+        if title == self.title {
+            return;
+        }
+        self.title = title.clone();
+        self.shell_provider.set_window_title(title);
+    }
+
+    /// Find the document's icon link again, after one may have been changed, and fetch the icon
+    /// if it's a different one
+    pub(crate) fn update_icon(&mut self) {
+        let link = TreeTraverser::new(self)
+            .filter(|node_id| is_icon_link(&self.nodes[*node_id]))
+            .filter_map(|node_id| {
+                let href = self.nodes[node_id].attr(local_name!("href"))?;
+                Some((node_id, self.url.resolve_relative(href)?))
+            })
+            .last();
+        if link == self.icon_link {
+            return;
+        }
+        self.icon_link = link.clone();
+
+        let Some((node_id, url)) = link else {
+            self.icon = None;
+            self.shell_provider.set_window_icon(None);
+            return;
+        };
+        let handler = ImageHandler::new(node_id, ImageType::Icon)
+            .with_url(url.clone())
+            .with_target_size(ICON_SIZE, ICON_SIZE);
+        let request = Request::get(url).with_priority(RequestPriority::Low);
+        self.net_provider.fetch(self.id(), request, Box::new(handler));
+    }
+
+    /// Use the icon fetched for the `<link>` element `node_id`
+    pub(crate) fn set_icon(&mut self, node_id: usize, image: RasterImageData) {
+        // The icon link may have changed while the icon was being fetched
+        let is_icon_link = self
+            .icon_link
+            .as_ref()
+            .is_some_and(|(icon_node_id, _)| *icon_node_id == node_id);
+        if !is_icon_link {
+            return;
+        }
+
+        let icon = WindowIcon {
+            width: image.width,
+            height: image.height,
+            rgba: image.data,
+        };
+        self.icon = Some(DocumentIcon::Raster(icon.clone()));
+        self.shell_provider.set_window_icon(Some(icon));
+    }
+
+    /// Use the SVG icon fetched for the `<link>` element `node_id`, which the shell rasterizes
+    /// when it next redraws
+    #[cfg(feature = "svg")]
+    pub(crate) fn set_svg_icon(&mut self, node_id: usize, svg: Arc<usvg::Tree>) {
+        let is_icon_link = self
+            .icon_link
+            .as_ref()
+            .is_some_and(|(icon_node_id, _)| *icon_node_id == node_id);
+        if !is_icon_link {
+            return;
+        }
+
+        self.icon = Some(DocumentIcon::Svg(svg));
+        self.shell_provider.request_redraw();
+    }
+
+    /// Read the document's theme color meta tags again, after one may have been changed or the
+    /// device their media queries are matched against has
+    pub(crate) fn update_theme_color(&mut self) {
+        let theme_color = TreeTraverser::new(self)
+            .filter_map(|node_id| self.nodes[node_id].element_data())
+            .filter(|element| {
+                element.name.local == local_name!("meta")
+                    && element
+                        .attr(local_name!("name"))
+                        .is_some_and(|name| name.eq_ignore_ascii_case("theme-color"))
+                    && self.media_matches(element.attr(local_name!("media")))
+            })
+            .find_map(|element| {
+                let content = element.attr(local_name!("content"))?;
+                Some(color::parse_color(content.trim()).ok()?.to_alpha_color())
+            });
+        if theme_color == self.theme_color {
+            return;
+        }
+        self.theme_color = theme_color;
+        let rgba = theme_color.map(|color| color.to_rgba8().to_u8_array());
+        self.shell_provider.set_theme_color(rgba);
+    }
+
+    /// Whether the media query list `media` matches the document's device. Meta tags without
+    /// one always match.
+    fn media_matches(&self, media: Option<&str>) -> bool {
+        let Some(media) = media.filter(|media| !media.trim().is_empty()) else {
+            return true;
+        };
+        let url_data = self.url.url_extra_data();
+        let quirks_mode = self.stylist.quirks_mode();
+        let context = ParserContext::new(
+            Origin::Author,
+            &url_data,
+            Some(CssRuleType::Media),
+            ParsingMode::DEFAULT,
+            quirks_mode,
+            Default::default(),
+            None,
+            None,
+        );
+        let mut input = ParserInput::new(media);
+        let media_list = MediaList::parse(&context, &mut Parser::new(&mut input));
+        media_list.evaluate(self.stylist.device(), quirks_mode)
+    }
+}
+
+/// Whether `node` is a `<link>` whose `rel` includes `icon`
+fn is_icon_link(node: &Node) -> bool {
+    node.data.is_element_with_tag_name(&local_name!("link"))
+        && node.attr(local_name!("rel")).is_some_and(|rels| {
+            rels.split_ascii_whitespace()
+                .any(|rel| rel.eq_ignore_ascii_case("icon"))
+        })
+}
+
+/// `text` with leading and trailing whitespace removed, and other runs of it replaced by a space
+fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_whitespace_is_collapsed() {
+        assert_eq!(collapse_whitespace("\n  A\t title \n"), "A title");
+    }
+}
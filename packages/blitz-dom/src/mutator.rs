@@ -1,19 +1,24 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::ops::{Deref, DerefMut};
 
 use blitz_text::Edit;
-use blitz_traits::net::Request;
+use blitz_traits::net::{Request, RequestPriority, Url};
+use blitz_traits::script::{Script, ScriptKind, ScriptSource};
 use blitz_traits::shell::Viewport;
 use selectors::matching::QuirksMode;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::stylesheets::OriginSet;
 
-use crate::document::make_device;
+use crate::journal::{InverseOp, MutationJournal, NodeSnapshot};
 use crate::net::{CssHandler, ImageHandler};
 use crate::node::{CanvasData, NodeFlags, SpecialElementData};
+use crate::traversal::TreeTraverser;
 use crate::util::ImageType;
-use crate::{Attribute, BaseDocument, ElementData, Node, NodeData, QualName, local_name, ns};
+use crate::{
+    Attribute, BaseDocument, ElementData, IdleDeadline, LocalName, Node, NodeData, QualName,
+    local_name, ns,
+};
 
 macro_rules! tag_and_attr {
     ($tag:tt, $attr:tt) => {
@@ -33,6 +38,7 @@ enum SpecialOp {
     LoadImage(usize),
     LoadStylesheet(usize),
     UnloadStylesheet(usize),
+    ProcessLinkHints(usize),
     LoadCustomPaintSource(usize),
     ProcessButtonInput(usize),
 }
@@ -47,7 +53,14 @@ pub struct DocumentMutator<'doc> {
     // Tracked nodes for deferred processing when mutations have completed
     title_node: Option<usize>,
     style_nodes: HashSet<usize>,
+    /// `<script>` elements which may have become ready to run, in the order they did
+    script_nodes: Vec<usize>,
     form_nodes: HashSet<usize>,
+    /// Whether a `<meta>` element (which may be the viewport, color scheme or theme color meta
+    /// tag) has changed
+    meta_changed: bool,
+    /// Whether a `<link>` element (which may be the icon link) has changed
+    links_changed: bool,
 
     /// Whether an element/attribute that affect animation status has been seen
     recompute_is_animating: bool,
@@ -55,6 +68,23 @@ pub struct DocumentMutator<'doc> {
     /// The (latest) node which has been mounted in and had autofocus=true, if any
     #[cfg(feature = "autofocus")]
     node_to_autofocus: Option<usize>,
+
+    /// Invalidation held back while a [`transaction`](Self::transaction) is running
+    deferred: Option<DeferredInvalidation>,
+    /// The number of `:has()` anchors restyled because of mutations made through this mutator
+    restyled_anchors: usize,
+}
+
+/// Invalidation which is deferred until the outermost transaction has finished, so that it is done
+/// once per node rather than once per mutation
+#[derive(Default)]
+struct DeferredInvalidation {
+    /// Nodes which have been snapshotted, so that their state from before the transaction is kept
+    snapshotted: HashSet<usize>,
+    /// Nodes whose subtree needs restyling, because their attributes changed
+    restyle_subtrees: HashSet<usize>,
+    /// Nodes whose children changed, with the children which were added or removed
+    children_changed: HashMap<usize, Vec<usize>>,
 }
 
 impl Drop for DocumentMutator<'_> {
@@ -70,10 +100,15 @@ impl DocumentMutator<'_> {
             eager_op_queue: Vec::new(),
             title_node: None,
             style_nodes: HashSet::new(),
+            script_nodes: Vec::new(),
             form_nodes: HashSet::new(),
+            meta_changed: false,
+            links_changed: false,
             recompute_is_animating: false,
             #[cfg(feature = "autofocus")]
             node_to_autofocus: None,
+            deferred: None,
+            restyled_anchors: 0,
         }
     }
 
@@ -115,11 +150,15 @@ impl DocumentMutator<'_> {
     // Node creation methods
 
     pub fn create_comment_node(&mut self) -> usize {
-        self.doc.create_node(NodeData::Comment)
+        let id = self.doc.create_node(NodeData::Comment);
+        self.record(InverseOp::DropNode { node_id: id });
+        id
     }
 
     pub fn create_text_node(&mut self, text: &str) -> usize {
-        self.doc.create_text_node(text)
+        let id = self.doc.create_text_node(text);
+        self.record(InverseOp::DropNode { node_id: id });
+        id
     }
 
     pub fn create_element(&mut self, name: QualName, attrs: Vec<Attribute>, quirks_mode: QuirksMode) -> usize {
@@ -127,6 +166,7 @@ impl DocumentMutator<'_> {
         data.flush_style_attribute(self.doc.guard(), &self.doc.url.url_extra_data(), quirks_mode);
 
         let id = self.doc.create_node(NodeData::Element(data));
+        self.record(InverseOp::DropNode { node_id: id });
         let node = match self.doc.get_node(id) {
             Some(node) => node,
             None => {
@@ -141,14 +181,32 @@ impl DocumentMutator<'_> {
         id
     }
 
+    /// Mark the `<script>` element `node_id` as created by the HTML parser. It isn't run until the
+    /// parser [completes](Self::complete_script) it.
+    pub fn set_parser_inserted(&mut self, node_id: usize) {
+        self.doc.nodes[node_id]
+            .flags
+            .insert(NodeFlags::IS_PARSER_INSERTED);
+    }
+
+    /// Run the `<script>` element `node_id`, whose end tag the HTML parser has just parsed
+    pub fn complete_script(&mut self, node_id: usize) {
+        self.doc.nodes[node_id]
+            .flags
+            .remove(NodeFlags::IS_PARSER_INSERTED);
+        self.script_nodes.push(node_id);
+    }
+
     pub fn deep_clone_node(&mut self, node_id: usize) -> usize {
-        self.doc.deep_clone_node(node_id)
+        let id = self.doc.deep_clone_node(node_id);
+        self.record(InverseOp::DropNode { node_id: id });
+        id
     }
 
     // Node mutation methods
 
     pub fn set_node_text(&mut self, node_id: usize, value: &str) {
-        let node = match self.doc.get_node_mut(node_id) {
+        let node = match self.doc.nodes.get_mut(node_id) {
             Some(node) => node,
             None => {
                 eprintln!(
@@ -167,6 +225,12 @@ impl DocumentMutator<'_> {
 
         let changed = text.content != value;
         if changed {
+            if let Some(journal) = &mut self.doc.journal {
+                journal.record(InverseOp::SetText {
+                    node_id,
+                    text: text.content.clone(),
+                });
+            }
             text.content.clear();
             text.content.push_str(value);
             let parent = node.parent;
@@ -177,6 +241,12 @@ impl DocumentMutator<'_> {
     pub fn append_text_to_node(&mut self, node_id: usize, text: &str) -> Result<(), AppendTextErr> {
         match self.doc.nodes[node_id].text_data_mut() {
             Some(data) => {
+                if let Some(journal) = &mut self.doc.journal {
+                    journal.record(InverseOp::SetText {
+                        node_id,
+                        text: data.content.clone(),
+                    });
+                }
                 data.content += text;
                 Ok(())
             }
@@ -212,20 +282,25 @@ impl DocumentMutator<'_> {
     }
 
     pub fn set_attribute(&mut self, node_id: usize, name: QualName, value: &str) {
-        self.doc.snapshot_node(node_id);
+        self.snapshot_node(node_id);
+        self.restyle_subtree(node_id);
 
         // Get quirks_mode before mutable borrows to avoid borrow conflicts
         let quirks_mode = self.doc.quirks_mode();
 
         let node = &mut self.doc.nodes[node_id];
-        if let Some(data) = &mut *node.stylo_element_data.borrow_mut() {
-            data.hint |= RestyleHint::restyle_subtree();
-        }
-
         let NodeData::Element(ref mut element) = node.data else {
             return;
         };
 
+        if let Some(journal) = &mut self.doc.journal {
+            let previous = element.attrs.iter().find(|attr| attr.name == name);
+            journal.record(InverseOp::SetAttribute {
+                node_id,
+                name: name.clone(),
+                value: previous.map(|attr| attr.value.clone()),
+            });
+        }
         element.attrs.set(name.clone(), value);
 
         let tag = &element.name.local;
@@ -263,28 +338,33 @@ impl DocumentMutator<'_> {
             self.load_image(node_id);
         } else if (tag, attr) == tag_and_attr!("canvas", "src") {
             self.load_custom_paint_src(node_id);
+        } else if (tag, attr) == tag_and_attr!("script", "src") {
+            self.script_nodes.push(node_id);
+        } else if *tag == local_name!("meta") {
+            self.meta_changed = true;
+        } else if *tag == local_name!("link") {
+            self.links_changed = true;
         }
     }
 
     pub fn clear_attribute(&mut self, node_id: usize, name: QualName) {
-        self.doc.snapshot_node(node_id);
+        self.snapshot_node(node_id);
+        self.restyle_subtree(node_id);
 
         let node = &mut self.doc.nodes[node_id];
-
-        let mut stylo_element_data = node.stylo_element_data.borrow_mut();
-        if let Some(data) = &mut *stylo_element_data {
-            data.hint |= RestyleHint::restyle_subtree();
-        }
-        drop(stylo_element_data);
-
         let Some(element) = node.element_data_mut() else {
             return;
         };
 
-        let removed_attr = element.attrs.remove(&name);
-        let had_attr = removed_attr.is_some();
-        if !had_attr {
+        let Some(removed_attr) = element.attrs.remove(&name) else {
             return;
+        };
+        if let Some(journal) = &mut self.doc.journal {
+            journal.record(InverseOp::SetAttribute {
+                node_id,
+                name: name.clone(),
+                value: Some(removed_attr.value),
+            });
         }
 
         // Extract element info before text system operations
@@ -324,11 +404,17 @@ impl DocumentMutator<'_> {
             self.recompute_is_animating = true;
         } else if (tag, attr) == tag_and_attr!("link", "href") {
             self.unload_stylesheet(node_id);
+            self.links_changed = true;
+        } else if *tag == local_name!("link") {
+            self.links_changed = true;
+        } else if *tag == local_name!("meta") {
+            self.meta_changed = true;
         }
     }
 
     /// Remove the node from it's parent but don't drop it
     pub fn remove_node(&mut self, node_id: usize) {
+        self.record_place(node_id);
         let node = &mut self.doc.nodes[node_id];
 
         // Update child_idx values
@@ -336,12 +422,33 @@ impl DocumentMutator<'_> {
             let parent = &mut self.doc.nodes[parent_id];
             parent.children.retain(|id| *id != node_id);
             self.maybe_record_node(parent_id);
+            self.invalidate_children(parent_id, &[node_id]);
         }
 
         self.process_removed_subtree(node_id);
     }
 
+    /// Remove the node from it's parent and drop it (along with its descendants).
+    ///
+    /// While journaling is enabled, a snapshot of the dropped nodes is recorded, so that removing
+    /// them can be undone.
     pub fn remove_and_drop_node(&mut self, node_id: usize) -> Option<Node> {
+        if self.doc.journal.is_some() && self.doc.get_node(node_id).is_some() {
+            self.record_place(node_id);
+            let nodes = TreeTraverser::new_with_root(self.doc, node_id)
+                .map(|id| {
+                    let node = &self.doc.nodes[id];
+                    NodeSnapshot {
+                        id,
+                        parent: node.parent.filter(|_| id != node_id),
+                        data: node.data.clone(),
+                        children: node.children.clone(),
+                    }
+                })
+                .collect();
+            self.record(InverseOp::Restore { nodes });
+        }
+
         self.process_removed_subtree(node_id);
 
         fn remove_node_ignoring_parent(mutr: &mut DocumentMutator, node_id: usize) -> Option<Node> {
@@ -361,6 +468,7 @@ impl DocumentMutator<'_> {
             let parent = &mut self.doc.nodes[parent_id];
             parent.children.retain(|id| *id != node_id);
             self.maybe_record_node(parent_id);
+            self.invalidate_children(parent_id, &[node_id]);
         }
 
         node
@@ -417,6 +525,10 @@ impl DocumentMutator<'_> {
         child_ids: &[usize],
         insert_children_fn: &dyn Fn(&mut Node, &[usize]),
     ) {
+        for child_id in child_ids.iter().copied() {
+            self.record_place(child_id);
+        }
+
         let new_parent = &mut self.doc.nodes[parent_id];
         let new_parent_is_in_doc = new_parent.flags.is_in_document();
 
@@ -436,10 +548,12 @@ impl DocumentMutator<'_> {
 
                 old_parent.children.retain(|id| *id != child_id);
                 self.maybe_record_node(old_parent_id);
+                self.invalidate_children(old_parent_id, &[child_id]);
             }
         }
 
         self.maybe_record_node(parent_id);
+        self.invalidate_children(parent_id, child_ids);
     }
 
     // Tree mutation methods (that defer to other methods)
@@ -463,6 +577,13 @@ impl DocumentMutator<'_> {
     }
 
     pub fn reparent_children(&mut self, old_parent_id: usize, new_parent_id: usize) {
+        // Record where each child was before they are taken from the old parent (which
+        // `append_children` can't see)
+        if self.doc.journal.is_some() {
+            for child_id in self.doc.nodes[old_parent_id].children.clone() {
+                self.record_place(child_id);
+            }
+        }
         let child_ids = std::mem::take(&mut self.doc.nodes[old_parent_id].children);
         self.maybe_record_node(old_parent_id);
         self.append_children(new_parent_id, &child_ids);
@@ -474,15 +595,292 @@ impl DocumentMutator<'_> {
     }
 }
 
+// Reloading resources
+impl DocumentMutator<'_> {
+    /// Fetch the linked stylesheets and images which `changed` returns true for the URLs of
+    /// again, to pick up changes to them (like assets being edited while an app hot-reloads)
+    pub fn reload_resources(&mut self, changed: impl Fn(&Url) -> bool) {
+        for (node_id, node) in self.doc.nodes.iter() {
+            let Some(element) = node.data.downcast_element() else {
+                continue;
+            };
+            if !node.is_in_document() {
+                continue;
+            }
+            let (op, url_attr) = match element.name.local.as_ref() {
+                "link" => (SpecialOp::LoadStylesheet(node_id), local_name!("href")),
+                "img" => (SpecialOp::LoadImage(node_id), local_name!("src")),
+                _ => continue,
+            };
+            let url = element.attr(url_attr).map(|url| self.doc.resolve_url(url));
+            if url.is_some_and(|url| changed(&url)) {
+                self.eager_op_queue.push(op);
+            }
+        }
+        self.flush_eager_ops();
+    }
+}
+
+// Undo history
+impl DocumentMutator<'_> {
+    /// Start recording the inverse of every mutation, so that up to `max_transactions`
+    /// transactions can be undone with [`undo_last_transaction`](Self::undo_last_transaction).
+    /// If journaling is already enabled, this only changes the number of transactions kept.
+    pub fn enable_journal(&mut self, max_transactions: usize) {
+        match &mut self.doc.journal {
+            Some(journal) => journal.set_limit(max_transactions),
+            None => self.doc.journal = Some(MutationJournal::new(max_transactions)),
+        }
+    }
+
+    /// Stop recording mutations and discard the undo history
+    pub fn disable_journal(&mut self) {
+        self.doc.journal = None;
+    }
+
+    pub fn is_journaling(&self) -> bool {
+        self.doc.journal.is_some()
+    }
+
+    /// End the current transaction, so that mutations made after this are undone separately from
+    /// those made before it. Transactions are also committed when the mutator is flushed.
+    pub fn commit_transaction(&mut self) {
+        if let Some(journal) = &mut self.doc.journal {
+            journal.commit();
+        }
+    }
+
+    /// Whether there is a transaction which can be undone
+    pub fn can_undo(&self) -> bool {
+        self.doc.journal.as_ref().is_some_and(|journal| journal.can_undo())
+    }
+
+    /// Revert every mutation made in the most recent transaction (committing the current one
+    /// first). Returns `false` if journaling is disabled or there is nothing to undo.
+    pub fn undo_last_transaction(&mut self) -> bool {
+        let Some(journal) = &mut self.doc.journal else {
+            return false;
+        };
+        let Some(transaction) = journal.pop() else {
+            return false;
+        };
+
+        // The mutations which revert the transaction aren't themselves recorded
+        let journal = self.doc.journal.take();
+        for op in transaction.ops.into_iter().rev() {
+            self.apply_inverse_op(op);
+        }
+        self.doc.journal = journal;
+        true
+    }
+
+    fn record(&mut self, op: InverseOp) {
+        if let Some(journal) = &mut self.doc.journal {
+            journal.record(op);
+        }
+    }
+
+    /// Record where a node currently is in the tree, so that it can be moved back there
+    fn record_place(&mut self, node_id: usize) {
+        if self.doc.journal.is_none() {
+            return;
+        }
+        let parent = self.doc.nodes[node_id].parent;
+        let next_sibling = parent.and_then(|parent_id| {
+            let siblings = &self.doc.nodes[parent_id].children;
+            let idx = siblings.iter().position(|id| *id == node_id)?;
+            siblings.get(idx + 1).copied()
+        });
+        self.record(InverseOp::Place {
+            node_id,
+            parent,
+            next_sibling,
+        });
+    }
+
+    fn apply_inverse_op(&mut self, op: InverseOp) {
+        match op {
+            InverseOp::SetText { node_id, text } => {
+                if self.doc.get_node(node_id).is_some() {
+                    self.set_node_text(node_id, &text);
+                }
+            }
+            InverseOp::SetAttribute {
+                node_id,
+                name,
+                value,
+            } => {
+                if self.doc.get_node(node_id).is_none() {
+                    return;
+                }
+                match value {
+                    Some(value) => self.set_attribute(node_id, name, &value),
+                    None => self.clear_attribute(node_id, name),
+                }
+            }
+            InverseOp::Place {
+                node_id,
+                parent,
+                next_sibling,
+            } => {
+                let Some(node) = self.doc.get_node(node_id) else {
+                    return;
+                };
+                if node.parent.is_some() {
+                    self.remove_node(node_id);
+                }
+                let Some(parent_id) = parent else {
+                    return;
+                };
+                match next_sibling {
+                    Some(next_id)
+                        if self.doc.get_node(next_id).and_then(|n| n.parent) == Some(parent_id) =>
+                    {
+                        self.insert_nodes_before(next_id, &[node_id])
+                    }
+                    _ => self.append_children(parent_id, &[node_id]),
+                }
+            }
+            InverseOp::DropNode { node_id } => {
+                if self.doc.get_node(node_id).is_some() {
+                    self.remove_and_drop_node(node_id);
+                }
+            }
+            InverseOp::Restore { nodes } => {
+                // Nodes created since then under the same ids must not be overwritten
+                if nodes.iter().any(|node| self.doc.get_node(node.id).is_some()) {
+                    return;
+                }
+                for snapshot in nodes {
+                    self.doc.create_node_with_id(snapshot.id, snapshot.data);
+                    let node = &mut self.doc.nodes[snapshot.id];
+                    node.parent = snapshot.parent;
+                    node.children = snapshot.children;
+                }
+            }
+        }
+    }
+}
+
+// Batched transactions
+impl DocumentMutator<'_> {
+    /// Apply a batch of mutations together. If `mutations` returns an error, every mutation it
+    /// made is reverted before the error is returned.
+    ///
+    /// Restyle hints, and the invalidation of `:has()` selectors which depend on the children of
+    /// changed nodes, are deferred until the outermost transaction has finished, and are then
+    /// applied once for each affected node, rather than once for each mutation. The layout of
+    /// restyled nodes is invalidated when they are next restyled, so it is deferred too.
+    ///
+    /// Transactions can be nested, in which case an inner transaction which fails only reverts
+    /// its own mutations. While journaling is enabled, the mutations of a successful transaction
+    /// are undone together with those of the journal's current transaction.
+    pub fn transaction<T, E>(
+        &mut self,
+        mutations: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let is_outermost = self.deferred.is_none();
+        if is_outermost {
+            self.deferred = Some(DeferredInvalidation::default());
+        }
+
+        // Record the transaction's mutations in a journal of its own, so that they can be
+        // reverted without touching the undo history
+        let outer_journal = self.doc.journal.replace(MutationJournal::new(usize::MAX));
+        let result = mutations(self);
+        if result.is_err() {
+            while self.undo_last_transaction() {}
+        }
+        let transactions = self
+            .doc
+            .journal
+            .take()
+            .map(MutationJournal::into_transactions)
+            .unwrap_or_default();
+        self.doc.journal = outer_journal;
+        if let Some(journal) = &mut self.doc.journal {
+            journal.extend(transactions);
+        }
+
+        let deferred = if is_outermost { self.deferred.take() } else { None };
+        if let Some(deferred) = deferred {
+            self.apply_deferred_invalidation(deferred);
+        }
+        result
+    }
+
+    /// The number of `:has()` anchors which have been restyled because of mutations made through
+    /// this mutator. Mutations made in a [`transaction`](Self::transaction) restyle each anchor
+    /// at most once.
+    pub fn restyled_anchors(&self) -> usize {
+        self.restyled_anchors
+    }
+
+    fn snapshot_node(&mut self, node_id: usize) {
+        let already_snapshotted = self
+            .deferred
+            .as_mut()
+            .is_some_and(|deferred| !deferred.snapshotted.insert(node_id));
+        if !already_snapshotted {
+            self.doc.snapshot_node(node_id);
+        }
+    }
+
+    fn restyle_subtree(&mut self, node_id: usize) {
+        match &mut self.deferred {
+            Some(deferred) => {
+                deferred.restyle_subtrees.insert(node_id);
+            }
+            None => self.doc.nodes[node_id].set_restyle_hint(RestyleHint::restyle_subtree()),
+        }
+    }
+
+    /// Restyle the `:has()` anchors which may depend on `child_ids` being added to or removed
+    /// from `parent_id`, and have the document's counters evaluated again
+    fn invalidate_children(&mut self, parent_id: usize, child_ids: &[usize]) {
+        self.doc.generated_content_dirty = true;
+        match &mut self.deferred {
+            Some(deferred) => deferred
+                .children_changed
+                .entry(parent_id)
+                .or_default()
+                .extend_from_slice(child_ids),
+            None => {
+                self.restyled_anchors += self
+                    .doc
+                    .invalidate_relative_selectors_for_children(parent_id, child_ids);
+            }
+        }
+    }
+
+    fn apply_deferred_invalidation(&mut self, deferred: DeferredInvalidation) {
+        for node_id in deferred.restyle_subtrees {
+            if let Some(node) = self.doc.nodes.get_mut(node_id) {
+                node.set_restyle_hint(RestyleHint::restyle_subtree());
+            }
+        }
+        for (parent_id, mut child_ids) in deferred.children_changed {
+            if self.doc.get_node(parent_id).is_none() {
+                continue;
+            }
+            child_ids.sort_unstable();
+            child_ids.dedup();
+            child_ids.retain(|child_id| self.doc.get_node(*child_id).is_some());
+            self.restyled_anchors += self
+                .doc
+                .invalidate_relative_selectors_for_children(parent_id, &child_ids);
+        }
+    }
+}
+
 impl<'doc> DocumentMutator<'doc> {
     pub fn flush(&mut self) {
         if self.recompute_is_animating {
             self.doc.is_animating = self.doc.compute_is_animating();
         }
 
-        if let Some(id) = self.title_node {
-            let title = self.doc.nodes[id].text_content();
-            self.doc.shell_provider.set_window_title(title);
+        if self.title_node.take().is_some() {
+            self.doc.update_title();
         }
 
         // Add/Update inline stylesheets (<style> elements)
@@ -490,16 +888,33 @@ impl<'doc> DocumentMutator<'doc> {
             self.doc.process_style_element(id);
         }
 
+        // Hand scripts which are ready to run to the script provider
+        for id in mem::take(&mut self.script_nodes) {
+            self.prepare_script(id);
+        }
+
         for id in self.form_nodes.drain() {
             self.doc.reset_form_owner(id);
         }
 
+        if mem::take(&mut self.meta_changed) {
+            self.doc.update_viewport_meta();
+            self.doc.update_color_scheme_meta();
+            self.doc.update_theme_color();
+        }
+
+        if mem::take(&mut self.links_changed) {
+            self.doc.update_icon();
+        }
+
         #[cfg(feature = "autofocus")]
         if let Some(node_id) = self.node_to_autofocus.take() {
             if self.doc.get_node(node_id).is_some() {
                 self.doc.set_focus_to(node_id);
             }
         }
+
+        self.commit_transaction();
     }
 
     fn flush_eager_ops(&mut self) {
@@ -509,6 +924,7 @@ impl<'doc> DocumentMutator<'doc> {
                 SpecialOp::LoadImage(node_id) => self.load_image(node_id),
                 SpecialOp::LoadStylesheet(node_id) => self.load_linked_stylesheet(node_id),
                 SpecialOp::UnloadStylesheet(node_id) => self.unload_stylesheet(node_id),
+                SpecialOp::ProcessLinkHints(node_id) => self.process_link_hints(node_id),
                 SpecialOp::LoadCustomPaintSource(node_id) => self.load_custom_paint_src(node_id),
                 SpecialOp::ProcessButtonInput(node_id) => self.process_button_input(node_id),
             }
@@ -536,7 +952,12 @@ impl<'doc> DocumentMutator<'doc> {
             let tag = element.name.local.as_ref();
             match tag {
                 "title" => self.title_node = Some(node_id),
-                "link" => self.eager_op_queue.push(SpecialOp::LoadStylesheet(node_id)),
+                "meta" => self.meta_changed = true,
+                "link" => {
+                    self.links_changed = true;
+                    self.eager_op_queue.push(SpecialOp::ProcessLinkHints(node_id));
+                    self.eager_op_queue.push(SpecialOp::LoadStylesheet(node_id));
+                }
                 "img" => self.eager_op_queue.push(SpecialOp::LoadImage(node_id)),
                 "canvas" => self
                     .eager_op_queue
@@ -544,6 +965,7 @@ impl<'doc> DocumentMutator<'doc> {
                 "style" => {
                     self.style_nodes.insert(node_id);
                 }
+                "script" => self.script_nodes.push(node_id),
                 "button" | "fieldset" | "input" | "select" | "textarea" | "object" | "output" => {
                     self.eager_op_queue
                         .push(SpecialOp::ProcessButtonInput(node_id));
@@ -569,6 +991,7 @@ impl<'doc> DocumentMutator<'doc> {
 
     fn process_removed_subtree(&mut self, node_id: usize) {
         self.doc.iter_subtree_mut(node_id, |node_id, doc| {
+            doc.grid_child_contexts.remove(&node_id);
             let node = &mut doc.nodes[node_id];
             node.flags.set(NodeFlags::IS_IN_DOCUMENT, false);
 
@@ -581,6 +1004,13 @@ impl<'doc> DocumentMutator<'doc> {
                 return;
             };
 
+            match element.name.local.as_ref() {
+                "title" => self.title_node = Some(node_id),
+                "meta" => self.meta_changed = true,
+                "link" => self.links_changed = true,
+                _ => {}
+            }
+
             match &element.special_data {
                 SpecialElementData::Stylesheet(_) => self
                     .eager_op_queue
@@ -618,6 +1048,10 @@ impl<'doc> DocumentMutator<'doc> {
             "style" => {
                 self.style_nodes.insert(node_id);
             }
+            // Scripts not in the document yet are found when they're inserted
+            "script" if self.doc.nodes[node_id].is_in_document() => {
+                self.script_nodes.push(node_id);
+            }
             _ => {}
         }
     }
@@ -638,17 +1072,52 @@ impl<'doc> DocumentMutator<'doc> {
         let url = self.doc.resolve_url(href);
         self.doc.net_provider.fetch(
             self.doc.id(),
-            Request::get(url.clone()),
+            Request::get(url.clone()).with_priority(RequestPriority::High),
             Box::new(CssHandler {
                 node: target_id,
                 source_url: url,
                 guard: self.doc.guard.clone(),
                 provider: self.doc.net_provider.clone(),
+                font_faces: self.doc.font_faces.clone(),
                 quirks_mode: self.doc.quirks_mode(),
+                preference_sheets: self.doc.preference_sheets.clone(),
             }),
         );
     }
 
+    /// Act on the `preconnect`, `preload` and `prefetch` hints of a `<link>` element
+    fn process_link_hints(&mut self, target_id: usize) {
+        let node = &self.doc.nodes[target_id];
+
+        let rel_attr = node.attr(local_name!("rel"));
+        let href_attr = node.attr(local_name!("href"));
+
+        let (Some(rels), Some(href)) = (rel_attr, href_attr) else {
+            return;
+        };
+
+        let url = self.doc.resolve_url(href);
+        for rel in rels.split_ascii_whitespace() {
+            let priority = if rel.eq_ignore_ascii_case("preconnect") {
+                self.doc.net_provider.preconnect(self.doc.id(), &url);
+                continue;
+            } else if rel.eq_ignore_ascii_case("preload") {
+                // Preloads which don't say what they are for are ignored
+                let destination = node.attr(LocalName::from("as")).unwrap_or_default();
+                match preload_priority(destination) {
+                    Some(priority) => priority,
+                    None => continue,
+                }
+            } else if rel.eq_ignore_ascii_case("prefetch") {
+                RequestPriority::Low
+            } else {
+                continue;
+            };
+
+            self.doc.preload(href, priority);
+        }
+    }
+
     fn unload_stylesheet(&mut self, node_id: usize) {
         let node = &mut self.doc.nodes[node_id];
         let Some(element) = node.element_data_mut() else {
@@ -663,8 +1132,10 @@ impl<'doc> DocumentMutator<'doc> {
         self.doc
             .stylist
             .force_stylesheet_origins_dirty(OriginSet::all());
+        drop(guard);
 
         self.doc.nodes_to_stylesheet.remove(&node_id);
+        self.doc.update_has_dark_styles();
     }
 
     fn load_image(&mut self, target_id: usize) {
@@ -673,14 +1144,97 @@ impl<'doc> DocumentMutator<'doc> {
             && !raw_src.is_empty()
         {
             let src = self.doc.resolve_url(raw_src);
-            self.doc.net_provider.fetch(
-                self.doc.id(),
-                Request::get(src),
-                Box::new(ImageHandler::new(target_id, ImageType::Image)),
-            );
+            // Until they're laid out (after which those in view are decoded first), images in the
+            // content are decoded before backgrounds, except for lazily-loaded ones, which are
+            // usually out of view
+            let loading = node.attr(LocalName::from("loading"));
+            let lazy = loading.is_some_and(|loading| loading.eq_ignore_ascii_case("lazy"));
+            let priority = if lazy {
+                RequestPriority::Low
+            } else {
+                RequestPriority::High
+            };
+            // Images are decoded at the size they're laid out at, once it's known
+            let handler = ImageHandler::new(target_id, ImageType::Image)
+                .with_url(src.clone())
+                .with_priority(priority);
+            self.doc.image_decodes.insert(target_id, handler.decode_handle());
+
+            if !lazy {
+                let request = Request::get(src).with_priority(RequestPriority::Normal);
+                self.doc.net_provider.fetch(self.doc.id(), request, Box::new(handler));
+                return;
+            }
+
+            // Lazily-loaded images are fetched and decoded speculatively, in an idle period
+            let fetch_when_idle = move |doc: &mut BaseDocument, _deadline: IdleDeadline| {
+                // Images whose source has changed meanwhile have been fetched again
+                let current_src = doc
+                    .get_node(target_id)
+                    .filter(|node| node.is_in_document())
+                    .and_then(|node| node.attr(local_name!("src")))
+                    .map(|src| doc.resolve_url(src));
+                if current_src.as_ref() == Some(&src) {
+                    let request = Request::get(src).with_priority(RequestPriority::Low);
+                    doc.net_provider.fetch(doc.id(), request, Box::new(handler));
+                }
+            };
+            self.doc.request_idle_callback(fetch_when_idle, None);
         }
     }
 
+    /// Hand a `<script>` element to the script provider, if it's ready to run and hasn't been
+    /// already. Inline scripts are ready once they have text, and external ones once they have
+    /// a `src`. Scripts created by the HTML parser are only ready once it has parsed their end
+    /// tag.
+    fn prepare_script(&mut self, target_id: usize) {
+        let Some(node) = self.doc.get_node(target_id) else {
+            return;
+        };
+        let not_ready = NodeFlags::IS_SCRIPT_STARTED | NodeFlags::IS_PARSER_INSERTED;
+        if !node.is_in_document() || node.flags.intersects(not_ready) {
+            return;
+        }
+        let Some(element) = node.element_data() else {
+            return;
+        };
+
+        let kind = match element.attr(local_name!("type")).map(str::trim) {
+            None | Some("") => ScriptKind::Classic,
+            Some(ty) if ty.eq_ignore_ascii_case("module") => ScriptKind::Module,
+            Some(ty) if is_javascript_mime_type(ty) => ScriptKind::Classic,
+            // Data blocks (like JSON or templates) aren't run
+            Some(_) => return,
+        };
+        let source = match element.attr(local_name!("src")) {
+            Some(src) => match self.doc.url.resolve_relative(src) {
+                Some(url) => ScriptSource::External(url),
+                None => return,
+            },
+            None => {
+                let text = node.text_content();
+                if text.is_empty() {
+                    return;
+                }
+                ScriptSource::Inline(text)
+            }
+        };
+        let script = Script {
+            node_id: target_id,
+            kind,
+            source,
+            is_async: element.attr(local_name!("async")).is_some(),
+            is_deferred: element.attr(local_name!("defer")).is_some(),
+        };
+
+        self.doc.nodes[target_id]
+            .flags
+            .insert(NodeFlags::IS_SCRIPT_STARTED);
+        self.doc
+            .script_provider
+            .script_discovered(self.doc.id(), script);
+    }
+
     fn load_custom_paint_src(&mut self, target_id: usize) {
         println!("🔧 load_custom_paint_src called for node {}", target_id);
         let node = &mut self.doc.nodes[target_id];
@@ -757,31 +1311,72 @@ fn set_input_checked_state(element: &mut ElementData, value: String) {
     }
 }
 
+/// The priority of a `<link rel=preload>` with the `as` attribute `destination`, if that is a
+/// destination which can be preloaded
+fn preload_priority(destination: &str) -> Option<RequestPriority> {
+    match destination.to_ascii_lowercase().as_str() {
+        "style" | "font" | "script" => Some(RequestPriority::High),
+        "image" | "fetch" | "track" | "audio" | "video" | "document" => {
+            Some(RequestPriority::Normal)
+        }
+        _ => None,
+    }
+}
+
+/// Whether `ty` is one of the MIME types which mark a `<script>` as JavaScript
+fn is_javascript_mime_type(ty: &str) -> bool {
+    const JAVASCRIPT_MIME_TYPES: &[&str] = &[
+        "application/ecmascript",
+        "application/javascript",
+        "application/x-ecmascript",
+        "application/x-javascript",
+        "text/ecmascript",
+        "text/javascript",
+        "text/javascript1.0",
+        "text/javascript1.1",
+        "text/javascript1.2",
+        "text/javascript1.3",
+        "text/javascript1.4",
+        "text/javascript1.5",
+        "text/jscript",
+        "text/livescript",
+        "text/x-ecmascript",
+        "text/x-javascript",
+    ];
+    JAVASCRIPT_MIME_TYPES
+        .iter()
+        .any(|mime_type| ty.eq_ignore_ascii_case(mime_type))
+}
+
 /// Type that allows mutable access to the viewport
 /// And syncs it back to stylist on drop.
+///
+/// While a device is emulated, this is the viewport of the window rather than the device's (see
+/// [`BaseDocument::set_viewport`]).
 pub struct ViewportMut<'doc> {
     doc: &'doc mut BaseDocument,
+    viewport: Viewport,
 }
 impl ViewportMut<'_> {
     pub fn new(doc: &mut BaseDocument) -> ViewportMut<'_> {
-        ViewportMut { doc }
+        let viewport = doc.unemulated_viewport();
+        ViewportMut { doc, viewport }
     }
 }
 impl Deref for ViewportMut<'_> {
     type Target = Viewport;
 
     fn deref(&self) -> &Self::Target {
-        &self.doc.viewport
+        &self.viewport
     }
 }
 impl DerefMut for ViewportMut<'_> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.doc.viewport
+        &mut self.viewport
     }
 }
 impl Drop for ViewportMut<'_> {
     fn drop(&mut self) {
-        self.doc.set_stylist_device(make_device(&self.doc.viewport, self.doc.quirks_mode()));
-        self.doc.scroll_viewport_by(0.0, 0.0); // Clamp scroll offset
+        self.doc.set_viewport(self.viewport.clone());
     }
 }
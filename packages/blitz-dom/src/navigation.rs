@@ -4,6 +4,8 @@ use std::sync::{Arc, Mutex};
 use blitz_traits::navigation::{NavigationProvider, NavigationOptions};
 use url::Url;
 
+use crate::url::CustomSchemes;
+
 /// Production navigation provider that maintains navigation history and handles navigation events
 #[derive(Debug, Clone)]
 pub struct BlitzNavigationProvider {
@@ -11,6 +13,8 @@ pub struct BlitzNavigationProvider {
     history: Arc<Mutex<Vec<Url>>>,
     /// Current position in navigation history
     current_index: Arc<Mutex<usize>>,
+    /// Schemes of the app's own, which can be navigated to
+    custom_schemes: CustomSchemes,
 }
 
 impl BlitzNavigationProvider {
@@ -19,9 +23,16 @@ impl BlitzNavigationProvider {
         Self {
             history: Arc::new(Mutex::new(Vec::new())),
             current_index: Arc::new(Mutex::new(0)),
+            custom_schemes: CustomSchemes::default(),
         }
     }
 
+    /// Navigate to URLs of `custom_schemes` too
+    pub(crate) fn with_custom_schemes(mut self, custom_schemes: CustomSchemes) -> Self {
+        self.custom_schemes = custom_schemes;
+        self
+    }
+
     /// Get the current URL from navigation history
     pub fn current_url(&self) -> Option<Url> {
         if let (Ok(history), Ok(index)) = (self.history.lock(), self.current_index.lock()) {
@@ -84,8 +95,10 @@ impl NavigationProvider for BlitzNavigationProvider {
         let url = options.url.clone();
         
         // Handle navigation based on URL scheme
+        let navigable = matches!(url.scheme(), "http" | "https" | "file" | "data" | "blob")
+            || self.custom_schemes.contains(url.scheme());
         match url.scheme() {
-            "http" | "https" | "file" | "data" => {
+            _ if navigable => {
                 // Add to navigation history
                 if let (Ok(mut history), Ok(mut index)) = (self.history.lock(), self.current_index.lock()) {
                     // If we're not at the end of history, truncate forward entries
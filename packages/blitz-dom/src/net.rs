@@ -1,6 +1,8 @@
-use std::{io::Cursor, sync::Arc, sync::atomic::AtomicBool};
+use std::{sync::Arc, sync::atomic::AtomicBool};
 
-use blitz_traits::net::{Bytes, NetHandler, Request, SharedCallback, SharedProvider};
+use blitz_traits::net::{
+    Bytes, NetHandler, Request, RequestPriority, SharedCallback, SharedProvider,
+};
 use selectors::context::QuirksMode;
 use style::{
     font_face::{FontFaceSourceFormat, FontFaceSourceFormatKeyword, Source},
@@ -19,15 +21,20 @@ use style::{
 };
 use url::Url;
 
+use crate::font_face_set::FontFaceSet;
+use crate::image_decode::{DecodePool, DecodeSource, ImageDecode};
+use crate::media::PreferenceDependentSheets;
+use crate::node::RasterImageData;
 use crate::util::ImageType;
 
 #[derive(Clone, Debug)]
 pub enum Resource {
-    Image(usize, ImageType, u32, u32, Arc<Vec<u8>>),
+    Image(usize, ImageType, RasterImageData),
     #[cfg(feature = "svg")]
-    Svg(usize, ImageType, Box<usvg::Tree>),
+    Svg(usize, ImageType, Arc<usvg::Tree>),
     Css(usize, DocumentStyleSheet),
-    Font(Bytes),
+    /// The data of a font, along with its id in the document's [`FontFaceSet`]
+    Font(usize, Bytes),
     Navigation {
         url: String,
         document: Bytes,
@@ -39,11 +46,18 @@ pub struct CssHandler {
     pub source_url: Url,
     pub guard: SharedRwLock,
     pub provider: SharedProvider<Resource>,
+    pub font_faces: FontFaceSet,
     pub quirks_mode: QuirksMode,
+    pub(crate) preference_sheets: PreferenceDependentSheets,
 }
 
 #[derive(Clone)]
-pub(crate) struct StylesheetLoader(pub(crate) usize, pub(crate) SharedProvider<Resource>);
+pub(crate) struct StylesheetLoader(
+    pub(crate) usize,
+    pub(crate) SharedProvider<Resource>,
+    pub(crate) FontFaceSet,
+    pub(crate) PreferenceDependentSheets,
+);
 impl ServoStylesheetLoader for StylesheetLoader {
     fn request_stylesheet(
         &self,
@@ -110,15 +124,25 @@ impl ServoStylesheetLoader for StylesheetLoader {
 
                 println!("{css}");
 
-                Stylesheet::update_from_str(
+                let url_data = UrlExtraData(self.url);
+                self.loader.3.parse(css, &url_data, |css| {
+                    Stylesheet::update_from_str(
+                        &self.sheet,
+                        css,
+                        url_data.clone(),
+                        Some(&self.loader),
+                        None,
+                        AllowImportRules::Yes,
+                    );
+                    self.sheet.clone()
+                });
+                fetch_font_face(
+                    doc_id,
                     &self.sheet,
-                    css,
-                    UrlExtraData(self.url),
-                    Some(&self.loader),
-                    None,
-                    AllowImportRules::Yes,
+                    &self.provider,
+                    &self.loader.2,
+                    &self.read_lock.read(),
                 );
-                fetch_font_face(doc_id, &self.sheet, &self.provider, &self.read_lock.read());
                 callback.call(doc_id, Ok(Resource::None))
             }
         }
@@ -131,7 +155,7 @@ impl ServoStylesheetLoader for StylesheetLoader {
         };
         self.1.fetch(
             self.0,
-            Request::get(url.as_ref().clone()),
+            Request::get(url.as_ref().clone()).with_priority(RequestPriority::High),
             Box::new(StylesheetLoaderInner {
                 url: url.clone(),
                 loader: self.clone(),
@@ -154,34 +178,60 @@ impl NetHandler<Resource> for CssHandler {
         // NOTE(Nico): I don't *think* external stylesheets should have HTML entities escaped
         // let escaped_css = html_escape::decode_html_entities(css);
 
-        let sheet = Stylesheet::from_str(
-            css,
-            self.source_url.into(),
-            Origin::Author,
-            ServoArc::new(self.guard.wrap(MediaList::empty())),
-            self.guard.clone(),
-            Some(&StylesheetLoader(doc_id, self.provider.clone())),
-            None,
-            self.quirks_mode,
-            AllowImportRules::Yes,
+        let url_data: UrlExtraData = self.source_url.into();
+        let loader = StylesheetLoader(
+            doc_id,
+            self.provider.clone(),
+            self.font_faces.clone(),
+            self.preference_sheets.clone(),
         );
+        let sheet = self.preference_sheets.parse(css, &url_data, |css| {
+            ServoArc::new(Stylesheet::from_str(
+                css,
+                url_data.clone(),
+                Origin::Author,
+                ServoArc::new(self.guard.wrap(MediaList::empty())),
+                self.guard.clone(),
+                Some(&loader),
+                None,
+                self.quirks_mode,
+                AllowImportRules::Yes,
+            ))
+        });
         let read_guard = self.guard.read();
-        fetch_font_face(doc_id, &sheet, &self.provider, &read_guard);
+        fetch_font_face(
+            doc_id,
+            &sheet,
+            &self.provider,
+            &self.font_faces,
+            &read_guard,
+        );
 
         callback.call(
             doc_id,
-            Ok(Resource::Css(
-                self.node,
-                DocumentStyleSheet(ServoArc::new(sheet)),
-            )),
+            Ok(Resource::Css(self.node, DocumentStyleSheet(sheet))),
         )
     }
 }
-struct FontFaceHandler(FontFaceSourceFormatKeyword);
+struct FontFaceHandler {
+    format: FontFaceSourceFormatKeyword,
+    face_id: usize,
+    font_faces: FontFaceSet,
+    settled: bool,
+}
+impl Drop for FontFaceHandler {
+    fn drop(&mut self) {
+        // Providers drop the handler without calling it if the request fails
+        if !self.settled {
+            self.font_faces
+                .mark_error(self.face_id, "Failed to fetch font");
+        }
+    }
+}
 impl NetHandler<Resource> for FontFaceHandler {
     fn bytes(mut self: Box<Self>, doc_id: usize, bytes: Bytes, callback: SharedCallback<Resource>) {
-        if self.0 == FontFaceSourceFormatKeyword::None {
-            self.0 = match bytes.as_ref() {
+        if self.format == FontFaceSourceFormatKeyword::None {
+            self.format = match bytes.as_ref() {
                 // WOFF (v1) files begin with 0x774F4646 ('wOFF' in ascii)
                 // See: <https://w3c.github.io/woff/woff1/spec/Overview.html#WOFFHeader>
                 // #[cfg(any(feature = "woff-c"))]
@@ -207,7 +257,7 @@ impl NetHandler<Resource> for FontFaceHandler {
         #[cfg(any(feature = "woff-c", feature = "woff-rust"))]
         let mut bytes = bytes;
 
-        match self.0 {
+        match self.format {
             // #[cfg(feature = "woff-c")]
             // FontFaceSourceFormatKeyword::Woff => {
             //     #[cfg(feature = "tracing")]
@@ -238,42 +288,60 @@ impl NetHandler<Resource> for FontFaceHandler {
                 #[cfg(not(any(feature = "woff-c", feature = "woff-rust")))]
                 let decompressed: Option<Vec<u8>> = None;
 
-                if let Some(decompressed) = decompressed {
-                    bytes = Bytes::from(decompressed);
-                } else {
+                let Some(decompressed) = decompressed else {
                     #[cfg(feature = "tracing")]
                     tracing::warn!("Failed to decompress woff2 font");
-                }
+                    self.font_faces
+                        .mark_error(self.face_id, "Failed to decompress WOFF2 font");
+                    self.settled = true;
+                    return;
+                };
+                bytes = Bytes::from(decompressed);
             }
             FontFaceSourceFormatKeyword::None => {
+                self.font_faces
+                    .mark_error(self.face_id, "Unrecognised font format");
+                self.settled = true;
                 return;
             }
             _ => {}
         }
 
-        callback.call(doc_id, Ok(Resource::Font(bytes)))
+        // The face is marked as loaded once the document has registered it with the text system
+        self.settled = true;
+        callback.call(doc_id, Ok(Resource::Font(self.face_id, bytes)))
     }
 }
 
-fn fetch_font_face(
+pub(crate) fn fetch_font_face(
     doc_id: usize,
     sheet: &Stylesheet,
     network_provider: &SharedProvider<Resource>,
+    font_faces: &FontFaceSet,
     read_guard: &SharedRwLockReadGuard,
 ) {
     sheet
         .rules(read_guard)
         .iter()
         .filter_map(|rule| match rule {
-            CssRule::FontFace(font_face) => font_face.read_with(read_guard).sources.as_ref(),
+            CssRule::FontFace(font_face) => {
+                let font_face = font_face.read_with(read_guard);
+                let family = font_face.family.as_ref().map(|family| family.name.to_string());
+                Some((family, font_face.sources.as_ref()?))
+            }
             _ => None,
         })
-        .flat_map(|source_list| &source_list.0)
-        .filter_map(|source| match source {
-            Source::Url(url_source) => Some(url_source),
+        .flat_map(|(family, source_list)| {
+            source_list
+                .0
+                .iter()
+                .map(move |source| (family.clone(), source))
+        })
+        .filter_map(|(family, source)| match source {
+            Source::Url(url_source) => Some((family, url_source)),
             _ => None,
         })
-        .for_each(|url_source| {
+        .for_each(|(family, url_source)| {
             let mut format = match &url_source.format_hint {
                 Some(FontFaceSourceFormat::Keyword(fmt)) => *fmt,
                 Some(FontFaceSourceFormat::String(str)) => match str.as_str() {
@@ -313,51 +381,86 @@ fn fetch_font_face(
                     return;
                 }
             };
-            network_provider.fetch(doc_id, Request::get(url), Box::new(FontFaceHandler(format)))
+            let Some(face_id) = font_faces.register(family, url.clone()) else {
+                return;
+            };
+            network_provider.fetch(
+                doc_id,
+                Request::get(url).with_priority(RequestPriority::High),
+                Box::new(FontFaceHandler {
+                    format,
+                    face_id,
+                    font_faces: font_faces.clone(),
+                    settled: false,
+                }),
+            )
         });
 }
 
-pub struct ImageHandler(usize, ImageType);
+/// Decodes fetched images on the [worker pool](crate::image_decode), most urgent first
+pub struct ImageHandler {
+    node_id: usize,
+    kind: ImageType,
+    /// The URL of the image, which decoded images are cached by
+    url: Option<Url>,
+    /// The size (in device pixels) to decode the image at and how urgently, which the document
+    /// updates as the image is laid out
+    decode: Arc<ImageDecode>,
+}
 impl ImageHandler {
     pub fn new(node_id: usize, kind: ImageType) -> Self {
-        Self(node_id, kind)
+        Self {
+            node_id,
+            kind,
+            url: None,
+            decode: Arc::new(ImageDecode::default()),
+        }
     }
-}
-impl NetHandler<Resource> for ImageHandler {
-    fn bytes(self: Box<Self>, doc_id: usize, bytes: Bytes, callback: SharedCallback<Resource>) {
-        // Try parse image
-        let image_reader = match image::ImageReader::new(Cursor::new(&bytes)).with_guessed_format()
-        {
-            Ok(reader) => reader,
-            Err(e) => {
-                eprintln!(
-                    "Warning: Failed to create image reader: {}. Skipping image processing.",
-                    e
-                );
-                return;
-            }
-        };
 
-        if let Ok(image) = image_reader.decode() {
-            let raw_rgba8_data = image.clone().into_rgba8().into_raw();
-            callback.call(
-                doc_id,
-                Ok(Resource::Image(
-                    self.0,
-                    self.1,
-                    image.width(),
-                    image.height(),
-                    Arc::new(raw_rgba8_data),
-                )),
-            );
-            return;
+    /// Cache the decoded image by `url` (which it was fetched from), so that it's only decoded
+    /// once wherever it's used
+    pub fn with_url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// Decode the image scaled down to cover `width` by `height` device pixels, rather than at
+    /// its natural size
+    pub fn with_target_size(self, width: u32, height: u32) -> Self {
+        self.decode.set_target_size(Some((width, height)));
+        self
+    }
+
+    /// Decode the image before those of a lower `priority`
+    pub fn with_priority(self, priority: RequestPriority) -> Self {
+        self.decode.priority().set(priority);
+        self
+    }
+
+    /// The decode of the image, for following its layout
+    pub(crate) fn decode_handle(&self) -> Arc<ImageDecode> {
+        self.decode.clone()
+    }
+
+    fn decode(self, doc_id: usize, bytes: Bytes, callback: SharedCallback<Resource>) {
+        let source = DecodeSource {
+            doc_id,
+            node_id: self.node_id,
+            kind: self.kind.clone(),
+            url: self.url,
+            bytes: bytes.clone(),
+            callback: callback.clone(),
         };
+        if self.decode.decode(source) {
+            return;
+        }
 
         #[cfg(feature = "svg")]
         {
             use crate::util::parse_svg;
             if let Ok(tree) = parse_svg(&bytes) {
-                callback.call(doc_id, Ok(Resource::Svg(self.0, self.1, Box::new(tree))));
+                let svg = Resource::Svg(self.node_id, self.kind, Arc::new(tree));
+                callback.call(doc_id, Ok(svg));
                 return;
             }
         }
@@ -365,3 +468,11 @@ impl NetHandler<Resource> for ImageHandler {
         callback.call(doc_id, Err(Some(String::from("Could not parse image"))))
     }
 }
+impl NetHandler<Resource> for ImageHandler {
+    fn bytes(self: Box<Self>, doc_id: usize, bytes: Bytes, callback: SharedCallback<Resource>) {
+        // Decoding large images takes long enough to hold up whichever thread it's done on
+        let handler = *self;
+        let priority = handler.decode.priority();
+        DecodePool::global().spawn(priority, move || handler.decode(doc_id, bytes, callback));
+    }
+}
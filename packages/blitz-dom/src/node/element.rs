@@ -1,3 +1,5 @@
+use std::ops::Range;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -8,7 +10,7 @@ use selectors::matching::QuirksMode;
 use style::Atom;
 use style::stylesheets::{DocumentStyleSheet, UrlExtraData};
 use style::{
-    properties::{PropertyDeclarationBlock, parse_style_attribute},
+    properties::{ComputedValues, PropertyDeclarationBlock, parse_style_attribute},
     servo_arc::Arc as ServoArc,
     shared_lock::{Locked, SharedRwLock},
     stylesheets::CssRuleType,
@@ -18,6 +20,7 @@ use url::Url;
 
 use super::{Attribute, Attributes};
 use crate::layout::table::TableContext;
+use crate::system_colors::substitute_unsupported_css;
 
 #[derive(Debug, Clone)]
 pub struct ElementData {
@@ -176,17 +179,19 @@ impl ElementData {
     }
 
     #[cfg(feature = "svg")]
-    pub fn svg_data(&self) -> Option<&usvg::Tree> {
+    pub fn svg_data(&self) -> Option<&Arc<usvg::Tree>> {
         match self.image_data()? {
             ImageData::Svg(data) => Some(data),
             _ => None,
         }
     }
 
+    /// The element's SVG image, which is cloned first if it's shared (as it is while it's painted
+    /// from a raster cache, which then rasterizes the changed image again)
     #[cfg(feature = "svg")]
     pub fn svg_data_mut(&mut self) -> Option<&mut usvg::Tree> {
         match self.image_data_mut()? {
-            ImageData::Svg(data) => Some(data),
+            ImageData::Svg(data) => Some(Arc::make_mut(data)),
             _ => None,
         }
     }
@@ -279,7 +284,7 @@ impl ElementData {
     pub fn flush_style_attribute(&mut self, guard: &SharedRwLock, url_extra_data: &UrlExtraData, quirks_mode: QuirksMode) {
         self.style_attribute = self.attr(local_name!("style")).map(|style_str| {
             ServoArc::new(guard.wrap(parse_style_attribute(
-                style_str,
+                &substitute_unsupported_css(style_str),
                 url_extra_data,
                 None,
                 quirks_mode,
@@ -299,6 +304,11 @@ pub struct RasterImageData {
     pub width: u32,
     /// The height of the image
     pub height: u32,
+    /// The width of the image as it was authored, which `width` is smaller than if the image was
+    /// scaled down to its display size when it was decoded. Layout uses the natural size.
+    pub natural_width: u32,
+    /// The height of the image as it was authored, see `natural_width`
+    pub natural_height: u32,
     /// The raw image data in RGBA8 format
     pub data: Arc<Vec<u8>>,
 }
@@ -307,22 +317,31 @@ impl RasterImageData {
         Self {
             width,
             height,
+            natural_width: width,
+            natural_height: height,
             data,
         }
     }
+
+    /// Set the natural size of an image which was scaled down when it was decoded
+    pub fn with_natural_size(mut self, natural_width: u32, natural_height: u32) -> Self {
+        self.natural_width = natural_width;
+        self.natural_height = natural_height;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ImageData {
     Raster(RasterImageData),
     #[cfg(feature = "svg")]
-    Svg(Box<usvg::Tree>),
+    Svg(Arc<usvg::Tree>),
     None,
 }
 #[cfg(feature = "svg")]
 impl From<usvg::Tree> for ImageData {
     fn from(value: usvg::Tree) -> Self {
-        Self::Svg(Box::new(value))
+        Self::Svg(Arc::new(value))
     }
 }
 
@@ -481,6 +500,8 @@ pub struct FileData {
     pub content_type: String,
     pub size: u64,
     pub data: Vec<u8>,
+    /// Where the file was picked from, which it is read from when it's uploaded
+    pub path: Option<PathBuf>,
 }
 
 impl std::fmt::Debug for SpecialElementData {
@@ -507,6 +528,9 @@ impl std::fmt::Debug for SpecialElementData {
 pub struct ListItemLayout {
     pub marker: Marker,
     pub position: ListItemLayoutPosition,
+    /// The style of the item's `::marker`, if any rules apply to it. Otherwise the marker is
+    /// styled like the list item.
+    pub style: Option<ServoArc<ComputedValues>>,
 }
 
 // We seperate chars from strings in order to optimise rendering - ie not needing to
@@ -590,6 +614,12 @@ pub struct TextLayout {
     pub text: String,
     pub layout: EnhancedBuffer,
     pub inline_boxes: Vec<InlineBox>,
+    /// The byte offset in `text` at which the text of each text node starts, with the id of the
+    /// text node, in order
+    pub text_nodes: Vec<(usize, usize)>,
+    /// The byte ranges of `text` with `white-space: break-spaces`, after whose preserved spaces
+    /// lines can break
+    pub break_spaces: Vec<Range<usize>>,
     
     // Content width caching fields
     pub cached_content_widths: Option<ContentWidths>,
@@ -736,6 +766,8 @@ impl TextLayout {
     ) {
         self.layout
             .set_size_cached(font_system, width, Some(f32::INFINITY));
+        self.layout
+            .break_preserved_spaces_cached(font_system, &self.break_spaces);
         
         // Invalidate cache when layout changes
         self.invalidate_content_width_cache();
@@ -746,6 +778,38 @@ impl TextLayout {
         self.max_intrinsic_width()
     }
 
+    /// The text node whose text is laid out under the point (`x`, `y`), relative to the top-left
+    /// corner of the layout
+    pub fn text_node_at(&self, x: f32, y: f32) -> Option<usize> {
+        let buffer = self.layout.inner();
+        for run in buffer.layout_runs() {
+            if y < run.line_top || y >= run.line_top + run.line_height {
+                continue;
+            }
+            let Some(glyph) = run
+                .glyphs
+                .iter()
+                .find(|glyph| x >= glyph.x && x < glyph.x + glyph.w)
+            else {
+                continue;
+            };
+            // Glyphs index into the text of their line, which starts after the previous lines
+            // and their endings
+            let line_start: usize = buffer.lines[..run.line_i]
+                .iter()
+                .map(|line| line.text().len() + line.ending().as_str().len())
+                .sum();
+            let index = line_start + glyph.start;
+            return self
+                .text_nodes
+                .iter()
+                .rev()
+                .find(|(start, _)| *start <= index)
+                .map(|(_, node_id)| *node_id);
+        }
+        None
+    }
+
     /// Get height of the layout
     pub fn height(&self) -> f32 {
         let mut total_height = 0.0f32;
@@ -831,6 +895,8 @@ mod content_width_caching_tests {
             text: "Test text content".to_string(),
             layout: buffer,
             inline_boxes: Vec::new(),
+            text_nodes: Vec::new(),
+            break_spaces: Vec::new(),
             cached_content_widths: None,
             cached_text_hash: None,
         }
@@ -3,6 +3,8 @@
 mod attributes;
 pub mod element;
 mod node;
+#[cfg(feature = "svg")]
+mod svg_source;
 
 pub use attributes::{Attribute, Attributes};
 pub use element::{
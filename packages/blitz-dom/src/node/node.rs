@@ -1,6 +1,6 @@
 use std::cell::{Cell, RefCell, UnsafeCell};
 use std::fmt::Write;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use atomic_refcell::{AtomicRef, AtomicRefCell};
 use bitflags::bitflags;
@@ -16,19 +16,22 @@ use style::invalidation::element::restyle_hints::RestyleHint;
 use style::properties::ComputedValues;
 use style::properties::generated::longhands::position::computed_value::T as Position;
 use style::selector_parser::PseudoElement;
+use style::servo_arc::Arc as ServoArc;
 use style::stylesheets::UrlExtraData;
 use style::values::computed::Display;
-use style::values::specified::box_::{DisplayInside, DisplayOutside};
+use style::values::specified::Contain;
+use style::values::specified::box_::{
+    ContainerType, ContentVisibility, DisplayInside, DisplayOutside,
+};
 use style::{data::ElementData as StyloElementData, shared_lock::SharedRwLock};
 use style_dom::ElementState;
-use style_traits::CssWriter;
-use style_traits::values::ToCss;
 use taffy::{
     Cache,
     prelude::{Layout, Style},
 };
 
 use super::{Attribute, ElementData};
+use crate::clip_path::clip_region;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DisplayOuter {
@@ -46,6 +49,21 @@ bitflags! {
         const IS_TABLE_ROOT = 0b00000010;
         /// Whether the node is "in the document" (~= has a parent and isn't a template node)
         const IS_IN_DOCUMENT = 0b00000100;
+        /// Whether layout and paint of the node's contents are currently skipped
+        /// (`content-visibility: hidden`, or `auto` while not relevant to the user)
+        const SKIPS_CONTENTS = 0b00001000;
+        /// Whether the node is a `<ruby>` container whose children are laid out as
+        /// base/annotation pairs
+        const IS_RUBY_ROOT = 0b00010000;
+        /// Whether the node is a MathML element whose children are laid out as a row, fraction
+        /// or scripts
+        const IS_MATH_LAYOUT = 0b00100000;
+        /// Whether the node is a `<script>` element which has been handed to the script
+        /// provider (its "already started" flag), so that it only runs once
+        const IS_SCRIPT_STARTED = 0b01000000;
+        /// Whether the node is a `<script>` element created by the HTML parser whose end tag
+        /// hasn't been parsed yet, so that it isn't run with only part of its text
+        const IS_PARSER_INSERTED = 0b10000000;
     }
 }
 
@@ -60,15 +78,32 @@ impl NodeFlags {
         self.contains(Self::IS_TABLE_ROOT)
     }
 
+    #[inline(always)]
+    pub fn is_ruby_root(&self) -> bool {
+        self.contains(Self::IS_RUBY_ROOT)
+    }
+
+    #[inline(always)]
+    pub fn is_math_layout(&self) -> bool {
+        self.contains(Self::IS_MATH_LAYOUT)
+    }
+
     #[inline(always)]
     pub fn is_in_document(&self) -> bool {
         self.contains(Self::IS_IN_DOCUMENT)
     }
 
+    #[inline(always)]
+    pub fn skips_contents(&self) -> bool {
+        self.contains(Self::SKIPS_CONTENTS)
+    }
+
     #[inline(always)]
     pub fn reset_construction_flags(&mut self) {
         self.remove(Self::IS_INLINE_ROOT);
         self.remove(Self::IS_TABLE_ROOT);
+        self.remove(Self::IS_RUBY_ROOT);
+        self.remove(Self::IS_MATH_LAYOUT);
     }
 }
 
@@ -116,12 +151,18 @@ pub struct Node {
     pub unrounded_layout: Layout,
     pub final_layout: Layout,
     pub scroll_offset: kurbo::Point,
+    /// Content-box size of this node when it is a size query container (`container-type`),
+    /// as of the last layout. This is what `@container` rules are evaluated against.
+    pub container_size: Option<taffy::Size<f32>>,
     
     // Style generation tracking for smart invalidation
     /// Track when stylo styles change to invalidate taffy cache
     pub style_generation: std::sync::atomic::AtomicU32,
     /// Cached style generation to check if taffy style is still valid
     pub cached_style_generation: std::sync::atomic::AtomicU32,
+    /// The primary styles [`Self::primary_styles_generation`] was last read for, and their
+    /// generation. Holding them keeps other styles from being allocated at their address.
+    primary_styles_generation: RefCell<Option<(ServoArc<ComputedValues>, u64)>>,
 
     // Cursor caching fields
     cached_cursor: Cell<Option<CursorIcon>>,
@@ -166,10 +207,12 @@ impl Node {
             unrounded_layout: Layout::new(),
             final_layout: Layout::new(),
             scroll_offset: kurbo::Point::ZERO,
+            container_size: None,
             
             // Initialize style generation tracking
             style_generation: std::sync::atomic::AtomicU32::new(0),
             cached_style_generation: std::sync::atomic::AtomicU32::new(0),
+            primary_styles_generation: RefCell::new(None),
 
             // Initialize cursor cache
             cached_cursor: Cell::new(None),
@@ -294,6 +337,17 @@ impl Node {
         self.element_state.contains(ElementState::FOCUS)
     }
 
+    /// Hide the focus ring of a focussed element, so that it doesn't match `:focus-visible`.
+    /// Elements focussed by clicking on them don't show it, unless they take keyboard input.
+    pub fn hide_focus_ring(&mut self) {
+        self.element_state.remove(ElementState::FOCUSRING);
+        self.set_restyle_hint(RestyleHint::restyle_subtree());
+    }
+
+    pub fn has_focus_ring(&self) -> bool {
+        self.element_state.contains(ElementState::FOCUSRING)
+    }
+
     pub fn active(&mut self) {
         self.element_state.insert(ElementState::ACTIVE);
         self.set_restyle_hint(RestyleHint::restyle_subtree());
@@ -726,15 +780,6 @@ impl Node {
 
     pub fn write_outer_html(&self, writer: &mut String) {
         let has_children = !self.children.is_empty();
-        let current_color = self
-            .primary_styles()
-            .map(|style| style.clone_color())
-            .map(|color| {
-                let mut css_string = String::new();
-                let mut writer = CssWriter::new(&mut css_string);
-                let _ = color.to_css(&mut writer);
-                css_string
-            });
 
         match &self.data {
             NodeData::Document => {}
@@ -752,15 +797,7 @@ impl Node {
                     writer.push(' ');
                     writer.push_str(&attr.name.local);
                     writer.push_str("=\"");
-                    if let Some(color) = current_color.as_ref() {
-                        if attr.value.contains("currentColor") {
-                            writer.push_str(&attr.value.replace("currentColor", color));
-                        } else {
-                            writer.push_str(&attr.value);
-                        }
-                    } else {
-                        writer.push_str(&attr.value);
-                    }
+                    writer.push_str(&attr.value);
                     writer.push('"');
                 }
                 if !has_children {
@@ -803,6 +840,25 @@ impl Node {
         )
     }
 
+    /// A number which changes whenever the node's primary styles are replaced, and which no other
+    /// styles (of this node or any other) ever have. Unlike the address of the styles, it can
+    /// tell new styles from freed ones which were allocated at the same address.
+    pub fn primary_styles_generation(&self) -> Option<u64> {
+        static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+        let stylo_element_data = self.stylo_element_data.borrow();
+        let styles = stylo_element_data.as_ref()?.styles.get_primary()?;
+        let mut generation = self.primary_styles_generation.borrow_mut();
+        if let Some((generation_styles, generation)) = &*generation {
+            if ServoArc::ptr_eq(generation_styles, styles) {
+                return Some(*generation);
+            }
+        }
+        let next = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+        *generation = Some((styles.clone(), next));
+        Some(next)
+    }
+
     pub fn text_content(&self) -> String {
         let mut out = String::new();
         self.write_text_content(&mut out);
@@ -845,6 +901,48 @@ impl Node {
             .unwrap_or(0)
     }
 
+    pub fn content_visibility(&self) -> ContentVisibility {
+        self.primary_styles()
+            .map(|s| s.clone_content_visibility())
+            .unwrap_or(ContentVisibility::Visible)
+    }
+
+    /// The effective containment of the node: the `contain` property combined with the
+    /// containment implied by `container-type` and `content-visibility`.
+    ///
+    /// Containment does not apply to inline boxes, so this is empty for them.
+    pub fn containment(&self) -> Contain {
+        if self.display_outer == DisplayOuter::Inline {
+            return Contain::empty();
+        }
+
+        let Some(styles) = self.primary_styles() else {
+            return Contain::empty();
+        };
+
+        let mut contain = styles.clone_contain();
+
+        // Size query containers apply layout, style and size containment in the queried axes
+        let container_type = styles.clone_container_type();
+        if container_type.contains(ContainerType::SIZE) {
+            contain |= Contain::SIZE | Contain::LAYOUT | Contain::STYLE;
+        } else if container_type.contains(ContainerType::INLINE_SIZE) {
+            contain |= Contain::INLINE_SIZE | Contain::LAYOUT | Contain::STYLE;
+        }
+
+        match styles.clone_content_visibility() {
+            ContentVisibility::Visible => {}
+            ContentVisibility::Auto => {
+                contain |= Contain::LAYOUT | Contain::STYLE | Contain::PAINT;
+                if self.flags.skips_contents() {
+                    contain |= Contain::SIZE;
+                }
+            }
+            ContentVisibility::Hidden => contain |= Contain::STRICT,
+        }
+        contain
+    }
+
     /// Takes an (x, y) position (relative to the *parent's* top-left corner) and returns:
     ///    - None if the position is outside of this node's bounds
     ///    - Some(HitResult) if the position is within the node but doesn't match any children
@@ -873,6 +971,29 @@ impl Node {
             return None;
         }
 
+        // Nothing outside of an element's `clip-path` can be hit, including its descendants
+        let clip = self
+            .primary_styles()
+            .and_then(|style| clip_region(&style, &self.final_layout));
+        if let Some(clip) = clip {
+            let border_box_point = kurbo::Point {
+                x: (x - self.scroll_offset.x as f32).into(),
+                y: (y - self.scroll_offset.y as f32).into(),
+            };
+            if !clip.contains(border_box_point) {
+                return None;
+            }
+        }
+
+        // Skipped contents are neither laid out nor painted, so they can't be hit
+        if self.flags.skips_contents() {
+            return matches_self.then_some(HitResult {
+                node_id: self.id,
+                x,
+                y,
+            });
+        }
+
         if self.flags.is_inline_root() {
             let content_box_offset = taffy::Point {
                 x: self.final_layout.padding.left + self.final_layout.border.left,
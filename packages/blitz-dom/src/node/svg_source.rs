@@ -0,0 +1,172 @@
+//! Serializing inline `<svg>` subtrees as standalone SVG documents
+//!
+//! Inline SVGs are rendered by parsing their subtree with usvg, which expects well-formed XML. So
+//! unlike [`Node::outer_html`], text and attribute values are escaped, the namespaces of the
+//! subtree are declared, and attributes keep their `xlink:` and `xml:` prefixes.
+
+use std::fmt::Write as _;
+
+use markup5ever::{Namespace, local_name, ns};
+
+use super::{Node, NodeData};
+use crate::util::ToColorColor as _;
+
+impl Node {
+    /// This `<svg>` element and its subtree as an SVG document
+    pub(crate) fn svg_source(&self) -> String {
+        let mut source = String::new();
+        self.write_svg_source(&mut source, None);
+        source
+    }
+
+    fn write_svg_source(&self, writer: &mut String, parent_ns: Option<&Namespace>) {
+        let element = match &self.data {
+            NodeData::Text(data) => {
+                escape_into(writer, &data.content, false);
+                return;
+            }
+            NodeData::Element(element) => element,
+            _ => return,
+        };
+
+        let name = &element.name.local;
+        writer.push('<');
+        writer.push_str(name);
+        if parent_ns != Some(&element.name.ns) {
+            writer.push_str(" xmlns=\"");
+            escape_into(writer, &element.name.ns, true);
+            writer.push('"');
+        }
+        if parent_ns.is_none() {
+            writer.push_str(" xmlns:xlink=\"http://www.w3.org/1999/xlink\"");
+        }
+
+        for attr in element.attrs() {
+            // Namespaces are declared above, as the subtree is now a document of its own
+            let attr_ns = &attr.name.ns;
+            if *attr_ns == ns!(xmlns) || (*attr_ns == ns!() && attr.name.local == *"xmlns") {
+                continue;
+            }
+            writer.push(' ');
+            if *attr_ns == ns!(xlink) {
+                writer.push_str("xlink:");
+            } else if *attr_ns == ns!(xml) {
+                writer.push_str("xml:");
+            }
+            writer.push_str(&attr.name.local);
+            writer.push_str("=\"");
+            escape_into(writer, &attr.value, true);
+            writer.push('"');
+        }
+
+        // SVG elements carry their computed color, so that `currentColor` (in presentation
+        // attributes and inline styles alike) resolves against it
+        let inherits_color =
+            element.name.ns == ns!(svg) && self.attr(local_name!("color")).is_none();
+        if let Some(styles) = self.primary_styles().filter(|_| inherits_color) {
+            let rgba = styles.clone_color().as_color_color().to_rgba8();
+            let alpha = rgba.a as f32 / 255.0;
+            let _ = write!(writer, " color=\"rgba({}, {}, {}, {alpha})\"", rgba.r, rgba.g, rgba.b);
+        }
+
+        if self.children.is_empty() {
+            writer.push_str("/>");
+            return;
+        }
+        writer.push('>');
+        for &child_id in &self.children {
+            self.tree()[child_id].write_svg_source(writer, Some(&element.name.ns));
+        }
+        writer.push_str("</");
+        writer.push_str(name);
+        writer.push('>');
+    }
+}
+
+/// Append `text` to `writer`, escaping the characters XML gives a meaning to
+fn escape_into(writer: &mut String, text: &str, in_attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => writer.push_str("&amp;"),
+            '<' => writer.push_str("&lt;"),
+            '>' => writer.push_str("&gt;"),
+            '"' if in_attribute => writer.push_str("&quot;"),
+            c => writer.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::{LocalName, QualName};
+    use selectors::matching::QuirksMode;
+
+    use super::*;
+    use crate::Attribute;
+    use crate::testing::document;
+
+    fn attr(ns: Namespace, local: LocalName, value: &str) -> Attribute {
+        Attribute {
+            name: QualName::new(None, ns, local),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn serializes_well_formed_xml() {
+        let mut doc = document();
+        let mut mutr = doc.mutate();
+        let svg_name = |local| QualName::new(None, ns!(svg), local);
+        let svg = mutr.create_element(
+            svg_name(local_name!("svg")),
+            vec![attr(ns!(), local_name!("viewBox"), "0 0 10 10")],
+            QuirksMode::NoQuirks,
+        );
+        let text = mutr.create_element(
+            svg_name(local_name!("text")),
+            vec![attr(ns!(xml), local_name!("space"), "preserve")],
+            QuirksMode::NoQuirks,
+        );
+        let content = mutr.create_text_node("a < b & \"c\"");
+        let link = mutr.create_element(
+            svg_name(local_name!("use")),
+            vec![attr(ns!(xlink), local_name!("href"), "#a\"b")],
+            QuirksMode::NoQuirks,
+        );
+        mutr.append_children(text, &[content]);
+        mutr.append_children(svg, &[text, link]);
+        drop(mutr);
+
+        assert_eq!(
+            doc.get_node(svg).unwrap().svg_source(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" viewBox=\"0 0 10 10\">\
+             <text xml:space=\"preserve\">a &lt; b &amp; \"c\"</text>\
+             <use xlink:href=\"#a&quot;b\"/></svg>"
+        );
+    }
+
+    #[test]
+    fn svg_elements_carry_their_computed_color() {
+        let mut doc = document();
+        let mut mutr = doc.mutate();
+        let mut element = |local, attrs| {
+            let name = QualName::new(None, ns!(svg), local);
+            mutr.create_element(name, attrs, QuirksMode::NoQuirks)
+        };
+        let style = attr(ns!(), local_name!("style"), "color: rgb(255, 0, 0)");
+        let svg = element(local_name!("svg"), vec![style]);
+        let rect = element(local_name!("rect"), vec![attr(ns!(), local_name!("color"), "blue")]);
+        mutr.append_children(svg, &[rect]);
+        mutr.append_children(0, &[svg]);
+        drop(mutr);
+        doc.resolve();
+
+        let source = doc.get_node(svg).unwrap().svg_source();
+        assert_eq!(source.matches("color=").count(), 2);
+        assert!(source.contains("<svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" style=\"color: rgb(255, 0, 0)\" \
+             color=\"rgba(255, 0, 0, 1)\">"));
+        assert!(source.contains("<rect color=\"blue\"/>"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Fragmenting a document into pages
+//!
+//! [`BaseDocument::paginate`] lays a document out for print (matching `@media print` rules) at the
+//! width of a page's content area, splits it into page-sized slices to be painted, and then lays
+//! it out for the screen again. Pages end between boxes or lines of text rather than through them
+//! where possible, honoring `break-before`, `break-after` and `break-inside`. Boxes aren't laid
+//! out again for each page they span: a box which is too tall for a page is simply sliced.
+
+use blitz_traits::shell::MediaType;
+use style::stylesheets::CssRule;
+use style::values::computed::{BreakBetween, BreakWithin};
+use style::values::generics::length::GenericMargin;
+use style::values::specified::LengthPercentage;
+
+use crate::BaseDocument;
+
+/// Breaks closer together than this (in CSS pixels) are considered to be at the same position
+const EPSILON: f32 = 0.01;
+
+/// The size and margins of a page, in CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageConfig {
+    pub width: f32,
+    pub height: f32,
+    /// The default margins, which `@page` rules override
+    pub margins: PageMargins,
+}
+
+impl PageConfig {
+    /// ISO A4 (210mm × 297mm) with half-inch margins
+    pub const A4: PageConfig = PageConfig {
+        width: 793.7,
+        height: 1122.5,
+        margins: PageMargins::uniform(48.0),
+    };
+    /// US Letter (8.5in × 11in) with half-inch margins
+    pub const LETTER: PageConfig = PageConfig {
+        width: 816.0,
+        height: 1056.0,
+        margins: PageMargins::uniform(48.0),
+    };
+
+    /// The width of the area the document is laid out in
+    pub fn content_width(&self) -> f32 {
+        (self.width - self.margins.left - self.margins.right).max(1.0)
+    }
+
+    /// The height of the slice of the document each page shows
+    pub fn content_height(&self) -> f32 {
+        (self.height - self.margins.top - self.margins.bottom).max(1.0)
+    }
+}
+
+impl Default for PageConfig {
+    fn default() -> Self {
+        Self::A4
+    }
+}
+
+/// The margins around a page's content area, in CSS pixels
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PageMargins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl PageMargins {
+    pub const fn uniform(margin: f32) -> Self {
+        Self {
+            top: margin,
+            right: margin,
+            bottom: margin,
+            left: margin,
+        }
+    }
+}
+
+/// A page of a paginated document
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Page {
+    /// The position in the document shown at the top of the page's content area
+    pub top: f32,
+    /// How much of the document the page shows, at most the height of the content area
+    pub height: f32,
+}
+
+/// A document split into pages by [`BaseDocument::paginate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pagination {
+    /// The page config, with the margins of the document's `@page` rules applied
+    pub config: PageConfig,
+    pub pages: Vec<Page>,
+}
+
+/// Where pages may, must and shouldn't end, as positions in the document
+#[derive(Debug, Default)]
+struct Breaks {
+    /// The edges of boxes and lines of text
+    allowed: Vec<f32>,
+    /// `break-before: page` and `break-after: page`
+    forced: Vec<f32>,
+    /// `break-before: avoid` and `break-after: avoid`
+    avoided: Vec<f32>,
+    /// Boxes with `break-inside: avoid`
+    unbreakable: Vec<(f32, f32)>,
+}
+
+impl Breaks {
+    fn can_break_at(&self, y: f32) -> bool {
+        !self.avoided.iter().any(|avoided| (avoided - y).abs() < EPSILON)
+            && !self
+                .unbreakable
+                .iter()
+                .any(|&(top, bottom)| top + EPSILON < y && y < bottom - EPSILON)
+    }
+
+    /// Split a document of `height` into pages of at most `page_height`
+    fn paginate(mut self, height: f32, page_height: f32) -> Vec<Page> {
+        self.allowed.sort_by(f32::total_cmp);
+        self.forced.sort_by(f32::total_cmp);
+
+        let mut pages = Vec::new();
+        let mut top = 0.0;
+        loop {
+            let limit = top + page_height;
+            let forced = self
+                .forced
+                .iter()
+                .copied()
+                .find(|&y| y > top + EPSILON && y <= limit && y < height - EPSILON);
+            let end = match forced {
+                Some(forced) => forced,
+                None if limit >= height => height,
+                // Otherwise end the page at the last break which fits, or slice through whatever
+                // crosses the bottom of the page if there isn't one
+                None => self
+                    .allowed
+                    .iter()
+                    .copied()
+                    .rev()
+                    .find(|&y| y > top + EPSILON && y <= limit && self.can_break_at(y))
+                    .unwrap_or(limit),
+            };
+            pages.push(Page {
+                top,
+                height: end - top,
+            });
+            if end >= height - EPSILON {
+                return pages;
+            }
+            top = end;
+        }
+    }
+}
+
+impl BaseDocument {
+    /// Lay the document out for print, split it into pages of `config`'s size and call `f` with
+    /// the document and its pages, which can be painted with `blitz_paint::paint_page`. The
+    /// document is laid out for the screen again once `f` returns.
+    pub fn paginate<R>(
+        &mut self,
+        config: PageConfig,
+        f: impl FnOnce(&BaseDocument, &Pagination) -> R,
+    ) -> R {
+        let config = PageConfig {
+            margins: self.page_margins(config),
+            ..config
+        };
+
+        let screen_viewport = self.viewport.clone();
+        let mut viewport = screen_viewport.clone();
+        let scale = viewport.scale();
+        viewport.media_type = MediaType::Print;
+        viewport.window_size = (
+            (config.content_width() * scale).round() as u32,
+            (config.content_height() * scale).round() as u32,
+        );
+        self.set_viewport(viewport);
+        self.resolve();
+
+        let mut breaks = Breaks::default();
+        let root = self.root_element();
+        self.collect_breaks(root.id, 0.0, &mut breaks);
+        let height = root.final_layout.size.height.max(root.final_layout.content_size.height);
+        let pages = breaks.paginate(height, config.content_height());
+        let result = f(self, &Pagination { config, pages });
+
+        self.set_viewport(screen_viewport);
+        self.resolve();
+        result
+    }
+
+    /// `config`'s margins, overridden by those of the document's `@page` rules
+    fn page_margins(&self, config: PageConfig) -> PageMargins {
+        let guard = self.guard.read();
+        let mut margins = config.margins;
+        let sheets = self.ua_stylesheets.values().chain(self.nodes_to_stylesheet.values());
+        for sheet in sheets {
+            for rule in sheet.0.rules(&guard).iter() {
+                let CssRule::Page(rule) = rule else {
+                    continue;
+                };
+                let block = rule.read_with(&guard).block.read_with(&guard);
+                for declaration in block.declarations() {
+                    use style::properties::PropertyDeclaration::*;
+                    // Vertical margins are relative to the page's height, horizontal to its width
+                    match declaration {
+                        MarginTop(margin) => set_margin(&mut margins.top, margin, config.height),
+                        MarginRight(margin) => set_margin(&mut margins.right, margin, config.width),
+                        MarginBottom(margin) => {
+                            set_margin(&mut margins.bottom, margin, config.height)
+                        }
+                        MarginLeft(margin) => set_margin(&mut margins.left, margin, config.width),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        margins
+    }
+
+    fn collect_breaks(&self, node_id: usize, parent_top: f32, breaks: &mut Breaks) {
+        let node = &self.nodes[node_id];
+        let layout = node.final_layout;
+        let top = parent_top + layout.location.y;
+        let bottom = top + layout.size.height;
+        breaks.allowed.extend([top, bottom]);
+
+        if let Some(style) = node.primary_styles() {
+            match style.clone_break_before() {
+                BreakBetween::Always
+                | BreakBetween::Page
+                | BreakBetween::Left
+                | BreakBetween::Right => breaks.forced.push(top),
+                BreakBetween::Avoid => breaks.avoided.push(top),
+                BreakBetween::Auto => {}
+            }
+            match style.clone_break_after() {
+                BreakBetween::Always
+                | BreakBetween::Page
+                | BreakBetween::Left
+                | BreakBetween::Right => breaks.forced.push(bottom),
+                BreakBetween::Avoid => breaks.avoided.push(bottom),
+                BreakBetween::Auto => {}
+            }
+            if matches!(
+                style.clone_break_inside(),
+                BreakWithin::Avoid | BreakWithin::AvoidPage
+            ) {
+                breaks.unbreakable.push((top, bottom));
+            }
+        }
+
+        let text = node
+            .element_data()
+            .and_then(|element| element.inline_layout_data.as_ref());
+        if let Some(text) = text {
+            let content_top = top + layout.border.top + layout.padding.top;
+            for run in text.layout.inner().layout_runs() {
+                breaks.allowed.push(content_top + run.line_top);
+            }
+        }
+
+        if let Some(children) = &*node.layout_children.borrow() {
+            for &child in children {
+                self.collect_breaks(child, top, breaks);
+            }
+        }
+    }
+}
+
+/// Set a margin from an `@page` rule, if it's a length or a percentage of `basis`
+fn set_margin(margin: &mut f32, value: &GenericMargin<LengthPercentage>, basis: f32) {
+    let GenericMargin::LengthPercentage(value) = value else {
+        return;
+    };
+    let px = match value {
+        LengthPercentage::Length(length) => length.to_computed_pixel_length_without_context().ok(),
+        LengthPercentage::Percentage(percentage) => Some(percentage.0 * basis),
+        _ => None,
+    };
+    if let Some(px) = px {
+        *margin = px;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_end_at_breaks() {
+        let breaks = Breaks {
+            allowed: vec![0.0, 40.0, 90.0, 130.0, 250.0],
+            forced: vec![130.0],
+            avoided: vec![],
+            unbreakable: vec![(40.0, 110.0)],
+        };
+        let pages = breaks.paginate(250.0, 100.0);
+        let tops: Vec<_> = pages.iter().map(|page| page.top).collect();
+        // 90 is inside an unbreakable box, so the first page ends at 40 instead. The second ends
+        // at the forced break, and the last box is too tall for a page so it is sliced.
+        assert_eq!(tops, [0.0, 40.0, 130.0, 230.0]);
+        assert_eq!(pages[3].height, 20.0);
+    }
+}
@@ -1,11 +1,26 @@
 use selectors::SelectorList;
 use smallvec::SmallVec;
-use style::dom_apis::{MayUseInvalidation, QueryAll, QueryFirst, query_selector};
+use style::dom_apis::{
+    MayUseInvalidation, QueryAll, QueryFirst, element_closest, element_matches, query_selector,
+};
 use style::selector_parser::{SelectorImpl, SelectorParser};
 use style_traits::ParseError;
 
 use crate::{BaseDocument, Node};
 
+/// The dynamic pseudo-classes which currently apply to a node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PseudoClassState {
+    /// `:hover` (the hovered node or one of its ancestors)
+    pub hover: bool,
+    /// `:active` (the active node or one of its ancestors)
+    pub active: bool,
+    /// `:focus`
+    pub focus: bool,
+    /// `:focus-within` (the focussed node or one of its ancestors)
+    pub focus_within: bool,
+}
+
 impl BaseDocument {
     /// Find the first node that matches the selector specified as a string
     /// Returns:
@@ -63,6 +78,84 @@ impl BaseDocument {
         results.iter().map(|node| node.id).collect()
     }
 
+    /// Whether the node matches the selector specified as a string
+    /// Returns:
+    ///   - Err(_) if parsing the selector fails
+    ///   - Ok(false) if the node doesn't exist or isn't an element
+    pub fn matches<'input>(
+        &self,
+        node_id: usize,
+        selector: &'input str,
+    ) -> Result<bool, ParseError<'input>> {
+        let selector_list = self.try_parse_selector_list(selector)?;
+        Ok(self.matches_raw(node_id, &selector_list))
+    }
+
+    /// Whether the node matches the selector(s) specified in selector_list
+    pub fn matches_raw(&self, node_id: usize, selector_list: &SelectorList<SelectorImpl>) -> bool {
+        let Some(node) = self.get_node(node_id).filter(|node| node.is_element()) else {
+            return false;
+        };
+        element_matches(&node, selector_list, self.quirks_mode())
+    }
+
+    /// Find the closest inclusive ancestor of the node that matches the selector specified as a
+    /// string. If the node isn't an element (for example an event target which is a text node),
+    /// the search starts from its parent element.
+    /// Returns:
+    ///   - Err(_) if parsing the selector fails
+    ///   - Ok(None) if no ancestor matches
+    ///   - Ok(Some(node_id)) with the closest matching ancestor otherwise
+    pub fn closest<'input>(
+        &self,
+        node_id: usize,
+        selector: &'input str,
+    ) -> Result<Option<usize>, ParseError<'input>> {
+        let selector_list = self.try_parse_selector_list(selector)?;
+        Ok(self.closest_raw(node_id, &selector_list))
+    }
+
+    /// Find the closest inclusive ancestor of the node that matches the selector(s) specified in
+    /// selector_list
+    pub fn closest_raw(
+        &self,
+        node_id: usize,
+        selector_list: &SelectorList<SelectorImpl>,
+    ) -> Option<usize> {
+        let mut node = self.get_node(node_id)?;
+        while !node.is_element() {
+            node = self.get_node(node.parent?)?;
+        }
+        element_closest(node, selector_list, self.quirks_mode()).map(|node| node.id)
+    }
+
+    /// The dynamic pseudo-classes which currently apply to the node
+    pub fn pseudo_class_state(&self, node_id: usize) -> PseudoClassState {
+        let Some(node) = self.get_node(node_id) else {
+            return PseudoClassState::default();
+        };
+        PseudoClassState {
+            hover: node.is_hovered(),
+            active: node.is_active(),
+            focus: node.is_focussed(),
+            focus_within: self
+                .focus_node_id
+                .is_some_and(|id| self.is_ancestor_or_self(node_id, id)),
+        }
+    }
+
+    /// Whether `ancestor_id` is `node_id` or one of its ancestors
+    fn is_ancestor_or_self(&self, ancestor_id: usize, node_id: usize) -> bool {
+        let mut current = Some(node_id);
+        while let Some(id) = current {
+            if id == ancestor_id {
+                return true;
+            }
+            current = self.get_node(id).and_then(|node| node.parent);
+        }
+        false
+    }
+
     pub fn try_parse_selector_list<'input>(
         &self,
         input: &'input str,
@@ -0,0 +1,566 @@
+//! Scrollbars: their styles, geometry, and interaction with the mouse
+//!
+//! Stylo only parses `scrollbar-width` and `scrollbar-color` in gecko mode. Declarations of them
+//! are renamed to the `--blitz-scrollbar-width` and `--blitz-scrollbar-color` custom properties
+//! before stylesheets are parsed (alongside the substitution of system colors), and read back from
+//! the computed custom properties. Custom properties are inherited but `scrollbar-width` isn't, so
+//! a user agent stylesheet resets it on every element. Stylo drops the rules for the
+//! `::-webkit-scrollbar` pseudo-elements, so those have no effect.
+//!
+//! How scrollbars look follows the platform's convention (see [`ScrollbarMode`]). Overlay
+//! scrollbars are thin thumbs over the content which widen while hovered, and classic scrollbars
+//! have a track and take up room next to the content of `overflow: scroll` elements.
+
+use blitz_traits::events::{MouseEventButton, UiEvent};
+use kurbo::{Point, Rect, Size, Vec2};
+use style::properties::ComputedValues;
+use style::stylesheets::OriginSet;
+use style::stylist::Stylist;
+use style::values::computed::Overflow;
+
+use crate::BaseDocument;
+use crate::util::{Color, custom_property};
+
+/// The custom properties the `scrollbar-*` properties are renamed to
+const SCROLLBAR_PROPERTIES: [(&str, &str); 2] = [
+    ("scrollbar-width", "--blitz-scrollbar-width"),
+    ("scrollbar-color", "--blitz-scrollbar-color"),
+];
+
+/// Resets `scrollbar-width` on every element, as it isn't inherited
+pub(crate) const SCROLLBAR_STYLESHEET: &str = "* { --blitz-scrollbar-width: auto; }\n";
+
+/// The shortest a thumb gets, so that it can still be grabbed in long documents
+const MIN_THUMB_LENGTH: f64 = 18.0;
+
+/// How much of the visible area clicking a scrollbar's track scrolls by
+const TRACK_CLICK_PAGE: f64 = 0.875;
+
+/// The custom property `property` is renamed to, if it is one of the `scrollbar-*` properties
+pub(crate) fn custom_property_for(property: &str) -> Option<&'static str> {
+    SCROLLBAR_PROPERTIES
+        .iter()
+        .find(|(name, _)| property.eq_ignore_ascii_case(name))
+        .map(|(_, custom_property)| *custom_property)
+}
+
+/// The `scrollbar-width` property
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollbarWidth {
+    #[default]
+    Auto,
+    Thin,
+    /// No scrollbars are shown, though the element can still be scrolled
+    None,
+}
+
+impl ScrollbarWidth {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "thin" => Self::Thin,
+            "none" => Self::None,
+            _ => Self::Auto,
+        }
+    }
+}
+
+/// The colors of a `scrollbar-color` other than `auto`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollbarColors {
+    pub thumb: Color,
+    pub track: Color,
+}
+
+impl ScrollbarColors {
+    /// Parse a `scrollbar-color` value, which is `None` for `auto` (and invalid values)
+    pub fn parse(value: &str) -> Option<Self> {
+        let colors = split_components(value);
+        let [thumb, track] = colors.as_slice() else {
+            return None;
+        };
+        Some(Self {
+            thumb: color::parse_color(thumb).ok()?.to_alpha_color(),
+            track: color::parse_color(track).ok()?.to_alpha_color(),
+        })
+    }
+}
+
+/// The scrollbar properties of an element
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ScrollbarStyle {
+    pub width: ScrollbarWidth,
+    /// The colors from `scrollbar-color`, or `None` for the default colors
+    pub colors: Option<ScrollbarColors>,
+}
+
+/// How scrollbars are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollbarMode {
+    /// Thin thumbs over the content, which take up no room (the convention on macOS, iOS and
+    /// Android)
+    Overlay,
+    /// Thumbs on a track, which take up room next to the content of `overflow: scroll` elements
+    /// (the convention on Windows and Linux)
+    Classic,
+}
+
+impl Default for ScrollbarMode {
+    fn default() -> Self {
+        if cfg!(any(target_os = "macos", target_os = "ios", target_os = "android")) {
+            Self::Overlay
+        } else {
+            Self::Classic
+        }
+    }
+}
+
+impl ScrollbarMode {
+    /// The thickness of scrollbars of `width`, while hovered or dragged if `expanded`
+    pub fn thickness(self, width: ScrollbarWidth, expanded: bool) -> f64 {
+        match (self, width) {
+            (_, ScrollbarWidth::None) => 0.0,
+            (Self::Classic, ScrollbarWidth::Auto) => 15.0,
+            (Self::Classic, ScrollbarWidth::Thin) => 11.0,
+            (Self::Overlay, ScrollbarWidth::Auto) if expanded => 12.0,
+            (Self::Overlay, ScrollbarWidth::Auto) => 8.0,
+            (Self::Overlay, ScrollbarWidth::Thin) if expanded => 9.0,
+            (Self::Overlay, ScrollbarWidth::Thin) => 6.0,
+        }
+    }
+
+    /// The room scrollbars of `width` take up next to the content of `overflow: scroll` elements
+    pub fn gutter(self, width: ScrollbarWidth) -> f32 {
+        match self {
+            Self::Overlay => 0.0,
+            Self::Classic => self.thickness(width, false) as f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollbarAxis {
+    Horizontal,
+    Vertical,
+}
+
+impl ScrollbarAxis {
+    /// The one of `x` and `y` along this axis
+    pub fn pick(self, x: f64, y: f64) -> f64 {
+        match self {
+            Self::Horizontal => x,
+            Self::Vertical => y,
+        }
+    }
+}
+
+/// What a scrollbar scrolls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollbarOwner {
+    Viewport,
+    Node(usize),
+}
+
+/// A scrollbar, in the coordinates of its owner: the viewport (in CSS pixels), or the border box
+/// of its node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scrollbar {
+    pub owner: ScrollbarOwner,
+    pub axis: ScrollbarAxis,
+    pub track: Rect,
+    /// The thumb, if there is anything to scroll
+    pub thumb: Option<Rect>,
+    pub style: ScrollbarStyle,
+    /// Whether the mouse is over the scrollbar
+    pub hovered: bool,
+    /// Whether the scrollbar is being pressed (its thumb dragged, or its track clicked)
+    pub pressed: bool,
+}
+
+/// A scrollbar the main mouse button was pressed on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ScrollbarPress {
+    owner: ScrollbarOwner,
+    axis: ScrollbarAxis,
+    /// When dragging the thumb, the distance from its start to the point it was grabbed at
+    grab: Option<f64>,
+}
+
+/// The area a scrollbar's owner shows, and how far it can be scrolled
+struct ScrollArea {
+    bounds: Rect,
+    scroll: Point,
+    max_scroll: Vec2,
+    horizontal: bool,
+    vertical: bool,
+    style: ScrollbarStyle,
+}
+
+impl BaseDocument {
+    pub fn scrollbar_mode(&self) -> ScrollbarMode {
+        self.scrollbar_mode
+    }
+
+    /// Switch between overlay and classic scrollbars, which lays the document out again
+    pub fn set_scrollbar_mode(&mut self, mode: ScrollbarMode) {
+        self.scrollbar_mode = mode;
+        self.stylist.force_stylesheet_origins_dirty(OriginSet::all());
+        self.shell_provider.request_redraw();
+    }
+
+    /// The `scrollbar-width` and `scrollbar-color` of a node
+    pub fn scrollbar_style(&self, node_id: usize) -> ScrollbarStyle {
+        self.nodes
+            .get(node_id)
+            .and_then(|node| node.primary_styles())
+            .map(|styles| computed_scrollbar_style(&self.stylist, &styles))
+            .unwrap_or_default()
+    }
+
+    /// The scrollbars of the viewport or of a scroll container which are currently shown
+    pub fn scrollbars(&self, owner: ScrollbarOwner) -> impl Iterator<Item = Scrollbar> {
+        self.shown_scrollbars(owner).into_iter().flatten()
+    }
+
+    /// The vertical and horizontal scrollbars of the viewport or of a scroll container, if shown
+    fn shown_scrollbars(&self, owner: ScrollbarOwner) -> [Option<Scrollbar>; 2] {
+        let Some(area) = self.scroll_area(owner) else {
+            return [None, None];
+        };
+        if area.style.width == ScrollbarWidth::None {
+            return [None, None];
+        }
+
+        let interaction = |axis| {
+            let pressed = self
+                .scrollbar_press
+                .is_some_and(|press| press.owner == owner && press.axis == axis);
+            let hovered = self.hovered_scrollbar == Some((owner, axis));
+            (hovered, pressed)
+        };
+        let thickness = |axis| {
+            let (hovered, pressed) = interaction(axis);
+            self.scrollbar_mode.thickness(area.style.width, hovered || pressed)
+        };
+
+        let bounds = area.bounds;
+        let scrollbar = |axis, track: Rect| {
+            let (hovered, pressed) = interaction(axis);
+            Scrollbar {
+                owner,
+                axis,
+                track,
+                thumb: thumb(axis, track, &area),
+                style: area.style,
+                hovered,
+                pressed,
+            }
+        };
+
+        // Where both scrollbars are shown, they leave the corner between them empty
+        let vertical_thickness = thickness(ScrollbarAxis::Vertical);
+        let horizontal_thickness = thickness(ScrollbarAxis::Horizontal);
+        let vertical = area.vertical.then(|| {
+            let bottom = if area.horizontal {
+                bounds.y1 - horizontal_thickness
+            } else {
+                bounds.y1
+            };
+            let track = Rect::new(bounds.x1 - vertical_thickness, bounds.y0, bounds.x1, bottom);
+            scrollbar(ScrollbarAxis::Vertical, track)
+        });
+        let horizontal = area.horizontal.then(|| {
+            let right = if area.vertical {
+                bounds.x1 - vertical_thickness
+            } else {
+                bounds.x1
+            };
+            let track = Rect::new(bounds.x0, bounds.y1 - horizontal_thickness, right, bounds.y1);
+            scrollbar(ScrollbarAxis::Horizontal, track)
+        });
+        [vertical, horizontal]
+    }
+
+    fn scroll_area(&self, owner: ScrollbarOwner) -> Option<ScrollArea> {
+        // Scroll containers show scrollbars if they are `overflow: scroll`, or have something to
+        // scroll to and are `overflow: auto`
+        let shows_scrollbar = |overflow: Overflow, max_scroll: f64| match overflow {
+            Overflow::Scroll => true,
+            Overflow::Auto => max_scroll > 0.0,
+            _ => false,
+        };
+
+        match owner {
+            ScrollbarOwner::Viewport => {
+                let styles = self.try_root_element()?.primary_styles()?;
+                let scale = self.viewport.scale() as f64;
+                let (width, height) = self.viewport.window_size;
+                let size = Size::new(width as f64 / scale, height as f64 / scale);
+                let content_size = self.content_size();
+                let max_scroll = Vec2::new(
+                    (content_size.width - size.width).max(0.0),
+                    (content_size.height - size.height).max(0.0),
+                );
+                // The root element's `visible` overflow means `auto` for the viewport
+                let viewport_overflow = |overflow| match overflow {
+                    Overflow::Visible => Overflow::Auto,
+                    overflow => overflow,
+                };
+                let overflow_x = viewport_overflow(styles.clone_overflow_x());
+                let overflow_y = viewport_overflow(styles.clone_overflow_y());
+                Some(ScrollArea {
+                    bounds: size.to_rect(),
+                    scroll: self.viewport_scroll,
+                    max_scroll,
+                    horizontal: shows_scrollbar(overflow_x, max_scroll.x),
+                    vertical: shows_scrollbar(overflow_y, max_scroll.y),
+                    style: computed_scrollbar_style(&self.stylist, &styles),
+                })
+            }
+            ScrollbarOwner::Node(node_id) => {
+                // The root element scrolls with the viewport, whose scrollbars it styles
+                if self.try_root_element().is_some_and(|root| root.id == node_id) {
+                    return None;
+                }
+                let node = self.nodes.get(node_id)?;
+                let styles = node.primary_styles()?;
+                let layout = &node.final_layout;
+                let max_scroll =
+                    Vec2::new(layout.scroll_width() as f64, layout.scroll_height() as f64);
+                let border = layout.border;
+                let bounds = Rect::new(
+                    border.left as f64,
+                    border.top as f64,
+                    (layout.size.width - border.right) as f64,
+                    (layout.size.height - border.bottom) as f64,
+                );
+                Some(ScrollArea {
+                    bounds,
+                    scroll: Point::new(node.scroll_offset.x, node.scroll_offset.y),
+                    max_scroll,
+                    horizontal: shows_scrollbar(styles.clone_overflow_x(), max_scroll.x),
+                    vertical: shows_scrollbar(styles.clone_overflow_y(), max_scroll.y),
+                    style: computed_scrollbar_style(&self.stylist, &styles),
+                })
+            }
+        }
+    }
+
+    /// The point of the document at the origin of a scrollbar owner's coordinates
+    fn scrollbar_owner_origin(&self, owner: ScrollbarOwner) -> Point {
+        match owner {
+            ScrollbarOwner::Viewport => self.viewport_scroll,
+            ScrollbarOwner::Node(node_id) => {
+                let node = &self.nodes[node_id];
+                let offset = node.scroll_offset;
+                let position = node.absolute_position(offset.x as f32, offset.y as f32);
+                Point::new(position.x as f64, position.y as f64)
+            }
+        }
+    }
+
+    /// The scrollbar at a point of the document, and whether the point is on its thumb
+    fn scrollbar_at(&self, point: Point) -> Option<(Scrollbar, bool)> {
+        let find = |owner| {
+            let point = point - self.scrollbar_owner_origin(owner).to_vec2();
+            self.scrollbars(owner)
+                .find(|scrollbar| scrollbar.track.contains(point))
+                .map(|scrollbar| {
+                    let on_thumb = scrollbar.thumb.is_some_and(|thumb| thumb.contains(point));
+                    (scrollbar, on_thumb)
+                })
+        };
+
+        if let Some(found) = find(ScrollbarOwner::Viewport) {
+            return Some(found);
+        }
+        // Scrollbars are painted over the content of their node, so the innermost scroll
+        // container whose scrollbar is under the point wins
+        let mut node_id = self.hit(point.x as f32, point.y as f32).map(|hit| hit.node_id);
+        while let Some(id) = node_id {
+            if let Some(found) = find(ScrollbarOwner::Node(id)) {
+                return Some(found);
+            }
+            node_id = self.nodes[id].layout_parent.get();
+        }
+        None
+    }
+
+    /// Let scrollbars handle a mouse event at a point of the document. Returns whether a
+    /// scrollbar used the event, in which case it isn't dispatched to the page.
+    pub(crate) fn handle_scrollbar_event(&mut self, event: &UiEvent, point: Point) -> bool {
+        match event {
+            UiEvent::MouseMove(_) => {
+                if let Some(press) = self.scrollbar_press {
+                    self.drag_scrollbar(press, point);
+                    return true;
+                }
+                let hovered = self.scrollbar_at(point).map(|(bar, _)| (bar.owner, bar.axis));
+                if hovered != self.hovered_scrollbar {
+                    self.hovered_scrollbar = hovered;
+                    self.shell_provider.request_redraw();
+                }
+                false
+            }
+            UiEvent::MouseDown(event) if event.button == MouseEventButton::Main => {
+                let Some((scrollbar, on_thumb)) = self.scrollbar_at(point) else {
+                    return false;
+                };
+                let axis = scrollbar.axis;
+                let point = point - self.scrollbar_owner_origin(scrollbar.owner).to_vec2();
+                let position = axis.pick(point.x, point.y);
+                let mut grab = None;
+                if let Some(thumb) = scrollbar.thumb {
+                    let thumb_start = axis.pick(thumb.x0, thumb.y0);
+                    if on_thumb {
+                        grab = Some(position - thumb_start);
+                    } else {
+                        // Clicking the track scrolls by a page towards the click
+                        let track = scrollbar.track;
+                        let page = axis.pick(track.width(), track.height()) * TRACK_CLICK_PAGE;
+                        let page = if position < thumb_start { -page } else { page };
+                        self.scroll_owner_by(scrollbar.owner, axis, page);
+                    }
+                }
+                self.scrollbar_press = Some(ScrollbarPress {
+                    owner: scrollbar.owner,
+                    axis,
+                    grab,
+                });
+                self.shell_provider.request_redraw();
+                true
+            }
+            UiEvent::MouseUp(_) if self.scrollbar_press.is_some() => {
+                self.scrollbar_press = None;
+                self.shell_provider.request_redraw();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move a dragged thumb so that the point it was grabbed at follows the mouse
+    fn drag_scrollbar(&mut self, press: ScrollbarPress, point: Point) {
+        let Some(grab) = press.grab else {
+            return;
+        };
+        let scrollbar = self
+            .scrollbars(press.owner)
+            .find(|scrollbar| scrollbar.axis == press.axis);
+        let Some((scrollbar, area)) = scrollbar.zip(self.scroll_area(press.owner)) else {
+            return;
+        };
+        let Some(thumb) = scrollbar.thumb else {
+            return;
+        };
+
+        let axis = press.axis;
+        let track = scrollbar.track;
+        let travel =
+            axis.pick(track.width(), track.height()) - axis.pick(thumb.width(), thumb.height());
+        if travel <= 0.0 {
+            return;
+        }
+        let point = point - self.scrollbar_owner_origin(press.owner).to_vec2();
+        let thumb_start = axis.pick(point.x, point.y) - grab - axis.pick(track.x0, track.y0);
+        let max_scroll = axis.pick(area.max_scroll.x, area.max_scroll.y);
+        let current = axis.pick(area.scroll.x, area.scroll.y);
+        self.scroll_owner_by(press.owner, axis, thumb_start / travel * max_scroll - current);
+        self.shell_provider.request_redraw();
+    }
+
+    /// Scroll a scrollbar's owner along `axis`, without going past its scroll range
+    fn scroll_owner_by(&mut self, owner: ScrollbarOwner, axis: ScrollbarAxis, delta: f64) {
+        let Some(area) = self.scroll_area(owner) else {
+            return;
+        };
+        let max_scroll = axis.pick(area.max_scroll.x, area.max_scroll.y);
+        let scroll = (axis.pick(area.scroll.x, area.scroll.y) + delta).clamp(0.0, max_scroll);
+        let (x, y) = match owner {
+            ScrollbarOwner::Viewport => {
+                let scroll_position = &mut self.viewport_scroll;
+                (&mut scroll_position.x, &mut scroll_position.y)
+            }
+            ScrollbarOwner::Node(node_id) => {
+                let scroll_offset = &mut self.nodes[node_id].scroll_offset;
+                (&mut scroll_offset.x, &mut scroll_offset.y)
+            }
+        };
+        match axis {
+            ScrollbarAxis::Horizontal => *x = scroll,
+            ScrollbarAxis::Vertical => *y = scroll,
+        }
+    }
+}
+
+/// The `scrollbar-width` and `scrollbar-color` of an element's computed styles
+pub(crate) fn computed_scrollbar_style(
+    stylist: &Stylist,
+    styles: &ComputedValues,
+) -> ScrollbarStyle {
+    let width = custom_property(stylist, styles, "blitz-scrollbar-width");
+    let colors = custom_property(stylist, styles, "blitz-scrollbar-color");
+    ScrollbarStyle {
+        width: width.map_or_else(Default::default, |width| ScrollbarWidth::parse(&width)),
+        colors: colors.and_then(|colors| ScrollbarColors::parse(&colors)),
+    }
+}
+
+/// The thumb of a scrollbar, if its owner can be scrolled along its axis
+fn thumb(axis: ScrollbarAxis, track: Rect, area: &ScrollArea) -> Option<Rect> {
+    let max_scroll = axis.pick(area.max_scroll.x, area.max_scroll.y);
+    if max_scroll <= 0.0 {
+        return None;
+    }
+    let visible = axis.pick(area.bounds.width(), area.bounds.height());
+    let track_length = axis.pick(track.width(), track.height());
+    let length = (track_length * visible / (visible + max_scroll))
+        .max(MIN_THUMB_LENGTH)
+        .min(track_length);
+    let progress = (axis.pick(area.scroll.x, area.scroll.y) / max_scroll).clamp(0.0, 1.0);
+    let start = axis.pick(track.x0, track.y0) + (track_length - length) * progress;
+    Some(match axis {
+        ScrollbarAxis::Horizontal => Rect::new(start, track.y0, start + length, track.y1),
+        ScrollbarAxis::Vertical => Rect::new(track.x0, start, track.x1, start + length),
+    })
+}
+
+/// Split a value at the whitespace outside of functions, e.g. `rgb(0 0 0) red` into two colors
+fn split_components(value: &str) -> Vec<&str> {
+    let mut components = Vec::new();
+    let mut depth = 0usize;
+    let mut start = None;
+    for (i, char) in value.char_indices() {
+        match char {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            char if char.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    components.push(&value[start..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(start) = start {
+        components.push(&value[start..]);
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scrollbar_properties() {
+        assert_eq!(ScrollbarWidth::parse(" thin "), ScrollbarWidth::Thin);
+        assert_eq!(ScrollbarWidth::parse("auto"), ScrollbarWidth::Auto);
+        assert_eq!(ScrollbarColors::parse("auto"), None);
+
+        let colors = ScrollbarColors::parse("rgb(255, 0, 0) #00f").unwrap();
+        assert_eq!(colors.thumb.to_rgba8().to_u8_array(), [255, 0, 0, 255]);
+        assert_eq!(colors.track.to_rgba8().to_u8_array(), [0, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,81 @@
+//! Spatial navigation: moving focus to the nearest focusable element in a direction, by where
+//! elements are painted rather than by their order in the document. Used for navigating with
+//! gamepads (and by embedders with other directional input, like TV remotes).
+
+use peniko::kurbo::Rect;
+
+use crate::BaseDocument;
+
+/// A direction to move focus in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpatialDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl BaseDocument {
+    /// The focusable element nearest to the focused one in `direction`, or the top-left one if
+    /// nothing is focused
+    pub fn focusable_in_direction(&self, direction: SpatialDirection) -> Option<usize> {
+        let focusable = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.flags.is_in_document() && node.is_focussable())
+            .filter_map(|(node_id, _)| Some((node_id, self.painted_border_box(node_id)?)));
+
+        let Some(from) = self.focus_node_id.and_then(|id| self.painted_border_box(id)) else {
+            return focusable
+                .min_by(|(_, a), (_, b)| a.y0.total_cmp(&b.y0).then(a.x0.total_cmp(&b.x0)))
+                .map(|(node_id, _)| node_id);
+        };
+        focusable
+            .filter(|&(node_id, _)| Some(node_id) != self.focus_node_id)
+            .filter_map(|(node_id, to)| Some((node_id, distance(from, to, direction)?)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(node_id, _)| node_id)
+    }
+}
+
+/// How far `to` is from `from` in `direction`, if it's in that direction at all (its center is
+/// past the edge of `from`)
+///
+/// Being out of line with `from` counts for more than being far away, so that elements in the
+/// same row or column are preferred, and ties are broken by how well their centers line up.
+fn distance(from: Rect, to: Rect, direction: SpatialDirection) -> Option<f64> {
+    let center = to.center();
+    let from_center = from.center();
+    let (is_past, along, across, misalignment) = match direction {
+        SpatialDirection::Up => (
+            center.y < from.y0,
+            from.y0 - to.y1,
+            gap(from.x0, from.x1, to.x0, to.x1),
+            (center.x - from_center.x).abs(),
+        ),
+        SpatialDirection::Down => (
+            center.y > from.y1,
+            to.y0 - from.y1,
+            gap(from.x0, from.x1, to.x0, to.x1),
+            (center.x - from_center.x).abs(),
+        ),
+        SpatialDirection::Left => (
+            center.x < from.x0,
+            from.x0 - to.x1,
+            gap(from.y0, from.y1, to.y0, to.y1),
+            (center.y - from_center.y).abs(),
+        ),
+        SpatialDirection::Right => (
+            center.x > from.x1,
+            to.x0 - from.x1,
+            gap(from.y0, from.y1, to.y0, to.y1),
+            (center.y - from_center.y).abs(),
+        ),
+    };
+    is_past.then(|| along.max(0.0) + 2.0 * across + 0.1 * misalignment)
+}
+
+/// The gap between two ranges, or zero if they overlap
+fn gap(a0: f64, a1: f64, b0: f64, b1: f64) -> f64 {
+    (b0 - a1).max(a0 - b1).max(0.0)
+}
@@ -23,10 +23,10 @@ use style::values::AtomString;
 use style::values::computed::Percentage;
 use style::values::computed::text::TextAlign as StyloTextAlign;
 use style::values::generics::image::Image as StyloImage;
-use style::values::specified::box_::DisplayOutside;
+use style::values::specified::box_::{ContainerType, DisplayOutside};
 use style::{
     Atom,
-    animation::DocumentAnimationSet,
+    animation::AnimationSetKey,
     context::{
         QuirksMode, RegisteredSpeculativePainter, RegisteredSpeculativePainters,
         SharedStyleContext, StyleContext,
@@ -51,6 +51,7 @@ use crate::net::ImageHandler;
 use crate::node::BackgroundImageData;
 use crate::node::Node;
 use crate::node::NodeData;
+use crate::scrollbar::computed_scrollbar_style;
 use crate::util::ImageType;
 
 impl crate::document::BaseDocument {
@@ -63,7 +64,7 @@ impl crate::document::BaseDocument {
     fn flush_styles_to_layout_with_grid_context(
         &mut self,
         node_id: usize,
-        grid_context: Option<GridContext>,
+        grid_context: Option<&GridContext>,
     ) {
         let doc_id = self.id();
 
@@ -89,7 +90,7 @@ impl crate::document::BaseDocument {
 
             let device = self.stylist.device();
             // Use interior mutability to safely update style while stylo_element_data is borrowed
-            let new_style = if let Some(ref grid_ctx) = grid_context {
+            let mut new_style = if let Some(grid_ctx) = grid_context {
                 stylo_taffy::to_taffy_style_with_grid_context(
                     style,
                     &device,
@@ -99,6 +100,9 @@ impl crate::document::BaseDocument {
             } else {
                 stylo_taffy::to_taffy_style_with_device(style, &device)
             };
+            // Overlay scrollbars and `scrollbar-width` change how much room scrollbars take up
+            let scrollbar_width = computed_scrollbar_style(&self.stylist, style).width;
+            new_style.scrollbar_width = self.scrollbar_mode.gutter(scrollbar_width);
             
             // Store display value before moving the style
             let display = new_style.display;
@@ -174,11 +178,11 @@ impl crate::document::BaseDocument {
                                 break;
                             }
 
-                            self.net_provider.fetch(
-                                doc_id,
-                                Request::get((**new_url).clone()),
-                                Box::new(ImageHandler::new(node_id, ImageType::Background(idx))),
-                            );
+                            let url = (**new_url).clone();
+                            let handler = ImageHandler::new(node_id, ImageType::Background(idx))
+                                .with_url(url.clone());
+                            let request = Request::get(url).with_priority(RequestPriority::Normal);
+                            self.net_provider.fetch(doc_id, request, Box::new(handler));
 
                             let bg_image_data = BackgroundImageData::new(new_url.clone());
                             Some(bg_image_data)
@@ -209,16 +213,23 @@ impl crate::document::BaseDocument {
         // If the node has children, then take those children and...
         let children = self.nodes[node_id].layout_children.borrow_mut().take();
         if let Some(mut children) = children {
-            // Create grid context for children if this is a grid container
+            // Create grid context for children if this is a grid container. It is converted
+            // from the templates (expanding their `repeat()`s) once per container, and shared
+            // with the children's on-demand style conversion.
             let child_grid_context = if matches!(display, taffy::Display::Grid) {
-                self.create_grid_context_for_children(node_id)
+                self.create_grid_context_for_children(node_id).map(std::sync::Arc::new)
             } else {
                 None
             };
+            match &child_grid_context {
+                Some(context) => self.grid_child_contexts.insert(node_id, context.clone()),
+                None => self.grid_child_contexts.remove(&node_id),
+            };
 
             // Recursively call flush_styles_to_layout on each child
+            let grid_context = child_grid_context.as_deref();
             for child in children.iter() {
-                self.flush_styles_to_layout_with_grid_context(*child, child_grid_context.clone());
+                self.flush_styles_to_layout_with_grid_context(*child, grid_context);
             }
 
             // If the node is a Flexbox or Grid node then sort by css order property
@@ -296,6 +307,9 @@ impl crate::document::BaseDocument {
                         })
                 });
             }
+        } else {
+            // Without children there is no grid context to share
+            self.grid_child_contexts.remove(&node_id);
         }
         
         // Verify smart cache functionality by testing get_or_compute_taffy_style
@@ -519,7 +533,10 @@ impl crate::document::BaseDocument {
         })
     }
 
-    pub fn resolve_stylist(&mut self) {
+    /// Restyle the elements whose styles may have changed, returning whether there were any
+    pub fn resolve_stylist(&mut self) -> bool {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("style").entered();
         style::thread_state::enter(ThreadState::LAYOUT);
 
         let guard = &self.guard;
@@ -532,7 +549,7 @@ impl crate::document::BaseDocument {
             Some(root) => root,
             None => {
                 eprintln!("Warning: Document has no root element child for styling");
-                return;
+                return false;
             }
         };
 
@@ -540,7 +557,7 @@ impl crate::document::BaseDocument {
             Some(element) => element,
             None => {
                 eprintln!("Warning: Root node is not an element, cannot perform styling");
-                return;
+                return false;
             }
         };
 
@@ -554,8 +571,8 @@ impl crate::document::BaseDocument {
             options: GLOBAL_STYLE_DATA.options.clone(),
             guards,
             visited_styles_enabled: false,
-            animations: DocumentAnimationSet::default().clone(),
-            current_time_for_animations: 0.0,
+            animations: self.animations.clone(),
+            current_time_for_animations: self.animation_time(),
             snapshot_map: &self.snapshots,
             registered_speculative_painters: &RegisteredPaintersImpl,
         };
@@ -565,13 +582,15 @@ impl crate::document::BaseDocument {
         // dbg!(root);
         let token = RecalcStyle::pre_traverse(root, &context);
 
-        if token.should_traverse() {
+        let restyled = token.should_traverse();
+        if restyled {
             // Style the elements, resolving their data
             let traverser = RecalcStyle::new(context);
             style::driver::traverse_dom(&traverser, token, None);
         }
 
         style::thread_state::exit(ThreadState::LAYOUT);
+        restyled
     }
 }
 
@@ -854,7 +873,9 @@ impl selectors::Element for BlitzNode<'_> {
             NonTSPseudoClass::Enabled => false,
             NonTSPseudoClass::Focus => self.element_state.contains(ElementState::FOCUS),
             NonTSPseudoClass::FocusWithin => false,
-            NonTSPseudoClass::FocusVisible => false,
+            NonTSPseudoClass::FocusVisible => {
+                self.element_state.contains(ElementState::FOCUSRING)
+            }
             NonTSPseudoClass::Fullscreen => false,
             NonTSPseudoClass::Hover => self.element_state.contains(ElementState::HOVER),
             NonTSPseudoClass::Indeterminate => false,
@@ -1187,24 +1208,26 @@ impl<'a> TElement for BlitzNode<'a> {
         false
     }
 
-    fn has_animations(&self, _context: &SharedStyleContext) -> bool {
-        false
+    fn has_animations(&self, context: &SharedStyleContext) -> bool {
+        self.has_css_animations(context, None) || self.has_css_transitions(context, None)
     }
 
     fn has_css_animations(
         &self,
-        _context: &SharedStyleContext,
-        _pseudo_element: Option<style::selector_parser::PseudoElement>,
+        context: &SharedStyleContext,
+        pseudo_element: Option<style::selector_parser::PseudoElement>,
     ) -> bool {
-        false
+        let key = AnimationSetKey::new(TNode::opaque(self), pseudo_element);
+        context.animations.has_active_animations(&key)
     }
 
     fn has_css_transitions(
         &self,
-        _context: &SharedStyleContext,
-        _pseudo_element: Option<style::selector_parser::PseudoElement>,
+        context: &SharedStyleContext,
+        pseudo_element: Option<style::selector_parser::PseudoElement>,
     ) -> bool {
-        false
+        let key = AnimationSetKey::new(TNode::opaque(self), pseudo_element);
+        context.animations.has_active_transitions(&key)
     }
 
     fn shadow_root(&self) -> Option<<Self::ConcreteNode as TNode>::ConcreteShadowRoot> {
@@ -1412,12 +1435,34 @@ impl<'a> TElement for BlitzNode<'a> {
 
     fn query_container_size(
         &self,
-        _display: &style::values::specified::Display,
+        display: &style::values::specified::Display,
     ) -> euclid::default::Size2D<Option<app_units::Au>> {
-        // TODO: Implement CSS Container Queries (@container rules)
-        // Returns Default::default() which effectively disables container queries
-        // See: https://developer.mozilla.org/en-US/docs/Web/CSS/CSS_containment/Container_queries
-        Default::default()
+        // Boxless containers can't be queried
+        if display.is_none() || display.is_contents() {
+            return Default::default();
+        }
+
+        // Sizes are recorded after layout by `BaseDocument::update_container_sizes`. Before the
+        // first layout the size is unknown and size queries evaluate to "unknown".
+        let Some(size) = self.container_size else {
+            return Default::default();
+        };
+        let width = Some(app_units::Au::from_f32_px(size.width));
+        let height = Some(app_units::Au::from_f32_px(size.height));
+
+        // `inline-size` containers can only be queried in their inline axis, which is vertical in
+        // vertical writing modes
+        let Some(styles) = self.primary_styles() else {
+            return Default::default();
+        };
+        let container_type = styles.clone_container_type();
+        if container_type.contains(ContainerType::SIZE) {
+            euclid::default::Size2D::new(width, height)
+        } else if styles.writing_mode.is_vertical() {
+            euclid::default::Size2D::new(None, height)
+        } else {
+            euclid::default::Size2D::new(width, None)
+        }
     }
 
     fn each_custom_state<F>(&self, _callback: F)
@@ -1514,7 +1559,7 @@ impl RegisteredSpeculativePainters for RegisteredPaintersImpl {
     }
 }
 
-use blitz_traits::net::Request;
+use blitz_traits::net::{Request, RequestPriority};
 use style::traversal::recalc_style_at;
 
 pub struct RecalcStyle<'a> {
@@ -0,0 +1,591 @@
+//! CSS system colors (`Canvas`, `CanvasText`, `ButtonFace`, ...)
+//!
+//! Stylo only parses system color keywords in gecko mode, so in servo mode declarations using them
+//! would be dropped. Instead, stylesheets and style attributes have the keywords in their
+//! declaration values replaced with references to custom properties (e.g. `Canvas` becomes
+//! `var(--blitz-system-canvas)`) before they are parsed. A user agent stylesheet generated from
+//! the palette of the document's used color scheme defines those properties on the root element.
+//! It is replaced when the used color scheme changes, so system colors follow it without
+//! reparsing any author stylesheets.
+//!
+//! The same pass renames declarations of the `scrollbar-*` properties, which stylo also only parses
+//! in gecko mode, to custom properties (see [`crate::scrollbar`]), and likewise those of
+//! `color-scheme` (see [`crate::color_scheme`]), which servo mode doesn't compute, and of
+//! `masonry-auto-flow` and `reading-order`, which it doesn't parse at all, and of
+//! `background-attachment` (see [`crate::background_attachment`]), whose `local` keyword it
+//! doesn't parse. In stylesheets, it also replaces the media features stylo doesn't evaluate in
+//! `@media` rules (see [`crate::media`]).
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::ops::Range;
+
+use blitz_traits::shell::ColorScheme;
+
+use crate::background_attachment;
+use crate::color_scheme;
+use crate::media::{self, MediaPreferences};
+use crate::layout::masonry::auto_flow;
+use crate::scrollbar;
+use crate::util::Color;
+
+/// A CSS system color keyword
+///
+/// See <https://drafts.csswg.org/css-color-4/#css-system-colors>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemColor {
+    AccentColor,
+    AccentColorText,
+    ActiveText,
+    ButtonBorder,
+    ButtonFace,
+    ButtonText,
+    Canvas,
+    CanvasText,
+    Field,
+    FieldText,
+    GrayText,
+    Highlight,
+    HighlightText,
+    LinkText,
+    Mark,
+    MarkText,
+    SelectedItem,
+    SelectedItemText,
+    VisitedText,
+}
+
+impl SystemColor {
+    pub const ALL: [SystemColor; 19] = [
+        Self::AccentColor,
+        Self::AccentColorText,
+        Self::ActiveText,
+        Self::ButtonBorder,
+        Self::ButtonFace,
+        Self::ButtonText,
+        Self::Canvas,
+        Self::CanvasText,
+        Self::Field,
+        Self::FieldText,
+        Self::GrayText,
+        Self::Highlight,
+        Self::HighlightText,
+        Self::LinkText,
+        Self::Mark,
+        Self::MarkText,
+        Self::SelectedItem,
+        Self::SelectedItemText,
+        Self::VisitedText,
+    ];
+
+    /// The CSS keyword for the color
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AccentColor => "AccentColor",
+            Self::AccentColorText => "AccentColorText",
+            Self::ActiveText => "ActiveText",
+            Self::ButtonBorder => "ButtonBorder",
+            Self::ButtonFace => "ButtonFace",
+            Self::ButtonText => "ButtonText",
+            Self::Canvas => "Canvas",
+            Self::CanvasText => "CanvasText",
+            Self::Field => "Field",
+            Self::FieldText => "FieldText",
+            Self::GrayText => "GrayText",
+            Self::Highlight => "Highlight",
+            Self::HighlightText => "HighlightText",
+            Self::LinkText => "LinkText",
+            Self::Mark => "Mark",
+            Self::MarkText => "MarkText",
+            Self::SelectedItem => "SelectedItem",
+            Self::SelectedItemText => "SelectedItemText",
+            Self::VisitedText => "VisitedText",
+        }
+    }
+
+    /// Parse a CSS keyword (ASCII case-insensitively)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|color| color.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The custom property which holds the color's value in the active palette
+    fn custom_property(self) -> String {
+        format!("--blitz-system-{}", self.name().to_ascii_lowercase())
+    }
+}
+
+/// The value of every [`SystemColor`] for one color scheme
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SystemColorPalette {
+    colors: [Color; SystemColor::ALL.len()],
+}
+
+impl SystemColorPalette {
+    /// The default palette for light mode
+    pub fn light() -> Self {
+        Self::from_rgb8([
+            [0, 117, 255],   // AccentColor
+            [255, 255, 255], // AccentColorText
+            [238, 0, 0],     // ActiveText
+            [118, 118, 118], // ButtonBorder
+            [239, 239, 239], // ButtonFace
+            [0, 0, 0],       // ButtonText
+            [255, 255, 255], // Canvas
+            [0, 0, 0],       // CanvasText
+            [255, 255, 255], // Field
+            [0, 0, 0],       // FieldText
+            [109, 109, 109], // GrayText
+            [0, 120, 215],   // Highlight
+            [255, 255, 255], // HighlightText
+            [0, 0, 238],     // LinkText
+            [255, 255, 0],   // Mark
+            [0, 0, 0],       // MarkText
+            [0, 120, 215],   // SelectedItem
+            [255, 255, 255], // SelectedItemText
+            [85, 26, 139],   // VisitedText
+        ])
+    }
+
+    /// The default palette for dark mode
+    pub fn dark() -> Self {
+        Self::from_rgb8([
+            [153, 200, 255], // AccentColor
+            [0, 0, 0],       // AccentColorText
+            [255, 102, 102], // ActiveText
+            [107, 107, 107], // ButtonBorder
+            [107, 107, 107], // ButtonFace
+            [255, 255, 255], // ButtonText
+            [18, 18, 18],    // Canvas
+            [255, 255, 255], // CanvasText
+            [59, 59, 59],    // Field
+            [255, 255, 255], // FieldText
+            [128, 128, 128], // GrayText
+            [153, 200, 255], // Highlight
+            [0, 0, 0],       // HighlightText
+            [158, 158, 255], // LinkText
+            [102, 92, 0],    // Mark
+            [255, 255, 255], // MarkText
+            [153, 200, 255], // SelectedItem
+            [0, 0, 0],       // SelectedItemText
+            [208, 173, 240], // VisitedText
+        ])
+    }
+
+    fn from_rgb8(colors: [[u8; 3]; SystemColor::ALL.len()]) -> Self {
+        Self {
+            colors: colors.map(|[r, g, b]| Color::from_rgb8(r, g, b)),
+        }
+    }
+
+    pub fn get(&self, color: SystemColor) -> Color {
+        self.colors[color as usize]
+    }
+
+    pub fn set(&mut self, color: SystemColor, value: Color) {
+        self.colors[color as usize] = value;
+    }
+
+    fn write_custom_properties(&self, css: &mut String) {
+        for color in SystemColor::ALL {
+            let rgba = self.get(color).to_rgba8();
+            let alpha = rgba.a as f32 / 255.0;
+            let _ = writeln!(
+                css,
+                "    {}: rgba({}, {}, {}, {alpha});",
+                color.custom_property(),
+                rgba.r,
+                rgba.g,
+                rgba.b
+            );
+        }
+    }
+}
+
+/// The palettes system colors resolve to in light and dark mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SystemColorTheme {
+    pub light: SystemColorPalette,
+    pub dark: SystemColorPalette,
+}
+
+impl Default for SystemColorTheme {
+    fn default() -> Self {
+        Self {
+            light: SystemColorPalette::light(),
+            dark: SystemColorPalette::dark(),
+        }
+    }
+}
+
+impl SystemColorTheme {
+    /// The palette used in `scheme`
+    pub fn palette(&self, scheme: ColorScheme) -> &SystemColorPalette {
+        match scheme {
+            ColorScheme::Light => &self.light,
+            ColorScheme::Dark => &self.dark,
+        }
+    }
+
+    /// The user agent stylesheet defining the custom properties system colors are replaced with,
+    /// for a document rendered in `scheme`
+    pub(crate) fn to_stylesheet(&self, scheme: ColorScheme) -> String {
+        let mut css = String::from(":root {\n");
+        self.palette(scheme).write_custom_properties(&mut css);
+        css.push_str("}\n");
+        css
+    }
+}
+
+/// A part of a stylesheet which stylo wouldn't parse in servo mode
+enum Substitution {
+    /// A system color keyword, replaced with a reference to the custom property holding it
+    SystemColor(SystemColor),
+    /// The name of a property, replaced with the custom property standing in for it
+    Property(&'static str),
+    /// The end of a shorthand declaration, after which a declaration resetting the custom
+    /// property standing in for one of its longhands is inserted
+    Reset {
+        property: &'static str,
+        important: bool,
+    },
+    /// A media feature stylo doesn't evaluate, replaced with a condition which always or never
+    /// matches
+    MediaFeature(bool),
+}
+
+/// Replace the system color keywords in the declaration values of a stylesheet (or style
+/// attribute) with references to the custom properties holding the active palette's colors, and
+/// rename the properties stylo doesn't support to the custom properties standing in for them
+pub(crate) fn substitute_unsupported_css(css: &str) -> Cow<'_, str> {
+    substitute(css, find_substitutions(css, None))
+}
+
+/// Like [`substitute_unsupported_css`], also replacing the media features of `@media` rules which
+/// stylo doesn't evaluate with conditions matching as they do with `preferences`. Returns whether
+/// there were any, which makes the result depend on the preferences.
+pub(crate) fn substitute_unsupported_stylesheet(
+    css: &str,
+    preferences: MediaPreferences,
+) -> (Cow<'_, str>, bool) {
+    let replacements = find_substitutions(css, Some(preferences));
+    let dependent = replacements
+        .iter()
+        .any(|(_, substitution)| matches!(substitution, Substitution::MediaFeature(_)));
+    (substitute(css, replacements), dependent)
+}
+
+fn substitute(css: &str, replacements: Vec<(Range<usize>, Substitution)>) -> Cow<'_, str> {
+    if replacements.is_empty() {
+        return Cow::Borrowed(css);
+    }
+
+    let mut output = String::with_capacity(css.len() + replacements.len() * 24);
+    let mut last = 0;
+    for (range, substitution) in replacements {
+        output.push_str(&css[last..range.start]);
+        match substitution {
+            Substitution::SystemColor(color) => {
+                let _ = write!(output, "var({})", color.custom_property());
+            }
+            Substitution::Property(name) => output.push_str(name),
+            Substitution::Reset {
+                property,
+                important,
+            } => {
+                let priority = if important { " !important" } else { "" };
+                let _ = write!(output, "; {property}: initial{priority}");
+            }
+            Substitution::MediaFeature(true) => output.push_str("(min-width: 0px)"),
+            Substitution::MediaFeature(false) => output.push_str("(not (min-width: 0px))"),
+        }
+        last = range.end;
+    }
+    output.push_str(&css[last..]);
+    Cow::Owned(output)
+}
+
+/// Find the system color keywords used as values in declarations, and the names of unsupported
+/// properties being declared, as well as the unsupported media features of `@media` rules if
+/// there are `preferences` to evaluate them with
+///
+/// A statement (the text following a `{`, `;` or `}`) which starts with the name of a property
+/// followed by a colon might be a declaration. Substitutions found in it are only kept if the
+/// statement ends with a `;` or `}` (or the end of the input), as a statement ending with `{` is
+/// the prelude of a nested rule (e.g. `color:hover canvas {`).
+fn find_substitutions(
+    css: &str,
+    preferences: Option<MediaPreferences>,
+) -> Vec<(Range<usize>, Substitution)> {
+    let bytes = css.as_bytes();
+    let mut found = Vec::new();
+    let mut pending = Vec::new();
+    let mut statement_start = 0;
+    let mut in_value = false;
+    // The custom property reset by the shorthand being declared, and where its value starts
+    let mut reset = None;
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = css[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+                // Comments before a declaration aren't part of it
+                if css[statement_start..i].trim().is_empty() {
+                    statement_start = end;
+                }
+                i = end;
+                continue;
+            }
+            quote @ (b'"' | b'\'') => {
+                i = skip_string(bytes, i + 1, quote);
+                continue;
+            }
+            b'\\' => {
+                i += 2;
+                continue;
+            }
+            b'{' => {
+                let prelude = &css[statement_start..i];
+                let media_prelude = prelude
+                    .trim_start()
+                    .get(..6)
+                    .is_some_and(|at_rule| at_rule.eq_ignore_ascii_case("@media"));
+                if let Some(preferences) = preferences.filter(|_| media_prelude) {
+                    let features = media::preference_features(prelude, preferences);
+                    found.extend(features.into_iter().map(|(range, matches)| {
+                        let range = statement_start + range.start..statement_start + range.end;
+                        (range, Substitution::MediaFeature(matches))
+                    }));
+                }
+                pending.clear();
+                reset = None;
+                in_value = false;
+                statement_start = i + 1;
+            }
+            b';' | b'}' => {
+                if let Some((property, value_start)) = reset.take() {
+                    pending.push(reset_after(css, statement_start, value_start, i, property));
+                }
+                found.append(&mut pending);
+                in_value = false;
+                statement_start = i + 1;
+            }
+            b':' if !in_value => {
+                let statement = &css[statement_start..i];
+                let property = statement.trim();
+                in_value = is_ident(property) && accepts_colors(property);
+                let custom_property = scrollbar::custom_property_for(property)
+                    .or_else(|| color_scheme::custom_property_for(property))
+                    .or_else(|| auto_flow::custom_property_for(property))
+                    .or_else(|| background_attachment::custom_property_for(property));
+                if let Some(custom_property) = custom_property {
+                    let start = i - statement.trim_start().len();
+                    let name = start..start + property.len();
+                    pending.push((name, Substitution::Property(custom_property)));
+                }
+                reset = background_attachment::custom_property_reset_by(property)
+                    .map(|custom_property| (custom_property, i + 1));
+            }
+            byte if in_value && is_ident_start(byte) => {
+                let end = ident_end(bytes, i);
+                let standalone = i
+                    .checked_sub(1)
+                    .is_none_or(|prev| !is_ident_char(bytes[prev]) && bytes[prev] != b'#');
+                if standalone && bytes.get(end) == Some(&b'(') {
+                    // The contents of an unquoted `url()` aren't tokenized
+                    if css[i..end].eq_ignore_ascii_case("url") {
+                        i = css[end..].find(')').map_or(bytes.len(), |close| end + close + 1);
+                        continue;
+                    }
+                } else if standalone {
+                    if let Some(color) = SystemColor::from_name(&css[i..end]) {
+                        pending.push((i..end, Substitution::SystemColor(color)));
+                    }
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if let Some((property, value_start)) = reset {
+        let end = bytes.len();
+        pending.push(reset_after(css, statement_start, value_start, end, property));
+    }
+    found.append(&mut pending);
+    found
+}
+
+/// The insertion resetting `property` after the declaration from `statement_start` to `end`, whose
+/// value starts at `value_start`, with the same priority as the declaration
+fn reset_after(
+    css: &str,
+    statement_start: usize,
+    value_start: usize,
+    end: usize,
+    property: &'static str,
+) -> (Range<usize>, Substitution) {
+    let declaration_end = statement_start + css[statement_start..end].trim_end().len();
+    let value = css[value_start..declaration_end].to_ascii_lowercase();
+    let important = value
+        .strip_suffix("important")
+        .is_some_and(|value| value.trim_end().ends_with('!'));
+    let reset = Substitution::Reset {
+        property,
+        important,
+    };
+    (declaration_end..declaration_end, reset)
+}
+
+/// The index after the string starting at `start` (just after its opening quote)
+fn skip_string(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\n' => return i,
+            byte if byte == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn is_ident_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || matches!(byte, b'_' | b'-') || !byte.is_ascii()
+}
+
+fn is_ident_char(byte: u8) -> bool {
+    is_ident_start(byte) || byte.is_ascii_digit()
+}
+
+fn ident_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&byte| !is_ident_char(byte))
+        .map_or(bytes.len(), |len| start + len)
+}
+
+/// Whether values of `property` may contain colors. Other properties are left alone, so that e.g.
+/// a font named "Mark" isn't mistaken for a color.
+fn accepts_colors(property: &str) -> bool {
+    const PREFIXES: [&str; 10] = [
+        "--",
+        "background",
+        "border",
+        "box-shadow",
+        "column-rule",
+        "outline",
+        "text-decoration",
+        "text-emphasis",
+        "text-shadow",
+        "-webkit-text",
+    ];
+    let property = property.to_ascii_lowercase();
+    property.contains("color")
+        || PREFIXES.iter().any(|prefix| property.starts_with(prefix))
+        || matches!(property.as_str(), "fill" | "stroke" | "filter")
+}
+
+fn is_ident(text: &str) -> bool {
+    text.bytes().next().is_some_and(is_ident_start) && text.bytes().all(is_ident_char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_keywords_in_declaration_values() {
+        let css = "dialog { background: canvas; /* Canvas */ color: CanvasText }";
+        assert_eq!(
+            substitute_unsupported_css(css),
+            "dialog { background: var(--blitz-system-canvas); /* Canvas */ \
+             color: var(--blitz-system-canvastext) }"
+        );
+
+        // Style attributes are a bare declaration list
+        assert_eq!(
+            substitute_unsupported_css("border: 1px solid ButtonBorder"),
+            "border: 1px solid var(--blitz-system-buttonborder)"
+        );
+    }
+
+    #[test]
+    fn leaves_selectors_strings_and_urls_alone() {
+        let css = "canvas { content: 'Canvas'; background: url(Canvas) }\n\
+                   div { a:hover canvas { color: red } }\n\
+                   .Canvas, #Mark { font-family: Mark, -Canvas; color: Canvas2 }";
+        assert_eq!(substitute_unsupported_css(css), css);
+    }
+
+    #[test]
+    fn renames_scrollbar_properties() {
+        let css = "div { Scrollbar-Width: thin; scrollbar-color: ButtonText Canvas }";
+        assert_eq!(
+            substitute_unsupported_css(css),
+            "div { --blitz-scrollbar-width: thin; --blitz-scrollbar-color: \
+             var(--blitz-system-buttontext) var(--blitz-system-canvas) }"
+        );
+    }
+
+    #[test]
+    fn renames_masonry_properties() {
+        let css = ".grid { masonry-auto-flow: next ordered } .item { reading-order: 1 }";
+        assert_eq!(
+            substitute_unsupported_css(css),
+            ".grid { --blitz-masonry-auto-flow: next ordered } .item { --blitz-reading-order: 1 }"
+        );
+    }
+
+    #[test]
+    fn renames_background_attachment() {
+        let css = ".scroller { background-attachment: local; background: fixed }";
+        assert_eq!(
+            substitute_unsupported_css(css),
+            ".scroller { --blitz-background-attachment: local; \
+             background: fixed; --blitz-background-attachment: initial }"
+        );
+    }
+
+    #[test]
+    fn background_shorthand_resets_background_attachment() {
+        assert_eq!(
+            substitute_unsupported_css("background: red !IMPORTANT; background-attachment: local"),
+            "background: red !IMPORTANT; --blitz-background-attachment: initial !important; \
+             --blitz-background-attachment: local"
+        );
+
+        // Nested rule preludes aren't declarations
+        let css = "div { background:hover a { color: red } }";
+        assert_eq!(substitute_unsupported_css(css), css);
+    }
+
+    #[test]
+    fn evaluates_preference_media_features() {
+        use blitz_traits::shell::HoverCapability;
+
+        let css = "@media (prefers-reduced-motion) and (HOVER: none) { a:hover { color: red } }\n\
+                   @media screen and (not (any-hover: hover)) { a { color: blue } }";
+        let preferences = MediaPreferences {
+            prefers_reduced_motion: true,
+            hover: HoverCapability::Hover,
+        };
+        let (substituted, dependent) = substitute_unsupported_stylesheet(css, preferences);
+        assert!(dependent);
+        assert_eq!(
+            substituted,
+            "@media (min-width: 0px) and (not (min-width: 0px)) { a:hover { color: red } }\n\
+             @media screen and (not (min-width: 0px)) { a { color: blue } }"
+        );
+
+        // Style attributes and stylesheets without them are left alone
+        assert_eq!(substitute_unsupported_css(css), css);
+        let css = "@media (min-width: 100px) { a { color: red } }";
+        assert_eq!(substitute_unsupported_stylesheet(css, preferences), (css.into(), false));
+    }
+}
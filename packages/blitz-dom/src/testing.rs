@@ -0,0 +1,54 @@
+//! Helpers for building documents in tests, shared by the unit and integration tests of the
+//! Blitz crates
+
+use blitz_traits::shell::Viewport;
+use markup5ever::{LocalName, QualName, ns};
+use selectors::matching::QuirksMode;
+
+use crate::{Attribute, BaseDocument, DocumentConfig, DocumentMutator};
+
+/// An empty document, with the providers of [`DocumentConfig::for_testing`]
+pub fn document() -> BaseDocument {
+    BaseDocument::new(DocumentConfig::for_testing()).unwrap()
+}
+
+/// An empty document with `viewport`, and the providers of [`DocumentConfig::for_testing`]
+pub fn document_with_viewport(viewport: Viewport) -> BaseDocument {
+    BaseDocument::new(DocumentConfig {
+        viewport: Some(viewport),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap()
+}
+
+/// Append an HTML element called `local` with `attrs` to `parent`, returning its id
+pub fn append_element(
+    mutr: &mut DocumentMutator,
+    parent: usize,
+    local: &str,
+    attrs: &[(&str, &str)],
+) -> usize {
+    let attrs = attrs
+        .iter()
+        .map(|(name, value)| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(*name)),
+            value: value.to_string(),
+        })
+        .collect();
+    let name = QualName::new(None, ns!(html), LocalName::from(local));
+    let element = mutr.create_element(name, attrs, QuirksMode::NoQuirks);
+    mutr.append_children(parent, &[element]);
+    element
+}
+
+/// Append an HTML element called `local` to `parent`, with the inline `style` unless it's empty,
+/// returning its id
+pub fn append_styled(
+    mutr: &mut DocumentMutator,
+    parent: usize,
+    local: &str,
+    style: &str,
+) -> usize {
+    let attrs: &[_] = if style.is_empty() { &[] } else { &[("style", style)] };
+    append_element(mutr, parent, local, attrs)
+}
@@ -4,8 +4,12 @@
 //! that eliminates initialization overhead during render loops and prevents multiple
 //! initialization attempts that cause cache failures.
 
+use blitz_text::cosmyc;
+#[cfg(feature = "gpu")]
+use blitz_text::{UnifiedTextSystem, text_system::config::TextSystemError};
+#[cfg(feature = "gpu")]
 use tokio::sync::OnceCell;
-use blitz_text::{UnifiedTextSystem, text_system::config::TextSystemError, cosmyc};
+#[cfg(feature = "gpu")]
 use wgpu::{Device, Queue, TextureFormat, MultisampleState, DepthStencilState};
 
 /// Custom error type for text system singleton operations
@@ -31,12 +35,35 @@ impl std::fmt::Display for TextSystemSingletonError {
 
 impl std::error::Error for TextSystemSingletonError {}
 
+#[cfg(feature = "gpu")]
 impl From<TextSystemError> for TextSystemSingletonError {
     fn from(err: TextSystemError) -> Self {
         Self::InitializationFailed(err.to_string())
     }
 }
 
+/// The text system documents lay out text with
+#[cfg(feature = "gpu")]
+pub type TextSystem = UnifiedTextSystem;
+
+/// The text system documents lay out text with. Without the `gpu` feature there's no GPU text
+/// renderer to initialize, so it's always available, and is each thread's font system.
+#[cfg(not(feature = "gpu"))]
+#[derive(Debug, Default)]
+pub struct TextSystem;
+
+#[cfg(not(feature = "gpu"))]
+impl TextSystem {
+    /// Access the calling thread's font system
+    pub fn with_font_system<T>(&self, f: impl FnOnce(&mut cosmyc::FontSystem) -> T) -> T {
+        thread_local! {
+            static FONT_SYSTEM: std::cell::RefCell<cosmyc::FontSystem> =
+                std::cell::RefCell::new(blitz_text::new_font_system());
+        }
+        FONT_SYSTEM.with_borrow_mut(f)
+    }
+}
+
 /// Lock-free, thread-safe singleton for UnifiedTextSystem
 /// 
 /// Uses OnceLock for zero-allocation initialization and ThreadLocal patterns
@@ -48,6 +75,7 @@ impl TextSystemSingleton {
     /// 
     /// OnceCell ensures initialization happens exactly once across all threads
     /// atomically, preventing race conditions during async initialization.
+    #[cfg(feature = "gpu")]
     fn instance() -> &'static OnceCell<UnifiedTextSystem> {
         static INSTANCE: OnceCell<UnifiedTextSystem> = OnceCell::const_new();
         &INSTANCE
@@ -69,6 +97,7 @@ impl TextSystemSingleton {
     /// # Returns
     /// * `Ok(())` - Initialization successful or already initialized
     /// * `Err(TextSystemSingletonError)` - Initialization failed
+    #[cfg(feature = "gpu")]
     #[inline]
     pub async fn initialize_once(
         device: &Device,
@@ -100,7 +129,7 @@ impl TextSystemSingleton {
     /// Zero-allocation status check for conditional initialization logic.
     #[inline]
     pub fn is_initialized() -> bool {
-        Self::instance().get().is_some()
+        Self::get().is_some()
     }
 
     /// Access the text system singleton with a closure
@@ -117,9 +146,9 @@ impl TextSystemSingleton {
     #[inline]
     pub fn with_text_system<R, F>(f: F) -> Result<R, TextSystemSingletonError>
     where
-        F: FnOnce(&UnifiedTextSystem) -> R,
+        F: FnOnce(&TextSystem) -> R,
     {
-        match Self::instance().get() {
+        match Self::get() {
             Some(text_system) => Ok(f(text_system)),
             None => Err(TextSystemSingletonError::NotInitialized),
         }
@@ -140,7 +169,7 @@ impl TextSystemSingleton {
     #[inline]
     pub fn with_text_system_mut<R, F>(f: F) -> Result<R, TextSystemSingletonError>
     where
-        F: FnOnce(&UnifiedTextSystem) -> R,
+        F: FnOnce(&TextSystem) -> R,
     {
         // UnifiedTextSystem uses interior mutability (ThreadLocal<RefCell<_>>)
         // so mutable operations work through immutable references
@@ -152,8 +181,12 @@ impl TextSystemSingleton {
     /// Returns a direct reference for cases where closure-based access
     /// is not suitable. Use with caution in performance-critical paths.
     #[inline]
-    pub fn get() -> Option<&'static UnifiedTextSystem> {
-        Self::instance().get()
+    pub fn get() -> Option<&'static TextSystem> {
+        #[cfg(feature = "gpu")]
+        let text_system = Self::instance().get();
+        #[cfg(not(feature = "gpu"))]
+        let text_system = Some(&TextSystem);
+        text_system
     }
 
     /// Check if singleton can be initialized (for testing)
@@ -211,6 +244,7 @@ mod tests {
         assert!(display_str.contains("GPU context"));
     }
 
+    #[cfg(feature = "gpu")]
     #[test]
     fn test_error_from_text_system_error() {
         use blitz_text::text_system::config::TextSystemError;
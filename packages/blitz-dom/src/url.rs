@@ -1,9 +1,14 @@
+use std::fmt;
 use std::ops::Deref;
 use std::str::FromStr;
+use std::sync::Arc;
 
+use blitz_traits::net::SharedProvider;
 use style::servo_arc::Arc as ServoArc;
 use style::stylesheets::UrlExtraData;
-use url::Url;
+use url::{Position, Url};
+
+use crate::net::Resource;
 
 #[derive(Debug, Clone)]
 pub enum DocumentUrlError {
@@ -48,6 +53,33 @@ impl std::error::Error for DocumentUrlError {
 #[derive(Clone)]
 pub(crate) struct DocumentUrl {
     base_url: ServoArc<Url>,
+    custom_schemes: CustomSchemes,
+}
+
+/// The URL schemes of the app's own (like `app:`) which a document's net provider loads, see
+/// [`NetProvider::is_custom_scheme`](blitz_traits::net::NetProvider::is_custom_scheme)
+#[derive(Clone, Default)]
+pub(crate) struct CustomSchemes(Option<Arc<dyn Fn(&str) -> bool + Send + Sync>>);
+
+impl CustomSchemes {
+    pub(crate) fn new(is_custom_scheme: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(is_custom_scheme)))
+    }
+
+    /// The custom schemes `net_provider` loads
+    pub(crate) fn of(net_provider: SharedProvider<Resource>) -> Self {
+        Self::new(move |scheme| net_provider.is_custom_scheme(scheme))
+    }
+
+    pub(crate) fn contains(&self, scheme: &str) -> bool {
+        self.0.as_ref().is_some_and(|is_custom_scheme| is_custom_scheme(scheme))
+    }
+}
+
+impl fmt::Debug for CustomSchemes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomSchemes").finish_non_exhaustive()
+    }
 }
 
 impl DocumentUrl {
@@ -83,7 +115,18 @@ impl DocumentUrl {
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub(crate) fn resolve_relative(&self, raw: &str) -> Option<url::Url> {
-        self.base_url.join(raw).ok()
+        let url = self.base_url.join(raw).ok()?;
+        Some(with_hierarchical_path(url, &self.custom_schemes))
+    }
+
+    /// This URL, treating the URLs of `custom_schemes` as hierarchical (see
+    /// [`with_hierarchical_path`]), as the base URL of a document whose net provider loads them
+    pub(crate) fn with_custom_schemes(self, custom_schemes: CustomSchemes) -> Self {
+        let base_url = with_hierarchical_path((*self.base_url).clone(), &custom_schemes);
+        Self {
+            base_url: ServoArc::new(base_url),
+            custom_schemes,
+        }
     }
 
     /// Creates a fallback URL when all standard URL creation methods fail
@@ -103,6 +146,7 @@ impl DocumentUrl {
         if let Ok(data_url) = url::Url::parse("data:") {
             return Ok(Self {
                 base_url: ServoArc::new(data_url),
+                custom_schemes: CustomSchemes::default(),
             });
         }
 
@@ -110,6 +154,7 @@ impl DocumentUrl {
         if let Ok(file_url) = url::Url::from_file_path("/") {
             return Ok(Self {
                 base_url: ServoArc::new(file_url),
+                custom_schemes: CustomSchemes::default(),
             });
         }
 
@@ -117,6 +162,7 @@ impl DocumentUrl {
         if let Ok(about_url) = url::Url::parse("about:blank") {
             return Ok(Self {
                 base_url: ServoArc::new(about_url),
+                custom_schemes: CustomSchemes::default(),
             });
         }
 
@@ -187,9 +233,7 @@ impl DocumentUrl {
 
         // 2. Try direct parsing
         match url::Url::parse(trimmed) {
-            Ok(url) => Ok(Self {
-                base_url: ServoArc::new(url),
-            }),
+            Ok(url) => Ok(Self::from(url)),
             Err(url::ParseError::RelativeUrlWithoutBase) => {
                 // 3. Try as relative URL with sensible base
                 Self::parse_relative_with_base(trimmed)
@@ -217,9 +261,7 @@ impl DocumentUrl {
         for base_str in &base_urls {
             if let Ok(base) = url::Url::parse(base_str) {
                 if let Ok(resolved) = base.join(relative_url) {
-                    return Ok(Self {
-                        base_url: ServoArc::new(resolved),
-                    });
+                    return Ok(Self::from(resolved));
                 }
             }
         }
@@ -228,16 +270,31 @@ impl DocumentUrl {
         Err(url::ParseError::RelativeUrlWithoutBase)
     }
 }
+
+/// Makes URLs of custom schemes which are written without an authority hierarchical
+///
+/// URLs like `app:index.html` can't be a base for relative URLs, so URLs of the
+/// `custom_schemes` are rewritten with an empty authority (`app:///index.html`),
+/// which can be, like `file:` URLs. Other URLs are returned unchanged.
+fn with_hierarchical_path(url: Url, custom_schemes: &CustomSchemes) -> Url {
+    if !url.cannot_be_a_base() || !custom_schemes.contains(url.scheme()) {
+        return url;
+    }
+    let rest = url[Position::BeforePath..].trim_start_matches('/');
+    Url::parse(&format!("{}:///{rest}", url.scheme())).unwrap_or(url)
+}
+
 impl From<Url> for DocumentUrl {
     fn from(base_url: Url) -> Self {
-        Self {
-            base_url: ServoArc::new(base_url),
-        }
+        Self::from(ServoArc::new(base_url))
     }
 }
 impl From<ServoArc<Url>> for DocumentUrl {
     fn from(base_url: ServoArc<Url>) -> Self {
-        Self { base_url }
+        Self {
+            base_url,
+            custom_schemes: CustomSchemes::default(),
+        }
     }
 }
 impl Deref for DocumentUrl {
@@ -393,6 +450,28 @@ mod tests {
         assert_eq!(doc_url.as_str(), url.as_str());
     }
 
+    #[test]
+    fn test_custom_scheme_resolution() {
+        let custom_schemes = CustomSchemes::new(|scheme| scheme == "blitz-test-app");
+
+        // Custom schemes written without an authority are made hierarchical
+        let doc_url = DocumentUrl::from_str("blitz-test-app:pages/index.html?lang=en")
+            .expect("Failed to parse custom scheme URL for resolution test")
+            .with_custom_schemes(custom_schemes.clone());
+        assert_eq!(doc_url.as_str(), "blitz-test-app:///pages/index.html?lang=en");
+        let resolved = doc_url.resolve_relative("../style.css");
+        assert_eq!(resolved.unwrap().as_str(), "blitz-test-app:///style.css");
+        let resolved = doc_url.resolve_relative("blitz-test-app:other.html");
+        assert_eq!(resolved.unwrap().as_str(), "blitz-test-app:///other.html");
+
+        // Other schemes are left alone
+        let doc_url = DocumentUrl::from_str("blitz-test-other:index.html")
+            .expect("Failed to parse unregistered scheme URL for resolution test")
+            .with_custom_schemes(custom_schemes);
+        assert_eq!(doc_url.as_str(), "blitz-test-other:index.html");
+        assert!(doc_url.resolve_relative("style.css").is_none());
+    }
+
     #[test]
     fn test_deref_functionality() {
         // Test Deref implementation allows direct URL method access
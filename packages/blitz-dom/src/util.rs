@@ -1,5 +1,9 @@
 use color::{AlphaColor, Srgb};
+use style::Atom;
 use style::color::AbsoluteColor;
+use style::properties::ComputedValues;
+use style::stylist::Stylist;
+use style_traits::ToCss;
 
 use crate::node::{Node, NodeData};
 
@@ -21,6 +25,8 @@ pub(crate) static FONT_DB: LazyLock<Arc<fontdb::Database>> = LazyLock::new(|| {
 pub enum ImageType {
     Image,
     Background(usize),
+    /// The document's icon, from a `<link rel="icon">`
+    Icon,
 }
 
 // Debug print an RcDom
@@ -111,3 +117,45 @@ impl ToColorColor for AbsoluteColor {
         )
     }
 }
+
+/// Append a fragment of text to `out`, collapsing whitespace as it goes.
+///
+/// Runs of whitespace (including whitespace spanning two fragments) become a single space and
+/// leading whitespace is dropped. Fragments that are not separated by whitespace are joined
+/// directly, so `"Sub"` followed by `"mit"` produces `"Submit"`. This is the normalization used
+/// both for accessible names and for extracting plain text from a painted document.
+pub fn push_collapsed_text(out: &mut String, fragment: &str) {
+    let needs_space = |out: &String| !out.is_empty() && !out.ends_with([' ', '\n']);
+
+    let mut pending_space = fragment.starts_with(char::is_whitespace);
+    for word in fragment.split_whitespace() {
+        if pending_space && needs_space(out) {
+            out.push(' ');
+        }
+        out.push_str(word);
+        pending_space = true;
+    }
+    if fragment.ends_with(char::is_whitespace) && needs_space(out) {
+        out.push(' ');
+    }
+}
+
+/// Collapse whitespace in `text` and trim both ends (see [`push_collapsed_text`])
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    push_collapsed_text(&mut out, text);
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// The computed value of a custom property, named without its leading `--`
+pub(crate) fn custom_property(
+    stylist: &Stylist,
+    styles: &ComputedValues,
+    name: &str,
+) -> Option<String> {
+    let name = Atom::from(name);
+    let registration = stylist.get_custom_property_registration(&name);
+    let value = styles.custom_properties().get(registration, &name)?;
+    Some(value.to_css_string())
+}
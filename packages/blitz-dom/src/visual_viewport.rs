@@ -0,0 +1,122 @@
+//! Pinch-zoom, as a magnification of the laid out page
+//!
+//! Blitz has two zoom models. [`Viewport::zoom`](blitz_traits::shell::Viewport::zoom) (what
+//! Ctrl+= changes) scales CSS pixels, so the document is laid out again and text reflows. The
+//! [`VisualViewport`] on the other hand only magnifies the rendered page, like pinch-zooming on a
+//! phone: the layout is unchanged, and the window shows a part of the unmagnified window (the
+//! layout viewport).
+
+use kurbo::{Affine, Point, Size, Vec2};
+
+use crate::BaseDocument;
+
+/// The part of the layout viewport shown in the window when pinch-zoomed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisualViewport {
+    /// The magnification, from `1.0` to [`Self::MAX_SCALE`]
+    pub scale: f64,
+    /// The top-left of the visible area, in logical pixels of the layout viewport
+    pub offset: Vec2,
+}
+
+impl Default for VisualViewport {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+impl VisualViewport {
+    pub const MAX_SCALE: f64 = 5.0;
+
+    pub fn is_magnified(&self) -> bool {
+        self.scale > 1.0
+    }
+
+    /// Map a point in the window to the layout viewport (both in logical pixels)
+    pub fn to_layout_viewport(&self, point: Point) -> Point {
+        (point.to_vec2() / self.scale + self.offset).to_point()
+    }
+
+    /// The transform from the layout viewport to the window, in physical pixels
+    pub fn transform(&self, hidpi_scale: f64) -> Affine {
+        Affine::scale(self.scale) * Affine::translate(-self.offset * hidpi_scale)
+    }
+
+    /// Magnify by `factor`, keeping the point `anchor` of the window in place. `window_size` is
+    /// the logical size of the window.
+    pub fn zoom_by(&mut self, factor: f64, anchor: Point, window_size: Size) {
+        let anchored = self.to_layout_viewport(anchor);
+        self.scale = (self.scale * factor).clamp(1.0, Self::MAX_SCALE);
+        self.offset = anchored.to_vec2() - anchor.to_vec2() / self.scale;
+        self.clamp(window_size);
+    }
+
+    /// Move the visible area by `delta` logical pixels of the window
+    pub fn pan_by(&mut self, delta: Vec2, window_size: Size) {
+        self.offset += delta / self.scale;
+        self.clamp(window_size);
+    }
+
+    /// Keep the visible area within the layout viewport
+    pub(crate) fn clamp(&mut self, window_size: Size) {
+        let max = window_size.to_vec2() * (1.0 - 1.0 / self.scale);
+        self.offset.x = self.offset.x.clamp(0.0, max.x.max(0.0));
+        self.offset.y = self.offset.y.clamp(0.0, max.y.max(0.0));
+    }
+}
+
+impl BaseDocument {
+    pub fn visual_viewport(&self) -> VisualViewport {
+        self.visual_viewport
+    }
+
+    /// Pinch-zoom by `factor`, keeping the point `anchor` of the window (in logical pixels) in
+    /// place. Unlike [`Self::zoom_by`] this doesn't lay the document out again.
+    pub fn pinch_zoom_by(&mut self, factor: f64, anchor: Point) {
+        if !self.is_user_scalable() {
+            return;
+        }
+        let window_size = self.logical_window_size();
+        self.visual_viewport.zoom_by(factor, anchor, window_size);
+    }
+
+    /// Move the pinch-zoomed area by `delta` logical pixels of the window
+    pub fn pan_visual_viewport_by(&mut self, delta: Vec2) {
+        let window_size = self.logical_window_size();
+        self.visual_viewport.pan_by(delta, window_size);
+    }
+
+    pub fn reset_pinch_zoom(&mut self) {
+        self.visual_viewport = VisualViewport::default();
+    }
+
+    pub(crate) fn logical_window_size(&self) -> Size {
+        let (width, height) = self.viewport.window_size;
+        let scale = self.viewport.hidpi_scale as f64;
+        Size::new(width as f64 / scale, height as f64 / scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zooming_keeps_the_anchor_in_place() {
+        let window_size = Size::new(800.0, 600.0);
+        let mut visual = VisualViewport::default();
+        let anchor = Point::new(200.0, 150.0);
+        visual.zoom_by(2.0, anchor, window_size);
+
+        assert_eq!(visual.scale, 2.0);
+        assert_eq!(visual.to_layout_viewport(anchor), anchor);
+        assert_eq!(visual.to_layout_viewport(Point::ZERO), Point::new(100.0, 75.0));
+
+        // The visible area can't be moved past the edges of the layout viewport
+        visual.pan_by(Vec2::new(-1000.0, 2000.0), window_size);
+        assert_eq!(visual.offset, Vec2::new(0.0, 300.0));
+    }
+}
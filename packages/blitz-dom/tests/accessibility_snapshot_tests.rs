@@ -0,0 +1,132 @@
+//! Tests for the serializable accessibility tree snapshot
+
+#![cfg(feature = "accessibility")]
+
+use blitz_dom::{
+    AccessibilitySnapshot, Attribute, BaseDocument, DocumentConfig, QualName, QuirksMode,
+    local_name, ns,
+};
+
+fn attr(name: &str, value: &str) -> Attribute {
+    Attribute {
+        name: QualName::new(None, ns!(), name.into()),
+        value: value.to_string(),
+    }
+}
+
+#[test]
+fn snapshot_contains_roles_names_and_states() {
+    let mut doc =
+        BaseDocument::new(DocumentConfig::for_testing()).expect("Failed to create test document");
+
+    let (button_id, checkbox_id) = {
+        let mut mutator = doc.mutate();
+        let html = QualName::new(None, ns!(html), local_name!("html"));
+        let html_id = mutator.create_element(html, Vec::new(), QuirksMode::NoQuirks);
+
+        let button = QualName::new(None, ns!(html), local_name!("button"));
+        let button_id = mutator.create_element(button, Vec::new(), QuirksMode::NoQuirks);
+        let label_id = mutator.create_text_node("  Save\n  changes ");
+        mutator.append_children(button_id, &[label_id]);
+
+        let input = QualName::new(None, ns!(html), local_name!("input"));
+        let checkbox_attrs = vec![
+            attr("type", "checkbox"),
+            attr("checked", ""),
+            attr("disabled", ""),
+            attr("aria-label", "Remember me"),
+        ];
+        let checkbox_id = mutator.create_element(input, checkbox_attrs, QuirksMode::NoQuirks);
+
+        let div = QualName::new(None, ns!(html), local_name!("div"));
+        let hidden_id =
+            mutator.create_element(div, vec![attr("aria-hidden", "true")], QuirksMode::NoQuirks);
+        let whitespace_id = mutator.create_text_node("\n  ");
+
+        mutator.append_children(html_id, &[button_id, whitespace_id, checkbox_id, hidden_id]);
+        mutator.append_children(0, &[html_id]);
+        (button_id, checkbox_id)
+    };
+    doc.set_focus_to(button_id);
+
+    let snapshot = doc.accessibility_snapshot();
+    assert_eq!(snapshot.focus, Some(button_id));
+    assert_eq!(snapshot.root.role, "Window");
+
+    let html = &snapshot.root.children[0];
+    assert_eq!(html.tag.as_deref(), Some("html"));
+    assert!(html.bounds.is_some());
+    // The whitespace text node and the aria-hidden div are omitted
+    assert_eq!(html.children.len(), 2);
+
+    let button = &html.children[0];
+    assert_eq!(button.role, "Button");
+    assert_eq!(button.name.as_deref(), Some("Save changes"));
+    assert!(button.states.focused);
+    assert_eq!(button.states.checked, None);
+    assert_eq!(button.children[0].role, "TextRun");
+    assert_eq!(button.children[0].value.as_deref(), Some("Save changes"));
+    assert_eq!(button.children[0].bounds, None);
+
+    let checkbox = &html.children[1];
+    assert_eq!(checkbox.id, checkbox_id);
+    assert_eq!(checkbox.role, "CheckBox");
+    assert_eq!(checkbox.name.as_deref(), Some("Remember me"));
+    assert!(checkbox.states.disabled);
+    assert_eq!(checkbox.states.checked, Some(true));
+
+    // The snapshot round-trips through JSON
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let parsed: AccessibilitySnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, snapshot);
+}
+
+#[test]
+fn masonry_items_are_read_in_placement_order() {
+    let mut doc =
+        BaseDocument::new(DocumentConfig::for_testing()).expect("Failed to create test document");
+
+    let (grid_id, [absolute_id, tall_id, definite_id, last_id]) = {
+        let mut mutator = doc.mutate();
+        let element = |local| QualName::new(None, ns!(html), local);
+        let html_id =
+            mutator.create_element(element(local_name!("html")), vec![], QuirksMode::NoQuirks);
+        let grid_style = "display: masonry; grid-template-columns: 100px 100px";
+        let grid_id = mutator.create_element(
+            element(local_name!("div")),
+            vec![attr("style", grid_style)],
+            QuirksMode::NoQuirks,
+        );
+        let mut item = |style: &str| {
+            let attrs = vec![attr("style", style)];
+            mutator.create_element(element(local_name!("div")), attrs, QuirksMode::NoQuirks)
+        };
+        let absolute_id = item("position: absolute");
+        let tall_id = item("height: 100px");
+        let definite_id = item("height: 10px; grid-column: 1");
+        let last_id = item("height: 10px");
+
+        mutator.append_children(grid_id, &[absolute_id, tall_id, definite_id, last_id]);
+        mutator.append_children(html_id, &[grid_id]);
+        mutator.append_children(0, &[html_id]);
+        (grid_id, [absolute_id, tall_id, definite_id, last_id])
+    };
+    doc.resolve();
+
+    // With `masonry-auto-flow: definite-first`, the item with a definite column is placed first.
+    // The absolutely positioned child isn't placed, so it's read last.
+    let order = [definite_id, tall_id, last_id, absolute_id];
+    assert_eq!(doc.children_in_reading_order(grid_id), order);
+
+    let snapshot = doc.accessibility_snapshot();
+    let grid = &snapshot.root.children[0].children[0];
+    let children: Vec<usize> = grid.children.iter().map(|child| child.id).collect();
+    assert_eq!(children, order);
+
+    // `reading-order` moves items within the reading order without moving them in the grid
+    let style = QualName::new(None, ns!(), local_name!("style"));
+    doc.mutate().set_attribute(last_id, style, "height: 10px; reading-order: -1");
+    doc.resolve();
+    let order = [last_id, definite_id, tall_id, absolute_id];
+    assert_eq!(doc.children_in_reading_order(grid_id), order);
+}
@@ -0,0 +1,58 @@
+//! Sizing boxes with a preferred `aspect-ratio` in the layout modes they are laid out in
+
+use blitz_dom::{Attribute, BaseDocument, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+
+/// Lay out a `<div>` styled with `css` and holding `text`, inside a 200px wide `<body>`. Returns
+/// its size.
+fn size(css: &str, text: &str) -> (f32, f32) {
+    let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let style = |style: &str| {
+        vec![Attribute {
+            name: QualName::new(None, ns!(), LocalName::from("style")),
+            value: style.to_string(),
+        }]
+    };
+    let div = mutr.create_element(name("div"), style(css), QuirksMode::NoQuirks);
+    if !text.is_empty() {
+        let text = mutr.create_text_node(text);
+        mutr.append_children(div, &[text]);
+    }
+    let body_style = style("margin: 0; width: 200px");
+    let body = mutr.create_element(name("body"), body_style, QuirksMode::NoQuirks);
+    mutr.append_children(body, &[div]);
+    let html = mutr.create_element(name("html"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    let size = doc.get_node(div).unwrap().final_layout.size;
+    (size.width, size.height)
+}
+
+#[test]
+fn stretched_blocks_take_their_height_from_the_aspect_ratio() {
+    // Laid out as a block container, an inline formatting context, a flex and a grid container
+    assert_eq!(size("aspect-ratio: 2", ""), (200.0, 100.0));
+    assert_eq!(size("aspect-ratio: 2", "Text"), (200.0, 100.0));
+    assert_eq!(size("aspect-ratio: 4 / 1; display: flex", "Text"), (200.0, 50.0));
+    assert_eq!(size("aspect-ratio: 4 / 1; display: grid", "Text"), (200.0, 50.0));
+
+    // The ratio applies to the content box, and the transferred height is clamped
+    let content_box = "aspect-ratio: 2; padding: 10px; box-sizing: content-box";
+    assert_eq!(size(content_box, ""), (200.0, 110.0));
+    assert_eq!(size("aspect-ratio: 2; max-height: 40px", ""), (200.0, 40.0));
+}
+
+#[test]
+fn content_taller_than_the_aspect_ratio_grows_the_box() {
+    let text = "Text which wraps onto many lines in a box this narrow";
+    let (_, height) = size("aspect-ratio: 20; font-size: 20px", text);
+    assert!(height > 40.0, "{height}");
+
+    // Unless it's a scroll container or has a min-height
+    assert_eq!(size("aspect-ratio: 20; overflow: hidden", text), (200.0, 10.0));
+    assert_eq!(size("aspect-ratio: 20; min-height: 0", text), (200.0, 10.0));
+}
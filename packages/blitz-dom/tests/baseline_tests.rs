@@ -0,0 +1,60 @@
+//! Aligning the baselines of flex items whose first baselines come from nested containers
+
+use blitz_dom::{Attribute, BaseDocument, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+
+/// Lay out a row of `align-items: baseline` flex items: a block holding a `nested` container,
+/// whose first item is pushed down 30px by its padding, and an item holding only text. Returns
+/// how far down the second item is aligned relative to the first.
+fn baseline_offset(nested: &str) -> f32 {
+    let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let mut div = |style: &str, text: Option<&str>| {
+        let attrs = vec![Attribute {
+            name: QualName::new(None, ns!(), LocalName::from("style")),
+            value: style.to_string(),
+        }];
+        let div = mutr.create_element(name("div"), attrs, QuirksMode::NoQuirks);
+        if let Some(text) = text {
+            let text = mutr.create_text_node(text);
+            mutr.append_children(div, &[text]);
+        }
+        div
+    };
+
+    let row = div("display: flex; align-items: baseline", None);
+    let block = div("", None);
+    let nested = div(nested, None);
+    let pushed = div("padding-top: 30px", Some("x"));
+    let next = div("", Some("y"));
+    let text = div("", Some("z"));
+    mutr.append_children(nested, &[pushed, next]);
+    mutr.append_children(block, &[nested]);
+    mutr.append_children(row, &[block, text]);
+    let body = mutr.create_element(name("body"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(body, &[row]);
+    let html = mutr.create_element(name("html"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    let y = |node_id: usize| doc.get_node(node_id).unwrap().final_layout.location.y;
+    y(text) - y(block)
+}
+
+#[test]
+fn blocks_take_the_first_baseline_of_nested_flex_containers() {
+    let offset = baseline_offset("display: flex");
+    assert!((offset - 30.0).abs() < 0.5, "{offset}");
+
+    let offset = baseline_offset("display: flex; flex-direction: column");
+    assert!((offset - 30.0).abs() < 0.5, "{offset}");
+}
+
+#[test]
+fn blocks_take_the_first_baseline_of_nested_grid_containers() {
+    let offset = baseline_offset("display: grid; grid-template-columns: 1fr 1fr");
+    assert!((offset - 30.0).abs() < 0.5, "{offset}");
+}
+
@@ -0,0 +1,8 @@
+//! Helpers shared by the integration tests
+
+use blitz_dom::{BaseDocument, DocumentConfig};
+
+/// An empty document, with the providers of [`DocumentConfig::for_testing`]
+pub fn document() -> BaseDocument {
+    BaseDocument::new(DocumentConfig::for_testing()).unwrap()
+}
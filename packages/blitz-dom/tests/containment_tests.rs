@@ -0,0 +1,78 @@
+//! Size containment and skipping the contents of `content-visibility` elements
+
+use blitz_dom::testing::{append_styled, document_with_viewport};
+use blitz_dom::{BaseDocument, QualName, local_name, ns};
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+/// A document with an 800x600 viewport and a margin-less body, returning the body's id
+fn document() -> (BaseDocument, usize) {
+    let mut doc = document_with_viewport(Viewport::new(800, 600, 1.0, ColorScheme::Light));
+    let mut mutr = doc.mutate();
+    let html = append_styled(&mut mutr, 0, "html", "");
+    let body = append_styled(&mut mutr, html, "body", "margin: 0");
+    drop(mutr);
+    (doc, body)
+}
+
+/// The height of the border box of `node_id`
+fn height(doc: &BaseDocument, node_id: usize) -> f32 {
+    doc.get_node(node_id).unwrap().final_layout.size.height
+}
+
+#[test]
+fn size_contained_elements_are_sized_as_if_empty() {
+    let (mut doc, body) = document();
+    let mut mutr = doc.mutate();
+    let contained = append_styled(&mut mutr, body, "div", "contain: size");
+    append_styled(&mut mutr, contained, "div", "height: 100px");
+    let sized = append_styled(&mut mutr, body, "div", "contain: size; height: 50px");
+    append_styled(&mut mutr, sized, "div", "height: 100px");
+    let uncontained = append_styled(&mut mutr, body, "div", "contain: paint");
+    append_styled(&mut mutr, uncontained, "div", "height: 100px");
+    drop(mutr);
+    doc.resolve();
+
+    assert_eq!(height(&doc, contained), 0.0);
+    assert_eq!(height(&doc, sized), 50.0);
+    assert_eq!(height(&doc, uncontained), 100.0);
+}
+
+#[test]
+fn hidden_content_visibility_skips_contents() {
+    let (mut doc, body) = document();
+    let mut mutr = doc.mutate();
+    let hidden = append_styled(&mut mutr, body, "div", "content-visibility: hidden");
+    append_styled(&mut mutr, hidden, "div", "height: 100px");
+    drop(mutr);
+    doc.resolve();
+
+    assert!(doc.get_node(hidden).unwrap().flags.skips_contents());
+    assert_eq!(height(&doc, hidden), 0.0);
+}
+
+#[test]
+fn auto_content_visibility_skips_contents_far_from_the_viewport() {
+    let (mut doc, body) = document();
+    let mut mutr = doc.mutate();
+    append_styled(&mut mutr, body, "div", "height: 2000px");
+    let card = append_styled(&mut mutr, body, "div", "content-visibility: auto");
+    let content = append_styled(&mut mutr, card, "div", "height: 100px");
+    drop(mutr);
+    doc.resolve();
+
+    // The card is laid out once, then skipped at the size it had
+    assert!(doc.get_node(card).unwrap().flags.skips_contents());
+    assert_eq!(height(&doc, card), 100.0);
+
+    // Its contents aren't laid out while it is skipped
+    let style = QualName::new(None, ns!(), local_name!("style"));
+    doc.mutate().set_attribute(content, style, "height: 300px");
+    doc.resolve();
+    assert_eq!(height(&doc, card), 100.0);
+
+    // Scrolling to it lays its contents out again
+    doc.scroll_viewport_by(0.0, -1800.0);
+    doc.resolve();
+    assert!(!doc.get_node(card).unwrap().flags.skips_contents());
+    assert_eq!(height(&doc, card), 300.0);
+}
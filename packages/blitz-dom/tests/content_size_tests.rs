@@ -0,0 +1,21 @@
+//! The size of a document's content, and the scale it fits a viewport at
+
+use blitz_dom::testing::{append_styled, document_with_viewport};
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+#[test]
+fn content_size_includes_overflowing_content() {
+    let mut doc = document_with_viewport(Viewport::new(800, 600, 1.0, ColorScheme::Light));
+    let mut mutr = doc.mutate();
+    let html = append_styled(&mut mutr, 0, "html", "");
+    let body = append_styled(&mut mutr, html, "body", "margin: 0");
+    append_styled(&mut mutr, body, "div", "width: 1200px; height: 2000px");
+    drop(mutr);
+    doc.resolve();
+
+    let size = doc.content_size();
+    assert_eq!((size.width, size.height), (1200.0, 2000.0));
+    // Fitting the width only, as full-page screenshots do, and fitting both
+    assert_eq!(doc.scale_to_fit(600.0, f64::INFINITY), 0.5);
+    assert_eq!(doc.scale_to_fit(600.0, 500.0), 0.25);
+}
@@ -0,0 +1,383 @@
+//! The capture, target and bubble phases of events, preventing their default actions, and
+//! pointer capture
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use blitz_dom::{
+    Attribute, BaseDocument, DocumentConfig, DocumentMutator, EventDriver, EventHandler,
+    LocalName, QualName, QuirksMode, local_name, ns,
+};
+use blitz_traits::events::{
+    BlitzKeyEvent, BlitzMouseButtonEvent, BlitzPointerEvent, DomEvent, DomEventData, EventPhase,
+    EventState, KeyState, MouseEventButton, MouseEventButtons, PointerType, UiEvent,
+};
+use blitz_traits::navigation::{NavigationOptions, NavigationProvider};
+use blitz_traits::net::{DummyNetProvider, Url};
+use blitz_traits::shell::{ColorScheme, ContextMenuRequest, ShellProvider, Viewport};
+use keyboard_types::{Code, Key, Location, Modifiers, NamedKey};
+
+#[derive(Default)]
+struct RecordingNavigation(Mutex<Vec<Url>>);
+
+impl NavigationProvider for RecordingNavigation {
+    fn navigate_to(&self, options: NavigationOptions) {
+        self.0.lock().unwrap().push(options.url);
+    }
+}
+
+type Calls = Rc<RefCell<Vec<(EventPhase, Vec<usize>)>>>;
+
+/// Records the chain each phase is handled with, stopping propagation in the `stop_in` phase
+/// and preventing default actions if `prevent_default`
+#[derive(Default)]
+struct Recorder {
+    calls: Calls,
+    stop_in: Option<EventPhase>,
+    prevent_default: bool,
+}
+
+impl EventHandler for Recorder {
+    fn handle_event(
+        &mut self,
+        chain: &[usize],
+        event: &mut DomEvent,
+        _mutr: &mut DocumentMutator<'_>,
+        event_state: &mut EventState,
+    ) {
+        self.calls.borrow_mut().push((event.phase, chain.to_vec()));
+        if self.prevent_default {
+            event_state.prevent_default();
+        }
+        if self.stop_in == Some(event.phase) {
+            event_state.stop_propagation();
+        }
+    }
+}
+
+/// A document with a link inside a `<div>`, returning the ids of the `<div>` and the link
+fn document(navigation: Arc<RecordingNavigation>) -> (BaseDocument, usize, usize) {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        navigation_provider: Some(navigation),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let mut mutr = doc.mutate();
+    let name = |local| QualName::new(None, ns!(html), local);
+    let div = mutr.create_element(name(local_name!("div")), Vec::new(), QuirksMode::NoQuirks);
+    let href = Attribute {
+        name: QualName::new(None, ns!(), LocalName::from("href")),
+        value: "https://example.com/next".to_string(),
+    };
+    let link = mutr.create_element(name(local_name!("a")), vec![href], QuirksMode::NoQuirks);
+    mutr.append_children(div, &[link]);
+    mutr.append_children(0, &[div]);
+    drop(mutr);
+
+    (doc, div, link)
+}
+
+fn click(doc: &mut BaseDocument, target: usize, recorder: Recorder) {
+    let data = doc.get_node(target).unwrap().synthetic_click_event_data(Modifiers::empty());
+    let mut driver = EventDriver::new(doc.mutate(), recorder);
+    driver.handle_dom_event(DomEvent::new(target, DomEventData::Click(data)));
+}
+
+#[test]
+fn events_are_captured_then_bubbled() {
+    let navigation = Arc::new(RecordingNavigation::default());
+    let (mut doc, div, link) = document(navigation.clone());
+    let recorder = Recorder::default();
+    let calls = recorder.calls.clone();
+
+    click(&mut doc, link, recorder);
+
+    let calls = calls.borrow();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].0, EventPhase::Capturing);
+    assert_eq!(calls[0].1.last(), Some(&div));
+    assert_eq!(calls[1], (EventPhase::AtTarget, vec![link]));
+    assert_eq!(calls[2].0, EventPhase::Bubbling);
+    assert_eq!(calls[2].1[0], div);
+    assert!(calls[0].1.iter().eq(calls[2].1.iter().rev()));
+    // The link's default action navigated
+    assert_eq!(navigation.0.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn stopping_propagation_skips_later_phases() {
+    let navigation = Arc::new(RecordingNavigation::default());
+    let (mut doc, _div, link) = document(navigation.clone());
+    let recorder = Recorder {
+        stop_in: Some(EventPhase::Capturing),
+        ..Default::default()
+    };
+    let calls = recorder.calls.clone();
+
+    click(&mut doc, link, recorder);
+
+    assert_eq!(calls.borrow().len(), 1);
+    // Stopping propagation doesn't prevent the default action
+    assert_eq!(navigation.0.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn preventing_default_cancels_navigation() {
+    let navigation = Arc::new(RecordingNavigation::default());
+    let (mut doc, _div, link) = document(navigation.clone());
+    let recorder = Recorder {
+        prevent_default: true,
+        ..Default::default()
+    };
+
+    click(&mut doc, link, recorder);
+
+    assert!(navigation.0.lock().unwrap().is_empty());
+}
+
+type Targets = Rc<RefCell<Vec<(&'static str, usize)>>>;
+
+/// Records the name and target of each event
+#[derive(Default)]
+struct TargetRecorder(Targets);
+
+impl EventHandler for TargetRecorder {
+    fn handle_event(
+        &mut self,
+        _chain: &[usize],
+        event: &mut DomEvent,
+        _mutr: &mut DocumentMutator<'_>,
+        _event_state: &mut EventState,
+    ) {
+        if event.phase == EventPhase::AtTarget {
+            self.0.borrow_mut().push((event.data.name(), event.target));
+        }
+    }
+}
+
+fn touch(x: f32, y: f32, pressed: bool) -> BlitzPointerEvent {
+    BlitzPointerEvent {
+        pointer_id: 2,
+        pointer_type: PointerType::Touch,
+        is_primary: true,
+        x,
+        y,
+        button: MouseEventButton::Main,
+        buttons: if pressed {
+            MouseEventButtons::Primary
+        } else {
+            MouseEventButtons::None
+        },
+        pressure: if pressed { 0.5 } else { 0.0 },
+        altitude_angle: std::f32::consts::FRAC_PI_2,
+        azimuth_angle: 0.0,
+        mods: Modifiers::empty(),
+    }
+}
+
+/// A laid out document with two 100px squares, one above the other, returning their ids
+fn stacked_document() -> (BaseDocument, usize, usize) {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        viewport: Some(Viewport::new(200, 200, 1.0, ColorScheme::Light)),
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+    doc.add_user_agent_stylesheet("body { margin: 0 } div { width: 100px; height: 100px }");
+
+    let mut mutr = doc.mutate();
+    let name = |local| QualName::new(None, ns!(html), local);
+    let html = mutr.create_element(name(local_name!("html")), Vec::new(), QuirksMode::NoQuirks);
+    let body = mutr.create_element(name(local_name!("body")), Vec::new(), QuirksMode::NoQuirks);
+    let top = mutr.create_element(name(local_name!("div")), Vec::new(), QuirksMode::NoQuirks);
+    let bottom = mutr.create_element(name(local_name!("div")), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(body, &[top, bottom]);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    (doc, top, bottom)
+}
+
+#[test]
+fn touches_are_captured_by_the_element_they_start_on() {
+    let (mut doc, top, _bottom) = stacked_document();
+
+    let recorder = TargetRecorder::default();
+    let targets = recorder.0.clone();
+    let mut driver = EventDriver::new(doc.mutate(), recorder);
+    driver.handle_ui_event(UiEvent::PointerDown(touch(50.0, 50.0, true)));
+    // The touch moves onto the bottom element, but stays targeted at the top one
+    driver.handle_ui_event(UiEvent::PointerMove(touch(50.0, 150.0, true)));
+    driver.handle_ui_event(UiEvent::PointerUp(touch(50.0, 150.0, false)));
+    drop(driver);
+
+    let targets = targets.borrow();
+    let pointer_events: Vec<_> = targets
+        .iter()
+        .filter(|(name, _)| name.contains("pointer"))
+        .copied()
+        .collect();
+    assert_eq!(
+        pointer_events,
+        [
+            ("pointerdown", top),
+            ("gotpointercapture", top),
+            ("pointermove", top),
+            ("pointerup", top),
+            ("lostpointercapture", top),
+        ]
+    );
+    // Touch and compatibility mouse events follow the capture too
+    assert!(targets.contains(&("touchmove", top)));
+    assert!(targets.contains(&("mousemove", top)));
+}
+
+/// Records the name and target of each event, cancelling `pointerdown` events
+#[derive(Default)]
+struct PointerDownCanceller(Targets);
+
+impl EventHandler for PointerDownCanceller {
+    fn handle_event(
+        &mut self,
+        _chain: &[usize],
+        event: &mut DomEvent,
+        _mutr: &mut DocumentMutator<'_>,
+        event_state: &mut EventState,
+    ) {
+        if event.phase == EventPhase::AtTarget {
+            self.0.borrow_mut().push((event.data.name(), event.target));
+        }
+        if matches!(event.data, DomEventData::PointerDown(_)) {
+            event_state.prevent_default();
+        }
+    }
+}
+
+#[test]
+fn cancelling_pointerdown_only_suppresses_compatibility_mouse_events() {
+    let (mut doc, top, _bottom) = stacked_document();
+    let canceller = PointerDownCanceller::default();
+    let targets = canceller.0.clone();
+    let mouse = |buttons| BlitzMouseButtonEvent {
+        x: 50.0,
+        y: 50.0,
+        button: MouseEventButton::Main,
+        buttons,
+        mods: Modifiers::empty(),
+    };
+
+    let mut driver = EventDriver::new(doc.mutate(), canceller);
+    driver.handle_ui_event(UiEvent::MouseMove(mouse(MouseEventButtons::None)));
+    driver.handle_ui_event(UiEvent::MouseDown(mouse(MouseEventButtons::Primary)));
+    driver.handle_ui_event(UiEvent::MouseUp(mouse(MouseEventButtons::None)));
+    driver.handle_ui_event(UiEvent::PointerDown(touch(50.0, 50.0, true)));
+    driver.handle_ui_event(UiEvent::PointerUp(touch(50.0, 50.0, false)));
+    drop(driver);
+
+    let names: Vec<&str> = targets
+        .borrow()
+        .iter()
+        .filter(|&&(_, target)| target == top)
+        .map(|&(name, _)| name)
+        .collect();
+    let count = |name| names.iter().filter(|&&other| other == name).count();
+    assert_eq!(count("pointerdown"), 2);
+    assert_eq!(count("pointerup"), 2);
+    assert_eq!(count("mousedown") + count("mouseup"), 0);
+    // Clicks aren't compatibility mouse events
+    assert_eq!(count("click"), 2);
+}
+
+#[derive(Default)]
+struct RecordingShell(Mutex<Vec<ContextMenuRequest>>);
+
+impl ShellProvider for RecordingShell {
+    fn show_context_menu(&self, request: ContextMenuRequest) {
+        self.0.lock().unwrap().push(request);
+    }
+}
+
+/// A document with a link inside a `<div>` which shows context menus with `shell`, returning the
+/// ids of the `<div>` and the link
+fn context_menu_document(shell: Arc<RecordingShell>) -> (BaseDocument, usize, usize) {
+    let (mut doc, div, link) = document(Arc::new(RecordingNavigation::default()));
+    doc.set_shell_provider(shell);
+    (doc, div, link)
+}
+
+#[test]
+fn right_clicks_show_context_menus() {
+    let shell = Arc::new(RecordingShell::default());
+    let (mut doc, _div, link) = context_menu_document(shell.clone());
+    let recorder = TargetRecorder::default();
+    let targets = recorder.0.clone();
+
+    let mut data = doc.get_node(link).unwrap().synthetic_click_event_data(Modifiers::empty());
+    data.button = MouseEventButton::Secondary;
+    let mut driver = EventDriver::new(doc.mutate(), recorder);
+    driver.handle_dom_event(DomEvent::new(link, DomEventData::MouseUp(data)));
+    drop(driver);
+
+    assert!(targets.borrow().contains(&("contextmenu", link)));
+    let requests = shell.0.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].node_id, link);
+    assert_eq!(requests[0].link.as_deref(), Some("https://example.com/next"));
+}
+
+#[test]
+fn preventing_default_suppresses_context_menus() {
+    let shell = Arc::new(RecordingShell::default());
+    let (mut doc, _div, link) = context_menu_document(shell.clone());
+    let recorder = Recorder {
+        prevent_default: true,
+        ..Default::default()
+    };
+
+    let mut data = doc.get_node(link).unwrap().synthetic_click_event_data(Modifiers::empty());
+    data.button = MouseEventButton::Secondary;
+    let mut driver = EventDriver::new(doc.mutate(), recorder);
+    driver.handle_dom_event(DomEvent::new(link, DomEventData::MouseUp(data)));
+    drop(driver);
+
+    assert!(shell.0.lock().unwrap().is_empty());
+}
+
+#[test]
+fn context_menu_keys_show_context_menus() {
+    let shell = Arc::new(RecordingShell::default());
+    let (mut doc, _div, link) = context_menu_document(shell.clone());
+    let key_down = |key, code, modifiers| BlitzKeyEvent {
+        key,
+        code,
+        modifiers,
+        location: Location::Standard,
+        is_auto_repeating: false,
+        is_composing: false,
+        state: KeyState::Pressed,
+        text: None,
+    };
+
+    let mut driver = EventDriver::new(doc.mutate(), Recorder::default());
+    let context_menu = key_down(
+        Key::Named(NamedKey::ContextMenu),
+        Code::ContextMenu,
+        Modifiers::empty(),
+    );
+    driver.handle_dom_event(DomEvent::new(link, DomEventData::KeyDown(context_menu)));
+    let shift_f10 = key_down(Key::Named(NamedKey::F10), Code::F10, Modifiers::SHIFT);
+    driver.handle_dom_event(DomEvent::new(link, DomEventData::KeyDown(shift_f10)));
+    // F10 alone doesn't
+    let f10 = key_down(Key::Named(NamedKey::F10), Code::F10, Modifiers::empty());
+    driver.handle_dom_event(DomEvent::new(link, DomEventData::KeyDown(f10)));
+    drop(driver);
+
+    let requests = shell.0.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|request| request.node_id == link));
+}
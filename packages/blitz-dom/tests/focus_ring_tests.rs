@@ -0,0 +1,81 @@
+//! Which ways of focussing elements show their focus ring (and make them match `:focus-visible`)
+
+use blitz_dom::testing::{append_element, document_with_viewport};
+use blitz_dom::{BaseDocument, EventDriver, NoopEventHandler};
+use blitz_traits::events::{BlitzMouseButtonEvent, MouseEventButton, MouseEventButtons, UiEvent};
+use blitz_traits::shell::{ColorScheme, Viewport};
+use keyboard_types::Modifiers;
+
+const CSS: &str = "body { margin: 0 } button, input, div { display: block; height: 40px }";
+
+/// A document with a button, a text input and a `<div>` about 40px tall each, one above the other,
+/// returning their ids
+fn controls_document() -> (BaseDocument, usize, usize, usize) {
+    let mut doc = document_with_viewport(Viewport::new(200, 200, 1.0, ColorScheme::Light));
+    doc.add_user_agent_stylesheet(CSS);
+
+    let mut mutr = doc.mutate();
+    let html = append_element(&mut mutr, 0, "html", &[]);
+    let body = append_element(&mut mutr, html, "body", &[]);
+    let button = append_element(&mut mutr, body, "button", &[]);
+    let label = mutr.create_text_node("Press");
+    mutr.append_children(button, &[label]);
+    let input = append_element(&mut mutr, body, "input", &[("type", "text")]);
+    let div = append_element(&mut mutr, body, "div", &[]);
+    drop(mutr);
+    doc.resolve();
+
+    (doc, button, input, div)
+}
+
+/// Click the main mouse button at `y`
+fn click(doc: &mut BaseDocument, y: f32) {
+    let event = |buttons| BlitzMouseButtonEvent {
+        x: 20.0,
+        y,
+        button: MouseEventButton::Main,
+        buttons,
+        mods: Modifiers::empty(),
+    };
+    let mut driver = EventDriver::new(doc.mutate(), NoopEventHandler);
+    driver.handle_ui_event(UiEvent::MouseMove(event(MouseEventButtons::None)));
+    driver.handle_ui_event(UiEvent::MouseDown(event(MouseEventButtons::Primary)));
+    driver.handle_ui_event(UiEvent::MouseUp(event(MouseEventButtons::None)));
+    drop(driver);
+    doc.resolve();
+}
+
+fn focus_visible(doc: &BaseDocument, node_id: usize) -> bool {
+    doc.matches(node_id, ":focus-visible").unwrap()
+}
+
+#[test]
+fn clicked_elements_only_show_a_focus_ring_if_they_take_text() {
+    let (mut doc, button, input, div) = controls_document();
+
+    click(&mut doc, 20.0);
+    assert_eq!(doc.get_focussed_node_id(), Some(button));
+    assert!(!focus_visible(&doc, button));
+
+    click(&mut doc, 60.0);
+    assert_eq!(doc.get_focussed_node_id(), Some(input));
+    assert!(focus_visible(&doc, input));
+
+    // Clicking elements which can't be focussed clears the focus
+    click(&mut doc, 100.0);
+    assert_eq!(doc.get_focussed_node_id(), None);
+    assert!(!focus_visible(&doc, div));
+}
+
+#[test]
+fn elements_focussed_with_the_keyboard_show_a_focus_ring() {
+    let (mut doc, button, input, _div) = controls_document();
+    click(&mut doc, 20.0);
+    assert!(!focus_visible(&doc, button));
+
+    // Tabbing away and back shows the ring, as does focussing a clicked element again
+    assert_eq!(doc.focus_next_node(), Some(input));
+    doc.set_focus_to(button);
+    assert!(focus_visible(&doc, button));
+    assert!(!doc.matches(input, ":focus").unwrap());
+}
@@ -0,0 +1,80 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use blitz_dom::net::Resource;
+use blitz_dom::{
+    BaseDocument, DocumentConfig, FontFaceEvent, FontFaceStatus, QualName, QuirksMode, local_name,
+    ns,
+};
+use blitz_traits::net::{BoxedHandler, Bytes, NetProvider, Request};
+
+/// Answers requests with `body`, or fails them (dropping the handler) if there is none
+struct FontProvider {
+    body: Option<&'static [u8]>,
+}
+
+impl NetProvider<Resource> for FontProvider {
+    fn fetch(&self, doc_id: usize, _request: Request, handler: BoxedHandler<Resource>) {
+        if let Some(body) = self.body {
+            handler.bytes(doc_id, Bytes::from_static(body), Arc::new(|_, _| {}));
+        }
+    }
+}
+
+/// A document which loads a font from `provider` with an `@font-face` rule
+fn document(provider: FontProvider) -> (BaseDocument, Arc<Mutex<Vec<FontFaceEvent>>>) {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        base_url: Some("https://example.com/".to_string()),
+        net_provider: Some(Arc::new(provider)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_clone = events.clone();
+    doc.fonts()
+        .add_listener(move |event| events_clone.lock().unwrap().push(event.clone()));
+
+    let mut mutr = doc.mutate();
+    let style = mutr.create_element(
+        QualName::new(None, ns!(html), local_name!("style")),
+        Vec::new(),
+        QuirksMode::NoQuirks,
+    );
+    let css = mutr.create_text_node("@font-face { font-family: Test; src: url(test.ttf) }");
+    mutr.append_children(style, &[css]);
+    mutr.append_children(0, &[style]);
+    drop(mutr);
+    (doc, events)
+}
+
+fn is_ready(doc: &BaseDocument) -> bool {
+    let ready = pin!(doc.fonts().ready());
+    ready.poll(&mut Context::from_waker(Waker::noop())) == Poll::Ready(())
+}
+
+#[test]
+fn failed_fetches_settle_the_set() {
+    let (doc, events) = document(FontProvider { body: None });
+
+    let faces = doc.fonts().faces();
+    assert_eq!(faces.len(), 1);
+    assert_eq!(faces[0].status, FontFaceStatus::Error);
+    assert!(is_ready(&doc));
+    let events = events.lock().unwrap();
+    assert!(matches!(events[0], FontFaceEvent::Error(_)));
+    assert_eq!(events[1], FontFaceEvent::LoadingDone);
+}
+
+#[test]
+fn fonts_which_are_not_fonts_are_rejected() {
+    let (doc, _) = document(FontProvider {
+        body: Some(b"<!doctype html><title>Not found</title>"),
+    });
+
+    let face = doc.fonts().face(0).unwrap();
+    assert_eq!(face.status, FontFaceStatus::Error);
+    assert_eq!(face.error.as_deref(), Some("Unrecognised font format"));
+    assert!(is_ready(&doc));
+}
@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+
+use blitz_dom::{Attribute, BaseDocument, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+use blitz_traits::navigation::{FormEntry, FormEntryValue, NavigationOptions, NavigationProvider};
+use blitz_traits::net::{Bytes, Method};
+
+#[derive(Default)]
+struct RecordingNavigation(Mutex<Vec<NavigationOptions>>);
+
+impl NavigationProvider for RecordingNavigation {
+    fn navigate_to(&self, options: NavigationOptions) {
+        self.0.lock().unwrap().push(options);
+    }
+}
+
+fn attrs(attrs: &[(&str, &str)]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .map(|(name, value)| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(*name)),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn multipart_forms_are_submitted_with_their_entries() {
+    let navigation = Arc::new(RecordingNavigation::default());
+    let mut doc = BaseDocument::new(DocumentConfig {
+        base_url: Some("https://example.com/".to_string()),
+        navigation_provider: Some(navigation.clone()),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let form_attrs = attrs(&[
+        ("action", "/upload"),
+        ("method", "post"),
+        ("enctype", "multipart/form-data"),
+    ]);
+    let form = mutr.create_element(name("form"), form_attrs, QuirksMode::NoQuirks);
+    let hidden = attrs(&[("type", "hidden"), ("name", "title"), ("value", "Holiday\nphotos")]);
+    let hidden = mutr.create_element(name("input"), hidden, QuirksMode::NoQuirks);
+    let file = attrs(&[("type", "file"), ("name", "photo")]);
+    let file = mutr.create_element(name("input"), file, QuirksMode::NoQuirks);
+    mutr.append_children(form, &[hidden, file]);
+    mutr.append_children(0, &[form]);
+    drop(mutr);
+
+    doc.submit_form(form, form);
+
+    let options = navigation.0.lock().unwrap().pop().unwrap();
+    assert_eq!(options.url.as_str(), "https://example.com/upload");
+    assert_eq!(options.content_type, "multipart/form-data");
+    let entries = vec![
+        FormEntry {
+            name: "title".to_string(),
+            value: FormEntryValue::Text("Holiday\r\nphotos".to_string()),
+        },
+        // With no file picked, an empty file is sent
+        FormEntry {
+            name: "photo".to_string(),
+            value: FormEntryValue::File {
+                name: String::new(),
+                content_type: "application/octet-stream".to_string(),
+                data: Bytes::new(),
+                path: None,
+            },
+        },
+    ];
+    assert_eq!(options.form_data, Some(entries));
+    assert_eq!(options.into_request().method, Method::POST);
+}
+
+#[test]
+fn picked_files_are_submitted_from_disk() {
+    let path = std::env::temp_dir().join(format!("blitz-form-{}.txt", fastrand::u64(..)));
+    std::fs::write(&path, "sand").unwrap();
+
+    let navigation = Arc::new(RecordingNavigation::default());
+    let mut doc = BaseDocument::new(DocumentConfig {
+        base_url: Some("https://example.com/".to_string()),
+        navigation_provider: Some(navigation.clone()),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let form_attrs = attrs(&[("method", "post"), ("enctype", "multipart/form-data")]);
+    let form = mutr.create_element(name("form"), form_attrs, QuirksMode::NoQuirks);
+    let file = attrs(&[("type", "file"), ("name", "photo")]);
+    let file = mutr.create_element(name("input"), file, QuirksMode::NoQuirks);
+    mutr.append_children(form, &[file]);
+    mutr.append_children(0, &[form]);
+    drop(mutr);
+
+    assert!(!doc.set_selected_files(form, std::slice::from_ref(&path)));
+    assert!(doc.set_selected_files(file, std::slice::from_ref(&path)));
+    doc.submit_form(form, form);
+
+    let options = navigation.0.lock().unwrap().pop().unwrap();
+    let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+    let entries = vec![FormEntry {
+        name: "photo".to_string(),
+        value: FormEntryValue::File {
+            name: file_name.clone(),
+            content_type: "text/plain".to_string(),
+            data: Bytes::new(),
+            path: Some(path.clone()),
+        },
+    }];
+    assert_eq!(options.form_data, Some(entries));
+
+    // The request's body is the encoded form, with the boundary in its content type
+    let request = options.into_request();
+    std::fs::remove_file(&path).unwrap();
+    let content_type = request.headers["content-type"].to_str().unwrap();
+    let boundary = content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+    let expected = format!(
+        concat!(
+            "--{0}\r\nContent-Disposition: form-data; name=\"photo\"; ",
+            "filename=\"{1}\"\r\nContent-Type: text/plain\r\n\r\n",
+            "sand\r\n",
+            "--{0}--\r\n",
+        ),
+        boundary,
+        file_name,
+    );
+    assert_eq!(request.body, Bytes::from(expected));
+}
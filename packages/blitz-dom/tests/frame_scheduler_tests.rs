@@ -0,0 +1,97 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use blitz_dom::testing::append_styled;
+use blitz_dom::{BaseDocument, QualName, local_name, ns};
+use common::document;
+
+#[test]
+fn callbacks_run_once_with_the_frame_time() {
+    let mut doc = document();
+    let timestamps = Rc::new(RefCell::new(Vec::new()));
+
+    let recorded = timestamps.clone();
+    doc.request_animation_frame(move |_, timestamp| recorded.borrow_mut().push(timestamp));
+    let cancelled = doc.request_animation_frame(|_, _| panic!("cancelled callback ran"));
+    doc.cancel_animation_frame(cancelled);
+    assert!(doc.has_animation_frame_callbacks());
+    assert!(doc.is_animating());
+
+    doc.tick_animation_frame(Duration::from_millis(250));
+    doc.tick_animation_frame(Duration::from_millis(266));
+    assert_eq!(*timestamps.borrow(), [250.0]);
+    assert!(!doc.is_animating());
+}
+
+#[test]
+fn callbacks_requested_during_a_frame_run_in_the_next_one() {
+    fn request(doc: &mut BaseDocument, timestamps: Rc<RefCell<Vec<f64>>>) {
+        doc.request_animation_frame(move |doc, timestamp| {
+            timestamps.borrow_mut().push(timestamp);
+            if timestamps.borrow().len() < 3 {
+                request(doc, timestamps);
+            }
+        });
+    }
+
+    let mut doc = document();
+    let timestamps = Rc::new(RefCell::new(Vec::new()));
+    request(&mut doc, timestamps.clone());
+    for frame in 0..5 {
+        doc.tick_animation_frame(Duration::from_millis(frame * 16));
+    }
+    assert_eq!(*timestamps.borrow(), [0.0, 16.0, 32.0]);
+}
+
+#[test]
+fn frames_are_accounted_against_the_budget() {
+    let mut doc = document();
+    doc.set_frame_budget(Duration::from_millis(10));
+
+    doc.tick_animation_frame(Duration::from_millis(100));
+    doc.finish_animation_frame(Duration::from_millis(4));
+    assert_eq!(doc.frame_stats().budget_remaining(), Duration::from_millis(6));
+
+    // Three budgets after the last frame, so two were skipped
+    doc.tick_animation_frame(Duration::from_millis(130));
+    doc.finish_animation_frame(Duration::from_millis(12));
+
+    let stats = doc.frame_stats();
+    assert_eq!(stats.frames, 2);
+    assert_eq!(stats.interval, Duration::from_millis(30));
+    assert_eq!(stats.skipped_frames, 2);
+    assert_eq!(stats.over_budget_frames, 1);
+    assert_eq!(stats.budget_remaining(), Duration::ZERO);
+}
+
+#[test]
+fn css_transitions_advance_with_the_frame_clock() {
+    let mut doc = document();
+    let mut mutr = doc.mutate();
+    let html = append_styled(&mut mutr, 0, "html", "");
+    let body = append_styled(&mut mutr, html, "body", "margin: 0");
+    let div = append_styled(&mut mutr, body, "div", "width: 100px; transition: width 1s linear");
+    drop(mutr);
+    doc.tick_animation_frame(Duration::ZERO);
+    doc.resolve();
+
+    let style = QualName::new(None, ns!(), local_name!("style"));
+    let value = "width: 200px; transition: width 1s linear";
+    doc.mutate().set_attribute(div, style, value);
+    doc.resolve();
+    assert!(doc.is_animating());
+
+    let width = |doc: &BaseDocument| doc.get_node(div).unwrap().final_layout.size.width;
+    doc.tick_animation_frame(Duration::from_millis(500));
+    doc.resolve();
+    assert!((width(&doc) - 150.0).abs() < 5.0, "{}", width(&doc));
+
+    doc.tick_animation_frame(Duration::from_millis(1500));
+    doc.resolve();
+    assert_eq!(width(&doc), 200.0);
+    doc.tick_animation_frame(Duration::from_millis(1516));
+    assert!(!doc.is_animating());
+}
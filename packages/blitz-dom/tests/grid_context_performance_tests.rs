@@ -9,6 +9,7 @@ use blitz_dom::layout::grid_context::{
     resolve_parent_grid_context_for_generic_tree,
     resolve_parent_grid_context_for_generic_tree_efficient,
 };
+use blitz_dom::layout::{GridPreprocessingError, GridStyleAccess};
 use taffy::prelude::*;
 
 /// Mock tree implementation for performance testing
@@ -138,6 +139,38 @@ impl taffy::LayoutPartialTree for MockTestTree {
     }
 }
 
+impl GridStyleAccess for MockTestTree {
+    fn grid_computed_styles(
+        &self,
+        _node_id: NodeId,
+    ) -> Option<atomic_refcell::AtomicRef<'_, style::properties::ComputedValues>> {
+        // Mock tree is not backed by stylo
+        None
+    }
+
+    fn grid_parent(&self, _node_id: NodeId) -> Option<NodeId> {
+        // No parent links - exercises the heuristic parent search
+        None
+    }
+
+    fn with_grid_style_mut<R>(
+        &mut self,
+        _node_id: NodeId,
+        _f: impl FnOnce(&mut taffy::Style) -> R,
+    ) -> Option<R> {
+        None
+    }
+
+    fn grid_item_intrinsic_size(
+        &self,
+        _item_id: NodeId,
+        _inputs: &taffy::tree::LayoutInput,
+        _masonry_axis: taffy::geometry::AbstractAxis,
+    ) -> Result<taffy::Size<f32>, GridPreprocessingError> {
+        Ok(taffy::Size::ZERO)
+    }
+}
+
 #[cfg(test)]
 mod performance_benchmarks {
     use super::*;
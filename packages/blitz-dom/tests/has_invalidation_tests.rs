@@ -0,0 +1,63 @@
+//! Restyling elements matching `:has()` when their descendants are mutated
+
+use blitz_dom::testing::{append_element, document_with_viewport};
+use blitz_dom::{BaseDocument, QualName, local_name, ns};
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+const CSS: &str = "
+    body { margin: 0 }
+    div { width: 100px; height: 10px }
+    .row:has(.selected) { width: 50px }
+";
+
+fn width(doc: &BaseDocument, node_id: usize) -> f32 {
+    doc.get_node(node_id).unwrap().final_layout.size.width
+}
+
+/// A document with a `.row` holding a cell, which holds another, returning the ids of the row
+/// and the innermost cell
+fn row_document() -> (BaseDocument, usize, usize) {
+    let mut doc = document_with_viewport(Viewport::new(200, 200, 1.0, ColorScheme::Light));
+    doc.add_user_agent_stylesheet(CSS);
+
+    let mut mutr = doc.mutate();
+    let html = append_element(&mut mutr, 0, "html", &[]);
+    let body = append_element(&mut mutr, html, "body", &[]);
+    let row = append_element(&mut mutr, body, "div", &[("class", "row")]);
+    let cell = append_element(&mut mutr, row, "div", &[]);
+    let inner = append_element(&mut mutr, cell, "div", &[]);
+    drop(mutr);
+    doc.resolve();
+
+    (doc, row, inner)
+}
+
+#[test]
+fn changing_attributes_of_descendants_restyles_anchors() {
+    let (mut doc, row, inner) = row_document();
+    assert_eq!(width(&doc, row), 100.0);
+
+    let class = QualName::new(None, ns!(), local_name!("class"));
+    doc.mutate().set_attribute(inner, class.clone(), "selected");
+    doc.resolve();
+    assert_eq!(width(&doc, row), 50.0);
+
+    doc.mutate().set_attribute(inner, class, "");
+    doc.resolve();
+    assert_eq!(width(&doc, row), 100.0);
+}
+
+#[test]
+fn inserting_and_removing_descendants_restyles_anchors() {
+    let (mut doc, row, inner) = row_document();
+
+    let mut mutr = doc.mutate();
+    let selected = append_element(&mut mutr, inner, "div", &[("class", "selected")]);
+    drop(mutr);
+    doc.resolve();
+    assert_eq!(width(&doc, row), 50.0);
+
+    doc.mutate().remove_node(selected);
+    doc.resolve();
+    assert_eq!(width(&doc, row), 100.0);
+}
@@ -0,0 +1,182 @@
+//! Finding every element under a point, as it's painted
+
+use std::sync::Arc;
+
+use blitz_dom::{
+    Attribute, BaseDocument, DocumentConfig, DocumentMutator, LocalName, QualName, QuirksMode,
+    local_name, ns,
+};
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+const CSS: &str = "
+body { margin: 0 }
+div { position: relative; width: 100px; height: 100px; border-radius: 50px }
+p { position: absolute; top: 0; left: 0; width: 20px; height: 20px; margin: 0 }
+span { display: block; width: 100px; height: 20px; pointer-events: none }
+";
+
+/// A document with a round `<div>` holding a `<p>` in its top-left corner, followed by a `<span>`
+/// which can't be hit. Returns the ids of the html, body, div, p and span elements.
+fn document() -> (BaseDocument, [usize; 5]) {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        viewport: Some(Viewport::new(200, 200, 1.0, ColorScheme::Light)),
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+    doc.add_user_agent_stylesheet(CSS);
+
+    let mut mutr = doc.mutate();
+    let name = |local| QualName::new(None, ns!(html), local);
+    let mut element = |local| mutr.create_element(name(local), Vec::new(), QuirksMode::NoQuirks);
+    let html = element(local_name!("html"));
+    let body = element(local_name!("body"));
+    let div = element(local_name!("div"));
+    let p = element(local_name!("p"));
+    let span = element(local_name!("span"));
+    mutr.append_children(div, &[p]);
+    mutr.append_children(body, &[div, span]);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    (doc, [html, body, div, p, span])
+}
+
+fn hit_ids(doc: &BaseDocument, x: f32, y: f32) -> Vec<usize> {
+    doc.hit_test(x, y).iter().map(|hit| hit.node_id).collect()
+}
+
+#[test]
+fn hits_every_element_under_the_point_topmost_first() {
+    let (doc, [html, body, div, p, _span]) = document();
+
+    assert_eq!(hit_ids(&doc, 50.0, 50.0), vec![div, body, html]);
+    let hits = doc.hit_test(50.0, 50.0);
+    assert_eq!((hits[0].x, hits[0].y), (50.0, 50.0));
+
+    // The div's rounded corner doesn't cover the p, which overflows it
+    assert_eq!(hit_ids(&doc, 5.0, 5.0), vec![p, body, html]);
+
+    // The span has `pointer-events: none`
+    assert_eq!(hit_ids(&doc, 50.0, 110.0), vec![body, html]);
+}
+
+/// An empty document with a 200px square viewport and the test styles
+fn empty_document() -> BaseDocument {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        viewport: Some(Viewport::new(200, 200, 1.0, ColorScheme::Light)),
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+    doc.add_user_agent_stylesheet(CSS);
+    doc
+}
+
+/// Append an element with inline `style` to `parent`, returning its id
+fn append(mutr: &mut DocumentMutator, parent: usize, local: LocalName, style: &str) -> usize {
+    let name = QualName::new(None, ns!(html), local);
+    let style = Attribute {
+        name: QualName::new(None, ns!(), local_name!("style")),
+        value: style.to_string(),
+    };
+    let element = mutr.create_element(name, vec![style], QuirksMode::NoQuirks);
+    mutr.append_children(parent, &[element]);
+    element
+}
+
+/// A document whose body holds a single element with `style`, built by `build`. Returns the
+/// ids of the body and the element.
+fn document_with(
+    style: &str,
+    build: impl FnOnce(&mut DocumentMutator, usize),
+) -> (BaseDocument, [usize; 2]) {
+    let mut doc = empty_document();
+    let mut mutr = doc.mutate();
+    let html = append(&mut mutr, 0, local_name!("html"), "");
+    let body = append(&mut mutr, html, local_name!("body"), "");
+    let element = append(&mut mutr, body, local_name!("div"), style);
+    build(&mut mutr, element);
+    drop(mutr);
+    doc.resolve();
+    (doc, [body, element])
+}
+
+/// A document with a 100px square `<div>` clipped by `clip_path`. Returns the ids of the body
+/// and the div.
+fn clipped_document(clip_path: &str) -> (BaseDocument, [usize; 2]) {
+    document_with(&format!("border-radius: 0; clip-path: {clip_path}"), |_, _| {})
+}
+#[test]
+fn clip_paths_limit_what_can_be_hit() {
+    // Each shape, with points inside and outside of it (but inside the div)
+    let cases = [
+        ("inset(10px 20px round 5px)", (50.0, 50.0), (10.0, 50.0)),
+        ("circle(40px at 50% 50%)", (50.0, 50.0), (5.0, 5.0)),
+        ("ellipse(50px 20px)", (90.0, 50.0), (50.0, 90.0)),
+        ("polygon(0 0, 100px 0, 0 100px)", (20.0, 20.0), (80.0, 80.0)),
+        ("path('M 50 0 L 100 50 L 50 100 L 0 50 Z')", (50.0, 50.0), (5.0, 5.0)),
+    ];
+    for (clip_path, inside, outside) in cases {
+        let (doc, [body, div]) = clipped_document(clip_path);
+        let hit = |(x, y)| doc.hit(x, y).map(|hit| hit.node_id);
+        assert_eq!(hit(inside), Some(div), "{clip_path} at {inside:?}");
+        assert_eq!(hit(outside), Some(body), "{clip_path} at {outside:?}");
+    }
+}
+
+#[test]
+fn clip_path_fill_rules_are_honored() {
+    // A pentagram, whose center is inside it with `nonzero` but outside of it with `evenodd`
+    let star = "50px 0, 79px 90px, 2px 35px, 98px 35px, 21px 90px";
+    let (doc, [body, div]) = clipped_document(&format!("polygon({star})"));
+    assert_eq!(doc.hit(50.0, 50.0).map(|hit| hit.node_id), Some(div));
+    let (doc, _) = clipped_document(&format!("polygon(evenodd, {star})"));
+    assert_eq!(doc.hit(50.0, 50.0).map(|hit| hit.node_id), Some(body));
+}
+
+#[test]
+fn transforms_move_what_can_be_hit() {
+    let style = "border-radius: 0; transform: translateX(50px)";
+    let (doc, [body, div]) = document_with(style, |_, _| {});
+    assert_eq!(hit_ids(&doc, 120.0, 50.0), vec![div, body, doc.root_element().id]);
+    assert_eq!(hit_ids(&doc, 20.0, 50.0), vec![body, doc.root_element().id]);
+    // Results are relative to the element's untransformed border box
+    let hits = doc.hit_test(120.0, 50.0);
+    assert_eq!((hits[0].x, hits[0].y), (70.0, 50.0));
+}
+
+#[test]
+fn overflow_clips_descendants() {
+    let inner = std::cell::Cell::new(0);
+    let (doc, [_body, div]) = document_with("border-radius: 0; overflow: hidden", |mutr, div| {
+        let style = "position: absolute; top: 0; left: 0; width: 150px; height: 20px";
+        inner.set(append(mutr, div, local_name!("p"), style));
+    });
+    assert_eq!(hit_ids(&doc, 50.0, 10.0)[..2], [inner.get(), div]);
+    // The overflowing part of the `<p>` is clipped away
+    assert!(!hit_ids(&doc, 120.0, 10.0).contains(&inner.get()));
+}
+
+#[test]
+fn inline_elements_are_hit_where_their_text_is() {
+    let link = std::cell::Cell::new(0);
+    let style = "border-radius: 0; width: 200px; font: 20px sans-serif";
+    let (doc, [body, div]) = document_with(style, |mutr, div| {
+        let text = mutr.create_text_node("Go ");
+        mutr.append_children(div, &[text]);
+        link.set(append(mutr, div, local_name!("a"), ""));
+        let text = mutr.create_text_node("there");
+        mutr.append_children(link.get(), &[text]);
+    });
+    let html = doc.root_element().id;
+
+    // The link's text is after "Go ", on the first line
+    assert_eq!(hit_ids(&doc, 2.0, 10.0), vec![div, body, html]);
+    assert_eq!(hit_ids(&doc, 60.0, 10.0), vec![link.get(), div, body, html]);
+    // Past the end of the text, only the block is hit
+    assert_eq!(hit_ids(&doc, 190.0, 10.0), vec![div, body, html]);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use common::document;
+
+#[test]
+fn callbacks_run_in_order_until_the_deadline() {
+    let mut doc = document();
+    let ran = Rc::new(RefCell::new(Vec::new()));
+    for name in ["first", "second"] {
+        let ran = ran.clone();
+        doc.request_idle_callback(
+            move |_, deadline| {
+                assert!(!deadline.did_timeout());
+                ran.borrow_mut().push(name);
+            },
+            None,
+        );
+    }
+    let cancelled = doc.request_idle_callback(|_, _| panic!("cancelled callback ran"), None);
+    doc.cancel_idle_callback(cancelled);
+
+    // An idle period which has already ended runs nothing
+    assert_eq!(doc.run_idle_callbacks(Instant::now()), 0);
+    assert!(doc.has_idle_callbacks());
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    assert_eq!(doc.run_idle_callbacks(deadline), 2);
+    assert_eq!(*ran.borrow(), ["first", "second"]);
+    assert!(!doc.has_idle_callbacks());
+}
+
+#[test]
+fn callbacks_run_without_time_once_their_timeout_passes() {
+    let mut doc = document();
+    let timed_out = Rc::new(RefCell::new(None));
+
+    let recorded = timed_out.clone();
+    doc.request_idle_callback(
+        move |doc, deadline| {
+            *recorded.borrow_mut() = Some(deadline.did_timeout());
+            // Requested during an idle period, so it waits for the next one
+            doc.request_idle_callback(|_, _| {}, None);
+        },
+        Some(Duration::ZERO),
+    );
+    assert!(doc.next_idle_timeout().is_some());
+
+    assert_eq!(doc.run_idle_callbacks(Instant::now()), 1);
+    assert_eq!(*timed_out.borrow(), Some(true));
+    assert!(doc.has_idle_callbacks());
+    assert_eq!(doc.next_idle_timeout(), None);
+}
@@ -24,6 +24,8 @@ fn create_layout_with_image(width: f32) -> TextLayout {
         text: "Hello World".to_string(),
         layout: buffer,
         inline_boxes: vec![inline_box],
+        text_nodes: Vec::new(),
+        break_spaces: Vec::new(),
         cached_content_widths: None,
         cached_text_hash: None,
     }
@@ -51,6 +53,8 @@ fn create_layout_with_images(widths: Vec<f32>) -> TextLayout {
         text: "Hello World with images".to_string(),
         layout: buffer,
         inline_boxes,
+        text_nodes: Vec::new(),
+        break_spaces: Vec::new(),
         cached_content_widths: None,
         cached_text_hash: None,
     }
@@ -123,6 +127,8 @@ fn test_empty_text_with_images() {
         text: "".to_string(), // Empty text
         layout: buffer,
         inline_boxes: vec![inline_box],
+        text_nodes: Vec::new(),
+        break_spaces: Vec::new(),
         cached_content_widths: None,
         cached_text_hash: None,
     };
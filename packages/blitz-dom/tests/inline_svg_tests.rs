@@ -0,0 +1,70 @@
+//! Keeping `currentColor` in inline SVGs in sync with their elements' colors
+#![cfg(feature = "svg")]
+
+use std::sync::Arc;
+
+use blitz_dom::{Attribute, BaseDocument, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+use blitz_traits::net::DummyNetProvider;
+
+fn attrs(attrs: &[(&str, &str)]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .map(|(name, value)| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(*name)),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+fn svg_source(doc: &BaseDocument, svg: usize) -> String {
+    let tree = doc.get_node(svg).unwrap().element_data().unwrap().svg_data().unwrap();
+    tree.to_string(&Default::default())
+}
+
+#[test]
+fn inline_svgs_repaint_when_their_color_changes() {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+
+    let mut mutr = doc.mutate();
+    let html = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let svg_name = |local: &str| QualName::new(None, ns!(svg), LocalName::from(local));
+    let div = attrs(&[("style", "color: rgb(255, 0, 0)")]);
+    let div = mutr.create_element(html("div"), div, QuirksMode::NoQuirks);
+    let svg = attrs(&[("width", "10"), ("height", "10"), ("viewBox", "0 0 10 10")]);
+    let svg = mutr.create_element(svg_name("svg"), svg, QuirksMode::NoQuirks);
+    let rect = attrs(&[("width", "10"), ("height", "10"), ("fill", "currentColor")]);
+    let rect = mutr.create_element(svg_name("rect"), rect, QuirksMode::NoQuirks);
+    mutr.append_children(svg, &[rect]);
+    mutr.append_children(div, &[svg]);
+    let body = mutr.create_element(html("body"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(body, &[div]);
+    let root = mutr.create_element(html("html"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(root, &[body]);
+    mutr.append_children(0, &[root]);
+    drop(mutr);
+    doc.resolve();
+
+    assert!(svg_source(&doc, svg).contains("#ff0000"));
+    let parsed = |doc: &BaseDocument| {
+        let node = doc.get_node(svg).unwrap();
+        Arc::clone(node.element_data().unwrap().svg_data().unwrap())
+    };
+    let before = parsed(&doc);
+
+    // Nothing was restyled, so the SVG isn't parsed again
+    doc.resolve();
+    assert!(Arc::ptr_eq(&before, &parsed(&doc)));
+
+    let style = QualName::new(None, ns!(), LocalName::from("style"));
+    doc.mutate().set_attribute(div, style, "color: rgb(0, 0, 255)");
+    doc.resolve();
+    assert!(!Arc::ptr_eq(&before, &parsed(&doc)));
+    assert!(svg_source(&doc, svg).contains("#0000ff"));
+
+    // The color is only given to the SVG when it's parsed, not to the document's markup
+    assert!(!doc.get_node(svg).unwrap().outer_html().contains("color="));
+}
@@ -0,0 +1,209 @@
+//! Grid, flexbox, ruby and container query layout fixtures, laid out through stylo →
+//! stylo_taffy → taffy
+//!
+//! Fixtures live in `tests/layout/*.json`. Hand-written ones are marked `"source": "curated"`;
+//! `scripts/import_layout_fixtures.py` can add cases from taffy's gentest fixtures and from WPT
+//! `check-layout` tests, but needs checkouts of those repositories, so the fixtures checked in so
+//! far are all curated ones. Each fixture is a tree of elements with inline styles (plus optional
+//! fixture-wide CSS), and any node can carry expectations:
+//!
+//!  - `x`, `y`, `width`, `height`: the border box relative to the parent, as taffy reports it
+//!  - `offset_x`, `offset_y`: `offsetLeft`/`offsetTop`, as WPT checks them
+//!
+//! Set `BLITZ_LAYOUT_FIXTURE` to a substring of fixture names to only run the matching ones.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use blitz_dom::{Attribute, BaseDocument, DocumentConfig, QualName, QuirksMode, local_name, ns};
+use blitz_traits::shell::{ColorScheme, Viewport};
+use serde_json::Value;
+use style::computed_values::position::T as Position;
+
+/// Mirrors the base stylesheet taffy's gentest fixtures are recorded with. Used for every fixture
+/// except those from WPT, which are laid out with only the UA stylesheet like in a browser.
+const BASE_CSS: &str = "
+html, body { margin: 0; padding: 0; }
+div {
+    box-sizing: border-box;
+    position: relative;
+    border: 0 solid red;
+    margin: 0;
+    padding: 0;
+    display: flex;
+}
+";
+
+/// Chrome reports fractional sizes which taffy rounds to whole pixels
+const TOLERANCE: f32 = 0.5;
+
+const DEFAULT_VIEWPORT: (u32, u32) = (800, 600);
+
+struct Expectation {
+    node_id: usize,
+    path: String,
+    expect: Value,
+}
+
+fn attr(name: &str, value: &str) -> Attribute {
+    Attribute {
+        name: QualName::new(None, ns!(), name.into()),
+        value: value.to_string(),
+    }
+}
+
+/// Create the element for a fixture node and its descendants, recording their expectations
+fn build_node(
+    doc: &mut BaseDocument,
+    node: &Value,
+    path: String,
+    expectations: &mut Vec<Expectation>,
+) -> usize {
+    let tag = node["tag"].as_str().unwrap_or("div");
+    let attrs = ["id", "class", "style"]
+        .into_iter()
+        .filter_map(|name| Some(attr(name, node[name].as_str()?)))
+        .collect();
+
+    let mut mutator = doc.mutate();
+    let name = QualName::new(None, ns!(html), tag.into());
+    let node_id = mutator.create_element(name, attrs, QuirksMode::NoQuirks);
+    drop(mutator);
+
+    if let Some(expect) = node.get("expect") {
+        expectations.push(Expectation {
+            node_id,
+            path: path.clone(),
+            expect: expect.clone(),
+        });
+    }
+
+    let children = node["children"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let child_ids: Vec<usize> = children
+        .iter()
+        .enumerate()
+        .map(|(index, child)| build_node(doc, child, format!("{path}.{index}"), expectations))
+        .collect();
+    doc.mutate().append_children(node_id, &child_ids);
+    node_id
+}
+
+/// `offsetLeft`/`offsetTop`: the distance from the padding box of the nearest positioned
+/// ancestor
+fn offset(doc: &BaseDocument, node_id: usize) -> (f32, f32) {
+    let node = doc.get_node(node_id).unwrap();
+    let (mut x, mut y) = (node.final_layout.location.x, node.final_layout.location.y);
+    let mut parent = node.parent;
+    while let Some(ancestor) = parent.and_then(|id| doc.get_node(id)) {
+        let positioned = ancestor
+            .primary_styles()
+            .is_some_and(|style| style.clone_position() != Position::Static);
+        if positioned || ancestor.parent.is_none() {
+            x -= ancestor.final_layout.border.left;
+            y -= ancestor.final_layout.border.top;
+            break;
+        }
+        x += ancestor.final_layout.location.x;
+        y += ancestor.final_layout.location.y;
+        parent = ancestor.parent;
+    }
+    (x, y)
+}
+
+/// Lay out a fixture, returning a description of each expectation it doesn't meet
+fn run_fixture(fixture: &Value) -> Vec<String> {
+    let (width, height) = match fixture["viewport"].as_array() {
+        Some(size) => (size[0].as_u64().unwrap() as u32, size[1].as_u64().unwrap() as u32),
+        None => DEFAULT_VIEWPORT,
+    };
+    let mut doc = BaseDocument::new(DocumentConfig {
+        viewport: Some(Viewport::new(width, height, 1.0, ColorScheme::Light)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+    if fixture["source"] != "wpt" {
+        doc.add_user_agent_stylesheet(BASE_CSS);
+    }
+    if let Some(css) = fixture["css"].as_str() {
+        doc.add_user_agent_stylesheet(css);
+    }
+
+    let mut expectations = Vec::new();
+    let root = build_node(&mut doc, &fixture["root"], "root".into(), &mut expectations);
+    let mut mutator = doc.mutate();
+    let element = |local| QualName::new(None, ns!(html), local);
+    let html = mutator.create_element(element(local_name!("html")), vec![], QuirksMode::NoQuirks);
+    let body = mutator.create_element(element(local_name!("body")), vec![], QuirksMode::NoQuirks);
+    mutator.append_children(body, &[root]);
+    mutator.append_children(html, &[body]);
+    mutator.append_children(0, &[html]);
+    drop(mutator);
+    doc.resolve();
+
+    let mut failures = Vec::new();
+    for Expectation { node_id, path, expect } in expectations {
+        let layout = doc.get_node(node_id).unwrap().final_layout;
+        let (offset_x, offset_y) = offset(&doc, node_id);
+        let actual = [
+            ("x", layout.location.x),
+            ("y", layout.location.y),
+            ("width", layout.size.width),
+            ("height", layout.size.height),
+            ("offset_x", offset_x),
+            ("offset_y", offset_y),
+        ];
+        for (key, actual) in actual {
+            let Some(expected) = expect[key].as_f64() else {
+                continue;
+            };
+            if (actual - expected as f32).abs() > TOLERANCE {
+                failures.push(format!("{path}: {key} is {actual}, expected {expected}"));
+            }
+        }
+    }
+    failures
+}
+
+#[test]
+fn layout_matches_fixtures() {
+    let filter = std::env::var("BLITZ_LAYOUT_FIXTURE").ok();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/layout");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let (mut passed, mut skipped) = (0, 0);
+    let mut report = String::new();
+    for path in paths {
+        let file = path.file_name().unwrap().to_string_lossy();
+        let json: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|err| panic!("{file} is not valid JSON: {err}"));
+        for fixture in json["fixtures"].as_array().unwrap() {
+            let name = fixture["name"].as_str().unwrap();
+            if filter.as_deref().is_some_and(|filter| !name.contains(filter)) {
+                continue;
+            }
+            // Known failures stay in the corpus, along with the reason they fail
+            if fixture.get("skip").is_some() {
+                skipped += 1;
+                continue;
+            }
+            let failures = run_fixture(fixture);
+            if failures.is_empty() {
+                passed += 1;
+                continue;
+            }
+            let source = fixture["source"].as_str().unwrap_or("unknown");
+            writeln!(report, "{file}: {name} ({source})").unwrap();
+            for failure in failures {
+                writeln!(report, "    {failure}").unwrap();
+            }
+        }
+    }
+
+    println!("{passed} layout fixtures passed, {skipped} skipped");
+    assert!(report.is_empty(), "layout fixtures failed:\n{report}");
+}
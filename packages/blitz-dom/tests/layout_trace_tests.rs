@@ -0,0 +1,50 @@
+//! Recording the layout of a document and replaying it with taffy
+
+#![cfg(feature = "layout-trace")]
+
+mod common;
+
+use blitz_dom::layout::trace::LayoutTrace;
+use blitz_dom::{Attribute, LocalName, QualName, QuirksMode, ns};
+use common::document;
+
+#[test]
+fn replays_the_layout_of_a_document() {
+    let mut doc = document();
+    let mut mutr = doc.mutate();
+    let mut element = |local: &str, style: &str, children: &[usize]| {
+        let name = QualName::new(None, ns!(html), LocalName::from(local));
+        let attrs = vec![Attribute {
+            name: QualName::new(None, ns!(), LocalName::from("style")),
+            value: style.to_string(),
+        }];
+        let id = mutr.create_element(name, attrs, QuirksMode::NoQuirks);
+        mutr.append_children(id, children);
+        id
+    };
+    let fixed = element("div", "width: 50px; height: 20px", &[]);
+    let growing = element("div", "flex: 1; min-height: 10px", &[]);
+    let flex = element("div", "display: flex; padding: 5px", &[fixed, growing]);
+    let narrow = element("div", "height: 30px", &[]);
+    let wide = element("div", "height: 40px", &[]);
+    let grid = element(
+        "div",
+        "display: grid; grid-template-columns: 1fr 2fr; gap: 10px",
+        &[narrow, wide],
+    );
+    let body = element("body", "margin: 8px", &[flex, grid]);
+    let html = element("html", "", &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+
+    doc.start_layout_trace();
+    doc.resolve();
+    let trace = doc.finish_layout_trace().unwrap();
+    assert_eq!(trace.root, Some(html));
+    assert!(trace.final_layout(grid).is_some());
+
+    // The trace replays to the same sizes after a round trip through JSON
+    let trace = LayoutTrace::from_json(&trace.to_json().unwrap()).unwrap();
+    let report = trace.replay();
+    assert!(report.is_match(), "{:?}", report.mismatches);
+}
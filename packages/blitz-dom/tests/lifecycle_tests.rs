@@ -0,0 +1,224 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
+
+use blitz_dom::net::{ImageHandler, Resource};
+use blitz_dom::util::ImageType;
+use blitz_dom::testing::append_element;
+use blitz_dom::{BaseDocument, DocumentConfig, DocumentEvent, DocumentLifecycle, DocumentVisibility};
+use blitz_traits::net::{
+    BoxedHandler, Bytes, NetProvider, Request, RequestPriority, SharedCallback, Url,
+};
+
+/// Holds on to handlers until the document cancels them
+#[derive(Default)]
+struct HoldingProvider {
+    handlers: Mutex<Vec<(usize, BoxedHandler<Resource>)>>,
+    /// The URL, priority and whether it is a preload of each request
+    requests: Mutex<Vec<(Url, RequestPriority, bool)>>,
+    preconnects: Mutex<Vec<Url>>,
+}
+
+impl NetProvider<Resource> for HoldingProvider {
+    fn fetch(&self, doc_id: usize, request: Request, handler: BoxedHandler<Resource>) {
+        let summary = (request.url, request.priority, request.preload);
+        self.requests.lock().unwrap().push(summary);
+        self.handlers.lock().unwrap().push((doc_id, handler));
+    }
+
+    fn preconnect(&self, _doc_id: usize, url: &Url) {
+        self.preconnects.lock().unwrap().push(url.clone());
+    }
+
+    fn cancel(&self, doc_id: usize) {
+        self.handlers.lock().unwrap().retain(|(id, _)| *id != doc_id);
+    }
+}
+
+fn document(provider: Arc<HoldingProvider>) -> BaseDocument {
+    let provider: Arc<dyn NetProvider<Resource>> = provider;
+    BaseDocument::new(DocumentConfig {
+        net_provider: Some(provider),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap()
+}
+
+fn fetch_image(doc: &BaseDocument) {
+    let url = Url::parse("https://example.com/image.png").unwrap();
+    let handler = ImageHandler::new(1, ImageType::Image);
+    doc.net_provider.fetch(doc.id(), Request::get(url), Box::new(handler));
+}
+
+#[test]
+fn lifecycle_advances_and_releases_requests_on_drop() {
+    let provider = Arc::new(HoldingProvider::default());
+    let mut doc = document(provider.clone());
+    assert_eq!(doc.lifecycle(), DocumentLifecycle::Loading);
+
+    let states = Rc::new(RefCell::new(Vec::new()));
+    let states_clone = states.clone();
+    doc.add_lifecycle_listener(move |state| states_clone.borrow_mut().push(state));
+
+    fetch_image(&doc);
+    assert_eq!(doc.pending_requests(), 1);
+
+    // Still loading a sub-resource, so the document can't complete yet
+    doc.set_lifecycle(DocumentLifecycle::Interactive);
+    doc.load_resource(Resource::None);
+    assert_eq!(doc.lifecycle(), DocumentLifecycle::Interactive);
+
+    drop(doc);
+    assert_eq!(
+        *states.borrow(),
+        vec![
+            DocumentLifecycle::Interactive,
+            DocumentLifecycle::Complete,
+            DocumentLifecycle::Unloading,
+        ]
+    );
+    // The in-flight request was cancelled
+    assert!(provider.handlers.lock().unwrap().is_empty());
+}
+
+#[test]
+fn document_completes_once_requests_settle() {
+    let provider = Arc::new(HoldingProvider::default());
+    let mut doc = document(provider.clone());
+
+    fetch_image(&doc);
+    doc.set_lifecycle(DocumentLifecycle::Interactive);
+
+    // Dropping the handler settles the request (as a failed fetch would)
+    provider.handlers.lock().unwrap().clear();
+    assert_eq!(doc.pending_requests(), 0);
+    doc.load_resource(Resource::None);
+    assert_eq!(doc.lifecycle(), DocumentLifecycle::Complete);
+}
+
+#[test]
+fn load_waits_for_images_to_decode() {
+    let provider = Arc::new(HoldingProvider::default());
+    let mut doc = document(provider.clone());
+    fetch_image(&doc);
+    doc.set_lifecycle(DocumentLifecycle::Interactive);
+
+    let mut png = Vec::new();
+    image::RgbaImage::new(1, 1)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+
+    // The callback holds up the decode pool until it's released, as the shell's event loop would
+    // take a while to pass the image on
+    let (delivered, delivery) = mpsc::channel();
+    let (release, released) = mpsc::channel::<()>();
+    let released = Mutex::new(released);
+    let callback: SharedCallback<Resource> = Arc::new(move |_doc_id, result| {
+        delivered.send(result).unwrap();
+        released.lock().unwrap().recv().unwrap();
+    });
+    let (doc_id, handler) = provider.handlers.lock().unwrap().pop().unwrap();
+    handler.bytes(doc_id, Bytes::from(png), callback);
+
+    // The image has been decoded, but not yet passed on
+    assert!(delivery.recv_timeout(Duration::from_secs(10)).unwrap().is_ok());
+    assert_eq!(doc.pending_requests(), 1);
+    doc.load_resource(Resource::None);
+    assert_eq!(doc.lifecycle(), DocumentLifecycle::Interactive);
+
+    release.send(()).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while doc.pending_requests() > 0 {
+        assert!(Instant::now() < deadline, "the decode never settled its request");
+        std::thread::yield_now();
+    }
+    doc.load_resource(Resource::None);
+    assert_eq!(doc.lifecycle(), DocumentLifecycle::Complete);
+}
+
+#[test]
+fn document_events_are_sent_in_order() {
+    let provider = Arc::new(HoldingProvider::default());
+    let mut doc = document(provider.clone());
+    let events = doc.document_events();
+
+    fetch_image(&doc);
+    doc.set_lifecycle(DocumentLifecycle::Interactive);
+    doc.mark_painted();
+    doc.mark_painted();
+    doc.set_visibility(DocumentVisibility::Hidden);
+    provider.handlers.lock().unwrap().clear();
+    doc.load_resource(Resource::None);
+    drop(doc);
+
+    assert_eq!(
+        events.iter().collect::<Vec<_>>(),
+        vec![
+            DocumentEvent::DomContentLoaded,
+            DocumentEvent::FirstPaint,
+            DocumentEvent::VisibilityChange(DocumentVisibility::Hidden),
+            DocumentEvent::Load,
+        ]
+    );
+}
+
+fn add_link(doc: &mut BaseDocument, attrs: &[(&str, &str)]) {
+    append_element(&mut doc.mutate(), 0, "link", attrs);
+}
+
+#[test]
+fn link_hints_preload_and_preconnect() {
+    let provider = Arc::new(HoldingProvider::default());
+    let mut doc = document(provider.clone());
+    let url = |path| Url::parse("https://cdn.example.com/").unwrap().join(path).unwrap();
+
+    add_link(&mut doc, &[("rel", "preconnect"), ("href", "https://cdn.example.com")]);
+    add_link(&mut doc, &[("rel", "preload"), ("as", "font"), ("href", url("a.woff2").as_str())]);
+    // Preloads which don't say what they are for are ignored
+    add_link(&mut doc, &[("rel", "preload"), ("href", url("b.js").as_str())]);
+    add_link(&mut doc, &[("rel", "prefetch"), ("href", url("next.html").as_str())]);
+
+    assert_eq!(*provider.preconnects.lock().unwrap(), vec![url("/")]);
+    assert_eq!(
+        *provider.requests.lock().unwrap(),
+        vec![
+            (url("a.woff2"), RequestPriority::High, true),
+            (url("next.html"), RequestPriority::Low, true),
+        ]
+    );
+    assert_eq!(doc.pending_preloads(), 2);
+    // The load event doesn't wait for prefetches
+    assert_eq!(doc.pending_requests(), 1);
+
+    provider.handlers.lock().unwrap().clear();
+    assert_eq!(doc.pending_preloads(), 0);
+}
+
+#[test]
+fn resources_are_requested_at_their_priorities() {
+    let provider = Arc::new(HoldingProvider::default());
+    let mut doc = document(provider.clone());
+
+    add_link(&mut doc, &[("rel", "stylesheet"), ("href", "https://example.com/a.css")]);
+    append_element(&mut doc.mutate(), 0, "img", &[("src", "https://example.com/a.png")]);
+
+    let priorities: Vec<_> = provider.requests.lock().unwrap().iter().map(|r| r.1).collect();
+    assert_eq!(priorities, [RequestPriority::High, RequestPriority::Normal]);
+}
+
+#[test]
+fn lazy_images_are_fetched_when_idle() {
+    let provider = Arc::new(HoldingProvider::default());
+    let mut doc = document(provider.clone());
+    let src = "https://example.com/lazy.png";
+
+    append_element(&mut doc.mutate(), 0, "img", &[("loading", "lazy"), ("src", src)]);
+    assert!(provider.requests.lock().unwrap().is_empty());
+
+    doc.run_idle_callbacks(Instant::now() + Duration::from_millis(50));
+    let requests = provider.requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].0.as_str(), src);
+    assert_eq!(requests[0].1, RequestPriority::Low);
+}
@@ -0,0 +1,74 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use blitz_dom::{
+    BaseDocument, DocumentConfig, LocalName, MediaEnvironment, QualName, QuirksMode, ns,
+};
+use blitz_traits::shell::{ColorScheme, HoverCapability, Viewport};
+
+#[test]
+fn media_listeners_are_told_about_changes() {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        viewport: Some(Viewport::new(800, 600, 2.0, ColorScheme::Light)),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let changes: Rc<RefCell<Vec<MediaEnvironment>>> = Rc::default();
+    let listener_changes = changes.clone();
+    doc.add_media_listener(move |environment| listener_changes.borrow_mut().push(*environment));
+
+    // Setting the same viewport again doesn't change anything
+    doc.set_viewport(doc.viewport().clone());
+    assert!(changes.borrow().is_empty());
+
+    doc.viewport_mut().color_scheme = ColorScheme::Dark;
+    let mut environment = doc.media_environment();
+    assert_eq!(changes.borrow().as_slice(), [environment]);
+    assert_eq!((environment.width, environment.height), (400.0, 300.0));
+    assert_eq!(environment.color_scheme, ColorScheme::Dark);
+
+    environment.prefers_reduced_motion = true;
+    environment.width = 500.0;
+    doc.set_media_environment(environment);
+    assert_eq!(doc.viewport().window_size, (1000, 600));
+    assert_eq!(changes.borrow().len(), 2);
+}
+
+#[test]
+fn media_rules_match_on_motion_and_hover_preferences() {
+    let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+    doc.add_user_agent_stylesheet(
+        "body { margin: 0 } div { width: 100px; height: 10px }
+         @media (prefers-reduced-motion: reduce) { div { width: 50px } }
+         @media (hover: none) { div { height: 20px } }",
+    );
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let div = mutr.create_element(name("div"), Vec::new(), QuirksMode::NoQuirks);
+    let body = mutr.create_element(name("body"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(body, &[div]);
+    let html = mutr.create_element(name("html"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    let size = |doc: &mut BaseDocument| {
+        doc.resolve();
+        let size = doc.get_node(div).unwrap().final_layout.size;
+        (size.width, size.height)
+    };
+    assert_eq!(size(&mut doc), (100.0, 10.0));
+
+    let mut environment = doc.media_environment();
+    environment.prefers_reduced_motion = true;
+    doc.set_media_environment(environment);
+    assert_eq!(size(&mut doc), (50.0, 10.0));
+
+    environment.hover = HoverCapability::None;
+    doc.set_media_environment(environment);
+    assert_eq!(size(&mut doc), (50.0, 20.0));
+
+    environment.prefers_reduced_motion = false;
+    doc.set_media_environment(environment);
+    assert_eq!(size(&mut doc), (100.0, 20.0));
+}
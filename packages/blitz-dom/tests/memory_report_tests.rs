@@ -0,0 +1,79 @@
+//! Estimating the memory held by a document
+
+mod common;
+
+use blitz_dom::{Attribute, QualName, QuirksMode, local_name, ns};
+use common::document;
+use style::properties::ComputedValues;
+
+#[test]
+fn nodes_and_their_text_are_counted() {
+    let mut doc = document();
+    let before = doc.memory_report();
+    assert_eq!(before.nodes.count, doc.nodes.len());
+
+    let text = "lorem ipsum ".repeat(1000);
+    let mut mutr = doc.mutate();
+    let name = QualName::new(None, ns!(html), local_name!("p"));
+    let paragraph = mutr.create_element(name, Vec::new(), QuirksMode::NoQuirks);
+    let text_node = mutr.create_text_node(&text);
+    mutr.append_children(paragraph, &[text_node]);
+    mutr.append_children(0, &[paragraph]);
+    drop(mutr);
+
+    let after = doc.memory_report();
+    assert_eq!(after.nodes.count, before.nodes.count + 2);
+    assert!(after.nodes.bytes >= before.nodes.bytes + text.len());
+    assert_eq!(after.layout.count, after.nodes.count);
+    assert!(after.total_bytes() >= after.nodes.bytes + after.layout.bytes);
+}
+
+#[test]
+fn styles_count_the_style_structs_they_hold() {
+    let mut doc = document();
+    let mut mutr = doc.mutate();
+    let name = QualName::new(None, ns!(html), local_name!("p"));
+    let paragraph = mutr.create_element(name, Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(0, &[paragraph]);
+    drop(mutr);
+    doc.resolve();
+
+    let styles = doc.memory_report().styles;
+    assert!(styles.count > 0);
+    assert!(styles.bytes > styles.count * size_of::<ComputedValues>());
+}
+
+#[cfg(feature = "svg")]
+#[test]
+fn svgs_count_their_path_data() {
+    let mut doc = document();
+    let before = doc.memory_report().images;
+
+    let path_data: String = (0..1000).map(|x| format!("L{x} {} ", x % 2)).collect();
+    let mut mutr = doc.mutate();
+    let attr = |name: &str, value: &str| Attribute {
+        name: QualName::new(None, ns!(), name.into()),
+        value: value.to_string(),
+    };
+    let svg_attrs = vec![attr("width", "1000"), attr("height", "2")];
+    let svg_name = QualName::new(None, ns!(svg), local_name!("svg"));
+    let svg = mutr.create_element(svg_name, svg_attrs, QuirksMode::NoQuirks);
+    let path_attrs = vec![attr("d", &format!("M0 0 {path_data}")), attr("stroke", "black")];
+    let path_name = QualName::new(None, ns!(svg), local_name!("path"));
+    let path = mutr.create_element(path_name, path_attrs, QuirksMode::NoQuirks);
+    mutr.append_children(svg, &[path]);
+    let body_name = QualName::new(None, ns!(html), local_name!("body"));
+    let body = mutr.create_element(body_name, Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(body, &[svg]);
+    let html_name = QualName::new(None, ns!(html), local_name!("html"));
+    let html = mutr.create_element(html_name, Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    let after = doc.memory_report().images;
+    assert_eq!(after.count, before.count + 1);
+    // Each point of the path is two `f32`s
+    assert!(after.bytes >= before.bytes + 1000 * 8, "{after:?}");
+}
@@ -0,0 +1,225 @@
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use blitz_dom::net::Resource;
+use blitz_dom::{
+    Attribute, BaseDocument, DocumentConfig, DocumentIcon, LocalName, QualName, QuirksMode,
+    local_name, ns,
+};
+use blitz_traits::net::{BoxedHandler, Bytes, NetProvider, Request};
+use blitz_traits::shell::{ColorScheme, ShellProvider, Viewport, WindowIcon};
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+
+/// Records what the document tells the shell to show in the window's chrome
+#[derive(Default)]
+struct RecordingShell {
+    redraws: Mutex<usize>,
+    titles: Mutex<Vec<String>>,
+    icons: Mutex<Vec<Option<WindowIcon>>>,
+    theme_colors: Mutex<Vec<Option<[u8; 4]>>>,
+}
+
+impl ShellProvider for RecordingShell {
+    fn request_redraw(&self) {
+        *self.redraws.lock().unwrap() += 1;
+    }
+    fn set_window_title(&self, title: String) {
+        self.titles.lock().unwrap().push(title);
+    }
+    fn set_window_icon(&self, icon: Option<WindowIcon>) {
+        self.icons.lock().unwrap().push(icon);
+    }
+    fn set_theme_color(&self, color: Option<[u8; 4]>) {
+        self.theme_colors.lock().unwrap().push(color);
+    }
+}
+
+/// Answers every request with `body`, sending the resources it's decoded into to `resources`
+struct ServingProvider {
+    body: Bytes,
+    resources: Mutex<Sender<Resource>>,
+}
+
+impl NetProvider<Resource> for ServingProvider {
+    fn fetch(&self, doc_id: usize, _request: Request, handler: BoxedHandler<Resource>) {
+        let resources = Mutex::new(self.resources.lock().unwrap().clone());
+        let callback = move |_doc_id, result: Result<Resource, Option<String>>| {
+            if let Ok(resource) = result {
+                let _ = resources.lock().unwrap().send(resource);
+            }
+        };
+        handler.bytes(doc_id, self.body.clone(), Arc::new(callback));
+    }
+}
+
+/// `image` encoded as `format`
+fn encode(image: RgbaImage, format: ImageFormat) -> Bytes {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(image).write_to(&mut bytes, format).unwrap();
+    Bytes::from(bytes.into_inner())
+}
+
+fn document(shell: Arc<RecordingShell>, net: Option<Arc<ServingProvider>>) -> BaseDocument {
+    BaseDocument::new(DocumentConfig {
+        viewport: Some(Viewport::new(800, 600, 1.0, ColorScheme::Light)),
+        base_url: Some("https://example.com/".to_string()),
+        shell_provider: Some(shell),
+        net_provider: net.map(|net| net as Arc<dyn NetProvider<Resource>>),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Append an element to the root of `doc`, returning its id
+fn append(doc: &mut BaseDocument, tag: LocalName, attrs: &[(&str, &str)]) -> usize {
+    let attrs = attrs
+        .iter()
+        .map(|(name, value)| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(*name)),
+            value: value.to_string(),
+        })
+        .collect();
+    let mut mutr = doc.mutate();
+    let name = QualName::new(None, ns!(html), tag);
+    let element = mutr.create_element(name, attrs, QuirksMode::NoQuirks);
+    mutr.append_children(0, &[element]);
+    element
+}
+
+#[test]
+fn titles_are_shown_and_cleared() {
+    let shell = Arc::new(RecordingShell::default());
+    let mut doc = document(shell.clone(), None);
+
+    let title = append(&mut doc, local_name!("title"), &[]);
+    let mut mutr = doc.mutate();
+    let text = mutr.create_text_node("\n  A\t page ");
+    mutr.append_children(title, &[text]);
+    drop(mutr);
+    assert_eq!(doc.title(), "A page");
+
+    doc.mutate().set_node_text(text, "Another page");
+    doc.mutate().remove_node(title);
+    assert_eq!(doc.title(), "");
+    assert_eq!(*shell.titles.lock().unwrap(), ["A page", "Another page", ""]);
+}
+
+#[test]
+fn theme_colors_follow_the_color_scheme() {
+    let shell = Arc::new(RecordingShell::default());
+    let mut doc = document(shell.clone(), None);
+
+    let dark = [
+        ("name", "theme-color"),
+        ("media", "(prefers-color-scheme: dark)"),
+        ("content", "#000"),
+    ];
+    append(&mut doc, local_name!("meta"), &dark);
+    let meta = append(
+        &mut doc,
+        local_name!("meta"),
+        &[("name", "theme-color"), ("content", "red")],
+    );
+    assert_eq!(*shell.theme_colors.lock().unwrap(), [Some([255, 0, 0, 255])]);
+
+    // Resizing the window doesn't change which meta tag matches
+    doc.set_viewport(Viewport::new(1000, 700, 2.0, ColorScheme::Light));
+    doc.viewport_mut().window_size = (1200, 800);
+    assert_eq!(shell.theme_colors.lock().unwrap().len(), 1);
+
+    doc.viewport_mut().color_scheme = ColorScheme::Dark;
+    assert_eq!(doc.theme_color().unwrap().to_rgba8().to_u8_array(), [0, 0, 0, 255]);
+    doc.set_viewport(Viewport::new(1000, 700, 2.0, ColorScheme::Light));
+    doc.mutate().remove_node(meta);
+    assert_eq!(
+        *shell.theme_colors.lock().unwrap(),
+        [Some([255, 0, 0, 255]), Some([0, 0, 0, 255]), Some([255, 0, 0, 255]), None]
+    );
+}
+
+#[test]
+fn theme_colors_follow_media_queries() {
+    let shell = Arc::new(RecordingShell::default());
+    let mut doc = document(shell.clone(), None);
+
+    let wide = [
+        ("name", "theme-color"),
+        ("media", "screen and (min-width: 600px)"),
+        ("content", "#00f"),
+    ];
+    append(&mut doc, local_name!("meta"), &wide);
+    append(
+        &mut doc,
+        local_name!("meta"),
+        &[("name", "theme-color"), ("content", "red")],
+    );
+    assert_eq!(doc.theme_color().unwrap().to_rgba8().to_u8_array(), [0, 0, 255, 255]);
+
+    doc.set_viewport(Viewport::new(500, 600, 1.0, ColorScheme::Light));
+    assert_eq!(
+        *shell.theme_colors.lock().unwrap(),
+        [Some([0, 0, 255, 255]), Some([255, 0, 0, 255])]
+    );
+}
+
+#[test]
+fn raster_icons_are_shown() {
+    let png = encode(RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255])), ImageFormat::Png);
+    let ico = encode(RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255])), ImageFormat::Ico);
+    for (href, body, size, rgba) in [
+        ("icon.png", png, (4, 2), [255, 0, 0, 255]),
+        ("favicon.ico", ico, (2, 2), [0, 0, 255, 255]),
+    ] {
+        let (sender, resources) = channel();
+        let net = Arc::new(ServingProvider {
+            body,
+            resources: Mutex::new(sender),
+        });
+        let shell = Arc::new(RecordingShell::default());
+        let mut doc = document(shell.clone(), Some(net));
+
+        append(&mut doc, local_name!("link"), &[("rel", "icon"), ("href", href)]);
+        let resource = resources.recv_timeout(Duration::from_secs(10)).unwrap();
+        doc.load_resource(resource);
+
+        let Some(DocumentIcon::Raster(icon)) = doc.icon() else {
+            panic!("{href} wasn't decoded");
+        };
+        assert_eq!((icon.width, icon.height), size);
+        assert_eq!(icon.rgba[..4], rgba);
+        assert_eq!(shell.icons.lock().unwrap().as_slice(), [Some(icon.clone())]);
+    }
+}
+
+#[cfg(feature = "svg")]
+#[test]
+fn svg_icons_are_kept_for_the_shell_to_rasterize() {
+    let (sender, resources) = channel();
+    let net = Arc::new(ServingProvider {
+        body: Bytes::from_static(
+            br##"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="16">
+                <rect width="32" height="16" fill="#00f"/>
+            </svg>"##,
+        ),
+        resources: Mutex::new(sender),
+    });
+    let shell = Arc::new(RecordingShell::default());
+    let mut doc = document(shell.clone(), Some(net));
+
+    let link = append(&mut doc, local_name!("link"), &[("rel", "icon"), ("href", "icon.svg")]);
+    let resource = resources.recv_timeout(Duration::from_secs(10)).unwrap();
+    let redraws = *shell.redraws.lock().unwrap();
+    doc.load_resource(resource);
+
+    let Some(DocumentIcon::Svg(svg)) = doc.icon() else {
+        panic!("the icon wasn't loaded as an SVG");
+    };
+    assert_eq!((svg.size().width(), svg.size().height()), (32.0, 16.0));
+    assert!(*shell.redraws.lock().unwrap() > redraws);
+    assert!(shell.icons.lock().unwrap().is_empty());
+
+    doc.mutate().remove_node(link);
+    assert!(doc.icon().is_none());
+    assert_eq!(*shell.icons.lock().unwrap(), [None]);
+}
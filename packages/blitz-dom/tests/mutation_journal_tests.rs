@@ -0,0 +1,153 @@
+mod common;
+
+use blitz_dom::{
+    Attribute, BaseDocument, DocumentMutator, LocalName, QualName, QuirksMode, local_name, ns,
+};
+use common::document;
+
+fn children(doc: &BaseDocument, node_id: usize) -> Vec<usize> {
+    doc.get_node(node_id).unwrap().children.clone()
+}
+
+#[test]
+fn undoes_transactions_in_reverse_order() {
+    let mut doc = document();
+    let root = doc.root_node().id;
+    let class = QualName::new(None, ns!(), local_name!("class"));
+
+    let mut mutr = doc.mutate();
+    mutr.enable_journal(8);
+    let quirks_mode = mutr.doc.quirks_mode();
+    let div_name = QualName::new(None, ns!(html), local_name!("div"));
+    let div = mutr.create_element(div_name, vec![], quirks_mode);
+    mutr.append_children(root, &[div]);
+    drop(mutr);
+
+    let mut mutr = doc.mutate();
+    mutr.set_attribute(div, class.clone(), "a");
+    let text = mutr.create_text_node("hello");
+    mutr.append_children(div, &[text]);
+    drop(mutr);
+
+    // Dropped nodes are created again under the same ids
+    let mut mutr = doc.mutate();
+    let removed = mutr.remove_and_drop_node(div).unwrap();
+    assert_eq!(removed.children, vec![text]);
+    assert!(mutr.doc.get_node(text).is_none());
+    assert!(mutr.undo_last_transaction());
+    drop(mutr);
+    assert_eq!(children(&doc, root), vec![div]);
+    assert_eq!(children(&doc, div), vec![text]);
+    assert_eq!(doc.get_node(text).unwrap().parent, Some(div));
+    let element = doc.get_node(div).unwrap().element_data().unwrap();
+    assert_eq!(element.attr(local_name!("class")), Some("a"));
+
+    let mut mutr = doc.mutate();
+    assert!(mutr.undo_last_transaction());
+    drop(mutr);
+    assert!(doc.get_node(text).is_none());
+    let element = doc.get_node(div).unwrap().element_data().unwrap();
+    assert!(element.attrs.iter().all(|attr| attr.name != class));
+
+    let mut mutr = doc.mutate();
+    assert!(mutr.undo_last_transaction());
+    assert!(!mutr.can_undo());
+    assert!(!mutr.undo_last_transaction());
+    drop(mutr);
+    assert!(children(&doc, root).is_empty());
+    assert!(doc.get_node(div).is_none());
+}
+
+fn element(mutr: &mut DocumentMutator, local: LocalName, class: Option<&str>) -> usize {
+    let attrs = class
+        .map(|class| Attribute {
+            name: QualName::new(None, ns!(), local_name!("class")),
+            value: class.to_string(),
+        })
+        .into_iter()
+        .collect();
+    mutr.create_element(QualName::new(None, ns!(html), local), attrs, QuirksMode::NoQuirks)
+}
+
+#[test]
+fn failed_transactions_are_rolled_back() {
+    let mut doc = document();
+    let root = doc.root_node().id;
+    let class = QualName::new(None, ns!(), local_name!("class"));
+
+    let mut mutr = doc.mutate();
+    let div = element(&mut mutr, local_name!("div"), Some("a"));
+    mutr.append_children(root, &[div]);
+
+    let result: Result<(), &str> = mutr.transaction(|tx| {
+        tx.set_attribute(div, class.clone(), "b");
+        let text = tx.create_text_node("hello");
+        tx.append_children(div, &[text]);
+        tx.remove_and_drop_node(div);
+        Err("failed")
+    });
+    assert_eq!(result, Err("failed"));
+    assert!(!mutr.is_journaling());
+    drop(mutr);
+
+    assert_eq!(children(&doc, root), vec![div]);
+    assert!(children(&doc, div).is_empty());
+    let element = doc.get_node(div).unwrap().element_data().unwrap();
+    assert_eq!(element.attr(local_name!("class")), Some("a"));
+}
+
+#[test]
+fn successful_transactions_are_undone_with_the_journal() {
+    let mut doc = document();
+    let root = doc.root_node().id;
+
+    let mut mutr = doc.mutate();
+    mutr.enable_journal(8);
+    let div = mutr
+        .transaction(|tx| {
+            let div = element(tx, local_name!("div"), None);
+            tx.append_children(root, &[div]);
+            Ok::<_, ()>(div)
+        })
+        .unwrap();
+    assert_eq!(children(mutr.doc, root), vec![div]);
+    assert!(mutr.undo_last_transaction());
+    drop(mutr);
+    assert!(children(&doc, root).is_empty());
+}
+
+fn append_items(mutr: &mut DocumentMutator, list: usize, item: usize) -> Result<(), ()> {
+    for _ in 0..5 {
+        let item = mutr.deep_clone_node(item);
+        mutr.append_children(list, &[item]);
+    }
+    Ok(())
+}
+
+#[test]
+fn transactions_restyle_has_anchors_once() {
+    let mut doc = document();
+    doc.add_user_agent_stylesheet(".list:has(> .item) { color: red; }");
+
+    let mut mutr = doc.mutate();
+    let html = element(&mut mutr, local_name!("html"), None);
+    let list = element(&mut mutr, local_name!("div"), Some("list"));
+    let item = element(&mut mutr, local_name!("div"), Some("item"));
+    mutr.append_children(list, &[item]);
+    mutr.append_children(html, &[list]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    let mut mutr = doc.mutate();
+    append_items(&mut mutr, list, item).unwrap();
+    let individually = mutr.restyled_anchors();
+    drop(mutr);
+    doc.resolve();
+
+    let mut mutr = doc.mutate();
+    mutr.transaction(|tx| append_items(tx, list, item)).unwrap();
+    let batched = mutr.restyled_anchors();
+    assert_eq!(individually, 5);
+    assert_eq!(batched, 1);
+}
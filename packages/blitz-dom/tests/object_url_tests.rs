@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use blitz_dom::{BaseDocument, DocumentConfig};
+use blitz_traits::blob::BlobRegistry;
+use blitz_traits::net::DummyNetProvider;
+
+#[test]
+fn object_urls_are_revoked_with_their_document() {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+    let kept = doc.create_object_url(&b"kept"[..], "text/plain");
+    let revoked = doc.create_object_url(&b"revoked"[..], "text/plain");
+
+    let registry = BlobRegistry::global();
+    doc.revoke_object_url(&revoked);
+    assert_eq!(registry.resolve(&revoked), None);
+    assert_eq!(registry.resolve(&kept).unwrap().bytes, &b"kept"[..]);
+
+    drop(doc);
+    assert_eq!(registry.resolve(&kept), None);
+}
@@ -0,0 +1,53 @@
+//! Fetching linked stylesheets and images again, as apps do when their assets hot-reload
+
+use std::sync::{Arc, Mutex};
+
+use blitz_dom::net::Resource;
+use blitz_dom::testing::append_element;
+use blitz_dom::{BaseDocument, DocumentConfig};
+use blitz_traits::net::{BoxedHandler, NetProvider, Request, Url};
+
+/// Records the URLs requested, without answering them
+#[derive(Default)]
+struct RecordingProvider {
+    requests: Mutex<Vec<String>>,
+}
+
+impl NetProvider<Resource> for RecordingProvider {
+    fn fetch(&self, _doc_id: usize, request: Request, _handler: BoxedHandler<Resource>) {
+        self.requests.lock().unwrap().push(request.url.path().to_string());
+    }
+}
+
+/// A document linking to two stylesheets and showing an image, and the URLs it requested
+fn linking_document() -> (BaseDocument, Arc<RecordingProvider>) {
+    let provider = Arc::new(RecordingProvider::default());
+    let mut doc = BaseDocument::new(DocumentConfig {
+        base_url: Some("https://example.com/".to_string()),
+        net_provider: Some(provider.clone()),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+
+    let mut mutr = doc.mutate();
+    for href in ["main.css", "theme.css"] {
+        append_element(&mut mutr, 0, "link", &[("rel", "stylesheet"), ("href", href)]);
+    }
+    append_element(&mut mutr, 0, "img", &[("src", "logo.png")]);
+    drop(mutr);
+    (doc, provider)
+}
+
+#[test]
+fn only_changed_resources_are_fetched_again() {
+    let (mut doc, provider) = linking_document();
+    let requests = || std::mem::take(&mut *provider.requests.lock().unwrap());
+    assert_eq!(requests(), ["/main.css", "/theme.css", "/logo.png"]);
+
+    let changed = |url: &Url| url.path() != "/main.css";
+    doc.mutate().reload_resources(changed);
+    assert_eq!(requests(), ["/theme.css", "/logo.png"]);
+
+    doc.mutate().reload_resources(|_| false);
+    assert!(requests().is_empty());
+}
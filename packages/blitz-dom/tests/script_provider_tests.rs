@@ -0,0 +1,96 @@
+//! Handing `<script>` elements to the script provider as they're inserted into documents
+
+use std::sync::{Arc, Mutex};
+use std::task::Context;
+
+use blitz_dom::{
+    Attribute, BaseDocument, DocumentConfig, DocumentMutator, LocalName, QualName, QuirksMode,
+    local_name, ns,
+};
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::script::{Script, ScriptKind, ScriptProvider, ScriptSource};
+
+/// Records the scripts it's handed
+#[derive(Default)]
+struct RecordingScriptProvider {
+    scripts: Mutex<Vec<Script>>,
+}
+
+impl ScriptProvider<BaseDocument> for RecordingScriptProvider {
+    fn script_discovered(&self, _doc_id: usize, script: Script) {
+        self.scripts.lock().unwrap().push(script);
+    }
+
+    fn poll_tasks(&self, _doc: &mut BaseDocument, _cx: &mut Context<'_>) -> bool {
+        false
+    }
+}
+
+fn script(mutr: &mut DocumentMutator, attrs: &[(&str, &str)], text: &str) -> usize {
+    let attrs = attrs
+        .iter()
+        .map(|&(name, value)| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(name)),
+            value: value.to_string(),
+        })
+        .collect();
+    let name = QualName::new(None, ns!(html), local_name!("script"));
+    let script = mutr.create_element(name, attrs, QuirksMode::NoQuirks);
+    if !text.is_empty() {
+        let text = mutr.create_text_node(text);
+        mutr.append_children(script, &[text]);
+    }
+    script
+}
+
+#[test]
+fn scripts_are_handed_to_the_provider_once_in_order() {
+    let provider = Arc::new(RecordingScriptProvider::default());
+    let mut doc = BaseDocument::new(DocumentConfig {
+        base_url: Some("https://example.com/page.html".to_string()),
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        script_provider: Some(provider.clone()),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+
+    let mut mutr = doc.mutate();
+    let html = mutr.create_element(
+        QualName::new(None, ns!(html), local_name!("html")),
+        Vec::new(),
+        QuirksMode::NoQuirks,
+    );
+    let inline = script(&mut mutr, &[], "run()");
+    let module = script(&mut mutr, &[("type", "module"), ("src", "app.js")], "");
+    let data = script(&mut mutr, &[("type", "application/json")], "{}");
+    // Inline scripts without text aren't ready to run yet
+    let empty = script(&mut mutr, &[("defer", "")], "");
+    mutr.append_children(html, &[inline, module, data, empty]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+
+    let scripts = provider.scripts.lock().unwrap().clone();
+    assert_eq!(scripts.len(), 2);
+    assert_eq!(scripts[0].node_id, inline);
+    assert_eq!(scripts[0].kind, ScriptKind::Classic);
+    assert!(matches!(&scripts[0].source, ScriptSource::Inline(text) if text == "run()"));
+    assert_eq!(scripts[1].node_id, module);
+    assert_eq!(scripts[1].kind, ScriptKind::Module);
+    assert!(matches!(
+        &scripts[1].source,
+        ScriptSource::External(url) if url.as_str() == "https://example.com/app.js"
+    ));
+
+    // Scripts run once they get text, but only once
+    let mut mutr = doc.mutate();
+    let text = mutr.create_text_node("later()");
+    mutr.append_children(empty, &[text]);
+    let text = mutr.create_text_node("again()");
+    mutr.append_children(inline, &[text]);
+    drop(mutr);
+
+    let scripts = provider.scripts.lock().unwrap().clone();
+    assert_eq!(scripts.len(), 3);
+    assert_eq!(scripts[2].node_id, empty);
+    assert!(scripts[2].is_deferred);
+}
@@ -0,0 +1,148 @@
+//! Moving focus between elements by where they're laid out, with a gamepad's D-pad and sticks
+
+use std::sync::Arc;
+
+use blitz_dom::{
+    BaseDocument, DocumentConfig, EventDriver, NoopEventHandler, QualName, QuirksMode,
+    SpatialDirection, local_name, ns,
+};
+use blitz_traits::events::{
+    BlitzGamepadAxisEvent, BlitzGamepadButtonEvent, GamepadAxis, GamepadButton, UiEvent,
+};
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+const GRID_CSS: &str = "
+body { margin: 0 }
+div { display: flex; flex-wrap: wrap; width: 100px }
+button { width: 50px; height: 50px; margin: 0; padding: 0; border: 0 }
+";
+
+/// A document with a 2x2 grid of buttons, styled with `css` too, returning their ids in reading
+/// order
+fn grid(css: &str) -> (BaseDocument, [usize; 4]) {
+    let mut doc = BaseDocument::new(DocumentConfig {
+        viewport: Some(Viewport::new(200, 200, 1.0, ColorScheme::Light)),
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    })
+    .unwrap();
+    doc.add_user_agent_stylesheet(GRID_CSS);
+    doc.add_user_agent_stylesheet(css);
+
+    let mut mutr = doc.mutate();
+    let name = |local| QualName::new(None, ns!(html), local);
+    let mut element = |local| mutr.create_element(name(local), Vec::new(), QuirksMode::NoQuirks);
+    let html = element(local_name!("html"));
+    let body = element(local_name!("body"));
+    let div = element(local_name!("div"));
+    let buttons = [(); 4].map(|_| element(local_name!("button")));
+    mutr.append_children(div, &buttons);
+    mutr.append_children(body, &[div]);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    (doc, buttons)
+}
+
+fn press(doc: &mut BaseDocument, button: GamepadButton) {
+    let event = BlitzGamepadButtonEvent {
+        gamepad_id: 0,
+        button,
+        value: 1.0,
+    };
+    let mut driver = EventDriver::new(doc.mutate(), NoopEventHandler);
+    driver.handle_ui_event(UiEvent::GamepadButtonDown(event));
+}
+
+fn push_stick(doc: &mut BaseDocument, axis: GamepadAxis, value: f32) {
+    let event = BlitzGamepadAxisEvent {
+        gamepad_id: 0,
+        axis,
+        value,
+    };
+    let mut driver = EventDriver::new(doc.mutate(), NoopEventHandler);
+    driver.handle_ui_event(UiEvent::GamepadAxisMove(event));
+}
+
+#[test]
+fn dpad_moves_focus_between_neighbours() {
+    let (mut doc, [top_left, top_right, bottom_left, bottom_right]) = grid("");
+
+    // With nothing focused, the top-left element is
+    assert_eq!(doc.focusable_in_direction(SpatialDirection::Down), Some(top_left));
+    press(&mut doc, GamepadButton::DPadRight);
+    assert_eq!(doc.get_focussed_node_id(), Some(top_left));
+
+    press(&mut doc, GamepadButton::DPadRight);
+    assert_eq!(doc.get_focussed_node_id(), Some(top_right));
+    press(&mut doc, GamepadButton::DPadDown);
+    assert_eq!(doc.get_focussed_node_id(), Some(bottom_right));
+    press(&mut doc, GamepadButton::DPadLeft);
+    assert_eq!(doc.get_focussed_node_id(), Some(bottom_left));
+
+    // There's nothing further left, so focus stays put
+    press(&mut doc, GamepadButton::DPadLeft);
+    assert_eq!(doc.get_focussed_node_id(), Some(bottom_left));
+}
+
+#[test]
+fn stick_navigates_once_per_push() {
+    let (mut doc, [top_left, top_right, bottom_left, _]) = grid("");
+    press(&mut doc, GamepadButton::DPadDown);
+    assert_eq!(doc.get_focussed_node_id(), Some(top_left));
+
+    push_stick(&mut doc, GamepadAxis::LeftStickX, 0.8);
+    push_stick(&mut doc, GamepadAxis::LeftStickX, 1.0);
+    assert_eq!(doc.get_focussed_node_id(), Some(top_right));
+
+    // Releasing the stick and pushing it again navigates again
+    push_stick(&mut doc, GamepadAxis::LeftStickX, 0.0);
+    push_stick(&mut doc, GamepadAxis::LeftStickX, -0.9);
+    assert_eq!(doc.get_focussed_node_id(), Some(top_left));
+    push_stick(&mut doc, GamepadAxis::LeftStickY, 0.9);
+    assert_eq!(doc.get_focussed_node_id(), Some(bottom_left));
+}
+
+#[test]
+fn navigation_follows_where_elements_are_painted() {
+    // The grid is scrolled, and its top-right button is moved left of the top-left one
+    let css = "div { height: 60px; overflow: auto }
+        button:nth-child(2) { transform: translateX(-100px) }";
+    let (mut doc, [top_left, top_right, bottom_left, bottom_right]) = grid(css);
+    let div = doc.get_node(top_left).unwrap().parent.unwrap();
+    doc.scroll_node_by(div, 0.0, -20.0);
+
+    let painted = |node_id| {
+        let rect = doc.painted_border_box(node_id).unwrap();
+        (rect.x0, rect.y0, rect.x1, rect.y1)
+    };
+    assert_eq!(painted(top_right), (-50.0, -20.0, 0.0, 30.0));
+    assert_eq!(painted(bottom_left), (0.0, 30.0, 50.0, 80.0));
+
+    doc.set_focus_to(top_left);
+    assert_eq!(doc.focusable_in_direction(SpatialDirection::Left), Some(top_right));
+    assert_eq!(doc.focusable_in_direction(SpatialDirection::Right), Some(bottom_right));
+}
+
+#[test]
+fn navigation_follows_transformed_ancestors() {
+    let (doc, [top_left, _, _, bottom_right]) = grid("div { transform: translateX(100px) }");
+    let rect = doc.painted_border_box(top_left).unwrap();
+    assert_eq!((rect.x0, rect.y0, rect.x1, rect.y1), (100.0, 0.0, 150.0, 50.0));
+    let rect = doc.painted_border_box(bottom_right).unwrap();
+    assert_eq!((rect.x0, rect.y0, rect.x1, rect.y1), (150.0, 50.0, 200.0, 100.0));
+}
+
+#[test]
+fn navigation_skips_removed_elements() {
+    let (mut doc, [top_left, _, bottom_left, bottom_right]) = grid("");
+    doc.set_focus_to(top_left);
+    doc.mutate().remove_node(bottom_left);
+    doc.resolve();
+
+    // The removed button keeps its last layout, but can't be focused
+    assert_eq!(doc.focusable_in_direction(SpatialDirection::Down), Some(bottom_right));
+}
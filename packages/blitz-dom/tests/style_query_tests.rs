@@ -0,0 +1,64 @@
+mod common;
+
+use blitz_dom::{Attribute, BaseDocument, PseudoClassState, QualName, local_name, ns};
+use common::document;
+
+/// Build `<ul class="menu"><li><button>text</button></li></ul>`, returning the ids of the list,
+/// the button and the text node
+fn menu(doc: &mut BaseDocument) -> (usize, usize, usize) {
+    let root = doc.root_node().id;
+    let element = |local| QualName::new(None, ns!(html), local);
+    let class = Attribute {
+        name: QualName::new(None, ns!(), local_name!("class")),
+        value: "menu".to_string(),
+    };
+
+    let mut mutr = doc.mutate();
+    let quirks_mode = mutr.doc.quirks_mode();
+    let list = mutr.create_element(element(local_name!("ul")), vec![class], quirks_mode);
+    let item = mutr.create_element(element(local_name!("li")), vec![], quirks_mode);
+    let button = mutr.create_element(element(local_name!("button")), vec![], quirks_mode);
+    let text = mutr.create_text_node("text");
+    mutr.append_children(root, &[list]);
+    mutr.append_children(list, &[item]);
+    mutr.append_children(item, &[button]);
+    mutr.append_children(button, &[text]);
+    drop(mutr);
+
+    (list, button, text)
+}
+
+#[test]
+fn matches_and_finds_closest_ancestor() {
+    let mut doc = document();
+    let (list, button, text) = menu(&mut doc);
+
+    assert!(doc.matches(list, "ul.menu").unwrap());
+    assert!(doc.matches(button, ".menu button").unwrap());
+    assert!(!doc.matches(button, "li > .menu").unwrap());
+    // Only elements match selectors
+    assert!(!doc.matches(text, "*").unwrap());
+    assert!(doc.matches(button, "button[").is_err());
+
+    assert_eq!(doc.closest(button, "button").unwrap(), Some(button));
+    assert_eq!(doc.closest(button, ".menu").unwrap(), Some(list));
+    // Text nodes start the search from their parent element
+    assert_eq!(doc.closest(text, "ul").unwrap(), Some(list));
+    assert_eq!(doc.closest(text, "table").unwrap(), None);
+}
+
+#[test]
+fn reports_focus_within_ancestors() {
+    let mut doc = document();
+    let (list, button, text) = menu(&mut doc);
+    assert_eq!(doc.pseudo_class_state(list), PseudoClassState::default());
+
+    doc.set_focus_to(button);
+    let state = doc.pseudo_class_state(button);
+    assert!(state.focus && state.focus_within);
+    assert!(!state.hover && !state.active);
+
+    let state = doc.pseudo_class_state(list);
+    assert!(!state.focus && state.focus_within);
+    assert!(!doc.pseudo_class_state(text).focus_within);
+}
@@ -30,6 +30,7 @@ fn create_test_parent_context() -> ParentGridContext {
             vec!["main-start".to_string()],
             vec!["main-end".to_string()],
         ],
+        parent_grid_areas: vec![],
         parent_has_subgrid_rows: false,
         parent_has_subgrid_columns: false,
         row_track_count: 3,
@@ -45,6 +46,7 @@ fn create_empty_parent_context() -> ParentGridContext {
         parent_column_tracks: vec![],
         parent_row_line_names: vec![],
         parent_column_line_names: vec![],
+        parent_grid_areas: vec![],
         parent_has_subgrid_rows: false,
         parent_has_subgrid_columns: false,
         row_track_count: 0,
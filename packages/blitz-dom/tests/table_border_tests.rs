@@ -0,0 +1,142 @@
+//! Resolving the conflicts between the borders of tables with `border-collapse: collapse`
+
+use blitz_dom::layout::table::CollapsedBorder;
+use blitz_dom::node::SpecialElementData;
+use blitz_dom::{Attribute, BaseDocument, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+use style::values::specified::BorderStyle;
+
+const CSS: &str = "table { border-collapse: collapse } td { padding: 0; height: 10px }";
+
+fn attrs(attrs: &[(&str, &str)]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .map(|(name, value)| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(*name)),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+/// A table with `table_style`, and a row for each list of its cells' attributes. Returns the
+/// document, the table and its cells.
+fn table(table_style: &str, rows: &[&[&[(&str, &str)]]]) -> (BaseDocument, usize, Vec<usize>) {
+    let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+    doc.add_user_agent_stylesheet(CSS);
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let table_attrs = attrs(&[("style", table_style)]);
+    let table = mutr.create_element(name("table"), table_attrs, QuirksMode::NoQuirks);
+    let mut cells = Vec::new();
+    for row_cells in rows {
+        let row = mutr.create_element(name("tr"), Vec::new(), QuirksMode::NoQuirks);
+        for cell_attrs in row_cells.iter() {
+            let cell = mutr.create_element(name("td"), attrs(cell_attrs), QuirksMode::NoQuirks);
+            mutr.append_children(row, &[cell]);
+            cells.push(cell);
+        }
+        mutr.append_children(table, &[row]);
+    }
+    let body = mutr.create_element(name("body"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(body, &[table]);
+    let html = mutr.create_element(name("html"), Vec::new(), QuirksMode::NoQuirks);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+    (doc, table, cells)
+}
+
+/// The collapsed borders of `cell`, in `table`
+fn collapsed(doc: &BaseDocument, table: usize, cell: usize) -> taffy::Rect<CollapsedBorder> {
+    let element = doc.get_node(table).unwrap().element_data().unwrap();
+    let SpecialElementData::TableRoot(context) = &element.special_data else {
+        panic!("The table has no table layout");
+    };
+    let cells = context.collapsed_borders();
+    cells.iter().find(|borders| borders.node_id == cell).unwrap().borders
+}
+
+fn set_style(doc: &mut BaseDocument, node_id: usize, style: &str) {
+    let name = QualName::new(None, ns!(), LocalName::from("style"));
+    doc.mutate().set_attribute(node_id, name, style);
+    doc.resolve();
+}
+
+#[test]
+fn wider_and_more_prominent_borders_win() {
+    let (doc, table, cells) = table(
+        "border: 1px solid",
+        &[&[
+            &[("style", "border-right: 3px dotted")],
+            &[("style", "border-left: 3px double")],
+        ]],
+    );
+    let (first, second) = (collapsed(&doc, table, cells[0]), collapsed(&doc, table, cells[1]));
+
+    // Both cells share the edge between them, where the double border wins the tie in width
+    assert_eq!((first.right.width, first.right.style), (3.0, BorderStyle::Double));
+    assert_eq!(first.right, second.left);
+    // Cells without borders of their own get the table's on the outside
+    assert_eq!((first.left.width, first.left.style), (1.0, BorderStyle::Solid));
+    assert_eq!((second.bottom.width, second.bottom.style), (1.0, BorderStyle::Solid));
+
+    // Cells are laid out with half of each border, and the table with half its outer ones
+    let layout = |node_id: usize| doc.get_node(node_id).unwrap().final_layout.border;
+    assert_eq!(layout(cells[0]).right, 1.5);
+    assert_eq!(layout(cells[0]).left, 0.5);
+    assert_eq!(layout(table).left, 0.5);
+}
+
+#[test]
+fn hidden_borders_suppress_their_edges() {
+    let (doc, table, cells) = table(
+        "border: 2px solid",
+        &[&[
+            &[("style", "border-right: 5px solid")],
+            &[("style", "border-left: hidden")],
+        ]],
+    );
+    let first = collapsed(&doc, table, cells[0]);
+    assert_eq!(first.right.width, 0.0);
+    assert_eq!(doc.get_node(cells[0]).unwrap().final_layout.border.right, 0.0);
+}
+
+#[test]
+fn cells_spanning_rows_get_the_widest_edge_along_them() {
+    let (doc, table, cells) = table(
+        "",
+        &[
+            &[&[("rowspan", "2")], &[("style", "border-left: 4px solid")]],
+            &[&[("style", "border-left: 2px dashed")]],
+        ],
+    );
+    let spanning = collapsed(&doc, table, cells[0]);
+    assert_eq!((spanning.right.width, spanning.right.style), (4.0, BorderStyle::Solid));
+    assert_eq!(collapsed(&doc, table, cells[2]).left.style, BorderStyle::Dashed);
+}
+
+#[test]
+fn collapsed_borders_survive_relayout() {
+    let (mut doc, table, cells) = table(
+        "border: 2px solid",
+        &[&[
+            &[("style", "border-right: 6px solid")],
+            &[("style", "border-left: 1px solid")],
+        ]],
+    );
+    let border = |doc: &BaseDocument| doc.get_node(cells[0]).unwrap().final_layout.border;
+    assert_eq!(border(&doc).right, 3.0);
+
+    // Laying the table out again doesn't use the cell's own borders
+    set_style(&mut doc, table, "border: 2px solid; width: 200px");
+    assert_eq!(border(&doc).right, 3.0);
+    assert_eq!(border(&doc).left, 1.0);
+
+    // The cell follows changes to the borders of its neighbor
+    set_style(&mut doc, cells[1], "border-left: 10px solid");
+    assert_eq!(border(&doc).right, 5.0);
+
+    // Cells keep their own borders once the table stops collapsing them
+    set_style(&mut doc, table, "border: 2px solid; border-collapse: separate");
+    assert_eq!(border(&doc).right, 6.0);
+}
@@ -0,0 +1,48 @@
+//! `letter-spacing` and `word-spacing` set on the inline elements of an inline context
+
+use blitz_dom::{Attribute, BaseDocument, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+
+/// The width of a shrink-to-fit box holding `ab` followed by a `<span>` with `style` holding
+/// `span_text`
+fn width(span_text: &str, style: &str) -> f32 {
+    let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let style_attr = |value: &str| {
+        vec![Attribute {
+            name: QualName::new(None, ns!(), LocalName::from("style")),
+            value: value.to_string(),
+        }]
+    };
+    let html = mutr.create_element(name("html"), Vec::new(), QuirksMode::NoQuirks);
+    let body = mutr.create_element(name("body"), Vec::new(), QuirksMode::NoQuirks);
+    let div = style_attr("position: absolute");
+    let div = mutr.create_element(name("div"), div, QuirksMode::NoQuirks);
+    let span = mutr.create_element(name("span"), style_attr(style), QuirksMode::NoQuirks);
+    let before = mutr.create_text_node("ab");
+    let span_text = mutr.create_text_node(span_text);
+    mutr.append_children(span, &[span_text]);
+    mutr.append_children(div, &[before, span]);
+    mutr.append_children(body, &[div]);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+
+    doc.get_node(div).unwrap().final_layout.size.width
+}
+
+#[test]
+fn letter_spacing_applies_to_the_text_of_inline_elements() {
+    let plain = width("cd", "");
+    let spaced = width("cd", "letter-spacing: 10px");
+    // Both of the span's characters are followed by the spacing, but not those before it
+    assert!((spaced - plain - 20.0).abs() < 1.0, "{plain} → {spaced}");
+}
+
+#[test]
+fn word_spacing_applies_to_the_text_of_inline_elements() {
+    let plain = width("c d", "");
+    let spaced = width("c d", "word-spacing: 10px");
+    assert!((spaced - plain - 10.0).abs() < 1.0, "{plain} → {spaced}");
+}
@@ -0,0 +1,63 @@
+//! Preserved white space laid out with the styles of the element it's in
+
+mod common;
+
+use blitz_dom::{Attribute, BaseDocument, LocalName, QualName, QuirksMode, ns};
+use common::document;
+
+/// A document with a `<div>` with `div_style`, holding a `<span>` with `span_style` holding
+/// `text`, and the id of the `<div>`
+fn layout(div_style: &str, span_style: &str, text: &str) -> (BaseDocument, usize) {
+    let mut doc = document();
+    let mut mutr = doc.mutate();
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+    let style_attr = |value: &str| {
+        vec![Attribute {
+            name: QualName::new(None, ns!(), LocalName::from("style")),
+            value: value.to_string(),
+        }]
+    };
+    let html = mutr.create_element(name("html"), Vec::new(), QuirksMode::NoQuirks);
+    let body = mutr.create_element(name("body"), Vec::new(), QuirksMode::NoQuirks);
+    let div = mutr.create_element(name("div"), style_attr(div_style), QuirksMode::NoQuirks);
+    let span = mutr.create_element(name("span"), style_attr(span_style), QuirksMode::NoQuirks);
+    let text = mutr.create_text_node(text);
+    mutr.append_children(span, &[text]);
+    mutr.append_children(div, &[span]);
+    mutr.append_children(body, &[div]);
+    mutr.append_children(html, &[body]);
+    mutr.append_children(0, &[html]);
+    drop(mutr);
+    doc.resolve();
+    (doc, div)
+}
+
+#[test]
+fn break_spaces_wrap_spaces_which_pre_wrap_hangs() {
+    let text = format!("a{}b", " ".repeat(40));
+    let height = |white_space: &str| {
+        let div_style = "width: 60px; font-size: 10px; line-height: 10px";
+        let (doc, div) = layout(div_style, &format!("white-space: {white_space}"), &text);
+        let node = doc.get_node(div).unwrap();
+        let inline_layout = node.element_data().unwrap().inline_layout_data.as_ref();
+        // The spaces are broken after in the layout, and the text itself is left alone
+        assert_eq!(inline_layout.unwrap().text, text);
+        node.final_layout.size.height
+    };
+
+    // `pre-wrap` spaces hang at the end of the first line, and `b` wraps to the second
+    assert_eq!(height("pre-wrap"), 20.0);
+    assert!(height("break-spaces") > 20.0);
+}
+
+#[test]
+fn tabs_are_expanded_to_the_tab_size_of_their_element() {
+    let width = |tab_size: u32| {
+        let div_style = "position: absolute; white-space: pre; font-size: 10px";
+        let (doc, div) = layout(div_style, &format!("tab-size: {tab_size}"), "\tx");
+        doc.get_node(div).unwrap().final_layout.size.width
+    };
+
+    // Each space is about a quarter of an em wide, so six spaces are at least 10px
+    assert!(width(8) - width(2) >= 10.0, "{} → {}", width(2), width(8));
+}
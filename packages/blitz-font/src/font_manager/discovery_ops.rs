@@ -9,7 +9,7 @@ use std::sync::{
     atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 
-use glyphon::cosmyc_text::{Stretch, Style, Weight};
+use blitz_text::{Stretch, Style, Weight};
 
 use crate::{
     FontError, FontKey, LoadedFont, SystemFont,
@@ -25,7 +25,7 @@ impl DiscoveryOps {
     #[inline(always)]
     pub fn initialize_system_fonts(
         registry_manager: &RegistryManager,
-        font_system: &Arc<Mutex<glyphon::cosmyc_text::FontSystem>>,
+        font_system: &Arc<Mutex<blitz_text::FontSystem>>,
         font_count: &AtomicUsize,
         system_fonts_loaded: &AtomicBool,
     ) -> Result<(), FontError> {
@@ -272,7 +272,7 @@ impl DiscoveryOps {
     #[inline(always)]
     fn convert_system_font_to_loaded_font(
         system_font: &SystemFont,
-        font_system: &mut glyphon::cosmyc_text::FontSystem,
+        font_system: &mut blitz_text::FontSystem,
     ) -> Result<LoadedFont, FontError> {
         use ttf_parser::Face;
 
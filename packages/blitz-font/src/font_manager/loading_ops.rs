@@ -22,11 +22,16 @@ impl FontLoadingOps {
     #[inline(always)]
     pub async fn load_system_font_async(
         registry_manager: &RegistryManager,
-        font_system: Arc<Mutex<glyphon::cosmyc_text::FontSystem>>,
+        font_system: Arc<Mutex<blitz_text::FontSystem>>,
         path: PathBuf,
     ) -> Result<FontKey, FontError> {
-        // Read font file data
-        let data = tokio::fs::read(&path).await.map_err(|e| {
+        // Read font file data (asynchronously, except on wasm32 where there are no threads to
+        // read files on)
+        #[cfg(not(target_arch = "wasm32"))]
+        let data = tokio::fs::read(&path).await;
+        #[cfg(target_arch = "wasm32")]
+        let data = std::fs::read(&path);
+        let data = data.map_err(|e| {
             FontError::IoError(format!("Failed to read font file {:?}: {}", path, e))
         })?;
 
@@ -71,7 +76,7 @@ impl FontLoadingOps {
     #[inline(always)]
     pub async fn load_memory_font_async(
         registry_manager: &RegistryManager,
-        font_system: Arc<Mutex<glyphon::cosmyc_text::FontSystem>>,
+        font_system: Arc<Mutex<blitz_text::FontSystem>>,
         data: Vec<u8>,
         key: FontKey,
     ) -> Result<(), FontError> {
@@ -116,7 +121,7 @@ impl FontLoadingOps {
     #[inline]
     pub async fn load_web_font_async(
         registry_manager: &RegistryManager,
-        font_system: Arc<Mutex<glyphon::cosmyc_text::FontSystem>>,
+        font_system: Arc<Mutex<blitz_text::FontSystem>>,
         url: Url,
     ) -> Result<FontKey, FontError> {
         use std::time::Duration;
@@ -237,22 +242,22 @@ impl FontLoadingOps {
             .ok_or_else(|| FontError::ParseError("No family name found in font".to_string()))?;
 
         // Extract font properties
-        let weight = glyphon::cosmyc_text::Weight(face.weight().to_number());
+        let weight = blitz_text::Weight(face.weight().to_number());
         let style = match face.style() {
-            ttf_parser::Style::Normal => glyphon::cosmyc_text::Style::Normal,
-            ttf_parser::Style::Italic => glyphon::cosmyc_text::Style::Italic,
-            ttf_parser::Style::Oblique => glyphon::cosmyc_text::Style::Oblique,
+            ttf_parser::Style::Normal => blitz_text::Style::Normal,
+            ttf_parser::Style::Italic => blitz_text::Style::Italic,
+            ttf_parser::Style::Oblique => blitz_text::Style::Oblique,
         };
         let stretch = match face.width() {
-            ttf_parser::Width::UltraCondensed => glyphon::cosmyc_text::Stretch::UltraCondensed,
-            ttf_parser::Width::ExtraCondensed => glyphon::cosmyc_text::Stretch::ExtraCondensed,
-            ttf_parser::Width::Condensed => glyphon::cosmyc_text::Stretch::Condensed,
-            ttf_parser::Width::SemiCondensed => glyphon::cosmyc_text::Stretch::SemiCondensed,
-            ttf_parser::Width::Normal => glyphon::cosmyc_text::Stretch::Normal,
-            ttf_parser::Width::SemiExpanded => glyphon::cosmyc_text::Stretch::SemiExpanded,
-            ttf_parser::Width::Expanded => glyphon::cosmyc_text::Stretch::Expanded,
-            ttf_parser::Width::ExtraExpanded => glyphon::cosmyc_text::Stretch::ExtraExpanded,
-            ttf_parser::Width::UltraExpanded => glyphon::cosmyc_text::Stretch::UltraExpanded,
+            ttf_parser::Width::UltraCondensed => blitz_text::Stretch::UltraCondensed,
+            ttf_parser::Width::ExtraCondensed => blitz_text::Stretch::ExtraCondensed,
+            ttf_parser::Width::Condensed => blitz_text::Stretch::Condensed,
+            ttf_parser::Width::SemiCondensed => blitz_text::Stretch::SemiCondensed,
+            ttf_parser::Width::Normal => blitz_text::Stretch::Normal,
+            ttf_parser::Width::SemiExpanded => blitz_text::Stretch::SemiExpanded,
+            ttf_parser::Width::Expanded => blitz_text::Stretch::Expanded,
+            ttf_parser::Width::ExtraExpanded => blitz_text::Stretch::ExtraExpanded,
+            ttf_parser::Width::UltraExpanded => blitz_text::Stretch::UltraExpanded,
         };
 
         Ok(FontKey::new(family, weight, style, stretch))
@@ -269,22 +274,22 @@ impl FontLoadingOps {
             .and_then(|name| name.to_string())
             .unwrap_or_else(|| "Unknown".to_string());
 
-        let parsed_weight = glyphon::cosmyc_text::Weight(face.weight().to_number());
+        let parsed_weight = blitz_text::Weight(face.weight().to_number());
         let parsed_style = match face.style() {
-            ttf_parser::Style::Normal => glyphon::cosmyc_text::Style::Normal,
-            ttf_parser::Style::Italic => glyphon::cosmyc_text::Style::Italic,
-            ttf_parser::Style::Oblique => glyphon::cosmyc_text::Style::Oblique,
+            ttf_parser::Style::Normal => blitz_text::Style::Normal,
+            ttf_parser::Style::Italic => blitz_text::Style::Italic,
+            ttf_parser::Style::Oblique => blitz_text::Style::Oblique,
         };
         let parsed_stretch = match face.width() {
-            ttf_parser::Width::UltraCondensed => glyphon::cosmyc_text::Stretch::UltraCondensed,
-            ttf_parser::Width::ExtraCondensed => glyphon::cosmyc_text::Stretch::ExtraCondensed,
-            ttf_parser::Width::Condensed => glyphon::cosmyc_text::Stretch::Condensed,
-            ttf_parser::Width::SemiCondensed => glyphon::cosmyc_text::Stretch::SemiCondensed,
-            ttf_parser::Width::Normal => glyphon::cosmyc_text::Stretch::Normal,
-            ttf_parser::Width::SemiExpanded => glyphon::cosmyc_text::Stretch::SemiExpanded,
-            ttf_parser::Width::Expanded => glyphon::cosmyc_text::Stretch::Expanded,
-            ttf_parser::Width::ExtraExpanded => glyphon::cosmyc_text::Stretch::ExtraExpanded,
-            ttf_parser::Width::UltraExpanded => glyphon::cosmyc_text::Stretch::UltraExpanded,
+            ttf_parser::Width::UltraCondensed => blitz_text::Stretch::UltraCondensed,
+            ttf_parser::Width::ExtraCondensed => blitz_text::Stretch::ExtraCondensed,
+            ttf_parser::Width::Condensed => blitz_text::Stretch::Condensed,
+            ttf_parser::Width::SemiCondensed => blitz_text::Stretch::SemiCondensed,
+            ttf_parser::Width::Normal => blitz_text::Stretch::Normal,
+            ttf_parser::Width::SemiExpanded => blitz_text::Stretch::SemiExpanded,
+            ttf_parser::Width::Expanded => blitz_text::Stretch::Expanded,
+            ttf_parser::Width::ExtraExpanded => blitz_text::Stretch::ExtraExpanded,
+            ttf_parser::Width::UltraExpanded => blitz_text::Stretch::UltraExpanded,
         };
 
         if key.family != parsed_family
@@ -307,7 +312,7 @@ impl FontLoadingOps {
     #[inline(always)]
     fn register_with_font_system(
         loaded_font: &mut LoadedFont,
-        font_system: &Arc<Mutex<glyphon::cosmyc_text::FontSystem>>,
+        font_system: &Arc<Mutex<blitz_text::FontSystem>>,
     ) -> Result<(), FontError> {
         let mut fs = font_system.lock().map_err(|e| {
             FontError::FontSystemError(format!("Failed to acquire FontSystem lock: {}", e))
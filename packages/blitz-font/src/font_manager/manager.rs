@@ -31,7 +31,7 @@ enum FontLoadRequest {
 
 /// Comprehensive lock-free font management system
 pub struct FontManager {
-    font_system: Arc<Mutex<glyphon::cosmyc_text::FontSystem>>,
+    font_system: Arc<Mutex<blitz_text::FontSystem>>,
     registry_manager: RegistryManager,
     font_count: AtomicUsize,
     system_fonts_loaded: AtomicBool,
@@ -54,7 +54,7 @@ impl FontManager {
 
     /// Create FontManager with custom configuration
     pub async fn with_config(config: crate::FontManagerBuilder) -> Result<Self, FontError> {
-        let font_system = Arc::new(Mutex::new(glyphon::cosmyc_text::FontSystem::new()));
+        let font_system = Arc::new(Mutex::new(blitz_text::FontSystem::new()));
         let registry_manager = RegistryManager::new();
 
         #[cfg(feature = "web-fonts")]
@@ -104,7 +104,7 @@ impl FontManager {
     }
 
     /// Get the underlying FontSystem for cosmyc-text integration
-    pub fn get_font_system(&self) -> Arc<Mutex<glyphon::cosmyc_text::FontSystem>> {
+    pub fn get_font_system(&self) -> Arc<Mutex<blitz_text::FontSystem>> {
         Arc::clone(&self.font_system)
     }
 
@@ -163,65 +163,32 @@ impl FontManager {
 
 impl Default for FontManager {
     fn default() -> Self {
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                // Provide a fallback implementation that cannot fail
-                // Create minimal FontManager without system font discovery to avoid potential failures
-                let font_system = Arc::new(Mutex::new(glyphon::cosmyc_text::FontSystem::new()));
-                let registry_manager = RegistryManager::new();
+        blitz_text::runtime::block_on(async {
+            // Provide a fallback implementation that cannot fail
+            // Create minimal FontManager without system font discovery to avoid potential failures
+            let font_system = Arc::new(Mutex::new(blitz_text::FontSystem::new()));
+            let registry_manager = RegistryManager::new();
 
-                #[cfg(feature = "web-fonts")]
-                let web_font_loader = WebFontLoader::minimal().await;
+            #[cfg(feature = "web-fonts")]
+            let web_font_loader = WebFontLoader::minimal().await;
 
-                let (font_load_tx, font_load_rx) = tokio::sync::mpsc::unbounded_channel();
+            let (font_load_tx, font_load_rx) = tokio::sync::mpsc::unbounded_channel();
 
-                Self {
-                    font_system,
-                    registry_manager,
-                    font_count: AtomicUsize::new(0),
-                    system_fonts_loaded: AtomicBool::new(false),
-                    max_cache_size: crate::constants::MAX_FONT_CACHE_SIZE,
-                    cache_ttl: std::time::Duration::from_secs(crate::constants::DEFAULT_CACHE_TTL_SECONDS),
+            Self {
+                font_system,
+                registry_manager,
+                font_count: AtomicUsize::new(0),
+                system_fonts_loaded: AtomicBool::new(false),
+                max_cache_size: crate::constants::MAX_FONT_CACHE_SIZE,
+                cache_ttl: std::time::Duration::from_secs(crate::constants::DEFAULT_CACHE_TTL_SECONDS),
 
-                    #[cfg(feature = "web-fonts")]
-                    web_font_loader,
+                #[cfg(feature = "web-fonts")]
+                web_font_loader,
 
-                    font_load_tx,
-                    font_load_rx: Arc::new(tokio::sync::Mutex::new(font_load_rx)),
-                }
-            })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    let font_system = Arc::new(Mutex::new(glyphon::cosmyc_text::FontSystem::new()));
-                    let registry_manager = RegistryManager::new();
-
-                    #[cfg(feature = "web-fonts")]
-                    let web_font_loader = WebFontLoader::minimal().await;
-
-                    let (font_load_tx, font_load_rx) = tokio::sync::mpsc::unbounded_channel();
-
-                    Self {
-                        font_system,
-                        registry_manager,
-                        font_count: AtomicUsize::new(0),
-                        system_fonts_loaded: AtomicBool::new(false),
-                        max_cache_size: crate::constants::MAX_FONT_CACHE_SIZE,
-                        cache_ttl: std::time::Duration::from_secs(crate::constants::DEFAULT_CACHE_TTL_SECONDS),
-
-                        #[cfg(feature = "web-fonts")]
-                        web_font_loader,
-
-                        font_load_tx,
-                        font_load_rx: Arc::new(tokio::sync::Mutex::new(font_load_rx)),
-                    }
-                })
-        }
+                font_load_tx,
+                font_load_rx: Arc::new(tokio::sync::Mutex::new(font_load_rx)),
+            }
+        })
     }
 }
 
@@ -10,7 +10,7 @@ pub struct LoadedFont {
     pub data: Arc<[u8]>,
     pub face_index: u32,
     pub metrics: FontMetrics,
-    pub load_time: std::time::Instant,
+    pub load_time: web_time::Instant,
     pub usage_count: Arc<std::sync::atomic::AtomicU64>,
     pub font_id: Option<blitz_text::fontdb::ID>,
 }
@@ -30,7 +30,7 @@ impl LoadedFont {
             data,
             face_index,
             metrics,
-            load_time: std::time::Instant::now(),
+            load_time: web_time::Instant::now(),
             usage_count: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             font_id: None,
         }
@@ -102,18 +102,18 @@ impl LoadedFont {
     }
 
     /// Convert LoadedFont to fontdb::Source for cosmyc-text integration
-    pub fn to_fontdb_source(&self) -> glyphon::cosmyc_text::fontdb::Source {
+    pub fn to_fontdb_source(&self) -> blitz_text::fontdb::Source {
         // Convert Arc<[u8]> to Arc<dyn AsRef<[u8]> + Send + Sync> by creating a Vec wrapper
         let data_vec = self.data.to_vec();
         let data: Arc<dyn AsRef<[u8]> + Send + Sync> = Arc::new(data_vec);
-        glyphon::cosmyc_text::fontdb::Source::Binary(data)
+        blitz_text::fontdb::Source::Binary(data)
     }
 
     /// Register this font with cosmyc-text FontSystem and return assigned font ID
     pub fn register_with_font_system(
         &mut self,
-        font_system: &mut glyphon::cosmyc_text::FontSystem,
-    ) -> Result<glyphon::cosmyc_text::fontdb::ID, FontError> {
+        font_system: &mut blitz_text::FontSystem,
+    ) -> Result<blitz_text::fontdb::ID, FontError> {
         let source = self.to_fontdb_source();
         let font_ids = font_system.db_mut().load_font_source(source);
 
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use glyphon::cosmyc_text::{Stretch, Style, Weight};
+use blitz_text::{Stretch, Style, Weight};
 
 use crate::FontKey;
 
@@ -1,7 +1,7 @@
 use std::ops::RangeInclusive;
 use std::path::Path;
 
-use glyphon::cosmyc_text::{Stretch, Style, Weight};
+use blitz_text::{Stretch, Style, Weight};
 
 use crate::{FontError, SystemFont};
 
@@ -128,7 +128,7 @@ impl FontParser {
 
     /// Parse with timing information for performance monitoring
     pub fn parse_with_timing(path: &Path) -> Result<FontParseResult, FontError> {
-        let start = std::time::Instant::now();
+        let start = web_time::Instant::now();
         let metadata = std::fs::metadata(path)?;
         let file_size_bytes = metadata.len();
 
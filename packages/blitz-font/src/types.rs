@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use glyphon::cosmyc_text::{Stretch, Style, Weight};
+use blitz_text::{Stretch, Style, Weight};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -38,19 +38,19 @@ pub struct WebFontEntry {
     pub url: String,
     pub status: FontLoadStatus,
     pub data: Option<Vec<u8>>,
-    #[serde(skip, default = "std::time::Instant::now")]
-    pub load_start: std::time::Instant,
+    #[serde(skip, default = "web_time::Instant::now")]
+    pub load_start: web_time::Instant,
     pub error: Option<String>,
     pub content_type: Option<String>,
     pub size: Option<u64>,
-    #[serde(skip, default = "std::time::Instant::now")]
-    pub last_accessed: std::time::Instant,
+    #[serde(skip, default = "web_time::Instant::now")]
+    pub last_accessed: web_time::Instant,
     pub access_count: u64,
 }
 
 impl Default for WebFontEntry {
     fn default() -> Self {
-        let now = std::time::Instant::now();
+        let now = web_time::Instant::now();
         Self {
             url: String::new(),
             status: FontLoadStatus::default(),
@@ -68,7 +68,7 @@ impl Default for WebFontEntry {
 impl WebFontEntry {
     /// Create a new web font entry in NotStarted state
     pub fn new(url: &Url) -> Self {
-        let now = std::time::Instant::now();
+        let now = web_time::Instant::now();
         Self {
             url: url.to_string(),
             status: FontLoadStatus::NotStarted,
@@ -85,7 +85,7 @@ impl WebFontEntry {
     /// Mark as loading
     pub fn mark_loading(&mut self) {
         self.status = FontLoadStatus::Loading;
-        self.load_start = std::time::Instant::now();
+        self.load_start = web_time::Instant::now();
         self.error = None;
     }
 
@@ -96,7 +96,7 @@ impl WebFontEntry {
         self.content_type = content_type;
         self.status = FontLoadStatus::Loaded;
         self.access_count += 1;
-        self.last_accessed = std::time::Instant::now();
+        self.last_accessed = web_time::Instant::now();
     }
 
     /// Mark as failed with error
@@ -108,7 +108,7 @@ impl WebFontEntry {
     /// Mark as accessed (update access tracking)
     pub fn mark_accessed(&mut self) {
         self.access_count += 1;
-        self.last_accessed = std::time::Instant::now();
+        self.last_accessed = web_time::Instant::now();
     }
 
     /// Get loading duration
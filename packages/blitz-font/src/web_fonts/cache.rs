@@ -2,13 +2,15 @@ use std::sync::{
     Arc,
     atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering},
 };
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
+use blitz_text::cache::ManagedGoldylox;
 use goldylox::cache::traits::supporting_types::HashAlgorithm;
 use goldylox::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use url::Url;
+use web_time::Instant;
 
 use crate::{FontError, WebFontCacheStats, WebFontEntry};
 
@@ -91,6 +93,9 @@ pub struct WebFontCache {
     generation: u64,
     // Add manual entry counter for accuracy
     entry_count: Arc<AtomicUsize>,
+    /// Keeps the cache registered with the memory budget shared by the Blitz crates, which evicts
+    /// the entries recorded with it
+    managed: Arc<ManagedGoldylox<WebFontCacheKey, WebFontEntry>>,
 }
 
 impl WebFontCache {
@@ -102,12 +107,13 @@ impl WebFontCache {
             .warm_tier_max_memory_bytes(64 * 1024 * 1024) // 64MB
             .cold_tier_max_size_bytes(128 * 1024 * 1024) // 128MB
             .compression_level(8)
-            .background_worker_threads(2)
+            .background_worker_threads(blitz_text::runtime::cache_worker_threads(2))
             .cache_id("web_font_cache")
             .build().await
             .map_err(|e| FontError::CacheError(e.to_string()))?;
 
         Ok(Self {
+            managed: ManagedGoldylox::register("web fonts", cache.clone()),
             cache,
             generation: 0,
             entry_count: Arc::new(AtomicUsize::new(0)),
@@ -123,6 +129,7 @@ impl WebFontCache {
 
         // Update cache
         let mut new_cache = self.clone();
+        new_cache.managed.record(key.clone());
         if let Err(e) = new_cache.cache.put(key, entry).await {
             log::warn!("Failed to insert cache entry: {}", e);
             return self; // Return unchanged on error
@@ -436,24 +443,10 @@ impl Drop for CacheManager {
 
 impl Default for WebFontCache {
     fn default() -> Self {
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| {
-                    panic!("Failed to create WebFontCache")
-                })
+        blitz_text::runtime::block_on(async {
+            Self::new().await.unwrap_or_else(|_| {
+                panic!("Failed to create WebFontCache")
             })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| {
-                        panic!("Failed to create WebFontCache")
-                    })
-                })
-        }
+        })
     }
 }
@@ -3,9 +3,10 @@ use std::sync::{
     Arc,
     atomic::{AtomicBool, AtomicUsize, Ordering},
 };
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use url::Url;
+use web_time::Instant;
 
 use crate::web_fonts::cache::CacheManager;
 use crate::{FontError, FontKey, FontLoadStatus, WebFontCacheStats, WebFontEntry};
@@ -1,7 +1,8 @@
 use std::sync::{Arc, atomic::AtomicBool};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use url::Url;
+use web_time::Instant;
 
 use crate::web_fonts::cache::CacheManager;
 use crate::{FontError, FontKey, FontLoadStatus, WebFontEntry};
@@ -139,22 +140,22 @@ impl WebFontOperations {
             });
 
         // Extract weight, style, stretch from OS/2 table
-        let weight = glyphon::cosmyc_text::Weight(face.weight().to_number());
+        let weight = blitz_text::Weight(face.weight().to_number());
         let style = match face.style() {
-            ttf_parser::Style::Normal => glyphon::cosmyc_text::Style::Normal,
-            ttf_parser::Style::Italic => glyphon::cosmyc_text::Style::Italic,
-            ttf_parser::Style::Oblique => glyphon::cosmyc_text::Style::Oblique,
+            ttf_parser::Style::Normal => blitz_text::Style::Normal,
+            ttf_parser::Style::Italic => blitz_text::Style::Italic,
+            ttf_parser::Style::Oblique => blitz_text::Style::Oblique,
         };
         let stretch = match face.width() {
-            ttf_parser::Width::UltraCondensed => glyphon::cosmyc_text::Stretch::UltraCondensed,
-            ttf_parser::Width::ExtraCondensed => glyphon::cosmyc_text::Stretch::ExtraCondensed,
-            ttf_parser::Width::Condensed => glyphon::cosmyc_text::Stretch::Condensed,
-            ttf_parser::Width::SemiCondensed => glyphon::cosmyc_text::Stretch::SemiCondensed,
-            ttf_parser::Width::Normal => glyphon::cosmyc_text::Stretch::Normal,
-            ttf_parser::Width::SemiExpanded => glyphon::cosmyc_text::Stretch::SemiExpanded,
-            ttf_parser::Width::Expanded => glyphon::cosmyc_text::Stretch::Expanded,
-            ttf_parser::Width::ExtraExpanded => glyphon::cosmyc_text::Stretch::ExtraExpanded,
-            ttf_parser::Width::UltraExpanded => glyphon::cosmyc_text::Stretch::UltraExpanded,
+            ttf_parser::Width::UltraCondensed => blitz_text::Stretch::UltraCondensed,
+            ttf_parser::Width::ExtraCondensed => blitz_text::Stretch::ExtraCondensed,
+            ttf_parser::Width::Condensed => blitz_text::Stretch::Condensed,
+            ttf_parser::Width::SemiCondensed => blitz_text::Stretch::SemiCondensed,
+            ttf_parser::Width::Normal => blitz_text::Stretch::Normal,
+            ttf_parser::Width::SemiExpanded => blitz_text::Stretch::SemiExpanded,
+            ttf_parser::Width::Expanded => blitz_text::Stretch::Expanded,
+            ttf_parser::Width::ExtraExpanded => blitz_text::Stretch::ExtraExpanded,
+            ttf_parser::Width::UltraExpanded => blitz_text::Stretch::UltraExpanded,
         };
 
         Ok(FontKey::new(family, weight, style, stretch))
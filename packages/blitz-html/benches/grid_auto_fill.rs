@@ -0,0 +1,78 @@
+//! Relayout cost of appending a card to an auto-fill grid of intrinsically sized cards
+//!
+//! Each iteration appends a copy of a card to the grid, restyles and relays out the document, and
+//! then removes the card again. Compare the times across grid sizes to see how relayout scales
+//! with the number of items.
+//!
+//! Run with `cargo bench -p blitz-html --bench grid_auto_fill`
+
+use std::sync::Arc;
+
+use blitz_dom::DocumentConfig;
+use blitz_html::HtmlDocument;
+use blitz_traits::net::DummyNetProvider;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+const CARD_COUNTS: [usize; 3] = [100, 200, 400];
+
+fn build_document(cards: usize) -> HtmlDocument {
+    let mut html = String::from(
+        "<style>
+            .grid {
+                display: grid;
+                grid-template-columns: repeat(auto-fill, minmax(180px, 1fr));
+                grid-auto-rows: auto;
+                gap: 8px;
+            }
+            .card { padding: 8px; border: 1px solid gray; }
+        </style>
+        <body><div class=\"grid\" id=\"grid\">",
+    );
+    for i in 0..cards {
+        html.push_str(&format!(
+            "<div class=\"card\"><h3>Card {i}</h3><p>Some text which wraps onto a few lines \
+             depending on how wide the column is.</p></div>"
+        ));
+    }
+    html.push_str("</div></body>");
+
+    let config = DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    };
+    let mut doc = HtmlDocument::from_html(&html, config);
+    doc.resolve();
+    doc
+}
+
+fn bench_append(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grid_auto_fill_append");
+
+    for cards in CARD_COUNTS {
+        let mut doc = build_document(cards);
+        let grid = doc
+            .query_selector("#grid")
+            .ok()
+            .flatten()
+            .expect("grid exists");
+        let card = doc.nodes[grid].children[0];
+
+        group.bench_function(BenchmarkId::from_parameter(cards), |b| {
+            b.iter(|| {
+                let mut mutator = doc.mutate();
+                let copy = mutator.deep_clone_node(card);
+                mutator.append_children(grid, &[copy]);
+                drop(mutator);
+                doc.resolve();
+
+                doc.mutate().remove_and_drop_node(copy);
+                doc.resolve();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_append);
+criterion_main!(benches);
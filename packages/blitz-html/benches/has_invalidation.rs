@@ -0,0 +1,81 @@
+//! Restyle cost of a single mutation in a large document which uses `:has()`
+//!
+//! Compares the targeted `:has()` invalidation done by `DocumentMutator` against restyling the
+//! whole document, which is what a mutation would need without relational invalidation sets.
+//!
+//! Run with `cargo bench -p blitz-html --bench has_invalidation`
+
+use std::sync::Arc;
+
+use blitz_dom::{BaseDocument, DocumentConfig, QualName, RestyleHint, local_name, ns};
+use blitz_html::HtmlDocument;
+use blitz_traits::net::DummyNetProvider;
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const ROWS: usize = 5_000;
+
+fn build_document() -> HtmlDocument {
+    let mut html = String::from(
+        "<style>
+            .row:has(.selected) { background: yellow; }
+            .row:has(> .cell:hover) .cell { color: red; }
+            .cell { padding: 2px; }
+        </style>
+        <body>",
+    );
+    for i in 0..ROWS {
+        html.push_str(&format!(
+            "<div class=\"row\"><span class=\"cell\" id=\"cell-{i}\">Row {i}</span><span class=\"cell\">Value</span></div>"
+        ));
+    }
+    html.push_str("</body>");
+
+    let config = DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    };
+    let mut doc = HtmlDocument::from_html(&html, config);
+    doc.resolve_stylist();
+    doc
+}
+
+fn toggle_selected(doc: &mut BaseDocument, node_id: usize, selected: bool) {
+    let class = QualName::new(None, ns!(), local_name!("class"));
+    let value = if selected { "cell selected" } else { "cell" };
+    doc.mutate().set_attribute(node_id, class, value);
+}
+
+fn bench_has_invalidation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("has_invalidation");
+
+    let mut doc = build_document();
+    let target = doc
+        .query_selector(&format!("#cell-{}", ROWS / 2))
+        .ok()
+        .flatten()
+        .expect("target cell exists");
+
+    let mut selected = false;
+    group.bench_function("targeted", |b| {
+        b.iter(|| {
+            selected = !selected;
+            toggle_selected(&mut doc, target, selected);
+            doc.resolve_stylist();
+        })
+    });
+
+    group.bench_function("full_restyle", |b| {
+        b.iter(|| {
+            selected = !selected;
+            toggle_selected(&mut doc, target, selected);
+            let root_id = doc.root_element().id;
+            doc.nodes[root_id].set_restyle_hint(RestyleHint::restyle_subtree());
+            doc.resolve_stylist();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_has_invalidation);
+criterion_main!(benches);
@@ -1,6 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
-use blitz_dom::{BaseDocument, DEFAULT_CSS, Document, DocumentConfig};
+use blitz_dom::{BaseDocument, DEFAULT_CSS, Document, DocumentConfig, DocumentLifecycle};
 
 use crate::DocumentHtmlParser;
 
@@ -32,19 +32,27 @@ impl Document for HtmlDocument {
 }
 
 impl HtmlDocument {
-    /// Parse HTML (or XHTML) into an [`HtmlDocument`]
-    pub fn from_html(html: &str, mut config: DocumentConfig) -> Self {
+    /// Create an empty [`HtmlDocument`], to be filled in by an [`HtmlParser`](crate::HtmlParser)
+    /// as its source arrives
+    pub fn new(mut config: DocumentConfig) -> Self {
         if let Some(ss) = &mut config.ua_stylesheets {
             if !ss.iter().any(|s| s == DEFAULT_CSS) {
                 ss.push(String::from(DEFAULT_CSS));
             }
         }
-        let mut doc = BaseDocument::new(config)
+        let doc = BaseDocument::new(config)
             .expect("Failed to create BaseDocument - invalid configuration");
-        DocumentHtmlParser::parse_into_doc(&mut doc, html);
         HtmlDocument { inner: doc }
     }
 
+    /// Parse HTML (or XHTML) into an [`HtmlDocument`]
+    pub fn from_html(html: &str, config: DocumentConfig) -> Self {
+        let mut doc = Self::new(config);
+        DocumentHtmlParser::parse_into_doc(&mut doc, html);
+        doc.set_lifecycle(DocumentLifecycle::Interactive);
+        doc
+    }
+
     /// Convert the [`HtmlDocument`] into it's inner [`BaseDocument`]
     pub fn into_inner(self) -> BaseDocument {
         self.into()
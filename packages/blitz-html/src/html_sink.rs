@@ -2,6 +2,7 @@
 
 use std::borrow::Cow;
 use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::collections::HashSet;
 use std::rc::Rc;
 
 use blitz_dom::node::Attribute;
@@ -11,9 +12,9 @@ use html5ever::ParseOpts;
 use html5ever::tokenizer::TokenizerOpts;
 use html5ever::tree_builder::TreeBuilderOpts;
 use html5ever::{
-    QualName,
+    QualName, local_name,
     tendril::{StrTendril, TendrilSink},
-    tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink},
+    tree_builder::{ElementFlags, NextParserState, NodeOrText, QuirksMode, TreeSink},
 };
 
 /// Convert html5ever QualName to blitz-dom QualName
@@ -36,6 +37,15 @@ fn convert_qualname_back(blitz_name: &BlitzQualName) -> html5ever::QualName {
     }
 }
 
+/// Convert html5ever's QuirksMode to blitz_dom's QuirksMode (from selectors)
+pub(crate) fn convert_quirks_mode(quirks_mode: QuirksMode) -> blitz_dom::QuirksMode {
+    match quirks_mode {
+        QuirksMode::NoQuirks => blitz_dom::QuirksMode::NoQuirks,
+        QuirksMode::LimitedQuirks => blitz_dom::QuirksMode::LimitedQuirks,
+        QuirksMode::Quirks => blitz_dom::QuirksMode::Quirks,
+    }
+}
+
 /// Convert an html5ever Attribute which uses tendril for its value to a blitz Attribute
 /// which uses String.
 fn html5ever_to_blitz_attr(attr: html5ever::Attribute) -> Attribute {
@@ -45,6 +55,48 @@ fn html5ever_to_blitz_attr(attr: html5ever::Attribute) -> Attribute {
     }
 }
 
+/// The options HTML (as opposed to XHTML) documents are parsed with
+pub(crate) fn html_parse_opts() -> ParseOpts {
+    ParseOpts {
+        tokenizer: TokenizerOpts::default(),
+        tree_builder: TreeBuilderOpts {
+            exact_errors: false,
+            scripting_enabled: false, // Enables parsing of <noscript> tags
+            iframe_srcdoc: false,
+            drop_doctype: true,
+            quirks_mode: QuirksMode::NoQuirks,
+        },
+    }
+}
+
+/// Why an HTML fragment couldn't be parsed into an element, see
+/// [`DocumentHtmlParser::parse_inner_html`]
+#[derive(Debug)]
+pub enum InnerHtmlError {
+    /// The node the fragment was to be parsed into doesn't exist or isn't an element
+    NotAnElement(usize),
+    /// The fragment couldn't be read
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for InnerHtmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAnElement(node_id) => write!(f, "node {node_id} is not an element"),
+            Self::Io(err) => write!(f, "failed to read the HTML fragment: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for InnerHtmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotAnElement(_) => None,
+            Self::Io(err) => Some(err),
+        }
+    }
+}
+
 pub struct DocumentHtmlParser<'doc> {
     document_mutator: RefCell<DocumentMutator<'doc>>,
 
@@ -57,6 +109,9 @@ pub struct DocumentHtmlParser<'doc> {
 
     /// Cache for converted QualNames in elem_name calls
     elem_name_cache: RefCell<Option<html5ever::QualName>>,
+
+    /// MathML `<annotation-xml>` elements whose contents are parsed as HTML rather than MathML
+    integration_points: RefCell<HashSet<usize>>,
 }
 
 impl<'doc> DocumentHtmlParser<'doc> {
@@ -75,6 +130,7 @@ impl DocumentHtmlParser<'_> {
             quirks_mode: Rc::new(Cell::new(QuirksMode::NoQuirks)),
             is_xml: false,
             elem_name_cache: RefCell::new(None),
+            integration_points: RefCell::new(HashSet::new()),
         }
     }
 
@@ -94,7 +150,7 @@ impl DocumentHtmlParser<'_> {
             // Parse as XHTML
             let mut xhtml_sink = sink;
             xhtml_sink.is_xml = true;
-            html5ever::parse_document(xhtml_sink, Default::default())
+            xml5ever::driver::parse_document(xhtml_sink, Default::default())
                 .from_utf8()
                 .read_from(&mut html.as_bytes())
                 .unwrap();
@@ -102,17 +158,7 @@ impl DocumentHtmlParser<'_> {
             // Parse as HTML
             let mut html_sink = sink;
             html_sink.is_xml = false;
-            let opts = ParseOpts {
-                tokenizer: TokenizerOpts::default(),
-                tree_builder: TreeBuilderOpts {
-                    exact_errors: false,
-                    scripting_enabled: false, // Enables parsing of <noscript> tags
-                    iframe_srcdoc: false,
-                    drop_doctype: true,
-                    quirks_mode: QuirksMode::NoQuirks,
-                },
-            };
-            html5ever::parse_document(html_sink, opts)
+            html5ever::parse_document(html_sink, html_parse_opts())
                 .from_utf8()
                 .read_from(&mut html.as_bytes())
                 .unwrap();
@@ -121,16 +167,44 @@ impl DocumentHtmlParser<'_> {
         let detected_quirks_mode = quirks_mode_cell.get();
 
         // Transfer detected quirks mode from HTML parser to document
-        // Convert html5ever's QuirksMode to blitz_dom's QuirksMode (from selectors)
-        let blitz_quirks_mode = match detected_quirks_mode {
-            QuirksMode::NoQuirks => blitz_dom::QuirksMode::NoQuirks,
-            QuirksMode::LimitedQuirks => blitz_dom::QuirksMode::LimitedQuirks,
-            QuirksMode::Quirks => blitz_dom::QuirksMode::Quirks,
-        };
-        doc.set_quirks_mode(blitz_quirks_mode);
+        doc.set_quirks_mode(convert_quirks_mode(detected_quirks_mode));
 
         doc
     }
+
+    /// Replace the children of the element `parent_id` with the HTML fragment `html`, as setting
+    /// `innerHTML` would
+    pub fn parse_inner_html(
+        doc: &mut BaseDocument,
+        parent_id: usize,
+        html: &str,
+    ) -> Result<(), InnerHtmlError> {
+        let (context_name, old_children) = doc
+            .get_node(parent_id)
+            .and_then(|node| {
+                let element = node.element_data()?;
+                Some((convert_qualname_back(&element.name), node.children.clone()))
+            })
+            .ok_or(InnerHtmlError::NotAnElement(parent_id))?;
+
+        // html5ever parses fragments into an `<html>` element it appends to the document
+        let sink = Self::new(doc);
+        html5ever::parse_fragment(sink, html_parse_opts(), context_name, Vec::new(), false)
+            .from_utf8()
+            .read_from(&mut html.as_bytes())
+            .map_err(InnerHtmlError::Io)?;
+        let Some(&fragment_root_id) = doc.root_node().children.last() else {
+            return Ok(());
+        };
+
+        let mut mutr = doc.mutate();
+        for child_id in old_children {
+            mutr.remove_and_drop_node(child_id);
+        }
+        mutr.reparent_children(fragment_root_id, parent_id);
+        mutr.remove_and_drop_node(fragment_root_id);
+        Ok(())
+    }
 }
 
 impl<'b> TreeSink for DocumentHtmlParser<'b> {
@@ -180,21 +254,28 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
         &self,
         name: QualName,
         attrs: Vec<html5ever::Attribute>,
-        _flags: ElementFlags,
+        flags: ElementFlags,
     ) -> Self::Handle {
         let attrs = attrs.into_iter().map(html5ever_to_blitz_attr).collect();
         
         // Get current quirks mode from parser sink (updated by html5ever during parsing)
-        let quirks_mode = self.quirks_mode.get();
-        
-        // Convert html5ever's QuirksMode to blitz_dom's QuirksMode (from selectors)
-        let blitz_quirks_mode = match quirks_mode {
-            QuirksMode::NoQuirks => blitz_dom::QuirksMode::NoQuirks,
-            QuirksMode::LimitedQuirks => blitz_dom::QuirksMode::LimitedQuirks,
-            QuirksMode::Quirks => blitz_dom::QuirksMode::Quirks,
-        };
-        
-        self.mutr().create_element(convert_qualname(name), attrs, blitz_quirks_mode)
+        let quirks_mode = convert_quirks_mode(self.quirks_mode.get());
+        let is_script = name.local == local_name!("script");
+        let node_id = self.mutr().create_element(convert_qualname(name), attrs, quirks_mode);
+        if flags.mathml_annotation_xml_integration_point {
+            self.integration_points.borrow_mut().insert(node_id);
+        }
+        // Scripts run once their end tag is parsed (see `complete_script`), which html5ever
+        // reports but xml5ever doesn't
+        if is_script && !self.is_xml {
+            self.mutr().set_parser_inserted(node_id);
+        }
+        node_id
+    }
+
+    fn complete_script(&self, node: &Self::Handle) -> NextParserState {
+        self.mutr().complete_script(*node);
+        NextParserState::Continue
     }
 
     fn create_comment(&self, _text: StrTendril) -> Self::Handle {
@@ -272,6 +353,10 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
         // After parsing completes, parse_into_doc() transfers the quirks mode to BaseDocument.
     }
 
+    fn is_mathml_annotation_xml_integration_point(&self, handle: &Self::Handle) -> bool {
+        self.integration_points.borrow().contains(handle)
+    }
+
     fn get_template_contents(&self, target: &Self::Handle) -> Self::Handle {
         // TODO: implement templates properly. This should allow to function like regular elements.
         *target
@@ -318,3 +403,48 @@ fn parses_some_html() {
 
     // Now our tree should have some nodes in it
 }
+
+#[test]
+fn parses_foreign_content_into_namespaces() {
+    use blitz_dom::DocumentConfig;
+    use html5ever::ns;
+
+    let html = "<!DOCTYPE html><body><svg viewBox=\"0 0 1 1\"><use xlink:href=\"#a\"/></svg>\
+        <math><annotation-xml encoding=\"text/html\"><p>x</p></annotation-xml></math></body>";
+    let mut doc = BaseDocument::new(DocumentConfig::default())
+        .expect("Failed to create test document");
+    DocumentHtmlParser::parse_into_doc(&mut doc, html);
+
+    let element = |selector: &str| {
+        let node_id = doc.query_selector(selector).unwrap().unwrap();
+        doc.get_node(node_id).unwrap().element_data().unwrap()
+    };
+    assert_eq!(element("svg").name.ns, ns!(svg));
+    assert_eq!(element("use").name.ns, ns!(svg));
+    assert_eq!(element("use").attrs()[0].name.ns, ns!(xlink));
+    assert_eq!(element("math").name.ns, ns!(mathml));
+    // The contents of an HTML integration point are HTML again
+    assert_eq!(element("p").name.ns, ns!(html));
+}
+
+#[test]
+fn parses_inline_svgs_in_xhtml() {
+    use blitz_dom::DocumentConfig;
+    use html5ever::ns;
+
+    let xhtml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <html xmlns=\"http://www.w3.org/1999/xhtml\"><body style=\"color: blue\">\
+        <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"10\">\
+        <rect width=\"20\" height=\"10\" fill=\"currentColor\"/></svg></body></html>";
+    let mut doc = BaseDocument::new(DocumentConfig::for_testing())
+        .expect("Failed to create test document");
+    DocumentHtmlParser::parse_into_doc(&mut doc, xhtml);
+    doc.resolve();
+
+    let svg = doc.query_selector("svg").unwrap().unwrap();
+    let element = doc.get_node(svg).unwrap().element_data().unwrap();
+    assert_eq!(element.name.ns, ns!(svg));
+    let tree = element.svg_data().expect("the inline SVG wasn't parsed");
+    assert_eq!((tree.size().width(), tree.size().height()), (20.0, 10.0));
+    assert!(tree.to_string(&Default::default()).contains("#0000ff"));
+}
@@ -1,5 +1,13 @@
 mod html_document;
 mod html_sink;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod streaming;
 
 pub use html_document::HtmlDocument;
-pub use html_sink::DocumentHtmlParser;
+pub use html_sink::{DocumentHtmlParser, InnerHtmlError};
+#[cfg(feature = "markdown")]
+pub use markdown::{
+    MarkdownDocument, MarkdownOptions, markdown_to_html, markdown_to_html_with_options,
+};
+pub use streaming::{HtmlParser, Preload, PreloadKind};
@@ -0,0 +1,190 @@
+//! Rendering Markdown documents
+//!
+//! Markdown is converted to HTML with [comrak](https://docs.rs/comrak), with the GitHub Flavored
+//! Markdown extensions (tables, task lists, strikethrough, autolinks, footnotes and alerts)
+//! enabled. With the `syntax-highlighting` feature, code fences are highlighted by syntect, which
+//! wraps each token in a `<span>` styled by its `style` attribute.
+//!
+//! HTML written in the Markdown is omitted (and links to `javascript:` and other dangerous URLs
+//! dropped) unless [`MarkdownOptions::allow_html`] is set, so untrusted Markdown can be rendered.
+
+use std::ops::{Deref, DerefMut};
+
+use blitz_dom::{BaseDocument, Document, DocumentConfig};
+use comrak::{Options, Plugins, markdown_to_html_with_plugins};
+
+use crate::{DocumentHtmlParser, HtmlDocument, InnerHtmlError};
+
+/// The document the rendered Markdown is placed in
+const MARKDOWN_SHELL: &str =
+    "<!DOCTYPE html><html><body><div class=\"markdown-body\"></div></body></html>";
+
+/// How Markdown is rendered
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownOptions {
+    /// Render the HTML written in the Markdown, and links to any URL. Only for trusted Markdown,
+    /// as its HTML can contain scripts.
+    pub allow_html: bool,
+}
+
+/// Convert Markdown to an HTML fragment, omitting any HTML written in it
+pub fn markdown_to_html(markdown: &str) -> String {
+    markdown_to_html_with_options(markdown, MarkdownOptions::default())
+}
+
+/// Convert Markdown to an HTML fragment
+pub fn markdown_to_html_with_options(markdown: &str, markdown_options: MarkdownOptions) -> String {
+    let mut options = Options::default();
+    options.extension.strikethrough = true;
+    options.extension.table = true;
+    options.extension.autolink = true;
+    options.extension.tasklist = true;
+    options.extension.footnotes = true;
+    options.extension.alerts = true;
+    options.render.unsafe_ = markdown_options.allow_html;
+    options.render.tasklist_classes = true;
+
+    #[allow(unused_mut)]
+    let mut plugins = Plugins::default();
+    #[cfg(feature = "syntax-highlighting")]
+    {
+        plugins.render.codefence_syntax_highlighter = Some(syntax_highlighter());
+    }
+
+    markdown_to_html_with_plugins(markdown, &options, &plugins)
+}
+
+#[cfg(feature = "syntax-highlighting")]
+fn syntax_highlighter() -> &'static comrak::plugins::syntect::SyntectAdapter {
+    use comrak::plugins::syntect::SyntectAdapter;
+    use std::sync::OnceLock;
+
+    // Loading syntect's syntaxes and themes is slow, so only do it once
+    static HIGHLIGHTER: OnceLock<SyntectAdapter> = OnceLock::new();
+    HIGHLIGHTER.get_or_init(|| SyntectAdapter::new(Some("InspiredGitHub")))
+}
+
+/// A document rendered from Markdown
+///
+/// The rendered Markdown is the contents of a `<div class="markdown-body">`, so stylesheets
+/// written for GitHub's rendering of Markdown (such as `github-markdown-css`) can be passed in
+/// [`DocumentConfig::ua_stylesheets`].
+pub struct MarkdownDocument {
+    inner: HtmlDocument,
+    /// The `<div class="markdown-body">` element
+    body_id: usize,
+    options: MarkdownOptions,
+}
+
+impl Deref for MarkdownDocument {
+    type Target = BaseDocument;
+    fn deref(&self) -> &BaseDocument {
+        &self.inner
+    }
+}
+impl DerefMut for MarkdownDocument {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+impl From<MarkdownDocument> for BaseDocument {
+    fn from(doc: MarkdownDocument) -> BaseDocument {
+        doc.inner.into()
+    }
+}
+impl Document for MarkdownDocument {
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl MarkdownDocument {
+    /// Render Markdown into a [`MarkdownDocument`], omitting any HTML written in it
+    pub fn from_markdown(markdown: &str, config: DocumentConfig) -> Self {
+        Self::from_markdown_with_options(markdown, MarkdownOptions::default(), config)
+    }
+
+    /// Render Markdown into a [`MarkdownDocument`] with `options`, which also apply when it's
+    /// [re-rendered](Self::set_markdown)
+    pub fn from_markdown_with_options(
+        markdown: &str,
+        options: MarkdownOptions,
+        config: DocumentConfig,
+    ) -> Self {
+        let inner = HtmlDocument::from_html(MARKDOWN_SHELL, config);
+        let body_id = inner
+            .query_selector(".markdown-body")
+            .ok()
+            .flatten()
+            .expect("The Markdown shell has a markdown-body element");
+        let mut doc = Self {
+            inner,
+            body_id,
+            options,
+        };
+        doc.set_markdown(markdown)
+            .expect("Rendered Markdown can be parsed into the markdown-body element");
+        doc
+    }
+
+    /// Re-render the document from changed Markdown
+    ///
+    /// Only the contents of the `markdown-body` element are replaced, so the document keeps its
+    /// stylesheets, scroll position and resources which are still referenced.
+    pub fn set_markdown(&mut self, markdown: &str) -> Result<(), InnerHtmlError> {
+        let html = markdown_to_html_with_options(markdown, self.options);
+        DocumentHtmlParser::parse_inner_html(&mut self.inner, self.body_id, &html)
+    }
+
+    /// Convert the [`MarkdownDocument`] into it's inner [`BaseDocument`]
+    pub fn into_inner(self) -> BaseDocument {
+        self.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has(doc: &MarkdownDocument, selector: &str) -> bool {
+        doc.query_selector(selector).unwrap().is_some()
+    }
+
+    #[test]
+    fn rerenders_on_change() {
+        let markdown = "| a |\n|---|\n| b |\n\n- [x] done\n\nNote[^1]\n\n[^1]: The note";
+        let mut doc = MarkdownDocument::from_markdown(markdown, DocumentConfig::default());
+        assert!(has(&doc, ".markdown-body > table"));
+        assert!(has(&doc, ".task-list-item input[type=checkbox][checked]"));
+        assert!(has(&doc, ".footnotes"));
+
+        doc.set_markdown("# Title").unwrap();
+        assert!(has(&doc, ".markdown-body > h1"));
+        assert!(!has(&doc, "table"));
+    }
+
+    #[test]
+    fn omits_html_unless_allowed() {
+        let markdown = "<script>alert(1)</script>\n\n[link](javascript:alert(1))";
+        let doc = MarkdownDocument::from_markdown(markdown, DocumentConfig::default());
+        assert!(!has(&doc, "script"));
+        assert!(!has(&doc, "a[href^=javascript]"));
+
+        let options = MarkdownOptions { allow_html: true };
+        let config = DocumentConfig::default();
+        let doc = MarkdownDocument::from_markdown_with_options(markdown, options, config);
+        assert!(has(&doc, "script"));
+        assert!(has(&doc, "a[href^=javascript]"));
+    }
+
+    #[cfg(feature = "syntax-highlighting")]
+    #[test]
+    fn highlights_code_fences() {
+        let markdown = "```rust\nfn main() {}\n```";
+        let html = markdown_to_html(markdown);
+        assert!(html.contains("<span style="), "{html}");
+
+        let doc = MarkdownDocument::from_markdown(markdown, DocumentConfig::default());
+        assert!(has(&doc, ".markdown-body > pre span[style]"));
+    }
+}
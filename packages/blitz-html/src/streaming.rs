@@ -0,0 +1,552 @@
+//! Incremental parsing of HTML as it arrives over the network
+//!
+//! [`DocumentHtmlParser`] mutates the document as it parses, so it borrows the document for as
+//! long as parsing takes. [`HtmlParser`] instead queues the tree operations html5ever asks for,
+//! without touching a document, so that a document can be styled, laid out and painted between
+//! chunks. The queued operations are applied to the document with [`HtmlParser::apply`], which
+//! replays them through a [`DocumentHtmlParser`].
+
+use std::borrow::Cow;
+use std::cell::{Ref, RefCell};
+use std::collections::HashSet;
+
+use blitz_dom::{BaseDocument, DocumentLifecycle};
+use html5ever::tendril::{ByteTendril, StrTendril, TendrilSink, stream::Utf8LossyDecoder};
+use html5ever::tree_builder::{ElementFlags, NextParserState, NodeOrText, QuirksMode, TreeSink};
+use html5ever::{Attribute, Parser, QualName, local_name};
+
+use crate::DocumentHtmlParser;
+use crate::html_sink::{convert_quirks_mode, html_parse_opts};
+
+/// The handle html5ever uses for the document node. Other handles are allocated as nodes are
+/// created, and are mapped to the ids of the nodes they become when applied.
+const DOCUMENT_HANDLE: usize = 0;
+
+/// A tree operation requested by html5ever, see the [`TreeSink`] method of the same name
+enum TreeOp {
+    CreateElement {
+        handle: usize,
+        name: QualName,
+        attrs: Vec<Attribute>,
+    },
+    CreateComment {
+        handle: usize,
+    },
+    Append {
+        parent: usize,
+        child: NodeOrText<usize>,
+    },
+    AppendBeforeSibling {
+        sibling: usize,
+        child: NodeOrText<usize>,
+    },
+    AppendBasedOnParentNode {
+        element: usize,
+        prev_element: usize,
+        child: NodeOrText<usize>,
+    },
+    AddAttrsIfMissing {
+        target: usize,
+        attrs: Vec<Attribute>,
+    },
+    RemoveFromParent {
+        target: usize,
+    },
+    ReparentChildren {
+        old_parent: usize,
+        new_parent: usize,
+    },
+    CompleteScript {
+        target: usize,
+    },
+    SetQuirksMode(QuirksMode),
+}
+
+/// The kind of resource a [`Preload`] is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreloadKind {
+    Image,
+    Stylesheet,
+}
+
+/// A resource referenced by an element which has been parsed but not yet added to the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preload {
+    pub kind: PreloadKind,
+    /// The URL as written in the document, which is relative to the document's base URL
+    pub url: String,
+}
+
+impl Preload {
+    fn for_element(name: &QualName, attrs: &[Attribute]) -> Option<Self> {
+        let attr = |local| {
+            attrs
+                .iter()
+                .find(|attr| attr.name.local == local)
+                .map(|attr| attr.value.trim())
+        };
+        let (kind, url) = if name.local == local_name!("img") {
+            (PreloadKind::Image, attr(local_name!("src"))?)
+        } else if name.local == local_name!("link") {
+            let rel = attr(local_name!("rel"))?;
+            if !rel.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case("stylesheet")) {
+                return None;
+            }
+            (PreloadKind::Stylesheet, attr(local_name!("href"))?)
+        } else {
+            return None;
+        };
+        (!url.is_empty()).then(|| Self {
+            kind,
+            url: url.to_string(),
+        })
+    }
+}
+
+/// A [`TreeSink`] which queues the operations html5ever asks for instead of applying them
+struct TreeOpSink {
+    ops: RefCell<Vec<TreeOp>>,
+    /// The name of each element, indexed by handle
+    names: RefCell<Vec<Option<QualName>>>,
+    /// MathML `<annotation-xml>` elements whose contents are parsed as HTML rather than MathML
+    integration_points: RefCell<HashSet<usize>>,
+    preloads: RefCell<Vec<Preload>>,
+    errors: RefCell<Vec<Cow<'static, str>>>,
+}
+
+impl TreeOpSink {
+    fn new() -> Self {
+        Self {
+            ops: RefCell::new(Vec::new()),
+            names: RefCell::new(vec![None]),
+            integration_points: RefCell::new(HashSet::new()),
+            preloads: RefCell::new(Vec::new()),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn push(&self, op: TreeOp) {
+        self.ops.borrow_mut().push(op);
+    }
+
+    fn new_handle(&self, name: Option<QualName>) -> usize {
+        let mut names = self.names.borrow_mut();
+        names.push(name);
+        names.len() - 1
+    }
+}
+
+impl TreeSink for TreeOpSink {
+    /// The operations which haven't been taken yet
+    type Output = Vec<TreeOp>;
+
+    type Handle = usize;
+
+    type ElemName<'a>
+        = Ref<'a, QualName>
+    where
+        Self: 'a;
+
+    fn finish(self) -> Self::Output {
+        #[cfg(feature = "tracing")]
+        for error in self.errors.borrow().iter() {
+            tracing::error!("{error}");
+        }
+        self.ops.into_inner()
+    }
+
+    fn parse_error(&self, msg: Cow<'static, str>) {
+        self.errors.borrow_mut().push(msg);
+    }
+
+    fn get_document(&self) -> Self::Handle {
+        DOCUMENT_HANDLE
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a Self::Handle) -> Self::ElemName<'a> {
+        Ref::map(self.names.borrow(), |names| {
+            names[*target]
+                .as_ref()
+                .expect("TreeSink::elem_name called on a node which is not an element!")
+        })
+    }
+
+    fn create_element(
+        &self,
+        name: QualName,
+        attrs: Vec<Attribute>,
+        flags: ElementFlags,
+    ) -> Self::Handle {
+        if let Some(preload) = Preload::for_element(&name, &attrs) {
+            self.preloads.borrow_mut().push(preload);
+        }
+        let handle = self.new_handle(Some(name.clone()));
+        if flags.mathml_annotation_xml_integration_point {
+            self.integration_points.borrow_mut().insert(handle);
+        }
+        self.push(TreeOp::CreateElement {
+            handle,
+            name,
+            attrs,
+        });
+        handle
+    }
+
+    fn is_mathml_annotation_xml_integration_point(&self, handle: &Self::Handle) -> bool {
+        self.integration_points.borrow().contains(handle)
+    }
+
+    fn create_comment(&self, _text: StrTendril) -> Self::Handle {
+        let handle = self.new_handle(None);
+        self.push(TreeOp::CreateComment { handle });
+        handle
+    }
+
+    fn create_pi(&self, _target: StrTendril, _data: StrTendril) -> Self::Handle {
+        let handle = self.new_handle(None);
+        self.push(TreeOp::CreateComment { handle });
+        handle
+    }
+
+    fn append(&self, parent: &Self::Handle, child: NodeOrText<Self::Handle>) {
+        self.push(TreeOp::Append {
+            parent: *parent,
+            child,
+        });
+    }
+
+    fn append_before_sibling(&self, sibling: &Self::Handle, child: NodeOrText<Self::Handle>) {
+        self.push(TreeOp::AppendBeforeSibling {
+            sibling: *sibling,
+            child,
+        });
+    }
+
+    fn append_based_on_parent_node(
+        &self,
+        element: &Self::Handle,
+        prev_element: &Self::Handle,
+        child: NodeOrText<Self::Handle>,
+    ) {
+        self.push(TreeOp::AppendBasedOnParentNode {
+            element: *element,
+            prev_element: *prev_element,
+            child,
+        });
+    }
+
+    fn append_doctype_to_document(
+        &self,
+        _name: StrTendril,
+        _public_id: StrTendril,
+        _system_id: StrTendril,
+    ) {
+        // DOCTYPE nodes are not stored in the DOM tree (see `DocumentHtmlParser`)
+    }
+
+    fn get_template_contents(&self, target: &Self::Handle) -> Self::Handle {
+        *target
+    }
+
+    fn same_node(&self, x: &Self::Handle, y: &Self::Handle) -> bool {
+        x == y
+    }
+
+    fn set_quirks_mode(&self, mode: QuirksMode) {
+        self.push(TreeOp::SetQuirksMode(mode));
+    }
+
+    fn add_attrs_if_missing(&self, target: &Self::Handle, attrs: Vec<Attribute>) {
+        self.push(TreeOp::AddAttrsIfMissing {
+            target: *target,
+            attrs,
+        });
+    }
+
+    fn remove_from_parent(&self, target: &Self::Handle) {
+        self.push(TreeOp::RemoveFromParent { target: *target });
+    }
+
+    fn reparent_children(&self, old_parent: &Self::Handle, new_parent: &Self::Handle) {
+        self.push(TreeOp::ReparentChildren {
+            old_parent: *old_parent,
+            new_parent: *new_parent,
+        });
+    }
+
+    fn complete_script(&self, target: &Self::Handle) -> NextParserState {
+        self.push(TreeOp::CompleteScript { target: *target });
+        NextParserState::Continue
+    }
+}
+
+/// Parses an HTML document chunk by chunk, see the [module docs](self)
+///
+/// ```ignore
+/// let mut parser = HtmlParser::new();
+/// while let Some(chunk) = chunks.recv().await {
+///     parser.feed(&chunk);
+///     parser.apply(&mut doc);
+///     doc.resolve();
+///     // paint
+/// }
+/// parser.finish(&mut doc);
+/// ```
+///
+/// Documents are always parsed as HTML, never as XHTML.
+pub struct HtmlParser {
+    decoder: Utf8LossyDecoder<Parser<TreeOpSink>>,
+    applied: AppliedNodes,
+}
+
+impl Default for HtmlParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HtmlParser {
+    pub fn new() -> Self {
+        Self {
+            decoder: html5ever::parse_document(TreeOpSink::new(), html_parse_opts()).from_utf8(),
+            applied: AppliedNodes {
+                node_ids: vec![0],
+                quirks_mode: QuirksMode::NoQuirks,
+            },
+        }
+    }
+
+    fn sink(&self) -> &TreeOpSink {
+        &self.decoder.inner_sink.tokenizer.sink.sink
+    }
+
+    /// Parse the next chunk of the document's UTF-8 source. A chunk may end part way through a
+    /// tag or a character, in which case the rest is parsed with the next chunk.
+    ///
+    /// The nodes parsed are only added to a document by [`apply`](Self::apply).
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.decoder.process(ByteTendril::from_slice(bytes));
+    }
+
+    /// Take the resources referenced by the images and stylesheets parsed since this was last
+    /// called. They are fetched once their elements are applied to the document, so fetching them
+    /// before then (while the document is being laid out, say) gets them sooner.
+    pub fn take_preloads(&mut self) -> Vec<Preload> {
+        self.sink().preloads.take()
+    }
+
+    /// Whether there are parsed nodes which haven't been applied to the document yet
+    pub fn has_pending(&self) -> bool {
+        !self.sink().ops.borrow().is_empty()
+    }
+
+    /// Add the nodes parsed so far to `doc`, which must be the document every other chunk was
+    /// applied to
+    pub fn apply(&mut self, doc: &mut BaseDocument) {
+        let ops = self.sink().ops.take();
+        self.applied.apply(doc, ops);
+    }
+
+    /// Parse the end of the document, add the remaining nodes to `doc` and mark it as
+    /// [interactive](DocumentLifecycle::Interactive)
+    pub fn finish(self, doc: &mut BaseDocument) {
+        let Self {
+            decoder,
+            mut applied,
+        } = self;
+        applied.apply(doc, decoder.finish());
+        doc.set_lifecycle(DocumentLifecycle::Interactive);
+    }
+}
+
+/// The nodes the handles of an [`HtmlParser`] have been applied to a document as
+struct AppliedNodes {
+    /// The id of the node each handle was applied as, indexed by handle
+    node_ids: Vec<usize>,
+    quirks_mode: QuirksMode,
+}
+
+impl AppliedNodes {
+    fn apply(&mut self, doc: &mut BaseDocument, ops: Vec<TreeOp>) {
+        if ops.is_empty() {
+            return;
+        }
+
+        let sink = DocumentHtmlParser::new(doc);
+        sink.quirks_mode.set(self.quirks_mode);
+        for op in ops {
+            match op {
+                TreeOp::CreateElement {
+                    handle,
+                    name,
+                    attrs,
+                } => {
+                    let node_id = sink.create_element(name, attrs, ElementFlags::default());
+                    self.set_node_id(handle, node_id);
+                }
+                TreeOp::CreateComment { handle } => {
+                    let node_id = sink.create_comment(StrTendril::new());
+                    self.set_node_id(handle, node_id);
+                }
+                TreeOp::Append { parent, child } => {
+                    sink.append(&self.node_ids[parent], self.child(child));
+                }
+                TreeOp::AppendBeforeSibling { sibling, child } => {
+                    sink.append_before_sibling(&self.node_ids[sibling], self.child(child));
+                }
+                TreeOp::AppendBasedOnParentNode {
+                    element,
+                    prev_element,
+                    child,
+                } => sink.append_based_on_parent_node(
+                    &self.node_ids[element],
+                    &self.node_ids[prev_element],
+                    self.child(child),
+                ),
+                TreeOp::AddAttrsIfMissing { target, attrs } => {
+                    sink.add_attrs_if_missing(&self.node_ids[target], attrs);
+                }
+                TreeOp::RemoveFromParent { target } => {
+                    sink.remove_from_parent(&self.node_ids[target]);
+                }
+                TreeOp::ReparentChildren {
+                    old_parent,
+                    new_parent,
+                } => sink.reparent_children(&self.node_ids[old_parent], &self.node_ids[new_parent]),
+                TreeOp::CompleteScript { target } => {
+                    sink.complete_script(&self.node_ids[target]);
+                }
+                TreeOp::SetQuirksMode(mode) => {
+                    self.quirks_mode = mode;
+                    sink.set_quirks_mode(mode);
+                }
+            }
+        }
+        sink.finish();
+        doc.set_quirks_mode(convert_quirks_mode(self.quirks_mode));
+    }
+
+    fn set_node_id(&mut self, handle: usize, node_id: usize) {
+        if self.node_ids.len() <= handle {
+            self.node_ids.resize(handle + 1, 0);
+        }
+        self.node_ids[handle] = node_id;
+    }
+
+    fn child(&self, child: NodeOrText<usize>) -> NodeOrText<usize> {
+        match child {
+            NodeOrText::AppendNode(handle) => NodeOrText::AppendNode(self.node_ids[handle]),
+            NodeOrText::AppendText(text) => NodeOrText::AppendText(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::task::Context;
+
+    use blitz_dom::DocumentConfig;
+    use blitz_traits::script::{Script, ScriptProvider, ScriptSource};
+
+    use super::*;
+
+    /// Records the scripts it's handed
+    #[derive(Default)]
+    struct RecordingScriptProvider {
+        scripts: Mutex<Vec<Script>>,
+    }
+
+    impl ScriptProvider<BaseDocument> for RecordingScriptProvider {
+        fn script_discovered(&self, _doc_id: usize, script: Script) {
+            self.scripts.lock().unwrap().push(script);
+        }
+
+        fn poll_tasks(&self, _doc: &mut BaseDocument, _cx: &mut Context<'_>) -> bool {
+            false
+        }
+    }
+
+    const HTML: &str = "<!DOCTYPE html><html><head><title>Streamed</title>\
+        <link rel=\"stylesheet\" href=\"style.css\"></head><body><table><tr><td>cell</td></tr>\
+        stray text</table><p>caf\u{e9} <img src=\"a.png\"><b>bold<i>both</b>italic</i></p>\
+        </body></html>";
+
+    fn tree(doc: &BaseDocument) -> String {
+        doc.root_element().outer_html()
+    }
+
+    #[test]
+    fn matches_parsing_all_at_once() {
+        let mut expected = BaseDocument::new(DocumentConfig::default()).unwrap();
+        DocumentHtmlParser::parse_into_doc(&mut expected, HTML);
+
+        let mut doc = BaseDocument::new(DocumentConfig::default()).unwrap();
+        let mut parser = HtmlParser::new();
+        let mut preloads = Vec::new();
+        // Split the source every three bytes, including within tags and characters
+        for chunk in HTML.as_bytes().chunks(3) {
+            parser.feed(chunk);
+            preloads.extend(parser.take_preloads());
+            parser.apply(&mut doc);
+        }
+        parser.finish(&mut doc);
+
+        assert_eq!(tree(&doc), tree(&expected));
+        assert_eq!(
+            preloads,
+            vec![
+                Preload {
+                    kind: PreloadKind::Stylesheet,
+                    url: "style.css".into(),
+                },
+                Preload {
+                    kind: PreloadKind::Image,
+                    url: "a.png".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_are_added_to_the_document_as_they_arrive() {
+        let mut doc = BaseDocument::new(DocumentConfig::default()).unwrap();
+        let mut parser = HtmlParser::new();
+
+        parser.feed(b"<!DOCTYPE html><p>one</p><p>caf\xc3");
+        assert!(parser.has_pending());
+        parser.apply(&mut doc);
+        assert!(!parser.has_pending());
+        assert!(tree(&doc).contains("<p>one</p>"));
+
+        // The rest of the split character comes with the next chunk
+        parser.feed(b"\xa9</p>");
+        parser.finish(&mut doc);
+        assert!(tree(&doc).contains("<p>one</p><p>caf\u{e9}</p>"));
+    }
+
+    #[test]
+    fn scripts_run_once_their_end_tag_is_parsed() {
+        let provider = Arc::new(RecordingScriptProvider::default());
+        let mut doc = BaseDocument::new(DocumentConfig {
+            script_provider: Some(provider.clone()),
+            ..DocumentConfig::default()
+        })
+        .unwrap();
+        let mut parser = HtmlParser::new();
+
+        parser.feed(b"<!DOCTYPE html><script>first();");
+        parser.apply(&mut doc);
+        assert!(provider.scripts.lock().unwrap().is_empty());
+
+        parser.feed(b" second();</script><p>after</p>");
+        parser.apply(&mut doc);
+        let scripts = provider.scripts.lock().unwrap().clone();
+        assert_eq!(scripts.len(), 1);
+        assert!(matches!(
+            &scripts[0].source,
+            ScriptSource::Inline(text) if text == "first(); second();"
+        ));
+        parser.finish(&mut doc);
+    }
+}
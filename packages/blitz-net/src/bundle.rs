@@ -0,0 +1,159 @@
+//! Resources bundled with an app, like the assets of Dioxus's `asset!()` macro
+//!
+//! Apps' documents have `dioxus://index.html` as their base URL, so both `asset!()` URLs (like
+//! `/assets/logo-dxh1f2e3d4c.png`) and relative paths resolve to `dioxus:` URLs, which the
+//! [`Provider`](crate::Provider) loads from its [`BundleProvider`].
+
+use std::borrow::Cow;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use blitz_traits::net::Bytes;
+use percent_encoding::percent_decode_str;
+
+/// The scheme of the URLs of bundled resources
+pub const BUNDLE_SCHEME: &str = "dioxus";
+
+/// Loads the resources bundled with an app
+pub trait BundleProvider: Send + Sync + 'static {
+    /// Load the resource at `path` (the percent-decoded path of its `dioxus:` URL)
+    fn load(&self, path: &str) -> io::Result<Bytes>;
+}
+
+/// Loads bundled resources from directories on disk
+///
+/// Resources are looked up by their exact path first, and then without the cache-busting hash
+/// `dx` adds to file names (so `assets/logo-dxh1f2e3d4c.png` is loaded from `assets/logo.png`),
+/// so that apps find their assets whether or not they were built by `dx`.
+pub struct AssetDirectory {
+    roots: Vec<PathBuf>,
+}
+
+impl AssetDirectory {
+    /// Load resources from `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            roots: vec![root.into()],
+        }
+    }
+
+    /// Also load resources from `root`, if they aren't in the directories added before it
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.roots.push(root.into());
+        self
+    }
+
+    /// The directories resources are loaded from, in the order they're looked in
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Load resources from where `dx bundle` puts them for the running executable (beside it,
+    /// or in the `Resources` directory of macOS app bundles), and then from the working
+    /// directory (which `cargo run` runs apps in the crate root of)
+    pub fn for_current_exe() -> Self {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        let bundle_dir = if exe_dir.ends_with("Contents/MacOS") {
+            exe_dir.join("../Resources")
+        } else {
+            exe_dir
+        };
+        let directory = Self::new(bundle_dir);
+        match std::env::current_dir() {
+            Ok(working_dir) => directory.with_root(working_dir),
+            Err(_) => directory,
+        }
+    }
+}
+
+impl BundleProvider for AssetDirectory {
+    fn load(&self, path: &str) -> io::Result<Bytes> {
+        let path = path.trim_start_matches('/');
+        // Resources can't be loaded from outside the directories
+        let is_contained = Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !is_contained {
+            return Err(io::Error::new(io::ErrorKind::NotFound, path.to_string()));
+        }
+
+        let unhashed = strip_asset_hash(path);
+        let mut error = io::Error::new(io::ErrorKind::NotFound, path.to_string());
+        for root in &self.roots {
+            for file in [root.join(path), root.join(&*unhashed)] {
+                match std::fs::read(file) {
+                    Ok(bytes) => return Ok(Bytes::from(bytes)),
+                    Err(err) => error = err,
+                }
+            }
+        }
+        Err(error)
+    }
+}
+
+/// The path of a `dioxus:` URL, percent-decoded, so `assets/my%20logo.png` is loaded from
+/// `assets/my logo.png`
+pub(crate) fn decode_path(path: &str) -> io::Result<Cow<'_, str>> {
+    percent_decode_str(path)
+        .decode_utf8()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+/// Remove the cache-busting hash which `dx` adds to the names of asset files (and changes
+/// whenever the file does) from a path, so `assets/logo-dxh1f2e3d4c.png` becomes
+/// `assets/logo.png`
+pub fn strip_asset_hash(path: &str) -> Cow<'_, str> {
+    let name_start = path.rfind('/').map_or(0, |index| index + 1);
+    let Some(hash_start) = path[name_start..].rfind("-dxh").map(|index| name_start + index) else {
+        return Cow::Borrowed(path);
+    };
+    let rest = &path[hash_start + "-dxh".len()..];
+    let hash_len = rest.find('.').unwrap_or(rest.len());
+    if hash_len == 0 || !rest[..hash_len].bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Cow::Borrowed(path);
+    }
+    Cow::Owned(format!("{}{}", &path[..hash_start], &rest[hash_len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AssetDirectory, BundleProvider, decode_path, strip_asset_hash};
+
+    #[test]
+    fn strips_asset_hashes() {
+        assert_eq!(strip_asset_hash("/assets/logo-dxh1f2e3d4c.png"), "/assets/logo.png");
+        assert_eq!(strip_asset_hash("assets/main-dxhab12"), "assets/main");
+        assert_eq!(strip_asset_hash("/assets/logo.png"), "/assets/logo.png");
+        // Only hexadecimal hashes in the file name are stripped
+        let path = "/assets/x-dxh1/logo-dxhzz.png";
+        assert_eq!(strip_asset_hash(path), path);
+    }
+
+    #[test]
+    fn decodes_paths() {
+        assert_eq!(decode_path("/assets/my%20logo.png").unwrap(), "/assets/my logo.png");
+        assert_eq!(decode_path("/assets/%C3%A9t%C3%A9.css").unwrap(), "/assets/été.css");
+        assert_eq!(decode_path("/assets/logo.png").unwrap(), "/assets/logo.png");
+        assert!(decode_path("/assets/%FF.png").is_err());
+    }
+
+    #[test]
+    fn decoded_paths_stay_in_the_asset_directory() {
+        let root = std::env::temp_dir().join(format!("blitz-bundle-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(root.join("assets")).unwrap();
+        std::fs::write(root.join("assets/my logo.png"), b"logo").unwrap();
+        let directory = AssetDirectory::new(root.join("assets"));
+
+        let path = decode_path("/my%20logo.png").unwrap();
+        assert_eq!(&directory.load(&path).unwrap()[..], b"logo");
+        // Encoded dot segments can't reach outside the directory once decoded
+        std::fs::write(root.join("secret.txt"), b"secret").unwrap();
+        let path = decode_path("/%2E%2E/secret.txt").unwrap();
+        assert!(directory.load(&path).is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
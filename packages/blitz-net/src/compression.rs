@@ -0,0 +1,239 @@
+//! Compressed response and request bodies
+//!
+//! HTTP responses are decoded by reqwest, for the encodings enabled by blitz-net's `gzip`,
+//! `brotli`, `zstd` and `deflate` features which the provider accepts (see
+//! [`Provider::with_accept_encoding`](crate::Provider::with_accept_encoding)). Responses which are
+//! still encoded after that, because the server ignored the `Accept-Encoding` header, are decoded
+//! here instead, or fail rather than being handed to documents compressed.
+//!
+//! Files and bundled resources may be precompressed: if `style.css` doesn't exist but
+//! `style.css.br` (or `style.css.gz`, or `style.css.zst`) does, that is loaded and decoded.
+//!
+//! Request bodies can be compressed too, see
+//! [`Provider::with_request_compression`](crate::Provider::with_request_compression).
+
+use std::io;
+
+use blitz_traits::net::Bytes;
+use blitz_traits::net::http::{HeaderMap, header};
+
+/// A `Content-Encoding` which blitz-net can decode (and encode) with the matching feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    Gzip,
+    /// The zlib format (which is what HTTP calls "deflate")
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Every encoding, in order of preference
+    pub const ALL: [Self; 4] = [Self::Zstd, Self::Brotli, Self::Gzip, Self::Deflate];
+
+    /// The token for the encoding in `Content-Encoding` and `Accept-Encoding` headers
+    pub fn token(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// The encoding a `Content-Encoding` token stands for
+    pub fn from_token(token: &str) -> Option<Self> {
+        let token = token.trim();
+        Self::ALL
+            .into_iter()
+            .find(|encoding| token.eq_ignore_ascii_case(encoding.token()))
+            .or_else(|| token.eq_ignore_ascii_case("x-gzip").then_some(Self::Gzip))
+    }
+
+    /// Whether blitz-net was built with the feature for the encoding
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Gzip => cfg!(feature = "gzip"),
+            Self::Deflate => cfg!(feature = "deflate"),
+            Self::Brotli => cfg!(feature = "brotli"),
+            Self::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// The encodings blitz-net was built with the features for, in order of preference
+    pub fn supported() -> impl Iterator<Item = Self> {
+        Self::ALL.into_iter().filter(|encoding| encoding.is_supported())
+    }
+
+    /// The extension of files precompressed with the encoding
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Gzip => Some("gz"),
+            Self::Brotli => Some("br"),
+            Self::Zstd => Some("zst"),
+            Self::Deflate => None,
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => read_all(flate2::read::MultiGzDecoder::new(bytes)),
+            #[cfg(feature = "deflate")]
+            Self::Deflate => read_all(flate2::read::ZlibDecoder::new(bytes)),
+            #[cfg(feature = "brotli")]
+            Self::Brotli => read_all(brotli::Decompressor::new(bytes, 4096)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::stream::decode_all(bytes),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        #[cfg(any(feature = "gzip", feature = "deflate", feature = "brotli"))]
+        use std::io::Write as _;
+
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let compression = flate2::Compression::default();
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), compression);
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "deflate")]
+            Self::Deflate => {
+                let compression = flate2::Compression::default();
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), compression);
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "brotli")]
+            Self::Brotli => {
+                // Quality 5 and a 4MiB window, which compress well without being slow
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+                encoder.write_all(bytes)?;
+                Ok(encoder.into_inner())
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::stream::encode_all(bytes, 0),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn unsupported(self) -> io::Error {
+        let feature = match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "brotli",
+            Self::Zstd => "zstd",
+        };
+        let message = format!("{} needs blitz-net's `{feature}` feature", self.token());
+        io::Error::new(io::ErrorKind::Unsupported, message)
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "deflate", feature = "brotli"))]
+fn read_all(mut reader: impl io::Read) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The encodings listed by the `Content-Encoding` headers of a response, in the order they were
+/// applied. Fails if any of them is unknown, since the body can't be decoded then.
+pub(crate) fn content_encodings(headers: &HeaderMap) -> io::Result<Vec<ContentEncoding>> {
+    let mut encodings = Vec::new();
+    for value in headers.get_all(header::CONTENT_ENCODING) {
+        let value = value.to_str().unwrap_or_default();
+        for token in value.split(',').map(str::trim) {
+            if token.is_empty() || token.eq_ignore_ascii_case("identity") {
+                continue;
+            }
+            let Some(encoding) = ContentEncoding::from_token(token) else {
+                let message = format!("Unknown content encoding {token}");
+                return Err(io::Error::new(io::ErrorKind::Unsupported, message));
+            };
+            encodings.push(encoding);
+        }
+    }
+    Ok(encodings)
+}
+
+/// Undo `encodings`, which were applied to `bytes` in order
+pub(crate) fn decode_all(encodings: &[ContentEncoding], bytes: Bytes) -> io::Result<Bytes> {
+    let mut bytes = bytes;
+    for encoding in encodings.iter().rev() {
+        bytes = Bytes::from(encoding.decode(&bytes)?);
+    }
+    Ok(bytes)
+}
+
+/// Load `path` with `load`, or a precompressed copy of it if it doesn't exist
+pub(crate) fn load_precompressed(
+    path: &str,
+    load: impl Fn(&str) -> io::Result<Bytes>,
+) -> io::Result<Bytes> {
+    let error = match load(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => error,
+        result => return result,
+    };
+    for encoding in ContentEncoding::supported() {
+        let Some(extension) = encoding.extension() else {
+            continue;
+        };
+        if let Ok(bytes) = load(&format!("{path}.{extension}")) {
+            return encoding.decode(&bytes).map(Bytes::from);
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blitz_traits::net::http::HeaderValue;
+
+    #[test]
+    fn content_encoding_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip, identity, BR"));
+        let encodings = content_encodings(&headers).unwrap();
+        assert_eq!(encodings, [ContentEncoding::Gzip, ContentEncoding::Brotli]);
+
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("compress"));
+        assert!(content_encodings(&headers).is_err());
+    }
+
+    #[test]
+    #[cfg(any(feature = "gzip", feature = "deflate", feature = "brotli", feature = "zstd"))]
+    fn supported_encodings_round_trip() {
+        let text = b"body { color: red }\n".repeat(100);
+        assert!(ContentEncoding::supported().next().is_some());
+        for encoding in ContentEncoding::supported() {
+            let encoded = encoding.encode(&text).unwrap();
+            assert!(encoded.len() < text.len());
+            let decoded = decode_all(&[encoding], Bytes::from(encoded)).unwrap();
+            assert_eq!(decoded, text, "{encoding:?}");
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+    fn precompressed_files_are_found() {
+        let encoding = ContentEncoding::supported().find(|e| e.extension().is_some()).unwrap();
+        let compressed = Bytes::from(encoding.encode(b"a { }").unwrap());
+        let compressed_path = format!("style.css.{}", encoding.extension().unwrap());
+        let load = |path: &str| {
+            if path == compressed_path {
+                Ok(compressed.clone())
+            } else {
+                Err(io::Error::from(io::ErrorKind::NotFound))
+            }
+        };
+        assert_eq!(load_precompressed("style.css", load).unwrap(), "a { }");
+        assert!(load_precompressed("script.js", load).is_err());
+    }
+}
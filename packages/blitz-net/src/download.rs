@@ -0,0 +1,649 @@
+//! Saving resources to disk
+//!
+//! The [`DownloadManager`] of a [`Provider`](crate::Provider) (see
+//! [`Provider::downloads`](crate::Provider::downloads)) saves resources to its directory (the
+//! user's downloads directory by default), which is what embedders should do when links with a
+//! `download` attribute are clicked (see [`NavigationOptions::download`]), or links to resources
+//! which can't be displayed.
+//!
+//! Downloads are written to a `.part` file beside their destination, which is renamed once the
+//! whole resource has been received. Paused (and failed) downloads can be resumed: if the server
+//! supports ranged requests, only the rest of the resource is requested. The manager's listener is
+//! told whenever the progress or state of a download changes.
+//!
+//! [`NavigationOptions::download`]: blitz_traits::navigation::NavigationOptions::download
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use blitz_traits::net::http::{HeaderMap, HeaderValue, header};
+use blitz_traits::net::{Request, Url};
+use tokio::io::AsyncWriteExt as _;
+use tokio::runtime::Handle;
+use tokio::task::AbortHandle;
+
+use crate::{HttpClient, ProviderError};
+
+/// The longest the listener goes without being told of a download's progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Called whenever the progress or state of a download changes
+pub type DownloadListener = Arc<dyn Fn(&DownloadInfo) + Send + Sync>;
+
+/// Identifies a download started by a [`DownloadManager`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DownloadId(u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadState {
+    InProgress,
+    /// Paused with [`DownloadManager::pause`], until it's resumed
+    Paused,
+    /// Saved to the download's path
+    Completed,
+    /// Failed with the given error. Failed downloads can be resumed.
+    Failed(String),
+    /// Cancelled with [`DownloadManager::cancel`], which deleted what had been downloaded
+    Cancelled,
+}
+
+/// The progress of a download
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadInfo {
+    pub id: DownloadId,
+    pub url: Url,
+    /// Where the resource is saved, which is chosen once the response to the download starts
+    /// arriving (as its headers may suggest a file name)
+    pub path: Option<PathBuf>,
+    pub state: DownloadState,
+    /// The number of bytes saved so far
+    pub received: u64,
+    /// The size of the resource, if the server said
+    pub total: Option<u64>,
+}
+
+struct Download {
+    info: DownloadInfo,
+    /// The document which started the download, if any
+    doc_id: Option<usize>,
+    request: Request,
+    /// The file name suggested by whoever started the download
+    suggested_name: Option<String>,
+    /// The `ETag` or `Last-Modified` header of the response, for resuming with `If-Range`
+    validator: Option<HeaderValue>,
+    task: Option<AbortHandle>,
+}
+
+/// Saves resources to disk, see the [module docs](self)
+pub struct DownloadManager {
+    rt: Handle,
+    client: HttpClient,
+    directory: Mutex<PathBuf>,
+    next_id: AtomicU64,
+    downloads: Mutex<HashMap<DownloadId, Download>>,
+    listener: Mutex<Option<DownloadListener>>,
+}
+
+impl DownloadManager {
+    pub(crate) fn new(rt: Handle, client: HttpClient) -> Self {
+        let directory = dirs::download_dir()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        Self {
+            rt,
+            client,
+            directory: Mutex::new(directory),
+            next_id: AtomicU64::new(0),
+            downloads: Mutex::new(HashMap::new()),
+            listener: Mutex::new(None),
+        }
+    }
+
+    /// Save later downloads to `directory`
+    pub fn set_directory(&self, directory: impl Into<PathBuf>) {
+        *self.directory.lock().unwrap_or_else(|err| err.into_inner()) = directory.into();
+    }
+
+    /// Tell `listener` whenever the progress or state of a download changes
+    pub fn set_listener(&self, listener: impl Fn(&DownloadInfo) + Send + Sync + 'static) {
+        let mut current = self.listener.lock().unwrap_or_else(|err| err.into_inner());
+        *current = Some(Arc::new(listener));
+    }
+
+    /// Download `request` (an HTTP(S) request, made for the document `doc_id` if any) to the
+    /// download directory. The file is named `suggested_name` if given (and not empty), or else as
+    /// the response suggests, or after the last segment of the URL.
+    ///
+    /// The request is checked against the security policy as one of the document's own, even if
+    /// it was made to navigate (as downloads of links with a `download` attribute are).
+    pub fn start(
+        self: &Arc<Self>,
+        doc_id: Option<usize>,
+        mut request: Request,
+        suggested_name: Option<String>,
+    ) -> Result<DownloadId, ProviderError> {
+        request.navigation = false;
+        let document_url = self.client.document_url(doc_id);
+        self.client.check_policy(document_url.as_ref(), false, &mut request.url)?;
+        if !matches!(request.url.scheme(), "http" | "https") {
+            let message = format!("{} can't be downloaded", request.url);
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, message).into());
+        }
+        // Compressed responses would make the offsets of ranged requests meaningless
+        let identity = HeaderValue::from_static("identity");
+        request.headers.insert(header::ACCEPT_ENCODING, identity);
+
+        let id = DownloadId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let info = DownloadInfo {
+            id,
+            url: request.url.clone(),
+            path: None,
+            state: DownloadState::InProgress,
+            received: 0,
+            total: None,
+        };
+        self.lock().insert(
+            id,
+            Download {
+                info: info.clone(),
+                doc_id,
+                request,
+                suggested_name: suggested_name.filter(|name| !name.is_empty()),
+                validator: None,
+                task: None,
+            },
+        );
+        self.notify(&info);
+        self.spawn(id);
+        Ok(id)
+    }
+
+    /// Stop a download, keeping what has been downloaded so far, so that it can be resumed
+    pub fn pause(&self, id: DownloadId) {
+        self.stop(id, DownloadState::Paused);
+    }
+
+    /// Carry on with a paused or failed download, from where it stopped if the server allows
+    pub fn resume(self: &Arc<Self>, id: DownloadId) {
+        let resumed = self.update(id, |download| {
+            if !matches!(download.info.state, DownloadState::Paused | DownloadState::Failed(_)) {
+                return None;
+            }
+            download.info.state = DownloadState::InProgress;
+            Some(())
+        });
+        if resumed.is_some() {
+            self.spawn(id);
+        }
+    }
+
+    /// Stop a download, deleting what has been downloaded so far
+    pub fn cancel(&self, id: DownloadId) {
+        let Some(info) = self.stop(id, DownloadState::Cancelled) else {
+            return;
+        };
+        if let Some(path) = &info.path {
+            let _ = std::fs::remove_file(part_path(path));
+        }
+    }
+
+    /// The downloads started by the manager, oldest first
+    pub fn downloads(&self) -> Vec<DownloadInfo> {
+        let mut downloads: Vec<_> =
+            self.lock().values().map(|download| download.info.clone()).collect();
+        downloads.sort_by_key(|info| info.id);
+        downloads
+    }
+
+    pub fn download(&self, id: DownloadId) -> Option<DownloadInfo> {
+        self.lock().get(&id).map(|download| download.info.clone())
+    }
+
+    /// Forget the downloads which have completed or been cancelled
+    pub fn clear_finished(&self) {
+        self.lock().retain(|_, download| {
+            !matches!(download.info.state, DownloadState::Completed | DownloadState::Cancelled)
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<DownloadId, Download>> {
+        self.downloads.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn notify(&self, info: &DownloadInfo) {
+        let listener = self.listener.lock().unwrap_or_else(|err| err.into_inner()).clone();
+        if let Some(listener) = listener {
+            listener(info);
+        }
+    }
+
+    /// Apply `update` to a download, telling the listener if it returned `Some`
+    fn update<T>(
+        &self,
+        id: DownloadId,
+        update: impl FnOnce(&mut Download) -> Option<T>,
+    ) -> Option<T> {
+        let (result, info) = {
+            let mut downloads = self.lock();
+            let download = downloads.get_mut(&id)?;
+            let result = update(download)?;
+            (result, download.info.clone())
+        };
+        // The listener is called without the lock held, so that it can call the manager
+        self.notify(&info);
+        Some(result)
+    }
+
+    /// Stop a download, moving it to `state`. Only downloads in progress can be paused, but
+    /// paused and failed downloads can be cancelled too.
+    fn stop(&self, id: DownloadId, state: DownloadState) -> Option<DownloadInfo> {
+        self.update(id, |download| {
+            let stoppable = match state {
+                DownloadState::Paused => download.info.state == DownloadState::InProgress,
+                _ => matches!(
+                    download.info.state,
+                    DownloadState::InProgress | DownloadState::Paused | DownloadState::Failed(_)
+                ),
+            };
+            if !stoppable {
+                return None;
+            }
+            if let Some(task) = download.task.take() {
+                task.abort();
+            }
+            download.info.state = state;
+            Some(download.info.clone())
+        })
+    }
+
+    fn spawn(self: &Arc<Self>, id: DownloadId) {
+        let manager = Arc::clone(self);
+        let task = self.rt.spawn(async move {
+            let result = manager.run(id).await;
+            manager.update(id, |download| {
+                // The download may have been paused or cancelled as it finished
+                if download.info.state != DownloadState::InProgress {
+                    return None;
+                }
+                download.task = None;
+                download.info.state = match &result {
+                    Ok(()) => DownloadState::Completed,
+                    Err(error) => DownloadState::Failed(error.to_string()),
+                };
+                Some(())
+            });
+        });
+        if let Some(download) = self.lock().get_mut(&id) {
+            download.task = Some(task.abort_handle());
+        }
+    }
+
+    async fn run(&self, id: DownloadId) -> Result<(), ProviderError> {
+        let (doc_id, mut request, path, received, validator) = {
+            let downloads = self.lock();
+            let Some(download) = downloads.get(&id) else {
+                return Ok(());
+            };
+            let info = &download.info;
+            let request = download.request.clone();
+            let validator = download.validator.clone();
+            (download.doc_id, request, info.path.clone(), info.received, validator)
+        };
+
+        // Ask for the rest of the resource, unless it has changed since the download started
+        let resuming = path.is_some() && received > 0;
+        if resuming {
+            let range = HeaderValue::from_str(&format!("bytes={received}-")).unwrap();
+            request.headers.insert(header::RANGE, range);
+            if let Some(validator) = validator {
+                request.headers.insert(header::IF_RANGE, validator);
+            }
+        }
+
+        let mut response = self.client.send(doc_id, request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = format!("The server responded with {status}");
+            return Err(std::io::Error::other(message).into());
+        }
+        let append = resuming && status == reqwest::StatusCode::PARTIAL_CONTENT;
+        let received = if append { received } else { 0 };
+        let total = response.content_length().map(|length| length + received);
+        let validator = validator_of(response.headers());
+
+        let path = match path {
+            Some(path) => path,
+            None => self.choose_path(id, response.url(), response.headers()).await,
+        };
+        self.update(id, |download| {
+            download.info.received = received;
+            download.info.total = total;
+            download.validator = validator;
+            Some(())
+        });
+
+        let part_path = part_path(&path);
+        if let Some(directory) = path.parent() {
+            tokio::fs::create_dir_all(directory).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&part_path)
+            .await?;
+
+        let mut received = received;
+        let mut last_notified = Instant::now();
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            received += chunk.len() as u64;
+            let notify = last_notified.elapsed() >= PROGRESS_INTERVAL;
+            self.update(id, |download| {
+                download.info.received = received;
+                notify.then_some(())
+            });
+            if notify {
+                last_notified = Instant::now();
+            }
+        }
+        file.flush().await?;
+        drop(file);
+        tokio::fs::rename(&part_path, &path).await?;
+        Ok(())
+    }
+
+    /// Choose where to save a download, in the download directory without replacing any files,
+    /// and claim the path for it
+    async fn choose_path(&self, id: DownloadId, url: &Url, headers: &HeaderMap) -> PathBuf {
+        let suggested = self.lock().get(&id).and_then(|download| download.suggested_name.clone());
+        let name = suggested
+            .or_else(|| content_disposition_file_name(headers))
+            .or_else(|| url_file_name(url))
+            .map(|name| sanitize_file_name(&name))
+            .unwrap_or_else(|| "download".to_string());
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+            _ => (name.as_str(), None),
+        };
+
+        let directory = self.directory.lock().unwrap_or_else(|err| err.into_inner()).clone();
+        let candidates = (0..).map(|n| match (n, extension) {
+            (0, _) => directory.join(&name),
+            (n, Some(extension)) => directory.join(format!("{stem} ({n}).{extension}")),
+            (n, None) => directory.join(format!("{stem} ({n})")),
+        });
+        for path in candidates {
+            // The file system is checked without holding the lock, and other downloads are
+            // checked (and the path claimed) with it, so that no two downloads get the same path
+            if exists(&path).await || exists(&part_path(&path)).await {
+                continue;
+            }
+            let mut downloads = self.lock();
+            let is_taken = downloads
+                .values()
+                .any(|download| download.info.path.as_deref() == Some(path.as_path()));
+            if is_taken {
+                continue;
+            }
+            if let Some(download) = downloads.get_mut(&id) {
+                download.info.path = Some(path.clone());
+            }
+            return path;
+        }
+        unreachable!("there are infinitely many candidates")
+    }
+}
+
+async fn exists(path: &Path) -> bool {
+    tokio::fs::try_exists(path).await.unwrap_or(false)
+}
+
+/// Where a download is written until it completes
+fn part_path(path: &Path) -> PathBuf {
+    let mut part_path = path.as_os_str().to_owned();
+    part_path.push(".part");
+    PathBuf::from(part_path)
+}
+
+/// A strong `ETag`, or else the `Last-Modified` date, which `If-Range` can check that a resource
+/// hasn't changed with
+fn validator_of(headers: &HeaderMap) -> Option<HeaderValue> {
+    let etag = headers.get(header::ETAG);
+    let strong_etag = etag.filter(|etag| !etag.as_bytes().starts_with(b"W/"));
+    strong_etag.or_else(|| headers.get(header::LAST_MODIFIED)).cloned()
+}
+
+/// The file name suggested by a `Content-Disposition` header (preferring its `filename*`)
+fn content_disposition_file_name(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::CONTENT_DISPOSITION)?.to_str().ok()?;
+    let mut file_name = None;
+    for param in value.split(';').skip(1) {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("filename*") {
+            // `charset'language'percent-encoded-name`, where only UTF-8 is worth supporting
+            if let Some(encoded) = value.splitn(3, '\'').nth(2) {
+                return Some(percent_decode(encoded));
+            }
+        }
+        if name.eq_ignore_ascii_case("filename") {
+            file_name = Some(value.trim_matches('"').to_string());
+        }
+    }
+    file_name
+}
+
+/// The last segment of the path of `url`
+fn url_file_name(url: &Url) -> Option<String> {
+    let segment = url.path_segments()?.next_back()?;
+    (!segment.is_empty()).then(|| percent_decode(segment))
+}
+
+fn percent_decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes.get(index + 1..index + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        let byte = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(byte) if bytes[index] == b'%' => {
+                decoded.push(byte);
+                index += 3;
+            }
+            _ => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Make a suggested file name safe to save to: without directories, characters which some file
+/// systems don't allow, or a leading `.` (which would hide it)
+fn sanitize_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        "download".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blitz_traits::navigation::NavigationOptions;
+    use blitz_traits::net::NetProvider as _;
+    use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+    use super::*;
+    use crate::tests::{provider, response, serve_requests};
+    use crate::{OriginPermissions, PolicyViolation, SecurityPolicy};
+
+    const BODY: &str = "0123456789";
+
+    /// Serve `BODY` (honoring ranges), returning its URL and the heads of the requests for it.
+    /// If `cut_short`, only the first four bytes of the first response are sent.
+    async fn serve_body(cut_short: bool) -> (Url, Arc<Mutex<Vec<String>>>) {
+        let heads = Arc::new(Mutex::new(Vec::new()));
+        let heads_clone = Arc::clone(&heads);
+        let server = serve_requests(move |head| {
+            let mut heads = heads_clone.lock().unwrap();
+            heads.push(head.to_ascii_lowercase());
+            let start = heads.last().unwrap().lines().find_map(|line| {
+                let start = line.strip_prefix("range: bytes=")?.strip_suffix('-')?;
+                start.parse::<usize>().ok()
+            });
+            let etag = ("ETag", "\"v1\"");
+            match start {
+                Some(start) => response("206 Partial Content", &[etag], &BODY[start..]),
+                None if cut_short && heads.len() == 1 => {
+                    let length = BODY.len();
+                    let head = "HTTP/1.1 200 OK\r\nConnection: close\r\nETag: \"v1\"\r\n";
+                    format!("{head}Content-Length: {length}\r\n\r\n{}", &BODY[..4])
+                }
+                None => response("200 OK", &[etag], BODY),
+            }
+        })
+        .await;
+        (server.join("files/digits.txt").unwrap(), heads)
+    }
+
+    /// A manager which saves downloads to a new directory, and the updates its listener is told
+    fn manager() -> (Arc<DownloadManager>, PathBuf, UnboundedReceiver<DownloadInfo>) {
+        let downloads = Arc::clone(provider(SecurityPolicy::default()).downloads());
+        let directory = std::env::temp_dir().join(format!("blitz-downloads-{}", fastrand::u64(..)));
+        downloads.set_directory(&directory);
+        let (sender, updates) = unbounded_channel();
+        downloads.set_listener(move |info| {
+            let _ = sender.send(info.clone());
+        });
+        (downloads, directory, updates)
+    }
+
+    async fn wait_for(
+        updates: &mut UnboundedReceiver<DownloadInfo>,
+        state: impl Fn(&DownloadState) -> bool,
+    ) -> DownloadInfo {
+        loop {
+            let info = updates.recv().await.unwrap();
+            if state(&info.state) {
+                return info;
+            }
+        }
+    }
+
+    fn failed(state: &DownloadState) -> bool {
+        matches!(state, DownloadState::Failed(_))
+    }
+
+    fn completed(state: &DownloadState) -> bool {
+        *state == DownloadState::Completed
+    }
+
+    #[tokio::test]
+    async fn failed_downloads_resume_with_ranges() {
+        let (url, heads) = serve_body(true).await;
+        let (downloads, directory, mut updates) = manager();
+
+        let id = downloads.start(None, Request::get(url), None).unwrap();
+        let info = wait_for(&mut updates, failed).await;
+        assert_eq!(info.received, 4);
+        let path = info.path.unwrap();
+        assert_eq!(path, directory.join("digits.txt"));
+
+        downloads.resume(id);
+        let info = wait_for(&mut updates, completed).await;
+        assert_eq!(info.total, Some(BODY.len() as u64));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), BODY);
+        let resumed = heads.lock().unwrap()[1].clone();
+        assert!(resumed.contains("range: bytes=4-\r\n"));
+        assert!(resumed.contains("if-range: \"v1\"\r\n"));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn downloads_can_be_paused_resumed_and_cancelled() {
+        let (url, _) = serve_body(false).await;
+        let (downloads, directory, mut updates) = manager();
+
+        // The download's task only runs once the test waits
+        let id = downloads.start(None, Request::get(url), None).unwrap();
+        downloads.pause(id);
+        assert_eq!(downloads.download(id).unwrap().state, DownloadState::Paused);
+        downloads.resume(id);
+        let info = wait_for(&mut updates, completed).await;
+        assert_eq!(std::fs::read_to_string(info.path.unwrap()).unwrap(), BODY);
+        downloads.cancel(id);
+        assert_eq!(downloads.download(id).unwrap().state, DownloadState::Completed);
+
+        // Another download of the file doesn't replace it, and cancelling it deletes what it had
+        // downloaded
+        let (url, _) = serve_body(true).await;
+        let id = downloads.start(None, Request::get(url), None).unwrap();
+        let path = wait_for(&mut updates, failed).await.path.unwrap();
+        assert_eq!(path, directory.join("digits (1).txt"));
+        assert!(part_path(&path).exists());
+        downloads.cancel(id);
+        assert!(!part_path(&path).exists());
+        downloads.resume(id);
+        assert_eq!(downloads.download(id).unwrap().state, DownloadState::Cancelled);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn downloads_are_checked_as_requests_of_the_document() {
+        let provider = provider(SecurityPolicy::default().with_default_permissions(
+            OriginPermissions {
+                cross_origin: false,
+                local_files: false,
+            },
+        ));
+        let document = Url::parse("https://example.com/").unwrap();
+        provider.set_document_url(1, Some(&document));
+
+        // Links with a `download` attribute may navigate to other origins, but not download from
+        // them
+        let url = Url::parse("https://other.example/archive.zip").unwrap();
+        let link = NavigationOptions::new(url, "text/plain".to_string(), 1);
+        let result = provider.downloads().start(Some(1), link.into_request(), None);
+        assert!(matches!(result, Err(ProviderError::Blocked(PolicyViolation::CrossOrigin(_)))));
+    }
+
+    #[test]
+    fn file_names() {
+        let mut headers = HeaderMap::new();
+        let disposition = concat!(
+            "attachment; filename=\"report.pdf\"; ",
+            "filename*=UTF-8''r%C3%A9sum%C3%A9.pdf",
+        );
+        headers.insert(header::CONTENT_DISPOSITION, HeaderValue::from_static(disposition));
+        assert_eq!(content_disposition_file_name(&headers).as_deref(), Some("résumé.pdf"));
+
+        let url = Url::parse("https://example.com/files/My%20Archive.zip?v=2").unwrap();
+        assert_eq!(url_file_name(&url).as_deref(), Some("My Archive.zip"));
+        assert_eq!(url_file_name(&Url::parse("https://example.com/").unwrap()), None);
+
+        assert_eq!(sanitize_file_name("../../.bashrc"), "bashrc");
+        assert_eq!(sanitize_file_name("a:b?.txt"), "a_b_.txt");
+        assert_eq!(sanitize_file_name(".."), "download");
+    }
+}
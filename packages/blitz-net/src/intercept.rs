@@ -0,0 +1,173 @@
+//! Intercepting requests before they're fetched
+//!
+//! [`RequestInterceptor`]s added with
+//! [`Provider::with_request_interceptor`](crate::Provider::with_request_interceptor) see every
+//! request the provider fetches for documents (and for [`Provider::fetch_async`] and friends),
+//! whatever its scheme, and can answer it themselves, send it somewhere else, change it, or fail
+//! it — much like a service worker. That's enough for offline fallbacks, mocking the network in
+//! tests, or schemes of the app's own.
+//!
+//! Interceptors run in the order they were added, each seeing the request as the previous one
+//! left it, until one doesn't continue with it. They run before the request is fetched from
+//! anywhere, but after it has been checked against the [`SecurityPolicy`](crate::SecurityPolicy),
+//! which isn't checked again: interceptors are trusted to send requests wherever they like.
+//! Preconnects are intercepted too (and skipped if they're answered or failed), but uploads and
+//! downloads aren't. Requests which are answered or failed are recorded in the provider's
+//! [`NetworkMetrics`] as they were made, marked as [intercepted](RequestMetrics::intercepted).
+//!
+//! [`Provider::fetch_async`]: crate::Provider::fetch_async
+//! [`NetworkMetrics`]: crate::NetworkMetrics
+//! [`RequestMetrics`]: crate::RequestMetrics
+
+use std::sync::Arc;
+
+use blitz_traits::net::{Bytes, Request, Url};
+
+use crate::ProviderError;
+use crate::metrics::NetworkMetrics;
+
+/// What a [`RequestInterceptor`] does with a request
+#[derive(Debug)]
+pub enum Interception {
+    /// Carry on with the request (which may have been changed)
+    Continue(Request),
+    /// Answer the request with a synthetic response body
+    Respond(Bytes),
+    /// Fetch the URL instead, with a plain `GET` request (like a `303 See Other` redirect)
+    Redirect(Url),
+    /// Fail the request with the given message
+    Fail(String),
+}
+
+/// Sees requests before they're fetched, see the [module docs](self)
+pub trait RequestInterceptor: Send + Sync + 'static {
+    /// Intercept `request`, made for the document `doc_id` (if any)
+    fn intercept(&self, doc_id: Option<usize>, request: Request) -> Interception;
+}
+
+impl<F> RequestInterceptor for F
+where
+    F: Fn(Option<usize>, Request) -> Interception + Send + Sync + 'static,
+{
+    fn intercept(&self, doc_id: Option<usize>, request: Request) -> Interception {
+        self(doc_id, request)
+    }
+}
+
+/// What became of a request once it was intercepted
+pub(crate) enum Intercepted {
+    /// It should be fetched
+    Request(Request),
+    /// It was answered, with the given URL and body
+    Response(String, Bytes),
+}
+
+/// Pass `request` (made for the document `doc_id`, if any) through `interceptors` in order,
+/// recording it in `metrics` if it's answered or failed
+pub(crate) fn intercept(
+    interceptors: &[Arc<dyn RequestInterceptor>],
+    metrics: &Arc<NetworkMetrics>,
+    doc_id: Option<usize>,
+    mut request: Request,
+) -> Result<Intercepted, ProviderError> {
+    if interceptors.is_empty() {
+        return Ok(Intercepted::Request(request));
+    }
+    let original = request.clone();
+    for interceptor in interceptors {
+        let url = request.url.to_string();
+        let (priority, preload) = (request.priority, request.preload);
+        let navigation = request.navigation;
+        let result = match interceptor.intercept(doc_id, request) {
+            Interception::Continue(request) => Ok(Intercepted::Request(request)),
+            Interception::Respond(bytes) => Ok(Intercepted::Response(url, bytes)),
+            Interception::Redirect(url) => {
+                let mut request = Request::get(url);
+                request.priority = priority;
+                request.preload = preload;
+                request.navigation = navigation;
+                Ok(Intercepted::Request(request))
+            }
+            Interception::Fail(message) => Err(ProviderError::Intercepted(message)),
+        };
+        request = match result {
+            Ok(Intercepted::Request(request)) => request,
+            result => {
+                let recorder = metrics.start(doc_id, &original);
+                recorder.intercepted();
+                if let Ok(Intercepted::Response(_, bytes)) = &result {
+                    recorder.received(bytes.len());
+                }
+                recorder.finish(&result);
+                return result;
+            }
+        };
+    }
+    Ok(Intercepted::Request(request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blitz_traits::net::http::HeaderValue;
+
+    #[test]
+    fn interceptors_run_in_order() {
+        let metrics = Arc::new(NetworkMetrics::default());
+        let offline = |_: Option<usize>, request: Request| {
+            if request.headers.contains_key("x-offline") {
+                return Interception::Respond(Bytes::from_static(b"offline"));
+            }
+            Interception::Continue(request)
+        };
+        let interceptors: Vec<Arc<dyn RequestInterceptor>> = vec![
+            Arc::new(|_: Option<usize>, mut request: Request| {
+                if request.url.host_str() == Some("moved.example") {
+                    return Interception::Redirect(Url::parse("https://example.com/new").unwrap());
+                }
+                if request.url.path() == "/offline" {
+                    request.headers.insert("x-offline", HeaderValue::from_static("1"));
+                }
+                Interception::Continue(request)
+            }),
+            Arc::new(offline),
+        ];
+        let request = |url| Request::get(Url::parse(url).unwrap());
+
+        let offline = request("https://example.com/offline");
+        let result = intercept(&interceptors, &metrics, None, offline);
+        let Ok(Intercepted::Response(url, bytes)) = result else {
+            panic!("request wasn't answered");
+        };
+        assert_eq!(url, "https://example.com/offline");
+        assert_eq!(bytes, "offline");
+
+        let result = intercept(&interceptors, &metrics, Some(1), request("https://moved.example/"));
+        let Ok(Intercepted::Request(request)) = result else {
+            panic!("request wasn't redirected");
+        };
+        assert_eq!(request.url.as_str(), "https://example.com/new");
+
+        // Only the answered request is recorded, as it was made
+        let requests = metrics.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url.as_str(), "https://example.com/offline");
+        assert_eq!(requests[0].bytes_received, 7);
+        assert!(requests[0].intercepted && requests[0].is_complete());
+    }
+
+    #[test]
+    fn failed_requests_are_recorded() {
+        let metrics = Arc::new(NetworkMetrics::default());
+        let interceptors: Vec<Arc<dyn RequestInterceptor>> = vec![Arc::new(
+            |_: Option<usize>, _: Request| Interception::Fail("offline".to_string()),
+        )];
+        let request = Request::get(Url::parse("https://example.com/").unwrap());
+
+        let result = intercept(&interceptors, &metrics, Some(1), request);
+        assert!(matches!(result, Err(ProviderError::Intercepted(_))));
+        let requests = metrics.requests_for_document(1);
+        assert!(requests[0].intercepted);
+        assert!(requests[0].error.as_deref().unwrap().contains("offline"));
+    }
+}
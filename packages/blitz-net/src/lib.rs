@@ -1,48 +1,455 @@
-//! Networking (HTTP, filesystem, Data URIs) for Blitz
+//! Networking (HTTP, filesystem, Data URIs, blob URLs, and schemes of the app's own) for Blitz
 //!
 //! Provides an implementation of the [`blitz_traits::net::NetProvider`] trait.
 
-use std::sync::Arc;
+mod auth;
+mod bundle;
+mod compression;
+mod download;
+mod intercept;
+mod metrics;
+mod multipart;
+mod preload;
+mod scheduler;
+mod scheme;
+mod security;
 
-use blitz_traits::net::{BoxedHandler, Bytes, NetCallback, NetProvider, Request, SharedCallback};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use blitz_traits::blob::BlobRegistry;
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use blitz_traits::net::http::{HeaderValue, header};
+use blitz_traits::net::{
+    BoxedHandler, Bytes, Method, NetCallback, NetProvider, Request, SharedCallback, Url,
+};
 use data_url::DataUrl;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use tokio::{
     runtime::Handle,
     sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+    sync::watch,
+    task::AbortHandle,
+};
+
+pub use crate::auth::{AuthChallenge, AuthResponder, AuthScheme, ChallengeHandler, Credentials};
+use crate::auth::Authenticator;
+pub use crate::bundle::{AssetDirectory, BUNDLE_SCHEME, BundleProvider, strip_asset_hash};
+pub use crate::compression::ContentEncoding;
+use crate::compression::{content_encodings, decode_all, load_precompressed};
+pub use crate::download::{
+    DownloadId, DownloadInfo, DownloadListener, DownloadManager, DownloadState,
 };
+pub use crate::intercept::{Interception, RequestInterceptor};
+use crate::intercept::{Intercepted, intercept};
+pub use crate::metrics::{NetworkMetrics, RequestMetrics};
+use crate::metrics::RequestRecorder;
+pub use crate::multipart::{MultipartForm, Part, UploadProgressCallback};
+use crate::preload::{PreloadCache, preloaded_bytes};
+use crate::scheduler::Scheduler;
+pub use crate::scheme::SchemeHandler;
+use crate::scheme::{BUILT_IN_SCHEMES, SchemeHandlers};
+pub use crate::security::{MixedContent, OriginPermissions, PolicyViolation, SecurityPolicy};
 
-const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:60.0) Gecko/20100101 Firefox/81.0";
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:60.0) Gecko/20100101 Firefox/81.0";
+/// The number of redirects followed before a request fails (the same as reqwest's default)
+const MAX_REDIRECTS: usize = 10;
+/// The number of times a request is retried with new credentials before its `401` is returned
+const MAX_AUTH_RETRIES: usize = 3;
+/// The size below which request bodies aren't worth compressing
+const MIN_COMPRESSED_BODY_SIZE: usize = 1024;
 
 pub struct Provider<D> {
     rt: Handle,
-    client: Client,
+    client: HttpClient,
     resource_callback: SharedCallback<D>,
+    /// The tasks fetching resources for each document, so that they can be cancelled
+    tasks: Mutex<HashMap<usize, Vec<AbortHandle>>>,
+    /// The `User-Agent` overrides set with [`NetProvider::set_user_agent`], by document
+    user_agents: Mutex<HashMap<usize, HeaderValue>>,
+    /// Holds back requests while requests of a higher priority are in flight
+    scheduler: Arc<Scheduler>,
+    /// The responses to preloads, until the resources are requested again (or they're evicted by
+    /// the global [`CacheCoordinator`])
+    preloads: Arc<PreloadCache>,
+    /// Loads the resources bundled with the app, which have `dioxus:` URLs
+    bundle: Option<Arc<dyn BundleProvider>>,
+    /// Whether HTTP requests are made over HTTP/3 (which needs the `http3` feature)
+    http3: bool,
+    /// The encodings HTTP responses may use
+    accept_encoding: Vec<ContentEncoding>,
+    /// Created when it's first used, once the provider has been set up
+    downloads: OnceLock<Arc<DownloadManager>>,
 }
 impl<D: 'static> Provider<D> {
     pub fn new(resource_callback: SharedCallback<D>) -> Self {
-        #[cfg(feature = "cookies")]
-        let client = Client::builder().cookie_store(true).build().unwrap();
-        #[cfg(not(feature = "cookies"))]
-        let client = Client::new();
+        let accept_encoding: Vec<_> = ContentEncoding::supported().collect();
+        let client = HttpClient {
+            client: build_client(false, &accept_encoding),
+            policy: Arc::new(SecurityPolicy::default()),
+            document_urls: Arc::default(),
+            auth: Arc::default(),
+            metrics: Arc::default(),
+            request_compression: None,
+            interceptors: Vec::new(),
+            schemes: Arc::default(),
+        };
+
+        let preloads = Arc::new(PreloadCache::default());
+        let managed_preloads: Arc<dyn ManagedCache> = preloads.clone();
+        CacheCoordinator::global().register(&managed_preloads, 1);
 
         Self {
             rt: Handle::current(),
             client,
             resource_callback,
+            tasks: Mutex::new(HashMap::new()),
+            user_agents: Mutex::new(HashMap::new()),
+            scheduler: Arc::new(Scheduler::default()),
+            preloads,
+            bundle: None,
+            http3: false,
+            accept_encoding,
+            downloads: OnceLock::new(),
         }
     }
+    /// Load the resources bundled with the app (which have `dioxus:` URLs) from `bundle`
+    pub fn with_bundle_provider(mut self, bundle: Arc<dyn BundleProvider>) -> Self {
+        self.bundle = Some(bundle);
+        self
+    }
+    /// Only fetch what `policy` allows
+    pub fn with_security_policy(mut self, policy: SecurityPolicy) -> Self {
+        self.client.policy = Arc::new(policy);
+        self
+    }
+    /// Make HTTP requests over HTTP/3, assuming that servers support it (since there is no
+    /// fallback to earlier versions). reqwest's HTTP/3 support is unstable, so this also needs
+    /// building with `RUSTFLAGS="--cfg reqwest_unstable"`.
+    #[cfg(feature = "http3")]
+    pub fn with_http3_prior_knowledge(mut self) -> Self {
+        self.http3 = true;
+        self.rebuild_client();
+        self
+    }
+    /// Only accept HTTP responses compressed with `encodings` (by default, every encoding enabled
+    /// by blitz-net's features). Encodings which aren't enabled are ignored, and an empty list
+    /// asks servers not to compress responses at all.
+    pub fn with_accept_encoding(
+        mut self,
+        encodings: impl IntoIterator<Item = ContentEncoding>,
+    ) -> Self {
+        let encodings = encodings.into_iter().filter(|encoding| encoding.is_supported());
+        self.accept_encoding = encodings.collect();
+        self.rebuild_client();
+        self
+    }
+    /// Compress the bodies of HTTP requests (those large enough to be worth it, which don't have
+    /// a `Content-Encoding` already) with `encoding`. Requests fail if blitz-net's feature for the
+    /// encoding isn't enabled. The server must accept compressed requests.
+    pub fn with_request_compression(mut self, encoding: ContentEncoding) -> Self {
+        self.client.request_compression = Some(encoding);
+        self
+    }
+    /// Let `interceptor` answer, redirect, change or fail requests before they're fetched, after
+    /// any interceptors added before it (see [`RequestInterceptor`])
+    pub fn with_request_interceptor(mut self, interceptor: impl RequestInterceptor) -> Self {
+        self.client.interceptors.push(Arc::new(interceptor));
+        self
+    }
+    fn rebuild_client(&mut self) {
+        self.client.client = build_client(self.http3, &self.accept_encoding);
+    }
     pub fn shared(res_callback: SharedCallback<D>) -> Arc<dyn NetProvider<D>> {
         Arc::new(Self::new(res_callback))
     }
     pub fn is_empty(&self) -> bool {
         Arc::strong_count(&self.resource_callback) == 1
     }
+
+    /// The timings and sizes of the requests made by the provider, for devtools
+    pub fn metrics(&self) -> &Arc<NetworkMetrics> {
+        &self.client.metrics
+    }
+
+    /// Saves resources to disk, with the provider's client and security policy
+    pub fn downloads(&self) -> &Arc<DownloadManager> {
+        self.downloads.get_or_init(|| {
+            Arc::new(DownloadManager::new(self.rt.clone(), self.client.clone()))
+        })
+    }
+
+    /// Load the resources of URLs with the scheme `scheme` (like `app`) with `handler`, replacing
+    /// any handler registered for it before. Documents navigate to the scheme's URLs, and resolve
+    /// relative URLs against them, like any other. Requests with the scheme must be allowed by the
+    /// security policy (if it limits the schemes documents may fetch).
+    ///
+    /// Panics if `scheme` is one the provider loads itself, like `https`.
+    pub fn register_scheme(&self, scheme: &str, handler: impl SchemeHandler) {
+        let scheme = scheme.to_ascii_lowercase();
+        assert!(
+            !BUILT_IN_SCHEMES.contains(&scheme.as_str()),
+            "the {scheme}: scheme can't have a handler"
+        );
+        self.client.schemes.insert(scheme, Arc::new(handler));
+    }
+
+    /// Answer challenges from the origin of `url` (in any realm) with `credentials`, or forget
+    /// the origin's credentials if `None`. They're sent once the origin has challenged for them.
+    pub fn set_credentials(&self, url: &Url, credentials: Option<Credentials>) {
+        self.client.auth.set_credentials(url, credentials);
+    }
+
+    /// Ask `handler` for credentials when a server challenges a request which the provider has
+    /// no credentials for (or whose credentials the server rejected), or which would send them in
+    /// the clear. Without a handler, such challenges are refused.
+    pub fn set_auth_challenge_handler(
+        &self,
+        handler: impl Fn(AuthChallenge, AuthResponder) + Send + Sync + 'static,
+    ) {
+        self.client.auth.set_handler(Some(Arc::new(handler)));
+    }
+
+    fn track_task(&self, doc_id: usize, task: AbortHandle) {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|err| err.into_inner());
+        let doc_tasks = tasks.entry(doc_id).or_default();
+        doc_tasks.retain(|task| !task.is_finished());
+        doc_tasks.push(task);
+    }
+
+    /// Set the document's `User-Agent` override on `request`, unless it has its own already
+    fn apply_user_agent(&self, doc_id: usize, request: &mut Request) {
+        if request.headers.contains_key(header::USER_AGENT) {
+            return;
+        }
+        let user_agents = self.user_agents.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(user_agent) = user_agents.get(&doc_id) {
+            request.headers.insert(header::USER_AGENT, user_agent.clone());
+        }
+    }
+
+    /// Check `request` (made for the document `doc_id`, if any) against the security policy,
+    /// which may upgrade its URL to `https:`
+    fn check_policy(
+        &self,
+        doc_id: Option<usize>,
+        request: &mut Request,
+    ) -> Result<(), ProviderError> {
+        let document_url = self.client.document_url(doc_id);
+        let navigation = request.navigation;
+        let checked = self.client.check_policy(document_url.as_ref(), navigation, &mut request.url);
+        let Err(violation) = checked else {
+            return Ok(());
+        };
+        #[cfg(feature = "tracing")]
+        tracing::warn!(doc_id, url = %request.url, %violation, "Fetch blocked by security policy");
+        #[cfg(not(feature = "tracing"))]
+        eprintln!("Fetch of {} blocked by security policy: {violation}", request.url);
+        Err(ProviderError::Blocked(violation))
+    }
 }
+
+/// The HTTP client, along with the credentials it sends and the metrics of the requests it makes,
+/// and what answers requests instead of it (interceptors, and handlers of the app's own schemes)
+#[derive(Clone)]
+struct HttpClient {
+    client: Client,
+    /// What documents may fetch, checked before every request and every redirect
+    policy: Arc<SecurityPolicy>,
+    /// The URLs set with [`NetProvider::set_document_url`], by document
+    document_urls: Arc<Mutex<HashMap<usize, Url>>>,
+    auth: Arc<Authenticator>,
+    metrics: Arc<NetworkMetrics>,
+    /// The encoding request bodies are compressed with (if any)
+    request_compression: Option<ContentEncoding>,
+    /// See requests before they're fetched, in order
+    interceptors: Vec<Arc<dyn RequestInterceptor>>,
+    /// Load the resources of URLs with schemes of the app's own
+    schemes: Arc<SchemeHandlers>,
+}
+
+impl HttpClient {
+    /// The URL of the document `doc_id`, if there is one and its URL has been set
+    fn document_url(&self, doc_id: Option<usize>) -> Option<Url> {
+        let document_urls = self.document_urls.lock().unwrap_or_else(|err| err.into_inner());
+        doc_id.and_then(|doc_id| document_urls.get(&doc_id).cloned())
+    }
+
+    /// Check a request for `url` made by the document at `document_url` (if any) against the
+    /// security policy. Navigations are only checked with [`SecurityPolicy::check_navigation`], as
+    /// they may go to other origins.
+    fn check_policy(
+        &self,
+        document_url: Option<&Url>,
+        navigation: bool,
+        url: &mut Url,
+    ) -> Result<(), PolicyViolation> {
+        if navigation {
+            self.policy.check_navigation(document_url, url)
+        } else {
+            self.policy.check(document_url, url)
+        }
+    }
+
+    /// Send an HTTP(S) request (for the document `doc_id`, if any), compressing its body if the
+    /// provider was set up to (see [`Provider::with_request_compression`])
+    async fn send(
+        &self,
+        doc_id: Option<usize>,
+        mut request: Request,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let mut body = request.body.clone();
+        let compress = body.len() >= MIN_COMPRESSED_BODY_SIZE
+            && !request.headers.contains_key(header::CONTENT_ENCODING);
+        if let Some(encoding) = self.request_compression.filter(|_| compress) {
+            body = Bytes::from(encoding.encode(&body)?);
+            let content_encoding = HeaderValue::from_static(encoding.token());
+            request.headers.insert(header::CONTENT_ENCODING, content_encoding);
+        }
+        self.send_body(doc_id, request, || reqwest::Body::from(body.clone())).await
+    }
+
+    /// Send an HTTP(S) request (for the document `doc_id`, if any) with the body made by `body`
+    /// (rather than the request's own body), returning the response once its headers have been
+    /// received.
+    ///
+    /// Redirects are followed once they've been checked against the security policy as requests
+    /// of the document's own. Requests which are challenged with `401 Unauthorized` are retried
+    /// with credentials (and a new body), unless they have an `Authorization` header of their own.
+    async fn send_body(
+        &self,
+        doc_id: Option<usize>,
+        request: Request,
+        body: impl Fn() -> reqwest::Body,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let Request {
+            mut url,
+            mut method,
+            mut headers,
+            navigation,
+            ..
+        } = request;
+        if !headers.contains_key(header::USER_AGENT) {
+            headers.insert(header::USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+        }
+        let document_url = self.document_url(doc_id);
+
+        let mut has_body = true;
+        let mut redirects = 0;
+        let mut retries = 0;
+        loop {
+            let own_authorization = headers.contains_key(header::AUTHORIZATION);
+            let mut request_headers = headers.clone();
+            let mut sent_credentials = false;
+            if !own_authorization {
+                if let Some(authorization) = self.auth.authorization(&method, &url) {
+                    request_headers.insert(header::AUTHORIZATION, authorization);
+                    sent_credentials = true;
+                }
+            }
+
+            let mut builder = self.client.request(method.clone(), url.clone());
+            builder = builder.headers(request_headers);
+            if has_body {
+                builder = builder.body(body());
+            }
+            let response = builder.send().await?;
+            let status = response.status();
+
+            if let Some(mut location) = redirect_location(&url, &response) {
+                if redirects == MAX_REDIRECTS {
+                    return Err(std::io::Error::other("Too many redirects").into());
+                }
+                redirects += 1;
+                self.check_policy(document_url.as_ref(), navigation, &mut location)?;
+                if location.origin() != url.origin() {
+                    headers.remove(header::AUTHORIZATION);
+                    headers.remove(header::COOKIE);
+                    headers.remove(header::PROXY_AUTHORIZATION);
+                }
+                // Forms posted to a URL which moved are fetched from the new URL with a `GET`
+                let see_other = status == StatusCode::SEE_OTHER && method != Method::HEAD;
+                let moved = matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND);
+                if see_other || (moved && method == Method::POST) {
+                    method = Method::GET;
+                    has_body = false;
+                    headers.remove(header::CONTENT_TYPE);
+                    headers.remove(header::CONTENT_LENGTH);
+                    headers.remove(header::CONTENT_ENCODING);
+                }
+                url = location;
+                continue;
+            }
+
+            if own_authorization
+                || retries == MAX_AUTH_RETRIES
+                || status != StatusCode::UNAUTHORIZED
+            {
+                return Ok(response);
+            }
+            if !self.auth.answer(&url, response.headers(), sent_credentials).await {
+                return Ok(response);
+            }
+            retries += 1;
+        }
+    }
+}
+
+/// Where `response` (to a request for `url`) redirects to, if it's a redirect
+fn redirect_location(url: &Url, response: &reqwest::Response) -> Option<Url> {
+    let redirect = matches!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    );
+    if !redirect {
+        return None;
+    }
+    let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
 impl<D: 'static> Provider<D> {
+    /// Fetch `request` (for the document `doc_id`, if any), unless an interceptor answers it
     async fn fetch_inner(
-        client: Client,
+        client: HttpClient,
+        bundle: Option<Arc<dyn BundleProvider>>,
+        doc_id: Option<usize>,
+        request: Request,
+    ) -> Result<(String, Bytes), ProviderError> {
+        match intercept(&client.interceptors, &client.metrics, doc_id, request)? {
+            Intercepted::Request(request) => {
+                Self::fetch_recorded(client, bundle, doc_id, request).await
+            }
+            Intercepted::Response(url, bytes) => Ok((url, bytes)),
+        }
+    }
+
+    /// Fetch `request` (for the document `doc_id`, if any), recording it in the metrics
+    async fn fetch_recorded(
+        client: HttpClient,
+        bundle: Option<Arc<dyn BundleProvider>>,
+        doc_id: Option<usize>,
         request: Request,
+    ) -> Result<(String, Bytes), ProviderError> {
+        let recorder = client.metrics.start(doc_id, &request);
+        let result = Self::load(client, bundle, doc_id, request, &recorder).await;
+        if let Ok((_, bytes)) = &result {
+            recorder.received(bytes.len());
+        }
+        recorder.finish(&result);
+        result
+    }
+
+    async fn load(
+        client: HttpClient,
+        bundle: Option<Arc<dyn BundleProvider>>,
+        doc_id: Option<usize>,
+        request: Request,
+        recorder: &RequestRecorder,
     ) -> Result<(String, Bytes), ProviderError> {
         Ok(match request.url.scheme() {
             "data" => {
@@ -51,109 +458,417 @@ impl<D: 'static> Provider<D> {
                 (request.url.to_string(), Bytes::from(decoded.0))
             }
             "file" => {
-                let file_content = std::fs::read(request.url.path())?;
-                (request.url.to_string(), Bytes::from(file_content))
+                let file_content = load_precompressed(request.url.path(), |path| {
+                    std::fs::read(path).map(Bytes::from)
+                })?;
+                (request.url.to_string(), file_content)
             }
-            _ => {
-                let response = client
-                    .request(request.method, request.url)
-                    .headers(request.headers)
-                    .header("User-Agent", USER_AGENT)
-                    .body(request.body)
-                    .send()
-                    .await?;
-
-                (response.url().to_string(), response.bytes().await?)
+            "blob" => {
+                let blob = BlobRegistry::global().resolve(&request.url).ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "Revoked blob URL")
+                })?;
+                (request.url.to_string(), blob.bytes)
             }
+            BUNDLE_SCHEME => {
+                let bundle = bundle.ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "No bundle provider")
+                })?;
+                let path = bundle::decode_path(request.url.path())?;
+                (request.url.to_string(), load_precompressed(&path, |path| bundle.load(path))?)
+            }
+            scheme => match client.schemes.get(scheme) {
+                Some(handler) => (request.url.to_string(), handler.load(&request)?),
+                None => {
+                    let response = client.send(doc_id, request).await?;
+                    Self::read_response(response, recorder).await?
+                }
+            },
         })
     }
 
+    /// Read the body of `response`, returning it along with the URL of the response
+    async fn read_response(
+        response: reqwest::Response,
+        recorder: &RequestRecorder,
+    ) -> Result<(String, Bytes), ProviderError> {
+        recorder.response(&response);
+        // Encodings which reqwest didn't decode, because they weren't accepted
+        let encodings = content_encodings(response.headers())?;
+        let url = response.url().to_string();
+        Ok((url, decode_all(&encodings, response.bytes().await?)?))
+    }
+
+    async fn stream_inner(
+        client: HttpClient,
+        bundle: Option<Arc<dyn BundleProvider>>,
+        doc_id: Option<usize>,
+        request: Request,
+        sender: &UnboundedSender<Result<ResponseChunk, ProviderError>>,
+    ) -> Result<(), ProviderError> {
+        let request = match intercept(&client.interceptors, &client.metrics, doc_id, request)? {
+            Intercepted::Request(request) => request,
+            Intercepted::Response(response_url, bytes) => {
+                let _ = sender.send(Ok(ResponseChunk::Url(response_url)));
+                let _ = sender.send(Ok(ResponseChunk::Body(bytes)));
+                return Ok(());
+            }
+        };
+        if !matches!(request.url.scheme(), "http" | "https") {
+            let (response_url, bytes) =
+                Self::fetch_recorded(client, bundle, doc_id, request).await?;
+            let _ = sender.send(Ok(ResponseChunk::Url(response_url)));
+            let _ = sender.send(Ok(ResponseChunk::Body(bytes)));
+            return Ok(());
+        }
+
+        let recorder = client.metrics.start(doc_id, &request);
+        let result = async {
+            let mut response = client.send(doc_id, request).await?;
+            recorder.response(&response);
+            let _ = sender.send(Ok(ResponseChunk::Url(response.url().to_string())));
+            let encodings = content_encodings(response.headers())?;
+            if !encodings.is_empty() {
+                // reqwest didn't decode the body (as the encoding wasn't accepted), so it's
+                // decoded whole instead
+                let bytes = decode_all(&encodings, response.bytes().await?)?;
+                recorder.received(bytes.len());
+                let _ = sender.send(Ok(ResponseChunk::Body(bytes)));
+                return Ok(());
+            }
+            while let Some(chunk) = response.chunk().await? {
+                recorder.received(chunk.len());
+                if sender.send(Ok(ResponseChunk::Body(chunk))).is_err() {
+                    // The receiver was dropped, so nobody wants the rest of the body
+                    break;
+                }
+            }
+            Ok::<_, ProviderError>(())
+        }
+        .await;
+        recorder.finish(&result);
+        result
+    }
+
     async fn fetch_with_handler(
-        client: Client,
+        client: HttpClient,
+        bundle: Option<Arc<dyn BundleProvider>>,
+        scheduler: Arc<Scheduler>,
         doc_id: usize,
         request: Request,
         handler: BoxedHandler<D>,
         res_callback: SharedCallback<D>,
+        preload: Option<watch::Sender<Option<Bytes>>>,
     ) -> Result<(), ProviderError> {
-        let (_response_url, bytes) = Self::fetch_inner(client, request).await?;
-        handler.bytes(doc_id, bytes, res_callback);
-        Ok(())
+        let (priority, holds_back) = (request.priority, !request.preload);
+        let fetch = async move {
+            let (_response_url, bytes) =
+                Self::fetch_inner(client, bundle, Some(doc_id), request).await?;
+            if let Some(preload) = preload {
+                preload.send_replace(Some(bytes.clone()));
+                CacheCoordinator::global().enforce_budget();
+            }
+            handler.bytes(doc_id, bytes, res_callback);
+            Ok(())
+        };
+        scheduler.run(priority, holds_back, fetch).await
     }
 
+    /// Fetch `request`, calling `callback` with the URL of the response and its body. The request
+    /// is made for the document `doc_id` (and checked against the security policy as the
+    /// document's own), or for none if `None` (as when loading a new document).
     #[allow(clippy::type_complexity)]
     pub fn fetch_with_callback(
         &self,
-        request: Request,
+        doc_id: Option<usize>,
+        mut request: Request,
         callback: Box<dyn FnOnce(Result<(String, Bytes), ProviderError>) + Send + Sync + 'static>,
     ) {
+        if let Err(error) = self.check_policy(doc_id, &mut request) {
+            callback(Err(error));
+            return;
+        }
         let client = self.client.clone();
-        self.rt.spawn(async move {
+        let bundle = self.bundle.clone();
+        #[cfg(feature = "tracing")]
+        let span = fetch_span(doc_id, &request);
+        let task = async move {
             let url = request.url.to_string();
-            let result = Self::fetch_inner(client, request).await;
-            if let Err(e) = &result {
-                eprintln!("Error fetching {url}: {e:?}");
-            } else {
-                println!("Success {url}");
-            }
+            let result = Self::fetch_inner(client, bundle, doc_id, request).await;
+            log_fetch_result(&url, &result);
             callback(result);
-        });
+        };
+        #[cfg(feature = "tracing")]
+        let task = tracing::Instrument::instrument(task, span);
+        self.rt.spawn(task);
+    }
+
+    /// Fetch `request`, sending each chunk of the response body to the returned receiver as it
+    /// arrives, so that it can be processed (an HTML document parsed, say) before the whole body
+    /// has been downloaded. The URL of the response is sent before the body. The channel is closed
+    /// once the body has been received, or after an error has been sent. `doc_id` is as for
+    /// [`fetch_with_callback`](Self::fetch_with_callback).
+    pub fn fetch_stream(
+        &self,
+        doc_id: Option<usize>,
+        mut request: Request,
+    ) -> UnboundedReceiver<Result<ResponseChunk, ProviderError>> {
+        let (sender, receiver) = unbounded_channel();
+        if let Err(error) = self.check_policy(doc_id, &mut request) {
+            let _ = sender.send(Err(error));
+            return receiver;
+        }
+        let client = self.client.clone();
+        let bundle = self.bundle.clone();
+        #[cfg(feature = "tracing")]
+        let span = fetch_span(doc_id, &request);
+        let task = async move {
+            let url = request.url.to_string();
+            let result = Self::stream_inner(client, bundle, doc_id, request, &sender).await;
+            log_fetch_result(&url, &result);
+            if let Err(e) = result {
+                let _ = sender.send(Err(e));
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let task = tracing::Instrument::instrument(task, span);
+        self.rt.spawn(task);
+        receiver
     }
 
-    pub async fn fetch_async(&self, request: Request) -> Result<(String, Bytes), ProviderError> {
+    /// Fetch `request`, returning the URL of the response and its body. `doc_id` is as for
+    /// [`fetch_with_callback`](Self::fetch_with_callback).
+    pub async fn fetch_async(
+        &self,
+        doc_id: Option<usize>,
+        mut request: Request,
+    ) -> Result<(String, Bytes), ProviderError> {
+        self.check_policy(doc_id, &mut request)?;
         let client = self.client.clone();
         let url = request.url.to_string();
-        let result = Self::fetch_inner(client, request).await;
-        if let Err(e) = &result {
-            eprintln!("Error fetching {url}: {e:?}");
-        } else {
-            println!("Success {url}");
+        #[cfg(feature = "tracing")]
+        let span = fetch_span(doc_id, &request);
+        let fetch = Self::fetch_inner(client, self.bundle.clone(), doc_id, request);
+        #[cfg(feature = "tracing")]
+        let fetch = tracing::Instrument::instrument(fetch, span);
+        let result = fetch.await;
+        log_fetch_result(&url, &result);
+        result
+    }
+
+    /// Send `form` as the body of `request` (which is usually a `POST`), streaming the form's
+    /// files from disk, and return the response. `doc_id` is as for
+    /// [`fetch_with_callback`](Self::fetch_with_callback).
+    pub async fn upload(
+        &self,
+        doc_id: Option<usize>,
+        mut request: Request,
+        form: MultipartForm,
+    ) -> Result<(String, Bytes), ProviderError> {
+        self.check_policy(doc_id, &mut request)?;
+        let client = self.client.clone();
+        let url = request.url.to_string();
+        #[cfg(feature = "tracing")]
+        let span = fetch_span(doc_id, &request);
+        let upload = Self::upload_inner(client, doc_id, request, form);
+        #[cfg(feature = "tracing")]
+        let upload = tracing::Instrument::instrument(upload, span);
+        let result = upload.await;
+        log_fetch_result(&url, &result);
+        result
+    }
+
+    async fn upload_inner(
+        client: HttpClient,
+        doc_id: Option<usize>,
+        mut request: Request,
+        form: MultipartForm,
+    ) -> Result<(String, Bytes), ProviderError> {
+        if !matches!(request.url.scheme(), "http" | "https") {
+            let message = format!("Forms can't be uploaded to {}", request.url);
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, message).into());
+        }
+        let content_length = form.content_length().await?;
+        let content_type = HeaderValue::from_str(&form.content_type())
+            .expect("form boundaries are alphanumeric");
+        request.headers.insert(header::CONTENT_TYPE, content_type);
+        request.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(content_length));
+
+        let recorder = client.metrics.start(doc_id, &request);
+        recorder.sent(content_length);
+        let result = async {
+            let body = || form.body(content_length);
+            let response = client.send_body(doc_id, request, body).await?;
+            Self::read_response(response, &recorder).await
+        }
+        .await;
+        if let Ok((_, bytes)) = &result {
+            recorder.received(bytes.len());
         }
+        recorder.finish(&result);
         result
     }
 }
 
 impl<D: 'static> NetProvider<D> for Provider<D> {
-    fn fetch(&self, doc_id: usize, request: Request, handler: BoxedHandler<D>) {
+    fn fetch(&self, doc_id: usize, mut request: Request, handler: BoxedHandler<D>) {
+        if let Err(error) = self.check_policy(Some(doc_id), &mut request) {
+            let error_msg = format!("Failed to fetch {}: {}", request.url, error);
+            self.resource_callback.call(doc_id, Err(Some(error_msg)));
+            return;
+        }
+        self.apply_user_agent(doc_id, &mut request);
         let client = self.client.clone();
+        let bundle = self.bundle.clone();
+        let scheduler = Arc::clone(&self.scheduler);
         let callback = Arc::clone(&self.resource_callback);
-        
+        let preloaded = self.preloads.take(doc_id, &request);
+        let preload = self.preloads.insert(doc_id, &request);
+
         #[cfg(feature = "tracing")]
-        tracing::debug!("Fetching {}", &request.url);
-        
-        self.rt.spawn(async move {
+        let span = fetch_span(Some(doc_id), &request);
+        let task = async move {
+            // Answer the request with the response to a preload of the same resource, unless the
+            // preload failed
+            let preloaded = match preloaded {
+                Some(response) => preloaded_bytes(response).await,
+                None => None,
+            };
+            if let Some(bytes) = preloaded {
+                handler.bytes(doc_id, bytes, callback);
+                return;
+            }
+
             let url = request.url.to_string();
-            let res = Self::fetch_with_handler(client, doc_id, request, handler, callback.clone()).await;
-            
+            let res = Self::fetch_with_handler(
+                client,
+                bundle,
+                scheduler,
+                doc_id,
+                request,
+                handler,
+                callback.clone(),
+                preload,
+            )
+            .await;
+
+            log_fetch_result(&url, &res);
             if let Err(e) = res {
-                // Structured logging with context
-                #[cfg(feature = "tracing")]
-                tracing::error!(
-                    url = %url,
-                    doc_id = doc_id,
-                    error = %e,
-                    "Network fetch failed"
-                );
-                
-                #[cfg(not(feature = "tracing"))]
-                eprintln!("Error fetching {url}: {e}");
-                
                 // Propagate error to callback consumers
                 let error_msg = format!("Failed to fetch {}: {}", url, e);
                 callback.call(doc_id, Err(Some(error_msg)));
-            } else {
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let task = tracing::Instrument::instrument(task, span);
+        let task = self.rt.spawn(task);
+        self.track_task(doc_id, task.abort_handle());
+    }
+
+    fn cancel(&self, doc_id: usize) {
+        let tasks = self
+            .tasks
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&doc_id);
+        for task in tasks.into_iter().flatten() {
+            task.abort();
+        }
+        self.preloads.clear(doc_id);
+    }
+
+    fn set_user_agent(&self, doc_id: usize, user_agent: Option<String>) {
+        let mut user_agents = self.user_agents.lock().unwrap_or_else(|err| err.into_inner());
+        match user_agent.map(HeaderValue::try_from) {
+            Some(Ok(user_agent)) => {
+                user_agents.insert(doc_id, user_agent);
+            }
+            Some(Err(_)) => {
                 #[cfg(feature = "tracing")]
-                tracing::debug!("Success {}", url);
+                tracing::warn!(doc_id, "Invalid User-Agent");
+                #[cfg(not(feature = "tracing"))]
+                eprintln!("Invalid User-Agent for document {doc_id}");
+            }
+            None => {
+                user_agents.remove(&doc_id);
+            }
+        }
+    }
+
+    fn set_document_url(&self, doc_id: usize, url: Option<&Url>) {
+        let document_urls = &self.client.document_urls;
+        let mut document_urls = document_urls.lock().unwrap_or_else(|err| err.into_inner());
+        match url {
+            Some(url) => {
+                document_urls.insert(doc_id, url.clone());
             }
+            None => {
+                document_urls.remove(&doc_id);
+            }
+        }
+    }
+
+    fn preconnect(&self, doc_id: usize, url: &Url) {
+        if !matches!(url.scheme(), "http" | "https") {
+            return;
+        }
+        let mut origin = url.clone();
+        origin.set_path("/");
+        origin.set_query(None);
+        origin.set_fragment(None);
+
+        let mut request = Request::get(origin);
+        if self.check_policy(Some(doc_id), &mut request).is_err() {
+            return;
+        }
+        let client = self.client.clone();
+        // Requests which interceptors answer (or send elsewhere than the web) don't need a
+        // connection
+        let intercepted = intercept(&client.interceptors, &client.metrics, Some(doc_id), request);
+        let request = match intercepted {
+            Ok(Intercepted::Request(request)) => request,
+            _ => return,
+        };
+        if !matches!(request.url.scheme(), "http" | "https") {
+            return;
+        }
+        let (Some(host), Some(port)) =
+            (request.url.host_str(), request.url.port_or_known_default())
+        else {
+            return;
+        };
+        let host = host.to_string();
+        // reqwest can't be handed a connection to pool, so no request is sent over this one: it
+        // only resolves the host and opens a route to it ahead of the requests which need it
+        let task = self.rt.spawn(async move {
+            let _ = tokio::net::TcpStream::connect((host.as_str(), port)).await;
         });
+        self.track_task(doc_id, task.abort_handle());
+    }
+
+    fn is_custom_scheme(&self, scheme: &str) -> bool {
+        self.client.schemes.get(scheme).is_some()
     }
 }
 
+/// Part of a response received with [`Provider::fetch_stream`]
+#[derive(Debug, Clone)]
+pub enum ResponseChunk {
+    /// The URL of the response, which differs from the request's if it was redirected
+    Url(String),
+    /// The next chunk of the body
+    Body(Bytes),
+}
+
 #[derive(Debug)]
 pub enum ProviderError {
     Io(std::io::Error),
     DataUrl(data_url::DataUrlError),
     DataUrlBase64(data_url::forgiving_base64::InvalidBase64),
     ReqwestError(reqwest::Error),
+    /// The [`SecurityPolicy`] doesn't allow the request
+    Blocked(PolicyViolation),
+    /// A [`RequestInterceptor`] failed the request, with the given message
+    Intercepted(String),
 }
 
 impl From<std::io::Error> for ProviderError {
@@ -180,6 +895,12 @@ impl From<reqwest::Error> for ProviderError {
     }
 }
 
+impl From<PolicyViolation> for ProviderError {
+    fn from(value: PolicyViolation) -> Self {
+        Self::Blocked(value)
+    }
+}
+
 impl std::fmt::Display for ProviderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -187,6 +908,8 @@ impl std::fmt::Display for ProviderError {
             Self::DataUrl(e) => write!(f, "Data URL parsing error: {}", e),
             Self::DataUrlBase64(e) => write!(f, "Base64 decode error: {}", e),
             Self::ReqwestError(e) => write!(f, "HTTP request error: {}", e),
+            Self::Blocked(e) => write!(f, "Blocked by security policy: {}", e),
+            Self::Intercepted(e) => write!(f, "Failed by request interceptor: {}", e),
         }
     }
 }
@@ -196,11 +919,69 @@ impl std::error::Error for ProviderError {
         match self {
             Self::Io(e) => Some(e),
             Self::ReqwestError(e) => Some(e),
+            Self::Blocked(e) => Some(e),
             _ => None,
         }
     }
 }
 
+/// Build the HTTP client. Requests are made over HTTP/3 if `http3`, and accept responses compressed
+/// with `accept_encoding`, which reqwest decodes. Redirects aren't followed by the client, but by
+/// [`HttpClient::send_body`], which checks them against the security policy.
+fn build_client(http3: bool, accept_encoding: &[ContentEncoding]) -> Client {
+    let builder = Client::builder().redirect(reqwest::redirect::Policy::none());
+    #[cfg(feature = "cookies")]
+    let builder = builder.cookie_store(true);
+    #[cfg(feature = "http3")]
+    let builder = if http3 {
+        builder.http3_prior_knowledge()
+    } else {
+        builder
+    };
+    #[cfg(not(feature = "http3"))]
+    let _ = http3;
+    #[cfg(feature = "gzip")]
+    let builder = builder.gzip(accept_encoding.contains(&ContentEncoding::Gzip));
+    #[cfg(feature = "brotli")]
+    let builder = builder.brotli(accept_encoding.contains(&ContentEncoding::Brotli));
+    #[cfg(feature = "zstd")]
+    let builder = builder.zstd(accept_encoding.contains(&ContentEncoding::Zstd));
+    #[cfg(feature = "deflate")]
+    let builder = builder.deflate(accept_encoding.contains(&ContentEncoding::Deflate));
+    #[cfg(not(any(feature = "gzip", feature = "brotli", feature = "zstd", feature = "deflate")))]
+    let _ = accept_encoding;
+    builder.build().unwrap()
+}
+
+/// The span that fetching `request` (for the document `doc_id`, if any) is recorded in
+#[cfg(feature = "tracing")]
+fn fetch_span(doc_id: Option<usize>, request: &Request) -> tracing::Span {
+    tracing::info_span!(
+        "fetch",
+        doc_id,
+        url = %request.url,
+        method = %request.method,
+        priority = ?request.priority,
+    )
+}
+
+/// Log the outcome of fetching `url`. Without the `tracing` feature, only failures are logged
+/// (to stderr).
+fn log_fetch_result<T>(url: &str, result: &Result<T, ProviderError>) {
+    match result {
+        Ok(_) => {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(url, "Fetched");
+        }
+        Err(error) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!(url, error = %error, "Network fetch failed");
+            #[cfg(not(feature = "tracing"))]
+            eprintln!("Error fetching {url}: {error}");
+        }
+    }
+}
+
 pub struct MpscCallback<T>(UnboundedSender<(usize, Result<T, String>)>);
 impl<T> MpscCallback<T> {
     pub fn new() -> (UnboundedReceiver<(usize, Result<T, String>)>, Self) {
@@ -229,3 +1010,148 @@ impl<T: Send + Sync + 'static> NetCallback<T> for MpscCallback<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use blitz_traits::navigation::NavigationOptions;
+    use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serve HTTP on a local port, answering each request with the response `respond` makes for
+    /// its path. Returns the URL of the server's root.
+    pub(crate) async fn serve(respond: impl Fn(&str) -> String + Send + Sync + 'static) -> Url {
+        serve_requests(move |head| {
+            let path = head.split(' ').nth(1).unwrap_or("/");
+            respond(path)
+        })
+        .await
+    }
+
+    /// Serve HTTP as [`serve`] does, making responses for the request line and headers of each
+    /// request
+    pub(crate) async fn serve_requests(
+        respond: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let respond = Arc::new(respond);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let respond = Arc::clone(&respond);
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut head = String::new();
+                    // Read up to the blank line which ends the headers
+                    loop {
+                        let read = stream.read_line(&mut head).await.unwrap();
+                        if read == 0 || head.ends_with("\r\n\r\n") {
+                            break;
+                        }
+                    }
+                    stream.write_all(respond(&head).as_bytes()).await.unwrap();
+                });
+            }
+        });
+        url
+    }
+
+    /// An HTTP response, which closes the connection
+    pub(crate) fn response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+        let mut response = format!("HTTP/1.1 {status}\r\nConnection: close\r\n");
+        for (name, value) in headers {
+            response.push_str(&format!("{name}: {value}\r\n"));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+        response
+    }
+
+    pub(crate) fn provider(policy: SecurityPolicy) -> Provider<()> {
+        let (_receiver, callback) = MpscCallback::new();
+        Provider::new(Arc::new(callback)).with_security_policy(policy)
+    }
+
+    #[tokio::test]
+    async fn redirects_are_checked_as_requests_of_the_document() {
+        let server = serve(|path| match path {
+            "/same" => response("302 Found", &[("Location", "/page")], ""),
+            // Another origin than the server's `127.0.0.1`
+            "/other" => response("307 Temporary Redirect", &[("Location", "http://[::1]/")], ""),
+            _ => response("200 OK", &[], "page"),
+        })
+        .await;
+        let provider = provider(SecurityPolicy::default().with_default_permissions(
+            OriginPermissions {
+                cross_origin: false,
+                local_files: false,
+            },
+        ));
+        provider.set_document_url(1, Some(&server));
+
+        let request = Request::get(server.join("same").unwrap());
+        let (url, body) = provider.fetch_async(Some(1), request).await.unwrap();
+        assert_eq!(url, server.join("page").unwrap().as_str());
+        assert_eq!(body, "page");
+
+        let request = Request::get(server.join("other").unwrap());
+        let result = provider.fetch_async(Some(1), request).await;
+        assert!(matches!(result, Err(ProviderError::Blocked(PolicyViolation::CrossOrigin(_)))));
+    }
+
+    #[tokio::test]
+    async fn navigations_may_go_to_other_origins() {
+        let server = serve(|_| response("200 OK", &[], "page")).await;
+        let provider = provider(SecurityPolicy::default().with_default_permissions(
+            OriginPermissions {
+                cross_origin: false,
+                local_files: false,
+            },
+        ));
+        let document = Url::parse("https://example.com/").unwrap();
+        provider.set_document_url(1, Some(&document));
+
+        let result = provider.fetch_async(Some(1), Request::get(server.clone())).await;
+        assert!(matches!(result, Err(ProviderError::Blocked(PolicyViolation::CrossOrigin(_)))));
+
+        let link = NavigationOptions::new(server, "text/plain".to_string(), 1);
+        let (_, body) = provider.fetch_async(Some(1), link.into_request()).await.unwrap();
+        assert_eq!(body, "page");
+    }
+
+    #[tokio::test]
+    async fn blob_urls_are_loaded_until_revoked() {
+        let provider = provider(SecurityPolicy::default());
+        let document = Url::parse("https://example.com/").unwrap();
+        provider.set_document_url(1, Some(&document));
+        let registry = BlobRegistry::global();
+        let url = registry.create_object_url(Some(&document), &b"pixels"[..], "image/png");
+
+        let (_, body) = provider.fetch_async(Some(1), Request::get(url.clone())).await.unwrap();
+        assert_eq!(body, &b"pixels"[..]);
+
+        registry.revoke_object_url(&url);
+        assert!(provider.fetch_async(Some(1), Request::get(url)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn redirects_to_blocked_hosts_fail() {
+        let location = ("Location", "http://ads.example./");
+        let server = serve(move |_| response("301 Moved Permanently", &[location], "")).await;
+        let provider = provider(SecurityPolicy::default().with_blocked_host("ads.example"));
+
+        let result = provider.fetch_async(None, Request::get(server)).await;
+        assert!(matches!(result, Err(ProviderError::Blocked(PolicyViolation::BlockedHost(_)))));
+    }
+
+    #[tokio::test]
+    async fn custom_schemes_are_those_of_the_provider() {
+        let app = provider(SecurityPolicy::default());
+        app.register_scheme("App", |_: &Request| Ok(Bytes::from_static(b"page")));
+        let other = provider(SecurityPolicy::default());
+
+        assert!(app.is_custom_scheme("app"));
+        assert!(!app.is_custom_scheme("https"));
+        assert!(!other.is_custom_scheme("app"));
+    }
+}
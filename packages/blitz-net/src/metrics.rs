@@ -0,0 +1,290 @@
+//! Timing and transfer statistics of requests, for devtools' network panels
+//!
+//! The [`Provider`](crate::Provider) records every fetch in its [`NetworkMetrics`] (see
+//! [`Provider::metrics`](crate::Provider::metrics)): the protocol the response came over,
+//! whether it reused a connection, the time to the first byte and in total, and the bytes
+//! transferred. Requests appear as soon as they start, and are updated as they progress.
+
+use std::collections::{HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use blitz_traits::net::http::{Method, Version};
+use blitz_traits::net::{Request, RequestPriority, Url};
+use hyper_util::client::legacy::connect::HttpInfo;
+
+use crate::ProviderError;
+
+/// The number of requests kept, the oldest being dropped first
+const MAX_RECORDED_REQUESTS: usize = 1000;
+/// The number of connections remembered to tell whether later requests reuse them
+const MAX_REMEMBERED_CONNECTIONS: usize = 1000;
+
+/// What happened to a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestMetrics {
+    /// Identifies the request among those recorded by the same provider
+    pub id: u64,
+    /// The document the request was made for (if any)
+    pub doc_id: Option<usize>,
+    pub url: Url,
+    pub method: Method,
+    pub priority: RequestPriority,
+    /// When the request started, since the metrics started being recorded
+    pub start: Duration,
+    /// The HTTP version of the response (`None` for other schemes, like `file:`)
+    pub protocol: Option<Version>,
+    pub status: Option<u16>,
+    /// Whether the response came over a connection which earlier requests used (`None` if that
+    /// isn't known, as for HTTP/3)
+    pub connection_reused: Option<bool>,
+    /// The time from starting the request to receiving the response headers
+    pub time_to_first_byte: Option<Duration>,
+    /// The time from starting the request to receiving the whole response, or to failing.
+    /// `None` while the request is in flight.
+    pub total_time: Option<Duration>,
+    /// The size of the request body
+    pub bytes_sent: u64,
+    /// The size of the response body received so far
+    pub bytes_received: u64,
+    /// Why the request failed (or `"Cancelled"`, if it was abandoned)
+    pub error: Option<String>,
+    /// Whether a [`RequestInterceptor`](crate::RequestInterceptor) answered or failed the
+    /// request, which then wasn't fetched
+    pub intercepted: bool,
+}
+
+impl RequestMetrics {
+    /// Whether the request has finished (successfully or not)
+    pub fn is_complete(&self) -> bool {
+        self.total_time.is_some()
+    }
+}
+
+/// The requests made by a [`Provider`](crate::Provider), see the [module docs](self)
+pub struct NetworkMetrics {
+    origin: Instant,
+    next_id: AtomicU64,
+    requests: Mutex<VecDeque<RequestMetrics>>,
+    /// The (local, remote) addresses of the connections responses have come over
+    connections: Mutex<HashSet<(SocketAddr, SocketAddr)>>,
+}
+
+impl Default for NetworkMetrics {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+            next_id: AtomicU64::new(0),
+            requests: Mutex::new(VecDeque::new()),
+            connections: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+impl NetworkMetrics {
+    /// The recent requests, oldest first
+    pub fn requests(&self) -> Vec<RequestMetrics> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// The recent requests made for the document `doc_id`, oldest first
+    pub fn requests_for_document(&self, doc_id: usize) -> Vec<RequestMetrics> {
+        let requests = self.lock();
+        let for_document = requests.iter().filter(|request| request.doc_id == Some(doc_id));
+        for_document.cloned().collect()
+    }
+
+    /// Forget the requests recorded so far (those in flight are still updated)
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<RequestMetrics>> {
+        self.requests.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Record the start of `request`
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        doc_id: Option<usize>,
+        request: &Request,
+    ) -> RequestRecorder {
+        let started = Instant::now();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let metrics = RequestMetrics {
+            id,
+            doc_id,
+            url: request.url.clone(),
+            method: request.method.clone(),
+            priority: request.priority,
+            start: started.duration_since(self.origin),
+            protocol: None,
+            status: None,
+            connection_reused: None,
+            time_to_first_byte: None,
+            total_time: None,
+            bytes_sent: request.body.len() as u64,
+            bytes_received: 0,
+            error: None,
+            intercepted: false,
+        };
+
+        let mut requests = self.lock();
+        if requests.len() == MAX_RECORDED_REQUESTS {
+            requests.pop_front();
+        }
+        requests.push_back(metrics);
+        RequestRecorder {
+            metrics: Arc::clone(self),
+            id,
+            started,
+            finished: false,
+        }
+    }
+
+    fn update(&self, id: u64, update: impl FnOnce(&mut RequestMetrics)) {
+        // Requests which were cleared (or dropped for newer ones) aren't updated
+        let mut requests = self.lock();
+        if let Some(request) = requests.iter_mut().rev().find(|request| request.id == id) {
+            update(request);
+        }
+    }
+
+    /// Whether the connection a response came over has been used before
+    fn connection_reused(&self, info: &HttpInfo) -> bool {
+        let mut connections = self.connections.lock().unwrap_or_else(|err| err.into_inner());
+        if connections.len() == MAX_REMEMBERED_CONNECTIONS {
+            connections.clear();
+        }
+        !connections.insert((info.local_addr(), info.remote_addr()))
+    }
+}
+
+/// Updates the [`RequestMetrics`] of a request as it progresses. Requests which are dropped
+/// without being finished (because their task was aborted) are recorded as cancelled.
+pub(crate) struct RequestRecorder {
+    metrics: Arc<NetworkMetrics>,
+    id: u64,
+    started: Instant,
+    finished: bool,
+}
+
+impl RequestRecorder {
+    /// Record the receipt of the response headers
+    pub(crate) fn response(&self, response: &reqwest::Response) {
+        let time_to_first_byte = self.started.elapsed();
+        // Found by type, so only if hyper-util is the version reqwest uses (which
+        // `reused_connections_are_recorded` checks)
+        let info = response.extensions().get::<HttpInfo>();
+        let connection_reused = info.map(|info| self.metrics.connection_reused(info));
+        self.metrics.update(self.id, |request| {
+            request.protocol = Some(response.version());
+            request.status = Some(response.status().as_u16());
+            request.connection_reused = connection_reused;
+            request.time_to_first_byte = Some(time_to_first_byte);
+        });
+    }
+
+    /// Record that an interceptor answered or failed the request
+    pub(crate) fn intercepted(&self) {
+        self.metrics.update(self.id, |request| request.intercepted = true);
+    }
+
+    /// Record the size of the request body, when it isn't that of the request's own body
+    pub(crate) fn sent(&self, bytes: u64) {
+        self.metrics.update(self.id, |request| request.bytes_sent = bytes);
+    }
+
+    /// Record the receipt of part of the response body
+    pub(crate) fn received(&self, bytes: usize) {
+        self.metrics.update(self.id, |request| request.bytes_received += bytes as u64);
+    }
+
+    /// Record the end of the request
+    pub(crate) fn finish<T>(mut self, result: &Result<T, ProviderError>) {
+        self.finished = true;
+        let error = result.as_ref().err().map(ToString::to_string);
+        self.end(error);
+    }
+
+    fn end(&self, error: Option<String>) {
+        let total_time = self.started.elapsed();
+        self.metrics.update(self.id, |request| {
+            request.total_time = Some(total_time);
+            request.error = error;
+        });
+    }
+}
+
+impl Drop for RequestRecorder {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.end(Some("Cancelled".to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::SecurityPolicy;
+    use crate::tests::provider;
+
+    #[test]
+    fn requests_are_recorded_as_they_progress() {
+        let metrics = Arc::new(NetworkMetrics::default());
+        let url = Url::parse("https://example.com/style.css").unwrap();
+
+        let recorder = metrics.start(Some(1), &Request::get(url.clone()));
+        assert!(!metrics.requests()[0].is_complete());
+        recorder.received(100);
+        recorder.received(20);
+        recorder.finish::<()>(&Ok(()));
+
+        drop(metrics.start(None, &Request::get(url)));
+
+        let requests = metrics.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].bytes_received, 120);
+        assert!(requests[0].is_complete());
+        assert_eq!(requests[0].error, None);
+        assert_eq!(requests[1].error.as_deref(), Some("Cancelled"));
+        assert_eq!(metrics.requests_for_document(1).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reused_connections_are_recorded() {
+        // Answers every request on a connection, keeping it open
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut line = String::new();
+                    while stream.read_line(&mut line).await.unwrap_or(0) > 0 {
+                        if line == "\r\n" {
+                            let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                            stream.write_all(response).await.unwrap();
+                        }
+                        line.clear();
+                    }
+                });
+            }
+        });
+        let provider = provider(SecurityPolicy::default());
+
+        for _ in 0..2 {
+            provider.fetch_async(None, Request::get(url.clone())).await.unwrap();
+        }
+
+        let requests = provider.metrics().requests();
+        assert_eq!(requests[0].connection_reused, Some(false));
+        assert_eq!(requests[1].connection_reused, Some(true));
+    }
+}
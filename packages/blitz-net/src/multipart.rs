@@ -0,0 +1,413 @@
+//! `multipart/form-data` request bodies
+//!
+//! A [`MultipartForm`] is sent with [`Provider::upload`](crate::Provider::upload). Its file parts
+//! are streamed from disk as the request is sent, rather than being read into memory first, and
+//! its progress callback is told how much of the body has been sent, for upload progress UIs.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use blitz_traits::navigation::{FormEntry, FormEntryValue};
+use blitz_traits::net::Bytes;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt as _;
+
+/// The size of the chunks files are read in
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Called with the number of bytes of a form which have been sent, and the size of the whole form
+pub type UploadProgressCallback = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+/// A field of a [`MultipartForm`]
+#[derive(Debug, Clone)]
+pub struct Part {
+    name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    data: PartData,
+}
+
+#[derive(Debug, Clone)]
+enum PartData {
+    Bytes(Bytes),
+    File(PathBuf),
+}
+
+impl Part {
+    /// A text field
+    pub fn text(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::new(name, PartData::Bytes(Bytes::from(value.into())))
+    }
+
+    /// A field with the contents `bytes`
+    pub fn bytes(name: impl Into<String>, bytes: impl Into<Bytes>) -> Self {
+        Self::new(name, PartData::Bytes(bytes.into()))
+    }
+
+    /// A file field with the contents of the file at `path`, which is read as the form is sent.
+    /// Its file name is that of `path`, and its content type is `application/octet-stream`.
+    pub fn file(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+        Self {
+            file_name: Some(file_name.unwrap_or_default()),
+            content_type: Some("application/octet-stream".to_string()),
+            ..Self::new(name, PartData::File(path))
+        }
+    }
+
+    fn new(name: impl Into<String>, data: PartData) -> Self {
+        Self {
+            name: name.into(),
+            file_name: None,
+            content_type: None,
+            data,
+        }
+    }
+
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// The part's headers, along with the boundary before them
+    fn headers(&self, boundary: &str) -> Bytes {
+        let mut headers = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"",
+            escape(&self.name),
+        );
+        if let Some(file_name) = &self.file_name {
+            headers.push_str(&format!("; filename=\"{}\"", escape(file_name)));
+        }
+        if let Some(content_type) = &self.content_type {
+            // Line breaks would end the header, and let the rest of the value inject others
+            let content_type = content_type.replace(['\r', '\n'], "");
+            headers.push_str(&format!("\r\nContent-Type: {content_type}"));
+        }
+        headers.push_str("\r\n\r\n");
+        Bytes::from(headers)
+    }
+}
+
+/// Escape a name for the `Content-Disposition` header of a part, as the HTML spec does
+fn escape(name: &str) -> String {
+    name.replace('\n', "%0A").replace('\r', "%0D").replace('"', "%22")
+}
+
+/// A `multipart/form-data` body, see the [module docs](self)
+///
+/// ```no_run
+/// use blitz_net::{MultipartForm, Part};
+///
+/// let form = MultipartForm::new()
+///     .with_text("title", "Holiday")
+///     .with_part(Part::file("photo", "photos/beach.jpg").with_content_type("image/jpeg"))
+///     .with_progress_callback(|sent, total| println!("Uploaded {sent} of {total} bytes"));
+/// ```
+#[derive(Clone)]
+pub struct MultipartForm {
+    boundary: String,
+    parts: Vec<Part>,
+    progress: Option<UploadProgressCallback>,
+}
+
+impl Default for MultipartForm {
+    fn default() -> Self {
+        let random: String = std::iter::repeat_with(fastrand::alphanumeric).take(24).collect();
+        Self {
+            boundary: format!("----BlitzFormBoundary{random}"),
+            parts: Vec::new(),
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for MultipartForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultipartForm")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MultipartForm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_part(mut self, part: Part) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// The form with the entries of a submitted `<form>`. Files picked from disk are streamed
+    /// from it, and others are sent from the contents they were read with.
+    pub fn from_form_data(entries: Vec<FormEntry>) -> Self {
+        let parts = entries.into_iter().map(|entry| match entry.value {
+            FormEntryValue::Text(value) => Part::text(entry.name, value),
+            FormEntryValue::File {
+                name,
+                content_type,
+                data,
+                path,
+            } => {
+                let part = match path {
+                    Some(path) => Part::file(entry.name, path),
+                    None => Part::bytes(entry.name, data),
+                };
+                part.with_file_name(name).with_content_type(content_type)
+            }
+        });
+        parts.fold(Self::new(), Self::with_part)
+    }
+
+    /// Add a text field
+    pub fn with_text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with_part(Part::text(name, value))
+    }
+
+    /// Add a file field with the contents of the file at `path`, see [`Part::file`]
+    pub fn with_file(self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.with_part(Part::file(name, path))
+    }
+
+    /// Call `callback` as the form is sent
+    pub fn with_progress_callback(
+        mut self,
+        callback: impl Fn(u64, u64) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// The value of the `Content-Type` header of requests with the form as their body
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// The pieces of the body, in order
+    fn segments(&self) -> VecDeque<Segment> {
+        let mut segments = VecDeque::new();
+        for part in &self.parts {
+            segments.push_back(Segment::Bytes(part.headers(&self.boundary)));
+            segments.push_back(match &part.data {
+                PartData::Bytes(bytes) => Segment::Bytes(bytes.clone()),
+                PartData::File(path) => Segment::File(path.clone()),
+            });
+            segments.push_back(Segment::Bytes(Bytes::from_static(b"\r\n")));
+        }
+        let end = format!("--{}--\r\n", self.boundary);
+        segments.push_back(Segment::Bytes(Bytes::from(end)));
+        segments
+    }
+
+    /// The size of the body, which fails if any of the form's files can't be read
+    pub async fn content_length(&self) -> io::Result<u64> {
+        let mut length = 0;
+        for segment in self.segments() {
+            length += match segment {
+                Segment::Bytes(bytes) => bytes.len() as u64,
+                Segment::File(path) => tokio::fs::metadata(&path).await?.len(),
+            };
+        }
+        Ok(length)
+    }
+
+    /// The body, which streams the files from disk as it's sent. `content_length` is the size of
+    /// the body (for progress callbacks).
+    pub(crate) fn body(&self, content_length: u64) -> reqwest::Body {
+        let upload = self.upload(content_length);
+        let stream = futures_util::stream::unfold(upload, |mut upload| async move {
+            let chunk = upload.next_chunk().await?;
+            Some((chunk, upload))
+        });
+        reqwest::Body::wrap_stream(stream)
+    }
+
+    /// The form, about to be sent
+    fn upload(&self, content_length: u64) -> Upload {
+        Upload {
+            segments: self.segments(),
+            file: None,
+            sent: 0,
+            content_length,
+            progress: self.progress.clone(),
+        }
+    }
+}
+
+enum Segment {
+    Bytes(Bytes),
+    File(PathBuf),
+}
+
+/// The state of a form being sent
+struct Upload {
+    segments: VecDeque<Segment>,
+    /// The file being read
+    file: Option<File>,
+    sent: u64,
+    content_length: u64,
+    progress: Option<UploadProgressCallback>,
+}
+
+impl Upload {
+    async fn next_chunk(&mut self) -> Option<io::Result<Bytes>> {
+        let chunk = loop {
+            if let Some(file) = &mut self.file {
+                let mut buf = vec![0; FILE_CHUNK_SIZE];
+                match file.read(&mut buf).await {
+                    Ok(0) => self.file = None,
+                    Ok(len) => {
+                        buf.truncate(len);
+                        break Bytes::from(buf);
+                    }
+                    Err(err) => return Some(Err(err)),
+                }
+                continue;
+            }
+            match self.segments.pop_front()? {
+                Segment::Bytes(bytes) => break bytes,
+                Segment::File(path) => match File::open(&path).await {
+                    Ok(file) => self.file = Some(file),
+                    Err(err) => return Some(Err(err)),
+                },
+            }
+        };
+
+        self.sent += chunk.len() as u64;
+        if let Some(progress) = &self.progress {
+            progress(self.sent, self.content_length);
+        }
+        Some(Ok(chunk))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Send `form` into a buffer
+    async fn encode(form: &MultipartForm) -> String {
+        let mut upload = Upload {
+            segments: form.segments(),
+            file: None,
+            sent: 0,
+            content_length: 0,
+            progress: None,
+        };
+        let mut body = Vec::new();
+        while let Some(chunk) = upload.next_chunk().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        String::from_utf8(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn submitted_forms_are_encoded_with_their_files() {
+        let entries = vec![
+            FormEntry {
+                name: "title".to_string(),
+                value: FormEntryValue::Text("Holiday".to_string()),
+            },
+            FormEntry {
+                name: "photo".to_string(),
+                value: FormEntryValue::File {
+                    name: "beach.txt".to_string(),
+                    content_type: "text/plain\r\nX-Injected: 1".to_string(),
+                    data: Bytes::from_static(b"sand"),
+                    path: None,
+                },
+            },
+        ];
+        let form = MultipartForm::from_form_data(entries);
+
+        let expected = format!(
+            concat!(
+                "--{0}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\n",
+                "Holiday\r\n",
+                "--{0}\r\nContent-Disposition: form-data; name=\"photo\"; ",
+                "filename=\"beach.txt\"\r\nContent-Type: text/plainX-Injected: 1\r\n\r\n",
+                "sand\r\n",
+                "--{0}--\r\n",
+            ),
+            form.boundary,
+        );
+        assert_eq!(encode(&form).await, expected);
+    }
+
+    #[tokio::test]
+    async fn forms_are_encoded_with_their_files() {
+        let path = std::env::temp_dir().join(format!("blitz-multipart-{}", fastrand::u64(..)));
+        std::fs::write(&path, "file contents").unwrap();
+
+        let form = MultipartForm::new()
+            .with_text("title", "A \"quoted\" title")
+            .with_part(Part::file("upload", &path).with_file_name("notes.txt"));
+        let mut upload = Upload {
+            segments: form.segments(),
+            file: None,
+            sent: 0,
+            content_length: form.content_length().await.unwrap(),
+            progress: None,
+        };
+        let mut body = Vec::new();
+        while let Some(chunk) = upload.next_chunk().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let boundary = &form.boundary;
+        let expected = format!(
+            concat!(
+                "--{0}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\n",
+                "A \"quoted\" title\r\n",
+                "--{0}\r\nContent-Disposition: form-data; name=\"upload\"; ",
+                "filename=\"notes.txt\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                "file contents\r\n",
+                "--{0}--\r\n",
+            ),
+            boundary,
+        );
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+        assert_eq!(upload.sent, expected.len() as u64);
+        assert_eq!(upload.content_length, expected.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn progress_is_reported_as_the_form_is_sent() {
+        let path = std::env::temp_dir().join(format!("blitz-multipart-{}", fastrand::u64(..)));
+        std::fs::write(&path, vec![b'x'; FILE_CHUNK_SIZE * 2 + 1]).unwrap();
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let form = MultipartForm::new()
+            .with_file("upload", &path)
+            .with_progress_callback({
+                let reports = Arc::clone(&reports);
+                move |sent, total| reports.lock().unwrap().push((sent, total))
+            });
+        let content_length = form.content_length().await.unwrap();
+        let mut upload = form.upload(content_length);
+        while let Some(chunk) = upload.next_chunk().await {
+            chunk.unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        // Each chunk is reported as it's sent, out of the size of the whole form: the part's
+        // headers, the file in three chunks, the line break after it and the end of the form
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 6);
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(reports.iter().all(|&(_, total)| total == content_length));
+        assert_eq!(reports.last(), Some(&(content_length, content_length)));
+    }
+}
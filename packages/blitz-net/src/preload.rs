@@ -0,0 +1,161 @@
+//! Keeping the responses to preload requests until the resources are requested again
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use blitz_traits::cache::ManagedCache;
+use blitz_traits::net::{Bytes, Method, Request, Url};
+use tokio::sync::watch;
+
+/// The response to a preload, which is `None` until it has been received. The sender is dropped
+/// without sending if the preload fails.
+pub(crate) type PreloadedResponse = watch::Receiver<Option<Bytes>>;
+
+/// The responses to preloads, by document and URL
+#[derive(Default)]
+pub(crate) struct PreloadCache {
+    responses: Mutex<HashMap<(usize, Url), PreloadedResponse>>,
+}
+
+impl PreloadCache {
+    /// Keep the response to `request` if it is a preload, returning where to send it
+    pub(crate) fn insert(
+        &self,
+        doc_id: usize,
+        request: &Request,
+    ) -> Option<watch::Sender<Option<Bytes>>> {
+        if !request.preload {
+            return None;
+        }
+        let (sender, receiver) = watch::channel(None);
+        let mut responses = self.responses.lock().unwrap_or_else(|err| err.into_inner());
+        responses.insert((doc_id, request.url.clone()), receiver);
+        Some(sender)
+    }
+
+    /// Take the response to a preload which `request` can be answered with
+    pub(crate) fn take(&self, doc_id: usize, request: &Request) -> Option<PreloadedResponse> {
+        if request.preload || request.method != Method::GET {
+            return None;
+        }
+        let mut responses = self.responses.lock().unwrap_or_else(|err| err.into_inner());
+        responses.remove(&(doc_id, request.url.clone()))
+    }
+
+    /// Drop the responses to the preloads made for the document `doc_id`
+    pub(crate) fn clear(&self, doc_id: usize) {
+        let mut responses = self.responses.lock().unwrap_or_else(|err| err.into_inner());
+        responses.retain(|(id, _), _| *id != doc_id);
+    }
+}
+
+/// Responses to preloads which haven't been requested again can be evicted: the resources are
+/// then fetched again when they're requested
+impl ManagedCache for PreloadCache {
+    fn name(&self) -> &str {
+        "preloaded responses"
+    }
+
+    fn memory_usage(&self) -> usize {
+        let responses = self.responses.lock().unwrap_or_else(|err| err.into_inner());
+        responses.values().map(received_len).sum()
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        let mut responses = self.responses.lock().unwrap_or_else(|err| err.into_inner());
+        let mut usage: usize = responses.values().map(received_len).sum();
+        if usage <= target_bytes {
+            return;
+        }
+
+        // Evict the largest responses first, so that as few as possible are fetched again
+        let mut received: Vec<(usize, Url, usize)> = responses
+            .iter()
+            .map(|(key, response)| (key.0, key.1.clone(), received_len(response)))
+            .filter(|&(_, _, len)| len > 0)
+            .collect();
+        received.sort_by_key(|&(_, _, len)| std::cmp::Reverse(len));
+        for (doc_id, url, len) in received {
+            if usage <= target_bytes {
+                break;
+            }
+            responses.remove(&(doc_id, url));
+            usage -= len;
+        }
+    }
+}
+
+/// The length of the response to a preload, or zero if it hasn't been received
+fn received_len(response: &PreloadedResponse) -> usize {
+    response.borrow().as_ref().map_or(0, Bytes::len)
+}
+
+/// Wait for the response to a preload, or `None` if it failed
+pub(crate) async fn preloaded_bytes(mut response: PreloadedResponse) -> Option<Bytes> {
+    let bytes = response.wait_for(Option::is_some).await.ok()?;
+    bytes.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use blitz_traits::net::RequestPriority;
+
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com/").unwrap().join(path).unwrap()
+    }
+
+    fn preload(cache: &PreloadCache, doc_id: usize, path: &str) -> watch::Sender<Option<Bytes>> {
+        let request = Request::preload(url(path), RequestPriority::High);
+        cache.insert(doc_id, &request).unwrap()
+    }
+
+    #[test]
+    fn preloads_answer_later_requests_for_the_same_resource() {
+        let cache = PreloadCache::default();
+        assert!(cache.insert(1, &Request::get(url("a.css"))).is_none());
+        let sender = preload(&cache, 1, "a.css");
+
+        // Only a GET which isn't itself a preload, from the same document, takes the response
+        let mut post = Request::get(url("a.css"));
+        post.method = Method::POST;
+        assert!(cache.take(1, &post).is_none());
+        let again = Request::preload(url("a.css"), RequestPriority::High);
+        assert!(cache.take(1, &again).is_none());
+        assert!(cache.take(2, &Request::get(url("a.css"))).is_none());
+
+        sender.send_replace(Some(Bytes::from_static(b"p {}")));
+        let response = cache.take(1, &Request::get(url("a.css"))).unwrap();
+        assert_eq!(response.borrow().as_deref(), Some(&b"p {}"[..]));
+        assert!(cache.take(1, &Request::get(url("a.css"))).is_none());
+    }
+
+    #[test]
+    fn clearing_a_document_drops_only_its_preloads() {
+        let cache = PreloadCache::default();
+        let _first = preload(&cache, 1, "a.css");
+        let _second = preload(&cache, 2, "a.css");
+
+        cache.clear(1);
+        assert!(cache.take(1, &Request::get(url("a.css"))).is_none());
+        assert!(cache.take(2, &Request::get(url("a.css"))).is_some());
+    }
+
+    #[test]
+    fn the_largest_responses_are_evicted_first() {
+        let cache = PreloadCache::default();
+        let small = preload(&cache, 1, "small.css");
+        let large = preload(&cache, 1, "large.css");
+        let _pending = preload(&cache, 1, "pending.css");
+        small.send_replace(Some(Bytes::from(vec![0; 10])));
+        large.send_replace(Some(Bytes::from(vec![0; 100])));
+        assert_eq!(cache.memory_usage(), 110);
+
+        cache.evict_to(50);
+        assert_eq!(cache.memory_usage(), 10);
+        assert!(cache.take(1, &Request::get(url("large.css"))).is_none());
+        assert!(cache.take(1, &Request::get(url("small.css"))).is_some());
+        assert!(cache.take(1, &Request::get(url("pending.css"))).is_some());
+    }
+}
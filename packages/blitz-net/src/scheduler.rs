@@ -0,0 +1,214 @@
+//! Ordering requests by their [`RequestPriority`]
+//!
+//! A request waits to be sent while requests of a higher priority are in flight, so that (for
+//! example) prefetches don't compete for bandwidth with the stylesheets a page needs to render.
+//! High priority requests never wait. Preloads wait their turn like other requests, but don't hold
+//! back the requests made while they're in flight, which are needed sooner. A request overtaken
+//! by many requests of a higher priority is promoted, so that a steady stream of them can't hold
+//! it back forever.
+//!
+//! Requests made with [`Scheduler::run`] can give up their slot while they wait on something
+//! other than the network (such as the user entering a password) with [`without_slot`].
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use blitz_traits::net::RequestPriority;
+use tokio::sync::Notify;
+
+tokio::task_local! {
+    /// The slot of the request being made by the current task, see [`Scheduler::run`]
+    static SLOT: RefCell<Option<InFlight>>;
+}
+
+/// The number of [`RequestPriority`] variants
+const PRIORITIES: usize = 3;
+
+/// The number of requests of a higher priority which can start while a request waits before it's
+/// promoted to the next higher priority
+const OVERTAKES_PER_PROMOTION: usize = 8;
+
+#[derive(Default)]
+pub(crate) struct Scheduler {
+    state: Mutex<State>,
+    /// Notified whenever a request starts or finishes
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct State {
+    /// The number of requests in flight, indexed by priority
+    in_flight: [usize; PRIORITIES],
+    /// The number of requests started so far, indexed by priority
+    started: [usize; PRIORITIES],
+}
+
+impl Scheduler {
+    /// Wait until no requests of a higher priority than `priority` (or than the priority the
+    /// request is promoted to while it waits) are in flight. The request then counts as in flight
+    /// (holding back requests of a lower priority, if `holds_back`) until the returned guard is
+    /// dropped.
+    pub(crate) async fn start(
+        self: Arc<Self>,
+        priority: RequestPriority,
+        holds_back: bool,
+    ) -> InFlight {
+        let index = priority as usize;
+        // The number of requests of a higher priority started before this one started waiting
+        let mut started_before = None;
+        loop {
+            // Wait for notifications from before checking, so that none are missed in between
+            let changed = self.changed.notified();
+            {
+                let mut state = self.lock();
+                let started = state.started[..index]
+                    .iter()
+                    .fold(0, |sum, &count| sum.wrapping_add(count));
+                let overtaken = started.wrapping_sub(*started_before.get_or_insert(started));
+                let promoted = index.saturating_sub(overtaken / OVERTAKES_PER_PROMOTION);
+                if state.in_flight[..promoted].iter().all(|&count| count == 0) {
+                    state.started[index] = state.started[index].wrapping_add(1);
+                    if holds_back {
+                        state.in_flight[index] += 1;
+                    }
+                    break;
+                }
+            }
+            changed.await;
+        }
+        // Requests of a lower priority may have been promoted past it
+        self.changed.notify_waiters();
+        InFlight {
+            scheduler: self,
+            priority,
+            holds_back,
+        }
+    }
+
+    /// Wait for a slot as [`Scheduler::start`] does, then make `request` in it
+    pub(crate) async fn run<T>(
+        self: Arc<Self>,
+        priority: RequestPriority,
+        holds_back: bool,
+        request: impl Future<Output = T>,
+    ) -> T {
+        let in_flight = self.start(priority, holds_back).await;
+        SLOT.scope(RefCell::new(Some(in_flight)), request).await
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Await `future` without holding the slot of the current request (if it was made with
+/// [`Scheduler::run`]), so that the requests waiting on it can go ahead. The slot is waited for
+/// again once `future` is done.
+pub(crate) async fn without_slot<T>(future: impl Future<Output = T>) -> T {
+    let released = SLOT.try_with(|slot| slot.borrow_mut().take()).ok().flatten();
+    let resume = released.map(|in_flight| {
+        let scheduler = Arc::clone(&in_flight.scheduler);
+        (scheduler, in_flight.priority, in_flight.holds_back)
+    });
+    let output = future.await;
+    if let Some((scheduler, priority, holds_back)) = resume {
+        let in_flight = scheduler.start(priority, holds_back).await;
+        SLOT.with(|slot| *slot.borrow_mut() = Some(in_flight));
+    }
+    output
+}
+
+/// A request which is in flight
+pub(crate) struct InFlight {
+    scheduler: Arc<Scheduler>,
+    priority: RequestPriority,
+    /// Whether the request is counted in [`Scheduler::in_flight`]
+    holds_back: bool,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if !self.holds_back {
+            return;
+        }
+        self.scheduler.lock().in_flight[self.priority as usize] -= 1;
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt as _;
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    fn try_start(scheduler: &Arc<Scheduler>, priority: RequestPriority) -> Option<InFlight> {
+        Arc::clone(scheduler).start(priority, true).now_or_never()
+    }
+
+    #[tokio::test]
+    async fn requests_wait_for_those_of_a_higher_priority() {
+        let scheduler = Arc::new(Scheduler::default());
+        let normal = try_start(&scheduler, RequestPriority::Normal).unwrap();
+        assert!(try_start(&scheduler, RequestPriority::High).is_some());
+        assert!(try_start(&scheduler, RequestPriority::Normal).is_some());
+
+        let low = tokio::spawn(Arc::clone(&scheduler).start(RequestPriority::Low, true));
+        tokio::task::yield_now().await;
+        assert!(!low.is_finished());
+        drop(normal);
+        low.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn requests_are_not_held_back_forever() {
+        let scheduler = Arc::new(Scheduler::default());
+        let mut normal = try_start(&scheduler, RequestPriority::Normal).unwrap();
+        let low = tokio::spawn(Arc::clone(&scheduler).start(RequestPriority::Low, true));
+        tokio::task::yield_now().await;
+
+        // Normal priority requests keep overtaking it, without all finishing
+        for _ in 0..OVERTAKES_PER_PROMOTION {
+            assert!(!low.is_finished());
+            normal = try_start(&scheduler, RequestPriority::Normal).unwrap();
+            tokio::task::yield_now().await;
+        }
+        low.await.unwrap();
+        drop(normal);
+    }
+
+    #[tokio::test]
+    async fn preloads_do_not_hold_back_other_requests() {
+        let scheduler = Arc::new(Scheduler::default());
+        let preload = Arc::clone(&scheduler).start(RequestPriority::High, false).now_or_never();
+        assert!(preload.is_some());
+        assert!(try_start(&scheduler, RequestPriority::Normal).is_some());
+
+        // But wait their turn
+        let normal = try_start(&scheduler, RequestPriority::Normal).unwrap();
+        let prefetch = Arc::clone(&scheduler).start(RequestPriority::Low, false).now_or_never();
+        assert!(prefetch.is_none());
+        drop(normal);
+    }
+
+    #[tokio::test]
+    async fn requests_waiting_on_something_else_free_their_slot() {
+        let scheduler = Arc::new(Scheduler::default());
+        let in_flight = try_start(&scheduler, RequestPriority::High);
+        assert!(in_flight.is_some());
+        assert!(try_start(&scheduler, RequestPriority::Low).is_none());
+        drop(in_flight);
+
+        let (sender, receiver) = oneshot::channel();
+        let scheduler_clone = Arc::clone(&scheduler);
+        let request = scheduler_clone.run(RequestPriority::High, true, without_slot(receiver));
+        let request = tokio::spawn(request);
+        tokio::task::yield_now().await;
+        assert!(try_start(&scheduler, RequestPriority::Low).is_some());
+
+        sender.send(()).unwrap();
+        request.await.unwrap().unwrap();
+    }
+}
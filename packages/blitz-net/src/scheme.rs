@@ -0,0 +1,51 @@
+//! URL schemes of the app's own, like `app:` or `res:`
+//!
+//! Handlers registered with [`Provider::register_scheme`](crate::Provider::register_scheme) load
+//! the resources of URLs with their scheme, so that documents can refer to content the app
+//! provides (from memory, an archive, or wherever) by URL. The provider reports the scheme from
+//! [`NetProvider::is_custom_scheme`](blitz_traits::net::NetProvider::is_custom_scheme), so the
+//! documents it loads for navigate to its URLs and resolve relative URLs against them like any
+//! other.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use blitz_traits::net::{Bytes, Request};
+
+/// The schemes [`Provider`](crate::Provider)s load themselves, which can't have handlers
+pub(crate) const BUILT_IN_SCHEMES: [&str; 6] =
+    ["http", "https", "file", "data", "blob", crate::BUNDLE_SCHEME];
+
+/// Loads the resources of URLs with a scheme of the app's own, see the [module docs](self)
+pub trait SchemeHandler: Send + Sync + 'static {
+    /// Load the resource `request` is for
+    fn load(&self, request: &Request) -> io::Result<Bytes>;
+}
+
+impl<F> SchemeHandler for F
+where
+    F: Fn(&Request) -> io::Result<Bytes> + Send + Sync + 'static,
+{
+    fn load(&self, request: &Request) -> io::Result<Bytes> {
+        self(request)
+    }
+}
+
+/// The handlers registered with a provider, by scheme
+#[derive(Default)]
+pub(crate) struct SchemeHandlers {
+    handlers: Mutex<HashMap<String, Arc<dyn SchemeHandler>>>,
+}
+
+impl SchemeHandlers {
+    pub(crate) fn insert(&self, scheme: String, handler: Arc<dyn SchemeHandler>) {
+        let mut handlers = self.handlers.lock().unwrap_or_else(|err| err.into_inner());
+        handlers.insert(scheme, handler);
+    }
+
+    pub(crate) fn get(&self, scheme: &str) -> Option<Arc<dyn SchemeHandler>> {
+        let handlers = self.handlers.lock().unwrap_or_else(|err| err.into_inner());
+        handlers.get(scheme).cloned()
+    }
+}
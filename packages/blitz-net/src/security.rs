@@ -0,0 +1,360 @@
+//! Restricting what documents can fetch
+//!
+//! A [`SecurityPolicy`] is checked by the [`Provider`](crate::Provider) before every fetch (and
+//! every redirect), so that embedders can stop documents reaching URLs they shouldn't:
+//!
+//!  - only URLs with an allowed scheme are fetched
+//!  - URLs on blocked hosts (or their subdomains) are never fetched
+//!  - `http:` requests made by `https:` documents ("mixed content") can be blocked, or upgraded
+//!    to `https:`
+//!  - documents can be kept from making cross-origin requests (though they may still navigate to
+//!    other origins), or from reading local files, depending on their origin
+//!
+//! The default policy allows everything, so that apps which load their own content work as
+//! before.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::Ipv4Addr;
+
+use blitz_traits::net::Url;
+
+/// What to do with `http:` requests made by `https:` documents
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MixedContent {
+    /// Fetch them as they are
+    #[default]
+    Allow,
+    /// Fetch them over `https:` instead
+    Upgrade,
+    /// Don't fetch them
+    Block,
+}
+
+/// What the documents of an origin may fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OriginPermissions {
+    /// Whether they may fetch from other origins. `data:` URLs are always allowed.
+    pub cross_origin: bool,
+    /// Whether they may read `file:` URLs (documents which are files themselves always may)
+    pub local_files: bool,
+}
+
+impl Default for OriginPermissions {
+    fn default() -> Self {
+        Self {
+            cross_origin: true,
+            local_files: true,
+        }
+    }
+}
+
+/// Why a [`SecurityPolicy`] refused a fetch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The scheme of the URL isn't allowed
+    DisallowedScheme(String),
+    /// The URL is on a blocked host
+    BlockedHost(String),
+    /// An `https:` document requested an `http:` URL
+    MixedContent(Url),
+    /// The document requested a URL from another origin
+    CrossOrigin(Url),
+    /// The document requested a `file:` URL
+    LocalFile(Url),
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DisallowedScheme(scheme) => write!(f, "The {scheme}: scheme is not allowed"),
+            Self::BlockedHost(host) => write!(f, "The host {host} is blocked"),
+            Self::MixedContent(url) => write!(f, "Mixed content blocked: {url}"),
+            Self::CrossOrigin(url) => write!(f, "Cross-origin request blocked: {url}"),
+            Self::LocalFile(url) => write!(f, "Local file access blocked: {url}"),
+        }
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// Which URLs documents may fetch, see the [module docs](self)
+///
+/// ```
+/// use blitz_net::{MixedContent, OriginPermissions, SecurityPolicy};
+/// use blitz_traits::net::Url;
+///
+/// let app = Url::parse("https://app.example").unwrap();
+/// let policy = SecurityPolicy::default()
+///     .with_allowed_schemes(["https", "data"])
+///     .with_blocked_host("ads.example")
+///     .with_mixed_content(MixedContent::Block)
+///     .with_default_permissions(OriginPermissions {
+///         cross_origin: false,
+///         local_files: false,
+///     })
+///     .with_origin_permissions(&app, OriginPermissions::default());
+///
+/// let mut url = Url::parse("https://cdn.example/logo.png").unwrap();
+/// assert!(policy.check(Some(&app), &mut url).is_ok());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SecurityPolicy {
+    /// The schemes which may be fetched, or `None` to allow every scheme
+    allowed_schemes: Option<HashSet<String>>,
+    blocked_hosts: Vec<String>,
+    mixed_content: MixedContent,
+    default_permissions: OriginPermissions,
+    /// Permissions by the ASCII serialization of the origin they apply to
+    origin_permissions: HashMap<String, OriginPermissions>,
+}
+
+impl SecurityPolicy {
+    /// Only fetch URLs with one of `schemes` (like `"https"`)
+    pub fn with_allowed_schemes<S: Into<String>>(
+        mut self,
+        schemes: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let schemes = schemes.into_iter().map(|scheme| scheme.into().to_ascii_lowercase());
+        self.allowed_schemes = Some(schemes.collect());
+        self
+    }
+
+    /// Never fetch URLs on `host`, or on its subdomains. Internationalized hosts may be given in
+    /// either their Unicode or their ASCII (`xn--`) form.
+    pub fn with_blocked_host(mut self, host: impl Into<String>) -> Self {
+        self.blocked_hosts.push(normalize_host(&host.into()));
+        self
+    }
+
+    pub fn with_mixed_content(mut self, mixed_content: MixedContent) -> Self {
+        self.mixed_content = mixed_content;
+        self
+    }
+
+    /// The permissions of documents whose origin has none of its own
+    pub fn with_default_permissions(mut self, permissions: OriginPermissions) -> Self {
+        self.default_permissions = permissions;
+        self
+    }
+
+    /// The permissions of documents from the origin of `url`. Documents with opaque origins (like
+    /// `file:` documents) all share the same permissions, so setting them for one sets them for
+    /// all of them.
+    pub fn with_origin_permissions(mut self, url: &Url, permissions: OriginPermissions) -> Self {
+        self.origin_permissions.insert(url.origin().ascii_serialization(), permissions);
+        self
+    }
+
+    /// The permissions of documents loaded from `document_url`
+    pub fn permissions(&self, document_url: &Url) -> OriginPermissions {
+        let origin = document_url.origin().ascii_serialization();
+        let permissions = self.origin_permissions.get(&origin);
+        permissions.copied().unwrap_or(self.default_permissions)
+    }
+
+    /// Check whether the document at `document_url` (if the request is made for a document) may
+    /// fetch `url`. Mixed content may be upgraded, by changing the scheme of `url` to `https:`.
+    pub fn check(
+        &self,
+        document_url: Option<&Url>,
+        url: &mut Url,
+    ) -> Result<(), PolicyViolation> {
+        if let Some(document_url) = document_url {
+            self.check_mixed_content(document_url, url)?;
+        }
+        self.check_url(url)?;
+
+        let Some(document_url) = document_url else {
+            return Ok(());
+        };
+        self.check_local_file(document_url, url)?;
+        // Opaque origins never equal each other, but a file may load the files beside it (and an
+        // app's bundled document its bundled assets)
+        let same_origin = url.origin() == document_url.origin()
+            || (url.scheme() == document_url.scheme() && !url.origin().is_tuple());
+        let cross_origin = self.permissions(document_url).cross_origin;
+        if !cross_origin && !same_origin && url.scheme() != "data" {
+            return Err(PolicyViolation::CrossOrigin(url.clone()));
+        }
+        Ok(())
+    }
+
+    /// Check whether the document at `document_url` (if the navigation was started by a document)
+    /// may navigate to `url`. Documents may navigate to other origins, and from `https:` to
+    /// `http:`, so only the scheme and host of `url` are checked, and whether the document may
+    /// read local files.
+    pub fn check_navigation(
+        &self,
+        document_url: Option<&Url>,
+        url: &Url,
+    ) -> Result<(), PolicyViolation> {
+        self.check_url(url)?;
+        match document_url {
+            Some(document_url) => self.check_local_file(document_url, url),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the scheme and host of `url`, which is all that can be checked without knowing the
+    /// document which requested it
+    pub fn check_url(&self, url: &Url) -> Result<(), PolicyViolation> {
+        let scheme = url.scheme();
+        let allowed = &self.allowed_schemes;
+        if allowed.as_ref().is_some_and(|allowed| !allowed.contains(scheme)) {
+            return Err(PolicyViolation::DisallowedScheme(scheme.to_string()));
+        }
+
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+        let host = normalize_host(host);
+        let blocked = self.blocked_hosts.iter().any(|blocked| {
+            let subdomain = host.strip_suffix(blocked.as_str());
+            subdomain.is_some_and(|subdomain| subdomain.is_empty() || subdomain.ends_with('.'))
+        });
+        if blocked {
+            return Err(PolicyViolation::BlockedHost(host.to_string()));
+        }
+        Ok(())
+    }
+
+    fn check_local_file(&self, document_url: &Url, url: &Url) -> Result<(), PolicyViolation> {
+        let local_files = self.permissions(document_url).local_files;
+        if url.scheme() == "file" && document_url.scheme() != "file" && !local_files {
+            return Err(PolicyViolation::LocalFile(url.clone()));
+        }
+        Ok(())
+    }
+
+    fn check_mixed_content(
+        &self,
+        document_url: &Url,
+        url: &mut Url,
+    ) -> Result<(), PolicyViolation> {
+        let secure = match url.scheme() {
+            "http" => "https",
+            "ws" => "wss",
+            _ => return Ok(()),
+        };
+        if document_url.scheme() != "https" || is_loopback(url) {
+            return Ok(());
+        }
+        match self.mixed_content {
+            MixedContent::Allow => Ok(()),
+            MixedContent::Upgrade => url
+                .set_scheme(secure)
+                .map_err(|_| PolicyViolation::MixedContent(url.clone())),
+            MixedContent::Block => Err(PolicyViolation::MixedContent(url.clone())),
+        }
+    }
+}
+
+/// `host` as blocked hosts are compared: in its ASCII form (as the hosts of URLs with schemes like
+/// `https:` are already), lowercase, and without the trailing dot of a fully qualified name
+fn normalize_host(host: &str) -> String {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    let url = Url::parse(&format!("http://{host}/"));
+    let ascii = url.ok().and_then(|url| url.host_str().map(str::to_string));
+    ascii.unwrap_or_else(|| host.to_ascii_lowercase())
+}
+
+/// Whether `url` is on this machine, which is trusted even without TLS
+pub(crate) fn is_loopback(url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    host == "localhost"
+        || host.ends_with(".localhost")
+        || host == "[::1]"
+        || host.parse::<Ipv4Addr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn schemes_and_hosts() {
+        let policy = SecurityPolicy::default()
+            .with_allowed_schemes(["https"])
+            .with_blocked_host("tracker.example");
+        assert!(policy.check_url(&url("https://example.com")).is_ok());
+        assert!(policy.check_url(&url("https://cdn.tracker.example/x.js")).is_err());
+        assert!(policy.check_url(&url("https://nottracker.example")).is_ok());
+        assert_eq!(
+            policy.check_url(&url("file:///etc/passwd")),
+            Err(PolicyViolation::DisallowedScheme("file".to_string()))
+        );
+    }
+
+    #[test]
+    fn blocked_hosts_are_normalized() {
+        let policy = SecurityPolicy::default()
+            .with_blocked_host("Tracker.example.")
+            .with_blocked_host("bücher.example");
+        assert!(policy.check_url(&url("https://tracker.example./x.js")).is_err());
+        assert!(policy.check_url(&url("https://cdn.TRACKER.example/x.js")).is_err());
+        assert!(policy.check_url(&url("https://xn--bcher-kva.example/")).is_err());
+        assert!(policy.check_url(&url("https://BÜCHER.example./")).is_err());
+        assert!(policy.check_url(&url("app://tracker.example./")).is_err());
+        assert!(policy.check_url(&url("https://bucher.example/")).is_ok());
+    }
+
+    #[test]
+    fn mixed_content_is_blocked_or_upgraded() {
+        let document = url("https://example.com/index.html");
+        let blocking = SecurityPolicy::default().with_mixed_content(MixedContent::Block);
+        assert!(blocking.check(Some(&document), &mut url("http://example.com/a.png")).is_err());
+        assert!(blocking.check(Some(&document), &mut url("http://localhost:8080/a.png")).is_ok());
+
+        let upgrading = SecurityPolicy::default().with_mixed_content(MixedContent::Upgrade);
+        let mut image = url("http://example.com:8080/a.png");
+        upgrading.check(Some(&document), &mut image).unwrap();
+        assert_eq!(image.as_str(), "https://example.com:8080/a.png");
+    }
+
+    #[test]
+    fn origin_permissions() {
+        let app = url("https://app.example/");
+        let policy = SecurityPolicy::default()
+            .with_default_permissions(OriginPermissions {
+                cross_origin: false,
+                local_files: false,
+            })
+            .with_origin_permissions(&app, OriginPermissions::default());
+
+        let other = url("https://other.example/");
+        assert!(policy.check(Some(&app), &mut url("https://cdn.example/a.css")).is_ok());
+        assert!(policy.check(Some(&other), &mut url("https://other.example/a.css")).is_ok());
+        assert!(policy.check(Some(&other), &mut url("data:text/css,a{}")).is_ok());
+        assert!(policy.check(Some(&other), &mut url("https://cdn.example/a.css")).is_err());
+        assert!(policy.check(Some(&other), &mut url("file:///etc/passwd")).is_err());
+
+        let file = url("file:///home/user/index.html");
+        assert!(policy.check(Some(&file), &mut url("file:///home/user/a.css")).is_ok());
+    }
+
+    #[test]
+    fn navigations_may_go_to_other_origins() {
+        let document = url("https://example.com/");
+        let policy = SecurityPolicy::default()
+            .with_blocked_host("ads.example")
+            .with_mixed_content(MixedContent::Block)
+            .with_default_permissions(OriginPermissions {
+                cross_origin: false,
+                local_files: false,
+            });
+        assert!(policy.check_navigation(Some(&document), &url("https://other.example/")).is_ok());
+        assert!(policy.check_navigation(Some(&document), &url("http://other.example/")).is_ok());
+        assert!(policy.check_navigation(Some(&document), &url("https://ads.example/")).is_err());
+        assert_eq!(
+            policy.check_navigation(Some(&document), &url("file:///etc/passwd")),
+            Err(PolicyViolation::LocalFile(url("file:///etc/passwd")))
+        );
+    }
+}
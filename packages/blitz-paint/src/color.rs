@@ -1,25 +1,314 @@
-use color::{AlphaColor, DynamicColor, Srgb};
-use style::color::AbsoluteColor;
+use std::cell::Cell;
+
+use anyrender::gradient::with_srgb_interpolation;
+use blitz_dom::{BaseDocument, SystemColor};
+use blitz_traits::render::OutputColorSpace;
+use color::ColorSpace as _;
+use color::{AlphaColor, ColorSpaceTag, DisplayP3, DynamicColor, Flags, Missing, Oklab, Oklch, Srgb};
+use style::color::{AbsoluteColor, ColorFlags, ColorSpace};
 
 pub type Color = AlphaColor<Srgb>;
 
 pub trait ToColorColor {
-    /// Converts a color into the `AlphaColor<Srgb>` type from the `color` crate
+    /// Converts a color into the `AlphaColor<Srgb>` type from the `color` crate, mapping colors
+    /// outside the sRGB gamut into it
     fn as_srgb_color(&self) -> Color;
 
-    /// Converts a color into the `DynamicColor` type from the `color` crate
+    /// Converts a color into `space`, mapping colors outside its gamut into it. The components
+    /// are encoded in `space`, but typed as sRGB as that is what peniko and the backends take.
+    fn as_output_color(&self, space: OutputColorSpace) -> Color;
+
+    /// Converts a color into the `DynamicColor` type from the `color` crate, keeping its color
+    /// space and missing (`none`) components
     fn as_dynamic_color(&self) -> DynamicColor;
 }
 impl ToColorColor for AbsoluteColor {
     fn as_srgb_color(&self) -> Color {
-        Color::new(
-            *self
-                .to_color_space(style::color::ColorSpace::Srgb)
-                .raw_components(),
-        )
+        self.as_output_color(OutputColorSpace::Srgb)
+    }
+
+    fn as_output_color(&self, space: OutputColorSpace) -> Color {
+        let color = forced_dark(self);
+        let stylo_space = match space {
+            OutputColorSpace::Srgb => ColorSpace::Srgb,
+            OutputColorSpace::DisplayP3 => ColorSpace::DisplayP3,
+        };
+        let [c0, c1, c2, alpha] = *color.to_color_space(stylo_space).raw_components();
+        if in_gamut([c0, c1, c2]) {
+            return Color::new([c0, c1, c2, alpha]);
+        }
+
+        let [l, c, h, _] = *color.to_color_space(ColorSpace::Oklch).raw_components();
+        let [c0, c1, c2] = match space {
+            OutputColorSpace::Srgb => gamut_map::<Srgb>([l, c, h]),
+            OutputColorSpace::DisplayP3 => gamut_map::<DisplayP3>([l, c, h]),
+        };
+        Color::new([c0, c1, c2, alpha])
     }
 
     fn as_dynamic_color(&self) -> DynamicColor {
-        DynamicColor::from_alpha_color(self.as_srgb_color())
+        let cs = match self.color_space {
+            // Stylo and `color` scale the components of these differently, so go through sRGB.
+            // Forced dark colors are converted anyway, so they lose their missing components too.
+            _ if FORCED_DARK.get() => return DynamicColor::from_alpha_color(self.as_srgb_color()),
+            ColorSpace::Hsl | ColorSpace::Hwb => {
+                return DynamicColor::from_alpha_color(self.as_srgb_color());
+            }
+            space => color_space_tag(space),
+        };
+
+        let mut missing = Missing::default();
+        let none_flags = [
+            ColorFlags::C0_IS_NONE,
+            ColorFlags::C1_IS_NONE,
+            ColorFlags::C2_IS_NONE,
+            ColorFlags::ALPHA_IS_NONE,
+        ];
+        for (idx, flag) in none_flags.into_iter().enumerate() {
+            if self.flags.contains(flag) {
+                missing.insert(idx);
+            }
+        }
+        DynamicColor {
+            cs,
+            flags: Flags::from_missing(missing),
+            components: *self.raw_components(),
+        }
+    }
+}
+
+thread_local! {
+    /// Whether the colors being painted are darkened for forced dark mode
+    static FORCED_DARK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The range of OKLab lightness colors are mapped into in forced dark mode. White becomes a dark
+/// gray rather than black, and black an off-white.
+const FORCED_DARK_LIGHTNESS: (f32, f32) = (0.18, 0.93);
+
+/// Turns forced dark mode on or off for the colors painted on this thread, until dropped
+pub(crate) struct ForcedDarkGuard(bool);
+
+impl ForcedDarkGuard {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self(FORCED_DARK.replace(enabled))
+    }
+}
+
+impl Drop for ForcedDarkGuard {
+    fn drop(&mut self) {
+        FORCED_DARK.set(self.0);
+    }
+}
+
+fn invert_lightness(lightness: f32) -> f32 {
+    let (min, max) = FORCED_DARK_LIGHTNESS;
+    min + (1.0 - lightness.clamp(0.0, 1.0)) * (max - min)
+}
+
+/// The color as painted: with its lightness inverted (keeping its hue) in forced dark mode
+fn forced_dark(color: &AbsoluteColor) -> AbsoluteColor {
+    if !FORCED_DARK.get() {
+        return *color;
+    }
+    let [l, c, h, alpha] = *color.to_color_space(ColorSpace::Oklch).raw_components();
+    AbsoluteColor::new(ColorSpace::Oklch, invert_lightness(l), c, h, alpha)
+}
+
+/// A color which doesn't come from a computed style, as painted
+pub(crate) fn forced_dark_color(color: Color) -> Color {
+    if !FORCED_DARK.get() {
+        return color;
+    }
+    color.map_lightness(invert_lightness)
+}
+
+/// A system color of the document's used color scheme, as painted
+pub(crate) fn system_color(dom: &BaseDocument, color: SystemColor) -> Color {
+    forced_dark_color(dom.system_color(color))
+}
+
+/// The `color` crate's tag for a stylo color space
+pub fn color_space_tag(space: ColorSpace) -> ColorSpaceTag {
+    match space {
+        ColorSpace::Srgb => ColorSpaceTag::Srgb,
+        ColorSpace::Hsl => ColorSpaceTag::Hsl,
+        ColorSpace::Hwb => ColorSpaceTag::Hwb,
+        ColorSpace::Lab => ColorSpaceTag::Lab,
+        ColorSpace::Lch => ColorSpaceTag::Lch,
+        ColorSpace::Oklab => ColorSpaceTag::Oklab,
+        ColorSpace::Oklch => ColorSpaceTag::Oklch,
+        ColorSpace::SrgbLinear => ColorSpaceTag::LinearSrgb,
+        ColorSpace::DisplayP3 => ColorSpaceTag::DisplayP3,
+        ColorSpace::A98Rgb => ColorSpaceTag::A98Rgb,
+        ColorSpace::ProphotoRgb => ColorSpaceTag::ProphotoRgb,
+        ColorSpace::Rec2020 => ColorSpaceTag::Rec2020,
+        ColorSpace::XyzD50 => ColorSpaceTag::XyzD50,
+        ColorSpace::XyzD65 => ColorSpaceTag::XyzD65,
+    }
+}
+
+/// The difference in OKLab below which a color clipped to a gamut is taken as close enough
+const JUST_NOTICEABLE_DIFFERENCE: f32 = 0.02;
+const GAMUT_MAP_EPSILON: f32 = 0.0001;
+
+fn in_gamut(components: [f32; 3]) -> bool {
+    components.iter().all(|c| (0.0..=1.0).contains(c))
+}
+
+/// Map an OKLCH color into the gamut of `CS` by reducing its chroma until clipping it to the gamut
+/// makes no noticeable difference, as in CSS Color 4's gamut mapping algorithm. Returns its
+/// components in `CS`.
+fn gamut_map<CS: color::ColorSpace>(oklch: [f32; 3]) -> [f32; 3] {
+    let [lightness, chroma, hue] = oklch;
+    if lightness >= 1.0 {
+        return Oklab::convert::<CS>([1.0, 0.0, 0.0]);
+    }
+    if lightness <= 0.0 {
+        return Oklab::convert::<CS>([0.0, 0.0, 0.0]);
+    }
+
+    let with_chroma = |chroma: f32| Oklch::convert::<CS>([lightness, chroma, hue]);
+    let clip = |components: [f32; 3]| components.map(|c| c.clamp(0.0, 1.0));
+    let delta_eok = |a: [f32; 3], b: [f32; 3]| {
+        let (a, b) = (CS::convert::<Oklab>(a), CS::convert::<Oklab>(b));
+        let [dl, da, db] = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+        (dl * dl + da * da + db * db).sqrt()
+    };
+
+    let current = with_chroma(chroma);
+    let mut clipped = clip(current);
+    if delta_eok(clipped, current) < JUST_NOTICEABLE_DIFFERENCE {
+        return clipped;
+    }
+
+    // Binary search for the highest chroma whose clipped color is just noticeably different
+    let (mut min, mut max) = (0.0, chroma);
+    let mut min_in_gamut = true;
+    while max - min > GAMUT_MAP_EPSILON {
+        let chroma = (min + max) / 2.0;
+        let current = with_chroma(chroma);
+        if min_in_gamut && in_gamut(current) {
+            min = chroma;
+            continue;
+        }
+        clipped = clip(current);
+        let delta = delta_eok(clipped, current);
+        if delta < JUST_NOTICEABLE_DIFFERENCE {
+            if JUST_NOTICEABLE_DIFFERENCE - delta < GAMUT_MAP_EPSILON {
+                return clipped;
+            }
+            min_in_gamut = false;
+            min = chroma;
+        } else {
+            max = chroma;
+        }
+    }
+    clipped
+}
+
+/// Re-encode the stops of `gradient` in `space` for surfaces which aren't sRGB.
+///
+/// Backends interpolate between the encoded components, in the gradient's interpolation space
+/// taking them as sRGB. The stops are interpolated in a linear working space instead: Display P3
+/// has the transfer function of sRGB, so interpolating its components in linear sRGB interpolates
+/// them in linear Display P3. Interpolating in any linear space (or XYZ) gives the same colors, so
+/// only gradients in other spaces are first resampled in their own interpolation space.
+pub(crate) fn encode_gradient_stops(gradient: &mut peniko::Gradient, space: OutputColorSpace) {
+    if space == OutputColorSpace::Srgb {
+        return;
+    }
+    let is_linear = matches!(
+        gradient.interpolation_cs,
+        ColorSpaceTag::LinearSrgb | ColorSpaceTag::XyzD50 | ColorSpaceTag::XyzD65
+    );
+    if !is_linear {
+        *gradient = with_srgb_interpolation(gradient);
+    }
+    for stop in gradient.stops.iter_mut() {
+        let [l, c, h, alpha] = stop.color.convert(ColorSpaceTag::Oklch).components;
+        let [c0, c1, c2] = gamut_map::<DisplayP3>([l, c, h]);
+        stop.color = DynamicColor::from_alpha_color(Color::new([c0, c1, c2, alpha]));
+    }
+    gradient.interpolation_cs = ColorSpaceTag::LinearSrgb;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamut_mapping_keeps_in_gamut_colors() {
+        // An sRGB orange, in OKLCH
+        let oklch = Srgb::convert::<Oklch>([1.0, 0.5, 0.0]);
+        let mapped = gamut_map::<Srgb>(oklch);
+        for (mapped, expected) in mapped.iter().zip([1.0, 0.5, 0.0]) {
+            assert!((mapped - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn gamut_mapping_reduces_chroma() {
+        // Display P3's most saturated green is outside sRGB, but within its own gamut
+        let oklch = DisplayP3::convert::<Oklch>([0.0, 1.0, 0.0]);
+        assert!(!in_gamut(Oklch::convert::<Srgb>(oklch)));
+        let mapped = gamut_map::<Srgb>(oklch);
+        assert!(in_gamut(mapped));
+        // Mapping keeps the hue (green) rather than clipping towards another one
+        assert!(mapped[1] > 0.9 && mapped[0] < mapped[1] && mapped[2] < mapped[1]);
+        assert!(in_gamut(gamut_map::<DisplayP3>(oklch)));
+    }
+
+    #[test]
+    fn p3_gradients_are_interpolated_in_linear_light() {
+        let red_to_blue = |space| {
+            let mut gradient = peniko::Gradient::new_linear((0.0, 0.0), (1.0, 0.0))
+                .with_stops([Color::new([1.0, 0.0, 0.0, 1.0]), Color::new([0.0, 0.0, 1.0, 1.0])]);
+            gradient.interpolation_cs = space;
+            encode_gradient_stops(&mut gradient, OutputColorSpace::DisplayP3);
+            gradient
+        };
+
+        // Interpolating in linear sRGB is interpolating in linear Display P3, so the stops are
+        // just re-encoded
+        let linear = red_to_blue(ColorSpaceTag::LinearSrgb);
+        assert_eq!(linear.interpolation_cs, ColorSpaceTag::LinearSrgb);
+        assert_eq!(linear.stops.len(), 2);
+        let p3_red = Srgb::convert::<DisplayP3>([1.0, 0.0, 0.0]);
+        let [r, g, b, _] = linear.stops[0].color.components;
+        for (component, expected) in [r, g, b].iter().zip(p3_red) {
+            assert!((component - expected).abs() < 1e-3);
+        }
+
+        // Gradients in other spaces are resampled in them first, and keep their stops' offsets
+        let oklab = red_to_blue(ColorSpaceTag::Oklab);
+        assert_eq!(oklab.interpolation_cs, ColorSpaceTag::LinearSrgb);
+        assert!(oklab.stops.len() > 2);
+        assert_eq!(oklab.stops.first().map(|stop| stop.offset), Some(0.0));
+        assert_eq!(oklab.stops.last().map(|stop| stop.offset), Some(1.0));
+    }
+
+    #[test]
+    fn forced_dark_inverts_lightness_until_the_guard_is_dropped() {
+        let lightness = |color: Color| color.convert::<Oklab>().components[0];
+        let white = Color::new([1.0, 1.0, 1.0, 1.0]);
+        let black = Color::new([0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(forced_dark_color(white).components, white.components);
+
+        let outer = ForcedDarkGuard::new(true);
+        assert!((lightness(forced_dark_color(white)) - FORCED_DARK_LIGHTNESS.0).abs() < 1e-3);
+        assert!((lightness(forced_dark_color(black)) - FORCED_DARK_LIGHTNESS.1).abs() < 1e-3);
+        let styled = AbsoluteColor::srgb_legacy(255, 255, 255, 1.0);
+        let [l, ..] = *forced_dark(&styled).raw_components();
+        assert!((l - FORCED_DARK_LIGHTNESS.0).abs() < 1e-3);
+
+        // Nested guards restore the state they found, so painting a subtree undarkened doesn't
+        // undarken what's painted after it
+        let inner = ForcedDarkGuard::new(false);
+        assert_eq!(forced_dark_color(white).components, white.components);
+        drop(inner);
+        assert_ne!(forced_dark_color(white).components, white.components);
+        drop(outer);
+        assert_eq!(forced_dark_color(white).components, white.components);
     }
 }
@@ -0,0 +1,118 @@
+//! Skipping the paint of elements which can't be seen
+//!
+//! Elements are culled along with their descendants when everything they paint (their border box
+//! and overflowing contents, grown to fit their shadows and outline, then transformed) is outside
+//! the visible area, or when they're fully transparent. The visible area is the viewport (or the
+//! pinch-zoomed part of it, or the page's content area), narrowed to the padding box of each
+//! ancestor which clips its contents.
+//!
+//! The overflowing contents of an element are laid out boxes, so descendants which are
+//! transformed or have shadows or outlines can paint outside of them. Elements with such
+//! descendants (which aren't clipped by another descendant) are painted wherever they are.
+//!
+//! Elements with `visibility: hidden` only paint their descendants, which can be visible.
+
+use std::cell::Cell;
+
+use blitz_dom::{BaseDocument, Node};
+use kurbo::{Affine, Rect};
+use style::values::computed::Overflow;
+use style::values::specified::Contain;
+
+use crate::render::{outline_extent, outset_shadow_rect};
+
+/// Counts of the elements painted and skipped in a scene
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+    /// Elements painted
+    pub painted: usize,
+    /// Elements skipped (with their descendants) because they were out of view
+    pub culled: usize,
+    /// Elements skipped (with their descendants) because none of them could be seen: they were
+    /// fully transparent, or hidden without any descendants to paint
+    pub invisible: usize,
+}
+
+thread_local! {
+    static LAST_CULL_STATS: Cell<CullStats> = Cell::new(CullStats::default());
+}
+
+/// The [`CullStats`] of the last scene painted on the calling thread
+pub fn cull_stats() -> CullStats {
+    LAST_CULL_STATS.with(Cell::get)
+}
+
+pub(crate) fn set_last_cull_stats(stats: CullStats) {
+    LAST_CULL_STATS.with(|last| last.set(stats));
+}
+
+/// Whether anything painted within `bounds` (in local device px) with `transform` can be seen
+/// in `cull_rect`
+pub(crate) fn is_in_view(bounds: Rect, transform: Affine, cull_rect: Rect) -> bool {
+    let bounds = transform.transform_rect_bbox(bounds);
+    bounds.x0 < cull_rect.x1
+        && bounds.x1 > cull_rect.x0
+        && bounds.y0 < cull_rect.y1
+        && bounds.y1 > cull_rect.y0
+}
+
+/// The part of `cull_rect` which can be seen inside `clip` (in local device px), when contents
+/// are clipped to it with `transform`
+pub(crate) fn clip_cull_rect(cull_rect: Rect, clip: Rect, transform: Affine) -> Rect {
+    let clip = transform.transform_rect_bbox(clip);
+    let rect = cull_rect.intersect(clip);
+    // Rects which don't intersect are left inverted, which nothing is in view of
+    Rect::new(rect.x0, rect.y0, rect.x1.max(rect.x0), rect.y1.max(rect.y0))
+}
+
+/// Whether a descendant of `node` may paint outside of the ink overflow rect of `node`, by being
+/// transformed or having an outset shadow or an outline. Descendants of elements which clip their
+/// contents are clipped with them, so aren't checked.
+pub(crate) fn has_overflowing_descendants(dom: &BaseDocument, node: &Node) -> bool {
+    let paint_children = node.paint_children.borrow();
+    let Some(children) = paint_children.as_ref() else {
+        return false;
+    };
+    children.iter().filter_map(|&id| dom.get_node(id)).any(|child| {
+        let Some(style) = child.primary_styles() else {
+            return false;
+        };
+        if !style.get_box().transform.0.is_empty()
+            || outset_shadow_rect(&style, Rect::ZERO, 1.0).is_some()
+            || outline_extent(&style) > 0.0
+        {
+            return true;
+        }
+        let box_style = style.get_box();
+        let clips = !matches!(box_style.overflow_x, Overflow::Visible)
+            || !matches!(box_style.overflow_y, Overflow::Visible)
+            || child.containment().contains(Contain::PAINT);
+        !clips && has_overflowing_descendants(dom, child)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elements_out_of_view_are_culled() {
+        let viewport = Rect::new(0.0, 0.0, 800.0, 600.0);
+        let element = Rect::new(0.0, 0.0, 100.0, 100.0);
+
+        assert!(is_in_view(element, Affine::translate((0.0, 550.0)), viewport));
+        assert!(!is_in_view(element, Affine::translate((0.0, 600.0)), viewport));
+        assert!(!is_in_view(element, Affine::translate((-120.0, 0.0)), viewport));
+        // Transforms grow the bounds (rotating by 45 degrees about the element's top-left corner
+        // swings its bottom-right corner into view)
+        let rotate = Affine::rotate(-std::f64::consts::FRAC_PI_4);
+        assert!(is_in_view(element, Affine::translate((-120.0, 0.0)) * rotate, viewport));
+
+        // Scroll containers narrow what's in view to their padding box
+        let scroller = clip_cull_rect(viewport, element, Affine::translate((0.0, 100.0)));
+        assert_eq!(scroller, Rect::new(0.0, 100.0, 100.0, 200.0));
+        assert!(!is_in_view(element, Affine::translate((0.0, 250.0)), scroller));
+        let offscreen = clip_cull_rect(viewport, element, Affine::translate((0.0, 700.0)));
+        assert!(!is_in_view(element, Affine::translate((0.0, 700.0)), offscreen));
+    }
+}
@@ -0,0 +1,321 @@
+//! Damage tracking, which finds the part of the window each frame changes
+//!
+//! [`DamageTracker`] remembers what painting read from each node in the last frame (as a
+//! fingerprint, like those of [retained fragments](crate::fragments)) and the area the node
+//! painted. A frame's damage is the area of the nodes whose fingerprints or areas changed (both
+//! where they were and where they are), and of the nodes which are no longer painted. Window
+//! renderers can then redraw just that part of the frame, see [`WindowRenderer::set_damage`].
+//!
+//! Changes which can move everything, like scrolling the viewport, pinch-zooming or resizing the
+//! window, damage the whole frame. So do changes to transformed elements and their descendants,
+//! whose painted area isn't known exactly, and frames painted while web fonts are loading.
+//!
+//! [`WindowRenderer::set_damage`]: anyrender::WindowRenderer::set_damage
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use blitz_dom::{BaseDocument, Node, ScrollbarOwner};
+use blitz_traits::render::RenderViewport;
+use blitz_traits::shell::ColorScheme;
+use kurbo::{Point, Rect, Vec2};
+use style::properties::ComputedValues;
+
+use crate::fragments::hash_node;
+use crate::render::{outline_extent, outset_shadow_rect};
+
+/// Finds the area (in device px) of each frame which differs from the frame before it
+#[derive(Debug, Default)]
+pub struct DamageTracker {
+    /// A fingerprint of the state which the painting of every node depends on
+    frame: Option<u64>,
+    /// What each node painted in the last frame
+    nodes: HashMap<usize, PaintedNode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PaintedNode {
+    /// `None` for nodes which paint state the DOM doesn't reflect (like the carets of text
+    /// inputs), which are damaged in every frame
+    fingerprint: Option<u64>,
+    /// The area the node paints in (in CSS px, relative to the viewport), or `None` if it isn't
+    /// known exactly
+    area: Option<Rect>,
+}
+
+impl DamageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the last frame, so that all of the next one is damaged
+    pub fn clear(&mut self) {
+        self.frame = None;
+        self.nodes.clear();
+    }
+
+    /// The area (in device px) of the frame which paints `doc` into `viewport` that differs from
+    /// the last frame this was called for, or `None` if all of it may. An empty rect means that
+    /// nothing changed.
+    ///
+    /// Styles and layout must be resolved, as for painting.
+    pub fn damage(&mut self, doc: &BaseDocument, viewport: RenderViewport) -> Option<Rect> {
+        let frame = frame_fingerprint(doc, viewport);
+        let previous_frame = self.frame.replace(frame);
+        let previous = std::mem::take(&mut self.nodes);
+        let scroll = doc.viewport_scroll();
+        let root_id = doc.root_element().id;
+        record_node(doc, root_id, Point::new(-scroll.x, -scroll.y), true, &mut self.nodes);
+
+        if previous_frame != Some(frame) || !doc.fonts().is_ready() {
+            return None;
+        }
+        let changed = self
+            .nodes
+            .iter()
+            .flat_map(|(node_id, node)| match previous.get(node_id) {
+                Some(previous) if previous.fingerprint.is_some() && previous == node => Vec::new(),
+                Some(previous) => vec![previous.area, node.area],
+                None => vec![node.area],
+            });
+        let removed = previous
+            .iter()
+            .filter(|(node_id, _)| !self.nodes.contains_key(node_id))
+            .map(|(_, node)| node.area);
+
+        let mut damage: Option<Rect> = None;
+        for area in changed.chain(removed) {
+            let area = area?;
+            damage = Some(damage.map_or(area, |damage| damage.union(area)));
+        }
+        let Some(damage) = damage else {
+            return Some(Rect::ZERO);
+        };
+        let window = Rect::new(0.0, 0.0, viewport.width as f64, viewport.height as f64);
+        // Pinch-zoom magnifies the damaged area along with the rest of the layout viewport
+        let magnified = doc.visual_viewport().transform(viewport.scale);
+        let damage = magnified.transform_rect_bbox(damage * viewport.scale);
+        Some(damage.expand().intersect(window))
+    }
+}
+
+/// Fingerprint the state of the document and viewport which the painting of every node depends on
+fn frame_fingerprint(doc: &BaseDocument, viewport: RenderViewport) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    doc.id().hash(&mut hasher);
+    (viewport.width, viewport.height, viewport.scale.to_bits()).hash(&mut hasher);
+    viewport.color_space.hash(&mut hasher);
+    let scroll = doc.viewport_scroll();
+    (scroll.x.to_bits(), scroll.y.to_bits()).hash(&mut hasher);
+    // Pinch-zooming and panning the magnified area move everything
+    let visual_viewport = doc.visual_viewport();
+    visual_viewport.scale.to_bits().hash(&mut hasher);
+    (visual_viewport.offset.x.to_bits(), visual_viewport.offset.y.to_bits()).hash(&mut hasher);
+    let devtools = doc.devtools();
+    (devtools.show_layout, devtools.highlight_hover).hash(&mut hasher);
+    if devtools.highlight_hover {
+        doc.get_hover_node_id().hash(&mut hasher);
+    }
+    (doc.is_forced_dark(), doc.used_color_scheme() == ColorScheme::Dark).hash(&mut hasher);
+    for scrollbar in doc.scrollbars(ScrollbarOwner::Viewport) {
+        (scrollbar.hovered, scrollbar.pressed).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Record what `node_id` and its descendants paint, given the position of the border box of its
+/// parent. Their areas are only `exact` if no ancestor is transformed.
+fn record_node(
+    doc: &BaseDocument,
+    node_id: usize,
+    parent_position: Point,
+    exact: bool,
+    nodes: &mut HashMap<usize, PaintedNode>,
+) {
+    let Some(node) = doc.get_node(node_id) else {
+        return;
+    };
+    if matches!(node.style().display, taffy::Display::None) || nodes.contains_key(&node_id) {
+        return;
+    }
+    let styles = node.primary_styles();
+    let exact = exact && styles.as_deref().is_none_or(|styles| !is_transformed(styles));
+    let layout = &node.unrounded_layout;
+    let position = parent_position + Vec2::new(layout.location.x as f64, layout.location.y as f64);
+
+    let mut hasher = DefaultHasher::new();
+    let fingerprint = hash_node(doc, node_id, true, &mut hasher).map(|()| {
+        hash_inline_contents(doc, node, &mut hasher);
+        hasher.finish()
+    });
+    let area = exact.then(|| painted_area(node, styles.as_deref(), position));
+    nodes.insert(node_id, PaintedNode { fingerprint, area });
+
+    let scrolled = position - Vec2::new(node.scroll_offset.x, node.scroll_offset.y);
+    for &child_id in node.paint_children.borrow().iter().flatten() {
+        record_node(doc, child_id, scrolled, exact, nodes);
+    }
+}
+
+fn is_transformed(styles: &ComputedValues) -> bool {
+    !styles.get_box().transform.0.is_empty()
+}
+
+/// The area (in CSS px) an element whose border box is at `position` paints in: its border box
+/// and overflowing contents, grown to fit its outset shadows and outline
+fn painted_area(node: &Node, styles: Option<&ComputedValues>, position: Point) -> Rect {
+    let layout = &node.unrounded_layout;
+    let size = (layout.size.width as f64, layout.size.height as f64);
+    let content_size = (layout.content_size.width as f64, layout.content_size.height as f64);
+    let border_box = Rect::from_origin_size(position, size);
+    let area = border_box.union(Rect::from_origin_size(position, content_size));
+    let Some(styles) = styles else {
+        return area;
+    };
+
+    let area = match outset_shadow_rect(styles, border_box, 1.0) {
+        Some(shadow_rect) => area.union(shadow_rect),
+        None => area,
+    };
+    // Glyphs can reach past the line boxes they're laid out in
+    let glyph_overhang = match node.flags.is_inline_root() {
+        true => styles.get_font().font_size.computed_size().px() as f64 / 2.0,
+        false => 0.0,
+    };
+    let extent = outline_extent(styles) + glyph_overhang;
+    area.inflate(extent, extent)
+}
+
+/// Hash the text and styles of the inline contents of an inline root, which it paints
+fn hash_inline_contents(doc: &BaseDocument, node: &Node, hasher: &mut DefaultHasher) {
+    if !node.flags.is_inline_root() {
+        return;
+    }
+    let mut stack: Vec<usize> = node.children.clone();
+    while let Some(child_id) = stack.pop() {
+        let Some(child) = doc.get_node(child_id) else {
+            continue;
+        };
+        child_id.hash(hasher);
+        if let Some(text) = child.text_data() {
+            text.content.hash(hasher);
+        }
+        child.primary_styles_generation().hash(hasher);
+        stack.extend(&child.children);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blitz_dom::testing::{append_styled, document_with_viewport};
+    use blitz_dom::{QualName, local_name, ns};
+    use blitz_traits::shell::Viewport;
+
+    use super::*;
+
+    /// A document with two boxes, one below the other. Returns the ids of the boxes.
+    fn two_boxes() -> (BaseDocument, [usize; 2]) {
+        let mut doc = document_with_viewport(Viewport::new(800, 600, 2.0, ColorScheme::Light));
+        let mut mutr = doc.mutate();
+        let html = append_styled(&mut mutr, 0, "html", "");
+        let body = append_styled(&mut mutr, html, "body", "margin: 0");
+        let first = "width: 100px; height: 50px; background: red";
+        let first = append_styled(&mut mutr, body, "div", first);
+        let second = "margin-left: 20px; width: 40px; height: 40px; background: blue";
+        let second = append_styled(&mut mutr, body, "div", second);
+        drop(mutr);
+        doc.resolve();
+
+        (doc, [first, second])
+    }
+
+    fn set_style(doc: &mut BaseDocument, node_id: usize, style: &str) {
+        let name = QualName::new(None, ns!(), local_name!("style"));
+        doc.mutate().set_attribute(node_id, name, style);
+        doc.resolve();
+    }
+
+    fn viewport() -> RenderViewport {
+        RenderViewport::new(800, 600, 2.0)
+    }
+
+    #[test]
+    fn unchanged_frames_have_no_damage() {
+        let (doc, _) = two_boxes();
+        let mut tracker = DamageTracker::new();
+        assert_eq!(tracker.damage(&doc, viewport()), None);
+        assert_eq!(tracker.damage(&doc, viewport()), Some(Rect::ZERO));
+    }
+
+    #[test]
+    fn restyled_elements_are_damaged() {
+        let (mut doc, [_, second]) = two_boxes();
+        let mut tracker = DamageTracker::new();
+        tracker.damage(&doc, viewport());
+
+        set_style(&mut doc, second, "margin-left: 20px; width: 40px; height: 40px");
+        // The second box is at (20, 50) CSS px, and the viewport is scaled by 2
+        let damage = tracker.damage(&doc, viewport());
+        assert_eq!(damage, Some(Rect::new(40.0, 100.0, 120.0, 180.0)));
+    }
+
+    #[test]
+    fn moved_elements_are_damaged_where_they_were_and_are() {
+        let (mut doc, [_, second]) = two_boxes();
+        let mut tracker = DamageTracker::new();
+        tracker.damage(&doc, viewport());
+
+        let moved = "margin-left: 30px; width: 40px; height: 40px; background: blue";
+        set_style(&mut doc, second, moved);
+        let damage = tracker.damage(&doc, viewport());
+        assert_eq!(damage, Some(Rect::new(40.0, 100.0, 140.0, 180.0)));
+    }
+
+    #[test]
+    fn removed_elements_are_damaged_where_they_were() {
+        let (mut doc, [_, second]) = two_boxes();
+        let mut tracker = DamageTracker::new();
+        tracker.damage(&doc, viewport());
+
+        doc.mutate().remove_node(second);
+        doc.resolve();
+        let damage = tracker.damage(&doc, viewport()).unwrap();
+        let removed = Rect::new(40.0, 100.0, 120.0, 180.0);
+        assert_eq!(damage.union(removed), damage);
+    }
+
+    #[test]
+    fn scrolling_and_transforms_damage_everything() {
+        let (mut doc, [first, _]) = two_boxes();
+        let mut tracker = DamageTracker::new();
+        tracker.damage(&doc, viewport());
+
+        let resized = RenderViewport::new(400, 600, 2.0);
+        assert_eq!(tracker.damage(&doc, resized), None);
+
+        doc.set_viewport_scroll(Point::new(0.0, 10.0));
+        assert_eq!(tracker.damage(&doc, resized), None);
+
+        let transformed = "width: 100px; height: 50px; transform: rotate(45deg)";
+        set_style(&mut doc, first, transformed);
+        assert_eq!(tracker.damage(&doc, resized), None);
+    }
+
+    #[test]
+    fn pinch_zoomed_damage_is_magnified() {
+        let (mut doc, [_, second]) = two_boxes();
+        let mut tracker = DamageTracker::new();
+        tracker.damage(&doc, viewport());
+
+        doc.pinch_zoom_by(2.0, Point::ZERO);
+        assert_eq!(tracker.damage(&doc, viewport()), None);
+
+        let generation = doc.get_node(second).unwrap().primary_styles_generation();
+        set_style(&mut doc, second, "margin-left: 20px; width: 40px; height: 40px");
+        assert_ne!(doc.get_node(second).unwrap().primary_styles_generation(), generation);
+        // The second box is at (20, 50) CSS px, scaled by 2 for the device and 2 by pinch-zoom
+        let damage = tracker.damage(&doc, viewport());
+        assert_eq!(damage, Some(Rect::new(80.0, 200.0, 240.0, 360.0)));
+    }
+}
@@ -0,0 +1,612 @@
+//! A rendering profile for e-paper and other low-color displays
+//!
+//! [`EinkPainter`] is a [`DocumentRenderer`] which paints a document with [`paint_scene`], but
+//! routes every drawing command through [`EinkScene`]. The adapter:
+//!
+//!  - Quantizes colors (including gradient stops) to a small number of gray levels
+//!  - Dithers images to the same gray levels (Floyd–Steinberg)
+//!  - Drops box shadows, which only produce muddy gray smears on e-paper
+//!  - Snaps text to pure black or white for maximum contrast
+//!
+//! Scenes can't turn off antialiasing themselves, so renderers are given the
+//! [`render_quality`](EinkProfile::render_quality) of the profile, which does (with backends which
+//! support it, see [`Antialiasing::Off`]).
+//!
+//! Quantized images are cached by a [`QuantizedImageCache`], whose memory is managed by the
+//! global [`CacheCoordinator`].
+//!
+//! Animations are suppressed at the document level with
+//! [`BaseDocument::set_animations_suppressed`]. For partial refresh, [`refresh_region`] grows the
+//! damage a [`DamageTracker`](crate::DamageTracker) finds to the tiles the panel refreshes.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyrender::{Antialiasing, Paint, PaintScene, RenderQuality};
+use blitz_dom::BaseDocument;
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use blitz_traits::render::{DocumentRenderer, RenderViewport};
+use kurbo::{Affine, Point, Rect, Shape, Stroke};
+use peniko::{BlendMode, Blob, BrushRef, Color, Fill, Gradient, Image, ImageFormat, WeakBlob};
+
+use crate::paint_scene;
+
+/// Configuration for the e-ink rendering profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EinkProfile {
+    /// Number of gray levels the display can show (clamped to `2..=256`)
+    pub gray_levels: u16,
+    /// Dither images instead of simply quantizing them
+    pub dither_images: bool,
+    /// Skip drawing box shadows
+    pub suppress_shadows: bool,
+    /// Luminance below which text is drawn black (and above which it is drawn white)
+    pub text_threshold: f32,
+    /// Antialias the edges of shapes and text, which e-paper panels show as fuzz
+    pub antialiasing: bool,
+}
+
+impl Default for EinkProfile {
+    /// A 16-level grayscale panel, which is what most e-readers provide
+    fn default() -> Self {
+        Self {
+            gray_levels: 16,
+            dither_images: true,
+            suppress_shadows: true,
+            text_threshold: 0.5,
+            antialiasing: false,
+        }
+    }
+}
+
+impl EinkProfile {
+    /// A profile for pure black and white panels
+    pub fn monochrome() -> Self {
+        Self {
+            gray_levels: 2,
+            ..Default::default()
+        }
+    }
+
+    /// The quality to render with, which renderers should be set to
+    pub fn render_quality(&self) -> RenderQuality {
+        let antialiasing = match self.antialiasing {
+            true => Antialiasing::default(),
+            false => Antialiasing::Off,
+        };
+        RenderQuality {
+            antialiasing,
+            box_shadows: !self.suppress_shadows,
+            ..RenderQuality::default()
+        }
+    }
+
+    fn levels(&self) -> f32 {
+        (self.gray_levels.clamp(2, 256) - 1) as f32
+    }
+
+    /// Snap a luminance value in `0.0..=1.0` to the nearest available gray level
+    fn quantize_luminance(&self, luminance: f32) -> f32 {
+        let levels = self.levels();
+        (luminance.clamp(0.0, 1.0) * levels).round() / levels
+    }
+
+    fn quantize_color(&self, color: Color) -> Color {
+        let [_, _, _, alpha] = color.components;
+        let gray = self.quantize_luminance(luminance(color));
+        Color::new([gray, gray, gray, alpha])
+    }
+
+    fn text_color(&self, color: Color) -> Color {
+        let [_, _, _, alpha] = color.components;
+        let gray = if luminance(color) < self.text_threshold {
+            0.0
+        } else {
+            1.0
+        };
+        Color::new([gray, gray, gray, alpha])
+    }
+
+    fn quantize_gradient(&self, gradient: &Gradient) -> Gradient {
+        let mut gradient = gradient.clone();
+        for stop in gradient.stops.0.iter_mut() {
+            let color = stop.color.to_alpha_color::<peniko::color::Srgb>();
+            stop.color = peniko::color::DynamicColor::from_alpha_color(self.quantize_color(color));
+        }
+        gradient
+    }
+
+    /// Convert an RGBA8 image to quantized (and optionally dithered) grayscale
+    fn quantize_image(&self, image: &Image) -> Image {
+        if image.format != ImageFormat::Rgba8 {
+            return image.clone();
+        }
+
+        let width = image.width as usize;
+        let height = image.height as usize;
+        let src = image.data.data();
+        let levels = self.levels();
+
+        let mut gray: Vec<f32> = src
+            .chunks_exact(4)
+            .map(|px| {
+                luminance(Color::new([
+                    px[0] as f32 / 255.0,
+                    px[1] as f32 / 255.0,
+                    px[2] as f32 / 255.0,
+                    1.0,
+                ]))
+            })
+            .collect();
+
+        let mut out = Vec::with_capacity(src.len());
+        for y in 0..height {
+            for x in 0..width {
+                let idx = y * width + x;
+                let old = gray[idx].clamp(0.0, 1.0);
+                let new = (old * levels).round() / levels;
+
+                if self.dither_images {
+                    // Floyd–Steinberg error diffusion
+                    let error = old - new;
+                    let mut spread = |dx: isize, dy: usize, weight: f32| {
+                        let nx = x as isize + dx;
+                        let ny = y + dy;
+                        if nx >= 0 && (nx as usize) < width && ny < height {
+                            gray[ny * width + nx as usize] += error * weight;
+                        }
+                    };
+                    spread(1, 0, 7.0 / 16.0);
+                    spread(-1, 1, 3.0 / 16.0);
+                    spread(0, 1, 5.0 / 16.0);
+                    spread(1, 1, 1.0 / 16.0);
+                }
+
+                let value = (new * 255.0).round() as u8;
+                out.extend_from_slice(&[value, value, value, src[idx * 4 + 3]]);
+            }
+        }
+
+        Image {
+            data: Blob::from(out),
+            ..image.clone()
+        }
+    }
+}
+
+/// Relative luminance of a color (using Rec. 709 coefficients on the sRGB-encoded components)
+fn luminance(color: Color) -> f32 {
+    let [r, g, b, _] = color.components;
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+struct QuantizedImage {
+    /// The image which was quantized, whose id isn't reused while it's alive
+    source: WeakBlob<u8>,
+    image: Image,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    images: HashMap<u64, QuantizedImage>,
+    /// Counts uses, for evicting the least recently used images first
+    clock: u64,
+}
+
+/// Images quantized by an [`EinkScene`], keyed by the id of the source image's data
+///
+/// The cache is registered with the global [`CacheCoordinator`] for as long as it's alive. Images
+/// are quantized again when they're painted after being evicted.
+pub struct QuantizedImageCache {
+    entries: Mutex<CacheEntries>,
+}
+
+impl QuantizedImageCache {
+    pub fn new() -> Arc<Self> {
+        let cache = Arc::new(Self {
+            entries: Mutex::default(),
+        });
+        let managed_cache: Arc<dyn ManagedCache> = cache.clone();
+        CacheCoordinator::global().register(&managed_cache, 1);
+        cache
+    }
+
+    /// `image` quantized with `profile`, which must be the same each time the cache is used
+    fn get_or_quantize(&self, image: &Image, profile: &EinkProfile) -> Image {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        if let Some(cached) = entries.images.get_mut(&image.data.id()) {
+            cached.last_used = clock;
+            return cached.image.clone();
+        }
+
+        let quantized = profile.quantize_image(image);
+        // Images which have been dropped can't be painted again
+        entries.images.retain(|_, cached| cached.source.upgrade().is_some());
+        let cached = QuantizedImage {
+            source: image.data.downgrade(),
+            image: quantized.clone(),
+            last_used: clock,
+        };
+        entries.images.insert(image.data.id(), cached);
+        drop(entries);
+        CacheCoordinator::global().enforce_budget();
+        quantized
+    }
+
+    /// Drop every cached image
+    pub fn clear(&self) {
+        self.lock().images.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheEntries> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for QuantizedImageCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuantizedImageCache")
+            .field("images", &self.lock().images.len())
+            .finish()
+    }
+}
+
+impl ManagedCache for QuantizedImageCache {
+    fn name(&self) -> &str {
+        "e-ink quantized images"
+    }
+
+    fn memory_usage(&self) -> usize {
+        let entries = self.lock();
+        entries.images.values().map(|cached| cached.image.data.len()).sum()
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        let mut entries = self.lock();
+        let mut usage: usize = entries.images.values().map(|cached| cached.image.data.len()).sum();
+        if usage <= target_bytes {
+            return;
+        }
+
+        let mut images: Vec<(u64, u64, usize)> = entries
+            .images
+            .iter()
+            .map(|(id, cached)| (*id, cached.last_used, cached.image.data.len()))
+            .collect();
+        images.sort_by_key(|&(_, last_used, _)| last_used);
+        for (id, _, len) in images {
+            if usage <= target_bytes {
+                break;
+            }
+            entries.images.remove(&id);
+            usage -= len;
+        }
+    }
+}
+
+/// A [`PaintScene`] adapter which applies an [`EinkProfile`] to every command before forwarding
+/// it to the wrapped scene.
+pub struct EinkScene<'a, S: PaintScene> {
+    inner: &'a mut S,
+    profile: EinkProfile,
+    image_cache: &'a QuantizedImageCache,
+}
+
+impl<'a, S: PaintScene> EinkScene<'a, S> {
+    pub fn new(
+        inner: &'a mut S,
+        profile: EinkProfile,
+        image_cache: &'a QuantizedImageCache,
+    ) -> Self {
+        Self {
+            inner,
+            profile,
+            image_cache,
+        }
+    }
+
+    fn quantized_image(&self, image: &Image) -> Image {
+        self.image_cache.get_or_quantize(image, &self.profile)
+    }
+}
+
+impl<S: PaintScene> PaintScene for EinkScene<'_, S> {
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        self.inner.push_layer(blend, alpha, transform, clip);
+    }
+
+    fn pop_layer(&mut self) {
+        self.inner.pop_layer();
+    }
+
+    fn stroke<'b>(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: impl Into<BrushRef<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        match brush.into() {
+            BrushRef::Solid(color) => self.inner.stroke(
+                style,
+                transform,
+                self.profile.quantize_color(color),
+                brush_transform,
+                shape,
+            ),
+            BrushRef::Gradient(gradient) => {
+                let gradient = self.profile.quantize_gradient(gradient);
+                self.inner
+                    .stroke(style, transform, &gradient, brush_transform, shape)
+            }
+            BrushRef::Image(image) => {
+                let image = self.quantized_image(image);
+                self.inner
+                    .stroke(style, transform, &image, brush_transform, shape)
+            }
+        }
+    }
+
+    fn fill<'b>(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: impl Into<Paint<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        match brush.into() {
+            Paint::Solid(color) => self.inner.fill(
+                style,
+                transform,
+                self.profile.quantize_color(color),
+                brush_transform,
+                shape,
+            ),
+            Paint::Gradient(gradient) => {
+                let gradient = self.profile.quantize_gradient(gradient);
+                self.inner
+                    .fill(style, transform, &gradient, brush_transform, shape)
+            }
+            Paint::Image(image) => {
+                let image = self.quantized_image(image);
+                self.inner
+                    .fill(style, transform, &image, brush_transform, shape)
+            }
+            // Custom paints are backend specific, so there is nothing we can do with them
+            paint @ Paint::Custom(_) => {
+                self.inner
+                    .fill(style, transform, paint, brush_transform, shape)
+            }
+        }
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        self.inner.render_text_buffer(
+            buffer,
+            position,
+            self.profile.text_color(color),
+            transform,
+        );
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        if self.profile.suppress_shadows {
+            return;
+        }
+        self.inner.draw_box_shadow(
+            transform,
+            rect,
+            self.profile.quantize_color(brush),
+            radius,
+            std_dev,
+        );
+    }
+}
+
+/// A [`DocumentRenderer`] which paints documents using an [`EinkProfile`]
+#[derive(Debug)]
+pub struct EinkPainter {
+    profile: EinkProfile,
+    image_cache: Arc<QuantizedImageCache>,
+}
+
+impl Default for EinkPainter {
+    fn default() -> Self {
+        Self::new(EinkProfile::default())
+    }
+}
+
+impl EinkPainter {
+    pub fn new(profile: EinkProfile) -> Self {
+        Self {
+            profile,
+            image_cache: QuantizedImageCache::new(),
+        }
+    }
+
+    pub fn profile(&self) -> &EinkProfile {
+        &self.profile
+    }
+
+    /// Drop all cached quantized images
+    pub fn clear_image_cache(&self) {
+        self.image_cache.clear();
+    }
+}
+
+impl<S: PaintScene> DocumentRenderer<BaseDocument, S> for EinkPainter {
+    fn render(&self, scene: &mut S, doc: &BaseDocument, viewport: RenderViewport) {
+        let mut scene = EinkScene::new(scene, self.profile, &self.image_cache);
+        paint_scene(
+            &mut scene,
+            doc,
+            viewport.scale,
+            viewport.width,
+            viewport.height,
+        );
+    }
+}
+
+/// A rectangular region of a rendered frame, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DamageRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The region of a `width` by `height` panel to refresh for a frame with `damage` (as found by a
+/// [`DamageTracker`](crate::DamageTracker)), grown to whole `tile_size` tiles for controllers
+/// which refresh the panel in tiles. `None` if nothing changed.
+pub fn refresh_region(
+    damage: Option<Rect>,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+) -> Option<DamageRect> {
+    let panel = Rect::new(0.0, 0.0, f64::from(width), f64::from(height));
+    let damage = damage.unwrap_or(panel).intersect(panel);
+    if damage.is_zero_area() {
+        return None;
+    }
+    let tile_size = f64::from(tile_size.max(1));
+    let snap_down = |value: f64| (value / tile_size).floor() * tile_size;
+    let snap_up = |value: f64| (value / tile_size).ceil() * tile_size;
+    let region = Rect::new(
+        snap_down(damage.x0),
+        snap_down(damage.y0),
+        snap_up(damage.x1),
+        snap_up(damage.y1),
+    )
+    .intersect(panel);
+    Some(DamageRect {
+        x: region.x0 as u32,
+        y: region.y0 as u32,
+        width: region.width() as u32,
+        height: region.height() as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_regions_cover_the_damaged_tiles() {
+        assert_eq!(refresh_region(Some(Rect::ZERO), 32, 30, 8), None);
+        assert_eq!(
+            refresh_region(Some(Rect::new(3.5, 9.0, 17.0, 29.5)), 32, 30, 8),
+            Some(DamageRect {
+                x: 0,
+                y: 8,
+                width: 24,
+                height: 22,
+            })
+        );
+        // Frames which may have changed everywhere refresh the whole panel
+        assert_eq!(
+            refresh_region(None, 32, 30, 8),
+            Some(DamageRect {
+                x: 0,
+                y: 0,
+                width: 32,
+                height: 30,
+            })
+        );
+    }
+
+    #[test]
+    fn antialiasing_is_off_by_default() {
+        let quality = EinkProfile::default().render_quality();
+        assert_eq!(quality.antialiasing, Antialiasing::Off);
+        assert!(!quality.box_shadows);
+        let profile = EinkProfile {
+            antialiasing: true,
+            ..EinkProfile::monochrome()
+        };
+        assert_eq!(profile.render_quality().antialiasing, Antialiasing::default());
+    }
+
+    fn image(pixels: Vec<u8>, width: u32) -> Image {
+        let height = pixels.len() as u32 / 4 / width;
+        Image::new(Blob::new(Arc::new(pixels)), ImageFormat::Rgba8, width, height)
+    }
+
+    #[test]
+    fn images_are_dithered_to_the_gray_levels() {
+        // A 4x4 mid gray image is dithered to a mix of black and white, keeping its alpha
+        let gray = image([128, 128, 128, 200].repeat(16), 4);
+        let quantized = EinkProfile::monochrome().quantize_image(&gray);
+        let pixels = quantized.data.data();
+        assert!(pixels.chunks_exact(4).all(|px| px[3] == 200));
+        let white = pixels.chunks_exact(4).filter(|px| px[..3] == [255; 3]).count();
+        let black = pixels.chunks_exact(4).filter(|px| px[..3] == [0; 3]).count();
+        assert_eq!(white + black, 16);
+        assert_eq!(white, 8);
+    }
+
+    #[test]
+    fn quantized_images_are_cached_until_evicted() {
+        let cache = QuantizedImageCache::new();
+        let profile = EinkProfile::default();
+        let first = image(vec![255, 0, 0, 255], 1);
+        let second = image(vec![0, 0, 255, 255], 1);
+
+        let quantized = cache.get_or_quantize(&first, &profile);
+        assert_eq!(cache.get_or_quantize(&first, &profile).data.id(), quantized.data.id());
+        cache.get_or_quantize(&second, &profile);
+        assert_eq!(cache.memory_usage(), 8);
+
+        // The least recently used image is evicted first
+        cache.get_or_quantize(&first, &profile);
+        cache.evict_to(4);
+        assert_eq!(cache.memory_usage(), 4);
+        assert_eq!(cache.get_or_quantize(&first, &profile).data.id(), quantized.data.id());
+
+        // Images which have been dropped are evicted when another is cached
+        let first_id = first.data.id();
+        drop(first);
+        cache.get_or_quantize(&second, &profile);
+        assert_eq!(cache.memory_usage(), 4);
+        assert!(!cache.lock().images.contains_key(&first_id));
+    }
+
+    #[test]
+    fn monochrome_profile_snaps_to_black_and_white() {
+        let profile = EinkProfile::monochrome();
+        let dark = profile.quantize_color(Color::new([0.2, 0.2, 0.2, 1.0]));
+        let light = profile.quantize_color(Color::new([0.8, 0.8, 0.8, 1.0]));
+        assert_eq!(dark.components, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(light.components, [1.0, 1.0, 1.0, 1.0]);
+    }
+}
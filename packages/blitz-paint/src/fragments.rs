@@ -0,0 +1,360 @@
+//! Retained scene fragments for paint-contained elements and scroll containers
+//!
+//! An element with `contain: paint` clips its contents to its box and is the containing block of
+//! its positioned descendants, so how it paints depends only on its own subtree. Such elements are
+//! painted as fragments (see [`PaintScene::draw_retained_fragment`]), which backends that retain
+//! fragments reuse in later frames without re-encoding them, even after scrolling, for as long as
+//! the subtree's fingerprint stays the same.
+//!
+//! Large scroll containers are promoted to scroll layers: their scrolled contents are painted as
+//! a fragment, recorded unscrolled, whose fingerprint leaves out the container's scroll offset.
+//! Scrolling them only moves the fragment, and their contents are painted again only once they
+//! change.
+//!
+//! The fingerprint covers what painting reads from each node: its layout and scroll offset (and
+//! whether its scrollbars are hovered or pressed), the generation of its computed styles (which
+//! changes whenever restyling replaces them), and its text and image data (including its
+//! background images). Subtrees containing text inputs, canvases or file inputs, which paint
+//! state the DOM doesn't reflect (carets, selections, externally rendered content), are never
+//! retained.
+//!
+//! [`PaintScene::draw_retained_fragment`]: anyrender::PaintScene::draw_retained_fragment
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use anyrender::FragmentKey;
+use blitz_dom::node::{ImageData, SpecialElementData};
+use blitz_dom::{BaseDocument, ScrollbarOwner};
+use taffy::Layout;
+
+/// Set in the ids of scroll layers' fragments, to tell them apart from the fragments of
+/// paint-contained elements (whose ids are their node's id)
+const SCROLL_LAYER_ID: u64 = 1 << 63;
+
+/// Fingerprint the painting of the subtree rooted at `node_id`, or `None` if it can't be retained
+pub(crate) fn paint_fingerprint(
+    dom: &BaseDocument,
+    node_id: usize,
+    scale: f64,
+    show_layout: bool,
+) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    scale.to_bits().hash(&mut hasher);
+    show_layout.hash(&mut hasher);
+    hash_subtree(dom, node_id, &mut hasher)?;
+    Some(hasher.finish())
+}
+
+/// The key of the fragment the scrolled contents of the scroll container `node_id` are painted
+/// as, or `None` if they can't be retained. Its version doesn't change as the container scrolls.
+pub(crate) fn scroll_layer_key(
+    dom: &BaseDocument,
+    node_id: usize,
+    scale: f64,
+    show_layout: bool,
+) -> Option<FragmentKey> {
+    let mut hasher = DefaultHasher::new();
+    scale.to_bits().hash(&mut hasher);
+    show_layout.hash(&mut hasher);
+    hash_node(dom, node_id, false, &mut hasher)?;
+    hash_descendants(dom, node_id, &mut hasher)?;
+    Some(FragmentKey {
+        id: node_id as u64 | SCROLL_LAYER_ID,
+        version: hasher.finish(),
+    })
+}
+
+fn hash_subtree(dom: &BaseDocument, node_id: usize, hasher: &mut DefaultHasher) -> Option<()> {
+    hash_node(dom, node_id, true, hasher)?;
+    hash_descendants(dom, node_id, hasher)
+}
+
+fn hash_descendants(dom: &BaseDocument, node_id: usize, hasher: &mut DefaultHasher) -> Option<()> {
+    let node = dom.get_node(node_id)?;
+
+    // Anonymous blocks aren't DOM children, but are positioned and painted like them. Their own
+    // children are DOM children, which are hashed below.
+    for &child_id in node.paint_children.borrow().iter().flatten() {
+        if dom.get_node(child_id).is_some_and(|child| child.is_anonymous()) {
+            hash_node(dom, child_id, true, hasher)?;
+        }
+    }
+
+    let pseudos = node.before.into_iter().chain(node.after);
+    for child_id in node.children.iter().copied().chain(pseudos) {
+        hash_subtree(dom, child_id, hasher)?;
+    }
+    Some(())
+}
+
+/// Hash what painting reads from a single node, including how it's scrolled if `scroll`
+pub(crate) fn hash_node(
+    dom: &BaseDocument,
+    node_id: usize,
+    scroll: bool,
+    hasher: &mut DefaultHasher,
+) -> Option<()> {
+    let node = dom.get_node(node_id)?;
+    node_id.hash(hasher);
+    hash_layout(&node.unrounded_layout, hasher);
+    if scroll {
+        node.scroll_offset.x.to_bits().hash(hasher);
+        node.scroll_offset.y.to_bits().hash(hasher);
+        for scrollbar in dom.scrollbars(ScrollbarOwner::Node(node_id)) {
+            (scrollbar.hovered, scrollbar.pressed).hash(hasher);
+        }
+    }
+    node.primary_styles_generation().hash(hasher);
+
+    if let Some(text) = node.text_data() {
+        text.content.hash(hasher);
+    }
+    if let Some(element) = node.element_data() {
+        match &element.special_data {
+            SpecialElementData::TextInput(_)
+            | SpecialElementData::Canvas(_)
+            | SpecialElementData::FileInput(_) => return None,
+            SpecialElementData::CheckboxInput(checked) => checked.hash(hasher),
+            _ => {}
+        }
+        if let Some(image) = element.image_data() {
+            hash_image(image, hasher);
+        }
+        for image in element.background_images.iter().flatten() {
+            hash_image(&image.image, hasher);
+        }
+        if let Some(inline_layout) = &element.inline_layout_data {
+            inline_layout.text.hash(hasher);
+        }
+    }
+    Some(())
+}
+
+/// Hash an image by identity, as loading or decoding it again replaces its data
+fn hash_image(image: &ImageData, hasher: &mut DefaultHasher) {
+    match image {
+        ImageData::Raster(image) => Arc::as_ptr(&image.data).hash(hasher),
+        #[cfg(feature = "svg")]
+        ImageData::Svg(svg) => {
+            // Painted from a stale raster until it has been rasterized at its new size
+            (Arc::as_ptr(svg), crate::svg_rasters_version()).hash(hasher)
+        }
+        ImageData::None => {}
+    }
+}
+
+fn hash_layout(layout: &Layout, hasher: &mut DefaultHasher) {
+    let rect = |rect: taffy::Rect<f32>| [rect.left, rect.right, rect.top, rect.bottom];
+    let values = [
+        layout.location.x,
+        layout.location.y,
+        layout.size.width,
+        layout.size.height,
+        layout.content_size.width,
+        layout.content_size.height,
+    ];
+    for value in values
+        .into_iter()
+        .chain(rect(layout.border))
+        .chain(rect(layout.padding))
+    {
+        value.to_bits().hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyrender::{Paint, PaintScene};
+    use blitz_dom::{
+        Attribute, DocumentConfig, DocumentMutator, LocalName, QualName, QuirksMode, local_name,
+        ns,
+    };
+    use blitz_traits::shell::{ColorScheme, Viewport};
+    use kurbo::{Affine, Point, Rect, Shape, Stroke};
+    use peniko::{BlendMode, BrushRef, Color, Fill};
+
+    use super::*;
+    use crate::paint_scene;
+
+    /// A scene which retains every fragment recorded in it, and records which fragments are
+    /// recorded and drawn in each frame
+    #[derive(Default)]
+    struct RetainingScene {
+        retained: HashSet<FragmentKey>,
+        recorded: Vec<FragmentKey>,
+        drawn: Vec<(FragmentKey, Affine)>,
+    }
+
+    impl PaintScene for RetainingScene {
+        fn reset(&mut self) {
+            self.recorded.clear();
+            self.drawn.clear();
+        }
+
+        fn push_layer(
+            &mut self,
+            _blend: impl Into<BlendMode>,
+            _alpha: f32,
+            _transform: Affine,
+            _clip: &impl Shape,
+        ) {
+        }
+
+        fn pop_layer(&mut self) {}
+
+        fn stroke<'a>(
+            &mut self,
+            _style: &Stroke,
+            _transform: Affine,
+            _brush: impl Into<BrushRef<'a>>,
+            _brush_transform: Option<Affine>,
+            _shape: &impl Shape,
+        ) {
+        }
+
+        fn fill<'a>(
+            &mut self,
+            _style: Fill,
+            _transform: Affine,
+            _brush: impl Into<Paint<'a>>,
+            _brush_transform: Option<Affine>,
+            _shape: &impl Shape,
+        ) {
+        }
+
+        fn render_text_buffer(
+            &mut self,
+            _buffer: &blitz_text::Buffer,
+            _position: Point,
+            _color: Color,
+            _transform: Affine,
+        ) {
+        }
+
+        fn draw_box_shadow(
+            &mut self,
+            _transform: Affine,
+            _rect: Rect,
+            _brush: Color,
+            _radius: f64,
+            _std_dev: f64,
+        ) {
+        }
+
+        fn supports_retained_fragments(&self) -> bool {
+            true
+        }
+
+        fn draw_retained_fragment(&mut self, key: FragmentKey, transform: Affine) -> bool {
+            if !self.retained.contains(&key) {
+                return false;
+            }
+            self.drawn.push((key, transform));
+            true
+        }
+
+        fn begin_fragment(&mut self, key: FragmentKey) {
+            self.recorded.push(key);
+        }
+
+        fn end_fragment(&mut self) {
+            self.retained.extend(self.recorded.last());
+        }
+    }
+
+    fn style_attr(style: &str) -> Vec<Attribute> {
+        vec![Attribute {
+            name: QualName::new(None, ns!(), local_name!("style")),
+            value: style.to_string(),
+        }]
+    }
+
+    /// Append an element with inline `style` to `parent`, returning its id
+    fn append(mutr: &mut DocumentMutator, parent: usize, local: LocalName, style: &str) -> usize {
+        let name = QualName::new(None, ns!(html), local);
+        let element = mutr.create_element(name, style_attr(style), QuirksMode::NoQuirks);
+        mutr.append_children(parent, &[element]);
+        element
+    }
+
+    /// A document with a scroll container large enough to be a scroll layer, holding a smaller
+    /// scroll container and a paragraph. Returns the ids of the scroll layer, the inner scroll
+    /// container, the inner container's contents and the paragraph's text.
+    fn document() -> (BaseDocument, [usize; 4]) {
+        let mut doc = BaseDocument::new(DocumentConfig {
+            viewport: Some(Viewport::new(800, 600, 1.0, ColorScheme::Light)),
+            ..DocumentConfig::for_testing()
+        })
+        .unwrap();
+
+        let mut mutr = doc.mutate();
+        let div = local_name!("div");
+        let html = append(&mut mutr, 0, local_name!("html"), "");
+        let body = append(&mut mutr, html, local_name!("body"), "margin: 0");
+        let scroller = "width: 400px; height: 300px; overflow: auto";
+        let scroller = append(&mut mutr, body, div.clone(), scroller);
+        let contents = append(&mut mutr, scroller, div.clone(), "height: 2000px");
+        let inner = "height: 100px; overflow: auto";
+        let inner = append(&mut mutr, contents, div.clone(), inner);
+        let inner_contents = append(&mut mutr, inner, div, "height: 500px");
+        let p = append(&mut mutr, contents, local_name!("p"), "");
+        let text = mutr.create_text_node("Hello");
+        mutr.append_children(p, &[text]);
+        drop(mutr);
+        doc.resolve();
+
+        (doc, [scroller, inner, inner_contents, text])
+    }
+
+    #[test]
+    fn scroll_layer_versions_ignore_only_the_layers_scrolling() {
+        let (mut doc, [scroller, inner, inner_contents, text]) = document();
+        let key = |doc: &BaseDocument| scroll_layer_key(doc, scroller, 1.0, false).unwrap();
+        let unscrolled = key(&doc);
+        assert_eq!(unscrolled.id, scroller as u64 | SCROLL_LAYER_ID);
+
+        doc.scroll_node_by(scroller, 0.0, -100.0);
+        assert_eq!(doc.get_node(scroller).unwrap().scroll_offset.y, 100.0);
+        assert_eq!(key(&doc), unscrolled);
+
+        // Scrolling a descendant, or changing its text or style, changes the layer
+        doc.scroll_node_by(inner, 0.0, -50.0);
+        let inner_scrolled = key(&doc);
+        assert_ne!(inner_scrolled, unscrolled);
+
+        doc.mutate().set_node_text(text, "Goodbye");
+        doc.resolve();
+        let edited = key(&doc);
+        assert_ne!(edited, inner_scrolled);
+        // Resolving again without restyling keeps the styles' generations
+        doc.resolve();
+        assert_eq!(key(&doc), edited);
+
+        let style = QualName::new(None, ns!(), local_name!("style"));
+        doc.mutate().set_attribute(inner_contents, style, "height: 500px; background: teal");
+        doc.resolve();
+        assert_ne!(key(&doc), edited);
+    }
+
+    #[test]
+    fn scroll_layers_are_reused_as_they_scroll() {
+        let (mut doc, [scroller, ..]) = document();
+        let mut scene = RetainingScene::default();
+
+        paint_scene(&mut scene, &doc, 1.0, 800, 600);
+        assert_eq!(scene.recorded.len(), 1);
+        let key = scene.recorded[0];
+        assert_eq!(key.id, scroller as u64 | SCROLL_LAYER_ID);
+        assert_eq!(scene.drawn, [(key, Affine::IDENTITY)]);
+
+        // The retained layer is moved rather than recorded again
+        doc.scroll_node_by(scroller, 0.0, -100.0);
+        paint_scene(&mut scene, &doc, 1.0, 800, 600);
+        assert!(scene.recorded.is_empty());
+        assert_eq!(scene.drawn, [(key, Affine::translate((0.0, -100.0)))]);
+    }
+}
@@ -1,8 +1,10 @@
 //! Gradient rendering utilities for converting CSS gradients to peniko gradients.
 
+use color::{ColorSpaceTag, DynamicColor, HueDirection};
 use kurbo::{self, Affine, Point, Rect, Vec2};
 use peniko::{self, Gradient};
 use style::color::AbsoluteColor;
+use style::color::mix::{ColorInterpolationMethod, HueInterpolationMethod};
 use style::values::{
     computed::{
         Angle, AngleOrPercentage, CSSPixelLength, Gradient as StyloGradient, LengthPercentage,
@@ -24,24 +26,27 @@ use style::values::{
 };
 use style_traits::owned_slice::OwnedSlice;
 
-use crate::color::{Color, ToColorColor};
+use crate::color::{ToColorColor, color_space_tag};
 
 type GradientItem<T> = GenericGradientItem<GenericColor<Percentage>, T>;
 type LinearGradient<'a> = (
     &'a LineDirection,
     &'a [GradientItem<LengthPercentage>],
+    &'a ColorInterpolationMethod,
     GradientFlags,
 );
 type RadialGradient<'a> = (
     &'a EndingShape<NonNegative<CSSPixelLength>, NonNegative<LengthPercentage>>,
     &'a GenericPosition<LengthPercentage, LengthPercentage>,
     &'a OwnedSlice<GenericGradientItem<GenericColor<Percentage>, LengthPercentage>>,
+    &'a ColorInterpolationMethod,
     GradientFlags,
 );
 type ConicGradient<'a> = (
     &'a Angle,
     &'a GenericPosition<LengthPercentage, LengthPercentage>,
     &'a OwnedSlice<GenericGradientItem<GenericColor<Percentage>, AngleOrPercentage>>,
+    &'a ColorInterpolationMethod,
     GradientFlags,
 );
 
@@ -57,11 +62,12 @@ pub(crate) fn to_peniko_gradient(
         GenericGradient::Linear {
             direction,
             items,
+            color_interpolation_method,
             flags,
             // compat_mode,
             ..
         } => linear_gradient(
-            (direction, items, *flags),
+            (direction, items, color_interpolation_method, *flags),
             origin_rect,
             bounding_box,
             scale,
@@ -71,17 +77,27 @@ pub(crate) fn to_peniko_gradient(
             shape,
             position,
             items,
+            color_interpolation_method,
             flags,
             // compat_mode,
             ..
-        } => radial_gradient((shape, position, items, *flags), origin_rect, current_color),
+        } => radial_gradient(
+            (shape, position, items, color_interpolation_method, *flags),
+            origin_rect,
+            current_color,
+        ),
         GenericGradient::Conic {
             angle,
             position,
             items,
+            color_interpolation_method,
             flags,
             ..
-        } => conic_gradient((angle, position, items, *flags), origin_rect, current_color),
+        } => conic_gradient(
+            (angle, position, items, color_interpolation_method, *flags),
+            origin_rect,
+            current_color,
+        ),
     }
 }
 
@@ -92,7 +108,7 @@ fn linear_gradient(
     scale: f64,
     current_color: &AbsoluteColor,
 ) -> (peniko::Gradient, Option<Affine>) {
-    let (direction, items, flags) = gradient;
+    let (direction, items, method, flags) = gradient;
 
     let center = bounding_box.center();
     let (start, end) = match direction {
@@ -140,6 +156,7 @@ fn linear_gradient(
     } else {
         peniko::Extend::Pad
     });
+    set_interpolation_method(&mut gradient, method, flags, items, current_color);
 
     let (first_offset, last_offset) = resolve_length_color_stops(
         current_color,
@@ -163,7 +180,7 @@ fn radial_gradient(
     rect: Rect,
     current_color: &AbsoluteColor,
 ) -> (peniko::Gradient, Option<Affine>) {
-    let (shape, position, items, flags) = gradient;
+    let (shape, position, items, method, flags) = gradient;
     let repeating = flags.contains(GradientFlags::REPEATING);
 
     let mut gradient = peniko::Gradient::new_radial((0.0, 0.0), 1.0).with_extend(if repeating {
@@ -171,6 +188,7 @@ fn radial_gradient(
     } else {
         peniko::Extend::Pad
     });
+    set_interpolation_method(&mut gradient, method, flags, items, current_color);
 
     let (width_px, height_px) = (
         position
@@ -272,7 +290,7 @@ fn conic_gradient(
     rect: Rect,
     current_color: &AbsoluteColor,
 ) -> (peniko::Gradient, Option<Affine>) {
-    let (angle, position, items, flags) = gradient;
+    let (angle, position, items, method, flags) = gradient;
 
     let repeating = flags.contains(GradientFlags::REPEATING);
     let mut gradient = peniko::Gradient::new_sweep((0.0, 0.0), 0.0, std::f32::consts::PI * 2.0)
@@ -281,6 +299,7 @@ fn conic_gradient(
         } else {
             peniko::Extend::Pad
         });
+    set_interpolation_method(&mut gradient, method, flags, items, current_color);
 
     let (first_offset, last_offset) = resolve_angle_color_stops(
         current_color,
@@ -305,6 +324,43 @@ fn conic_gradient(
     (gradient, gradient_transform)
 }
 
+/// Set the color space (and hue direction) that the gradient's stops are interpolated in.
+///
+/// Gradients without an explicit `in <colorspace>` are interpolated in Oklab, unless all of their
+/// colors use legacy syntax (`rgb()`, `hsl()`, `hwb()`, named or hex colors), in which case they
+/// are interpolated in sRGB. See <https://drafts.csswg.org/css-images-4/#color-interpolation>.
+fn set_interpolation_method<T>(
+    gradient: &mut Gradient,
+    method: &ColorInterpolationMethod,
+    flags: GradientFlags,
+    items: &[GradientItem<T>],
+    current_color: &AbsoluteColor,
+) {
+    if flags.contains(GradientFlags::HAS_DEFAULT_COLOR_INTERPOLATION_METHOD) {
+        let all_legacy = items.iter().all(|item| match item {
+            GenericGradientItem::SimpleColorStop(color)
+            | GenericGradientItem::ComplexColorStop { color, .. } => {
+                color.resolve_to_absolute(current_color).is_legacy_syntax()
+            }
+            GenericGradientItem::InterpolationHint(_) => true,
+        });
+        gradient.interpolation_cs = match all_legacy {
+            true => ColorSpaceTag::Srgb,
+            false => ColorSpaceTag::Oklab,
+        };
+        gradient.hue_direction = HueDirection::Shorter;
+        return;
+    }
+
+    gradient.interpolation_cs = color_space_tag(method.space);
+    gradient.hue_direction = match method.hue {
+        HueInterpolationMethod::Shorter => HueDirection::Shorter,
+        HueInterpolationMethod::Longer => HueDirection::Longer,
+        HueInterpolationMethod::Increasing => HueDirection::Increasing,
+        HueInterpolationMethod::Decreasing => HueDirection::Decreasing,
+    };
+}
+
 #[inline]
 fn resolve_length_color_stops(
     current_color: &AbsoluteColor,
@@ -439,19 +495,19 @@ fn resolve_color_stops<T>(
                 } else {
                     let mid_point = (hint - last_stop.offset) / (offset - last_stop.offset);
 
+                    // The stops may be in different color spaces, so mix them in the gradient's
+                    // interpolation space
+                    let interpolator = last_stop.color.interpolate(
+                        color,
+                        gradient.interpolation_cs,
+                        gradient.hue_direction,
+                    );
+
                     // Define interpolation function as a closure to avoid code duplication
-                    let interpolate_color = |t: f32| -> Color {
+                    let interpolate_color = |t: f32| -> DynamicColor {
                         let relative_offset = (t - last_stop.offset) / (offset - last_stop.offset);
                         let multiplier = relative_offset.powf(0.5f32.log(mid_point));
-                        let [last_r, last_g, last_b, last_a] = last_stop.color.components;
-                        let [r, g, b, a] = color.components;
-
-                        Color::new([
-                            (last_r + multiplier * (r - last_r)),
-                            (last_g + multiplier * (g - last_g)),
-                            (last_b + multiplier * (b - last_b)),
-                            (last_a + multiplier * (a - last_a)),
-                        ])
+                        interpolator.eval(multiplier)
                     };
 
                     // Add interpolated stops based on the mid-point position
@@ -463,7 +519,7 @@ fn resolve_color_stops<T>(
                                     * (INTERPOLATION_STEPS as f32 + step as f32)
                                     / INTERPOLATION_DENOMINATOR;
                             gradient.stops.push(peniko::ColorStop {
-                                color: interpolate_color(t).into(),
+                                color: interpolate_color(t),
                                 offset: t,
                             });
                         }
@@ -472,7 +528,7 @@ fn resolve_color_stops<T>(
                         for &factor in &[1.0 / 3.0, 2.0 / 3.0] {
                             let t = hint + (offset - hint) * factor;
                             gradient.stops.push(peniko::ColorStop {
-                                color: interpolate_color(t).into(),
+                                color: interpolate_color(t),
                                 offset: t,
                             });
                         }
@@ -481,7 +537,7 @@ fn resolve_color_stops<T>(
                         for &factor in &[1.0 / 3.0, 2.0 / 3.0] {
                             let t = last_stop.offset + (hint - last_stop.offset) * factor;
                             gradient.stops.push(peniko::ColorStop {
-                                color: interpolate_color(t).into(),
+                                color: interpolate_color(t),
                                 offset: t,
                             });
                         }
@@ -491,7 +547,7 @@ fn resolve_color_stops<T>(
                             let t =
                                 hint + (offset - hint) * (step as f32) / INTERPOLATION_DENOMINATOR;
                             gradient.stops.push(peniko::ColorStop {
-                                color: interpolate_color(t).into(),
+                                color: interpolate_color(t),
                                 offset: t,
                             });
                         }
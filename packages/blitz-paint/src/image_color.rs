@@ -0,0 +1,189 @@
+//! Images converted to the color space of the surface they're painted on
+//!
+//! Raster images and SVG rasters hold sRGB pixels (embedded color profiles aren't read yet), but
+//! a Display P3 surface interprets the pixels painted on it as Display P3, which would make images
+//! look oversaturated. Images painted in Display P3 are converted first. Conversions are cached by
+//! image, and the cache's memory is managed by the global [`CacheCoordinator`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use blitz_traits::render::OutputColorSpace;
+use peniko::{Blob, ImageFormat, WeakBlob};
+
+/// Linear sRGB to linear Display P3 (the two share a white point and a transfer function)
+const SRGB_TO_DISPLAY_P3: [[f32; 3]; 3] = [
+    [0.822_462_1, 0.177_538, 0.0],
+    [0.033_194_1, 0.966_805_8, 0.0],
+    [0.017_082_7, 0.072_397_4, 0.910_519_9],
+];
+
+/// `image` with its sRGB pixels converted to `space`
+pub(crate) fn image_in_color_space(image: peniko::Image, space: OutputColorSpace) -> peniko::Image {
+    if space == OutputColorSpace::Srgb || image.format != ImageFormat::Rgba8 {
+        return image;
+    }
+    let cache = ConvertedImageCache::global();
+    let data = match cache.get(image.data.id()) {
+        Some(data) => data,
+        None => {
+            let data = Blob::new(Arc::new(srgb_to_display_p3(image.data.data())));
+            cache.insert(&image.data, data.clone());
+            data
+        }
+    };
+    peniko::Image { data, ..image }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Convert RGBA8 pixels from sRGB to Display P3, leaving alpha as it is
+fn srgb_to_display_p3(pixels: &[u8]) -> Vec<u8> {
+    let decode: [f32; 256] = std::array::from_fn(|value| srgb_to_linear(value as f32 / 255.0));
+    let encode = |linear: f32| (linear_to_srgb(linear.clamp(0.0, 1.0)) * 255.0).round() as u8;
+
+    let mut converted = Vec::with_capacity(pixels.len());
+    for pixel in pixels.chunks_exact(4) {
+        let rgb = [pixel[0], pixel[1], pixel[2]].map(|channel| decode[usize::from(channel)]);
+        for row in SRGB_TO_DISPLAY_P3 {
+            converted.push(encode(row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]));
+        }
+        converted.push(pixel[3]);
+    }
+    converted
+}
+
+struct ConvertedImage {
+    /// The image which was converted, whose id isn't reused while it's alive
+    source: WeakBlob<u8>,
+    data: Blob<u8>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    images: HashMap<u64, ConvertedImage>,
+    /// Counts uses, for evicting the least recently used images first
+    clock: u64,
+}
+
+#[derive(Default)]
+struct ConvertedImageCache {
+    entries: Mutex<CacheEntries>,
+}
+
+impl ConvertedImageCache {
+    fn global() -> &'static ConvertedImageCache {
+        static GLOBAL: OnceLock<Arc<ConvertedImageCache>> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let cache = Arc::new(ConvertedImageCache::default());
+            let managed_cache: Arc<dyn ManagedCache> = cache.clone();
+            CacheCoordinator::global().register(&managed_cache, 1);
+            cache
+        })
+    }
+
+    fn get(&self, id: u64) -> Option<Blob<u8>> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let cached = entries.images.get_mut(&id)?;
+        cached.last_used = clock;
+        Some(cached.data.clone())
+    }
+
+    fn insert(&self, source: &Blob<u8>, data: Blob<u8>) {
+        let mut entries = self.lock();
+        // Conversions of images which have been dropped can't be painted again
+        entries.images.retain(|_, cached| cached.source.upgrade().is_some());
+        entries.clock += 1;
+        let cached = ConvertedImage {
+            source: source.downgrade(),
+            data,
+            last_used: entries.clock,
+        };
+        entries.images.insert(source.id(), cached);
+        drop(entries);
+        CacheCoordinator::global().enforce_budget();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheEntries> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Evicted images are converted again the next time they're painted
+impl ManagedCache for ConvertedImageCache {
+    fn name(&self) -> &str {
+        "color-converted images"
+    }
+
+    fn memory_usage(&self) -> usize {
+        let entries = self.lock();
+        entries.images.values().map(|cached| cached.data.len()).sum()
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        let mut entries = self.lock();
+        let mut usage: usize = entries.images.values().map(|cached| cached.data.len()).sum();
+        if usage <= target_bytes {
+            return;
+        }
+
+        let mut images: Vec<(u64, u64, usize)> = entries
+            .images
+            .iter()
+            .map(|(id, cached)| (*id, cached.last_used, cached.data.len()))
+            .collect();
+        images.sort_by_key(|&(_, last_used, _)| last_used);
+        for (id, _, len) in images {
+            if usage <= target_bytes {
+                break;
+            }
+            entries.images.remove(&id);
+            usage -= len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_converted_to_display_p3() {
+        // Red, white, mid gray and a translucent green
+        let pixels = vec![255, 0, 0, 255, 255, 255, 255, 255, 128, 128, 128, 255, 0, 255, 0, 64];
+        let image = peniko::Image::new(Blob::new(Arc::new(pixels)), ImageFormat::Rgba8, 4, 1);
+
+        let srgb = image_in_color_space(image.clone(), OutputColorSpace::Srgb);
+        assert_eq!(srgb.data.id(), image.data.id());
+
+        let p3 = image_in_color_space(image.clone(), OutputColorSpace::DisplayP3);
+        assert_eq!((p3.width, p3.height), (4, 1));
+        let converted = p3.data.data();
+        // sRGB red is `color(display-p3 0.9175 0.2003 0.1386)`
+        assert_eq!(&converted[..4], &[234, 51, 35, 255]);
+        // Neutral colors are the same in both spaces
+        assert_eq!(&converted[4..12], &[255, 255, 255, 255, 128, 128, 128, 255]);
+        assert_eq!(converted[15], 64);
+
+        let cached = image_in_color_space(image, OutputColorSpace::DisplayP3);
+        assert_eq!(cached.data.id(), p3.data.id());
+    }
+}
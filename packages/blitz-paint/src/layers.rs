@@ -1,8 +1,9 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use anyrender::PaintScene;
-use kurbo::{Affine, Shape};
-use peniko::Mix;
+use anyrender::{DisplayList, PaintScene};
+use blitz_dom::ClipRegion;
+use kurbo::{Affine, BezPath, Shape};
+use peniko::{BlendMode, Color, Compose, Fill, Mix};
 
 const LAYER_LIMIT: usize = 1024;
 
@@ -10,10 +11,36 @@ static LAYERS_USED: AtomicUsize = AtomicUsize::new(0);
 static LAYER_DEPTH: AtomicUsize = AtomicUsize::new(0);
 static LAYER_DEPTH_USED: AtomicUsize = AtomicUsize::new(0);
 static LAYERS_WANTED: AtomicUsize = AtomicUsize::new(0);
+static LAYERS_ELIDED: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts of the layers used to paint the last scene
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayerStats {
+    /// Layers pushed
+    pub used: usize,
+    /// Layers which would have been pushed without the limit on them
+    pub wanted: usize,
+    /// The deepest the layers were nested
+    pub max_depth: usize,
+    /// Opacity groups painted without a layer, by multiplying their opacity into their only
+    /// drawing's brush
+    pub elided: usize,
+}
+
+/// The [`LayerStats`] of the last scene painted
+pub fn layer_stats() -> LayerStats {
+    LayerStats {
+        used: LAYERS_USED.load(Ordering::SeqCst),
+        wanted: LAYERS_WANTED.load(Ordering::SeqCst),
+        max_depth: LAYER_DEPTH_USED.load(Ordering::SeqCst),
+        elided: LAYERS_ELIDED.load(Ordering::SeqCst),
+    }
+}
 
 pub(crate) fn reset_layer_stats() {
     LAYERS_USED.store(0, Ordering::SeqCst);
     LAYERS_WANTED.store(0, Ordering::SeqCst);
+    LAYERS_ELIDED.store(0, Ordering::SeqCst);
     LAYER_DEPTH.store(0, Ordering::SeqCst);
     LAYER_DEPTH_USED.store(0, Ordering::SeqCst);
 }
@@ -43,20 +70,30 @@ pub(crate) fn maybe_push_layer(
     }
     LAYERS_WANTED.fetch_add(1, Ordering::SeqCst);
 
-    // Check if clips are above limit
-    let layers_available = LAYERS_USED.load(Ordering::SeqCst) <= LAYER_LIMIT;
-    if !layers_available {
-        return false;
-    }
-
     let blend_mode = if opacity == 1.0 {
         Mix::Clip
     } else {
         Mix::Normal
     };
+    push_counted_layer(scene, blend_mode.into(), opacity, transform, shape)
+}
+
+/// Push a layer with `blend`, unless the limit on layers has been reached
+fn push_counted_layer(
+    scene: &mut impl PaintScene,
+    blend: BlendMode,
+    opacity: f32,
+    transform: Affine,
+    shape: &impl Shape,
+) -> bool {
+    // Check if clips are above limit
+    let layers_available = LAYERS_USED.load(Ordering::SeqCst) <= LAYER_LIMIT;
+    if !layers_available {
+        return false;
+    }
 
     // Actually push the clip layer
-    scene.push_layer(blend_mode, opacity, transform, shape);
+    scene.push_layer(blend, opacity, transform, shape);
 
     // Update accounting
     LAYERS_USED.fetch_add(1, Ordering::SeqCst);
@@ -72,3 +109,63 @@ pub(crate) fn maybe_pop_layer(scene: &mut impl PaintScene, condition: bool) {
         LAYER_DEPTH.fetch_sub(1, Ordering::SeqCst);
     }
 }
+
+/// Draw the recorded `group` with `opacity`. A group of one drawing is drawn with its brush's
+/// opacity multiplied by `opacity`, which looks the same as drawing it into a layer but is much
+/// cheaper. Others are drawn into a layer.
+pub(crate) fn draw_opacity_group(
+    scene: &mut impl PaintScene,
+    group: &DisplayList,
+    opacity: f32,
+    transform: Affine,
+    shape: &impl Shape,
+) {
+    if group.replay_with_alpha(scene, opacity) {
+        LAYERS_ELIDED.fetch_add(1, Ordering::SeqCst);
+        return;
+    }
+    maybe_with_layer(scene, true, opacity, transform, shape, |scene| group.replay(scene));
+}
+
+/// A `clip-path` pushed by [`push_clip_path`], to be popped by [`pop_clip_path`]
+pub(crate) enum ClipPathLayer {
+    /// A clip layer, which clips to a `nonzero` path
+    Clip,
+    /// An isolated layer which an `evenodd` path is masked out of when it's popped, as clip
+    /// layers always fill their shape with the `nonzero` rule
+    Mask { path: BezPath, transform: Affine },
+}
+
+/// Clip everything drawn until [`pop_clip_path`] to `clip`, scaled by `scale` and drawn with
+/// `transform`
+pub(crate) fn push_clip_path(
+    scene: &mut impl PaintScene,
+    clip: &ClipRegion,
+    scale: f64,
+    transform: Affine,
+) -> Option<ClipPathLayer> {
+    let path = Affine::scale(scale) * clip.path.clone();
+    if !clip.even_odd {
+        return maybe_push_layer(scene, true, 1.0, transform, &path).then_some(ClipPathLayer::Clip);
+    }
+
+    LAYERS_WANTED.fetch_add(1, Ordering::SeqCst);
+    let bounds = path.bounding_box();
+    push_counted_layer(scene, Mix::Normal.into(), 1.0, transform, &bounds)
+        .then_some(ClipPathLayer::Mask { path, transform })
+}
+
+pub(crate) fn pop_clip_path(scene: &mut impl PaintScene, layer: Option<ClipPathLayer>) {
+    match layer {
+        None => {}
+        Some(ClipPathLayer::Clip) => maybe_pop_layer(scene, true),
+        Some(ClipPathLayer::Mask { path, transform }) => {
+            // Keep what was drawn only where the path is filled
+            let mask = BlendMode::new(Mix::Normal, Compose::DestIn);
+            scene.push_layer(mask, 1.0, transform, &path.bounding_box());
+            scene.fill(Fill::EvenOdd, transform, Color::BLACK, None, &path);
+            scene.pop_layer();
+            maybe_pop_layer(scene, true);
+        }
+    }
+}
@@ -2,21 +2,44 @@
 //! an impl [`anyrender::PaintScene`].
 
 mod color;
+mod cull;
+mod damage;
 mod debug_overlay;
+pub mod eink;
+mod fragments;
 mod gradient;
+mod image_color;
 mod layers;
 mod multicolor_rounded_rect;
 mod non_uniform_rounded_rect;
+pub mod print;
 mod render;
+#[cfg(feature = "screenshot")]
 pub mod screenshot;
 mod sizing;
+#[cfg(feature = "svg")]
+mod svg_raster;
 mod text;
+pub mod text_extract;
+#[cfg(feature = "tile-rasters")]
+mod tile_raster;
+mod writing_mode;
 
 use anyrender::PaintScene;
 use blitz_dom::BaseDocument;
+use blitz_traits::render::{DocumentRenderer, OutputColorSpace, RenderViewport};
+pub use cull::{CullStats, cull_stats};
+pub use damage::DamageTracker;
+use kurbo::Rect;
 use layers::reset_layer_stats;
+pub use layers::{LayerStats, layer_stats};
+pub use print::{paint_page, paint_pages};
 use render::BlitzDomPainter;
+#[cfg(feature = "svg")]
+pub use svg_raster::{rasterize_svg_icon, svg_rasters_version, wake_when_svgs_rasterized};
+use writing_mode::TransformedScene;
 // Re-export screenshot types for public API
+#[cfg(feature = "screenshot")]
 pub use screenshot::{
     ScreenshotConfig, ScreenshotConfigBuilder, ScreenshotEngine, ScreenshotRequest,
 };
@@ -37,12 +60,72 @@ pub fn paint_scene(
     width: u32,
     height: u32,
 ) {
+    paint_scene_in_color_space(scene, dom, scale, width, height, OutputColorSpace::Srgb);
+}
+
+/// [`paint_scene`] for a surface whose colors are encoded in `color_space`. Colors outside its
+/// gamut are mapped into it.
+pub fn paint_scene_in_color_space(
+    scene: &mut impl PaintScene,
+    dom: &BaseDocument,
+    scale: f64,
+    width: u32,
+    height: u32,
+    color_space: OutputColorSpace,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("paint", width, height, scale).entered();
     reset_layer_stats();
 
     let devtools = *dom.devtools();
     let mut generator = BlitzDomPainter::new(dom, width, height, scale);
     generator.devtools = devtools;
-    generator.paint_scene(scene);
+    generator.color_space = color_space;
+
+    // Pinch-zoom magnifies the painted layout viewport
+    let visual_viewport = dom.visual_viewport();
+    if visual_viewport.is_magnified() {
+        let hidpi_scale = dom.viewport().hidpi_scale as f64;
+        let transform = visual_viewport.transform(hidpi_scale);
+        // Only the magnified part of the layout viewport can be seen
+        let window = Rect::new(0.0, 0.0, width as f64, height as f64);
+        generator.cull_rect = transform.inverse().transform_rect_bbox(window);
+        let mut scene = TransformedScene {
+            inner: scene,
+            transform,
+        };
+        generator.paint_scene(&mut scene);
+    } else {
+        generator.paint_scene(scene);
+    }
+}
+
+/// How many SVG rasters have been finished in the background. Without the `svg` feature SVGs are
+/// never rasterized, so it's always 0.
+#[cfg(not(feature = "svg"))]
+pub fn svg_rasters_version() -> u64 {
+    0
+}
+
+/// Wake `waker` once the SVGs being rasterized in the background are ready to be painted. Without
+/// the `svg` feature none ever are.
+#[cfg(not(feature = "svg"))]
+pub fn wake_when_svgs_rasterized(_painted_version: u64, _waker: &std::task::Waker) {}
+
+/// The default [`DocumentRenderer`], which paints a [`BaseDocument`] using [`paint_scene`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlitzPainter;
+
+impl<S: PaintScene> DocumentRenderer<BaseDocument, S> for BlitzPainter {
+    fn render(&self, scene: &mut S, doc: &BaseDocument, viewport: RenderViewport) {
+        let RenderViewport {
+            width,
+            height,
+            scale,
+            color_space,
+        } = viewport;
+        paint_scene_in_color_space(scene, doc, scale, width, height, color_space)
+    }
 }
 
 /// Paint a [`blitz_dom::BaseDocument`] with screenshot capabilities
@@ -62,6 +145,7 @@ pub fn paint_scene(
 /// # Returns
 ///
 /// A painter instance that can be used for additional screenshot operations
+#[cfg(feature = "screenshot")]
 pub fn paint_scene_with_screenshot<'dom>(
     scene: &mut impl PaintScene,
     dom: &'dom BaseDocument,
@@ -70,6 +154,8 @@ pub fn paint_scene_with_screenshot<'dom>(
     height: u32,
     screenshot_engine: Option<std::sync::Arc<ScreenshotEngine>>,
 ) -> BlitzDomPainter<'dom> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("paint", width, height, scale).entered();
     reset_layer_stats();
 
     let devtools = *dom.devtools();
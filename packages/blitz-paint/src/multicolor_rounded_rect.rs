@@ -27,9 +27,6 @@ pub struct ElementFrame {
     pub border_box: Rect,
     pub padding_box: Rect,
     pub content_box: Rect,
-    pub outline_box: Rect,
-
-    pub outline_width: f64,
 
     pub padding_width: taffy::Rect<f64>,
     pub border_width: taffy::Rect<f64>,
@@ -40,7 +37,6 @@ pub struct ElementFrame {
 impl ElementFrame {
     pub fn new(style: &ComputedValues, layout: &Layout, scale: f64) -> Self {
         let s_border = style.get_border();
-        let outline = style.get_outline();
 
         // Resolve and rescale
         // We have to scale since document pixels are not same same as rendered pixels
@@ -48,7 +44,6 @@ impl ElementFrame {
         let height: f64 = layout.size.height as f64 * scale;
         let border = layout.border.map(|p| p as f64 * scale);
         let padding = layout.padding.map(|p| p as f64 * scale);
-        let outline_width = scale * outline.outline_width.to_f64_px();
 
         let border_box = Rect::new(0.0, 0.0, width, height);
         let padding_box = Rect::new(
@@ -63,12 +58,6 @@ impl ElementFrame {
             width - border.right - padding.right,
             height - border.bottom - padding.bottom,
         );
-        let outline_box = Rect::new(
-            border.left - outline_width,
-            border.top - outline_width,
-            width + (outline_width * 2.0),
-            height + (outline_width * 2.0),
-        );
 
         // Resolve the radii to a length. need to downscale since the radii are in document pixels
         let resolve_w = CSSPixelLength::new((padding_box.width() / scale) as _);
@@ -110,14 +99,33 @@ impl ElementFrame {
             padding_box,
             border_box,
             content_box,
-            outline_box,
-            outline_width,
             padding_width: padding,
             border_width: border,
             border_radii,
         }
     }
 
+    /// The radii of the corners of the padding box, which are those of the border box less the
+    /// border widths
+    pub fn padding_radii(&self) -> NonUniformRoundedRectRadii {
+        let inner = |radius: Vec2, x: f64, y: f64| {
+            Vec2::new((radius.x - x).max(0.0), (radius.y - y).max(0.0))
+        };
+        let NonUniformRoundedRectRadii {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        } = self.border_radii;
+        let border = self.border_width;
+        NonUniformRoundedRectRadii {
+            top_left: inner(top_left, border.left, border.top),
+            top_right: inner(top_right, border.right, border.top),
+            bottom_right: inner(bottom_right, border.right, border.bottom),
+            bottom_left: inner(bottom_left, border.left, border.bottom),
+        }
+    }
+
     /// Construct a BezPath representing one edge of a box's border.
     /// Takes into account border-radius and the possibility that the edges
     /// are different colors.
@@ -171,20 +179,6 @@ impl ElementFrame {
         path
     }
 
-    /// Construct a bezpath drawing the outline
-    pub fn outline(&self) -> BezPath {
-        let mut path = BezPath::new();
-
-        // TODO: this has been known to produce quirky outputs with hugely rounded edges
-        self.shape(&mut path, CssBox::OutlineBox, Direction::Clockwise);
-        path.move_to(self.corner(Corner::TopLeft, CssBox::BorderBox));
-
-        self.shape(&mut path, CssBox::BorderBox, Direction::Anticlockwise);
-        path.move_to(self.corner(Corner::TopLeft, CssBox::BorderBox));
-
-        path
-    }
-
     /// Construct a bezpath drawing the frame border
     pub fn border_box_path(&self) -> BezPath {
         let mut path = BezPath::new();
@@ -261,7 +255,6 @@ impl ElementFrame {
 
     fn corner(&self, corner: Corner, css_box: CssBox) -> Point {
         let Rect { x0, y0, x1, y1 } = match css_box {
-            CssBox::OutlineBox => self.outline_box,
             CssBox::BorderBox => self.border_box,
             CssBox::PaddingBox => self.padding_box,
             CssBox::ContentBox => self.content_box,
@@ -443,7 +436,6 @@ impl ElementFrame {
         }
 
         let css_box = match side {
-            OutlineBox => return false,
             BorderBox => return false,
             PaddingBox => self.border_width,
             ContentBox => self.border_width + self.padding_width,
@@ -491,7 +483,6 @@ impl ElementFrame {
 
         let radii: Vec2 = match side {
             BorderBox => corner_radii,
-            OutlineBox => corner_radii + Vec2::new(self.outline_width, self.outline_width),
             PaddingBox => match corner {
                 TopLeft => Vec2 {
                     x: corner_radii.x - border_width.left,
@@ -593,7 +584,6 @@ enum Corner {
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(clippy::enum_variant_names, reason = "Use CSS standard terminology")]
 enum CssBox {
-    OutlineBox,
     BorderBox,
     PaddingBox,
     ContentBox,
@@ -1,6 +1,7 @@
+use std::f64::consts::{FRAC_PI_2, PI};
 use std::ops::{Mul, MulAssign};
 
-use kurbo::{Rect, Vec2};
+use kurbo::{Arc, BezPath, Point, Rect, Vec2};
 
 /// Radii for each corner of a non-uniform rounded rectangle.
 ///
@@ -24,20 +25,6 @@ pub struct NonUniformRoundedRectRadii {
     pub bottom_left: Vec2,
 }
 
-impl NonUniformRoundedRectRadii {
-    pub fn average(&self) -> f64 {
-        (self.top_left.x
-            + self.top_left.y
-            + self.top_right.x
-            + self.top_right.y
-            + self.bottom_left.x
-            + self.bottom_left.y
-            + self.bottom_right.x
-            + self.bottom_right.y)
-            / 8.0
-    }
-}
-
 impl Mul<f64> for NonUniformRoundedRectRadii {
     type Output = Self;
 
@@ -64,7 +51,6 @@ impl MulAssign<f64> for NonUniformRoundedRectRadii {
 ///
 /// By construction the rounded rectangle will have
 /// non-negative dimensions and radii clamped to half size of the rect.
-#[allow(dead_code)]
 #[derive(Clone, Copy, Default, Debug, PartialEq)]
 // #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 // #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -74,3 +60,75 @@ pub struct NonUniformRoundedRect {
     /// Radius of all four corners.
     radii: NonUniformRoundedRectRadii,
 }
+
+impl NonUniformRoundedRect {
+    /// A rounded rectangle, with `radii` scaled down (all by the same factor) where the radii of
+    /// adjacent corners would overlap, like CSS `border-radius`
+    pub fn new(rect: Rect, radii: NonUniformRoundedRectRadii) -> Self {
+        let rect = rect.abs();
+        let non_negative = |radius: Vec2| Vec2::new(radius.x.max(0.0), radius.y.max(0.0));
+        let radii = NonUniformRoundedRectRadii {
+            top_left: non_negative(radii.top_left),
+            top_right: non_negative(radii.top_right),
+            bottom_right: non_negative(radii.bottom_right),
+            bottom_left: non_negative(radii.bottom_left),
+        };
+        let factor = [
+            rect.width() / (radii.top_left.x + radii.top_right.x),
+            rect.width() / (radii.bottom_left.x + radii.bottom_right.x),
+            rect.height() / (radii.top_left.y + radii.bottom_left.y),
+            rect.height() / (radii.top_right.y + radii.bottom_right.y),
+        ]
+        .into_iter()
+        .fold(1.0, f64::min);
+
+        Self {
+            rect,
+            radii: radii * factor,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn radii(&self) -> NonUniformRoundedRectRadii {
+        self.radii
+    }
+
+    /// The outline of the rectangle, clockwise from its top left corner
+    pub fn path(&self) -> BezPath {
+        const TOLERANCE: f64 = 0.1;
+
+        let Rect { x0, y0, x1, y1 } = self.rect;
+        let NonUniformRoundedRectRadii {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        } = self.radii;
+        let corners = [
+            (Point::new(x0 + top_left.x, y0 + top_left.y), top_left, PI),
+            (Point::new(x1 - top_right.x, y0 + top_right.y), top_right, PI + FRAC_PI_2),
+            (Point::new(x1 - bottom_right.x, y1 - bottom_right.y), bottom_right, 0.0),
+            (Point::new(x0 + bottom_left.x, y1 - bottom_left.y), bottom_left, FRAC_PI_2),
+        ];
+
+        let mut path = BezPath::new();
+        for (center, radii, start_angle) in corners {
+            let arc = Arc::new(center, radii, start_angle, FRAC_PI_2, 0.0);
+            let (sin, cos) = start_angle.sin_cos();
+            let start = center + Vec2::new(radii.x * cos, radii.y * sin);
+            if path.elements().is_empty() {
+                path.move_to(start);
+            } else {
+                path.line_to(start);
+            }
+            if radii.x > 0.0 && radii.y > 0.0 {
+                arc.to_cubic_beziers(TOLERANCE, |p1, p2, p3| path.curve_to(p1, p2, p3));
+            }
+        }
+        path.close_path();
+        path
+    }
+}
@@ -0,0 +1,69 @@
+//! Painting a paginated document, one scene per page
+//!
+//! Paint every page of a document with [`paint_pages`], or paint pages one by one with
+//! [`paint_page`] from the callback of [`BaseDocument::paginate`] (while the document is laid out
+//! with `@media print` styles). Each page is painted at its full size: the root background covers
+//! the whole page, while the document is clipped to the content area inside the page's margins.
+
+use anyrender::PaintScene;
+use blitz_dom::BaseDocument;
+use blitz_dom::pagination::{Page, PageConfig, Pagination};
+use kurbo::{Point, Rect};
+
+use crate::layers::reset_layer_stats;
+use crate::render::BlitzDomPainter;
+
+/// The page a [`BlitzDomPainter`] paints, in CSS pixels
+pub(crate) struct PageArea {
+    /// The position in the document shown at the top left of the content area
+    pub(crate) scroll: Point,
+    /// The content area, relative to the page
+    pub(crate) content: Rect,
+}
+
+/// Paint `page` of `pagination` into `scene`, at `scale` device pixels per CSS pixel
+pub fn paint_page(
+    scene: &mut impl PaintScene,
+    dom: &BaseDocument,
+    pagination: &Pagination,
+    page: &Page,
+    scale: f64,
+) {
+    reset_layer_stats();
+
+    let config = &pagination.config;
+    let content = Rect::from_origin_size(
+        (config.margins.left as f64, config.margins.top as f64),
+        (config.content_width() as f64, page.height as f64),
+    );
+    let width = (config.width as f64 * scale).round() as u32;
+    let height = (config.height as f64 * scale).round() as u32;
+
+    let mut generator = BlitzDomPainter::new(dom, width, height, scale);
+    generator.page = Some(PageArea {
+        scroll: Point::new(0.0, page.top as f64),
+        content,
+    });
+    generator.paint_scene(scene);
+}
+
+/// Paginate `dom` into pages of `config`'s size and paint each one into a scene created by
+/// `new_scene`
+pub fn paint_pages<S: PaintScene>(
+    dom: &mut BaseDocument,
+    config: PageConfig,
+    scale: f64,
+    mut new_scene: impl FnMut() -> S,
+) -> Vec<S> {
+    dom.paginate(config, |dom, pagination| {
+        pagination
+            .pages
+            .iter()
+            .map(|page| {
+                let mut scene = new_scene();
+                paint_page(&mut scene, dom, pagination, page, scale);
+                scene
+            })
+            .collect()
+    })
+}
@@ -1,20 +1,29 @@
 mod background;
 mod box_shadow;
 mod form_controls;
+mod outline;
+mod scrollbars;
+mod tables;
+
+pub(crate) use box_shadow::outset_shadow_rect;
+pub(crate) use outline::outline_extent;
 
 use std::{cell::RefCell, collections::HashSet, rc::Rc, sync::Arc};
 
-use anyrender::{CustomPaint, Paint, PaintScene};
+use anyrender::{CustomPaint, DisplayList, FragmentKey, Paint, PaintScene};
+use blitz_dom::clip_path::clip_region;
 use blitz_dom::node::{
     ListItemLayout, ListItemLayoutPosition, Marker, NodeData, RasterImageData, TextInputData,
     TextNodeData,
 };
-use blitz_dom::{BaseDocument, ElementData, Node, local_name};
+use blitz_dom::{BaseDocument, ElementData, Node, SystemColor, local_name};
 use blitz_text;
 use blitz_traits::devtools::DevtoolSettings;
+use blitz_traits::render::OutputColorSpace;
+use blitz_traits::shell::ColorScheme;
 use euclid::Transform3D;
 use kurbo::{self, Affine, Point, Rect, Stroke, Vec2};
-use peniko::{self, Fill};
+use peniko::{self, Fill, Mix};
 use style::color::AbsoluteColor;
 use style::values::generics::color::GenericColor;
 use style::values::generics::image::GenericImage;
@@ -26,18 +35,28 @@ use style::{
     },
     values::{
         computed::{CSSPixelLength, Overflow},
-        specified::{BorderStyle, OutlineStyle, image::ImageRendering},
+        specified::{Contain, image::ImageRendering},
     },
 };
 use taffy::Layout;
 use unicode_segmentation::UnicodeSegmentation;
 
 use super::multicolor_rounded_rect::{Edge, ElementFrame};
-use crate::color::{Color, ToColorColor};
+use crate::color::{Color, ForcedDarkGuard, ToColorColor, system_color};
+use crate::cull::{
+    CullStats, clip_cull_rect, has_overflowing_descendants, is_in_view, set_last_cull_stats,
+};
 use crate::debug_overlay::render_debug_overlay;
-use crate::layers::maybe_with_layer;
+use crate::fragments::{paint_fingerprint, scroll_layer_key};
+use crate::image_color::image_in_color_space;
+use crate::layers::{draw_opacity_group, maybe_with_layer, pop_clip_path, push_clip_path};
+use crate::print::PageArea;
+#[cfg(feature = "screenshot")]
 use crate::screenshot::ScreenshotEngine;
 use crate::sizing::compute_object_fit;
+#[cfg(feature = "svg")]
+use crate::svg_raster::rasterized_svg;
+use crate::writing_mode::{TransformedScene, vertical_lr_line_transform, vertical_text_transform};
 
 /// Alpha transparency threshold for visibility determination
 /// Uses epsilon comparison for floating point precision
@@ -47,6 +66,12 @@ const ALPHA_VISIBILITY_THRESHOLD: f32 = f32::EPSILON;
 /// Based on CSS specification limits and practical rendering constraints
 const MAX_BORDER_WIDTH_PX: f32 = 1000.0;
 
+/// The smallest padding box (in CSS px²) of scroll containers promoted to scroll layers
+const MIN_SCROLL_LAYER_AREA: f32 = 256.0 * 256.0;
+
+/// The largest contents (in CSS px²) of scroll containers promoted to scroll layers
+const MAX_SCROLL_LAYER_CONTENT_AREA: f32 = 4096.0 * 4096.0;
+
 /// Default border width fallback for error cases
 /// Provides graceful degradation when border width conversion fails
 const DEFAULT_BORDER_WIDTH_PX: f32 = 1.0;
@@ -82,6 +107,15 @@ struct RenderState {
     rendered_nodes: HashSet<usize>,
     /// Current render pass number (incremented on each full render)
     pass: u64,
+    /// Whether a retained fragment is being recorded, relative to the origin
+    recording_fragment: bool,
+    /// The element whose fragment is being recorded. Its `clip-path` is applied where the
+    /// fragment is drawn rather than recorded into it.
+    fragment_root: Option<usize>,
+    /// The area (in device px) elements are culled against, narrowed by their ancestors' clips
+    cull_rect: Rect,
+    /// The elements painted and skipped in the current pass
+    cull_stats: CullStats,
 }
 
 pub struct BlitzDomPainter<'dom> {
@@ -91,9 +125,17 @@ pub struct BlitzDomPainter<'dom> {
     pub(crate) width: u32,
     pub(crate) height: u32,
     pub(crate) devtools: DevtoolSettings,
+    /// The color space colors are encoded in
+    pub(crate) color_space: OutputColorSpace,
+    /// The page being painted, instead of the viewport
+    pub(crate) page: Option<PageArea>,
+    /// The part of the scene (in device px) which can be seen. Elements outside of it aren't
+    /// painted.
+    pub(crate) cull_rect: Rect,
     /// Tracks render state across the current render pass
     render_state: Rc<RefCell<RenderState>>,
     /// Screenshot engine for capture functionality
+    #[cfg(feature = "screenshot")]
     screenshot_engine: Option<Arc<ScreenshotEngine>>,
 }
 
@@ -117,11 +159,16 @@ impl<'dom> BlitzDomPainter<'dom> {
             height,
             scale,
             devtools: Default::default(),
+            color_space: OutputColorSpace::Srgb,
+            page: None,
+            cull_rect: Rect::new(0.0, 0.0, width as f64, height as f64),
             render_state: Rc::new(RefCell::new(RenderState::default())),
+            #[cfg(feature = "screenshot")]
             screenshot_engine: None,
         }
     }
 
+    #[cfg(feature = "screenshot")]
     pub fn new_with_screenshot_engine(
         dom: &'dom BaseDocument,
         width: u32,
@@ -135,18 +182,23 @@ impl<'dom> BlitzDomPainter<'dom> {
             height,
             scale,
             devtools: Default::default(),
+            color_space: OutputColorSpace::Srgb,
+            page: None,
+            cull_rect: Rect::new(0.0, 0.0, width as f64, height as f64),
             render_state: Rc::new(RefCell::new(RenderState::default())),
             screenshot_engine: Some(screenshot_engine),
         }
     }
 
     /// Set the screenshot engine for this painter
+    #[cfg(feature = "screenshot")]
     #[inline]
     pub fn set_screenshot_engine(&mut self, engine: Arc<ScreenshotEngine>) {
         self.screenshot_engine = Some(engine);
     }
 
     /// Get reference to screenshot engine
+    #[cfg(feature = "screenshot")]
     #[inline]
     pub fn screenshot_engine(&self) -> Option<&Arc<ScreenshotEngine>> {
         self.screenshot_engine.as_ref()
@@ -174,7 +226,12 @@ impl<'dom> BlitzDomPainter<'dom> {
             let mut state = self.render_state.borrow_mut();
             state.rendered_nodes.clear();
             state.pass = state.pass.wrapping_add(1);
+            state.cull_stats = CullStats::default();
         }
+        // Darken every color painted in this frame if the page is shown in forced dark mode
+        let dom = self.dom.as_ref();
+        let _forced_dark = ForcedDarkGuard::new(dom.is_forced_dark());
+
         // Reset the scene and get viewport information
         scene.reset();
         let viewport_scroll = self.dom.as_ref().viewport_scroll();
@@ -216,35 +273,61 @@ impl<'dom> BlitzDomPainter<'dom> {
             }
         };
 
-        if let Some(bg_color) = background_color {
-            let bg_color = bg_color.as_srgb_color();
+        // Pages without a background of their own are shown on the canvas of their color scheme.
+        // The light canvas is left to the embedder, which has always drawn white behind pages.
+        let bg_color = background_color
+            .map(|color| color.as_output_color(self.color_space))
+            .filter(|color| color.components[3] > 0.0)
+            .or_else(|| {
+                let dark = dom.used_color_scheme() == ColorScheme::Dark || dom.is_forced_dark();
+                dark.then(|| system_color(dom, SystemColor::Canvas))
+            });
+        if let Some(bg_color) = bg_color {
             let rect = Rect::from_origin_size((0.0, 0.0), (bg_width as f64, bg_height as f64));
             scene.fill(Fill::NonZero, Affine::IDENTITY, bg_color, None, &rect);
         }
 
+        // Pages show a slice of the document in their content area, clipped to it
+        let origin = match &self.page {
+            Some(page) => {
+                let clip = page.content.scale_from_origin(self.scale);
+                scene.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &clip);
+                self.render_state.borrow_mut().cull_rect = self.cull_rect.intersect(clip);
+                page.content.origin() - page.scroll.to_vec2()
+            }
+            None => {
+                self.render_state.borrow_mut().cull_rect = self.cull_rect;
+                Point::new(-viewport_scroll.x, -viewport_scroll.y)
+            }
+        };
+
         // Clear thread-local visited set for cycle detection
         RENDER_VISITED.with(|visited| {
             let mut visited = visited.borrow_mut();
             visited.clear();
 
             // Render the root element
-            self.render_element(
-                scene,
-                root_id,
-                Point {
-                    x: -viewport_scroll.x,
-                    y: -viewport_scroll.y,
-                },
-                &mut visited,
-            );
+            self.render_element(scene, root_id, origin, &mut visited);
         });
 
+        if self.page.is_some() {
+            scene.pop_layer();
+        } else {
+            self.draw_viewport_scrollbars(scene);
+        }
+
         // Render debug overlay
         if self.devtools.highlight_hover {
             if let Some(node_id) = self.dom.as_ref().get_hover_node_id() {
                 render_debug_overlay(scene, self.dom, node_id, self.scale);
             }
         }
+        set_last_cull_stats(self.cull_stats());
+    }
+
+    /// The [`CullStats`] of the last scene this painted
+    pub fn cull_stats(&self) -> CullStats {
+        self.render_state.borrow().cull_stats
     }
 
     /// Check if screenshot engine is available and active
@@ -252,7 +335,10 @@ impl<'dom> BlitzDomPainter<'dom> {
     /// Returns true if a screenshot engine is configured and available for processing.
     #[inline]
     pub fn has_screenshot_engine(&self) -> bool {
-        self.screenshot_engine.is_some()
+        #[cfg(feature = "screenshot")]
+        return self.screenshot_engine.is_some();
+        #[cfg(not(feature = "screenshot"))]
+        return false;
     }
 
     /// Get screenshot engine statistics
@@ -277,7 +363,9 @@ impl<'dom> BlitzDomPainter<'dom> {
     /// if painter.has_screenshot_engine() {
     ///     // Get mutable reference to screenshot engine from graphics context
     ///     if let Some(engine) = graphics_context.screenshot_engine_mut() {
-    ///         let processed = engine.process_pending_requests(&texture, &texture_view).await?;
+    ///         let processed = engine
+    ///             .process_pending_requests(&texture, &texture_view, dom.fonts())
+    ///             .await?;
     ///     }
     /// }
     /// ```
@@ -344,25 +432,33 @@ impl<'dom> BlitzDomPainter<'dom> {
             return;
         }
 
-        // Hide elements with a visibility style other than visible
-        if node
+        // Elements with a visibility style other than visible don't paint themselves, but their
+        // descendants may be visible
+        let is_visible = node
             .primary_styles()
             .unwrap()
             .get_inherited_box()
             .visibility
-            != StyloVisibility::Visible
-        {
+            == StyloVisibility::Visible;
+        let has_descendants = node
+            .paint_children
+            .borrow()
+            .as_ref()
+            .is_some_and(|children| !children.is_empty());
+        if !is_visible && !has_descendants {
+            self.render_state.borrow_mut().cull_stats.invisible += 1;
             visited.remove(&render_key);
             return;
         }
 
-        // We can't fully support opacity yet, but we can hide elements with opacity 0
+        // Elements with opacity 0 are invisible, so aren't painted at all
         // Graceful handling: default to fully opaque if styles are missing
         let opacity = node
             .primary_styles()
             .map(|styles| styles.get_effects().opacity)
             .unwrap_or(1.0); // CSS specification default: fully opaque
         if opacity == 0.0 {
+            self.render_state.borrow_mut().cull_stats.invisible += 1;
             visited.remove(&render_key);
             return;
         }
@@ -381,7 +477,11 @@ impl<'dom> BlitzDomPainter<'dom> {
             .element_data()
             .and_then(|e| e.raster_image_data())
             .is_some();
+        // Paint containment clips contents to the padding box, just like `overflow: clip`
+        let has_paint_containment = node.containment().contains(Contain::PAINT);
+        let skips_contents = node.flags.skips_contents();
         let should_clip = is_image
+            || has_paint_containment
             || !matches!(overflow_x, Overflow::Visible)
             || !matches!(overflow_y, Overflow::Visible);
 
@@ -391,7 +491,6 @@ impl<'dom> BlitzDomPainter<'dom> {
             size,
             border,
             padding,
-            content_size,
             ..
         } = node.final_layout;
         let scaled_pb = (padding + border).map(f64::from);
@@ -404,10 +503,20 @@ impl<'dom> BlitzDomPainter<'dom> {
             height: (size.height as f64 - scaled_pb.top - scaled_pb.bottom) * self.scale,
         };
 
-        // Don't render things that are out of view
-        let scaled_y = box_position.y * self.scale;
-        let scaled_content_height = content_size.height.max(size.height) as f64 * self.scale;
-        if scaled_y > self.height as f64 || scaled_y + scaled_content_height < 0.0 {
+        // Don't render things that are out of view. Fragments are recorded whole, so that they
+        // can be reused as they scroll into view.
+        let mut cx = self.element_cx(node, layout, box_position);
+        cx.is_visible = is_visible;
+        let (recording_fragment, cull_rect) = {
+            let state = self.render_state.borrow();
+            (state.recording_fragment, state.cull_rect)
+        };
+        let ink_rect = cx.ink_overflow_rect(should_clip);
+        if !recording_fragment
+            && !is_in_view(ink_rect, cx.transform, cull_rect)
+            && !has_overflowing_descendants(self.dom, node)
+        {
+            self.render_state.borrow_mut().cull_stats.culled += 1;
             visited.remove(&render_key);
             return;
         }
@@ -419,54 +528,107 @@ impl<'dom> BlitzDomPainter<'dom> {
             return;
         }
 
-        let mut cx = self.element_cx(node, layout, box_position);
-        cx.draw_outline(scene);
-        cx.draw_outset_box_shadow(scene);
-
-        // Enhanced background rendering with computed styles
-        cx.apply_computed_background_styles(scene);
-        cx.draw_background(scene);
-
-        // Enhanced border rendering (integrated into draw_border)
-        cx.draw_border(scene);
+        // Everything the element paints (including its shadows, outline and descendants) is
+        // clipped to its `clip-path`
+        let clip_path = clip_region(&cx.style, &layout);
+        let is_fragment_root = self.render_state.borrow().fragment_root == Some(node_id);
+
+        // Paint-contained elements are painted as fragments which the scene may retain, so that
+        // later frames can reuse them while their subtree is unchanged
+        if has_paint_containment && !recording_fragment && scene.supports_retained_fragments() {
+            let version =
+                paint_fingerprint(self.dom, node_id, self.scale, self.devtools.show_layout);
+            if let Some(version) = version {
+                let key = FragmentKey {
+                    id: node_id as u64,
+                    version,
+                };
+                let clip = clip_path
+                    .as_ref()
+                    .and_then(|clip| push_clip_path(scene, clip, self.scale, cx.transform));
+                self.paint_fragment(scene, key, node_id, layout, box_position, visited);
+                pop_clip_path(scene, clip);
+                visited.remove(&render_key);
+                return;
+            }
+        }
 
-        // TODO: allow layers with opacity to be unclipped (overflow: visible)
-        let wants_layer = should_clip | has_opacity;
-        let clip = &cx.frame.padding_box_path();
+        self.render_state.borrow_mut().cull_stats.painted += 1;
 
-        maybe_with_layer(scene, wants_layer, opacity, cx.transform, clip, |scene| {
-            cx.draw_inset_box_shadow(scene);
-            cx.stroke_devtools(scene);
+        // Descendants outside of the padding box of elements which clip their contents can't be
+        // seen, so are culled against it
+        if should_clip {
+            let clipped_cull_rect = clip_cull_rect(cull_rect, cx.frame.padding_box, cx.transform);
+            self.render_state.borrow_mut().cull_rect = clipped_cull_rect;
+        }
 
-            // Now that background has been drawn, offset pos and cx in order to draw our contents scrolled
-            let content_position = Point {
-                x: content_position.x - node.scroll_offset.x,
-                y: content_position.y - node.scroll_offset.y,
-            };
-            cx.pos = Point {
-                x: cx.pos.x - node.scroll_offset.x,
-                y: cx.pos.y - node.scroll_offset.y,
-            };
-            cx.transform = cx.transform.then_translate(Vec2 {
-                x: -node.scroll_offset.x,
-                y: -node.scroll_offset.y,
+        let clip = clip_path
+            .as_ref()
+            .filter(|_| !is_fragment_root)
+            .and_then(|clip| push_clip_path(scene, clip, self.scale, cx.transform));
+
+        // Opacity applies to everything the element paints at once. Elements without descendants
+        // are recorded first, so that their opacity can be multiplied into what they draw instead
+        // of being applied with a layer, if they only draw one thing
+        let transform = cx.transform;
+        if !has_opacity {
+            cx.draw_element(scene, content_position, should_clip, skips_contents, visited);
+        } else if has_descendants {
+            maybe_with_layer(scene, true, opacity, transform, &ink_rect, |scene| {
+                cx.draw_element(scene, content_position, should_clip, skips_contents, visited)
             });
-            cx.draw_image(scene);
-            #[cfg(feature = "svg")]
-            cx.draw_svg(scene);
-            cx.draw_canvas(scene);
-            cx.draw_input(scene);
-
-            cx.draw_text_input_text(scene, content_position);
-            cx.draw_inline_layout(scene, content_position);
-            cx.draw_marker(scene, content_position);
-            cx.draw_children(scene, visited);
-        });
+        } else {
+            let mut group = DisplayList::new();
+            cx.draw_element(&mut group, content_position, should_clip, skips_contents, visited);
+            draw_opacity_group(scene, &group, opacity, transform, &ink_rect);
+        }
+        pop_clip_path(scene, clip);
+        self.render_state.borrow_mut().cull_rect = cull_rect;
 
         // Remove from visited set when exiting the function
         visited.remove(&render_key);
     }
 
+    /// Draw the fragment for a paint-contained element, recording it first if the scene hasn't
+    /// retained it. Fragments are recorded with the element's border box at the origin, and
+    /// without culling the parts which are currently out of view.
+    fn paint_fragment(
+        &self,
+        scene: &mut impl PaintScene,
+        key: FragmentKey,
+        node_id: usize,
+        layout: Layout,
+        box_position: Point,
+        visited: &mut HashSet<RenderKey>,
+    ) {
+        let transform =
+            Affine::translate((box_position.x * self.scale, box_position.y * self.scale));
+        if scene.draw_retained_fragment(key, transform) {
+            return;
+        }
+
+        {
+            let mut state = self.render_state.borrow_mut();
+            state.recording_fragment = true;
+            state.fragment_root = Some(node_id);
+            state.rendered_nodes.remove(&node_id);
+        }
+        scene.begin_fragment(key);
+        let origin = Point {
+            x: -layout.location.x as f64,
+            y: -layout.location.y as f64,
+        };
+        self.render_element(scene, node_id, origin, visited);
+        scene.end_fragment();
+        {
+            let mut state = self.render_state.borrow_mut();
+            state.recording_fragment = false;
+            state.fragment_root = None;
+        }
+
+        scene.draw_retained_fragment(key, transform);
+    }
+
     fn render_node(
         &self,
         scene: &mut impl PaintScene,
@@ -577,6 +739,7 @@ impl<'dom> BlitzDomPainter<'dom> {
             text_input: element.text_input_data(),
             list_item: element.list_item_data.as_deref(),
             devtools: &self.devtools,
+            is_visible: true,
         }
     }
 }
@@ -630,13 +793,221 @@ struct ElementCx<'a> {
     element: &'a ElementData,
     transform: Affine,
     #[cfg(feature = "svg")]
-    svg: Option<&'a usvg::Tree>,
+    svg: Option<&'a Arc<usvg::Tree>>,
     text_input: Option<&'a TextInputData>,
     list_item: Option<&'a ListItemLayout>,
     devtools: &'a DevtoolSettings,
+    /// Whether the element paints itself (rather than only its descendants)
+    is_visible: bool,
 }
 
 impl ElementCx<'_> {
+    /// Draw the element: its shadows, background and border, then its (scrolled) contents and
+    /// descendants, then its scrollbars and outline
+    fn draw_element(
+        &mut self,
+        scene: &mut impl PaintScene,
+        content_position: Point,
+        should_clip: bool,
+        skips_contents: bool,
+        visited: &mut HashSet<RenderKey>,
+    ) {
+        if !self.is_visible {
+            self.draw_descendants(scene, should_clip, skips_contents, visited);
+            return;
+        }
+
+        self.draw_outset_box_shadow(scene);
+
+        // Enhanced background rendering with computed styles
+        self.apply_computed_background_styles(scene);
+        self.draw_background(scene);
+
+        // Enhanced border rendering (integrated into draw_border)
+        if !self.has_collapsed_borders() {
+            self.draw_border(scene);
+        }
+
+        let clip = &self.frame.padding_box_path();
+        let border_box_transform = self.transform;
+        let scroll_offset = self.node.scroll_offset;
+
+        maybe_with_layer(scene, should_clip, 1.0, self.transform, clip, |scene| {
+            self.draw_inset_box_shadow(scene);
+            self.stroke_devtools(scene);
+
+            // Skipped contents (`content-visibility`) are not painted
+            if skips_contents {
+                return;
+            }
+
+            match self.scroll_layer_key(&*scene) {
+                Some(key) => self.draw_scroll_layer(scene, key, content_position, visited),
+                None => self.draw_contents(scene, content_position, scroll_offset, visited),
+            }
+        });
+        self.draw_scrollbars(scene, border_box_transform);
+
+        // Outlines are drawn over the element and its contents
+        self.transform = border_box_transform;
+        self.draw_outline(scene);
+    }
+
+    /// Draw the element's contents and descendants, scrolled by `scroll_offset`
+    fn draw_contents(
+        &mut self,
+        scene: &mut impl PaintScene,
+        content_position: Point,
+        scroll_offset: Point,
+        visited: &mut HashSet<RenderKey>,
+    ) {
+        // Now that background has been drawn, offset pos and cx in order to draw our contents
+        // scrolled
+        let content_position = Point {
+            x: content_position.x - scroll_offset.x,
+            y: content_position.y - scroll_offset.y,
+        };
+        self.pos = Point {
+            x: self.pos.x - scroll_offset.x,
+            y: self.pos.y - scroll_offset.y,
+        };
+        self.transform = self.transform.then_translate(Vec2 {
+            x: -scroll_offset.x,
+            y: -scroll_offset.y,
+        });
+
+        self.draw_image(scene);
+        #[cfg(feature = "svg")]
+        self.draw_svg(scene);
+        self.draw_canvas(scene);
+        self.draw_input(scene);
+
+        self.draw_text_input_text(scene, content_position);
+        self.draw_inline_layout(scene, content_position);
+        self.draw_marker(scene, content_position);
+        self.draw_children(scene, visited);
+        self.draw_collapsed_table_borders(scene);
+    }
+
+    /// The key of the retained fragment the element's scrolled contents are painted as, if it's
+    /// a scroll container large enough to be promoted to a scroll layer (see
+    /// [`crate::fragments`])
+    fn scroll_layer_key(&self, scene: &impl PaintScene) -> Option<FragmentKey> {
+        let recording_fragment = self.context.render_state.borrow().recording_fragment;
+        if recording_fragment || !scene.supports_retained_fragments() {
+            return None;
+        }
+
+        let box_style = self.style.get_box();
+        let scrolls = |overflow| matches!(overflow, Overflow::Scroll | Overflow::Auto);
+        if !scrolls(box_style.overflow_x) && !scrolls(box_style.overflow_y) {
+            return None;
+        }
+        // The layer is moved with the element's position, which is all that applies to its
+        // descendants when the element is transformed too
+        if !box_style.transform.0.is_empty() {
+            return None;
+        }
+
+        // Contents which don't overflow can't be scrolled, and huge ones are cheaper to paint
+        // culled to what's in view than to record whole
+        let layout = &self.node.final_layout;
+        let padding_box = taffy::Size {
+            width: layout.size.width - layout.border.left - layout.border.right,
+            height: layout.size.height - layout.border.top - layout.border.bottom,
+        };
+        let content_size = layout.content_size;
+        let overflows =
+            content_size.width > padding_box.width || content_size.height > padding_box.height;
+        let content_area = content_size.width * content_size.height;
+        if !overflows
+            || padding_box.width * padding_box.height < MIN_SCROLL_LAYER_AREA
+            || content_area > MAX_SCROLL_LAYER_CONTENT_AREA
+        {
+            return None;
+        }
+
+        let show_layout = self.devtools.show_layout;
+        scroll_layer_key(self.context.dom, self.node.id, self.scale, show_layout)
+    }
+
+    /// Draw the element's scrolled contents as the retained fragment for `key`, recording them
+    /// first if the scene hasn't retained it. They're recorded unscrolled with the element's
+    /// border box at the origin, and without culling the parts which are currently out of view,
+    /// so that the fragment can be moved as the element is scrolled.
+    fn draw_scroll_layer(
+        &mut self,
+        scene: &mut impl PaintScene,
+        key: FragmentKey,
+        content_position: Point,
+        visited: &mut HashSet<RenderKey>,
+    ) {
+        let scroll_offset = self.node.scroll_offset;
+        let (pos, transform) = (self.pos, self.transform);
+        let layer_transform = Affine::translate((pos - scroll_offset) * self.scale);
+        if scene.draw_retained_fragment(key, layer_transform) {
+            return;
+        }
+
+        let render_state = &self.context.render_state;
+        render_state.borrow_mut().recording_fragment = true;
+        scene.begin_fragment(key);
+        self.pos = Point::ZERO;
+        self.transform = Affine::IDENTITY;
+        let content_position = content_position - pos.to_vec2();
+        self.draw_contents(scene, content_position, Point::ZERO, visited);
+        scene.end_fragment();
+        render_state.borrow_mut().recording_fragment = false;
+        (self.pos, self.transform) = (pos, transform);
+
+        scene.draw_retained_fragment(key, layer_transform);
+    }
+
+    /// Draw only the (scrolled) descendants of an element which isn't visible itself, since they
+    /// may be
+    // TODO: Paint the visible text of hidden inline roots
+    fn draw_descendants(
+        &mut self,
+        scene: &mut impl PaintScene,
+        should_clip: bool,
+        skips_contents: bool,
+        visited: &mut HashSet<RenderKey>,
+    ) {
+        if skips_contents {
+            return;
+        }
+        let clip = &self.frame.padding_box_path();
+        let scroll_offset = self.node.scroll_offset;
+        maybe_with_layer(scene, should_clip, 1.0, self.transform, clip, |scene| {
+            self.pos = Point {
+                x: self.pos.x - scroll_offset.x,
+                y: self.pos.y - scroll_offset.y,
+            };
+            self.draw_children(scene, visited);
+        });
+    }
+
+    /// The area (in local device px) that everything the element paints fits in: its border box
+    /// and overflowing contents (unless they're clipped to it), grown to fit its outset shadows
+    /// and outline
+    fn ink_overflow_rect(&self, clips_contents: bool) -> Rect {
+        let border_box = self.frame.border_box;
+        let mut rect = border_box;
+        if !clips_contents {
+            let content_size = self.node.final_layout.content_size.map(f64::from);
+            let content = Rect::from_origin_size(
+                border_box.origin(),
+                (content_size.width * self.scale, content_size.height * self.scale),
+            );
+            rect = rect.union(content);
+        }
+        if let Some(shadow_rect) = self.outset_shadow_rect() {
+            rect = rect.union(shadow_rect);
+        }
+        let outline = self.outline_extent();
+        rect.inflate(outline, outline)
+    }
+
     /// Enhanced background style application with zero allocation
     #[inline(always)]
     fn apply_computed_background_styles(&self, scene: &mut impl PaintScene) {
@@ -661,7 +1032,7 @@ impl ElementCx<'_> {
             let bg_color = background_styles
                 .background_color
                 .resolve_to_absolute(&current_color)
-                .as_srgb_color();
+                .as_output_color(self.context.color_space);
 
             // Enhanced visibility - only render if alpha > epsilon threshold
             if bg_color.components[3] > ALPHA_VISIBILITY_THRESHOLD {
@@ -687,13 +1058,15 @@ impl ElementCx<'_> {
                  self.element.inline_layout_data.is_some());
         if self.node.flags.is_inline_root() {
             if let Some(text_layout) = &self.element.inline_layout_data {
+                let color = extract_text_color(&self.style, self.context.color_space);
                 crate::text::render_text_buffer(
                     self.scale,
                     scene,
                     &text_layout.layout.inner(),
                     pos,
                     Some(&self.style),
-                    &blitz_dom::node::TextBrush::from_color(extract_text_color(&self.style)),
+                    &blitz_dom::node::TextBrush::from_color(color),
+                    self.context.color_space,
                 );
             }
         }
@@ -721,6 +1094,53 @@ impl ElementCx<'_> {
             #[cfg(feature = "tracing")]
             tracing::debug!("Found inline layout data, proceeding with text rendering");
 
+            let color = extract_text_color(&self.style, self.context.color_space);
+            let brush = blitz_dom::node::TextBrush::from_color(color);
+            let (writing_mode, _) = blitz_dom::layout::text_writing_mode(&self.style);
+            if writing_mode == blitz_text::WritingMode::VerticalLeftRight {
+                let origin = Point::new(pos.x * self.scale, pos.y * self.scale);
+                let buffer = text_layout.layout.inner();
+                let width = text_layout.width() as f64;
+                for run in buffer.layout_runs() {
+                    let (top, height) = (run.line_top as f64, run.line_height as f64);
+                    let mut scene = TransformedScene {
+                        inner: &mut *scene,
+                        transform: vertical_lr_line_transform(origin, top, height),
+                    };
+                    // Leave room either side of the line for glyphs which overhang it
+                    let band = Rect::new(-height, top, width + height, top + height);
+                    maybe_with_layer(&mut scene, true, 1.0, Affine::IDENTITY, &band, |scene| {
+                        crate::text::render_text_buffer(
+                            self.scale,
+                            scene,
+                            buffer,
+                            Point::ZERO,
+                            Some(&self.style),
+                            &brush,
+                            self.context.color_space,
+                        );
+                    });
+                }
+                return;
+            }
+            if writing_mode == blitz_text::WritingMode::VerticalRightLeft {
+                let origin = Point::new(pos.x * self.scale, pos.y * self.scale);
+                let mut scene = TransformedScene {
+                    inner: scene,
+                    transform: vertical_text_transform(origin, text_layout.height() as f64),
+                };
+                crate::text::render_text_buffer(
+                    self.scale,
+                    &mut scene,
+                    &text_layout.layout.inner(),
+                    Point::ZERO,
+                    Some(&self.style),
+                    &brush,
+                    self.context.color_space,
+                );
+                return;
+            }
+
             // Enhanced text rendering with computed CSS styles
             crate::text::render_text_buffer(
                 self.scale,
@@ -728,7 +1148,8 @@ impl ElementCx<'_> {
                 &text_layout.layout.inner(),
                 pos,
                 Some(&self.style),
-                &blitz_dom::node::TextBrush::from_color(extract_text_color(&self.style)),
+                &brush,
+                self.context.color_space,
             );
         }
     }
@@ -767,6 +1188,7 @@ impl ElementCx<'_> {
                     pos,
                     Some(&self.style),
                     &brush,
+                    self.context.color_space,
                 );
 
                 #[cfg(feature = "tracing")]
@@ -777,9 +1199,10 @@ impl ElementCx<'_> {
                 // Implement selection/cursor rendering with cosmyc-text
                 use blitz_text::Edit;
 
-                // Define cursor and selection colors
-                let cursor_color = peniko::Color::from_rgb8(0, 0, 0); // Black cursor
-                let selection_color = peniko::Color::from_rgba8(0, 120, 215, 128); // Semi-transparent blue
+                // Cursor and selection colors follow the active system color palette
+                let dom = self.context.dom;
+                let cursor_color = system_color(dom, SystemColor::FieldText);
+                let selection_color = system_color(dom, SystemColor::Highlight).with_alpha(0.5);
 
                 input_data.editor.with_buffer(|buffer| {
                     // Get selection bounds
@@ -886,6 +1309,7 @@ impl ElementCx<'_> {
         if let Some(ListItemLayout {
             marker,
             position: ListItemLayoutPosition::Outside(layout),
+            style: marker_style,
         }) = self.list_item
         {
             // Right align and pad the bullet when rendering outside
@@ -899,14 +1323,17 @@ impl ElementCx<'_> {
 
             let pos = marker_pos;
 
-            // Enhanced marker rendering with computed CSS styles
+            // `::marker` rules style the marker, which otherwise looks like its list item
+            let style = marker_style.as_ref().unwrap_or(&self.style);
+            let color = extract_text_color(style, self.context.color_space);
             crate::text::render_text_buffer(
                 self.scale,
                 scene,
                 layout.inner(), // Get inner Buffer from EnhancedBuffer
                 pos,
-                Some(&self.style),
-                &blitz_dom::node::TextBrush::from_color(extract_text_color(&self.style)),
+                Some(style),
+                &blitz_dom::node::TextBrush::from_color(color),
+                self.context.color_space,
             );
         }
     }
@@ -1006,10 +1433,27 @@ impl ElementCx<'_> {
         let y_scale = paint_size.height as f64 / object_size.height as f64;
 
         let transform =
-            Affine::translate((self.pos.x * self.scale + x, self.pos.y * self.scale + y))
-                .pre_scale_non_uniform(x_scale, y_scale);
-
-        anyrender_svg::render_svg_tree(scene, svg, transform);
+            Affine::translate((self.pos.x * self.scale + x, self.pos.y * self.scale + y));
+
+        let raster_width = paint_size.width.ceil() as u32;
+        let raster_height = paint_size.height.ceil() as u32;
+        match rasterized_svg(svg, raster_width, raster_height) {
+            Some(image) => {
+                let quality = to_image_quality(self.style.clone_image_rendering());
+                let image = image_in_color_space(image, self.context.color_space);
+                let image = image.with_quality(quality);
+                // The raster may be of another size while it's rasterized again
+                let transform = transform.pre_scale_non_uniform(
+                    paint_size.width as f64 / image.width as f64,
+                    paint_size.height as f64 / image.height as f64,
+                );
+                scene.draw_image(&image, transform);
+            }
+            None => {
+                let transform = transform.pre_scale_non_uniform(x_scale, y_scale);
+                anyrender_svg::render_svg_tree(scene, svg, transform);
+            }
+        }
     }
 
     fn draw_image(&self, scene: &mut impl PaintScene) {
@@ -1030,8 +1474,8 @@ impl ElementCx<'_> {
                 height: height as f32,
             };
             let object_size = taffy::Size {
-                width: image.width as f32,
-                height: image.height as f32,
+                width: image.natural_width as f32,
+                height: image.natural_height as f32,
             };
             let paint_size = compute_object_fit(container_size, Some(object_size), object_fit);
 
@@ -1045,14 +1489,17 @@ impl ElementCx<'_> {
             let x = x + x_offset.px() as f64;
             let y = y + y_offset.px() as f64;
 
-            let x_scale = paint_size.width as f64 / object_size.width as f64;
-            let y_scale = paint_size.height as f64 / object_size.height as f64;
+            // The image may have been decoded at less than its natural size
+            let x_scale = paint_size.width as f64 / image.width as f64;
+            let y_scale = paint_size.height as f64 / image.height as f64;
             let transform = self
                 .transform
                 .pre_scale_non_uniform(x_scale, y_scale)
                 .then_translate(Vec2 { x, y });
 
-            scene.draw_image(&to_peniko_image(image, quality), transform);
+            let image = to_peniko_image(image, quality);
+            let image = image_in_color_space(image, self.context.color_space);
+            scene.draw_image(&image, transform);
         }
     }
 
@@ -1178,7 +1625,9 @@ impl ElementCx<'_> {
         };
 
         // Resolve color and width in one operation
-        let color = color.resolve_to_absolute(&current_color).as_srgb_color();
+        let color = color
+            .resolve_to_absolute(&current_color)
+            .as_output_color(self.context.color_space);
         let width = safe_border_width_px(width);
 
         // Enhanced border visibility check - width and alpha must both be > threshold
@@ -1188,56 +1637,18 @@ impl ElementCx<'_> {
             sb.fill(Fill::NonZero, self.transform, color, None, &path);
         }
     }
-
-    /// ❌ dotted - Defines a dotted border
-    /// ❌ dashed - Defines a dashed border
-    /// ✅ solid - Defines a solid border
-    /// ❌ double - Defines a double border
-    /// ❌ groove - Defines a 3D grooved border. The effect depends on the border-color value
-    /// ❌ ridge - Defines a 3D ridged border. The effect depends on the border-color value
-    /// ❌ inset - Defines a 3D inset border. The effect depends on the border-color value
-    /// ❌ outset - Defines a 3D outset border. The effect depends on the border-color value
-    /// ✅ none - Defines no border
-    /// ✅ hidden - Defines a hidden border
-    fn draw_outline(&self, scene: &mut impl PaintScene) {
-        let outline = self.style.get_outline();
-
-        let current_color = self.style.clone_color();
-        let color = outline
-            .outline_color
-            .resolve_to_absolute(&current_color)
-            .as_srgb_color();
-
-        let style = match outline.outline_style {
-            OutlineStyle::Auto => return,
-            OutlineStyle::BorderStyle(style) => style,
-        };
-
-        let path = match style {
-            BorderStyle::None | BorderStyle::Hidden => return,
-            BorderStyle::Solid => self.frame.outline(),
-
-            // TODO: Implement other border styles
-            BorderStyle::Inset
-            | BorderStyle::Groove
-            | BorderStyle::Outset
-            | BorderStyle::Ridge
-            | BorderStyle::Dotted
-            | BorderStyle::Dashed
-            | BorderStyle::Double => self.frame.outline(),
-        };
-
-        scene.fill(Fill::NonZero, self.transform, color, None, &path);
-    }
 }
 
 /// Extract text color from computed styles for TextBrush creation
 /// Converts stylo computed color values to color::AlphaColor<color::Srgb> for TextBrush
-fn extract_text_color(computed: &ComputedValues) -> color::AlphaColor<color::Srgb> {
+fn extract_text_color(
+    computed: &ComputedValues,
+    color_space: OutputColorSpace,
+) -> color::AlphaColor<color::Srgb> {
     use color::{AlphaColor, Srgb};
 
     let text_styles = computed.get_inherited_text();
-    let color = text_styles.color.as_srgb_color();
+    let color = text_styles.color.as_output_color(color_space);
 
     // Convert peniko::Color to palette::AlphaColor<Srgb>
     AlphaColor::<Srgb>::new([
@@ -1,18 +1,23 @@
+#[cfg(feature = "svg")]
+use std::sync::Arc;
+
 use anyrender::PaintScene;
+use blitz_dom::background_attachment::BackgroundAttachment;
 use blitz_dom::node::ImageData;
-use kurbo::{self, BezPath, Point, Rect, Shape, Size, Vec2};
+use kurbo::{self, Affine, BezPath, Point, Rect, Shape, Size, Vec2};
 use peniko::{self, Fill};
 use style::dom::TElement;
 use style::{
     properties::{
         generated::longhands::{
+            background_attachment::single_value::computed_value::T as StyloBackgroundAttachment,
             background_clip::single_value::computed_value::T as StyloBackgroundClip,
             background_origin::single_value::computed_value::T as StyloBackgroundOrigin,
         },
         style_structs::Background,
     },
     values::{
-        computed::{BackgroundRepeat, Gradient as StyloGradient},
+        computed::{BackgroundRepeat, Gradient as StyloGradient, Overflow},
         generics::image::GenericImage,
         specified::background::BackgroundRepeatKeyword,
     },
@@ -21,9 +26,14 @@ use style::{
 use tracing::warn;
 
 use super::{ElementCx, to_image_quality, to_peniko_image};
-use crate::color::{Color, ToColorColor};
+use crate::color::{Color, ToColorColor, encode_gradient_stops};
 use crate::gradient::to_peniko_gradient;
+use crate::image_color::image_in_color_space;
 use crate::layers::maybe_with_layer;
+#[cfg(feature = "svg")]
+use crate::svg_raster::rasterized_svg;
+#[cfg(feature = "tile-rasters")]
+use crate::tile_raster::{MAX_BACKGROUND_TILES, TileRasterContent, TileRasterKey, rasterized_tile};
 
 impl ElementCx<'_> {
     pub(super) fn draw_background(&self, scene: &mut impl PaintScene) {
@@ -69,9 +79,7 @@ impl ElementCx<'_> {
                         None => {
                             // Do nothing
                         }
-                        Gradient(gradient) => {
-                            self.draw_gradient_bg(scene, gradient, idx, *background_clip)
-                        }
+                        Gradient(gradient) => self.draw_gradient_bg(scene, gradient, idx),
                         Url(_) => {
                             self.draw_raster_bg_image(scene, idx);
                             #[cfg(feature = "svg")]
@@ -104,7 +112,7 @@ impl ElementCx<'_> {
         let background_color = &self.style.get_background().background_color;
         let bg_color = background_color
             .resolve_to_absolute(&current_color)
-            .as_srgb_color();
+            .as_output_color(self.context.color_space);
 
         if bg_color != Color::TRANSPARENT {
             // Fill the color
@@ -123,47 +131,29 @@ impl ElementCx<'_> {
             return;
         };
 
-        let bg_styles = &self.style.get_background();
-
-        let frame_w = self.frame.padding_box.width() as f32;
-        let frame_h = self.frame.padding_box.height() as f32;
-
         let svg_size = svg.size();
-        let bg_size = compute_background_size(
-            bg_styles,
-            frame_w,
-            frame_h,
-            idx,
-            BackgroundSizeComputeMode::Size(
-                svg_size.width() / self.scale as f32,
-                svg_size.height() / self.scale as f32,
-            ),
-            self.scale as f32,
-        );
-        let bg_size = bg_size * self.scale;
+        let natural_size = Size::new(svg_size.width() as f64, svg_size.height() as f64);
+        let Some(tiles) = self.background_tiles(idx, Some(natural_size)) else {
+            return;
+        };
 
-        let x_ratio = bg_size.width as f64 / svg_size.width() as f64;
-        let y_ratio = bg_size.height as f64 / svg_size.height() as f64;
+        let raster_width = tiles.size.width.ceil() as u32;
+        let raster_height = tiles.size.height.ceil() as u32;
+        if let Some(image) = rasterized_svg(svg, raster_width, raster_height) {
+            let quality = to_image_quality(self.style.clone_image_rendering());
+            self.draw_bg_image(scene, &tiles, image.with_quality(quality));
+            return;
+        }
 
-        let bg_pos = compute_background_position(
-            bg_styles,
-            idx,
-            frame_w - bg_size.width as f32,
-            frame_h - bg_size.height as f32,
+        // Too large to rasterize, so each tile is drawn as paths
+        let svg_scale = Affine::scale_non_uniform(
+            tiles.size.width / natural_size.width,
+            tiles.size.height / natural_size.height,
         );
-
-        let transform = kurbo::Affine::translate((
-            (self.pos.x * self.scale) + bg_pos.x,
-            (self.pos.y * self.scale) + bg_pos.y,
-        ))
-        .pre_scale_non_uniform(x_ratio, y_ratio);
-
-        anyrender_svg::render_svg_tree(scene, svg, transform);
+        self.draw_tiles(scene, &tiles, &TileContent::Svg(svg, svg_scale));
     }
 
     fn draw_raster_bg_image(&self, scene: &mut impl PaintScene, idx: usize) {
-        use BackgroundRepeatKeyword::*;
-
         let bg_image = self.element.background_images.get(idx);
 
         let Some(Some(bg_image)) = bg_image.as_ref() else {
@@ -173,624 +163,634 @@ impl ElementCx<'_> {
             return;
         };
 
-        let image_rendering = self.style.clone_image_rendering();
-        let quality = to_image_quality(image_rendering);
-
-        let bg_styles = &self.style.get_background();
-
-        let background_origin = get_cyclic(&bg_styles.background_origin.0, idx);
-        let origin_rect = match background_origin {
-            StyloBackgroundOrigin::BorderBox => self.frame.border_box,
-            StyloBackgroundOrigin::PaddingBox => self.frame.padding_box,
-            StyloBackgroundOrigin::ContentBox => self.frame.content_box,
+        // Backgrounds are sized by the natural size of the image, which it may have been decoded
+        // at less than
+        let natural_size = Size::new(
+            image_data.natural_width as f64,
+            image_data.natural_height as f64,
+        );
+        let Some(tiles) = self.background_tiles(idx, Some(natural_size)) else {
+            return;
         };
 
-        let image_width = image_data.width as f64;
-        let image_height = image_data.height as f64;
+        let quality = to_image_quality(self.style.clone_image_rendering());
+        self.draw_bg_image(scene, &tiles, to_peniko_image(image_data, quality));
+    }
 
-        let (bg_pos, bg_size) = compute_background_position_and_background_size(
-            bg_styles,
-            origin_rect.width() / self.scale,
-            origin_rect.height() / self.scale,
-            idx,
-            BackgroundSizeComputeMode::Size(image_width as f32, image_height as f32),
+    /// Draw `image` stretched over each of the `tiles`
+    fn draw_bg_image(
+        &self,
+        scene: &mut impl PaintScene,
+        tiles: &BackgroundTiles,
+        image: peniko::Image,
+    ) {
+        let image = image_in_color_space(image, self.context.color_space);
+        let image = image.with_extend(peniko::Extend::Repeat);
+        let image_scale = Affine::scale_non_uniform(
+            tiles.size.width / image.width as f64,
+            tiles.size.height / image.height as f64,
         );
 
-        let bg_pos_x = bg_pos.x * self.scale;
-        let bg_pos_y = bg_pos.y * self.scale;
-        let bg_size = bg_size * self.scale;
+        // Tiles which abut are filled all at once by the repeating image
+        if !tiles.is_spaced() {
+            let bounds = tiles.bounds();
+            let brush_transform = Affine::translate(bounds.origin().to_vec2()) * image_scale;
+            scene.fill(Fill::NonZero, self.transform, &image, Some(brush_transform), &bounds);
+            return;
+        }
 
-        let x_ratio = bg_size.width / image_width;
-        let y_ratio = bg_size.height / image_height;
+        self.draw_tiles(scene, tiles, &TileContent::Image(&image, image_scale));
+    }
 
-        let BackgroundRepeat(repeat_x, repeat_y) = get_cyclic(&bg_styles.background_repeat.0, idx);
+    fn draw_gradient_bg(&self, scene: &mut impl PaintScene, gradient: &StyloGradient, idx: usize) {
+        let Some(tiles) = self.background_tiles(idx, None) else {
+            return;
+        };
 
-        let transform = self.transform.pre_scale_non_uniform(x_ratio, y_ratio);
-        let (origin_rect, transform) = match repeat_x {
-            Repeat | Round => {
-                let extend_width = extend(bg_pos_x, bg_size.width);
+        let tile = Rect::from_origin_size(Point::ZERO, tiles.size);
+        let bounding_box = self.frame.border_box.bounding_box();
+        let current_color = self.style.clone_color();
 
-                let transform = transform.then_translate(Vec2 {
-                    x: origin_rect.x0 - extend_width,
-                    y: 0.0,
-                });
+        let (mut gradient, gradient_transform) =
+            to_peniko_gradient(gradient, tile, bounding_box, self.scale, &current_color);
+        encode_gradient_stops(&mut gradient, self.context.color_space);
+        let content = TileContent::Gradient(&gradient, gradient_transform);
+        self.draw_tiles(scene, &tiles, &content);
+    }
 
-                let origin_rect = origin_rect.with_size(Size::new(
-                    (origin_rect.width() + extend_width) / x_ratio,
-                    origin_rect.height(),
-                ));
+    /// Paint each of the `tiles` with `content`. Backgrounds with more tiles than are painted one
+    /// by one are painted with a raster of one tile (and the gap after it, if they're spaced)
+    /// repeated.
+    fn draw_tiles(
+        &self,
+        scene: &mut impl PaintScene,
+        tiles: &BackgroundTiles,
+        content: &TileContent<'_>,
+    ) {
+        #[cfg(feature = "tile-rasters")]
+        if tiles.count() > MAX_BACKGROUND_TILES {
+            let key = TileRasterKey {
+                content: content.raster_content(),
+                tile: tiles.size,
+                cell: Size::new(tiles.x.step, tiles.y.step),
+            };
+            let (image, to_raster) = rasterized_tile(key, |scene, to_raster| {
+                content.paint(scene, to_raster, tiles.size);
+            });
+            let image = image.with_extend(peniko::Extend::Repeat);
+            let bounds = tiles.bounds();
+            let brush_transform =
+                Affine::translate(bounds.origin().to_vec2()) * to_raster.inverse();
+            scene.fill(Fill::NonZero, self.transform, &image, Some(brush_transform), &bounds);
+            return;
+        }
 
-                (origin_rect, transform)
-            }
-            Space => (origin_rect, transform),
-            NoRepeat => {
-                let transform = transform.then_translate(Vec2 {
-                    x: origin_rect.x0 + bg_pos_x,
-                    y: 0.0,
-                });
+        for origin in tiles.origins() {
+            let transform = self.transform * Affine::translate(origin.to_vec2());
+            content.paint(scene, transform, tiles.size);
+        }
+    }
 
-                let origin_rect =
-                    origin_rect.with_size(Size::new(image_width, origin_rect.height()));
+    /// Where the tiles of background layer `idx` go, per its `background-size`,
+    /// `background-position` and `background-repeat`, or `None` if it has no area. Images have a
+    /// `natural_size` (in CSS px), gradients don't.
+    fn background_tiles(&self, idx: usize, natural_size: Option<Size>) -> Option<BackgroundTiles> {
+        use BackgroundRepeatKeyword::Round;
+        use StyloBackgroundClip::*;
 
-                (origin_rect, transform)
-            }
+        let bg_styles = self.style.get_background();
+        let area = self.background_positioning_area(idx);
+        let clip_rect = match get_cyclic(&bg_styles.background_clip.0, idx) {
+            BorderBox => self.frame.border_box,
+            PaddingBox => self.frame.padding_box,
+            ContentBox => self.frame.content_box,
         };
-        let (origin_rect, transform) = match repeat_y {
-            Repeat | Round => {
-                let extend_height = extend(bg_pos_y, bg_size.height);
-
-                let transform = transform.then_translate(Vec2 {
-                    x: 0.0,
-                    y: origin_rect.y0 - extend_height,
-                });
 
-                let origin_rect = origin_rect.with_size(Size::new(
-                    origin_rect.width(),
-                    (origin_rect.height() + extend_height) / y_ratio,
-                ));
+        let area_size = Size::new(area.width() / self.scale, area.height() / self.scale);
+        let size = compute_background_size(bg_styles, area_size, idx, natural_size);
+        if size.is_zero_area() || !size.is_finite() {
+            return None;
+        }
 
-                (origin_rect, transform)
+        let BackgroundRepeat(repeat_x, repeat_y) = get_cyclic(&bg_styles.background_repeat.0, idx);
+        let rounded = Size::new(
+            round_tile_length(*repeat_x, size.width, area_size.width),
+            round_tile_length(*repeat_y, size.height, area_size.height),
+        );
+        // Tiles rounded along one axis keep their aspect ratio if they're `auto` sized along the
+        // other
+        let (auto_width, auto_height) = auto_background_size(bg_styles, idx);
+        let size = match (*repeat_x, *repeat_y) {
+            (Round, repeat_y) if repeat_y != Round && auto_height => {
+                Size::new(rounded.width, rounded.width * size.height / size.width)
             }
-            Space => (origin_rect, transform),
-            NoRepeat => {
-                let transform = transform.then_translate(Vec2 {
-                    x: 0.0,
-                    y: origin_rect.y0 + bg_pos_y,
-                });
-
-                let origin_rect =
-                    origin_rect.with_size(Size::new(origin_rect.width(), image_height));
-                (origin_rect, transform)
+            (repeat_x, Round) if repeat_x != Round && auto_width => {
+                Size::new(rounded.height * size.width / size.height, rounded.height)
             }
+            _ => rounded,
         };
 
-        if matches!(repeat_x, Space) || matches!(repeat_y, Space) {
-            let (origin_rect, transform, width_count, width_gap) = if matches!(repeat_x, Space) {
-                let (count, gap) = compute_space_count_and_gap(origin_rect.width(), bg_size.width);
-
-                let transform = if count == 1 {
-                    transform.then_translate(Vec2 {
-                        x: bg_pos_x,
-                        y: 0.0,
-                    })
-                } else {
-                    transform
-                };
-
-                let origin_rect =
-                    origin_rect.with_size(Size::new(image_width, origin_rect.height()));
-
-                (origin_rect, transform, count, gap)
-            } else {
-                (origin_rect, transform, 1, 0.0)
-            };
-
-            let (origin_rect, transform, height_count, height_gap) = if matches!(repeat_y, Space) {
-                let (count, gap) =
-                    compute_space_count_and_gap(origin_rect.height(), bg_size.height);
-
-                let transform = if count == 1 {
-                    transform.then_translate(Vec2 {
-                        x: 0.0,
-                        y: bg_pos_y,
-                    })
-                } else {
-                    transform
-                };
-
-                let origin_rect =
-                    origin_rect.with_size(Size::new(origin_rect.width(), image_height));
-
-                (origin_rect, transform, count, gap)
-            } else {
-                (origin_rect, transform, 1, 0.0)
-            };
-
-            for hc in 0..height_count {
-                for wc in 0..width_count {
-                    let width_gap = if matches!(repeat_x, Space) {
-                        origin_rect.x0 + wc as f64 * width_gap
-                    } else {
-                        0.0
-                    };
-
-                    let height_gap = if matches!(repeat_y, Space) {
-                        origin_rect.y0 + hc as f64 * height_gap
-                    } else {
-                        0.0
-                    };
-
-                    let transform = transform.then_translate(Vec2 {
-                        x: width_gap,
-                        y: height_gap,
-                    });
-
-                    scene.fill(
-                        peniko::Fill::NonZero,
-                        transform,
-                        &to_peniko_image(image_data, quality),
-                        None,
-                        &Rect::new(0.0, 0.0, origin_rect.width(), origin_rect.height()),
-                    );
-                }
-            }
-        } else {
-            scene.fill(
-                peniko::Fill::NonZero,
-                transform,
-                &to_peniko_image(image_data, quality),
-                None,
-                &Rect::new(0.0, 0.0, origin_rect.width(), origin_rect.height()),
-            );
-        }
+        let position = compute_background_position(bg_styles, idx, area_size - size);
+        let size = size * self.scale;
+        let position = position.to_vec2() * self.scale;
+        Some(BackgroundTiles {
+            size,
+            x: TileAxis::new(
+                *repeat_x,
+                (area.x0, area.width()),
+                size.width,
+                position.x,
+                (clip_rect.x0, clip_rect.x1),
+            ),
+            y: TileAxis::new(
+                *repeat_y,
+                (area.y0, area.height()),
+                size.height,
+                position.y,
+                (clip_rect.y0, clip_rect.y1),
+            ),
+        })
     }
 
-    fn draw_gradient_bg(
-        &self,
-        scene: &mut impl PaintScene,
-        gradient: &StyloGradient,
-        idx: usize,
-        background_clip: StyloBackgroundClip,
-    ) {
-        use BackgroundRepeatKeyword::*;
-
-        let bg_styles = &self.style.get_background();
-
-        let background_origin = *get_cyclic(&bg_styles.background_origin.0, idx);
-        let origin_rect = match background_origin {
+    /// The area background layer `idx` is positioned (and sized) in, in the element's coordinates.
+    /// That's its `background-origin` box, or the viewport (or page) for `fixed` backgrounds, which
+    /// stay put as the document scrolls. The box of `local` backgrounds of scroll containers is
+    /// grown by how far their contents can be scrolled, and moves as they are.
+    fn background_positioning_area(&self, idx: usize) -> Rect {
+        let bg_styles = self.style.get_background();
+        let origin_rect = match get_cyclic(&bg_styles.background_origin.0, idx) {
             StyloBackgroundOrigin::BorderBox => self.frame.border_box,
             StyloBackgroundOrigin::PaddingBox => self.frame.padding_box,
             StyloBackgroundOrigin::ContentBox => self.frame.content_box,
         };
+        match self.background_attachment(idx) {
+            BackgroundAttachment::Scroll => return origin_rect,
+            BackgroundAttachment::Local => return self.local_positioning_area(origin_rect),
+            BackgroundAttachment::Fixed => {}
+        }
+
+        // Fixed backgrounds of transformed elements are positioned like `scroll` ones, as in
+        // other engines
+        let [a, b, c, d, x, y] = self.transform.as_coeffs();
+        if (a, b, c, d) != (1.0, 0.0, 0.0, 1.0) {
+            return origin_rect;
+        }
+        let viewport = match &self.context.page {
+            Some(page) => page.content.scale_from_origin(self.scale),
+            None => Rect::new(0.0, 0.0, self.context.width as f64, self.context.height as f64),
+        };
+        viewport - Vec2::new(x, y)
+    }
 
-        let (bg_pos, bg_size) = compute_background_position_and_background_size(
-            bg_styles,
-            origin_rect.width() / self.scale,
-            origin_rect.height() / self.scale,
-            idx,
-            BackgroundSizeComputeMode::Auto,
+    /// The `background-attachment` of layer `idx`. Declarations of the longhand are read from
+    /// the custom property stylo sees them as, as it doesn't parse `local`.
+    fn background_attachment(&self, idx: usize) -> BackgroundAttachment {
+        if let Some(attachments) = self.context.dom.background_attachments(self.node.id) {
+            return *get_cyclic(&attachments, idx);
+        }
+        let bg_styles = self.style.get_background();
+        match get_cyclic(&bg_styles.background_attachment.0, idx) {
+            StyloBackgroundAttachment::Scroll => BackgroundAttachment::Scroll,
+            StyloBackgroundAttachment::Fixed => BackgroundAttachment::Fixed,
+        }
+    }
+
+    /// `origin_rect` grown by how far the element's contents overflow its padding box, and moved
+    /// by how far they're scrolled, if it's a scroll container
+    fn local_positioning_area(&self, origin_rect: Rect) -> Rect {
+        let box_style = self.style.get_box();
+        let scrolls = |overflow| !matches!(overflow, Overflow::Visible | Overflow::Clip);
+        if !scrolls(box_style.overflow_x) && !scrolls(box_style.overflow_y) {
+            return origin_rect;
+        }
+
+        let (border_box, padding_box) = (self.frame.border_box, self.frame.padding_box);
+        let content_size = self.node.final_layout.content_size.map(f64::from);
+        let overflow_x = border_box.x0 + content_size.width * self.scale - padding_box.x1;
+        let overflow_y = border_box.y0 + content_size.height * self.scale - padding_box.y1;
+        let area = Rect::new(
+            origin_rect.x0,
+            origin_rect.y0,
+            origin_rect.x1 + overflow_x.max(0.0),
+            origin_rect.y1 + overflow_y.max(0.0),
         );
+        area - self.node.scroll_offset.to_vec2() * self.scale
+    }
+}
 
-        let bg_pos_x = bg_pos.x * self.scale;
-        let bg_pos_y = bg_pos.y * self.scale;
-        let bg_size = bg_size * self.scale;
+/// What each tile of a background layer is painted with
+enum TileContent<'a> {
+    /// An image, scaled to the size of the tile by the transform
+    Image(&'a peniko::Image, Affine),
+    /// A gradient, and its brush transform
+    Gradient(&'a peniko::Gradient, Option<Affine>),
+    /// An SVG too large to rasterize, scaled to the size of the tile by the transform
+    #[cfg(feature = "svg")]
+    Svg(&'a Arc<usvg::Tree>, Affine),
+}
 
-        let BackgroundRepeat(repeat_x, repeat_y) = get_cyclic(&bg_styles.background_repeat.0, idx);
+impl TileContent<'_> {
+    /// Paint a tile of `size` at the origin of `transform`
+    fn paint(&self, scene: &mut impl PaintScene, transform: Affine, size: Size) {
+        let tile = Rect::from_origin_size(Point::ZERO, size);
+        match self {
+            Self::Image(image, scale) => {
+                scene.fill(Fill::NonZero, transform, *image, Some(*scale), &tile);
+            }
+            Self::Gradient(gradient, brush_transform) => {
+                let paint = anyrender::Paint::Gradient(*gradient);
+                scene.fill(Fill::NonZero, transform, paint, *brush_transform, &tile);
+            }
+            #[cfg(feature = "svg")]
+            Self::Svg(svg, scale) => anyrender_svg::render_svg_tree(scene, svg, transform * *scale),
+        }
+    }
 
-        let transform = self.transform;
-        let (origin_rect, transform, width_count, width_gap) = match repeat_x {
-            Repeat | Round => {
-                let (origin_rect, extend_width, count) = if (background_clip, background_origin)
-                    == (
-                        StyloBackgroundClip::BorderBox,
-                        StyloBackgroundOrigin::PaddingBox,
-                    ) {
-                    let extend_width =
-                        extend(self.frame.border_width.left + bg_pos_x, bg_size.width);
-
-                    let width = self.frame.border_box.width() + extend_width;
-                    let count = (width / bg_size.width).ceil() as u32;
-
-                    let origin_rect = Rect::from_origin_size(
-                        Point::new(self.frame.border_box.x0, origin_rect.y0),
-                        Size::new(bg_size.width, origin_rect.height()),
-                    );
-
-                    (origin_rect, extend_width, count)
-                } else if (background_clip, background_origin)
-                    == (
-                        StyloBackgroundClip::BorderBox,
-                        StyloBackgroundOrigin::ContentBox,
-                    )
-                {
-                    let extend_width = extend(
-                        self.frame.border_width.left + self.frame.padding_width.left + bg_pos_x,
-                        bg_size.width,
-                    );
-                    let width = self.frame.border_box.width() + extend_width;
-                    let count = (width / bg_size.width).ceil() as u32;
-
-                    let origin_rect = Rect::from_origin_size(
-                        Point::new(self.frame.border_box.x0, origin_rect.y0),
-                        Size::new(bg_size.width, origin_rect.height()),
-                    );
-
-                    (origin_rect, extend_width, count)
-                } else if (background_clip, background_origin)
-                    == (
-                        StyloBackgroundClip::PaddingBox,
-                        StyloBackgroundOrigin::ContentBox,
-                    )
-                {
-                    let extend_width =
-                        extend(self.frame.padding_width.left + bg_pos_x, bg_size.width);
-                    let width = self.frame.padding_box.width() + extend_width;
-                    let count = (width / bg_size.width).ceil() as u32;
-
-                    let origin_rect = Rect::from_origin_size(
-                        Point::new(self.frame.padding_box.x0, origin_rect.y0),
-                        Size::new(bg_size.width, origin_rect.height()),
-                    );
-
-                    (origin_rect, extend_width, count)
-                } else {
-                    let extend_width = extend(bg_pos_x, bg_size.width);
-                    let width = origin_rect.width() + extend_width;
-                    let count = (width / bg_size.width).ceil() as u32;
-                    let origin_rect =
-                        origin_rect.with_size(Size::new(bg_size.width, origin_rect.height()));
-
-                    (origin_rect, extend_width, count)
-                };
-
-                let transform = transform.then_translate(Vec2 {
-                    x: origin_rect.x0 - extend_width,
-                    y: 0.0,
-                });
-
-                (origin_rect, transform, count, bg_size.width)
+    /// What the raster of a tile painted with this is cached by
+    #[cfg(feature = "tile-rasters")]
+    fn raster_content(&self) -> TileRasterContent {
+        match self {
+            Self::Image(image, scale) => TileRasterContent::Image((*image).clone(), *scale),
+            Self::Gradient(gradient, brush_transform) => {
+                TileRasterContent::Gradient((*gradient).clone(), *brush_transform)
             }
-            Space => {
-                let (count, gap) = compute_space_count_and_gap(origin_rect.width(), bg_size.width);
+            #[cfg(feature = "svg")]
+            Self::Svg(svg, scale) => TileRasterContent::Svg(Arc::clone(svg), *scale),
+        }
+    }
+}
 
-                let transform = transform.then_translate(Vec2 {
-                    x: origin_rect.x0 + if count == 1 { bg_pos_x } else { 0.0 },
-                    y: 0.0,
-                });
+/// Where the tiles of a background layer go, in the element's (scaled) coordinates. Tiles cover
+/// the layer's `background-clip` box.
+struct BackgroundTiles {
+    size: Size,
+    x: TileAxis,
+    y: TileAxis,
+}
 
-                let origin_rect =
-                    origin_rect.with_size(Size::new(bg_size.width, origin_rect.height()));
+impl BackgroundTiles {
+    fn count(&self) -> u32 {
+        self.x.count.saturating_mul(self.y.count)
+    }
 
-                (origin_rect, transform, count, gap)
-            }
-            NoRepeat => {
-                let transform = transform.then_translate(Vec2 {
-                    x: origin_rect.x0 + bg_pos_x,
-                    y: 0.0,
-                });
+    /// Whether there are gaps between tiles (from `background-repeat: space`)
+    fn is_spaced(&self) -> bool {
+        self.x.step != self.size.width || self.y.step != self.size.height
+    }
 
-                let origin_rect =
-                    origin_rect.with_size(Size::new(bg_size.width, origin_rect.height()));
+    /// The top left corners of the tiles
+    fn origins(&self) -> impl Iterator<Item = Point> + '_ {
+        let ys = (0..self.y.count).map(|i| self.y.start + i as f64 * self.y.step);
+        ys.flat_map(|y| {
+            let xs = (0..self.x.count).map(|i| self.x.start + i as f64 * self.x.step);
+            xs.map(move |x| Point::new(x, y))
+        })
+    }
 
-                (origin_rect, transform, 1, 0.0)
-            }
-        };
-        let (origin_rect, transform, height_count, height_gap) = match repeat_y {
-            Repeat | Round => {
-                let (origin_rect, extend_height, count) = if (background_clip, background_origin)
-                    == (
-                        StyloBackgroundClip::BorderBox,
-                        StyloBackgroundOrigin::PaddingBox,
-                    ) {
-                    let extend_height =
-                        extend(self.frame.border_width.top + bg_pos_y, bg_size.height);
-                    let height = self.frame.border_box.height() + extend_height;
-                    let count = (height / bg_size.height).ceil() as u32;
-
-                    let origin_rect = Rect::from_origin_size(
-                        Point::new(origin_rect.x0, self.frame.border_box.y0),
-                        Size::new(origin_rect.width(), bg_size.height),
-                    );
-
-                    (origin_rect, extend_height, count)
-                } else if (background_clip, background_origin)
-                    == (
-                        StyloBackgroundClip::BorderBox,
-                        StyloBackgroundOrigin::ContentBox,
-                    )
-                {
-                    let extend_height = extend(
-                        self.frame.border_width.top + self.frame.padding_width.top + bg_pos_x,
-                        bg_size.height,
-                    );
-                    let height = self.frame.border_box.height() + extend_height;
-                    let count = (height / bg_size.height).ceil() as u32;
-
-                    let origin_rect = Rect::from_origin_size(
-                        Point::new(origin_rect.x0, self.frame.border_box.y0),
-                        Size::new(origin_rect.width(), bg_size.height),
-                    );
-
-                    (origin_rect, extend_height, count)
-                } else if (background_clip, background_origin)
-                    == (
-                        StyloBackgroundClip::PaddingBox,
-                        StyloBackgroundOrigin::ContentBox,
-                    )
-                {
-                    let extend_height =
-                        extend(self.frame.padding_width.top + bg_pos_x, bg_size.height);
-                    let height = self.frame.padding_box.height() + extend_height;
-                    let count = (height / bg_size.height).ceil() as u32;
-
-                    let origin_rect = Rect::from_origin_size(
-                        Point::new(origin_rect.x0, self.frame.padding_box.y0),
-                        Size::new(origin_rect.width(), bg_size.height),
-                    );
-
-                    (origin_rect, extend_height, count)
-                } else {
-                    let extend_height = extend(bg_pos_x, bg_size.height);
-                    let height = origin_rect.height() + extend_height;
-                    let count = (height / bg_size.height).ceil() as u32;
-                    let origin_rect =
-                        origin_rect.with_size(Size::new(origin_rect.width(), bg_size.height));
-
-                    (origin_rect, extend_height, count)
-                };
-
-                let transform = transform.then_translate(Vec2 {
-                    x: 0.0,
-                    y: origin_rect.y0 - extend_height,
-                });
-
-                (origin_rect, transform, count, bg_size.height)
-            }
-            Space => {
-                let (count, gap) =
-                    compute_space_count_and_gap(origin_rect.height(), bg_size.height);
+    /// The box around all the tiles
+    fn bounds(&self) -> Rect {
+        Rect::new(
+            self.x.start,
+            self.y.start,
+            self.x.start + (self.x.count - 1) as f64 * self.x.step + self.size.width,
+            self.y.start + (self.y.count - 1) as f64 * self.y.step + self.size.height,
+        )
+    }
+}
 
-                let transform = transform.then_translate(Vec2 {
-                    x: 0.0,
-                    y: origin_rect.y0 + if count == 1 { bg_pos_y } else { 0.0 },
-                });
+/// The tiles of a background layer along one axis
+struct TileAxis {
+    start: f64,
+    count: u32,
+    /// The distance from the start of one tile to the start of the next
+    step: f64,
+}
 
-                let origin_rect =
-                    origin_rect.with_size(Size::new(origin_rect.width(), bg_size.height));
+impl TileAxis {
+    /// Tiles of `size`, repeated with `repeat` across `(start, length)` of the positioning area,
+    /// where they're `position`ed, and covering `(start, end)` of the clip box
+    fn new(
+        repeat: BackgroundRepeatKeyword,
+        (area_start, area_length): (f64, f64),
+        size: f64,
+        position: f64,
+        (clip_start, clip_end): (f64, f64),
+    ) -> Self {
+        use BackgroundRepeatKeyword::*;
 
-                (origin_rect, transform, count, gap)
-            }
-            NoRepeat => {
-                let transform = transform.then_translate(Vec2 {
-                    x: 0.0,
-                    y: origin_rect.y0 + bg_pos_y,
-                });
-
-                let origin_rect =
-                    origin_rect.with_size(Size::new(origin_rect.width(), bg_size.height));
-                (origin_rect, transform, 1, 0.0)
+        let (anchor, step) = match repeat {
+            Repeat | Round => (area_start + position, size),
+            // As many tiles as fit without being clipped, spaced evenly from edge to edge, or a
+            // single tile where it's positioned
+            Space => {
+                let count = (area_length / size).floor();
+                if count < 2.0 {
+                    return Self::single(area_start + position, size);
+                }
+                (area_start, size + (area_length - count * size) / (count - 1.0))
             }
+            NoRepeat => return Self::single(area_start + position, size),
         };
 
-        // FIXME: https://wpt.live/css/css-backgrounds/background-size/background-size-near-zero-gradient.html
-        if width_count * height_count > 500 {
-            return;
-        }
-
-        let origin_rect = Rect::new(0.0, 0.0, origin_rect.width(), origin_rect.height());
-        let bounding_box = self.frame.border_box.bounding_box();
-        let current_color = self.style.clone_color();
-
-        let (gradient, gradient_transform) = to_peniko_gradient(
-            gradient,
-            origin_rect,
-            bounding_box,
-            self.scale,
-            &current_color,
-        );
+        let start = anchor - ((anchor - clip_start) / step).ceil() * step;
+        let count = ((clip_end - start) / step).ceil().max(1.0) as u32;
+        Self { start, count, step }
+    }
 
-        for hc in 0..height_count {
-            for wc in 0..width_count {
-                let transform = transform.then_translate(Vec2 {
-                    x: wc as f64 * width_gap,
-                    y: hc as f64 * height_gap,
-                });
-
-                scene.fill(
-                    peniko::Fill::NonZero,
-                    transform,
-                    anyrender::Paint::Gradient(&gradient),
-                    gradient_transform,
-                    &origin_rect,
-                );
-            }
+    fn single(start: f64, size: f64) -> Self {
+        Self {
+            start,
+            count: 1,
+            step: size,
         }
     }
 }
 
-fn compute_background_position_and_background_size(
-    background: &Background,
-    container_w: f64,
-    container_h: f64,
-    bg_idx: usize,
-    size_mode: BackgroundSizeComputeMode,
-) -> (Point, Size) {
-    use BackgroundRepeatKeyword::*;
-
-    let bg_size = compute_background_size(
-        background,
-        container_w as f32,
-        container_h as f32,
-        bg_idx,
-        size_mode,
-        1.0,
-    );
-
-    let bg_pos = compute_background_position(
-        background,
-        bg_idx,
-        (container_w - bg_size.width) as f32,
-        (container_h - bg_size.height) as f32,
-    );
-
-    let BackgroundRepeat(repeat_x, repeat_y) = get_cyclic(&background.background_repeat.0, bg_idx);
-
-    let bg_size = if matches!(repeat_x, Round) && matches!(repeat_y, Round) {
-        let count = (container_w / bg_size.width).round();
-        let width = container_w / count;
-
-        let count = (container_h / bg_size.height).round();
-        let height = container_h / count;
-
-        Size::new(width, height)
-    } else if matches!(repeat_x, Round) {
-        let count = (container_w / bg_size.width).round();
-        let width = container_w / count;
-        Size::new(width, bg_size.height)
-    } else if matches!(repeat_y, Round) {
-        let count = (container_h / bg_size.height).round();
-        let height = container_h / count;
-        Size::new(bg_size.width, height)
-    } else {
-        bg_size
-    };
+/// The length of tiles along an axis with `background-repeat: round`, which fit a whole number of
+/// times into the positioning area
+fn round_tile_length(repeat: BackgroundRepeatKeyword, length: f64, area_length: f64) -> f64 {
+    if repeat != BackgroundRepeatKeyword::Round || length <= 0.0 {
+        return length;
+    }
+    area_length / (area_length / length).round().max(1.0)
+}
+
+/// Whether layer `bg_idx` has an `auto` width and height in its `background-size`
+fn auto_background_size(background: &Background, bg_idx: usize) -> (bool, bool) {
+    use style::values::computed::BackgroundSize;
+    use style::values::generics::length::GenericLengthPercentageOrAuto as Lpa;
 
-    (bg_pos, bg_size)
+    match get_cyclic(&background.background_size.0, bg_idx) {
+        BackgroundSize::ExplicitSize { width, height } => {
+            (matches!(width, Lpa::Auto), matches!(height, Lpa::Auto))
+        }
+        BackgroundSize::Cover | BackgroundSize::Contain => (false, false),
+    }
 }
 
+/// The offset of layer `bg_idx` in its positioning area, given the `free_space` around it
 #[inline]
-fn compute_background_position(
-    background: &Background,
-    bg_idx: usize,
-    width: f32,
-    height: f32,
-) -> Point {
+fn compute_background_position(background: &Background, bg_idx: usize, free_space: Size) -> Point {
     use style::values::computed::Length;
 
     let bg_pos_x = get_cyclic(&background.background_position_x.0, bg_idx)
-        .resolve(Length::new(width))
+        .resolve(Length::new(free_space.width as f32))
         .px() as f64;
     let bg_pos_y = get_cyclic(&background.background_position_y.0, bg_idx)
-        .resolve(Length::new(height))
+        .resolve(Length::new(free_space.height as f32))
         .px() as f64;
 
     Point::new(bg_pos_x, bg_pos_y)
 }
 
+/// The size of layer `bg_idx` in a positioning area of `area` (in CSS px), for an image of
+/// `natural_size` (or a gradient, which has none)
 fn compute_background_size(
     background: &Background,
-    container_w: f32,
-    container_h: f32,
+    area: Size,
     bg_idx: usize,
-    mode: BackgroundSizeComputeMode,
-    scale: f32,
-) -> kurbo::Size {
+    natural_size: Option<Size>,
+) -> Size {
     use style::values::computed::{BackgroundSize, Length};
     use style::values::generics::length::GenericLengthPercentageOrAuto as Lpa;
 
     let bg_size = get_cyclic(&background.background_size.0, bg_idx);
+    let Some(natural) = natural_size.filter(|size| size.width > 0.0 && size.height > 0.0) else {
+        // Gradients fill the area, unless they're given a size
+        let BackgroundSize::ExplicitSize { width, height } = bg_size else {
+            return area;
+        };
+        let width = match width {
+            Lpa::LengthPercentage(width) => width.0.resolve(Length::new(area.width as f32)).px(),
+            Lpa::Auto => area.width as f32,
+        };
+        let height = match height {
+            Lpa::LengthPercentage(height) => height.0.resolve(Length::new(area.height as f32)).px(),
+            Lpa::Auto => area.height as f32,
+        };
+        return Size::new(width as f64, height as f64);
+    };
 
-    let (width, height): (f32, f32) = match bg_size {
+    match bg_size {
         BackgroundSize::ExplicitSize { width, height } => {
-            let width = width.map(|w| w.0.resolve(Length::new(container_w)));
-            let height = height.map(|h| h.0.resolve(Length::new(container_h)));
-
+            let width = width.map(|w| w.0.resolve(Length::new(area.width as f32)).px() as f64);
+            let height = height.map(|h| h.0.resolve(Length::new(area.height as f32)).px() as f64);
             match (width, height) {
                 (Lpa::LengthPercentage(width), Lpa::LengthPercentage(height)) => {
-                    let width = width.px();
-                    let height = height.px();
-                    match mode {
-                        BackgroundSizeComputeMode::Auto => (width, height),
-                        BackgroundSizeComputeMode::Size(_, _) => (width, height),
-                    }
+                    Size::new(width, height)
                 }
                 (Lpa::LengthPercentage(width), Lpa::Auto) => {
-                    let width = width.px();
-                    let height = match mode {
-                        BackgroundSizeComputeMode::Auto => container_h,
-                        BackgroundSizeComputeMode::Size(bg_w, bg_h) => bg_h / bg_w * width,
-                    };
-                    (width, height)
+                    Size::new(width, natural.height / natural.width * width)
                 }
                 (Lpa::Auto, Lpa::LengthPercentage(height)) => {
-                    let height = height.px();
-                    let width = match mode {
-                        BackgroundSizeComputeMode::Auto => container_w,
-                        BackgroundSizeComputeMode::Size(bg_w, bg_h) => bg_w / bg_h * height,
-                    };
-                    (width, height)
+                    Size::new(natural.width / natural.height * height, height)
                 }
-                (Lpa::Auto, Lpa::Auto) => match mode {
-                    BackgroundSizeComputeMode::Auto => (container_w, container_h),
-                    BackgroundSizeComputeMode::Size(bg_w, bg_h) => (bg_w * scale, bg_h * scale),
-                },
+                (Lpa::Auto, Lpa::Auto) => natural,
             }
         }
-        BackgroundSize::Cover => match mode {
-            BackgroundSizeComputeMode::Auto => (container_w, container_h),
-            BackgroundSizeComputeMode::Size(bg_w, bg_h) => {
-                let x_ratio = container_w / bg_w;
-                let y_ratio = container_h / bg_h;
-
-                let ratio = if x_ratio < 1.0 || y_ratio < 1.0 {
-                    x_ratio.min(y_ratio)
-                } else {
-                    x_ratio.max(y_ratio)
-                };
-
-                (bg_w * ratio, bg_h * ratio)
-            }
-        },
-        BackgroundSize::Contain => match mode {
-            BackgroundSizeComputeMode::Auto => (container_w, container_h),
-            BackgroundSizeComputeMode::Size(bg_w, bg_h) => {
-                let x_ratio = container_w / bg_w;
-                let y_ratio = container_h / bg_h;
-
-                let ratio = if x_ratio < 1.0 || y_ratio < 1.0 {
-                    x_ratio.max(y_ratio)
-                } else {
-                    x_ratio.min(y_ratio)
-                };
-
-                (bg_w * ratio, bg_h * ratio)
-            }
-        },
-    };
-
-    kurbo::Size {
-        width: width as f64,
-        height: height as f64,
+        // The smallest size which covers the area, and the largest which fits in it
+        BackgroundSize::Cover => {
+            natural * (area.width / natural.width).max(area.height / natural.height)
+        }
+        BackgroundSize::Contain => {
+            natural * (area.width / natural.width).min(area.height / natural.height)
+        }
     }
 }
 
-enum BackgroundSizeComputeMode {
-    Auto,
-    Size(f32, f32),
-}
-
-fn compute_space_count_and_gap(bg_size: f64, size: f64) -> (u32, f64) {
-    let modulo = bg_size % size;
-    let count = (((bg_size - modulo) / size) as u32).max(1);
-    let gap = if count > 1 {
-        modulo / (count - 1) as f64
-    } else {
-        0.0
-    } + size;
-
-    (count, gap)
-}
-
 #[inline]
 fn get_cyclic<T>(values: &[T], layer_index: usize) -> &T {
     &values[layer_index % values.len()]
 }
 
-fn extend(offset: f64, length: f64) -> f64 {
-    let extend_length = offset % length;
-    if extend_length > 0.0 {
-        length - extend_length
-    } else {
-        -extend_length
+#[cfg(test)]
+mod tests {
+    use anyrender::Paint;
+    use blitz_dom::{
+        Attribute, BaseDocument, DocumentConfig, DocumentMutator, QualName, QuirksMode,
+        local_name, ns,
+    };
+    use blitz_traits::shell::{ColorScheme, Viewport};
+    use peniko::{BlendMode, BrushRef};
+
+    use super::*;
+    use crate::paint_scene;
+
+    /// A scene which records the area (in device pixels) of each fill with a gradient
+    #[derive(Default)]
+    struct GradientFills(Vec<Rect>);
+
+    impl PaintScene for GradientFills {
+        fn reset(&mut self) {
+            self.0.clear();
+        }
+
+        fn push_layer(
+            &mut self,
+            _blend: impl Into<BlendMode>,
+            _alpha: f32,
+            _transform: Affine,
+            _clip: &impl Shape,
+        ) {
+        }
+
+        fn pop_layer(&mut self) {}
+
+        fn stroke<'a>(
+            &mut self,
+            _style: &kurbo::Stroke,
+            _transform: Affine,
+            _brush: impl Into<BrushRef<'a>>,
+            _brush_transform: Option<Affine>,
+            _shape: &impl Shape,
+        ) {
+        }
+
+        fn fill<'a>(
+            &mut self,
+            _style: Fill,
+            transform: Affine,
+            brush: impl Into<Paint<'a>>,
+            _brush_transform: Option<Affine>,
+            shape: &impl Shape,
+        ) {
+            if let Paint::Gradient(_) = brush.into() {
+                self.0.push(transform.transform_rect_bbox(shape.bounding_box()));
+            }
+        }
+
+        fn render_text_buffer(
+            &mut self,
+            _buffer: &blitz_text::Buffer,
+            _position: Point,
+            _color: peniko::Color,
+            _transform: Affine,
+        ) {
+        }
+
+        fn draw_box_shadow(
+            &mut self,
+            _transform: Affine,
+            _rect: Rect,
+            _brush: peniko::Color,
+            _radius: f64,
+            _std_dev: f64,
+        ) {
+        }
+    }
+
+    /// Append a `div` with inline `style` to `parent`, returning its id
+    fn append_div(mutr: &mut DocumentMutator, parent: usize, style: &str) -> usize {
+        let attrs = vec![Attribute {
+            name: QualName::new(None, ns!(), local_name!("style")),
+            value: style.to_string(),
+        }];
+        let name = QualName::new(None, ns!(html), local_name!("div"));
+        let element = mutr.create_element(name, attrs, QuirksMode::NoQuirks);
+        mutr.append_children(parent, &[element]);
+        element
+    }
+
+    /// A document with a `div` with inline `style` 100px down the page, holding a `div` 300px
+    /// tall. Returns the id of the outer `div`.
+    fn document(style: &str) -> (BaseDocument, usize) {
+        let mut doc = BaseDocument::new(DocumentConfig {
+            viewport: Some(Viewport::new(800, 600, 1.0, ColorScheme::Light)),
+            ..DocumentConfig::for_testing()
+        })
+        .unwrap();
+
+        let mut mutr = doc.mutate();
+        let body = append_div(&mut mutr, 0, "padding-top: 100px");
+        let div = append_div(&mut mutr, body, style);
+        append_div(&mut mutr, div, "height: 300px");
+        drop(mutr);
+        doc.resolve();
+        (doc, div)
+    }
+
+    fn gradient_fills(doc: &BaseDocument) -> Vec<Rect> {
+        let mut scene = GradientFills::default();
+        paint_scene(&mut scene, doc, 1.0, 800, 600);
+        scene.0
+    }
+
+    #[test]
+    fn layers_are_painted_bottom_first() {
+        let (doc, _) = document(
+            "width: 100px; height: 100px; \
+             background: linear-gradient(red, red) 10px 20px / 30px 40px no-repeat, \
+             linear-gradient(blue, blue)",
+        );
+        assert_eq!(
+            gradient_fills(&doc),
+            [
+                Rect::new(0.0, 100.0, 100.0, 200.0),
+                Rect::new(10.0, 120.0, 40.0, 160.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn tiles_are_sized_by_background_size() {
+        let (doc, _) = document(
+            "width: 200px; height: 100px; \
+             background: linear-gradient(red, blue) no-repeat; background-size: 50% 25px",
+        );
+        assert_eq!(gradient_fills(&doc), [Rect::new(0.0, 100.0, 100.0, 125.0)]);
+
+        // Repeated tiles cover the element
+        let (doc, _) = document(
+            "width: 200px; height: 100px; \
+             background: linear-gradient(red, blue); background-size: 100px 50px",
+        );
+        assert_eq!(gradient_fills(&doc).len(), 4);
+    }
+
+    #[test]
+    fn fixed_backgrounds_are_positioned_in_the_viewport() {
+        let (doc, _) = document(
+            "width: 100px; height: 100px; \
+             background: linear-gradient(red, blue) fixed no-repeat; background-size: 100% 50%",
+        );
+        assert_eq!(gradient_fills(&doc), [Rect::new(0.0, 0.0, 800.0, 300.0)]);
+    }
+
+    #[test]
+    fn local_backgrounds_scroll_with_the_contents() {
+        let scroller = "width: 100px; height: 100px; overflow: auto; \
+                        background: linear-gradient(red, blue) no-repeat; \
+                        background-size: 100% 100%;";
+        let (mut doc, div) = document(&format!("{scroller} background-attachment: local"));
+        let fills = gradient_fills(&doc);
+        assert_eq!((fills.len(), fills[0].y0, fills[0].height()), (1, 100.0, 300.0));
+
+        doc.scroll_node_by(div, 0.0, -50.0);
+        let fills = gradient_fills(&doc);
+        assert_eq!((fills.len(), fills[0].y0, fills[0].height()), (1, 50.0, 300.0));
+
+        // The `background` shorthand resets earlier `background-attachment` declarations
+        let (doc, _) = document(&format!("background-attachment: local; {scroller}"));
+        let fills = gradient_fills(&doc);
+        assert_eq!((fills.len(), fills[0].y0, fills[0].height()), (1, 100.0, 100.0));
+    }
+
+    #[test]
+    fn tiles_cover_the_clip_box() {
+        use BackgroundRepeatKeyword::*;
+
+        // Repeated tiles start before the positioned one, to cover the clip box from its start
+        let axis = TileAxis::new(Repeat, (10.0, 100.0), 30.0, 5.0, (0.0, 120.0));
+        assert_eq!((axis.start, axis.count, axis.step), (-15.0, 5, 30.0));
+
+        let axis = TileAxis::new(NoRepeat, (10.0, 100.0), 30.0, 5.0, (0.0, 120.0));
+        assert_eq!((axis.start, axis.count, axis.step), (15.0, 1, 30.0));
+
+        // Three tiles fit, with the 10px left over split between the two gaps
+        let axis = TileAxis::new(Space, (0.0, 100.0), 30.0, 5.0, (0.0, 100.0));
+        assert_eq!((axis.start, axis.count, axis.step), (0.0, 3, 35.0));
+
+        assert_eq!(round_tile_length(Round, 30.0, 100.0), 100.0 / 3.0);
+        assert_eq!(round_tile_length(Round, 300.0, 100.0), 100.0);
     }
 }
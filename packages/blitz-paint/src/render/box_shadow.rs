@@ -1,10 +1,14 @@
 use anyrender::PaintScene;
 use kurbo::{Rect, Vec2};
+use peniko::{BlendMode, Compose, Fill, Mix};
+use style::properties::ComputedValues;
+use style::values::computed::BoxShadow;
 
 use super::ElementCx;
 use crate::{
     color::{Color, ToColorColor as _},
     layers::maybe_with_layer,
+    non_uniform_rounded_rect::{NonUniformRoundedRect, NonUniformRoundedRectRadii},
 };
 
 impl ElementCx<'_> {
@@ -12,73 +16,44 @@ impl ElementCx<'_> {
         let box_shadow = &self.style.get_effects().box_shadow.0;
 
         // TODO: Only apply clip if element has transparency
-        let has_outset_shadow = box_shadow.iter().any(|s| !s.inset);
-        if !has_outset_shadow {
+        let Some(max_shadow_rect) = self.outset_shadow_rect() else {
             return;
-        }
-
-        let current_color = self.style.clone_color();
-        let max_shadow_rect = box_shadow.iter().fold(Rect::ZERO, |prev, shadow| {
-            let x = shadow.base.horizontal.px() as f64 * self.scale;
-            let y = shadow.base.vertical.px() as f64 * self.scale;
-            let blur = shadow.base.blur.px() as f64 * self.scale;
-            let spread = shadow.spread.px() as f64 * self.scale;
-            let offset = spread + blur * 2.5;
-
-            let rect = self.frame.border_box.inflate(offset, offset) + Vec2::new(x, y);
-
-            prev.union(rect)
-        });
+        };
 
         maybe_with_layer(
             scene,
-            has_outset_shadow,
+            true,
             1.0,
             self.transform,
             &self.frame.shadow_clip(max_shadow_rect),
             |scene| {
+                // The first shadow is painted on top
                 for shadow in box_shadow.iter().filter(|s| !s.inset).rev() {
-                    let shadow_color = shadow
-                        .base
-                        .color
-                        .resolve_to_absolute(&current_color)
-                        .as_srgb_color();
-
-                    let alpha = shadow_color.components[3];
-                    if alpha != 0.0 {
-                        let transform = self.transform.then_translate(Vec2 {
-                            x: shadow.base.horizontal.px() as f64 * self.scale,
-                            y: shadow.base.vertical.px() as f64 * self.scale,
-                        });
-
-                        // TODO draw shadows with matching individual radii instead of averaging
-                        let radius = self.frame.border_radii.average();
-
-                        let spread = shadow.spread.px() as f64 * self.scale;
-                        let rect = self.frame.border_box.inflate(spread, spread);
-
-                        // Fill the color
-                        scene.draw_box_shadow(
-                            transform,
-                            rect,
-                            shadow_color,
-                            radius,
-                            shadow.base.blur.px() as f64,
-                        );
-                    }
+                    let Some(shadow_color) = self.shadow_color(shadow) else {
+                        continue;
+                    };
+
+                    // Shadows are cast by the border box grown by their spread
+                    let spread = shadow.spread.px() as f64 * self.scale;
+                    let rect = self.frame.border_box.inflate(spread, spread);
+                    let rect = rect + self.shadow_offset(shadow);
+                    let radii = spread_radii(self.frame.border_radii, spread);
+                    let shape = NonUniformRoundedRect::new(rect, radii);
+
+                    self.draw_shadow_shape(scene, shape, shadow_color, shadow);
                 }
             },
         )
     }
 
     pub(super) fn draw_inset_box_shadow(&self, scene: &mut impl PaintScene) {
-        let current_color = self.style.clone_color();
         let box_shadow = &self.style.get_effects().box_shadow.0;
         let has_inset_shadow = box_shadow.iter().any(|s| s.inset);
         if !has_inset_shadow {
             return;
         }
 
+        let padding_box = self.frame.padding_box;
         maybe_with_layer(
             scene,
             has_inset_shadow,
@@ -86,32 +61,193 @@ impl ElementCx<'_> {
             self.transform,
             &self.frame.padding_box_path(),
             |scene| {
-                for shadow in box_shadow.iter().filter(|s| s.inset) {
-                    let shadow_color = shadow
-                        .base
-                        .color
-                        .resolve_to_absolute(&current_color)
-                        .as_srgb_color();
-                    if shadow_color != Color::TRANSPARENT {
-                        let transform = self.transform.then_translate(Vec2 {
-                            x: shadow.base.horizontal.px() as f64,
-                            y: shadow.base.vertical.px() as f64,
-                        });
-
-                        // TODO draw shadows with matching individual radii instead of averaging
-                        let radius = self.frame.border_radii.average();
-
-                        // Fill the color
-                        scene.draw_box_shadow(
-                            transform,
-                            self.frame.border_box,
-                            shadow_color,
-                            radius,
-                            shadow.base.blur.px() as f64 * self.scale,
-                        );
+                // The first shadow is painted on top
+                for shadow in box_shadow.iter().filter(|s| s.inset).rev() {
+                    let Some(shadow_color) = self.shadow_color(shadow) else {
+                        continue;
+                    };
+
+                    // Inset shadows fill the padding box, except for the hole cast by the padding
+                    // box shrunk by their spread, which is knocked out of a layer of their own
+                    scene.push_layer(Mix::Normal, 1.0, self.transform, &padding_box);
+                    scene.fill(Fill::NonZero, self.transform, shadow_color, None, &padding_box);
+
+                    let spread = shadow.spread.px() as f64 * self.scale;
+                    let hole = padding_box.inflate(-spread, -spread);
+                    if hole.width() > 0.0 && hole.height() > 0.0 {
+                        let hole = hole + self.shadow_offset(shadow);
+                        let radii = spread_radii(self.frame.padding_radii(), -spread);
+                        let shape = NonUniformRoundedRect::new(hole, radii);
+
+                        let knock_out = BlendMode::new(Mix::Normal, Compose::DestOut);
+                        scene.push_layer(knock_out, 1.0, self.transform, &padding_box);
+                        self.draw_shadow_shape(scene, shape, Color::BLACK, shadow);
+                        scene.pop_layer();
                     }
+                    scene.pop_layer();
                 }
             },
         );
     }
+
+    /// The area (in local device px) the element's outset shadows are drawn in, if it has any
+    pub(super) fn outset_shadow_rect(&self) -> Option<Rect> {
+        outset_shadow_rect(&self.style, self.frame.border_box, self.scale)
+    }
+
+    /// The color of `shadow`, unless it's transparent
+    fn shadow_color(&self, shadow: &BoxShadow) -> Option<Color> {
+        let shadow_color = shadow
+            .base
+            .color
+            .resolve_to_absolute(&self.style.clone_color())
+            .as_output_color(self.context.color_space);
+        (shadow_color.components[3] != 0.0).then_some(shadow_color)
+    }
+
+    fn shadow_offset(&self, shadow: &BoxShadow) -> Vec2 {
+        shadow_offset(shadow, self.scale)
+    }
+
+    /// Fill the shape `shadow` is cast by, blurred by its blur radius
+    fn draw_shadow_shape(
+        &self,
+        scene: &mut impl PaintScene,
+        shape: NonUniformRoundedRect,
+        color: Color,
+        shadow: &BoxShadow,
+    ) {
+        let blur = shadow.base.blur.px() as f64 * self.scale;
+        if blur <= 0.0 {
+            scene.fill(Fill::NonZero, self.transform, color, None, &shape.path());
+            return;
+        }
+
+        // Shadows are blurred with a standard deviation of half their blur radius
+        let std_dev = blur / 2.0;
+        let rect = shape.rect();
+        let [top_left, top_right, bottom_right, bottom_left] = corner_radii(shape.radii());
+        if [top_right, bottom_right, bottom_left].iter().all(|&radius| radius == top_left) {
+            scene.draw_box_shadow(self.transform, rect, color, top_left, std_dev);
+            return;
+        }
+
+        // Blurred shadows can only be drawn with one radius, so each quarter of the shadow is
+        // drawn with the radius of its corner, clipped to the quarter. They meet at the (pixel
+        // aligned) center of the shape.
+        let bounds = rect.inflate(blur * 2.0, blur * 2.0);
+        let center = rect.center().round();
+        let quarters = [
+            (Rect::new(bounds.x0, bounds.y0, center.x, center.y), top_left),
+            (Rect::new(center.x, bounds.y0, bounds.x1, center.y), top_right),
+            (Rect::new(center.x, center.y, bounds.x1, bounds.y1), bottom_right),
+            (Rect::new(bounds.x0, center.y, center.x, bounds.y1), bottom_left),
+        ];
+        for (quarter, radius) in quarters {
+            scene.push_layer(Mix::Clip, 1.0, self.transform, &quarter);
+            scene.draw_box_shadow(self.transform, rect, color, radius, std_dev);
+            scene.pop_layer();
+        }
+    }
+}
+
+/// The radii of the corners of a shadow's shape, which are those of the box it's cast by grown
+/// (or shrunk) by its `spread`. See <https://drafts.csswg.org/css-backgrounds/#shadow-shape>.
+fn spread_radii(radii: NonUniformRoundedRectRadii, spread: f64) -> NonUniformRoundedRectRadii {
+    let spread_radius = |radius: f64| {
+        // Sharp corners stay sharp, and small radii grow less than the spread so that slightly
+        // rounded boxes don't cast very rounded shadows
+        if radius <= 0.0 {
+            return 0.0;
+        }
+        if spread <= 0.0 {
+            return (radius + spread).max(0.0);
+        }
+        let ratio = radius / spread;
+        if ratio < 1.0 {
+            radius + spread * (1.0 + (ratio - 1.0).powi(3))
+        } else {
+            radius + spread
+        }
+    };
+    let spread_corner = |radius: Vec2| Vec2::new(spread_radius(radius.x), spread_radius(radius.y));
+    NonUniformRoundedRectRadii {
+        top_left: spread_corner(radii.top_left),
+        top_right: spread_corner(radii.top_right),
+        bottom_right: spread_corner(radii.bottom_right),
+        bottom_left: spread_corner(radii.bottom_left),
+    }
+}
+
+/// The radius of each corner (clockwise from the top left) of a blurred shadow, which is circular.
+/// Elliptical corners are drawn with the mean of their horizontal and vertical radii.
+fn corner_radii(radii: NonUniformRoundedRectRadii) -> [f64; 4] {
+    [radii.top_left, radii.top_right, radii.bottom_right, radii.bottom_left]
+        .map(|radius| (radius.x + radius.y) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spread_grows_radii() {
+        let radii = |radius: f64| NonUniformRoundedRectRadii {
+            top_left: Vec2::new(radius, radius),
+            top_right: Vec2::new(radius, 0.0),
+            ..Default::default()
+        };
+
+        let spread = spread_radii(radii(10.0), 5.0);
+        assert_eq!(spread.top_left, Vec2::new(15.0, 15.0));
+        // Sharp corners stay sharp
+        assert_eq!(spread.top_right, Vec2::new(15.0, 0.0));
+        assert_eq!(spread.bottom_left, Vec2::ZERO);
+
+        // Radii smaller than the spread grow by less than it
+        let spread = spread_radii(radii(5.0), 10.0);
+        assert_eq!(spread.top_left, Vec2::new(13.75, 13.75));
+
+        let spread = spread_radii(radii(10.0), -15.0);
+        assert_eq!(spread.top_left, Vec2::ZERO);
+    }
+
+    #[test]
+    fn blurred_corners_keep_their_own_radius() {
+        let radii = NonUniformRoundedRectRadii {
+            top_left: Vec2::new(20.0, 20.0),
+            bottom_right: Vec2::new(4.0, 8.0),
+            ..Default::default()
+        };
+        assert_eq!(corner_radii(radii), [20.0, 0.0, 6.0, 0.0]);
+    }
+}
+
+/// The area the outset shadows of an element whose border box is `border_box` are drawn in, if it
+/// has any, scaled from CSS px by `scale`
+pub(crate) fn outset_shadow_rect(
+    style: &ComputedValues,
+    border_box: Rect,
+    scale: f64,
+) -> Option<Rect> {
+    style
+        .get_effects()
+        .box_shadow
+        .0
+        .iter()
+        .filter(|shadow| !shadow.inset)
+        .map(|shadow| {
+            let blur = shadow.base.blur.px() as f64 * scale;
+            let spread = shadow.spread.px() as f64 * scale;
+            let offset = spread + blur * 2.5;
+            border_box.inflate(offset, offset) + shadow_offset(shadow, scale)
+        })
+        .reduce(|rect, shadow_rect| rect.union(shadow_rect))
+}
+
+fn shadow_offset(shadow: &BoxShadow, scale: f64) -> Vec2 {
+    Vec2 {
+        x: shadow.base.horizontal.px() as f64 * scale,
+        y: shadow.base.vertical.px() as f64 * scale,
+    }
 }
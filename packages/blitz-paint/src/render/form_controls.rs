@@ -1,11 +1,11 @@
 use anyrender::PaintScene;
-use blitz_dom::local_name;
+use blitz_dom::{SystemColor, local_name};
 use kurbo::{Affine, BezPath, Cap, Circle, Join, Point, RoundedRect, Stroke, Vec2};
 use peniko::Fill;
 use style::dom::TElement as _;
 
 use super::ElementCx;
-use crate::color::{Color, ToColorColor as _};
+use crate::color::{Color, ToColorColor as _, system_color};
 
 impl ElementCx<'_> {
     pub(super) fn draw_input(&self, scene: &mut impl PaintScene) {
@@ -45,7 +45,7 @@ impl ElementCx<'_> {
         let accent_color = if disabled {
             Color::from_rgba8(209, 209, 209, 255)
         } else {
-            self.style.clone_color().as_srgb_color()
+            self.style.clone_color().as_output_color(self.context.color_space)
         };
 
         let width = self.frame.border_box.width();
@@ -76,19 +76,20 @@ impl ElementCx<'_> {
         // Use subtle rounded corners for text inputs
         let frame = self.frame.border_box.to_rounded_rect(2.0);
 
-        // Draw input background
+        // Draw input background, using the system colors of the active color scheme
+        let dom = self.context.dom;
         let input_bg_color = if disabled {
-            Color::from_rgba8(245, 245, 245, 255) // Light gray for disabled
+            system_color(dom, SystemColor::ButtonFace)
         } else {
-            Color::WHITE // White background for enabled inputs
+            system_color(dom, SystemColor::Field)
         };
         scene.fill(Fill::NonZero, self.transform, input_bg_color, None, &frame);
 
         // Draw border
         let border_color = if disabled {
-            Color::from_rgba8(200, 200, 200, 255)
+            system_color(dom, SystemColor::GrayText)
         } else {
-            Color::from_rgba8(118, 118, 118, 255)
+            system_color(dom, SystemColor::ButtonBorder)
         };
         scene.stroke(
             &Stroke::new(1.0),
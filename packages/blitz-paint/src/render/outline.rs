@@ -0,0 +1,176 @@
+use anyrender::PaintScene;
+use blitz_dom::SystemColor;
+use kurbo::{Cap, Stroke, Vec2};
+use peniko::Fill;
+use style::properties::ComputedValues;
+use style::values::specified::{BorderStyle, OutlineStyle};
+
+use super::ElementCx;
+use crate::color::{Color, ToColorColor as _, system_color};
+use crate::non_uniform_rounded_rect::{NonUniformRoundedRect, NonUniformRoundedRectRadii};
+
+/// How `outline-style: auto` focus rings are drawn, which is like the platform's own
+struct FocusRing {
+    /// The width of the ring (in CSS px)
+    width: f64,
+    opacity: f32,
+    /// The smallest radius of the ring's outer corners (in CSS px)
+    min_radius: f64,
+}
+
+/// macOS draws soft, rounded focus rings
+#[cfg(target_os = "macos")]
+const FOCUS_RING: FocusRing = FocusRing {
+    width: 3.0,
+    opacity: 0.5,
+    min_radius: 4.0,
+};
+
+/// Other platforms draw solid focus rings which follow the element's corners
+#[cfg(not(target_os = "macos"))]
+const FOCUS_RING: FocusRing = FocusRing {
+    width: 2.0,
+    opacity: 1.0,
+    min_radius: 0.0,
+};
+
+impl ElementCx<'_> {
+    /// Outlines are drawn around the border box, `outline-offset` away from it, and follow its
+    /// rounded corners. They don't take up space in layout.
+    ///
+    /// The following values of outline-style are allowed:
+    /// ✅ auto - Draws a focus ring, like the platform's
+    /// ✅ dotted - Defines a dotted outline
+    /// ✅ dashed - Defines a dashed outline
+    /// ✅ solid - Defines a solid outline
+    /// ✅ double - Defines a double outline
+    /// ❌ groove - Defines a 3D grooved outline (drawn solid)
+    /// ❌ ridge - Defines a 3D ridged outline (drawn solid)
+    /// ❌ inset - Defines a 3D inset outline (drawn solid)
+    /// ❌ outset - Defines a 3D outset outline (drawn solid)
+    /// ✅ none - Defines no outline
+    pub(super) fn draw_outline(&self, scene: &mut impl PaintScene) {
+        let outline = self.style.get_outline();
+        let offset = outline.outline_offset.px() as f64 * self.scale;
+
+        let style = match outline.outline_style {
+            OutlineStyle::Auto => {
+                self.draw_focus_ring(scene, offset);
+                return;
+            }
+            OutlineStyle::BorderStyle(style) => style,
+        };
+        if matches!(style, BorderStyle::None | BorderStyle::Hidden) {
+            return;
+        }
+
+        let width = outline.outline_width.to_f64_px() * self.scale;
+        let color = outline
+            .outline_color
+            .resolve_to_absolute(&self.style.clone_color())
+            .as_output_color(self.context.color_space);
+        if width <= 0.0 || color.components[3] == 0.0 {
+            return;
+        }
+
+        match style {
+            BorderStyle::Dotted | BorderStyle::Dashed => {
+                // Dots and dashes are stroked along the middle of the outline
+                let stroke = match style {
+                    BorderStyle::Dotted => Stroke::new(width)
+                        .with_caps(Cap::Round)
+                        .with_dashes(0.0, [0.0, width * 2.0]),
+                    _ => Stroke::new(width).with_dashes(0.0, [width * 3.0, width * 3.0]),
+                };
+                let middle = self.outline_shape(offset + width / 2.0, 0.0);
+                scene.stroke(&stroke, self.transform, color, None, &middle.path());
+            }
+            BorderStyle::Double => {
+                // Two lines a third of the width each, with a gap between them
+                let third = width / 3.0;
+                self.fill_outline_ring(scene, color, offset, offset + third, 0.0);
+                self.fill_outline_ring(scene, color, offset + 2.0 * third, offset + width, 0.0);
+            }
+            // TODO: Implement the 3D styles
+            _ => self.fill_outline_ring(scene, color, offset, offset + width, 0.0),
+        }
+    }
+
+    /// How far (in local device px) the element's outline reaches outside its border box
+    pub(super) fn outline_extent(&self) -> f64 {
+        outline_extent(&self.style) * self.scale
+    }
+
+    /// Draw the focus ring of `outline-style: auto`, in the outline color if one is given, or
+    /// the platform's accent color
+    fn draw_focus_ring(&self, scene: &mut impl PaintScene, offset: f64) {
+        let outline_color = &self.style.get_outline().outline_color;
+        let color = if outline_color.is_currentcolor() {
+            system_color(self.context.dom, SystemColor::AccentColor)
+        } else {
+            outline_color
+                .resolve_to_absolute(&self.style.clone_color())
+                .as_output_color(self.context.color_space)
+        };
+
+        let width = FOCUS_RING.width * self.scale;
+        let min_radius = FOCUS_RING.min_radius * self.scale;
+        let color = color.multiply_alpha(FOCUS_RING.opacity);
+        self.fill_outline_ring(scene, color, offset, offset + width, min_radius);
+    }
+
+    /// Fill the ring between `inner` and `outer` px outside the border box, with outer corners
+    /// rounded by at least `min_radius`
+    fn fill_outline_ring(
+        &self,
+        scene: &mut impl PaintScene,
+        color: Color,
+        inner: f64,
+        outer: f64,
+        min_radius: f64,
+    ) {
+        let width = outer - inner;
+        let mut path = self.outline_shape(outer, min_radius).path();
+        path.extend(self.outline_shape(inner, min_radius - width).path());
+        scene.fill(Fill::EvenOdd, self.transform, color, None, &path);
+    }
+
+    /// The border box grown by `distance` (or shrunk, down to nothing), with its corners rounded
+    /// by at least `min_radius`
+    fn outline_shape(&self, distance: f64, min_radius: f64) -> NonUniformRoundedRect {
+        let border_box = self.frame.border_box;
+        let distance = distance.max(-border_box.width().min(border_box.height()) / 2.0);
+
+        // Rounded corners are grown along with the box, and sharp ones stay sharp (unless a
+        // minimum radius is given)
+        let grow = |radius: f64| {
+            let radius = if radius > 0.0 {
+                (radius + distance).max(0.0)
+            } else {
+                0.0
+            };
+            radius.max(min_radius)
+        };
+        let grow_corner = |radius: Vec2| Vec2::new(grow(radius.x), grow(radius.y));
+        let radii = self.frame.border_radii;
+        let radii = NonUniformRoundedRectRadii {
+            top_left: grow_corner(radii.top_left),
+            top_right: grow_corner(radii.top_right),
+            bottom_right: grow_corner(radii.bottom_right),
+            bottom_left: grow_corner(radii.bottom_left),
+        };
+        NonUniformRoundedRect::new(border_box.inflate(distance, distance), radii)
+    }
+}
+
+/// How far (in CSS px) an element's outline reaches outside its border box
+pub(crate) fn outline_extent(style: &ComputedValues) -> f64 {
+    let outline = style.get_outline();
+    let width = match outline.outline_style {
+        OutlineStyle::Auto => FOCUS_RING.width,
+        OutlineStyle::BorderStyle(BorderStyle::None | BorderStyle::Hidden) => return 0.0,
+        OutlineStyle::BorderStyle(_) => outline.outline_width.to_f64_px(),
+    };
+    let extent = outline.outline_offset.px() as f64 + width;
+    extent.max(0.0)
+}
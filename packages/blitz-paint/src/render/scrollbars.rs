@@ -0,0 +1,67 @@
+use anyrender::PaintScene;
+use blitz_dom::{BaseDocument, Scrollbar, ScrollbarMode, ScrollbarOwner, SystemColor};
+use kurbo::Affine;
+use peniko::Fill;
+
+use super::{BlitzDomPainter, ElementCx};
+use crate::color::{forced_dark_color, system_color};
+
+impl ElementCx<'_> {
+    /// Draw the element's scrollbars over its content, with `transform` placing its border box
+    /// before it is scrolled
+    pub(super) fn draw_scrollbars(&self, scene: &mut impl PaintScene, transform: Affine) {
+        let dom = self.context.dom;
+        for scrollbar in dom.scrollbars(ScrollbarOwner::Node(self.node.id)) {
+            draw_scrollbar(scene, dom, transform, self.scale, &scrollbar);
+        }
+    }
+}
+
+impl BlitzDomPainter<'_> {
+    /// Draw the viewport's scrollbars over the page
+    pub(super) fn draw_viewport_scrollbars(&self, scene: &mut impl PaintScene) {
+        let dom = self.dom.as_ref();
+        for scrollbar in dom.scrollbars(ScrollbarOwner::Viewport) {
+            draw_scrollbar(scene, dom, Affine::IDENTITY, self.scale, &scrollbar);
+        }
+    }
+}
+
+fn draw_scrollbar(
+    scene: &mut impl PaintScene,
+    dom: &BaseDocument,
+    transform: Affine,
+    scale: f64,
+    scrollbar: &Scrollbar,
+) {
+    let mode = dom.scrollbar_mode();
+    let (thumb_color, track_color) = match scrollbar.style.colors {
+        Some(colors) => (forced_dark_color(colors.thumb), forced_dark_color(colors.track)),
+        // The default colors are shades of the used color scheme's text color
+        None => {
+            let text = system_color(dom, SystemColor::CanvasText);
+            let thumb_alpha = match (scrollbar.pressed, scrollbar.hovered) {
+                (true, _) => 0.6,
+                (false, true) => 0.5,
+                (false, false) => 0.35,
+            };
+            (text.multiply_alpha(thumb_alpha), text.multiply_alpha(0.06))
+        }
+    };
+
+    // Overlay scrollbars only show their track while the mouse is over them
+    let track = scrollbar.track;
+    if mode == ScrollbarMode::Classic || scrollbar.hovered || scrollbar.pressed {
+        let track = track.scale_from_origin(scale);
+        scene.fill(Fill::NonZero, transform, track_color, None, &track);
+    }
+
+    // Thumbs are capsules, inset into their track
+    let Some(thumb) = scrollbar.thumb else {
+        return;
+    };
+    let inset = track.width().min(track.height()) * 0.2;
+    let thumb = thumb.inset(-inset).scale_from_origin(scale);
+    let radius = thumb.width().min(thumb.height()) / 2.0;
+    scene.fill(Fill::NonZero, transform, thumb_color, None, &thumb.to_rounded_rect(radius));
+}
@@ -0,0 +1,166 @@
+use anyrender::PaintScene;
+use blitz_dom::layout::table::CollapsedBorder;
+use blitz_dom::node::SpecialElementData;
+use kurbo::{Cap, Line, Rect, Stroke};
+use peniko::{Color, Fill};
+use style::computed_values::border_collapse::T as BorderCollapse;
+use style::values::specified::BorderStyle;
+use style::values::specified::box_::DisplayInside;
+
+use super::ElementCx;
+use crate::color::ToColorColor;
+
+impl ElementCx<'_> {
+    /// Whether the element is a table, or a cell of a table, which collapses its borders. Their
+    /// borders are drawn together by the table instead.
+    pub(super) fn has_collapsed_borders(&self) -> bool {
+        self.style.get_inherited_table().border_collapse == BorderCollapse::Collapse
+            && (self.node.flags.is_table_root()
+                || self.style.get_box().display.inside() == DisplayInside::TableCell)
+    }
+
+    /// Draw the collapsed borders of a table's cells, centered on the lines between them. Each
+    /// edge is drawn once, by the cell after or below it if there is one.
+    pub(super) fn draw_collapsed_table_borders(&self, scene: &mut impl PaintScene) {
+        let SpecialElementData::TableRoot(table) = &self.element.special_data else {
+            return;
+        };
+        let tree = self.context.dom.as_ref().tree();
+        for cell in table.collapsed_borders() {
+            let layout = &tree[cell.node_id].final_layout;
+            let cell_box = Rect::new(
+                layout.location.x as f64,
+                layout.location.y as f64,
+                (layout.location.x + layout.size.width) as f64,
+                (layout.location.y + layout.size.height) as f64,
+            );
+            let borders = &cell.borders;
+            let half = |border: &CollapsedBorder| border.width as f64 / 2.0;
+            // Horizontal edges fill the corners before them, and the ones after them at the end of
+            // a row, so that each corner is filled once
+            let x0 = cell_box.x0 - half(&borders.left);
+            let x1 = match cell.last_in_row {
+                true => cell_box.x1 + half(&borders.right),
+                false => cell_box.x1 - half(&borders.right),
+            };
+            let (y0, y1) = (cell_box.y0 + half(&borders.top), cell_box.y1 - half(&borders.bottom));
+            let horizontal = |border: &CollapsedBorder, y: f64| {
+                Rect::new(x0, y - half(border), x1, y + half(border))
+            };
+            let vertical = |border: &CollapsedBorder, x: f64| {
+                Rect::new(x - half(border), y0, x + half(border), y1)
+            };
+            let edges = [
+                (&borders.top, horizontal(&borders.top, cell_box.y0), true, true),
+                (&borders.left, vertical(&borders.left, cell_box.x0), false, true),
+                (
+                    &borders.bottom,
+                    horizontal(&borders.bottom, cell_box.y1),
+                    true,
+                    cell.last_in_column,
+                ),
+                (&borders.right, vertical(&borders.right, cell_box.x1), false, cell.last_in_row),
+            ];
+            for (border, edge, is_horizontal, drawn) in edges {
+                if drawn && border.width > 0.0 && edge.width() > 0.0 && edge.height() > 0.0 {
+                    self.draw_collapsed_edge(scene, border, edge, is_horizontal);
+                }
+            }
+        }
+    }
+
+    /// Draw a collapsed border over `edge` (in CSS px), in its style
+    fn draw_collapsed_edge(
+        &self,
+        scene: &mut impl PaintScene,
+        border: &CollapsedBorder,
+        edge: Rect,
+        is_horizontal: bool,
+    ) {
+        let color = border.color.as_output_color(self.context.color_space);
+        let edge = edge.scale_from_origin(self.scale);
+        let width = if is_horizontal { edge.height() } else { edge.width() };
+        match border.style {
+            BorderStyle::Dotted | BorderStyle::Dashed => {
+                // Dots and dashes are stroked along the middle of the edge
+                let stroke = match border.style {
+                    BorderStyle::Dotted => Stroke::new(width)
+                        .with_caps(Cap::Round)
+                        .with_dashes(0.0, [0.0, width * 2.0]),
+                    _ => Stroke::new(width).with_dashes(0.0, [width * 3.0, width * 3.0]),
+                };
+                let center = edge.center();
+                let middle = if is_horizontal {
+                    Line::new((edge.x0, center.y), (edge.x1, center.y))
+                } else {
+                    Line::new((center.x, edge.y0), (center.x, edge.y1))
+                };
+                scene.stroke(&stroke, self.transform, color, None, &middle);
+            }
+            BorderStyle::Double => {
+                // Two lines a third of the width each, with a gap between them
+                let third = width / 3.0;
+                let lines = if is_horizontal {
+                    [
+                        Rect::new(edge.x0, edge.y0, edge.x1, edge.y0 + third),
+                        Rect::new(edge.x0, edge.y1 - third, edge.x1, edge.y1),
+                    ]
+                } else {
+                    [
+                        Rect::new(edge.x0, edge.y0, edge.x0 + third, edge.y1),
+                        Rect::new(edge.x1 - third, edge.y0, edge.x1, edge.y1),
+                    ]
+                };
+                for line in lines {
+                    scene.fill(Fill::NonZero, self.transform, color, None, &line);
+                }
+            }
+            BorderStyle::Groove | BorderStyle::Ridge | BorderStyle::Inset | BorderStyle::Outset => {
+                // The halves of the edge are shaded as if it were carved into the table (`groove`)
+                // or raised from it (`ridge`). In the collapsing border model, `inset` is drawn
+                // like `ridge` and `outset` like `groove`.
+                let (dark, light) = (shade(color, 0.5), shade(color, -0.5));
+                let carved = matches!(border.style, BorderStyle::Groove | BorderStyle::Outset);
+                let [first, second] = if carved { [dark, light] } else { [light, dark] };
+                let center = edge.center();
+                let halves = if is_horizontal {
+                    [
+                        Rect::new(edge.x0, edge.y0, edge.x1, center.y),
+                        Rect::new(edge.x0, center.y, edge.x1, edge.y1),
+                    ]
+                } else {
+                    [
+                        Rect::new(edge.x0, edge.y0, center.x, edge.y1),
+                        Rect::new(center.x, edge.y0, edge.x1, edge.y1),
+                    ]
+                };
+                scene.fill(Fill::NonZero, self.transform, first, None, &halves[0]);
+                scene.fill(Fill::NonZero, self.transform, second, None, &halves[1]);
+            }
+            _ => scene.fill(Fill::NonZero, self.transform, color, None, &edge),
+        }
+    }
+}
+
+/// Darken a color by `amount` (from 0 to 1) towards black, or lighten it towards white if
+/// `amount` is negative
+fn shade(color: Color, amount: f32) -> Color {
+    let [r, g, b, alpha] = color.components;
+    let shade = |c: f32| match amount >= 0.0 {
+        true => c * (1.0 - amount),
+        false => c + (1.0 - c) * -amount,
+    };
+    Color::new([shade(r), shade(g), shade(b), alpha])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shades_towards_black_and_white() {
+        let color = Color::new([0.5, 0.75, 0.0, 0.5]);
+        assert_eq!(shade(color, 0.5).components, [0.25, 0.375, 0.0, 0.5]);
+        assert_eq!(shade(color, -0.5).components, [0.75, 0.875, 0.5, 0.5]);
+    }
+}
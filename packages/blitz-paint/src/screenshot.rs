@@ -5,6 +5,7 @@
 
 use std::sync::Arc;
 
+use blitz_dom::FontFaceSet;
 use tokio::sync::oneshot;
 use wgpu::{
     BufferDescriptor, BufferUsages, CommandEncoderDescriptor, TexelCopyBufferInfo,
@@ -244,15 +245,27 @@ impl ScreenshotEngine {
     }
 
     /// Process all pending screenshot requests
+    ///
+    /// `fonts` are the [`BaseDocument::fonts`] of the document rendered to `texture`. Captures
+    /// are deferred until every one of them has loaded (or failed), so that screenshots never
+    /// show fallback fonts.
+    ///
+    /// [`BaseDocument::fonts`]: blitz_dom::BaseDocument::fonts
     pub async fn process_pending_requests(
         &mut self,
         texture: &wgpu::Texture,
         texture_view: &wgpu::TextureView,
+        fonts: &FontFaceSet,
     ) -> Result<usize, ScreenshotError> {
         if self.is_processing {
             return Ok(0);
         }
 
+        // Leave the requests queued so that they are captured from a later frame
+        if !fonts.is_ready() {
+            return Ok(0);
+        }
+
         self.is_processing = true;
 
         let mut processed_count = 0;
@@ -418,24 +431,33 @@ impl ScreenshotEngine {
         height: u32,
         config: &ScreenshotConfig,
     ) -> ScreenshotResult {
-        let format = config.format.clone();
-        let quality = config.quality;
-
         // Yield to allow other async tasks to run, then encode directly
         tokio::task::yield_now().await;
 
-        match format {
-            ImageFormat::Png => encode_png(rgba_buffer, width, height, quality),
-            #[cfg(feature = "jpeg")]
-            ImageFormat::Jpeg => encode_jpeg(rgba_buffer, width, height, quality),
-            #[cfg(feature = "webp")]
-            ImageFormat::WebP => encode_webp(rgba_buffer, width, height, quality),
-        }
+        encode_rgba(rgba_buffer, width, height, config)
     }
 
 
 }
 
+/// Encode an RGBA8 buffer in the format and quality given by `config`. The region of `config`
+/// is ignored.
+pub fn encode_rgba(
+    rgba_buffer: &[u8],
+    width: u32,
+    height: u32,
+    config: &ScreenshotConfig,
+) -> ScreenshotResult {
+    let quality = config.quality;
+    match config.format {
+        ImageFormat::Png => encode_png(rgba_buffer, width, height, quality),
+        #[cfg(feature = "jpeg")]
+        ImageFormat::Jpeg => encode_jpeg(rgba_buffer, width, height, quality),
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => encode_webp(rgba_buffer, width, height, quality),
+    }
+}
+
 /// Encode RGBA buffer to PNG format
 #[cfg(feature = "png")]
 fn encode_png(buffer: &[u8], width: u32, height: u32, _quality: u8) -> ScreenshotResult {
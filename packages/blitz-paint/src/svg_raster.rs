@@ -0,0 +1,375 @@
+//! SVG images rasterized at the size they're painted at
+//!
+//! SVG images (`<img src="icon.svg">`, SVG backgrounds and inline `<svg>` elements) are rasterized
+//! with anyrender_svg on tiny-skia at their painted size in device pixels, rather than being
+//! encoded into the scene as paths every frame. Rasters are cached by image and size, so an image
+//! is rasterized again only when the size it's painted at changes (on resize, or when the scale
+//! factor does), which keeps it crisp. The cache's memory is managed by the global
+//! [`CacheCoordinator`].
+//!
+//! Images are rasterized on the painting thread the first time they're painted. When an image
+//! which has been rasterized is painted at a new size, it's rasterized again on a background
+//! thread, and painted from its old raster (scaled to the new size) until then, so that resizing
+//! a window doesn't hold up its frames. Shells call [`wake_when_svgs_rasterized`] after painting
+//! to paint again once the new rasters are ready.
+//!
+//! SVG icons of documents are rasterized for shells with [`rasterize_svg_icon`].
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, Weak};
+use std::task::Waker;
+
+use anyrender::ImageRenderer;
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_dom::ICON_SIZE;
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use blitz_traits::shell::WindowIcon;
+use kurbo::Affine;
+
+/// The largest rasters (in pixels): larger images are painted as paths
+const MAX_RASTER_PIXELS: u64 = 4096 * 4096;
+
+/// `svg` rasterized at `width` by `height` pixels, or `None` if it's too large (or empty) to be
+/// rasterized. If it has only been rasterized at other sizes, the raster used most recently is
+/// returned while it's rasterized at this size in the background.
+pub(crate) fn rasterized_svg(
+    svg: &Arc<usvg::Tree>,
+    width: u32,
+    height: u32,
+) -> Option<peniko::Image> {
+    if width == 0 || height == 0 || u64::from(width) * u64::from(height) > MAX_RASTER_PIXELS {
+        return None;
+    }
+    let cache = SvgRasterCache::global();
+    let key = (Arc::as_ptr(svg) as usize, width, height);
+    if let Some(image) = cache.get(key) {
+        return Some(image);
+    }
+    if let Some(image) = cache.get_other_size(key) {
+        RasterQueue::global().push(svg, key);
+        return Some(image);
+    }
+
+    let image = rasterize(svg, width, height);
+    cache.insert(svg, key, image.clone());
+    Some(image)
+}
+
+/// How many rasters have been finished in the background
+pub fn svg_rasters_version() -> u64 {
+    RasterQueue::global().lock().version
+}
+
+/// Wake `waker` once the SVGs being rasterized in the background are ready to be painted, or now
+/// if any were finished since [`svg_rasters_version`] returned `painted_version`. Called after
+/// painting, with the version from before it, so that rasters started while painting are painted
+/// once they're ready.
+pub fn wake_when_svgs_rasterized(painted_version: u64, waker: &Waker) {
+    let mut queue = RasterQueue::global().lock();
+    if queue.version != painted_version {
+        drop(queue);
+        waker.wake_by_ref();
+        return;
+    }
+    let pending = queue.running.is_some() || !queue.jobs.is_empty();
+    if pending && !queue.wakers.iter().any(|queued| queued.will_wake(waker)) {
+        queue.wakers.push(waker.clone());
+    }
+}
+
+/// The SVG icon of a document (see [`blitz_dom::DocumentIcon`]) rasterized to fit within
+/// [`ICON_SIZE`], keeping its aspect ratio, or `None` if it's empty
+pub fn rasterize_svg_icon(svg: &usvg::Tree) -> Option<WindowIcon> {
+    let size = svg.size();
+    let scale = f64::from(ICON_SIZE) / f64::from(size.width().max(size.height()));
+    let width = (f64::from(size.width()) * scale).round() as u32;
+    let height = (f64::from(size.height()) * scale).round() as u32;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let image = rasterize(svg, width, height);
+    Some(WindowIcon {
+        width,
+        height,
+        rgba: Arc::new(image.data.data().to_vec()),
+    })
+}
+
+fn rasterize(svg: &usvg::Tree, width: u32, height: u32) -> peniko::Image {
+    let svg_size = svg.size();
+    let transform = Affine::scale_non_uniform(
+        f64::from(width) / f64::from(svg_size.width()),
+        f64::from(height) / f64::from(svg_size.height()),
+    );
+    let mut renderer = TinySkiaImageRenderer::new(width, height);
+    let mut pixels = Vec::new();
+    let draw = |scene: &mut _| anyrender_svg::render_svg_tree(scene, svg, transform);
+    renderer.render(draw, &mut pixels);
+
+    peniko::Image::new(
+        peniko::Blob::new(Arc::new(pixels)),
+        peniko::ImageFormat::Rgba8,
+        width,
+        height,
+    )
+}
+
+/// The address of a tree, and the size it was rasterized at
+type RasterKey = (usize, u32, u32);
+
+struct CachedRaster {
+    /// The tree the raster is of, which keeps its address from being reused by another tree while
+    /// the raster is cached. Trees which are changed (see `ImageData::svg_data_mut`) are copied
+    /// first if they're shared, so they don't keep their address either
+    svg: Weak<usvg::Tree>,
+    image: peniko::Image,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    rasters: HashMap<RasterKey, CachedRaster>,
+    /// Counts uses, for evicting the least recently used rasters first
+    clock: u64,
+}
+
+#[derive(Default)]
+struct SvgRasterCache {
+    entries: Mutex<CacheEntries>,
+}
+
+impl SvgRasterCache {
+    fn global() -> &'static SvgRasterCache {
+        static GLOBAL: OnceLock<Arc<SvgRasterCache>> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let cache = Arc::new(SvgRasterCache::default());
+            let managed_cache: Arc<dyn ManagedCache> = cache.clone();
+            CacheCoordinator::global().register(&managed_cache, 1);
+            cache
+        })
+    }
+
+    fn get(&self, key: RasterKey) -> Option<peniko::Image> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let cached = entries.rasters.get_mut(&key)?;
+        cached.last_used = clock;
+        Some(cached.image.clone())
+    }
+
+    /// The raster of the tree `key` is of at another size which was used most recently
+    fn get_other_size(&self, key: RasterKey) -> Option<peniko::Image> {
+        let entries = self.lock();
+        let (_, cached) = entries
+            .rasters
+            .iter()
+            .filter(|(other, _)| other.0 == key.0)
+            .max_by_key(|(_, cached)| cached.last_used)?;
+        Some(cached.image.clone())
+    }
+
+    fn insert(&self, svg: &Arc<usvg::Tree>, key: RasterKey, image: peniko::Image) {
+        let mut entries = self.lock();
+        // Rasters of trees which have been dropped can't be painted again
+        entries.rasters.retain(|_, cached| cached.svg.strong_count() > 0);
+        entries.clock += 1;
+        let cached = CachedRaster {
+            svg: Arc::downgrade(svg),
+            image,
+            last_used: entries.clock,
+        };
+        entries.rasters.insert(key, cached);
+        drop(entries);
+        CacheCoordinator::global().enforce_budget();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheEntries> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// A raster to rasterize in the background
+struct RasterJob {
+    svg: Weak<usvg::Tree>,
+    key: RasterKey,
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: VecDeque<RasterJob>,
+    /// The raster being rasterized
+    running: Option<RasterKey>,
+    /// Woken when a raster is finished
+    wakers: Vec<Waker>,
+    /// Counts the rasters finished
+    version: u64,
+    worker_started: bool,
+}
+
+/// The rasters to rasterize on the background thread, which is started when it's first needed
+#[derive(Default)]
+struct RasterQueue {
+    state: Mutex<QueueState>,
+    job_queued: Condvar,
+}
+
+impl RasterQueue {
+    fn global() -> &'static RasterQueue {
+        static GLOBAL: OnceLock<RasterQueue> = OnceLock::new();
+        GLOBAL.get_or_init(RasterQueue::default)
+    }
+
+    /// Rasterize `svg` at the size in `key` in the background, unless it already is being.
+    /// It's rasterized now where threads aren't available.
+    fn push(&'static self, svg: &Arc<usvg::Tree>, key: RasterKey) {
+        let mut state = self.lock();
+        if state.running == Some(key) || state.jobs.iter().any(|job| job.key == key) {
+            return;
+        }
+        if !state.worker_started {
+            let spawned = !cfg!(target_arch = "wasm32")
+                && std::thread::Builder::new()
+                    .name("blitz-svg-raster".to_string())
+                    .spawn(move || self.work())
+                    .is_ok();
+            if !spawned {
+                drop(state);
+                let (_, width, height) = key;
+                SvgRasterCache::global().insert(svg, key, rasterize(svg, width, height));
+                return;
+            }
+            state.worker_started = true;
+        }
+        let job = RasterJob {
+            svg: Arc::downgrade(svg),
+            key,
+        };
+        state.jobs.push_back(job);
+        drop(state);
+        self.job_queued.notify_one();
+    }
+
+    fn work(&self) {
+        let mut state = self.lock();
+        loop {
+            let Some(job) = state.jobs.pop_front() else {
+                state = self.job_queued.wait(state).unwrap_or_else(|err| err.into_inner());
+                continue;
+            };
+            state.running = Some(job.key);
+            drop(state);
+
+            // Trees which have been dropped meanwhile won't be painted again
+            if let Some(svg) = job.svg.upgrade() {
+                let (_, width, height) = job.key;
+                SvgRasterCache::global().insert(&svg, job.key, rasterize(&svg, width, height));
+            }
+
+            state = self.lock();
+            state.running = None;
+            state.version += 1;
+            for waker in state.wakers.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Evicted rasters are rasterized again the next time they're painted
+impl ManagedCache for SvgRasterCache {
+    fn name(&self) -> &str {
+        "rasterized SVGs"
+    }
+
+    fn memory_usage(&self) -> usize {
+        let entries = self.lock();
+        entries.rasters.values().map(|cached| cached.image.data.len()).sum()
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        let mut entries = self.lock();
+        let mut usage: usize = entries.rasters.values().map(|cached| cached.image.data.len()).sum();
+        if usage <= target_bytes {
+            return;
+        }
+
+        let mut rasters: Vec<(RasterKey, u64, usize)> = entries
+            .rasters
+            .iter()
+            .map(|(key, cached)| (*key, cached.last_used, cached.image.data.len()))
+            .collect();
+        rasters.sort_by_key(|&(_, last_used, _)| last_used);
+        for (key, _, len) in rasters {
+            if usage <= target_bytes {
+                break;
+            }
+            entries.rasters.remove(&key);
+            usage -= len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rasters_are_cached_by_size() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10" fill="red"/>
+        </svg>"#;
+        let svg = Arc::new(usvg::Tree::from_str(svg, &usvg::Options::default()).unwrap());
+
+        let image = rasterized_svg(&svg, 4, 2).unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+        assert_eq!(&image.data.data()[..4], &[255, 0, 0, 255]);
+
+        let cached = rasterized_svg(&svg, 4, 2).unwrap();
+        assert_eq!(cached.data.id(), image.data.id());
+
+        // Resized images are painted from their old raster until they're rasterized again
+        let mut version = svg_rasters_version();
+        let stale = rasterized_svg(&svg, 8, 4).unwrap();
+        assert_eq!(stale.data.id(), image.data.id());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let waker = Waker::from(Arc::new(ChannelWaker(Mutex::new(sender))));
+        loop {
+            wake_when_svgs_rasterized(version, &waker);
+            receiver.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+            version = svg_rasters_version();
+            let resized = rasterized_svg(&svg, 8, 4).unwrap();
+            if (resized.width, resized.height) == (8, 4) {
+                break;
+            }
+        }
+
+        assert!(rasterized_svg(&svg, 0, 4).is_none());
+        assert!(rasterized_svg(&svg, 8192, 8192).is_none());
+    }
+
+    #[test]
+    fn icons_are_rasterized_to_fit() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="16">
+            <rect width="32" height="16" fill="#00f"/>
+        </svg>"##;
+        let svg = usvg::Tree::from_str(svg, &usvg::Options::default()).unwrap();
+
+        let icon = rasterize_svg_icon(&svg).unwrap();
+        assert_eq!((icon.width, icon.height), (ICON_SIZE, ICON_SIZE / 2));
+        let center = ((icon.height / 2 * icon.width + icon.width / 2) * 4) as usize;
+        assert_eq!(icon.rgba[center..center + 4], [0, 0, 255, 255]);
+    }
+
+    struct ChannelWaker(Mutex<std::sync::mpsc::Sender<()>>);
+
+    impl std::task::Wake for ChannelWaker {
+        fn wake(self: Arc<Self>) {
+            let _ = self.0.lock().unwrap().send(());
+        }
+    }
+}
@@ -1,14 +1,20 @@
 //! Universal text rendering using blitz-text shaping pipeline
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyrender::PaintScene;
+use blitz_dom::TextSystemSingleton;
 use blitz_dom::node::TextBrush;
+use blitz_text::decoration::{self, DecorationMetrics};
 use blitz_text::{Attrs, Buffer};
+use blitz_traits::render::OutputColorSpace;
 use kurbo::{Affine, Point};
 use log;
 use peniko::Fill;
 use style::properties::ComputedValues;
+use style::values::computed::text::{TextDecorationLength, TextDecorationSkipInk};
 
 use crate::color::ToColorColor;
 
@@ -56,6 +62,7 @@ pub(crate) fn render_text_buffer(
     pos: Point,
     computed_styles: Option<&ComputedValues>,
     default_brush: &TextBrush,
+    color_space: OutputColorSpace,
 ) {
     println!("🎯 BLITZ-PAINT render_text_buffer called at pos: ({}, {}), scale: {}", pos.x, pos.y, scale);
     #[cfg(feature = "tracing")]
@@ -78,6 +85,7 @@ pub(crate) fn render_text_buffer(
         computed_styles,
         default_brush,
         transform,
+        color_space,
     );
 }
 
@@ -90,11 +98,12 @@ fn render_buffer_with_enhanced_styling(
     computed_styles: Option<&ComputedValues>,
     default_brush: &TextBrush,
     transform: Affine,
+    color_space: OutputColorSpace,
 ) {
     // Extract enhanced text properties with zero allocation
-    let text_color = extract_enhanced_color(computed_styles, default_brush);
-    let text_decoration = extract_text_decoration(computed_styles);
-    let text_shadow = extract_text_shadow(computed_styles);
+    let text_color = extract_enhanced_color(computed_styles, default_brush, color_space);
+    let text_decoration = extract_text_decoration(computed_styles, color_space);
+    let text_shadow = extract_text_shadow(computed_styles, color_space);
 
     // Apply all text shadows if present (render shadows first, then text)
     // Render in reverse order for proper layering (last shadow rendered first)
@@ -110,7 +119,6 @@ fn render_buffer_with_enhanced_styling(
         render_text_decoration(
             scene,
             buffer,
-            pos,
             decoration_params,
             transform,
             scale,
@@ -123,10 +131,11 @@ fn render_buffer_with_enhanced_styling(
 fn extract_enhanced_color(
     computed_styles: Option<&ComputedValues>,
     default_brush: &TextBrush,
+    color_space: OutputColorSpace,
 ) -> peniko::Color {
     if let Some(styles) = computed_styles {
         let text_styles = styles.get_inherited_text();
-        let mut color = text_styles.color.as_srgb_color();
+        let mut color = text_styles.color.as_output_color(color_space);
 
         // Enhanced color correction for better readability
         // Handle transparent text (common CSS issue)
@@ -157,13 +166,8 @@ fn extract_enhanced_color(
             peniko::Brush::Gradient(gradient) => {
                 // For gradients, use the first color stop
                 if let Some(stop) = gradient.stops.first() {
-                    // Convert DynamicColor to peniko::Color using components array
-                    peniko::Color::from_rgba8(
-                        (stop.color.components[0] * 255.0) as u8,
-                        (stop.color.components[1] * 255.0) as u8,
-                        (stop.color.components[2] * 255.0) as u8,
-                        (stop.color.components[3] * 255.0) as u8,
-                    )
+                    // Stops keep the color space they were specified in
+                    stop.color.to_alpha_color::<peniko::color::Srgb>()
                 } else {
                     peniko::Color::BLACK
                 }
@@ -176,6 +180,7 @@ fn extract_enhanced_color(
 /// Extract text decoration properties from computed styles
 fn extract_text_decoration(
     computed_styles: Option<&ComputedValues>,
+    color_space: OutputColorSpace,
 ) -> Option<TextDecorationParams> {
     let styles = computed_styles?;
     let text_styles = styles.get_text();
@@ -204,7 +209,7 @@ fn extract_text_decoration(
     let decoration_color = text_styles
         .text_decoration_color
         .resolve_to_absolute(&current_color)
-        .as_srgb_color();
+        .as_output_color(color_space);
 
     // Extract text decoration style from stylo using proper computed value path
     let decoration_style = {
@@ -220,22 +225,29 @@ fn extract_text_decoration(
         }
     };
 
-    // Extract text decoration thickness from stylo (Gecko engine supports this)
-    let font = styles.get_font();
-    let font_size = font.font_size.computed_size.px();
-    // Fallback calculation for text decoration thickness
-    let thickness = (font_size / 14.0).max(1.0);
+    // Percentages are of the font size, and `auto` and `from-font` use the font's own metrics
+    let font_size = styles.get_font().font_size.computed_size();
+    let decoration_length = |length: &TextDecorationLength| match length {
+        TextDecorationLength::LengthPercentage(length) => Some(length.resolve(font_size).px()),
+        TextDecorationLength::Auto | TextDecorationLength::FromFont => None,
+    };
+    let inherited_text = styles.get_inherited_text();
 
     Some(TextDecorationParams {
         line: line_types,
         color: decoration_color,
         style: decoration_style,
-        thickness,
+        thickness: decoration_length(&text_styles.text_decoration_thickness),
+        underline_offset: decoration_length(&inherited_text.text_underline_offset),
+        skip_ink: inherited_text.text_decoration_skip_ink != TextDecorationSkipInk::None,
     })
 }
 
 /// Extract text shadow properties from computed styles - supports multiple shadows
-fn extract_text_shadow(computed_styles: Option<&ComputedValues>) -> Vec<TextShadowParams> {
+fn extract_text_shadow(
+    computed_styles: Option<&ComputedValues>,
+    color_space: OutputColorSpace,
+) -> Vec<TextShadowParams> {
     let styles = match computed_styles {
         Some(s) => s,
         None => return Vec::new(),
@@ -262,7 +274,7 @@ fn extract_text_shadow(computed_styles: Option<&ComputedValues>) -> Vec<TextShad
             let shadow_color = shadow
                 .color
                 .resolve_to_absolute(&current_color)
-                .as_srgb_color();
+                .as_output_color(color_space);
 
             TextShadowParams {
                 offset_x,
@@ -359,461 +371,245 @@ fn calculate_text_bounds(buffer: &Buffer, pos: Point, scale: f64) -> kurbo::Rect
 }
 
 /// Render text decorations (underline, overline, line-through)
+///
+/// Lines are positioned using the decoration metrics of the first font in each line of text and
+/// span from the start of its first glyph to the end of its last. With skip-ink, underlines and
+/// overlines are interrupted wherever they would cross a glyph's outline.
 fn render_text_decoration(
     scene: &mut impl PaintScene,
     buffer: &Buffer,
-    pos: Point,
     decoration: TextDecorationParams,
     transform: Affine,
     scale: f64,
     text_color: peniko::Color,
 ) {
     // Get decoration color (use text color if not specified)
-    let decoration_color = if decoration.color.components[3] > 0.0 {
+    let color = if decoration.color.components[3] > 0.0 {
         decoration.color
     } else {
         text_color
     };
+    // Never draw lines thinner than a device pixel
+    let min_thickness = (1.0 / scale) as f32;
 
-    // Calculate line thickness (default to 1px if not specified)
-    let line_thickness = decoration.thickness as f64 * scale;
+    for run in buffer.layout_runs() {
+        let Some(first_glyph) = run.glyphs.first() else {
+            continue;
+        };
+        let start = run.glyphs.iter().map(|g| g.x).fold(f32::INFINITY, f32::min);
+        let end = run
+            .glyphs
+            .iter()
+            .map(|g| g.x + g.w)
+            .fold(f32::NEG_INFINITY, f32::max);
+        if end <= start {
+            continue;
+        }
 
-    // Render each type of decoration with style-specific rendering
-    for line_type in decoration.line.iter() {
-        match decoration.style {
-            TextDecorationStyleType::Solid => {
-                // Use original solid line rendering functions
-                match line_type {
-                    TextDecorationLineType::Underline => {
-                        render_underline(
-                            scene,
-                            buffer,
-                            pos,
-                            decoration_color,
-                            line_thickness,
-                            transform,
-                            scale,
-                        );
-                    }
-                    TextDecorationLineType::Overline => {
-                        render_overline(
-                            scene,
-                            buffer,
-                            pos,
-                            decoration_color,
-                            line_thickness,
-                            transform,
-                            scale,
-                        );
-                    }
-                    TextDecorationLineType::LineThrough => {
-                        render_line_through(
-                            scene,
-                            buffer,
-                            pos,
-                            decoration_color,
-                            line_thickness,
-                            transform,
-                            scale,
-                        );
-                    }
+        let metrics = with_decoration_font(first_glyph.font_id, |data, face_index| {
+            DecorationMetrics::from_font(data, face_index, first_glyph.font_size)
+        })
+        .unwrap_or_else(|| DecorationMetrics::fallback(first_glyph.font_size));
+
+        for line_type in decoration.line.iter() {
+            let (offset, font_thickness) = match line_type {
+                TextDecorationLineType::Underline => (
+                    decoration
+                        .underline_offset
+                        .unwrap_or(metrics.underline_offset),
+                    metrics.underline_thickness,
+                ),
+                TextDecorationLineType::Overline => {
+                    (metrics.overline_offset, metrics.underline_thickness)
                 }
-            }
-            TextDecorationStyleType::Dotted => {
-                render_dotted_decoration(
-                    scene,
-                    buffer,
-                    pos,
-                    decoration_color,
-                    line_thickness,
-                    transform,
-                    scale,
-                    line_type,
-                );
-            }
-            TextDecorationStyleType::Dashed => {
-                render_dashed_decoration(
-                    scene,
-                    buffer,
-                    pos,
-                    decoration_color,
-                    line_thickness,
-                    transform,
-                    scale,
-                    line_type,
-                );
-            }
-            TextDecorationStyleType::Wavy => {
-                render_wavy_decoration(
-                    scene,
-                    buffer,
-                    pos,
-                    decoration_color,
-                    line_thickness,
-                    transform,
-                    scale,
-                    line_type,
-                );
-            }
-            TextDecorationStyleType::Double => {
-                render_double_decoration(
+                TextDecorationLineType::LineThrough => {
+                    (metrics.strikeout_offset, metrics.strikeout_thickness)
+                }
+            };
+            let thickness = decoration
+                .thickness
+                .unwrap_or(font_thickness)
+                .max(min_thickness);
+            let top = run.line_y + offset;
+
+            // Skip-ink doesn't apply to line-throughs, which are meant to cross the text
+            let pieces = match line_type {
+                TextDecorationLineType::LineThrough => vec![(start, end)],
+                _ if !decoration.skip_ink => vec![(start, end)],
+                _ => skip_ink(&run, start, end, top, thickness),
+            };
+
+            let line = DecorationLine {
+                top,
+                thickness,
+                // The second line of a double decoration is drawn away from the text
+                away: match line_type {
+                    TextDecorationLineType::Overline => -1.0,
+                    _ => 1.0,
+                },
+            };
+            for (piece_start, piece_end) in pieces {
+                line.render(
                     scene,
-                    buffer,
-                    pos,
-                    decoration_color,
-                    line_thickness,
+                    &decoration.style,
+                    piece_start,
+                    piece_end,
+                    color,
                     transform,
                     scale,
-                    line_type,
                 );
             }
         }
     }
 }
 
-/// Render underline decoration
-fn render_underline(
-    scene: &mut impl PaintScene,
-    buffer: &Buffer,
-    pos: Point,
-    color: peniko::Color,
-    thickness: f64,
-    transform: Affine,
-    scale: f64,
-) {
-    for run in buffer.layout_runs() {
-        let line_height = run.line_height;
-        let line_y = run.line_y;
-
-        // Calculate underline position (below baseline)
-        let underline_y = pos.y * scale + (line_y + line_height * 0.9) as f64 * scale;
-
-        // Calculate line width from run glyphs
-        let line_width = run.glyphs.iter().map(|g| g.w as f64).sum::<f64>() * scale;
-
-        if line_width > 0.0 {
-            let start_x = pos.x * scale;
-            let underline_rect = kurbo::Rect::new(
-                start_x,
-                underline_y,
-                start_x + line_width,
-                underline_y + thickness,
-            );
-
-            scene.fill(Fill::NonZero, transform, color, None, &underline_rect);
+/// Split the span `start..end` of a decoration line into the pieces which don't cross the ink of
+/// any glyph in `run`
+fn skip_ink(
+    run: &blitz_text::LayoutRun,
+    start: f32,
+    end: f32,
+    top: f32,
+    thickness: f32,
+) -> Vec<(f32, f32)> {
+    // The gap left either side of the ink, which also widens the band that is checked for ink
+    let gap = thickness.max(1.0);
+    let mut gaps = Vec::new();
+    for glyph in run.glyphs.iter() {
+        // The band relative to the glyph's own baseline
+        let baseline = run.line_y + glyph.y;
+        let band_top = top - gap - baseline;
+        let band_bottom = top + thickness + gap - baseline;
+        let extent = with_decoration_font(glyph.font_id, |data, face_index| {
+            decoration::glyph_ink_extent(
+                data,
+                face_index,
+                glyph.glyph_id,
+                glyph.font_size,
+                band_top,
+                band_bottom,
+            )
+        });
+        if let Some((ink_start, ink_end)) = extent {
+            gaps.push((glyph.x + ink_start - gap, glyph.x + ink_end + gap));
         }
     }
+    decoration::subtract_gaps(start, end, &mut gaps, thickness)
 }
 
-/// Render overline decoration
-fn render_overline(
-    scene: &mut impl PaintScene,
-    buffer: &Buffer,
-    pos: Point,
-    color: peniko::Color,
-    thickness: f64,
-    transform: Affine,
-    scale: f64,
-) {
-    for run in buffer.layout_runs() {
-        let line_top = run.line_top;
-
-        // Calculate overline position (above text)
-        let overline_y = pos.y * scale + (line_top - thickness as f32 * 0.5) as f64 * scale;
-
-        // Calculate line width from run glyphs
-        let line_width = run.glyphs.iter().map(|g| g.w as f64).sum::<f64>() * scale;
-
-        if line_width > 0.0 {
-            let start_x = pos.x * scale;
-            let overline_rect = kurbo::Rect::new(
-                start_x,
-                overline_y,
-                start_x + line_width,
-                overline_y + thickness,
-            );
-
-            scene.fill(Fill::NonZero, transform, color, None, &overline_rect);
-        }
+/// Run `f` with the data of the font `font_id` in the document's font system, which the text was
+/// shaped with. The data is copied out on first use and cached per thread, as the font system is.
+fn with_decoration_font<R>(
+    font_id: blitz_text::fontdb::ID,
+    f: impl FnOnce(&[u8], u32) -> Option<R>,
+) -> Option<R> {
+    thread_local! {
+        static DECORATION_FONTS: RefCell<HashMap<blitz_text::fontdb::ID, (Arc<[u8]>, u32)>> =
+            RefCell::new(HashMap::new());
     }
-}
 
-/// Render line-through decoration
-fn render_line_through(
-    scene: &mut impl PaintScene,
-    buffer: &Buffer,
-    pos: Point,
-    color: peniko::Color,
-    thickness: f64,
-    transform: Affine,
-    scale: f64,
-) {
-    for run in buffer.layout_runs() {
-        let line_height = run.line_height;
-        let line_y = run.line_y;
-
-        // Calculate line-through position (middle of text)
-        let line_through_y = pos.y * scale + (line_y + line_height * 0.5) as f64 * scale;
-
-        // Calculate line width from run glyphs
-        let line_width = run.glyphs.iter().map(|g| g.w as f64).sum::<f64>() * scale;
-
-        if line_width > 0.0 {
-            let start_x = pos.x * scale;
-            let line_through_rect = kurbo::Rect::new(
-                start_x,
-                line_through_y,
-                start_x + line_width,
-                line_through_y + thickness,
-            );
-
-            scene.fill(Fill::NonZero, transform, color, None, &line_through_rect);
+    let cached = DECORATION_FONTS.with_borrow(|fonts| fonts.get(&font_id).cloned());
+    let (data, face_index) = match cached {
+        Some(font) => font,
+        None => {
+            let font = TextSystemSingleton::with_font_system(|font_system| {
+                font_system.db().with_face_data(font_id, |data, face_index| {
+                    (Arc::<[u8]>::from(data), face_index)
+                })
+            })
+            .ok()
+            .flatten()?;
+            DECORATION_FONTS.with_borrow_mut(|fonts| fonts.insert(font_id, font.clone()));
+            font
         }
-    }
+    };
+    f(&data, face_index)
 }
 
-/// Render dotted decoration style with consistent spacing
-/// Zero allocation implementation using iterator chains for dot generation
-#[inline]
-fn render_dotted_decoration(
-    scene: &mut impl PaintScene,
-    buffer: &Buffer,
-    pos: Point,
-    color: peniko::Color,
-    thickness: f64,
-    transform: Affine,
-    scale: f64,
-    line_type: &TextDecorationLineType,
-) {
-    for run in buffer.layout_runs() {
-        let line_width = run.glyphs.iter().map(|g| g.w as f64).sum::<f64>() * scale;
-        if line_width <= 0.0 {
-            continue;
-        }
-
-        let start_x = pos.x * scale;
-        let dot_radius = (thickness * 0.5).max(0.5);
-        let dot_spacing = thickness * 2.0; // CSS standard: 2x thickness spacing
-        let dot_count = (line_width / dot_spacing).floor() as usize;
-
-        // Calculate line position based on decoration type
-        let line_y = match line_type {
-            TextDecorationLineType::Underline => {
-                pos.y * scale + (run.line_y + run.line_height * 0.9) as f64 * scale
-            }
-            TextDecorationLineType::Overline => {
-                pos.y * scale + (run.line_top - thickness as f32 * 0.5) as f64 * scale
-            }
-            TextDecorationLineType::LineThrough => {
-                pos.y * scale + (run.line_y + run.line_height * 0.5) as f64 * scale
-            }
-        };
-
-        // Render dots with consistent spacing
-        for i in 0..dot_count {
-            let dot_x = start_x + (i as f64 * dot_spacing) + dot_radius;
-            let dot_center = kurbo::Point::new(dot_x, line_y + dot_radius);
-            let dot_circle = kurbo::Circle::new(dot_center, dot_radius);
-
-            scene.fill(Fill::NonZero, transform, color, None, &dot_circle);
-        }
-    }
+/// The geometry of one decoration line (in CSS pixels, relative to the text buffer)
+struct DecorationLine {
+    top: f32,
+    thickness: f32,
+    /// The direction (1.0 for down, -1.0 for up) away from the text
+    away: f64,
 }
 
-/// Render dashed decoration style with CSS-compliant patterns
-/// Zero allocation implementation with proper dash-gap ratios
-#[inline]
-fn render_dashed_decoration(
-    scene: &mut impl PaintScene,
-    buffer: &Buffer,
-    pos: Point,
-    color: peniko::Color,
-    thickness: f64,
-    transform: Affine,
-    scale: f64,
-    line_type: &TextDecorationLineType,
-) {
-    for run in buffer.layout_runs() {
-        let line_width = run.glyphs.iter().map(|g| g.w as f64).sum::<f64>() * scale;
-        if line_width <= 0.0 {
-            continue;
-        }
-
-        let start_x = pos.x * scale;
-        let dash_length = thickness * 3.0; // CSS standard: 3x thickness
-        let gap_length = thickness * 2.0; // CSS standard: 2x thickness
-        let pattern_length = dash_length + gap_length;
-        let pattern_count = (line_width / pattern_length).floor() as usize;
-
-        // Calculate line position based on decoration type
-        let line_y = match line_type {
-            TextDecorationLineType::Underline => {
-                pos.y * scale + (run.line_y + run.line_height * 0.9) as f64 * scale
-            }
-            TextDecorationLineType::Overline => {
-                pos.y * scale + (run.line_top - thickness as f32 * 0.5) as f64 * scale
-            }
-            TextDecorationLineType::LineThrough => {
-                pos.y * scale + (run.line_y + run.line_height * 0.5) as f64 * scale
+impl DecorationLine {
+    /// Draw the part of the line between `start` and `end` in the given style
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        &self,
+        scene: &mut impl PaintScene,
+        style: &TextDecorationStyleType,
+        start: f32,
+        end: f32,
+        color: peniko::Color,
+        transform: Affine,
+        scale: f64,
+    ) {
+        let start = start as f64 * scale;
+        let end = end as f64 * scale;
+        let top = self.top as f64 * scale;
+        let thickness = self.thickness as f64 * scale;
+        let center = top + thickness / 2.0;
+
+        match style {
+            TextDecorationStyleType::Solid => {
+                let rect = kurbo::Rect::new(start, top, end, top + thickness);
+                scene.fill(Fill::NonZero, transform, color, None, &rect);
             }
-        };
-
-        // Render dashes with proper spacing
-        for i in 0..pattern_count {
-            let dash_start_x = start_x + (i as f64 * pattern_length);
-            let dash_rect = kurbo::Rect::new(
-                dash_start_x,
-                line_y,
-                dash_start_x + dash_length,
-                line_y + thickness,
-            );
-
-            scene.fill(Fill::NonZero, transform, color, None, &dash_rect);
-        }
-
-        // Handle remainder dash if line doesn't end on pattern boundary
-        let remainder_start = start_x + (pattern_count as f64 * pattern_length);
-        let remainder_width = line_width - (pattern_count as f64 * pattern_length);
-        if remainder_width > gap_length {
-            let final_dash_width = (remainder_width - gap_length).min(dash_length);
-            let final_dash_rect = kurbo::Rect::new(
-                remainder_start,
-                line_y,
-                remainder_start + final_dash_width,
-                line_y + thickness,
-            );
-            scene.fill(Fill::NonZero, transform, color, None, &final_dash_rect);
-        }
-    }
-}
-
-/// Render wavy decoration style with smooth sine wave patterns
-/// Zero allocation implementation using kurbo::BezierPath for smooth curves
-#[inline]
-fn render_wavy_decoration(
-    scene: &mut impl PaintScene,
-    buffer: &Buffer,
-    pos: Point,
-    color: peniko::Color,
-    thickness: f64,
-    transform: Affine,
-    scale: f64,
-    line_type: &TextDecorationLineType,
-) {
-    for run in buffer.layout_runs() {
-        let line_width = run.glyphs.iter().map(|g| g.w as f64).sum::<f64>() * scale;
-        if line_width <= 0.0 {
-            continue;
-        }
-
-        let start_x = pos.x * scale;
-        let wave_amplitude = thickness * 0.75; // Wave height proportional to thickness
-        let wave_frequency = thickness * 4.0; // Wave period: 4x thickness
-
-        // Calculate line position based on decoration type
-        let line_y = match line_type {
-            TextDecorationLineType::Underline => {
-                pos.y * scale + (run.line_y + run.line_height * 0.9) as f64 * scale
+            TextDecorationStyleType::Double => {
+                // Two lines of the full thickness, separated by the thickness
+                let second_top = top + self.away * thickness * 2.0;
+                for line_top in [top, second_top] {
+                    let rect = kurbo::Rect::new(start, line_top, end, line_top + thickness);
+                    scene.fill(Fill::NonZero, transform, color, None, &rect);
+                }
             }
-            TextDecorationLineType::Overline => {
-                pos.y * scale + (run.line_top - thickness as f32 * 0.5) as f64 * scale
+            TextDecorationStyleType::Dotted => {
+                // Round dots with a dot-sized gap between them
+                let radius = thickness / 2.0;
+                let mut x = start + radius;
+                while x + radius <= end + f64::EPSILON {
+                    let dot = kurbo::Circle::new((x, center), radius);
+                    scene.fill(Fill::NonZero, transform, color, None, &dot);
+                    x += thickness * 2.0;
+                }
             }
-            TextDecorationLineType::LineThrough => {
-                pos.y * scale + (run.line_y + run.line_height * 0.5) as f64 * scale
+            TextDecorationStyleType::Dashed => {
+                // Dashes three times as long as they are thick, with gaps twice the thickness
+                let dash = thickness * 3.0;
+                let mut x = start;
+                while x < end {
+                    let rect = kurbo::Rect::new(x, top, (x + dash).min(end), top + thickness);
+                    scene.fill(Fill::NonZero, transform, color, None, &rect);
+                    x += dash + thickness * 2.0;
+                }
             }
-        };
-
-        // Create smooth wave using Bezier curves
-        let mut path = kurbo::BezPath::new();
-        let wave_start = kurbo::Point::new(start_x, line_y);
-        path.move_to(wave_start);
-
-        // Generate wave segments using cubic Bezier curves
-        let segment_count = (line_width / wave_frequency).ceil() as usize;
-        for i in 0..segment_count {
-            let segment_start_x = start_x + (i as f64 * wave_frequency);
-            let segment_end_x =
-                (start_x + ((i + 1) as f64 * wave_frequency)).min(start_x + line_width);
-
-            if segment_end_x <= segment_start_x {
-                break;
+            TextDecorationStyleType::Wavy => {
+                // A wave made of alternating half-periods, centred on the line
+                let amplitude = thickness * 1.5;
+                let half_period = (thickness * 3.0).max(2.0 * scale);
+                let mut path = kurbo::BezPath::new();
+                path.move_to((start, center));
+                let mut x = start;
+                let mut direction = self.away;
+                while x < end {
+                    let next = (x + half_period).min(end);
+                    let peak = center + direction * amplitude * 4.0 / 3.0;
+                    let width = next - x;
+                    path.curve_to(
+                        (x + width / 3.0, peak),
+                        (next - width / 3.0, peak),
+                        (next, center),
+                    );
+                    x = next;
+                    direction = -direction;
+                }
+                let stroke = kurbo::Stroke::new(thickness);
+                scene.stroke(&stroke, transform, color, None, &path);
             }
-
-            let segment_width = segment_end_x - segment_start_x;
-            let _mid_x = segment_start_x + segment_width * 0.5;
-
-            // Control points for smooth sine wave approximation
-            let cp1_x = segment_start_x + segment_width * 0.25;
-            let cp2_x = segment_start_x + segment_width * 0.75;
-            let cp1_y = line_y + wave_amplitude * if i % 2 == 0 { 1.0 } else { -1.0 };
-            let cp2_y = line_y + wave_amplitude * if i % 2 == 0 { 1.0 } else { -1.0 };
-
-            path.curve_to(
-                kurbo::Point::new(cp1_x, cp1_y),
-                kurbo::Point::new(cp2_x, cp2_y),
-                kurbo::Point::new(segment_end_x, line_y),
-            );
         }
-
-        // Stroke the wave path with specified thickness
-        let stroke = kurbo::Stroke::new(thickness);
-        scene.stroke(&stroke, transform, color, None, &path);
-    }
-}
-
-/// Render double decoration style with parallel lines
-/// Zero allocation implementation with proper line spacing
-#[inline]
-fn render_double_decoration(
-    scene: &mut impl PaintScene,
-    buffer: &Buffer,
-    pos: Point,
-    color: peniko::Color,
-    thickness: f64,
-    transform: Affine,
-    scale: f64,
-    line_type: &TextDecorationLineType,
-) {
-    for run in buffer.layout_runs() {
-        let line_width = run.glyphs.iter().map(|g| g.w as f64).sum::<f64>() * scale;
-        if line_width <= 0.0 {
-            continue;
-        }
-
-        let start_x = pos.x * scale;
-        let line_thickness = (thickness * 0.4).max(0.5); // Each line is thinner
-        let line_separation = thickness * 0.6; // Space between lines
-
-        // Calculate base line position based on decoration type
-        let base_line_y = match line_type {
-            TextDecorationLineType::Underline => {
-                pos.y * scale + (run.line_y + run.line_height * 0.9) as f64 * scale
-            }
-            TextDecorationLineType::Overline => {
-                pos.y * scale + (run.line_top - thickness as f32 * 0.5) as f64 * scale
-            }
-            TextDecorationLineType::LineThrough => {
-                pos.y * scale + (run.line_y + run.line_height * 0.5) as f64 * scale
-            }
-        };
-
-        // Render first line
-        let first_line_rect = kurbo::Rect::new(
-            start_x,
-            base_line_y - line_separation * 0.5,
-            start_x + line_width,
-            base_line_y - line_separation * 0.5 + line_thickness,
-        );
-        scene.fill(Fill::NonZero, transform, color, None, &first_line_rect);
-
-        // Render second line
-        let second_line_rect = kurbo::Rect::new(
-            start_x,
-            base_line_y + line_separation * 0.5,
-            start_x + line_width,
-            base_line_y + line_separation * 0.5 + line_thickness,
-        );
-        scene.fill(Fill::NonZero, transform, color, None, &second_line_rect);
     }
 }
 
@@ -823,7 +619,13 @@ struct TextDecorationParams {
     line: Vec<TextDecorationLineType>,
     color: peniko::Color,
     style: TextDecorationStyleType,
-    thickness: f32,
+    /// The thickness of the lines in CSS pixels. `None` uses the thickness the font specifies.
+    thickness: Option<f32>,
+    /// The offset of the underline below the baseline in CSS pixels. `None` uses the offset the
+    /// font specifies.
+    underline_offset: Option<f32>,
+    /// Whether underlines and overlines are interrupted where they cross glyphs
+    skip_ink: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -885,3 +687,45 @@ pub(crate) fn text_shaper_stats() -> Option<blitz_text::shaper::ShaperStats> {
     ensure_text_shaper_initialized();
     TEXT_SHAPER.with(|shaper| shaper.borrow().as_ref().map(|s| s.stats()))
 }
+
+#[cfg(test)]
+mod tests {
+    use blitz_dom::{Attribute, BaseDocument, DocumentConfig, QualName, QuirksMode, local_name, ns};
+
+    use super::*;
+
+    /// The decoration of a paragraph with inline `style`
+    fn decoration(style: &str) -> TextDecorationParams {
+        let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+        let mut mutr = doc.mutate();
+        let attrs = vec![Attribute {
+            name: QualName::new(None, ns!(), local_name!("style")),
+            value: style.to_string(),
+        }];
+        let name = QualName::new(None, ns!(html), local_name!("p"));
+        let p = mutr.create_element(name, attrs, QuirksMode::NoQuirks);
+        mutr.append_children(0, &[p]);
+        drop(mutr);
+        doc.resolve();
+
+        let styles = doc.get_node(p).unwrap().primary_styles().unwrap();
+        extract_text_decoration(Some(&styles), OutputColorSpace::Srgb).unwrap()
+    }
+
+    #[test]
+    fn decorations_use_their_thickness_offset_and_skip_ink() {
+        let auto = decoration("text-decoration: underline; font-size: 20px");
+        assert_eq!((auto.thickness, auto.underline_offset, auto.skip_ink), (None, None, true));
+
+        let styled = decoration(
+            "text-decoration: underline 3px; text-underline-offset: 25%; font-size: 20px;
+             text-decoration-skip-ink: none",
+        );
+        assert_eq!(styled.thickness, Some(3.0));
+        assert_eq!(styled.underline_offset, Some(5.0));
+        assert!(!styled.skip_ink);
+
+        let from_font = decoration("text-decoration: underline from-font");
+        assert_eq!(from_font.thickness, None);
+    }
+}
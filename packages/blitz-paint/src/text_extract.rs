@@ -0,0 +1,342 @@
+//! Plain text extraction from a painted document
+//!
+//! [`TextExtractionScene`] is a [`PaintScene`] which ignores everything except text. Each line of
+//! each text buffer is recorded as a [`TextRun`] together with its on-screen bounds, and the runs
+//! can then be turned into plain text with [`TextExtractionScene::to_text`]:
+//!
+//!  - [`TextLayoutMode::ReadingOrder`] groups runs into lines (top to bottom, then left to right)
+//!    and separates paragraphs with blank lines. This is what you want for indexing or asserting
+//!    on the content of a page in tests.
+//!  - [`TextLayoutMode::Preserve`] places every run on a fixed grid of character cells, which
+//!    approximates the visual layout of the page for line printers and braille displays.
+//!
+//! Whitespace is collapsed the same way as for accessible names (see
+//! [`blitz_dom::util::push_collapsed_text`]), so the extracted text matches what assistive
+//! technology is given.
+
+use std::collections::HashMap;
+
+use anyrender::{Paint, PaintScene};
+use blitz_dom::BaseDocument;
+use blitz_dom::util::push_collapsed_text;
+use kurbo::{Affine, Point, Rect, Shape, Stroke};
+use peniko::{BlendMode, BrushRef, Color, Fill};
+
+use crate::paint_scene;
+
+/// How [`TextExtractionScene::to_text`] lays out the extracted text
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextLayoutMode {
+    /// One line of output per visual line, with a blank line between paragraphs
+    #[default]
+    ReadingOrder,
+    /// Place text on a grid of fixed size character cells (sizes are in device pixels)
+    Preserve { cell_width: f64, cell_height: f64 },
+}
+
+impl TextLayoutMode {
+    /// Preserve the layout using a typical 8x16px terminal cell
+    pub fn preserve() -> Self {
+        Self::Preserve {
+            cell_width: 8.0,
+            cell_height: 16.0,
+        }
+    }
+}
+
+/// A single line of text from a text buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    /// The text of the line, with whitespace collapsed
+    pub text: String,
+    /// The bounds of the line box (in device pixels)
+    pub bounds: Rect,
+}
+
+/// A [`PaintScene`] which records text runs and discards all other drawing commands
+#[derive(Debug, Default)]
+pub struct TextExtractionScene {
+    runs: Vec<TextRun>,
+    /// Index into `runs` for each (buffer, line) pair. Text shadows paint the same buffer more
+    /// than once, and only the last (topmost) copy should be kept.
+    seen: HashMap<(usize, usize), usize>,
+}
+
+impl TextExtractionScene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The recorded runs, in paint order
+    pub fn runs(&self) -> &[TextRun] {
+        &self.runs
+    }
+
+    pub fn into_runs(self) -> Vec<TextRun> {
+        self.runs
+    }
+
+    /// Convert the recorded runs into plain text
+    pub fn to_text(&self, mode: TextLayoutMode) -> String {
+        match mode {
+            TextLayoutMode::ReadingOrder => reading_order_text(&self.runs),
+            TextLayoutMode::Preserve {
+                cell_width,
+                cell_height,
+            } => preserved_layout_text(&self.runs, cell_width, cell_height),
+        }
+    }
+}
+
+/// Paint `doc` into a [`TextExtractionScene`] and return its text.
+///
+/// As with [`paint_scene`], styles and layout must already be resolved.
+pub fn extract_text(doc: &BaseDocument, width: u32, height: u32, mode: TextLayoutMode) -> String {
+    let mut scene = TextExtractionScene::new();
+    paint_scene(&mut scene, doc, 1.0, width, height);
+    scene.to_text(mode)
+}
+
+impl PaintScene for TextExtractionScene {
+    fn reset(&mut self) {
+        self.runs.clear();
+        self.seen.clear();
+    }
+
+    fn push_layer(
+        &mut self,
+        _blend: impl Into<BlendMode>,
+        _alpha: f32,
+        _transform: Affine,
+        _clip: &impl Shape,
+    ) {
+    }
+
+    fn pop_layer(&mut self) {}
+
+    fn stroke<'a>(
+        &mut self,
+        _style: &Stroke,
+        _transform: Affine,
+        _brush: impl Into<BrushRef<'a>>,
+        _brush_transform: Option<Affine>,
+        _shape: &impl Shape,
+    ) {
+    }
+
+    fn fill<'a>(
+        &mut self,
+        _style: Fill,
+        _transform: Affine,
+        _brush: impl Into<Paint<'a>>,
+        _brush_transform: Option<Affine>,
+        _shape: &impl Shape,
+    ) {
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        _color: Color,
+        transform: Affine,
+    ) {
+        let buffer_key = buffer as *const blitz_text::Buffer as usize;
+
+        for (line, run) in buffer.layout_runs().enumerate() {
+            let Some(start) = run.glyphs.iter().map(|glyph| glyph.start).min() else {
+                continue;
+            };
+            let end = run.glyphs.iter().map(|glyph| glyph.end).max().unwrap_or(start);
+
+            let mut text = String::new();
+            push_collapsed_text(&mut text, run.text.get(start..end).unwrap_or_default());
+            text.truncate(text.trim_end().len());
+            if text.is_empty() {
+                continue;
+            }
+
+            let left = run
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.x)
+                .fold(f32::INFINITY, f32::min);
+            let right = run
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.x + glyph.w)
+                .fold(f32::NEG_INFINITY, f32::max);
+            let local = Rect::new(
+                position.x + left as f64,
+                position.y + run.line_top as f64,
+                position.x + right as f64,
+                position.y + (run.line_top + run.line_height) as f64,
+            );
+            let text_run = TextRun {
+                text,
+                bounds: transform.transform_rect_bbox(local),
+            };
+
+            match self.seen.get(&(buffer_key, line)) {
+                Some(&idx) => self.runs[idx] = text_run,
+                None => {
+                    self.seen.insert((buffer_key, line), self.runs.len());
+                    self.runs.push(text_run);
+                }
+            }
+        }
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        _transform: Affine,
+        _rect: Rect,
+        _brush: Color,
+        _radius: f64,
+        _std_dev: f64,
+    ) {
+    }
+}
+
+/// Group runs into visual lines, ordered top to bottom and then left to right
+fn group_lines(runs: &[TextRun]) -> Vec<Vec<&TextRun>> {
+    let mut sorted: Vec<&TextRun> = runs.iter().collect();
+    sorted.sort_by(|a, b| {
+        a.bounds
+            .y0
+            .total_cmp(&b.bounds.y0)
+            .then(a.bounds.x0.total_cmp(&b.bounds.x0))
+    });
+
+    let mut lines: Vec<Vec<&TextRun>> = Vec::new();
+    for run in sorted {
+        // A run belongs to the current line if its vertical center falls inside the line
+        let center = run.bounds.center().y;
+        match lines.last_mut() {
+            Some(line) if center >= line[0].bounds.y0 && center <= line[0].bounds.y1 => {
+                line.push(run)
+            }
+            _ => lines.push(vec![run]),
+        }
+    }
+    for line in lines.iter_mut() {
+        line.sort_by(|a, b| a.bounds.x0.total_cmp(&b.bounds.x0));
+    }
+    lines
+}
+
+fn reading_order_text(runs: &[TextRun]) -> String {
+    let mut out = String::new();
+    let mut previous_line: Option<Rect> = None;
+
+    for line in group_lines(runs) {
+        let line_bounds = line
+            .iter()
+            .map(|run| run.bounds)
+            .reduce(|a, b| a.union(b))
+            .unwrap_or_default();
+
+        if let Some(previous) = previous_line {
+            // A gap of more than half a line between two lines is treated as a paragraph break
+            let gap = line_bounds.y0 - previous.y1;
+            out.push('\n');
+            if gap > line_bounds.height() * 0.5 {
+                out.push('\n');
+            }
+        }
+
+        let mut previous_run: Option<&TextRun> = None;
+        for run in line {
+            // Runs which touch horizontally are parts of the same word (e.g. inline elements)
+            if let Some(previous) = previous_run
+                && run.bounds.x0 - previous.bounds.x1 > run.bounds.height() * 0.2
+            {
+                out.push(' ');
+            }
+            push_collapsed_text(&mut out, &run.text);
+            previous_run = Some(run);
+        }
+
+        previous_line = Some(line_bounds);
+    }
+
+    out
+}
+
+fn preserved_layout_text(runs: &[TextRun], cell_width: f64, cell_height: f64) -> String {
+    let cell_width = cell_width.max(1.0);
+    let cell_height = cell_height.max(1.0);
+    let mut rows: Vec<Vec<char>> = Vec::new();
+
+    for line in group_lines(runs) {
+        let row = (line[0].bounds.center().y / cell_height).floor().max(0.0) as usize;
+        if rows.len() <= row {
+            rows.resize(row + 1, Vec::new());
+        }
+        let cells = &mut rows[row];
+
+        for run in line {
+            let mut column = (run.bounds.x0 / cell_width).round().max(0.0) as usize;
+            // Never overwrite text which is already on this row: shift right instead
+            if !cells.is_empty() && column <= cells.len() {
+                column = cells.len() + 1;
+            }
+            if cells.len() < column {
+                cells.resize(column, ' ');
+            }
+            cells.extend(run.text.chars());
+        }
+    }
+
+    let mut out = rows
+        .iter()
+        .map(|row| row.iter().collect::<String>().trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    out.truncate(out.trim_end().len());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(text: &str, x: f64, y: f64, width: f64) -> TextRun {
+        TextRun {
+            text: text.to_string(),
+            bounds: Rect::new(x, y, x + width, y + 16.0),
+        }
+    }
+
+    #[test]
+    fn reading_order_sorts_lines_and_paragraphs() {
+        let mut scene = TextExtractionScene::new();
+        scene.runs = vec![
+            run("Second paragraph", 0.0, 64.0, 128.0),
+            run("world", 48.0, 0.0, 40.0),
+            run("Hello", 0.0, 0.0, 40.0),
+            run("next line", 0.0, 16.0, 72.0),
+        ];
+        assert_eq!(
+            scene.to_text(TextLayoutMode::ReadingOrder),
+            "Hello world\nnext line\n\nSecond paragraph"
+        );
+    }
+
+    #[test]
+    fn touching_runs_are_joined() {
+        let mut scene = TextExtractionScene::new();
+        scene.runs = vec![run("Sub", 0.0, 0.0, 24.0), run("mit", 24.0, 0.0, 24.0)];
+        assert_eq!(scene.to_text(TextLayoutMode::ReadingOrder), "Submit");
+    }
+
+    #[test]
+    fn preserve_places_text_on_a_grid() {
+        let mut scene = TextExtractionScene::new();
+        scene.runs = vec![run("Name", 0.0, 0.0, 32.0), run("Value", 80.0, 32.0, 40.0)];
+        assert_eq!(
+            scene.to_text(TextLayoutMode::preserve()),
+            "Name\n\n          Value"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Rasters of one tile of a background, which are repeated to paint backgrounds with too many
+//! tiles to paint one by one
+//!
+//! Tiles are rasterized with tiny-skia. Rasters are cached by what the tile is painted with and
+//! the size it's rasterized at, so a background is rasterized again only when it changes or is
+//! painted at another size, rather than every frame. The cache's memory is managed by the global
+//! [`CacheCoordinator`].
+
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use anyrender::ImageRenderer;
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use kurbo::{Affine, Size};
+
+/// The most tiles a background is painted with one by one: beyond that, a raster of one tile is
+/// repeated
+pub(crate) const MAX_BACKGROUND_TILES: u32 = 500;
+
+/// The largest raster (in pixels) of a tile which is repeated
+const MAX_CELL_PIXELS: f64 = 1024.0 * 1024.0;
+
+/// The most rasters cached, as backgrounds with changing contents (e.g. animated gradients) get a
+/// raster per frame
+const MAX_CACHED_RASTERS: usize = 32;
+
+/// What a tile is painted with, which identifies its raster
+pub(crate) enum TileRasterContent {
+    /// An image, and the transform scaling it to the size of the tile. Images are compared by
+    /// their pixels' address, which the cached image keeps from being reused.
+    Image(peniko::Image, Affine),
+    /// A gradient, and its brush transform
+    Gradient(peniko::Gradient, Option<Affine>),
+    /// An SVG, and the transform scaling it to the size of the tile
+    #[cfg(feature = "svg")]
+    Svg(Arc<usvg::Tree>, Affine),
+}
+
+impl PartialEq for TileRasterContent {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Image(image, scale), Self::Image(other, other_scale)) => {
+                std::ptr::eq(image.data.data(), other.data.data())
+                    && (image.width, image.height) == (other.width, other.height)
+                    && (image.quality, image.alpha) == (other.quality, other.alpha)
+                    && scale == other_scale
+            }
+            (Self::Gradient(gradient, transform), Self::Gradient(other, other_transform)) => {
+                gradient == other && transform == other_transform
+            }
+            #[cfg(feature = "svg")]
+            (Self::Svg(svg, scale), Self::Svg(other, other_scale)) => {
+                Arc::ptr_eq(svg, other) && scale == other_scale
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A raster of a tile of `tile` size painted with `content`, and the gap after it to make up a
+/// `cell`
+#[derive(PartialEq)]
+pub(crate) struct TileRasterKey {
+    pub content: TileRasterContent,
+    pub tile: Size,
+    pub cell: Size,
+}
+
+/// The raster of the tile `key` is of, which `paint` paints (with the transform it's given) if it
+/// isn't cached. It's `to_raster` scaled.
+pub(crate) fn rasterized_tile(
+    key: TileRasterKey,
+    paint: impl FnOnce(&mut <TinySkiaImageRenderer as ImageRenderer>::ScenePainter<'_>, Affine),
+) -> (peniko::Image, Affine) {
+    let (width, height) = raster_cell_size(key.cell);
+    let to_raster = Affine::scale_non_uniform(
+        f64::from(width) / key.cell.width,
+        f64::from(height) / key.cell.height,
+    );
+    let cache = TileRasterCache::global();
+    if let Some(image) = cache.get(&key) {
+        return (image, to_raster);
+    }
+
+    let mut renderer = TinySkiaImageRenderer::new(width, height);
+    let mut pixels = Vec::new();
+    renderer.render(|scene| paint(scene, to_raster), &mut pixels);
+    let image = peniko::Image::new(
+        peniko::Blob::new(Arc::new(pixels)),
+        peniko::ImageFormat::Rgba8,
+        width,
+        height,
+    );
+    cache.insert(key, image.clone());
+    (image, to_raster)
+}
+
+/// The size in pixels of the raster of a tile (and the gap after it) of `cell` size, which is at
+/// least a pixel, and scaled down if it's larger than [`MAX_CELL_PIXELS`]
+fn raster_cell_size(cell: Size) -> (u32, u32) {
+    let scale = (MAX_CELL_PIXELS / cell.area()).sqrt().min(1.0);
+    let width = (cell.width * scale).ceil().max(1.0) as u32;
+    let height = (cell.height * scale).ceil().max(1.0) as u32;
+    (width, height)
+}
+
+struct CachedRaster {
+    key: TileRasterKey,
+    image: peniko::Image,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    rasters: Vec<CachedRaster>,
+    /// Counts uses, for evicting the least recently used rasters first
+    clock: u64,
+}
+
+impl CacheEntries {
+    fn memory_usage(&self) -> usize {
+        self.rasters.iter().map(|cached| cached.image.data.len()).sum()
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .rasters
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(index, _)| index);
+        if let Some(index) = oldest {
+            self.rasters.swap_remove(index);
+        }
+    }
+}
+
+#[derive(Default)]
+struct TileRasterCache {
+    entries: Mutex<CacheEntries>,
+}
+
+impl TileRasterCache {
+    fn global() -> &'static TileRasterCache {
+        static GLOBAL: OnceLock<Arc<TileRasterCache>> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let cache = Arc::new(TileRasterCache::default());
+            let managed_cache: Arc<dyn ManagedCache> = cache.clone();
+            CacheCoordinator::global().register(&managed_cache, 1);
+            cache
+        })
+    }
+
+    fn get(&self, key: &TileRasterKey) -> Option<peniko::Image> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let cached = entries.rasters.iter_mut().find(|cached| cached.key == *key)?;
+        cached.last_used = clock;
+        Some(cached.image.clone())
+    }
+
+    fn insert(&self, key: TileRasterKey, image: peniko::Image) {
+        let mut entries = self.lock();
+        if entries.rasters.len() >= MAX_CACHED_RASTERS {
+            entries.evict_least_recently_used();
+        }
+        entries.clock += 1;
+        let cached = CachedRaster {
+            key,
+            image,
+            last_used: entries.clock,
+        };
+        entries.rasters.push(cached);
+        drop(entries);
+        CacheCoordinator::global().enforce_budget();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheEntries> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Evicted rasters are rasterized again the next time they're painted
+impl ManagedCache for TileRasterCache {
+    fn name(&self) -> &str {
+        "rasterized background tiles"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.lock().memory_usage()
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        let mut entries = self.lock();
+        while !entries.rasters.is_empty() && entries.memory_usage() > target_bytes {
+            entries.evict_least_recently_used();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use anyrender::PaintScene;
+    use kurbo::{Point, Rect};
+    use peniko::{Color, Fill};
+
+    use super::*;
+
+    #[test]
+    fn repeated_tiles_are_rasterized_at_a_bounded_size() {
+        assert_eq!(raster_cell_size(Size::new(10.5, 4.0)), (11, 4));
+        assert_eq!(raster_cell_size(Size::new(0.01, 0.01)), (1, 1));
+        assert_eq!(raster_cell_size(Size::new(4096.0, 1024.0)), (2048, 512));
+    }
+
+    #[test]
+    fn rasters_are_cached_by_content_and_size() {
+        let paints = Cell::new(0);
+        let raster = |gradient: &peniko::Gradient, tile: Size| {
+            let key = TileRasterKey {
+                content: TileRasterContent::Gradient(gradient.clone(), None),
+                tile,
+                cell: Size::new(4.0, 2.0),
+            };
+            let (image, _) = rasterized_tile(key, |scene, transform| {
+                paints.set(paints.get() + 1);
+                let rect = Rect::from_origin_size(Point::ZERO, tile);
+                let paint = anyrender::Paint::Gradient(gradient);
+                scene.fill(Fill::NonZero, transform, paint, None, &rect);
+            });
+            image
+        };
+        let gradient = peniko::Gradient::new_linear((0.0, 0.0), (3.0, 0.0))
+            .with_stops([Color::from_rgb8(1, 2, 3), Color::from_rgb8(4, 5, 6)]);
+
+        let image = raster(&gradient, Size::new(3.0, 2.0));
+        assert_eq!((image.width, image.height), (4, 2));
+        let cached = raster(&gradient, Size::new(3.0, 2.0));
+        assert_eq!(cached.data.id(), image.data.id());
+        assert_eq!(paints.get(), 1);
+
+        let resized = raster(&gradient, Size::new(2.0, 2.0));
+        assert_ne!(resized.data.id(), image.data.id());
+        raster(&gradient.clone().with_extend(peniko::Extend::Reflect), Size::new(3.0, 2.0));
+        assert_eq!(paints.get(), 3);
+    }
+}
@@ -0,0 +1,144 @@
+//! Painting text in vertical writing modes
+//!
+//! Inline layout lays vertical text out as if it were horizontal, with the inline axis mapped onto
+//! the physical height of the box (see `blitz_dom::layout::text_writing_mode`). When painting, the
+//! text is rotated a quarter turn clockwise so that lines run top to bottom and the first line
+//! ends up on the right.
+//!
+//! In `vertical-lr`, lines stack left to right instead, which no single transform of the buffer
+//! does without mirroring its glyphs. Each line is drawn on its own, moved across to the other
+//! side of the box and clipped to its band of the buffer.
+
+use anyrender::{Paint, PaintScene};
+use kurbo::{Affine, Point, Rect, Shape, Stroke, Vec2};
+use peniko::{BlendMode, BrushRef, Color, Fill};
+
+/// The transform which maps a horizontally laid out text buffer into a vertical box.
+///
+/// `origin` is the top-left of the content box and `block_size` the height of the laid out
+/// text (both in device pixels).
+pub(crate) fn vertical_text_transform(origin: Point, block_size: f64) -> Affine {
+    Affine::translate(origin.to_vec2() + Vec2::new(block_size, 0.0))
+        * Affine::rotate(std::f64::consts::FRAC_PI_2)
+}
+
+/// The transform which maps the line of a horizontally laid out text buffer spanning
+/// `line_top..line_top + line_height` into a `vertical-lr` box.
+///
+/// This is [`vertical_text_transform`] with the line moved to the same distance from the left of
+/// the box as it was from the right (all in device pixels).
+pub(crate) fn vertical_lr_line_transform(origin: Point, line_top: f64, line_height: f64) -> Affine {
+    Affine::translate(origin.to_vec2() + Vec2::new(2.0 * line_top + line_height, 0.0))
+        * Affine::rotate(std::f64::consts::FRAC_PI_2)
+}
+
+/// A [`PaintScene`] which applies an additional transform to everything drawn through it
+pub(crate) struct TransformedScene<'a, S: PaintScene> {
+    pub(crate) inner: &'a mut S,
+    pub(crate) transform: Affine,
+}
+
+impl<S: PaintScene> PaintScene for TransformedScene<'_, S> {
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        self.inner
+            .push_layer(blend, alpha, self.transform * transform, clip);
+    }
+
+    fn pop_layer(&mut self) {
+        self.inner.pop_layer();
+    }
+
+    fn stroke<'b>(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: impl Into<BrushRef<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.inner.stroke(
+            style,
+            self.transform * transform,
+            brush,
+            brush_transform,
+            shape,
+        );
+    }
+
+    fn fill<'b>(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: impl Into<Paint<'b>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.inner.fill(
+            style,
+            self.transform * transform,
+            brush,
+            brush_transform,
+            shape,
+        );
+    }
+
+    fn render_text_buffer(
+        &mut self,
+        buffer: &blitz_text::Buffer,
+        position: Point,
+        color: Color,
+        transform: Affine,
+    ) {
+        // TODO: glyphs with an upright orientation (most CJK characters) should be counter-rotated
+        // around their own center.
+        self.inner
+            .render_text_buffer(buffer, position, color, self.transform * transform);
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        self.inner
+            .draw_box_shadow(self.transform * transform, rect, brush, radius, std_dev);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertical_lines_stack_in_their_block_direction() {
+        let origin = Point::new(10.0, 20.0);
+        // Two 20px lines, which start 5px into the inline axis
+        let first_line = Point::new(5.0, 0.0);
+        let second_line = Point::new(5.0, 20.0);
+
+        // `vertical-rl` puts the first line on the right, with the inline axis running down
+        let rl = vertical_text_transform(origin, 40.0);
+        assert!((rl * first_line - Point::new(50.0, 25.0)).hypot() < 1e-9);
+        assert!((rl * second_line - Point::new(30.0, 25.0)).hypot() < 1e-9);
+
+        // `vertical-lr` puts it on the left, with glyphs turned the same way
+        let first = vertical_lr_line_transform(origin, 0.0, 20.0);
+        let second = vertical_lr_line_transform(origin, 20.0, 20.0);
+        assert!((first * first_line - Point::new(30.0, 25.0)).hypot() < 1e-9);
+        assert!((second * second_line - Point::new(50.0, 25.0)).hypot() < 1e-9);
+        assert_eq!(first.as_coeffs()[..4], rl.as_coeffs()[..4]);
+    }
+}
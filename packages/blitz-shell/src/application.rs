@@ -1,45 +1,87 @@
 use std::collections::HashMap;
+#[cfg(feature = "net")]
+use std::sync::Arc;
 
 use anyrender::WindowRenderer;
+use blitz_dom::BaseDocument;
+#[cfg(feature = "net")]
+use blitz_net::DownloadManager;
+use blitz_paint::BlitzPainter;
+use blitz_traits::cache::{CacheCoordinator, MemoryPressure};
+#[cfg(feature = "net")]
+use blitz_traits::navigation::NavigationOptions;
+use blitz_traits::render::DocumentRenderer;
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
 use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
 use winit::window::WindowId;
 
 use crate::event::BlitzShellEvent;
+use crate::idle::run_idle_periods;
 use crate::{View, WindowConfig};
 
-pub struct BlitzApplication<Rend: WindowRenderer> {
-    pub windows: HashMap<WindowId, View<Rend>>,
-    pub pending_windows: Vec<WindowConfig<Rend>>,
+pub struct BlitzApplication<Rend: WindowRenderer, Painter = BlitzPainter> {
+    pub windows: HashMap<WindowId, View<Rend, Painter>>,
+    pub pending_windows: Vec<WindowConfig<Rend, Painter>>,
     pub proxy: EventLoopProxy<BlitzShellEvent>,
+    /// Saves links with a `download` attribute, when documents navigate with
+    /// [`BlitzShellNavigationProvider`](crate::BlitzShellNavigationProvider)
+    #[cfg(feature = "net")]
+    pub downloads: Option<Arc<DownloadManager>>,
 }
 
-impl<Rend: WindowRenderer> BlitzApplication<Rend> {
+impl<Rend: WindowRenderer, Painter> BlitzApplication<Rend, Painter> {
     pub fn new(proxy: EventLoopProxy<BlitzShellEvent>) -> Self {
         BlitzApplication {
             windows: HashMap::new(),
             pending_windows: Vec::new(),
             proxy,
+            #[cfg(feature = "net")]
+            downloads: None,
         }
     }
 
-    pub fn add_window(&mut self, window_config: WindowConfig<Rend>) {
+    pub fn add_window(&mut self, window_config: WindowConfig<Rend, Painter>) {
         self.pending_windows.push(window_config);
     }
 
-    fn window_mut_by_doc_id(&mut self, doc_id: usize) -> Option<&mut View<Rend>> {
+    fn window_mut_by_doc_id(&mut self, doc_id: usize) -> Option<&mut View<Rend, Painter>> {
         self.windows.values_mut().find(|w| w.doc.id() == doc_id)
     }
+
+    /// Save a link with a `download` attribute with [`downloads`](Self::downloads)
+    #[cfg(feature = "net")]
+    fn download(&self, mut options: NavigationOptions) {
+        let Some(downloads) = &self.downloads else {
+            return;
+        };
+        let file_name = options.download.take();
+        let doc_id = options.source_document;
+        let result = downloads.start(Some(doc_id), options.into_request(), file_name);
+        #[cfg(feature = "tracing")]
+        if let Err(err) = result {
+            tracing::warn!("Couldn't download: {err}");
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = result;
+    }
 }
 
-impl<Rend: WindowRenderer> ApplicationHandler<BlitzShellEvent> for BlitzApplication<Rend> {
+impl<Rend, Painter> ApplicationHandler<BlitzShellEvent> for BlitzApplication<Rend, Painter>
+where
+    Rend: WindowRenderer,
+    Painter: for<'a> DocumentRenderer<BaseDocument, Rend::ScenePainter<'a>>,
+{
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         println!("📱 ApplicationHandler::resumed() called - existing windows: {}, pending windows: {}", 
                  self.windows.len(), self.pending_windows.len());
         
-        // Resume existing windows
+        // Resume windows which were suspended (mobile platforms destroy windows' surfaces when
+        // apps are backgrounded, and resume them when they're foregrounded again)
         for (window_id, view) in self.windows.iter_mut() {
+            if view.renderer.is_active() {
+                continue;
+            }
             println!("📱 Resuming existing window: {:?}", window_id);
             if let Err(e) = view.resume() {
                 eprintln!("❌ Failed to resume window {:?}: {}", window_id, e);
@@ -71,6 +113,19 @@ impl<Rend: WindowRenderer> ApplicationHandler<BlitzShellEvent> for BlitzApplicat
         }
     }
 
+    fn memory_warning(&mut self, _event_loop: &ActiveEventLoop) {
+        CacheCoordinator::global().handle_memory_pressure(MemoryPressure::Critical);
+        // Caches used by renderers (like the glyph atlas) shrink at the end of their next frame
+        for view in self.windows.values() {
+            view.request_redraw();
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // The event loop has nothing else to do, so this is an idle period
+        event_loop.set_control_flow(run_idle_periods(self.windows.values_mut()));
+    }
+
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
@@ -128,9 +183,33 @@ impl<Rend: WindowRenderer> ApplicationHandler<BlitzShellEvent> for BlitzApplicat
                 }
             }
 
+            BlitzShellEvent::ContextMenu { window_id, request } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.show_context_menu(request);
+                }
+            }
+            BlitzShellEvent::ContextMenuItem { window_id, id } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.handle_context_menu_item(&id);
+                    window.request_redraw();
+                }
+            }
+            BlitzShellEvent::SystemPreferences {
+                window_id,
+                prefers_reduced_motion,
+            } => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.set_prefers_reduced_motion(prefers_reduced_motion);
+                }
+            }
+
             BlitzShellEvent::Embedder(_) => {
                 // Do nothing. Should be handled by embedders (if required).
             }
+            #[cfg(feature = "net")]
+            BlitzShellEvent::Navigate(options) if options.download.is_some() => {
+                self.download(*options);
+            }
             BlitzShellEvent::Navigate(_opts) => {
                 // Do nothing. Should be handled by embedders (if required).
             }
@@ -0,0 +1,235 @@
+//! Context menus, shown when `contextmenu` events' default actions aren't prevented
+//!
+//! Menus have items for copying the selected text, selecting all of a text input's text and
+//! (when devtools are enabled) inspecting the element, followed by the items embedders add with
+//! [`WindowConfig::with_context_menu_items`](crate::WindowConfig::with_context_menu_items).
+//! They're shown as native menus on Windows and macOS with the `native-context-menu` feature.
+
+use std::sync::Arc;
+
+use blitz_traits::shell::ContextMenuRequest;
+use winit::event_loop::EventLoopProxy;
+use winit::window::{Window, WindowId};
+
+use crate::event::BlitzShellEvent;
+
+/// Builds the items embedders add to context menus, after Blitz's own
+pub type ContextMenuItems = Arc<dyn Fn(&ContextMenuRequest) -> Vec<ContextMenuItem> + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextMenuItem {
+    /// Copies the selected text
+    Copy,
+    /// Selects all of the text input's text
+    SelectAll,
+    /// Logs the element's details (when devtools are enabled)
+    InspectElement,
+    Separator,
+    /// An item added by the embedder. Choosing it sends a [`ContextMenuSelection`] to the event
+    /// loop as an embedder event.
+    Custom { id: String, label: String },
+}
+
+impl ContextMenuItem {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Copy => "blitz.copy",
+            Self::SelectAll => "blitz.select-all",
+            Self::InspectElement => "blitz.inspect-element",
+            Self::Separator => "blitz.separator",
+            Self::Custom { id, .. } => id,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Copy => "Copy",
+            Self::SelectAll => "Select All",
+            Self::InspectElement => "Inspect Element",
+            Self::Separator => "",
+            Self::Custom { label, .. } => label,
+        }
+    }
+}
+
+/// An embedder's item chosen from a context menu
+#[derive(Debug, Clone)]
+pub struct ContextMenuSelection {
+    pub window_id: WindowId,
+    /// The id of the item
+    pub id: String,
+    /// What the menu was shown for
+    pub request: ContextMenuRequest,
+}
+
+/// The items of the context menu for `request`
+pub(crate) fn context_menu_items(
+    request: &ContextMenuRequest,
+    devtools_enabled: bool,
+    embedder_items: Option<&ContextMenuItems>,
+) -> Vec<ContextMenuItem> {
+    let mut items = Vec::new();
+    if request.selected_text.is_some() {
+        items.push(ContextMenuItem::Copy);
+    }
+    if request.editable {
+        items.push(ContextMenuItem::SelectAll);
+    }
+    if devtools_enabled {
+        items.push(ContextMenuItem::InspectElement);
+    }
+
+    let embedder_items = embedder_items.map(|build| build(request)).unwrap_or_default();
+    if !items.is_empty() && !embedder_items.is_empty() {
+        items.push(ContextMenuItem::Separator);
+    }
+    items.extend(embedder_items);
+    items
+}
+
+#[cfg(all(feature = "native-context-menu", any(target_os = "windows", target_os = "macos")))]
+mod native {
+    use std::sync::{Mutex, Once};
+
+    use muda::MenuEvent;
+    use winit::event_loop::EventLoopProxy;
+    use winit::window::WindowId;
+
+    use crate::event::BlitzShellEvent;
+
+    /// The prefix of the ids of context menu items, which tells their events apart from those of
+    /// the embedder's own menus
+    pub(super) const ID_PREFIX: &str = "blitz-context-menu:";
+
+    type MenuEventHandler = Box<dyn Fn(MenuEvent) + Send + Sync>;
+
+    /// Where the events of the context menu being shown go
+    pub(super) static CONTEXT_MENU: Mutex<Option<(WindowId, EventLoopProxy<BlitzShellEvent>)>> =
+        Mutex::new(None);
+    static EMBEDDER_HANDLER: Mutex<Option<MenuEventHandler>> = Mutex::new(None);
+    static INSTALL_HANDLER: Once = Once::new();
+
+    /// Handle muda's menu events, sending those of context menu items to the event loop and
+    /// passing the rest on to the embedder's handler
+    pub(super) fn install_handler() {
+        INSTALL_HANDLER.call_once(|| {
+            MenuEvent::set_event_handler(Some(|event: MenuEvent| {
+                let Some(id) = event.id.0.strip_prefix(ID_PREFIX) else {
+                    let handler = EMBEDDER_HANDLER.lock().unwrap_or_else(|err| err.into_inner());
+                    if let Some(handler) = handler.as_ref() {
+                        handler(event);
+                    }
+                    return;
+                };
+                let context_menu = CONTEXT_MENU.lock().unwrap_or_else(|err| err.into_inner());
+                if let Some((window_id, proxy)) = context_menu.as_ref() {
+                    let id = id.to_string();
+                    let window_id = *window_id;
+                    let _ = proxy.send_event(BlitzShellEvent::ContextMenuItem { window_id, id });
+                }
+            }));
+        });
+    }
+
+    /// Set the handler for the events of the embedder's own muda menus. Blitz handles muda's
+    /// menu events to find out which context menu items are chosen, which replaces any handler
+    /// set with [`MenuEvent::set_event_handler`], so embedders with menus of their own should
+    /// set their handler with this instead. It's called with every event which isn't for a
+    /// context menu item.
+    pub fn set_menu_event_handler(handler: Option<impl Fn(MenuEvent) + Send + Sync + 'static>) {
+        let handler = handler.map(|handler| Box::new(handler) as MenuEventHandler);
+        *EMBEDDER_HANDLER.lock().unwrap_or_else(|err| err.into_inner()) = handler;
+        install_handler();
+    }
+}
+
+#[cfg(all(feature = "native-context-menu", any(target_os = "windows", target_os = "macos")))]
+pub use native::set_menu_event_handler;
+
+/// Show `items` as a native context menu at the cursor, returning whether it could be. The item
+/// chosen from it (if any) is sent to the event loop as a [`BlitzShellEvent::ContextMenuItem`].
+#[cfg(all(feature = "native-context-menu", any(target_os = "windows", target_os = "macos")))]
+pub(crate) fn show_context_menu(
+    window: &Window,
+    items: &[ContextMenuItem],
+    proxy: &EventLoopProxy<BlitzShellEvent>,
+) -> bool {
+    use muda::{ContextMenu as _, Menu, MenuItem, PredefinedMenuItem};
+    use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    let menu = Menu::new();
+    for item in items {
+        let appended = match item {
+            ContextMenuItem::Separator => menu.append(&PredefinedMenuItem::separator()),
+            _ => {
+                let id = format!("{}{}", native::ID_PREFIX, item.id());
+                menu.append(&MenuItem::with_id(id, item.label(), true, None))
+            }
+        };
+        if appended.is_err() {
+            return false;
+        }
+    }
+
+    native::install_handler();
+    let context_menu = Some((window.id(), proxy.clone()));
+    *native::CONTEXT_MENU.lock().unwrap_or_else(|err| err.into_inner()) = context_menu;
+
+    let Ok(handle) = window.window_handle() else {
+        return false;
+    };
+    match handle.as_raw() {
+        #[cfg(target_os = "windows")]
+        RawWindowHandle::Win32(handle) => unsafe {
+            menu.show_context_menu_for_hwnd(handle.hwnd.get(), None)
+        },
+        #[cfg(target_os = "macos")]
+        RawWindowHandle::AppKit(handle) => unsafe {
+            menu.show_context_menu_for_nsview(handle.ns_view.as_ptr(), None)
+        },
+        _ => false,
+    }
+}
+
+/// Native context menus aren't available, so none are shown
+#[cfg(not(all(feature = "native-context-menu", any(target_os = "windows", target_os = "macos"))))]
+pub(crate) fn show_context_menu(
+    _window: &Window,
+    _items: &[ContextMenuItem],
+    _proxy: &EventLoopProxy<BlitzShellEvent>,
+) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_depend_on_the_request() {
+        let request = ContextMenuRequest {
+            selected_text: Some(String::from("text")),
+            editable: true,
+            ..Default::default()
+        };
+        let items = context_menu_items(&request, false, None);
+        assert_eq!(items, [ContextMenuItem::Copy, ContextMenuItem::SelectAll]);
+
+        let embedder_items: ContextMenuItems = Arc::new(|request: &ContextMenuRequest| {
+            let open_link = ContextMenuItem::Custom {
+                id: String::from("open-link"),
+                label: String::from("Open Link"),
+            };
+            request.link.as_ref().map(|_| open_link).into_iter().collect()
+        });
+        let request = ContextMenuRequest {
+            link: Some(String::from("https://example.com/")),
+            ..Default::default()
+        };
+        let items = context_menu_items(&request, true, Some(&embedder_items));
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], ContextMenuItem::InspectElement);
+        assert_eq!(items[1], ContextMenuItem::Separator);
+        assert_eq!(items[2].id(), "open-link");
+    }
+}
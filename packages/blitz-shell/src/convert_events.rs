@@ -1,9 +1,14 @@
-use blitz_traits::events::{BlitzImeEvent, BlitzKeyEvent, KeyState};
+use blitz_traits::events::{
+    BlitzImeEvent, BlitzKeyEvent, BlitzPointerEvent, KeyState, MOUSE_POINTER_ID, MouseEventButton,
+    MouseEventButtons, PointerType, UiEvent,
+};
 use blitz_traits::shell::ColorScheme;
 use keyboard_types::{Code, Key, Location, Modifiers, NamedKey};
+use winit::dpi::LogicalPosition;
 use winit::event::ElementState;
 use winit::event::Ime;
 use winit::event::KeyEvent as WinitKeyEvent;
+use winit::event::{Force, Touch, TouchPhase};
 use winit::keyboard::Key as WinitKey;
 use winit::keyboard::KeyCode as WinitKeyCode;
 use winit::keyboard::KeyLocation as WinitKeyLocation;
@@ -35,6 +40,56 @@ pub(crate) fn winit_ime_to_blitz(event: Ime) -> BlitzImeEvent {
     }
 }
 
+/// Convert a touch to an event of its pointer
+pub(crate) fn winit_touch_to_blitz(
+    touch: &Touch,
+    scale_factor: f64,
+    is_primary: bool,
+    mods: Modifiers,
+) -> UiEvent {
+    let LogicalPosition::<f32> { x, y } = touch.location.to_logical(scale_factor);
+    let pressed = !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled);
+    let pressure = match touch.force {
+        Some(force) if pressed => force.normalized() as f32,
+        _ if pressed => 0.5,
+        _ => 0.0,
+    };
+    let altitude_angle = match touch.force {
+        Some(Force::Calibrated {
+            altitude_angle: Some(angle),
+            ..
+        }) => angle as f32,
+        _ => std::f32::consts::FRAC_PI_2,
+    };
+
+    let event = BlitzPointerEvent {
+        // Touch ids start from zero, so are offset to not collide with the mouse's pointer id
+        pointer_id: MOUSE_POINTER_ID + 1 + touch.id,
+        pointer_type: PointerType::Touch,
+        is_primary,
+        x,
+        y,
+        button: MouseEventButton::Main,
+        buttons: if pressed {
+            MouseEventButtons::Primary
+        } else {
+            MouseEventButtons::None
+        },
+        pressure,
+        altitude_angle,
+        // winit reports the altitude of pens but not their azimuth, which pointers that can't
+        // report it give as 0
+        azimuth_angle: 0.0,
+        mods,
+    };
+    match touch.phase {
+        TouchPhase::Started => UiEvent::PointerDown(event),
+        TouchPhase::Moved => UiEvent::PointerMove(event),
+        TouchPhase::Ended => UiEvent::PointerUp(event),
+        TouchPhase::Cancelled => UiEvent::PointerCancel(event),
+    }
+}
+
 pub(crate) fn winit_key_event_to_blitz(
     event: &WinitKeyEvent,
     mods: WinitModifiers,
@@ -4,8 +4,10 @@ use std::{any::Any, sync::Arc};
 use accesskit_winit::{Event as AccessKitEvent, WindowEvent as AccessKitWindowEvent};
 use blitz_dom::net::Resource;
 use blitz_traits::navigation::NavigationOptions;
+use blitz_traits::shell::ContextMenuRequest;
 use futures_util::task::ArcWake;
-use winit::{event_loop::EventLoopProxy, window::WindowId};
+use winit::event_loop::EventLoopProxy;
+use winit::window::{Window, WindowId};
 
 #[derive(Debug, Clone)]
 pub enum BlitzShellEvent {
@@ -25,6 +27,24 @@ pub enum BlitzShellEvent {
         data: Arc<AccessKitWindowEvent>,
     },
 
+    /// Show a context menu for the document in a window
+    ContextMenu {
+        window_id: WindowId,
+        request: ContextMenuRequest,
+    },
+
+    /// The item with `id` was chosen from a window's context menu
+    ContextMenuItem {
+        window_id: WindowId,
+        id: String,
+    },
+
+    /// The preferences which winit doesn't report were read for a window
+    SystemPreferences {
+        window_id: WindowId,
+        prefers_reduced_motion: bool,
+    },
+
     /// An arbitary event from the Blitz embedder
     Embedder(Arc<dyn Any + Send + Sync>),
 
@@ -90,3 +110,16 @@ pub fn create_waker(proxy: &EventLoopProxy<BlitzShellEvent>, id: WindowId) -> st
         proxy: proxy.clone(),
     }))
 }
+
+/// Create a waker that will request a redraw of `window`
+pub(crate) fn create_redraw_waker(window: &Arc<Window>) -> std::task::Waker {
+    struct RedrawHandle(Arc<Window>);
+
+    impl ArcWake for RedrawHandle {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.request_redraw()
+        }
+    }
+
+    futures_util::task::waker(Arc::new(RedrawHandle(window.clone())))
+}
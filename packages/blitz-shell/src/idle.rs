@@ -0,0 +1,190 @@
+//! Low-priority work which runs when frames have time to spare
+//!
+//! Applications run an idle period whenever the event loop is about to wait for events, by
+//! calling [`run_idle_periods`] from [`ApplicationHandler::about_to_wait`]. In it, each view's
+//! [`IdleTasks`] update its accessibility tree, run its document's idle callbacks (see
+//! [`BaseDocument::request_idle_callback`]) and trim caches to their budgets, until the period's
+//! deadline.
+//!
+//! While the document is animating, idle periods end with the current frame's budget, so frames
+//! which use all of it leave no time for idle work. Work which has waited [`MAX_IDLE_DELAY`] runs
+//! anyway, in a short period of its own, so that it isn't put off for as long as the animation
+//! lasts.
+//!
+//! [`ApplicationHandler::about_to_wait`]: winit::application::ApplicationHandler::about_to_wait
+
+use std::time::Duration;
+
+use anyrender::WindowRenderer;
+use blitz_dom::BaseDocument;
+use blitz_traits::cache::CacheCoordinator;
+use blitz_traits::render::DocumentRenderer;
+use winit::event_loop::ControlFlow;
+// `std::time::Instant` isn't available on wasm32
+use web_time::Instant;
+
+use crate::View;
+
+/// The longest idle work waits for an idle period with time to spare before running anyway
+const MAX_IDLE_DELAY: Duration = Duration::from_millis(250);
+
+/// How long work which has waited [`MAX_IDLE_DELAY`] is given to run
+const OVERDUE_PERIOD: Duration = Duration::from_millis(4);
+
+/// Run an idle period for each of `views`, returning how the event loop should wait for the next
+/// one. Applications call this whenever the event loop is about to wait for events.
+pub fn run_idle_periods<'a, Rend, Painter>(
+    views: impl IntoIterator<Item = &'a mut View<Rend, Painter>>,
+) -> ControlFlow
+where
+    Rend: WindowRenderer + 'a,
+    Painter: for<'b> DocumentRenderer<BaseDocument, Rend::ScenePainter<'b>> + 'a,
+{
+    let next_period = views
+        .into_iter()
+        .filter_map(|view| view.run_idle_tasks())
+        .min();
+    match next_period {
+        Some(start) if start <= Instant::now() => ControlFlow::Poll,
+        Some(start) => ControlFlow::WaitUntil(start),
+        None => ControlFlow::Wait,
+    }
+}
+
+/// What happened in an idle period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleOutcome {
+    /// Whether any of the document's idle callbacks ran, which may have changed it
+    pub ran_callbacks: bool,
+    /// When the next idle period should start, if any work is left: straight away if the period
+    /// ended before the work did, or once the work is overdue if there was no time for it
+    pub next_period: Option<Instant>,
+}
+
+/// The idle work of a view
+#[derive(Debug, Clone)]
+pub struct IdleTasks {
+    /// When the last frame started, which idle periods between frames are measured from
+    pub last_frame_start: Instant,
+    /// Whether caches have been trimmed to their budgets since the last frame
+    pub caches_trimmed: bool,
+    /// Whether the accessibility tree needs updating
+    pub accessibility_stale: bool,
+    /// When the work which is left was first left waiting
+    waiting_since: Option<Instant>,
+}
+
+impl Default for IdleTasks {
+    fn default() -> Self {
+        Self {
+            last_frame_start: Instant::now(),
+            caches_trimmed: true,
+            accessibility_stale: false,
+            waiting_since: None,
+        }
+    }
+}
+
+impl IdleTasks {
+    /// Note that a frame started at `start`, after which caches are trimmed again
+    pub fn frame_started(&mut self, start: Instant) {
+        self.last_frame_start = start;
+        self.caches_trimmed = false;
+    }
+
+    /// Run the work which fits in an idle period starting now: updating the accessibility tree
+    /// (with `update_accessibility`), `doc`'s idle callbacks, then trimming caches
+    pub fn run(
+        &mut self,
+        doc: &mut BaseDocument,
+        update_accessibility: impl FnOnce(&BaseDocument),
+    ) -> IdleOutcome {
+        let now = Instant::now();
+        let mut deadline = doc.idle_deadline(self.last_frame_start);
+        if self.waiting_since.is_some_and(|since| now >= since + MAX_IDLE_DELAY) {
+            deadline = deadline.max(now + OVERDUE_PERIOD);
+        }
+        let has_time = now < deadline;
+
+        if self.accessibility_stale && has_time {
+            update_accessibility(doc);
+            self.accessibility_stale = false;
+        }
+
+        let ran_callbacks = doc.run_idle_callbacks(deadline) > 0;
+
+        if !self.caches_trimmed && Instant::now() < deadline {
+            CacheCoordinator::global().enforce_budget();
+            self.caches_trimmed = true;
+        }
+
+        let has_work = self.accessibility_stale || doc.has_idle_callbacks() || !self.caches_trimmed;
+        if !has_work {
+            self.waiting_since = None;
+            return IdleOutcome {
+                ran_callbacks,
+                next_period: None,
+            };
+        }
+
+        // Frames come before idle work while animating, so there's only any point coming
+        // straight back when the document isn't
+        if has_time {
+            self.waiting_since = Some(now);
+        }
+        let waiting_since = *self.waiting_since.get_or_insert(now);
+        let next_period = if has_time && !doc.is_animating() {
+            now
+        } else {
+            waiting_since + MAX_IDLE_DELAY
+        };
+        let next_timeout = doc.next_idle_timeout();
+        IdleOutcome {
+            ran_callbacks,
+            next_period: Some(next_timeout.map_or(next_period, |timeout| timeout.min(next_period))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use blitz_dom::DocumentConfig;
+
+    use super::*;
+
+    #[test]
+    fn idle_work_waits_for_spare_time_until_it_is_overdue() {
+        let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+        let ran = Rc::new(Cell::new(0));
+        let request = |doc: &mut BaseDocument| {
+            let ran = ran.clone();
+            doc.request_idle_callback(move |_, _| ran.set(ran.get() + 1), None);
+        };
+        let mut tasks = IdleTasks::default();
+
+        request(&mut doc);
+        let outcome = tasks.run(&mut doc, |_| {});
+        assert_eq!(ran.get(), 1);
+        assert_eq!(outcome.next_period, None);
+
+        // A frame which used its whole budget leaves no time, and the work waits rather than
+        // the event loop spinning until there is some
+        doc.request_animation_frame(|_, _| {});
+        tasks.frame_started(Instant::now() - Duration::from_secs(1));
+        tasks.caches_trimmed = true;
+        request(&mut doc);
+        let outcome = tasks.run(&mut doc, |_| {});
+        assert_eq!(ran.get(), 1);
+        assert!(outcome.next_period.unwrap() > Instant::now());
+
+        // Until it has waited too long
+        tasks.waiting_since = Some(Instant::now() - MAX_IDLE_DELAY);
+        let outcome = tasks.run(&mut doc, |_| {});
+        assert_eq!(ran.get(), 2);
+        assert!(outcome.ran_callbacks);
+        assert_eq!(outcome.next_period, None);
+    }
+}
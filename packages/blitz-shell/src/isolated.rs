@@ -0,0 +1,390 @@
+//! Running a document's style, layout and paint on a worker thread
+//!
+//! An [`IsolatedDocument`] owns a [`BaseDocument`] which lives on a dedicated thread. The shell
+//! sends it mutations and events, and gets back [`Frame`]s of recorded drawing commands to draw
+//! into its renderer's scene. A slow layout then can't freeze input handling, and a panic during
+//! style, layout or paint stops the worker rather than the whole app.
+//!
+//! The worker handles what the document does by itself: it loads the resources the document
+//! fetches (when they're fetched with the callback handed to
+//! [`spawn`](IsolatedDocument::spawn)), and polls the document, its scripts and its input
+//! provider whenever they wake it. The shell's windows don't run their documents in isolation
+//! (yet), so apps using it drive it themselves: they forward input as [`UiEvent`]s, request a
+//! frame whenever the waker is woken and draw the frames they get back. What a window does for
+//! its document using the window itself, like IME, cursor icons, menus and accessibility, isn't
+//! done for isolated documents.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::task::{Context, Wake, Waker};
+use std::thread::{self, JoinHandle};
+
+use anyrender::{DisplayList, PaintScene};
+use blitz_dom::net::Resource;
+use blitz_dom::{BaseDocument, Document};
+use blitz_paint::BlitzPainter;
+use blitz_traits::events::UiEvent;
+use blitz_traits::net::{NetCallback, SharedCallback};
+use blitz_traits::render::{DocumentRenderer, RenderViewport};
+use blitz_traits::shell::Viewport;
+// `std::time::Instant` isn't available on wasm32
+use web_time::Instant;
+
+/// A change to make to an isolated document, run on its worker thread
+pub type DocumentMutation = Box<dyn FnOnce(&mut BaseDocument) + Send>;
+
+enum WorkerMessage {
+    Mutate(DocumentMutation),
+    Event(UiEvent),
+    SetViewport(Viewport),
+    Render(RenderViewport),
+    Resource(Resource),
+    /// The document, its scripts or its input provider woke the worker to be polled again
+    Poll,
+    /// The shell dropped the document. The worker's own senders (in its waker and net
+    /// callback) keep the channel open, so this is how it knows to stop.
+    Stop,
+}
+
+/// A document painted by its worker thread
+pub struct Frame {
+    pub display_list: DisplayList,
+    pub viewport: RenderViewport,
+}
+
+impl Frame {
+    /// Draw the frame into `scene`
+    pub fn draw(&self, scene: &mut impl PaintScene) {
+        self.display_list.replay(scene);
+    }
+}
+
+/// Returned once an isolated document's worker thread has stopped
+#[derive(Clone, Debug)]
+pub enum IsolationError {
+    /// The worker panicked, with this message
+    Crashed(String),
+    /// The worker stopped without panicking
+    Stopped,
+}
+
+impl fmt::Display for IsolationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Crashed(message) => write!(f, "document worker crashed: {message}"),
+            Self::Stopped => f.write_str("document worker stopped"),
+        }
+    }
+}
+
+impl std::error::Error for IsolationError {}
+
+/// A document whose style, layout and paint run on a dedicated worker thread
+///
+/// Messages are handled in the order they're sent. When several renders are requested before
+/// the worker gets to them, only the last is painted, so a slow document drops frames rather
+/// than falling behind.
+pub struct IsolatedDocument {
+    messages: Sender<WorkerMessage>,
+    frames: Receiver<Frame>,
+    worker: Option<JoinHandle<()>>,
+    error: Option<IsolationError>,
+}
+
+impl IsolatedDocument {
+    /// Start a worker thread running the document returned by `create_document`. The document is
+    /// created on the worker, so it doesn't need to be `Send`. It's given the callback its net
+    /// provider should deliver resources to, which loads them into the document on the worker.
+    ///
+    /// `waker` is woken whenever a frame is ready, the document changed by itself (like when a
+    /// resource loaded) and should be rendered again, or the worker stops. A waker from
+    /// [`create_waker`](crate::create_waker) polls the window it was created for.
+    ///
+    /// Documents lay out text with the global text system, so the shell's renderer must have
+    /// initialized it before the first render.
+    pub fn spawn<D, F>(create_document: F, waker: Waker) -> Self
+    where
+        D: Document,
+        F: FnOnce(SharedCallback<Resource>) -> D + Send + 'static,
+    {
+        let (messages, worker_messages) = mpsc::channel();
+        let (worker_frames, frames) = mpsc::channel();
+        let worker_sender = messages.clone();
+        let worker = thread::Builder::new()
+            .name("blitz-document".to_string())
+            .spawn(move || {
+                // Wake the shell however the worker stops (even if creating the document
+                // panics), after the frame channel is closed, so that it notices
+                let _wake_on_exit = WakeOnDrop(waker.clone());
+                let net_callback = Arc::new(WorkerNetCallback(worker_sender.clone()));
+                let doc = create_document(net_callback);
+                let (messages, frames) = (worker_messages, worker_frames);
+                run_worker(doc, worker_sender, messages, frames, waker);
+            })
+            .expect("failed to spawn document worker thread");
+
+        Self {
+            messages,
+            frames,
+            worker: Some(worker),
+            error: None,
+        }
+    }
+
+    /// Make a change to the document, like applying mutations from a framework
+    pub fn mutate(&self, mutation: impl FnOnce(&mut BaseDocument) + Send + 'static) {
+        self.send(WorkerMessage::Mutate(Box::new(mutation)));
+    }
+
+    /// Dispatch an input event to the document
+    pub fn handle_ui_event(&self, event: UiEvent) {
+        self.send(WorkerMessage::Event(event));
+    }
+
+    /// Update the document's viewport, like when the window is resized
+    pub fn set_viewport(&self, viewport: Viewport) {
+        self.send(WorkerMessage::SetViewport(viewport));
+    }
+
+    /// Ask the worker to lay out and paint the document. The frame is returned by a later
+    /// [`poll_frame`](Self::poll_frame).
+    pub fn request_frame(&self, viewport: RenderViewport) {
+        self.send(WorkerMessage::Render(viewport));
+    }
+
+    /// The most recent frame painted since the last poll, if any. Once the worker has stopped,
+    /// this returns why.
+    pub fn poll_frame(&mut self) -> Result<Option<Frame>, IsolationError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        let mut latest = None;
+        loop {
+            match self.frames.try_recv() {
+                Ok(frame) => latest = Some(frame),
+                Err(TryRecvError::Empty) => return Ok(latest),
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        // Frames painted before the worker stopped are still worth drawing
+        let error = match self.worker.take().map(JoinHandle::join) {
+            Some(Err(panic)) => IsolationError::Crashed(panic_message(&*panic)),
+            _ => IsolationError::Stopped,
+        };
+        self.error = Some(error.clone());
+        match latest {
+            Some(frame) => Ok(Some(frame)),
+            None => Err(error),
+        }
+    }
+
+    /// Whether the worker is still running
+    pub fn is_running(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_some_and(|worker| !worker.is_finished())
+    }
+
+    fn send(&self, message: WorkerMessage) {
+        // Messages to a stopped worker are dropped, and the error returned from `poll_frame`
+        let _ = self.messages.send(message);
+    }
+}
+
+impl Drop for IsolatedDocument {
+    fn drop(&mut self) {
+        self.send(WorkerMessage::Stop);
+    }
+}
+
+/// Delivers the resources fetched for a document to its worker
+struct WorkerNetCallback(Sender<WorkerMessage>);
+
+impl NetCallback<Resource> for WorkerNetCallback {
+    fn call(&self, _doc_id: usize, result: Result<Resource, Option<String>>) {
+        // Failed fetches leave the document as it is, as they do for the shell's documents
+        if let Ok(resource) = result {
+            let _ = self.0.send(WorkerMessage::Resource(resource));
+        }
+    }
+}
+
+/// Wakes the worker to poll its document again
+struct WorkerWaker(Sender<WorkerMessage>);
+
+impl Wake for WorkerWaker {
+    fn wake(self: Arc<Self>) {
+        let _ = self.0.send(WorkerMessage::Poll);
+    }
+}
+
+fn run_worker<D: Document>(
+    mut doc: D,
+    sender: Sender<WorkerMessage>,
+    messages: Receiver<WorkerMessage>,
+    frames: Sender<Frame>,
+    waker: Waker,
+) {
+    let poll_waker = Waker::from(Arc::new(WorkerWaker(sender)));
+    let frame_clock = Instant::now();
+    while let Ok(message) = messages.recv() {
+        let mut render = None;
+        let mut changed = false;
+        for message in std::iter::once(message).chain(messages.try_iter()) {
+            match message {
+                WorkerMessage::Mutate(mutation) => mutation(&mut doc),
+                WorkerMessage::Event(event) => doc.handle_ui_event(event),
+                WorkerMessage::SetViewport(viewport) => doc.set_viewport(viewport),
+                WorkerMessage::Render(viewport) => render = Some(viewport),
+                WorkerMessage::Resource(resource) => {
+                    doc.load_resource(resource);
+                    changed = true;
+                }
+                WorkerMessage::Poll => {}
+                WorkerMessage::Stop => return,
+            }
+        }
+
+        // Mutations and events can start async work too, so the document is polled after every
+        // batch of messages, as a window polls its document
+        let mut cx = Context::from_waker(&poll_waker);
+        let input_events = doc.input_provider.poll_events(&mut cx);
+        changed |= !input_events.is_empty();
+        for event in input_events {
+            doc.handle_ui_event(event);
+        }
+        changed |= doc.poll_scripts(&mut cx);
+        changed |= doc.poll(Some(cx));
+
+        let Some(viewport) = render else {
+            // Ask the shell for a frame showing the changes
+            if changed {
+                waker.wake_by_ref();
+            }
+            continue;
+        };
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::info_span!("frame", frame_id = crate::trace::next_frame_id()).entered();
+        let tick_start = Instant::now();
+        doc.tick_animation_frame(tick_start.duration_since(frame_clock));
+        doc.resolve();
+        let mut display_list = DisplayList::new();
+        BlitzPainter.render(&mut display_list, &doc, viewport);
+        doc.finish_animation_frame(tick_start.elapsed());
+        let frame = Frame {
+            display_list,
+            viewport,
+        };
+        if frames.send(frame).is_err() {
+            // The shell dropped the document
+            return;
+        }
+        waker.wake_by_ref();
+    }
+}
+
+struct WakeOnDrop(Waker);
+
+impl Drop for WakeOnDrop {
+    fn drop(&mut self) {
+        self.0.wake_by_ref();
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::ops::{Deref, DerefMut};
+    use std::sync::Arc;
+    use std::sync::mpsc;
+    use std::task::Waker;
+
+    use blitz_dom::net::Resource;
+    use blitz_dom::node::{RasterImageData, SpecialElementData};
+    use blitz_dom::util::ImageType;
+    use blitz_dom::{BaseDocument, Document, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+
+    use super::{IsolatedDocument, IsolationError};
+
+    struct TestDocument(BaseDocument);
+
+    impl Deref for TestDocument {
+        type Target = BaseDocument;
+        fn deref(&self) -> &BaseDocument {
+            &self.0
+        }
+    }
+
+    impl DerefMut for TestDocument {
+        fn deref_mut(&mut self) -> &mut BaseDocument {
+            &mut self.0
+        }
+    }
+
+    impl Document for TestDocument {
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn worker_panics_are_contained() {
+        let mut doc = IsolatedDocument::spawn(
+            |_| -> TestDocument { panic!("layout exploded") },
+            Waker::noop().clone(),
+        );
+        let error = loop {
+            match doc.poll_frame() {
+                Ok(_) => std::thread::yield_now(),
+                Err(error) => break error,
+            }
+        };
+        assert!(matches!(error, IsolationError::Crashed(message) if message == "layout exploded"));
+        assert!(!doc.is_running());
+    }
+
+    #[test]
+    fn fetched_resources_are_loaded_on_the_worker() {
+        let (created, creation) = mpsc::channel();
+        let doc = IsolatedDocument::spawn(
+            move |net_callback| {
+                let mut doc = BaseDocument::new(DocumentConfig::for_testing()).unwrap();
+                let mut mutr = doc.mutate();
+                let name = QualName::new(None, ns!(html), LocalName::from("img"));
+                let img = mutr.create_element(name, Vec::new(), QuirksMode::NoQuirks);
+                mutr.append_children(0, &[img]);
+                drop(mutr);
+                created.send((net_callback, img)).unwrap();
+                TestDocument(doc)
+            },
+            Waker::noop().clone(),
+        );
+        let (net_callback, img) = creation.recv().unwrap();
+
+        let image = RasterImageData::new(1, 1, Arc::new(vec![255; 4]));
+        net_callback.call(0, Ok(Resource::Image(img, ImageType::Image, image)));
+        // Messages are handled in order, so the image is loaded before this runs
+        let (loaded, load) = mpsc::channel();
+        doc.mutate(move |doc| {
+            let element = doc.get_node(img).unwrap().element_data().unwrap();
+            let is_image = matches!(element.special_data, SpecialElementData::Image(_));
+            loaded.send(is_image).unwrap();
+        });
+        assert!(load.recv().unwrap());
+    }
+}
@@ -2,15 +2,34 @@
 
 //! Event loop, windowing and system integration.
 //!
+//! ## Mobile
+//! On Android and iOS, windows' surfaces are released when apps are backgrounded and recreated
+//! when they're foregrounded, the soft keyboard is shown while a text input is focused, and
+//! touches are handled as pointer events. Android apps must call `set_android_app` with the
+//! `AndroidApp` passed to their `android_main` before creating the event loop.
+//!
 //! ## Feature flags
 //!  - `default`: Enables the features listed below.
 //!  - `accessibility`: Enables [`accesskit`] accessibility support.
 //!  - `hot-reload`: Enables hot-reloading of Dioxus RSX.
-//!  - `tracing`: Enables tracing support.
+//!  - `tracing`: Records spans for each frame and the style, layout and paint passes within it,
+//!    which [`ChromeTraceLayer`] can export for `chrome://tracing`.
+//!  - `vello`, `vello_cpu`, `tinyskia`: Renderer backends which [`FallbackRenderer`] can use.
+//!  - `native-context-menu`: Shows context menus as native menus on Windows and macOS.
+//!  - `net`: Saves links with a `download` attribute with [`BlitzApplication::downloads`].
 
 mod application;
+mod context_menu;
 mod convert_events;
 mod event;
+mod idle;
+#[cfg(not(target_arch = "wasm32"))]
+mod isolated;
+#[cfg(any(feature = "vello", feature = "vello_cpu", feature = "tinyskia"))]
+mod renderer;
+mod system_preferences;
+#[cfg(feature = "tracing")]
+mod trace;
 mod window;
 
 #[cfg(feature = "accessibility")]
@@ -19,13 +38,27 @@ mod accessibility;
 use std::sync::Arc;
 
 use blitz_dom::net::Resource;
+use blitz_traits::navigation::{NavigationOptions, NavigationProvider};
 use blitz_traits::net::NetCallback;
-use blitz_traits::shell::ShellProvider;
+use blitz_traits::shell::{ContextMenuRequest, ShellProvider, WindowIcon};
 pub use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
 pub use winit::window::{CursorIcon, Window};
 
 pub use crate::application::BlitzApplication;
-pub use crate::event::BlitzShellEvent;
+pub use crate::context_menu::{ContextMenuItem, ContextMenuItems, ContextMenuSelection};
+#[cfg(all(feature = "native-context-menu", any(target_os = "windows", target_os = "macos")))]
+pub use crate::context_menu::set_menu_event_handler;
+pub use crate::event::{BlitzShellEvent, create_waker};
+pub use crate::idle::{IdleOutcome, IdleTasks, run_idle_periods};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::isolated::{DocumentMutation, Frame, IsolatedDocument, IsolationError};
+#[cfg(any(feature = "vello", feature = "vello_cpu", feature = "tinyskia"))]
+pub use crate::renderer::{
+    FallbackRenderer, NoRendererError, RENDERER_ENV_VAR, RendererBackend, RendererConfig,
+    RendererFailure,
+};
+#[cfg(feature = "tracing")]
+pub use crate::trace::{ChromeTraceGuard, ChromeTraceLayer, next_frame_id};
 pub use crate::window::{View, WindowConfig};
 
 #[derive(Default)]
@@ -90,14 +123,44 @@ impl NetCallback<Resource> for BlitzShellNetCallback {
     }
 }
 
+/// A NavigationProvider that sends navigations to our winit event loop, as
+/// [`BlitzShellEvent::Navigate`]
+pub struct BlitzShellNavigationProvider(EventLoopProxy<BlitzShellEvent>);
+
+impl BlitzShellNavigationProvider {
+    pub fn new(proxy: EventLoopProxy<BlitzShellEvent>) -> Self {
+        Self(proxy)
+    }
+
+    pub fn shared(proxy: EventLoopProxy<BlitzShellEvent>) -> Arc<dyn NavigationProvider> {
+        Arc::new(Self(proxy))
+    }
+}
+impl NavigationProvider for BlitzShellNavigationProvider {
+    fn navigate_to(&self, options: NavigationOptions) {
+        let _ = self.0.send_event(BlitzShellEvent::Navigate(Box::new(options)));
+    }
+}
+
 pub struct BlitzShellProvider {
     window: Arc<Window>,
+    proxy: Option<EventLoopProxy<BlitzShellEvent>>,
 }
 
 impl BlitzShellProvider {
     /// Create a new BlitzShellProvider with window context
     pub fn new(window: Arc<Window>) -> Self {
-        Self { window }
+        Self {
+            window,
+            proxy: None,
+        }
+    }
+
+    /// Send requests which the window's [`View`] handles (like showing context menus) through
+    /// `proxy`. Without one they're ignored.
+    pub fn with_proxy(mut self, proxy: EventLoopProxy<BlitzShellEvent>) -> Self {
+        self.proxy = Some(proxy);
+        self
     }
 }
 
@@ -111,6 +174,30 @@ impl ShellProvider for BlitzShellProvider {
     fn set_window_title(&self, title: String) {
         self.window.set_title(&title);
     }
+    fn set_window_icon(&self, icon: Option<WindowIcon>) {
+        let icon = icon.and_then(|icon| {
+            let rgba = Arc::unwrap_or_clone(icon.rgba);
+            winit::window::Icon::from_rgba(rgba, icon.width, icon.height).ok()
+        });
+        self.window.set_window_icon(icon);
+    }
+    /// Only Windows lets the title bar be colored
+    fn set_theme_color(&self, color: Option<[u8; 4]>) {
+        #[cfg(target_os = "windows")]
+        {
+            use winit::platform::windows::{Color, WindowExtWindows as _};
+            let color = color.map(|[r, g, b, _]| Color::from_rgb(r, g, b));
+            self.window.set_title_background_color(color);
+        }
+        #[cfg(not(target_os = "windows"))]
+        let _ = color;
+    }
+    fn show_context_menu(&self, request: ContextMenuRequest) {
+        if let Some(proxy) = &self.proxy {
+            let window_id = self.window.id();
+            let _ = proxy.send_event(BlitzShellEvent::ContextMenu { window_id, request });
+        }
+    }
 
     #[cfg(all(
         feature = "clipboard",
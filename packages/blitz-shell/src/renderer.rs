@@ -0,0 +1,350 @@
+//! Runtime selection of the [`WindowRenderer`] backend
+//!
+//! [`FallbackRenderer`] tries each backend of its [`RendererConfig`] in order until one resumes,
+//! so that machines without a usable GPU adapter fall back to CPU rendering instead of panicking.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use anyrender::{
+    DynScenePainter, RenderQuality, ResumeError, SurfaceColorSpace, WindowHandle, WindowRenderer,
+};
+use peniko::kurbo::Rect;
+
+/// Environment variable which forces a backend, e.g. `BLITZ_RENDERER=vello_cpu`.
+/// `auto` (or leaving it unset) tries every compiled-in backend.
+pub const RENDERER_ENV_VAR: &str = "BLITZ_RENDERER";
+
+/// A [`WindowRenderer`] backend [`FallbackRenderer`] can use
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RendererBackend {
+    /// GPU rendering with vello (`vello` feature)
+    Vello,
+    /// CPU rendering with vello_cpu (`vello_cpu` feature)
+    VelloCpu,
+    /// CPU rendering with tiny-skia (`tinyskia` feature)
+    TinySkia,
+}
+
+impl RendererBackend {
+    /// Every backend, from most to least preferred
+    pub const ALL: [RendererBackend; 3] = [Self::Vello, Self::VelloCpu, Self::TinySkia];
+
+    /// The name used for the backend by [`RENDERER_ENV_VAR`]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Vello => "vello",
+            Self::VelloCpu => "vello_cpu",
+            Self::TinySkia => "tinyskia",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the backend was compiled in
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Vello => cfg!(feature = "vello"),
+            Self::VelloCpu => cfg!(feature = "vello_cpu"),
+            Self::TinySkia => cfg!(feature = "tinyskia"),
+        }
+    }
+}
+
+impl fmt::Display for RendererBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Which backends [`FallbackRenderer`] tries, in order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RendererConfig {
+    pub backends: Vec<RendererBackend>,
+}
+
+impl Default for RendererConfig {
+    /// Every compiled-in backend, GPU first
+    fn default() -> Self {
+        Self {
+            backends: RendererBackend::ALL
+                .into_iter()
+                .filter(|backend| backend.is_available())
+                .collect(),
+        }
+    }
+}
+
+impl RendererConfig {
+    /// Only use `backend`, failing rather than falling back if it can't be resumed
+    pub fn forced(backend: RendererBackend) -> Self {
+        Self {
+            backends: vec![backend],
+        }
+    }
+
+    /// The default config, unless a backend is forced with [`RENDERER_ENV_VAR`]
+    pub fn from_env() -> Self {
+        let Ok(name) = std::env::var(RENDERER_ENV_VAR) else {
+            return Self::default();
+        };
+        if name.is_empty() || name.eq_ignore_ascii_case("auto") {
+            return Self::default();
+        }
+        match RendererBackend::from_name(&name) {
+            Some(backend) => Self::forced(backend),
+            None => {
+                eprintln!("Unknown renderer {name:?} in {RENDERER_ENV_VAR}, ignoring it");
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Why a backend couldn't be used
+#[derive(Clone, Debug)]
+pub struct RendererFailure {
+    pub backend: RendererBackend,
+    pub error: String,
+}
+
+/// Returned from [`FallbackRenderer::try_resume`] when none of the backends could be resumed
+#[derive(Clone, Debug)]
+pub struct NoRendererError {
+    pub failures: Vec<RendererFailure>,
+}
+
+impl fmt::Display for NoRendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.failures.is_empty() {
+            return f.write_str("no renderer backends are configured");
+        }
+        f.write_str("no renderer backend could be resumed")?;
+        for failure in &self.failures {
+            write!(f, "\n  {}: {}", failure.backend, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for NoRendererError {}
+
+enum ActiveRenderer {
+    #[cfg(feature = "vello")]
+    Vello(anyrender_vello::VelloWindowRenderer),
+    #[cfg(feature = "vello_cpu")]
+    VelloCpu(anyrender_vello_cpu::VelloCpuWindowRenderer),
+    #[cfg(feature = "tinyskia")]
+    TinySkia(anyrender_tinyskia::TinySkiaWindowRenderer),
+}
+
+/// Evaluate `$body` with `$renderer` bound to the backend's renderer
+macro_rules! dispatch {
+    ($active:expr, $renderer:ident => $body:expr) => {
+        match $active {
+            #[cfg(feature = "vello")]
+            ActiveRenderer::Vello($renderer) => $body,
+            #[cfg(feature = "vello_cpu")]
+            ActiveRenderer::VelloCpu($renderer) => $body,
+            #[cfg(feature = "tinyskia")]
+            ActiveRenderer::TinySkia($renderer) => $body,
+        }
+    };
+}
+
+impl ActiveRenderer {
+    fn new(backend: RendererBackend) -> Option<Self> {
+        match backend {
+            #[cfg(feature = "vello")]
+            RendererBackend::Vello => {
+                Some(Self::Vello(anyrender_vello::VelloWindowRenderer::new()))
+            }
+            #[cfg(feature = "vello_cpu")]
+            RendererBackend::VelloCpu => Some(Self::VelloCpu(
+                anyrender_vello_cpu::VelloCpuWindowRenderer::new(),
+            )),
+            #[cfg(feature = "tinyskia")]
+            RendererBackend::TinySkia => Some(Self::TinySkia(
+                anyrender_tinyskia::TinySkiaWindowRenderer::new(),
+            )),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    fn backend(&self) -> RendererBackend {
+        match self {
+            #[cfg(feature = "vello")]
+            Self::Vello(_) => RendererBackend::Vello,
+            #[cfg(feature = "vello_cpu")]
+            Self::VelloCpu(_) => RendererBackend::VelloCpu,
+            #[cfg(feature = "tinyskia")]
+            Self::TinySkia(_) => RendererBackend::TinySkia,
+        }
+    }
+}
+
+/// A [`WindowRenderer`] which picks its backend when resumed, falling back to the next backend
+/// of its [`RendererConfig`] when one fails (e.g. when no GPU adapter is available)
+pub struct FallbackRenderer {
+    config: RendererConfig,
+    active: Option<ActiveRenderer>,
+    failures: Vec<RendererFailure>,
+    quality: RenderQuality,
+}
+
+impl Default for FallbackRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FallbackRenderer {
+    /// Uses [`RendererConfig::from_env`]
+    pub fn new() -> Self {
+        Self::with_config(RendererConfig::from_env())
+    }
+
+    pub fn with_config(config: RendererConfig) -> Self {
+        Self {
+            config,
+            active: None,
+            failures: Vec::new(),
+            quality: RenderQuality::default(),
+        }
+    }
+
+    pub fn config(&self) -> &RendererConfig {
+        &self.config
+    }
+
+    /// The backend in use, once resumed
+    pub fn active_backend(&self) -> Option<RendererBackend> {
+        self.active.as_ref().map(ActiveRenderer::backend)
+    }
+
+    /// The backends which failed to resume before the active one was picked
+    pub fn failures(&self) -> &[RendererFailure] {
+        &self.failures
+    }
+}
+
+impl WindowRenderer for FallbackRenderer {
+    type ScenePainter<'a>
+        = DynScenePainter<'a>
+    where
+        Self: 'a;
+
+    fn resume(&mut self, window: Arc<dyn WindowHandle>, width: u32, height: u32) {
+        if let Err(err) = self.try_resume(window, width, height) {
+            panic!("{err}");
+        }
+    }
+
+    fn try_resume(
+        &mut self,
+        window: Arc<dyn WindowHandle>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ResumeError> {
+        self.failures.clear();
+        let mut previous = self.active.take();
+        for &backend in &self.config.backends {
+            // Keep the previous renderer (and its caches) when resuming after a suspend
+            let renderer = previous
+                .take_if(|renderer| renderer.backend() == backend)
+                .or_else(|| ActiveRenderer::new(backend));
+            let Some(mut renderer) = renderer else {
+                self.failures.push(RendererFailure {
+                    backend,
+                    error: format!("not compiled in (enable the `{backend}` feature)"),
+                });
+                continue;
+            };
+
+            match dispatch!(&mut renderer, r => r.try_resume(window.clone(), width, height)) {
+                Ok(()) => {
+                    dispatch!(&mut renderer, r => r.set_quality(self.quality));
+                    self.active = Some(renderer);
+                    return Ok(());
+                }
+                Err(err) => {
+                    eprintln!("Failed to resume the {backend} renderer: {err}");
+                    self.failures.push(RendererFailure {
+                        backend,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+
+        Err(Box::new(NoRendererError {
+            failures: self.failures.clone(),
+        }))
+    }
+
+    fn suspend(&mut self) {
+        if let Some(active) = &mut self.active {
+            dispatch!(active, renderer => renderer.suspend());
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+            .as_ref()
+            .is_some_and(|active| dispatch!(active, renderer => renderer.is_active()))
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) {
+        if let Some(active) = &mut self.active {
+            dispatch!(active, renderer => renderer.set_size(width, height));
+        }
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+        let Some(active) = &mut self.active else {
+            return;
+        };
+        dispatch!(active, renderer => {
+            renderer.render(|scene| draw_fn(&mut DynScenePainter::new(scene)))
+        });
+    }
+
+    fn initialize_text_system(&self, doc: &dyn Any) -> Result<(), String> {
+        match &self.active {
+            Some(active) => dispatch!(active, renderer => renderer.initialize_text_system(doc)),
+            None => Ok(()),
+        }
+    }
+
+    fn color_space(&self) -> SurfaceColorSpace {
+        match &self.active {
+            Some(active) => dispatch!(active, renderer => renderer.color_space()),
+            None => SurfaceColorSpace::Srgb,
+        }
+    }
+
+    fn set_damage(&mut self, damage: &[Rect]) {
+        if let Some(active) = &mut self.active {
+            dispatch!(active, renderer => renderer.set_damage(damage));
+        }
+    }
+
+    fn trim_caches(&mut self) {
+        if let Some(active) = &mut self.active {
+            dispatch!(active, renderer => renderer.trim_caches());
+        }
+    }
+
+    fn set_quality(&mut self, quality: RenderQuality) {
+        self.quality = quality;
+        if let Some(active) = &mut self.active {
+            dispatch!(active, renderer => renderer.set_quality(quality));
+        }
+    }
+}
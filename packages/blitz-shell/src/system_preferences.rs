@@ -0,0 +1,78 @@
+//! User preferences which winit doesn't report
+//!
+//! These are read from the desktop's settings, and read again whenever a window is focused (there
+//! is no notification when they change). Reading them runs a command, so it's done on a background
+//! thread, which sends them to the window's event loop.
+
+use winit::event_loop::EventLoopProxy;
+use winit::window::WindowId;
+
+use crate::event::BlitzShellEvent;
+
+/// Read the preferences on a background thread, and send them to the window with `window_id`
+pub(crate) fn read_in_background(proxy: &EventLoopProxy<BlitzShellEvent>, window_id: WindowId) {
+    let proxy = proxy.clone();
+    let spawned = std::thread::Builder::new()
+        .name("system-preferences".to_string())
+        .spawn(move || {
+            let _ = proxy.send_event(BlitzShellEvent::SystemPreferences {
+                window_id,
+                prefers_reduced_motion: prefers_reduced_motion(),
+            });
+        });
+    if let Err(err) = spawned {
+        eprintln!("Failed to read the system preferences: {err}");
+    }
+}
+
+/// Whether the user asked the system to minimize non-essential motion
+#[cfg(target_os = "macos")]
+fn prefers_reduced_motion() -> bool {
+    read_setting("defaults", &["read", "com.apple.universalaccess", "reduceMotion"])
+        .is_some_and(|value| value == "1")
+}
+
+/// Whether the user asked the system to minimize non-essential motion
+#[cfg(target_os = "windows")]
+fn prefers_reduced_motion() -> bool {
+    // "Animate controls and elements inside windows" in the system's performance options
+    let key = r"HKCU\Control Panel\Desktop\WindowMetrics";
+    read_setting("reg", &["query", key, "/v", "MinAnimate"])
+        .is_some_and(|value| value.ends_with("0x0"))
+}
+
+/// Whether the user asked the system to minimize non-essential motion
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+fn prefers_reduced_motion() -> bool {
+    let schema = "org.gnome.desktop.interface";
+    read_setting("gsettings", &["get", schema, "enable-animations"])
+        .is_some_and(|value| value == "false")
+}
+
+/// Whether the user asked the system to minimize non-essential motion
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd"
+)))]
+fn prefers_reduced_motion() -> bool {
+    false
+}
+
+/// The trimmed output of a command which reads a setting, if it succeeds
+#[cfg(any(
+    target_os = "macos",
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd"
+))]
+fn read_setting(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,385 @@
+//! Exporting Blitz's tracing spans as a Chrome trace
+//!
+//! Each frame a window draws is recorded as a `frame` span (with a process-wide `frame_id`),
+//! containing spans for the passes it runs: `resolve`, and within it `style`, `construct_boxes`
+//! and `layout`, then `paint`. blitz-net records each resource it fetches as a `fetch` span.
+//! [`ChromeTraceLayer`] writes these spans in the Trace Event Format, which can be loaded into
+//! `chrome://tracing` or <https://ui.perfetto.dev> to profile a frame end to end.
+//!
+//! ```no_run
+//! use tracing_subscriber::prelude::*;
+//!
+//! let (layer, _guard) = blitz_shell::ChromeTraceLayer::create("trace.json").unwrap();
+//! tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer)).unwrap();
+//! // Run the app. The trace is finished when `_guard` is dropped.
+//! ```
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+// `std::time::Instant` isn't available on wasm32
+use web_time::Instant;
+
+static NEXT_FRAME_ID: AtomicU64 = AtomicU64::new(1);
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A new id for the `frame_id` field of a `frame` span, unique within the process
+pub fn next_frame_id() -> u64 {
+    NEXT_FRAME_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A small number identifying the current thread, for the `tid` of trace events
+fn thread_id() -> u64 {
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+struct TraceWriter {
+    out: BufWriter<Box<dyn Write + Send>>,
+    /// Whether an event has been written, so the next one needs a separating comma
+    started: bool,
+    finished: bool,
+    /// Threads whose name has been written
+    named_threads: HashSet<u64>,
+}
+
+impl TraceWriter {
+    fn write_event(&mut self, event: &str) {
+        if self.finished {
+            return;
+        }
+        let separator = if self.started { ",\n" } else { "" };
+        self.started = true;
+        // Failing to write the trace shouldn't take the app down
+        let _ = write!(self.out, "{separator}{event}");
+    }
+
+    fn name_thread(&mut self, tid: u64) {
+        if !self.named_threads.insert(tid) {
+            return;
+        }
+        let thread = std::thread::current();
+        let name = thread.name().map_or_else(|| format!("thread {tid}"), str::to_string);
+        let mut event = format!(r#"{{"ph":"M","name":"thread_name","pid":1,"tid":{tid},"args":"#);
+        event.push_str(r#"{"name":"#);
+        push_json_string(&mut event, &name);
+        event.push_str("}}");
+        self.write_event(&event);
+    }
+
+    fn finish(&mut self) {
+        if !self.finished {
+            let _ = self.out.write_all(b"\n]\n");
+            let _ = self.out.flush();
+            self.finished = true;
+        }
+    }
+}
+
+/// A [`Layer`] which writes spans and events in the Chrome trace format
+///
+/// Each time a span is entered and exited, a complete (`"X"`) event is written with the span's
+/// fields as its arguments, so spans instrumenting futures (like `fetch`) appear once for each
+/// time they're polled. Events are written as instant (`"i"`) events.
+pub struct ChromeTraceLayer {
+    start: Instant,
+    writer: Arc<Mutex<TraceWriter>>,
+}
+
+/// Finishes the trace when dropped. Events recorded afterwards are discarded.
+pub struct ChromeTraceGuard {
+    writer: Arc<Mutex<TraceWriter>>,
+}
+
+impl Drop for ChromeTraceGuard {
+    fn drop(&mut self) {
+        lock(&self.writer).finish();
+    }
+}
+
+impl ChromeTraceLayer {
+    /// A layer writing the trace to a new file at `path`
+    pub fn create(path: impl AsRef<Path>) -> io::Result<(Self, ChromeTraceGuard)> {
+        Ok(Self::with_writer(File::create(path)?))
+    }
+
+    /// A layer writing the trace to `out`
+    pub fn with_writer(out: impl Write + Send + 'static) -> (Self, ChromeTraceGuard) {
+        let mut out = BufWriter::new(Box::new(out) as Box<dyn Write + Send>);
+        let _ = out.write_all(b"[\n");
+        let writer = Arc::new(Mutex::new(TraceWriter {
+            out,
+            started: false,
+            finished: false,
+            named_threads: HashSet::new(),
+        }));
+        let guard = ChromeTraceGuard {
+            writer: Arc::clone(&writer),
+        };
+        let layer = Self {
+            start: Instant::now(),
+            writer,
+        };
+        (layer, guard)
+    }
+
+    /// Microseconds since the layer was created
+    fn timestamp(&self, instant: Instant) -> f64 {
+        instant.duration_since(self.start).as_secs_f64() * 1_000_000.0
+    }
+
+    fn write_event(&self, event: &str, tid: u64) {
+        let mut writer = lock(&self.writer);
+        writer.name_thread(tid);
+        writer.write_event(event);
+    }
+}
+
+/// A span's fields, as the members of a JSON object
+struct SpanFields(String);
+
+/// When a span was entered, on each thread it's currently entered on
+struct SpanEntries(Vec<(u64, Instant)>);
+
+impl<S> Layer<S> for ChromeTraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = JsonFields::default();
+        attrs.record(&mut fields);
+        let mut extensions = span.extensions_mut();
+        extensions.insert(SpanFields(fields.members));
+        extensions.insert(SpanEntries(Vec::new()));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(SpanFields(members)) = extensions.get_mut::<SpanFields>() else {
+            return;
+        };
+        let mut fields = JsonFields {
+            members: std::mem::take(members),
+            message: None,
+        };
+        values.record(&mut fields);
+        *members = fields.members;
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(SpanEntries(entries)) = span.extensions_mut().get_mut::<SpanEntries>() {
+            entries.push((thread_id(), Instant::now()));
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let end = Instant::now();
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let tid = thread_id();
+        let mut extensions = span.extensions_mut();
+        let Some(SpanEntries(entries)) = extensions.get_mut::<SpanEntries>() else {
+            return;
+        };
+        let Some(index) = entries.iter().rposition(|&(thread, _)| thread == tid) else {
+            return;
+        };
+        let (_, start) = entries.remove(index);
+        let members = extensions
+            .get::<SpanFields>()
+            .map_or("", |SpanFields(members)| members.as_str());
+
+        let mut event = String::from(r#"{"ph":"X","name":"#);
+        push_json_string(&mut event, span.name());
+        event.push_str(r#","cat":"#);
+        push_json_string(&mut event, span.metadata().target());
+        let ts = self.timestamp(start);
+        let dur = end.duration_since(start).as_secs_f64() * 1_000_000.0;
+        let _ = write!(event, r#","ts":{ts:.3},"dur":{dur:.3},"pid":1,"tid":{tid}"#);
+        let _ = write!(event, r#","args":{{{members}}}}}"#);
+        drop(extensions);
+        self.write_event(&event, tid);
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let now = Instant::now();
+        let tid = thread_id();
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        let name = fields.message.as_deref().unwrap_or(metadata.name());
+
+        let mut json = String::from(r#"{"ph":"i","s":"t","name":"#);
+        push_json_string(&mut json, name);
+        json.push_str(r#","cat":"#);
+        push_json_string(&mut json, metadata.target());
+        let ts = self.timestamp(now);
+        let _ = write!(json, r#","ts":{ts:.3},"pid":1,"tid":{tid}"#);
+        let _ = write!(json, r#","args":{{"level":"{}""#, metadata.level());
+        if !fields.members.is_empty() {
+            json.push(',');
+            json.push_str(&fields.members);
+        }
+        json.push_str("}}");
+        self.write_event(&json, tid);
+    }
+}
+
+/// Records fields as the members of a JSON object, apart from an event's message
+#[derive(Default)]
+struct JsonFields {
+    members: String,
+    message: Option<String>,
+}
+
+impl JsonFields {
+    fn push_member(&mut self, field: &Field, value: impl FnOnce(&mut String)) {
+        if !self.members.is_empty() {
+            self.members.push(',');
+        }
+        push_json_string(&mut self.members, field.name());
+        self.members.push(':');
+        value(&mut self.members);
+    }
+}
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if value.is_finite() {
+            self.push_member(field, |out| {
+                let _ = write!(out, "{value}");
+            });
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push_member(field, |out| {
+            let _ = write!(out, "{value}");
+        });
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push_member(field, |out| {
+            let _ = write!(out, "{value}");
+        });
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push_member(field, |out| {
+            let _ = write!(out, "{value}");
+        });
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.push_member(field, |out| push_json_string(out, value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{value:?}");
+        self.record_str(field, &value);
+    }
+}
+
+fn push_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn lock(writer: &Mutex<TraceWriter>) -> MutexGuard<'_, TraceWriter> {
+    writer.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::prelude::*;
+
+    use super::ChromeTraceLayer;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn spans_are_written_as_complete_events() {
+        let buffer = SharedBuffer::default();
+        let (layer, guard) = ChromeTraceLayer::with_writer(buffer.clone());
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _frame = tracing::info_span!("frame", frame_id = 7).entered();
+            let _paint = tracing::info_span!("paint", label = "a \"quoted\" name").entered();
+            tracing::info!(nodes = 3, "painted");
+        });
+        drop(guard);
+
+        let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(trace.starts_with("[\n") && trace.ends_with("\n]\n"));
+        assert!(trace.contains(r#""ph":"X","name":"frame""#));
+        assert!(trace.contains(r#""args":{"frame_id":7}"#));
+        assert!(trace.contains(r#""args":{"label":"a \"quoted\" name"}"#));
+        assert!(trace.contains(r#""ph":"i","s":"t","name":"painted""#));
+        assert!(trace.contains(r#""level":"INFO","nodes":3"#));
+        // Spans are written when they're exited, so the inner span comes first
+        assert!(trace.find(r#""name":"paint""#) < trace.find(r#""name":"frame""#));
+    }
+}
@@ -1,30 +1,44 @@
 use std::sync::Arc;
 use std::task::Waker;
+use std::time::Duration;
 
-use anyrender::WindowRenderer;
-use blitz_dom::Document;
-use blitz_paint::paint_scene;
+use anyrender::{AdaptiveQualityConfig, QualityController, SurfaceColorSpace, WindowRenderer};
+use blitz_dom::{BaseDocument, Document, DocumentIcon, DocumentLifecycle, DocumentVisibility};
+use blitz_paint::{BlitzPainter, DamageTracker};
 use blitz_traits::events::{BlitzMouseButtonEvent, MouseEventButton, MouseEventButtons, UiEvent};
-use blitz_traits::shell::Viewport;
-use winit::event::{ElementState, MouseButton};
+use blitz_traits::render::{DocumentRenderer, OutputColorSpace, RenderViewport};
+use blitz_traits::shell::{ContextMenuRequest, Viewport};
+use peniko::kurbo::{Point, Rect, Vec2};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, MouseButton, TouchPhase};
 use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
 use winit::keyboard::PhysicalKey;
 use winit::window::{Theme, WindowAttributes, WindowId};
 use winit::{event::Modifiers, event::WindowEvent, keyboard::KeyCode, window::Window};
+// `std::time::Instant` isn't available on wasm32
+use web_time::Instant;
 
 use crate::BlitzShellProvider;
 #[cfg(feature = "accessibility")]
 use crate::accessibility::AccessibilityState;
+use crate::context_menu::{
+    ContextMenuItem, ContextMenuItems, ContextMenuSelection, context_menu_items, show_context_menu,
+};
 use crate::convert_events::{
     color_scheme_to_theme, theme_to_color_scheme, winit_ime_to_blitz, winit_key_event_to_blitz,
-    winit_modifiers_to_kbt_modifiers,
+    winit_modifiers_to_kbt_modifiers, winit_touch_to_blitz,
 };
-use crate::event::{BlitzShellEvent, create_waker};
+use crate::event::{BlitzShellEvent, create_redraw_waker, create_waker};
+use crate::idle::IdleTasks;
+use crate::system_preferences;
 
-pub struct WindowConfig<Rend: WindowRenderer> {
+pub struct WindowConfig<Rend: WindowRenderer, Painter = BlitzPainter> {
     doc: Box<dyn Document>,
     attributes: WindowAttributes,
     renderer: Rend,
+    painter: Painter,
+    adaptive_quality: Option<AdaptiveQualityConfig>,
+    context_menu_items: Option<ContextMenuItems>,
 }
 
 impl<Rend: WindowRenderer> WindowConfig<Rend> {
@@ -41,15 +55,69 @@ impl<Rend: WindowRenderer> WindowConfig<Rend> {
             doc,
             attributes,
             renderer,
+            painter: BlitzPainter,
+            adaptive_quality: None,
+            context_menu_items: None,
         }
     }
 }
 
-pub struct View<Rend: WindowRenderer> {
+impl<Rend: WindowRenderer, Painter> WindowConfig<Rend, Painter> {
+    /// Replace the [`DocumentRenderer`] used to paint the document into the renderer's scene.
+    ///
+    /// Defaults to [`BlitzPainter`].
+    pub fn with_document_renderer<P>(self, painter: P) -> WindowConfig<Rend, P>
+    where
+        P: for<'a> DocumentRenderer<BaseDocument, Rend::ScenePainter<'a>>,
+    {
+        WindowConfig {
+            doc: self.doc,
+            attributes: self.attributes,
+            renderer: self.renderer,
+            painter,
+            adaptive_quality: self.adaptive_quality,
+            context_menu_items: self.context_menu_items,
+        }
+    }
+
+    /// Automatically lower the render quality (see [`anyrender::RenderQuality`]) when frames take
+    /// longer than the configured budget, and raise it again once they fit comfortably.
+    ///
+    /// Only has an effect with renderers that implement [`WindowRenderer::set_quality`].
+    pub fn with_adaptive_quality(mut self, config: AdaptiveQualityConfig) -> Self {
+        self.adaptive_quality = Some(config);
+        self
+    }
+
+    /// Add the items `build` returns to the window's context menus, after Blitz's own. Choosing
+    /// one sends a [`ContextMenuSelection`] to the event loop as an embedder event.
+    pub fn with_context_menu_items(
+        mut self,
+        build: impl Fn(&ContextMenuRequest) -> Vec<ContextMenuItem> + Send + Sync + 'static,
+    ) -> Self {
+        self.context_menu_items = Some(Arc::new(build));
+        self
+    }
+}
+
+pub struct View<Rend: WindowRenderer, Painter = BlitzPainter> {
     pub doc: Box<dyn Document>,
 
     pub renderer: Rend,
+    /// Paints the document into the renderer's scene
+    pub painter: Painter,
+    /// Adjusts the renderer's quality based on frame times (if enabled)
+    pub quality_controller: Option<QualityController>,
+    /// The origin of the timestamps delivered to the document's animation frame callbacks
+    pub frame_clock: Instant,
+    /// Low-priority work run in idle periods, see [`crate::idle`]
+    pub idle_tasks: IdleTasks,
+    /// Finds the part of each frame which changed, so that renderers can redraw just that part.
+    /// It assumes that nodes are painted from their own state, as [`BlitzPainter`] paints them.
+    pub damage: DamageTracker,
     pub waker: Option<Waker>,
+    /// Redraws the window once the SVGs rasterized in the background while painting are ready
+    pub redraw_waker: Waker,
 
     pub event_loop_proxy: EventLoopProxy<BlitzShellEvent>,
     pub window: Arc<Window>,
@@ -60,17 +128,27 @@ pub struct View<Rend: WindowRenderer> {
     pub keyboard_modifiers: Modifiers,
     pub buttons: MouseEventButtons,
     pub mouse_pos: (f32, f32),
+    /// The touch which started while no others were down, which compatibility mouse events are
+    /// made for
+    pub primary_touch: Option<u64>,
     /// Whether IME is currently enabled
     pub ime_enabled: bool,
+    /// Builds the embedder's context menu items
+    pub context_menu_items: Option<ContextMenuItems>,
+    /// What the context menu which was shown last was shown for
+    pub context_menu: Option<ContextMenuRequest>,
+    /// The document's SVG icon, once it has been rasterized for the window
+    #[cfg(feature = "svg")]
+    pub svg_icon: Option<DocumentIcon>,
 
     #[cfg(feature = "accessibility")]
     /// Accessibility adapter for `accesskit`.
     pub accessibility: AccessibilityState,
 }
 
-impl<Rend: WindowRenderer> View<Rend> {
+impl<Rend: WindowRenderer, Painter> View<Rend, Painter> {
     pub fn init(
-        config: WindowConfig<Rend>,
+        config: WindowConfig<Rend, Painter>,
         event_loop: &ActiveEventLoop,
         proxy: &EventLoopProxy<BlitzShellEvent>,
     ) -> Self {
@@ -89,20 +167,37 @@ impl<Rend: WindowRenderer> View<Rend> {
         let theme = winit_window.theme().unwrap_or(Theme::Light);
         let color_scheme = theme_to_color_scheme(theme);
         let viewport = Viewport::new(size.width, size.height, scale, color_scheme);
+        system_preferences::read_in_background(proxy, winit_window.id());
 
         // Create shell provider
-        let shell_provider = BlitzShellProvider::new(winit_window.clone());
+        let shell_provider =
+            BlitzShellProvider::new(winit_window.clone()).with_proxy(proxy.clone());
 
         let mut doc = config.doc;
         doc.set_viewport(viewport);
         doc.set_shell_provider(Arc::new(shell_provider));
 
-        // If the document title is set prior to the window being created then it will
-        // have been sent to a dummy ShellProvider and won't get picked up.
-        // So we look for it here and set it if present.
-        let title = doc.find_title_node().map(|node| node.text_content());
-        if let Some(title) = title {
-            winit_window.set_title(&title);
+        // Budget frames by the refresh rate of the display the window opened on
+        let refresh_rate = winit_window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz());
+        if let Some(millihertz) = refresh_rate {
+            doc.set_frame_budget(Duration::from_secs_f64(1000.0 / millihertz as f64));
+        }
+
+        // If the document title, icon or theme color is set prior to the window being created
+        // then it will have been sent to a dummy ShellProvider and won't get picked up.
+        // So we set them here if present.
+        if !doc.title().is_empty() {
+            winit_window.set_title(doc.title());
+        }
+        // (SVG icons are rasterized when the window is first drawn)
+        if let Some(DocumentIcon::Raster(icon)) = doc.icon() {
+            doc.shell_provider.set_window_icon(Some(icon.clone()));
+        }
+        if let Some(theme_color) = doc.theme_color() {
+            let rgba = theme_color.to_rgba8().to_u8_array();
+            doc.shell_provider.set_theme_color(Some(rgba));
         }
 
         #[cfg(feature = "accessibility")]
@@ -113,7 +208,13 @@ impl<Rend: WindowRenderer> View<Rend> {
 
         Self {
             renderer: config.renderer,
+            painter: config.painter,
+            quality_controller: config.adaptive_quality.map(QualityController::new),
+            frame_clock: Instant::now(),
+            idle_tasks: IdleTasks::default(),
+            damage: DamageTracker::new(),
             waker: None,
+            redraw_waker: create_redraw_waker(&winit_window),
             keyboard_modifiers: Default::default(),
             event_loop_proxy: proxy.clone(),
             window: winit_window.clone(),
@@ -121,12 +222,25 @@ impl<Rend: WindowRenderer> View<Rend> {
             theme_override: None,
             buttons: MouseEventButtons::None,
             mouse_pos: Default::default(),
+            primary_touch: None,
             ime_enabled: has_focused_text_input,
+            context_menu_items: config.context_menu_items,
+            context_menu: None,
+            #[cfg(feature = "svg")]
+            svg_icon: None,
             #[cfg(feature = "accessibility")]
             accessibility,
         }
     }
 
+    /// Apply whether the user prefers reduced motion, once it has been read from the system
+    pub fn set_prefers_reduced_motion(&mut self, prefers_reduced_motion: bool) {
+        if self.doc.viewport().prefers_reduced_motion != prefers_reduced_motion {
+            self.doc.viewport_mut().prefers_reduced_motion = prefers_reduced_motion;
+            self.request_redraw();
+        }
+    }
+
     pub fn replace_document(&mut self, new_doc: Box<dyn Document>, retain_scroll_position: bool) {
         let scroll = self.doc.viewport_scroll();
         let viewport = self.doc.viewport().clone();
@@ -163,17 +277,49 @@ impl<Rend: WindowRenderer> View<Rend> {
     }
 }
 
-impl<Rend: WindowRenderer> View<Rend> {
+impl<Rend: WindowRenderer, Painter> Drop for View<Rend, Painter> {
+    fn drop(&mut self) {
+        // Release the renderer's GPU resources before the document is dropped
+        self.waker = None;
+        self.renderer.suspend();
+    }
+}
+
+impl<Rend, Painter> View<Rend, Painter>
+where
+    Rend: WindowRenderer,
+    Painter: for<'a> DocumentRenderer<BaseDocument, Rend::ScenePainter<'a>>,
+{
     pub fn resume(&mut self) -> Result<(), String> {
         println!("🪟 View::resume() called for window {:?}", self.window.id());
         
+        // The window may have been resized or moved to a display with a different scale while
+        // suspended (like a phone being rotated while the app was in the background)
+        let size = self.window.inner_size();
+        if size.width > 0 && size.height > 0 {
+            let scale_factor = self.window.scale_factor() as f32;
+            let mut viewport = self.doc.viewport_mut();
+            viewport.window_size = (size.width, size.height);
+            viewport.set_hidpi_scale(scale_factor);
+        }
+
         // STEP 1: Resume renderer first to get GPU context
         let (width, height) = self.doc.viewport().window_size;
-        let scale = self.doc.viewport().scale_f64();
-        self.renderer.resume(self.window.clone(), width, height);
+        if let Err(e) = self.renderer.try_resume(self.window.clone(), width, height) {
+            return Err(format!("Renderer failed to resume: {}", e));
+        }
+        self.finish_resume()
+    }
+
+    /// Lay out and render the document once the renderer has been resumed. Called by
+    /// [`resume`](Self::resume), or by embedders which resume the renderer themselves (like the
+    /// web shell, which can't block while the GPU device is requested).
+    pub fn finish_resume(&mut self) -> Result<(), String> {
         if !self.renderer.is_active() {
             return Err("Renderer failed to resume - GPU context unavailable".to_string());
         }
+        let (width, height) = self.doc.viewport().window_size;
+        let scale = self.doc.viewport().scale_f64();
 
         // STEP 2: Initialize text system with GPU context - MUST happen before layout
         let doc_as_any: &dyn std::any::Any = &**self.doc;
@@ -182,6 +328,7 @@ impl<Rend: WindowRenderer> View<Rend> {
         }
 
         // STEP 3: Now resolve DOM - this triggers layout which needs text system
+        self.update_lifecycle();
         self.doc.resolve();
 
         // STEP 4: Perform initial render
@@ -189,41 +336,112 @@ impl<Rend: WindowRenderer> View<Rend> {
             "🚀 Window::resume() - calling initial render with size {}x{}",
             width, height
         );
+        let viewport = self.render_viewport(width, height, scale);
+        self.damage.clear();
         self.renderer.render(|scene| {
-            println!("🚀 Inside renderer.render() callback - calling paint_scene()");
-            paint_scene(scene, &self.doc, scale, width, height);
-            println!("🚀 paint_scene() completed");
+            println!("🚀 Inside renderer.render() callback - calling painter.render()");
+            self.painter.render(scene, &self.doc, viewport);
+            println!("🚀 painter.render() completed");
         });
+        self.doc.mark_painted();
 
         // Set waker
         self.waker = Some(create_waker(&self.event_loop_proxy, self.window_id()));
-        
+
+        // Bring the soft keyboard back if a text input is still focused
+        self.update_ime_state();
+
         Ok(())
     }
 
+    /// The viewport to paint the document into, in the renderer's color space
+    fn render_viewport(&self, width: u32, height: u32, scale: f64) -> RenderViewport {
+        let color_space = match self.renderer.color_space() {
+            SurfaceColorSpace::Srgb => OutputColorSpace::Srgb,
+            SurfaceColorSpace::DisplayP3 => OutputColorSpace::DisplayP3,
+        };
+        RenderViewport::new(width, height, scale).with_color_space(color_space)
+    }
+
+    /// Release the renderer's surface, which mobile platforms destroy when apps are backgrounded.
+    /// The surface is recreated by [`resume`](Self::resume).
     pub fn suspend(&mut self) {
         self.waker = None;
         self.renderer.suspend();
+        // Touches are cancelled and the soft keyboard is hidden when apps are backgrounded
+        self.primary_touch = None;
+        self.ime_enabled = false;
     }
 
-    /// Update IME state based on current text input focus
+    /// Update IME state (and show or hide the soft keyboard) based on current text input focus
     pub fn update_ime_state(&mut self) {
         let has_focused_text_input = self.doc.has_focused_text_input();
 
         if self.ime_enabled != has_focused_text_input {
             self.window.set_ime_allowed(has_focused_text_input);
+            set_soft_keyboard_visible(has_focused_text_input);
             self.ime_enabled = has_focused_text_input;
         }
+        if has_focused_text_input {
+            self.update_ime_cursor_area();
+        }
+    }
+
+    /// Tell the platform where the focused input is, so that candidate windows are shown beside
+    /// it and soft keyboards avoid covering it
+    fn update_ime_cursor_area(&self) {
+        let Some(rect) = focused_element_rect(&self.doc) else {
+            return;
+        };
+        let scale = self.doc.viewport().scale_f64();
+        let scroll = self.doc.viewport_scroll();
+        let position =
+            PhysicalPosition::new((rect.x0 - scroll.x) * scale, (rect.y0 - scroll.y) * scale);
+        let size = PhysicalSize::new(rect.width() * scale, rect.height() * scale);
+        self.window.set_ime_cursor_area(position, size);
+    }
+
+    /// Scroll the viewport so the focused input is visible, if it's been covered (like when the
+    /// window shrinks to make room for a soft keyboard)
+    fn scroll_focused_input_into_view(&mut self) {
+        if !self.ime_enabled {
+            return;
+        }
+        let Some(rect) = focused_element_rect(&self.doc) else {
+            return;
+        };
+        let viewport = self.doc.viewport();
+        let visible_height = viewport.window_size.1 as f64 / viewport.scale_f64();
+        drop(viewport);
+
+        let top = self.doc.viewport_scroll().y;
+        let Some(distance) = distance_to_reveal(rect, top, top + visible_height) else {
+            return;
+        };
+        self.doc.scroll_viewport_by(0.0, -distance);
+        self.update_ime_cursor_area();
     }
 
     pub fn poll(&mut self) -> bool {
         if let Some(waker) = &self.waker {
-            let cx = std::task::Context::from_waker(waker);
-            if self.doc.poll(Some(cx)) {
+            let mut cx = std::task::Context::from_waker(waker);
+
+            // Input from devices the window doesn't handle, like gamepads
+            let input_events = self.doc.input_provider.poll_events(&mut cx);
+            let has_input = !input_events.is_empty();
+            for event in input_events {
+                self.doc.handle_ui_event(event);
+            }
+
+            // Tasks scheduled by the document's scripts
+            let ran_scripts = self.doc.poll_scripts(&mut cx);
+
+            let changed = self.doc.poll(Some(cx)) | self.update_lifecycle();
+            if changed || has_input || ran_scripts {
                 #[cfg(feature = "accessibility")]
                 {
                     if self.doc.has_changes() {
-                        self.accessibility.update_tree(&self.doc);
+                        self.idle_tasks.accessibility_stale = true;
                     }
                 }
 
@@ -236,6 +454,38 @@ impl<Rend: WindowRenderer> View<Rend> {
         false
     }
 
+    /// Make a document which has been built (rather than parsed as it arrives) interactive, if it
+    /// didn't make itself interactive. It becomes complete once its sub-resources have loaded
+    /// (when it is next resolved). Returns whether it became interactive.
+    fn update_lifecycle(&mut self) -> bool {
+        if self.doc.is_parsing() || self.doc.lifecycle() >= DocumentLifecycle::Interactive {
+            return false;
+        }
+        self.doc.set_lifecycle(DocumentLifecycle::Interactive);
+        true
+    }
+
+    /// Run the view's low-priority work which fits in an idle period starting now (see
+    /// [`IdleTasks::run`]), returning when the next idle period should start if any is left
+    pub fn run_idle_tasks(&mut self) -> Option<Instant> {
+        #[cfg(feature = "accessibility")]
+        let accessibility = &mut self.accessibility;
+        let caches_trimmed = self.idle_tasks.caches_trimmed;
+        let outcome = self.idle_tasks.run(&mut self.doc, |_doc| {
+            #[cfg(feature = "accessibility")]
+            accessibility.update_tree(_doc);
+        });
+        // The coordinator may have asked the renderer's caches to shrink, which they can only do
+        // on this thread
+        if !caches_trimmed && self.idle_tasks.caches_trimmed {
+            self.renderer.trim_caches();
+        }
+        if outcome.ran_callbacks {
+            self.request_redraw();
+        }
+        outcome.next_period
+    }
+
     pub fn request_redraw(&self) {
         if self.renderer.is_active() {
             self.window.request_redraw();
@@ -243,22 +493,107 @@ impl<Rend: WindowRenderer> View<Rend> {
     }
 
     pub fn redraw(&mut self) {
-        println!("🖼️ Window::redraw() called!");
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "frame",
+            frame_id = crate::trace::next_frame_id(),
+            window = ?self.window.id(),
+        )
+        .entered();
+        let tick_start = Instant::now();
+        self.idle_tasks.frame_started(tick_start);
+        self.doc.tick_animation_frame(tick_start.duration_since(self.frame_clock));
         self.doc.resolve();
+        #[cfg(feature = "svg")]
+        self.update_svg_icon();
         let (width, height) = self.doc.viewport().window_size;
         let scale = self.doc.viewport().scale_f64();
-        println!(
-            "🖼️ About to call renderer.render() with size {}x{}, scale {}",
-            width, height, scale
-        );
+        let viewport = self.render_viewport(width, height, scale);
+        let svg_rasters = blitz_paint::svg_rasters_version();
+        // Renderers which can redraw part of a frame only redraw what changed since the last one
+        if let Some(damage) = self.damage.damage(&self.doc, viewport) {
+            if damage.area() > 0.0 {
+                self.renderer.set_damage(&[damage]);
+            } else {
+                self.renderer.set_damage(&[]);
+            }
+        }
+        let frame_start = Instant::now();
         self.renderer
-            .render(|scene| paint_scene(scene, &self.doc, scale, width, height));
+            .render(|scene| self.painter.render(scene, &self.doc, viewport));
+        self.doc.mark_painted();
+        self.doc.finish_animation_frame(tick_start.elapsed());
+        blitz_paint::wake_when_svgs_rasterized(svg_rasters, &self.redraw_waker);
+
+        if let Some(controller) = &mut self.quality_controller
+            && let Some(quality) = controller.record_frame(frame_start.elapsed())
+        {
+            self.renderer.set_quality(quality);
+            self.damage.clear();
+        }
 
         if self.doc.is_animating() {
             self.request_redraw();
         }
     }
 
+    /// Rasterize the document's icon for the window, if it's an SVG which hasn't been yet.
+    /// Documents tell the shell provider about their other icons themselves.
+    #[cfg(feature = "svg")]
+    fn update_svg_icon(&mut self) {
+        let icon = self.doc.icon();
+        if icon == self.svg_icon.as_ref() {
+            return;
+        }
+        self.svg_icon = icon.cloned();
+        if let Some(DocumentIcon::Svg(svg)) = icon {
+            let icon = blitz_paint::rasterize_svg_icon(svg);
+            self.doc.shell_provider.set_window_icon(icon);
+        }
+    }
+
+    /// Show a context menu for `request`, if it has any items
+    pub fn show_context_menu(&mut self, request: ContextMenuRequest) {
+        let devtools = self.doc.devtools();
+        let devtools_enabled = devtools.show_layout || devtools.highlight_hover;
+        let embedder_items = self.context_menu_items.as_ref();
+        let items = context_menu_items(&request, devtools_enabled, embedder_items);
+        if items.is_empty() {
+            return;
+        }
+
+        // The chosen item is sent back to the event loop, and run by `handle_context_menu_item`
+        self.context_menu = Some(request);
+        if !show_context_menu(&self.window, &items, &self.event_loop_proxy) {
+            self.context_menu = None;
+        }
+    }
+
+    /// Run the item with `id`, which was chosen from the context menu
+    pub fn handle_context_menu_item(&mut self, id: &str) {
+        let Some(request) = self.context_menu.take() else {
+            return;
+        };
+
+        if id == ContextMenuItem::Copy.id() {
+            if let Some(text) = request.selected_text {
+                let _ = self.doc.shell_provider.set_clipboard_text(text);
+            }
+        } else if id == ContextMenuItem::SelectAll.id() {
+            self.doc.select_all_text(request.node_id);
+        } else if id == ContextMenuItem::InspectElement.id() {
+            self.doc.debug_log_node(request.node_id);
+        } else {
+            let selection = ContextMenuSelection {
+                window_id: self.window_id(),
+                id: id.to_string(),
+                request,
+            };
+            let event = BlitzShellEvent::embedder_event(selection);
+            let _ = self.event_loop_proxy.send_event(event);
+        }
+    }
+
     pub fn window_id(&self) -> WindowId {
         self.window.id()
     }
@@ -275,6 +610,11 @@ impl<Rend: WindowRenderer> View<Rend> {
         }
     }
 
+    /// The mouse position, in logical pixels of the window
+    fn mouse_point(&self) -> Point {
+        Point::new(self.mouse_pos.0 as f64, self.mouse_pos.1 as f64)
+    }
+
     #[cfg(feature = "accessibility")]
     pub fn build_accessibility_tree(&mut self) {
         self.accessibility.update_tree(&self.doc);
@@ -294,9 +634,17 @@ impl<Rend: WindowRenderer> View<Rend> {
 
             // Window size/position events
             WindowEvent::Moved(_) => {}
-            WindowEvent::Occluded(_) => {},
+            WindowEvent::Occluded(occluded) => {
+                let visibility = if occluded {
+                    DocumentVisibility::Hidden
+                } else {
+                    DocumentVisibility::Visible
+                };
+                self.doc.set_visibility(visibility);
+            }
             WindowEvent::Resized(physical_size) => {
                 self.with_viewport(|v| v.window_size = (physical_size.width, physical_size.height));
+                self.scroll_focused_input_into_view();
             }
             WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                 self.with_viewport(|v| v.set_hidpi_scale(scale_factor as f32));
@@ -333,7 +681,10 @@ impl<Rend: WindowRenderer> View<Rend> {
                         match key_code {
                             KeyCode::Equal => self.doc.viewport_mut().zoom_by(0.1),
                             KeyCode::Minus => self.doc.viewport_mut().zoom_by(-0.1),
-                            KeyCode::Digit0 => self.doc.viewport_mut().set_zoom(1.0),
+                            KeyCode::Digit0 => {
+                                self.doc.viewport_mut().set_zoom(1.0);
+                                self.doc.reset_pinch_zoom();
+                            }
                             _ => {}
                         };
                     }
@@ -433,17 +784,140 @@ impl<Rend: WindowRenderer> View<Rend> {
             WindowEvent::DroppedFile(_) => {}
             WindowEvent::HoveredFile(_) => {}
             WindowEvent::HoveredFileCancelled => {}
-            WindowEvent::Focused(_) => {}
+            WindowEvent::Focused(true) => {
+                // The preferences may have changed while another window was focused
+                system_preferences::read_in_background(&self.event_loop_proxy, self.window_id());
+            }
+            WindowEvent::Focused(false) => {}
 
             // Touch and motion events
             // Todo implement touch scrolling
-            WindowEvent::Touch(_) => {}
+            WindowEvent::Touch(touch) => {
+                if touch.phase == TouchPhase::Started && self.primary_touch.is_none() {
+                    self.primary_touch = Some(touch.id);
+                }
+                let is_primary = self.primary_touch == Some(touch.id);
+                if is_primary && matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled) {
+                    self.primary_touch = None;
+                }
+
+                let mods = winit_modifiers_to_kbt_modifiers(self.keyboard_modifiers.state());
+                let scale_factor = self.window.scale_factor();
+                let event = winit_touch_to_blitz(&touch, scale_factor, is_primary, mods);
+                self.doc.handle_ui_event(event);
+                self.update_ime_state();
+                self.request_redraw();
+            }
             WindowEvent::TouchpadPressure { .. } => {}
             WindowEvent::AxisMotion { .. } => {}
-            WindowEvent::PinchGesture { .. } => {},
-            WindowEvent::PanGesture { .. } => {},
-            WindowEvent::DoubleTapGesture { .. } => {},
+            // Pinch-zoom magnifies the page without reflowing it (unlike Ctrl+=)
+            WindowEvent::PinchGesture { delta, .. } => {
+                self.doc.pinch_zoom_by(1.0 + delta, self.mouse_point());
+                self.request_redraw();
+            }
+            WindowEvent::PanGesture { delta, .. } => {
+                if self.doc.visual_viewport().is_magnified() {
+                    let delta = delta.to_logical::<f64>(self.window.scale_factor());
+                    self.doc.pan_visual_viewport_by(Vec2::new(-delta.x, -delta.y));
+                    self.request_redraw();
+                }
+            }
+            WindowEvent::DoubleTapGesture { .. } => {
+                if self.doc.visual_viewport().is_magnified() {
+                    self.doc.reset_pinch_zoom();
+                } else {
+                    self.doc.pinch_zoom_by(2.0, self.mouse_point());
+                }
+                self.request_redraw();
+            }
             WindowEvent::RotationGesture { .. } => {},
         }
     }
 }
+
+/// Where the focused element is painted, in document coordinates (so after scrolling the
+/// elements it's in, but not the viewport)
+fn focused_element_rect(doc: &BaseDocument) -> Option<Rect> {
+    doc.painted_border_box(doc.get_focussed_node_id()?)
+}
+
+/// How far the viewport, showing from `top` to `bottom` of the document, has to scroll down to
+/// show `rect` (negative to scroll up), if it's not already shown
+fn distance_to_reveal(rect: Rect, top: f64, bottom: f64) -> Option<f64> {
+    if rect.y1 > bottom {
+        Some(rect.y1 - bottom)
+    } else if rect.y0 < top {
+        Some(rect.y0 - top)
+    } else {
+        None
+    }
+}
+
+/// Show or hide the soft keyboard. Android's has to be shown through the activity, while other
+/// platforms show theirs when IME is allowed.
+fn set_soft_keyboard_visible(visible: bool) {
+    #[cfg(target_os = "android")]
+    {
+        let app = crate::current_android_app();
+        if visible {
+            app.show_soft_input(true);
+        } else {
+            app.hide_soft_input(false);
+        }
+    }
+    #[cfg(not(target_os = "android"))]
+    let _ = visible;
+}
+
+#[cfg(test)]
+mod tests {
+    use blitz_dom::{Attribute, DocumentConfig, LocalName, QualName, QuirksMode, ns};
+    use blitz_traits::shell::ColorScheme;
+
+    use super::*;
+
+    #[test]
+    fn focused_inputs_are_found_inside_scrolled_elements() {
+        let mut doc = BaseDocument::new(DocumentConfig {
+            viewport: Some(Viewport::new(200, 100, 1.0, ColorScheme::Light)),
+            ..DocumentConfig::for_testing()
+        })
+        .unwrap();
+        doc.add_user_agent_stylesheet(
+            "body { margin: 0 } div { height: 100px; overflow: auto } p { height: 150px; margin: 0 }
+            input { display: block; height: 20px; margin: 0; padding: 0; border: 0 }",
+        );
+
+        let mut mutr = doc.mutate();
+        let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+        let mut element =
+            |local, attrs| mutr.create_element(name(local), attrs, QuirksMode::NoQuirks);
+        let text = Attribute {
+            name: QualName::new(None, ns!(), LocalName::from("type")),
+            value: "text".to_string(),
+        };
+        let input = element("input", vec![text]);
+        let p = element("p", Vec::new());
+        let div = element("div", Vec::new());
+        let body = element("body", Vec::new());
+        let html = element("html", Vec::new());
+        mutr.append_children(div, &[p, input]);
+        mutr.append_children(body, &[div]);
+        mutr.append_children(html, &[body]);
+        mutr.append_children(0, &[html]);
+        drop(mutr);
+        doc.resolve();
+        doc.set_focus_to(input);
+
+        let rect = focused_element_rect(&doc).unwrap();
+        assert_eq!((rect.y0, rect.y1), (150.0, 170.0));
+        assert_eq!(distance_to_reveal(rect, 0.0, 100.0), Some(70.0));
+
+        // Once the div is scrolled to the input, the viewport doesn't need to scroll
+        doc.scroll_node_by(div, 0.0, -70.0);
+        let rect = focused_element_rect(&doc).unwrap();
+        assert_eq!((rect.y0, rect.y1), (80.0, 100.0));
+        assert_eq!(distance_to_reveal(rect, 0.0, 100.0), None);
+        assert_eq!(distance_to_reveal(rect, 90.0, 190.0), Some(-10.0));
+    }
+}
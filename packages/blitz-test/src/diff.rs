@@ -0,0 +1,118 @@
+//! Perceptual comparison of rendered images
+//!
+//! Pixels are compared by the distance between their colors in the YIQ color space, which
+//! weights differences in brightness above differences in hue the way people perceive them
+//! (as described in "Measuring perceived color difference using YIQ NTSC transmission color
+//! space in mobile applications" by Kotsarenko and Ramos). Colors are blended onto white first,
+//! so that fully transparent pixels of different colors compare equal.
+
+use crate::RgbaImage;
+
+/// The largest possible squared YIQ distance between two colors
+const MAX_DELTA: f64 = 35215.0;
+
+/// How different two images may be and still match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// How different a pixel may be before it counts as differing, from 0 (identical colors)
+    /// to 1 (as different as colors get). Differences from anti-aliasing are usually below 0.1.
+    pub threshold: f64,
+    /// How many pixels may differ
+    pub max_differing_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            max_differing_pixels: 0,
+        }
+    }
+}
+
+impl Tolerance {
+    /// Tolerate no difference at all
+    pub const EXACT: Self = Self {
+        threshold: 0.0,
+        max_differing_pixels: 0,
+    };
+}
+
+/// The result of comparing two images of the same size
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// The number of pixels which differ by more than the threshold
+    pub differing_pixels: usize,
+    /// The largest difference between two pixels, from 0 to 1
+    pub max_difference: f64,
+    /// The expected image faded to grey, with differing pixels drawn in red
+    pub diff_image: RgbaImage,
+}
+
+impl Comparison {
+    /// Whether the images are within `tolerance` of each other
+    pub fn matches(&self, tolerance: &Tolerance) -> bool {
+        self.differing_pixels <= tolerance.max_differing_pixels
+    }
+}
+
+/// Compare `actual` to `expected` pixel by pixel, counting the pixels which differ by more than
+/// `threshold` (see [`Tolerance::threshold`]). Returns `None` if the images differ in size.
+pub fn compare(expected: &RgbaImage, actual: &RgbaImage, threshold: f64) -> Option<Comparison> {
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return None;
+    }
+
+    let mut differing_pixels = 0;
+    let mut max_difference: f64 = 0.0;
+    let mut diff_image = RgbaImage::new(expected.width, expected.height);
+    let pixels = expected.data.chunks_exact(4).zip(actual.data.chunks_exact(4));
+    for ((expected, actual), diff) in pixels.zip(diff_image.data.chunks_exact_mut(4)) {
+        let difference = pixel_difference(expected, actual);
+        max_difference = max_difference.max(difference);
+        if difference > threshold {
+            differing_pixels += 1;
+            diff.copy_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let grey = 255.0 - (255.0 - luma(blend_onto_white(expected))) * 0.1;
+            diff.copy_from_slice(&[grey as u8, grey as u8, grey as u8, 255]);
+        }
+    }
+
+    Some(Comparison {
+        differing_pixels,
+        max_difference,
+        diff_image,
+    })
+}
+
+/// The perceived difference between two RGBA pixels, from 0 to 1
+pub fn pixel_difference(a: &[u8], b: &[u8]) -> f64 {
+    if a == b {
+        return 0.0;
+    }
+    let (a, b) = (blend_onto_white(a), blend_onto_white(b));
+    let y = luma(a) - luma(b);
+    let i = in_phase(a) - in_phase(b);
+    let q = quadrature(a) - quadrature(b);
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+    (delta / MAX_DELTA).sqrt()
+}
+
+fn blend_onto_white(pixel: &[u8]) -> [f64; 3] {
+    let alpha = pixel[3] as f64 / 255.0;
+    let blend = |channel: u8| 255.0 + (channel as f64 - 255.0) * alpha;
+    [blend(pixel[0]), blend(pixel[1]), blend(pixel[2])]
+}
+
+fn luma([r, g, b]: [f64; 3]) -> f64 {
+    r * 0.29889531 + g * 0.58662247 + b * 0.11448223
+}
+
+fn in_phase([r, g, b]: [f64; 3]) -> f64 {
+    r * 0.59597799 - g * 0.27417610 - b * 0.32180189
+}
+
+fn quadrature([r, g, b]: [f64; 3]) -> f64 {
+    r * 0.21147017 - g * 0.52261711 + b * 0.31114694
+}
@@ -0,0 +1,33 @@
+//! Golden image testing for Blitz
+//!
+//! This crate renders HTML headlessly and compares the result to golden images checked in next
+//! to the tests, so that changes to style, layout or paint which alter how pages look are
+//! caught in review:
+//!
+//!  - [`render_html`] renders a document at a fixed size and device pixel ratio, laying text out
+//!    with fonts bundled with this crate rather than those installed on the machine
+//!  - [`compare`] counts the pixels which differ perceptibly between two images, so that tiny
+//!    differences between GPUs don't fail tests
+//!  - [`Snapshots`] compares renders to the golden images in a directory. Running the tests with
+//!    `BLITZ_BLESS=1` writes renders as the new golden images instead.
+//!
+//! ```no_run
+//! use blitz_test::{RenderConfig, Snapshots};
+//!
+//! let snapshots = Snapshots::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots"))
+//!     .with_config(RenderConfig {
+//!         width: 200,
+//!         height: 100,
+//!         scale: 2.0,
+//!         ..RenderConfig::default()
+//!     });
+//! snapshots.assert_html("hello", "<p style='font: 16px sans-serif'>Hello, world!</p>");
+//! ```
+
+mod diff;
+mod render;
+mod snapshot;
+
+pub use diff::{Comparison, Tolerance, compare, pixel_difference};
+pub use render::{RenderConfig, RgbaImage, install_bundled_fonts, render_html, render_html_with};
+pub use snapshot::{BLESS_ENV_VAR, SnapshotError, Snapshots, read_png, write_png};
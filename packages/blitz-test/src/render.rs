@@ -0,0 +1,155 @@
+//! Rendering HTML headlessly with bundled fonts
+
+use std::sync::Arc;
+
+use anyrender::ImageRenderer;
+use anyrender_vello::VelloImageRenderer;
+use blitz_dom::DocumentConfig;
+use blitz_html::HtmlDocument;
+use blitz_paint::paint_scene;
+use blitz_text::cosmyc::FontSystem;
+use blitz_text::{FontOverride, ensure_embedded_fallback, set_font_override};
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+/// The fonts used for every render, instead of the fonts installed on the machine
+const BUNDLED_FONTS: [&[u8]; 4] = [
+    include_bytes!("../assets/fonts/DejaVuSans.ttf"),
+    include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf"),
+    include_bytes!("../assets/fonts/DejaVuSerif.ttf"),
+    include_bytes!("../assets/fonts/DejaVuSansMono.ttf"),
+];
+
+/// How to render a document
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    /// The width of the viewport (in CSS pixels)
+    pub width: u32,
+    /// The height of the viewport (in CSS pixels)
+    pub height: u32,
+    /// The device pixel ratio. Rendered images are `scale` times the size of the viewport.
+    pub scale: f64,
+    pub color_scheme: ColorScheme,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            scale: 1.0,
+            color_scheme: ColorScheme::Light,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// The size of rendered images in pixels
+    pub fn physical_size(&self) -> (u32, u32) {
+        let scale = |length: u32| ((length as f64 * self.scale).round() as u32).max(1);
+        (scale(self.width), scale(self.height))
+    }
+}
+
+/// An image with 8-bit RGBA pixels, stored row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// An image of `width` by `height` transparent pixels
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// The pixel at `(x, y)`
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.data[index..index + 4]);
+        pixel
+    }
+}
+
+/// Render `html` with the GPU image renderer
+pub fn render_html(html: &str, config: &RenderConfig) -> RgbaImage {
+    render_html_with::<VelloImageRenderer>(html, config)
+}
+
+/// Render `html` with the image renderer `R`
+///
+/// Resources are never fetched, so documents should inline their styles and images. Text is
+/// laid out with the [bundled fonts](install_bundled_fonts) rather than the fonts installed on
+/// the machine, except by font systems created before the first render. Text is only drawn by
+/// renderers which initialize the global text system (like [`VelloImageRenderer`]).
+pub fn render_html_with<R: ImageRenderer>(html: &str, config: &RenderConfig) -> RgbaImage {
+    let (width, height) = config.physical_size();
+
+    // The fonts must be replaced first, as the renderer creates font systems to draw text with
+    use_bundled_fonts();
+    let mut renderer = R::new(width, height);
+
+    let mut doc = HtmlDocument::from_html(
+        html,
+        DocumentConfig {
+            viewport: Some(Viewport::new(width, height, config.scale as f32, config.color_scheme)),
+            net_provider: Some(Arc::new(DummyNetProvider)),
+            ..DocumentConfig::for_testing()
+        },
+    )
+    .into_inner();
+    doc.resolve();
+
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    renderer.render(
+        |scene| paint_scene(scene, &doc, config.scale, width, height),
+        &mut data,
+    );
+    RgbaImage {
+        width,
+        height,
+        data,
+    }
+}
+
+/// Replace the fonts in `font_system` with the fonts bundled with this crate, so that text looks
+/// the same on every machine. Every generic family (like `sans-serif` or `monospace`) maps to
+/// one of them.
+pub fn install_bundled_fonts(font_system: &mut FontSystem) {
+    let db = font_system.db_mut();
+    let installed: Vec<_> = db.faces().map(|face| face.id).collect();
+    for id in installed {
+        db.remove_face(id);
+    }
+    for font in BUNDLED_FONTS {
+        db.load_font_data(font.to_vec());
+    }
+    db.set_serif_family("DejaVu Serif");
+    db.set_sans_serif_family("DejaVu Sans");
+    db.set_monospace_family("DejaVu Sans Mono");
+    db.set_cursive_family("DejaVu Sans");
+    db.set_fantasy_family("DejaVu Sans");
+    ensure_embedded_fallback(font_system);
+}
+
+/// Use the bundled fonts in every font system created from now on: those the text system lays
+/// text out with on each thread, and those renderers draw glyphs from. As they're all created
+/// with the same fonts in the same order, a font has the same id in each of them.
+fn use_bundled_fonts() {
+    // The first render sets the fonts for the rest of the process
+    let _ = set_font_override(FontOverride {
+        fonts: BUNDLED_FONTS.to_vec(),
+        serif: "DejaVu Serif",
+        sans_serif: "DejaVu Sans",
+        monospace: "DejaVu Sans Mono",
+        cursive: "DejaVu Sans",
+        fantasy: "DejaVu Sans",
+    });
+}
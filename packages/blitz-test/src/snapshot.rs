@@ -0,0 +1,215 @@
+//! Comparing renders to golden images, and blessing new ones
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use anyrender::ImageRenderer;
+use anyrender_vello::VelloImageRenderer;
+use thiserror::Error;
+
+use crate::{RenderConfig, RgbaImage, Tolerance, compare, render_html_with};
+
+/// The environment variable which makes [`Snapshots`] write renders as the new golden images
+/// instead of comparing them. Any value except `0` enables it.
+pub const BLESS_ENV_VAR: &str = "BLITZ_BLESS";
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("No golden image at {} (run with BLITZ_BLESS=1 to create it)", path.display())]
+    Missing { path: PathBuf },
+
+    #[error(
+        "Render is {actual_width}x{actual_height} but the golden image at {} is \
+        {expected_width}x{expected_height} (run with BLITZ_BLESS=1 to update it)",
+        path.display()
+    )]
+    SizeMismatch {
+        path: PathBuf,
+        expected_width: u32,
+        expected_height: u32,
+        actual_width: u32,
+        actual_height: u32,
+    },
+
+    #[error(
+        "{differing_pixels} pixels differ from the golden image at {} (see {}, or run with \
+        BLITZ_BLESS=1 to update it)",
+        path.display(),
+        diff_path.display()
+    )]
+    Mismatch {
+        path: PathBuf,
+        diff_path: PathBuf,
+        differing_pixels: usize,
+    },
+
+    #[error("{} is not an 8-bit RGBA png", .0.display())]
+    UnsupportedImage(PathBuf),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to decode png: {0}")]
+    Decode(#[from] png::DecodingError),
+
+    #[error("Failed to encode png: {0}")]
+    Encode(#[from] png::EncodingError),
+}
+
+/// A directory of golden images that renders are compared to
+///
+/// Each snapshot is stored as `<name>.png`. When a render doesn't match, it's written next to
+/// the golden image as `<name>.actual.png`, along with `<name>.diff.png` highlighting the pixels
+/// which differ. Setting [`BLITZ_BLESS`](BLESS_ENV_VAR) accepts renders as the new golden
+/// images.
+///
+/// ```no_run
+/// use blitz_test::Snapshots;
+///
+/// let snapshots = Snapshots::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots"));
+/// snapshots.assert_html("red_box", "<div style='width: 50px; height: 50px; background: red'>");
+/// ```
+pub struct Snapshots<R: ImageRenderer = VelloImageRenderer> {
+    dir: PathBuf,
+    pub config: RenderConfig,
+    pub tolerance: Tolerance,
+    bless: bool,
+    renderer: PhantomData<fn() -> R>,
+}
+
+impl Snapshots {
+    /// Snapshots in `dir`, rendered with the GPU image renderer
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::for_renderer(dir)
+    }
+}
+
+impl<R: ImageRenderer> Snapshots<R> {
+    /// Snapshots in `dir`, rendered with the image renderer `R`
+    pub fn for_renderer(dir: impl Into<PathBuf>) -> Self {
+        let bless = std::env::var_os(BLESS_ENV_VAR).is_some_and(|value| value != "0");
+        Self {
+            dir: dir.into(),
+            config: RenderConfig::default(),
+            tolerance: Tolerance::default(),
+            bless,
+            renderer: PhantomData,
+        }
+    }
+
+    pub fn with_config(mut self, config: RenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Write renders as the new golden images, regardless of [`BLITZ_BLESS`](BLESS_ENV_VAR)
+    pub fn with_bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    /// The path of the golden image for the snapshot called `name`
+    pub fn golden_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.png"))
+    }
+
+    /// Render `html` and compare it to the golden image called `name`
+    pub fn check_html(&self, name: &str, html: &str) -> Result<(), SnapshotError> {
+        let image = render_html_with::<R>(html, &self.config);
+        self.check_image(name, &image)
+    }
+
+    /// Compare `image` to the golden image called `name`
+    pub fn check_image(&self, name: &str, image: &RgbaImage) -> Result<(), SnapshotError> {
+        let path = self.golden_path(name);
+        let actual_path = self.dir.join(format!("{name}.actual.png"));
+        let diff_path = self.dir.join(format!("{name}.diff.png"));
+
+        if self.bless {
+            fs::create_dir_all(&self.dir)?;
+            write_png(&path, image)?;
+            remove_if_exists(&actual_path)?;
+            return remove_if_exists(&diff_path);
+        }
+
+        if !path.exists() {
+            fs::create_dir_all(&self.dir)?;
+            write_png(&actual_path, image)?;
+            return Err(SnapshotError::Missing { path });
+        }
+
+        let expected = read_png(&path)?;
+        let Some(comparison) = compare(&expected, image, self.tolerance.threshold) else {
+            write_png(&actual_path, image)?;
+            return Err(SnapshotError::SizeMismatch {
+                path,
+                expected_width: expected.width,
+                expected_height: expected.height,
+                actual_width: image.width,
+                actual_height: image.height,
+            });
+        };
+
+        if comparison.matches(&self.tolerance) {
+            remove_if_exists(&actual_path)?;
+            return remove_if_exists(&diff_path);
+        }
+        write_png(&actual_path, image)?;
+        write_png(&diff_path, &comparison.diff_image)?;
+        Err(SnapshotError::Mismatch {
+            path,
+            diff_path,
+            differing_pixels: comparison.differing_pixels,
+        })
+    }
+
+    /// Render `html` and compare it to the golden image called `name`, panicking if it doesn't
+    /// match
+    #[track_caller]
+    pub fn assert_html(&self, name: &str, html: &str) {
+        if let Err(err) = self.check_html(name, html) {
+            panic!("snapshot `{name}` failed: {err}");
+        }
+    }
+}
+
+/// Read an 8-bit RGBA png, like those written by [`write_png`]
+pub fn read_png(path: &Path) -> Result<RgbaImage, SnapshotError> {
+    let decoder = png::Decoder::new(Cursor::new(fs::read(path)?));
+    let mut reader = decoder.read_info()?;
+    let info = reader.info();
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(SnapshotError::UnsupportedImage(path.to_path_buf()));
+    }
+
+    let mut image = RgbaImage::new(info.width, info.height);
+    reader.next_frame(&mut image.data)?;
+    Ok(image)
+}
+
+/// Write `image` as an 8-bit RGBA png
+pub fn write_png(path: &Path, image: &RgbaImage) -> Result<(), SnapshotError> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), SnapshotError> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,56 @@
+//! Painting elements clipped by `clip-path`
+
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_test::{RenderConfig, RgbaImage, render_html_with};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// Render a red 100px square clipped by `clip_path`, with `extra` styles, on a white page
+fn render_clipped(clip_path: &str, extra: &str) -> RgbaImage {
+    let style = format!("width: 100px; height: 100px; background: red; {extra}");
+    let html = format!(
+        "<body style='margin: 0; background: white'>
+          <div style='{style}; clip-path: {clip_path}'></div>
+        </body>"
+    );
+    let config = RenderConfig {
+        width: 120,
+        height: 120,
+        ..RenderConfig::default()
+    };
+    render_html_with::<TinySkiaImageRenderer>(&html, &config)
+}
+
+#[test]
+fn elements_are_clipped_to_each_shape() {
+    // Each shape, with points inside and outside of it (but inside the div)
+    let cases = [
+        ("inset(10px 20px round 5px)", (50, 50), (10, 50)),
+        ("circle(40px at 50% 50%)", (50, 50), (5, 5)),
+        ("ellipse(50px 20px)", (90, 50), (50, 90)),
+        ("polygon(0 0, 100px 0, 0 100px)", (20, 20), (80, 80)),
+        ("path('M 50 0 L 100 50 L 50 100 L 0 50 Z')", (50, 50), (5, 5)),
+    ];
+    // Paint-contained elements are clipped in the same way
+    for extra in ["", "contain: paint"] {
+        for (clip_path, inside, outside) in cases {
+            let image = render_clipped(clip_path, extra);
+            assert_eq!(image.pixel(inside.0, inside.1), RED, "{clip_path} at {inside:?}");
+            assert_eq!(image.pixel(outside.0, outside.1), WHITE, "{clip_path} at {outside:?}");
+        }
+    }
+}
+
+#[test]
+fn clip_paths_are_filled_with_their_fill_rule() {
+    // A pentagram, whose center is inside it with `nonzero` but outside of it with `evenodd`
+    let star = "50px 0, 79px 90px, 2px 35px, 98px 35px, 21px 90px";
+    let nonzero = render_clipped(&format!("polygon({star})"), "");
+    assert_eq!(nonzero.pixel(50, 50), RED);
+    let evenodd = render_clipped(&format!("polygon(evenodd, {star})"), "");
+    assert_eq!(evenodd.pixel(50, 50), WHITE);
+    // Its points are inside it either way, and the gap between its legs isn't
+    assert_eq!(evenodd.pixel(50, 10), RED);
+    assert_eq!(evenodd.pixel(50, 80), WHITE);
+}
@@ -0,0 +1,74 @@
+//! Elements outside the visible area are culled rather than painted
+
+use std::sync::Arc;
+
+use anyrender::DisplayList;
+use blitz_dom::DocumentConfig;
+use blitz_html::HtmlDocument;
+use blitz_paint::{cull_stats, paint_scene};
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+/// The number of rows in the document, which are each 52px tall
+const ROWS: u32 = 200;
+
+/// Paint a long document of rows into a display list for a viewport `height` px tall
+fn paint_rows(height: u32) -> DisplayList {
+    let row = "<div style='height: 50px; border: 1px solid black; background: teal'></div>";
+    let html = format!("<body style='margin: 0'>{}</body>", row.repeat(ROWS as usize));
+    paint(&html, height)
+}
+
+/// Paint `html` into a display list for a viewport `height` px tall
+fn paint(html: &str, height: u32) -> DisplayList {
+    let mut doc = HtmlDocument::from_html(
+        html,
+        DocumentConfig {
+            viewport: Some(Viewport::new(800, height, 1.0, ColorScheme::Light)),
+            net_provider: Some(Arc::new(DummyNetProvider)),
+            ..DocumentConfig::for_testing()
+        },
+    )
+    .into_inner();
+    doc.resolve();
+
+    let mut list = DisplayList::new();
+    paint_scene(&mut list, &doc, 1.0, 800, height);
+    list
+}
+
+#[test]
+fn long_documents_only_paint_what_is_in_view() {
+    // A viewport tall enough for the whole document, so that nothing is culled
+    let unculled = paint_rows(ROWS * 52);
+    assert_eq!(cull_stats().culled, 0);
+
+    let culled = paint_rows(600);
+    let stats = cull_stats();
+    assert!(stats.culled > 0);
+    assert!(stats.painted < ROWS as usize);
+    assert!(culled.len() < unculled.len());
+}
+
+#[test]
+fn elements_out_of_view_with_descendants_moved_into_view_are_painted() {
+    let html = |child_style: &str| {
+        format!(
+            "<body style='margin: 0; height: 100px'>\
+             <div style='position: absolute; top: -420px; width: 100px; height: 50px'>\
+             <div style='height: 50px; background: teal; {child_style}'></div></div></body>"
+        )
+    };
+
+    paint(&html(""), 600);
+    assert_eq!(cull_stats().culled, 1);
+
+    for child_style in [
+        "transform: translateY(400px)",
+        "box-shadow: 0 400px teal",
+        "outline: 2px solid teal; outline-offset: 400px",
+    ] {
+        paint(&html(child_style), 600);
+        assert_eq!(cull_stats().culled, 0, "{child_style}");
+    }
+}
@@ -0,0 +1,31 @@
+//! Painting with `currentColor`, which is each element's own used color
+
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_test::{RenderConfig, render_html_with};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+#[test]
+fn current_color_is_each_elements_color() {
+    let html = "<body style='margin: 0; background: white; color: rgb(0, 0, 255)'>
+          <div style='width: 20px; height: 20px; border: 10px solid currentColor'></div>
+          <div style='margin: 10px 0; width: 20px; height: 20px; color: rgb(0, 128, 0);
+            box-shadow: 30px 0 currentColor'></div>
+          <div style='width: 40px; height: 20px; color: rgb(255, 0, 0);
+            background: linear-gradient(currentColor, currentColor)'></div>
+        </body>";
+    let config = RenderConfig {
+        width: 100,
+        height: 120,
+        ..RenderConfig::default()
+    };
+    let image = render_html_with::<TinySkiaImageRenderer>(html, &config);
+
+    // The border inherits the body's color
+    assert_eq!(image.pixel(5, 5), [0, 0, 255, 255]);
+    assert_eq!(image.pixel(20, 20), WHITE);
+    // The shadow and gradient use their elements' colors
+    assert_eq!(image.pixel(40, 60), [0, 128, 0, 255]);
+    assert_eq!(image.pixel(10, 60), WHITE);
+    assert_eq!(image.pixel(20, 90), [255, 0, 0, 255]);
+}
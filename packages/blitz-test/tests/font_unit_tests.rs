@@ -0,0 +1,26 @@
+//! Font-relative units, measured in the bundled fonts
+
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_test::{RenderConfig, render_html_with};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+#[test]
+fn ch_and_ex_are_measured_in_the_first_available_font() {
+    // DejaVu Sans has a `0` 0.636em wide and an x-height of 0.547em, rather than the 0.6em and
+    // 0.5em used for fonts without them
+    let html = "<body style='margin: 0; background: white'>
+          <div style='font: 100px DejaVu Sans; width: 1ch; height: 1ex; background: red'></div>
+        </body>";
+    let config = RenderConfig {
+        width: 80,
+        height: 80,
+        ..RenderConfig::default()
+    };
+    let image = render_html_with::<TinySkiaImageRenderer>(html, &config);
+
+    assert_eq!(image.pixel(61, 52), RED);
+    assert_eq!(image.pixel(66, 52), WHITE);
+    assert_eq!(image.pixel(61, 57), WHITE);
+}
@@ -0,0 +1,49 @@
+//! Renders compared to the golden images in `tests/snapshots`
+
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_test::{RenderConfig, Snapshots, render_html_with};
+use blitz_text::{EnhancedFontSystem, FontSystem, new_font_system};
+
+fn snapshots() -> Snapshots<TinySkiaImageRenderer> {
+    Snapshots::for_renderer(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots")).with_config(
+        RenderConfig {
+            width: 30,
+            height: 20,
+            scale: 2.0,
+            ..RenderConfig::default()
+        },
+    )
+}
+
+#[test]
+fn boxes_are_painted_at_the_device_pixel_ratio() {
+    snapshots().assert_html(
+        "boxes",
+        "<body style='margin: 0; background: white'>
+          <div style='width: 10px; height: 10px; background: red'></div>
+          <div style='margin-left: 10px; width: 20px; height: 10px; background: blue'></div>
+        </body>",
+    );
+}
+
+#[test]
+fn font_systems_hold_the_bundled_fonts_under_the_same_ids() {
+    let config = RenderConfig {
+        width: 1,
+        height: 1,
+        ..RenderConfig::default()
+    };
+    render_html_with::<TinySkiaImageRenderer>("", &config);
+
+    let faces = |font_system: &FontSystem| {
+        let faces = font_system.db().faces();
+        faces.map(|face| (face.id, face.families[0].0.clone())).collect::<Vec<_>>()
+    };
+    let layout = faces(&new_font_system());
+    // The four bundled fonts and the embedded fallback, whatever is installed on the machine
+    assert_eq!(layout.len(), 5);
+    let families = layout.iter().filter(|(_, family)| family.starts_with("DejaVu")).count();
+    assert_eq!(families, 4);
+    // Fonts are found by the id text was shaped with when drawing glyph runs
+    assert_eq!(faces(EnhancedFontSystem::new().inner()), layout);
+}
@@ -0,0 +1,76 @@
+//! Laying out MathML, and text raised and lowered by `<sup>` and `<sub>`
+
+use std::sync::Arc;
+
+use blitz_dom::{BaseDocument, DocumentConfig};
+use blitz_html::HtmlDocument;
+use blitz_traits::net::DummyNetProvider;
+
+/// Lay out `html`, in a document without margins
+fn layout(html: &str) -> BaseDocument {
+    let html = format!("<body style='margin: 0'>{html}</body>");
+    let config = DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    };
+    let mut doc = HtmlDocument::from_html(&html, config).into_inner();
+    doc.resolve();
+    doc
+}
+
+/// The position and size of the element with `id`, as `[x, y, width, height]`
+fn bounds(doc: &BaseDocument, id: &str) -> [f32; 4] {
+    let node_id = doc.query_selector(&format!("#{id}")).unwrap().unwrap();
+    let node = doc.get_node(node_id).unwrap();
+    let position = node.absolute_position(0.0, 0.0);
+    let size = node.final_layout.size;
+    [position.x, position.y, size.width, size.height]
+}
+
+#[test]
+fn scripts_follow_their_base_raised_or_lowered() {
+    let doc = layout(
+        "<math style='font-size: 20px'>
+          <msup><mi id=sup-base>x</mi><mn id=sup>2</mn></msup>
+          <msub><mi id=sub-base>y</mi><mn id=sub>1</mn></msub>
+        </math>",
+    );
+    let [base_x, base_y, base_width, _] = bounds(&doc, "sup-base");
+    let [sup_x, sup_y, _, _] = bounds(&doc, "sup");
+    assert!(sup_x >= base_x + base_width, "{sup_x} after {base_x} + {base_width}");
+    assert!(sup_y < base_y, "{sup_y} above {base_y}");
+
+    let [base_x, base_y, base_width, base_height] = bounds(&doc, "sub-base");
+    let [sub_x, sub_y, _, sub_height] = bounds(&doc, "sub");
+    assert!(sub_x >= base_x + base_width, "{sub_x} after {base_x} + {base_width}");
+    assert!(sub_y + sub_height > base_y + base_height);
+}
+
+#[test]
+fn fractions_stack_the_numerator_over_the_denominator() {
+    let doc = layout(
+        "<math style='font-size: 20px'>
+          <mfrac><mn id=numerator>1</mn><mn id=denominator>22</mn></mfrac>
+        </math>",
+    );
+    let [numerator_x, numerator_y, numerator_width, numerator_height] = bounds(&doc, "numerator");
+    let [denominator_x, denominator_y, denominator_width, _] = bounds(&doc, "denominator");
+    assert!(numerator_y + numerator_height <= denominator_y);
+    // Both are laid out at the width of the fraction
+    assert_eq!(numerator_x, denominator_x);
+    assert_eq!(numerator_width, denominator_width);
+}
+
+#[test]
+fn raised_and_lowered_text_makes_its_line_taller() {
+    let height = |inner: &str| {
+        let doc = layout(&format!(
+            "<div id=line style='font-size: 20px; line-height: 20px'>x{inner}</div>"
+        ));
+        bounds(&doc, "line")[3]
+    };
+    let plain = height("");
+    assert!(height("<sup>2</sup>") > plain);
+    assert!(height("<sub>2</sub>") > plain);
+    assert!(height("<span style='vertical-align: 10px'>2</span>") >= plain + 10.0);
+}
@@ -0,0 +1,52 @@
+//! Painting elements with opacity without a layer, where they only draw one thing
+
+use std::sync::Arc;
+
+use anyrender::DisplayList;
+use blitz_dom::DocumentConfig;
+use blitz_html::HtmlDocument;
+use blitz_paint::{LayerStats, layer_stats, paint_scene};
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::shell::{ColorScheme, Viewport};
+
+/// Paint a box styled with `style` (and holding `contents`), returning the layer stats of the
+/// scene
+fn paint_box(style: &str, contents: &str) -> LayerStats {
+    let html = format!(
+        "<body style='margin: 0'><div style='width: 100px; height: 100px; {style}'>{contents}</div>\
+         </body>"
+    );
+    let mut doc = HtmlDocument::from_html(
+        &html,
+        DocumentConfig {
+            viewport: Some(Viewport::new(200, 200, 1.0, ColorScheme::Light)),
+            net_provider: Some(Arc::new(DummyNetProvider)),
+            ..DocumentConfig::for_testing()
+        },
+    )
+    .into_inner();
+    doc.resolve();
+
+    paint_scene(&mut DisplayList::new(), &doc, 1.0, 200, 200);
+    layer_stats()
+}
+
+#[test]
+fn opacity_is_folded_into_elements_which_draw_one_thing() {
+    let opaque = paint_box("background: teal", "");
+    assert_eq!(opaque.elided, 0);
+
+    // A background alone is drawn with the opacity in its brush
+    let background = paint_box("background: teal; opacity: 0.5", "");
+    assert_eq!(background.elided, 1);
+    assert_eq!(background.used, opaque.used);
+
+    // A background and a border are drawn into a layer, as are elements with children
+    let bordered = paint_box("background: teal; border: 2px solid black; opacity: 0.5", "");
+    assert_eq!(bordered.elided, 0);
+    assert_eq!(bordered.used, opaque.used + 1);
+
+    let parent = paint_box("opacity: 0.5", "<div style='height: 50px; background: teal'></div>");
+    assert_eq!(parent.elided, 0);
+    assert_eq!(parent.used, opaque.used + 1);
+}
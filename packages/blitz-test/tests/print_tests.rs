@@ -0,0 +1,64 @@
+//! Splitting documents into pages for print
+
+use std::sync::Arc;
+
+use blitz_dom::pagination::{PageConfig, PageMargins};
+use blitz_dom::{BaseDocument, DocumentConfig};
+use blitz_html::HtmlDocument;
+use blitz_traits::net::DummyNetProvider;
+
+/// The document for `html`, laid out for the screen
+fn layout(html: &str) -> BaseDocument {
+    let config = DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    };
+    let mut doc = HtmlDocument::from_html(html, config).into_inner();
+    doc.resolve();
+    doc
+}
+
+/// The top of the element with `id`
+fn top(doc: &BaseDocument, id: &str) -> f32 {
+    let node_id = doc.query_selector(&format!("#{id}")).unwrap().unwrap();
+    doc.get_node(node_id).unwrap().absolute_position(0.0, 0.0).y
+}
+
+#[test]
+fn documents_are_paginated_with_print_styles_and_restored() {
+    let mut doc = layout(
+        "<style>
+          @media print { #screen-only { display: none } }
+          @page { margin: 10px }
+          body { margin: 0 }
+          div { height: 300px }
+        </style>
+        <body>
+          <div id=screen-only></div>
+          <div id=first></div>
+          <div id=second style='break-before: page'></div>
+        </body>",
+    );
+    assert_eq!(top(&doc, "first"), 300.0);
+
+    let config = PageConfig {
+        width: 400.0,
+        height: 500.0,
+        margins: PageMargins::uniform(50.0),
+    };
+    let pages = doc.paginate(config, |doc, pagination| {
+        // The `@page` margins replace the default ones
+        assert_eq!(pagination.config.margins, PageMargins::uniform(10.0));
+        assert_eq!(top(doc, "first"), 0.0);
+        pagination
+            .pages
+            .iter()
+            .map(|page| (page.top, page.height))
+            .collect::<Vec<_>>()
+    });
+    // The second box starts a page, though the first has room for some of it
+    assert_eq!(pages, [(0.0, 300.0), (300.0, 300.0)]);
+
+    // The document is laid out for the screen again
+    assert_eq!(top(&doc, "first"), 300.0);
+}
@@ -0,0 +1,60 @@
+//! Comparing images and blessing golden images, without rendering
+
+use blitz_test::{RgbaImage, SnapshotError, Snapshots, Tolerance, compare, pixel_difference};
+
+fn filled(width: u32, height: u32, pixel: [u8; 4]) -> RgbaImage {
+    RgbaImage {
+        width,
+        height,
+        data: pixel.repeat(width as usize * height as usize),
+    }
+}
+
+#[test]
+fn perceptual_difference() {
+    assert_eq!(pixel_difference(&[10, 20, 30, 255], &[10, 20, 30, 255]), 0.0);
+    assert!(pixel_difference(&[0, 0, 0, 255], &[255, 255, 255, 255]) > 0.9);
+    assert!(pixel_difference(&[100, 100, 100, 255], &[101, 100, 100, 255]) < 0.01);
+    // Fully transparent pixels look the same whatever their color
+    assert_eq!(pixel_difference(&[255, 0, 0, 0], &[0, 0, 255, 0]), 0.0);
+}
+
+#[test]
+fn differing_pixels_are_counted() {
+    let expected = filled(4, 4, [255, 255, 255, 255]);
+    let mut actual = expected.clone();
+    actual.data[..4].copy_from_slice(&[0, 0, 0, 255]);
+    actual.data[4..8].copy_from_slice(&[254, 255, 255, 255]);
+
+    let comparison = compare(&expected, &actual, 0.1).unwrap();
+    assert_eq!(comparison.differing_pixels, 1);
+    assert_eq!(comparison.diff_image.pixel(0, 0), [255, 0, 0, 255]);
+    assert!(!comparison.matches(&Tolerance::default()));
+    assert!(comparison.matches(&Tolerance {
+        threshold: 0.1,
+        max_differing_pixels: 1,
+    }));
+
+    assert!(compare(&expected, &filled(4, 5, [255; 4]), 0.1).is_none());
+}
+
+#[test]
+fn blessing_writes_golden_images() {
+    let dir = tempfile::tempdir().unwrap();
+    let image = filled(3, 2, [0, 128, 255, 255]);
+    let snapshots = Snapshots::new(dir.path()).with_bless(false);
+
+    let missing = snapshots.check_image("box", &image);
+    assert!(matches!(missing, Err(SnapshotError::Missing { .. })));
+    assert!(dir.path().join("box.actual.png").exists());
+
+    let blessing = Snapshots::new(dir.path()).with_bless(true);
+    blessing.check_image("box", &image).unwrap();
+    assert!(!dir.path().join("box.actual.png").exists());
+    snapshots.check_image("box", &image).unwrap();
+
+    let changed = filled(3, 2, [255, 0, 0, 255]);
+    let mismatch = snapshots.check_image("box", &changed);
+    assert!(matches!(mismatch, Err(SnapshotError::Mismatch { differing_pixels: 6, .. })));
+    assert!(dir.path().join("box.diff.png").exists());
+}
@@ -0,0 +1,115 @@
+//! Laying out and painting tables
+
+use std::sync::Arc;
+
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_dom::{BaseDocument, DocumentConfig};
+use blitz_html::HtmlDocument;
+use blitz_test::{RenderConfig, render_html_with};
+use blitz_traits::net::DummyNetProvider;
+
+/// Lay out `html`, in a document without margins
+fn layout(html: &str) -> BaseDocument {
+    let html = format!("<body style='margin: 0'>{html}</body>");
+    let config = DocumentConfig {
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        ..DocumentConfig::for_testing()
+    };
+    let mut doc = HtmlDocument::from_html(&html, config).into_inner();
+    doc.resolve();
+    doc
+}
+
+/// The position and size of the element with `id`, as `[x, y, width, height]`
+fn bounds(doc: &BaseDocument, id: &str) -> [f32; 4] {
+    let node_id = doc.query_selector(&format!("#{id}")).unwrap().unwrap();
+    let node = doc.get_node(node_id).unwrap();
+    let position = node.absolute_position(0.0, 0.0);
+    let size = node.final_layout.size;
+    [position.x, position.y, size.width, size.height]
+}
+
+#[test]
+fn captions_go_above_and_below_the_rows() {
+    let doc = layout(
+        "<table style='border-spacing: 0'>
+          <caption id=top style='height: 20px'></caption>
+          <caption id=bottom style='caption-side: bottom; height: 10px'></caption>
+          <tr><td id=cell style='padding: 0; width: 50px; height: 30px'></td></tr>
+        </table>",
+    );
+    let [_, top_y, top_width, _] = bounds(&doc, "top");
+    let [_, cell_y, cell_width, _] = bounds(&doc, "cell");
+    let [_, bottom_y, _, _] = bounds(&doc, "bottom");
+    assert_eq!((top_y, cell_y, bottom_y), (0.0, 20.0, 50.0));
+    assert_eq!(top_width, cell_width);
+}
+
+#[test]
+fn fixed_tables_take_column_widths_from_the_first_row() {
+    let doc = layout(
+        "<table style='table-layout: fixed; width: 300px; border-spacing: 0'>
+          <tr><td id=sized style='padding: 0; width: 100px'></td><td id=auto></td></tr>
+          <tr><td style='padding: 0; width: 250px'>ignored</td><td></td></tr>
+        </table>",
+    );
+    assert_eq!(bounds(&doc, "sized")[2], 100.0);
+    assert_eq!(bounds(&doc, "auto")[2], 200.0);
+}
+
+#[test]
+fn percentage_widths_are_of_the_table() {
+    let doc = layout(
+        "<table style='width: 400px; border-spacing: 0'>
+          <tr><td id=quarter style='padding: 0; width: 25%'></td><td id=rest></td></tr>
+        </table>",
+    );
+    assert_eq!(bounds(&doc, "quarter")[2], 100.0);
+    assert_eq!(bounds(&doc, "rest")[2], 300.0);
+}
+
+#[test]
+fn cells_spanning_rows_push_later_cells_aside() {
+    let doc = layout(
+        "<table style='border-spacing: 0'>
+          <tr>
+            <td id=spanning rowspan=2 style='padding: 0; width: 10px'></td>
+            <td id=first style='padding: 0; height: 20px'></td>
+          </tr>
+          <tr><td id=second style='padding: 0; height: 30px'></td></tr>
+        </table>",
+    );
+    let [_, _, _, spanning_height] = bounds(&doc, "spanning");
+    let [first_x, first_y, _, _] = bounds(&doc, "first");
+    let [second_x, second_y, _, _] = bounds(&doc, "second");
+    assert_eq!(spanning_height, 50.0);
+    assert_eq!(second_x, first_x);
+    assert_eq!(second_y, first_y + 20.0);
+}
+
+#[test]
+fn shared_collapsed_borders_are_painted_once() {
+    // Two cells with translucent 10px borders, which collapse to one 10px edge between them
+    let cell = "<td style='padding: 0; width: 20px; height: 20px;
+      border: 10px solid rgba(255, 0, 0, 0.5)'></td>";
+    let html = format!(
+        "<body style='margin: 0; background: white'>
+          <table style='border-collapse: collapse'><tr>{cell}{cell}</tr></table>
+        </body>"
+    );
+    let config = RenderConfig {
+        width: 80,
+        height: 50,
+        ..RenderConfig::default()
+    };
+    let image = render_html_with::<TinySkiaImageRenderer>(&html, &config);
+
+    // The outer edge is centered on x = 5 and the shared one on x = 35, and their corners with
+    // the top edge on y = 5
+    let outer = image.pixel(5, 20);
+    assert_ne!(outer, [255, 255, 255, 255]);
+    assert_ne!(outer, [255, 0, 0, 255]);
+    assert_eq!(image.pixel(35, 20), outer);
+    assert_eq!(image.pixel(35, 5), outer);
+    assert_eq!(image.pixel(20, 5), outer);
+}
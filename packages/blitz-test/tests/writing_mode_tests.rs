@@ -0,0 +1,46 @@
+//! Painting text in vertical writing modes
+
+use anyrender_tinyskia::TinySkiaImageRenderer;
+use blitz_test::{RenderConfig, RgbaImage, render_html_with};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// Render a long line of text over a short one in a 100px square box with `writing_mode`
+fn render_lines(writing_mode: &str) -> RgbaImage {
+    let html = format!(
+        "<body style='margin: 0; background: white'>
+          <div style='writing-mode: {writing_mode}; width: 100px; height: 100px;
+            font-size: 20px; line-height: 20px; color: black'>XXXX<br>X</div>
+        </body>"
+    );
+    let config = RenderConfig {
+        width: 100,
+        height: 100,
+        ..RenderConfig::default()
+    };
+    render_html_with::<TinySkiaImageRenderer>(&html, &config)
+}
+
+/// How far down the ink between `left` and `right` reaches, or 0 if there is none
+fn ink_depth(image: &RgbaImage, left: u32, right: u32) -> u32 {
+    (0..image.height)
+        .filter(|&y| (left..right).any(|x| image.pixel(x, y) != WHITE))
+        .map(|y| y + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+#[test]
+fn vertical_lines_stack_in_their_block_direction() {
+    // `vertical-rl` puts the first (long) line on the right, with the next to its left
+    let image = render_lines("vertical-rl");
+    assert!(ink_depth(&image, 80, 100) > 40);
+    assert!((1..30).contains(&ink_depth(&image, 60, 80)));
+    assert_eq!(ink_depth(&image, 0, 60), 0);
+
+    // `vertical-lr` puts it on the left, with the next to its right
+    let image = render_lines("vertical-lr");
+    assert!(ink_depth(&image, 0, 20) > 40);
+    assert!((1..30).contains(&ink_depth(&image, 20, 40)));
+    assert_eq!(ink_depth(&image, 40, 100), 0);
+}
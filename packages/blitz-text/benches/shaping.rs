@@ -0,0 +1,95 @@
+//! Cost of each stage of the shaping pipeline for a range of scripts
+//!
+//! Every input is benchmarked as a whole (`<input>/pipeline`) and per stage
+//! (`<input>/analysis`, `<input>/bidi`, ...), with the stage timings taken from
+//! [`ShapingProfiler`]. To catch regressions, save a baseline before making a change and compare
+//! against it afterwards:
+//!
+//! ```sh
+//! cargo bench -p blitz-text --bench shaping -- --save-baseline before
+//! cargo bench -p blitz-text --bench shaping -- --baseline before
+//! ```
+
+use std::hint::black_box;
+use std::time::Duration;
+
+use blitz_text::{
+    ensure_embedded_fallback, Attrs, Family, FontSystem, ShapingProfiler, ShapingStage,
+};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// Width to break lines at, in pixels
+const MAX_WIDTH: f32 = 400.0;
+
+const INPUTS: &[(&str, &str)] = &[
+    (
+        "latin",
+        "The quick brown fox jumps over the lazy dog. Pack my box with five dozen liquor jugs, \
+         and sphinx of black quartz, judge my vow. Ünïcödé accents sneak into otherwise plain text.",
+    ),
+    (
+        "arabic",
+        "نص حكيم له سر قاطع وذو شأن عظيم مكتوب على ثوب أخضر ومغلف بجلد أزرق. \
+         صِف خَلقَ خَودِ كَمِثلِ الشَمسِ إِذ بَزَغَت يَحظى الضَجيعُ بِها نَجلاءَ مِعطارِ.",
+    ),
+    (
+        "mixed_bidi",
+        "The title is مرحبا بالعالم in Arabic and שלום עולם in Hebrew, followed by 123 numbers \
+         and (parenthesised עברית text) to exercise bracket pairing.",
+    ),
+    (
+        "devanagari",
+        "ऋषियों को सताने वाले दुष्ट राक्षसों के राजा रावण का सर्वनाश करने वाले विष्णुवतार भगवान \
+         श्रीराम, अयोध्या के महाराज दशरथ के बड़े सपुत्र थे।",
+    ),
+    (
+        "cjk",
+        "天地玄黄，宇宙洪荒。日月盈昃，辰宿列张。寒来暑往，秋收冬藏。いろはにほへと ちりぬるを \
+         わかよたれそ つねならむ。다람쥐 헌 쳇바퀴에 타고파.",
+    ),
+];
+
+fn profiler() -> ShapingProfiler {
+    let mut font_system = FontSystem::new();
+    // Make sure something can be shaped even on machines without system fonts
+    ensure_embedded_fallback(&mut font_system);
+    ShapingProfiler::new(font_system)
+}
+
+fn bench_shaping(c: &mut Criterion) {
+    let mut profiler = profiler();
+    let attrs = Attrs::new().family(Family::SansSerif);
+
+    for (name, text) in INPUTS {
+        let mut group = c.benchmark_group(*name);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+
+        group.bench_function("pipeline", |b| {
+            b.iter(|| {
+                profiler
+                    .profile(black_box(text), attrs.clone(), Some(MAX_WIDTH))
+                    .expect("shaping succeeds")
+            })
+        });
+
+        for stage in ShapingStage::ALL {
+            group.bench_function(stage.name().replace(' ', "_"), |b| {
+                b.iter_custom(|iters| {
+                    let mut total = Duration::ZERO;
+                    for _ in 0..iters {
+                        let profile = profiler
+                            .profile(black_box(text), attrs.clone(), Some(MAX_WIDTH))
+                            .expect("shaping succeeds");
+                        total += profile.stage(stage);
+                    }
+                    total
+                })
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_shaping);
+criterion_main!(benches);
@@ -0,0 +1,134 @@
+#!/usr/bin/env python3
+"""Record HarfBuzz reference shaping output for the shaping conformance corpus.
+
+Shapes every single-direction case in tests/shaping/corpus.json with `hb-shape` (or with the
+HarfBuzz library directly, where `hb-shape` isn't installed), using the font fontconfig matches for
+the case's family, and stores the result as the case's `reference`. Run it after adding cases or
+changing the fonts used, with the same fonts installed that the tests run with (see
+BLITZ_SHAPING_FONTS in tests/shaping_conformance_tests.rs).
+
+Usage: scripts/record_shaping_references.py [path/to/corpus.json]
+"""
+
+import ctypes
+import ctypes.util
+import json
+import pathlib
+import shutil
+import subprocess
+import sys
+
+CORPUS = pathlib.Path(__file__).resolve().parent.parent / "tests" / "shaping" / "corpus.json"
+
+
+def font_file(family):
+    result = subprocess.run(
+        ["fc-match", "--format=%{file}|%{family}", family],
+        capture_output=True,
+        text=True,
+        check=True,
+    )
+    path, matched = result.stdout.split("|", 1)
+    if family.lower() not in (name.lower() for name in matched.split(",")):
+        return None
+    return path
+
+
+class GlyphInfo(ctypes.Structure):
+    _fields_ = [
+        ("codepoint", ctypes.c_uint32),
+        ("mask", ctypes.c_uint32),
+        ("cluster", ctypes.c_uint32),
+        ("var1", ctypes.c_uint32),
+        ("var2", ctypes.c_uint32),
+    ]
+
+
+class GlyphPosition(ctypes.Structure):
+    _fields_ = [
+        ("x_advance", ctypes.c_int32),
+        ("y_advance", ctypes.c_int32),
+        ("x_offset", ctypes.c_int32),
+        ("y_offset", ctypes.c_int32),
+        ("var", ctypes.c_uint32),
+    ]
+
+
+def shape_with_library(font, text):
+    """Shape like `hb-shape --font-size=16` does, with libharfbuzz"""
+    hb = ctypes.CDLL(ctypes.util.find_library("harfbuzz") or "libharfbuzz.so.0")
+    for name in ("hb_blob_create_from_file", "hb_face_create", "hb_font_create"):
+        getattr(hb, name).restype = ctypes.c_void_p
+    hb.hb_buffer_create.restype = ctypes.c_void_p
+    hb.hb_face_create.argtypes = [ctypes.c_void_p, ctypes.c_uint]
+    hb.hb_font_create.argtypes = [ctypes.c_void_p]
+    hb.hb_font_set_scale.argtypes = [ctypes.c_void_p, ctypes.c_int, ctypes.c_int]
+    hb.hb_buffer_add_utf8.argtypes = [
+        ctypes.c_void_p,
+        ctypes.c_char_p,
+        ctypes.c_int,
+        ctypes.c_uint,
+        ctypes.c_int,
+    ]
+    hb.hb_buffer_guess_segment_properties.argtypes = [ctypes.c_void_p]
+    hb.hb_shape.argtypes = [ctypes.c_void_p, ctypes.c_void_p, ctypes.c_void_p, ctypes.c_uint]
+    hb.hb_buffer_get_glyph_infos.restype = ctypes.POINTER(GlyphInfo)
+    hb.hb_buffer_get_glyph_infos.argtypes = [ctypes.c_void_p, ctypes.POINTER(ctypes.c_uint)]
+    hb.hb_buffer_get_glyph_positions.restype = ctypes.POINTER(GlyphPosition)
+    hb.hb_buffer_get_glyph_positions.argtypes = [ctypes.c_void_p, ctypes.POINTER(ctypes.c_uint)]
+
+    blob = hb.hb_blob_create_from_file(font.encode())
+    face = hb.hb_face_create(blob, 0)
+    hb_font = hb.hb_font_create(face)
+    hb.hb_font_set_scale(hb_font, 16, 16)
+    buffer = hb.hb_buffer_create()
+    encoded = text.encode("utf-8")
+    hb.hb_buffer_add_utf8(buffer, encoded, len(encoded), 0, len(encoded))
+    hb.hb_buffer_guess_segment_properties(buffer)
+    hb.hb_shape(hb_font, buffer, None, 0)
+
+    count = ctypes.c_uint()
+    infos = hb.hb_buffer_get_glyph_infos(buffer, ctypes.byref(count))
+    positions = hb.hb_buffer_get_glyph_positions(buffer, None)
+    return [
+        {"g": infos[i].codepoint, "cl": infos[i].cluster, "ax": positions[i].x_advance}
+        for i in range(count.value)
+    ]
+
+
+def shape(font, text):
+    if shutil.which("hb-shape") is None:
+        return shape_with_library(font, text)
+    result = subprocess.run(
+        ["hb-shape", "--output-format=json", "--no-glyph-names", "--font-size=16", font, text],
+        capture_output=True,
+        text=True,
+        check=True,
+    )
+    # Only glyph ids, clusters and advances are compared
+    return [
+        {"g": glyph["g"], "cl": glyph["cl"], "ax": glyph["ax"]}
+        for glyph in json.loads(result.stdout)
+    ]
+
+
+def main():
+    path = pathlib.Path(sys.argv[1]) if len(sys.argv) > 1 else CORPUS
+    corpus = json.loads(path.read_text(encoding="utf-8"))
+
+    for case in corpus["cases"]:
+        directions = case.get("expect", {}).get("directions") or []
+        if len(directions) > 1:
+            continue
+        font = font_file(case["font"])
+        if font is None:
+            print(f"{case['name']}: font {case['font']!r} is not installed, skipping")
+            continue
+        case["reference"] = shape(font, case["text"])
+        print(f"{case['name']}: recorded {len(case['reference'])} glyphs")
+
+    path.write_text(json.dumps(corpus, ensure_ascii=False, indent=2) + "\n", encoding="utf-8")
+
+
+if __name__ == "__main__":
+    main()
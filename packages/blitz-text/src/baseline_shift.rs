@@ -0,0 +1,98 @@
+//! Baseline shifts for spans of inline text
+//!
+//! cosmyc-text places every glyph of a line on the same baseline, so text which should sit above
+//! or below it (`vertical-align: super`, `sub` or a length, as used by `<sup>` and `<sub>`) is
+//! shaped and broken into lines normally, and moved when it is drawn. The shift of a span is
+//! stored in the metadata of its [`Attrs`](cosmyc_text::Attrs), which every glyph shaped from the
+//! span carries along.
+//!
+//! The shift takes the high bits of the metadata, marked by [`SHIFT_TAG`], and the low bits keep
+//! the metadata the span already had (the id of the node the text belongs to). Metadata without
+//! the tag holds no shift.
+
+use cosmyc_text::{Buffer, LayoutGlyph};
+
+/// Marks metadata which holds a baseline shift
+const SHIFT_TAG: usize = 1 << (usize::BITS - 1);
+/// Shifts are stored in 1/64ths of a pixel, in the 24 bits below the tag
+const SHIFT_BITS: u32 = 24;
+const SHIFT_UNITS: f32 = 64.0;
+const SHIFT_OFFSET: u32 = usize::BITS - 1 - SHIFT_BITS;
+/// The bits which keep the other metadata of a span
+const METADATA_MASK: usize = (1 << SHIFT_OFFSET) - 1;
+
+/// Add a baseline shift (in pixels, positive values raising the text) to the glyph `metadata` of
+/// a span
+pub fn encode_baseline_shift(metadata: usize, shift: f32) -> usize {
+    let max = ((1 << (SHIFT_BITS - 1)) - 1) as f32;
+    let units = (shift * SHIFT_UNITS).round().clamp(-max, max) as i32;
+    let bits = (units as u32 & ((1 << SHIFT_BITS) - 1)) as usize;
+    (metadata & METADATA_MASK) | SHIFT_TAG | (bits << SHIFT_OFFSET)
+}
+
+/// Decode the baseline shift stored in glyph metadata, if there is one
+pub fn decode_baseline_shift(metadata: usize) -> Option<f32> {
+    if metadata & SHIFT_TAG == 0 {
+        return None;
+    }
+    // Sign-extend the shift's bits
+    let bits = (metadata >> SHIFT_OFFSET) as u32;
+    let units = (bits << (32 - SHIFT_BITS)) as i32 >> (32 - SHIFT_BITS);
+    Some(units as f32 / SHIFT_UNITS)
+}
+
+/// How far a glyph should be moved down from its line's baseline when drawn
+pub fn glyph_baseline_offset(glyph: &LayoutGlyph) -> f32 {
+    decode_baseline_shift(glyph.metadata).map_or(0.0, |shift| -shift)
+}
+
+/// Whether any laid out glyph in `buffer` is moved off its line's baseline
+pub fn has_baseline_shifts(buffer: &Buffer) -> bool {
+    buffer
+        .layout_runs()
+        .flat_map(|run| run.glyphs.iter())
+        .any(|glyph| glyph_baseline_offset(glyph) != 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_shifts() {
+        for shift in [0.0, 3.25, -4.5, 1000.0] {
+            let metadata = encode_baseline_shift(42, shift);
+            assert_eq!(decode_baseline_shift(metadata), Some(shift));
+            // The metadata the span had is kept
+            assert_eq!(metadata & METADATA_MASK, 42);
+        }
+        // Node ids and other metadata don't hold a shift
+        assert_eq!(decode_baseline_shift(42), None);
+    }
+
+    #[test]
+    fn shifted_spans_move_their_glyphs() {
+        use cosmyc_text::{Attrs, Metrics, Shaping};
+
+        crate::measurement::with_font_system(|font_system| {
+            let mut buffer = Buffer::new(font_system, Metrics::new(16.0, 20.0));
+            let mut raised = Attrs::new();
+            raised.metadata = encode_baseline_shift(0, 4.0);
+            let spans = [("x", Attrs::new()), ("2", raised)];
+            buffer.set_rich_text(font_system, spans, &Attrs::new(), Shaping::Advanced, None);
+            buffer.shape_until_scroll(font_system, false);
+
+            let offsets: Vec<f32> = buffer
+                .layout_runs()
+                .flat_map(|run| run.glyphs.iter().map(glyph_baseline_offset))
+                .collect();
+            assert_eq!(offsets, [0.0, -4.0]);
+            assert!(has_baseline_shifts(&buffer));
+
+            buffer.set_text(font_system, "x2", &Attrs::new(), Shaping::Advanced);
+            buffer.shape_until_scroll(font_system, false);
+            assert!(!has_baseline_shifts(&buffer));
+        })
+        .unwrap();
+    }
+}
@@ -71,7 +71,7 @@ impl BidiCache {
             .warm_tier_max_memory_bytes(128 * 1024 * 1024) // 128MB
             .cold_tier_max_size_bytes(512 * 1024 * 1024) // 512MB
             .compression_level(4)
-            .background_worker_threads(1)
+            .background_worker_threads(crate::runtime::cache_worker_threads(1))
             .cache_id(&cache_id)
             .build().await?;
 
@@ -120,25 +120,11 @@ impl BidiCache {
 impl Default for BidiCache {
     fn default() -> Self {
         // Since new() is async and Default can't be async, we use a blocking approach
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| {
-                    panic!("Failed to create BidiCache")
-                })
+        crate::runtime::block_on(async {
+            Self::new().await.unwrap_or_else(|_| {
+                panic!("Failed to create BidiCache")
             })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| {
-                        panic!("Failed to create BidiCache")
-                    })
-                })
-        }
+        })
     }
 }
 
@@ -74,7 +74,7 @@ impl BidiRenderer {
         text: &str,
         options: &BidiRenderOptions,
     ) -> Result<std::sync::Arc<ProcessedBidi>, BidiError> {
-        let start_time = std::time::Instant::now();
+        let start_time = web_time::Instant::now();
         let result = self.processor.process_bidi_text(text, options);
         let elapsed = start_time.elapsed().as_nanos() as u64;
 
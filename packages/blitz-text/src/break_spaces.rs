@@ -0,0 +1,161 @@
+//! Line breaks after preserved spaces, for `white-space: break-spaces`
+//!
+//! cosmyc-text lets the spaces at the end of a line hang past its end, as `pre-wrap` spaces do.
+//! `break-spaces` spaces don't hang: a line can break after any of them, including between two
+//! of them, see <https://drafts.csswg.org/css-text/#valdef-white-space-collapse-break-spaces>.
+//! The layout is left to break lines as usual, and each buffer line which a breakable space
+//! overflows is then split in two after the last space which fits. The two lines have no line
+//! ending between them, so the text of the buffer is unchanged, and lines split at one width are
+//! joined again before the buffer is laid out at another.
+
+use std::ops::Range;
+
+use cosmyc_text::{Buffer, FontSystem, LayoutGlyph, LineEnding, Wrap};
+
+/// Lay out `buffer` at its width, breaking lines after the spaces in `breakable` (byte ranges of
+/// the text of the whole buffer, including line endings) rather than letting them hang
+pub fn break_preserved_spaces(
+    buffer: &mut Buffer,
+    font_system: &mut FontSystem,
+    breakable: &[Range<usize>],
+) {
+    if breakable.is_empty() {
+        return;
+    }
+    join_split_lines(buffer);
+    buffer.shape_until_scroll(font_system, false);
+    let Some(width) = buffer.size().0.filter(|width| width.is_finite()) else {
+        return;
+    };
+    if buffer.wrap() == Wrap::None {
+        return;
+    }
+    while let Some((line_i, index)) = overflowing_space(buffer, width, breakable) {
+        let line = &mut buffer.lines[line_i];
+        let rest = line.split_off(index);
+        line.set_ending(LineEnding::None);
+        buffer.lines.insert(line_i + 1, rest);
+        buffer.shape_until_scroll(font_system, false);
+    }
+}
+
+/// Join lines which were split after a space, which are the lines other than the last without
+/// a line ending
+fn join_split_lines(buffer: &mut Buffer) {
+    let mut line_i = 0;
+    while line_i + 1 < buffer.lines.len() {
+        if buffer.lines[line_i].ending() != LineEnding::None {
+            line_i += 1;
+            continue;
+        }
+        let next = buffer.lines.remove(line_i + 1);
+        let ending = next.ending();
+        let line = &mut buffer.lines[line_i];
+        line.append(next);
+        line.set_ending(ending);
+    }
+}
+
+/// The buffer line which a breakable space overflows, and the byte offset in it at which the
+/// line should break
+fn overflowing_space(
+    buffer: &Buffer,
+    width: f32,
+    breakable: &[Range<usize>],
+) -> Option<(usize, usize)> {
+    let mut line_starts = Vec::with_capacity(buffer.lines.len());
+    let mut offset = 0;
+    for line in &buffer.lines {
+        line_starts.push(offset);
+        offset += line.text().len() + line.ending().as_str().len();
+    }
+
+    for run in buffer.layout_runs() {
+        if run.rtl {
+            continue;
+        }
+        let line_start = line_starts[run.line_i];
+        let is_breakable_space = |glyph: &LayoutGlyph| {
+            matches!(run.text.get(glyph.start..glyph.end), Some(" " | "\t"))
+                && breakable
+                    .iter()
+                    .any(|range| range.contains(&(line_start + glyph.start)))
+        };
+        // Alignment moves the whole line, so widths are measured from its first glyph
+        let Some(left) = run.glyphs.first().map(|glyph| glyph.x) else {
+            continue;
+        };
+        let Some(overflow) = run
+            .glyphs
+            .iter()
+            .position(|glyph| glyph.x - left + glyph.w > width)
+        else {
+            continue;
+        };
+        let space = &run.glyphs[overflow];
+        if !is_breakable_space(space) {
+            continue;
+        }
+
+        // Break before the overflowing space if another space precedes it. Otherwise it follows
+        // a word, so the line breaks before that word if it can, and after the space if not.
+        let fitting = &run.glyphs[..overflow];
+        let index = match fitting.last() {
+            Some(last) if is_breakable_space(last) => space.start,
+            _ => match fitting.iter().rposition(is_breakable_space) {
+                Some(before_word) => fitting[before_word].end,
+                None => space.end,
+            },
+        };
+        if index > 0 && index < run.text.len() {
+            return Some((run.line_i, index));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmyc_text::{Attrs, Metrics, Shaping};
+
+    use super::*;
+
+    fn line_texts(buffer: &Buffer) -> Vec<&str> {
+        buffer.lines.iter().map(|line| line.text()).collect()
+    }
+
+    #[test]
+    fn spaces_wrap_instead_of_hanging() {
+        crate::measurement::with_font_system(|font_system| {
+            let mut buffer = Buffer::new(font_system, Metrics::new(16.0, 20.0));
+            buffer.set_wrap(font_system, Wrap::Word);
+            let text = "ab      cd\nef";
+            buffer.set_text(font_system, text, &Attrs::new(), Shaping::Advanced);
+            buffer.set_size(font_system, None, None);
+            buffer.shape_until_scroll(font_system, false);
+            let (ab_width, space_width) = {
+                let run = buffer.layout_runs().next().unwrap();
+                (run.glyphs[2].x, run.glyphs[2].w)
+            };
+
+            // Room for "ab" and three and a half spaces: the rest of the spaces wrap
+            let width = ab_width + 3.5 * space_width;
+            buffer.set_size(font_system, Some(width), None);
+            break_preserved_spaces(&mut buffer, font_system, &[0..text.len()]);
+            assert_eq!(line_texts(&buffer), ["ab   ", "   cd", "ef"]);
+            assert_eq!(buffer.lines[0].ending(), LineEnding::None);
+            assert_eq!(buffer.lines[1].ending(), LineEnding::Lf);
+
+            // At a width which fits the whole line, the split lines are joined again
+            buffer.set_size(font_system, None, None);
+            break_preserved_spaces(&mut buffer, font_system, &[0..text.len()]);
+            assert_eq!(line_texts(&buffer), ["ab      cd", "ef"]);
+
+            // Spaces outside the breakable ranges hang as usual
+            buffer.set_size(font_system, Some(width), None);
+            break_preserved_spaces(&mut buffer, font_system, &[10..text.len()]);
+            assert_eq!(line_texts(&buffer), ["ab      cd", "ef"]);
+        })
+        .unwrap();
+    }
+}
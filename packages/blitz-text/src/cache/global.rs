@@ -1,5 +1,6 @@
-use goldylox::{Goldylox, GoldyloxBuilder};
+use goldylox::GoldyloxBuilder;
 use std::sync::{Arc, OnceLock};
+use crate::cache::managed::ManagedGoldylox;
 use crate::shaping::ShapedText;
 use crate::measurement::types::TextMeasurement;
 
@@ -9,13 +10,13 @@ use crate::measurement::types::TextMeasurement;
 /// the application lifecycle, preventing the creation of multiple expensive cache instances.
 pub struct GlobalCacheManager {
     /// Cache for shaped text results (used by TextShaper)
-    text_shaping_cache: Arc<Goldylox<String, ShapedText>>,
+    text_shaping_cache: Arc<ManagedGoldylox<String, ShapedText>>,
     
     /// Cache for text measurements (used by measurement components)
-    text_measurement_cache: Arc<Goldylox<String, TextMeasurement>>,
+    text_measurement_cache: Arc<ManagedGoldylox<String, TextMeasurement>>,
     
     /// Cache for serialized data (font metrics, bidi, features, etc.)
-    serialized_cache: Arc<Goldylox<String, Vec<u8>>>,
+    serialized_cache: Arc<ManagedGoldylox<String, Vec<u8>>>,
 }
 
 impl GlobalCacheManager {
@@ -27,21 +28,10 @@ impl GlobalCacheManager {
         INSTANCE.get_or_init(|| {
             println!("🚀 Initializing global Goldylox cache manager (singleton)");
             
-            // Use tokio runtime to block on async build() calls
-            use tokio::runtime::Handle;
-            
-            let manager = if let Ok(handle) = Handle::try_current() {
-                handle.block_on(async {
-                    Self::create_manager().await
-                })
-            } else {
-                // No runtime available, create one temporarily
-                tokio::runtime::Runtime::new()
-                    .expect("Failed to create tokio runtime")
-                    .block_on(async {
-                        Self::create_manager().await
-                    })
-            };
+            // Block on the async build() calls
+            let manager = crate::runtime::block_on(async {
+                Self::create_manager().await
+            });
             
             println!("✅ Global Goldylox cache manager initialized successfully");
             manager
@@ -67,7 +57,7 @@ impl GlobalCacheManager {
             .warm_tier_max_memory_bytes(256 * 1024 * 1024) // 256MB
             .cold_tier_max_size_bytes(2 * 1024 * 1024 * 1024) // 2GB
             .compression_level(8)
-            .background_worker_threads(8)
+            .background_worker_threads(crate::runtime::cache_worker_threads(8))
             .cache_id(&format!("blitz_text_measurement_{}", std::process::id()))
             .build().await
             .map_err(|e| {
@@ -86,40 +76,44 @@ impl GlobalCacheManager {
             .build().await
             .expect("Failed to initialize global serialized cache");
         
+        // Registered with the memory budget shared by the Blitz crates
         GlobalCacheManager {
-            text_shaping_cache: Arc::new(text_shaping_cache),
-            text_measurement_cache: Arc::new(text_measurement_cache),
-            serialized_cache: Arc::new(serialized_cache),
+            text_shaping_cache: ManagedGoldylox::register("text shaping", text_shaping_cache),
+            text_measurement_cache: ManagedGoldylox::register(
+                "text measurements",
+                text_measurement_cache,
+            ),
+            serialized_cache: ManagedGoldylox::register("serialized text data", serialized_cache),
         }
     }
     
     /// Get the shared text shaping cache instance
-    pub fn text_shaping_cache(&self) -> Arc<Goldylox<String, ShapedText>> {
+    pub fn text_shaping_cache(&self) -> Arc<ManagedGoldylox<String, ShapedText>> {
         self.text_shaping_cache.clone()
     }
     
     /// Get the shared text measurement cache instance
-    pub fn text_measurement_cache(&self) -> Arc<Goldylox<String, TextMeasurement>> {
+    pub fn text_measurement_cache(&self) -> Arc<ManagedGoldylox<String, TextMeasurement>> {
         self.text_measurement_cache.clone()
     }
     
     /// Get the shared serialized data cache instance
-    pub fn serialized_cache(&self) -> Arc<Goldylox<String, Vec<u8>>> {
+    pub fn serialized_cache(&self) -> Arc<ManagedGoldylox<String, Vec<u8>>> {
         self.serialized_cache.clone()
     }
 }
 
 /// Convenience function to get the text shaping cache
-pub fn get_text_shaping_cache() -> Arc<Goldylox<String, ShapedText>> {
+pub fn get_text_shaping_cache() -> Arc<ManagedGoldylox<String, ShapedText>> {
     GlobalCacheManager::instance().text_shaping_cache()
 }
 
 /// Convenience function to get the text measurement cache
-pub fn get_text_measurement_cache() -> Arc<Goldylox<String, TextMeasurement>> {
+pub fn get_text_measurement_cache() -> Arc<ManagedGoldylox<String, TextMeasurement>> {
     GlobalCacheManager::instance().text_measurement_cache()
 }
 
 /// Convenience function to get the serialized data cache
-pub fn get_serialized_cache() -> Arc<Goldylox<String, Vec<u8>>> {
+pub fn get_serialized_cache() -> Arc<ManagedGoldylox<String, Vec<u8>>> {
     GlobalCacheManager::instance().serialized_cache()
 }
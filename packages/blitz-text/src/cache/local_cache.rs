@@ -25,7 +25,7 @@ where
             .warm_tier_max_memory_bytes(64 * 1024 * 1024) // 64MB
             .cold_tier_max_size_bytes(128 * 1024 * 1024) // 128MB
             .compression_level(5)
-            .background_worker_threads(1)
+            .background_worker_threads(crate::runtime::cache_worker_threads(1))
             .cache_id("local_cache")
             .build()?;
         
@@ -0,0 +1,204 @@
+//! Goldylox caches whose memory is managed by the [`CacheCoordinator`]
+//!
+//! Goldylox only reports how much memory a cache holds altogether, and can't evict down to a
+//! size or list its entries. So the owners of a managed cache [record](ManagedGoldylox::record)
+//! the keys they put in it, and a cache asked to hold less than it does removes the least
+//! recently recorded entries until it holds little enough. Removing entries is async, so it
+//! happens on a thread of its own rather than blocking the caller (which may be a runtime's
+//! worker).
+
+use blitz_traits::cache::{CacheCoordinator, ManagedCache};
+use goldylox::Goldylox;
+use goldylox::traits::{CacheKey, CacheValue};
+use lru::LruCache;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The number of entries removed between checks of the cache's memory usage
+const EVICTION_BATCH: usize = 16;
+
+/// A Goldylox cache registered with the global [`CacheCoordinator`]
+///
+/// The cache stays registered for as long as the returned handle is alive, so it's kept next to
+/// the cache it manages. It derefs to the cache; entries put in it should be
+/// [recorded](Self::record) so that they can be evicted.
+pub struct ManagedGoldylox<K, V>
+where
+    K: CacheKey + Serialize + DeserializeOwned,
+    V: CacheValue + Clone + Serialize + DeserializeOwned,
+{
+    name: &'static str,
+    cache: Goldylox<K, V>,
+    /// The keys of the recorded entries, least recently recorded first
+    keys: Arc<Mutex<LruCache<K, ()>>>,
+    /// Whether entries are being evicted, so that evictions don't pile up
+    evicting: Arc<AtomicBool>,
+}
+
+impl<K, V> ManagedGoldylox<K, V>
+where
+    K: CacheKey + Serialize + DeserializeOwned + Hash + Eq + Send + 'static,
+    V: CacheValue + Clone + Serialize + DeserializeOwned + 'static,
+    Goldylox<K, V>: Send + Sync,
+{
+    /// Register `cache` (a clone sharing its entries) with the global coordinator as `name`
+    pub fn register(name: &'static str, cache: Goldylox<K, V>) -> Arc<Self> {
+        Self::register_with(CacheCoordinator::global(), name, cache)
+    }
+
+    fn register_with(
+        coordinator: &CacheCoordinator,
+        name: &'static str,
+        cache: Goldylox<K, V>,
+    ) -> Arc<Self> {
+        let managed = Arc::new(Self {
+            name,
+            cache,
+            keys: Arc::new(Mutex::new(LruCache::unbounded())),
+            evicting: Arc::new(AtomicBool::new(false)),
+        });
+        let managed_cache: Arc<dyn ManagedCache> = managed.clone();
+        coordinator.register(&managed_cache, 1);
+        managed
+    }
+
+    /// Record that the entry `key` was put in the cache (or used again), making it the last to
+    /// be evicted
+    pub fn record(&self, key: K) {
+        lock(&self.keys).put(key, ());
+    }
+}
+
+impl<K, V> Deref for ManagedGoldylox<K, V>
+where
+    K: CacheKey + Serialize + DeserializeOwned,
+    V: CacheValue + Clone + Serialize + DeserializeOwned,
+{
+    type Target = Goldylox<K, V>;
+
+    fn deref(&self) -> &Goldylox<K, V> {
+        &self.cache
+    }
+}
+
+impl<K, V> ManagedCache for ManagedGoldylox<K, V>
+where
+    K: CacheKey + Serialize + DeserializeOwned + Hash + Eq + Send + 'static,
+    V: CacheValue + Clone + Serialize + DeserializeOwned + 'static,
+    Goldylox<K, V>: Send + Sync,
+{
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn memory_usage(&self) -> usize {
+        memory_usage(&self.cache)
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        if self.memory_usage() <= target_bytes || self.evicting.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let name = self.name;
+        let cache = self.cache.clone();
+        let keys = self.keys.clone();
+        let evicting = self.evicting.clone();
+        crate::runtime::run_detached(move || async move {
+            evict(name, &cache, &keys, target_bytes).await;
+            evicting.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Remove the least recently recorded entries of `cache` until it holds at most `target_bytes`.
+/// Entries which weren't recorded can only be removed by clearing the cache, which is done if it
+/// still holds too much once the recorded entries are gone.
+async fn evict<K, V>(
+    name: &str,
+    cache: &Goldylox<K, V>,
+    keys: &Mutex<LruCache<K, ()>>,
+    target_bytes: usize,
+) where
+    K: CacheKey + Serialize + DeserializeOwned + Hash + Eq,
+    V: CacheValue + Clone + Serialize + DeserializeOwned,
+{
+    while memory_usage(cache) > target_bytes {
+        let batch: Vec<K> = {
+            let mut keys = lock(keys);
+            std::iter::from_fn(|| keys.pop_lru().map(|(key, ())| key))
+                .take(EVICTION_BATCH)
+                .collect()
+        };
+        if batch.is_empty() {
+            if let Err(err) = cache.clear().await {
+                log::warn!("Failed to clear the {} cache: {}", name, err);
+            }
+            return;
+        }
+        for key in &batch {
+            let _ = cache.remove(key).await;
+        }
+    }
+}
+
+fn memory_usage<K, V>(cache: &Goldylox<K, V>) -> usize
+where
+    K: CacheKey + Serialize + DeserializeOwned,
+    V: CacheValue + Clone + Serialize + DeserializeOwned,
+{
+    let Ok(stats) = cache.stats() else {
+        return 0;
+    };
+    serde_json::from_str::<serde_json::Value>(&stats)
+        .ok()
+        .and_then(|stats| stats["total_memory_usage"].as_u64())
+        .unwrap_or(0) as usize
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use goldylox::GoldyloxBuilder;
+
+    use super::*;
+
+    #[test]
+    fn enforcing_the_budget_on_a_runtime_evicts_the_oldest_entries() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let cache = GoldyloxBuilder::<String, Vec<u8>>::new()
+                .hot_tier_max_entries(100)
+                .hot_tier_memory_limit_mb(16)
+                .cache_id(&format!("managed_goldylox_test_{}", std::process::id()))
+                .build()
+                .await
+                .unwrap();
+            let coordinator = CacheCoordinator::new(usize::MAX);
+            let managed = ManagedGoldylox::register_with(&coordinator, "test", cache.clone());
+            for i in 0..64 {
+                let key = format!("entry {i}");
+                cache.put(key.clone(), vec![0; 16 * 1024]).await.unwrap();
+                managed.record(key);
+            }
+            let full = managed.memory_usage();
+            assert!(full > 0);
+
+            // Evicting mustn't block (or panic) inside the runtime
+            coordinator.set_budget(full / 2);
+            while managed.evicting.load(Ordering::SeqCst) {
+                tokio::task::yield_now().await;
+            }
+
+            assert!(managed.memory_usage() <= full / 2);
+            assert!(cache.get(&"entry 0".to_string()).await.is_none());
+            assert!(cache.get(&"entry 63".to_string()).await.is_some());
+        });
+    }
+}
@@ -6,12 +6,16 @@
 // Global singleton cache manager
 pub mod global;
 
+// Registering caches with the memory budget shared by the Blitz crates
+pub mod managed;
+
 // Re-export goldylox types for convenience
 pub use goldylox::traits::{CacheKey, CacheValue};
 pub use goldylox::{Goldylox, GoldyloxBuilder};
 
 // Re-export global cache functions
 pub use global::{GlobalCacheManager, get_text_shaping_cache, get_text_measurement_cache, get_serialized_cache};
+pub use managed::ManagedGoldylox;
 
 // Type alias for compatibility - generic cache manager
 pub type CacheManager<K, V> = Goldylox<K, V>;
@@ -8,6 +8,7 @@ use std::sync::atomic::Ordering;
 use cosmyc_text::{Action, Cursor, Edit, FontSystem, Motion, Selection};
 
 use super::types::EnhancedEditor;
+use crate::navigation;
 
 /// Handle editor actions with enhanced functionality
 pub fn handle_action<'buffer>(
@@ -175,6 +176,32 @@ fn handle_motion<'buffer>(
 ) {
     let current_cursor = editor.cursor();
 
+    // Move over whole grapheme clusters and words rather than relying on the buffer's own
+    // motion handling, so that the cursor never lands inside an emoji or combining sequence.
+    // Arrow keys move through characters in the order they're displayed.
+    let navigated = editor.with_buffer(|buffer| {
+        let rtl = navigation::is_rtl_line(buffer, current_cursor.line);
+        match (motion, rtl) {
+            (Motion::Previous, _) => Some(navigation::prev_grapheme(buffer, current_cursor)),
+            (Motion::Next, _) => Some(navigation::next_grapheme(buffer, current_cursor)),
+            (Motion::Left, _) => Some(navigation::left_grapheme(buffer, current_cursor)),
+            (Motion::Right, _) => Some(navigation::right_grapheme(buffer, current_cursor)),
+            (Motion::PreviousWord, _) | (Motion::LeftWord, false) | (Motion::RightWord, true) => {
+                Some(navigation::prev_word(buffer, current_cursor))
+            }
+            (Motion::NextWord, _) | (Motion::RightWord, false) | (Motion::LeftWord, true) => {
+                Some(navigation::next_word(buffer, current_cursor))
+            }
+            _ => None,
+        }
+    });
+    if let Some(new_cursor) = navigated {
+        if let Some(new_cursor) = new_cursor {
+            editor.set_cursor(new_cursor);
+        }
+        return;
+    }
+
     if let Some(cursor_result) = editor
         .with_buffer_mut(|buffer| buffer.cursor_motion(font_system, current_cursor, None, motion))
     {
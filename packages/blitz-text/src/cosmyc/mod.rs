@@ -97,7 +97,7 @@ pub struct CosmicTextIntegration {
     /// Optional enhanced editor for text editing
     pub editor: Option<EnhancedEditor<'static>>,
     /// Integration-wide statistics
-    stats_start_time: std::time::Instant,
+    stats_start_time: web_time::Instant,
 }
 
 impl CosmicTextIntegration {
@@ -107,7 +107,7 @@ impl CosmicTextIntegration {
             swash_cache: EnhancedSwashCache::new(),
             shape_cache: EnhancedShapeRunCache::new(),
             editor: None,
-            stats_start_time: std::time::Instant::now(),
+            stats_start_time: web_time::Instant::now(),
         }
     }
 
@@ -117,7 +117,7 @@ impl CosmicTextIntegration {
             swash_cache: EnhancedSwashCache::new(),
             shape_cache: EnhancedShapeRunCache::new(),
             editor: Some(EnhancedEditor::from_buffer(buffer)),
-            stats_start_time: std::time::Instant::now(),
+            stats_start_time: web_time::Instant::now(),
         }
     }
 
@@ -172,7 +172,7 @@ impl CosmicTextIntegration {
 
     /// Optimize all caches and components
     pub fn optimize_all(&mut self) -> IntegrationOptimizationResult {
-        let start_time = std::time::Instant::now();
+        let start_time = web_time::Instant::now();
 
         // Clear swash cache statistics (it manages its own optimization)
         self.swash_cache.clear_stats();
@@ -206,7 +206,7 @@ impl CosmicTextIntegration {
             editor.clear_stats();
         }
 
-        self.stats_start_time = std::time::Instant::now();
+        self.stats_start_time = web_time::Instant::now();
     }
 
     /// Check if system-wide optimization is recommended
@@ -217,6 +217,17 @@ impl EnhancedBuffer {
         self.update_cached_layout_runs();
     }
 
+    /// Break lines after the spaces in `breakable` with cache invalidation, see
+    /// [`crate::break_spaces`]
+    pub fn break_preserved_spaces_cached(
+        &mut self,
+        font_system: &mut FontSystem,
+        breakable: &[std::ops::Range<usize>],
+    ) {
+        crate::break_spaces::break_preserved_spaces(&mut self.inner, font_system, breakable);
+        self.update_cached_layout_runs();
+    }
+
     /// Set buffer wrap with cache invalidation
     pub fn set_wrap_cached(&mut self, font_system: &mut FontSystem, wrap: cosmyc_text::Wrap) {
         self.inner.set_wrap(font_system, wrap);
@@ -514,7 +525,7 @@ impl EnhancedBuffer {
         font_system: &mut FontSystem,
         metrics: &mut CssWidthMetrics,
     ) -> f32 {
-        let start = std::time::Instant::now();
+        let start = web_time::Instant::now();
         metrics.calculation_count += 1;
         
         let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
@@ -539,7 +550,7 @@ impl EnhancedBuffer {
         font_system: &mut FontSystem,
         metrics: &mut CssWidthMetrics,
     ) -> f32 {
-        let start = std::time::Instant::now();
+        let start = web_time::Instant::now();
         metrics.calculation_count += 1;
         
         let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
@@ -23,11 +23,11 @@ impl EnhancedFontSystem {
             crate::embedded_fallback::ensure_embedded_fallback(font_system)
         });
 
-        let mut font_system = FontSystem::new();
+        let mut font_system = crate::font_override::new_font_system();
 
         // CRITICAL: Load embedded fallback font to guarantee font_id validity
         let embedded_fallback_id =
-            crate::embedded_fallback::load_embedded_fallback(font_system.db_mut());
+            crate::embedded_fallback::ensure_embedded_fallback(&mut font_system);
 
         log::debug!(
             "Loaded embedded fallback font with ID: {:?}",
@@ -23,6 +23,7 @@ pub use cosmyc_text::{
     Cursor,
     // Text formatting
     Family,
+    FeatureTag,
     // Font types
     Font,
     FontFeatures,
@@ -43,7 +43,7 @@ impl CustomGlyphsCache {
             .warm_tier_max_memory_bytes(256 * 1024 * 1024) // 256MB
             .cold_tier_max_size_bytes(1024 * 1024 * 1024) // 1GB
             .compression_level(2)
-            .background_worker_threads(1)
+            .background_worker_threads(crate::runtime::cache_worker_threads(1))
             .cache_id("custom_glyphs_cache")
             .build().await?;
 
@@ -88,24 +88,10 @@ impl CustomGlyphsCache {
 impl Default for CustomGlyphsCache {
     fn default() -> Self {
         // Since new() is async and Default can't be async, we use a blocking approach
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| {
-                    panic!("Failed to create CustomGlyphsCache")
-                })
+        crate::runtime::block_on(async {
+            Self::new().await.unwrap_or_else(|_| {
+                panic!("Failed to create CustomGlyphsCache")
             })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| {
-                        panic!("Failed to create CustomGlyphsCache")
-                    })
-                })
-        }
+        })
     }
 }
\ No newline at end of file
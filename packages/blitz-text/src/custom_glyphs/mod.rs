@@ -30,6 +30,7 @@ pub use types::{
     GlyphSystemStats,
 };
 
+use crate::emoji;
 use crate::types::ShapedRun;
 
 thread_local! {
@@ -138,7 +139,8 @@ pub mod utils {
 
         let mut registered_ids = Vec::new();
 
-        for ch in text.chars() {
+        // Emoji sequences are drawn with the color font rather than from the atlas
+        for (_, ch) in emoji::standalone_chars(text) {
             let codepoint = ch as u32;
 
             if AtlasProcessor::is_emoji_codepoint(codepoint) {
@@ -176,10 +178,12 @@ pub mod utils {
         text: &str,
         color_key: u32,
     ) -> Vec<(char, Option<CustomGlyphData>)> {
-        text.chars()
-            .map(|ch| {
+        let mut standalone = emoji::standalone_chars(text).map(|(index, _)| index).peekable();
+        text.char_indices()
+            .map(|(index, ch)| {
                 let codepoint = ch as u32;
-                let glyph_data = if needs_custom_rendering(ch) {
+                let is_standalone = standalone.next_if_eq(&index).is_some();
+                let glyph_data = if needs_custom_rendering(ch) && is_standalone {
                     get_global_glyph(codepoint, color_key)
                 } else {
                     None
@@ -296,8 +296,8 @@ impl CustomGlyphRegistry {
 
     /// Cleanup old unused glyphs
     pub fn cleanup_unused_glyphs(&self, max_age_seconds: u32) -> usize {
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
+        let current_time = web_time::SystemTime::now()
+            .duration_since(web_time::SystemTime::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs() as u32;
 
@@ -10,6 +10,7 @@ use super::super::registry::CustomGlyphRegistry;
 use super::super::types::{
     CustomGlyphData, CustomGlyphError, GlyphKey, GlyphSystemConfig, GlyphSystemStats,
 };
+use crate::emoji;
 
 /// Main custom glyph system integrating registry and GPU cache
 pub struct CustomGlyphSystem {
@@ -65,8 +66,9 @@ impl CustomGlyphSystem {
             .get(range.clone())
             .ok_or(CustomGlyphError::InvalidRange)?;
 
-        // Iterate through characters in the range
-        for ch in text_slice.chars() {
+        // Iterate through characters in the range, skipping those which are part of an emoji
+        // sequence as the whole sequence is drawn with the color font
+        for (_, ch) in emoji::standalone_chars(text_slice) {
             let codepoint = ch as u32;
 
             // Check if we have a custom glyph for this codepoint
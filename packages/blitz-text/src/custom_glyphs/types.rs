@@ -264,8 +264,8 @@ impl CustomGlyphData {
     pub fn record_access(&self) {
         self.access_count
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
+        let now = web_time::SystemTime::now()
+            .duration_since(web_time::SystemTime::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs() as u32;
         self.last_used_ns
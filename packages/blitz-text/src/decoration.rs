@@ -0,0 +1,252 @@
+//! Font metrics and glyph geometry for text decorations
+//!
+//! Underlines, overlines and line-throughs are positioned using the metrics the font provides for
+//! them (the `post` and `OS/2` tables), falling back to proportions of the font size when a font
+//! doesn't provide them. [`glyph_ink_extent`] supports `text-decoration-skip-ink` by finding where a
+//! glyph's outline crosses the band an underline is drawn in, so that the underline can be
+//! interrupted there. See <https://drafts.csswg.org/css-text-decor-4/#text-decoration-skip-ink-property>.
+//!
+//! All values are in pixels and measured downwards from the alphabetic baseline (so an underline
+//! usually has a positive offset and an overline a negative one).
+
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// Number of line segments each curve of an outline is flattened into
+const CURVE_SEGMENTS: usize = 8;
+
+/// Decoration metrics of a font at a particular size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecorationMetrics {
+    /// Offset of the top of the underline
+    pub underline_offset: f32,
+    pub underline_thickness: f32,
+    /// Offset of the top of the line-through
+    pub strikeout_offset: f32,
+    pub strikeout_thickness: f32,
+    /// Offset of the top of the overline (the font's ascent)
+    pub overline_offset: f32,
+}
+
+impl DecorationMetrics {
+    /// Read the decoration metrics of a font. Returns `None` if the font can't be parsed.
+    pub fn from_font(font_data: &[u8], face_index: u32, font_size: f32) -> Option<Self> {
+        let face = Face::parse(font_data, face_index).ok()?;
+        let scale = font_size / face.units_per_em() as f32;
+        let fallback = Self::fallback(font_size);
+
+        let (underline_offset, underline_thickness) = match face.underline_metrics() {
+            Some(metrics) if metrics.thickness > 0 => (
+                -(metrics.position as f32) * scale,
+                metrics.thickness as f32 * scale,
+            ),
+            _ => (fallback.underline_offset, fallback.underline_thickness),
+        };
+        let (strikeout_offset, strikeout_thickness) = match face.strikeout_metrics() {
+            Some(metrics) if metrics.thickness > 0 => (
+                -(metrics.position as f32) * scale,
+                metrics.thickness as f32 * scale,
+            ),
+            _ => {
+                // Centre the line-through on half the x-height
+                let x_height = face
+                    .x_height()
+                    .map(|x_height| x_height as f32 * scale)
+                    .unwrap_or(font_size * 0.5);
+                (
+                    -(x_height / 2.0) - underline_thickness / 2.0,
+                    underline_thickness,
+                )
+            }
+        };
+
+        Some(Self {
+            underline_offset,
+            underline_thickness,
+            strikeout_offset,
+            strikeout_thickness,
+            overline_offset: -(face.ascender() as f32) * scale,
+        })
+    }
+
+    /// Metrics derived from the font size alone, for fonts that can't be read
+    pub fn fallback(font_size: f32) -> Self {
+        let thickness = font_size / 14.0;
+        Self {
+            underline_offset: font_size * 0.1,
+            underline_thickness: thickness,
+            strikeout_offset: -font_size * 0.3,
+            strikeout_thickness: thickness,
+            overline_offset: -font_size * 0.9,
+        }
+    }
+}
+
+/// The horizontal extent (relative to the glyph origin) of the parts of a glyph's outline which
+/// lie between `top` and `bottom`. Returns `None` if the glyph has no ink in that band.
+pub fn glyph_ink_extent(
+    font_data: &[u8],
+    face_index: u32,
+    glyph_id: u16,
+    font_size: f32,
+    top: f32,
+    bottom: f32,
+) -> Option<(f32, f32)> {
+    let face = Face::parse(font_data, face_index).ok()?;
+    let scale = font_size / face.units_per_em() as f32;
+
+    // Outlines are y-up in font units
+    let mut builder = BandExtent {
+        min_y: -bottom / scale,
+        max_y: -top / scale,
+        current: (0.0, 0.0),
+        start: (0.0, 0.0),
+        extent: None,
+    };
+    face.outline_glyph(GlyphId(glyph_id), &mut builder)?;
+    builder.extent.map(|(min, max)| (min * scale, max * scale))
+}
+
+/// An [`OutlineBuilder`] which records the horizontal extent of an outline within a band
+struct BandExtent {
+    /// The lower edge of the band (in y-up font units)
+    min_y: f32,
+    /// The upper edge of the band (in y-up font units)
+    max_y: f32,
+    current: (f32, f32),
+    start: (f32, f32),
+    extent: Option<(f32, f32)>,
+}
+
+impl BandExtent {
+    fn include(&mut self, x: f32) {
+        self.extent = Some(match self.extent {
+            Some((min, max)) => (min.min(x), max.max(x)),
+            None => (x, x),
+        });
+    }
+
+    /// Clip the segment from the current point to `to` against the band
+    fn segment(&mut self, to: (f32, f32)) {
+        let (x0, y0) = self.current;
+        let (x1, y1) = to;
+        self.current = to;
+
+        if y0.max(y1) < self.min_y || y0.min(y1) > self.max_y {
+            return;
+        }
+        if y0 == y1 {
+            self.include(x0);
+            self.include(x1);
+            return;
+        }
+
+        // The range of the segment's parameter over which it lies within the band
+        let t_min = (self.min_y - y0) / (y1 - y0);
+        let t_max = (self.max_y - y0) / (y1 - y0);
+        let t0 = t_min.min(t_max).max(0.0);
+        let t1 = t_min.max(t_max).min(1.0);
+        self.include(x0 + (x1 - x0) * t0);
+        self.include(x0 + (x1 - x0) * t1);
+    }
+}
+
+impl OutlineBuilder for BandExtent {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.current = (x, y);
+        self.start = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.segment((x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        for step in 1..=CURVE_SEGMENTS {
+            let t = step as f32 / CURVE_SEGMENTS as f32;
+            let mt = 1.0 - t;
+            self.segment((
+                mt * mt * x0 + 2.0 * mt * t * x1 + t * t * x,
+                mt * mt * y0 + 2.0 * mt * t * y1 + t * t * y,
+            ));
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        for step in 1..=CURVE_SEGMENTS {
+            let t = step as f32 / CURVE_SEGMENTS as f32;
+            let mt = 1.0 - t;
+            let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+            self.segment((
+                a * x0 + b * x1 + c * x2 + d * x,
+                a * y0 + b * y1 + c * y2 + d * y,
+            ));
+        }
+    }
+
+    fn close(&mut self) {
+        self.segment(self.start);
+    }
+}
+
+/// Subtract `gaps` from the span `start..end`, returning the pieces that remain. Pieces shorter
+/// than `min_length` are dropped.
+pub fn subtract_gaps(
+    start: f32,
+    end: f32,
+    gaps: &mut [(f32, f32)],
+    min_length: f32,
+) -> Vec<(f32, f32)> {
+    gaps.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut pieces = Vec::new();
+    let mut cursor = start;
+    for &(gap_start, gap_end) in gaps.iter() {
+        if gap_end <= cursor {
+            continue;
+        }
+        if gap_start >= end {
+            break;
+        }
+        if gap_start - cursor >= min_length {
+            pieces.push((cursor, gap_start));
+        }
+        cursor = gap_end;
+    }
+    if end - cursor >= min_length {
+        pieces.push((cursor, end));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn band_extent_clips_segments() {
+        let mut builder = BandExtent {
+            min_y: 0.0,
+            max_y: 10.0,
+            current: (0.0, 0.0),
+            start: (0.0, 0.0),
+            extent: None,
+        };
+        // A diagonal crossing the band, and a segment entirely above it
+        builder.move_to(0.0, -10.0);
+        builder.line_to(20.0, 30.0);
+        builder.move_to(100.0, 20.0);
+        builder.line_to(200.0, 20.0);
+        assert_eq!(builder.extent, Some((5.0, 10.0)));
+    }
+
+    #[test]
+    fn subtracts_gaps_from_span() {
+        let mut gaps = [(8.0, 12.0), (2.0, 4.0), (11.0, 13.0), (19.5, 25.0)];
+        assert_eq!(
+            subtract_gaps(0.0, 20.0, &mut gaps, 1.0),
+            vec![(0.0, 2.0), (4.0, 8.0), (13.0, 19.5)]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Emoji sequence segmentation
+//!
+//! Splits text into emoji sequences as defined by
+//! [UTS #51](https://www.unicode.org/reports/tr51/): ZWJ sequences, skin tone modifier sequences,
+//! flags (regional indicator pairs and tag sequences) and keycaps. Each sequence is displayed as a
+//! single glyph, so it must be shaped as a unit with the color font and must not be drawn one
+//! codepoint at a time from the custom glyph atlas.
+//!
+//! Sequences are found by classifying extended grapheme clusters, which never split an emoji
+//! sequence. Emoji characters are recognised by code point range (the emoji blocks and the
+//! pictographic symbols in the BMP) rather than with the full Unicode emoji data, which is
+//! accurate for the sequences this module cares about.
+
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+const ZWJ: char = '\u{200D}';
+const VS15: char = '\u{FE0E}';
+const VS16: char = '\u{FE0F}';
+const KEYCAP: char = '\u{20E3}';
+const CANCEL_TAG: char = '\u{E007F}';
+const BLACK_FLAG: char = '\u{1F3F4}';
+
+/// The kind of an emoji sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmojiSequenceKind {
+    /// A single emoji, optionally followed by U+FE0F to request emoji presentation
+    Single,
+    /// An emoji followed by a skin tone modifier (U+1F3FB..U+1F3FF)
+    Modifier,
+    /// A pair of regional indicators, such as 🇳🇿
+    Flag,
+    /// A black flag followed by tag characters, such as the flag of Scotland
+    TagFlag,
+    /// A digit, `#` or `*` followed by U+20E3, such as 1️⃣
+    Keycap,
+    /// Emoji joined with U+200D, such as 👩‍💻
+    Zwj,
+}
+
+/// An emoji sequence within a piece of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmojiSequence {
+    /// Byte range of the sequence in the text
+    pub range: Range<usize>,
+    pub kind: EmojiSequenceKind,
+}
+
+impl EmojiSequence {
+    /// The emoji, if the sequence consists of a single emoji character (ignoring any variation
+    /// selector)
+    pub fn single_char(&self, text: &str) -> Option<char> {
+        match self.kind {
+            EmojiSequenceKind::Single => text[self.range.clone()].chars().next(),
+            _ => None,
+        }
+    }
+
+    /// Whether the sequence is made up of several emoji characters or an emoji and modifiers
+    pub fn is_multi_codepoint(&self) -> bool {
+        self.kind != EmojiSequenceKind::Single
+    }
+}
+
+/// Whether `ch` is in one of the ranges containing emoji
+pub fn is_emoji_char(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{00A9}'
+            | '\u{00AE}'
+            | '\u{203C}'
+            | '\u{2049}'
+            | '\u{2122}'
+            | '\u{2139}'
+            | '\u{2194}'..='\u{21AA}'
+            | '\u{231A}'..='\u{23FF}'
+            | '\u{24C2}'
+            | '\u{25AA}'..='\u{25FE}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2934}'..='\u{2935}'
+            | '\u{2B05}'..='\u{2B55}'
+            | '\u{3030}'
+            | '\u{303D}'
+            | '\u{3297}'
+            | '\u{3299}'
+            | '\u{1F000}'..='\u{1FAFF}'
+    )
+}
+
+/// Whether `ch` is shown as an emoji rather than as text when it isn't followed by a variation
+/// selector. Emoji in the BMP default to text presentation.
+pub fn has_emoji_presentation(ch: char) -> bool {
+    ch >= '\u{1F000}' && is_emoji_char(ch) && !is_regional_indicator(ch)
+}
+
+/// Whether `ch` is a skin tone modifier
+pub fn is_emoji_modifier(ch: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&ch)
+}
+
+pub fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch)
+}
+
+fn is_tag(ch: char) -> bool {
+    ('\u{E0020}'..='\u{E007E}').contains(&ch)
+}
+
+fn is_keycap_base(ch: char) -> bool {
+    ch.is_ascii_digit() || ch == '#' || ch == '*'
+}
+
+/// Classify a single extended grapheme cluster, returning `None` if it isn't an emoji
+pub fn classify_cluster(cluster: &str) -> Option<EmojiSequenceKind> {
+    let mut chars = cluster.chars();
+    let first = chars.next()?;
+    let rest = chars.as_str();
+
+    if is_keycap_base(first) {
+        let rest = rest.strip_prefix(VS16).unwrap_or(rest);
+        return (rest == "\u{20E3}").then_some(EmojiSequenceKind::Keycap);
+    }
+    if is_regional_indicator(first) {
+        let mut rest = rest.chars();
+        return match (rest.next(), rest.next()) {
+            (Some(second), None) if is_regional_indicator(second) => Some(EmojiSequenceKind::Flag),
+            _ => None,
+        };
+    }
+    if !is_emoji_char(first) {
+        return None;
+    }
+
+    if first == BLACK_FLAG && rest.ends_with(CANCEL_TAG) {
+        let tags = rest.trim_end_matches(CANCEL_TAG);
+        if !tags.is_empty() && tags.chars().all(is_tag) {
+            return Some(EmojiSequenceKind::TagFlag);
+        }
+    }
+
+    // Each element of a ZWJ sequence is an emoji, optionally followed by a presentation
+    // selector or a modifier. Joined elements don't need a selector to be shown as emoji.
+    let joined = cluster.contains(ZWJ);
+    let mut kind = None;
+    for element in cluster.split(ZWJ) {
+        let mut chars = element.chars();
+        let base = chars.next().filter(|ch| is_emoji_char(*ch))?;
+        let element_kind = match chars.as_str() {
+            "" if joined || has_emoji_presentation(base) => EmojiSequenceKind::Single,
+            "\u{FE0F}" => EmojiSequenceKind::Single,
+            modifier if !modifier.is_empty() && modifier.chars().all(is_emoji_modifier) => {
+                EmojiSequenceKind::Modifier
+            }
+            _ => return None,
+        };
+        kind = Some(match kind {
+            None => element_kind,
+            Some(_) => EmojiSequenceKind::Zwj,
+        });
+    }
+    kind
+}
+
+/// Iterate over the emoji sequences in `text`
+pub fn emoji_sequences(text: &str) -> impl Iterator<Item = EmojiSequence> + '_ {
+    text.grapheme_indices(true)
+        .filter_map(|(start, cluster)| {
+            let kind = classify_cluster(cluster)?;
+            Some(EmojiSequence {
+                range: start..start + cluster.len(),
+                kind,
+            })
+        })
+}
+
+/// Whether `text` contains an emoji sequence which must be shaped as a unit (anything other than
+/// a single emoji character)
+pub fn contains_emoji_sequence(text: &str) -> bool {
+    // Every multi-codepoint sequence contains one of these characters, so most text can skip
+    // grapheme segmentation entirely
+    let may_contain = text.chars().any(|ch| {
+        matches!(ch, ZWJ | VS16 | KEYCAP)
+            || is_emoji_modifier(ch)
+            || is_regional_indicator(ch)
+            || is_tag(ch)
+    });
+    may_contain && emoji_sequences(text).any(|sequence| sequence.is_multi_codepoint())
+}
+
+/// Whether the character at `index` in `text` can be drawn on its own, i.e. it isn't part of a
+/// multi-codepoint emoji sequence or followed by a text presentation selector
+///
+/// This segments `text` up to `index`, so use [`standalone_chars`] to check every character.
+pub fn is_standalone_char(text: &str, index: usize) -> bool {
+    let Some((start, cluster)) = text
+        .grapheme_indices(true)
+        .find(|(start, cluster)| (*start..start + cluster.len()).contains(&index))
+    else {
+        return false;
+    };
+    start == index && is_standalone_cluster(cluster)
+}
+
+/// Iterate over the characters of `text` which can be drawn on their own (see
+/// [`is_standalone_char`]) and their byte indices, in a single pass over its grapheme clusters
+pub fn standalone_chars(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    text.grapheme_indices(true)
+        .filter(|(_, cluster)| is_standalone_cluster(cluster))
+        .filter_map(|(start, cluster)| Some((start, cluster.chars().next()?)))
+}
+
+/// Whether the first character of a grapheme cluster can be drawn on its own
+fn is_standalone_cluster(cluster: &str) -> bool {
+    let Some(first) = cluster.chars().next() else {
+        return false;
+    };
+    if cluster.ends_with(VS15) {
+        return false;
+    }
+    match classify_cluster(cluster) {
+        Some(kind) => kind == EmojiSequenceKind::Single,
+        None => cluster.len() == first.len_utf8(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_sequences() {
+        let cases = [
+            ("😀", Some(EmojiSequenceKind::Single)),
+            ("❤\u{FE0F}", Some(EmojiSequenceKind::Single)),
+            ("❤", None),
+            ("👋🏽", Some(EmojiSequenceKind::Modifier)),
+            ("🇳🇿", Some(EmojiSequenceKind::Flag)),
+            (
+                "🏴\u{E0067}\u{E0062}\u{E0073}\u{E0063}\u{E0074}\u{E007F}",
+                Some(EmojiSequenceKind::TagFlag),
+            ),
+            ("1\u{FE0F}\u{20E3}", Some(EmojiSequenceKind::Keycap)),
+            ("👩\u{200D}💻", Some(EmojiSequenceKind::Zwj)),
+            ("👩\u{200D}❤\u{200D}👨", Some(EmojiSequenceKind::Zwj)),
+            ("👩🏽\u{200D}💻", Some(EmojiSequenceKind::Zwj)),
+            ("a", None),
+            ("1", None),
+        ];
+        for (cluster, kind) in cases {
+            assert_eq!(classify_cluster(cluster), kind, "{cluster:?}");
+        }
+    }
+
+    #[test]
+    fn finds_sequences_in_text() {
+        let text = "hi 👨\u{200D}👩\u{200D}👧 and 🙋🏽 😀";
+        let sequences: Vec<_> = emoji_sequences(text).collect();
+        assert_eq!(sequences.len(), 3);
+        assert_eq!(&text[sequences[0].range.clone()], "👨\u{200D}👩\u{200D}👧");
+        assert_eq!(sequences[2].single_char(text), Some('😀'));
+
+        assert!(contains_emoji_sequence(text));
+        assert!(!contains_emoji_sequence("plain 😀 text"));
+
+        let raised_hand = text.find('🙋').unwrap();
+        assert!(!is_standalone_char(text, raised_hand));
+        assert!(is_standalone_char(text, text.find('😀').unwrap()));
+    }
+
+    #[test]
+    fn finds_standalone_chars_in_one_pass() {
+        let text = "a🙋🏽❤\u{FE0E}😀e\u{301}";
+        let standalone: Vec<_> = standalone_chars(text).collect();
+        assert_eq!(standalone, [(0, 'a'), (text.find('😀').unwrap(), '😀')]);
+        for (index, _) in text.char_indices() {
+            let expected = standalone.iter().any(|(start, _)| *start == index);
+            assert_eq!(is_standalone_char(text, index), expected, "{index}");
+        }
+    }
+}
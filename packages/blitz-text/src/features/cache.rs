@@ -33,15 +33,15 @@ impl FeaturesCacheKey {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FeaturesValue {
     pub features: Vec<String>,
-    #[serde(skip, default = "std::time::Instant::now")]
-    pub cached_at: std::time::Instant,
+    #[serde(skip, default = "web_time::Instant::now")]
+    pub cached_at: web_time::Instant,
 }
 
 impl Default for FeaturesValue {
     fn default() -> Self {
         Self {
             features: Vec::new(),
-            cached_at: std::time::Instant::now(),
+            cached_at: web_time::Instant::now(),
         }
     }
 }
@@ -84,7 +84,7 @@ impl FeaturesCache {
             .warm_tier_max_memory_bytes(32 * 1024 * 1024) // 32MB
             .cold_tier_max_size_bytes(64 * 1024 * 1024) // 64MB
             .compression_level(5)
-            .background_worker_threads(1)
+            .background_worker_threads(crate::runtime::cache_worker_threads(1))
             .cache_id(&cache_id)
             .build().await?;
 
@@ -105,7 +105,7 @@ impl FeaturesCache {
         let key = format!("{}:{}", feature_name, context);
         let value = FeaturesValue {
             features,
-            cached_at: std::time::Instant::now(),
+            cached_at: web_time::Instant::now(),
         };
         self.cache
             .put(key, value).await
@@ -141,24 +141,10 @@ impl Default for FeaturesCache {
     fn default() -> Self {
         // Since new() is async and Default can't be async, we use a blocking approach
         // This is only used in fallback scenarios, so the blocking is acceptable
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| {
-                    panic!("Failed to create FeaturesCache - goldylox initialization failed")
-                })
+        crate::runtime::block_on(async {
+            Self::new().await.unwrap_or_else(|_| {
+                panic!("Failed to create FeaturesCache - goldylox initialization failed")
             })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| {
-                        panic!("Failed to create FeaturesCache - goldylox initialization failed")
-                    })
-                })
-        }
+        })
     }
 }
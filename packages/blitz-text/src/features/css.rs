@@ -0,0 +1,200 @@
+//! OpenType features selected by CSS
+//!
+//! Converts `font-variant-caps`, `font-variant-numeric`, `font-variant-ligatures` and
+//! `font-feature-settings` into the [`FontFeatures`] that cosmyc-text applies when shaping a span
+//! of text. Features from the `font-variant-*` properties are applied first, so that
+//! `font-feature-settings` can override them.
+//! See <https://drafts.csswg.org/css-fonts-4/#feature-precedence>.
+
+use cosmyc_text::{FeatureTag, FontFeatures};
+
+/// `font-variant-caps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontVariantCaps {
+    #[default]
+    Normal,
+    SmallCaps,
+    AllSmallCaps,
+    PetiteCaps,
+    AllPetiteCaps,
+    Unicase,
+    TitlingCaps,
+}
+
+/// `font-variant-numeric`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FontVariantNumeric {
+    pub figures: NumericFigures,
+    pub spacing: NumericSpacing,
+    pub fractions: NumericFractions,
+    pub ordinal: bool,
+    pub slashed_zero: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumericFigures {
+    #[default]
+    Normal,
+    Lining,
+    Oldstyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumericSpacing {
+    #[default]
+    Normal,
+    Proportional,
+    Tabular,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumericFractions {
+    #[default]
+    Normal,
+    Diagonal,
+    Stacked,
+}
+
+/// `font-variant-ligatures`. `None` for each kind of ligature leaves the font's default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FontVariantLigatures {
+    pub common: Option<bool>,
+    pub discretionary: Option<bool>,
+    pub historical: Option<bool>,
+    pub contextual: Option<bool>,
+}
+
+impl FontVariantLigatures {
+    /// `font-variant-ligatures: none`
+    pub const NONE: Self = Self {
+        common: Some(false),
+        discretionary: Some(false),
+        historical: Some(false),
+        contextual: Some(false),
+    };
+}
+
+/// The font feature properties of an element
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CssFontFeatures {
+    pub caps: FontVariantCaps,
+    pub numeric: FontVariantNumeric,
+    pub ligatures: FontVariantLigatures,
+    /// `font-feature-settings`, as (tag, value) pairs
+    pub settings: Vec<([u8; 4], u32)>,
+}
+
+impl CssFontFeatures {
+    /// Whether every property has its initial value, in which case the font's defaults are used
+    pub fn is_normal(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The features to enable or disable when shaping
+    pub fn to_font_features(&self) -> FontFeatures {
+        let mut features = FontFeatures::new();
+        let mut set = |tag: &[u8; 4], value: u32| {
+            features.set(FeatureTag::new(tag), value);
+        };
+
+        let caps: &[&[u8; 4]] = match self.caps {
+            FontVariantCaps::Normal => &[],
+            FontVariantCaps::SmallCaps => &[b"smcp"],
+            FontVariantCaps::AllSmallCaps => &[b"smcp", b"c2sc"],
+            FontVariantCaps::PetiteCaps => &[b"pcap"],
+            FontVariantCaps::AllPetiteCaps => &[b"pcap", b"c2pc"],
+            FontVariantCaps::Unicase => &[b"unic"],
+            FontVariantCaps::TitlingCaps => &[b"titl"],
+        };
+        for tag in caps {
+            set(tag, 1);
+        }
+
+        let numeric = self.numeric;
+        match numeric.figures {
+            NumericFigures::Normal => {}
+            NumericFigures::Lining => set(b"lnum", 1),
+            NumericFigures::Oldstyle => set(b"onum", 1),
+        }
+        match numeric.spacing {
+            NumericSpacing::Normal => {}
+            NumericSpacing::Proportional => set(b"pnum", 1),
+            NumericSpacing::Tabular => set(b"tnum", 1),
+        }
+        match numeric.fractions {
+            NumericFractions::Normal => {}
+            NumericFractions::Diagonal => set(b"frac", 1),
+            NumericFractions::Stacked => set(b"afrc", 1),
+        }
+        if numeric.ordinal {
+            set(b"ordn", 1);
+        }
+        if numeric.slashed_zero {
+            set(b"zero", 1);
+        }
+
+        let ligatures = self.ligatures;
+        if let Some(common) = ligatures.common {
+            set(b"liga", common as u32);
+            set(b"clig", common as u32);
+        }
+        if let Some(discretionary) = ligatures.discretionary {
+            set(b"dlig", discretionary as u32);
+        }
+        if let Some(historical) = ligatures.historical {
+            set(b"hlig", historical as u32);
+        }
+        if let Some(contextual) = ligatures.contextual {
+            set(b"calt", contextual as u32);
+        }
+
+        for (tag, value) in &self.settings {
+            set(tag, *value);
+        }
+
+        features
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(features: &FontFeatures) -> Vec<(&[u8; 4], u32)> {
+        features
+            .features
+            .iter()
+            .map(|feature| (feature.tag.as_bytes(), feature.value))
+            .collect()
+    }
+
+    #[test]
+    fn feature_settings_override_variants() {
+        let css = CssFontFeatures {
+            caps: FontVariantCaps::AllSmallCaps,
+            numeric: FontVariantNumeric {
+                spacing: NumericSpacing::Tabular,
+                ..Default::default()
+            },
+            ligatures: FontVariantLigatures::NONE,
+            settings: vec![(*b"liga", 1)],
+        };
+        let features = css.to_font_features();
+        let tags = tags(&features);
+
+        assert!(tags.contains(&(b"smcp", 1)));
+        assert!(tags.contains(&(b"c2sc", 1)));
+        assert!(tags.contains(&(b"tnum", 1)));
+        assert!(tags.contains(&(b"calt", 0)));
+        // The later setting wins
+        let liga: Vec<_> = tags.iter().filter(|(tag, _)| *tag == b"liga").collect();
+        assert_eq!(liga.last().map(|(_, value)| *value), Some(1));
+    }
+
+    #[test]
+    fn normal_has_no_features() {
+        let css = CssFontFeatures::default();
+        assert!(css.is_normal());
+        assert!(css.to_font_features().features.is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 //! Lock-free OpenType feature settings and script-specific configurations
 
 pub mod cache;
+pub mod css;
 pub mod custom;
 pub mod lookup;
 pub mod registry;
@@ -9,6 +10,10 @@ pub mod types;
 
 // Re-export main types and functions for API compatibility
 pub use cache::FeaturesCache;
+pub use css::{
+    CssFontFeatures, FontVariantCaps, FontVariantLigatures, FontVariantNumeric, NumericFigures,
+    NumericFractions, NumericSpacing,
+};
 pub use custom::CustomFeatures;
 pub use lookup::FeatureLookup;
 pub use registry::FEATURE_REGISTRY;
@@ -0,0 +1,54 @@
+//! Replacing the fonts installed on the machine with a fixed set
+//!
+//! Font systems load the machine's fonts when they're created, so text is laid out and drawn
+//! differently from one machine to the next. Golden image tests call [`set_font_override`] before
+//! rendering anything, so that every font system created afterwards (the text system's, the
+//! glyph caches' and those created to draw glyph runs) holds the same fonts, under the same ids.
+
+use std::sync::{Arc, OnceLock};
+
+use cosmyc_text::fontdb::Source;
+use cosmyc_text::FontSystem;
+
+use crate::embedded_fallback::ensure_embedded_fallback;
+
+/// The fonts used instead of the machine's, and the family each generic family maps to
+#[derive(Debug, Clone)]
+pub struct FontOverride {
+    pub fonts: Vec<&'static [u8]>,
+    pub serif: &'static str,
+    pub sans_serif: &'static str,
+    pub monospace: &'static str,
+    pub cursive: &'static str,
+    pub fantasy: &'static str,
+}
+
+static FONT_OVERRIDE: OnceLock<FontOverride> = OnceLock::new();
+
+/// Use `fonts` instead of the machine's fonts in every font system created from now on. The
+/// override can only be set once: if it already was, `fonts` is handed back.
+pub fn set_font_override(fonts: FontOverride) -> Result<(), FontOverride> {
+    FONT_OVERRIDE.set(fonts)
+}
+
+/// The fonts used instead of the machine's, if they've been set
+pub fn font_override() -> Option<&'static FontOverride> {
+    FONT_OVERRIDE.get()
+}
+
+/// A font system holding the machine's fonts, or the [override](set_font_override) if it's set
+pub fn new_font_system() -> FontSystem {
+    let Some(fonts) = font_override() else {
+        return FontSystem::new();
+    };
+    let sources = fonts.fonts.iter().map(|font| Source::Binary(Arc::new(*font)));
+    let mut font_system = FontSystem::new_with_fonts(sources);
+    let db = font_system.db_mut();
+    db.set_serif_family(fonts.serif);
+    db.set_sans_serif_family(fonts.sans_serif);
+    db.set_monospace_family(fonts.monospace);
+    db.set_cursive_family(fonts.cursive);
+    db.set_fantasy_family(fonts.fantasy);
+    ensure_embedded_fallback(&mut font_system);
+    font_system
+}
@@ -0,0 +1,106 @@
+//! Font metrics for CSS font-relative units
+//!
+//! `ex`, `cap`, `ch` and `ic` are measured in the first available font of an element, see
+//! <https://drafts.csswg.org/css-values-4/#font-relative-lengths>. The x-height and cap height
+//! are read from the font's `OS/2` table, or measured from the outlines of `x` and `H` in fonts
+//! which don't record them (as older `OS/2` tables don't).
+
+use cosmyc_text::{Attrs, FontSystem};
+use ttf_parser::Face;
+
+/// The metrics of a font which font-relative units are measured in, in pixels at a particular
+/// size. Metrics the font doesn't have are `None`, and CSS falls back to proportions of the font
+/// size for them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontUnitMetrics {
+    pub x_height: Option<f32>,
+    pub cap_height: Option<f32>,
+    /// The advance of `0`, the `ch` unit
+    pub zero_advance: Option<f32>,
+    /// The advance of `水` (U+6C34), the `ic` unit
+    pub ideographic_advance: Option<f32>,
+    pub ascent: f32,
+}
+
+impl FontUnitMetrics {
+    /// Read the metrics of the font text with `attrs` is set in first. Advances are vertical
+    /// ones if `vertical` is set and the font has them. Returns `None` if no font matches or it
+    /// can't be parsed.
+    pub fn for_attrs(
+        font_system: &mut FontSystem,
+        attrs: &Attrs,
+        font_size: f32,
+        vertical: bool,
+    ) -> Option<Self> {
+        let id = font_system.get_font_matches(attrs).first()?.id;
+        font_system
+            .db()
+            .with_face_data(id, |font_data, face_index| {
+                Self::from_font(font_data, face_index, font_size, vertical)
+            })
+            .flatten()
+    }
+
+    /// Read the metrics of a font. Returns `None` if the font can't be parsed.
+    pub fn from_font(
+        font_data: &[u8],
+        face_index: u32,
+        font_size: f32,
+        vertical: bool,
+    ) -> Option<Self> {
+        let face = Face::parse(font_data, face_index).ok()?;
+        let scale = font_size / face.units_per_em() as f32;
+        let glyph_height = |c: char| {
+            let glyph = face.glyph_index(c)?;
+            let height = face.glyph_bounding_box(glyph)?.y_max;
+            (height > 0).then_some(height)
+        };
+        let advance = |c: char| {
+            let glyph = face.glyph_index(c)?;
+            let advance = match vertical {
+                true => face
+                    .glyph_ver_advance(glyph)
+                    .or_else(|| face.glyph_hor_advance(glyph)),
+                false => face.glyph_hor_advance(glyph),
+            };
+            Some(advance? as f32 * scale)
+        };
+
+        let x_height = face.x_height().filter(|height| *height > 0);
+        let cap_height = face.capital_height().filter(|height| *height > 0);
+        Some(Self {
+            x_height: x_height
+                .or_else(|| glyph_height('x'))
+                .map(|height| height as f32 * scale),
+            cap_height: cap_height
+                .or_else(|| glyph_height('H'))
+                .map(|height| height as f32 * scale),
+            zero_advance: advance('0'),
+            ideographic_advance: advance('\u{6C34}'),
+            ascent: face.ascender() as f32 * scale,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heights_are_measured_from_outlines_without_os2_metrics() {
+        // DejaVu Sans has a version 1 `OS/2` table, which doesn't record its x-height or cap
+        // height. Measured at its units per em, metrics are in font units.
+        const FONT: &[u8] = include_bytes!("../../blitz-test/assets/fonts/DejaVuSans.ttf");
+        let metrics = FontUnitMetrics::from_font(FONT, 0, 2048.0, false).unwrap();
+        assert_eq!(
+            metrics,
+            FontUnitMetrics {
+                x_height: Some(1120.0),
+                cap_height: Some(1493.0),
+                zero_advance: Some(1303.0),
+                ideographic_advance: None,
+                ascent: 1901.0,
+            }
+        );
+    }
+}
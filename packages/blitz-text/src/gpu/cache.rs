@@ -48,8 +48,8 @@ pub struct GpuResource {
     pub width: u32,
     pub height: u32,
     pub format: String,
-    #[serde(skip, default = "std::time::Instant::now")]
-    pub created_at: std::time::Instant,
+    #[serde(skip, default = "web_time::Instant::now")]
+    pub created_at: web_time::Instant,
 }
 
 impl Default for GpuResource {
@@ -59,7 +59,7 @@ impl Default for GpuResource {
             width: 0,
             height: 0,
             format: String::from("RGBA"),
-            created_at: std::time::Instant::now(),
+            created_at: web_time::Instant::now(),
         }
     }
 }
@@ -105,7 +105,7 @@ impl EnhancedGpuCache {
             .warm_tier_max_memory_bytes(256 * 1024 * 1024) // 256MB
             .cold_tier_max_size_bytes(512 * 1024 * 1024) // 512MB
             .compression_level(6)
-            .background_worker_threads(4)
+            .background_worker_threads(crate::runtime::cache_worker_threads(4))
             .cache_id("enhanced_gpu_cache")
             .build().await?;
 
@@ -220,7 +220,7 @@ impl TextureAtlasCache {
             .warm_tier_max_memory_bytes(128 * 1024 * 1024) // 128MB
             .cold_tier_max_size_bytes(256 * 1024 * 1024) // 256MB
             .compression_level(7)
-            .background_worker_threads(2)
+            .background_worker_threads(crate::runtime::cache_worker_threads(2))
             .cache_id("texture_atlas_cache")
             .build().await?;
 
@@ -252,21 +252,9 @@ impl TextureAtlasCache {
 impl Default for TextureAtlasCache {
     fn default() -> Self {
         // Since new() is async and Default can't be async, we use a blocking approach
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| panic!("Failed to create texture atlas cache"))
-            })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| panic!("Failed to create texture atlas cache"))
-                })
-        }
+        crate::runtime::block_on(async {
+            Self::new().await.unwrap_or_else(|_| panic!("Failed to create texture atlas cache"))
+        })
     }
 }
 
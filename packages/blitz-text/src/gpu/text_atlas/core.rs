@@ -3,7 +3,8 @@
 //! This module contains the main EnhancedTextAtlas struct and its basic operations.
 
 use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
-use std::time::Instant;
+
+use web_time::Instant;
 
 // Re-export cosmyc-text types
 pub use cosmyc_text::{FontSystem, SwashCache};
@@ -4,9 +4,9 @@
 //! and atlas growth monitoring.
 
 use std::sync::atomic::Ordering;
-use std::time::Instant;
 
 use glyphon::ContentType;
+use web_time::Instant;
 
 use super::core::EnhancedTextAtlas;
 use super::types::AtlasGrowthEvent;
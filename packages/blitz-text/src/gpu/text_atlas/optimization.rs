@@ -4,7 +4,8 @@
 //! including growth prediction and performance tuning.
 
 use std::sync::atomic::Ordering;
-use std::time::Instant;
+
+use web_time::Instant;
 
 use super::core::EnhancedTextAtlas;
 use super::types::{GrowthPrediction, OptimizationResult};
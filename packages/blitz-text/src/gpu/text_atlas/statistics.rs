@@ -88,6 +88,6 @@ impl EnhancedTextAtlas {
             .store(current_memory, Ordering::Relaxed);
 
         self.growth_events.lock().clear();
-        self.stats_reset_time = std::time::Instant::now();
+        self.stats_reset_time = web_time::Instant::now();
     }
 }
@@ -2,9 +2,8 @@
 //!
 //! This module contains all the type definitions used by the enhanced text atlas system.
 
-use std::time::Instant;
-
 use glyphon::ContentType;
+use web_time::Instant;
 
 /// Atlas performance statistics
 #[derive(Debug, Clone, Copy)]
@@ -3,7 +3,8 @@
 //! This module contains the main EnhancedTextRenderer struct and its basic operations.
 
 use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize};
-use std::time::Instant;
+
+use web_time::Instant;
 
 // Re-export cosmyc-text types
 pub use cosmyc_text::{Buffer, Color, FontSystem, LayoutGlyph, LayoutRun, SwashCache};
@@ -18,6 +19,7 @@ use crate::custom_glyphs::{
     system::codepoint_to_compact_id, AtlasProcessor, CustomGlyphCache, CustomGlyphError,
     CustomGlyphRegistry,
 };
+use crate::emoji;
 use crate::gpu::GpuRenderConfig;
 
 /// Enhanced TextRenderer with comprehensive performance monitoring and optimization
@@ -160,6 +162,9 @@ impl EnhancedTextRenderer {
 
         // Iterate through all layout runs in the buffer
         for run in buffer.layout_runs() {
+            let standalone: Vec<usize> =
+                emoji::standalone_chars(run.text).map(|(index, _)| index).collect();
+
             // Iterate through glyphs in this run
             for glyph in run.glyphs.iter() {
                 // Extract character(s) from glyph cluster
@@ -170,9 +175,11 @@ impl EnhancedTextRenderer {
                     if let Some(ch) = char_range.chars().next() {
                         let codepoint = ch as u32;
 
-                        // Check if this is a custom glyph using existing detection
-                        if AtlasProcessor::is_emoji_codepoint(codepoint)
-                            || AtlasProcessor::is_icon_codepoint(codepoint)
+                        // Check if this is a custom glyph using existing detection. Emoji which
+                        // are part of a sequence (ZWJ, skin tone, ...) are left to the color font.
+                        if (AtlasProcessor::is_emoji_codepoint(codepoint)
+                            || AtlasProcessor::is_icon_codepoint(codepoint))
+                            && standalone.binary_search(&glyph.start).is_ok()
                         {
                             // Map codepoint to compact ID using helper function
                             let Some(id) = codepoint_to_compact_id(codepoint) else {
@@ -3,10 +3,10 @@
 //! This module handles text preparation, layout processing, and glyph preparation.
 
 use std::sync::atomic::Ordering;
-use std::time::Instant;
 
 use cosmyc_text::{Buffer, FontSystem, SwashCache};
 use glyphon::{PrepareError, TextArea, TextAtlas};
+use web_time::Instant;
 use wgpu::{Device, Queue};
 
 use super::core::EnhancedTextRenderer;
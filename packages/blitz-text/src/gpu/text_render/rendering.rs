@@ -3,9 +3,9 @@
 //! This module handles the actual rendering of text and performance metrics collection.
 
 use std::sync::atomic::Ordering;
-use std::time::Instant;
 
 use glyphon::{RenderError, TextBounds, Viewport};
+use web_time::Instant;
 use wgpu::RenderPass;
 
 use super::core::EnhancedTextRenderer;
@@ -3,7 +3,8 @@
 //! This module provides comprehensive statistics collection and analysis capabilities.
 
 use std::sync::atomic::Ordering;
-use std::time::Instant;
+
+use web_time::Instant;
 
 use super::core::EnhancedTextRenderer;
 use super::types::{PerformanceMetrics, RenderMetrics};
@@ -3,7 +3,7 @@
 //! This module contains the main EnhancedViewport struct and its core functionality,
 //! integrating resolution management and performance analytics.
 
-use std::time::Instant;
+use web_time::Instant;
 
 // Re-export glyphon types for convenience
 pub use glyphon::{Cache, Resolution, Viewport};
@@ -4,9 +4,10 @@
 //! and optimization detection for viewport operations.
 
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use parking_lot::Mutex;
+use web_time::Instant;
 
 use super::types::ViewportStats;
 
@@ -4,9 +4,9 @@
 //! and optimal resolution prediction based on usage patterns.
 
 use std::collections::HashMap;
-use std::time::Instant;
 
 use glyphon::Resolution;
+use web_time::Instant;
 
 use super::types::{OptimalResolutionPrediction, ResolutionAnalysis, ResolutionEvent};
 use crate::gpu::{GpuTextError, GpuTextResult};
@@ -3,9 +3,10 @@
 //! This module contains all the data structures used for viewport statistics,
 //! resolution tracking, and analysis results.
 
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use glyphon::Resolution;
+use web_time::Instant;
 
 /// Viewport performance statistics
 #[derive(Debug, Clone, Copy)]
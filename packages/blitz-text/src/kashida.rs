@@ -0,0 +1,305 @@
+//! Kashida justification of Arabic text
+//!
+//! cosmyc-text justifies a line (`text-align: justify`) by widening its word separators, which is
+//! how Latin text is justified. Arabic text is instead justified by elongating the joins between
+//! its letters with kashidas (U+0640 ARABIC TATWEEL), so that words rather than the gaps between
+//! them grow, see <https://drafts.csswg.org/css-text-3/#justify-cursive>.
+//!
+//! Lines are broken and justified by cosmyc-text as usual, which fixes the extent of each line.
+//! When a justified line holding Arabic text is drawn, [`justified_glyphs`] takes the space added
+//! to its separators back and spreads it over the last join of its words, which are filled with
+//! tatweels. A kashida is never narrower than a tatweel, so lines with too little space to spread
+//! keep their widened separators, as do lines without Arabic text. Like baseline shifts (see
+//! [`crate::baseline_shift`]), kashidas only move glyphs when they are drawn.
+
+use std::borrow::Cow;
+
+use cosmyc_text::{Align, Buffer, FontSystem, LayoutGlyph, LayoutRun};
+
+use crate::spacing::is_word_separator;
+
+const TATWEEL: char = '\u{0640}';
+
+/// Tolerance when comparing glyph positions, in pixels
+const EPSILON: f32 = 0.01;
+
+/// Whether any justified line of `buffer` holds Arabic text, so may be drawn with kashidas
+pub fn may_have_kashidas(buffer: &Buffer) -> bool {
+    buffer.lines.iter().any(|line| {
+        line.align() == Some(Align::Justified) && line.text().chars().any(joins_to_previous)
+    })
+}
+
+/// The glyphs of `run` as they should be drawn: when its line is justified and holds Arabic text,
+/// with the space added to its separators moved into kashidas, which are appended to the glyphs
+pub fn justified_glyphs<'b>(
+    buffer: &Buffer,
+    run: &LayoutRun<'b>,
+    font_system: &mut FontSystem,
+) -> Cow<'b, [LayoutGlyph]> {
+    let glyphs = run.glyphs;
+    let justified = buffer
+        .lines
+        .get(run.line_i)
+        .is_some_and(|line| line.align() == Some(Align::Justified));
+    if !justified {
+        return Cow::Borrowed(glyphs);
+    }
+    let joins = word_end_joins(run);
+    if joins.is_empty() {
+        return Cow::Borrowed(glyphs);
+    }
+
+    // The space justification added to each separator, over its advance in the font. It is
+    // taken back from the right edge of the separator.
+    let expansions: Vec<(f32, f32)> = glyphs
+        .iter()
+        .filter(|glyph| {
+            let text = run.text.get(glyph.start..glyph.end).unwrap_or_default();
+            !text.is_empty() && text.chars().all(is_word_separator)
+        })
+        .filter_map(|glyph| {
+            let expansion = glyph.w - glyph_advance(font_system, glyph, glyph.glyph_id)?;
+            (expansion > EPSILON).then_some((glyph.x + glyph.w, expansion))
+        })
+        .collect();
+    let space: f32 = expansions.iter().map(|(_, expansion)| expansion).sum();
+
+    // The tatweel of the font of the glyph before each join
+    let joins: Vec<(f32, LayoutGlyph)> = joins
+        .into_iter()
+        .filter_map(|(at, before)| {
+            let before = &glyphs[before];
+            let font = font_system.get_font(before.font_id, before.font_weight)?;
+            let id = font.as_swash().charmap().map(TATWEEL);
+            if id == 0 {
+                return None;
+            }
+            let mut tatweel = before.clone();
+            tatweel.glyph_id = id;
+            tatweel.w = glyph_advance(font_system, before, id)?;
+            tatweel.x_offset = 0.0;
+            tatweel.y_offset = 0.0;
+            Some((at, tatweel))
+        })
+        .collect();
+
+    // Use as many of the joins as leaves each kashida at least a tatweel wide, spread evenly
+    // through the line
+    let count = (1..=joins.len()).rev().find(|&count| {
+        let width = space / count as f32;
+        (0..count).all(|i| joins[i * joins.len() / count].1.w <= width)
+    });
+    let Some(count) = count else {
+        return Cow::Borrowed(glyphs);
+    };
+    let width = space / count as f32;
+    let kashidas: Vec<&(f32, LayoutGlyph)> =
+        (0..count).map(|i| &joins[i * joins.len() / count]).collect();
+
+    // How far whatever is drawn at `x` moves: right by the kashidas to its left, and left by the
+    // expansions taken back from the separators to its left
+    let shift = |x: f32| {
+        let kashidas = kashidas.iter().filter(|(at, _)| *at <= x + EPSILON).count();
+        let expansions: f32 = expansions
+            .iter()
+            .filter(|(end, _)| *end <= x + EPSILON)
+            .map(|(_, expansion)| expansion)
+            .sum();
+        kashidas as f32 * width - expansions
+    };
+
+    let mut justified: Vec<LayoutGlyph> = glyphs
+        .iter()
+        .map(|glyph| {
+            let mut glyph = glyph.clone();
+            if let Some((_, expansion)) = expansions
+                .iter()
+                .find(|(end, _)| (*end - (glyph.x + glyph.w)).abs() <= EPSILON)
+            {
+                glyph.w -= *expansion;
+            }
+            glyph.x += shift(glyph.x);
+            glyph
+        })
+        .collect();
+
+    // Fill each kashida with overlapping tatweels, the last ending where the kashida does
+    for (at, tatweel) in &kashidas {
+        let start = at + shift(*at) - width;
+        let copies = (width / tatweel.w).ceil().max(1.0) as usize;
+        let step = match copies {
+            1 => 0.0,
+            _ => (width - tatweel.w) / (copies - 1) as f32,
+        };
+        justified.extend((0..copies).map(|i| {
+            let mut tatweel = tatweel.clone();
+            tatweel.x = start + i as f32 * step;
+            tatweel
+        }));
+    }
+    Cow::Owned(justified)
+}
+
+/// The last join of each word of `run`, as its position along the line and the index of the
+/// glyph to its left
+fn word_end_joins(run: &LayoutRun) -> Vec<(f32, usize)> {
+    // Base glyphs in visual order, leaving out marks which sit on them
+    let mut order: Vec<usize> = (0..run.glyphs.len())
+        .filter(|&i| run.glyphs[i].w > 0.0)
+        .collect();
+    order.sort_by(|&a, &b| run.glyphs[a].x.total_cmp(&run.glyphs[b].x));
+
+    // Joins with the logical offset of the text after them
+    let mut joins: Vec<(usize, f32, usize)> = order
+        .windows(2)
+        .filter_map(|pair| {
+            let (left, right) = (&run.glyphs[pair[0]], &run.glyphs[pair[1]]);
+            let (first, second) = if run.rtl { (right, left) } else { (left, right) };
+            if first.end != second.start {
+                return None;
+            }
+            let last = run.text[first.start..first.end]
+                .chars()
+                .rev()
+                .find(|&c| !is_arabic_mark(c))?;
+            let next = run.text[second.start..second.end].chars().next()?;
+            (joins_to_next(last) && joins_to_previous(next))
+                .then_some((second.start, left.x + left.w, pair[0]))
+        })
+        .collect();
+    joins.sort_by_key(|(offset, ..)| *offset);
+
+    // A join is the last of its word when a separator comes before the next join. Lines break
+    // between words, so the last join of the line ends its word too.
+    joins
+        .iter()
+        .enumerate()
+        .filter(|(i, (offset, ..))| match joins.get(i + 1) {
+            Some((next, ..)) => run.text[*offset..*next].chars().any(is_word_separator),
+            None => true,
+        })
+        .map(|(_, (_, at, before))| (*at, *before))
+        .collect()
+}
+
+/// The advance of glyph `id` in the font of `glyph`, in pixels
+fn glyph_advance(font_system: &mut FontSystem, glyph: &LayoutGlyph, id: u16) -> Option<f32> {
+    let font = font_system.get_font(glyph.font_id, glyph.font_weight)?;
+    Some(
+        font.as_swash()
+            .glyph_metrics(&[])
+            .scale(glyph.font_size)
+            .advance_width(id),
+    )
+}
+
+/// Whether `c` is an Arabic letter which joins to the letter before it
+fn joins_to_previous(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0620}'..='\u{064A}'
+            | '\u{066E}'..='\u{066F}'
+            | '\u{0671}'..='\u{0673}'
+            | '\u{0675}'..='\u{06D3}'
+            | '\u{06D5}'
+            | '\u{06EE}'..='\u{06EF}'
+            | '\u{06FA}'..='\u{06FC}'
+            | '\u{06FF}'
+            | '\u{0750}'..='\u{077F}'
+    ) && c != '\u{0621}'
+}
+
+/// Whether `c` is an Arabic letter which joins to the letter after it, which leaves out the
+/// letters that only join to the one before them (alef, dal, reh, waw, ...)
+fn joins_to_next(c: char) -> bool {
+    joins_to_previous(c)
+        && !matches!(
+            c,
+            '\u{0622}'..='\u{0625}'
+                | '\u{0627}'
+                | '\u{0629}'
+                | '\u{062F}'..='\u{0632}'
+                | '\u{0648}'
+                | '\u{0671}'..='\u{0673}'
+                | '\u{0675}'..='\u{0677}'
+                | '\u{0688}'..='\u{0699}'
+                | '\u{06C0}'
+                | '\u{06C3}'..='\u{06CB}'
+                | '\u{06CD}'
+                | '\u{06CF}'
+                | '\u{06D2}'..='\u{06D3}'
+                | '\u{06D5}'
+                | '\u{06EE}'..='\u{06EF}'
+                | '\u{0759}'..='\u{075B}'
+                | '\u{076B}'..='\u{076C}'
+                | '\u{0771}'
+                | '\u{0773}'..='\u{0774}'
+                | '\u{0778}'..='\u{0779}'
+        )
+}
+
+/// Whether `c` is an Arabic combining mark (a vowel sign, shadda, ...), which doesn't join
+fn is_arabic_mark(c: char) -> bool {
+    matches!(c, '\u{064B}'..='\u{065F}' | '\u{0670}' | '\u{06D6}'..='\u{06ED}')
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmyc_text::{Attrs, Family, Metrics, Shaping, Wrap};
+
+    use super::*;
+
+    #[test]
+    fn justified_arabic_lines_are_elongated_instead_of_spaced() {
+        crate::measurement::with_font_system(|font_system| {
+            let mut buffer = Buffer::new(font_system, Metrics::new(16.0, 20.0));
+            buffer.set_wrap(font_system, Wrap::Word);
+            let text = "بسم الله الرحمن الرحيم بسم الله الرحمن الرحيم";
+            let attrs = Attrs::new().family(Family::Name("DejaVu Sans"));
+            buffer.set_text(font_system, text, &attrs, Shaping::Advanced);
+            buffer.set_size(font_system, Some(200.0), None);
+            for line in &mut buffer.lines {
+                line.set_align(Some(Align::Justified));
+            }
+            buffer.shape_until_scroll(font_system, false);
+            assert!(may_have_kashidas(&buffer));
+
+            let extent = |glyphs: &[LayoutGlyph]| {
+                let left = glyphs.iter().map(|glyph| glyph.x).fold(f32::INFINITY, f32::min);
+                let right = glyphs
+                    .iter()
+                    .map(|glyph| glyph.x + glyph.w)
+                    .fold(f32::NEG_INFINITY, f32::max);
+                (left, right)
+            };
+            let run = buffer.layout_runs().next().unwrap();
+            let glyphs = justified_glyphs(&buffer, &run, font_system);
+
+            // Tatweels are added, and the line keeps its extent
+            assert!(glyphs.len() > run.glyphs.len());
+            let tatweels = &glyphs[run.glyphs.len()..];
+            assert!(tatweels.iter().all(|glyph| glyph.glyph_id == tatweels[0].glyph_id));
+            let (left, right) = extent(run.glyphs);
+            let (justified_left, justified_right) = extent(&glyphs);
+            assert!((justified_left - left).abs() < 0.1, "{left} → {justified_left}");
+            assert!((justified_right - right).abs() < 0.1, "{right} → {justified_right}");
+
+            // The last line isn't justified, so is drawn as it was laid out
+            let last = buffer.layout_runs().last().unwrap();
+            let glyphs = justified_glyphs(&buffer, &last, font_system);
+            assert!(matches!(glyphs, Cow::Borrowed(_)));
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn only_some_arabic_letters_join_to_the_next() {
+        // Beh, lam and yeh join on both sides, alef and reh only to the letter before them
+        assert!(joins_to_next('ب') && joins_to_next('ل') && joins_to_next('ي'));
+        assert!(!joins_to_next('ا') && !joins_to_next('ر'));
+        assert!(joins_to_previous('ا') && joins_to_previous('ر'));
+        // Hamza and Latin letters don't join at all
+        assert!(!joins_to_previous('ء') && !joins_to_previous('a'));
+    }
+}
@@ -12,19 +12,32 @@
 #![allow(unused_variables)]
 
 pub mod analysis;
+pub mod baseline_shift;
 pub mod bidi;
+pub mod break_spaces;
 pub mod cache;
 pub mod cosmyc;
 pub mod cosmyc_types;
+#[cfg(feature = "gpu")]
 pub mod custom_glyphs;
+pub mod decoration;
 pub mod embedded_fallback;
+pub mod emoji;
 pub mod error;
 pub mod features;
+pub mod font_override;
+pub mod font_units;
+#[cfg(feature = "gpu")]
 pub mod gpu;
+pub mod kashida;
 pub mod line_breaking;
 pub mod measurement;
+pub mod navigation;
+pub mod runtime;
 pub mod shaper;
 pub mod shaping;
+pub mod spacing;
+#[cfg(feature = "gpu")]
 pub mod text_system;
 pub mod types;
 
@@ -56,6 +69,7 @@ pub use cosmyc_types::{
     EnhancedBuffer,
     EnhancedFontSystem,
     Family,
+    FeatureTag,
     Font,
     FontFeatures,
     FontSystem,
@@ -76,16 +90,24 @@ pub use cosmyc_types::{
     Weight,
     Wrap,
 };
+#[cfg(feature = "gpu")]
 pub use custom_glyphs::{
     hash_color_key, AtlasCoords, CustomGlyph, CustomGlyphCache, CustomGlyphData, CustomGlyphError,
     CustomGlyphId, CustomGlyphRegistry, CustomGlyphSystem, GlyphKey, GlyphMetrics,
     GlyphSystemConfig, GlyphSystemStats,
 };
+pub use decoration::DecorationMetrics;
 pub use embedded_fallback::{
     ensure_embedded_fallback, load_embedded_fallback, EMBEDDED_FALLBACK_FAMILY,
 };
 pub use error::ShapingError;
-pub use features::{CustomFeatures, FeatureLookup, FeatureSettings, FeaturesCache};
+pub use features::{
+    CssFontFeatures, CustomFeatures, FeatureLookup, FeatureSettings, FeaturesCache,
+    FontVariantCaps,
+};
+pub use font_override::{font_override, new_font_system, set_font_override, FontOverride};
+pub use font_units::FontUnitMetrics;
+#[cfg(feature = "gpu")]
 pub use gpu::{
     cache::GpuCacheStats, text_atlas::AtlasStats, viewport::ViewportStats, EnhancedGpuCache,
     EnhancedTextAtlas, EnhancedTextRenderer, EnhancedViewport, GpuRenderConfig, GpuRenderStats,
@@ -96,20 +118,18 @@ pub use measurement::{
     CharacterPosition, EnhancedTextMeasurement, EnhancedTextMeasurer, FontMetrics, LineMeasurement,
     MeasurementStats, TextMeasurement, TextMeasurer,
 };
-pub use shaper::TextShaper;
+pub use shaper::{
+    CaseOutcome, ConformanceReport, ConformanceRunner, ShapingCorpus, ShapingProfile,
+    ShapingProfiler, ShapingStage, TextShaper,
+};
+pub use spacing::TextSpacing;
+pub use cosmyc::AttrsList;
+pub use cosmyc_text::{Action, BufferLine, Edit, Editor, FamilyOwned, LineEnding, Selection};
+#[cfg(feature = "gpu")]
 pub use text_system::{
-    Action,
-    AttrsList,
-    BufferLine,
     ComprehensiveStats,
-    Edit,
-    Editor,
-    // Re-export FamilyOwned for font family handling
-    FamilyOwned,
-    LineEnding,
     PreparedText,
     RenderMetrics as SystemRenderMetrics,
-    Selection,
     SystemOptimizationResult,
     SystemPerformanceStats,
     TextAreaConfig,
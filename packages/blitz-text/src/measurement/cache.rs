@@ -3,13 +3,16 @@
 //! This module provides caching functionality for text measurement operations
 //! to optimize font metrics and layout calculations.
 
-use goldylox::{Goldylox, GoldyloxBuilder};
+use std::sync::Arc;
 
+use goldylox::GoldyloxBuilder;
+
+use crate::cache::ManagedGoldylox;
 use crate::measurement::types::{MeasurementCacheKey, TextMeasurement};
 
 /// Cache manager for text measurement results
 pub struct CacheManager {
-    cache: Goldylox<String, TextMeasurement>,
+    cache: Arc<ManagedGoldylox<String, TextMeasurement>>,
 }
 
 impl CacheManager {
@@ -28,7 +31,7 @@ impl CacheManager {
         println!("✅ CacheManager using global Goldylox cache (singleton)");
 
         Ok(Self { 
-            cache: (*cache).clone() // Clone the Arc to get the underlying Goldylox instance
+            cache,
         })
     }
 
@@ -45,6 +48,7 @@ impl CacheManager {
         value: TextMeasurement,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let string_key = Self::key_to_string(&key);
+        self.cache.record(string_key.clone());
         self.cache
             .put(string_key, value).await
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
@@ -191,53 +195,27 @@ impl CacheManager {
 impl Default for CacheManager {
     fn default() -> Self {
         // Since build() is async and Default can't be async, we use a blocking approach
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                let cache = GoldyloxBuilder::<String, TextMeasurement>::new()
-                    .hot_tier_max_entries(2000)
-                    .hot_tier_memory_limit_mb(32)
-                    .warm_tier_max_entries(8000)
-                    .warm_tier_max_memory_bytes(64 * 1024 * 1024) // 64MB
-                    .cold_tier_max_size_bytes(128 * 1024 * 1024) // 128MB
-                    .compression_level(4)
-                    .background_worker_threads(2)
-                    .cache_id("measurement_cache_default")
+        crate::runtime::block_on(async {
+            let cache = match GoldyloxBuilder::<String, TextMeasurement>::new()
+                .hot_tier_max_entries(2000)
+                .hot_tier_memory_limit_mb(32)
+                .warm_tier_max_entries(8000)
+                .warm_tier_max_memory_bytes(64 * 1024 * 1024) // 64MB
+                .cold_tier_max_size_bytes(128 * 1024 * 1024) // 128MB
+                .compression_level(4)
+                .background_worker_threads(crate::runtime::cache_worker_threads(2))
+                .cache_id("measurement_cache_default")
+                .build().await
+            {
+                Ok(cache) => cache,
+                // Last resort: minimal cache configuration
+                Err(_) => GoldyloxBuilder::<String, TextMeasurement>::new()
+                    .cache_id("measurement_cache_minimal")
                     .build().await
-                    .unwrap_or_else(|_| {
-                        // Last resort: minimal cache configuration
-                        tokio::runtime::Runtime::new()
-                            .expect("Failed to create tokio runtime")
-                            .block_on(async {
-                                GoldyloxBuilder::<String, TextMeasurement>::new()
-                                    .cache_id("measurement_cache_minimal")
-                                    .build().await
-                                    .unwrap()
-                            })
-                    });
-                Self { cache }
-            })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    let cache = GoldyloxBuilder::<String, TextMeasurement>::new()
-                        .hot_tier_max_entries(2000)
-                        .hot_tier_memory_limit_mb(32)
-                        .warm_tier_max_entries(8000)
-                        .warm_tier_max_memory_bytes(64 * 1024 * 1024) // 64MB
-                        .cold_tier_max_size_bytes(128 * 1024 * 1024) // 128MB
-                        .compression_level(4)
-                        .background_worker_threads(2)
-                        .cache_id("measurement_cache_default")
-                        .build().await
-                        .expect("Failed to build cache");
-                    Self { cache }
-                })
-        }
+                    .unwrap(),
+            };
+            Self { cache }
+        })
     }
 }
 
@@ -1,12 +1,13 @@
 //! Enhanced measurement core using goldylox multi-tier caching
 
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use cosmyc_text::fontdb;
 use goldylox::traits::CacheKey;
-use goldylox::Goldylox;
 use serde::{Deserialize, Serialize};
 
+use crate::cache::ManagedGoldylox;
 use crate::measurement::cache::UnifiedCacheManager;
 use crate::measurement::types::*;
 
@@ -86,7 +87,7 @@ impl CacheKey for EnhancedMeasurementKey {
 
 /// Enhanced measurement system using goldylox (lock-free)
 pub struct EnhancedMeasurementCore {
-    cache: Goldylox<String, TextMeasurement>,
+    cache: Arc<ManagedGoldylox<String, TextMeasurement>>,
     unified_cache: UnifiedCacheManager,
 }
 
@@ -100,7 +101,7 @@ impl EnhancedMeasurementCore {
 impl EnhancedMeasurementCore {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
         // Get global Goldylox cache (singleton)
-        let cache = (*crate::cache::get_text_measurement_cache()).clone();
+        let cache = crate::cache::get_text_measurement_cache();
         
         // Create unified cache manager for font metrics, bidi, features
         let unified_cache = UnifiedCacheManager::new().await
@@ -156,6 +157,7 @@ impl EnhancedMeasurementCore {
     }
 
     async fn put(&self, key: String, value: TextMeasurement) {
+        self.cache.record(key.clone());
         if let Err(e) = self.cache.put(key, value).await {
             eprintln!("Warning: Failed to cache measurement in Goldylox: {}", e);
         }
@@ -190,21 +192,9 @@ impl EnhancedMeasurementCore {
 impl Default for EnhancedMeasurementCore {
     fn default() -> Self {
         // Since new() is async and Default can't be async, we use a blocking approach
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.expect("Failed to create EnhancedMeasurementCore")
-            })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.expect("Failed to create EnhancedMeasurementCore")
-                })
-        }
+        crate::runtime::block_on(async {
+            Self::new().await.expect("Failed to create EnhancedMeasurementCore")
+        })
     }
 }
 
@@ -289,20 +279,8 @@ impl EnhancedTextMeasurer {
 
 impl Default for EnhancedTextMeasurer {
     fn default() -> Self {
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.expect("Failed to create EnhancedTextMeasurer")
-            })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.expect("Failed to create EnhancedTextMeasurer")
-                })
-        }
+        crate::runtime::block_on(async {
+            Self::new().await.expect("Failed to create EnhancedTextMeasurer")
+        })
     }
 }
@@ -206,30 +206,14 @@ impl FontMetricsCache {
 impl Default for FontMetricsCache {
     fn default() -> Self {
         // Since new() is async and Default can't be async, we use a blocking approach
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| {
-                    // Fallback: create a minimal HashMap-based cache that always works
-                    FontMetricsCache {
-                        cache_type: CacheType::HashMap(Mutex::new(HashMap::new())),
-                    }
-                })
+        crate::runtime::block_on(async {
+            Self::new().await.unwrap_or_else(|_| {
+                // Fallback: create a minimal HashMap-based cache that always works
+                FontMetricsCache {
+                    cache_type: CacheType::HashMap(Mutex::new(HashMap::new())),
+                }
             })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| {
-                        FontMetricsCache {
-                            cache_type: CacheType::HashMap(Mutex::new(HashMap::new())),
-                        }
-                    })
-                })
-        }
+        })
     }
 }
 
@@ -350,29 +334,13 @@ impl FontMetricsCalculator {
 impl Default for FontMetricsCalculator {
     fn default() -> Self {
         // Since new() is async and Default can't be async, we use a blocking approach
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::new().await.unwrap_or_else(|_| {
-                    // Fallback: Use HashMap-based cache when goldylox initialization fails
-                    FontMetricsCalculator {
-                        cache: FontMetricsCache::default(),
-                    }
-                })
+        crate::runtime::block_on(async {
+            Self::new().await.unwrap_or_else(|_| {
+                // Fallback: Use HashMap-based cache when goldylox initialization fails
+                FontMetricsCalculator {
+                    cache: FontMetricsCache::default(),
+                }
             })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::new().await.unwrap_or_else(|_| {
-                        FontMetricsCalculator {
-                            cache: FontMetricsCache::default(),
-                        }
-                    })
-                })
-        }
+        })
     }
 }
@@ -147,8 +147,8 @@ impl EnhancedTextMeasurer {
             line_measurements,
             total_character_count,
             baseline_offset: 0.0, // Calculated from first line
-            measured_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
+            measured_at: web_time::SystemTime::now()
+                .duration_since(web_time::SystemTime::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_millis() as u64,
 
@@ -3,7 +3,7 @@
 //! API References:
 //! - std::sync::atomic::{AtomicU64, Ordering} for thread-safe counters
 //! - std::sync::{Arc, Mutex} for shared ownership and thread safety
-//! - std::time::Instant for high-precision timing
+//! - web_time::Instant for high-precision timing
 //! - std::collections::HashMap for key-value storage
 //! - ahash::AHashMap for fast hashing (dependency: ahash = "0.8")
 //! - crossbeam::channel for lock-free communication (dependency: crossbeam = "0.8")
@@ -13,7 +13,8 @@
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+
+use web_time::Instant;
 
 use crate::types::{ShapedText, ShapingCacheKey};
 
@@ -72,60 +72,27 @@ pub struct TextMeasurer {
 impl TextMeasurer {
     /// Create a new TextMeasurer with default settings
     pub fn new() -> Self {
-        use tokio::runtime::Handle;
-        
-        // Try to use current runtime if available
-        let result = if let Ok(handle) = Handle::try_current() {
-            handle.block_on(async {
-                Self::with_cache_size(10000).await
-            })
-        } else {
-            // No runtime available, create one temporarily
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create tokio runtime")
-                .block_on(async {
-                    Self::with_cache_size(10000).await
-                })
-        };
+        let result = crate::runtime::block_on(async {
+            Self::with_cache_size(10000).await
+        });
         
         result.unwrap_or_else(|_| {
             // Fallback to basic implementation without goldylox if initialization fails
             let font_system = Arc::new(ArcSwap::new(Arc::new(FontSystem::new())));
             let cache_manager = Arc::new(ArcSwap::new(Arc::new({
-                use tokio::runtime::Handle;
-                
-                // Try to use current runtime if available
-                if let Ok(handle) = Handle::try_current() {
-                    handle.block_on(async {
-                        UnifiedCacheManager::new().await.unwrap_or_else(|_| {
-                            // If goldylox cache creation fails, create fallback with default implementations
-                            UnifiedCacheManager {
-                                measurement_cache: CacheManager::new().unwrap_or_else(|_| {
-                                    panic!("Failed to create measurement cache manager")
-                                }),
-                                font_metrics_cache: FontMetricsCache::default(),
-                                bidi_cache: BidiCache::default(),
-                                features_cache: FeaturesCache::default(),
-                            }
-                        })
+                crate::runtime::block_on(async {
+                    UnifiedCacheManager::new().await.unwrap_or_else(|_| {
+                        // If goldylox cache creation fails, create fallback with default implementations
+                        UnifiedCacheManager {
+                            measurement_cache: CacheManager::new().unwrap_or_else(|_| {
+                                panic!("Failed to create measurement cache manager")
+                            }),
+                            font_metrics_cache: FontMetricsCache::default(),
+                            bidi_cache: BidiCache::default(),
+                            features_cache: FeaturesCache::default(),
+                        }
                     })
-                } else {
-                    // No runtime available, create one temporarily
-                    tokio::runtime::Runtime::new()
-                        .expect("Failed to create tokio runtime")
-                        .block_on(async {
-                            UnifiedCacheManager::new().await.unwrap_or_else(|_| {
-                                UnifiedCacheManager {
-                                    measurement_cache: CacheManager::new().unwrap_or_else(|_| {
-                                        panic!("Failed to create measurement cache manager")
-                                    }),
-                                    font_metrics_cache: FontMetricsCache::default(),
-                                    bidi_cache: BidiCache::default(),
-                                    features_cache: FeaturesCache::default(),
-                                }
-                            })
-                        })
-                }
+                })
             })));
             let stats = Arc::new(MeasurementStatsInner::new());
             
@@ -4,7 +4,8 @@
 //! for high-performance concurrent access without locks.
 
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::time::SystemTime;
+
+use web_time::SystemTime;
 
 /// Lock-free atomic metrics with cache-line alignment
 #[repr(align(64))]
@@ -33,7 +34,7 @@ impl AtomicMetrics {
     #[inline(always)]
     pub fn new() -> Self {
         let now_ns = SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
+            .duration_since(web_time::SystemTime::UNIX_EPOCH)
             .map(|d| d.as_nanos() as u64)
             .unwrap_or(0);
 
@@ -169,7 +170,7 @@ impl AtomicMetrics {
     #[inline(always)]
     pub fn reset(&self) {
         let now_ns = SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
+            .duration_since(web_time::SystemTime::UNIX_EPOCH)
             .map(|d| d.as_nanos() as u64)
             .unwrap_or(0);
 
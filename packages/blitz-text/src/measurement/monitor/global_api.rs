@@ -179,7 +179,7 @@ macro_rules! record_cache_error {
 
 /// Performance monitoring utilities
 pub mod utils {
-    use std::time::Instant;
+    use web_time::Instant;
 
     use super::*;
 
@@ -4,10 +4,10 @@
 //! all monitoring functionality with lock-free operations.
 
 use std::sync::Arc;
-use std::time::SystemTime;
 
 use arrayvec::ArrayVec;
 use crossbeam::channel::Sender;
+use web_time::SystemTime;
 
 use super::alerts::{AlertThresholds, PerformanceAlert};
 use super::atomic_metrics::{AtomicMetrics, CacheMetricsSnapshot};
@@ -73,7 +73,7 @@ impl CachePerformanceMonitor {
         self.metrics.increment_operations();
 
         let now_ns = SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
+            .duration_since(web_time::SystemTime::UNIX_EPOCH)
             .map(|d| d.as_nanos() as u64)
             .unwrap_or(0);
 
@@ -3,7 +3,7 @@
 //! This module provides structures and functions for collecting performance
 //! samples and calculating trends with zero allocations.
 
-use std::time::SystemTime;
+use web_time::SystemTime;
 
 /// Cache-aligned performance sample (64 bytes = 1 cache line)
 #[derive(Debug, Clone, Copy)]
@@ -65,7 +65,7 @@ impl PerformanceSample {
         last_op_ns: u64,
     ) -> Self {
         let now_ns = SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
+            .duration_since(web_time::SystemTime::UNIX_EPOCH)
             .map(|d| d.as_nanos() as u64)
             .unwrap_or(0);
 
@@ -3,9 +3,8 @@
 //! This module contains the main text measurement algorithm that coordinates
 //! font processing, glyph analysis, and character positioning.
 
-use std::time::Instant;
-
 use cosmyc_text::{Attrs, Buffer, Shaping};
+use web_time::Instant;
 
 use super::cache::UnifiedCacheManager;
 use super::font_metrics::{calculate_baseline_offset, extract_font_metrics};
@@ -93,8 +92,8 @@ pub fn perform_measurement(
             let mut overall_cap_height = 0.0f32;
             let mut advance_width = 0.0f32;
             let mut all_glyphs = Vec::new();
-            let measurement_time = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
+            let measurement_time = web_time::SystemTime::now()
+                .duration_since(web_time::SystemTime::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_millis() as u64;
 
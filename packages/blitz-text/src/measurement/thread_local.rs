@@ -112,7 +112,7 @@ where
 
         // Initialize FontSystem on first access WITH embedded fallback
         if font_system_opt.is_none() {
-            let mut new_font_system = FontSystem::new();
+            let mut new_font_system = crate::font_override::new_font_system();
 
             // CRITICAL: Load embedded fallback to guarantee font_id validity
             let _ = crate::embedded_fallback::load_embedded_fallback(new_font_system.db_mut());
@@ -136,7 +136,7 @@ pub fn initialize_from_shared_font_system(_shared_font_system: &FontSystem) {
 
         // Create a new FontSystem for thread-local use
         // It will automatically load system fonts on first use
-        let new_font_system = FontSystem::new();
+        let new_font_system = crate::font_override::new_font_system();
 
         *font_system_opt = Some(new_font_system);
     });
@@ -48,7 +48,7 @@ impl GoldyloxMeasurementCache {
             .warm_tier_max_memory_bytes(192 * 1024 * 1024) // 192MB
             .cold_tier_max_size_bytes(384 * 1024 * 1024) // 384MB
             .compression_level(6)
-            .background_worker_threads(3)
+            .background_worker_threads(crate::runtime::cache_worker_threads(3))
             .cache_id("goldylox_measurement_cache")
             .build()
             .await?;
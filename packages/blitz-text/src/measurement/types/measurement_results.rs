@@ -94,8 +94,8 @@ impl CacheValue for TextMeasurement {
 #[derive(Debug, Clone)]
 pub struct TextMeasurementMetadata {
     pub access_count: u64,
-    pub creation_time: std::time::SystemTime,
-    pub last_access: std::time::SystemTime,
+    pub creation_time: web_time::SystemTime,
+    pub last_access: web_time::SystemTime,
 }
 
 unsafe impl Send for TextMeasurement {}
@@ -0,0 +1,356 @@
+//! Cursor navigation by grapheme cluster, word and line
+//!
+//! Positions are byte indices into a line of text. Moving by grapheme cluster uses the extended
+//! grapheme cluster boundaries of [UAX #29](https://www.unicode.org/reports/tr29/), so that a
+//! cursor never ends up inside an emoji sequence, a base character and its combining marks, or a
+//! Hangul syllable. Moving by word skips to the end (or start) of the next word, where a word is a
+//! UAX #29 word segment that contains a letter or digit.
+//!
+//! The functions taking a [`Buffer`] move a [`Cursor`] between the lines of the buffer, and
+//! [`line_start`] / [`line_end`] find the ends of the visual (wrapped) line containing a cursor.
+//! [`left_grapheme`] and [`right_grapheme`] move in visual order, so that in bidirectional text
+//! the cursor moves the way the arrow key points rather than through the text's logical order.
+
+use cosmyc_text::{Affinity, Buffer, Cursor, LayoutGlyph, LayoutLine};
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+
+/// Clamp `index` to the text and round it down to a `char` boundary
+fn char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Whether `index` is on a grapheme cluster boundary
+pub fn is_grapheme_boundary(text: &str, index: usize) -> bool {
+    if index > text.len() || !text.is_char_boundary(index) {
+        return false;
+    }
+    GraphemeCursor::new(index, text.len(), true)
+        .is_boundary(text, 0)
+        .unwrap_or(true)
+}
+
+/// The first grapheme cluster boundary after `index`, or `None` at the end of the text
+pub fn next_grapheme_boundary(text: &str, index: usize) -> Option<usize> {
+    let index = char_boundary(text, index);
+    GraphemeCursor::new(index, text.len(), true)
+        .next_boundary(text, 0)
+        .ok()
+        .flatten()
+}
+
+/// The last grapheme cluster boundary before `index`, or `None` at the start of the text
+pub fn prev_grapheme_boundary(text: &str, index: usize) -> Option<usize> {
+    let index = char_boundary(text, index);
+    GraphemeCursor::new(index, text.len(), true)
+        .prev_boundary(text, 0)
+        .ok()
+        .flatten()
+}
+
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+/// The end of the word at or after `index`, or `None` at the end of the text. Moves to the end
+/// of the text if there are no more words.
+pub fn next_word_boundary(text: &str, index: usize) -> Option<usize> {
+    if index >= text.len() {
+        return None;
+    }
+    let end = text
+        .split_word_bound_indices()
+        .map(|(start, segment)| (start + segment.len(), segment))
+        .find(|(end, segment)| *end > index && is_word(segment))
+        .map_or(text.len(), |(end, _)| end);
+    Some(end)
+}
+
+/// The start of the word before `index`, or `None` at the start of the text. Moves to the start
+/// of the text if there are no more words.
+pub fn prev_word_boundary(text: &str, index: usize) -> Option<usize> {
+    if index == 0 {
+        return None;
+    }
+    let start = text
+        .split_word_bound_indices()
+        .rev()
+        .find(|(start, segment)| *start < index && is_word(segment))
+        .map_or(0, |(start, _)| start);
+    Some(start)
+}
+
+fn line_text(buffer: &Buffer, line: usize) -> Option<&str> {
+    buffer.lines.get(line).map(|line| line.text())
+}
+
+/// The position one grapheme cluster after `cursor`, moving onto the next line at the end of a
+/// line. Returns `None` at the end of the buffer.
+pub fn next_grapheme(buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+    let text = line_text(buffer, cursor.line)?;
+    match next_grapheme_boundary(text, cursor.index) {
+        Some(index) => Some(Cursor::new(cursor.line, index)),
+        None => line_text(buffer, cursor.line + 1).map(|_| Cursor::new(cursor.line + 1, 0)),
+    }
+}
+
+/// The position one grapheme cluster before `cursor`, moving onto the end of the previous line
+/// at the start of a line. Returns `None` at the start of the buffer.
+pub fn prev_grapheme(buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+    let text = line_text(buffer, cursor.line)?;
+    match prev_grapheme_boundary(text, cursor.index) {
+        Some(index) => Some(Cursor::new(cursor.line, index)),
+        None => previous_line_end(buffer, cursor),
+    }
+}
+
+/// The end of the next word after `cursor`, continuing onto the following lines
+pub fn next_word(buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+    let text = line_text(buffer, cursor.line)?;
+    match next_word_boundary(text, cursor.index) {
+        Some(index) => Some(Cursor::new(cursor.line, index)),
+        None => {
+            let next = cursor.line + 1;
+            let text = line_text(buffer, next)?;
+            Some(Cursor::new(next, next_word_boundary(text, 0).unwrap_or(0)))
+        }
+    }
+}
+
+/// The start of the previous word before `cursor`, continuing onto the preceding lines
+pub fn prev_word(buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+    let text = line_text(buffer, cursor.line)?;
+    match prev_word_boundary(text, cursor.index) {
+        Some(index) => Some(Cursor::new(cursor.line, index)),
+        None => {
+            let end = previous_line_end(buffer, cursor)?;
+            let text = line_text(buffer, end.line)?;
+            Some(Cursor::new(end.line, prev_word_boundary(text, end.index).unwrap_or(0)))
+        }
+    }
+}
+
+fn previous_line_end(buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+    let line = cursor.line.checked_sub(1)?;
+    let text = line_text(buffer, line)?;
+    Some(Cursor::new(line, text.len()))
+}
+
+/// The visual line containing `cursor` and its byte range, if its line has been laid out
+fn visual_line(buffer: &Buffer, cursor: Cursor) -> Option<(&LayoutLine, (usize, usize))> {
+    let line = buffer.lines.get(cursor.line)?;
+    let layout = line.layout_opt()?;
+
+    let ranges: Vec<(&LayoutLine, (usize, usize))> = layout
+        .iter()
+        .map(|layout_line| {
+            let start = layout_line.glyphs.iter().map(|glyph| glyph.start).min();
+            let end = layout_line.glyphs.iter().map(|glyph| glyph.end).max();
+            (layout_line, (start.unwrap_or(0), end.unwrap_or(line.text().len())))
+        })
+        .collect();
+
+    // A position where a line wraps is both the end of one visual line and the start of the
+    // next, so use the cursor's affinity to choose between them
+    let contains = |&&(_, (start, end)): &&(&LayoutLine, (usize, usize))| match cursor.affinity {
+        Affinity::Before => start < cursor.index && cursor.index <= end,
+        Affinity::After => start <= cursor.index && cursor.index < end,
+    };
+    ranges
+        .iter()
+        .find(contains)
+        .or_else(|| match cursor.index == 0 {
+            true => ranges.first(),
+            false => ranges.last(),
+        })
+        .copied()
+}
+
+/// The byte range of the visual line containing `cursor`, if its line has been laid out
+fn visual_line_range(buffer: &Buffer, cursor: Cursor) -> Option<(usize, usize)> {
+    visual_line(buffer, cursor).map(|(_, range)| range)
+}
+
+/// The start of the visual line containing `cursor` (the start of its line if it hasn't been
+/// laid out)
+pub fn line_start(buffer: &Buffer, cursor: Cursor) -> Cursor {
+    let start = visual_line_range(buffer, cursor).map_or(0, |(start, _)| start);
+    Cursor::new_with_affinity(cursor.line, start, Affinity::After)
+}
+
+/// The end of the visual line containing `cursor` (the end of its line if it hasn't been laid
+/// out)
+pub fn line_end(buffer: &Buffer, cursor: Cursor) -> Cursor {
+    let end = match visual_line_range(buffer, cursor) {
+        Some((_, end)) => end,
+        None => line_text(buffer, cursor.line).map_or(cursor.index, str::len),
+    };
+    Cursor::new_with_affinity(cursor.line, end, Affinity::Before)
+}
+
+/// Whether a line of the buffer is laid out right to left
+pub fn is_rtl_line(buffer: &Buffer, line: usize) -> bool {
+    buffer
+        .lines
+        .get(line)
+        .and_then(|line| line.shape_opt())
+        .is_some_and(|shape| shape.rtl)
+}
+
+/// The cursor positions along the visual line containing `cursor`, from left to right: the left
+/// edge of each glyph, then the right edge of the last one. The left edge of a right-to-left
+/// glyph is the end of its text.
+fn visual_positions(buffer: &Buffer, cursor: Cursor) -> Option<Vec<usize>> {
+    let text = line_text(buffer, cursor.line)?;
+    let (layout_line, _) = visual_line(buffer, cursor)?;
+
+    let mut glyphs: Vec<&LayoutGlyph> = layout_line.glyphs.iter().collect();
+    glyphs.sort_by(|a, b| a.x.total_cmp(&b.x));
+    let edges = |glyph: &LayoutGlyph| match glyph.level.is_rtl() {
+        true => (glyph.end, glyph.start),
+        false => (glyph.start, glyph.end),
+    };
+    let mut positions: Vec<usize> = glyphs.iter().map(|glyph| edges(glyph).0).collect();
+    positions.extend(glyphs.last().map(|glyph| edges(glyph).1));
+    // Glyphs of the same cluster share their edges, and ligatures can span several clusters
+    positions.dedup();
+    positions.retain(|&index| is_grapheme_boundary(text, index));
+    Some(positions)
+}
+
+/// Move `cursor` one position to the right (or left) along its visual line. Returns `None` where
+/// there's no position to move to on the line, or `cursor` isn't on one of its positions.
+fn visual_step(buffer: &Buffer, cursor: Cursor, right: bool) -> Option<Cursor> {
+    let positions = visual_positions(buffer, cursor)?;
+    let current = positions.iter().position(|&index| index == cursor.index)?;
+    let next = match right {
+        true => current + 1,
+        false => current.checked_sub(1)?,
+    };
+    let index = *positions.get(next)?;
+
+    // Stay on the same visual line where it wraps
+    let (_, end) = visual_line_range(buffer, cursor)?;
+    let affinity = match index == end {
+        true => Affinity::Before,
+        false => Affinity::After,
+    };
+    Some(Cursor::new_with_affinity(cursor.line, index, affinity))
+}
+
+/// Move `cursor` past the end of its visual line in the direction of its line's text (`forward`)
+/// or against it, onto the neighboring visual line
+fn leave_visual_line(buffer: &Buffer, cursor: Cursor, forward: bool) -> Option<Cursor> {
+    let text = line_text(buffer, cursor.line)?;
+    match forward {
+        true => {
+            let end = line_end(buffer, cursor);
+            match end.index < text.len() {
+                true => Some(Cursor::new_with_affinity(end.line, end.index, Affinity::After)),
+                false => next_grapheme(buffer, Cursor::new(end.line, text.len())),
+            }
+        }
+        false => {
+            let start = line_start(buffer, cursor);
+            match start.index > 0 {
+                true => Some(Cursor::new_with_affinity(start.line, start.index, Affinity::Before)),
+                false => previous_line_end(buffer, start),
+            }
+        }
+    }
+}
+
+/// The position one grapheme cluster to the right of `cursor` on screen, which in right-to-left
+/// text comes before it. Moves onto the neighboring line at the end of a visual line, and in
+/// logical order where `cursor` isn't on a laid out line.
+pub fn right_grapheme(buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+    visual_arrow(buffer, cursor, true)
+}
+
+/// The position one grapheme cluster to the left of `cursor` on screen (see
+/// [`right_grapheme`])
+pub fn left_grapheme(buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+    visual_arrow(buffer, cursor, false)
+}
+
+fn visual_arrow(buffer: &Buffer, cursor: Cursor, right: bool) -> Option<Cursor> {
+    if let Some(moved) = visual_step(buffer, cursor, right) {
+        return Some(moved);
+    }
+    let forward = right != is_rtl_line(buffer, cursor.line);
+    match visual_positions(buffer, cursor) {
+        Some(positions) if positions.contains(&cursor.index) => {
+            leave_visual_line(buffer, cursor, forward)
+        }
+        _ if forward => next_grapheme(buffer, cursor),
+        _ => prev_grapheme(buffer, cursor),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_over_whole_grapheme_clusters() {
+        // A family emoji (joined with ZWJs), "e" with a combining acute accent, and a flag
+        let text = "a👨\u{200d}👩\u{200d}👧e\u{301}🇳🇿";
+        let mut boundaries = vec![0];
+        while let Some(next) = next_grapheme_boundary(text, *boundaries.last().unwrap()) {
+            boundaries.push(next);
+        }
+        assert_eq!(boundaries, vec![0, 1, 19, 22, 30]);
+
+        assert_eq!(prev_grapheme_boundary(text, 30), Some(22));
+        // From inside a cluster, move to its start
+        assert_eq!(prev_grapheme_boundary(text, 5), Some(1));
+        assert!(!is_grapheme_boundary(text, 21));
+        assert_eq!(next_grapheme_boundary(text, 30), None);
+    }
+
+    #[test]
+    fn moves_between_words() {
+        let text = "hello, wide world!";
+        assert_eq!(next_word_boundary(text, 0), Some(5));
+        assert_eq!(next_word_boundary(text, 5), Some(11));
+        assert_eq!(next_word_boundary(text, 17), Some(18));
+        assert_eq!(prev_word_boundary(text, 18), Some(12));
+        assert_eq!(prev_word_boundary(text, 12), Some(7));
+        assert_eq!(prev_word_boundary(text, 3), Some(0));
+        assert_eq!(prev_word_boundary(text, 0), None);
+    }
+
+    #[test]
+    fn arrows_move_in_visual_order() {
+        use cosmyc_text::{Attrs, Family, Metrics, Shaping};
+
+        const FONT: &[u8] = include_bytes!("../../blitz-test/assets/fonts/DejaVuSans.ttf");
+        crate::measurement::with_font_system(|font_system| {
+            font_system.db_mut().load_font_data(FONT.to_vec());
+            let mut buffer = Buffer::new(font_system, Metrics::new(16.0, 20.0));
+            let attrs = Attrs::new().family(Family::Name("DejaVu Sans"));
+            // Hebrew letters are two bytes long, and displayed right to left
+            buffer.set_text(font_system, "abc אבג", &attrs, Shaping::Advanced);
+            buffer.shape_until_scroll(font_system, false);
+
+            let mut cursor = Cursor::new(0, 0);
+            let mut rights = Vec::new();
+            while let Some(next) = right_grapheme(&buffer, cursor) {
+                rights.push(next.index);
+                cursor = next;
+            }
+            assert_eq!(rights, [1, 2, 3, 10, 8, 6, 4]);
+
+            let mut lefts = Vec::new();
+            while let Some(next) = left_grapheme(&buffer, cursor) {
+                lefts.push(next.index);
+                cursor = next;
+            }
+            assert_eq!(lefts, [6, 8, 10, 3, 2, 1, 0]);
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! Running the async constructors of caches from synchronous code
+
+use std::future::Future;
+
+/// Run `future` to completion, blocking the current thread
+///
+/// Uses the current Tokio runtime if there is one, or a temporary one if not.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle.block_on(future),
+        // No runtime available, create one temporarily
+        Err(_) => tokio::runtime::Runtime::new()
+            .expect("Failed to create tokio runtime")
+            .block_on(future),
+    }
+}
+
+/// Run `future` to completion, blocking the current thread
+///
+/// The browser's main thread can't block, so the future is polled in place for as long as it
+/// wakes itself (as futures which yield do). A future which is left waiting on anything else could
+/// only be woken by the browser's event loop, which can't run until this returns, so that panics.
+#[cfg(target_arch = "wasm32")]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    poll_in_place(future).expect("block_on can't wait for the browser's event loop on wasm32")
+}
+
+/// Run the future made by `make_future` to completion without blocking the caller
+///
+/// It runs on a thread of its own, with the current Tokio runtime if there is one, so that the
+/// caller (which may be one of the runtime's workers) can go on.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_detached<F, Fut>(make_future: F)
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = ()>,
+{
+    let handle = tokio::runtime::Handle::try_current().ok();
+    let spawned = std::thread::Builder::new()
+        .name("blitz-text-cache".into())
+        .spawn(move || match handle {
+            Some(handle) => handle.block_on(make_future()),
+            None => block_on(make_future()),
+        });
+    if let Err(err) = spawned {
+        log::warn!("Failed to spawn a cache thread: {}", err);
+    }
+}
+
+/// Run the future made by `make_future` to completion without blocking the caller
+///
+/// It's spawned on the browser's event loop, as threads can't be.
+#[cfg(target_arch = "wasm32")]
+pub fn run_detached<F, Fut>(make_future: F)
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    wasm_bindgen_futures::spawn_local(make_future());
+}
+
+/// Poll `future` until it's ready, or until it's pending without having woken itself
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn poll_in_place<F: Future>(future: F) -> Option<F::Output> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// Records whether the future has been woken since it was last polled
+    struct Woken(AtomicBool);
+
+    impl Wake for Woken {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = Waker::from(woken.clone());
+    let mut context = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return Some(output);
+        }
+        if !woken.0.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+    }
+}
+
+/// The number of background worker threads to give a cache which would use `threads`. Threads
+/// can't be spawned on wasm32, so caches there have none and do their maintenance inline.
+pub fn cache_worker_threads(threads: usize) -> usize {
+    if cfg!(target_arch = "wasm32") {
+        0
+    } else {
+        threads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+
+    use super::*;
+
+    /// A future which is pending `yields` times before it's ready, and wakes itself each time
+    /// unless it's `stuck`
+    struct Yield {
+        yields: usize,
+        stuck: bool,
+    }
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.yields == 0 {
+                return Poll::Ready(());
+            }
+            self.yields -= 1;
+            if !self.stuck {
+                cx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn futures_which_wake_themselves_are_polled_in_place() {
+        let future = Yield {
+            yields: 3,
+            stuck: false,
+        };
+        assert_eq!(poll_in_place(future), Some(()));
+        let future = Yield {
+            yields: 1,
+            stuck: true,
+        };
+        assert_eq!(poll_in_place(future), None);
+    }
+}
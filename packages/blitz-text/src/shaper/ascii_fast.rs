@@ -103,7 +103,7 @@ pub(super) fn shape_ascii_fast(
         total_height,
         baseline,
         line_count: 1,
-        shaped_at: std::time::Instant::now(),
+        shaped_at: web_time::Instant::now(),
         cache_key,
     });
 
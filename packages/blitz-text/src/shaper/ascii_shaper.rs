@@ -118,7 +118,7 @@ impl AsciiShaper {
             total_height,
             baseline,
             line_count: 1,
-            shaped_at: std::time::Instant::now(),
+            shaped_at: web_time::Instant::now(),
             cache_key,
         });
 
@@ -0,0 +1,465 @@
+//! Shaping conformance checks against reference shaping results
+//!
+//! A [`ShapingCorpus`] is a list of [`ShapingCase`]s, each a piece of text in some font along with
+//! what shaping it should produce. Cases can state font-independent expectations (where clusters
+//! start, the direction of each run, whether repeated characters take different contextual forms)
+//! and can carry a reference: the output of `hb-shape --output-format=json --font-size=16` for the
+//! same text and font, which is compared glyph by glyph. References are whole-text shaping
+//! results, so they only make sense for text in a single direction.
+//!
+//! [`ConformanceRunner`] shapes each case through the same pipeline as
+//! [`TextShaper`](super::TextShaper) and reports how it differs from the expectations. Cases whose
+//! font isn't installed are skipped rather than failed, as glyph ids and clusters depend on it.
+
+use std::fmt;
+
+use cosmyc_text::{Attrs, Family, FontSystem};
+use serde::Deserialize;
+
+use super::RunShaper;
+use crate::analysis::TextAnalyzer;
+use crate::error::ShapingError;
+use crate::shaping::types::{ShapedRun, TextDirection};
+
+/// How far (in pixels) advances may differ from the reference before a case fails
+const ADVANCE_TOLERANCE: f32 = 1.0;
+
+/// The kind of shaping behavior a case exercises
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapingCategory {
+    ArabicJoining,
+    DevanagariConjuncts,
+    ThaiClusters,
+    EmojiZwj,
+    BidiMixed,
+    CombiningMarks,
+    Ligatures,
+    /// Scripts whose letters are shaped one glyph each, without joining or reordering
+    Alphabetic,
+}
+
+/// The direction of a run, as written in a corpus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunDirection {
+    Ltr,
+    Rtl,
+}
+
+/// A glyph in `hb-shape`'s JSON output
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ReferenceGlyph {
+    #[serde(rename = "g")]
+    pub glyph_id: u16,
+    #[serde(rename = "cl")]
+    pub cluster: u32,
+    #[serde(rename = "ax", default)]
+    pub x_advance: f32,
+}
+
+/// Font-independent expectations for a case
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ShapingExpectations {
+    /// Byte offsets at which clusters start, in ascending order
+    pub clusters: Option<Vec<usize>>,
+    /// Run directions in logical order, with adjacent runs in the same direction merged
+    pub directions: Option<Vec<RunDirection>>,
+    /// Whether every glyph must be different (e.g. the initial, medial and final forms of a
+    /// repeated Arabic letter)
+    pub distinct_glyphs: bool,
+    /// The most glyphs the text may be shaped into (e.g. 1 for an emoji ZWJ sequence)
+    pub max_glyphs: Option<usize>,
+}
+
+/// A piece of text and the shaping it should produce
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShapingCase {
+    pub name: String,
+    pub category: ShapingCategory,
+    pub text: String,
+    /// The font family to shape with
+    pub font: String,
+    #[serde(default)]
+    pub expect: ShapingExpectations,
+    /// Reference shaping output for the whole text
+    #[serde(default)]
+    pub reference: Option<Vec<ReferenceGlyph>>,
+}
+
+/// A set of shaping cases
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ShapingCorpus {
+    pub cases: Vec<ShapingCase>,
+}
+
+impl ShapingCorpus {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// A way in which shaping differed from a case's expectations
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConformanceFailure {
+    Clusters {
+        expected: Vec<usize>,
+        actual: Vec<usize>,
+    },
+    Directions {
+        expected: Vec<RunDirection>,
+        actual: Vec<RunDirection>,
+    },
+    RepeatedGlyph {
+        glyph_id: u16,
+    },
+    TooManyGlyphs {
+        max: usize,
+        actual: usize,
+    },
+    GlyphCount {
+        expected: usize,
+        actual: usize,
+    },
+    Glyph {
+        index: usize,
+        expected: ReferenceGlyph,
+        actual: ReferenceGlyph,
+    },
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clusters { expected, actual } => {
+                write!(f, "clusters start at {actual:?}, expected {expected:?}")
+            }
+            Self::Directions { expected, actual } => {
+                write!(f, "run directions are {actual:?}, expected {expected:?}")
+            }
+            Self::RepeatedGlyph { glyph_id } => write!(f, "glyph {glyph_id} is repeated"),
+            Self::TooManyGlyphs { max, actual } => {
+                write!(f, "shaped into {actual} glyphs, expected at most {max}")
+            }
+            Self::GlyphCount { expected, actual } => {
+                write!(f, "shaped into {actual} glyphs, reference has {expected}")
+            }
+            Self::Glyph {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "glyph {index} is {} (cluster {}, advance {}), reference is {} (cluster {}, \
+                 advance {})",
+                actual.glyph_id,
+                actual.cluster,
+                actual.x_advance,
+                expected.glyph_id,
+                expected.cluster,
+                expected.x_advance
+            ),
+        }
+    }
+}
+
+/// The result of checking a single case
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaseOutcome {
+    Passed,
+    /// The case couldn't be checked (e.g. its font isn't installed)
+    Skipped(String),
+    Failed(Vec<ConformanceFailure>),
+}
+
+/// The outcome of every case in a corpus
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    pub outcomes: Vec<(String, CaseOutcome)>,
+}
+
+impl ConformanceReport {
+    fn count(&self, filter: impl Fn(&CaseOutcome) -> bool) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| filter(outcome))
+            .count()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.count(|outcome| *outcome == CaseOutcome::Passed)
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|outcome| matches!(outcome, CaseOutcome::Skipped(_)))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, CaseOutcome::Failed(_)))
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed(),
+            self.failed(),
+            self.skipped()
+        )?;
+        for (name, outcome) in &self.outcomes {
+            match outcome {
+                CaseOutcome::Passed => {}
+                CaseOutcome::Skipped(reason) => writeln!(f, "  {name}: skipped ({reason})")?,
+                CaseOutcome::Failed(failures) => {
+                    for failure in failures {
+                        writeln!(f, "  {name}: {failure}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shapes [`ShapingCase`]s and checks the results
+pub struct ConformanceRunner {
+    font_system: FontSystem,
+    analyzer: TextAnalyzer,
+    run_shaper: RunShaper,
+}
+
+impl ConformanceRunner {
+    pub fn new(font_system: FontSystem) -> Self {
+        Self {
+            font_system,
+            analyzer: TextAnalyzer::new(),
+            run_shaper: RunShaper::new(),
+        }
+    }
+
+    pub fn font_system_mut(&mut self) -> &mut FontSystem {
+        &mut self.font_system
+    }
+
+    /// Whether a font with the family `family` is installed
+    pub fn has_font(&self, family: &str) -> bool {
+        self.font_system.db().faces().any(|face| {
+            face.families
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case(family))
+        })
+    }
+
+    /// Shape `text` into runs (in logical order), without breaking it into lines
+    pub fn shape(&mut self, text: &str, attrs: Attrs) -> Result<Vec<ShapedRun>, ShapingError> {
+        let analysis = self.analyzer.analyze_text(text)?;
+        let bidi_info = match analysis.requires_bidi {
+            true => Some(self.analyzer.process_bidi(text, analysis.base_direction)?),
+            false => None,
+        };
+        let text_runs = self.run_shaper.create_text_runs_optimized(
+            text,
+            &analysis,
+            bidi_info.as_ref(),
+            attrs,
+        )?;
+        self.run_shaper
+            .shape_runs_optimized(&mut self.font_system, text_runs)
+    }
+
+    pub fn run_case(&mut self, case: &ShapingCase) -> CaseOutcome {
+        if !self.has_font(&case.font) {
+            return CaseOutcome::Skipped(format!("font {:?} is not installed", case.font));
+        }
+
+        let attrs = Attrs::new().family(Family::Name(&case.font));
+        match self.shape(&case.text, attrs) {
+            Ok(runs) => {
+                let failures = check_case(case, &runs);
+                match failures.is_empty() {
+                    true => CaseOutcome::Passed,
+                    false => CaseOutcome::Failed(failures),
+                }
+            }
+            Err(err) => CaseOutcome::Skipped(format!("shaping failed: {err}")),
+        }
+    }
+
+    pub fn run(&mut self, corpus: &ShapingCorpus) -> ConformanceReport {
+        let outcomes = corpus
+            .cases
+            .iter()
+            .map(|case| (case.name.clone(), self.run_case(case)))
+            .collect();
+        ConformanceReport { outcomes }
+    }
+}
+
+/// Compare shaped runs against a case's expectations and reference
+pub fn check_case(case: &ShapingCase, runs: &[ShapedRun]) -> Vec<ConformanceFailure> {
+    let mut failures = Vec::new();
+    // Clusters are relative to their run, so make them relative to the whole text
+    let glyphs: Vec<ReferenceGlyph> = runs
+        .iter()
+        .flat_map(|run| {
+            run.glyphs.iter().map(|glyph| ReferenceGlyph {
+                glyph_id: glyph.glyph_id,
+                cluster: run.start_index as u32 + glyph.cluster,
+                x_advance: glyph.x_advance,
+            })
+        })
+        .collect();
+
+    if let Some(expected) = &case.expect.clusters {
+        let mut actual: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster as usize).collect();
+        actual.sort_unstable();
+        actual.dedup();
+        if actual != *expected {
+            failures.push(ConformanceFailure::Clusters {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+
+    if let Some(expected) = &case.expect.directions {
+        let mut actual: Vec<RunDirection> = runs
+            .iter()
+            .map(|run| match run.direction {
+                TextDirection::RightToLeft => RunDirection::Rtl,
+                _ => RunDirection::Ltr,
+            })
+            .collect();
+        actual.dedup();
+        if actual != *expected {
+            failures.push(ConformanceFailure::Directions {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+
+    if case.expect.distinct_glyphs {
+        let repeated = glyphs.iter().enumerate().find_map(|(idx, glyph)| {
+            glyphs[..idx]
+                .iter()
+                .any(|other| other.glyph_id == glyph.glyph_id)
+                .then_some(glyph.glyph_id)
+        });
+        if let Some(glyph_id) = repeated {
+            failures.push(ConformanceFailure::RepeatedGlyph { glyph_id });
+        }
+    }
+
+    if let Some(max) = case.expect.max_glyphs {
+        if glyphs.len() > max {
+            failures.push(ConformanceFailure::TooManyGlyphs {
+                max,
+                actual: glyphs.len(),
+            });
+        }
+    }
+
+    if let Some(reference) = &case.reference {
+        if reference.len() != glyphs.len() {
+            failures.push(ConformanceFailure::GlyphCount {
+                expected: reference.len(),
+                actual: glyphs.len(),
+            });
+        } else {
+            let mismatch = reference
+                .iter()
+                .zip(&glyphs)
+                .enumerate()
+                .find(|(_, (expected, actual))| {
+                    expected.glyph_id != actual.glyph_id
+                        || expected.cluster != actual.cluster
+                        || (expected.x_advance - actual.x_advance).abs() > ADVANCE_TOLERANCE
+                });
+            if let Some((index, (expected, actual))) = mismatch {
+                failures.push(ConformanceFailure::Glyph {
+                    index,
+                    expected: *expected,
+                    actual: *actual,
+                });
+            }
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shaping::types::{GlyphFlags, ShapedGlyph};
+
+    fn run(start_index: usize, direction: TextDirection, glyphs: &[(u16, u32)]) -> ShapedRun {
+        ShapedRun {
+            glyphs: glyphs
+                .iter()
+                .map(|&(glyph_id, cluster)| ShapedGlyph {
+                    glyph_id,
+                    cluster,
+                    x_advance: 8.0,
+                    y_advance: 0.0,
+                    x_offset: 0.0,
+                    y_offset: 0.0,
+                    flags: GlyphFlags::empty(),
+                    font_size: 16.0,
+                    color: None,
+                })
+                .collect(),
+            script: unicode_script::Script::Latin,
+            direction,
+            language: None,
+            level: unicode_bidi::Level::ltr(),
+            width: 0.0,
+            height: 0.0,
+            ascent: 0.0,
+            descent: 0.0,
+            line_gap: 0.0,
+            start_index,
+            end_index: 0,
+        }
+    }
+
+    #[test]
+    fn reports_differences_from_expectations() {
+        let json = r#"{ "cases": [{
+            "name": "mixed",
+            "category": "bidi_mixed",
+            "text": "ab אב",
+            "font": "Test",
+            "expect": { "clusters": [0, 1, 2, 3, 5], "directions": ["ltr", "rtl"] },
+            "reference": [{ "g": 1, "cl": 0, "ax": 8 }]
+        }] }"#;
+        let corpus = ShapingCorpus::from_json(json).unwrap();
+        let case = &corpus.cases[0];
+
+        let runs = [
+            run(0, TextDirection::LeftToRight, &[(1, 0), (2, 1), (3, 2)]),
+            run(3, TextDirection::RightToLeft, &[(5, 2), (4, 0)]),
+        ];
+        let failures = check_case(case, &runs);
+        assert_eq!(
+            failures,
+            vec![ConformanceFailure::GlyphCount {
+                expected: 1,
+                actual: 5
+            }]
+        );
+
+        // Clusters are merged over the whole text
+        let runs = [
+            run(0, TextDirection::LeftToRight, &[(1, 0), (2, 1), (3, 2)]),
+            run(3, TextDirection::LeftToRight, &[(4, 0), (4, 0)]),
+        ];
+        let failures = check_case(case, &runs);
+        assert!(matches!(failures[0], ConformanceFailure::Clusters { .. }));
+        assert!(matches!(failures[1], ConformanceFailure::Directions { .. }));
+    }
+}
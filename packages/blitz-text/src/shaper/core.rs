@@ -99,7 +99,7 @@ impl TextShaper {
                 total_height: 0.0,
                 baseline: 0.0,
                 line_count: 0,
-                shaped_at: std::time::Instant::now(),
+                shaped_at: web_time::Instant::now(),
                 cache_key: self
                     .cache_manager
                     .create_cache_key(text, &attrs, max_width)?,
@@ -164,7 +164,7 @@ impl TextShaper {
             total_height,
             baseline,
             line_count,
-            shaped_at: std::time::Instant::now(),
+            shaped_at: web_time::Instant::now(),
             cache_key: cache_key.clone(),
         });
 
@@ -8,9 +8,11 @@
 //! - Fast metrics computation with SIMD optimization
 
 pub mod ascii_shaper;
+pub mod conformance;
 pub mod glyph_analysis;
 pub mod line_breaking;
 pub mod metrics_calculation;
+pub mod profiler;
 pub mod run_shaping;
 
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -19,14 +21,20 @@ use std::sync::Arc;
 use arc_swap::ArcSwap;
 // Re-export public types and functionality
 pub use ascii_shaper::{AsciiShaper, AsciiShaperStats};
+pub use conformance::{
+    CaseOutcome, ConformanceFailure, ConformanceReport, ConformanceRunner, ShapingCase,
+    ShapingCorpus,
+};
 use cosmyc_text::{Attrs, FontSystem, Metrics};
 pub use glyph_analysis::{GlyphAnalysisStats, GlyphAnalyzer};
-use goldylox::{Goldylox, GoldyloxBuilder};
+use goldylox::GoldyloxBuilder;
 pub use line_breaking::{LineBreakStats, LineBreaker};
 pub use metrics_calculation::{BoundingBox, LineMetrics, MetricsCalculator, MetricsStats};
+pub use profiler::{ShapingProfile, ShapingProfiler, ShapingStage};
 pub use run_shaping::{RunShaper, RunShapingStats};
 
 use crate::analysis::TextAnalyzer;
+use crate::cache::ManagedGoldylox;
 use crate::error::ShapingError;
 use crate::shaping::types::{ShapedText, ShapingCacheKey};
 use crate::types::{ShapingContext, TextDirection};
@@ -119,7 +127,7 @@ static TOTAL_GLYPHS_SHAPED: AtomicUsize = AtomicUsize::new(0);
 pub struct TextShaper {
     font_system: Arc<ArcSwap<FontSystem>>,
     analyzer: TextAnalyzer,
-    cache: Goldylox<String, ShapedText>,
+    cache: Arc<ManagedGoldylox<String, ShapedText>>,
     ascii_shaper: AsciiShaper,
     run_shaper: RunShaper,
     line_breaker: LineBreaker,
@@ -141,7 +149,7 @@ impl TextShaper {
         Ok(Self {
             font_system: Arc::new(ArcSwap::new(Arc::new(font_system))),
             analyzer: TextAnalyzer::new(),
-            cache: (*cache).clone(), // Clone the Arc to get the underlying Goldylox instance
+            cache,
             ascii_shaper: AsciiShaper::new(),
             run_shaper: RunShaper::new(),
             line_breaker: LineBreaker::new(),
@@ -166,7 +174,7 @@ impl TextShaper {
         Ok(Self {
             font_system: Arc::new(ArcSwap::new(Arc::new(font_system))),
             analyzer: TextAnalyzer::new(),
-            cache: (*cache).clone(), // Clone the Arc to get the underlying Goldylox instance
+            cache,
             ascii_shaper: AsciiShaper::new(),
             run_shaper: RunShaper::new(),
             line_breaker: LineBreaker::new(),
@@ -191,7 +199,7 @@ impl TextShaper {
                 total_height: 0.0,
                 baseline: 0.0,
                 line_count: 0,
-                shaped_at: std::time::Instant::now(),
+                shaped_at: web_time::Instant::now(),
                 cache_key: Self::create_cache_key(text, &attrs, max_width),
             }));
         }
@@ -274,13 +282,14 @@ impl TextShaper {
             total_height,
             baseline,
             line_count,
-            shaped_at: std::time::Instant::now(),
+            shaped_at: web_time::Instant::now(),
             cache_key: cache_key.clone(),
         });
 
         // Cache result if appropriate
         if shaped_text.runs.len() > 1 || text.len() > 10 {
             let string_key = Self::key_to_string(&cache_key);
+            self.cache.record(string_key.clone());
             if let Err(_) = self.cache.put(string_key, (*shaped_text).clone()).await {
                 // Cache failure is non-fatal, continue with result
             }
@@ -0,0 +1,218 @@
+//! Per-stage timings of the shaping pipeline
+//!
+//! [`ShapingProfiler`] runs text through the same stages as [`TextShaper`](super::TextShaper)
+//! (analysis, bidi resolution, run shaping and line breaking) one at a time, and records how long
+//! each stage took. It never consults the shaped text cache, and clears the analysis caches
+//! before each run, so the timings reflect the cost of shaping the input from scratch. Use it to
+//! find out which stage makes a particular piece of content slow to lay out.
+
+use std::fmt;
+use std::time::Duration;
+
+use cosmyc_text::{Attrs, FontSystem};
+use web_time::Instant;
+
+use super::{LineBreaker, MetricsCalculator, RunShaper};
+use crate::analysis::TextAnalyzer;
+use crate::error::ShapingError;
+
+/// A stage of the shaping pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapingStage {
+    /// Script detection and complexity analysis
+    Analysis,
+    /// Resolving bidi levels (only for text which contains right-to-left characters)
+    Bidi,
+    /// Splitting the text into runs and shaping each run
+    Shaping,
+    /// Breaking the shaped runs into lines (only when a maximum width is given)
+    LineBreaking,
+}
+
+impl ShapingStage {
+    pub const ALL: [ShapingStage; 4] = [
+        ShapingStage::Analysis,
+        ShapingStage::Bidi,
+        ShapingStage::Shaping,
+        ShapingStage::LineBreaking,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShapingStage::Analysis => "analysis",
+            ShapingStage::Bidi => "bidi",
+            ShapingStage::Shaping => "shaping",
+            ShapingStage::LineBreaking => "line breaking",
+        }
+    }
+}
+
+/// The timings and results of shaping a piece of text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShapingProfile {
+    pub analysis: Duration,
+    pub bidi: Duration,
+    pub shaping: Duration,
+    pub line_breaking: Duration,
+    /// Length of the text in bytes
+    pub text_len: usize,
+    /// Number of script runs found by analysis
+    pub script_runs: usize,
+    pub requires_bidi: bool,
+    /// Number of shaped runs after line breaking
+    pub shaped_runs: usize,
+    pub glyphs: usize,
+    pub lines: usize,
+}
+
+impl ShapingProfile {
+    /// How long `stage` took
+    pub fn stage(&self, stage: ShapingStage) -> Duration {
+        match stage {
+            ShapingStage::Analysis => self.analysis,
+            ShapingStage::Bidi => self.bidi,
+            ShapingStage::Shaping => self.shaping,
+            ShapingStage::LineBreaking => self.line_breaking,
+        }
+    }
+
+    /// Time taken by every stage together
+    pub fn total(&self) -> Duration {
+        self.analysis + self.bidi + self.shaping + self.line_breaking
+    }
+
+    /// The stage which took the longest
+    pub fn slowest_stage(&self) -> ShapingStage {
+        ShapingStage::ALL
+            .into_iter()
+            .max_by_key(|stage| self.stage(*stage))
+            .unwrap_or(ShapingStage::Shaping)
+    }
+}
+
+impl fmt::Display for ShapingProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} bytes, {} script runs, {} shaped runs, {} glyphs, {} lines",
+            self.text_len, self.script_runs, self.shaped_runs, self.glyphs, self.lines
+        )?;
+        let total = self.total().as_secs_f64();
+        for stage in ShapingStage::ALL {
+            let time = self.stage(stage);
+            let share = match total > 0.0 {
+                true => time.as_secs_f64() / total * 100.0,
+                false => 0.0,
+            };
+            writeln!(f, "  {:<14} {:>10.3?} ({share:>5.1}%)", stage.name(), time)?;
+        }
+        write!(f, "  {:<14} {:>10.3?}", "total", self.total())
+    }
+}
+
+/// Records how long each stage of the shaping pipeline takes for a given input
+pub struct ShapingProfiler {
+    font_system: FontSystem,
+    analyzer: TextAnalyzer,
+    run_shaper: RunShaper,
+    line_breaker: LineBreaker,
+}
+
+impl ShapingProfiler {
+    pub fn new(font_system: FontSystem) -> Self {
+        Self {
+            font_system,
+            analyzer: TextAnalyzer::new(),
+            run_shaper: RunShaper::new(),
+            line_breaker: LineBreaker::new(),
+        }
+    }
+
+    pub fn font_system_mut(&mut self) -> &mut FontSystem {
+        &mut self.font_system
+    }
+
+    /// Shape `text` and record the time taken by each stage. Text is only broken into lines if
+    /// `max_width` is given.
+    pub fn profile(
+        &mut self,
+        text: &str,
+        attrs: Attrs,
+        max_width: Option<f32>,
+    ) -> Result<ShapingProfile, ShapingError> {
+        self.analyzer.clear_caches();
+        self.run_shaper.clear_caches();
+        self.line_breaker.clear_caches();
+
+        let mut profile = ShapingProfile {
+            text_len: text.len(),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let analysis = self.analyzer.analyze_text(text)?;
+        profile.analysis = start.elapsed();
+        profile.script_runs = analysis.script_runs.len();
+        profile.requires_bidi = analysis.requires_bidi;
+
+        let bidi_info = match analysis.requires_bidi {
+            true => {
+                let start = Instant::now();
+                let bidi_info = self.analyzer.process_bidi(text, analysis.base_direction)?;
+                profile.bidi = start.elapsed();
+                Some(bidi_info)
+            }
+            false => None,
+        };
+
+        let start = Instant::now();
+        let text_runs = self.run_shaper.create_text_runs_optimized(
+            text,
+            &analysis,
+            bidi_info.as_ref(),
+            attrs,
+        )?;
+        let shaped_runs = self
+            .run_shaper
+            .shape_runs_optimized(&mut self.font_system, text_runs)?;
+        profile.shaping = start.elapsed();
+
+        let runs = match max_width {
+            Some(max_width) => {
+                let start = Instant::now();
+                let runs = self
+                    .line_breaker
+                    .apply_line_breaking_optimized(shaped_runs, max_width)?;
+                profile.line_breaking = start.elapsed();
+                runs
+            }
+            None => shaped_runs,
+        };
+
+        profile.shaped_runs = runs.len();
+        profile.glyphs = runs.iter().map(|run| run.glyphs.len()).sum();
+        profile.lines = MetricsCalculator::calculate_metrics_fast(&runs).3;
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_stage_shares() {
+        let profile = ShapingProfile {
+            analysis: Duration::from_millis(1),
+            shaping: Duration::from_millis(3),
+            text_len: 10,
+            ..Default::default()
+        };
+        assert_eq!(profile.total(), Duration::from_millis(4));
+        assert_eq!(profile.slowest_stage(), ShapingStage::Shaping);
+
+        let report = profile.to_string();
+        assert!(report.contains("shaping"));
+        assert!(report.contains("75.0%"));
+    }
+}
@@ -6,6 +6,7 @@ use cosmyc_text::{Attrs, AttrsOwned, Buffer, FontSystem, Metrics, Shaping};
 
 use super::glyph_analysis::GlyphAnalyzer;
 use crate::analysis::TextAnalyzer;
+use crate::emoji;
 use crate::error::ShapingError;
 use crate::features::FeatureLookup;
 use crate::shaping::types::{ShapedGlyph, ShapedRun, TextDirection as ShapingTextDirection};
@@ -145,8 +146,9 @@ impl RunShaper {
 
         let mut buffer = Buffer::new(font_system, metrics);
 
-        // Set shaping direction based on script and bidi level
-        let shaping_mode = if run.script.is_complex() {
+        // Set shaping direction based on script and bidi level. Emoji sequences need advanced
+        // shaping to be substituted with a single glyph by the color font.
+        let shaping_mode = if run.script.is_complex() || emoji::contains_emoji_sequence(&run.text) {
             Shaping::Advanced
         } else {
             Shaping::Basic
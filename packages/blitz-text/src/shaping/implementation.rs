@@ -7,18 +7,19 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use cosmyc_text::{Attrs, FontSystem};
-use goldylox::{Goldylox, GoldyloxBuilder};
+use goldylox::GoldyloxBuilder;
 
 use super::analysis::analyze_text_comprehensive;
 use super::features::get_script_features;
 use super::types::{FeatureSettings, ShapedText, ShapingCacheKey};
+use crate::cache::ManagedGoldylox;
 use crate::error::ShapingError;
 
 /// High-performance text shaper with complex script support
 pub struct TextShaper {
     font_system: Arc<parking_lot::RwLock<FontSystem>>,
     feature_settings: HashMap<&'static str, FeatureSettings>,
-    cache: Goldylox<String, ShapedText>,
+    cache: Arc<ManagedGoldylox<String, ShapedText>>,
 }
 
 impl TextShaper {
@@ -39,7 +40,7 @@ impl TextShaper {
         Ok(Self {
             font_system,
             feature_settings: HashMap::new(), // Features now accessed via get_script_features()
-            cache: (*cache).clone(), // Clone the Arc to get the underlying Goldylox instance
+            cache,
         })
     }
 
@@ -57,7 +58,7 @@ impl TextShaper {
                 total_height: 0.0,
                 baseline: 0.0,
                 line_count: 0,
-                shaped_at: std::time::Instant::now(),
+                shaped_at: web_time::Instant::now(),
                 cache_key: self.create_cache_key(text, &attrs, max_width)?,
             }));
         }
@@ -81,12 +82,13 @@ impl TextShaper {
             total_height: 0.0,
             baseline: 0.0,
             line_count: 0,
-            shaped_at: std::time::Instant::now(),
+            shaped_at: web_time::Instant::now(),
             cache_key: cache_key.clone(),
         };
 
         // Store in cache
         let string_key = Self::key_to_string(&cache_key);
+        self.cache.record(string_key.clone());
         self.cache
             .put(string_key, shaped_text.clone()).await
             .map_err(|e| ShapingError::CacheOperationError(format!("{:?}", e)))?;
@@ -30,8 +30,8 @@ pub struct ShapedText {
     pub total_height: f32,
     pub baseline: f32,
     pub line_count: usize,
-    #[serde(skip, default = "std::time::Instant::now")]
-    pub shaped_at: std::time::Instant,
+    #[serde(skip, default = "web_time::Instant::now")]
+    pub shaped_at: web_time::Instant,
     pub cache_key: ShapingCacheKey,
 }
 
@@ -43,7 +43,7 @@ impl Default for ShapedText {
             total_height: 0.0,
             baseline: 0.0,
             line_count: 0,
-            shaped_at: std::time::Instant::now(),
+            shaped_at: web_time::Instant::now(),
             cache_key: ShapingCacheKey::default(),
         }
     }
@@ -0,0 +1,138 @@
+//! CSS `letter-spacing` and `word-spacing`
+//!
+//! cosmyc-text applies letter spacing per span of text (see [`Attrs::letter_spacing_opt`]), adding
+//! the extra advance after every glyph in the span. Both properties are implemented on top of that
+//! by splitting text into spans at grapheme cluster boundaries:
+//!
+//!  - word separators receive the word spacing as well as the letter spacing
+//!  - cursive scripts (Arabic, Syriac, Mongolian, ...) receive no letter spacing, as inserting space
+//!    between their letters would break the joins between them
+//!    (see <https://drafts.csswg.org/css-text-3/#cursive-tracking>)
+//!
+//! Justification (`text-align: justify`) is done by cosmyc-text, which distributes the extra space
+//! on each line between its word separators. Justified Arabic text is drawn with that space moved
+//! into kashidas instead, see [`crate::kashida`].
+//!
+//! [`Attrs::letter_spacing_opt`]: cosmyc_text::Attrs
+
+use cosmyc_text::LetterSpacing;
+use unicode_script::{Script, UnicodeScript};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::features::FeatureLookup;
+
+/// Extra spacing to apply to text (in pixels)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextSpacing {
+    /// Added after every typographic character unit
+    pub letter_spacing: f32,
+    /// Added after every word separator
+    pub word_spacing: f32,
+}
+
+impl TextSpacing {
+    pub fn is_zero(&self) -> bool {
+        self.letter_spacing == 0.0 && self.word_spacing == 0.0
+    }
+
+    /// Split `text` into spans which each have a uniform letter spacing, in the `em` units which
+    /// cosmyc-text expects. Spans which don't need any spacing are `None`.
+    pub fn spans<'t>(
+        &self,
+        text: &'t str,
+        font_size: f32,
+    ) -> Vec<(&'t str, Option<LetterSpacing>)> {
+        if self.is_zero() || font_size <= 0.0 {
+            return vec![(text, None)];
+        }
+
+        let mut spans: Vec<(&str, f32)> = Vec::new();
+        let mut span_start = 0;
+        let mut span_spacing = None;
+        let mut script = Script::Common;
+        for (offset, cluster) in text.grapheme_indices(true) {
+            let Some(first) = cluster.chars().next() else {
+                continue;
+            };
+            // Common and inherited characters (punctuation, combining marks, ...) take on the
+            // script of the text before them
+            if !matches!(first.script(), Script::Common | Script::Inherited) {
+                script = first.script();
+            }
+
+            let spacing = if is_word_separator(first) {
+                self.letter_spacing + self.word_spacing
+            } else if FeatureLookup::is_cursive_script(script) {
+                0.0
+            } else {
+                self.letter_spacing
+            };
+
+            match span_spacing {
+                Some(current) if current == spacing => {}
+                Some(current) => {
+                    spans.push((&text[span_start..offset], current));
+                    span_start = offset;
+                    span_spacing = Some(spacing);
+                }
+                None => span_spacing = Some(spacing),
+            }
+        }
+        if let Some(spacing) = span_spacing {
+            spans.push((&text[span_start..], spacing));
+        }
+
+        spans
+            .into_iter()
+            .map(|(span, spacing)| {
+                let spacing = (spacing != 0.0).then(|| LetterSpacing(spacing / font_size));
+                (span, spacing)
+            })
+            .collect()
+    }
+}
+
+/// Whether `c` is a word separator, which receives `word-spacing` and is a justification
+/// opportunity. See <https://drafts.csswg.org/css-text-3/#word-separator>
+#[inline]
+pub fn is_word_separator(c: char) -> bool {
+    matches!(
+        c,
+        ' ' | '\u{00A0}' | '\u{1361}' | '\u{10100}' | '\u{10101}' | '\u{1039F}' | '\u{1091F}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_spans_at_word_separators_and_cursive_text() {
+        fn spans(spacing: TextSpacing, text: &str) -> Vec<(&str, Option<f32>)> {
+            spacing
+                .spans(text, 10.0)
+                .into_iter()
+                .map(|(span, spacing)| (span, spacing.map(|spacing| spacing.0)))
+                .collect()
+        }
+
+        let spacing = TextSpacing {
+            letter_spacing: 2.0,
+            word_spacing: 6.0,
+        };
+
+        assert_eq!(
+            spans(spacing, "ab cd سلام."),
+            vec![
+                ("ab", Some(0.2)),
+                (" ", Some(0.8)),
+                ("cd", Some(0.2)),
+                (" ", Some(0.8)),
+                // The trailing period follows Arabic text, so is treated as Arabic too
+                ("سلام.", None),
+            ]
+        );
+
+        assert_eq!(spans(TextSpacing::default(), "ab cd"), vec![("ab cd", None)]);
+    }
+}
@@ -3,7 +3,7 @@
 //! This module handles system-wide operations including statistics,
 //! health monitoring, optimization, and configuration management.
 
-use std::time::Instant;
+use web_time::Instant;
 
 use super::super::config::UnifiedTextConfig;
 use super::super::performance::{ComprehensiveStats, SystemOptimizationResult};
@@ -3,10 +3,9 @@
 //! This module handles the complex process of preparing text for GPU rendering,
 //! including buffer creation, text shaping, and GPU preparation.
 
-use std::time::Instant;
-
 use cosmyc_text::{Attrs, Buffer, Color, FontSystem, Metrics, Shaping};
 use glyphon::{Resolution, TextArea, TextBounds};
+use web_time::Instant;
 use wgpu::{Device, Queue};
 
 use super::UnifiedTextSystem;
@@ -3,10 +3,9 @@
 //! This module handles all text rendering operations, including rendering
 //! prepared text and combined measure/prepare/render operations.
 
-use std::time::Instant;
-
 use cosmyc_text::{Attrs, Color};
 use glyphon::TextBounds;
+use web_time::Instant;
 use wgpu::{Device, Queue, RenderPass};
 
 use super::system::UnifiedTextSystem;
@@ -4,11 +4,11 @@
 //! and its initialization logic that coordinates all subsystems.
 
 use std::cell::RefCell;
-use std::time::Instant;
 
 use cosmyc_text::{Attrs, FontSystem};
 use glyphon::CustomGlyph;
 use thread_local::ThreadLocal;
+use web_time::Instant;
 use wgpu::{DepthStencilState, Device, MultisampleState, Queue, TextureFormat};
 
 use super::super::config::{TextSystemError, TextSystemResult, UnifiedTextConfig};
@@ -165,9 +165,12 @@ impl UnifiedTextSystem {
 
 
     /// Get reference to thread-local font system (lock-free access)
-    /// Each thread gets its own FontSystem instance for zero contention
+    /// Each thread gets its own FontSystem instance for zero contention, with the machine's fonts
+    /// or the [font override](crate::font_override)
     pub fn with_font_system<T>(&self, f: impl FnOnce(&mut FontSystem) -> T) -> T {
-        let font_system_cell = self.font_system.get_or(|| RefCell::new(FontSystem::new()));
+        let font_system_cell = self
+            .font_system
+            .get_or(|| RefCell::new(crate::font_override::new_font_system()));
         let mut font_system = font_system_cell.borrow_mut();
         f(&mut *font_system)
     }
@@ -3,7 +3,7 @@
 //! This module handles performance tracking, statistics collection,
 //! and system health monitoring for the text system.
 
-use std::time::Instant;
+use web_time::Instant;
 
 /// System performance monitor
 #[derive(Debug)]
@@ -23,7 +23,7 @@ pub struct ShapedText {
     pub total_height: f32,
     pub baseline: f32,
     pub line_count: usize,
-    pub shaped_at: std::time::Instant,
+    pub shaped_at: web_time::Instant,
     pub cache_key: ShapingCacheKey,
 }
 
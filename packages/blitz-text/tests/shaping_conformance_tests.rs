@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use blitz_text::{
+    ensure_embedded_fallback, CaseOutcome, ConformanceRunner, FontSystem, ShapingCorpus,
+};
+
+const CORPUS: &str = include_str!("shaping/corpus.json");
+
+/// The fonts bundled with blitz-test, which the DejaVu Sans references were recorded with
+const BUNDLED_FONTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../blitz-test/assets/fonts");
+
+fn runner() -> ConformanceRunner {
+    let mut font_system = FontSystem::new();
+    ensure_embedded_fallback(&mut font_system);
+    font_system.db_mut().load_fonts_dir(Path::new(BUNDLED_FONTS));
+    // Fonts which aren't installed system-wide (e.g. the fonts references were recorded with)
+    if let Ok(dir) = std::env::var("BLITZ_SHAPING_FONTS") {
+        font_system.db_mut().load_fonts_dir(dir);
+    }
+    ConformanceRunner::new(font_system)
+}
+
+/// Check the corpus's cases in `font` (or all of them), failing on cases which couldn't be checked
+/// as well as those which don't conform
+fn check_cases(font: Option<&str>) {
+    let mut corpus = ShapingCorpus::from_json(CORPUS).unwrap();
+    corpus
+        .cases
+        .retain(|case| font.is_none() || font == Some(case.font.as_str()));
+    assert!(!corpus.cases.is_empty());
+
+    let report = runner().run(&corpus);
+    println!("{report}");
+
+    assert_eq!(report.outcomes.len(), corpus.cases.len());
+    assert_eq!(report.failed(), 0, "{report}");
+    assert_eq!(report.skipped(), 0, "{report}");
+}
+
+#[test]
+fn dejavu_sans_cases_match_corpus() {
+    check_cases(Some("DejaVu Sans"));
+}
+
+/// Every case conforms. Without BLITZ_SHAPING_FONTS, cases in the Noto fonts which aren't
+/// installed are skipped, and every other case still has to pass.
+#[test]
+fn shaping_matches_corpus() {
+    if std::env::var_os("BLITZ_SHAPING_FONTS").is_some() {
+        check_cases(None);
+        return;
+    }
+
+    let corpus = ShapingCorpus::from_json(CORPUS).unwrap();
+    let mut runner = runner();
+    let report = runner.run(&corpus);
+    println!("{report}");
+
+    assert_eq!(report.failed(), 0, "{report}");
+    for (case, (name, outcome)) in corpus.cases.iter().zip(&report.outcomes) {
+        if matches!(outcome, CaseOutcome::Skipped(_)) {
+            assert!(!runner.has_font(&case.font), "{name} was skipped: {report}");
+        }
+    }
+}
+
+#[test]
+fn single_direction_dejavu_cases_have_references() {
+    let corpus = ShapingCorpus::from_json(CORPUS).unwrap();
+    for case in &corpus.cases {
+        let single_direction = case.expect.directions.as_ref().map_or(1, Vec::len) == 1;
+        if case.font == "DejaVu Sans" && single_direction {
+            assert!(case.reference.is_some(), "{} has no reference", case.name);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use blitz_text::{ensure_embedded_fallback, Attrs, FontSystem, ShapingProfiler, ShapingStage};
+
+fn profiler() -> ShapingProfiler {
+    let mut font_system = FontSystem::new();
+    ensure_embedded_fallback(&mut font_system);
+    ShapingProfiler::new(font_system)
+}
+
+#[test]
+fn profiles_each_stage() {
+    let mut profiler = profiler();
+
+    let latin = profiler
+        .profile("Hello world, hello shaping", Attrs::new(), Some(50.0))
+        .unwrap();
+    assert!(!latin.requires_bidi);
+    assert_eq!(latin.bidi, Duration::ZERO);
+    assert_eq!(latin.text_len, 26);
+
+    let mixed = profiler
+        .profile("Hello مرحبا world", Attrs::new(), None)
+        .unwrap();
+    assert!(mixed.requires_bidi);
+    assert!(mixed.script_runs >= 2);
+    let stages: Duration = ShapingStage::ALL
+        .into_iter()
+        .map(|stage| mixed.stage(stage))
+        .sum();
+    assert_eq!(mixed.total(), stages);
+}
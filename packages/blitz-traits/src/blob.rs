@@ -0,0 +1,80 @@
+//! In-memory resources with `blob:` URLs
+//!
+//! Like `URL.createObjectURL` on the web, [`BlobRegistry::create_object_url`] gives resources
+//! which only exist in memory (like images an app generates at runtime) a `blob:` URL, which
+//! documents can refer to like any other URL. Net providers answer requests for `blob:` URLs from
+//! the [global](BlobRegistry::global) registry. Object URLs last until they're revoked, which
+//! documents do for the URLs they created when they're dropped.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use bytes::Bytes;
+use url::Url;
+use uuid::Uuid;
+
+/// A resource registered with a [`BlobRegistry`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blob {
+    pub bytes: Bytes,
+    /// The MIME type of the resource, like `image/png`
+    pub content_type: String,
+}
+
+/// The resources which have been given `blob:` URLs, see the [module docs](self)
+#[derive(Default)]
+pub struct BlobRegistry {
+    /// Blobs by their URL (without any fragment)
+    blobs: Mutex<HashMap<String, Blob>>,
+}
+
+impl BlobRegistry {
+    /// The registry net providers answer requests for `blob:` URLs from
+    pub fn global() -> &'static BlobRegistry {
+        static GLOBAL: OnceLock<BlobRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(BlobRegistry::default)
+    }
+
+    /// Register `bytes` (of the MIME type `content_type`), returning a new `blob:` URL for them.
+    /// The URL has the origin of `creator_url`, the URL of the document creating it (if any).
+    pub fn create_object_url(
+        &self,
+        creator_url: Option<&Url>,
+        bytes: impl Into<Bytes>,
+        content_type: &str,
+    ) -> Url {
+        let origin = match creator_url {
+            Some(url) => url.origin().ascii_serialization(),
+            None => "null".to_string(),
+        };
+        // Random (version 4) UUIDs, so that blob URLs can't be guessed
+        let url = Url::parse(&format!("blob:{origin}/{}", Uuid::new_v4())).unwrap();
+        let blob = Blob {
+            bytes: bytes.into(),
+            content_type: content_type.to_string(),
+        };
+        self.lock().insert(key(&url), blob);
+        url
+    }
+
+    /// Forget the resource of a `blob:` URL, so that it can no longer be loaded
+    pub fn revoke_object_url(&self, url: &Url) {
+        self.lock().remove(&key(url));
+    }
+
+    /// The resource of a `blob:` URL, unless it has been revoked
+    pub fn resolve(&self, url: &Url) -> Option<Blob> {
+        self.lock().get(&key(url)).cloned()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Blob>> {
+        self.blobs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Blobs are looked up without the fragment of their URL
+fn key(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.into()
+}
@@ -0,0 +1,173 @@
+//! A memory budget shared between the caches of the Blitz crates
+//!
+//! Caches (like the glyph atlas, or responses to preloads) implement [`ManagedCache`] and
+//! register with the [`CacheCoordinator`], which splits a single budget between them. When they
+//! hold more than the budget altogether, each is asked to evict down to its share. Shells pass on
+//! the operating system's memory warnings with [`CacheCoordinator::handle_memory_pressure`].
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+/// The budget of the [global](CacheCoordinator::global) coordinator until it's changed
+pub const DEFAULT_CACHE_BUDGET: usize = 256 * 1024 * 1024;
+
+/// A cache whose memory is managed by a [`CacheCoordinator`]
+pub trait ManagedCache: Send + Sync + 'static {
+    /// A name for the cache, for reporting
+    fn name(&self) -> &str;
+
+    /// The approximate number of bytes the cache holds
+    fn memory_usage(&self) -> usize;
+
+    /// Evict entries until the cache holds at most `target_bytes`, or as close to it as it can.
+    /// Caches which can't evict immediately (like those only usable from a render thread) may do
+    /// so the next time they're used.
+    fn evict_to(&self, target_bytes: usize);
+}
+
+/// How urgently the operating system wants memory back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryPressure {
+    /// Memory is running low: caches are shrunk to half of the budget
+    Moderate,
+    /// The app may be killed if it doesn't free memory: caches are emptied
+    Critical,
+}
+
+struct RegisteredCache {
+    cache: Weak<dyn ManagedCache>,
+    weight: u32,
+}
+
+/// Splits a memory budget between the [`ManagedCache`]s registered with it
+///
+/// Each cache's share is proportional to its weight. Shares which caches don't use are split
+/// between the others, so a cache is only asked to evict when the caches hold more than the
+/// budget altogether, and then only if it holds more than its share.
+pub struct CacheCoordinator {
+    budget: AtomicUsize,
+    caches: Mutex<Vec<RegisteredCache>>,
+}
+
+impl CacheCoordinator {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget: AtomicUsize::new(budget),
+            caches: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The coordinator the Blitz crates register their caches with
+    pub fn global() -> &'static CacheCoordinator {
+        static GLOBAL: OnceLock<CacheCoordinator> = OnceLock::new();
+        GLOBAL.get_or_init(|| CacheCoordinator::new(DEFAULT_CACHE_BUDGET))
+    }
+
+    /// Manage `cache`'s memory, giving it a share of the budget proportional to `weight`. The
+    /// cache is unregistered when it's dropped.
+    pub fn register(&self, cache: &Arc<dyn ManagedCache>, weight: u32) {
+        self.lock().push(RegisteredCache {
+            cache: Arc::downgrade(cache),
+            weight,
+        });
+    }
+
+    /// The number of bytes the caches may hold altogether
+    pub fn budget(&self) -> usize {
+        self.budget.load(Ordering::Relaxed)
+    }
+
+    /// Change the budget, evicting from the caches if they hold more than it
+    pub fn set_budget(&self, budget: usize) {
+        self.budget.store(budget, Ordering::Relaxed);
+        self.enforce_budget();
+    }
+
+    /// The name and memory usage of each registered cache
+    pub fn usage(&self) -> Vec<(String, usize)> {
+        self.live_caches()
+            .iter()
+            .map(|(cache, _)| (cache.name().to_string(), cache.memory_usage()))
+            .collect()
+    }
+
+    /// Evict from the caches which hold more than their share, if the caches hold more than the
+    /// budget altogether. Caches which grow should call this after they do.
+    pub fn enforce_budget(&self) {
+        self.shrink_to(self.budget());
+    }
+
+    /// Free memory in response to a warning from the operating system
+    pub fn handle_memory_pressure(&self, pressure: MemoryPressure) {
+        let target = match pressure {
+            MemoryPressure::Moderate => self.budget() / 2,
+            MemoryPressure::Critical => 0,
+        };
+        self.shrink_to(target);
+    }
+
+    fn shrink_to(&self, target: usize) {
+        let caches = self.live_caches();
+        let usages: Vec<usize> = caches.iter().map(|(cache, _)| cache.memory_usage()).collect();
+        if usages.iter().sum::<usize>() <= target {
+            return;
+        }
+
+        let weights: Vec<u32> = caches.iter().map(|&(_, weight)| weight).collect();
+        let shares = distribute_budget(target, &weights, &usages);
+        for (((cache, _), usage), share) in caches.iter().zip(usages).zip(shares) {
+            if usage > share {
+                cache.evict_to(share);
+            }
+        }
+    }
+
+    /// The caches which haven't been dropped, forgetting those which have
+    fn live_caches(&self) -> Vec<(Arc<dyn ManagedCache>, u32)> {
+        let mut caches = self.lock();
+        caches.retain(|registered| registered.cache.strong_count() > 0);
+        caches
+            .iter()
+            .filter_map(|registered| Some((registered.cache.upgrade()?, registered.weight)))
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<RegisteredCache>> {
+        self.caches.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Split `budget` between caches in proportion to their `weights`. Caches whose `usages` are
+/// within their share keep what they use, and the rest is split again between the others.
+pub fn distribute_budget(budget: usize, weights: &[u32], usages: &[usize]) -> Vec<usize> {
+    let mut shares = vec![0; weights.len()];
+    let mut remaining = budget;
+    let mut unsettled: Vec<usize> = (0..weights.len()).collect();
+
+    while !unsettled.is_empty() {
+        let total_weight: u64 = unsettled.iter().map(|&index| weights[index] as u64).sum();
+        if total_weight == 0 {
+            break;
+        }
+        let share = |index: usize| {
+            (remaining as u128 * weights[index] as u128 / total_weight as u128) as usize
+        };
+
+        let (within, over): (Vec<usize>, Vec<usize>) = unsettled
+            .iter()
+            .copied()
+            .partition(|&index| usages[index] <= share(index));
+        if within.is_empty() {
+            for index in over {
+                shares[index] = share(index);
+            }
+            break;
+        }
+        for index in within {
+            shares[index] = usages[index];
+            remaining -= usages[index];
+        }
+        unsettled = over;
+    }
+    shares
+}
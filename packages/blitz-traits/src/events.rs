@@ -9,13 +9,27 @@ use smol_str::SmolStr;
 #[derive(Default)]
 pub struct EventState {
     cancelled: bool,
+    /// Whether `prevent_default` is ignored, as the event isn't cancelable or is passive
+    uncancelable: bool,
     propagation_stopped: bool,
     redraw_requested: bool,
 }
 impl EventState {
+    /// The state of `event` before any handler has seen it
+    pub fn for_event(event: &DomEvent) -> Self {
+        Self {
+            uncancelable: !event.cancelable || event.passive,
+            ..Self::default()
+        }
+    }
+
+    /// Prevent the event's default action. Does nothing if the event isn't cancelable or is
+    /// passive.
     #[inline(always)]
     pub fn prevent_default(&mut self) {
-        self.cancelled = true;
+        if !self.uncancelable {
+            self.cancelled = true;
+        }
     }
 
     #[inline(always)]
@@ -52,6 +66,27 @@ pub enum UiEvent {
     KeyUp(BlitzKeyEvent),
     KeyDown(BlitzKeyEvent),
     Ime(BlitzImeEvent),
+    /// Events from pointers other than the mouse (touches and pens). The mouse's pointer events
+    /// are made from its mouse events by the event driver.
+    PointerDown(BlitzPointerEvent),
+    PointerMove(BlitzPointerEvent),
+    PointerUp(BlitzPointerEvent),
+    PointerCancel(BlitzPointerEvent),
+    GamepadButtonDown(BlitzGamepadButtonEvent),
+    GamepadButtonUp(BlitzGamepadButtonEvent),
+    GamepadAxisMove(BlitzGamepadAxisEvent),
+}
+
+/// Which part of its path through the DOM tree an event is at, see
+/// <https://dom.spec.whatwg.org/#dom-event-eventphase>
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventPhase {
+    /// Going down from the root to the target's parent
+    Capturing,
+    #[default]
+    AtTarget,
+    /// Going up from the target's parent to the root (only if the event bubbles)
+    Bubbling,
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +96,11 @@ pub struct DomEvent {
     pub bubbles: bool,
     /// which is true if the event can be canceled.
     pub cancelable: bool,
+    /// Which is true if handlers can't cancel the event, so that its default action (like
+    /// scrolling for touches) doesn't have to wait for them. Passive events have their default
+    /// action run before they are dispatched.
+    pub passive: bool,
+    pub phase: EventPhase,
 
     pub data: DomEventData,
     pub request_redraw: bool,
@@ -72,6 +112,8 @@ impl DomEvent {
             target,
             bubbles: data.bubbles(),
             cancelable: data.cancelable(),
+            passive: data.passive(),
+            phase: EventPhase::AtTarget,
             data,
             request_redraw: false,
         }
@@ -89,6 +131,8 @@ pub enum DomEventData {
     MouseDown(BlitzMouseButtonEvent),
     MouseUp(BlitzMouseButtonEvent),
     Click(BlitzMouseButtonEvent),
+    /// A request to open a context menu, e.g. by right-clicking
+    ContextMenu(BlitzMouseButtonEvent),
     KeyPress(BlitzKeyEvent),
     KeyDown(BlitzKeyEvent),
     KeyUp(BlitzKeyEvent),
@@ -96,8 +140,20 @@ pub enum DomEventData {
     Change,
     Focus,
     Blur,
-    Submit,
+    Submit(BlitzSubmitEvent),
     Ime(BlitzImeEvent),
+    TouchStart(BlitzTouchEvent),
+    TouchMove(BlitzTouchEvent),
+    TouchEnd(BlitzTouchEvent),
+    PointerDown(BlitzPointerEvent),
+    PointerMove(BlitzPointerEvent),
+    PointerUp(BlitzPointerEvent),
+    PointerCancel(BlitzPointerEvent),
+    GotPointerCapture(BlitzPointerEvent),
+    LostPointerCapture(BlitzPointerEvent),
+    GamepadButtonDown(BlitzGamepadButtonEvent),
+    GamepadButtonUp(BlitzGamepadButtonEvent),
+    GamepadAxisMove(BlitzGamepadAxisEvent),
 }
 
 impl DomEventData {
@@ -107,6 +163,7 @@ impl DomEventData {
             Self::MouseDown { .. } => "mousedown",
             Self::MouseUp { .. } => "mouseup",
             Self::Click { .. } => "click",
+            Self::ContextMenu { .. } => "contextmenu",
             Self::KeyPress { .. } => "keypress",
             Self::KeyDown { .. } => "keydown",
             Self::KeyUp { .. } => "keyup",
@@ -115,7 +172,19 @@ impl DomEventData {
             Self::Change => "change",
             Self::Focus => "focus",
             Self::Blur => "blur",
-            Self::Submit => "submit",
+            Self::Submit { .. } => "submit",
+            Self::TouchStart { .. } => "touchstart",
+            Self::TouchMove { .. } => "touchmove",
+            Self::TouchEnd { .. } => "touchend",
+            Self::PointerDown { .. } => "pointerdown",
+            Self::PointerMove { .. } => "pointermove",
+            Self::PointerUp { .. } => "pointerup",
+            Self::PointerCancel { .. } => "pointercancel",
+            Self::GotPointerCapture { .. } => "gotpointercapture",
+            Self::LostPointerCapture { .. } => "lostpointercapture",
+            Self::GamepadButtonDown { .. } => "gamepadbuttondown",
+            Self::GamepadButtonUp { .. } => "gamepadbuttonup",
+            Self::GamepadAxisMove { .. } => "gamepadaxismove",
         }
     }
 
@@ -125,6 +194,7 @@ impl DomEventData {
             Self::MouseDown { .. } => true,
             Self::MouseUp { .. } => true,
             Self::Click { .. } => true,
+            Self::ContextMenu { .. } => true,
             Self::KeyDown { .. } => true,
             Self::KeyUp { .. } => true,
             Self::KeyPress { .. } => true,
@@ -133,16 +203,35 @@ impl DomEventData {
             Self::Change => false,
             Self::Focus => false,
             Self::Blur => false,
-            Self::Submit => true,
+            Self::Submit { .. } => true,
+            Self::TouchStart { .. } => true,
+            Self::TouchMove { .. } => true,
+            Self::TouchEnd { .. } => true,
+            Self::PointerDown { .. } => true,
+            Self::PointerMove { .. } => true,
+            Self::PointerUp { .. } => true,
+            Self::PointerCancel { .. } => false,
+            Self::GotPointerCapture { .. } => false,
+            Self::LostPointerCapture { .. } => false,
+            Self::GamepadButtonDown { .. } => true,
+            Self::GamepadButtonUp { .. } => true,
+            Self::GamepadAxisMove { .. } => true,
         }
     }
 
+    /// Whether the event is passive, like touch starts and moves are (in browsers, when listened
+    /// for on the document) so that they don't hold up scrolling
+    pub fn passive(&self) -> bool {
+        matches!(self, Self::TouchStart { .. } | Self::TouchMove { .. })
+    }
+
     pub fn bubbles(&self) -> bool {
         match self {
             Self::MouseMove { .. } => true,
             Self::MouseDown { .. } => true,
             Self::MouseUp { .. } => true,
             Self::Click { .. } => true,
+            Self::ContextMenu { .. } => true,
             Self::KeyDown { .. } => true,
             Self::KeyUp { .. } => true,
             Self::KeyPress { .. } => true,
@@ -151,7 +240,19 @@ impl DomEventData {
             Self::Change => true,
             Self::Focus => false,
             Self::Blur => false,
-            Self::Submit => true,
+            Self::Submit { .. } => true,
+            Self::TouchStart { .. } => true,
+            Self::TouchMove { .. } => true,
+            Self::TouchEnd { .. } => true,
+            Self::PointerDown { .. } => true,
+            Self::PointerMove { .. } => true,
+            Self::PointerUp { .. } => true,
+            Self::PointerCancel { .. } => true,
+            Self::GotPointerCapture { .. } => true,
+            Self::LostPointerCapture { .. } => true,
+            Self::GamepadButtonDown { .. } => true,
+            Self::GamepadButtonUp { .. } => true,
+            Self::GamepadAxisMove { .. } => true,
         }
     }
 
@@ -169,7 +270,20 @@ impl DomEventData {
             Self::Change => 9,
             Self::Focus => 10,
             Self::Blur => 11,
-            Self::Submit => 12,
+            Self::Submit { .. } => 12,
+            Self::TouchStart { .. } => 13,
+            Self::TouchMove { .. } => 14,
+            Self::TouchEnd { .. } => 15,
+            Self::PointerDown { .. } => 16,
+            Self::PointerMove { .. } => 17,
+            Self::PointerUp { .. } => 18,
+            Self::PointerCancel { .. } => 19,
+            Self::GotPointerCapture { .. } => 20,
+            Self::LostPointerCapture { .. } => 21,
+            Self::GamepadButtonDown { .. } => 22,
+            Self::GamepadButtonUp { .. } => 23,
+            Self::GamepadAxisMove { .. } => 24,
+            Self::ContextMenu { .. } => 25,
         }
     }
 }
@@ -180,6 +294,7 @@ pub enum DomEventKind {
     MouseDown,
     MouseUp,
     Click,
+    ContextMenu,
     KeyPress,
     KeyDown,
     KeyUp,
@@ -188,6 +303,18 @@ pub enum DomEventKind {
     Focus,
     Blur,
     Ime,
+    TouchStart,
+    TouchMove,
+    TouchEnd,
+    PointerDown,
+    PointerMove,
+    PointerUp,
+    PointerCancel,
+    GotPointerCapture,
+    LostPointerCapture,
+    GamepadButtonDown,
+    GamepadButtonUp,
+    GamepadAxisMove,
 }
 
 impl DomEventKind {
@@ -205,6 +332,19 @@ impl DomEventKind {
             DomEventKind::Change => 9,
             DomEventKind::Focus => 10,
             DomEventKind::Blur => 11,
+            DomEventKind::TouchStart => 13,
+            DomEventKind::TouchMove => 14,
+            DomEventKind::TouchEnd => 15,
+            DomEventKind::PointerDown => 16,
+            DomEventKind::PointerMove => 17,
+            DomEventKind::PointerUp => 18,
+            DomEventKind::PointerCancel => 19,
+            DomEventKind::GotPointerCapture => 20,
+            DomEventKind::LostPointerCapture => 21,
+            DomEventKind::GamepadButtonDown => 22,
+            DomEventKind::GamepadButtonUp => 23,
+            DomEventKind::GamepadAxisMove => 24,
+            DomEventKind::ContextMenu => 25,
         }
     }
 }
@@ -218,6 +358,7 @@ impl FromStr for DomEventKind {
             "mousedown" => Ok(DomEventKind::MouseDown),
             "mouseup" => Ok(DomEventKind::MouseUp),
             "click" => Ok(DomEventKind::Click),
+            "contextmenu" => Ok(DomEventKind::ContextMenu),
             "keypress" => Ok(DomEventKind::KeyPress),
             "keydown" => Ok(DomEventKind::KeyDown),
             "keyup" => Ok(DomEventKind::KeyUp),
@@ -226,6 +367,18 @@ impl FromStr for DomEventKind {
             "focus" => Ok(DomEventKind::Focus),
             "blur" => Ok(DomEventKind::Blur),
             "composition" => Ok(DomEventKind::Ime),
+            "touchstart" => Ok(DomEventKind::TouchStart),
+            "touchmove" => Ok(DomEventKind::TouchMove),
+            "touchend" => Ok(DomEventKind::TouchEnd),
+            "pointerdown" => Ok(DomEventKind::PointerDown),
+            "pointermove" => Ok(DomEventKind::PointerMove),
+            "pointerup" => Ok(DomEventKind::PointerUp),
+            "pointercancel" => Ok(DomEventKind::PointerCancel),
+            "gotpointercapture" => Ok(DomEventKind::GotPointerCapture),
+            "lostpointercapture" => Ok(DomEventKind::LostPointerCapture),
+            "gamepadbuttondown" => Ok(DomEventKind::GamepadButtonDown),
+            "gamepadbuttonup" => Ok(DomEventKind::GamepadButtonUp),
+            "gamepadaxismove" => Ok(DomEventKind::GamepadAxisMove),
             _ => Err(()),
         }
     }
@@ -250,6 +403,137 @@ pub struct BlitzMouseButtonEvent {
     pub mods: Modifiers,
 }
 
+/// A touch, from a touchscreen or emulated with the mouse
+#[derive(Clone, Debug)]
+pub struct BlitzTouchEvent {
+    /// Identifies the touch point across the events of a gesture
+    pub identifier: u64,
+    pub x: f32,
+    pub y: f32,
+    pub mods: Modifiers,
+}
+
+/// The pointer of the mouse, which is always the same one
+pub const MOUSE_POINTER_ID: u64 = 1;
+
+/// The kind of device a pointer is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointerType {
+    Mouse,
+    Pen,
+    Touch,
+}
+
+/// An event from a pointer: the mouse, a pen or a touch, see
+/// <https://w3c.github.io/pointerevents/#pointerevent-interface>
+#[derive(Clone, Debug)]
+pub struct BlitzPointerEvent {
+    /// Identifies the pointer across its events. The mouse is [`MOUSE_POINTER_ID`].
+    pub pointer_id: u64,
+    pub pointer_type: PointerType,
+    /// Whether this is the main pointer of its type (the first finger of a multi-touch, say),
+    /// which compatibility mouse events are made for
+    pub is_primary: bool,
+    pub x: f32,
+    pub y: f32,
+    /// The button whose state changed, for downs and ups
+    pub button: MouseEventButton,
+    pub buttons: MouseEventButtons,
+    /// From 0 to 1. Pointers which can't sense pressure report 0.5 while pressed and 0 otherwise.
+    pub pressure: f32,
+    /// The angle between the pen and the screen, in radians: π/2 when perpendicular to it
+    pub altitude_angle: f32,
+    /// The angle of the pen around the screen's normal, in radians clockwise from the x axis. 0
+    /// for pointers which can't report it.
+    pub azimuth_angle: f32,
+    pub mods: Modifiers,
+}
+
+impl BlitzPointerEvent {
+    /// The pointer event of the mouse at the time of `event`
+    pub fn from_mouse(event: &BlitzMouseButtonEvent) -> Self {
+        Self {
+            pointer_id: MOUSE_POINTER_ID,
+            pointer_type: PointerType::Mouse,
+            is_primary: true,
+            x: event.x,
+            y: event.y,
+            button: event.button,
+            buttons: event.buttons,
+            pressure: if event.buttons.is_empty() { 0.0 } else { 0.5 },
+            altitude_angle: std::f32::consts::FRAC_PI_2,
+            azimuth_angle: 0.0,
+            mods: event.mods,
+        }
+    }
+
+    /// The mouse event this pointer event is compatible with
+    pub fn to_mouse(&self) -> BlitzMouseButtonEvent {
+        BlitzMouseButtonEvent {
+            x: self.x,
+            y: self.y,
+            button: self.button,
+            buttons: self.buttons,
+            mods: self.mods,
+        }
+    }
+}
+
+/// The buttons of a gamepad, named after their places in the standard gamepad layout, see
+/// <https://w3c.github.io/gamepad/#remapping>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// The bottom face button (A on Xbox controllers, Cross on PlayStation ones)
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// Pressing the left stick in
+    LeftStick,
+    /// Pressing the right stick in
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Home,
+}
+
+/// The axes of a gamepad's sticks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+/// A gamepad button being pressed or released
+#[derive(Clone, Debug)]
+pub struct BlitzGamepadButtonEvent {
+    /// Identifies the gamepad when several are connected
+    pub gamepad_id: u64,
+    pub button: GamepadButton,
+    /// How far the button is pressed, from 0 to 1 (only analog triggers are in between)
+    pub value: f32,
+}
+
+/// A gamepad stick moving along one of its axes
+#[derive(Clone, Debug)]
+pub struct BlitzGamepadAxisEvent {
+    /// Identifies the gamepad when several are connected
+    pub gamepad_id: u64,
+    pub axis: GamepadAxis,
+    /// The stick's position along the axis, from -1 (left or up) to 1 (right or down)
+    pub value: f32,
+}
+
 bitflags! {
     /// The buttons property indicates which buttons are pressed on the mouse
     /// (or other input device) when a mouse event is triggered.
@@ -338,6 +622,15 @@ pub struct BlitzInputEvent {
     pub value: String,
 }
 
+/// A form being submitted. The event targets the form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlitzSubmitEvent {
+    /// The button which submitted the form, or the form itself for implicit submissions
+    pub submitter: usize,
+    /// Where an image button was clicked, relative to the image
+    pub coordinates: Option<(i32, i32)>,
+}
+
 /// Copy of Winit IME event to avoid lower-level Blitz crates depending on winit
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum BlitzImeEvent {
@@ -0,0 +1,25 @@
+//! Abstractions allowing embedders to feed input from devices the windowing system doesn't
+//! handle (like gamepads) into documents
+
+use std::task::Context;
+
+use crate::events::UiEvent;
+
+/// A source of input events from devices other than the keyboard, mouse and touchscreen, such
+/// as gamepads read through a library like `gilrs`.
+///
+/// Shells poll the provider whenever they're woken, and dispatch its events like the window's
+/// own. Providers should wake the context's waker when new events arrive.
+pub trait InputProvider: Send + Sync + 'static {
+    /// Take the events which have arrived since the last poll
+    fn poll_events(&self, cx: &mut Context<'_>) -> Vec<UiEvent>;
+}
+
+pub struct DummyInputProvider;
+
+impl InputProvider for DummyInputProvider {
+    fn poll_events(&self, _cx: &mut Context<'_>) -> Vec<UiEvent> {
+        // Default impl: no devices
+        Vec::new()
+    }
+}
@@ -1,8 +1,13 @@
 //! Types and traits to enable interoperability between the other Blitz crates without
 //! circular or unnecessary dependencies.
 
+pub mod blob;
+pub mod cache;
 pub mod devtools;
 pub mod events;
+pub mod input;
 pub mod navigation;
 pub mod net;
+pub mod render;
+pub mod script;
 pub mod shell;
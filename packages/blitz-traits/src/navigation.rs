@@ -1,10 +1,13 @@
 //! Abstractions allow embedders to handle link clicks and form submissions
 
+use std::path::PathBuf;
+
 use bytes::Bytes;
 use http::{HeaderMap, HeaderValue, Method};
 use url::Url;
+use uuid::Uuid;
 
-use crate::net::Request;
+use crate::net::{Request, RequestPriority};
 
 /// An abstraction to allow embedders to hook into "navigation events" such as clicking a link
 /// or submitting a form.
@@ -32,6 +35,15 @@ pub struct NavigationOptions {
     pub source_document: usize,
 
     pub document_resource: Option<Bytes>,
+
+    /// The entries of a form submitted as `multipart/form-data`, which are the body of the
+    /// request. [`into_request`](Self::into_request) encodes them, reading any files picked from
+    /// disk into memory, so embedders which can stream files should take them out first.
+    pub form_data: Option<Vec<FormEntry>>,
+
+    /// Set for links with a `download` attribute, which should be saved to disk rather than
+    /// navigated to. Holds the file name the attribute suggests, which may be empty.
+    pub download: Option<String>,
 }
 
 impl NavigationOptions {
@@ -41,12 +53,22 @@ impl NavigationOptions {
             content_type,
             source_document,
             document_resource: None,
+            form_data: None,
+            download: None,
         }
     }
     pub fn set_document_resource(mut self, document_resource: Option<Bytes>) -> Self {
         self.document_resource = document_resource;
         self
     }
+    pub fn set_form_data(mut self, form_data: Option<Vec<FormEntry>>) -> Self {
+        self.form_data = form_data;
+        self
+    }
+    pub fn set_download(mut self, download: Option<String>) -> Self {
+        self.download = download;
+        self
+    }
 
     pub fn into_request(self) -> Request {
         let mut headers = HeaderMap::new();
@@ -55,12 +77,28 @@ impl NavigationOptions {
             HeaderValue::from_str(&self.content_type).unwrap(),
         );
 
-        if let Some(document_resource) = self.document_resource {
+        if let Some(form_data) = self.form_data {
+            let boundary = format!("----BlitzFormBoundary{}", Uuid::new_v4().simple());
+            let content_type = format!("multipart/form-data; boundary={boundary}");
+            headers.insert("content-type", HeaderValue::from_str(&content_type).unwrap());
+            Request {
+                url: self.url,
+                method: Method::POST,
+                headers,
+                body: encode_multipart(&form_data, &boundary),
+                priority: RequestPriority::High,
+                preload: false,
+                navigation: true,
+            }
+        } else if let Some(document_resource) = self.document_resource {
             Request {
                 url: self.url,
                 method: Method::POST,
                 headers,
                 body: document_resource,
+                priority: RequestPriority::High,
+                preload: false,
+                navigation: true,
             }
         } else {
             Request {
@@ -68,7 +106,72 @@ impl NavigationOptions {
                 method: Method::GET,
                 headers,
                 body: Bytes::new(),
+                priority: RequestPriority::High,
+                preload: false,
+                navigation: true,
             }
         }
     }
 }
+
+/// Encode `entries` as a `multipart/form-data` body, see
+/// <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#multipart/form-data-encoding-algorithm>.
+/// Files are read from the paths they were picked from, unless they were read already.
+fn encode_multipart(entries: &[FormEntry], boundary: &str) -> Bytes {
+    // Line breaks in names would end the header, so they're escaped as the HTML spec does
+    let escape = |name: &str| name.replace('\n', "%0A").replace('\r', "%0D").replace('"', "%22");
+    let mut body = Vec::new();
+    for entry in entries {
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        let name = escape(&entry.name);
+        let disposition = format!("Content-Disposition: form-data; name=\"{name}\"");
+        body.extend_from_slice(disposition.as_bytes());
+        match &entry.value {
+            FormEntryValue::Text(value) => {
+                body.extend_from_slice(b"\r\n\r\n");
+                body.extend_from_slice(value.as_bytes());
+            }
+            FormEntryValue::File {
+                name,
+                content_type,
+                data,
+                path,
+            } => {
+                let content_type = content_type.replace(['\r', '\n'], "");
+                let headers = format!(
+                    "; filename=\"{}\"\r\nContent-Type: {content_type}\r\n\r\n",
+                    escape(name),
+                );
+                body.extend_from_slice(headers.as_bytes());
+                match path.as_ref().filter(|_| data.is_empty()) {
+                    Some(path) => body.extend(std::fs::read(path).unwrap_or_default()),
+                    None => body.extend_from_slice(data),
+                }
+            }
+        }
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    Bytes::from(body)
+}
+
+/// An entry of a submitted form, see [`NavigationOptions::form_data`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormEntry {
+    pub name: String,
+    pub value: FormEntryValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormEntryValue {
+    Text(String),
+    File {
+        /// The file's name, without its directory
+        name: String,
+        content_type: String,
+        /// The file's contents, if they have been read
+        data: Bytes,
+        /// Where the file was picked from, which its contents can be streamed from
+        path: Option<PathBuf>,
+    },
+}
@@ -15,6 +15,39 @@ pub type SharedCallback<D> = Arc<dyn NetCallback<D>>;
 /// This may be over the network via http(s), via the filesystem, or some other method.
 pub trait NetProvider<Data>: Send + Sync + 'static {
     fn fetch(&self, doc_id: usize, request: Request, handler: BoxedHandler<Data>);
+
+    /// Abandon every in-flight request made for the document `doc_id`. The handlers of cancelled
+    /// requests are dropped without being called. Called when a document is dropped.
+    fn cancel(&self, doc_id: usize) {
+        let _ = doc_id;
+    }
+
+    /// Send `user_agent` as the `User-Agent` of the requests made for the document `doc_id`
+    /// (instead of the provider's default), or stop overriding it if `None`.
+    fn set_user_agent(&self, doc_id: usize, user_agent: Option<String>) {
+        let _ = (doc_id, user_agent);
+    }
+
+    /// Tell the provider the URL of the document `doc_id`, so that it can apply security
+    /// policies (like blocking mixed content) to the document's requests, or forget it if `None`.
+    fn set_document_url(&self, doc_id: usize, url: Option<&Url>) {
+        let _ = (doc_id, url);
+    }
+
+    /// Open a connection to the origin of `url` ahead of requests to it, as hinted by
+    /// `<link rel=preconnect>`
+    fn preconnect(&self, doc_id: usize, url: &Url) {
+        let _ = (doc_id, url);
+    }
+
+    /// Whether the provider loads URLs with the (lowercase) `scheme`, one of the app's own (like
+    /// `app`). Documents navigate to them like any other fetchable URLs, and resolve relative URLs
+    /// against them even when they're written without an authority (`app:index.html`, which is
+    /// treated as `app:///index.html`).
+    fn is_custom_scheme(&self, scheme: &str) -> bool {
+        let _ = scheme;
+        false
+    }
 }
 
 /// A type that parses raw bytes from a network request into a Data and then calls
@@ -36,13 +69,20 @@ impl<D, F: Fn(usize, Result<D, Option<String>>) + Send + Sync + 'static> NetCall
 }
 
 #[non_exhaustive]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// A request type loosely representing <https://fetch.spec.whatwg.org/#requests>
 pub struct Request {
     pub url: Url,
     pub method: Method,
     pub headers: HeaderMap,
     pub body: Bytes,
+    pub priority: RequestPriority,
+    /// Whether this request fetches a resource ahead of it being used (`<link rel=preload>`). A
+    /// provider may keep the response to answer the next request for the same URL with.
+    pub preload: bool,
+    /// Whether this request navigates to a new document (a link followed, or a form submitted),
+    /// which may load it from another origin than the document the request is made for
+    pub navigation: bool,
 }
 impl Request {
     /// A get request to the specified Url and an empty body
@@ -52,8 +92,38 @@ impl Request {
             method: Method::GET,
             headers: HeaderMap::new(),
             body: Bytes::new(),
+            priority: RequestPriority::Normal,
+            preload: false,
+            navigation: false,
+        }
+    }
+
+    /// A preload request for the specified Url
+    pub fn preload(url: Url, priority: RequestPriority) -> Self {
+        Self {
+            priority,
+            preload: true,
+            ..Self::get(url)
         }
     }
+
+    /// Make the request with `priority`
+    pub fn with_priority(mut self, priority: RequestPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// How urgently the response to a request is needed. Providers may hold back requests while
+/// requests of a higher priority are in flight.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    /// Render-blocking resources, like stylesheets and fonts
+    High,
+    #[default]
+    Normal,
+    /// Resources which may be needed later, like those hinted by `<link rel=prefetch>`
+    Low,
 }
 
 /// A default noop NetProvider
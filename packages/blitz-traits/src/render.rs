@@ -0,0 +1,65 @@
+//! Abstraction over the paint pipeline so that alternative painters can be plugged in
+
+/// The color space of the surface a document is painted into, which painted colors are encoded in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OutputColorSpace {
+    /// sRGB, which every display can show
+    #[default]
+    Srgb,
+    /// Display P3, the wider gamut of most recent Apple displays and many others
+    DisplayP3,
+}
+
+/// The size and scale of the surface a document is being painted into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderViewport {
+    /// Physical width of the surface in pixels
+    pub width: u32,
+    /// Physical height of the surface in pixels
+    pub height: u32,
+    /// Total scale factor (hidpi scale multiplied by zoom)
+    pub scale: f64,
+    /// The color space of the surface
+    pub color_space: OutputColorSpace,
+}
+
+impl RenderViewport {
+    /// An sRGB viewport
+    pub fn new(width: u32, height: u32, scale: f64) -> Self {
+        Self {
+            width,
+            height,
+            scale,
+            color_space: OutputColorSpace::Srgb,
+        }
+    }
+
+    pub fn with_color_space(mut self, color_space: OutputColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+}
+
+/// A type that paints a document into a scene.
+///
+/// `Doc` is the document type (usually `blitz_dom::BaseDocument`) and `Scene` is the drawing
+/// target (usually an impl `anyrender::PaintScene`). Both are type parameters so that this crate
+/// does not need to depend on either of them.
+///
+/// Implementations may assume that styles and layout have already been resolved. `blitz-paint`
+/// provides the default implementation (`blitz_paint::BlitzPainter`), but embedders can swap in
+/// their own (e.g. a simplified renderer for e-ink displays or a wireframe renderer for debugging).
+pub trait DocumentRenderer<Doc: ?Sized, Scene: ?Sized> {
+    fn render(&self, scene: &mut Scene, doc: &Doc, viewport: RenderViewport);
+}
+
+impl<Doc, Scene, F> DocumentRenderer<Doc, Scene> for F
+where
+    Doc: ?Sized,
+    Scene: ?Sized,
+    F: Fn(&mut Scene, &Doc, RenderViewport),
+{
+    fn render(&self, scene: &mut Scene, doc: &Doc, viewport: RenderViewport) {
+        self(scene, doc, viewport)
+    }
+}
@@ -0,0 +1,73 @@
+//! Abstractions allowing embedders to run the scripts of documents with a JavaScript engine
+//! (like boa, QuickJS or V8), which Blitz doesn't include itself
+
+use std::task::Context;
+
+use url::Url;
+
+/// A `<script>` element which has been inserted into a document and is ready to run
+#[derive(Clone, Debug)]
+pub struct Script {
+    /// The id of the `<script>` element's node
+    pub node_id: usize,
+    pub kind: ScriptKind,
+    pub source: ScriptSource,
+    /// Whether the element has the `async` attribute
+    pub is_async: bool,
+    /// Whether the element has the `defer` attribute
+    pub is_deferred: bool,
+}
+
+/// The kind of script, from the `type` attribute of a `<script>` element
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptKind {
+    /// A classic script (no `type`, or a JavaScript MIME type)
+    Classic,
+    /// A module script (`type="module"`)
+    Module,
+}
+
+#[derive(Clone, Debug)]
+pub enum ScriptSource {
+    /// The text of an inline script
+    Inline(String),
+    /// The URL of an external script (the element's `src`), which the provider fetches itself
+    External(Url),
+}
+
+/// Runs the scripts of documents.
+///
+/// Providers are generic over the document type they run scripts in (`BaseDocument` for
+/// `blitz-dom`), which [`poll_tasks`](Self::poll_tasks) hands them. Scripts should change
+/// `BaseDocument`s through the `DocumentMutator` returned by its `mutate` method, so that changes
+/// made by scripts are tracked like any others.
+///
+/// Scripts run from tasks which the provider schedules on the shell's event loop: shells poll
+/// the provider whenever they're woken, and it should wake the context's waker whenever tasks
+/// become ready (like a script's source being fetched or a timer firing).
+pub trait ScriptProvider<Doc: ?Sized>: Send + Sync + 'static {
+    /// A script was found in the document with id `doc_id` (while it was being parsed, or when
+    /// a `<script>` element was inserted later). Scripts are found in document order.
+    fn script_discovered(&self, doc_id: usize, script: Script);
+
+    /// Run the tasks which are ready, each followed by the microtasks it queued, returning
+    /// whether any ran
+    fn poll_tasks(&self, doc: &mut Doc, cx: &mut Context<'_>) -> bool;
+
+    /// The document with id `doc_id` is being unloaded, so its tasks should be dropped
+    fn document_unloaded(&self, doc_id: usize) {
+        let _ = doc_id;
+    }
+}
+
+pub struct DummyScriptProvider;
+
+impl<Doc: ?Sized> ScriptProvider<Doc> for DummyScriptProvider {
+    fn script_discovered(&self, _doc_id: usize, _script: Script) {
+        // Default impl: scripts don't run
+    }
+
+    fn poll_tasks(&self, _doc: &mut Doc, _cx: &mut Context<'_>) -> bool {
+        false
+    }
+}
@@ -1,5 +1,7 @@
 //! Abstraction over windowing / operating system ("shell") functionality
 
+use std::sync::Arc;
+
 use cursor_icon::CursorIcon;
 
 /// Type representing an error performing a clipboard operation
@@ -16,6 +18,16 @@ pub trait ShellProvider {
     fn set_window_title(&self, title: String) {
         let _ = title;
     }
+    /// Set the window's icon (from the document's `<link rel="icon">`), or go back to the
+    /// default one
+    fn set_window_icon(&self, icon: Option<WindowIcon>) {
+        let _ = icon;
+    }
+    /// Color the window's title bar with the document's theme color (as RGBA8), or go back to the
+    /// default color
+    fn set_theme_color(&self, color: Option<[u8; 4]>) {
+        let _ = color;
+    }
     fn get_clipboard_text(&self) -> Result<String, ClipboardError> {
         Err(ClipboardError)
     }
@@ -23,25 +35,73 @@ pub trait ShellProvider {
         let _ = text;
         Err(ClipboardError)
     }
+    /// Show a context menu, as the default action of a `contextmenu` event
+    fn show_context_menu(&self, request: ContextMenuRequest) {
+        let _ = request;
+    }
+}
+
+/// What a context menu is being shown for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextMenuRequest {
+    /// The node the menu was opened on
+    pub node_id: usize,
+    /// The text selected in the text input the menu was opened on, if any
+    pub selected_text: Option<String>,
+    /// Whether the menu was opened on a text input
+    pub editable: bool,
+    /// The URL of the link the menu was opened on, if any
+    pub link: Option<String>,
+}
+
+/// A decoded window icon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowIcon {
+    pub width: u32,
+    pub height: u32,
+    /// The icon's pixels in RGBA8 format
+    pub rgba: Arc<Vec<u8>>,
 }
 
 pub struct DummyShellProvider;
 impl ShellProvider for DummyShellProvider {}
 
 /// The system color scheme (light and dark mode)
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorScheme {
     #[default]
     Light,
     Dark,
 }
 
+/// The media type `@media` rules are matched against
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    #[default]
+    Screen,
+    /// Paged output, e.g. when printing or exporting a document
+    Print,
+}
+
+/// Whether the primary pointing device can hover over elements (the `hover` media feature)
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoverCapability {
+    #[default]
+    Hover,
+    /// E.g. a touchscreen
+    None,
+}
+
 #[derive(Debug, Clone)]
 pub struct Viewport {
     pub color_scheme: ColorScheme,
     pub window_size: (u32, u32),
     pub hidpi_scale: f32,
     pub zoom: f32,
+    pub media_type: MediaType,
+    /// Whether the user asked the system to minimize non-essential motion
+    pub prefers_reduced_motion: bool,
+    pub hover: HoverCapability,
 }
 
 impl Default for Viewport {
@@ -51,6 +111,9 @@ impl Default for Viewport {
             hidpi_scale: 1.0,
             zoom: 1.0,
             color_scheme: ColorScheme::Light,
+            media_type: MediaType::Screen,
+            prefers_reduced_motion: false,
+            hover: HoverCapability::Hover,
         }
     }
 }
@@ -67,6 +130,9 @@ impl Viewport {
             hidpi_scale: scale_factor,
             zoom: 1.0,
             color_scheme,
+            media_type: MediaType::Screen,
+            prefers_reduced_motion: false,
+            hover: HoverCapability::Hover,
         }
     }
 
@@ -0,0 +1,32 @@
+//! Giving in-memory resources `blob:` URLs
+
+use blitz_traits::blob::BlobRegistry;
+use url::Url;
+use uuid::Uuid;
+
+#[test]
+fn object_urls_resolve_until_revoked() {
+    let registry = BlobRegistry::default();
+    let document = Url::parse("https://example.com/app/index.html").unwrap();
+
+    let url = registry.create_object_url(Some(&document), &b"\x89PNG"[..], "image/png");
+    assert_eq!(url.scheme(), "blob");
+    assert!(url.as_str().starts_with("blob:https://example.com/"));
+    assert_eq!(url.origin(), document.origin());
+    let id = url.path().rsplit('/').next().unwrap();
+    assert_eq!(Uuid::parse_str(id).unwrap().get_version_num(), 4);
+
+    let other = registry.create_object_url(None, &b"other"[..], "text/plain");
+    assert_ne!(url, other);
+    assert!(other.as_str().starts_with("blob:null/"));
+
+    let mut with_fragment = url.clone();
+    with_fragment.set_fragment(Some("frame"));
+    let blob = registry.resolve(&with_fragment).unwrap();
+    assert_eq!(blob.bytes, &b"\x89PNG"[..]);
+    assert_eq!(blob.content_type, "image/png");
+
+    registry.revoke_object_url(&url);
+    assert_eq!(registry.resolve(&url), None);
+    assert!(registry.resolve(&other).is_some());
+}
@@ -0,0 +1,75 @@
+//! Splitting a memory budget between caches
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use blitz_traits::cache::{CacheCoordinator, ManagedCache, MemoryPressure, distribute_budget};
+
+struct TestCache {
+    bytes: AtomicUsize,
+}
+
+impl TestCache {
+    fn new(bytes: usize) -> Arc<Self> {
+        Arc::new(Self {
+            bytes: AtomicUsize::new(bytes),
+        })
+    }
+
+    fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+}
+
+impl ManagedCache for TestCache {
+    fn name(&self) -> &str {
+        "test"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.bytes()
+    }
+
+    fn evict_to(&self, target_bytes: usize) {
+        self.bytes.fetch_min(target_bytes, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn unused_shares_go_to_other_caches() {
+    // Equal weights would give each cache 300, but the first only uses 100
+    assert_eq!(distribute_budget(600, &[1, 1], &[100, 800]), [100, 500]);
+    // Shares are proportional to weights
+    assert_eq!(distribute_budget(600, &[1, 2], &[500, 500]), [200, 400]);
+    // A cache with no weight gets nothing when the others need the budget
+    assert_eq!(distribute_budget(600, &[1, 0], &[700, 10]), [600, 0]);
+}
+
+#[test]
+fn caches_over_budget_are_evicted_to_their_share() {
+    let coordinator = CacheCoordinator::new(1000);
+    let small = TestCache::new(200);
+    let large = TestCache::new(1500);
+    coordinator.register(&(small.clone() as Arc<dyn ManagedCache>), 1);
+    coordinator.register(&(large.clone() as Arc<dyn ManagedCache>), 1);
+
+    coordinator.enforce_budget();
+    assert_eq!((small.bytes(), large.bytes()), (200, 800));
+
+    coordinator.handle_memory_pressure(MemoryPressure::Moderate);
+    assert_eq!((small.bytes(), large.bytes()), (200, 300));
+
+    coordinator.handle_memory_pressure(MemoryPressure::Critical);
+    assert_eq!((small.bytes(), large.bytes()), (0, 0));
+}
+
+#[test]
+fn dropped_caches_are_forgotten() {
+    let coordinator = CacheCoordinator::new(1000);
+    let cache = TestCache::new(10);
+    coordinator.register(&(cache.clone() as Arc<dyn ManagedCache>), 1);
+    assert_eq!(coordinator.usage(), [("test".to_string(), 10)]);
+
+    drop(cache);
+    assert!(coordinator.usage().is_empty());
+}
@@ -0,0 +1,92 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyrender_vello::VelloWindowRenderer;
+use blitz_shell::{BlitzShellEvent, EventLoopProxy, View, WindowConfig, run_idle_periods};
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::WindowId;
+
+type WebView = View<VelloWindowRenderer>;
+
+/// Drives a document rendered into a canvas.
+///
+/// WebGPU devices are only handed out by the browser's event loop, so the renderer is resumed
+/// asynchronously (rather than blocking like `BlitzApplication` does). Events which arrive
+/// before it has resumed are dropped.
+pub struct WebApplication {
+    pending_window: Option<WindowConfig<VelloWindowRenderer>>,
+    /// The view, once its renderer has resumed
+    view: Rc<RefCell<Option<WebView>>>,
+    proxy: EventLoopProxy<BlitzShellEvent>,
+}
+
+impl WebApplication {
+    pub fn new(
+        proxy: EventLoopProxy<BlitzShellEvent>,
+        window: WindowConfig<VelloWindowRenderer>,
+    ) -> Self {
+        Self {
+            pending_window: Some(window),
+            view: Rc::new(RefCell::new(None)),
+            proxy,
+        }
+    }
+}
+
+impl ApplicationHandler<BlitzShellEvent> for WebApplication {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(window) = self.pending_window.take() else {
+            return;
+        };
+        let mut view = View::init(window, event_loop, &self.proxy);
+        let slot = Rc::clone(&self.view);
+        wasm_bindgen_futures::spawn_local(async move {
+            let (width, height) = view.doc.viewport().window_size;
+            let resumed = view
+                .renderer
+                .resume_async(view.window.clone(), width, height)
+                .await
+                .map_err(|err| format!("Renderer failed to resume: {err}"))
+                .and_then(|()| view.finish_resume());
+            match resumed {
+                Ok(()) => {
+                    view.request_redraw();
+                    *slot.borrow_mut() = Some(view);
+                }
+                Err(err) => web_sys::console::error_1(&err.into()),
+            }
+        });
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let mut view = self.view.borrow_mut();
+        event_loop.set_control_flow(run_idle_periods(view.as_mut()));
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+        if let Some(view) = self.view.borrow_mut().as_mut() {
+            view.handle_winit_event(event);
+            let _ = self.proxy.send_event(BlitzShellEvent::Poll { window_id });
+        }
+    }
+
+    fn user_event(&mut self, _: &ActiveEventLoop, event: BlitzShellEvent) {
+        let mut view = self.view.borrow_mut();
+        let Some(view) = view.as_mut() else {
+            return;
+        };
+        match event {
+            BlitzShellEvent::Poll { .. } => {
+                view.poll();
+            }
+            BlitzShellEvent::ResourceLoad { data, .. } => {
+                view.doc.as_mut().load_resource(data);
+                view.request_redraw();
+            }
+            // Navigation and embedder events are left to embedders
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Runs Blitz in the browser.
+//!
+//! Documents are styled, laid out and painted by the same crates as on native, and rendered
+//! into a `<canvas>` with WebGPU, with input from winit's web backend. Embedders provide
+//! resources through the document's `NetProvider` (`blitz-net` doesn't build for wasm32).
+//!
+//! This crate only has contents when built for `wasm32`.
+
+#![cfg(target_arch = "wasm32")]
+
+mod application;
+
+use anyrender_vello::VelloWindowRenderer;
+use blitz_dom::Document;
+use blitz_shell::{BlitzShellEvent, WindowConfig, create_default_event_loop};
+use wasm_bindgen::JsCast;
+use web_sys::HtmlCanvasElement;
+use winit::platform::web::{EventLoopExtWebSys, WindowAttributesExtWebSys};
+use winit::window::WindowAttributes;
+
+pub use crate::application::WebApplication;
+
+/// Render `doc` into the `<canvas>` element with id `canvas_id`, or into a new canvas appended
+/// to the page's `<body>` if there's no id.
+///
+/// Returns once the event loop has been started, which runs for as long as the page does.
+pub fn launch(doc: Box<dyn Document>, canvas_id: Option<&str>) {
+    let attributes = match canvas_id {
+        Some(id) => WindowAttributes::default().with_canvas(Some(find_canvas(id))),
+        None => WindowAttributes::default().with_append(true),
+    };
+
+    let event_loop = create_default_event_loop::<BlitzShellEvent>();
+    let window = WindowConfig::with_attributes(doc, VelloWindowRenderer::new(), attributes);
+    let application = WebApplication::new(event_loop.create_proxy(), window);
+    event_loop.spawn_app(application);
+}
+
+fn find_canvas(id: &str) -> HtmlCanvasElement {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .expect("Blitz must run in a page with a document");
+    document
+        .get_element_by_id(id)
+        .and_then(|element| element.dyn_into::<HtmlCanvasElement>().ok())
+        .unwrap_or_else(|| panic!("There is no <canvas> with id {id:?}"))
+}
@@ -17,11 +17,10 @@
 
 use std::sync::Arc;
 
-use anyrender_vello::VelloWindowRenderer as WindowRenderer;
 #[doc(inline)]
 /// Re-export of [`blitz_dom`].
 pub use blitz_dom as dom;
-use blitz_dom::DocumentConfig;
+use blitz_dom::{Document, DocumentConfig};
 use blitz_dom::net::Resource;
 #[doc(inline)]
 /// Re-export of [`blitz_html`]. HTML parsing on top of blitz-dom
@@ -37,8 +36,8 @@ pub use blitz_paint as paint;
 /// Re-export of [`blitz_shell`].
 pub use blitz_shell as shell;
 use blitz_shell::{
-    BlitzApplication, BlitzShellEvent, BlitzShellNetCallback, Config, EventLoop, WindowConfig,
-    create_default_event_loop,
+    BlitzApplication, BlitzShellEvent, BlitzShellNavigationProvider, BlitzShellNetCallback, Config,
+    EventLoop, FallbackRenderer, WindowConfig, create_default_event_loop,
 };
 #[doc(inline)]
 /// Re-export of [`blitz_text`]. Advanced text shaping and layout with international support
@@ -49,7 +48,14 @@ pub use blitz_traits as traits;
 
 /// Command execution utilities.
 pub mod command;
-use blitz_traits::net::{NetProvider, Request};
+#[cfg(feature = "net")]
+mod page_load;
+/// Rendering many documents in parallel
+pub mod pool;
+/// Thumbnail generation with caching
+pub mod thumbnail;
+#[cfg(feature = "net")]
+use crate::page_load::StreamedDocument;
 
 #[cfg(feature = "net")]
 pub fn launch_url(url: &str) {
@@ -68,20 +74,18 @@ pub fn launch_url(url: &str) {
     let event_loop = create_default_event_loop::<BlitzShellEvent>();
     let net_provider = create_net_provider(&event_loop);
 
-    let (url, bytes) = rt
-        .block_on(net_provider.fetch_async(Request::get(url)))
-        .unwrap();
-    let html = std::str::from_utf8(bytes.as_ref()).unwrap();
+    let doc = HtmlDocument::new(DocumentConfig {
+        base_url: Some(url.to_string()),
+        ua_stylesheets: Some(Vec::new()),
+        net_provider: Some(net_provider.clone()),
+        navigation_provider: Some(BlitzShellNavigationProvider::shared(event_loop.create_proxy())),
+        ..Default::default()
+    });
 
-    launch_internal(
-        html,
-        Config {
-            stylesheets: Vec::new(),
-            base_url: Some(url),
-        },
-        event_loop,
-        net_provider,
-    )
+    // The window opens with an empty document, which the page is added to as it downloads
+    let doc = StreamedDocument::load(doc, &net_provider, url);
+
+    launch_document(doc, event_loop, &net_provider)
 }
 
 pub fn launch_static_html(html: &str) {
@@ -108,22 +112,40 @@ fn launch_internal(
     html: &str,
     cfg: Config,
     event_loop: EventLoop<BlitzShellEvent>,
-    net_provider: Arc<dyn NetProvider<Resource>>,
+    net_provider: Arc<EnabledNetProvider>,
 ) {
     let doc = HtmlDocument::from_html(
         html,
         DocumentConfig {
             base_url: cfg.base_url,
             ua_stylesheets: Some(cfg.stylesheets),
-            net_provider: Some(net_provider),
+            net_provider: Some(net_provider.clone()),
+            navigation_provider: Some(BlitzShellNavigationProvider::shared(
+                event_loop.create_proxy(),
+            )),
             ..Default::default()
         },
     );
-    let renderer = WindowRenderer::new();
+    launch_document(doc, event_loop, &net_provider)
+}
+
+fn launch_document(
+    doc: impl Document,
+    event_loop: EventLoop<BlitzShellEvent>,
+    net_provider: &EnabledNetProvider,
+) {
+    // Falls back to CPU rendering when no GPU adapter is available (see `BLITZ_RENDERER`)
+    let renderer = FallbackRenderer::new();
     let window = WindowConfig::new(Box::new(doc) as _, renderer);
 
     // Create application
     let mut application = BlitzApplication::new(event_loop.create_proxy());
+    #[cfg(feature = "net")]
+    {
+        application.downloads = Some(Arc::clone(net_provider.downloads()));
+    }
+    #[cfg(not(feature = "net"))]
+    let _ = net_provider;
     application.add_window(window);
 
     // Run event loop
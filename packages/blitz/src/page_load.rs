@@ -0,0 +1,200 @@
+//! Loading a page over the network into a document which is already being displayed
+
+use std::ops::{Deref, DerefMut};
+use std::task::{Context as TaskContext, Poll};
+
+use blitz_dom::net::Resource;
+use blitz_dom::{BaseDocument, Document};
+use blitz_html::{HtmlDocument, HtmlParser, PreloadKind};
+use blitz_net::{Provider, ProviderError, ResponseChunk};
+use blitz_traits::net::{Request, RequestPriority, Url};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// A document which is parsed as its source arrives, so that the window showing it opens straight
+/// away and the page is displayed (and its stylesheets and images fetched) while it downloads
+pub(crate) struct StreamedDocument {
+    inner: HtmlDocument,
+    /// The URL the page was requested from
+    url: String,
+    /// The parser for the rest of the page, until it has all been received
+    parser: Option<HtmlParser>,
+    chunks: UnboundedReceiver<Result<ResponseChunk, ProviderError>>,
+    /// Whether any of the page has been received
+    has_body: bool,
+}
+
+impl StreamedDocument {
+    /// Start loading `url` into the empty document `inner`
+    pub(crate) fn load(inner: HtmlDocument, net_provider: &Provider<Resource>, url: Url) -> Self {
+        let chunks = net_provider.fetch_stream(None, Request::get(url.clone()));
+        Self::new(inner, url.to_string(), chunks)
+    }
+
+    fn new(
+        inner: HtmlDocument,
+        url: String,
+        chunks: UnboundedReceiver<Result<ResponseChunk, ProviderError>>,
+    ) -> Self {
+        Self {
+            inner,
+            url,
+            parser: Some(HtmlParser::new()),
+            chunks,
+            has_body: false,
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        let Some(parser) = &mut self.parser else {
+            return;
+        };
+        parser.feed(bytes);
+        // Resources are fetched before the elements referencing them are added to the document,
+        // which restyles it
+        for preload in parser.take_preloads() {
+            let priority = match preload.kind {
+                PreloadKind::Stylesheet => RequestPriority::High,
+                PreloadKind::Image => RequestPriority::Normal,
+            };
+            self.inner.preload(&preload.url, priority);
+        }
+        parser.apply(&mut self.inner);
+    }
+
+    /// Show `error` instead of the page, or after the part of it which was received
+    fn fail(&mut self, error: &str) {
+        let html = if self.has_body {
+            format!("<p>Couldn't load the rest of this page: {}</p>", escape(error))
+        } else {
+            format!(
+                "<!DOCTYPE html><title>Couldn't load {url}</title>\
+                <h1>Couldn't load this page</h1><p>{url}</p><p>{error}</p>",
+                url = escape(&self.url),
+                error = escape(error),
+            )
+        };
+        self.feed(html.as_bytes());
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        if let Some(parser) = self.parser.take() {
+            parser.finish(&mut self.inner);
+        }
+    }
+}
+
+impl Deref for StreamedDocument {
+    type Target = BaseDocument;
+    fn deref(&self) -> &BaseDocument {
+        &self.inner
+    }
+}
+
+impl DerefMut for StreamedDocument {
+    fn deref_mut(&mut self) -> &mut BaseDocument {
+        &mut self.inner
+    }
+}
+
+impl Document for StreamedDocument {
+    fn poll(&mut self, task_context: Option<TaskContext>) -> bool {
+        let Some(mut cx) = task_context else {
+            return false;
+        };
+        let mut changed = false;
+        while self.parser.is_some() {
+            let chunk = match self.chunks.poll_recv(&mut cx) {
+                Poll::Pending => break,
+                Poll::Ready(chunk) => chunk,
+            };
+            match chunk {
+                Some(Ok(ResponseChunk::Url(url))) => self.inner.set_base_url(&url),
+                Some(Ok(ResponseChunk::Body(bytes))) => {
+                    self.has_body = true;
+                    self.feed(&bytes);
+                }
+                Some(Err(error)) => self.fail(&error.to_string()),
+                None => self.finish(),
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn is_parsing(&self) -> bool {
+        self.parser.is_some()
+    }
+}
+
+/// Escape `text` for use as the contents of an HTML element
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Waker;
+
+    use blitz_dom::{DocumentConfig, DocumentLifecycle};
+    use blitz_traits::net::Bytes;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+
+    fn poll(doc: &mut StreamedDocument) -> bool {
+        doc.poll(Some(TaskContext::from_waker(Waker::noop())))
+    }
+
+    fn body(doc: &StreamedDocument) -> String {
+        doc.root_element().outer_html()
+    }
+
+    #[test]
+    fn chunks_are_parsed_as_they_arrive() {
+        let (sender, chunks) = unbounded_channel();
+        let inner = HtmlDocument::new(DocumentConfig::default());
+        let mut doc = StreamedDocument::new(inner, "https://example.com/".into(), chunks);
+        assert!(!poll(&mut doc));
+
+        let url = "https://example.com/redirected/".to_string();
+        sender.send(Ok(ResponseChunk::Url(url))).unwrap();
+        sender.send(Ok(ResponseChunk::Body(Bytes::from_static(b"<p>one</p><p>tw")))).unwrap();
+        assert!(poll(&mut doc));
+        assert!(body(&doc).contains("<p>one</p>"));
+        assert_ne!(doc.lifecycle(), DocumentLifecycle::Interactive);
+        assert!(doc.is_parsing());
+
+        sender.send(Ok(ResponseChunk::Body(Bytes::from_static(b"o</p>")))).unwrap();
+        drop(sender);
+        assert!(poll(&mut doc));
+        assert!(body(&doc).contains("<p>one</p><p>two</p>"));
+        assert_eq!(doc.lifecycle(), DocumentLifecycle::Interactive);
+        assert!(!doc.is_parsing());
+    }
+
+    #[test]
+    fn errors_are_shown_as_a_page() {
+        let (sender, chunks) = unbounded_channel();
+        let inner = HtmlDocument::new(DocumentConfig::default());
+        let mut doc = StreamedDocument::new(inner, "https://example.com/?a<b".into(), chunks);
+
+        let error = ProviderError::Intercepted("offline".into());
+        sender.send(Err(error)).unwrap();
+        assert!(poll(&mut doc));
+        let html = body(&doc);
+        assert!(html.contains("<h1>Couldn't load this page</h1>"));
+        assert!(html.contains("https://example.com/?a&lt;b"));
+        assert!(html.contains("offline"));
+        assert_eq!(doc.lifecycle(), DocumentLifecycle::Interactive);
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(escape("<a href=\"x\">&</a>"), "&lt;a href=\"x\"&gt;&amp;&lt;/a&gt;");
+    }
+}
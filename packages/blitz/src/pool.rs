@@ -0,0 +1,471 @@
+//! Rendering many documents in parallel
+//!
+//! [`RenderPool`] is intended for batch workloads such as taking screenshots of a large set of
+//! pages. It runs a fixed number of worker threads, each of which renders one job at a time into a
+//! fresh document. Workers keep their image renderer between jobs of the same size, and their font
+//! system for their whole lifetime: blitz-text keeps font systems in thread-local storage as they
+//! can't be shared between threads, so fonts are loaded once per worker rather than once per job.
+//!
+//! Every job is rendered under the limits set in [`RenderPoolConfig`]:
+//!
+//!  - documents larger than [`max_html_bytes`](RenderPoolConfig::max_html_bytes) or with more than
+//!    [`max_nodes`](RenderPoolConfig::max_nodes) nodes are rejected
+//!  - images whose pixel buffer would exceed [`max_image_bytes`](RenderPoolConfig::max_image_bytes)
+//!    are rejected before any memory is allocated for them
+//!  - a job which takes longer than [`job_timeout`](RenderPoolConfig::job_timeout) (measured from
+//!    when a worker picks it up) fails with [`RenderPoolError::Timeout`]
+//!
+//! A job which times out, or whose [`RenderHandle`] is dropped, is cancelled: the worker stops
+//! parsing it part way through, or abandons it after styling and layout or after painting, which
+//! can't be interrupted, and moves on to the next job.
+//!
+//! Sub-resources (stylesheets, images, fonts) are never fetched, and documents are always parsed
+//! as HTML (never as XHTML).
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyrender::ImageRenderer;
+use anyrender_vello::VelloImageRenderer;
+use blitz_dom::{BaseDocument, DocumentConfig};
+use blitz_html::{HtmlDocument, HtmlParser};
+use blitz_paint::paint_scene;
+use blitz_paint::screenshot::{ImageFormat, ScreenshotConfig, ScreenshotError, encode_rgba};
+use blitz_traits::net::DummyNetProvider;
+use blitz_traits::shell::{ColorScheme, DummyShellProvider, Viewport};
+use thiserror::Error;
+
+/// How much of a document is parsed between checks for whether its job was cancelled
+const PARSE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Configuration for a [`RenderPool`]
+#[derive(Debug, Clone)]
+pub struct RenderPoolConfig {
+    /// The number of worker threads
+    pub workers: usize,
+    /// How long a job may take once a worker has started rendering it
+    pub job_timeout: Duration,
+    /// Documents larger than this are rejected without being parsed
+    pub max_html_bytes: usize,
+    /// Documents with more nodes than this are rejected after parsing
+    pub max_nodes: usize,
+    /// The maximum size of the RGBA pixel buffer for a single image
+    pub max_image_bytes: usize,
+    /// User agent stylesheets applied to every document, in addition to the default one
+    pub ua_stylesheets: Vec<String>,
+}
+
+impl Default for RenderPoolConfig {
+    fn default() -> Self {
+        Self {
+            workers: thread::available_parallelism().map_or(4, |n| n.get()),
+            job_timeout: Duration::from_secs(30),
+            max_html_bytes: 8 * 1024 * 1024,
+            max_nodes: 500_000,
+            max_image_bytes: 256 * 1024 * 1024,
+            ua_stylesheets: Vec::new(),
+        }
+    }
+}
+
+/// A document to render
+#[derive(Debug, Clone)]
+pub struct RenderJob {
+    pub html: String,
+    /// The url which relative urls in the document are resolved against
+    pub base_url: Option<String>,
+    /// The width of the viewport (in CSS pixels)
+    pub viewport_width: u32,
+    /// The height of the viewport (in CSS pixels)
+    pub viewport_height: u32,
+    /// The device pixel ratio. The rendered image is `scale` times the size of the viewport.
+    pub scale: f64,
+    pub format: ImageFormat,
+    /// Encoding quality (0-100)
+    pub quality: u8,
+}
+
+impl RenderJob {
+    /// A job which renders `html` in a 1280x800 viewport to a PNG
+    pub fn new(html: impl Into<String>) -> Self {
+        Self {
+            html: html.into(),
+            base_url: None,
+            viewport_width: 1280,
+            viewport_height: 800,
+            scale: 1.0,
+            format: ImageFormat::Png,
+            quality: 90,
+        }
+    }
+
+    /// The size of the rendered image in pixels
+    pub fn image_size(&self) -> (u32, u32) {
+        let scale = self.scale.max(0.0);
+        (
+            (self.viewport_width as f64 * scale).round() as u32,
+            (self.viewport_height as f64 * scale).round() as u32,
+        )
+    }
+}
+
+/// An encoded image produced by a [`RenderPool`]
+#[derive(Debug, Clone)]
+pub struct RenderedImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub format: ImageFormat,
+}
+
+#[derive(Error, Debug)]
+pub enum RenderPoolError {
+    #[error("Document is too large ({0} bytes)")]
+    TooLarge(usize),
+
+    #[error("Document has too many nodes ({0})")]
+    TooManyNodes(usize),
+
+    #[error("Image is too large ({width}x{height})")]
+    ImageTooLarge { width: u32, height: u32 },
+
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Rendering panicked")]
+    Panicked,
+
+    #[error("The render pool has shut down")]
+    Closed,
+
+    #[error("Encoding failed: {0}")]
+    Encoding(#[from] ScreenshotError),
+}
+
+/// A pool of worker threads which render documents to images.
+///
+/// Dropping the pool stops accepting jobs, lets the workers finish the jobs already queued and
+/// waits for them to exit.
+pub struct RenderPool {
+    config: Arc<RenderPoolConfig>,
+    sender: Option<mpsc::Sender<Task>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl RenderPool {
+    /// Start a pool which renders using vello
+    pub fn new(config: RenderPoolConfig) -> Self {
+        Self::with_renderer::<VelloImageRenderer>(config)
+    }
+
+    /// Start a pool which renders using the image renderer `R`
+    pub fn with_renderer<R: ImageRenderer + 'static>(config: RenderPoolConfig) -> Self {
+        let config = Arc::new(config);
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..config.workers.max(1))
+            .map(|idx| {
+                let receiver = Arc::clone(&receiver);
+                let config = Arc::clone(&config);
+                thread::Builder::new()
+                    .name(format!("blitz-render-{idx}"))
+                    .spawn(move || run_worker::<R>(&receiver, &config))
+                    .expect("Failed to spawn render worker")
+            })
+            .collect();
+
+        Self {
+            config,
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    pub fn config(&self) -> &RenderPoolConfig {
+        &self.config
+    }
+
+    /// Queue a job. Its result can be collected from the returned handle.
+    pub fn submit(&self, job: RenderJob) -> RenderHandle {
+        let (events, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task = Task {
+            job,
+            events,
+            cancelled: Arc::clone(&cancelled),
+        };
+        if let Some(sender) = &self.sender {
+            // If the workers have all exited, the task (and with it the sending half of the
+            // channel) is dropped, and waiting on the handle reports that the pool has closed
+            let _ = sender.send(task);
+        }
+        RenderHandle {
+            receiver,
+            timeout: self.config.job_timeout,
+            cancelled,
+        }
+    }
+
+    /// Render every job in `jobs`, returning their results in the same order
+    pub fn render_all(
+        &self,
+        jobs: impl IntoIterator<Item = RenderJob>,
+    ) -> Vec<Result<RenderedImage, RenderPoolError>> {
+        let handles: Vec<_> = jobs.into_iter().map(|job| self.submit(job)).collect();
+        handles.into_iter().map(RenderHandle::wait).collect()
+    }
+}
+
+impl Drop for RenderPool {
+    fn drop(&mut self) {
+        // Closing the channel makes workers exit once the queue is empty
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// The pending result of a job submitted to a [`RenderPool`].
+///
+/// Dropping the handle cancels the job, as does waiting on it until it times out.
+pub struct RenderHandle {
+    receiver: mpsc::Receiver<JobEvent>,
+    timeout: Duration,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RenderHandle {
+    /// Block until the job has been rendered, has failed, or has timed out
+    pub fn wait(self) -> Result<RenderedImage, RenderPoolError> {
+        // Time spent in the queue doesn't count towards the timeout
+        loop {
+            match self.receiver.recv() {
+                Ok(JobEvent::Started) => break,
+                Ok(JobEvent::Finished(result)) => return result,
+                Err(_) => return Err(RenderPoolError::Closed),
+            }
+        }
+        match self.receiver.recv_timeout(self.timeout) {
+            Ok(JobEvent::Finished(result)) => result,
+            Ok(JobEvent::Started) | Err(RecvTimeoutError::Disconnected) => {
+                Err(RenderPoolError::Closed)
+            }
+            Err(RecvTimeoutError::Timeout) => Err(RenderPoolError::Timeout(self.timeout)),
+        }
+    }
+}
+
+impl Drop for RenderHandle {
+    fn drop(&mut self) {
+        // Nobody will read the result, so stop the worker rendering it
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Task {
+    job: RenderJob,
+    events: mpsc::Sender<JobEvent>,
+    /// Set when the job's handle is dropped
+    cancelled: Arc<AtomicBool>,
+}
+
+enum JobEvent {
+    Started,
+    Finished(Result<RenderedImage, RenderPoolError>),
+}
+
+/// A renderer kept between jobs, along with the size it was created for
+struct CachedRenderer<R> {
+    width: u32,
+    height: u32,
+    renderer: R,
+}
+
+fn run_worker<R: ImageRenderer>(
+    receiver: &Mutex<mpsc::Receiver<Task>>,
+    config: &RenderPoolConfig,
+) {
+    let mut renderer: Option<CachedRenderer<R>> = None;
+    loop {
+        let task = receiver.lock().unwrap_or_else(|err| err.into_inner()).recv();
+        let Ok(task) = task else {
+            // The pool has been dropped
+            break;
+        };
+        if task.events.send(JobEvent::Started).is_err() {
+            // The handle was dropped, so nobody is waiting for the result
+            continue;
+        }
+
+        let deadline = Instant::now() + config.job_timeout;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            render_job(&task.job, config, deadline, &task.cancelled, &mut renderer)
+        }))
+        .unwrap_or_else(|_| {
+            // The renderer may have been left in an inconsistent state
+            renderer = None;
+            Err(RenderPoolError::Panicked)
+        });
+        let _ = task.events.send(JobEvent::Finished(result));
+    }
+}
+
+fn render_job<R: ImageRenderer>(
+    job: &RenderJob,
+    config: &RenderPoolConfig,
+    deadline: Instant,
+    cancelled: &AtomicBool,
+    renderer: &mut Option<CachedRenderer<R>>,
+) -> Result<RenderedImage, RenderPoolError> {
+    let check_deadline = || match Instant::now() > deadline || cancelled.load(Ordering::Relaxed) {
+        true => Err(RenderPoolError::Timeout(config.job_timeout)),
+        false => Ok(()),
+    };
+    let check_nodes = |doc: &BaseDocument| match doc.nodes.len() > config.max_nodes {
+        true => Err(RenderPoolError::TooManyNodes(doc.nodes.len())),
+        false => Ok(()),
+    };
+
+    if job.html.len() > config.max_html_bytes {
+        return Err(RenderPoolError::TooLarge(job.html.len()));
+    }
+    let (width, height) = job.image_size();
+    if width as u64 * height as u64 * 4 > config.max_image_bytes as u64 {
+        return Err(RenderPoolError::ImageTooLarge { width, height });
+    }
+
+    let doc_config = static_document_config(
+        job.base_url.as_deref(),
+        Viewport::new(width, height, job.scale as f32, ColorScheme::Light),
+        Some(config.ua_stylesheets.clone()),
+    );
+    let mut doc = HtmlDocument::new(doc_config).into_inner();
+    let mut parser = HtmlParser::new();
+    for chunk in job.html.as_bytes().chunks(PARSE_CHUNK_BYTES) {
+        parser.feed(chunk);
+        parser.apply(&mut doc);
+        check_nodes(&doc)?;
+        check_deadline()?;
+    }
+    parser.finish(&mut doc);
+    check_nodes(&doc)?;
+
+    doc.resolve();
+    check_deadline()?;
+
+    if !matches!(renderer, Some(cached) if cached.width == width && cached.height == height) {
+        *renderer = Some(CachedRenderer {
+            width,
+            height,
+            renderer: R::new(width, height),
+        });
+    }
+    let renderer = &mut renderer.as_mut().expect("renderer was just created").renderer;
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    renderer.render(
+        |scene| paint_scene(scene, &doc, job.scale, width, height),
+        &mut rgba,
+    );
+    check_deadline()?;
+
+    let encode_config = ScreenshotConfig::builder()
+        .format(job.format.clone())
+        .quality(job.quality)
+        .build();
+    let data = encode_rgba(&rgba, width, height, &encode_config)?;
+    Ok(RenderedImage {
+        data,
+        width,
+        height,
+        format: job.format.clone(),
+    })
+}
+
+/// Parse a document which never fetches sub-resources
+pub(crate) fn static_document(
+    html: &str,
+    base_url: Option<&str>,
+    viewport: Viewport,
+    ua_stylesheets: Option<Vec<String>>,
+) -> BaseDocument {
+    let config = static_document_config(base_url, viewport, ua_stylesheets);
+    HtmlDocument::from_html(html, config).into_inner()
+}
+
+/// The configuration of a document which never fetches sub-resources
+fn static_document_config(
+    base_url: Option<&str>,
+    viewport: Viewport,
+    ua_stylesheets: Option<Vec<String>>,
+) -> DocumentConfig {
+    DocumentConfig {
+        base_url: base_url.map(String::from),
+        viewport: Some(viewport),
+        ua_stylesheets,
+        net_provider: Some(Arc::new(DummyNetProvider)),
+        shell_provider: Some(Arc::new(DummyShellProvider)),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_size_follows_scale() {
+        let mut job = RenderJob::new("<p>hello</p>");
+        job.viewport_width = 400;
+        job.viewport_height = 301;
+        job.scale = 1.5;
+        assert_eq!(job.image_size(), (600, 452));
+    }
+
+    #[test]
+    fn oversized_jobs_are_rejected_before_rendering() {
+        let config = RenderPoolConfig {
+            max_html_bytes: 8,
+            ..Default::default()
+        };
+        let job = RenderJob::new("<p>too long</p>");
+        let mut renderer: Option<CachedRenderer<VelloImageRenderer>> = None;
+        let deadline = Instant::now();
+        let result = render_job(&job, &config, deadline, &AtomicBool::new(false), &mut renderer);
+        assert!(matches!(result, Err(RenderPoolError::TooLarge(15))));
+        assert!(renderer.is_none());
+    }
+
+    #[test]
+    fn cancelled_jobs_stop_while_parsing() {
+        let config = RenderPoolConfig::default();
+        let job = RenderJob::new("<p>cancelled</p>".repeat(PARSE_CHUNK_BYTES));
+        let mut renderer: Option<CachedRenderer<VelloImageRenderer>> = None;
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let result = render_job(&job, &config, deadline, &AtomicBool::new(true), &mut renderer);
+        assert!(matches!(result, Err(RenderPoolError::Timeout(_))));
+        assert!(renderer.is_none());
+    }
+
+    #[test]
+    fn documents_with_too_many_nodes_are_rejected_while_parsing() {
+        let config = RenderPoolConfig {
+            max_nodes: 1000,
+            ..Default::default()
+        };
+        let job = RenderJob::new("<p></p>".repeat(PARSE_CHUNK_BYTES));
+        let mut renderer: Option<CachedRenderer<VelloImageRenderer>> = None;
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let result = render_job(&job, &config, deadline, &AtomicBool::new(false), &mut renderer);
+        // The first chunk holds about 9000 paragraphs, so parsing stops well before the end
+        let Err(RenderPoolError::TooManyNodes(nodes)) = result else {
+            panic!("the document wasn't rejected");
+        };
+        assert!(nodes < 20_000, "{nodes}");
+    }
+}
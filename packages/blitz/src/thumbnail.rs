@@ -0,0 +1,328 @@
+//! Thumbnail generation for link previews and file browsers
+//!
+//! [`ThumbnailService`] lays a page out in a desktop-sized viewport, paints it at a reduced scale
+//! and encodes the result with the screenshot encoders from [`blitz_paint::screenshot`].
+//!
+//! Thumbnails are rendered under tight limits: documents larger than
+//! [`ThumbnailConfig::max_html_bytes`] are rejected, fetching a URL is abandoned after
+//! [`ThumbnailConfig::timeout`], rendering is abandoned after
+//! [`ThumbnailConfig::render_timeout`], and sub-resources (stylesheets, images, fonts) are never
+//! fetched.
+//! Results are cached by a hash of the content they were rendered from, so repeated requests for
+//! an unchanged page are cheap.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyrender::{ImageRenderer, render_to_buffer};
+use anyrender_vello::VelloImageRenderer;
+use blitz_paint::paint_scene;
+use blitz_paint::screenshot::{ImageFormat, ScreenshotConfig, ScreenshotError, encode_rgba};
+use blitz_traits::shell::{ColorScheme, Viewport};
+use thiserror::Error;
+
+use crate::pool::static_document;
+
+/// Configuration for a [`ThumbnailService`]
+#[derive(Debug, Clone)]
+pub struct ThumbnailConfig {
+    /// The width of the viewport that pages are laid out in (in CSS pixels)
+    pub viewport_width: u32,
+    /// The height of the viewport that pages are laid out in (in CSS pixels)
+    pub viewport_height: u32,
+    /// The width of the thumbnail image. Its height follows from the aspect ratio of the viewport.
+    pub width: u32,
+    pub format: ImageFormat,
+    /// Encoding quality (0-100)
+    pub quality: u8,
+    /// Documents larger than this are rejected without being parsed
+    pub max_html_bytes: usize,
+    /// How long to wait for a URL to be fetched
+    pub timeout: Duration,
+    /// How long rendering a thumbnail may take. A render which takes longer is abandoned at the
+    /// next opportunity (after parsing, styling and layout, or painting).
+    pub render_timeout: Duration,
+    /// How many thumbnails to keep in the cache
+    pub cache_capacity: usize,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            viewport_width: 1280,
+            viewport_height: 800,
+            width: 320,
+            format: ImageFormat::Png,
+            quality: 80,
+            max_html_bytes: 2 * 1024 * 1024,
+            timeout: Duration::from_secs(10),
+            render_timeout: Duration::from_secs(5),
+            cache_capacity: 64,
+        }
+    }
+}
+
+impl ThumbnailConfig {
+    /// The size of the thumbnail image in pixels
+    pub fn thumbnail_size(&self) -> (u32, u32) {
+        let height = self.viewport_height as u64 * self.width as u64
+            / self.viewport_width.max(1) as u64;
+        (self.width, (height as u32).max(1))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ThumbnailError {
+    #[error("Document is too large ({0} bytes)")]
+    TooLarge(usize),
+
+    #[error("Document is not valid UTF-8")]
+    InvalidUtf8,
+
+    #[cfg(feature = "net")]
+    #[error("Invalid url: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+
+    #[cfg(feature = "net")]
+    #[error("Failed to fetch {url}: {message}")]
+    Fetch { url: String, message: String },
+
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Encoding failed: {0}")]
+    Encoding(#[from] ScreenshotError),
+}
+
+/// An encoded thumbnail image
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    /// The encoded image
+    pub data: Arc<[u8]>,
+    pub width: u32,
+    pub height: u32,
+    pub format: ImageFormat,
+}
+
+/// Renders and caches thumbnails of web pages.
+///
+/// Rendering happens synchronously on the calling thread using the image renderer `R`.
+pub struct ThumbnailService<R: ImageRenderer = VelloImageRenderer> {
+    config: ThumbnailConfig,
+    cache: Mutex<ThumbnailCache>,
+    #[cfg(feature = "net")]
+    net_provider: blitz_net::Provider<blitz_dom::net::Resource>,
+    renderer: PhantomData<fn() -> R>,
+}
+
+impl<R: ImageRenderer> ThumbnailService<R> {
+    pub fn new(config: ThumbnailConfig) -> Self {
+        Self {
+            cache: Mutex::new(ThumbnailCache::new(config.cache_capacity)),
+            config,
+            #[cfg(feature = "net")]
+            net_provider: blitz_net::Provider::new(Arc::new(blitz_traits::net::DummyNetCallback)),
+            renderer: PhantomData,
+        }
+    }
+
+    pub fn config(&self) -> &ThumbnailConfig {
+        &self.config
+    }
+
+    /// Render a thumbnail of an HTML document. Relative urls are resolved against `base_url`.
+    pub fn render_html(
+        &self,
+        html: &str,
+        base_url: Option<&str>,
+    ) -> Result<Thumbnail, ThumbnailError> {
+        if html.len() > self.config.max_html_bytes {
+            return Err(ThumbnailError::TooLarge(html.len()));
+        }
+
+        let key = content_hash(html, base_url);
+        if let Some(thumbnail) = self.lock_cache().get(key) {
+            return Ok(thumbnail);
+        }
+
+        let config = &self.config;
+        let deadline = Instant::now() + config.render_timeout;
+        let check_deadline = || match Instant::now() > deadline {
+            true => Err(ThumbnailError::Timeout(config.render_timeout)),
+            false => Ok(()),
+        };
+        let (width, height) = config.thumbnail_size();
+        let scale = width as f64 / config.viewport_width.max(1) as f64;
+
+        let mut doc = static_document(
+            html,
+            base_url,
+            Viewport::new(config.viewport_width, config.viewport_height, 1.0, ColorScheme::Light),
+            None,
+        );
+        check_deadline()?;
+
+        doc.resolve();
+        check_deadline()?;
+
+        let rgba = render_to_buffer::<R, _>(
+            |scene| paint_scene(scene, &doc, scale, width, height),
+            width,
+            height,
+        );
+        check_deadline()?;
+        let encode_config = ScreenshotConfig::builder()
+            .format(config.format.clone())
+            .quality(config.quality)
+            .build();
+        let data = encode_rgba(&rgba, width, height, &encode_config)?;
+
+        let thumbnail = Thumbnail {
+            data: data.into(),
+            width,
+            height,
+            format: config.format.clone(),
+        };
+        self.lock_cache().insert(key, thumbnail.clone());
+        Ok(thumbnail)
+    }
+
+    /// Fetch the page at `url` and render a thumbnail of it
+    #[cfg(feature = "net")]
+    pub async fn render_url(&self, url: &str) -> Result<Thumbnail, ThumbnailError> {
+        use blitz_traits::net::Request;
+
+        let url = url::Url::parse(url)?;
+        let timeout = self.config.timeout;
+        let fetch = self.net_provider.fetch_async(None, Request::get(url.clone()));
+        let (final_url, bytes) = tokio::time::timeout(timeout, fetch)
+            .await
+            .map_err(|_| ThumbnailError::Timeout(timeout))?
+            .map_err(|err| ThumbnailError::Fetch {
+                url: url.to_string(),
+                message: err.to_string(),
+            })?;
+
+        if bytes.len() > self.config.max_html_bytes {
+            return Err(ThumbnailError::TooLarge(bytes.len()));
+        }
+        let html = std::str::from_utf8(&bytes).map_err(|_| ThumbnailError::InvalidUtf8)?;
+        self.render_html(html, Some(final_url.as_str()))
+    }
+
+    /// The number of cached thumbnails
+    pub fn cached_count(&self) -> usize {
+        self.lock_cache().entries.len()
+    }
+
+    pub fn clear_cache(&self) {
+        let mut cache = self.lock_cache();
+        cache.entries.clear();
+        cache.order.clear();
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, ThumbnailCache> {
+        self.cache.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+fn content_hash(html: &str, base_url: Option<&str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    html.hash(&mut hasher);
+    base_url.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A least-recently-used cache of thumbnails keyed by content hash
+struct ThumbnailCache {
+    capacity: usize,
+    entries: HashMap<u64, Thumbnail>,
+    /// Keys from least to most recently used
+    order: VecDeque<u64>,
+}
+
+impl ThumbnailCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&mut self, key: u64) -> Option<Thumbnail> {
+        let thumbnail = self.entries.get(&key)?.clone();
+        self.touch(key);
+        Some(thumbnail)
+    }
+
+    fn insert(&mut self, key: u64, thumbnail: Thumbnail) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key, thumbnail).is_some() {
+            self.touch(key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: u64) {
+        if let Some(idx) = self.order.iter().position(|k| *k == key) {
+            self.order.remove(idx);
+        }
+        self.order.push_back(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thumbnail(width: u32) -> Thumbnail {
+        Thumbnail {
+            data: Arc::from(&[][..]),
+            width,
+            height: 1,
+            format: ImageFormat::Png,
+        }
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = ThumbnailCache::new(2);
+        cache.insert(1, thumbnail(1));
+        cache.insert(2, thumbnail(2));
+        assert!(cache.get(1).is_some());
+        cache.insert(3, thumbnail(3));
+
+        assert!(cache.get(2).is_none());
+        assert_eq!(cache.get(1).map(|t| t.width), Some(1));
+        assert_eq!(cache.get(3).map(|t| t.width), Some(3));
+    }
+
+    #[test]
+    fn thumbnail_size_keeps_viewport_aspect_ratio() {
+        let config = ThumbnailConfig::default();
+        assert_eq!(config.thumbnail_size(), (320, 200));
+    }
+
+    #[test]
+    fn slow_renders_are_abandoned() {
+        let service = ThumbnailService::<VelloImageRenderer>::new(ThumbnailConfig {
+            render_timeout: Duration::ZERO,
+            ..Default::default()
+        });
+        let result = service.render_html("<p>hello</p>", None);
+        assert!(matches!(result, Err(ThumbnailError::Timeout(Duration::ZERO))));
+        assert_eq!(service.cached_count(), 0);
+    }
+}
@@ -0,0 +1,124 @@
+use blitz_shell::{BlitzApplication, BlitzShellEvent, WindowConfig};
+use winit::application::ApplicationHandler;
+use winit::event::{StartCause, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
+use winit::window::WindowId;
+
+use crate::DxnWindowRenderer;
+#[cfg(feature = "hot-reload")]
+use crate::hot_reload::HotReloadEvent;
+
+/// The application which runs Dioxus apps, handling the events specific to them on top of
+/// those of [`BlitzApplication`]
+pub struct DioxusNativeApplication {
+    inner: BlitzApplication<DxnWindowRenderer>,
+    #[cfg(feature = "hot-reload")]
+    asset_watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl DioxusNativeApplication {
+    pub fn new(proxy: EventLoopProxy<BlitzShellEvent>) -> Self {
+        Self {
+            inner: BlitzApplication::new(proxy),
+            #[cfg(feature = "hot-reload")]
+            asset_watcher: None,
+        }
+    }
+
+    pub fn add_window(&mut self, window_config: WindowConfig<DxnWindowRenderer>) {
+        self.inner.add_window(window_config);
+    }
+
+    /// Save links with a `download` attribute with `downloads`
+    #[cfg(feature = "net")]
+    pub fn set_downloads(&mut self, downloads: std::sync::Arc<blitz_net::DownloadManager>) {
+        self.inner.downloads = Some(downloads);
+    }
+
+    /// Hot-reload changes to `rsx!` templates, and to the assets in `asset_dirs`, while the app
+    /// runs
+    #[cfg(feature = "hot-reload")]
+    pub fn enable_hot_reload(&mut self, asset_dirs: Vec<std::path::PathBuf>) {
+        self.asset_watcher = crate::hot_reload::start(&self.inner.proxy, &asset_dirs);
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn handle_hot_reload_event(&mut self, event_loop: &ActiveEventLoop, event: &HotReloadEvent) {
+        use dioxus_devtools::DevserverMsg;
+
+        match event {
+            HotReloadEvent::AssetsChanged(paths) => {
+                for window in self.inner.windows.values_mut() {
+                    reload_assets(window, paths);
+                }
+            }
+            HotReloadEvent::Devserver(DevserverMsg::HotReload(msg)) => {
+                for window in self.inner.windows.values_mut() {
+                    let doc = window.downcast_doc_mut::<crate::DioxusDocument>();
+                    dioxus_devtools::apply_changes(&doc.vdom, msg);
+                    reload_assets(window, &msg.assets);
+                    window.poll();
+                }
+            }
+            HotReloadEvent::Devserver(DevserverMsg::Shutdown) => event_loop.exit(),
+            HotReloadEvent::Devserver(_) => {}
+        }
+    }
+}
+
+/// Reload the stylesheets and images of a window's document which were loaded from `paths`
+#[cfg(feature = "hot-reload")]
+fn reload_assets(window: &mut blitz_shell::View<DxnWindowRenderer>, paths: &[std::path::PathBuf]) {
+    // Assets are only loaded from files with the `net` feature
+    #[cfg(feature = "net")]
+    if !paths.is_empty() {
+        window
+            .downcast_doc_mut::<crate::DioxusDocument>()
+            .reload_assets(paths);
+        window.request_redraw();
+    }
+    #[cfg(not(feature = "net"))]
+    let _ = (window, paths);
+}
+
+impl ApplicationHandler<BlitzShellEvent> for DioxusNativeApplication {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        self.inner.resumed(event_loop);
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        self.inner.suspended(event_loop);
+    }
+
+    fn memory_warning(&mut self, event_loop: &ActiveEventLoop) {
+        self.inner.memory_warning(event_loop);
+    }
+
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+        self.inner.new_events(event_loop, cause);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.inner.about_to_wait(event_loop);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        self.inner.window_event(event_loop, window_id, event);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: BlitzShellEvent) {
+        match event {
+            #[cfg(feature = "hot-reload")]
+            BlitzShellEvent::Embedder(event) if event.is::<HotReloadEvent>() => {
+                let event = event.downcast_ref::<HotReloadEvent>().unwrap();
+                self.handle_hot_reload_event(event_loop, event);
+            }
+            event => self.inner.user_event(event_loop, event),
+        }
+    }
+}
@@ -1,15 +1,18 @@
 //! Integration between Dioxus and Blitz
+use std::ffi::OsStr;
 use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use std::task::{Context as TaskContext, Waker};
 use std::{any::Any, collections::HashMap, rc::Rc, sync::Arc};
 
 use blitz_dom::DocumentConfig;
 use blitz_dom::{
-    BaseDocument, DEFAULT_CSS, Document, EventDriver, EventHandler, Node, net::Resource,
+    BaseDocument, DEFAULT_CSS, Document, DocumentLifecycle, EventDriver, EventHandler, Node,
+    net::Resource,
 };
 use blitz_traits::{
-    events::{BlitzImeEvent, DomEvent, DomEventData, EventState, UiEvent},
+    events::{BlitzImeEvent, DomEvent, DomEventData, EventPhase, EventState, UiEvent},
     net::NetProvider,
 };
 use dioxus_core::{ElementId, Event, VirtualDom};
@@ -18,6 +21,7 @@ use futures_util::{FutureExt, pin_mut, task::noop_waker};
 
 use crate::events::{
     BlitzKeyboardData, NativeClickData, NativeCompositionData, NativeConverter, NativeFormData, NativeFocusData,
+    NativePointerData,
 };
 use crate::mutation_writer::{DioxusState, MutationWriter};
 use crate::qual_name;
@@ -88,6 +92,7 @@ impl DioxusDocument {
 
         doc.inner.set_base_url("dioxus://index.html");
         doc.initial_build();
+        doc.inner.set_lifecycle(DocumentLifecycle::Interactive);
         doc.inner.print_tree();
 
         doc
@@ -97,6 +102,21 @@ impl DioxusDocument {
         let mut writer = MutationWriter::new(&mut self.inner, &mut self.vdom_state);
         self.vdom.rebuild(&mut writer);
     }
+
+    /// Reload the stylesheets and images loaded from bundled asset files which have changed
+    /// (matching them by file name, ignoring the hashes `dx` adds to them)
+    #[cfg(feature = "net")]
+    pub fn reload_assets(&mut self, changed: &[PathBuf]) {
+        let changed_names: Vec<&OsStr> = changed.iter().filter_map(|path| path.file_name()).collect();
+        self.inner.mutate().reload_resources(|url| {
+            if url.scheme() != blitz_net::BUNDLE_SCHEME {
+                return false;
+            }
+            let path = blitz_net::strip_asset_hash(url.path());
+            let name = Path::new(&*path).file_name();
+            name.is_some_and(|name| changed_names.contains(&name))
+        });
+    }
 }
 
 // Implement Document and required traits for DioxusDocument
@@ -167,6 +187,11 @@ impl EventHandler for DioxusEventHandler<'_> {
         mutr: &mut blitz_dom::DocumentMutator<'_>,
         event_state: &mut EventState,
     ) {
+        // Dioxus only has bubbling listeners
+        if event.phase == EventPhase::Capturing {
+            return;
+        }
+
         let event_data = match &event.data {
             DomEventData::MouseMove(mouse_event) => {
                 let viewport_scroll = mutr.doc.viewport_scroll();
@@ -201,7 +226,7 @@ impl EventHandler for DioxusEventHandler<'_> {
                     target_layout,
                 )))
             }
-            DomEventData::Click(mouse_event) => {
+            DomEventData::Click(mouse_event) | DomEventData::ContextMenu(mouse_event) => {
                 let viewport_scroll = mutr.doc.viewport_scroll();
                 let target_layout = mutr.doc.get_node(event.target)
                     .map(|node| node.final_layout.location)
@@ -250,10 +275,35 @@ impl EventHandler for DioxusEventHandler<'_> {
                 values: HashMap::new(),
             })),
 
-            DomEventData::Submit => Some(wrap_event_data(NativeFormData {
+            DomEventData::Submit(_) => Some(wrap_event_data(NativeFormData {
                 value: String::new(),
                 values: HashMap::new(),
             })),
+
+            DomEventData::PointerDown(pointer_event)
+            | DomEventData::PointerMove(pointer_event)
+            | DomEventData::PointerUp(pointer_event)
+            | DomEventData::PointerCancel(pointer_event)
+            | DomEventData::GotPointerCapture(pointer_event)
+            | DomEventData::LostPointerCapture(pointer_event) => {
+                let viewport_scroll = mutr.doc.viewport_scroll();
+                let target_layout = mutr.doc.get_node(event.target)
+                    .map(|node| node.final_layout.location)
+                    .unwrap_or(taffy::Point::ZERO);
+                Some(wrap_event_data(NativePointerData::from_pointer(
+                    pointer_event,
+                    viewport_scroll,
+                    target_layout,
+                )))
+            }
+
+            // Not yet forwarded to Dioxus
+            DomEventData::TouchStart(_)
+            | DomEventData::TouchMove(_)
+            | DomEventData::TouchEnd(_)
+            | DomEventData::GamepadButtonDown(_)
+            | DomEventData::GamepadButtonUp(_)
+            | DomEventData::GamepadAxisMove(_) => None,
         };
 
         let Some(event_data) = event_data else {
@@ -97,6 +97,10 @@ impl WindowRenderer for DxnWindowRenderer {
         self.inner.borrow_mut().set_size(width, height)
     }
 
+    fn trim_caches(&mut self) {
+        self.inner.borrow_mut().trim_caches()
+    }
+
     fn initialize_text_system(&self, doc: &dyn std::any::Any) -> Result<(), String> {
         // Try DioxusDocument first, then fall back to BaseDocument
         if let Some(dioxus_doc) = doc.downcast_ref::<crate::DioxusDocument>() {
@@ -1,7 +1,10 @@
 use std::any::Any;
 use std::collections::HashMap;
 
-use blitz_traits::events::{BlitzKeyEvent, BlitzMouseButtonEvent, MouseEventButton, MouseEventButtons};
+use blitz_traits::events::{
+    BlitzKeyEvent, BlitzMouseButtonEvent, BlitzPointerEvent, MouseEventButton, MouseEventButtons,
+    PointerType,
+};
 use dioxus_html::{
     AnimationData, ClipboardData, CompositionData, DragData, FocusData, FormData, FormValue,
     HasAnimationData, HasClipboardData, HasCompositionData, HasDragData, HasFileData, HasFocusData, 
@@ -248,8 +251,10 @@ impl HtmlEventConverter for NativeConverter {
     }
 
     fn convert_pointer_data(&self, event: &PlatformEventData) -> PointerData {
-        // Convert mouse data to pointer data if available
-        if let Some(mouse_data) = event.downcast::<NativeClickData>() {
+        // Pointer events carry their own data, and mouse events are converted to it
+        if let Some(pointer_data) = event.downcast::<NativePointerData>() {
+            PointerData::new(pointer_data.clone())
+        } else if let Some(mouse_data) = event.downcast::<NativeClickData>() {
             PointerData::new(NativePointerData::from_mouse_data(mouse_data.clone()))
         } else {
             PointerData::new(NativePointerData::default())
@@ -379,6 +384,43 @@ impl NativePointerData {
     }
 }
 
+impl NativePointerData {
+    /// Create a new NativePointerData from a BlitzPointerEvent, with its location converted like
+    /// [`NativeClickData::new`] does
+    pub fn from_pointer(
+        event: &BlitzPointerEvent,
+        viewport_scroll: kurbo::Point,
+        target_location: TaffyPoint<f32>,
+    ) -> Self {
+        let mouse_data = NativeClickData::new(event.to_mouse(), viewport_scroll, target_location);
+        let (tilt_x, tilt_y) = tilt(event.altitude_angle, event.azimuth_angle);
+        let pointer_type = match event.pointer_type {
+            PointerType::Mouse => "mouse",
+            PointerType::Pen => "pen",
+            PointerType::Touch => "touch",
+        };
+        Self {
+            pointer_id: i32::try_from(event.pointer_id).unwrap_or(i32::MAX),
+            pressure: event.pressure as f64,
+            tilt_x,
+            tilt_y,
+            pointer_type: pointer_type.to_string(),
+            is_primary: event.is_primary,
+            ..Self::from_mouse_data(mouse_data)
+        }
+    }
+}
+
+/// The `tiltX` and `tiltY` (in degrees) of a pointer at `altitude` and `azimuth` (in radians), see
+/// <https://w3c.github.io/pointerevents/#converting-between-tiltx-tilty-and-altitudeangle-azimuthangle>
+fn tilt(altitude: f32, azimuth: f32) -> (f64, f64) {
+    // Pens lying flat on the screen are tilted all the way towards their azimuth
+    let tan = (altitude as f64).max(1e-6).tan();
+    let (sin, cos) = (azimuth as f64).sin_cos();
+    let degrees = |component: f64| (component / tan).atan().to_degrees().round();
+    (degrees(cos), degrees(sin))
+}
+
 impl Default for NativePointerData {
     fn default() -> Self {
         Self::from_mouse_data(NativeClickData::default())
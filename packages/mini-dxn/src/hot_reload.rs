@@ -0,0 +1,130 @@
+//! Hot-reloading in development, turned on by launching apps with `--hot-reload`
+//!
+//! Asset files (like stylesheets) are watched for changes in the `assets` directories of every
+//! directory bundled resources are loaded from, and swapped into the running app and restyled
+//! without a rebuild. When the app is served by `dx serve`, changes to `rsx!`
+//! templates are also received from its devserver and re-rendered.
+
+use std::path::PathBuf;
+
+use blitz_shell::{BlitzShellEvent, EventLoopProxy};
+use dioxus_devtools::DevserverMsg;
+use notify::{Event as NotifyEvent, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+/// The command line option which turns on hot-reloading
+pub const HOT_RELOAD_ARG: &str = "--hot-reload";
+
+/// Sent to the event loop (as an embedder event) when something to hot-reload changes
+pub enum HotReloadEvent {
+    /// Files in a watched asset directory were changed
+    AssetsChanged(Vec<PathBuf>),
+    /// A message from the `dx` devserver
+    Devserver(DevserverMsg),
+}
+
+/// Whether the app was launched with [`HOT_RELOAD_ARG`]
+pub(crate) fn is_enabled() -> bool {
+    std::env::args().skip(1).any(|arg| arg == HOT_RELOAD_ARG)
+}
+
+/// The `assets` directories of the directories bundled resources are loaded from (beside the
+/// executable and the working directory)
+#[cfg(feature = "net")]
+pub(crate) fn asset_dirs() -> Vec<PathBuf> {
+    let assets = blitz_net::AssetDirectory::for_current_exe();
+    assets.roots().iter().map(|root| root.join("assets")).collect()
+}
+
+/// Without the `net` feature, assets aren't loaded from files, so there are none to watch
+#[cfg(not(feature = "net"))]
+pub(crate) fn asset_dirs() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Start hot-reloading, returning the watcher of those of `asset_dirs` which exist (which stops
+/// watching when dropped)
+pub(crate) fn start(
+    proxy: &EventLoopProxy<BlitzShellEvent>,
+    asset_dirs: &[PathBuf],
+) -> Option<RecommendedWatcher> {
+    let devserver_proxy = proxy.clone();
+    dioxus_devtools::connect(move |msg| {
+        let event = BlitzShellEvent::embedder_event(HotReloadEvent::Devserver(msg));
+        let _ = devserver_proxy.send_event(event);
+    });
+
+    let asset_dirs: Vec<&PathBuf> = asset_dirs.iter().filter(|dir| dir.is_dir()).collect();
+    if asset_dirs.is_empty() {
+        return None;
+    }
+    let proxy = proxy.clone();
+    let on_change = move |paths| {
+        let changed = HotReloadEvent::AssetsChanged(paths);
+        let _ = proxy.send_event(BlitzShellEvent::embedder_event(changed));
+    };
+    match watch_assets(&asset_dirs, on_change) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            eprintln!("Failed to watch assets for hot-reloading: {err}");
+            None
+        }
+    }
+}
+
+/// Call `on_change` with the paths of the files created or modified in `asset_dirs`
+fn watch_assets(
+    asset_dirs: &[&PathBuf],
+    on_change: impl Fn(Vec<PathBuf>) + Send + 'static,
+) -> notify::Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<NotifyEvent>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            on_change(event.paths);
+        }
+    })?;
+    for asset_dir in asset_dirs {
+        watcher.watch(asset_dir, RecursiveMode::Recursive)?;
+    }
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::watch_assets;
+
+    #[test]
+    fn changes_in_every_asset_directory_are_reported() {
+        let root = std::env::temp_dir().join(format!("mini-dxn-assets-{}", std::process::id()));
+        let (bundled, working) = (root.join("bundle/assets"), root.join("working/assets"));
+        std::fs::create_dir_all(&bundled).unwrap();
+        std::fs::create_dir_all(working.join("css")).unwrap();
+
+        let (sender, changes) = mpsc::channel();
+        let on_change = move |paths| {
+            let _ = sender.send(paths);
+        };
+        let watcher = watch_assets(&[&bundled, &working], on_change).unwrap();
+        // Wait for each change to be reported (a write may be reported as several events)
+        let changed = |path: &std::path::Path| {
+            std::fs::write(path, "body { color: red }").unwrap();
+            let path = path.canonicalize().unwrap();
+            while let Ok(paths) = changes.recv_timeout(Duration::from_secs(10)) {
+                let mut canonical = paths.iter().filter_map(|path| path.canonicalize().ok());
+                if canonical.any(|changed| changed == path) {
+                    return true;
+                }
+            }
+            false
+        };
+        assert!(changed(&bundled.join("logo.svg")));
+        assert!(changed(&working.join("css/main.css")));
+
+        drop(watcher);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
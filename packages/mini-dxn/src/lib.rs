@@ -5,11 +5,15 @@
 //! ## Feature flags
 //!  - `default`: Enables the features listed below.
 //!  - `accessibility`: Enables [`accesskit`] accessibility support.
-//!  - `hot-reload`: Enables hot-reloading of Dioxus RSX.
+//!  - `hot-reload`: Enables hot-reloading of Dioxus RSX (and of assets, with `net`), for apps
+//!    launched with `--hot-reload`.
 //!  - `tracing`: Enables tracing support.
 
+mod dioxus_application;
 mod dioxus_document;
 mod events;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
 mod mutation_writer;
 
 mod dioxus_renderer;
@@ -18,12 +22,17 @@ use std::any::Any;
 #[cfg(feature = "gpu_backend")]
 pub use anyrender_vello::wgpu::{Features, Limits};
 use blitz_dom::{LocalName, Namespace, QualName, ns};
-use blitz_shell::{BlitzApplication, BlitzShellEvent, WindowConfig, create_default_event_loop};
+use blitz_shell::{
+    BlitzShellEvent, BlitzShellNavigationProvider, WindowConfig, create_default_event_loop,
+};
 use dioxus_core::{Element, VirtualDom};
+pub use dioxus_application::DioxusNativeApplication;
 pub use dioxus_document::DioxusDocument;
 pub use dioxus_renderer::DxnWindowRenderer;
 #[cfg(feature = "gpu_backend")]
 pub use dioxus_renderer::use_wgpu;
+#[cfg(feature = "hot-reload")]
+pub use hot_reload::{HOT_RELOAD_ARG, HotReloadEvent};
 pub use mutation_writer::MutationWriter;
 
 type NodeId = usize;
@@ -77,15 +86,23 @@ pub fn launch_cfg(
     let _guard = rt.enter();
 
     #[cfg(feature = "net")]
-    let net_provider = {
-        use blitz_net::Provider;
+    let (net_provider, downloads) = {
+        use std::sync::Arc;
+
+        use blitz_dom::net::Resource;
+        use blitz_net::{AssetDirectory, Provider};
         use blitz_shell::BlitzShellNetCallback;
+        use blitz_traits::net::NetProvider;
 
         let proxy = event_loop.create_proxy();
         let net_callback = BlitzShellNetCallback::shared(proxy);
-        let net_provider = Provider::shared(net_callback);
+        // Serve `asset!()`s and other `dioxus:` URLs from the files bundled with the app
+        let assets = Arc::new(AssetDirectory::for_current_exe());
+        let provider = Arc::new(Provider::new(net_callback).with_bundle_provider(assets));
+        let downloads = Arc::clone(provider.downloads());
+        let net_provider: Arc<dyn NetProvider<Resource>> = provider;
 
-        Some(net_provider)
+        (Some(net_provider), downloads)
     };
 
     #[cfg(not(feature = "net"))]
@@ -105,7 +122,9 @@ pub fn launch_cfg(
     }
 
     // Create the document and renderer
-    let doc = DioxusDocument::new(vdom, net_provider);
+    let mut doc = DioxusDocument::new(vdom, net_provider);
+    // Navigations are sent to the application, which saves links with a `download` attribute
+    doc.set_navigation_provider(BlitzShellNavigationProvider::shared(event_loop.create_proxy()));
     
     // Text system initialization happens automatically during Window::resume()
     // The shell calls renderer.initialize_text_system(doc) after GPU context is available
@@ -114,8 +133,14 @@ pub fn launch_cfg(
     let window = WindowConfig::new(Box::new(doc) as _, renderer);
 
     // Create application
-    let mut application = BlitzApplication::<DxnWindowRenderer>::new(event_loop.create_proxy());
+    let mut application = DioxusNativeApplication::new(event_loop.create_proxy());
+    #[cfg(feature = "net")]
+    application.set_downloads(downloads);
     application.add_window(window);
+    #[cfg(feature = "hot-reload")]
+    if hot_reload::is_enabled() {
+        application.enable_hot_reload(hot_reload::asset_dirs());
+    }
 
     // Run event loop
     event_loop.run_app(&mut application).unwrap();
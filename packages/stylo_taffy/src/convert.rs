@@ -5,6 +5,7 @@ use tracing;
 
 /// Private module of type aliases so we can refer to stylo types with nicer names
 pub mod stylo {
+    pub(crate) use style::logical_geometry::WritingMode;
     pub(crate) use style::properties::ComputedValues;
     pub(crate) use style::properties::generated::longhands::box_sizing::computed_value::T as BoxSizing;
     pub(crate) use style::properties::longhands::aspect_ratio::computed_value::T as AspectRatio;
@@ -555,10 +556,22 @@ pub fn overflow(input: stylo::Overflow) -> taffy::Overflow {
 pub fn aspect_ratio(input: stylo::AspectRatio) -> Option<f32> {
     match input.ratio {
         stylo::PreferredRatio::None => None,
-        stylo::PreferredRatio::Ratio(val) => Some(val.0.0 / val.1.0),
+        // A degenerate ratio (with a zero or infinite component) behaves as `auto`
+        // https://drafts.csswg.org/css-values-4/#degenerate-ratio
+        stylo::PreferredRatio::Ratio(val) => {
+            let ratio = val.0.0 / val.1.0;
+            (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+        }
     }
 }
 
+/// Whether a replaced element's natural aspect ratio takes precedence over the preferred
+/// aspect ratio from the style (`aspect-ratio: auto` and `aspect-ratio: auto && <ratio>`)
+#[inline]
+pub fn prefers_natural_aspect_ratio(input: stylo::AspectRatio) -> bool {
+    input.auto
+}
+
 #[inline]
 pub fn content_alignment(input: stylo::ContentDistribution) -> Option<taffy::AlignContent> {
     match input.primary().value() {
@@ -575,6 +588,10 @@ pub fn content_alignment(input: stylo::ContentDistribution) -> Option<taffy::Ali
         stylo::AlignFlags::SPACE_BETWEEN => Some(taffy::AlignContent::SpaceBetween),
         stylo::AlignFlags::SPACE_AROUND => Some(taffy::AlignContent::SpaceAround),
         stylo::AlignFlags::SPACE_EVENLY => Some(taffy::AlignContent::SpaceEvenly),
+        // Taffy doesn't align flex lines or grid tracks by their baselines, so use the fallback
+        // alignments: https://drafts.csswg.org/css-align-3/#valdef-align-content-first-baseline
+        stylo::AlignFlags::BASELINE => Some(taffy::AlignContent::Start),
+        stylo::AlignFlags::LAST_BASELINE => Some(taffy::AlignContent::End),
         // Should never be hit. But no real reason to panic here.
         _ => None,
     }
@@ -596,6 +613,8 @@ pub fn item_alignment(input: stylo::AlignFlags) -> Option<taffy::AlignItems> {
         stylo::AlignFlags::RIGHT => Some(taffy::AlignItems::End),
         stylo::AlignFlags::CENTER => Some(taffy::AlignItems::Center),
         stylo::AlignFlags::BASELINE => Some(taffy::AlignItems::Baseline),
+        // Taffy only aligns first baselines, so use the fallback alignment of `last baseline`
+        stylo::AlignFlags::LAST_BASELINE => Some(taffy::AlignItems::End),
         // Should never be hit. But no real reason to panic here.
         _ => None,
     }
@@ -643,6 +662,32 @@ pub fn flex_direction(input: stylo::FlexDirection) -> taffy::FlexDirection {
     }
 }
 
+/// Convert `flex-direction`, taking the writing mode into account.
+///
+/// `row` follows the inline axis and `column` follows the block axis. In vertical writing modes
+/// these are the physical vertical and horizontal axes respectively, and in `vertical-rl` the
+/// block axis runs from right to left.
+#[inline]
+#[cfg(feature = "flexbox")]
+pub fn flex_direction_for_writing_mode(
+    input: stylo::FlexDirection,
+    writing_mode: stylo::WritingMode,
+) -> taffy::FlexDirection {
+    if !writing_mode.is_vertical() {
+        return flex_direction(input);
+    }
+
+    let block_reversed = !writing_mode.is_vertical_lr();
+    match input {
+        stylo::FlexDirection::Row => taffy::FlexDirection::Column,
+        stylo::FlexDirection::RowReverse => taffy::FlexDirection::ColumnReverse,
+        stylo::FlexDirection::Column if block_reversed => taffy::FlexDirection::RowReverse,
+        stylo::FlexDirection::Column => taffy::FlexDirection::Row,
+        stylo::FlexDirection::ColumnReverse if block_reversed => taffy::FlexDirection::Row,
+        stylo::FlexDirection::ColumnReverse => taffy::FlexDirection::RowReverse,
+    }
+}
+
 #[inline]
 #[cfg(feature = "flexbox")]
 pub fn flex_wrap(input: stylo::FlexWrap) -> taffy::FlexWrap {
@@ -1335,7 +1380,10 @@ pub fn to_taffy_style_with_grid_context(
 
         // Flexbox
         #[cfg(feature = "flexbox")]
-        flex_direction: self::flex_direction(pos.flex_direction),
+        flex_direction: self::flex_direction_for_writing_mode(
+            pos.flex_direction,
+            style.writing_mode,
+        ),
         #[cfg(feature = "flexbox")]
         flex_wrap: self::flex_wrap(pos.flex_wrap),
         #[cfg(feature = "flexbox")]
@@ -1468,7 +1516,10 @@ pub fn to_taffy_style(style: &stylo::ComputedValues) -> taffy::Style {
 
         // Flexbox
         #[cfg(feature = "flexbox")]
-        flex_direction: self::flex_direction(pos.flex_direction),
+        flex_direction: self::flex_direction_for_writing_mode(
+            pos.flex_direction,
+            style.writing_mode,
+        ),
         #[cfg(feature = "flexbox")]
         flex_wrap: self::flex_wrap(pos.flex_wrap),
         #[cfg(feature = "flexbox")]
@@ -1555,6 +1606,33 @@ pub fn grid_template_line_names(input: &stylo::GridTemplateComponent) -> Option<
     }
 }
 
+/// The line names a subgrid declares (e.g. `subgrid [a] [b c]`), for each of its lines from the
+/// first. `repeat()`s are expanded, with `auto-fill` repeated once.
+#[cfg(feature = "grid")]
+pub fn subgrid_line_names(input: &stylo::GridTemplateComponent) -> Vec<Vec<String>> {
+    use style::values::generics::grid::{GenericLineNameListValue, RepeatCount};
+
+    let stylo::GenericGridTemplateComponent::Subgrid(subgrid) = input else {
+        return Vec::new();
+    };
+    subgrid
+        .line_names
+        .iter()
+        .flat_map(|value| match value {
+            GenericLineNameListValue::LineNames(names) => {
+                vec![names.iter().map(|ident| ident.0.to_string()).collect()]
+            }
+            GenericLineNameListValue::Repeat(repeat) => {
+                let count = match repeat.count {
+                    RepeatCount::Number(count) => count.max(1) as usize,
+                    RepeatCount::AutoFill | RepeatCount::AutoFit => 1,
+                };
+                extract_line_names(&repeat.line_names).repeat(count)
+            }
+        })
+        .collect()
+}
+
 // Reverse conversion functions for subgrid track inheritance
 // These convert from taffy types back to stylo types for CSS style modification
 
@@ -192,7 +192,10 @@ impl<T: Deref<Target = ComputedValues>> taffy::BlockItemStyle for TaffyStyloStyl
 impl<T: Deref<Target = ComputedValues>> taffy::FlexboxContainerStyle for TaffyStyloStyle<T> {
     #[inline]
     fn flex_direction(&self) -> taffy::FlexDirection {
-        convert::flex_direction(self.computed_values.get_position().flex_direction)
+        convert::flex_direction_for_writing_mode(
+            self.computed_values.get_position().flex_direction,
+            self.computed_values.writing_mode,
+        )
     }
 
     #[inline]
@@ -26,7 +26,7 @@ use panic_backtrace::StashedPanicInfo;
 use cosmyc_text::FontSystem;
 use rayon::prelude::*;
 use regex::Regex;
-use report::{generate_expectations, generate_report};
+use report::{generate_conformance_summary, generate_expectations, generate_report};
 use supports_hyperlinks::supports_hyperlinks;
 use terminal_link::Link;
 use test_runners::{SubtestResult, process_test_file};
@@ -180,6 +180,7 @@ fn collect_tests(wpt_dir: &Path) -> Vec<PathBuf> {
     if suites.is_empty() {
         suites.push("css/css-flexbox".to_string());
         suites.push("css/css-grid".to_string());
+        suites.push("css/css-text".to_string());
     }
 
     for suite in suites {
@@ -672,6 +673,12 @@ fn main() {
     let expectations_path = out_dir.join("wpt_expectations.txt");
     fs::write(&expectations_path, expectations).unwrap();
 
+    // Generate wpt_conformance.md
+    let conformance = generate_conformance_summary(&results);
+    let conformance_path = out_dir.join("wpt_conformance.md");
+    fs::write(&conformance_path, conformance).unwrap();
+    println!("\nConformance summary written to {conformance_path:?}");
+
     // Generate wptreport.json
     let report_start = Instant::now();
     let report = generate_report(&wpt_dir, results, start_timestamp, end_timestamp);
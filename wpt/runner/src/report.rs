@@ -1,5 +1,7 @@
-//! Code related to writing a report in "WPT Report" format
+//! Code related to writing a report in "WPT Report" format, and a conformance summary for humans
 
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
 use std::{path::Path, process::Command};
 
 use wptreport::{
@@ -7,7 +9,7 @@ use wptreport::{
     wpt_report::WptReport,
 };
 
-use crate::{TestResult, TestStatus};
+use crate::{TestFlags, TestKind, TestResult, TestStatus};
 
 fn get_git_hash(path: &Path) -> String {
     let output = Command::new("git")
@@ -139,3 +141,146 @@ pub fn generate_expectations(results: &[TestResult]) -> String {
 
     out
 }
+
+#[derive(Default)]
+struct ConformanceCounts {
+    pass: u32,
+    fail: u32,
+    crash: u32,
+    skip: u32,
+    /// Failures of tests which don't use any of the features flagged as unsupported
+    unexplained_fail: u32,
+}
+
+impl ConformanceCounts {
+    fn add(&mut self, test: &TestResult) {
+        match test.status {
+            TestStatus::Pass => self.pass += 1,
+            TestStatus::Fail => {
+                self.fail += 1;
+                let mut flags = test.flags;
+                if test.kind != TestKind::Ref {
+                    flags.remove(TestFlags::USES_SCRIPT);
+                }
+                if flags.is_empty() {
+                    self.unexplained_fail += 1;
+                }
+            }
+            TestStatus::Crash => self.crash += 1,
+            TestStatus::Skip => self.skip += 1,
+        }
+    }
+
+    fn write_row(&self, out: &mut String, name: &str) {
+        let run = self.pass + self.fail + self.crash;
+        let pass_rate = if run == 0 {
+            String::from("-")
+        } else {
+            format!("{:.1}%", self.pass as f32 / run as f32 * 100.0)
+        };
+        writeln!(
+            out,
+            "| {name} | {pass_rate} | {} | {} | {} | {} | {} |",
+            self.pass, self.fail, self.unexplained_fail, self.crash, self.skip
+        )
+        .unwrap();
+    }
+}
+
+/// A markdown summary of which suites (like `css/css-flexbox`), and which directories within
+/// them, pass. Failures of tests which don't use features flagged as unsupported (floats,
+/// writing modes, etc) are counted separately, as they're the most likely to be real bugs.
+pub fn generate_conformance_summary(results: &[TestResult]) -> String {
+    let mut suites: BTreeMap<&str, (ConformanceCounts, BTreeMap<&str, ConformanceCounts>)> =
+        BTreeMap::new();
+    let mut total = ConformanceCounts::default();
+
+    for test in results {
+        let directory = test.name.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let suite_len = directory
+            .match_indices('/')
+            .nth(1)
+            .map_or(directory.len(), |(index, _)| index);
+        let suite = &directory[..suite_len];
+
+        let (suite_counts, directories) = suites.entry(suite).or_default();
+        suite_counts.add(test);
+        if directory != suite {
+            directories.entry(directory).or_default().add(test);
+        }
+        total.add(test);
+    }
+
+    let mut out = String::new();
+    out.push_str("# Blitz WPT conformance\n\n");
+    out.push_str("Pass rates are of the tests which ran (passed, failed or crashed). ");
+    out.push_str("\"Unexplained\" failures are of tests which don't use a feature that's known ");
+    out.push_str("to be unsupported.\n\n");
+    out.push_str("| Suite | Pass rate | Passed | Failed | Unexplained | Crashed | Skipped |\n");
+    out.push_str("|---|---|---|---|---|---|---|\n");
+    for (&suite, (counts, directories)) in &suites {
+        counts.write_row(&mut out, &format!("**{suite}**"));
+        for (&directory, counts) in directories {
+            let name = directory.strip_prefix(suite).unwrap_or(directory);
+            counts.write_row(&mut out, &format!("&nbsp;&nbsp;{name}"));
+        }
+    }
+    total.write_row(&mut out, "**Total**");
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::generate_conformance_summary;
+    use crate::{SubtestCounts, TestFlags, TestKind, TestResult, TestStatus};
+
+    fn result(name: &str, kind: TestKind, flags: TestFlags, status: TestStatus) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            kind,
+            flags,
+            status,
+            subtest_counts: SubtestCounts::ZERO_OF_ZERO,
+            subtest_results: Vec::new(),
+            duration: Duration::ZERO,
+            panic_info: None,
+        }
+    }
+
+    #[test]
+    fn results_are_summarized_by_suite_and_directory() {
+        let none = TestFlags::empty();
+        let (float, script) = (TestFlags::USES_FLOAT, TestFlags::USES_SCRIPT);
+        let results = [
+            result("css/css-flexbox/align-001.html", TestKind::Ref, none, TestStatus::Pass),
+            result("css/css-flexbox/align-002.html", TestKind::Ref, none, TestStatus::Fail),
+            // Failures of tests using unsupported features are explained, and scripts are
+            // unsupported in reftests (but not in testharness tests)
+            result("css/css-flexbox/abspos/abs-001.html", TestKind::Ref, float, TestStatus::Fail),
+            result("css/css-flexbox/abspos/abs-002.html", TestKind::Ref, script, TestStatus::Fail),
+            result("css/css-flexbox/abspos/abs-003.html", TestKind::Ref, none, TestStatus::Crash),
+            result("css/css-grid/grid-001.html", TestKind::Ref, none, TestStatus::Skip),
+            result("css/css-text/ws/ws-001.html", TestKind::Attr, script, TestStatus::Fail),
+            result("css/css-text/ws/ws-002.html", TestKind::Attr, none, TestStatus::Skip),
+        ];
+        let summary = generate_conformance_summary(&results);
+
+        let table = summary.lines().skip_while(|line| !line.starts_with("|---"));
+        let rows: Vec<&str> = table.skip(1).collect();
+        assert_eq!(
+            rows,
+            [
+                "| **css/css-flexbox** | 20.0% | 1 | 3 | 1 | 1 | 0 |",
+                "| &nbsp;&nbsp;/abspos | 0.0% | 0 | 2 | 0 | 1 | 0 |",
+                // Suites where no tests ran have no pass rate
+                "| **css/css-grid** | - | 0 | 0 | 0 | 0 | 1 |",
+                "| **css/css-text** | 0.0% | 0 | 1 | 1 | 0 | 1 |",
+                "| &nbsp;&nbsp;/ws | 0.0% | 0 | 1 | 1 | 0 | 1 |",
+                "| **Total** | 16.7% | 1 | 4 | 2 | 1 | 2 |",
+            ]
+        );
+    }
+}